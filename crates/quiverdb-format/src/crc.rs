@@ -0,0 +1,49 @@
+//! crc — CRC32C (Castagnoli), общий для трейлеров страниц и кадров WAL.
+//!
+//! С фичей `std` — крейт crc32c (SSE4.2 / ARM CRC, если доступны). Без `std` — табличная
+//! реализация (const-таблица 256×u32); результаты идентичны.
+
+/// Продолжить CRC32C по data (crc=0 — начало).
+#[inline]
+pub fn crc32c_append(crc: u32, data: &[u8]) -> u32 {
+    #[cfg(feature = "std")]
+    {
+        crc32c::crc32c_append(crc, data)
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        let mut c = !crc;
+        for &b in data {
+            c = TABLE[((c ^ b as u32) & 0xFF) as usize] ^ (c >> 8);
+        }
+        !c
+    }
+}
+
+/// CRC32C буфера.
+#[inline]
+pub fn crc32c(data: &[u8]) -> u32 {
+    crc32c_append(0, data)
+}
+
+#[cfg(not(feature = "std"))]
+const TABLE: [u32; 256] = make_table();
+
+#[cfg(not(feature = "std"))]
+const fn make_table() -> [u32; 256] {
+    // Отражённый полином Castagnoli
+    const POLY: u32 = 0x82F6_3B78;
+    let mut t = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { (c >> 1) ^ POLY } else { c >> 1 };
+            k += 1;
+        }
+        t[i] = c;
+        i += 1;
+    }
+    t
+}
@@ -0,0 +1,23 @@
+//! quiverdb-format — on-disk формат QuiverDB без файловой системы и движка.
+//!
+//! Чистые encode/decode-хелперы для внешних инструментов (парсеры, фаззеры, WASM-вьюеры):
+//! - page      — страницы v3 (KV_RH3, OVERFLOW3): заголовки, записи, packing, CRC32C-трейлер;
+//! - wal       — кадры WAL v2 (P2WAL001): константы, заголовок записи + CRC, COMMIT timestamp;
+//! - placeholder — TLV-плейсхолдер OVERFLOW3-значения (OVF_CHAIN);
+//! - crc       — CRC32C (аппаратный через крейт crc32c при `std`, табличный в no_std).
+//!
+//! Фича `std` (по умолчанию). Без неё крейт собирается как `no_std` + `alloc`:
+//!   quiverdb-format = { version = "2.2", default-features = false }
+//!
+//! Движок (крейт QuiverDB) реэкспортирует эти модули как crate::page / crate::wal, поэтому
+//! формат остаётся единственной точкой правды. AEAD-трейлер (TDE), OVERFLOW-цепочки и
+//! чтение WAL-файлов живут в движке (им нужны ключи/Pager/файлы).
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod crc;
+pub mod page;
+pub mod placeholder;
+pub mod wal;
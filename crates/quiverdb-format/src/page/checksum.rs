@@ -0,0 +1,86 @@
+//! page/checksum — CRC32C-трейлер страницы (16 байт), чистые функции.
+//!
+//! - trailer[0..4] — CRC32C (LE) по всей странице с занулённым трейлером;
+//! - trailer[4..16] — нули;
+//! - stored == 0 — «чексумма не записана» (нулевая страница / чексуммы выключены у writer'а).
+//!
+//! CRC считается без копии страницы: по телу и затем по 16 нулевым байтам.
+//! ENV-тумблеры, батч-расчёт в потоках и AEAD-тег (TDE) — в движке (QuiverDB::page::checksum).
+
+use anyhow::{anyhow, Result};
+use byteorder::{ByteOrder, LittleEndian};
+
+use super::common::TRAILER_LEN;
+use crate::crc::crc32c_append;
+
+/// Нулевой трейлер — для расчёта CRC без копии страницы.
+const ZERO_TRAILER: [u8; TRAILER_LEN] = [0u8; TRAILER_LEN];
+
+/// CRC32C страницы «как будто трейлер занулён» (страница не короче TRAILER_LEN).
+#[inline]
+pub fn compute_page_crc32c(page: &[u8]) -> u32 {
+    let ps = page.len();
+    let c = crc32c_append(0, &page[..ps - TRAILER_LEN]);
+    crc32c_append(c, &ZERO_TRAILER)
+}
+
+/// Записать трейлер: crc (LE) + нули.
+#[inline]
+pub fn write_crc_trailer_value(page: &mut [u8], crc: u32) -> Result<()> {
+    if page.len() < TRAILER_LEN {
+        return Err(anyhow!("page buffer too small for checksum"));
+    }
+    let ps = page.len();
+    let tr = &mut page[ps - TRAILER_LEN..ps];
+    tr.fill(0);
+    LittleEndian::write_u32(&mut tr[0..4], crc);
+    Ok(())
+}
+
+/// Посчитать и записать CRC32C-трейлер.
+pub fn page_write_crc32c(page: &mut [u8]) -> Result<()> {
+    if page.len() < TRAILER_LEN {
+        return Err(anyhow!("page buffer too small for checksum"));
+    }
+    let crc = compute_page_crc32c(page);
+    write_crc_trailer_value(page, crc)
+}
+
+/// Прочитать низшие 4 байта трейлера страницы как u32 (LE).
+#[inline]
+pub fn page_trailer_crc32_le(page: &[u8]) -> Result<u32> {
+    if page.len() < TRAILER_LEN {
+        return Err(anyhow!("page buffer too small for trailer read"));
+    }
+    let ps = page.len();
+    Ok(LittleEndian::read_u32(
+        &page[ps - TRAILER_LEN..ps - TRAILER_LEN + 4],
+    ))
+}
+
+/// Проверка «нулевого трейлера» для CRC‑режима (stored CRC32 == 0).
+#[inline]
+pub fn page_trailer_is_zero_crc32(page: &[u8]) -> Result<bool> {
+    Ok(page_trailer_crc32_le(page)? == 0)
+}
+
+/// Проверка CRC32C; stored == 0 считается допустимым (нулевая/неинициализированная страница).
+pub fn page_verify_crc32c(page: &[u8]) -> Result<bool> {
+    let stored = page_trailer_crc32_le(page)?;
+    if stored == 0 {
+        return Ok(true);
+    }
+    Ok(stored == compute_page_crc32c(page))
+}
+
+/// Строгая проверка CRC32C: stored == 0 — ошибка целостности (false).
+pub fn verify_page_crc_strict(page: &[u8]) -> Result<bool> {
+    if page.len() < TRAILER_LEN {
+        return Err(anyhow!("page buffer too small for strict CRC verify"));
+    }
+    let stored = page_trailer_crc32_le(page)?;
+    if stored == 0 {
+        return Ok(false);
+    }
+    Ok(stored == compute_page_crc32c(page))
+}
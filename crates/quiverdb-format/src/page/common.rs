@@ -65,3 +65,8 @@ pub const OVF_OFF_NEXT_PID: usize = 24;
 pub const OVF_OFF_LSN: usize = 32;
 /// codec_id (u16): 0=none, 1=zstd, 2=lz4 (резерв).
 pub const OVF_OFF_CODEC_ID: usize = 40;
+
+// ---------- NEW: KID страницы (TDE, KV_RH3 и OVERFLOW3) ----------
+/// key_fp (u32): отпечаток KID, ключом которого подписан AEAD-трейлер (резерв заголовка после
+/// codec_id, одинаков для KV_RH3 и OVERFLOW3); 0 — не записан (CRC-режим, страница до per-page KID).
+pub const OFF_KEY_FP: usize = 56;
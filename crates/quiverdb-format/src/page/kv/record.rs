@@ -14,12 +14,12 @@ use crate::page::common::{KV_EMPTY_OFF, KV_HDR_MIN, KV_SLOT_SIZE, TRAILER_LEN};
 ///   или kv_read_record_at_checked(..), которые учитывают data_end.
 ///
 /// Возвращает None при выходе за пределы буфера страницы.
-pub fn kv_read_record_unchecked<'a>(
-    page: &'a [u8],
+pub fn kv_read_record_unchecked(
+    page: &[u8],
     off: usize,
 ) -> Option<(
-    &'a [u8],
-    &'a [u8],
+    &[u8],
+    &[u8],
     u32, /*expires_at_sec*/
     u8,  /*vflags*/
 )> {
@@ -50,7 +50,7 @@ pub fn kv_read_record_unchecked<'a>(
     note = "Use kv_find_record_by_key/kv_for_each_record/kv_for_each_record_with_off or kv_read_record_at_checked; \
 this function does not guard against slot-table bounds and may read beyond data area on packed pages."
 )]
-pub fn kv_read_record<'a>(page: &'a [u8], off: usize) -> Option<(&'a [u8], &'a [u8], u32, u8)> {
+pub fn kv_read_record(page: &[u8], off: usize) -> Option<(&[u8], &[u8], u32, u8)> {
     kv_read_record_unchecked(page, off)
 }
 
@@ -73,11 +73,11 @@ fn data_end_for_page(hdr: &KvHeaderV3, ps: usize) -> Option<usize> {
 /// Публичный безопасный ридер записи по смещению с учётом границы data_end.
 /// Возвращает None, если запись целиком не помещается в data‑area.
 #[inline]
-pub fn kv_read_record_at_checked<'a>(
-    page: &'a [u8],
+pub fn kv_read_record_at_checked(
+    page: &[u8],
     off: usize,
     data_end: usize,
-) -> Option<(&'a [u8], &'a [u8], u32, u8)> {
+) -> Option<(&[u8], &[u8], u32, u8)> {
     // Минимальная “шапка” записи
     if off.checked_add(11)? > data_end {
         return None;
@@ -101,7 +101,7 @@ pub fn kv_read_record_at_checked<'a>(
 /// Используется для раннего отсева слотов перед чтением записи.
 /// Замечание: значение 0 допустимо; в слотах старых страниц fp=0 трактуется как “без отпечатка”.
 pub fn kv_fp8(key: &[u8]) -> u8 {
-    use std::hash::Hasher;
+    use core::hash::Hasher;
     let mut h = twox_hash::XxHash64::with_seed(0);
     h.write(key);
     (h.finish() & 0xFF) as u8
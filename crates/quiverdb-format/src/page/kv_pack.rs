@@ -5,7 +5,7 @@
 //! - Пишем несколько записей формата [klen u16][vlen u32][expires u32][vflags u8][key][value]
 //!   в data‑область подряд, начиная c KV_HDR_MIN.
 //! - В хвосте размещаем слот‑таблицу из N слотов; для каждого слота пишем:
-//!     [off u32][fp u8][dist u8], где fp = kv_fp8(key) (низшие 8 бит xxhash64(seed=0)).
+//!   [off u32][fp u8][dist u8], где fp = kv_fp8(key) (низшие 8 бит xxhash64(seed=0)).
 //!   dist остаётся 0 (подготовка к Robin Hood, сейчас не используется).
 //! - Заголовок: data_start = конец данных, table_slots = used_slots = N, next_page_id задаёт вызывающий код.
 //!
@@ -17,6 +17,8 @@
 //! - Никакого сжатия KV на уровне страницы (codec_id оставляем как в init_v3).
 //! - Проверка вместимости: KV_HDR_MIN + sum(records) ≤ ps - TRAILER_LEN - N*KV_SLOT_SIZE.
 
+use alloc::vec;
+use alloc::vec::Vec;
 use anyhow::{anyhow, Result};
 use byteorder::{ByteOrder, LittleEndian};
 
@@ -164,7 +166,7 @@ impl KvPagePacker {
 
         // Слот‑таблица: [off u32][fp u8][dist u8]
         let mut slot_off = table_start;
-        for (rec_off, fp) in record_offsets.into_iter().zip(record_fps.into_iter()) {
+        for (rec_off, fp) in record_offsets.into_iter().zip(record_fps) {
             // off u32
             LittleEndian::write_u32(&mut page[slot_off..slot_off + 4], rec_off);
             // fp u8 = kv_fp8(key); 0 допустим (wildcard на чтении)
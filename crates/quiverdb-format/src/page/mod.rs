@@ -0,0 +1,13 @@
+//! page — v3 on-disk page types (KV_RH3, OVERFLOW3) + fixed checksum trailer (чистый формат).
+//!
+//! - common.rs   — общие константы/offset’ы, MAGIC/версия/типы, размеры заголовков.
+//! - checksum.rs — CRC32C-трейлер (расчёт/проверка/чтение поля), без ENV-тумблеров.
+//! - kv/         — KV_RH3: заголовок и безопасное чтение записей.
+//! - ovf/        — OVERFLOW3: заголовок.
+//! - kv_pack.rs  — упаковка нескольких KV-записей на одну страницу.
+
+pub mod checksum;
+pub mod common;
+pub mod kv;
+pub mod kv_pack;
+pub mod ovf;
@@ -0,0 +1,5 @@
+//! page/ovf — OVERFLOW3 (v3): заголовок страницы (чтение цепочек — в движке, page/ovf/chain).
+
+pub mod header;
+
+pub use header::{ovf_header_read_v3, ovf_header_write_v3, ovf_init_v3, OvfHeaderV3};
@@ -0,0 +1,40 @@
+//! placeholder — TLV-плейсхолдер OVERFLOW3-значения (OVF_CHAIN, v3).
+//!
+//! Хранится в KV-записи вместо значения, вынесенного в OVERFLOW-цепочку:
+//!   [tag u8=0x01][len u8=16][total_len u64 LE][head_pid u64 LE]   (18 байт)
+//! total_len — длина исходного значения, head_pid — первая страница цепочки.
+
+use alloc::vec::Vec;
+use byteorder::{ByteOrder, LittleEndian};
+
+/// TLV-тег OVF_CHAIN.
+pub const OVF_PLACEHOLDER_TAG: u8 = 0x01;
+/// Длина тела TLV (total_len + head_pid).
+pub const OVF_PLACEHOLDER_BODY_LEN: u8 = 16;
+/// Полная длина плейсхолдера.
+pub const OVF_PLACEHOLDER_LEN: usize = 2 + OVF_PLACEHOLDER_BODY_LEN as usize;
+
+/// Построить плейсхолдер для значения длины total_len в цепочке с головой head_pid.
+pub fn encode_ovf_placeholder_v3(total_len: u64, head_pid: u64) -> Vec<u8> {
+    let mut out = alloc::vec![0u8; OVF_PLACEHOLDER_LEN];
+    out[0] = OVF_PLACEHOLDER_TAG;
+    out[1] = OVF_PLACEHOLDER_BODY_LEN;
+    LittleEndian::write_u64(&mut out[2..10], total_len);
+    LittleEndian::write_u64(&mut out[10..18], head_pid);
+    out
+}
+
+/// Разобрать плейсхолдер. Some((total_len, head_pid)) при успехе, иначе None.
+#[inline]
+pub fn decode_ovf_placeholder_v3(v: &[u8]) -> Option<(u64, u64)> {
+    if v.len() >= OVF_PLACEHOLDER_LEN
+        && v[0] == OVF_PLACEHOLDER_TAG
+        && v[1] == OVF_PLACEHOLDER_BODY_LEN
+    {
+        let total = LittleEndian::read_u64(&v[2..10]);
+        let head = LittleEndian::read_u64(&v[10..18]);
+        Some((total, head))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,207 @@
+//! wal — кадры WAL v2 (P2WAL001): константы формата, заголовок записи + CRC32C, COMMIT timestamp.
+//!
+//! Файл: [MAGIC "P2WAL001"][stream_id u64 LE], далее кадры
+//!   [type u8][flags u8][reserved u16][lsn u64][page_id u64][len u32][crc32c u32][payload; len]
+//! CRC32C считается по header[0..24) + payload.
+//!
+//! Здесь только байты → структуры и обратно; чтение/запись файлов, реплей и ротация — в движке.
+
+use anyhow::{anyhow, Result};
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::crc::crc32c_append;
+
+// -------------------- Константы WAL v2 --------------------
+
+pub const WAL_MAGIC: &[u8; 8] = b"P2WAL001";
+pub const WAL_HDR_SIZE: usize = 16; // magic8 + reserved u64(stream_id)
+
+// Offsets внутри заголовка файла
+pub const WAL_HDR_OFF_STREAM_ID: usize = 8; // 8 байт после MAGIC (LE u64)
+
+// Record header (v2): 28 bytes (включая поле CRC на смещении 24..28)
+pub const WAL_REC_HDR_SIZE: usize = 28;
+
+// Offsets внутри заголовка записи
+pub const WAL_REC_OFF_TYPE: usize = 0;
+pub const WAL_REC_OFF_FLAGS: usize = 1;
+pub const WAL_REC_OFF_RESERVED: usize = 2;
+pub const WAL_REC_OFF_LSN: usize = 4;
+pub const WAL_REC_OFF_PAGE_ID: usize = 12;
+pub const WAL_REC_OFF_LEN: usize = 20;
+pub const WAL_REC_OFF_CRC32: usize = 24;
+
+// Типы записей
+pub const WAL_REC_BEGIN: u8 = 1;
+pub const WAL_REC_PAGE_IMAGE: u8 = 2;
+pub const WAL_REC_PAGE_DELTA: u8 = 3; // зарезервировано — игнорируется реплеем в 2.0
+pub const WAL_REC_COMMIT: u8 = 4;
+pub const WAL_REC_TRUNCATE: u8 = 5;
+// атомарные обновления голов каталога (между IMAGE… и COMMIT в батче)
+pub const WAL_REC_HEADS_UPDATE: u8 = 6;
+// idempotency-токен батча (сразу после BEGIN, payload = SHA-256 digest токена)
+pub const WAL_REC_IDEMPOTENCY: u8 = 7;
+// логический кадр — ключи, вычищенные по TTL (перед COMMIT батча компактации)
+pub const WAL_REC_EXPIRY: u8 = 8;
+
+// -------------------- CRC и заголовок записи --------------------
+
+/// Инкрементальный CRC32C по двум срезам без аллокаций.
+#[inline]
+pub fn crc32c_of_parts(head_without_crc: &[u8], payload: &[u8]) -> u32 {
+    let c = crc32c_append(0, head_without_crc);
+    crc32c_append(c, payload)
+}
+
+/// Построить заголовок WAL с заполненным CRC32C.
+/// CRC считается по header[0..WAL_REC_OFF_CRC32] + payload.
+pub fn build_hdr_with_crc(
+    rec_type: u8,
+    lsn: u64,
+    page_id: u64,
+    payload: &[u8],
+) -> [u8; WAL_REC_HDR_SIZE] {
+    let mut hdr = [0u8; WAL_REC_HDR_SIZE];
+    hdr[WAL_REC_OFF_TYPE] = rec_type;
+    hdr[WAL_REC_OFF_FLAGS] = 0;
+    LittleEndian::write_u16(&mut hdr[WAL_REC_OFF_RESERVED..WAL_REC_OFF_RESERVED + 2], 0);
+    LittleEndian::write_u64(&mut hdr[WAL_REC_OFF_LSN..WAL_REC_OFF_LSN + 8], lsn);
+    LittleEndian::write_u64(
+        &mut hdr[WAL_REC_OFF_PAGE_ID..WAL_REC_OFF_PAGE_ID + 8],
+        page_id,
+    );
+    LittleEndian::write_u32(
+        &mut hdr[WAL_REC_OFF_LEN..WAL_REC_OFF_LEN + 4],
+        payload.len() as u32,
+    );
+
+    let crc = crc32c_of_parts(&hdr[..WAL_REC_OFF_CRC32], payload);
+    LittleEndian::write_u32(&mut hdr[WAL_REC_OFF_CRC32..WAL_REC_OFF_CRC32 + 4], crc);
+    hdr
+}
+
+/// Разобранный заголовок кадра WAL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalFrameHeader {
+    pub rec_type: u8,
+    pub flags: u8,
+    pub lsn: u64,
+    pub page_id: u64,
+    pub payload_len: u32,
+    pub crc32c: u32,
+}
+
+impl WalFrameHeader {
+    /// Разобрать 28-байтовый заголовок записи (без проверки CRC — payload ещё не прочитан).
+    pub fn decode(hdr: &[u8]) -> Result<Self> {
+        if hdr.len() < WAL_REC_HDR_SIZE {
+            return Err(anyhow!("wal record header too short: {} bytes", hdr.len()));
+        }
+        Ok(Self {
+            rec_type: hdr[WAL_REC_OFF_TYPE],
+            flags: hdr[WAL_REC_OFF_FLAGS],
+            lsn: LittleEndian::read_u64(&hdr[WAL_REC_OFF_LSN..WAL_REC_OFF_LSN + 8]),
+            page_id: LittleEndian::read_u64(&hdr[WAL_REC_OFF_PAGE_ID..WAL_REC_OFF_PAGE_ID + 8]),
+            payload_len: LittleEndian::read_u32(&hdr[WAL_REC_OFF_LEN..WAL_REC_OFF_LEN + 4]),
+            crc32c: LittleEndian::read_u32(&hdr[WAL_REC_OFF_CRC32..WAL_REC_OFF_CRC32 + 4]),
+        })
+    }
+
+    /// Полная длина кадра (заголовок + payload).
+    #[inline]
+    pub fn frame_len(&self) -> u64 {
+        WAL_REC_HDR_SIZE as u64 + self.payload_len as u64
+    }
+}
+
+/// Разобрать один кадр из буфера: Ok(Some((header, payload, next_off))) или Ok(None) при
+/// неполном хвосте. Ошибка — CRC mismatch.
+pub fn decode_wal_frame(buf: &[u8], off: usize) -> Result<Option<(WalFrameHeader, &[u8], usize)>> {
+    if buf.len() < off + WAL_REC_HDR_SIZE {
+        return Ok(None);
+    }
+    let hdr = &buf[off..off + WAL_REC_HDR_SIZE];
+    let h = WalFrameHeader::decode(hdr)?;
+    let end = off + h.frame_len() as usize;
+    if buf.len() < end {
+        return Ok(None);
+    }
+    let payload = &buf[off + WAL_REC_HDR_SIZE..end];
+    let calc = crc32c_of_parts(&hdr[..WAL_REC_OFF_CRC32], payload);
+    if calc != h.crc32c {
+        return Err(anyhow!(
+            "WAL CRC mismatch at offset {} (stored={}, calc={})",
+            off,
+            h.crc32c,
+            calc
+        ));
+    }
+    Ok(Some((h, payload, end)))
+}
+
+/// stream_id из 16-байтового заголовка файла WAL (ошибка при неверной магии/длине).
+pub fn wal_file_header_stream_id(hdr: &[u8]) -> Result<u64> {
+    if hdr.len() < WAL_HDR_SIZE {
+        return Err(anyhow!("wal too small (< header)"));
+    }
+    if &hdr[..8] != WAL_MAGIC {
+        return Err(anyhow!("bad WAL magic"));
+    }
+    Ok(LittleEndian::read_u64(
+        &hdr[WAL_HDR_OFF_STREAM_ID..WAL_HDR_OFF_STREAM_ID + 8],
+    ))
+}
+
+// -------------------- COMMIT timestamp payload --------------------
+
+/// Длина payload COMMIT-кадра с временем коммита.
+pub const WAL_COMMIT_TS_LEN: usize = 16;
+
+/// Время коммита батча (payload COMMIT-кадра, LE):
+///   [u64 wall_ms — unix-время, мс][u64 mono_ns — монотонное время writer-процесса, нс]
+/// mono_ns отсчитывается от старта процесса writer'а: сравнимо только внутри одного процесса
+/// (упорядочивание/интервалы без скачков часов), wall_ms — для time-based представлений.
+/// Пустой payload COMMIT (старые WAL, P1_WAL_COMMIT_TS=0) — времени нет.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommitTimestamp {
+    pub wall_ms: u64,
+    pub mono_ns: u64,
+}
+
+impl CommitTimestamp {
+    /// Текущее время (только с фичей `std`).
+    #[cfg(feature = "std")]
+    pub fn now() -> Self {
+        use std::sync::OnceLock;
+        use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+        static BASE: OnceLock<Instant> = OnceLock::new();
+        let base = *BASE.get_or_init(Instant::now);
+        let wall_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        Self {
+            wall_ms,
+            mono_ns: base.elapsed().as_nanos() as u64,
+        }
+    }
+
+    pub fn encode(&self) -> [u8; WAL_COMMIT_TS_LEN] {
+        let mut out = [0u8; WAL_COMMIT_TS_LEN];
+        LittleEndian::write_u64(&mut out[0..8], self.wall_ms);
+        LittleEndian::write_u64(&mut out[8..16], self.mono_ns);
+        out
+    }
+
+    /// Разобрать payload COMMIT-кадра. None — времени нет (пустой/неизвестный payload).
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        if payload.len() < WAL_COMMIT_TS_LEN {
+            return None;
+        }
+        Some(Self {
+            wall_ms: LittleEndian::read_u64(&payload[0..8]),
+            mono_ns: LittleEndian::read_u64(&payload[8..16]),
+        })
+    }
+}
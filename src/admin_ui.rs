@@ -0,0 +1,304 @@
+//! admin_ui — встроенная read-only HTTP-страница администратора (feature "admin-ui").
+//!
+//! Быстрый визуальный health-check для операторов: статус БД, состояние WAL/checkpoint,
+//! графики метрик и список снапшотов. UI ничего не меняет в БД:
+//! - все маршруты — только GET (иначе 405);
+//! - кнопки restore в списке снапшотов отключены (restore — через CLI snapshot-restore);
+//! - статус читается из файлов (meta/dir/WAL), Db не открывается и LOCK не удерживается
+//!   (кратковременная shared-попытка только для определения активного writer'а).
+//!
+//! Маршруты:
+//! - GET /admin                — HTML-дашборд (опрашивает API раз в 2 с)
+//! - GET /admin/api/status     — JSON: meta, directory, WAL/checkpoint, writer_active
+//! - GET /admin/api/metrics    — JSON: снимок счётчиков процесса (metrics::snapshot)
+//! - GET /admin/api/snapshots  — JSON: список persisted-снапшотов (manifest meta)
+//!
+//! Встраивание:
+//! - route() — чистая функция маршрутизации (используется quiverdb_metrics и тестами);
+//! - AdminUi::bind(..).serve() — блокирующий сервер (CLI admin-ui);
+//! - AdminUi::spawn(..) — фоновый поток (follower: cdc-apply при P1_ADMIN_UI_ADDR).
+//!
+//! Метрики — process-wide: осмысленны, когда UI запущен в процессе writer'а/follower'а.
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use tiny_http::{Header, Response, Server};
+
+use crate::dir::Directory;
+use crate::meta::read_meta;
+use crate::metrics;
+use crate::snapstore::{list_manifests, read_manifest};
+use crate::wal::state::load_last_heads_lsn;
+use crate::wal::{wal_header_read_stream_id, wal_path, WAL_HDR_SIZE};
+
+/// Префикс маршрутов admin UI.
+pub const ADMIN_PREFIX: &str = "/admin";
+
+/// Ответ маршрутизатора: (status, content-type, body).
+pub struct AdminResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl AdminResponse {
+    fn json(v: Value) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json",
+            body: v.to_string(),
+        }
+    }
+
+    fn text(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.to_string(),
+        }
+    }
+}
+
+/// Маршрутизация запроса admin UI. None — URL не относится к /admin (пусть обработает вызывающий).
+pub fn route(root: Option<&Path>, method: &str, url: &str) -> Option<AdminResponse> {
+    let path = url.split('?').next().unwrap_or(url);
+    if path != ADMIN_PREFIX && !path.starts_with("/admin/") {
+        return None;
+    }
+    if method != "GET" {
+        return Some(AdminResponse::text(405, "admin UI is read-only\n"));
+    }
+    let need_root = |f: fn(&Path) -> Result<Value>| match root {
+        Some(r) => match f(r) {
+            Ok(v) => AdminResponse::json(v),
+            Err(e) => AdminResponse {
+                status: 500,
+                content_type: "application/json",
+                body: json!({ "error": format!("{:#}", e) }).to_string(),
+            },
+        },
+        None => AdminResponse::text(404, "no database path configured (--path)\n"),
+    };
+    let resp = match path {
+        "/admin" | "/admin/" => AdminResponse {
+            status: 200,
+            content_type: "text/html; charset=utf-8",
+            body: DASHBOARD_HTML.to_string(),
+        },
+        "/admin/api/status" => need_root(status_json),
+        "/admin/api/snapshots" => need_root(snapshots_json),
+        "/admin/api/metrics" => AdminResponse::json(metrics_json()),
+        _ => AdminResponse::text(404, "not found\n"),
+    };
+    Some(resp)
+}
+
+/// Статус БД по файлам: meta, directory, WAL/checkpoint, признак активного writer'а.
+/// Примечание: meta.last_lsn/next_page_id при живом writer'е отстают (пишутся на закрытии).
+pub fn status_json(root: &Path) -> Result<Value> {
+    let m = read_meta(root)?;
+    let dir = Directory::open(root)?;
+    let used = dir.count_used_buckets().unwrap_or(0);
+
+    let wp = wal_path(root);
+    let (wal_bytes, stream_id) = match std::fs::File::open(&wp) {
+        Ok(mut f) => (
+            f.metadata().map(|md| md.len()).unwrap_or(0),
+            wal_header_read_stream_id(&mut f).unwrap_or(0),
+        ),
+        Err(_) => (0, 0),
+    };
+    let wal_pending = wal_bytes > WAL_HDR_SIZE as u64;
+
+    Ok(json!({
+        "path": root.display().to_string(),
+        "writer_active": writer_active(root),
+        "meta": {
+            "version": m.version,
+            "page_size": m.page_size,
+            "next_page_id": m.next_page_id,
+            "last_lsn": m.last_lsn,
+            "clean_shutdown": m.clean_shutdown,
+            "checksum_kind": m.checksum_kind,
+            "codec_default": m.codec_default,
+        },
+        "directory": {
+            "buckets": dir.bucket_count,
+            "used_buckets": used,
+        },
+        "wal": {
+            "path": wp.display().to_string(),
+            "bytes": wal_bytes,
+            "stream_id": format!("{:016x}", stream_id),
+            "pending_frames": wal_pending,
+            "last_heads_lsn": load_last_heads_lsn(root).unwrap_or(0),
+        },
+        "checkpoint": {
+            // clean_shutdown && пустой WAL — ничего реплеить при открытии
+            "clean": m.clean_shutdown && !wal_pending,
+            "replay_on_open": !m.clean_shutdown && wal_pending,
+        },
+    }))
+}
+
+/// Список persisted-снапшотов (метаданные манифестов).
+pub fn snapshots_json(root: &Path) -> Result<Value> {
+    let mut out = Vec::new();
+    for id in list_manifests(root)? {
+        match read_manifest(root, &id) {
+            Ok(mf) => out.push(json!({
+                "id": mf.meta.id,
+                "parent": mf.meta.parent,
+                "created_unix_ms": mf.meta.created_unix_ms,
+                "message": mf.meta.message,
+                "labels": mf.meta.labels,
+                "lsn": mf.meta.lsn,
+                "objects": mf.objects.len(),
+            })),
+            Err(e) => out.push(json!({ "id": id, "error": format!("{:#}", e) })),
+        }
+    }
+    Ok(json!({ "snapshots": out }))
+}
+
+/// Снимок основных счётчиков процесса (для графиков дашборда).
+pub fn metrics_json() -> Value {
+    let ms = metrics::snapshot();
+    json!({
+        "wal_appends_total": ms.wal_appends_total,
+        "wal_bytes_written": ms.wal_bytes_written,
+        "wal_fsync_calls": ms.wal_fsync_calls,
+        "wal_truncations": ms.wal_truncations,
+        "wal_flushed_lsn": ms.wal_flushed_lsn,
+        "page_cache_hits": ms.page_cache_hits,
+        "page_cache_misses": ms.page_cache_misses,
+        "page_cache_hit_ratio": ms.cache_hit_ratio(),
+        "keydir_hits": ms.keydir_hits,
+        "keydir_misses": ms.keydir_misses,
+        "ttl_skipped": ms.ttl_skipped,
+        "snapshots_active": ms.snapshots_active,
+        "expiry_events_emitted": ms.expiry_events_emitted,
+    })
+}
+
+/// Держит ли кто-то эксклюзивный LOCK (writer). Кратковременная shared-попытка.
+fn writer_active(root: &Path) -> bool {
+    use fs2::FileExt;
+    match std::fs::File::open(root.join(crate::db::core::LOCK_FILE)) {
+        Ok(f) => match FileExt::try_lock_shared(&f) {
+            Ok(()) => {
+                let _ = FileExt::unlock(&f);
+                false
+            }
+            Err(_) => true,
+        },
+        Err(_) => false,
+    }
+}
+
+/// Блокирующий/фоновый HTTP-сервер admin UI.
+pub struct AdminUi {
+    server: Server,
+    root: Option<PathBuf>,
+}
+
+impl AdminUi {
+    pub fn bind(addr: &str, root: Option<PathBuf>) -> Result<Self> {
+        let server = Server::http(addr).map_err(|e| anyhow!("bind admin UI at {}: {}", addr, e))?;
+        Ok(Self { server, root })
+    }
+
+    /// Фактический адрес (полезно при bind на порт 0).
+    pub fn local_addr(&self) -> Option<std::net::SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+
+    /// Обслуживать запросы до ошибки сервера. Не-/admin маршруты: "/" → редирект на /admin.
+    pub fn serve(self) {
+        for rq in self.server.incoming_requests() {
+            let url = rq.url().to_string();
+            let method = rq.method().as_str().to_string();
+            let resp = route(self.root.as_deref(), &method, &url).unwrap_or_else(|| {
+                if url == "/" {
+                    AdminResponse {
+                        status: 200,
+                        content_type: "text/html; charset=utf-8",
+                        body: "<meta http-equiv=\"refresh\" content=\"0; url=/admin\">".into(),
+                    }
+                } else {
+                    AdminResponse::text(404, "not found\n")
+                }
+            });
+            let _ = rq.respond(to_http(resp));
+        }
+    }
+
+    /// Запустить в фоновом потоке.
+    pub fn spawn(addr: &str, root: Option<PathBuf>) -> Result<JoinHandle<()>> {
+        let ui = Self::bind(addr, root)?;
+        Ok(std::thread::Builder::new()
+            .name("quiverdb-admin-ui".into())
+            .spawn(move || ui.serve())?)
+    }
+}
+
+/// Преобразовать ответ маршрутизатора в tiny_http::Response.
+pub fn to_http(r: AdminResponse) -> Response<std::io::Cursor<Vec<u8>>> {
+    let mut resp = Response::from_string(r.body).with_status_code(r.status);
+    if let Ok(ct) = Header::from_bytes(b"Content-Type", r.content_type.as_bytes()) {
+        resp.add_header(ct);
+    }
+    resp
+}
+
+const DASHBOARD_HTML: &str = r##"<!doctype html>
+<html><head><meta charset="utf-8"><title>QuiverDB admin</title>
+<style>
+body{font-family:system-ui,sans-serif;margin:1.5em;background:#fafafa;color:#222}
+h1{font-size:1.3em}h2{font-size:1.05em;margin-top:1.5em}
+.grid{display:flex;flex-wrap:wrap;gap:1em}
+.card{background:#fff;border:1px solid #ddd;border-radius:6px;padding:.8em;min-width:16em}
+.ok{color:#1a7f37}.warn{color:#b35900}
+table{border-collapse:collapse;background:#fff}td,th{border:1px solid #ddd;padding:.3em .6em;font-size:.9em}
+canvas{border:1px solid #eee;background:#fff}
+</style></head><body>
+<h1>QuiverDB admin <small>(read-only)</small></h1>
+<div class="grid">
+ <div class="card"><b>Database</b><div id="db">loading…</div></div>
+ <div class="card"><b>WAL / checkpoint</b><div id="wal">loading…</div></div>
+</div>
+<h2>Metrics (deltas per 2 s)</h2>
+<div class="grid" id="charts"></div>
+<h2>Snapshots</h2>
+<table><thead><tr><th>id</th><th>created</th><th>lsn</th><th>message</th><th>labels</th><th></th></tr></thead>
+<tbody id="snaps"><tr><td colspan="6">loading…</td></tr></tbody></table>
+<script>
+const SERIES=["wal_appends_total","wal_fsync_calls","wal_bytes_written","page_cache_hits","page_cache_misses","keydir_hits"];
+const hist={};let prev=null;
+function esc(s){return String(s??"").replace(/[&<>"]/g,c=>({"&":"&amp;","<":"&lt;",">":"&gt;",'"':"&quot;"}[c]))}
+function kv(o){return Object.entries(o).map(([k,v])=>`<div>${esc(k)}: <b>${esc(v)}</b></div>`).join("")}
+async function j(u){const r=await fetch(u);return r.json()}
+function draw(name){
+ let c=document.getElementById("c_"+name);
+ if(!c){const d=document.createElement("div");d.className="card";d.innerHTML=`<div>${name}</div><canvas id="c_${name}" width="240" height="60"></canvas>`;document.getElementById("charts").appendChild(d);c=document.getElementById("c_"+name)}
+ const g=c.getContext("2d"),h=hist[name]||[],mx=Math.max(1,...h);g.clearRect(0,0,c.width,c.height);g.beginPath();
+ h.forEach((v,i)=>{const x=i*(c.width/59),y=c.height-(v/mx)*(c.height-4)-2;i?g.lineTo(x,y):g.moveTo(x,y)});g.strokeStyle="#2f6feb";g.stroke();
+ g.fillText(h.length?h[h.length-1]:0,4,10)}
+async function tick(){
+ try{const s=await j("/admin/api/status");
+  document.getElementById("db").innerHTML=kv({path:s.path,writer_active:s.writer_active,page_size:s.meta.page_size,last_lsn:s.meta.last_lsn,next_page_id:s.meta.next_page_id,buckets:s.directory.buckets,used_buckets:s.directory.used_buckets});
+  const cls=s.checkpoint.clean?"ok":"warn";
+  document.getElementById("wal").innerHTML=kv({bytes:s.wal.bytes,stream_id:s.wal.stream_id,last_heads_lsn:s.wal.last_heads_lsn,clean_shutdown:s.meta.clean_shutdown})+`<div class="${cls}">${s.checkpoint.clean?"checkpointed":"WAL has frames (replayed on next open)"}</div>`;
+ }catch(e){document.getElementById("db").textContent="status unavailable"}
+ const m=await j("/admin/api/metrics");
+ if(prev){for(const k of SERIES){(hist[k]=hist[k]||[]).push(Math.max(0,m[k]-prev[k]));if(hist[k].length>60)hist[k].shift();draw(k)}}
+ prev=m}
+async function snaps(){
+ try{const r=await j("/admin/api/snapshots");const b=document.getElementById("snaps");
+  b.innerHTML=r.snapshots.length?r.snapshots.map(s=>`<tr><td>${esc(s.id)}</td><td>${s.created_unix_ms?new Date(s.created_unix_ms).toISOString():""}</td><td>${esc(s.lsn)}</td><td>${esc(s.message)}</td><td>${esc((s.labels||[]).join(", "))}</td><td><button disabled title="read-only UI: use quiverdb snapshot-restore">restore</button></td></tr>`).join(""):'<tr><td colspan="6">(no snapshots)</td></tr>';
+ }catch(e){}}
+tick();snaps();setInterval(tick,2000);setInterval(snaps,10000);
+</script></body></html>
+"##;
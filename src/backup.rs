@@ -0,0 +1,989 @@
+//! backup — потоковый бэкап в один архив (stdout/S3/ssh/pipe) и восстановление из потока.
+//!
+//! backup_to_writer(&db, impl Write, since_lsn) пишет самодостаточный поток без временных файлов:
+//! страницы (page_lsn > since_lsn), головы каталога, хвост WAL и финальный манифест. Поток можно
+//! сразу отдать в `aws s3 cp - s3://...`, ssh или компрессор. restore_from_reader(dst, impl Read)
+//! разворачивает его в БД (полный бэкап — в пустой корень, инкрементальный — поверх базы).
+//!
+//! Консистентность при живом writer'е (backup_to_writer от writer-хэндла, пока идут записи из потоков):
+//! - страницы читаются «как есть» (порванная/невалидная страница пропускается);
+//! - после страниц в поток идёт текущий WAL (только целые батчи, до последнего COMMIT) —
+//!   любая страница, изменённая во время бэкапа, описана этими кадрами; restore реплеит их
+//!   с LSN‑гейтингом;
+//! - если WAL был ротирован во время бэкапа (кадры потеряны), бэкап завершается ошибкой без
+//!   записи END — restore такой поток отвергнет; повторите бэкап.
+//!
+//! NEW: сжатие (BackupOptions::compress, CLI `--compress zstd[:level]`): payload'ы записей PAGE и WAL
+//! сжимаются zstd по одному (флаг записи REC_FLAG_ZSTD); запись, которую сжатие не уменьшило,
+//! остаётся сырой. Инкрементальная семантика не меняется — сжимаются только отобранные страницы.
+//! Кодек пишется в заголовок и в манифест END (codec/compress_level); restore распознаёт его сам.
+//!
+//! Формат потока (LE), P2BKS001:
+//!   header (64 B): [magic8][u32 version=1][u32 page_size][u32 buckets][u32 hash_kind]
+//!                  [u16 codec_default][u16 flags=0][u16 archive_codec][u16 reserved]
+//!                  [u64 since_lsn][u64 base_lsn]
+//!                  [u64 created_unix_ms][u32 reserved][u32 crc32c(header[0..56])]
+//!   records:       [u8 kind][u8 flags][u16 reserved][u32 len][payload][u32 crc32c(hdr8+payload)]
+//!     flags bit0 = payload сжат zstd (CRC — по сохранённым байтам)
+//!     kind 1 PAGE  — [u64 page_id][page bytes]
+//!     kind 2 HEADS — повторяющиеся [u32 bucket][u64 head_pid] (все бакеты)
+//!     kind 3 WAL   — сырые кадры WAL v2 (без 16‑байтового заголовка файла), целые батчи
+//!     kind 4 END   — JSON BackupStreamManifest; обязателен (его отсутствие — усечённый поток)
+//!     kind 5 SIDECAR — [u16 name_len][name][file bytes] (BackupOptions::include_sidecars)
+//!     kind 6 IDENT — JSON DbIdentity источника (db_uuid/провенанс), сразу после заголовка
+//!
+//! NEW: sidecars (BackupOptions::include_sidecars, CLI `--with-sidecars`): образ bloom.bin
+//! пишется перед END, если он покрывает ровно консистентную точку бэкапа (last_lsn == lsn).
+//! Restore ставит его после реплея хвоста WAL с last_lsn восстановленной БД — реплика сразу
+//! отвечает на промахи через Bloom. Несвежий образ не пишется (и не ставится) — sidecar
+//! перестраивается как обычно. Старые restore пропускают запись как неизвестную.
+//!
+//! NEW: словарь значений (value_dict.bin, db/value_transform.rs) пишется SIDECAR-записью всегда,
+//! когда он есть (без него значения БД не прочитать); restore ставит его до реплея WAL.
+//!
+//! NEW: идентичность БД (meta::DbIdentity): IDENT идёт до страниц, её же копия — в END
+//! (BackupStreamManifest::db). Инкрементальный бэкап не накатывается на БД с другим db_uuid
+//! (проверка до записи страниц); полный restore принимает UUID и провенанс источника, а также
+//! AAD v2 (tde_aad_since_lsn) — AEAD-трейлеры страниц привязаны к db_uuid.
+//!
+//! NEW: restore с проверкой поколения (RestoreOptions::force, CLI `restore --force`):
+//! инкремент не накатывается на цель новее бэкапа (last_lsn цели > max(since, base) архива:
+//! страницы пишутся как есть, и restore откатил бы более новые данные) и на другую БД.
+//! Отказ — RestoreConflictError (оба LSN и UUID в сообщении) до любых изменений в цели.
+//!
+//! NEW: двухфазная запись в файл (backup_to_path): архив пишется в `<out>.partial`, после END
+//! и fsync атомарно переименовывается в `<out>` (затем fsync каталога). Процесс, умерший
+//! посередине, оставляет только `.partial` — прежний `<out>` не тронут. restore_from_path
+//! отвергает `.partial` (и путь, для которого есть только `.partial`) с явным сообщением.
+
+use anyhow::{anyhow, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::bloom::sidecar::{BloomImage, BLOOM_FILE};
+use crate::db::value_transform::{install_value_dict, VALUE_DICT_FILE};
+use crate::db::Db;
+use crate::dir::Directory;
+use crate::meta::{
+    adopt_db_identity, adopt_user_meta_head, init_meta_v4, read_meta, write_meta_overwrite,
+    DbIdentity, CKSUM_CRC32C, FEATURE_TDE_AAD_V2,
+};
+use crate::metrics::{record_backup_page_emitted, record_restore_page_written};
+use crate::page::{
+    KV_HDR_MIN, KV_OFF_LSN, OFF_TYPE, OVF_OFF_LSN, PAGE_MAGIC, PAGE_TYPE_KV_RH3,
+    PAGE_TYPE_OVERFLOW3,
+};
+use crate::pager::Pager;
+use crate::util::fsx::{fsync_parent_dir, replace_file};
+use crate::wal::encode::write_record;
+use crate::wal::reader::WalStreamReader;
+use crate::wal::{
+    generate_stream_id, wal_path, write_wal_file_header_with_stream_id, WAL_FILE, WAL_HDR_SIZE,
+    WAL_REC_BEGIN, WAL_REC_COMMIT,
+};
+
+pub const BACKUP_STREAM_MAGIC: &[u8; 8] = b"P2BKS001";
+pub const BACKUP_STREAM_VERSION: u32 = 1;
+const HDR_LEN: usize = 64;
+const REC_HDR_LEN: usize = 8;
+
+const REC_PAGE: u8 = 1;
+const REC_HEADS: u8 = 2;
+const REC_WAL: u8 = 3;
+const REC_END: u8 = 4;
+const REC_SIDECAR: u8 = 5;
+const REC_IDENT: u8 = 6;
+
+const REC_FLAG_ZSTD: u8 = 0x01;
+
+/// archive_codec в заголовке потока.
+pub const BACKUP_CODEC_NONE: u16 = 0;
+pub const BACKUP_CODEC_ZSTD: u16 = 1;
+
+/// Размер WAL‑чанка в потоке (кадры не режутся между чанками).
+const WAL_CHUNK: usize = 1 << 20;
+
+/// Сжатие payload'ов архива.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackupCompression {
+    #[default]
+    None,
+    /// Уровень zstd (1..=22; 0 — уровень библиотеки по умолчанию).
+    Zstd(i32),
+}
+
+impl BackupCompression {
+    pub fn codec_id(&self) -> u16 {
+        match self {
+            BackupCompression::None => BACKUP_CODEC_NONE,
+            BackupCompression::Zstd(_) => BACKUP_CODEC_ZSTD,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackupCompression::None => "none",
+            BackupCompression::Zstd(_) => "zstd",
+        }
+    }
+
+    fn level(&self) -> i32 {
+        match self {
+            BackupCompression::None => 0,
+            BackupCompression::Zstd(l) => *l,
+        }
+    }
+}
+
+impl std::str::FromStr for BackupCompression {
+    type Err = anyhow::Error;
+
+    /// "none" | "zstd" | "zstd:<level>"
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        let (name, level) = match s.split_once(':') {
+            Some((n, l)) => (n, Some(l)),
+            None => (s.as_str(), None),
+        };
+        match name {
+            "none" | "off" if level.is_none() => Ok(BackupCompression::None),
+            "zstd" => {
+                let lvl = match level {
+                    Some(l) => l
+                        .parse::<i32>()
+                        .map_err(|_| anyhow!("invalid zstd level '{}'", l))?,
+                    None => 0,
+                };
+                if !(0..=22).contains(&lvl) {
+                    return Err(anyhow!("zstd level must be in 0..=22, got {}", lvl));
+                }
+                Ok(BackupCompression::Zstd(lvl))
+            }
+            "gzip" => Err(anyhow!(
+                "gzip is not supported; use --compress zstd[:level]"
+            )),
+            _ => Err(anyhow!(
+                "unknown backup compression '{}' (expected none|zstd[:level])",
+                s
+            )),
+        }
+    }
+}
+
+/// Параметры потокового бэкапа.
+#[derive(Debug, Clone, Default)]
+pub struct BackupOptions {
+    /// 0 — полный бэкап; иначе только страницы с page_lsn > since_lsn.
+    pub since_lsn: u64,
+    pub compress: BackupCompression,
+    /// Включить в архив образы sidecar'ов (bloom.bin), если они свежие.
+    pub include_sidecars: bool,
+}
+
+/// Манифест потока (запись END) — он же отчёт backup/restore.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackupStreamManifest {
+    pub version: u32,
+    pub since_lsn: u64,
+    /// meta.last_lsn источника на старте бэкапа.
+    pub base_lsn: u64,
+    /// LSN, до которого бэкап консистентен (max(base, page LSN, COMMIT хвоста WAL)).
+    /// Используйте как since_lsn следующего инкрементального бэкапа.
+    pub lsn: u64,
+    pub page_size: u32,
+    pub buckets: u32,
+    pub next_page_id: u64,
+    pub pages: u64,
+    pub page_bytes: u64,
+    /// Страницы, не прошедшие проверку при чтении (их состояние восстанавливает хвост WAL).
+    pub pages_skipped: u64,
+    pub heads: u64,
+    pub wal_frames: u64,
+    pub wal_bytes: u64,
+    pub created_unix_ms: u64,
+    /// Кодек payload'ов ("none" | "zstd") и уровень (для zstd).
+    #[serde(default)]
+    pub codec: String,
+    #[serde(default)]
+    pub compress_level: i32,
+    /// Байты payload'ов PAGE/WAL в потоке (после сжатия).
+    #[serde(default)]
+    pub stored_bytes: u64,
+    /// Sidecar'ы в архиве (имена файлов). В отчёте restore — фактически установленные.
+    #[serde(default)]
+    pub sidecars: Vec<String>,
+    /// Идентичность источника (пусто — бэкап старой сборки).
+    #[serde(default)]
+    pub db: DbIdentity,
+    /// С какого LSN источник подписывает AEAD-трейлеры с AAD v2 (0 — AAD v2 выключен).
+    #[serde(default)]
+    pub tde_aad_since_lsn: u64,
+    /// NEW: голова области метаданных приложения (db/user_meta.rs; нет поля — бэкап старой сборки).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_meta_head: Option<u64>,
+}
+
+// ----------------------------- backup -----------------------------
+
+/// Записать бэкап БД в поток. since_lsn=0 — полный; иначе только страницы с page_lsn > since_lsn.
+/// Writer'у передаётся поток как есть (буферизация — на стороне вызывающего или BufWriter здесь).
+pub fn backup_to_writer<W: Write>(db: &Db, out: W, since_lsn: u64) -> Result<BackupStreamManifest> {
+    let opts = BackupOptions {
+        since_lsn,
+        ..Default::default()
+    };
+    backup_to_writer_with(db, out, &opts)
+}
+
+/// Как backup_to_writer, с опциями (сжатие).
+pub fn backup_to_writer_with<W: Write>(
+    db: &Db,
+    out: W,
+    opts: &BackupOptions,
+) -> Result<BackupStreamManifest> {
+    let since_lsn = opts.since_lsn;
+    let cz = opts.compress;
+    let root = &db.root;
+    let meta = &db.pager.meta;
+    let ps = meta.page_size as usize;
+
+    // Отметка начала: всё, что закоммичено после неё, должно оказаться в хвосте WAL.
+    let start = wal_snapshot(root)?;
+    let (scan_end, scan_max_lsn) = if start.last_lsn.is_none() {
+        crate::db::clone::scan_pages(root)?
+    } else {
+        (0, 0)
+    };
+    let start_mark = start
+        .last_lsn
+        .unwrap_or(0)
+        .max(scan_max_lsn)
+        .max(meta.last_lsn);
+
+    let mut man = BackupStreamManifest {
+        version: BACKUP_STREAM_VERSION,
+        since_lsn,
+        base_lsn: meta.last_lsn,
+        lsn: meta.last_lsn,
+        page_size: meta.page_size,
+        buckets: db.dir.bucket_count,
+        next_page_id: meta.next_page_id.max(scan_end),
+        created_unix_ms: now_ms(),
+        codec: cz.name().to_string(),
+        compress_level: cz.level(),
+        db: meta.identity(),
+        tde_aad_since_lsn: if meta.flags & FEATURE_TDE_AAD_V2 != 0 {
+            meta.tde_aad_since_lsn
+        } else {
+            0
+        },
+        ..Default::default()
+    };
+
+    let mut w = BufWriter::with_capacity(1 << 20, out);
+    let mut hdr = [0u8; HDR_LEN];
+    hdr[0..8].copy_from_slice(BACKUP_STREAM_MAGIC);
+    LittleEndian::write_u32(&mut hdr[8..12], BACKUP_STREAM_VERSION);
+    LittleEndian::write_u32(&mut hdr[12..16], meta.page_size);
+    LittleEndian::write_u32(&mut hdr[16..20], man.buckets);
+    LittleEndian::write_u32(&mut hdr[20..24], meta.hash_kind);
+    LittleEndian::write_u16(&mut hdr[24..26], meta.codec_default);
+    LittleEndian::write_u16(&mut hdr[28..30], cz.codec_id());
+    LittleEndian::write_u64(&mut hdr[32..40], since_lsn);
+    LittleEndian::write_u64(&mut hdr[40..48], man.base_lsn);
+    LittleEndian::write_u64(&mut hdr[48..56], man.created_unix_ms);
+    let crc = crc32c::crc32c(&hdr[..56]);
+    LittleEndian::write_u32(&mut hdr[60..64], crc);
+    w.write_all(&hdr)?;
+    if !man.db.db_uuid.is_empty() {
+        write_rec(&mut w, REC_IDENT, 0, &serde_json::to_vec(&man.db)?)?;
+    }
+
+    // [1] Страницы
+    let mut page = vec![0u8; ps];
+    let mut rec = Vec::with_capacity(8 + ps);
+    for pid in 0..man.next_page_id {
+        if db.pager.read_page(pid, &mut page).is_err() {
+            man.pages_skipped += 1;
+            continue;
+        }
+        if &page[0..4] != PAGE_MAGIC {
+            continue; // свободная/неинициализированная страница
+        }
+        let lsn = v3_page_lsn(&page).unwrap_or(0);
+        if lsn <= since_lsn {
+            continue;
+        }
+        man.lsn = man.lsn.max(lsn);
+        rec.clear();
+        rec.extend_from_slice(&pid.to_le_bytes());
+        rec.extend_from_slice(&page);
+        man.stored_bytes += write_data_rec(&mut w, REC_PAGE, &rec, cz)?;
+        man.pages += 1;
+        man.page_bytes += ps as u64;
+        record_backup_page_emitted(ps);
+    }
+
+    // [2] Головы каталога (все бакеты)
+    let mut heads = Vec::with_capacity(man.buckets as usize * 12);
+    for b in 0..man.buckets {
+        heads.extend_from_slice(&b.to_le_bytes());
+        heads.extend_from_slice(&db.dir.head(b)?.to_le_bytes());
+    }
+    write_rec(&mut w, REC_HEADS, 0, &heads)?;
+    man.heads = man.buckets as u64;
+    // NEW: голова области метаданных — meta пишется после коммита её страниц, так что они уже
+    // в секции страниц или в хвосте WAL ниже
+    man.user_meta_head = Some(db.user_meta_head()?);
+
+    // [3] Хвост WAL: целые батчи; проверка ротации относительно отметки начала
+    let end = wal_snapshot(root)?;
+    if let Some(first) = end.first_begin_lsn {
+        if first > start_mark.saturating_add(1) {
+            return Err(anyhow!(
+                "WAL rotated during streaming backup (frames {}..{} lost); retry the backup",
+                start_mark + 1,
+                first
+            ));
+        }
+    }
+    let mut chunk = Vec::with_capacity(WAL_CHUNK.min(end.frames.len()));
+    for (frame, _) in &end.frames {
+        if chunk.len() + frame.len() > WAL_CHUNK && !chunk.is_empty() {
+            man.stored_bytes += write_data_rec(&mut w, REC_WAL, &chunk, cz)?;
+            chunk.clear();
+        }
+        chunk.extend_from_slice(frame);
+        man.wal_frames += 1;
+        man.wal_bytes += frame.len() as u64;
+    }
+    if !chunk.is_empty() {
+        man.stored_bytes += write_data_rec(&mut w, REC_WAL, &chunk, cz)?;
+    }
+    if let Some(l) = end.last_lsn {
+        man.lsn = man.lsn.max(l);
+    }
+
+    // [4] Sidecars: словарь значений — всегда (без него данные не читаются)
+    let dict_path = root.join(VALUE_DICT_FILE);
+    if dict_path.exists() {
+        let bytes =
+            std::fs::read(&dict_path).with_context(|| format!("read {}", dict_path.display()))?;
+        let mut rec = Vec::with_capacity(2 + VALUE_DICT_FILE.len() + bytes.len());
+        rec.extend_from_slice(&(VALUE_DICT_FILE.len() as u16).to_le_bytes());
+        rec.extend_from_slice(VALUE_DICT_FILE.as_bytes());
+        rec.extend_from_slice(&bytes);
+        man.stored_bytes += write_data_rec(&mut w, REC_SIDECAR, &rec, cz)?;
+        man.sidecars.push(VALUE_DICT_FILE.to_string());
+    }
+    // Bloom: только образ, покрывающий ровно консистентную точку бэкапа
+    if opts.include_sidecars {
+        match BloomImage::read(root) {
+            Ok(Some(img)) if img.covers(man.buckets, man.lsn) => {
+                let mut rec = Vec::with_capacity(2 + BLOOM_FILE.len() + img.bytes.len());
+                rec.extend_from_slice(&(BLOOM_FILE.len() as u16).to_le_bytes());
+                rec.extend_from_slice(BLOOM_FILE.as_bytes());
+                rec.extend_from_slice(&img.bytes);
+                man.stored_bytes += write_data_rec(&mut w, REC_SIDECAR, &rec, cz)?;
+                man.sidecars.push(BLOOM_FILE.to_string());
+            }
+            Ok(Some(img)) => eprintln!(
+                "[WARN] backup: bloom.bin is stale (last_lsn {} != backup lsn {}); not included",
+                img.last_lsn, man.lsn
+            ),
+            Ok(None) => {}
+            Err(e) => eprintln!("[WARN] backup: bloom.bin not included: {:#}", e),
+        }
+    }
+
+    // [5] END
+    let body = serde_json::to_vec(&man)?;
+    write_rec(&mut w, REC_END, 0, &body)?;
+    w.flush()?;
+    Ok(man)
+}
+
+/// Суффикс незавершённого архива (backup_to_path).
+pub const PARTIAL_SUFFIX: &str = ".partial";
+
+/// `<out>.partial` — куда backup_to_path пишет архив до финализации.
+pub fn partial_path(out: &Path) -> PathBuf {
+    let mut s = out.as_os_str().to_os_string();
+    s.push(PARTIAL_SUFFIX);
+    PathBuf::from(s)
+}
+
+/// Бэкап в файл; путь "-" — stdout. Файл появляется под именем `out` только целиком
+/// (запись в `<out>.partial`, fsync, атомарный rename).
+pub fn backup_to_path(db: &Db, out: &Path, opts: &BackupOptions) -> Result<BackupStreamManifest> {
+    if out.as_os_str() == "-" {
+        let stdout = std::io::stdout();
+        return backup_to_writer_with(db, stdout.lock(), opts);
+    }
+    let part = partial_path(out);
+    let res = (|| -> Result<BackupStreamManifest> {
+        let f = File::create(&part).with_context(|| format!("create {}", part.display()))?;
+        let man = backup_to_writer_with(db, &f, opts)?;
+        f.sync_all()?;
+        drop(f);
+        replace_file(&part, out)
+            .with_context(|| format!("rename {} -> {}", part.display(), out.display()))?;
+        let _ = fsync_parent_dir(out);
+        Ok(man)
+    })();
+    if res.is_err() {
+        let _ = std::fs::remove_file(&part);
+    }
+    res
+}
+
+// ----------------------------- restore -----------------------------
+
+/// Параметры restore.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RestoreOptions {
+    /// Сверка счётчиков записей с манифестом END.
+    pub verify: bool,
+    /// Пропустить проверку поколения (цель новее бэкапа / другая БД).
+    pub force: bool,
+    /// NEW: snapshot-restore — требовать верную доверенную подпись манифеста
+    /// (snapstore/signature). Потоковый restore поле игнорирует.
+    pub require_signature: bool,
+}
+
+/// Restore отвергнут проверкой поколения. Достаётся из anyhow через downcast_ref.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestoreConflictError {
+    pub path: PathBuf,
+    /// "newer_target" | "foreign_db"
+    pub reason: &'static str,
+    pub target_lsn: u64,
+    pub backup_lsn: u64,
+    pub target_uuid: String,
+    pub backup_uuid: String,
+}
+
+impl std::fmt::Display for RestoreConflictError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.reason == "foreign_db" {
+            write!(
+                f,
+                "restore target {} is database {} but the backup is of database {} \
+                 (target lsn={}, backup lsn={}); use --force to restore anyway",
+                self.path.display(),
+                self.target_uuid,
+                self.backup_uuid,
+                self.target_lsn,
+                self.backup_lsn
+            )
+        } else {
+            write!(
+                f,
+                "restore would go backwards: target {} is at lsn={}, the backup covers lsn={}; \
+                 use --force to restore anyway",
+                self.path.display(),
+                self.target_lsn,
+                self.backup_lsn
+            )
+        }
+    }
+}
+
+impl std::error::Error for RestoreConflictError {}
+
+/// Проверка поколения цели: не новее бэкапа (target_lsn <= backup_lsn) и та же БД
+/// (если UUID известны обеим сторонам). force — только [WARN].
+pub(crate) fn check_restore_target(
+    dst_root: &Path,
+    backup: &DbIdentity,
+    backup_lsn: u64,
+    force: bool,
+) -> Result<()> {
+    let m = read_meta(dst_root)?;
+    let target = m.identity();
+    let reason = if !backup.db_uuid.is_empty()
+        && !target.db_uuid.is_empty()
+        && backup.db_uuid != target.db_uuid
+    {
+        "foreign_db"
+    } else if m.last_lsn > backup_lsn {
+        "newer_target"
+    } else {
+        return Ok(());
+    };
+    let e = RestoreConflictError {
+        path: dst_root.to_path_buf(),
+        reason,
+        target_lsn: m.last_lsn,
+        backup_lsn,
+        target_uuid: target.db_uuid,
+        backup_uuid: backup.db_uuid.clone(),
+    };
+    if !force {
+        return Err(e.into());
+    }
+    eprintln!("[WARN] {}; --force given, restoring", e);
+    Ok(())
+}
+
+/// Восстановить БД из потока. Полный бэкап (since_lsn=0) — в пустой/новый корень;
+/// инкрементальный — поверх существующей БД с теми же page_size/buckets.
+/// verify=true — сверка счётчиков записей с манифестом END.
+pub fn restore_from_reader<R: Read>(
+    dst_root: &Path,
+    input: R,
+    verify: bool,
+) -> Result<BackupStreamManifest> {
+    let opts = RestoreOptions {
+        verify,
+        ..Default::default()
+    };
+    restore_from_reader_with(dst_root, input, &opts)
+}
+
+/// Как restore_from_reader, с опциями (проверка поколения цели).
+pub fn restore_from_reader_with<R: Read>(
+    dst_root: &Path,
+    mut input: R,
+    opts: &RestoreOptions,
+) -> Result<BackupStreamManifest> {
+    let verify = opts.verify;
+    let mut hdr = [0u8; HDR_LEN];
+    input
+        .read_exact(&mut hdr)
+        .context("read backup stream header")?;
+    if &hdr[0..8] != BACKUP_STREAM_MAGIC {
+        return Err(anyhow!("not a QuiverDB backup stream (bad magic)"));
+    }
+    if crc32c::crc32c(&hdr[..56]) != LittleEndian::read_u32(&hdr[60..64]) {
+        return Err(anyhow!("backup stream header CRC mismatch"));
+    }
+    let version = LittleEndian::read_u32(&hdr[8..12]);
+    if version != BACKUP_STREAM_VERSION {
+        return Err(anyhow!("unsupported backup stream version {}", version));
+    }
+    let page_size = LittleEndian::read_u32(&hdr[12..16]);
+    let buckets = LittleEndian::read_u32(&hdr[16..20]);
+    let hash_kind = LittleEndian::read_u32(&hdr[20..24]);
+    let codec_default = LittleEndian::read_u16(&hdr[24..26]);
+    let since_lsn = LittleEndian::read_u64(&hdr[32..40]);
+    let base_lsn = LittleEndian::read_u64(&hdr[40..48]);
+
+    prepare_dst(
+        dst_root,
+        page_size,
+        buckets,
+        hash_kind,
+        codec_default,
+        since_lsn,
+    )?;
+
+    // IDENT — первая запись: сверка с базой инкрементального бэкапа до любых изменений в dst
+    // (поток старой сборки без IDENT сверяется только по LSN)
+    let mut first = read_data_rec(&mut input)?;
+    let mut src = DbIdentity::default();
+    if let Some((REC_IDENT, payload)) = &first {
+        src = serde_json::from_slice(payload).context("parse IDENT record")?;
+        first = None;
+    }
+    if since_lsn > 0 {
+        // Архив знает всё до max(since, base): цель дальше — у неё есть коммиты новее бэкапа
+        check_restore_target(dst_root, &src, base_lsn.max(since_lsn), opts.force)?;
+    }
+
+    let mut pager = Pager::open(dst_root)?;
+    let dir = Directory::open(dst_root)?;
+    let ps = page_size as usize;
+
+    // WAL клона — новый поток; захваченные кадры реплеятся при открытии
+    let wal_p = dst_root.join(WAL_FILE);
+    let mut wal = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .open(&wal_p)
+        .with_context(|| format!("create {}", wal_p.display()))?;
+    write_wal_file_header_with_stream_id(&mut wal, generate_stream_id())?;
+
+    let (mut pages, mut heads, mut wal_frames) = (0u64, 0u64, 0u64);
+    let mut max_pid_end = 0u64;
+    let mut max_lsn = 0u64;
+    let mut sidecars: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut man: BackupStreamManifest = loop {
+        let next = match first.take() {
+            Some(r) => Some(r),
+            None => read_data_rec(&mut input)?,
+        };
+        let (kind, payload) = match next {
+            Some(r) => r,
+            None => {
+                return Err(anyhow!(
+                    "truncated backup stream (no END record): the backup did not finish"
+                ))
+            }
+        };
+        match kind {
+            REC_PAGE => {
+                if payload.len() != 8 + ps {
+                    return Err(anyhow!(
+                        "PAGE record length {} != 8 + page_size {}",
+                        payload.len(),
+                        ps
+                    ));
+                }
+                let pid = LittleEndian::read_u64(&payload[0..8]);
+                let page = &payload[8..];
+                pager.ensure_allocated(pid)?;
+                pager.write_page_raw(pid, page)?;
+                max_pid_end = max_pid_end.max(pid + 1);
+                max_lsn = max_lsn.max(v3_page_lsn(page).unwrap_or(0));
+                pages += 1;
+                record_restore_page_written(ps);
+            }
+            REC_HEADS => {
+                let updates: Vec<(u32, u64)> = payload
+                    .chunks_exact(12)
+                    .map(|c| {
+                        (
+                            LittleEndian::read_u32(&c[0..4]),
+                            LittleEndian::read_u64(&c[4..12]),
+                        )
+                    })
+                    .collect();
+                heads += updates.len() as u64;
+                if !updates.is_empty() {
+                    dir.set_heads_bulk(&updates)?;
+                }
+            }
+            REC_WAL => {
+                wal_frames += append_wal_chunk(&mut wal, &payload)?;
+            }
+            REC_SIDECAR => {
+                if payload.len() < 2 {
+                    return Err(anyhow!("SIDECAR record too short"));
+                }
+                let n = LittleEndian::read_u16(&payload[0..2]) as usize;
+                if payload.len() < 2 + n {
+                    return Err(anyhow!("SIDECAR record name overflows payload"));
+                }
+                let name = String::from_utf8_lossy(&payload[2..2 + n]).into_owned();
+                sidecars.push((name, payload[2 + n..].to_vec()));
+            }
+            REC_END => break serde_json::from_slice(&payload).context("parse END manifest")?,
+            _ => { /* неизвестные записи пропускаются (forward-compatible) */
+            }
+        }
+    };
+    wal.sync_all()?;
+    drop(wal);
+
+    if verify && (pages != man.pages || heads != man.heads || wal_frames != man.wal_frames) {
+        return Err(anyhow!(
+            "backup stream verify failed: pages {}/{}, heads {}/{}, wal_frames {}/{}",
+            pages,
+            man.pages,
+            heads,
+            man.heads,
+            wal_frames,
+            man.wal_frames
+        ));
+    }
+
+    // meta: LSN/next_page_id по содержимому; при наличии кадров — реплей на открытии
+    let mut m = read_meta(dst_root)?;
+    m.last_lsn = m.last_lsn.max(man.base_lsn).max(max_lsn);
+    m.next_page_id = m.next_page_id.max(man.next_page_id).max(max_pid_end);
+    m.clean_shutdown = wal_frames == 0;
+    if since_lsn == 0 {
+        adopt_db_identity(&mut m, &man.db)?;
+    }
+    if man.tde_aad_since_lsn > 0 && m.flags & FEATURE_TDE_AAD_V2 == 0 {
+        m.flags |= FEATURE_TDE_AAD_V2;
+        m.tde_aad_since_lsn = man.tde_aad_since_lsn;
+    }
+    adopt_user_meta_head(&mut m, man.user_meta_head);
+    write_meta_overwrite(dst_root, &m)?;
+    drop(pager);
+    // Словарь значений — до реплея: открытие БД с value_transform требует его
+    let dict_included = man.sidecars.iter().any(|n| n == VALUE_DICT_FILE);
+    for (name, bytes) in &sidecars {
+        let install = since_lsn == 0 || !dst_root.join(VALUE_DICT_FILE).exists();
+        if name == VALUE_DICT_FILE && dict_included && install {
+            install_value_dict(dst_root, bytes).context("restore value dictionary")?;
+        }
+    }
+    if wal_frames > 0 {
+        // Реплей захваченного хвоста (LSN‑гейтинг), Drop — clean shutdown + усечение WAL
+        let db = Db::open(dst_root).context("replay backup WAL tail")?;
+        drop(db);
+    }
+
+    // Sidecars — после реплея: last_lsn образа переписывается на итоговый LSN БД
+    let included = std::mem::take(&mut man.sidecars);
+    for (name, bytes) in sidecars {
+        if name == VALUE_DICT_FILE && included.contains(&name) {
+            man.sidecars.push(name); // уже поставлен до реплея
+            continue;
+        }
+        if name != BLOOM_FILE || !included.contains(&name) {
+            continue; // неизвестный sidecar
+        }
+        let img = match BloomImage::parse(bytes) {
+            Ok(img) if img.covers(man.buckets, man.lsn) => img,
+            Ok(_) => {
+                eprintln!("[WARN] restore: bloom.bin image does not cover the backup; skipped");
+                continue;
+            }
+            Err(e) => {
+                eprintln!("[WARN] restore: bloom.bin image skipped: {:#}", e);
+                continue;
+            }
+        };
+        img.install(dst_root, read_meta(dst_root)?.last_lsn)?;
+        man.sidecars.push(name);
+    }
+    Ok(man)
+}
+
+/// Восстановить из файла; путь "-" — stdin.
+pub fn restore_from_path(
+    dst_root: &Path,
+    src: &Path,
+    opts: &RestoreOptions,
+) -> Result<BackupStreamManifest> {
+    if src.as_os_str() == "-" {
+        let stdin = std::io::stdin();
+        return restore_from_reader_with(dst_root, stdin.lock(), opts);
+    }
+    let is_partial = src.to_string_lossy().ends_with(PARTIAL_SUFFIX);
+    if is_partial || (!src.exists() && partial_path(src).exists()) {
+        return Err(anyhow!(
+            "backup {} is incomplete: the backup process did not finish (only {} exists); \
+             take the backup again",
+            src.display(),
+            if is_partial {
+                src.display().to_string()
+            } else {
+                partial_path(src).display().to_string()
+            }
+        ));
+    }
+    let f = File::open(src).with_context(|| format!("open {}", src.display()))?;
+    restore_from_reader_with(
+        dst_root,
+        std::io::BufReader::with_capacity(1 << 20, f),
+        opts,
+    )
+}
+
+// ----------------------------- helpers -----------------------------
+
+/// Записать PAGE/WAL запись (со сжатием, если оно уменьшает payload); возвращает сохранённые байты.
+fn write_data_rec<W: Write>(
+    w: &mut W,
+    kind: u8,
+    payload: &[u8],
+    cz: BackupCompression,
+) -> Result<u64> {
+    if let BackupCompression::Zstd(level) = cz {
+        let packed = zstd::bulk::compress(payload, level)?;
+        if packed.len() < payload.len() {
+            write_rec(w, kind, REC_FLAG_ZSTD, &packed)?;
+            return Ok(packed.len() as u64);
+        }
+    }
+    write_rec(w, kind, 0, payload)?;
+    Ok(payload.len() as u64)
+}
+
+fn write_rec<W: Write>(w: &mut W, kind: u8, flags: u8, payload: &[u8]) -> Result<()> {
+    let mut h = [0u8; REC_HDR_LEN];
+    h[0] = kind;
+    h[1] = flags;
+    LittleEndian::write_u32(&mut h[4..8], payload.len() as u32);
+    let crc = crc32c::crc32c_append(crc32c::crc32c(&h), payload);
+    w.write_all(&h)?;
+    w.write_all(payload)?;
+    w.write_all(&crc.to_le_bytes())?;
+    Ok(())
+}
+
+/// Следующая запись потока с распакованным payload'ом.
+fn read_data_rec<R: Read>(r: &mut R) -> Result<Option<(u8, Vec<u8>)>> {
+    let (kind, flags, payload) = match read_rec(r)? {
+        Some(x) => x,
+        None => return Ok(None),
+    };
+    if flags & REC_FLAG_ZSTD == 0 {
+        return Ok(Some((kind, payload)));
+    }
+    let raw = zstd::stream::decode_all(payload.as_slice())
+        .with_context(|| format!("zstd decode of backup stream record (kind={})", kind))?;
+    Ok(Some((kind, raw)))
+}
+
+/// Следующая запись потока. Ok(None) — чистый EOF на границе записи.
+fn read_rec<R: Read>(r: &mut R) -> Result<Option<(u8, u8, Vec<u8>)>> {
+    let mut h = [0u8; REC_HDR_LEN];
+    let mut got = 0;
+    while got < REC_HDR_LEN {
+        let n = r.read(&mut h[got..])?;
+        if n == 0 {
+            if got == 0 {
+                return Ok(None);
+            }
+            return Err(anyhow!("truncated backup stream (partial record header)"));
+        }
+        got += n;
+    }
+    let len = LittleEndian::read_u32(&h[4..8]) as usize;
+    let mut payload = vec![0u8; len];
+    r.read_exact(&mut payload)
+        .context("truncated backup stream (partial record)")?;
+    let mut c = [0u8; 4];
+    r.read_exact(&mut c)
+        .context("truncated backup stream (missing record CRC)")?;
+    if crc32c::crc32c_append(crc32c::crc32c(&h), &payload) != u32::from_le_bytes(c) {
+        return Err(anyhow!("backup stream record CRC mismatch (kind={})", h[0]));
+    }
+    Ok(Some((h[0], h[1], payload)))
+}
+
+/// Дописать чанк сырых кадров WAL в файл WAL назначения; возвращает число кадров.
+fn append_wal_chunk(wal: &mut File, chunk: &[u8]) -> Result<u64> {
+    use crate::wal::{WAL_REC_HDR_SIZE, WAL_REC_OFF_LEN};
+    use std::io::{Seek, SeekFrom};
+    let mut off = 0usize;
+    let mut n = 0u64;
+    while off + WAL_REC_HDR_SIZE <= chunk.len() {
+        let len = LittleEndian::read_u32(&chunk[off + WAL_REC_OFF_LEN..off + WAL_REC_OFF_LEN + 4])
+            as usize;
+        off += WAL_REC_HDR_SIZE + len;
+        n += 1;
+    }
+    if off != chunk.len() {
+        return Err(anyhow!("WAL chunk in backup stream is not frame-aligned"));
+    }
+    wal.seek(SeekFrom::End(0))?;
+    wal.write_all(chunk)?;
+    Ok(n)
+}
+
+/// Снимок текущего WAL: целые батчи (кадры до последнего COMMIT).
+struct WalSnapshot {
+    frames: Vec<(Vec<u8>, u64)>,
+    first_begin_lsn: Option<u64>,
+    last_lsn: Option<u64>,
+}
+
+fn wal_snapshot(root: &Path) -> Result<WalSnapshot> {
+    let mut snap = WalSnapshot {
+        frames: Vec::new(),
+        first_begin_lsn: None,
+        last_lsn: None,
+    };
+    let p = wal_path(root);
+    let mut f = match File::open(&p) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(snap),
+        Err(e) => return Err(e).with_context(|| format!("open {}", p.display())),
+    };
+    let len = f.metadata()?.len();
+    let mut rdr = WalStreamReader::new();
+    let mut pos = WAL_HDR_SIZE as u64;
+    let mut pending: Vec<(Vec<u8>, u64)> = Vec::new();
+    loop {
+        let (rec, next) = match rdr.read_next(&mut f, pos, len) {
+            Ok(Some(x)) => x,
+            // частичный хвост / кадр дописывается прямо сейчас — граница снимка
+            Ok(None) | Err(_) => break,
+        };
+        pos = next;
+        if rec.rec_type == WAL_REC_BEGIN && snap.first_begin_lsn.is_none() {
+            snap.first_begin_lsn = Some(rec.lsn);
+        }
+        let mut raw = Vec::with_capacity(rec.len_total as usize);
+        write_record(&mut raw, rec.rec_type, rec.lsn, rec.page_id, &rec.payload)?;
+        pending.push((raw, rec.lsn));
+        if rec.rec_type == WAL_REC_COMMIT {
+            snap.last_lsn = Some(rec.lsn);
+            snap.frames.append(&mut pending);
+        }
+    }
+    Ok(snap)
+}
+
+fn prepare_dst(
+    dst_root: &Path,
+    page_size: u32,
+    buckets: u32,
+    hash_kind: u32,
+    codec_default: u16,
+    since_lsn: u64,
+) -> Result<()> {
+    let has_meta = dst_root.join("meta").exists();
+    if since_lsn == 0 {
+        if has_meta {
+            return Err(anyhow!(
+                "restore of a full backup requires an empty destination ({} has a DB)",
+                dst_root.display()
+            ));
+        }
+        std::fs::create_dir_all(dst_root)
+            .with_context(|| format!("create {}", dst_root.display()))?;
+        init_meta_v4(dst_root, page_size, hash_kind, codec_default, CKSUM_CRC32C)?;
+        Directory::create(dst_root, buckets)?;
+        return Ok(());
+    }
+    if !has_meta {
+        return Err(anyhow!(
+            "incremental backup (since_lsn={}) must be restored on top of its base DB",
+            since_lsn
+        ));
+    }
+    let m = read_meta(dst_root)?;
+    let dir = Directory::open(dst_root)?;
+    if m.page_size != page_size || dir.bucket_count != buckets {
+        return Err(anyhow!(
+            "base DB mismatch: page_size {} vs {}, buckets {} vs {}",
+            m.page_size,
+            page_size,
+            dir.bucket_count,
+            buckets
+        ));
+    }
+    if m.last_lsn < since_lsn {
+        return Err(anyhow!(
+            "base DB is older than the incremental backup base (last_lsn {} < since_lsn {})",
+            m.last_lsn,
+            since_lsn
+        ));
+    }
+    Ok(())
+}
+
+fn v3_page_lsn(buf: &[u8]) -> Option<u64> {
+    if buf.len() < KV_HDR_MIN || &buf[..4] != PAGE_MAGIC {
+        return None;
+    }
+    match LittleEndian::read_u16(&buf[OFF_TYPE..OFF_TYPE + 2]) {
+        t if t == PAGE_TYPE_KV_RH3 => {
+            Some(LittleEndian::read_u64(&buf[KV_OFF_LSN..KV_OFF_LSN + 8]))
+        }
+        t if t == PAGE_TYPE_OVERFLOW3 => {
+            Some(LittleEndian::read_u64(&buf[OVF_OFF_LSN..OVF_OFF_LSN + 8]))
+        }
+        _ => None,
+    }
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
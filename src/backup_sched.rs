@@ -0,0 +1,743 @@
+//! backup_sched — планировщик offsite-бэкапов поверх потокового бэкапа (backup.rs).
+//!
+//! Цель (BackupTarget) — каталог (локальный или смонтированный bucket) или S3-совместимое
+//! хранилище через CLI (`s3://bucket/prefix`, команды `aws s3 cp/ls/rm`; endpoint для
+//! MinIO/Ceph и т. п. — ENV P1_BACKUP_S3_ENDPOINT). Своего HTTP/S3-клиента в крейте нет.
+//!
+//! Раскладка на цели (имена сортируются по времени):
+//!   qdb-<created_ms 13 цифр>-<full|incr>.qbk       — архив P2BKS001 (backup_to_writer_with)
+//!   qdb-<created_ms 13 цифр>-<full|incr>.qbk.json  — хэш-манифест OffsiteBackupEntry
+//! Манифест пишется последним, после проверки: архив читается с цели обратно и сверяется
+//! SHA-256/длина. Архив без манифеста — незавершённая выгрузка (удаляется prune).
+//!
+//! Цепочки: полный бэкап + инкрементальные (since_lsn = lsn предыдущего, parent — его имя).
+//! Полный бэкап делается, если цепочки нет, если в ней уже full_every инкрементальных или
+//! если LSN БД меньше LSN последнего бэкапа (БД пересоздана/восстановлена).
+//!
+//! Lifecycle (keep N daily, M weekly): хранится новейший бэкап каждого из N последних дней
+//! (UTC) и каждой из M последних недель, где были бэкапы, плюс самый свежий; для каждого
+//! оставленного — вся его цепочка до полного. Остальное удаляется (сначала манифест).
+//! keep_daily = keep_weekly = 0 — хранить всё.
+//!
+//! Restore-test: новейшая цепочка скачивается и разворачивается во временный корень
+//! (restore_from_reader с verify), затем БД открывается RO и сверяется LSN.
+//!
+//! Итоги пишутся в метрики (backup_sched_*) и audit log maint-daemon (<root>/.maint_audit.jsonl).
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::backup::{backup_to_writer_with, restore_from_reader, BackupCompression, BackupOptions};
+use crate::db::maint_sched::append_maint_audit;
+use crate::db::Db;
+use crate::metrics::{
+    record_backup_sched_failure, record_backup_sched_pruned, record_backup_sched_run,
+    record_restore_test,
+};
+
+const DAY_MS: u64 = 86_400_000;
+const ARCHIVE_EXT: &str = ".qbk";
+const MANIFEST_EXT: &str = ".qbk.json";
+
+// ------------------------- targets -------------------------
+
+/// Хранилище бэкапов (плоское пространство имён объектов).
+pub trait BackupTarget: Send {
+    /// Записать объект целиком из потока.
+    fn put(&self, name: &str, src: &mut dyn Read) -> Result<()>;
+    /// Прочитать объект потоком.
+    fn get(&self, name: &str) -> Result<Box<dyn Read>>;
+    /// Имена всех объектов.
+    fn list(&self) -> Result<Vec<String>>;
+    fn delete(&self, name: &str) -> Result<()>;
+    /// Для логов.
+    fn describe(&self) -> String;
+}
+
+/// Каталог (локальный диск, NFS, смонтированный bucket).
+pub struct DirTarget {
+    dir: PathBuf,
+}
+
+impl DirTarget {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).with_context(|| format!("create {}", dir.display()))?;
+        Ok(Self { dir })
+    }
+}
+
+impl BackupTarget for DirTarget {
+    fn put(&self, name: &str, src: &mut dyn Read) -> Result<()> {
+        let path = self.dir.join(name);
+        let tmp = self.dir.join(format!("{}.tmp", name));
+        {
+            let mut f = File::create(&tmp).with_context(|| format!("create {}", tmp.display()))?;
+            std::io::copy(src, &mut f)?;
+            f.sync_all()?;
+        }
+        crate::util::fsx::replace_file(&tmp, &path)
+            .with_context(|| format!("rename {} -> {}", tmp.display(), path.display()))
+    }
+
+    fn get(&self, name: &str) -> Result<Box<dyn Read>> {
+        let p = self.dir.join(name);
+        let f = File::open(&p).with_context(|| format!("open {}", p.display()))?;
+        Ok(Box::new(BufReader::with_capacity(1 << 20, f)))
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let mut out = Vec::new();
+        for e in
+            fs::read_dir(&self.dir).with_context(|| format!("read_dir {}", self.dir.display()))?
+        {
+            let e = e?;
+            if e.file_type()?.is_file() {
+                if let Some(n) = e.file_name().to_str() {
+                    if !n.ends_with(".tmp") {
+                        out.push(n.to_string());
+                    }
+                }
+            }
+        }
+        out.sort();
+        Ok(out)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        let p = self.dir.join(name);
+        match fs::remove_file(&p) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("remove {}", p.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("dir {}", self.dir.display())
+    }
+}
+
+/// Цель через внешние команды (`sh -c`); `{name}` подставляется именем объекта.
+/// put получает объект в stdin, get отдаёт его в stdout, list печатает по объекту на строку
+/// (берётся последнее слово строки — подходит и для вывода `aws s3 ls`).
+pub struct CommandTarget {
+    pub put_cmd: String,
+    pub get_cmd: String,
+    pub list_cmd: String,
+    pub delete_cmd: String,
+}
+
+impl CommandTarget {
+    /// S3-совместимое хранилище через AWS CLI: url = s3://bucket[/prefix].
+    pub fn s3(url: &str) -> Result<Self> {
+        let rest = url
+            .strip_prefix("s3://")
+            .ok_or_else(|| anyhow!("not an s3:// url: {}", url))?;
+        if rest.is_empty() || rest.starts_with('/') {
+            return Err(anyhow!("s3 url must name a bucket: {}", url));
+        }
+        let base = format!("s3://{}/", rest.trim_end_matches('/'));
+        let ep = std::env::var("P1_BACKUP_S3_ENDPOINT")
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .map(|s| format!(" --endpoint-url {}", shell_quote(&s)))
+            .unwrap_or_default();
+        let obj = shell_quote(&format!("{}{{name}}", base));
+        Ok(Self {
+            put_cmd: format!("aws s3 cp{} - {}", ep, obj),
+            get_cmd: format!("aws s3 cp{} {} -", ep, obj),
+            list_cmd: format!("aws s3 ls{} {}", ep, shell_quote(&base)),
+            delete_cmd: format!("aws s3 rm{} {}", ep, obj),
+        })
+    }
+
+    fn command(tpl: &str, name: &str) -> Command {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(tpl.replace("{name}", name));
+        c
+    }
+}
+
+impl BackupTarget for CommandTarget {
+    fn put(&self, name: &str, src: &mut dyn Read) -> Result<()> {
+        let mut child = Self::command(&self.put_cmd, name)
+            .stdin(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("spawn '{}'", self.put_cmd))?;
+        let copied = {
+            let mut stdin = child.stdin.take().expect("piped stdin");
+            std::io::copy(src, &mut stdin).and_then(|_| stdin.flush())
+        };
+        let st = child.wait()?;
+        copied.with_context(|| format!("upload {}", name))?;
+        if !st.success() {
+            return Err(anyhow!(
+                "upload {}: '{}' exited with {}",
+                name,
+                self.put_cmd,
+                st
+            ));
+        }
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<Box<dyn Read>> {
+        let out = Self::command(&self.get_cmd, name)
+            .stderr(Stdio::inherit())
+            .output()
+            .with_context(|| format!("spawn '{}'", self.get_cmd))?;
+        if !out.status.success() {
+            return Err(anyhow!(
+                "download {}: '{}' exited with {}",
+                name,
+                self.get_cmd,
+                out.status
+            ));
+        }
+        Ok(Box::new(std::io::Cursor::new(out.stdout)))
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let out = Self::command(&self.list_cmd, "")
+            .stderr(Stdio::inherit())
+            .output()
+            .with_context(|| format!("spawn '{}'", self.list_cmd))?;
+        // пустой префикс у `aws s3 ls` — код 1 без вывода
+        if !out.status.success() && !out.stdout.is_empty() {
+            return Err(anyhow!(
+                "list: '{}' exited with {}",
+                self.list_cmd,
+                out.status
+            ));
+        }
+        let mut names: Vec<String> = String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .filter_map(|l| l.split_whitespace().last().map(str::to_string))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        let st = Self::command(&self.delete_cmd, name)
+            .status()
+            .with_context(|| format!("spawn '{}'", self.delete_cmd))?;
+        if !st.success() {
+            return Err(anyhow!(
+                "delete {}: '{}' exited with {}",
+                name,
+                self.delete_cmd,
+                st
+            ));
+        }
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        format!("command ({})", self.put_cmd)
+    }
+}
+
+/// Цель по строке: `s3://bucket/prefix`, `file:///path` или путь к каталогу.
+pub fn open_backup_target(spec: &str) -> Result<Box<dyn BackupTarget>> {
+    let s = spec.trim();
+    if s.starts_with("s3://") {
+        return Ok(Box::new(CommandTarget::s3(s)?));
+    }
+    let path = s.strip_prefix("file://").unwrap_or(s);
+    if path.is_empty() {
+        return Err(anyhow!("empty backup target"));
+    }
+    Ok(Box::new(DirTarget::new(path)?))
+}
+
+// ------------------------- catalog -------------------------
+
+/// Хэш-манифест бэкапа на цели (<name>.json).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OffsiteBackupEntry {
+    /// Имя архива на цели.
+    pub name: String,
+    /// "full" | "incr".
+    pub kind: String,
+    /// Предыдущее звено цепочки (для incr).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    pub since_lsn: u64,
+    /// LSN консистентной точки (since_lsn следующего incr).
+    pub lsn: u64,
+    pub bytes: u64,
+    pub sha256: String,
+    pub created_unix_ms: u64,
+    pub codec: String,
+}
+
+impl OffsiteBackupEntry {
+    pub fn is_full(&self) -> bool {
+        self.kind == "full"
+    }
+}
+
+/// Завершённые бэкапы на цели (с манифестом), старые → новые.
+pub fn list_offsite_backups(target: &dyn BackupTarget) -> Result<Vec<OffsiteBackupEntry>> {
+    let mut out = Vec::new();
+    for n in target.list()? {
+        if !n.ends_with(MANIFEST_EXT) {
+            continue;
+        }
+        let mut buf = Vec::new();
+        target.get(&n)?.read_to_end(&mut buf)?;
+        let e: OffsiteBackupEntry =
+            serde_json::from_slice(&buf).with_context(|| format!("parse {}", n))?;
+        out.push(e);
+    }
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(out)
+}
+
+/// Цепочка от полного бэкапа до `name` включительно.
+pub fn backup_chain<'a>(
+    all: &'a [OffsiteBackupEntry],
+    name: &str,
+) -> Result<Vec<&'a OffsiteBackupEntry>> {
+    let mut chain = Vec::new();
+    let mut cur = Some(name.to_string());
+    while let Some(n) = cur {
+        let e = all
+            .iter()
+            .find(|e| e.name == n)
+            .ok_or_else(|| anyhow!("backup chain is broken: '{}' is missing", n))?;
+        chain.push(e);
+        if chain.len() > all.len() {
+            return Err(anyhow!("backup chain of '{}' has a cycle", name));
+        }
+        cur = if e.is_full() { None } else { e.parent.clone() };
+        if cur.is_none() && !e.is_full() {
+            return Err(anyhow!("incremental backup '{}' has no parent", e.name));
+        }
+    }
+    chain.reverse();
+    Ok(chain)
+}
+
+/// Какие бэкапы оставить по политике (имена). keep_daily = keep_weekly = 0 — все.
+pub fn lifecycle_keep(
+    all: &[OffsiteBackupEntry],
+    keep_daily: u32,
+    keep_weekly: u32,
+) -> Result<Vec<String>> {
+    if keep_daily == 0 && keep_weekly == 0 {
+        return Ok(all.iter().map(|e| e.name.clone()).collect());
+    }
+    // новейший бэкап каждого периода, периоды — от новых к старым
+    let newest_per = |period_ms: u64, n: u32| -> Vec<&OffsiteBackupEntry> {
+        let mut picked: Vec<&OffsiteBackupEntry> = Vec::new();
+        for e in all.iter().rev() {
+            let p = e.created_unix_ms / period_ms;
+            if picked.last().map(|l| l.created_unix_ms / period_ms) == Some(p) {
+                continue;
+            }
+            if picked.len() as u32 >= n {
+                break;
+            }
+            picked.push(e);
+        }
+        picked
+    };
+    let mut roots: Vec<&OffsiteBackupEntry> = newest_per(DAY_MS, keep_daily);
+    roots.extend(newest_per(7 * DAY_MS, keep_weekly));
+    roots.extend(all.last());
+
+    let mut keep: Vec<String> = Vec::new();
+    for r in roots {
+        for e in backup_chain(all, &r.name)? {
+            if !keep.contains(&e.name) {
+                keep.push(e.name.clone());
+            }
+        }
+    }
+    keep.sort();
+    Ok(keep)
+}
+
+// ------------------------- scheduler -------------------------
+
+/// Расписание offsite-бэкапов.
+#[derive(Debug, Clone)]
+pub struct BackupSchedule {
+    /// Период бэкапа, сек (0 — выключено).
+    pub interval_secs: u64,
+    /// Полный бэкап после стольких инкрементальных (0 — всегда полный).
+    pub full_every: u32,
+    pub keep_daily: u32,
+    pub keep_weekly: u32,
+    /// Период restore-test, сек (0 — выключено).
+    pub restore_test_interval_secs: u64,
+    pub compress: BackupCompression,
+    pub include_sidecars: bool,
+}
+
+impl Default for BackupSchedule {
+    fn default() -> Self {
+        Self {
+            interval_secs: 0,
+            full_every: 7,
+            keep_daily: 7,
+            keep_weekly: 4,
+            restore_test_interval_secs: 0,
+            compress: BackupCompression::default(),
+            include_sidecars: true,
+        }
+    }
+}
+
+/// Итог restore-test.
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreTestReport {
+    /// Проверенный (новейший) бэкап.
+    pub name: String,
+    /// Звеньев цепочки развернуто.
+    pub chain_len: usize,
+    pub lsn: u64,
+    pub elapsed_ms: u64,
+}
+
+/// Итог тика планировщика бэкапов (поля заполнены только у выполненных задач).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BackupTickReport {
+    pub backup: Option<OffsiteBackupEntry>,
+    pub pruned: Vec<String>,
+    pub restore_test: Option<RestoreTestReport>,
+    pub errors: Vec<String>,
+}
+
+pub struct BackupScheduler {
+    target: Box<dyn BackupTarget>,
+    sched: BackupSchedule,
+    last_backup_ms: Option<u64>,
+    last_restore_test_ms: Option<u64>,
+}
+
+impl BackupScheduler {
+    /// Время последнего бэкапа берётся из каталога цели (перезапуск не делает внеочередной).
+    pub fn new(target: Box<dyn BackupTarget>, sched: BackupSchedule) -> Result<Self> {
+        let last = list_offsite_backups(target.as_ref())?
+            .last()
+            .map(|e| e.created_unix_ms);
+        Ok(Self {
+            target,
+            sched,
+            last_backup_ms: last,
+            last_restore_test_ms: None,
+        })
+    }
+
+    pub fn target(&self) -> &dyn BackupTarget {
+        self.target.as_ref()
+    }
+
+    pub fn schedule(&self) -> &BackupSchedule {
+        &self.sched
+    }
+
+    /// Выполнить созревшие задачи к моменту now_ms (Unix ms).
+    pub fn tick_at(&mut self, db: &Db, now_ms: u64) -> BackupTickReport {
+        let mut rep = BackupTickReport::default();
+        if due(self.last_backup_ms, self.sched.interval_secs, now_ms) {
+            self.last_backup_ms = Some(now_ms);
+            record_backup_sched_run();
+            match self.backup_now(db, now_ms) {
+                Ok(e) => {
+                    audit(
+                        db,
+                        "backup",
+                        true,
+                        Some(e.name.clone()),
+                        format!(
+                            "{} since_lsn={} lsn={} bytes={} sha256={} target={}",
+                            e.kind,
+                            e.since_lsn,
+                            e.lsn,
+                            e.bytes,
+                            e.sha256,
+                            self.target.describe()
+                        ),
+                    );
+                    rep.backup = Some(e);
+                    match self.prune() {
+                        Ok(p) => {
+                            for n in &p {
+                                audit(db, "backup_prune", true, Some(n.clone()), self.policy());
+                            }
+                            rep.pruned = p;
+                        }
+                        Err(err) => {
+                            record_backup_sched_failure();
+                            rep.errors.push(format!("backup_prune: {:#}", err));
+                            audit(db, "backup_prune", false, None, format!("{:#}", err));
+                        }
+                    }
+                }
+                Err(err) => {
+                    record_backup_sched_failure();
+                    rep.errors.push(format!("backup: {:#}", err));
+                    audit(db, "backup", false, None, format!("{:#}", err));
+                }
+            }
+        }
+        if due(
+            self.last_restore_test_ms,
+            self.sched.restore_test_interval_secs,
+            now_ms,
+        ) {
+            self.last_restore_test_ms = Some(now_ms);
+            match self.restore_test() {
+                Ok(Some(t)) => {
+                    record_restore_test(true);
+                    audit(
+                        db,
+                        "restore_test",
+                        true,
+                        Some(t.name.clone()),
+                        format!(
+                            "chain_len={} lsn={} elapsed_ms={}",
+                            t.chain_len, t.lsn, t.elapsed_ms
+                        ),
+                    );
+                    rep.restore_test = Some(t);
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    record_restore_test(false);
+                    rep.errors.push(format!("restore_test: {:#}", err));
+                    audit(db, "restore_test", false, None, format!("{:#}", err));
+                }
+            }
+        }
+        rep
+    }
+
+    /// Через сколько мс созреет ближайшая задача (None — обе выключены).
+    pub fn next_due_in_ms(&self, now_ms: u64) -> Option<u64> {
+        let next = |last: Option<u64>, every: u64| {
+            (every > 0).then(|| {
+                last.map(|l| (l + every * 1000).saturating_sub(now_ms))
+                    .unwrap_or(0)
+            })
+        };
+        let a = next(self.last_backup_ms, self.sched.interval_secs);
+        let b = next(
+            self.last_restore_test_ms,
+            self.sched.restore_test_interval_secs,
+        );
+        match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Сделать бэкап (полный или инкрементальный), выгрузить, проверить и записать манифест.
+    pub fn backup_now(&self, db: &Db, now_ms: u64) -> Result<OffsiteBackupEntry> {
+        let all = list_offsite_backups(self.target.as_ref())?;
+        let db_lsn = db.pager.meta.last_lsn;
+        let parent = all.last().filter(|last| {
+            let incrs = backup_chain(&all, &last.name)
+                .map(|c| c.len() as u32 - 1)
+                .unwrap_or(u32::MAX);
+            self.sched.full_every > 0 && incrs < self.sched.full_every && last.lsn <= db_lsn
+        });
+        let since_lsn = parent.map(|p| p.lsn).unwrap_or(0);
+        let kind = if parent.is_some() { "incr" } else { "full" };
+        // имена сортируются по времени; не раньше предыдущего (часы могли уйти назад)
+        let ts = all
+            .last()
+            .map(|l| l.created_unix_ms + 1)
+            .unwrap_or(0)
+            .max(now_ms);
+        let name = format!("qdb-{:013}-{}{}", ts, kind, ARCHIVE_EXT);
+
+        // архив локально (рядом с БД), затем выгрузка
+        let tmp = db
+            .root
+            .join(format!(".backup_sched-{}.tmp", std::process::id()));
+        let res = (|| -> Result<OffsiteBackupEntry> {
+            let opts = BackupOptions {
+                since_lsn,
+                compress: self.sched.compress,
+                include_sidecars: self.sched.include_sidecars,
+            };
+            let f = File::create(&tmp).with_context(|| format!("create {}", tmp.display()))?;
+            let man = backup_to_writer_with(db, &f, &opts)?;
+            f.sync_all()?;
+            drop(f);
+            let (sha256, bytes) = sha256_of(&mut File::open(&tmp)?)?;
+
+            self.target
+                .put(
+                    &name,
+                    &mut BufReader::with_capacity(1 << 20, File::open(&tmp)?),
+                )
+                .with_context(|| format!("upload {} to {}", name, self.target.describe()))?;
+            // verify: читаем с цели обратно
+            let (got, got_bytes) = sha256_of(&mut self.target.get(&name)?)?;
+            if got != sha256 || got_bytes != bytes {
+                let _ = self.target.delete(&name);
+                return Err(anyhow!(
+                    "verify {}: uploaded object differs (sha256 {} / {} bytes, expected {} / {})",
+                    name,
+                    got,
+                    got_bytes,
+                    sha256,
+                    bytes
+                ));
+            }
+            let e = OffsiteBackupEntry {
+                name: name.clone(),
+                kind: kind.to_string(),
+                parent: parent.map(|p| p.name.clone()),
+                since_lsn,
+                lsn: man.lsn,
+                bytes,
+                sha256,
+                created_unix_ms: ts,
+                codec: man.codec,
+            };
+            let body = serde_json::to_vec_pretty(&e)?;
+            self.target
+                .put(&format!("{}.json", name), &mut body.as_slice())
+                .with_context(|| format!("upload manifest of {}", name))?;
+            Ok(e)
+        })();
+        let _ = fs::remove_file(&tmp);
+        res
+    }
+
+    /// Применить lifecycle; возвращает удалённые бэкапы.
+    pub fn prune(&self) -> Result<Vec<String>> {
+        let all = list_offsite_backups(self.target.as_ref())?;
+        let keep = lifecycle_keep(&all, self.sched.keep_daily, self.sched.keep_weekly)?;
+        let mut pruned = Vec::new();
+        // новые → старые: цепочка оставленных бэкапов не рвётся при сбое посередине
+        for e in all.iter().rev() {
+            if keep.contains(&e.name) {
+                continue;
+            }
+            self.target.delete(&format!("{}.json", e.name))?;
+            self.target.delete(&e.name)?;
+            pruned.push(e.name.clone());
+        }
+        // архивы без манифеста — незавершённые выгрузки
+        for n in self.target.list()? {
+            if n.ends_with(ARCHIVE_EXT) && !all.iter().any(|e| e.name == n) {
+                self.target.delete(&n)?;
+            }
+        }
+        if !pruned.is_empty() {
+            record_backup_sched_pruned(pruned.len() as u64);
+        }
+        pruned.reverse();
+        Ok(pruned)
+    }
+
+    /// Развернуть новейшую цепочку во временный корень и проверить её. Ok(None) — бэкапов нет.
+    pub fn restore_test(&self) -> Result<Option<RestoreTestReport>> {
+        let t0 = std::time::Instant::now();
+        let all = list_offsite_backups(self.target.as_ref())?;
+        let Some(last) = all.last() else {
+            return Ok(None);
+        };
+        let chain = backup_chain(&all, &last.name)?;
+        let dst = std::env::temp_dir().join(format!(
+            "qdb2-restore-test-{}-{}",
+            std::process::id(),
+            last.created_unix_ms
+        ));
+        let _ = fs::remove_dir_all(&dst);
+        let res = (|| -> Result<u64> {
+            for e in &chain {
+                let mut data = Vec::with_capacity(e.bytes as usize);
+                self.target.get(&e.name)?.read_to_end(&mut data)?;
+                let (sha, _) = sha256_of(&mut data.as_slice())?;
+                if sha != e.sha256 {
+                    return Err(anyhow!(
+                        "{}: sha256 {} does not match manifest {}",
+                        e.name,
+                        sha,
+                        e.sha256
+                    ));
+                }
+                restore_from_reader(&dst, data.as_slice(), true)
+                    .with_context(|| format!("restore {}", e.name))?;
+            }
+            let db = Db::open_ro(&dst).context("open restored DB")?;
+            let lsn = db.pager.meta.last_lsn;
+            if lsn < last.lsn {
+                return Err(anyhow!(
+                    "restored DB is at lsn {} < backup lsn {}",
+                    lsn,
+                    last.lsn
+                ));
+            }
+            Ok(lsn)
+        })();
+        let _ = fs::remove_dir_all(&dst);
+        let lsn = res?;
+        Ok(Some(RestoreTestReport {
+            name: last.name.clone(),
+            chain_len: chain.len(),
+            lsn,
+            elapsed_ms: t0.elapsed().as_millis() as u64,
+        }))
+    }
+
+    fn policy(&self) -> String {
+        format!(
+            "keep_daily={} keep_weekly={}",
+            self.sched.keep_daily, self.sched.keep_weekly
+        )
+    }
+}
+
+// ------------------------- helpers -------------------------
+
+#[inline]
+fn due(last_ms: Option<u64>, every_secs: u64, now_ms: u64) -> bool {
+    every_secs > 0
+        && last_ms
+            .map(|l| now_ms >= l.saturating_add(every_secs.saturating_mul(1000)))
+            .unwrap_or(true)
+}
+
+fn sha256_of(r: &mut dyn Read) -> Result<(String, u64)> {
+    let mut h = Sha256::new();
+    let mut buf = vec![0u8; 1 << 16];
+    let mut total = 0u64;
+    loop {
+        let n = r.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        h.update(&buf[..n]);
+        total += n as u64;
+    }
+    let hex = h.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    Ok((hex, total))
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+fn audit(db: &Db, task: &str, ok: bool, name: Option<String>, detail: String) {
+    if let Err(e) = append_maint_audit(&db.root, task, ok, name, detail) {
+        eprintln!("[WARN] maint audit log: {:#}", e);
+    }
+}
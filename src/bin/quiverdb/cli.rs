@@ -1,10 +1,21 @@
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use clap::{Args, Parser, Subcommand};
+use std::path::{Path, PathBuf};
+
+use crate::output::OutputFormat;
+use crate::user_config::PATH_ENV;
 
 /// Минимальный CLI для QuiverDB 2.x (модульная версия)
+///
+/// NEW: --path берётся из QUIVERDB_PATH, если не задан; QUIVERDB_PATH по умолчанию —
+/// путь, записанный `quiverdb use <path>` (см. user_config.rs).
 #[derive(Parser, Debug)]
 #[command(name = "quiverdb", version, about = "QuiverDB 2.x CLI")]
 pub struct Cli {
+    /// Output format for command reports (status, doctor, compact, vacuum, auto-maint,
+    /// snapshot-*). Overrides per-command --json; other --json commands accept `json`.
+    #[arg(long, global = true, value_enum)]
+    pub output: Option<OutputFormat>,
+
     #[command(subcommand)]
     pub cmd: Cmd,
 }
@@ -13,17 +24,29 @@ pub struct Cli {
 pub enum Cmd {
     /// Initialize a new DB (meta v4 + dir v2)
     Init {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         #[arg(long, default_value_t = 65536)]
         page_size: u32,
         #[arg(long, default_value_t = 128)]
         buckets: u32,
+        /// NEW: bucket = hash of the key up to the first occurrence of this byte (e.g. "/")
+        #[arg(long)]
+        bucket_key_delimiter: Option<String>,
+        /// NEW: bucket = hash of at most N leading key bytes (after the delimiter cut)
+        #[arg(long)]
+        bucket_key_prefix_len: Option<u16>,
+        /// NEW: canonicalize keys with a built-in transform: lowercase | strip_namespace:<hex> | hash_long:<N>
+        #[arg(long)]
+        key_transform: Option<String>,
+        /// NEW: compress values with this zstd dictionary (copied into the DB as value_dict.bin)
+        #[arg(long)]
+        value_dict: Option<PathBuf>,
     },
 
     /// Put key/value (value as string or from file)
     Put {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         #[arg(long)]
         key: String,
@@ -37,7 +60,7 @@ pub enum Cmd {
 
     /// Get key
     Get {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         #[arg(long)]
         key: String,
@@ -48,20 +71,41 @@ pub enum Cmd {
 
     /// Quick existence check (Bloom fast‑path if fresh)
     Exists {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         #[arg(long)]
         key: String,
     },
 
     /// Delete key (tombstone write)
+    ///
+    /// В trash-режиме (P1_TRASH_GRACE_SECS > 0) значение можно вернуть через undelete.
     Del {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         #[arg(long)]
         key: String,
     },
 
+    /// NEW: Undelete: вернуть значение ключа, удалённого в trash-режиме (P1_TRASH_GRACE_SECS),
+    /// пока не истёк срок корзины. --list — ключи в корзине и их сроки.
+    ///
+    /// Примеры:
+    ///   quiverdb undelete --path ./db --key alpha
+    ///   quiverdb undelete --path ./db --list --json
+    Undelete {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        #[arg(long, required_unless_present = "list")]
+        key: Option<String>,
+        /// Показать ключи в корзине (read-only)
+        #[arg(long, default_value_t = false, conflicts_with = "key")]
+        list: bool,
+        /// JSON output
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
     /// Batch operations from JSON (single WAL batch commit)
     ///
     /// JSON формат (массив объектов):
@@ -72,7 +116,7 @@ pub enum Cmd {
     ///   {"op":"del","key":"alpha"}
     /// ]
     Batch {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         /// JSON-файл с операциями
         #[arg(long)]
@@ -84,7 +128,7 @@ pub enum Cmd {
 
     /// Scan with optional prefix. --json prints JSON array (or JSONL with --stream).
     Scan {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         /// Optional UTF-8 prefix
         #[arg(long)]
@@ -95,6 +139,15 @@ pub enum Cmd {
         /// Stream results (JSONL if --json, otherwise plain lines)
         #[arg(long, default_value_t = false)]
         stream: bool,
+        /// Только ключи: значения (и OVERFLOW-цепочки) не читаются
+        #[arg(long, default_value_t = false)]
+        keys_only: bool,
+        /// Постранично (Db::scan_page): не больше N пар, затем курсор следующей страницы
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Курсор из предыдущего вызова с --limit (next_cursor)
+        #[arg(long, requires = "limit")]
+        cursor: Option<String>,
     },
 
     /// Print meta/dir/metrics summary
@@ -102,8 +155,9 @@ pub enum Cmd {
     /// Пример:
     ///   quiverdb status --path ./db
     ///   quiverdb status --path ./db --json
+    ///   quiverdb --output plain status --path ./db
     Status {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         /// JSON output (single object)
         #[arg(long, default_value_t = false)]
@@ -112,16 +166,30 @@ pub enum Cmd {
 
     /// Sweep orphan OVERFLOW pages (writer-only)
     Sweep {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
     },
 
-    /// Doctor: scan all pages with CRC/IO checks (use --json for JSON)
+    /// Return space of free-list pages to the filesystem (punch hole, writer-only)
+    ///
+    /// Пример: quiverdb trim --path ./db --json
+    Trim {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// JSON output
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Doctor: scan all pages with CRC/IO checks and allocator double-use (use --json for JSON)
     Doctor {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         #[arg(long, default_value_t = false)]
         json: bool,
+        /// Rebuild the free list from reachability (writer; refused if a chain is broken)
+        #[arg(long, default_value_t = false)]
+        fix_alloc: bool,
     },
 
     /// Truncate WAL to header (exclusive lock required)
@@ -130,7 +198,7 @@ pub enum Cmd {
     /// - Требует эксклюзивного lock на <root>/LOCK (как writer).
     /// - Безопасно вызывать при отсутствии активного writer’а.
     Checkpoint {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
     },
 
@@ -140,7 +208,7 @@ pub enum Cmd {
     ///   quiverdb compact --path ./db --bucket 42
     /// Формат вывода: текст или JSON (--json).
     Compact {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         /// Optional bucket number to compact. If omitted, compacts all buckets.
         #[arg(long)]
@@ -154,7 +222,7 @@ pub enum Cmd {
     ///
     /// Выполняет перестройку цепочек и затем освобождает сиротские OVERFLOW страницы.
     Vacuum {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         /// JSON output
         #[arg(long, default_value_t = false)]
@@ -167,7 +235,7 @@ pub enum Cmd {
     /// Можно указать конкретный бакет и параметры фильтра.
     ///   quiverdb bloom --path ./db --bucket 10 --bpb 4096 --k 6
     Bloom {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         /// Optional bucket number. If omitted, rebuilds all buckets.
         #[arg(long)]
@@ -188,7 +256,7 @@ pub enum Cmd {
     /// Выполняет ротацию KID: включает TDE (если выключен), проверяет ключ из ENV
     /// и добавляет новую эпоху (since_lsn, kid) в журнал.
     TdeRotate {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         #[arg(long)]
         kid: String,
@@ -200,7 +268,7 @@ pub enum Cmd {
     ///   quiverdb auto-maint --path ./db --max-buckets 32 --sweep
     ///   quiverdb auto-maint --path ./db --max-buckets 16 --json
     AutoMaint {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         /// Максимум непустых бакетов для компактации в одном прогоне
         #[arg(long, default_value_t = 32)]
@@ -213,6 +281,68 @@ pub enum Cmd {
         json: bool,
     },
 
+    /// NEW: Maintenance daemon: держит writer и по расписанию выполняет auto-maintenance,
+    /// плановые persisted-снапшоты (метка "scheduled") и их retention.
+    /// Итоги задач — в метриках maint_* и в <root>/.maint_audit.jsonl.
+    ///
+    /// Интервал/retention снапшотов по умолчанию — из конфига
+    /// (P1_SNAPSHOT_INTERVAL_SECS / P1_SNAPSHOT_RETENTION).
+    ///
+    /// Примеры:
+    ///   quiverdb maint-daemon --path ./db --interval 300 --sweep --snapshot-interval 3600 --snapshot-retention 24
+    ///   quiverdb maint-daemon --path ./db --snapshot-interval 600 --once
+    ///   quiverdb maint-daemon --path ./db --backup-to s3://bucket/db --backup-interval 86400 \
+    ///       --backup-keep-daily 7 --backup-keep-weekly 4 --restore-test-interval 604800
+    MaintDaemon {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// Период auto-maintenance, сек (0 — выключено)
+        #[arg(long, default_value_t = 0)]
+        interval: u64,
+        /// Максимум непустых бакетов для компактации за запуск
+        #[arg(long, default_value_t = 32)]
+        max_buckets: u32,
+        /// Sweep сиротских OVERFLOW-страниц после компактации
+        #[arg(long, default_value_t = false)]
+        sweep: bool,
+        /// Период плановых снапшотов, сек (0 — выключено; по умолчанию snapshot_interval_secs)
+        #[arg(long)]
+        snapshot_interval: Option<u64>,
+        /// Сколько плановых снапшотов хранить (0 — все; по умолчанию snapshot_retention)
+        #[arg(long)]
+        snapshot_retention: Option<u32>,
+        /// Выполнить созревшие задачи один раз и выйти
+        #[arg(long, default_value_t = false)]
+        once: bool,
+        #[command(flatten)]
+        offsite: OffsiteBackupArgs,
+    },
+
+    /// NEW: Freeze: заморозить writer для снапшота тома (LVM/EBS/ZFS) — WAL и данные сброшены
+    /// на диск, в корне маркер консистентной точки .freeze.json, коммиты запрещены до thaw.
+    ///
+    /// Команда сама держит writer (LOCK): другой писатель на это время не откроется.
+    /// Долгоживущий процесс со своим writer'ом замораживается через Db::freeze/Db::thaw.
+    /// С --exec команда (sh -c) выполняется под заморозкой, затем thaw; без --exec — thaw по
+    /// Enter/EOF в stdin. По истечении --timeout — thaw в любом случае (--exec прерывается).
+    ///
+    /// Примеры:
+    ///   quiverdb freeze --path ./db --timeout 30 --exec 'lvcreate -s -n db-snap -L 1G vg/db'
+    ///   quiverdb freeze --path ./db --timeout 120
+    Freeze {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// Максимальная длительность заморозки, сек (0 — без ограничения)
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+        /// Команда для выполнения под заморозкой (sh -c); получает QDB_FREEZE_ROOT/QDB_FREEZE_LSN
+        #[arg(long)]
+        exec: Option<String>,
+        /// JSON output
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
     // -------------------- NEW: CDC commands --------------------
     /// CDC apply: применить поток WAL (file:// или tcp+psk://).
     ///
@@ -221,11 +351,15 @@ pub enum Cmd {
     ///   quiverdb cdc-apply --path ./follower --from tcp+psk://127.0.0.1:9099
     CdcApply {
         /// Путь к целевой БД (follower).
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         /// Источник WAL: file://<path> или tcp+psk://host:port
         #[arg(long)]
         from: String,
+        /// Применить поток другого источника (stream_id/db_uuid не совпали с запомненными)
+        /// и перепривязать к нему follower.
+        #[arg(long, default_value_t = false)]
+        force: bool,
     },
 
     /// CDC ship: отправить WAL поток (file:// sink или tcp+psk://).
@@ -233,16 +367,78 @@ pub enum Cmd {
     /// Примеры:
     ///   quiverdb cdc-ship --path ./db --to file://./wal-stream.bin
     ///   quiverdb cdc-ship --path ./db --to tcp+psk://127.0.0.1:9099 --since-lsn 12345
+    ///   quiverdb cdc-ship --path ./db --to file://./users.bin --filter-prefix user:
     CdcShip {
         /// Путь к исходной БД (producer).
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         /// Приёмник: file://<path> или tcp+psk://host:port
         #[arg(long)]
         to: String,
-        /// Отправлять кадры с lsn > N (или >= N при ENV P1_SHIP_SINCE_INCLUSIVE=1)
+        /// Отправлять кадры с lsn > N (или >= N при ENV P1_SHIP_SINCE_INCLUSIVE=1).
+        /// tcp/tls без флага — позиция из HELLO получателя (resume)
         #[arg(long)]
         since_lsn: Option<u64>,
+        /// Только страницы с ключами этого префикса (можно несколько раз).
+        /// tcp/tls без флагов — фильтр из HELLO получателя
+        #[arg(long = "filter-prefix")]
+        filter_prefix: Vec<String>,
+        /// Только бакеты из диапазонов: "0-99,512" (согласованная частичная реплика)
+        #[arg(long)]
+        filter_buckets: Option<String>,
+    },
+
+    /// CDC serve: fan-out ship-сервер — один читатель WAL, много подписчиков (tcp+psk / tls+psk).
+    ///
+    /// Подписчики подключаются как к источнику: `cdc-apply --from tcp+psk://<listen>`; у каждого
+    /// свой курсор (since_lsn из HELLO) и свой фильтр (P1_CDC_FILTER_* у подписчика).
+    /// Медленный подписчик отстаёт, не тормозя остальных. tls+psk:// — TLS; обновлённый
+    /// сертификат подхватывается без перезапуска, скорое истечение — [WARN] и метрика.
+    /// Клиентские сертификаты (mTLS) не проверяются — подписчик аутентифицируется PSK.
+    ///
+    /// Примеры:
+    ///   quiverdb cdc-serve --path ./db --listen 0.0.0.0:9099
+    ///   P1_TLS_SERVER_CERT=cert.pem P1_TLS_SERVER_KEY=key.pem \
+    ///     quiverdb cdc-serve --path ./db --listen tls+psk://0.0.0.0:9443
+    CdcServe {
+        /// Путь к исходной БД (producer).
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// Адрес прослушивания host:port (префикс tcp+psk:// допустим; tls+psk:// — TLS,
+        /// сертификат и ключ из P1_TLS_SERVER_CERT / P1_TLS_SERVER_KEY)
+        #[arg(long)]
+        listen: String,
+        /// Максимум одновременно подключённых подписчиков
+        #[arg(long, default_value_t = 64)]
+        max_subscribers: usize,
+        /// Буфер кадров в памяти (MiB); отставшие сильнее догоняют чтением WAL
+        #[arg(long, default_value_t = 64)]
+        buffer_mb: u64,
+        /// Удалять курсоры подписчиков без обновлений дольше N часов (0 — хранить всегда)
+        #[arg(long, default_value_t = 168)]
+        cursor_retention_hours: u64,
+    },
+
+    /// CDC cursors: именованные курсоры подписчиков cdc-serve (список / сброс / удаление).
+    ///
+    /// Примеры:
+    ///   quiverdb cdc-cursors --path ./db
+    ///   quiverdb cdc-cursors --path ./db --reset follower-1 --lsn 0
+    ///   quiverdb cdc-cursors --path ./db --delete follower-1
+    CdcCursors {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// Установить курсор подписчика (на --lsn, по умолчанию 0 — с начала WAL)
+        #[arg(long, conflicts_with = "delete")]
+        reset: Option<String>,
+        /// LSN для --reset
+        #[arg(long, requires = "reset")]
+        lsn: Option<u64>,
+        /// Удалить курсор подписчика
+        #[arg(long)]
+        delete: Option<String>,
+        #[arg(long, default_value_t = false)]
+        json: bool,
     },
 
     // -------------------- NEW: Snapshots (2.2) --------------------
@@ -251,7 +447,7 @@ pub enum Cmd {
     /// Пример:
     ///   quiverdb snapshot-create --path ./db --message "baseline" --label prod --label v2
     SnapshotCreate {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         /// Optional message to store in manifest
         #[arg(long)]
@@ -262,17 +458,23 @@ pub enum Cmd {
         /// Optional parent snapshot id
         #[arg(long)]
         parent: Option<String>,
+        /// Include a fresh bloom.bin so the restored DB serves misses immediately
+        #[arg(long, default_value_t = false)]
+        with_sidecars: bool,
+        /// Sign the manifest (Ed25519) with the key of this KID (default: P1_SNAPSHOT_SIGN_KID)
+        #[arg(long)]
+        sign_kid: Option<String>,
     },
 
-    /// Snapshot: list manifests (ids)
+    /// Snapshot: list manifests (ids and signature status)
     ///
     /// Пример:
     ///   quiverdb snapshot-list --path ./db
     ///   quiverdb snapshot-list --path ./db --json
     SnapshotList {
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
-        /// JSON output (array of strings)
+        /// JSON output (array of {id, signature, kid})
         #[arg(long, default_value_t = false)]
         json: bool,
     },
@@ -282,11 +484,37 @@ pub enum Cmd {
     /// Пример:
     ///   quiverdb snapshot-inspect --path ./db --id <snapshot_id>
     ///   quiverdb snapshot-inspect --path ./db --id <id> --json
+    ///   quiverdb snapshot-inspect --path ./db --id <id> --require-signature
     SnapshotInspect {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
         #[arg(long)]
+        id: String,
+        /// Fail unless the manifest carries a valid trusted signature
+        #[arg(long, default_value_t = false)]
+        require_signature: bool,
+        /// JSON output
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Snapshot: verify a snapshot against its SnapStore (manifest checksum, object sizes,
+    /// SHA-256 of a sample of objects)
+    ///
+    /// Пример:
+    ///   quiverdb snapshot-verify --path ./db --id <snapshot_id>
+    ///   quiverdb snapshot-verify --path ./db --id <snapshot_id> --full --json
+    SnapshotVerify {
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         #[arg(long)]
         id: String,
+        /// Objects to download and hash-check (0 — sizes only)
+        #[arg(long, default_value_t = 16)]
+        sample: usize,
+        /// Hash-check every object
+        #[arg(long, default_value_t = false)]
+        full: bool,
         /// JSON output
         #[arg(long, default_value_t = false)]
         json: bool,
@@ -299,7 +527,7 @@ pub enum Cmd {
     ///   quiverdb snapshot-restore --path ./dst --src ./source_db --id <snapshot_id> --verify
     SnapshotRestore {
         /// Куда восстановить БД
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         /// Откуда брать SnapStore (.snapstore/{objects,manifests}). По умолчанию совпадает с --path.
         #[arg(long)]
@@ -310,6 +538,44 @@ pub enum Cmd {
         /// Включить проверку размеров страниц (равны page_size из манифеста)
         #[arg(long, default_value_t = false)]
         verify: bool,
+        /// Восстановить поверх более новой БД (откат на месте) или чужой БД
+        #[arg(long, default_value_t = false)]
+        force: bool,
+        /// Отказать, если манифест не подписан верным доверенным ключом
+        #[arg(long, default_value_t = false)]
+        require_signature: bool,
+    },
+
+    /// Writable branch of a persisted snapshot (copy-on-write, pages are not copied)
+    ///
+    /// Непрочитанные страницы ветки читаются из SnapStore источника, записи идут в локальные
+    /// сегменты. Объекты снапшота закрепляются (snapshot-delete их не удалит).
+    ///
+    /// Примеры:
+    ///   quiverdb branch --from-snapshot <snapshot_id> --src ./prod --path ./test_db
+    ///   quiverdb branch-detach --path ./test_db
+    Branch {
+        /// Корень новой ветки (пустой или несуществующий каталог)
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// Откуда брать SnapStore. По умолчанию совпадает с --path (общий P1_SNAPSTORE_DIR).
+        #[arg(long)]
+        src: Option<PathBuf>,
+        /// Идентификатор снапшота
+        #[arg(long = "from-snapshot")]
+        from_snapshot: String,
+        /// JSON output
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Detach a branch: copy remaining snapshot pages locally and release the snapshot
+    BranchDetach {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// JSON output
+        #[arg(long, default_value_t = false)]
+        json: bool,
     },
 
     /// NEW: Snapshot: delete persisted snapshot by id (dec-ref objects + remove manifest)
@@ -318,16 +584,434 @@ pub enum Cmd {
     ///   quiverdb snapshot-delete --path ./db --id <snapshot_id>
     SnapshotDelete {
         /// Корень БД, где находится SnapStore (учитывает P1_SNAPSTORE_DIR).
-        #[arg(long)]
+        #[arg(long, env = PATH_ENV)]
         path: PathBuf,
         /// Идентификатор снапшота, который нужно удалить.
         #[arg(long)]
         id: String,
     },
+
+    /// Scrub: read-only проверка CRC/AEAD всех страниц с ограничением скорости
+    ///
+    /// Прогресс хранится в <root>/.scrub_state.json (прерванный проход продолжается),
+    /// алерты дописываются в <root>/.scrub_alerts.jsonl. LOCK не берётся: можно запускать рядом
+    /// с живым writer'ом.
+    ///
+    /// Примеры:
+    ///   quiverdb scrub --path ./db --rate-mb 16
+    ///   quiverdb scrub --path ./db --continuous --interval-secs 3600 --max-pages 4096 --json
+    Scrub {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// Лимит скорости чтения, MiB/s (0 — без ограничения)
+        #[arg(long, default_value_t = 0)]
+        rate_mb: u64,
+        /// Максимум страниц за шаг (0 — до конца прохода)
+        #[arg(long, default_value_t = 0)]
+        max_pages: u64,
+        /// Работать непрерывно: после завершения прохода ждать --interval-secs и начинать новый
+        #[arg(long, default_value_t = false)]
+        continuous: bool,
+        /// Пауза между проходами в continuous-режиме (сек)
+        #[arg(long, default_value_t = 86400)]
+        interval_secs: u64,
+        /// Сбросить сохранённый курсор и начать проход заново
+        #[arg(long, default_value_t = false)]
+        reset: bool,
+        /// JSON output (один объект на шаг)
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// WAL tail: вывести кадры WAL (тип, LSN, page_id, время коммита у COMMIT)
+    ///
+    /// Источник — WAL БД (--path) или произвольный WAL-стрим (--file, например результат
+    /// cdc-ship в file://). --follow — продолжать читать по мере роста (ротация WAL учитывается).
+    ///
+    /// Примеры:
+    ///   quiverdb wal-tail --path ./db --json
+    ///   quiverdb wal-tail --file ./stream.wal --commits-only --since-lsn 100
+    WalTail {
+        #[arg(long, conflicts_with = "file")]
+        path: Option<PathBuf>,
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Только кадры с lsn > N
+        #[arg(long)]
+        since_lsn: Option<u64>,
+        /// Только COMMIT-кадры (границы батчей с временем коммита)
+        #[arg(long, default_value_t = false)]
+        commits_only: bool,
+        /// Ждать новые кадры
+        #[arg(long, default_value_t = false)]
+        follow: bool,
+        /// JSON lines
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// WAL salvage: восстановление после частичной порчи WAL
+    ///
+    /// Сканирует WAL мимо повреждённых регионов (ресинхронизация по валидным заголовкам кадров),
+    /// печатает отчёт о пропущенном. С --apply применяет целые (BEGIN..COMMIT без пропусков)
+    /// батчи, сохраняет копию WAL в wal-000001.log.salvage-<ts> и усекает WAL.
+    ///
+    /// Примеры:
+    ///   quiverdb wal-salvage --path ./db
+    ///   quiverdb wal-salvage --path ./db --apply --json
+    WalSalvage {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// Применить целые батчи (иначе — только отчёт)
+        #[arg(long, default_value_t = false)]
+        apply: bool,
+        /// JSON output
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Live clone: консистентная копия работающей БД (staging-копии без backup+restore)
+    ///
+    /// Копирует файлы источника, параллельно догоняя WAL-дельту, и завершает короткой паузой
+    /// writer'а (shared LOCK источника) для финального догона. --dst должен быть пуст.
+    Clone {
+        #[arg(long, env = PATH_ENV)]
+        src: PathBuf,
+        #[arg(long)]
+        dst: PathBuf,
+        /// Сколько ждать паузы writer'а на переключении (мс)
+        #[arg(long, default_value_t = 2000)]
+        pause_wait_ms: u64,
+        /// Повторы при потере WAL-дельты (ротация WAL во время копирования)
+        #[arg(long, default_value_t = 3)]
+        max_rounds: u32,
+        /// Не проверять клон после реплея
+        #[arg(long, default_value_t = false)]
+        no_verify: bool,
+        /// JSON output
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Read-only HTTP admin UI: статус БД, WAL/checkpoint, графики метрик, список снапшотов
+    ///
+    /// Требует сборки с фичей "admin-ui". Только GET; restore снапшотов — через snapshot-restore.
+    ///
+    /// Примеры:
+    ///   quiverdb admin-ui --path ./db --addr 127.0.0.1:9899
+    AdminUi {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        #[arg(long, default_value = "127.0.0.1:9899")]
+        addr: String,
+    },
+
+    /// Read-only FUSE-монтирование: ключи — файлы в каталогах по префиксам ключей
+    ///
+    /// Требует сборки с фичей "fuse" (Linux, root). Снимок на момент монтирования: писать в БД
+    /// можно параллельно, файлы не меняются. Отмонтирование — umount или Ctrl-C.
+    ///
+    /// Примеры:
+    ///   quiverdb mount --path ./db --mountpoint /mnt/kv
+    ///   quiverdb mount --path ./db --mountpoint /mnt/kv --prefix users/ --delimiter :
+    Mount {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        #[arg(long)]
+        mountpoint: PathBuf,
+        /// Показывать только ключи с префиксом (срезается с путей)
+        #[arg(long)]
+        prefix: Option<String>,
+        /// Разделитель компонентов пути в ключе (один ASCII-символ)
+        #[arg(long, default_value = "/")]
+        delimiter: String,
+    },
+
+    /// Топ «горячих» префиксов ключей (профиль пишет writer при закрытии, P1_HOT_PREFIX_SAMPLE=N)
+    HotKeys {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// Сколько префиксов показать
+        #[arg(long, default_value_t = 20)]
+        top: usize,
+        /// JSON output
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Обучить словарь zstd на выборке значений (и добавить версией в value_dict.bin БД со словарём)
+    ///
+    /// Пример: quiverdb dict-train --path ./db --sample 10000 --out dict.bin
+    DictTrain {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// Сколько значений выбрать (случайная выборка)
+        #[arg(long, default_value_t = 10000)]
+        sample: usize,
+        /// Куда записать словарь (сырой формат zstd, годится для init --value-dict)
+        #[arg(long)]
+        out: PathBuf,
+        /// Максимальный размер словаря, КиБ
+        #[arg(long, default_value_t = 112)]
+        max_dict_kb: usize,
+        /// Уровень zstd для значений
+        #[arg(long, default_value_t = 3)]
+        level: i32,
+        /// JSON output
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Разбивка занятого места: живые/мёртвые данные, OVERFLOW, free, WAL, bloom, SnapStore
+    ///
+    /// Пример: quiverdb du --path ./db --top 5 --json
+    Du {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// Сколько самых больших бакетов показать
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+        /// JSON output
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Key change history: which LSN/batch put, deleted, expired or rewrote the key
+    /// (history.bin from P1_HISTORY_INDEX + the current WAL)
+    ///
+    /// Пример: quiverdb history --path ./db --key user:42 --limit 20
+    History {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        #[arg(long)]
+        key: String,
+        /// Показать только последние N изменений
+        #[arg(long)]
+        limit: Option<usize>,
+        /// JSON output
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Bucket digests and the whole-DB digest tree root; --compare lists the buckets that
+    /// differ from another replica (anti-entropy)
+    ///
+    /// Пример: quiverdb digest --path ./leader --compare ./follower
+    Digest {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// Показать digest одного бакета
+        #[arg(long)]
+        bucket: Option<u32>,
+        /// Сверить с другой репликой и вывести различающиеся бакеты
+        #[arg(long)]
+        compare: Option<PathBuf>,
+        /// JSON output
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Потоковый бэкап в один архив (файл или stdout: --out -)
+    ///
+    /// Пример: quiverdb backup --path ./db --out - | aws s3 cp - s3://bucket/db.qbk
+    Backup {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// Файл архива или "-" (stdout)
+        #[arg(long)]
+        out: PathBuf,
+        /// Инкрементальный бэкап: только страницы с page_lsn > since_lsn
+        #[arg(long, default_value_t = 0)]
+        since_lsn: u64,
+        /// Сжатие страниц/WAL в архиве: none | zstd | zstd:<level>
+        #[arg(long)]
+        compress: Option<String>,
+        /// Включить свежий bloom.bin в архив (restore без холодного Bloom)
+        #[arg(long, default_value_t = false)]
+        with_sidecars: bool,
+        /// JSON output (в stderr при --out -)
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Выгрузка всех живых ключей в порядке возрастания ключа (внешняя сортировка, формат P2EXS001)
+    ///
+    /// Пример: quiverdb export-sorted --path ./db --out keys.qex --mem-mb 256
+    ExportSorted {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// Файл дампа или "-" (stdout)
+        #[arg(long)]
+        out: PathBuf,
+        /// Бюджет памяти сортировки (MiB); сверх него — spill run'ов во временные файлы
+        #[arg(long, default_value_t = 64)]
+        mem_mb: usize,
+        /// Каталог временных run-файлов (по умолчанию системный temp)
+        #[arg(long)]
+        tmp_dir: Option<PathBuf>,
+        /// Выгружать только ключи с префиксом
+        #[arg(long)]
+        prefix: Option<String>,
+        /// JSON output (в stderr при --out -)
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Миграция формата БД (реестр шагов; прерванный upgrade продолжается с места остановки)
+    ///
+    /// Пример: quiverdb upgrade --path ./db --to v3 --dry-run
+    Upgrade {
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        /// Целевая версия формата
+        #[arg(long, default_value = "v3")]
+        to: String,
+        /// Только отчёт, без записи
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Эффективный конфиг (defaults → ENV → --config → --set) с источником каждого поля
+    ///
+    /// Пример: quiverdb config-show --path ./db --config quiver.json --set page_cache_pages=8192
+    ConfigShow {
+        /// БД (необязательно): показать производные значения для её page_size
+        #[arg(long, env = PATH_ENV)]
+        path: Option<PathBuf>,
+        /// JSON-файл конфига ({"field": value, ...})
+        #[arg(long)]
+        config: Option<PathBuf>,
+        /// Переопределение поля: --set field=value (можно несколько раз)
+        #[arg(long = "set")]
+        set: Vec<String>,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Восстановление из потокового архива (файл или stdin: --from -)
+    ///
+    /// Сжатый архив (--compress при бэкапе) распознаётся автоматически.
+    Restore {
+        /// Корень назначения (для полного бэкапа — пустой/новый)
+        #[arg(long, env = PATH_ENV)]
+        path: PathBuf,
+        #[arg(long)]
+        from: PathBuf,
+        /// Сверить счётчики записей с манифестом архива
+        #[arg(long, default_value_t = false)]
+        verify: bool,
+        /// Накатить инкремент на цель новее бэкапа или на другую БД
+        #[arg(long, default_value_t = false)]
+        force: bool,
+        /// JSON output
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Записать БД по умолчанию в пользовательский конфиг (для команд без --path)
+    ///
+    /// Приоритет: --path → QUIVERDB_PATH → путь из `quiverdb use`.
+    /// Без аргументов — показать текущий путь по умолчанию и его источник.
+    Use {
+        /// Путь к БД (относительный путь записывается как абсолютный)
+        path: Option<PathBuf>,
+        /// Удалить записанный путь
+        #[arg(long, default_value_t = false, conflicts_with = "path")]
+        clear: bool,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
 }
 
 impl Cli {
-    pub fn parse() -> Self {
-        <Cli as Parser>::parse()
+    /// Разбор без завершения процесса: ошибку аргументов main печатает по контракту кодов выхода.
+    pub fn try_parse() -> Result<Self, clap::Error> {
+        <Cli as Parser>::try_parse()
+    }
+}
+
+/// NEW: offsite-бэкапы в maint-daemon (backup_sched.rs).
+#[derive(Args, Debug, Clone)]
+pub struct OffsiteBackupArgs {
+    /// Цель offsite-бэкапов: s3://bucket/prefix (AWS CLI; ENV P1_BACKUP_S3_ENDPOINT),
+    /// file:///path или каталог
+    #[arg(long)]
+    pub backup_to: Option<String>,
+    /// Период бэкапа, сек
+    #[arg(long, default_value_t = 86400)]
+    pub backup_interval: u64,
+    /// Полный бэкап после стольких инкрементальных (0 — всегда полный)
+    #[arg(long, default_value_t = 7)]
+    pub backup_full_every: u32,
+    /// Хранить новейший бэкап каждого из N последних дней
+    #[arg(long, default_value_t = 7)]
+    pub backup_keep_daily: u32,
+    /// Хранить новейший бэкап каждой из M последних недель
+    #[arg(long, default_value_t = 4)]
+    pub backup_keep_weekly: u32,
+    /// Период restore-test (развернуть новейшую цепочку во временный корень), сек; 0 — выключено
+    #[arg(long, default_value_t = 0)]
+    pub restore_test_interval: u64,
+    /// Сжатие архивов: none | zstd[:level]
+    #[arg(long)]
+    pub backup_compress: Option<String>,
+}
+
+impl Cmd {
+    /// Путь к БД, с которой работает команда (для объектов ошибок; clone — источник).
+    pub fn db_path(&self) -> Option<&Path> {
+        match self {
+            Cmd::Init { path, .. }
+            | Cmd::Put { path, .. }
+            | Cmd::Get { path, .. }
+            | Cmd::Exists { path, .. }
+            | Cmd::Del { path, .. }
+            | Cmd::Undelete { path, .. }
+            | Cmd::Batch { path, .. }
+            | Cmd::Scan { path, .. }
+            | Cmd::Status { path, .. }
+            | Cmd::Sweep { path, .. }
+            | Cmd::Trim { path, .. }
+            | Cmd::Doctor { path, .. }
+            | Cmd::Checkpoint { path, .. }
+            | Cmd::Compact { path, .. }
+            | Cmd::Vacuum { path, .. }
+            | Cmd::Bloom { path, .. }
+            | Cmd::TdeRotate { path, .. }
+            | Cmd::AutoMaint { path, .. }
+            | Cmd::MaintDaemon { path, .. }
+            | Cmd::Freeze { path, .. }
+            | Cmd::CdcApply { path, .. }
+            | Cmd::CdcShip { path, .. }
+            | Cmd::CdcServe { path, .. }
+            | Cmd::CdcCursors { path, .. }
+            | Cmd::SnapshotCreate { path, .. }
+            | Cmd::SnapshotList { path, .. }
+            | Cmd::SnapshotInspect { path, .. }
+            | Cmd::SnapshotVerify { path, .. }
+            | Cmd::SnapshotRestore { path, .. }
+            | Cmd::SnapshotDelete { path, .. }
+            | Cmd::Branch { path, .. }
+            | Cmd::BranchDetach { path, .. }
+            | Cmd::Scrub { path, .. }
+            | Cmd::WalSalvage { path, .. }
+            | Cmd::AdminUi { path, .. }
+            | Cmd::Mount { path, .. }
+            | Cmd::HotKeys { path, .. }
+            | Cmd::Du { path, .. }
+            | Cmd::DictTrain { path, .. }
+            | Cmd::History { path, .. }
+            | Cmd::Digest { path, .. }
+            | Cmd::Backup { path, .. }
+            | Cmd::ExportSorted { path, .. }
+            | Cmd::Upgrade { path, .. }
+            | Cmd::Restore { path, .. } => Some(path.as_path()),
+            Cmd::WalTail { path, .. } | Cmd::ConfigShow { path, .. } | Cmd::Use { path, .. } => {
+                path.as_deref()
+            }
+            Cmd::Clone { src, .. } => Some(src.as_path()),
+        }
     }
 }
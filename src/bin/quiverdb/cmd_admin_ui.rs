@@ -0,0 +1,26 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// CLI: admin-ui — блокирующий read-only HTTP-дашборд (см. QuiverDB::admin_ui).
+#[cfg(feature = "admin-ui")]
+pub fn exec(path: PathBuf, addr: String) -> Result<()> {
+    use anyhow::Context;
+    use QuiverDB::admin_ui::AdminUi;
+
+    QuiverDB::meta::read_meta(&path)
+        .with_context(|| format!("not a QuiverDB root: {}", path.display()))?;
+    let ui = AdminUi::bind(&addr, Some(path))?;
+    println!(
+        "admin-ui: listening on http://{}/admin (read-only)",
+        ui.local_addr().map(|a| a.to_string()).unwrap_or(addr)
+    );
+    ui.serve();
+    Ok(())
+}
+
+#[cfg(not(feature = "admin-ui"))]
+pub fn exec(_path: PathBuf, _addr: String) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "admin-ui is not available: rebuild with `--features admin-ui`"
+    ))
+}
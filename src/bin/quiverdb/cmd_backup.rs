@@ -0,0 +1,87 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use QuiverDB::backup::{
+    backup_to_path, restore_from_path, BackupCompression, BackupOptions, BackupStreamManifest,
+    RestoreOptions,
+};
+use QuiverDB::Db;
+
+/// CLI: backup — потоковый архив в файл или stdout (`--out -`).
+/// При выводе в stdout отчёт печатается в stderr, чтобы не смешиваться с архивом.
+pub fn exec_backup(
+    path: PathBuf,
+    out: PathBuf,
+    since_lsn: u64,
+    compress: Option<String>,
+    with_sidecars: bool,
+    json: bool,
+) -> Result<()> {
+    let compress = match compress {
+        Some(c) => c.parse::<BackupCompression>()?,
+        None => BackupCompression::None,
+    };
+    let opts = BackupOptions {
+        since_lsn,
+        compress,
+        include_sidecars: with_sidecars,
+    };
+    let db = Db::open_ro(&path).with_context(|| format!("open RO DB at {}", path.display()))?;
+    let man = backup_to_path(&db, &out, &opts)
+        .with_context(|| format!("backup {} -> {}", path.display(), out.display()))?;
+    let to_stderr = out.as_os_str() == "-";
+    print_manifest("Backup", &man, json, to_stderr);
+    Ok(())
+}
+
+/// CLI: restore — из файла или stdin (`--from -`). Кодек сжатия берётся из самого архива.
+/// --force — накатить инкремент, даже если цель новее бэкапа или это другая БД.
+pub fn exec_restore(
+    path: PathBuf,
+    from: PathBuf,
+    verify: bool,
+    force: bool,
+    json: bool,
+) -> Result<()> {
+    let opts = RestoreOptions {
+        verify,
+        force,
+        ..Default::default()
+    };
+    let man = restore_from_path(&path, &from, &opts)
+        .with_context(|| format!("restore {} -> {}", from.display(), path.display()))?;
+    print_manifest("Restore", &man, json, false);
+    Ok(())
+}
+
+fn print_manifest(what: &str, m: &BackupStreamManifest, json: bool, to_stderr: bool) {
+    let text = if json {
+        serde_json::to_string(m).unwrap()
+    } else {
+        format!(
+            "{}:\n  codec         = {}\n  since_lsn     = {}\n  lsn           = {}\n  pages         = {}\n  page_bytes    = {}\n  pages_skipped = {}\n  heads         = {}\n  wal_frames    = {}\n  wal_bytes     = {}\n  stored_bytes  = {}\n  next_page_id  = {}\n  sidecars      = {}",
+            what,
+            if m.codec.is_empty() { "none" } else { &m.codec },
+            m.since_lsn,
+            m.lsn,
+            m.pages,
+            m.page_bytes,
+            m.pages_skipped,
+            m.heads,
+            m.wal_frames,
+            m.wal_bytes,
+            m.stored_bytes,
+            m.next_page_id,
+            if m.sidecars.is_empty() {
+                "-".to_string()
+            } else {
+                m.sidecars.join(",")
+            }
+        )
+    };
+    if to_stderr {
+        eprintln!("{}", text);
+    } else {
+        println!("{}", text);
+    }
+}
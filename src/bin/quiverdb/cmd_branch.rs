@@ -0,0 +1,53 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+use QuiverDB::snapstore::branch::detach_branch;
+use QuiverDB::snapstore::create_branch;
+
+use crate::output::{emit, OutputFormat};
+
+/// CLI: branch — writable-ветка из persisted‑снапшота (страницы не копируются).
+///
+/// Аргументы:
+/// - --from-snapshot: идентификатор снапшота (см. snapshot-list).
+/// - --path: корень новой ветки (пустой или несуществующий каталог).
+/// - --src:  корень, где расположен SnapStore источника. По умолчанию совпадает с --path
+///   (имеет смысл с абсолютным P1_SNAPSTORE_DIR — общим SnapStore).
+pub fn exec(path: PathBuf, src: Option<PathBuf>, id: String, fmt: OutputFormat) -> Result<()> {
+    if id.trim().is_empty() {
+        anyhow::bail!("provide --from-snapshot <snapshot_id>");
+    }
+    let src = src.unwrap_or_else(|| path.clone());
+    let info = create_branch(&src, &path, &id).with_context(|| {
+        format!(
+            "branch snapshot '{}' from {} into {}",
+            id,
+            src.display(),
+            path.display()
+        )
+    })?;
+    if !emit(fmt, &info)? {
+        println!(
+            "branch: OK (snapshot='{}', base_lsn={}, {} page(s) served from {})",
+            info.snapshot_id,
+            info.base_lsn,
+            info.snapshot_pages,
+            info.snapstore_dir.display()
+        );
+    }
+    Ok(())
+}
+
+/// CLI: branch-detach — сделать ветку самостоятельной БД (writer-only).
+pub fn exec_detach(path: PathBuf, fmt: OutputFormat) -> Result<()> {
+    let mut db = Db::open(&path)?;
+    let rep = detach_branch(&mut db)?;
+    if !emit(fmt, &rep)? {
+        println!(
+            "branch-detach: OK (snapshot='{}', materialized {} page(s), {} already local)",
+            rep.snapshot_id, rep.materialized_pages, rep.local_pages
+        );
+    }
+    Ok(())
+}
@@ -1,28 +1,50 @@
 use anyhow::{anyhow, Context, Result};
 use byteorder::{ByteOrder, LittleEndian};
+use std::collections::VecDeque;
 use std::fs::OpenOptions;
 use std::net::TcpStream;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use QuiverDB::db::Db;
+use QuiverDB::db::resync::RESYNC_MAX_PAGES;
+use QuiverDB::db::{BucketDigest, BucketState, Db};
 use QuiverDB::meta::set_last_lsn;
+use QuiverDB::metrics::{
+    record_cdc_resync, record_cdc_resync_deferred, record_cdc_resync_failed, record_cdc_verify,
+    record_cdc_verify_deferred,
+};
 use QuiverDB::page::{
     KV_HDR_MIN, KV_OFF_LSN, OFF_TYPE, OVF_OFF_LSN, PAGE_MAGIC, PAGE_TYPE_KV_RH3,
     PAGE_TYPE_OVERFLOW3,
 };
 use QuiverDB::wal::{
-    crc32c_of_parts, wal_header_read_stream_id, WAL_HDR_SIZE, WAL_MAGIC, WAL_REC_HDR_SIZE,
+    crc32c_of_parts, decode_heads_update_payload, parse_expiry_payload, wal_header_read_stream_id,
+    WAL_HDR_SIZE, WAL_MAGIC, WAL_REC_COMMIT, WAL_REC_EXPIRY, WAL_REC_HDR_SIZE,
     WAL_REC_HEADS_UPDATE, WAL_REC_OFF_CRC32, WAL_REC_OFF_FLAGS, WAL_REC_OFF_LEN, WAL_REC_OFF_LSN,
     WAL_REC_OFF_PAGE_ID, WAL_REC_OFF_RESERVED, WAL_REC_OFF_TYPE, WAL_REC_PAGE_IMAGE,
 };
 // NEW: stateful reader
 use QuiverDB::wal::reader::WalStreamReader;
 // CDC транспорт
-use QuiverDB::wal::net::{load_psk_from_env, open_tls_psk_stream, read_next_framed_psk, IoStream};
+use QuiverDB::util::now_secs;
+use QuiverDB::wal::net::{
+    cdc_proto_from_env, decode_frame_payload, hello_from_env, is_ctrl_payload, is_timeout,
+    load_psk_from_env, read_next_framed_psk, resume_from_env, resync_from_env,
+    verify_sample_from_env, write_ctrl_psk, CdcWelcome, CtrlMsg, IoStream, CAP_DIGEST, CAP_FILTER,
+    CAP_RESYNC, CTRL_MAX_LEN, DIGEST_OK, SINCE_LSN_NONE,
+};
+// NEW: фильтр потока (по бакетам/префиксам ключей), применяется отправителем
+use QuiverDB::wal::filter::CdcFilter;
+// NEW: TLS-клиент с перезагрузкой клиентской identity
+use QuiverDB::wal::tls_client::{shared_from_env, ReloadingTlsConnector};
+// NEW: idempotency-токены батчей (пропуск уже применённых батчей)
+use QuiverDB::wal::IdemApplyGate;
+// NEW: имя подписчика (именованный курсор cdc-serve)
+use QuiverDB::wal::cursors::validate_subscriber_name;
 // NEW: персистентные маркеры
 use QuiverDB::wal::state::{
-    load_last_heads_lsn, load_last_seq, load_stream_id, store_last_heads_lsn, store_last_seq,
-    store_stream_id,
+    check_stream_source, load_last_heads_lsn, load_last_seq, load_stream_id, store_last_heads_lsn,
+    store_last_seq,
 };
 
 /// CDC apply: применить WAL‑поток в целевую БД.
@@ -31,15 +53,54 @@ use QuiverDB::wal::state::{
 ///   - file://<path>        — бинарный WAL файл (WAL header + кадры, P2WAL001)
 ///   - tcp+psk://host:port  — поток WAL кадров (первый кадр — hello с WAL header) по TCP
 ///   - tls+psk://host:port  — поток WAL кадров (первый кадр — hello с WAL header) по TLS
-pub fn exec(path: PathBuf, from: String) -> Result<()> {
+///
+/// tcp/tls (протокол v2, wal/net.rs): сразу после connect отправляется HELLO (capabilities,
+/// stream_id, keepalive); ответ WELCOME — сессия v2: на KEEPALIVE отвечаем ACK{applied_lsn},
+/// BYE завершает сессию, тишина дольше 3 интервалов keepalive — ошибка. Сжатые кадры
+/// (алгоритм из WELCOME) распаковываются до проверки CRC. Ответ v1 HELLO
+/// (WAL header) — прежний поток без согласования.
+///
+/// Resume (v2): HELLO несёт since_lsn = meta.last_lsn follower'а, и отправитель без явного
+/// --since-lsn шлёт только новые кадры. meta.last_lsn продвигается по COMMIT (последний целиком
+/// полученный батч) на каждом ACK и в конце сессии; оборванный батч будет прислан заново
+/// (повторное применение безопасно: LSN‑гейтинг страниц/голов). P1_CDC_RESUME=0 — не запрашивать.
+///
+/// Фильтр (v2): P1_CDC_FILTER_PREFIX / P1_CDC_FILTER_BUCKETS уходят в HELLO, и отправитель
+/// шлёт только релевантные страницы (wal/filter.rs).
+///
+/// Источник (wal::state::check_stream_source): stream_id потока и db_uuid источника (WELCOME v2)
+/// сверяются с запомненными follower'ом; чужой поток отвергается до применения кадров.
+/// --force — применить и перепривязать follower к новому источнику.
+///
+/// Verify (v2, P1_CDC_VERIFY_SAMPLE=N > 0, отправитель с CAP_DIGEST): догнав отправителя
+/// (KEEPALIVE.last_lsn == применённый COMMIT), follower перед ACK шлёт DIGEST_REQ на N бакетов
+/// (окно идёт по кругу, со временем покрывает все) и сверяет ответ с digest'ами, посчитанными
+/// у себя на том же LSN и now (db/digest.rs: живые ключи, значения и сроки TTL). Расхождение —
+/// [WARN] по бакету и метрика cdc_verify_divergent_buckets; сессия продолжается. Отправитель,
+/// ушедший дальше, отвечает DIGEST_LSN_MOVED — сверка повторится на следующем KEEPALIVE.
+/// С фильтром потока verify выключен (follower держит только часть бакетов/ключей).
+///
+/// Resync (P1_CDC_RESYNC=1 вместе с verify, отправитель с CAP_RESYNC): разошедшийся бакет
+/// ставится в очередь; на следующем KEEPALIVE, где follower снова догнал отправителя, вместо
+/// DIGEST_REQ уходит RESYNC_REQ, и отправитель присылает состояние бакета на том же LSN
+/// (RESYNC_PAGE* + RESYNC_END, db/resync.rs). Оно принимается целиком и только затем заменяет
+/// цепочку бакета (Db::apply_bucket_state); digest после замены сверяется с digest'ом leader'а.
+/// Неполное состояние или ушедший дальше отправитель — бакет остаётся в очереди.
+///
+/// TLS-identity (wal/tls_client.rs): PFX (P1_TLS_CLIENT_PFX) и CA перечитываются при изменении
+/// файлов, до истечения клиентского сертификата — [WARN]. Перезагруженная identity в сессии v2
+/// с resume вступает в силу сразу: на ближайшем KEEPALIVE, где follower догнал отправителя
+/// (батч применён целиком, сверка не в полёте), он отвечает ACK и переподключается с
+/// since_lsn = применённый COMMIT. Без resume — при следующем подключении.
+pub fn exec(path: PathBuf, from: String, force: bool) -> Result<()> {
     if let Some(src_path) = from.strip_prefix("file://") {
-        return apply_from_file(path, PathBuf::from(src_path));
+        return apply_from_file(path, PathBuf::from(src_path), force);
     }
     if let Some(addr) = from.strip_prefix("tcp+psk://") {
-        return apply_from_psk(path, addr, false);
+        return apply_from_psk(path, addr, false, force);
     }
     if let Some(addr) = from.strip_prefix("tls+psk://") {
-        return apply_from_psk(path, addr, true);
+        return apply_from_psk(path, addr, true, force);
     }
     Err(anyhow!(
         "unsupported source '{}': use file://<path>, tcp+psk://host:port or tls+psk://host:port",
@@ -60,7 +121,7 @@ fn env_bool(name: &str) -> bool {
 
 // -------------------- file:// source --------------------
 
-fn apply_from_file(path: PathBuf, src: PathBuf) -> Result<()> {
+fn apply_from_file(path: PathBuf, src: PathBuf, force: bool) -> Result<()> {
     // Откроем source-файл (WAL‑стрим)
     let mut f = OpenOptions::new()
         .read(true)
@@ -77,8 +138,8 @@ fn apply_from_file(path: PathBuf, src: PathBuf) -> Result<()> {
     let stream_id = wal_header_read_stream_id(&mut f)
         .with_context(|| format!("read WAL header (stream_id) from {}", src.display()))?;
 
-    // Валидация/фиксация stream_id на стороне follower’а
-    verify_and_store_stream_id(&path, stream_id)?;
+    // Валидация/фиксация stream_id на стороне follower’а (у file:// нет db_uuid)
+    check_stream_source(&path, stream_id, &[0u8; 16], force)?;
 
     // Writer DB
     let mut db =
@@ -101,6 +162,9 @@ fn apply_from_file(path: PathBuf, src: PathBuf) -> Result<()> {
 
     let heads_strict = env_bool("P1_CDC_HEADS_STRICT");
 
+    let mut idem = IdemApplyGate::open(&path)?;
+    let mut expired_keys = 0u64;
+
     while let Some((rec, next_pos)) = rdr.read_next(&mut f, pos, file_len)? {
         let frame_len = next_pos - pos;
         bytes += frame_len;
@@ -110,6 +174,12 @@ fn apply_from_file(path: PathBuf, src: PathBuf) -> Result<()> {
             max_lsn = rec.lsn;
         }
 
+        // Батч с уже применённым idempotency-токеном — пропускаем его кадры
+        if !idem.observe(rec.rec_type, rec.lsn, &rec.payload)? {
+            pos = next_pos;
+            continue;
+        }
+
         match rec.rec_type {
             WAL_REC_PAGE_IMAGE => {
                 // LSN‑гейтинг до ensure_allocated, если страница уже существует
@@ -131,8 +201,9 @@ fn apply_from_file(path: PathBuf, src: PathBuf) -> Result<()> {
                 }
             }
             WAL_REC_HEADS_UPDATE => {
-                // Проверим корректность payload длины
-                if rec.payload.len() == 0 || rec.payload.len() % 12 != 0 {
+                // Проверим корректность payload (legacy 12 B/запись или delta-формат)
+                let parsed = decode_heads_update_payload(&rec.payload);
+                if parsed.is_none() {
                     if heads_strict {
                         return Err(anyhow!(
                             "invalid HEADS_UPDATE payload length={} (strict mode)",
@@ -145,7 +216,7 @@ fn apply_from_file(path: PathBuf, src: PathBuf) -> Result<()> {
                         );
                     }
                 } else if rec.lsn > last_heads_lsn {
-                    let updates = parse_heads_updates(&rec.payload);
+                    let updates = parsed.unwrap_or_default();
                     if !updates.is_empty() {
                         db.set_dir_heads_bulk(&updates)?;
                         last_heads_lsn = rec.lsn;
@@ -155,6 +226,12 @@ fn apply_from_file(path: PathBuf, src: PathBuf) -> Result<()> {
                     }
                 }
             }
+            WAL_REC_EXPIRY => {
+                // Логический кадр: ключи, вычищенные по TTL на источнике
+                let events = parse_expiry_payload(&rec.payload, rec.lsn);
+                expired_keys += events.len() as u64;
+                db.publish_expiry_events(&events);
+            }
             _ => {}
         }
 
@@ -162,11 +239,11 @@ fn apply_from_file(path: PathBuf, src: PathBuf) -> Result<()> {
     }
 
     // best-effort: обновим meta.last_lsn
-    let _ = set_last_lsn(&path, max_lsn);
+    persist_last_lsn(&mut db, &path, max_lsn);
 
     println!(
-        "cdc-apply[file]: applied {} frames ({} bytes) to {}, last_lsn={}, last_heads_lsn={}, stream_id={}",
-        frames, bytes, path.display(), max_lsn, last_heads_lsn, stream_id
+        "cdc-apply[file]: applied {} frames ({} bytes) to {}, last_lsn={}, last_heads_lsn={}, stream_id={}, idem_skipped_batches={}, expired_keys={}",
+        frames, bytes, path.display(), max_lsn, last_heads_lsn, stream_id, idem.skipped_batches, expired_keys
     );
 
     Ok(())
@@ -174,15 +251,61 @@ fn apply_from_file(path: PathBuf, src: PathBuf) -> Result<()> {
 
 // -------------------- tcp/tls+psk:// source --------------------
 
-fn apply_from_psk(path: PathBuf, addr: &str, use_tls: bool) -> Result<()> {
+/// Чем закончилась сессия apply_session.
+enum SessionEnd {
+    Done,
+    /// TLS-identity перезагружена — переподключиться (resume с применённого COMMIT).
+    Rotate,
+}
+
+fn apply_from_psk(path: PathBuf, addr: &str, use_tls: bool, force: bool) -> Result<()> {
+    // NEW: read-only admin UI follower'а в фоне (P1_ADMIN_UI_ADDR=host:port, фича "admin-ui")
+    #[cfg(feature = "admin-ui")]
+    if let Ok(ui_addr) = std::env::var("P1_ADMIN_UI_ADDR") {
+        let ui_addr = ui_addr.trim().to_string();
+        if !ui_addr.is_empty() {
+            QuiverDB::admin_ui::AdminUi::spawn(&ui_addr, Some(path.clone()))?;
+            eprintln!("cdc-apply: admin UI on http://{}/admin", ui_addr);
+        }
+    }
+
+    // NEW: TLS — identity и CA перечитываются в фоне (вдобавок к опросу при connect)
+    let tls = if use_tls {
+        let tls = shared_from_env()?;
+        tls.spawn_watcher()?;
+        Some(tls)
+    } else {
+        None
+    };
+    loop {
+        match apply_session(&path, addr, tls.as_deref(), force)? {
+            SessionEnd::Done => return Ok(()),
+            SessionEnd::Rotate => {
+                eprintln!(
+                    "cdc-apply: TLS client identity reloaded; reconnecting to {}",
+                    addr
+                )
+            }
+        }
+    }
+}
+
+fn apply_session(
+    path: &Path,
+    addr: &str,
+    tls: Option<&ReloadingTlsConnector>,
+    force: bool,
+) -> Result<SessionEnd> {
+    let use_tls = tls.is_some();
     // Writer DB
-    let mut db =
-        Db::open(&path).with_context(|| format!("open writer DB at {}", path.display()))?;
+    let mut db = Db::open(path).with_context(|| format!("open writer DB at {}", path.display()))?;
     let ps = db.pager.meta.page_size as usize;
 
-    // Транспорт
-    let mut stream = if use_tls {
-        open_tls_psk_stream(addr)?
+    // Транспорт (generation — до connect: перезагрузка во время connect даст лишнее, а не
+    // пропущенное переподключение)
+    let identity_gen = tls.map(|t| t.generation());
+    let mut stream = if let Some(tls) = tls {
+        tls.connect(addr)?
     } else {
         let sock =
             TcpStream::connect(addr).with_context(|| format!("connect tcp+psk source {}", addr))?;
@@ -196,6 +319,8 @@ fn apply_from_psk(path: PathBuf, addr: &str, use_tls: bool) -> Result<()> {
     let mut frames = 0u64;
     let mut bytes = 0u64;
     let mut max_lsn = db.pager.meta.last_lsn;
+    // LSN последнего COMMIT (батч получен целиком) — позиция resume
+    let mut committed_lsn = db.pager.meta.last_lsn;
 
     // Безопасный предел размера фрейма
     let heads_bytes = (db.dir.bucket_count as usize).saturating_mul(12);
@@ -206,26 +331,94 @@ fn apply_from_psk(path: PathBuf, addr: &str, use_tls: bool) -> Result<()> {
     }
 
     // Персистентные маркеры
-    let mut last_heads_lsn = load_last_heads_lsn(&path).unwrap_or(0);
-    let mut last_seq = load_last_seq(&path).unwrap_or(0);
+    let mut last_heads_lsn = load_last_heads_lsn(path).unwrap_or(0);
+    let mut last_seq = load_last_seq(path).unwrap_or(0);
 
     // STRICT/compat флаги
     let seq_strict = env_bool("P1_CDC_SEQ_STRICT");
     let allow_no_hello = env_bool("P1_CDC_ALLOW_NO_HELLO");
     let heads_strict = env_bool("P1_CDC_HEADS_STRICT");
 
-    // 0) Ожидаем HELLO кадр (WAL header = MAGIC + stream_id).
-    let hello = read_next_framed_psk(&mut stream, &psk, WAL_HDR_SIZE)?;
+    let mut idem = IdemApplyGate::open(path)?;
+
+    // NEW: протокол v2 — HELLO получателя (собственная последовательность seq)
+    let mut tx_seq = 1u64;
+    let sent_hello = cdc_proto_from_env() >= 2;
+    let mut filter_asked = false;
+    let resume = sent_hello && resume_from_env();
+    if sent_hello {
+        let since = if resume {
+            committed_lsn
+        } else {
+            SINCE_LSN_NONE
+        };
+        let mut hello = hello_from_env(since, load_stream_id(path).unwrap_or(0));
+        if let Some(name) = &hello.subscriber {
+            validate_subscriber_name(name).context("P1_CDC_SUBSCRIBER")?;
+        }
+        hello.filter = CdcFilter::from_env()?;
+        filter_asked = hello.filter.is_some();
+        write_ctrl_psk(&mut stream, tx_seq, &CtrlMsg::Hello(hello), &psk)?;
+        tx_seq += 1;
+    }
+    let mut session: Option<CdcWelcome> = None;
+    let verify_sample = verify_sample_from_env();
+    let mut verifier: Option<Verifier> = None;
+    // NEW: переподключение с перезагруженной TLS-identity (см. exec)
+    let mut rotate = false;
+    let mut rotate_noted = false;
+
+    // 0) Ожидаем HELLO кадр (v1: WAL header = MAGIC + stream_id; v2: WELCOME).
+    let hello = read_next_framed_psk(&mut stream, &psk, WAL_HDR_SIZE.max(CTRL_MAX_LEN))?;
     let mut stream_id: u64 = 0;
     if let Some((seq0, payload0)) = hello {
-        if payload0.len() == WAL_HDR_SIZE && &payload0[..8] == WAL_MAGIC {
+        if let Some(CtrlMsg::Welcome(w)) = CtrlMsg::decode(&payload0)? {
+            stream_id = w.stream_id;
+            check_stream_source(path, stream_id, &w.db_uuid, force)?;
+            if seq0 > last_seq {
+                last_seq = seq0;
+                let _ = store_last_seq(path, last_seq);
+            }
+            // Тишина дольше 3 интервалов keepalive — отправитель считается потерянным
+            if let Some(ka) = w.keepalive() {
+                stream
+                    .set_read_timeout(Some(idle_timeout(ka)))
+                    .context("set CDC idle timeout")?;
+            }
+            if filter_asked && !w.has(CAP_FILTER) {
+                eprintln!(
+                    "[WARN] CDC sender does not support stream filters; receiving the full stream"
+                );
+            }
+            if verify_sample > 0 {
+                if filter_asked {
+                    eprintln!("[WARN] CDC verify is disabled for filtered streams");
+                } else if !w.has(CAP_DIGEST) {
+                    eprintln!("[WARN] CDC sender does not support digests; verify is disabled");
+                } else {
+                    let resync = resync_from_env();
+                    if resync && !w.has(CAP_RESYNC) {
+                        eprintln!(
+                            "[WARN] CDC sender does not support bucket resync; divergence is only reported"
+                        );
+                    }
+                    verifier = Some(Verifier::new(verify_sample, resync && w.has(CAP_RESYNC)));
+                }
+            } else if resync_from_env() {
+                eprintln!("[WARN] CDC resync needs verify (P1_CDC_VERIFY_SAMPLE > 0); disabled");
+            }
+            session = Some(w);
+        } else if payload0.len() == WAL_HDR_SIZE && &payload0[..8] == WAL_MAGIC {
             // HELLO: wal header
             stream_id = LittleEndian::read_u64(&payload0[8..16]);
-            verify_and_store_stream_id(&path, stream_id)?;
+            check_stream_source(path, stream_id, &[0u8; 16], force)?;
+            if filter_asked {
+                eprintln!("[WARN] CDC sender speaks protocol v1; stream filter ignored");
+            }
             // Персистентно обновим last_seq
             if seq0 > last_seq {
                 last_seq = seq0;
-                let _ = store_last_seq(&path, last_seq);
+                let _ = store_last_seq(path, last_seq);
             }
         } else {
             // Нет HELLO — по умолчанию ошибка; fallback только если разрешён ENV
@@ -257,17 +450,19 @@ fn apply_from_psk(path: PathBuf, addr: &str, use_tls: bool) -> Result<()> {
                 } else {
                     handle_wal_frame_payload(
                         &mut db,
-                        &path,
+                        path,
                         &payload0,
                         &mut frames,
                         &mut bytes,
                         &mut max_lsn,
+                        &mut committed_lsn,
                         &mut last_heads_lsn,
+                        &mut idem,
                         ps,
                         heads_strict,
                     )?;
                     last_seq = seq0;
-                    let _ = store_last_seq(&path, last_seq);
+                    let _ = store_last_seq(path, last_seq);
                 }
             }
         }
@@ -280,11 +475,37 @@ fn apply_from_psk(path: PathBuf, addr: &str, use_tls: bool) -> Result<()> {
             max_lsn,
             last_heads_lsn
         );
-        return Ok(());
+        return Ok(SessionEnd::Done);
     }
 
     // Основной цикл
-    while let Some((seq, payload)) = read_next_framed_psk(&mut stream, &psk, max_len)? {
+    loop {
+        let next = match read_next_framed_psk(&mut stream, &psk, max_len) {
+            Ok(next) => next,
+            Err(e) if is_timeout(&e) => {
+                let ka = session.and_then(|w| w.keepalive()).unwrap_or_default();
+                return Err(anyhow!(
+                    "CDC: no frames from {} within {} ms (keepalive lost)",
+                    addr,
+                    idle_timeout(ka).as_millis()
+                ));
+            }
+            // Отправитель v1 не читает наш HELLO: закрытие сокета с непрочитанными данными
+            // даёт RST вместо FIN — после всех полученных кадров это конец потока
+            Err(e) if session.is_none() && sent_hello && is_conn_reset(&e) => {
+                eprintln!("[WARN] CDC: v1 sender reset the connection; treating as end of stream");
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+        let Some((seq, payload)) = next else {
+            break;
+        };
+        // v2: сжатый кадр (алгоритм согласован в WELCOME)
+        let payload = match session {
+            Some(w) => decode_frame_payload(payload, w.compress, max_len)?,
+            None => payload,
+        };
         // Проверка монотонности seq (персистентная)
         if seq <= last_seq {
             if seq_strict {
@@ -298,45 +519,388 @@ fn apply_from_psk(path: PathBuf, addr: &str, use_tls: bool) -> Result<()> {
             }
         }
 
+        // v2: управляющие сообщения отправителя
+        if session.is_some() && is_ctrl_payload(&payload) {
+            last_seq = seq;
+            let _ = store_last_seq(path, last_seq);
+            match CtrlMsg::decode(&payload)? {
+                Some(CtrlMsg::Keepalive { last_lsn }) => {
+                    // Позиция resume переживёт обрыв/падение follower'а
+                    persist_last_lsn(&mut db, path, committed_lsn);
+                    // TLS-identity перезагружена: переподключиться на границе батча
+                    let reloaded = tls
+                        .zip(identity_gen)
+                        .is_some_and(|(t, gen)| t.generation() != gen);
+                    if reloaded && !rotate_noted {
+                        if !resume {
+                            eprintln!(
+                                "[WARN] cdc-apply: TLS client identity reloaded; it takes effect on the next connection (resume is off)"
+                            );
+                            rotate_noted = true;
+                        } else if max_lsn == committed_lsn
+                            && verifier.as_ref().is_none_or(Verifier::idle)
+                        {
+                            let ack = CtrlMsg::Ack {
+                                applied_lsn: committed_lsn,
+                            };
+                            write_ctrl_psk(&mut stream, tx_seq, &ack, &psk)?;
+                            rotate = true;
+                            break;
+                        }
+                    }
+                    // verify: догнали отправителя — запрос digest'ов (или resync) до ACK (его ждёт отправитель)
+                    if let Some(v) = verifier.as_mut().filter(|_| last_lsn == committed_lsn) {
+                        if let Some(req) = v.request(&db, committed_lsn)? {
+                            write_ctrl_psk(&mut stream, tx_seq, &req, &psk)?;
+                            tx_seq += 1;
+                        }
+                    }
+                    let ack = CtrlMsg::Ack {
+                        applied_lsn: committed_lsn,
+                    };
+                    write_ctrl_psk(&mut stream, tx_seq, &ack, &psk)?;
+                    tx_seq += 1;
+                }
+                Some(CtrlMsg::Bye { .. }) => break,
+                Some(CtrlMsg::Digest {
+                    lsn,
+                    status,
+                    digests,
+                }) => {
+                    if let Some(v) = verifier.as_mut() {
+                        v.on_reply(lsn, status, &digests, db.dir.bucket_count);
+                    }
+                }
+                Some(CtrlMsg::ResyncPage { page_id, image }) => {
+                    if let Some(v) = verifier.as_mut() {
+                        v.on_resync_page(page_id, image);
+                    }
+                }
+                Some(CtrlMsg::ResyncEnd {
+                    lsn,
+                    status,
+                    bucket,
+                    head,
+                    pages,
+                    digest,
+                }) => {
+                    if let Some(v) = verifier.as_mut() {
+                        let end = ResyncEnd {
+                            lsn,
+                            status,
+                            bucket,
+                            head,
+                            pages,
+                            digest,
+                        };
+                        v.on_resync_end(&mut db, committed_lsn, end)?;
+                    }
+                }
+                other => eprintln!(
+                    "[WARN] CDC: unexpected control message {:?}; skipping",
+                    other
+                ),
+            }
+            continue;
+        }
+
         handle_wal_frame_payload(
             &mut db,
-            &path,
+            path,
             &payload,
             &mut frames,
             &mut bytes,
             &mut max_lsn,
+            &mut committed_lsn,
             &mut last_heads_lsn,
+            &mut idem,
             ps,
             heads_strict,
         )?;
 
         // Персистентно обновим last_seq после успешной обработки кадра
         last_seq = seq;
-        let _ = store_last_seq(&path, last_seq);
+        let _ = store_last_seq(path, last_seq);
     }
 
-    // best-effort: обновим meta.last_lsn
-    let _ = set_last_lsn(&path, max_lsn);
+    // best-effort: обновим meta.last_lsn (v2 — по последнему COMMIT, см. resume в exec)
+    let last_lsn = if session.is_some() {
+        committed_lsn
+    } else {
+        max_lsn
+    };
+    persist_last_lsn(&mut db, path, last_lsn);
 
     println!(
-        "cdc-apply[{}+psk]: applied {} frames ({} bytes) to {}, last_lsn={}, last_heads_lsn={}, last_seq={}, stream_id={}",
+        "cdc-apply[{}+psk]: applied {} frames ({} bytes) to {}, last_lsn={}, last_heads_lsn={}, last_seq={}, stream_id={}, idem_skipped_batches={}, proto={}",
         if use_tls { "tls" } else { "tcp" },
-        frames, bytes, path.display(), max_lsn, last_heads_lsn, last_seq, stream_id
+        frames, bytes, path.display(), max_lsn, last_heads_lsn, last_seq, stream_id, idem.skipped_batches,
+        session.map(|w| w.version).unwrap_or(1)
     );
+    if let Some(v) = &verifier {
+        eprintln!(
+            "cdc-apply verify: {} check(s), {} divergent bucket(s), {} deferred",
+            v.checks, v.divergent, v.deferred
+        );
+        if let Some(q) = &v.resync {
+            eprintln!(
+                "cdc-apply resync: {} bucket(s) replaced, {} failed, {} deferred, {} queued",
+                v.resynced,
+                v.resync_failed,
+                v.resync_deferred,
+                q.len()
+            );
+        }
+    }
 
-    Ok(())
+    Ok(if rotate {
+        SessionEnd::Rotate
+    } else {
+        SessionEnd::Done
+    })
+}
+
+// -------- verify (P1_CDC_VERIFY_SAMPLE) --------
+
+/// Сверка digest'ов бакетов с отправителем и resync разошедшихся (см. exec).
+struct Verifier {
+    sample: usize,
+    /// Начало следующего окна бакетов.
+    next_bucket: u32,
+    /// LSN последней завершённой сверки — на том же LSN повторно не сверяем.
+    verified_lsn: Option<u64>,
+    /// Запрос в полёте: (lsn, локальные digest'ы на момент запроса).
+    pending: Option<(u64, Vec<BucketDigest>)>,
+    checks: u64,
+    divergent: u64,
+    deferred: u64,
+    /// Очередь бакетов на resync (None — resync выключен).
+    resync: Option<VecDeque<u32>>,
+    /// Resync в полёте.
+    staged: Option<StagedResync>,
+    resynced: u64,
+    resync_failed: u64,
+    resync_deferred: u64,
+}
+
+/// Запрошенное состояние бакета и полученные страницы.
+struct StagedResync {
+    lsn: u64,
+    now: u32,
+    bucket: u32,
+    pages: Vec<(u64, Vec<u8>)>,
+}
+
+/// Поля RESYNC_END.
+struct ResyncEnd {
+    lsn: u64,
+    status: u8,
+    bucket: u32,
+    head: u64,
+    pages: u32,
+    digest: BucketDigest,
+}
+
+impl Verifier {
+    fn new(sample: usize, resync: bool) -> Self {
+        Self {
+            sample,
+            next_bucket: 0,
+            verified_lsn: None,
+            pending: None,
+            checks: 0,
+            divergent: 0,
+            deferred: 0,
+            resync: resync.then(VecDeque::new),
+            staged: None,
+            resynced: 0,
+            resync_failed: 0,
+            resync_deferred: 0,
+        }
+    }
+
+    /// Нет запроса digest'ов или resync в полёте.
+    fn idle(&self) -> bool {
+        self.pending.is_none() && self.staged.is_none()
+    }
+
+    /// RESYNC_REQ на первый бакет очереди, иначе DIGEST_REQ на следующее окно бакетов;
+    /// локальные digest'ы фиксируются сейчас (до ответа отправителя новые кадры не придут —
+    /// он ждёт ACK).
+    fn request(&mut self, db: &Db, lsn: u64) -> Result<Option<CtrlMsg>> {
+        if self.staged.is_none() {
+            if let Some(bucket) = self.resync.as_mut().and_then(|q| q.pop_front()) {
+                let now = now_secs();
+                self.staged = Some(StagedResync {
+                    lsn,
+                    now,
+                    bucket,
+                    pages: Vec::new(),
+                });
+                return Ok(Some(CtrlMsg::ResyncRequest { lsn, now, bucket }));
+            }
+        }
+        let total = db.dir.bucket_count;
+        if total == 0 || self.verified_lsn == Some(lsn) {
+            return Ok(None);
+        }
+        let n = (self.sample as u32).min(total);
+        let buckets: Vec<u32> = (0..n).map(|i| (self.next_bucket + i) % total).collect();
+        let now = now_secs();
+        let local = db.bucket_digests(&buckets, now)?;
+        self.pending = Some((lsn, local));
+        Ok(Some(CtrlMsg::DigestRequest { lsn, now, buckets }))
+    }
+
+    fn on_reply(&mut self, lsn: u64, status: u8, digests: &[BucketDigest], total: u32) {
+        let Some((asked, local)) = self.pending.take() else {
+            return;
+        };
+        if status != DIGEST_OK || lsn != asked {
+            self.deferred += 1;
+            record_cdc_verify_deferred();
+            return;
+        }
+        let mut bad = 0u64;
+        for l in &local {
+            match digests.iter().find(|d| d.bucket == l.bucket) {
+                Some(d) if d == l => {}
+                Some(d) => {
+                    bad += 1;
+                    self.queue_resync(l.bucket);
+                    eprintln!(
+                        "[WARN] cdc-apply verify: bucket {} diverged at lsn={} (follower: keys={} hash={:016x}; leader: keys={} hash={:016x})",
+                        l.bucket, asked, l.live_keys, l.hash, d.live_keys, d.hash
+                    );
+                }
+                None => {
+                    bad += 1;
+                    self.queue_resync(l.bucket);
+                    eprintln!(
+                        "[WARN] cdc-apply verify: bucket {} missing from leader digest at lsn={}",
+                        l.bucket, asked
+                    );
+                }
+            }
+        }
+        self.next_bucket = (self.next_bucket + local.len() as u32) % total.max(1);
+        self.verified_lsn = Some(asked);
+        self.checks += 1;
+        self.divergent += bad;
+        record_cdc_verify(bad);
+    }
+
+    fn queue_resync(&mut self, bucket: u32) {
+        if let Some(q) = self.resync.as_mut() {
+            if !q.contains(&bucket) {
+                q.push_back(bucket);
+            }
+        }
+    }
+
+    fn on_resync_page(&mut self, page_id: u64, image: Vec<u8>) {
+        let Some(st) = self.staged.as_mut() else {
+            return;
+        };
+        if st.pages.len() < RESYNC_MAX_PAGES {
+            st.pages.push((page_id, image));
+        }
+    }
+
+    /// RESYNC_END: состояние получено целиком и снято на применённом LSN — заменить цепочку
+    /// бакета; иначе бакет возвращается в очередь.
+    fn on_resync_end(&mut self, db: &mut Db, applied_lsn: u64, end: ResyncEnd) -> Result<()> {
+        let Some(StagedResync {
+            lsn: asked,
+            now,
+            bucket,
+            pages,
+        }) = self.staged.take()
+        else {
+            return Ok(());
+        };
+        if end.status != DIGEST_OK || end.lsn != asked || end.bucket != bucket {
+            self.resync_deferred += 1;
+            record_cdc_resync_deferred();
+            self.queue_resync(bucket);
+            return Ok(());
+        }
+        if applied_lsn != asked || pages.len() != end.pages as usize {
+            eprintln!(
+                "[WARN] cdc-apply resync: bucket {} state at lsn={} is incomplete ({} of {} pages, applied lsn={}); retrying",
+                bucket,
+                asked,
+                pages.len(),
+                end.pages,
+                applied_lsn
+            );
+            self.resync_failed += 1;
+            record_cdc_resync_failed();
+            self.queue_resync(bucket);
+            return Ok(());
+        }
+        let state = BucketState {
+            bucket,
+            lsn: asked,
+            head: end.head,
+            pages,
+            digest: end.digest,
+        };
+        let rep = db
+            .apply_bucket_state(&state, now)
+            .with_context(|| format!("resync bucket {} at lsn={}", bucket, asked))?;
+        record_cdc_resync(rep.pages_written);
+        self.resynced += 1;
+        if rep.digest_ok {
+            eprintln!(
+                "cdc-apply resync: bucket {} replaced at lsn={} ({} page(s), head={})",
+                bucket, asked, rep.pages_written, rep.new_head
+            );
+        } else {
+            self.resync_failed += 1;
+            record_cdc_resync_failed();
+            eprintln!(
+                "[WARN] cdc-apply resync: bucket {} still differs from the leader after resync at lsn={}",
+                bucket, asked
+            );
+        }
+        Ok(())
+    }
 }
 
 // -------- helpers --------
 
+/// meta.last_lsn := max(текущий, lsn) — и на диске, и в pager.meta (Drop writer'а пишет её).
+fn persist_last_lsn(db: &mut Db, root: &Path, lsn: u64) {
+    if lsn > db.pager.meta.last_lsn {
+        db.pager.meta.last_lsn = lsn;
+    }
+    let _ = set_last_lsn(root, lsn);
+}
+
+fn is_conn_reset(e: &anyhow::Error) -> bool {
+    e.chain().any(|c| {
+        c.downcast_ref::<std::io::Error>()
+            .map(|io| io.kind() == std::io::ErrorKind::ConnectionReset)
+            .unwrap_or(false)
+    })
+}
+
+/// Сколько получатель ждёт следующий фрейм в сессии с keepalive.
+fn idle_timeout(keepalive: Duration) -> Duration {
+    (keepalive * 3).max(Duration::from_secs(5))
+}
+
 fn handle_wal_frame_payload(
     db: &mut Db,
-    root: &PathBuf,
+    root: &Path,
     payload: &[u8],
     frames: &mut u64,
     bytes: &mut u64,
     max_lsn: &mut u64,
+    committed_lsn: &mut u64,
     last_heads_lsn: &mut u64,
+    idem: &mut IdemApplyGate,
     ps: usize,
     heads_strict: bool,
 ) -> Result<()> {
@@ -380,6 +944,14 @@ fn handle_wal_frame_payload(
     if lsn > *max_lsn {
         *max_lsn = lsn;
     }
+    if rec_type == WAL_REC_COMMIT && lsn > *committed_lsn {
+        *committed_lsn = lsn;
+    }
+
+    // Батч с уже применённым idempotency-токеном — пропускаем его кадры
+    if !idem.observe(rec_type, lsn, &payload[WAL_REC_HDR_SIZE..])? {
+        return Ok(());
+    }
 
     match rec_type {
         t if t == WAL_REC_PAGE_IMAGE => {
@@ -406,7 +978,8 @@ fn handle_wal_frame_payload(
 
         t if t == WAL_REC_HEADS_UPDATE => {
             let pl = &payload[WAL_REC_HDR_SIZE..];
-            if pl.len() == 0 || pl.len() % 12 != 0 {
+            let parsed = decode_heads_update_payload(pl);
+            if parsed.is_none() {
                 if heads_strict {
                     return Err(anyhow!(
                         "invalid HEADS_UPDATE payload length={} (strict mode)",
@@ -423,7 +996,7 @@ fn handle_wal_frame_payload(
 
             // LSN-гейтинг: применяем только если lsn > last_heads_lsn
             if lsn > *last_heads_lsn {
-                let updates = parse_heads_updates(pl);
+                let updates = parsed.unwrap_or_default();
                 if !updates.is_empty() {
                     db.set_dir_heads_bulk(&updates)?;
                     *last_heads_lsn = lsn;
@@ -434,6 +1007,11 @@ fn handle_wal_frame_payload(
             }
         }
 
+        t if t == WAL_REC_EXPIRY => {
+            let events = parse_expiry_payload(&payload[WAL_REC_HDR_SIZE..], lsn);
+            db.publish_expiry_events(&events);
+        }
+
         _ => { /* BEGIN/COMMIT/TRUNCATE/PAGE_DELTA — игнорируем */ }
     }
 
@@ -458,39 +1036,3 @@ fn v3_page_lsn(buf: &[u8]) -> Option<u64> {
         _ => None,
     }
 }
-
-#[inline]
-fn parse_heads_updates(buf: &[u8]) -> Vec<(u32, u64)> {
-    if buf.len() % 12 != 0 || buf.is_empty() {
-        return Vec::new();
-    }
-    let mut out = Vec::with_capacity(buf.len() / 12);
-    let mut off = 0usize;
-    while off + 12 <= buf.len() {
-        let b = LittleEndian::read_u32(&buf[off..off + 4]);
-        let pid = LittleEndian::read_u64(&buf[off + 4..off + 12]);
-        out.push((b, pid));
-        off += 12;
-    }
-    out
-}
-
-fn verify_and_store_stream_id(root: &PathBuf, incoming: u64) -> Result<()> {
-    if incoming == 0 {
-        return Err(anyhow!("incoming WAL stream_id is zero (invalid)"));
-    }
-    let local = load_stream_id(root)?;
-    if local == 0 {
-        // Запомним первый валидный stream_id
-        store_stream_id(root, incoming)?;
-        Ok(())
-    } else if local == incoming {
-        Ok(())
-    } else {
-        Err(anyhow!(
-            "WAL stream source mismatch: local stream_id={} vs incoming={}",
-            local,
-            incoming
-        ))
-    }
-}
@@ -0,0 +1,82 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use QuiverDB::wal::cursors::{cursors_path, delete_cursor, load_cursors, set_cursor};
+
+use crate::exit_code::{CliError, ErrorKind};
+use crate::output::{emit, OutputFormat};
+
+#[derive(Serialize)]
+struct CursorRow {
+    name: String,
+    acked_lsn: u64,
+    updated_at: u64,
+    peer: Option<String>,
+}
+
+/// CLI: cdc-cursors — именованные курсоры подписчиков cdc-serve (wal/cursors.rs).
+/// Без флагов — список; --reset NAME [--lsn N] — установить курсор; --delete NAME — удалить.
+/// Курсор подключённого подписчика перезапишется его следующим ACK.
+pub fn exec(
+    path: PathBuf,
+    reset: Option<String>,
+    lsn: Option<u64>,
+    delete: Option<String>,
+    fmt: OutputFormat,
+) -> Result<()> {
+    if let Some(name) = reset {
+        let lsn = lsn.unwrap_or(0);
+        set_cursor(&path, &name, lsn)
+            .with_context(|| format!("reset cursor '{}' at {}", name, path.display()))?;
+        println!("cursor '{}' set to acked_lsn={}", name, lsn);
+        return Ok(());
+    }
+    if let Some(name) = delete {
+        if !delete_cursor(&path, &name)? {
+            return Err(CliError::new(
+                ErrorKind::NotFound,
+                format!("no cursor '{}' in {}", name, cursors_path(&path).display()),
+            )
+            .into());
+        }
+        println!("cursor '{}' deleted", name);
+        return Ok(());
+    }
+
+    let rows: Vec<CursorRow> = load_cursors(&path)?
+        .into_iter()
+        .map(|(name, c)| CursorRow {
+            name,
+            acked_lsn: c.acked_lsn,
+            updated_at: c.updated_at,
+            peer: c.peer,
+        })
+        .collect();
+    if emit(fmt, &rows)? {
+        return Ok(());
+    }
+    if rows.is_empty() {
+        println!("(no subscriber cursors)");
+        return Ok(());
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    println!(
+        "  {:<24}  {:>14}  {:>10}  peer",
+        "name", "acked_lsn", "age_s"
+    );
+    for r in rows {
+        println!(
+            "  {:<24}  {:>14}  {:>10}  {}",
+            r.name,
+            r.acked_lsn,
+            now.saturating_sub(r.updated_at),
+            r.peer.as_deref().unwrap_or("-")
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,758 @@
+//! cdc-serve — fan-out ship-сервер leader'а: один читатель WAL, много подписчиков.
+//!
+//! Поток данных:
+//! - tail-поток читает WAL один раз (опрос, усечение при checkpoint/закрытии учитывается) и
+//!   складывает кадры в общий буфер в памяти (кольцо с пределом по байтам, --buffer-mb);
+//! - каждый подписчик (`cdc-apply --from tcp+psk://<listen>`) обслуживается своим потоком со
+//!   своим курсором: handshake v2 (since_lsn из HELLO, capabilities, сжатие, keepalive) или v1;
+//! - backpressure: медленный подписчик не тормозит ни читателя, ни остальных — он просто
+//!   отстаёт в буфере. Если нужные ему кадры уже вытеснены, он догоняет чтением WAL-файла
+//!   (отдельный проход только для него, целыми батчами до последнего COMMIT) и возвращается
+//!   в буфер.
+//!
+//! Именованные подписчики (P1_CDC_SUBSCRIBER у cdc-apply): ACK сохраняется в курсор
+//! (wal/cursors.rs), и при переподключении по имени поток начинается с курсора — at-least-once.
+//! Если и курсор, и since_lsn из HELLO известны, берётся меньший (follower, восстановленный из
+//! старого бэкапа, получит недостающее). Курсоры без обновлений дольше
+//! --cursor-retention-hours удаляются. Просмотр/сброс — `quiverdb cdc-cursors`.
+//!
+//! Фильтр потока (P1_CDC_FILTER_PREFIX / P1_CDC_FILTER_BUCKETS у cdc-apply, поле HELLO) —
+//! свой у каждого подписчика: буфер общий, нерелевантные страницы отбрасываются в потоке
+//! подписчика при отправке (wal/filter.rs).
+//!
+//! seq кадров — общий для всех сессий и монотонный между перезапусками: номера резервируются
+//! блоками в <root>/.cdc_seq.bin (тот же маркер, что у cdc-ship).
+//!
+//! Verify (P1_CDC_VERIFY_SAMPLE у cdc-apply): DIGEST_REQ подписчика, догнавшего поток, отвечается
+//! digest'ами бакетов БД (без LOCK), только если подписчику отправлено всё, что tail прочитал,
+//! и WAL не рос за время подсчёта — иначе DIGEST_LSN_MOVED, follower повторит позже.
+//! RESYNC_REQ (P1_CDC_RESYNC) — так же: состояние бакета (RESYNC_PAGE* + RESYNC_END) или статус.
+//!
+//! NEW: TLS — `--listen tls+psk://host:port` (follower: `cdc-apply --from tls+psk://...`),
+//! сертификат и ключ — P1_TLS_SERVER_CERT / P1_TLS_SERVER_KEY (PEM). Обновлённые файлы
+//! подхватываются без перезапуска и без закрытия слушателя, срок действия сертификата
+//! отслеживается (wal/tls_server.rs). Handshake идёт в потоке подписчика. Клиентские
+//! сертификаты не проверяются (native-tls) — подписчик аутентифицируется PSK.
+
+use anyhow::{anyhow, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use QuiverDB::meta::read_meta;
+use QuiverDB::wal::cursors::{
+    advance_cursor, load_cursor, prune_cursors, validate_subscriber_name,
+};
+use QuiverDB::wal::filter::{FilterFrame, FrameFilter};
+use QuiverDB::wal::net::{
+    cdc_proto_from_env, compression_from_env, load_psk_from_env, negotiate, write_ctrl_psk,
+    write_framed_psk, CtrlMsg, FrameCompression, IoStream, CAP_HEADS_DELTA, COMPRESS_NONE,
+    SINCE_LSN_NONE,
+};
+use QuiverDB::wal::reader::WalStreamReader;
+use QuiverDB::wal::state::{load_last_seq, store_last_seq};
+use QuiverDB::wal::tls_server::{ReloadingTlsAcceptor, TlsServerConfig};
+use QuiverDB::wal::{
+    decode_heads_update_payload, encode, encode_heads_update_legacy,
+    registry::get_or_create_wal_inner, WAL_HDR_SIZE, WAL_MAGIC, WAL_REC_COMMIT, WAL_REC_HDR_SIZE,
+    WAL_REC_HEADS_UPDATE,
+};
+
+use super::cmd_cdc_ship::{
+    answer_request, await_receiver_hello, filter_summary, moved_reply, ping,
+};
+
+/// Сколько seq резервируется одной записью .cdc_seq.bin.
+const SEQ_RESERVE: u64 = 4096;
+/// Период опроса WAL tail-потоком.
+const POLL: Duration = Duration::from_millis(50);
+/// Подписчик без keepalive (v1) ждёт новые кадры порциями по столько.
+const IDLE_WAIT: Duration = Duration::from_secs(1);
+/// Сколько кадров подписчик забирает из буфера за один захват мьютекса.
+const TAKE_MAX: usize = 256;
+/// Период проверки retention курсоров.
+const PRUNE_EVERY: Duration = Duration::from_secs(3600);
+/// Предел на TLS-handshake подписчика.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// CLI: cdc-serve --path <db> --listen host:port
+pub fn exec(
+    path: PathBuf,
+    listen: String,
+    max_subscribers: usize,
+    buffer_mb: u64,
+    cursor_retention_hours: u64,
+) -> Result<()> {
+    let wal_path = QuiverDB::wal::wal_path(&path);
+    if !wal_path.exists() {
+        return Err(anyhow!("WAL does not exist at {}", wal_path.display()));
+    }
+    let psk = load_psk_from_env()?;
+    let stream_id = get_or_create_wal_inner(&path)?.get_stream_id();
+
+    let (addr, tls) = match listen.strip_prefix("tls+psk://") {
+        Some(a) => {
+            let tls = Arc::new(ReloadingTlsAcceptor::new(TlsServerConfig::from_env()?)?);
+            tls.spawn_watcher()?;
+            eprintln!(
+                "[WARN] cdc-serve: the TLS listener does not verify client certificates; subscribers are authenticated by the PSK only"
+            );
+            (a, Some(tls))
+        }
+        None => (listen.strip_prefix("tcp+psk://").unwrap_or(&listen), None),
+    };
+    let listener =
+        TcpListener::bind(addr).with_context(|| format!("bind cdc-serve listener {}", addr))?;
+
+    let ctx = Arc::new(ServeCtx {
+        root: path.clone(),
+        cursors_lock: Mutex::new(()),
+        wal_path: wal_path.clone(),
+        psk,
+        stream_id,
+        feed: Feed::new((buffer_mb.max(1) as usize).saturating_mul(1 << 20)),
+        seq: SeqAlloc::open(&path),
+        active: AtomicUsize::new(0),
+        tail_pos: AtomicU64::new(WAL_HDR_SIZE as u64),
+    });
+
+    {
+        let ctx = ctx.clone();
+        std::thread::Builder::new()
+            .name("cdc-serve-tail".into())
+            .spawn(move || tail_wal(&ctx))
+            .context("spawn WAL tail thread")?;
+    }
+
+    // Retention именованных курсоров: сразу и раз в час
+    let retention = cursor_retention_hours.saturating_mul(3600);
+    if retention > 0 {
+        let ctx = ctx.clone();
+        std::thread::Builder::new()
+            .name("cdc-serve-cursors".into())
+            .spawn(move || loop {
+                let pruned = {
+                    let _g = ctx.cursors_lock.lock().unwrap();
+                    prune_cursors(&ctx.root, retention)
+                };
+                match pruned {
+                    Ok(names) if !names.is_empty() => eprintln!(
+                        "cdc-serve: pruned {} stale subscriber cursor(s): {}",
+                        names.len(),
+                        names.join(", ")
+                    ),
+                    Ok(_) => {}
+                    Err(e) => eprintln!("[WARN] cdc-serve: prune cursors: {:#}", e),
+                }
+                std::thread::sleep(PRUNE_EVERY);
+            })
+            .context("spawn cursor retention thread")?;
+    }
+
+    eprintln!(
+        "cdc-serve: listening on {}+psk://{} (db={}, stream_id={}, max_subscribers={}, buffer={} MiB)",
+        if tls.is_some() { "tls" } else { "tcp" },
+        listener.local_addr()?,
+        path.display(),
+        stream_id,
+        max_subscribers,
+        buffer_mb.max(1)
+    );
+
+    for conn in listener.incoming() {
+        let sock = match conn {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("[WARN] cdc-serve: accept failed: {}", e);
+                continue;
+            }
+        };
+        let peer = sock
+            .peer_addr()
+            .unwrap_or_else(|_| SocketAddr::from(([0, 0, 0, 0], 0)));
+        if ctx.active.load(Ordering::Relaxed) >= max_subscribers {
+            eprintln!(
+                "[WARN] cdc-serve: refusing {}: {} subscribers already connected",
+                peer, max_subscribers
+            );
+            continue;
+        }
+        let _ = sock.set_nodelay(true);
+        // Зависший подписчик (не читает сокет) не должен держать поток вечно
+        let _ = sock.set_write_timeout(Some(Duration::from_secs(30)));
+
+        ctx.active.fetch_add(1, Ordering::Relaxed);
+        let ctx = ctx.clone();
+        let tls = tls.clone();
+        let spawned = std::thread::Builder::new()
+            .name(format!("cdc-serve-{}", peer))
+            .spawn(move || {
+                let mut st = SubStats::default();
+                let res = subscriber_stream(sock, tls.as_deref())
+                    .and_then(|stream| serve_subscriber(stream, peer, &ctx, &mut st));
+                ctx.active.fetch_sub(1, Ordering::Relaxed);
+                let why = match res {
+                    Ok(()) => "closed".to_string(),
+                    Err(e) => format!("{:#}", e),
+                };
+                eprintln!(
+                    "cdc-serve: subscriber {} disconnected ({}): sent {} frames, {} bytes, last_lsn={}, acked_lsn={}, catch_ups={}{}",
+                    peer,
+                    why,
+                    st.frames,
+                    st.bytes,
+                    st.last_lsn,
+                    st.acked_lsn
+                        .map(|l| l.to_string())
+                        .unwrap_or_else(|| "-".into()),
+                    st.catch_ups,
+                    st.filter
+                );
+            });
+        if let Err(e) = spawned {
+            eprintln!("[WARN] cdc-serve: spawn subscriber thread: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Поток подписчика: TCP как есть или TLS-handshake (tls+psk://) с текущим сертификатом.
+fn subscriber_stream(sock: TcpStream, tls: Option<&ReloadingTlsAcceptor>) -> Result<IoStream> {
+    let Some(tls) = tls else {
+        return Ok(IoStream::Plain(sock));
+    };
+    let _ = sock.set_read_timeout(Some(TLS_HANDSHAKE_TIMEOUT));
+    let stream = tls.accept(sock)?;
+    let _ = stream.set_read_timeout(None);
+    Ok(stream)
+}
+
+// -------------------- shared state --------------------
+
+struct ServeCtx {
+    root: PathBuf,
+    /// read-modify-write .cdc_cursors.json внутри процесса
+    cursors_lock: Mutex<()>,
+    wal_path: PathBuf,
+    psk: Vec<u8>,
+    stream_id: u64,
+    feed: Feed,
+    seq: SeqAlloc,
+    active: AtomicUsize,
+    /// Позиция в WAL, до которой tail опубликовал кадры в буфер.
+    tail_pos: AtomicU64,
+}
+
+/// Кадр WAL в буфере: готовые байты [WAL header 28][payload].
+struct Frame {
+    rec_type: u8,
+    lsn: u64,
+    page_id: u64,
+    bytes: Vec<u8>,
+}
+
+impl FilterFrame for Frame {
+    fn rec_type(&self) -> u8 {
+        self.rec_type
+    }
+    fn page_id(&self) -> u64 {
+        self.page_id
+    }
+    fn payload(&self) -> &[u8] {
+        &self.bytes[WAL_REC_HDR_SIZE..]
+    }
+}
+
+/// Общий буфер кадров: индекс кадра монотонен, старые кадры вытесняются по пределу байт.
+struct Feed {
+    state: Mutex<FeedState>,
+    cv: Condvar,
+}
+
+struct FeedState {
+    frames: VecDeque<Arc<Frame>>,
+    /// Индекс frames[0].
+    base_idx: u64,
+    bytes: usize,
+    cap_bytes: usize,
+    /// Максимальный LSN среди вытесненных кадров (0 — ничего не вытеснено).
+    evicted_lsn: u64,
+}
+
+/// Где подписчику продолжать.
+enum Cursor {
+    /// Индекс следующего кадра в буфере.
+    At(u64),
+    /// Нужные кадры вытеснены — догонять из WAL-файла.
+    Lagged,
+}
+
+impl Feed {
+    fn new(cap_bytes: usize) -> Self {
+        Self {
+            state: Mutex::new(FeedState {
+                frames: VecDeque::new(),
+                base_idx: 0,
+                bytes: 0,
+                cap_bytes,
+                evicted_lsn: 0,
+            }),
+            cv: Condvar::new(),
+        }
+    }
+
+    fn push(&self, batch: Vec<Frame>) {
+        if batch.is_empty() {
+            return;
+        }
+        let mut st = self.state.lock().unwrap();
+        for f in batch {
+            st.bytes += f.bytes.len();
+            st.frames.push_back(Arc::new(f));
+        }
+        while st.bytes > st.cap_bytes && st.frames.len() > 1 {
+            let old = st.frames.pop_front().expect("non-empty");
+            st.bytes -= old.bytes.len();
+            st.evicted_lsn = st.evicted_lsn.max(old.lsn);
+            st.base_idx += 1;
+        }
+        drop(st);
+        self.cv.notify_all();
+    }
+
+    /// Максимальный LSN опубликованных кадров (0 — пусто).
+    fn last_lsn(&self) -> u64 {
+        let st = self.state.lock().unwrap();
+        st.frames.back().map(|f| f.lsn).unwrap_or(st.evicted_lsn)
+    }
+
+    /// Первый кадр с lsn > since (если буфер их все ещё держит).
+    fn locate(&self, since: u64) -> Cursor {
+        let st = self.state.lock().unwrap();
+        if st.evicted_lsn > since {
+            return Cursor::Lagged;
+        }
+        let off = st.frames.partition_point(|f| f.lsn <= since);
+        Cursor::At(st.base_idx + off as u64)
+    }
+
+    /// Кадры начиная с idx (до TAKE_MAX); пусто — ждать не дольше `wait`.
+    /// Err(()) — кадры idx.. уже вытеснены.
+    fn take(&self, idx: u64, wait: Duration) -> std::result::Result<Vec<Arc<Frame>>, ()> {
+        let mut st = self.state.lock().unwrap();
+        let end = st.base_idx + st.frames.len() as u64;
+        if idx >= end {
+            st = self.cv.wait_timeout(st, wait).unwrap().0;
+        }
+        if idx < st.base_idx {
+            return Err(());
+        }
+        let from = (idx - st.base_idx) as usize;
+        Ok(st
+            .frames
+            .iter()
+            .skip(from)
+            .take(TAKE_MAX)
+            .cloned()
+            .collect())
+    }
+}
+
+/// Монотонный seq для всех сессий: блоки по SEQ_RESERVE фиксируются в .cdc_seq.bin заранее.
+struct SeqAlloc {
+    root: PathBuf,
+    /// (следующий seq, последний зарезервированный)
+    inner: Mutex<(u64, u64)>,
+}
+
+impl SeqAlloc {
+    fn open(root: &Path) -> Self {
+        let last = load_last_seq(root).unwrap_or(0);
+        Self {
+            root: root.to_path_buf(),
+            inner: Mutex::new((last.wrapping_add(1), last)),
+        }
+    }
+
+    fn next(&self) -> u64 {
+        let mut g = self.inner.lock().unwrap();
+        if g.0 > g.1 {
+            g.1 = g.0 + SEQ_RESERVE - 1;
+            let _ = store_last_seq(&self.root, g.1);
+        }
+        let s = g.0;
+        g.0 += 1;
+        s
+    }
+}
+
+// -------------------- WAL tail --------------------
+
+/// Читать WAL по мере роста и публиковать кадры в буфер. После усечения WAL (или
+/// нечитаемых данных на месте старого хвоста) WAL перечитывается с начала; уже
+/// опубликованные кадры пропускаются по (max_lsn, число кадров с этим lsn).
+fn tail_wal(ctx: &ServeCtx) {
+    let mut warned = false;
+    loop {
+        if let Err(e) = tail_wal_pass(ctx) {
+            if !warned {
+                eprintln!("[WARN] cdc-serve: WAL tail: {:#}; retrying", e);
+                warned = true;
+            }
+            std::thread::sleep(POLL * 10);
+        }
+    }
+}
+
+fn tail_wal_pass(ctx: &ServeCtx) -> Result<()> {
+    let mut f = OpenOptions::new()
+        .read(true)
+        .open(&ctx.wal_path)
+        .with_context(|| format!("open wal {}", ctx.wal_path.display()))?;
+    let mut hdr = [0u8; WAL_HDR_SIZE];
+    std::io::Read::read_exact(&mut f, &mut hdr)?;
+    if &hdr[..8] != WAL_MAGIC {
+        return Err(anyhow!("bad WAL magic in {}", ctx.wal_path.display()));
+    }
+
+    let mut rdr = WalStreamReader::new();
+    let mut pos = WAL_HDR_SIZE as u64;
+    let mut max_lsn = 0u64;
+    let mut at_max = 0u64;
+    // кадров с lsn == max_lsn, которые надо пропустить при перечитывании
+    let mut skip_at_max = 0u64;
+
+    loop {
+        let len = f.metadata()?.len();
+        if len < pos {
+            pos = WAL_HDR_SIZE as u64;
+            rdr.reset_stream();
+            skip_at_max = at_max;
+        }
+        let mut batch = Vec::new();
+        loop {
+            match rdr.read_next(&mut f, pos, len) {
+                Ok(Some((rec, next))) => {
+                    pos = next;
+                    if rec.lsn < max_lsn {
+                        continue;
+                    }
+                    if rec.lsn == max_lsn && skip_at_max > 0 {
+                        skip_at_max -= 1;
+                        continue;
+                    }
+                    if rec.lsn == max_lsn {
+                        at_max += 1;
+                    } else {
+                        max_lsn = rec.lsn;
+                        at_max = 1;
+                        skip_at_max = 0;
+                    }
+                    let mut bytes = Vec::with_capacity(WAL_REC_HDR_SIZE + rec.payload.len());
+                    bytes.extend_from_slice(&encode::build_hdr_with_crc(
+                        rec.rec_type,
+                        rec.lsn,
+                        rec.page_id,
+                        &rec.payload,
+                    ));
+                    bytes.extend_from_slice(&rec.payload);
+                    batch.push(Frame {
+                        rec_type: rec.rec_type,
+                        lsn: rec.lsn,
+                        page_id: rec.page_id,
+                        bytes,
+                    });
+                    if batch.len() >= TAKE_MAX {
+                        ctx.feed.push(std::mem::take(&mut batch));
+                    }
+                }
+                Ok(None) => break,
+                Err(_) => {
+                    // WAL переписан после усечения, пока мы спали — перечитать с начала
+                    pos = WAL_HDR_SIZE as u64;
+                    rdr.reset_stream();
+                    skip_at_max = at_max;
+                    break;
+                }
+            }
+        }
+        ctx.feed.push(batch);
+        ctx.tail_pos.store(pos, Ordering::Relaxed);
+        std::thread::sleep(POLL);
+    }
+}
+
+// -------------------- subscriber --------------------
+
+#[derive(Default)]
+struct SubStats {
+    frames: u64,
+    bytes: u64,
+    last_lsn: u64,
+    acked_lsn: Option<u64>,
+    catch_ups: u64,
+    /// Итог фильтра (filter_summary), пусто — без фильтра.
+    filter: String,
+}
+
+/// Параметры сессии подписчика после handshake.
+struct Session {
+    heads_legacy: bool,
+    compress: Option<FrameCompression>,
+    keepalive: Option<Duration>,
+}
+
+fn serve_subscriber(
+    mut stream: IoStream,
+    peer: SocketAddr,
+    ctx: &ServeCtx,
+    st: &mut SubStats,
+) -> Result<()> {
+    let psk = &ctx.psk;
+
+    // Handshake: как у cdc-ship (v2 — HELLO/WELCOME, иначе v1 HELLO = WAL header)
+    let hello = if cdc_proto_from_env() >= 2 {
+        await_receiver_hello(&mut stream, psk)?
+    } else {
+        None
+    };
+    if let Some(h) = &hello {
+        if h.stream_id != 0 && h.stream_id != ctx.stream_id {
+            return Err(anyhow!(
+                "receiver is bound to stream_id={}, this source has stream_id={}",
+                h.stream_id,
+                ctx.stream_id
+            ));
+        }
+    }
+    let name = hello.as_ref().and_then(|h| h.subscriber.clone());
+    if let Some(n) = &name {
+        validate_subscriber_name(n)?;
+    }
+    let cursor = match &name {
+        Some(n) => {
+            let _g = ctx.cursors_lock.lock().unwrap();
+            load_cursor(&ctx.root, n)?
+        }
+        None => None,
+    };
+    // Позиция: меньшее из курсора и запроса получателя (at-least-once)
+    let asked = hello
+        .as_ref()
+        .map(|h| h.since_lsn)
+        .filter(|&s| s != SINCE_LSN_NONE);
+    let (since, since_src) = match (asked, cursor.as_ref().map(|c| c.acked_lsn)) {
+        (Some(a), Some(c)) if c < a => (c, "cursor"),
+        (Some(a), _) => (a, "receiver"),
+        (None, Some(c)) => (c, "cursor"),
+        (None, None) => (0, "none"),
+    };
+    let sess = match &hello {
+        Some(h) => {
+            let mut w = negotiate(h, ctx.stream_id, since);
+            w.db_uuid = read_meta(&ctx.root).map(|m| m.db_uuid).unwrap_or_default();
+            write_ctrl_psk(&mut stream, ctx.seq.next(), &CtrlMsg::Welcome(w), psk)?;
+            Session {
+                heads_legacy: !w.has(CAP_HEADS_DELTA),
+                compress: (w.compress != COMPRESS_NONE).then(|| FrameCompression {
+                    algo: w.compress,
+                    ..compression_from_env()
+                }),
+                keepalive: w.keepalive(),
+            }
+        }
+        None => {
+            let mut v1 = Vec::with_capacity(WAL_HDR_SIZE);
+            v1.extend_from_slice(WAL_MAGIC);
+            let mut sid = [0u8; 8];
+            LittleEndian::write_u64(&mut sid, ctx.stream_id);
+            v1.extend_from_slice(&sid);
+            write_framed_psk(&mut stream, ctx.seq.next(), &v1, psk)?;
+            Session {
+                heads_legacy: false,
+                compress: None,
+                keepalive: None,
+            }
+        }
+    };
+    let filter = hello
+        .as_ref()
+        .and_then(|h| h.filter.clone())
+        .unwrap_or_default();
+    let mut ff: FrameFilter<Arc<Frame>> = FrameFilter::open(&ctx.root, filter)?;
+    eprintln!(
+        "cdc-serve: subscriber {}{} connected (proto={}, since_lsn={} from {}{})",
+        peer,
+        name.as_deref()
+            .map(|n| format!(" '{}'", n))
+            .unwrap_or_default(),
+        if hello.is_some() { 2 } else { 1 },
+        since,
+        since_src,
+        if ff.filter().is_empty() {
+            String::new()
+        } else {
+            format!(", filter={}", ff.filter())
+        }
+    );
+
+    // Курсор: последний полностью отправленный батч (COMMIT) — на случай догоняния из файла
+    st.last_lsn = since;
+    let mut committed = since;
+    let mut cursor = ctx.feed.locate(since);
+    let mut last_ping = Instant::now();
+    let wait = sess.keepalive.unwrap_or(IDLE_WAIT).min(IDLE_WAIT);
+
+    loop {
+        let idx = match cursor {
+            Cursor::At(idx) => idx,
+            Cursor::Lagged => {
+                st.catch_ups += 1;
+                // незавершённый батч из буфера будет прочитан из файла заново
+                ff.reset();
+                committed = catch_up_from_wal(&mut stream, ctx, &sess, &mut ff, committed, st)?;
+                cursor = ctx.feed.locate(committed);
+                continue;
+            }
+        };
+        let frames = match ctx.feed.take(idx, wait) {
+            Ok(f) => f,
+            Err(()) => {
+                cursor = Cursor::Lagged;
+                continue;
+            }
+        };
+        cursor = Cursor::At(idx + frames.len() as u64);
+        for fr in frames {
+            for (fr, filtered) in ff.push(fr) {
+                send_frame(&mut stream, ctx, &sess, &fr, filtered.as_deref(), st)?;
+                if fr.rec_type == WAL_REC_COMMIT {
+                    committed = fr.lsn;
+                }
+            }
+        }
+        st.filter = filter_summary(&ff);
+
+        if let Some(ka) = sess.keepalive {
+            if last_ping.elapsed() >= ka {
+                let sent = st.last_lsn;
+                let acked = ping(
+                    &mut stream,
+                    &mut || ctx.seq.next(),
+                    sent,
+                    ka,
+                    psk,
+                    &mut |req| {
+                        // подписчику отправлено не всё, что уже в буфере
+                        if ctx.feed.last_lsn() > sent {
+                            return moved_reply(req, sent);
+                        }
+                        let wal_end = ctx.tail_pos.load(Ordering::Relaxed);
+                        answer_request(&ctx.root, &ctx.wal_path, sent, wal_end, req)
+                    },
+                )?;
+                st.acked_lsn = Some(acked);
+                if let Some(n) = &name {
+                    let _g = ctx.cursors_lock.lock().unwrap();
+                    if let Err(e) = advance_cursor(&ctx.root, n, acked, Some(&peer.to_string())) {
+                        eprintln!("[WARN] cdc-serve: store cursor '{}': {:#}", n, e);
+                    }
+                }
+                last_ping = Instant::now();
+            }
+        }
+    }
+}
+
+/// Догнать подписчика чтением WAL-файла: кадры с lsn > since, целыми батчами (хвост без
+/// COMMIT остаётся буферу). Возвращает LSN последнего отправленного COMMIT.
+fn catch_up_from_wal(
+    stream: &mut IoStream,
+    ctx: &ServeCtx,
+    sess: &Session,
+    ff: &mut FrameFilter<Arc<Frame>>,
+    since: u64,
+    st: &mut SubStats,
+) -> Result<u64> {
+    let mut f = OpenOptions::new()
+        .read(true)
+        .open(&ctx.wal_path)
+        .with_context(|| format!("open wal {}", ctx.wal_path.display()))?;
+    let len = f.metadata()?.len();
+    let mut rdr = WalStreamReader::new();
+    let mut pos = WAL_HDR_SIZE as u64;
+    let mut committed = since;
+    let mut pending: Vec<Frame> = Vec::new();
+
+    while let Some((rec, next)) = rdr.read_next(&mut f, pos, len)? {
+        pos = next;
+        if rec.lsn <= since {
+            continue;
+        }
+        let mut bytes = Vec::with_capacity(WAL_REC_HDR_SIZE + rec.payload.len());
+        bytes.extend_from_slice(&encode::build_hdr_with_crc(
+            rec.rec_type,
+            rec.lsn,
+            rec.page_id,
+            &rec.payload,
+        ));
+        bytes.extend_from_slice(&rec.payload);
+        let is_commit = rec.rec_type == WAL_REC_COMMIT;
+        pending.push(Frame {
+            rec_type: rec.rec_type,
+            lsn: rec.lsn,
+            page_id: rec.page_id,
+            bytes,
+        });
+        if is_commit {
+            for fr in pending.drain(..) {
+                for (fr, filtered) in ff.push(Arc::new(fr)) {
+                    send_frame(stream, ctx, sess, &fr, filtered.as_deref(), st)?;
+                }
+            }
+            committed = rec.lsn;
+        }
+    }
+    Ok(committed)
+}
+
+fn send_frame(
+    stream: &mut IoStream,
+    ctx: &ServeCtx,
+    sess: &Session,
+    fr: &Frame,
+    filtered: Option<&[u8]>,
+    st: &mut SubStats,
+) -> Result<()> {
+    // Получатель без delta HEADS_UPDATE — перекодируем в legacy
+    let payload = filtered.unwrap_or(fr.payload());
+    let legacy = if sess.heads_legacy && fr.rec_type == WAL_REC_HEADS_UPDATE {
+        decode_heads_update_payload(payload).map(|u| encode_heads_update_legacy(&u))
+    } else {
+        None
+    };
+    let rebuilt;
+    let buf: &[u8] = match (legacy.as_deref(), filtered) {
+        (None, None) => &fr.bytes,
+        (Some(pl), _) | (None, Some(pl)) => {
+            let mut b = encode::build_hdr_with_crc(fr.rec_type, fr.lsn, fr.page_id, pl).to_vec();
+            b.extend_from_slice(pl);
+            rebuilt = b;
+            &rebuilt
+        }
+    };
+    let wire = match &sess.compress {
+        Some(c) => c.encode(buf)?,
+        None => std::borrow::Cow::Borrowed(buf),
+    };
+    write_framed_psk(stream, ctx.seq.next(), &wire, &ctx.psk)?;
+    st.frames += 1;
+    st.bytes += wire.len() as u64;
+    st.last_lsn = st.last_lsn.max(fr.lsn);
+    Ok(())
+}
@@ -1,22 +1,42 @@
 use anyhow::{anyhow, Context, Result};
 use byteorder::{ByteOrder, LittleEndian};
+use std::borrow::Cow;
 use std::fs::OpenOptions;
 use std::io::{Seek, SeekFrom};
 use std::net::TcpStream;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+use QuiverDB::meta::read_meta;
 use QuiverDB::wal::{
-    encode,                            // build_hdr_with_crc / write_record
+    decode_heads_update_payload,
+    encode, // build_hdr_with_crc / write_record
+    encode_heads_update_legacy,
     registry::get_or_create_wal_inner, // для чтения stream_id
     write_wal_file_header_with_stream_id,
     WAL_HDR_SIZE,
     WAL_MAGIC,
     WAL_REC_HDR_SIZE,
+    WAL_REC_HEADS_UPDATE,
 };
 // NEW: stateful WAL reader вместо глобальной функции
 use QuiverDB::wal::reader::WalStreamReader;
+// NEW: серверный фильтр потока (бакеты / префиксы ключей)
+use QuiverDB::wal::filter::{CdcFilter, FilterFrame, FrameFilter};
 // NEW: защищённый транспорт (framing + HMAC-PSK) + TLS
-use QuiverDB::wal::net::{load_psk_from_env, open_tls_psk_stream, write_framed_psk, IoStream};
+use QuiverDB::wal::net::{
+    cdc_proto_from_env, compress_name, compression_from_env, hello_wait_from_env,
+    load_psk_from_env, negotiate, open_tls_psk_stream, read_next_framed_psk_timeout,
+    write_ctrl_psk, write_framed_psk, CdcHello, CtrlMsg, FrameCompression, FrameWait, IoStream,
+    CAP_HEADS_DELTA, COMPRESS_NONE, CTRL_MAX_LEN, DIGEST_LSN_MOVED, DIGEST_OK, DIGEST_UNAVAILABLE,
+    SINCE_LSN_NONE,
+};
+// NEW: digest бакетов для verify follower'а (DIGEST_REQ)
+use QuiverDB::db::digest::bucket_digests_at;
+// NEW: состояние бакета для resync follower'а (RESYNC_REQ)
+use QuiverDB::db::resync::bucket_state_at;
+use QuiverDB::db::BucketDigest;
+use QuiverDB::dir::NO_PAGE;
 // NEW: персистентное состояние seq
 use QuiverDB::wal::state::{load_last_seq, store_last_seq};
 
@@ -32,20 +52,41 @@ use QuiverDB::wal::state::{load_last_seq, store_last_seq};
 ///   quiverdb cdc-ship --path ./db --to file://./wal-stream.bin --since-lsn 12345
 ///   quiverdb cdc-ship --path ./db --to tcp+psk://127.0.0.1:9099 --since-lsn 12345
 ///   quiverdb cdc-ship --path ./db --to tls+psk://127.0.0.1:9443 --since-lsn 12345
+///   quiverdb cdc-ship --path ./db --to file://./users.bin --filter-prefix user:
 ///
 /// ENV:
 ///   P1_SHIP_SINCE_INCLUSIVE=1|true|yes|on  — трактовать --since-lsn как >=
 ///   P1_CDC_PSK_HEX / P1_CDC_PSK_BASE64 / P1_CDC_PSK — PSK ключ (минимум 16 байт)
 ///   P1_CDC_SEQ_RESET=1 — сбросить последовательность (начать с 1)
-pub fn exec(path: PathBuf, to: String, since_lsn: Option<u64>) -> Result<()> {
+///   P1_CDC_PROTO / P1_CDC_KEEPALIVE_MS / P1_CDC_HELLO_WAIT_MS — протокол v2 (см. wal/net.rs)
+///   P1_CDC_COMPRESS / P1_CDC_COMPRESS_MIN — сжатие кадров (если получатель его поддерживает)
+///
+/// tcp/tls: по протоколу v2 ship ждёт HELLO получателя (since_lsn, capabilities), отвечает
+/// WELCOME, по ходу потока и в конце делает KEEPALIVE→ACK и завершает сессию BYE.
+/// Без --since-lsn поток начинается с since_lsn из HELLO (resume по meta.last_lsn follower'а).
+/// Получатель без HELLO — поток v1 (HELLO = WAL header).
+/// DIGEST_REQ получателя (verify, P1_CDC_VERIFY_SAMPLE) на KEEPALIVE отвечается digest'ами
+/// бакетов источника, если с тех пор WAL не вырос (digest_reply); RESYNC_REQ (P1_CDC_RESYNC) —
+/// состоянием бакета на том же условии (resync_reply).
+///
+/// Фильтр (wal/filter.rs): --filter-prefix / --filter-buckets, иначе (tcp/tls v2) — фильтр из
+/// HELLO получателя. Отправляются только релевантные страницы, HEADS_UPDATE/EXPIRY урезаются.
+pub fn exec(
+    path: PathBuf,
+    to: String,
+    since_lsn: Option<u64>,
+    filter_prefix: Vec<String>,
+    filter_buckets: Option<String>,
+) -> Result<()> {
+    let filter = CdcFilter::from_parts(&filter_prefix, filter_buckets.as_deref())?;
     if let Some(dst_path) = to.strip_prefix("file://") {
-        return ship_to_file(path, PathBuf::from(dst_path), since_lsn);
+        return ship_to_file(path, PathBuf::from(dst_path), since_lsn, filter);
     }
     if let Some(addr) = to.strip_prefix("tcp+psk://") {
-        return ship_to_psk_stream(path, addr, since_lsn, false);
+        return ship_to_psk_stream(path, addr, since_lsn, false, filter);
     }
     if let Some(addr) = to.strip_prefix("tls+psk://") {
-        return ship_to_psk_stream(path, addr, since_lsn, true);
+        return ship_to_psk_stream(path, addr, since_lsn, true, filter);
     }
     Err(anyhow!(
         "unsupported sink '{}': use file://<path>, tcp+psk://host:port or tls+psk://host:port",
@@ -55,7 +96,12 @@ pub fn exec(path: PathBuf, to: String, since_lsn: Option<u64>) -> Result<()> {
 
 // ---------------- file sink ----------------
 
-fn ship_to_file(root: PathBuf, dst: PathBuf, since_lsn: Option<u64>) -> Result<()> {
+fn ship_to_file(
+    root: PathBuf,
+    dst: PathBuf,
+    since_lsn: Option<u64>,
+    filter: CdcFilter,
+) -> Result<()> {
     // Откроем исходный WAL (из корня DB)
     let wal_path = QuiverDB::wal::wal_path(&root);
     if !wal_path.exists() {
@@ -109,18 +155,24 @@ fn ship_to_file(root: PathBuf, dst: PathBuf, since_lsn: Option<u64>) -> Result<(
     let mut max_lsn = 0u64;
 
     let mut rdr = WalStreamReader::new();
+    let mut ff = FrameFilter::open(&root, filter)?;
 
     while let Some((rec, next_pos)) = rdr.read_next(&mut src, pos, file_len)? {
+        pos = next_pos;
         // LSN фильтр
         let pass = if inclusive {
             rec.lsn >= since
         } else {
             rec.lsn > since
         };
-        if pass {
+        if !pass {
+            continue;
+        }
+        for (rec, filtered) in ff.push(rec) {
             // Запишем кадр в sink (заголовок+payload со свежей CRC)
+            let payload = filtered.as_deref().unwrap_or(&rec.payload);
             let before = out.metadata()?.len();
-            encode::write_record(&mut out, rec.rec_type, rec.lsn, rec.page_id, &rec.payload)?;
+            encode::write_record(&mut out, rec.rec_type, rec.lsn, rec.page_id, payload)?;
             let after = out.metadata()?.len();
             frames += 1;
             bytes += after.saturating_sub(before);
@@ -128,13 +180,12 @@ fn ship_to_file(root: PathBuf, dst: PathBuf, since_lsn: Option<u64>) -> Result<(
                 max_lsn = rec.lsn;
             }
         }
-        pos = next_pos;
     }
 
     let _ = out.sync_all();
 
     println!(
-        "cdc-ship[file]: wrote {} frames, {} bytes to {}, last_lsn={} (since_lsn={}{}), src={}, stream_id={}",
+        "cdc-ship[file]: wrote {} frames, {} bytes to {}, last_lsn={} (since_lsn={}{}), src={}, stream_id={}{}",
         frames,
         bytes,
         dst.display(),
@@ -142,7 +193,8 @@ fn ship_to_file(root: PathBuf, dst: PathBuf, since_lsn: Option<u64>) -> Result<(
         since,
         if inclusive { " (inclusive)" } else { "" },
         wal_path.display(),
-        stream_id
+        stream_id,
+        filter_summary(&ff)
     );
     Ok(())
 }
@@ -154,6 +206,7 @@ fn ship_to_psk_stream(
     addr: &str,
     since_lsn: Option<u64>,
     use_tls: bool,
+    filter: CdcFilter,
 ) -> Result<()> {
     // Откроем источник WAL (как в file sink)
     let wal_path = QuiverDB::wal::wal_path(&root);
@@ -192,14 +245,6 @@ fn ship_to_psk_stream(
     let inner = get_or_create_wal_inner(&root)?;
     let stream_id = inner.get_stream_id();
 
-    // Фильтр
-    let since = since_lsn.unwrap_or(0);
-    let inclusive = std::env::var("P1_SHIP_SINCE_INCLUSIVE")
-        .ok()
-        .map(|s| s.to_ascii_lowercase())
-        .map(|s| s == "1" || s == "true" || s == "yes" || s == "on")
-        .unwrap_or(false);
-
     // Последовательность кадров (персистентная)
     let mut seq = if std::env::var("P1_CDC_SEQ_RESET")
         .ok()
@@ -215,17 +260,77 @@ fn ship_to_psk_stream(
         load_last_seq(&root).unwrap_or(0).wrapping_add(1)
     };
 
-    // 0) HELLO‑фрейм со stream_id: отправим один PSK‑кадр с 16‑байтовым WAL header (MAGIC + stream_id)
-    let mut hello = Vec::with_capacity(WAL_HDR_SIZE);
-    hello.extend_from_slice(WAL_MAGIC);
-    let mut sid = [0u8; 8];
-    LittleEndian::write_u64(&mut sid, stream_id);
-    hello.extend_from_slice(&sid);
+    // 0) Handshake v2: HELLO получателя (не дождались — v1)
+    let hello = if cdc_proto_from_env() >= 2 {
+        await_receiver_hello(&mut stream, &psk)?
+    } else {
+        None
+    };
+    if let Some(h) = &hello {
+        if h.stream_id != 0 && h.stream_id != stream_id {
+            return Err(anyhow!(
+                "CDC: receiver is bound to stream_id={}, this source has stream_id={}",
+                h.stream_id,
+                stream_id
+            ));
+        }
+    }
+
+    // Фильтр: явный --since-lsn, иначе запрос получателя из HELLO
+    let (since, since_src) = match (since_lsn, &hello) {
+        (Some(s), _) => (s, "flag"),
+        (None, Some(h)) if h.since_lsn != SINCE_LSN_NONE => (h.since_lsn, "receiver"),
+        _ => (0, "none"),
+    };
+    let inclusive = std::env::var("P1_SHIP_SINCE_INCLUSIVE")
+        .ok()
+        .map(|s| s.to_ascii_lowercase())
+        .map(|s| s == "1" || s == "true" || s == "yes" || s == "on")
+        .unwrap_or(false);
 
-    write_framed_psk(&mut stream, seq, &hello, &psk)?;
+    let welcome = match &hello {
+        Some(h) => {
+            // WELCOME: итог согласования
+            let mut w = negotiate(h, stream_id, since);
+            w.db_uuid = read_meta(&root).map(|m| m.db_uuid).unwrap_or_default();
+            write_ctrl_psk(&mut stream, seq, &CtrlMsg::Welcome(w), &psk)?;
+            Some(w)
+        }
+        None => {
+            // v1 HELLO со stream_id: один PSK‑кадр с 16‑байтовым WAL header (MAGIC + stream_id)
+            let mut hello = Vec::with_capacity(WAL_HDR_SIZE);
+            hello.extend_from_slice(WAL_MAGIC);
+            let mut sid = [0u8; 8];
+            LittleEndian::write_u64(&mut sid, stream_id);
+            hello.extend_from_slice(&sid);
+            write_framed_psk(&mut stream, seq, &hello, &psk)?;
+            None
+        }
+    };
     let _ = store_last_seq(&root, seq);
     seq = seq.wrapping_add(1);
 
+    // Получатель без delta HEADS_UPDATE — перекодируем в legacy
+    let heads_legacy = welcome.map(|w| !w.has(CAP_HEADS_DELTA)).unwrap_or(false);
+    let keepalive = welcome.and_then(|w| w.keepalive());
+    // Сжатие кадров — только если согласовано в WELCOME
+    let compress = match welcome {
+        Some(w) if w.compress != COMPRESS_NONE => Some(FrameCompression {
+            algo: w.compress,
+            ..compression_from_env()
+        }),
+        _ => None,
+    };
+    // Фильтр: флаги, иначе запрос получателя из HELLO
+    let filter = match &hello {
+        Some(h) if filter.is_empty() => h.filter.clone().unwrap_or_default(),
+        _ => filter,
+    };
+    let mut ff = FrameFilter::open(&root, filter)?;
+    let mut raw_bytes = 0u64;
+    let mut last_ping = Instant::now();
+    let mut acked_lsn: Option<u64> = None;
+
     // Перебор кадров WAL stateful‑ридером
     let mut pos = WAL_HDR_SIZE as u64;
     let file_len = src.metadata()?.len();
@@ -237,38 +342,83 @@ fn ship_to_psk_stream(
     let mut rdr = WalStreamReader::new();
 
     while let Some((rec, next_pos)) = rdr.read_next(&mut src, pos, file_len)? {
+        pos = next_pos;
         let pass = if inclusive {
             rec.lsn >= since
         } else {
             rec.lsn > since
         };
-        if pass {
+        let out = if pass { ff.push(rec) } else { Vec::new() };
+        for (rec, filtered) in out {
+            let payload = filtered.as_deref().unwrap_or(&rec.payload);
+            let payload: Cow<[u8]> = if heads_legacy && rec.rec_type == WAL_REC_HEADS_UPDATE {
+                match decode_heads_update_payload(payload) {
+                    Some(updates) => Cow::Owned(encode_heads_update_legacy(&updates)),
+                    None => Cow::Borrowed(payload),
+                }
+            } else {
+                Cow::Borrowed(payload)
+            };
+
             // Сформируем bytes кадра WAL: [WAL header 28][payload]
-            let mut buf = Vec::with_capacity(WAL_REC_HDR_SIZE + rec.payload.len());
-            let hdr28 =
-                encode::build_hdr_with_crc(rec.rec_type, rec.lsn, rec.page_id, &rec.payload);
+            let mut buf = Vec::with_capacity(WAL_REC_HDR_SIZE + payload.len());
+            let hdr28 = encode::build_hdr_with_crc(rec.rec_type, rec.lsn, rec.page_id, &payload);
             buf.extend_from_slice(&hdr28);
-            if !rec.payload.is_empty() {
-                buf.extend_from_slice(&rec.payload);
+            if !payload.is_empty() {
+                buf.extend_from_slice(&payload);
             }
 
-            // Отправим фрейм [header(len,seq,mac)][buf]
-            write_framed_psk(&mut stream, seq, &buf, &psk)?;
+            // Отправим фрейм [header(len,seq,mac)][buf] (сжатый, если выгодно)
+            let wire = match &compress {
+                Some(c) => c.encode(&buf)?,
+                None => Cow::Borrowed(buf.as_slice()),
+            };
+            write_framed_psk(&mut stream, seq, &wire, &psk)?;
             // Персистентно зафиксируем seq после успешной отправки
             let _ = store_last_seq(&root, seq);
             seq = seq.wrapping_add(1);
 
             frames += 1;
-            bytes += buf.len() as u64;
+            bytes += wire.len() as u64;
+            raw_bytes += buf.len() as u64;
             if rec.lsn > max_lsn {
                 max_lsn = rec.lsn;
             }
         }
-        pos = next_pos;
+        // ping и при пропуске кадров фильтром — получатель не должен принять паузу за обрыв
+        if let Some(ka) = keepalive {
+            if last_ping.elapsed() >= ka {
+                acked_lsn = Some(ping(
+                    &mut stream,
+                    &mut || take_seq(&root, &mut seq),
+                    max_lsn,
+                    ka,
+                    &psk,
+                    &mut |req| answer_request(&root, &wal_path, max_lsn, pos, req),
+                )?);
+                last_ping = Instant::now();
+            }
+        }
+    }
+
+    // v2: финальный KEEPALIVE→ACK (получатель применил всё отправленное) и BYE
+    if welcome.is_some() {
+        if let Some(ka) = keepalive {
+            acked_lsn = Some(ping(
+                &mut stream,
+                &mut || take_seq(&root, &mut seq),
+                max_lsn,
+                ka,
+                &psk,
+                &mut |req| answer_request(&root, &wal_path, max_lsn, pos, req),
+            )?);
+        }
+        write_ctrl_psk(&mut stream, seq, &CtrlMsg::Bye { last_lsn: max_lsn }, &psk)?;
+        let _ = store_last_seq(&root, seq);
     }
 
     println!(
-        "cdc-ship[{}+psk]: sent {} frames (+1 hello), {} bytes to {}, last_lsn={} (since_lsn={}{}), src={}, stream_id={}",
+        "cdc-ship[{}+psk]: sent {} frames (+1 hello), {} bytes to {}, last_lsn={} (since_lsn={}{}, from {}), src={}, stream_id={}, proto={}{}{}{}",
         if use_tls { "tls" } else { "tcp" },
         frames,
         bytes,
@@ -276,9 +426,230 @@ fn ship_to_psk_stream(
         max_lsn,
         since,
         if inclusive { " (inclusive)" } else { "" },
+        since_src,
         wal_path.display(),
-        stream_id
+        stream_id,
+        welcome.map(|w| w.version).unwrap_or(1),
+        acked_lsn
+            .map(|l| format!(", acked_lsn={}", l))
+            .unwrap_or_default(),
+        compress
+            .map(|c| format!(
+                ", compress={} (raw {} bytes)",
+                compress_name(c.algo),
+                raw_bytes
+            ))
+            .unwrap_or_default(),
+        filter_summary(&ff)
     );
 
     Ok(())
 }
+
+/// ", filter=... (dropped N pages, M bytes)" для итоговой строки; пусто без фильтра.
+pub fn filter_summary<T: FilterFrame>(ff: &FrameFilter<T>) -> String {
+    if ff.filter().is_empty() {
+        return String::new();
+    }
+    let st = ff.stats();
+    format!(
+        ", filter={} (dropped {} pages, {} bytes)",
+        ff.filter(),
+        st.pages_dropped,
+        st.bytes_dropped
+    )
+}
+
+/// Следующий seq отправителя (персистентно, как у кадров данных).
+fn take_seq(root: &Path, seq: &mut u64) -> u64 {
+    let s = *seq;
+    let _ = store_last_seq(root, s);
+    *seq = s.wrapping_add(1);
+    s
+}
+
+/// Ждать HELLO получателя (P1_CDC_HELLO_WAIT_MS). None — получатель v1 (молчит).
+pub fn await_receiver_hello(stream: &mut IoStream, psk: &[u8]) -> Result<Option<CdcHello>> {
+    match read_next_framed_psk_timeout(stream, psk, CTRL_MAX_LEN, hello_wait_from_env())? {
+        FrameWait::Frame(_, payload) => match CtrlMsg::decode(&payload)? {
+            Some(CtrlMsg::Hello(h)) => Ok(Some(h)),
+            other => Err(anyhow!(
+                "CDC: expected HELLO from receiver, got {:?}",
+                other
+                    .map(|m| format!("{:?}", m))
+                    .unwrap_or_else(|| "data frame".into())
+            )),
+        },
+        FrameWait::Eof => Err(anyhow!(
+            "CDC: receiver closed the connection before handshake"
+        )),
+        FrameWait::Timeout => Ok(None),
+    }
+}
+
+/// KEEPALIVE{last_lsn} → ждать ACK (до 3 интервалов, минимум 5 с). Возвращает applied_lsn.
+/// `next_seq` выдаёт seq фреймов KEEPALIVE и ответов. DIGEST_REQ / RESYNC_REQ follower'а
+/// (verify/resync, приходят перед ACK) отвечаются кадрами, которые строит `on_request`
+/// (обычно answer_request).
+pub fn ping(
+    stream: &mut IoStream,
+    next_seq: &mut dyn FnMut() -> u64,
+    last_lsn: u64,
+    keepalive: Duration,
+    psk: &[u8],
+    on_request: &mut dyn FnMut(&CtrlMsg) -> Vec<CtrlMsg>,
+) -> Result<u64> {
+    write_ctrl_psk(stream, next_seq(), &CtrlMsg::Keepalive { last_lsn }, psk)?;
+
+    let wait = (keepalive * 3).max(Duration::from_secs(5));
+    let deadline = Instant::now() + wait;
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(anyhow!(
+                "CDC: no ACK from receiver within {} ms (last_lsn={})",
+                wait.as_millis(),
+                last_lsn
+            ));
+        }
+        match read_next_framed_psk_timeout(stream, psk, CTRL_MAX_LEN, left)? {
+            FrameWait::Frame(_, payload) => match CtrlMsg::decode(&payload)? {
+                Some(CtrlMsg::Ack { applied_lsn }) => return Ok(applied_lsn),
+                Some(req @ (CtrlMsg::DigestRequest { .. } | CtrlMsg::ResyncRequest { .. })) => {
+                    for reply in on_request(&req) {
+                        write_ctrl_psk(stream, next_seq(), &reply, psk)?;
+                    }
+                }
+                // прочие/неизвестные управляющие сообщения пропускаем
+                _ => continue,
+            },
+            FrameWait::Eof => {
+                return Err(anyhow!(
+                    "CDC: receiver closed the connection (awaiting ACK for last_lsn={})",
+                    last_lsn
+                ))
+            }
+            FrameWait::Timeout => continue,
+        }
+    }
+}
+
+/// Ответ на DIGEST_REQ / RESYNC_REQ follower'а при отправленном `shipped_lsn` и позиции WAL
+/// `wal_end` (см. digest_reply / resync_reply); прочие сообщения — без ответа.
+pub fn answer_request(
+    root: &Path,
+    wal_path: &Path,
+    shipped_lsn: u64,
+    wal_end: u64,
+    req: &CtrlMsg,
+) -> Vec<CtrlMsg> {
+    match req {
+        CtrlMsg::DigestRequest { lsn, now, buckets } => vec![digest_reply(
+            root,
+            wal_path,
+            shipped_lsn,
+            wal_end,
+            *lsn,
+            *now,
+            buckets,
+        )],
+        CtrlMsg::ResyncRequest { lsn, now, bucket } => {
+            resync_reply(root, wal_path, shipped_lsn, wal_end, *lsn, *now, *bucket)
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Ответ DIGEST_LSN_MOVED на DIGEST_REQ / RESYNC_REQ: отправитель уже ушёл дальше `shipped_lsn`.
+pub fn moved_reply(req: &CtrlMsg, shipped_lsn: u64) -> Vec<CtrlMsg> {
+    match req {
+        CtrlMsg::DigestRequest { .. } => vec![CtrlMsg::Digest {
+            lsn: shipped_lsn,
+            status: DIGEST_LSN_MOVED,
+            digests: Vec::new(),
+        }],
+        CtrlMsg::ResyncRequest { bucket, .. } => {
+            vec![resync_status(shipped_lsn, *bucket, DIGEST_LSN_MOVED)]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// DIGEST на запрос verify: digest бакетов источника (без LOCK, db/digest.rs), если его
+/// состояние — ровно отправленное: запрошенный LSN равен `shipped_lsn`, а WAL не вырос дальше
+/// `wal_end` (позиция отправленного) ни до, ни после подсчёта. Иначе — DIGEST_LSN_MOVED.
+pub fn digest_reply(
+    root: &Path,
+    wal_path: &Path,
+    shipped_lsn: u64,
+    wal_end: u64,
+    lsn: u64,
+    now: u32,
+    buckets: &[u32],
+) -> CtrlMsg {
+    let status_only = |status| CtrlMsg::Digest {
+        lsn: shipped_lsn,
+        status,
+        digests: Vec::new(),
+    };
+    let wal_len = || std::fs::metadata(wal_path).map(|m| m.len()).ok();
+    if lsn != shipped_lsn || wal_len() != Some(wal_end) {
+        return status_only(DIGEST_LSN_MOVED);
+    }
+    match bucket_digests_at(root, buckets, now) {
+        Ok(digests) if wal_len() == Some(wal_end) => CtrlMsg::Digest {
+            lsn,
+            status: DIGEST_OK,
+            digests,
+        },
+        Ok(_) => status_only(DIGEST_LSN_MOVED),
+        Err(e) => {
+            eprintln!("[WARN] CDC: bucket digests for verify: {:#}", e);
+            status_only(DIGEST_UNAVAILABLE)
+        }
+    }
+}
+
+/// RESYNC_PAGE* + RESYNC_END на запрос resync: состояние бакета источника (без LOCK,
+/// db/resync.rs) при тех же условиях, что у digest_reply; иначе — один RESYNC_END со статусом.
+pub fn resync_reply(
+    root: &Path,
+    wal_path: &Path,
+    shipped_lsn: u64,
+    wal_end: u64,
+    lsn: u64,
+    now: u32,
+    bucket: u32,
+) -> Vec<CtrlMsg> {
+    let wal_len = || std::fs::metadata(wal_path).map(|m| m.len()).ok();
+    if lsn != shipped_lsn || wal_len() != Some(wal_end) {
+        return vec![resync_status(shipped_lsn, bucket, DIGEST_LSN_MOVED)];
+    }
+    match bucket_state_at(root, bucket, now) {
+        Ok(mut state) if wal_len() == Some(wal_end) => {
+            // meta.last_lsn на диске при живом writer'е отстаёт — состояние снято на отправленном
+            state.lsn = lsn;
+            CtrlMsg::resync_frames(state)
+        }
+        Ok(_) => vec![resync_status(shipped_lsn, bucket, DIGEST_LSN_MOVED)],
+        Err(e) => {
+            eprintln!("[WARN] CDC: bucket {} state for resync: {:#}", bucket, e);
+            vec![resync_status(shipped_lsn, bucket, DIGEST_UNAVAILABLE)]
+        }
+    }
+}
+
+fn resync_status(lsn: u64, bucket: u32, status: u8) -> CtrlMsg {
+    CtrlMsg::ResyncEnd {
+        lsn,
+        status,
+        bucket,
+        head: NO_PAGE,
+        pages: 0,
+        digest: BucketDigest {
+            bucket,
+            live_keys: 0,
+            hash: 0,
+        },
+    }
+}
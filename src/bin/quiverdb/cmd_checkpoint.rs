@@ -3,13 +3,15 @@ use fs2::FileExt;
 use std::fs::OpenOptions;
 use std::path::PathBuf;
 
+use QuiverDB::config::QuiverConfig;
 use QuiverDB::meta::set_clean_shutdown;
-use QuiverDB::wal::Wal;
+use QuiverDB::wal::{index_wal_history, Wal};
 
 /// Выполнить WAL checkpoint: усечь WAL до заголовка под эксклюзивной блокировкой.
 /// Замечания:
 /// - Предпочтительно делать, когда нет активного writer’а.
 /// - Требует эксклюзивный lock (<root>/LOCK). Если lock занят — вернёт ошибку.
+/// - NEW: при P1_HISTORY_INDEX батчи WAL сначала индексируются в history.bin.
 pub fn exec(root: PathBuf) -> Result<()> {
     if !root.exists() {
         return Err(anyhow!("DB root does not exist: {}", root.display()));
@@ -28,6 +30,15 @@ pub fn exec(root: PathBuf) -> Result<()> {
 
     // 2) Усечение WAL до заголовка (идемпотентно)
     {
+        if QuiverConfig::from_env().history_index {
+            let rep = index_wal_history(&root).context("index WAL history")?;
+            if rep.entries > 0 {
+                println!(
+                    "checkpoint: indexed {} history entr(ies) from {} batch(es)",
+                    rep.entries, rep.batches
+                );
+            }
+        }
         let mut wal = Wal::open_for_append(&root)?;
         wal.truncate_to_header()?;
     }
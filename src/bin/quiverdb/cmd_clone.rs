@@ -0,0 +1,48 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use QuiverDB::db::clone::{clone_live, CloneOptions, CloneReport};
+
+/// CLI: clone
+/// - Источник может быть открыт writer'ом в другом процессе (copy + WAL catch-up).
+/// - Финальное переключение ждёт паузы writer'а (shared LOCK) не дольше --pause-wait-ms.
+pub fn exec(
+    src: PathBuf,
+    dst: PathBuf,
+    pause_wait_ms: u64,
+    max_rounds: u32,
+    verify: bool,
+    json: bool,
+) -> Result<()> {
+    let opts = CloneOptions {
+        pause_wait_ms,
+        max_rounds,
+        verify,
+        ..Default::default()
+    };
+    let rep = clone_live(&src, &dst, &opts)
+        .with_context(|| format!("clone {} -> {}", src.display(), dst.display()))?;
+    print_report(&rep, json);
+    Ok(())
+}
+
+fn print_report(rep: &CloneReport, json: bool) {
+    if json {
+        println!("{}", serde_json::to_string(rep).unwrap());
+        return;
+    }
+    println!("Clone {} -> {}:", rep.src, rep.dst);
+    println!("  rounds              = {}", rep.rounds);
+    println!("  files_copied        = {}", rep.files_copied);
+    println!("  bytes_copied        = {}", rep.bytes_copied);
+    println!("  files_reflinked     = {}", rep.files_reflinked);
+    println!("  wal_frames_captured = {}", rep.wal_frames_captured);
+    println!("  wal_bytes_captured  = {}", rep.wal_bytes_captured);
+    println!("  wal_rotations_seen  = {}", rep.wal_rotations_seen);
+    println!("  writer_paused       = {}", rep.writer_paused);
+    println!("  pause_ms            = {}", rep.pause_ms);
+    println!("  last_lsn            = {}", rep.last_lsn);
+    println!("  next_page_id        = {}", rep.next_page_id);
+    println!("  heads_checked       = {}", rep.heads_checked);
+    println!("  elapsed_ms          = {}", rep.elapsed_ms);
+}
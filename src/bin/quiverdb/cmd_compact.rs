@@ -4,11 +4,13 @@ use std::path::PathBuf;
 use QuiverDB::db::compaction::{CompactBucketReport, CompactSummary};
 use QuiverDB::db::Db;
 
+use crate::output::{emit, OutputFormat};
+
 /// CLI: compact
 /// - Если указан --bucket, компактуем один бакет.
 /// - Иначе — всю БД.
-/// - --json управляет форматом вывода.
-pub fn exec(path: PathBuf, bucket: Option<u32>, json: bool) -> Result<()> {
+/// - --output/--json управляют форматом вывода.
+pub fn exec(path: PathBuf, bucket: Option<u32>, fmt: OutputFormat) -> Result<()> {
     // Компактация — операция записи: нужен writer (эксклюзивный lock).
     let mut db =
         Db::open(&path).with_context(|| format!("open writer DB at {}", path.display()))?;
@@ -17,36 +19,20 @@ pub fn exec(path: PathBuf, bucket: Option<u32>, json: bool) -> Result<()> {
         let rep = db
             .compact_bucket(b)
             .with_context(|| format!("compact bucket {}", b))?;
-        print_bucket_report(&rep, json);
+        print_bucket_report(&rep, fmt)?;
     } else {
         let sum = db
             .compact_all()
             .with_context(|| format!("compact all buckets at {}", path.display()))?;
-        print_summary(&sum, json);
+        print_summary(&sum, fmt)?;
     }
 
     Ok(())
 }
 
-fn print_bucket_report(rep: &CompactBucketReport, json: bool) {
-    if json {
-        println!(
-            "{{\
-                \"bucket\":{},\
-                \"old_chain_len\":{},\
-                \"keys_kept\":{},\
-                \"keys_deleted\":{},\
-                \"pages_written\":{},\
-                \"new_head\":{}\
-            }}",
-            rep.bucket,
-            rep.old_chain_len,
-            rep.keys_kept,
-            rep.keys_deleted,
-            rep.pages_written,
-            rep.new_head
-        );
-        return;
+fn print_bucket_report(rep: &CompactBucketReport, fmt: OutputFormat) -> Result<()> {
+    if emit(fmt, rep)? {
+        return Ok(());
     }
 
     println!("Compaction (bucket {}):", rep.bucket);
@@ -55,27 +41,12 @@ fn print_bucket_report(rep: &CompactBucketReport, json: bool) {
     println!("  keys_deleted   = {}", rep.keys_deleted);
     println!("  pages_written  = {}", rep.pages_written);
     println!("  new_head       = {}", rep.new_head);
+    Ok(())
 }
 
-fn print_summary(sum: &CompactSummary, json: bool) {
-    if json {
-        println!(
-            "{{\
-                \"buckets_total\":{},\
-                \"buckets_compacted\":{},\
-                \"old_chain_len_sum\":{},\
-                \"keys_kept_sum\":{},\
-                \"keys_deleted_sum\":{},\
-                \"pages_written_sum\":{}\
-            }}",
-            sum.buckets_total,
-            sum.buckets_compacted,
-            sum.old_chain_len_sum,
-            sum.keys_kept_sum,
-            sum.keys_deleted_sum,
-            sum.pages_written_sum
-        );
-        return;
+fn print_summary(sum: &CompactSummary, fmt: OutputFormat) -> Result<()> {
+    if emit(fmt, sum)? {
+        return Ok(());
     }
 
     println!("Compaction summary:");
@@ -85,4 +56,5 @@ fn print_summary(sum: &CompactSummary, json: bool) {
     println!("  keys_kept_sum      = {}", sum.keys_kept_sum);
     println!("  keys_deleted_sum   = {}", sum.keys_deleted_sum);
     println!("  pages_written_sum  = {}", sum.pages_written_sum);
+    Ok(())
 }
@@ -0,0 +1,75 @@
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use std::path::PathBuf;
+
+use QuiverDB::config::{resolve_effective_config, Severity};
+use QuiverDB::meta::read_meta;
+
+/// CLI: config-show — эффективный конфиг с источником каждого поля и замечаниями validate().
+/// Ошибки конфигурации (Severity::Error) печатаются и дают ненулевой код выхода.
+pub fn exec(
+    path: Option<PathBuf>,
+    config: Option<PathBuf>,
+    set: Vec<String>,
+    json: bool,
+) -> Result<()> {
+    let mut overrides = Vec::with_capacity(set.len());
+    for s in &set {
+        let (k, v) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("--set expects field=value, got '{}'", s))?;
+        overrides.push((k.trim().to_string(), v.to_string()));
+    }
+    let eff = resolve_effective_config(config.as_deref(), &overrides)?;
+
+    // Производные значения для конкретной БД
+    let page_size = match &path {
+        Some(p) => Some(read_meta(p)?.page_size as u64),
+        None => None,
+    };
+    let page_cache_bytes = page_size.map(|ps| ps * eff.config.page_cache_pages as u64);
+    let has_errors = eff.issues.iter().any(|i| i.severity == Severity::Error);
+
+    if json {
+        let obj = json!({
+            "path": path.as_ref().map(|p| p.display().to_string()),
+            "config_file": config.as_ref().map(|p| p.display().to_string()),
+            "fields": eff.entries,
+            "page_size": page_size,
+            "page_cache_bytes": page_cache_bytes,
+            "issues": eff.issues,
+        });
+        println!("{}", serde_json::to_string_pretty(&obj)?);
+    } else {
+        println!("Effective config:");
+        for e in &eff.entries {
+            let value = if e.value.is_empty() { "-" } else { &e.value };
+            println!(
+                "  {:<26} = {:<24} [{}] ({})",
+                e.field,
+                value,
+                e.source.as_str(),
+                e.env
+            );
+        }
+        if let (Some(ps), Some(bytes)) = (page_size, page_cache_bytes) {
+            println!("  page_size                  = {}", ps);
+            println!("  page_cache_bytes           = {}", bytes);
+        }
+        if !eff.issues.is_empty() {
+            println!("Issues:");
+            for i in &eff.issues {
+                let sev = match i.severity {
+                    Severity::Warning => "WARN",
+                    Severity::Error => "ERROR",
+                };
+                println!("  [{}] {}: {}", sev, i.field, i.message);
+            }
+        }
+    }
+
+    if has_errors {
+        return Err(anyhow!("configuration has errors"));
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+
+use QuiverDB::db::{train_value_dictionary, Db, ZstdDictValues};
+use QuiverDB::meta::read_meta;
+
+#[derive(Serialize)]
+struct DictTrainReport {
+    samples: usize,
+    sample_bytes: u64,
+    dict_bytes: usize,
+    dict_crc32c: u32,
+    level: i32,
+    /// Оценка на выборке: сумма размеров значений, сжатых по одному без словаря / со словарём.
+    plain_zstd_bytes: u64,
+    dict_zstd_bytes: u64,
+    out: PathBuf,
+    /// Версия в value_dict.bin (None — БД без zstd_dict, словарь только в --out).
+    installed_version: Option<u32>,
+}
+
+/// CLI: dict-train — обучить словарь zstd на случайной выборке значений БД, записать его в --out
+/// и, если БД сжимает значения словарём (value transform zstd_dict), добавить новой версией
+/// в value_dict.bin (новые записи используют её после переоткрытия writer'а).
+pub fn exec(
+    path: PathBuf,
+    sample: usize,
+    out: PathBuf,
+    max_dict_kb: usize,
+    level: i32,
+    json: bool,
+) -> Result<()> {
+    if sample == 0 {
+        return Err(anyhow!("--sample must be > 0"));
+    }
+    let samples = {
+        let db = Db::open_ro(&path).with_context(|| format!("open RO DB at {}", path.display()))?;
+        db.sample_values(sample)?
+    };
+    if samples.is_empty() {
+        return Err(anyhow!("no values to sample at {}", path.display()));
+    }
+    let dict = train_value_dictionary(&samples, max_dict_kb.max(1) << 10)?;
+    std::fs::write(&out, &dict).with_context(|| format!("write {}", out.display()))?;
+
+    // Оценка выигрыша словаря на малых значениях
+    let mut rep = DictTrainReport {
+        samples: samples.len(),
+        sample_bytes: samples.iter().map(|s| s.len() as u64).sum(),
+        dict_bytes: dict.len(),
+        dict_crc32c: crc32c::crc32c(&dict),
+        level,
+        plain_zstd_bytes: 0,
+        dict_zstd_bytes: 0,
+        out,
+        installed_version: None,
+    };
+    let mut plain = zstd::bulk::Compressor::new(level)?;
+    let mut with_dict = zstd::bulk::Compressor::with_dictionary(level, &dict)?;
+    for s in &samples {
+        rep.plain_zstd_bytes += plain.compress(s)?.len() as u64;
+        rep.dict_zstd_bytes += with_dict.compress(s)?.len() as u64;
+    }
+
+    if read_meta(&path)?.value_transform.starts_with("zstd_dict:") {
+        rep.installed_version = Some(ZstdDictValues::add_version(&path, &dict, level)?);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string(&rep)?);
+        return Ok(());
+    }
+    println!(
+        "Trained {} byte dictionary on {} values ({} bytes) -> {}",
+        rep.dict_bytes,
+        rep.samples,
+        rep.sample_bytes,
+        rep.out.display()
+    );
+    println!(
+        "  per-value zstd level {}: {} bytes plain, {} bytes with dictionary",
+        level, rep.plain_zstd_bytes, rep.dict_zstd_bytes
+    );
+    match rep.installed_version {
+        Some(v) => println!(
+            "  installed as value dictionary version {} (new writes use it after the writer reopens)",
+            v
+        ),
+        None => println!(
+            "  DB does not compress values with a dictionary; use it for a new DB: quiverdb init --value-dict {}",
+            rep.out.display()
+        ),
+    }
+    Ok(())
+}
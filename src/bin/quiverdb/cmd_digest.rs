@@ -0,0 +1,109 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use QuiverDB::db::{BucketDigest, Db, DigestTree};
+use QuiverDB::util::now_secs;
+
+use crate::output::{emit, OutputFormat};
+
+#[derive(Serialize)]
+struct DigestOut {
+    path: String,
+    lsn: u64,
+    now: u32,
+    buckets: u32,
+    /// Корень дерева digest'ов (hex).
+    root: String,
+    live_keys: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bucket: Option<BucketDigest>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compare: Option<CompareOut>,
+}
+
+#[derive(Serialize)]
+struct CompareOut {
+    path: String,
+    lsn: u64,
+    root: String,
+    /// Бакеты с различающимся digest'ом (кандидаты на resync).
+    differing: Vec<u32>,
+}
+
+/// CLI: digest — digest'ы бакетов и корень дерева digest'ов БД (RO).
+/// bucket — показать digest одного бакета; compare — сверить с другой репликой (одинаковый now)
+/// и вывести различающиеся бакеты.
+pub fn exec(
+    path: PathBuf,
+    bucket: Option<u32>,
+    compare: Option<PathBuf>,
+    fmt: OutputFormat,
+) -> Result<()> {
+    let now = now_secs();
+    let tree = tree_of(&path, now)?;
+    let bucket = match bucket {
+        Some(b) => Some(*tree.leaves.get(b as usize).with_context(|| {
+            format!("bucket {} out of range (buckets={})", b, tree.leaves.len())
+        })?),
+        None => None,
+    };
+    let compare = match compare {
+        Some(other) => {
+            let t2 = tree_of(&other, now)?;
+            Some(CompareOut {
+                path: other.display().to_string(),
+                lsn: t2.lsn,
+                root: format!("{:016x}", t2.root()),
+                differing: tree.diff(&t2)?,
+            })
+        }
+        None => None,
+    };
+    let out = DigestOut {
+        path: path.display().to_string(),
+        lsn: tree.lsn,
+        now,
+        buckets: tree.leaves.len() as u32,
+        root: format!("{:016x}", tree.root()),
+        live_keys: tree.leaves.iter().map(|d| d.live_keys).sum(),
+        bucket,
+        compare,
+    };
+    if emit(fmt, &out)? {
+        return Ok(());
+    }
+
+    println!(
+        "digest {}: root={} lsn={} buckets={} live_keys={}",
+        out.path, out.root, out.lsn, out.buckets, out.live_keys
+    );
+    if let Some(d) = &out.bucket {
+        println!(
+            "  bucket {}: hash={:016x} live_keys={}",
+            d.bucket, d.hash, d.live_keys
+        );
+    }
+    if let Some(c) = &out.compare {
+        println!("digest {}: root={} lsn={}", c.path, c.root, c.lsn);
+        if c.differing.is_empty() {
+            println!("replicas are identical");
+        } else {
+            println!(
+                "{} bucket(s) differ: {}",
+                c.differing.len(),
+                c.differing
+                    .iter()
+                    .map(|b| b.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn tree_of(path: &Path, now: u32) -> Result<DigestTree> {
+    let db = Db::open_ro(path).with_context(|| format!("open RO DB at {}", path.display()))?;
+    db.digest_tree_at(now)
+}
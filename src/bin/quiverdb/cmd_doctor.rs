@@ -3,7 +3,21 @@ use std::path::PathBuf;
 
 use QuiverDB::db::Db;
 
-pub fn exec(path: PathBuf, json: bool) -> Result<()> {
-    let db = Db::open_ro(&path)?;
-    db.doctor(json)
+use crate::output::{emit, OutputFormat};
+
+pub fn exec(path: PathBuf, fix_alloc: bool, fmt: OutputFormat) -> Result<()> {
+    let rep = if fix_alloc {
+        // Ремонт — writer; в отчёте alloc — находки до пересборки + итог ремонта
+        let mut db = Db::open(&path)?;
+        let alloc = db.alloc_check_fix()?;
+        let mut rep = db.doctor_report()?;
+        rep.alloc = alloc;
+        rep
+    } else {
+        Db::open_ro(&path)?.doctor_report()?
+    };
+    if !emit(fmt, &rep)? {
+        rep.print_text();
+    }
+    Ok(())
 }
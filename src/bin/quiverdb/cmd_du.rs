@@ -0,0 +1,16 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+
+use crate::output::{emit, OutputFormat};
+
+/// CLI: du — разбивка занятого места (Db::space_usage) на RO-хэндле.
+pub fn exec(path: PathBuf, top: usize, fmt: OutputFormat) -> Result<()> {
+    let db = Db::open_ro(&path).with_context(|| format!("open RO DB at {}", path.display()))?;
+    let rep = db.space_usage(top)?;
+    if !emit(fmt, &rep)? {
+        rep.print_text();
+    }
+    Ok(())
+}
@@ -3,14 +3,24 @@ use std::path::PathBuf;
 
 use QuiverDB::db::Db;
 
+use super::exit_code::{CliError, ErrorKind};
+
 /// CLI: exists — быстрый presence‑check (использует Bloom fast‑path, если фильтр свежий).
-pub fn exec(path: PathBuf, key: String) -> Result<()> {
+/// Отсутствие ключа — код выхода 3 (not_found); в режиме --output json — только объект ошибки.
+pub fn exec(path: PathBuf, key: String, json: bool) -> Result<()> {
     let db = Db::open_ro(&path)?;
     let present = db.exists(key.as_bytes())?;
     if present {
         println!("FOUND '{}'", key);
     } else {
-        println!("NOT FOUND '{}'", key);
+        if !json {
+            println!("NOT FOUND '{}'", key);
+        }
+        return Err(
+            CliError::new(ErrorKind::NotFound, format!("key '{}' not found", key))
+                .with_path(&path)
+                .into(),
+        );
     }
     Ok(())
 }
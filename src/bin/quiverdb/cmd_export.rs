@@ -0,0 +1,39 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use QuiverDB::export::{export_sorted_to_path, ExportOptions};
+use QuiverDB::Db;
+
+/// CLI: export-sorted — дамп живых ключей в порядке ключа (файл или stdout: `--out -`).
+/// При выводе в stdout отчёт печатается в stderr, чтобы не смешиваться с дампом.
+pub fn exec(
+    path: PathBuf,
+    out: PathBuf,
+    mem_mb: usize,
+    tmp_dir: Option<PathBuf>,
+    prefix: Option<String>,
+    json: bool,
+) -> Result<()> {
+    let opts = ExportOptions {
+        mem_budget_bytes: mem_mb.max(1) << 20,
+        tmp_dir,
+        prefix: prefix.map(|p| p.into_bytes()),
+    };
+    let db = Db::open_ro(&path).with_context(|| format!("open RO DB at {}", path.display()))?;
+    let rep = export_sorted_to_path(&db, &out, &opts)
+        .with_context(|| format!("export {} -> {}", path.display(), out.display()))?;
+    let text = if json {
+        serde_json::to_string(&rep)?
+    } else {
+        format!(
+            "Export (sorted):\n  keys          = {}\n  payload_bytes = {}\n  runs_spilled  = {}\n  bytes_written = {}",
+            rep.keys, rep.payload_bytes, rep.runs_spilled, rep.bytes_written
+        )
+    };
+    if out.as_os_str() == "-" {
+        eprintln!("{}", text);
+    } else {
+        println!("{}", text);
+    }
+    Ok(())
+}
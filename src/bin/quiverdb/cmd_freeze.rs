@@ -0,0 +1,122 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::{Db, FreezeInfo};
+
+use crate::exit_code::{CliError, ErrorKind};
+use crate::output::{emit, OutputFormat};
+
+#[derive(Serialize)]
+struct FreezeReport {
+    path: String,
+    frozen: FreezeInfo,
+    frozen_ms: u64,
+    /// "exec" | "stdin" | "timeout"
+    thawed_by: String,
+    exec_status: Option<i32>,
+}
+
+/// CLI: freeze — заморозить writer на время снапшота тома, затем thaw.
+/// --exec CMD — выполнить под заморозкой; иначе ждать Enter/EOF в stdin. --timeout — страховка:
+/// по истечении thaw выполняется в любом случае (команда --exec завершается kill).
+pub fn exec(path: PathBuf, timeout: u64, exec: Option<String>, fmt: OutputFormat) -> Result<()> {
+    let cfg = QuiverConfig::from_env();
+    let mut db = Db::open_with_config(&path, cfg)
+        .with_context(|| format!("open writer DB at {}", path.display()))?;
+    let info = db.freeze()?;
+    let t0 = Instant::now();
+    let limit = (timeout > 0).then(|| Duration::from_secs(timeout));
+    if !fmt.is_json() {
+        eprintln!(
+            "[INFO] frozen {} at lsn={} ({} segments synced){}",
+            path.display(),
+            info.lsn,
+            info.segments_synced,
+            match (&exec, limit) {
+                (Some(_), _) => String::new(),
+                (None, Some(_)) => format!("; press Enter to thaw (auto-thaw in {}s)", timeout),
+                (None, None) => "; press Enter to thaw".to_string(),
+            }
+        );
+    }
+
+    let (thawed_by, exec_status) = match &exec {
+        Some(cmd) => {
+            let mut child = Command::new("sh")
+                .arg("-c")
+                .arg(cmd)
+                .env("QDB_FREEZE_ROOT", &path)
+                .env("QDB_FREEZE_LSN", info.lsn.to_string())
+                .spawn()
+                .with_context(|| format!("spawn '{}'", cmd))?;
+            loop {
+                if let Some(st) = child.try_wait()? {
+                    break ("exec", Some(st.code().unwrap_or(-1)));
+                }
+                if limit.is_some_and(|l| t0.elapsed() >= l) {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    break ("timeout", None);
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        }
+        None => {
+            let (tx, rx) = mpsc::channel();
+            std::thread::spawn(move || {
+                let mut line = String::new();
+                let _ = std::io::stdin().read_line(&mut line);
+                let _ = tx.send(());
+            });
+            let got = match limit {
+                Some(l) => rx.recv_timeout(l).is_ok(),
+                None => rx.recv().is_ok(),
+            };
+            (if got { "stdin" } else { "timeout" }, None)
+        }
+    };
+
+    db.thaw()?;
+    let rep = FreezeReport {
+        path: path.display().to_string(),
+        frozen: info,
+        frozen_ms: t0.elapsed().as_millis() as u64,
+        thawed_by: thawed_by.to_string(),
+        exec_status,
+    };
+    drop(db);
+
+    if !emit(fmt, &rep)? {
+        if rep.thawed_by == "timeout" {
+            eprintln!("[WARN] freeze timeout ({}s) reached, thawed", timeout);
+        }
+        println!(
+            "thawed {} after {} ms (lsn={}, by {})",
+            rep.path, rep.frozen_ms, rep.frozen.lsn, rep.thawed_by
+        );
+    }
+
+    if rep.thawed_by == "timeout" && exec.is_some() {
+        return Err(CliError::new(
+            ErrorKind::Error,
+            format!(
+                "--exec did not finish within {}s; killed and thawed",
+                timeout
+            ),
+        )
+        .into());
+    }
+    if let Some(code) = rep.exec_status.filter(|c| *c != 0) {
+        return Err(CliError::new(
+            ErrorKind::Error,
+            format!("--exec exited with status {} (DB thawed)", code),
+        )
+        .into());
+    }
+    Ok(())
+}
@@ -5,9 +5,11 @@ use std::path::PathBuf;
 
 use QuiverDB::db::Db;
 
+use super::exit_code::{CliError, ErrorKind};
 use super::util::{display_text, hex_dump};
 
-pub fn exec(path: PathBuf, key: String, out: Option<PathBuf>) -> Result<()> {
+/// `json` — режим --output json: промах сообщается только объектом ошибки (код 3).
+pub fn exec(path: PathBuf, key: String, out: Option<PathBuf>, json: bool) -> Result<()> {
     let db = Db::open_ro(&path)?;
     match db.get(key.as_bytes())? {
         Some(v) => {
@@ -36,7 +38,17 @@ pub fn exec(path: PathBuf, key: String, out: Option<PathBuf>) -> Result<()> {
                 println!("hex:  {}", hex_dump(&v[..v.len().min(64)]));
             }
         }
-        None => println!("NOT FOUND '{}'", key),
+        None => {
+            if !json {
+                println!("NOT FOUND '{}'", key);
+            }
+            // код выхода 3 (not_found) — отличим от ошибок БД
+            return Err(
+                CliError::new(ErrorKind::NotFound, format!("key '{}' not found", key))
+                    .with_path(&path)
+                    .into(),
+            );
+        }
     }
     Ok(())
 }
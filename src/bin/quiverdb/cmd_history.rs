@@ -0,0 +1,66 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+use QuiverDB::wal::HistoryEntry;
+
+use crate::output::{emit, OutputFormat};
+
+#[derive(Serialize)]
+struct HistoryOut {
+    key: String,
+    /// Всего изменений (до --limit).
+    total: usize,
+    entries: Vec<HistoryEntry>,
+}
+
+/// CLI: history — когда и каким батчем менялся ключ (history.bin + текущий WAL), RO.
+/// limit — показать последние N изменений.
+pub fn exec(path: PathBuf, key: String, limit: Option<usize>, fmt: OutputFormat) -> Result<()> {
+    let db = Db::open_ro(&path).with_context(|| format!("open RO DB at {}", path.display()))?;
+    let mut entries = db.key_history(key.as_bytes())?;
+    let total = entries.len();
+    if let Some(n) = limit {
+        entries.drain(..total.saturating_sub(n));
+    }
+    let out = HistoryOut {
+        key,
+        total,
+        entries,
+    };
+    if emit(fmt, &out)? {
+        return Ok(());
+    }
+
+    if out.total == 0 {
+        println!(
+            "history '{}': no changes recorded (enable P1_HISTORY_INDEX to keep history past WAL truncation)",
+            out.key
+        );
+        return Ok(());
+    }
+    println!(
+        "history '{}': {} change(s){}",
+        out.key,
+        out.total,
+        if out.entries.len() < out.total {
+            format!(", showing last {}", out.entries.len())
+        } else {
+            String::new()
+        }
+    );
+    for e in &out.entries {
+        println!(
+            "  lsn={} batch={} commit_ms={} op={} page={} value_len={}{}",
+            e.lsn,
+            e.batch_lsn,
+            e.commit_unix_ms,
+            e.op.as_str(),
+            e.page_id,
+            e.value_len,
+            if e.indexed { "" } else { " (wal)" }
+        );
+    }
+    Ok(())
+}
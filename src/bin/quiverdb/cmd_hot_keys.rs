@@ -0,0 +1,40 @@
+use anyhow::{anyhow, Result};
+use std::path::PathBuf;
+
+use QuiverDB::db::hotkeys::{read_hot_prefixes, HOT_PREFIXES_FILE};
+
+/// CLI: hot-keys — топ префиксов ключей из профиля, сохранённого writer'ом при закрытии
+/// (профайлер включается P1_HOT_PREFIX_SAMPLE=N в процессе-владельце БД).
+pub fn exec(path: PathBuf, top: usize, json: bool) -> Result<()> {
+    let mut rep = read_hot_prefixes(&path)?.ok_or_else(|| {
+        anyhow!(
+            "no hot-prefix profile at {} (run the writer with P1_HOT_PREFIX_SAMPLE=N)",
+            path.join(HOT_PREFIXES_FILE).display()
+        )
+    })?;
+    rep.prefixes.truncate(top);
+    if json {
+        println!("{}", serde_json::to_string(&rep)?);
+        return Ok(());
+    }
+    println!(
+        "Hot key prefixes (prefix_len={}, sample 1/{}, ops_seen={}, ops_sampled={}):",
+        rep.prefix_len, rep.sample_every, rep.ops_seen, rep.ops_sampled
+    );
+    println!(
+        "  {:>4}  {:>12}  {:>12}  {:>12}  prefix",
+        "#", "total", "reads", "writes"
+    );
+    for (i, p) in rep.prefixes.iter().enumerate() {
+        println!(
+            "  {:>4}  {:>12}  {:>12}  {:>12}  {:?} (hex {})",
+            i + 1,
+            p.total(),
+            p.reads,
+            p.writes,
+            p.prefix,
+            p.prefix_hex
+        );
+    }
+    Ok(())
+}
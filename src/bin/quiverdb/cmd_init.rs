@@ -1,11 +1,37 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::path::PathBuf;
 
-use QuiverDB::db::Db;
-use QuiverDB::dir::Directory;
-use QuiverDB::meta::read_meta;
+use QuiverDB::db::{builtin_key_transform, Db, ValueTransform, ZstdDictValues};
+use QuiverDB::dir::routing::parse_delimiter;
+use QuiverDB::dir::{BucketRouting, Directory};
+use QuiverDB::meta::{read_meta, record_key_transform, record_value_transform};
 
-pub fn exec(path: PathBuf, page_size: u32, buckets: u32) -> Result<()> {
+pub fn exec(
+    path: PathBuf,
+    page_size: u32,
+    buckets: u32,
+    bucket_key_delimiter: Option<String>,
+    bucket_key_prefix_len: Option<u16>,
+    key_transform: Option<String>,
+    value_dict: Option<PathBuf>,
+) -> Result<()> {
+    // NEW: встроенная трансформация ключей (пользовательские — через DbBuilder::key_transform)
+    if let Some(id) = key_transform.as_deref() {
+        if builtin_key_transform(id).is_none() {
+            return Err(anyhow!(
+                "unknown key transform {:?} (expected lowercase | strip_namespace:<hex> | hash_long:<N>)",
+                id
+            ));
+        }
+    }
+    // NEW: маршрутизация ключей в бакеты — флаги поверх ENV (P1_BUCKET_KEY_*)
+    let mut routing = BucketRouting::from_env()?;
+    if let Some(d) = bucket_key_delimiter.as_deref() {
+        routing.delimiter = parse_delimiter(d)?;
+    }
+    if let Some(n) = bucket_key_prefix_len {
+        routing.prefix_len = n;
+    }
     if !path.exists() {
         std::fs::create_dir_all(&path)?;
     }
@@ -18,6 +44,28 @@ pub fn exec(path: PathBuf, page_size: u32, buckets: u32) -> Result<()> {
                 m.page_size, page_size, m.page_size
             );
         }
+        if let Some(id) = key_transform.as_deref() {
+            if m.key_transform != id {
+                eprintln!(
+                    "warning: DB already initialized with key transform {:?}, requested {:?} (keeping it)",
+                    m.key_transform, id
+                );
+            }
+        }
+        if value_dict.is_some() {
+            eprintln!(
+                "warning: DB already initialized, --value-dict ignored (value transform {:?})",
+                m.value_transform
+            );
+        }
+        if m.bucket_routing() != routing {
+            eprintln!(
+                "warning: DB already initialized with bucket routing {}, requested {} (keeping {})",
+                m.bucket_routing(),
+                routing,
+                m.bucket_routing()
+            );
+        }
         match Directory::open(&path) {
             Ok(_) => println!("DB already initialized at {}", path.display()),
             Err(_) => {
@@ -31,7 +79,31 @@ pub fn exec(path: PathBuf, page_size: u32, buckets: u32) -> Result<()> {
         }
         return Ok(());
     }
-    Db::init(&path, page_size, buckets)?;
-    println!("Initialized DB at {}", path.display());
+    Db::init_with_routing(&path, page_size, buckets, routing)?;
+    if let Some(id) = key_transform.as_deref() {
+        record_key_transform(&path, id)?;
+        println!("Key transform: {}", id);
+    }
+    // NEW: словарь zstd для значений — копия в БД, id с CRC словаря в meta
+    if let Some(dict_path) = value_dict.as_deref() {
+        let dict = std::fs::read(dict_path)
+            .map_err(|e| anyhow!("read value dictionary {}: {}", dict_path.display(), e))?;
+        let t = ZstdDictValues::from_dictionary(&path, &dict, 3)?;
+        record_value_transform(&path, &t.id())?;
+        println!(
+            "Value transform: {} ({} bytes dictionary)",
+            t.id(),
+            t.dict_len()
+        );
+    }
+    if routing.is_full_key() {
+        println!("Initialized DB at {}", path.display());
+    } else {
+        println!(
+            "Initialized DB at {} (bucket routing: {})",
+            path.display(),
+            routing
+        );
+    }
     Ok(())
 }
@@ -1,14 +1,22 @@
 use anyhow::{Context, Result};
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use QuiverDB::db::Db;
+use QuiverDB::backup::BackupCompression;
+use QuiverDB::backup_sched::{open_backup_target, BackupSchedule, BackupScheduler};
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::{Db, MaintSchedule, MaintScheduler, MaintTickReport};
+
+use crate::cli::OffsiteBackupArgs;
+use crate::exit_code::{CliError, ErrorKind};
+use crate::output::{emit, OutputFormat};
 
 /// CLI: auto-maintenance — компактация ограниченного числа бакетов + опциональный sweep сиротских OVERFLOW.
 ///
 /// Примеры:
 ///   quiverdb auto-maint --path ./db --max-buckets 32 --sweep
 ///   quiverdb auto-maint --path ./db --max-buckets 16 --json
-pub fn exec(path: PathBuf, max_buckets: u32, do_sweep: bool, json: bool) -> Result<()> {
+pub fn exec(path: PathBuf, max_buckets: u32, do_sweep: bool, fmt: OutputFormat) -> Result<()> {
     let mut db =
         Db::open(&path).with_context(|| format!("open writer DB at {}", path.display()))?;
 
@@ -16,19 +24,7 @@ pub fn exec(path: PathBuf, max_buckets: u32, do_sweep: bool, json: bool) -> Resu
         .auto_maintenance(max_buckets, do_sweep)
         .with_context(|| "auto_maintenance")?;
 
-    if json {
-        println!(
-            "{{\
-                \"buckets_scanned\":{},\
-                \"buckets_compacted\":{},\
-                \"pages_written_sum\":{},\
-                \"overflow_pages_freed\":{}\
-            }}",
-            sum.buckets_scanned,
-            sum.buckets_compacted,
-            sum.pages_written_sum,
-            sum.overflow_pages_freed
-        );
+    if emit(fmt, &sum)? {
         return Ok(());
     }
 
@@ -44,3 +40,141 @@ pub fn exec(path: PathBuf, max_buckets: u32, do_sweep: bool, json: bool) -> Resu
 
     Ok(())
 }
+
+/// CLI: maint-daemon — планировщик обслуживания поверх writer'а (db/maint_sched.rs).
+/// Снапшотные параметры без флагов берутся из конфига (ENV P1_SNAPSHOT_INTERVAL_SECS /
+/// P1_SNAPSHOT_RETENTION). --once — выполнить созревшие задачи и выйти.
+/// --backup-to подключает offsite-бэкапы (backup_sched.rs) к тем же тикам.
+#[allow(clippy::too_many_arguments)]
+pub fn exec_daemon(
+    path: PathBuf,
+    interval: u64,
+    max_buckets: u32,
+    sweep: bool,
+    snapshot_interval: Option<u64>,
+    snapshot_retention: Option<u32>,
+    once: bool,
+    offsite: OffsiteBackupArgs,
+    fmt: OutputFormat,
+) -> Result<()> {
+    let cfg = QuiverConfig::from_env();
+    let mut sched = MaintSchedule::from_config(&cfg);
+    sched.maint_interval_secs = interval;
+    sched.max_buckets = max_buckets;
+    sched.sweep = sweep;
+    if let Some(s) = snapshot_interval {
+        sched.snapshot_interval_secs = s;
+    }
+    if let Some(n) = snapshot_retention {
+        sched.snapshot_retention = n;
+    }
+    if sched.is_empty() && offsite.backup_to.is_none() {
+        return Err(CliError::new(
+            ErrorKind::Usage,
+            "nothing to schedule: set --interval, --snapshot-interval (P1_SNAPSHOT_INTERVAL_SECS) or --backup-to",
+        )
+        .into());
+    }
+
+    let mut db = Db::open_with_config(&path, cfg)
+        .with_context(|| format!("open writer DB at {}", path.display()))?;
+    let mut sch = MaintScheduler::new(&path, sched.clone())?;
+    if let Some(spec) = &offsite.backup_to {
+        let bs = BackupSchedule {
+            interval_secs: offsite.backup_interval,
+            full_every: offsite.backup_full_every,
+            keep_daily: offsite.backup_keep_daily,
+            keep_weekly: offsite.backup_keep_weekly,
+            restore_test_interval_secs: offsite.restore_test_interval,
+            compress: match &offsite.backup_compress {
+                Some(c) => c.parse::<BackupCompression>()?,
+                None => BackupCompression::None,
+            },
+            include_sidecars: true,
+        };
+        let target = open_backup_target(spec)?;
+        eprintln!(
+            "[INFO] maint-daemon: offsite backups to {} every {}s (full every {}, keep {} daily / {} weekly, restore-test every {}s)",
+            target.describe(),
+            bs.interval_secs,
+            bs.full_every,
+            bs.keep_daily,
+            bs.keep_weekly,
+            bs.restore_test_interval_secs
+        );
+        sch = sch.with_backup(
+            BackupScheduler::new(target, bs)
+                .with_context(|| format!("read backup catalog at {}", spec))?,
+        );
+    }
+    eprintln!(
+        "[INFO] maint-daemon: {} (maint every {}s, max_buckets={}, sweep={}; snapshot every {}s, retention={})",
+        path.display(),
+        sched.maint_interval_secs,
+        sched.max_buckets,
+        sched.sweep,
+        sched.snapshot_interval_secs,
+        sched.snapshot_retention
+    );
+
+    loop {
+        let rep = sch.tick(&mut db)?;
+        print_tick(&rep, fmt)?;
+        if once {
+            return Ok(());
+        }
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let wait = sch
+            .next_due_in(now_ms)
+            .unwrap_or(Duration::from_secs(60))
+            .clamp(Duration::from_millis(100), Duration::from_secs(60));
+        std::thread::sleep(wait);
+    }
+}
+
+fn print_tick(rep: &MaintTickReport, fmt: OutputFormat) -> Result<()> {
+    let idle = rep.auto_maint.is_none()
+        && rep.snapshot_created.is_none()
+        && rep.snapshots_pruned.is_empty()
+        && rep.offsite.is_none()
+        && rep.errors.is_empty();
+    if idle || emit(fmt, rep)? {
+        return Ok(());
+    }
+    if let Some((compacted, freed)) = rep.auto_maint {
+        println!(
+            "maint: buckets_compacted={} overflow_pages_freed={}",
+            compacted, freed
+        );
+    }
+    if let Some(id) = &rep.snapshot_created {
+        println!("snapshot: id={}", id);
+    }
+    for id in &rep.snapshots_pruned {
+        println!("snapshot pruned: id={}", id);
+    }
+    if let Some(o) = &rep.offsite {
+        if let Some(b) = &o.backup {
+            println!(
+                "backup: {} {} lsn={} bytes={} sha256={}",
+                b.kind, b.name, b.lsn, b.bytes, b.sha256
+            );
+        }
+        for n in &o.pruned {
+            println!("backup pruned: {}", n);
+        }
+        if let Some(t) = &o.restore_test {
+            println!(
+                "restore-test: {} ok (chain_len={}, lsn={}, {} ms)",
+                t.name, t.chain_len, t.lsn, t.elapsed_ms
+            );
+        }
+    }
+    for e in &rep.errors {
+        eprintln!("[ERROR] maint-daemon: {}", e);
+    }
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// CLI: mount — read-only FUSE-представление ключей до отмонтирования (см. QuiverDB::kvfs).
+#[cfg(all(feature = "fuse", target_os = "linux"))]
+pub fn exec(
+    path: PathBuf,
+    mountpoint: PathBuf,
+    prefix: Option<String>,
+    delimiter: String,
+) -> Result<()> {
+    use anyhow::{anyhow, Context};
+    use QuiverDB::kvfs::fuse::KvMount;
+    use QuiverDB::kvfs::{KvFsOptions, KvView};
+
+    let delimiter = match delimiter.as_bytes() {
+        [d] => *d,
+        _ => return Err(anyhow!("--delimiter must be a single ASCII character")),
+    };
+    QuiverDB::meta::read_meta(&path)
+        .with_context(|| format!("not a QuiverDB root: {}", path.display()))?;
+    let opts = KvFsOptions {
+        delimiter,
+        prefix: prefix.map(String::into_bytes),
+    };
+    let view = KvView::open(&path, &opts)?;
+    let st = view.stats();
+    let mount = KvMount::mount(view, &mountpoint)?;
+    mount.unmount_on_signals()?;
+    println!(
+        "mount: {} keys in {} dirs at {} (read-only, lsn={}); umount or Ctrl-C to stop",
+        st.files,
+        st.dirs,
+        mountpoint.display(),
+        st.lsn
+    );
+    if st.skipped > 0 {
+        println!(
+            "mount: {} keys skipped (path component longer than 255 bytes)",
+            st.skipped
+        );
+    }
+    mount.serve()
+}
+
+#[cfg(all(feature = "fuse", not(target_os = "linux")))]
+pub fn exec(
+    _path: PathBuf,
+    _mountpoint: PathBuf,
+    _prefix: Option<String>,
+    _delimiter: String,
+) -> Result<()> {
+    Err(anyhow::anyhow!("mount is only supported on Linux"))
+}
+
+#[cfg(not(feature = "fuse"))]
+pub fn exec(
+    _path: PathBuf,
+    _mountpoint: PathBuf,
+    _prefix: Option<String>,
+    _delimiter: String,
+) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "mount is not available: rebuild with `--features fuse`"
+    ))
+}
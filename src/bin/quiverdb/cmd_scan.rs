@@ -5,10 +5,20 @@ use QuiverDB::db::Db;
 
 use super::util::{display_text, to_hex};
 
-pub fn exec(path: PathBuf, prefix: Option<String>, json: bool, stream: bool) -> Result<()> {
+pub fn exec(
+    path: PathBuf,
+    prefix: Option<String>,
+    json: bool,
+    stream: bool,
+    keys_only: bool,
+) -> Result<()> {
     let db = Db::open_ro(&path)?;
     let pref_bytes = prefix.as_ref().map(|s| s.as_bytes());
 
+    if keys_only {
+        return exec_keys_only(&db, pref_bytes, json, stream);
+    }
+
     if stream {
         if json {
             db.scan_stream(pref_bytes, |k, v| {
@@ -72,3 +82,86 @@ pub fn exec(path: PathBuf, prefix: Option<String>, json: bool, stream: bool) ->
 
     Ok(())
 }
+
+/// --keys-only: ключи без значений (Db::scan_keys_stream).
+fn exec_keys_only(db: &Db, prefix: Option<&[u8]>, json: bool, stream: bool) -> Result<()> {
+    let print_line = |k: &[u8]| {
+        if json {
+            println!("{{\"key_hex\":\"{}\",\"key_len\":{}}}", to_hex(k), k.len());
+        } else {
+            println!("key='{}' ({} B)", display_text(k), k.len());
+        }
+    };
+    if stream {
+        return db.scan_keys_stream(prefix, print_line);
+    }
+
+    let keys = db.scan_keys(prefix)?;
+    if json {
+        print!("[");
+        for (i, k) in keys.iter().enumerate() {
+            if i > 0 {
+                print!(",");
+            }
+            print!("{{\"key_hex\":\"{}\",\"key_len\":{}}}", to_hex(k), k.len());
+        }
+        println!("]");
+    } else if keys.is_empty() {
+        println!("(no items)");
+    } else {
+        for k in &keys {
+            print_line(k);
+        }
+    }
+    Ok(())
+}
+
+/// --limit [--cursor]: одна страница Db::scan_page и курсор следующей.
+pub fn exec_page(
+    path: PathBuf,
+    prefix: Option<String>,
+    json: bool,
+    limit: usize,
+    cursor: Option<String>,
+) -> Result<()> {
+    let db = Db::open_ro(&path)?;
+    let pref_bytes = prefix.as_ref().map(|s| s.as_bytes());
+    let (items, next) = db.scan_page(pref_bytes, cursor.as_deref(), limit)?;
+
+    if json {
+        let items: Vec<serde_json::Value> = items
+            .iter()
+            .map(|(k, v)| {
+                serde_json::json!({
+                    "key_hex": to_hex(k),
+                    "value_hex": to_hex(v),
+                    "key_len": k.len(),
+                    "value_len": v.len(),
+                })
+            })
+            .collect();
+        println!(
+            "{}",
+            serde_json::json!({ "items": items, "next_cursor": next })
+        );
+        return Ok(());
+    }
+
+    if items.is_empty() {
+        println!("(no items)");
+    }
+    for (k, v) in &items {
+        println!(
+            "key='{}' ({} B) -> value '{}' ({} B)",
+            display_text(k),
+            k.len(),
+            display_text(v),
+            v.len()
+        );
+    }
+    match next {
+        Some(c) => println!("next_cursor = {}", c),
+        None => println!("next_cursor = (end)"),
+    }
+    Ok(())
+}
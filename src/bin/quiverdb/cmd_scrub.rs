@@ -0,0 +1,73 @@
+use anyhow::{anyhow, Context, Result};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use QuiverDB::db::scrub::{scrub_step_at, ScrubOptions, ScrubReport};
+
+/// CLI: scrub
+/// - Каждый шаг открывает БД заново (RO), чтобы continuous-режим видел рост next_page_id.
+/// - NEW: RO-хэндл без shared LOCK (scrub_step_at) — scrub не блокирует открытие writer'а.
+/// - Без --continuous: один шаг; при найденных сбоях возвращается ошибка (exit code 1).
+pub fn exec(
+    path: PathBuf,
+    rate_mb: u64,
+    max_pages: u64,
+    continuous: bool,
+    interval_secs: u64,
+    reset: bool,
+    json: bool,
+) -> Result<()> {
+    let opts = ScrubOptions {
+        max_bytes_per_sec: rate_mb.saturating_mul(1024 * 1024),
+        max_pages,
+        ..Default::default()
+    };
+
+    let mut need_reset = reset;
+    loop {
+        let rep = scrub_step_at(&path, opts.clone(), need_reset)
+            .with_context(|| format!("scrub DB at {}", path.display()))?;
+        need_reset = false;
+        print_report(&rep, json);
+
+        if !continuous {
+            let failures = rep.integrity_fail + rep.io_fail;
+            if failures > 0 {
+                return Err(anyhow!(
+                    "scrub found {} failing page(s) (integrity={}, io={})",
+                    failures,
+                    rep.integrity_fail,
+                    rep.io_fail
+                ));
+            }
+            return Ok(());
+        }
+
+        if rep.pass_completed {
+            std::thread::sleep(Duration::from_secs(interval_secs));
+        }
+    }
+}
+
+fn print_report(rep: &ScrubReport, json: bool) {
+    if json {
+        println!("{}", serde_json::to_string(rep).unwrap());
+        return;
+    }
+    for a in &rep.alerts {
+        eprintln!(
+            "scrub alert: page={} seg={} kind={} error={}",
+            a.page_id, a.seg_no, a.kind, a.error
+        );
+    }
+    println!("Scrub step (mode={}):", rep.mode);
+    println!("  pages_total      = {}", rep.pages_total);
+    println!("  pages_scanned    = {}", rep.pages_scanned);
+    println!("  ok_pages         = {}", rep.ok_pages);
+    println!("  integrity_fail   = {}", rep.integrity_fail);
+    println!("  io_fail          = {}", rep.io_fail);
+    println!("  cursor           = {}", rep.cursor);
+    println!("  pass_completed   = {}", rep.pass_completed);
+    println!("  passes_completed = {}", rep.passes_completed);
+    println!("  elapsed_ms       = {}", rep.elapsed_ms);
+}
@@ -1,59 +1,149 @@
 use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
 use std::path::PathBuf;
 
+use QuiverDB::config::QuiverConfig;
 use QuiverDB::db::Db;
-use QuiverDB::snapstore::{list_manifests, manifest_path, read_manifest, SnapshotManager};
+use QuiverDB::snapstore::{
+    list_manifests, manifest_path, manifest_signature_status, read_manifest, verify_snapshot,
+    ManifestSignatureError, SignatureStatus, SnapshotManager, VerifyOptions,
+};
 
-/// Создать persisted‑снапшот и вывести id/путь.
+use crate::exit_code::{CliError, ErrorKind};
+use crate::output::{emit, OutputFormat};
+
+/// Отчёт snapshot-create.
+#[derive(Serialize)]
+struct SnapshotCreated {
+    id: String,
+    manifest: String,
+    /// KID подписи (None — манифест не подписан).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signed_kid: Option<String>,
+}
+
+/// Строка snapshot-list.
+#[derive(Serialize)]
+struct SnapshotListEntry {
+    id: String,
+    /// unsigned | valid | untrusted | invalid
+    signature: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+}
+
+/// Создать persisted‑снапшот и вывести id/путь. sign_kid — подписать манифест ключом KID
+/// (иначе — P1_SNAPSHOT_SIGN_KID).
 pub fn exec_create(
     path: PathBuf,
     message: Option<String>,
     labels: Vec<String>,
     parent: Option<String>,
+    with_sidecars: bool,
+    sign_kid: Option<String>,
+    fmt: OutputFormat,
 ) -> Result<()> {
+    let mut cfg = QuiverConfig::from_env();
+    if sign_kid.is_some() {
+        cfg.snapshot_sign_kid = sign_kid;
+    }
+    let signed_kid = cfg.snapshot_sign_kid.clone();
     // Откроем БД в RO-режиме: снимок не требует writer'а
-    let db = Db::open_ro(&path).with_context(|| format!("open RO DB at {}", path.display()))?;
+    let db = Db::open_ro_with_config(&path, cfg)
+        .with_context(|| format!("open RO DB at {}", path.display()))?;
 
     // labels: Vec<String> -> Vec<&str>
     let label_refs: Vec<&str> = labels.iter().map(|s| s.as_str()).collect();
 
-    let id =
+    let id = if with_sidecars {
+        SnapshotManager::create_persisted_with_sidecars(
+            &db,
+            message.as_deref(),
+            &label_refs,
+            parent.as_deref(),
+        )
+    } else {
         SnapshotManager::create_persisted(&db, message.as_deref(), &label_refs, parent.as_deref())
-            .with_context(|| "create_persisted snapshot")?;
+    }
+    .with_context(|| "create_persisted snapshot")?;
 
     let mpath = manifest_path(&path, &id);
-    println!("snapshot: id={} manifest={}", id, mpath.display());
+    let rep = SnapshotCreated {
+        id,
+        manifest: mpath.display().to_string(),
+        signed_kid,
+    };
+    if !emit(fmt, &rep)? {
+        match rep.signed_kid.as_deref() {
+            Some(kid) => println!(
+                "snapshot: id={} manifest={} signed (kid={})",
+                rep.id, rep.manifest, kid
+            ),
+            None => println!("snapshot: id={} manifest={}", rep.id, rep.manifest),
+        }
+    }
     Ok(())
 }
 
-/// Список всех манифестов (id), опционально JSON.
-pub fn exec_list(path: PathBuf, json: bool) -> Result<()> {
+/// Список всех манифестов со статусом подписи: JSON — массив {id, signature, kid},
+/// plain — `<id>  <signature>` на строку. Нечитаемый манифест — статус invalid.
+pub fn exec_list(path: PathBuf, fmt: OutputFormat) -> Result<()> {
     let ids =
         list_manifests(&path).with_context(|| format!("list manifests at {}", path.display()))?;
-    if json {
-        print_json_array(&ids);
+    let entries: Vec<SnapshotListEntry> = ids
+        .into_iter()
+        .map(|id| match read_manifest(&path, &id) {
+            Ok(m) => SnapshotListEntry {
+                signature: manifest_signature_status(&path, &m).as_str(),
+                kid: m.signature.map(|s| s.kid),
+                id,
+            },
+            Err(_) => SnapshotListEntry {
+                id,
+                signature: "invalid",
+                kid: None,
+            },
+        })
+        .collect();
+    if emit(fmt, &entries)? {
         return Ok(());
     }
-    if ids.is_empty() {
+    if entries.is_empty() {
         println!("(no snapshots)");
         return Ok(());
     }
-    for id in ids {
-        println!("{id}");
+    for e in entries {
+        match e.kid {
+            Some(kid) => println!("{}  {} (kid={})", e.id, e.signature, kid),
+            None => println!("{}  {}", e.id, e.signature),
+        }
     }
     Ok(())
 }
 
-/// Инспекция манифеста по id, опционально JSON.
-pub fn exec_inspect(path: PathBuf, id: String, json: bool) -> Result<()> {
+/// Инспекция манифеста по id (--output table|plain|json). require_signature — ошибка
+/// (ManifestSignatureError), если подпись не valid.
+pub fn exec_inspect(
+    path: PathBuf,
+    id: String,
+    require_signature: bool,
+    fmt: OutputFormat,
+) -> Result<()> {
     if id.trim().is_empty() {
         return Err(anyhow!("provide snapshot id"));
     }
     let m = read_manifest(&path, &id)
         .with_context(|| format!("read manifest {} at {}", id, path.display()))?;
-    if json {
-        let s = serde_json::to_string_pretty(&m).unwrap_or_else(|_| "{}".to_string());
-        println!("{s}");
+    let status = manifest_signature_status(&path, &m);
+    if require_signature && status != SignatureStatus::Valid {
+        return Err(ManifestSignatureError {
+            path: path.clone(),
+            snapshot_id: m.meta.id.clone(),
+            status,
+        }
+        .into());
+    }
+    if emit(fmt, &m)? {
         return Ok(());
     }
 
@@ -75,6 +165,18 @@ pub fn exec_inspect(path: PathBuf, id: String, json: bool) -> Result<()> {
     println!("  page_size   = {}", m.meta.page_size);
     println!("  next_page_id= {}", m.meta.next_page_id);
     println!("  buckets     = {}", m.meta.buckets);
+    match m.signature.as_ref() {
+        Some(sig) => println!("  signature   = {} (kid={}, {})", status, sig.kid, sig.alg),
+        None => println!("  signature   = {}", status),
+    }
+    println!(
+        "  checksum    = {}",
+        match m.checksum_matches() {
+            Some(true) => "ok",
+            Some(false) => "mismatch",
+            None => "absent",
+        }
+    );
     println!("Heads (bucket -> head_pid): {}", m.heads.len());
     for h in &m.heads {
         println!("  - {:6} -> {}", h.bucket, h.head_pid);
@@ -87,6 +189,64 @@ pub fn exec_inspect(path: PathBuf, id: String, json: bool) -> Result<()> {
     Ok(())
 }
 
+/// Проверка снапшота против SnapStore: checksum манифеста, HEAD-размеры всех объектов,
+/// SHA-256 выборки (`--sample N`, `--full` — всех). Проблемы — код выхода corruption.
+pub fn exec_verify(
+    path: PathBuf,
+    id: String,
+    sample: usize,
+    full: bool,
+    fmt: OutputFormat,
+) -> Result<()> {
+    if id.trim().is_empty() {
+        return Err(anyhow!("provide snapshot id"));
+    }
+    let rep = verify_snapshot(&path, &id, &VerifyOptions { sample, full })
+        .with_context(|| format!("verify snapshot {} at {}", id, path.display()))?;
+    if !emit(fmt, &rep)? {
+        println!("Snapshot verify: {}", rep.snapshot_id);
+        println!("  checksum        = {}", rep.checksum);
+        println!(
+            "  objects         = {} ({} unique, {} B)",
+            rep.objects, rep.unique_objects, rep.stored_bytes
+        );
+        println!("  missing         = {}", rep.missing.len());
+        println!("  size_mismatch   = {}", rep.size_mismatch.len());
+        println!(
+            "  hash_checked    = {} (mismatch {}, skipped {})",
+            rep.hash_checked,
+            rep.hash_mismatch.len(),
+            rep.hash_skipped
+        );
+        for h in rep.missing.iter().take(10) {
+            println!("  - missing  {}", h);
+        }
+        for h in rep.size_mismatch.iter().chain(&rep.hash_mismatch).take(10) {
+            println!("  - damaged  {}", h);
+        }
+        println!(
+            "  result          = {}",
+            if rep.ok { "OK" } else { "FAILED" }
+        );
+    }
+    if !rep.ok {
+        return Err(CliError::new(
+            ErrorKind::Corruption,
+            format!(
+                "snapshot {}: checksum {}, {} missing, {} size mismatch, {} hash mismatch",
+                rep.snapshot_id,
+                rep.checksum,
+                rep.missing.len(),
+                rep.size_mismatch.len(),
+                rep.hash_mismatch.len()
+            ),
+        )
+        .with_path(&path)
+        .into());
+    }
+    Ok(())
+}
+
 /// NEW: Удаление persisted‑снапшота по id.
 /// Поведение:
 /// - уменьшает refcount у всех объектов снапшота (объект удаляется при rc==0),
@@ -100,16 +260,3 @@ pub fn exec_delete(path: PathBuf, id: String) -> Result<()> {
     println!("snapshot-delete: OK (id='{}')", id);
     Ok(())
 }
-
-// ------------- helpers -------------
-
-fn print_json_array(ids: &[String]) {
-    print!("[");
-    for (i, id) in ids.iter().enumerate() {
-        if i > 0 {
-            print!(",");
-        }
-        print!("\"{}\"", id);
-    }
-    println!("]");
-}
@@ -1,7 +1,8 @@
 use anyhow::{Context, Result};
 use std::path::PathBuf;
 
-use QuiverDB::snapstore::restore_from_id;
+use QuiverDB::backup::RestoreOptions;
+use QuiverDB::snapstore::restore_from_id_with;
 
 /// CLI: snapshot-restore — восстановить БД из persisted‑снапшота.
 ///
@@ -15,13 +16,28 @@ use QuiverDB::snapstore::restore_from_id;
 ///           По умолчанию совпадает с --path.
 /// - --id:   идентификатор снапшота (см. snapshot-list).
 /// - --verify: включить базовую проверку длины страниц (по page_size).
-pub fn exec(dst_root: PathBuf, src_root: Option<PathBuf>, id: String, verify: bool) -> Result<()> {
+/// - --force:  восстановить поверх более новой БД (откат на месте) или чужой БД.
+/// - --require-signature: отказать, если подпись манифеста не valid (изменённый манифест
+///   отвергается всегда).
+pub fn exec(
+    dst_root: PathBuf,
+    src_root: Option<PathBuf>,
+    id: String,
+    verify: bool,
+    force: bool,
+    require_signature: bool,
+) -> Result<()> {
     if id.trim().is_empty() {
         anyhow::bail!("provide --id <snapshot_id>");
     }
     let src = src_root.unwrap_or_else(|| dst_root.clone());
 
-    restore_from_id(&src, &dst_root, &id, verify).with_context(|| {
+    let opts = RestoreOptions {
+        verify,
+        force,
+        require_signature,
+    };
+    restore_from_id_with(&src, &dst_root, &id, &opts).with_context(|| {
         format!(
             "restore snapshot id='{}' from {} to {}",
             id,
@@ -3,12 +3,13 @@ use std::path::PathBuf;
 
 use QuiverDB::db::Db;
 use QuiverDB::dir::Directory;
-use QuiverDB::meta::read_meta;
+use QuiverDB::meta::{feature_names, read_meta, FEATURES_REQUIRED_MASK, FEATURE_TDE_AAD_V2};
+use QuiverDB::page::crc32c_hw_available;
 // Bloom side-car status + cache counters (через реэкспорт)
 use QuiverDB::bloom::{bloom_cache_counters, bloom_cache_stats, BloomSidecar};
 // Page cache diagnostics
 use QuiverDB::pager::cache::{
-    page_cache_evictions_total, page_cache_invalidations_total, page_cache_len,
+    page_cache_evictions_total, page_cache_invalidations_total, page_cache_len, page_cache_policy,
 };
 // metrics snapshot
 use QuiverDB::metrics;
@@ -18,8 +19,10 @@ use QuiverDB::crypto::KeyJournal;
 // serde_json для безопасного JSON-вывода
 use serde_json::json;
 
-/// New: JSON-aware status (when json=true prints one JSON object).
-pub fn exec_with_json(path: PathBuf, json: bool) -> Result<()> {
+use crate::output::{emit, OutputFormat};
+
+/// Status: table — человекочитаемый отчёт; json/plain — один объект (см. output.rs).
+pub fn exec(path: PathBuf, fmt: OutputFormat) -> Result<()> {
     let m = read_meta(&path)?;
 
     // RO-открытие для статуса каталога/ускорителей и печати статистики
@@ -34,6 +37,19 @@ pub fn exec_with_json(path: PathBuf, json: bool) -> Result<()> {
     let tde_kid = db.pager.tde_kid().map(|s| s.to_string());
     let tde_mode = if tde_enabled { "aead" } else { "crc" };
     let tde_key_loaded = db.pager.tde_key_loaded();
+    let tde_epoch_kids = db.pager.tde_epoch_kids();
+    // NEW: страницы по KID подписи (прогресс ротации) — полный проход страниц, только при TDE
+    let tde_kid_pages = if tde_enabled {
+        Some(db.tde_kid_pages()?)
+    } else {
+        None
+    };
+    let ident = m.identity();
+    let tde_aad = if m.flags & FEATURE_TDE_AAD_V2 != 0 {
+        "v2"
+    } else {
+        "v1"
+    };
 
     // TDE key journal (epochs)
     let tde_epochs: Vec<(u64, String)> = match KeyJournal::open(&path) {
@@ -43,6 +59,7 @@ pub fn exec_with_json(path: PathBuf, json: bool) -> Result<()> {
 
     // Acceleration: mem_keydir
     let mem_keydir_present = db.has_mem_keydir();
+    let crc32c_hw = crc32c_hw_available();
 
     // Bloom status + meta + cache stats
     let bloom_sidecar_ro = BloomSidecar::open_ro(&path);
@@ -80,7 +97,7 @@ pub fn exec_with_json(path: PathBuf, json: bool) -> Result<()> {
         "absent".to_string()
     };
 
-    if json {
+    if fmt != OutputFormat::Table {
         // Metrics snapshot
         let ms = metrics::snapshot();
 
@@ -100,22 +117,39 @@ pub fn exec_with_json(path: PathBuf, json: bool) -> Result<()> {
                 "version": m.version,
                 "page_size": m.page_size,
                 "flags": m.flags,
+                "features": {
+                    "required": feature_names(m.flags & FEATURES_REQUIRED_MASK),
+                    "optional": feature_names(m.flags & !FEATURES_REQUIRED_MASK)
+                },
                 "hash_kind": m.hash_kind,
+                "bucket_routing": m.bucket_routing(),
+                "key_transform": (!m.key_transform.is_empty()).then_some(&m.key_transform),
+                "value_transform": (!m.value_transform.is_empty()).then_some(&m.value_transform),
                 "checksum_kind": m.checksum_kind,
                 "codec_default": m.codec_default,
                 "next_page_id": m.next_page_id,
                 "last_lsn": m.last_lsn,
-                "clean_shutdown": m.clean_shutdown
+                "clean_shutdown": m.clean_shutdown,
+                "db_uuid": ident.db_uuid,
+                "created_unix_ms": ident.created_unix_ms,
+                "created_by": ident.created_by
             },
             "tde": {
                 "enabled": tde_enabled,
                 "mode": tde_mode,
                 "key_loaded": tde_key_loaded,
                 "kid": tde_kid,
-                "epochs": tde_epochs_json
+                "epochs": tde_epochs_json,
+                "epoch_keys_loaded": tde_epoch_kids,
+                "aad": tde_aad,
+                "aad_v2_since_lsn": m.tde_aad_since_lsn,
+                "key_check_kid": if m.tde_check.is_empty() { None } else { Some(m.tde_check_kid.clone()) },
+                "kid_pages": tde_kid_pages.as_ref().map(|u| &u.kids),
+                "untagged_pages": tde_kid_pages.as_ref().map(|u| u.untagged_pages)
             },
             "acceleration": {
-                "mem_keydir": mem_keydir_present
+                "mem_keydir": mem_keydir_present,
+                "crc32c_hw": crc32c_hw
             },
             "bloom": {
                 "present": bloom_present,
@@ -134,7 +168,9 @@ pub fn exec_with_json(path: PathBuf, json: bool) -> Result<()> {
             },
             "directory": {
                 "buckets": dir.bucket_count,
-                "used_buckets": used_buckets
+                "used_buckets": used_buckets,
+                "format": if dir.is_paged() { "paged" } else { "flat" },
+                "chunk_heads": dir.paged_layout().map(|l| l.chunk_heads)
             },
             "metrics": {
                 "wal_appends_total": ms.wal_appends_total,
@@ -144,6 +180,12 @@ pub fn exec_with_json(path: PathBuf, json: bool) -> Result<()> {
                 "wal_truncations": ms.wal_truncations,
                 "wal_pending_max_lsn": ms.wal_pending_max_lsn,
                 "wal_flushed_lsn": ms.wal_flushed_lsn,
+                "heads_update_frames": ms.heads_update_frames,
+                "heads_update_entries": ms.heads_update_entries,
+                "heads_update_skipped": ms.heads_update_skipped,
+                "heads_update_bytes": ms.heads_update_bytes,
+                "heads_update_bytes_legacy": ms.heads_update_bytes_legacy,
+                "heads_update_savings_ratio": ms.heads_update_savings_ratio(),
 
                 "page_cache_hits": ms.page_cache_hits,
                 "page_cache_misses": ms.page_cache_misses,
@@ -151,6 +193,8 @@ pub fn exec_with_json(path: PathBuf, json: bool) -> Result<()> {
                 "page_cache_len": pc_len,
                 "page_cache_evictions_total": pc_ev,
                 "page_cache_invalidations_total": pc_inv,
+                "page_cache_policy": page_cache_policy().as_str(),
+                "page_cache_locked_bytes": ms.page_cache_locked_bytes,
 
                 "keydir_hits": ms.keydir_hits,
                 "keydir_misses": ms.keydir_misses,
@@ -187,14 +231,16 @@ pub fn exec_with_json(path: PathBuf, json: bool) -> Result<()> {
 
                 "bloom_updates_total": ms.bloom_updates_total,
                 "bloom_update_bytes": ms.bloom_update_bytes,
+                "bloom_auto_refreshes": ms.bloom_auto_refreshes,
+                "bloom_auto_refresh_buckets": ms.bloom_auto_refresh_buckets,
 
                 "lazy_compact_runs": ms.lazy_compact_runs,
                 "lazy_compact_pages_written": ms.lazy_compact_pages_written
             }
         });
 
-        // Печатаем pretty JSON
-        println!("{}", serde_json::to_string_pretty(&status).unwrap());
+        // JSON (pretty) или plain key=value
+        emit(fmt, &status)?;
         return Ok(());
     }
 
@@ -203,12 +249,52 @@ pub fn exec_with_json(path: PathBuf, json: bool) -> Result<()> {
     println!("  version        = {}", m.version);
     println!("  page_size      = {}", m.page_size);
     println!("  flags          = 0x{:08x}", m.flags);
+    let features = feature_names(m.flags);
+    println!(
+        "  features       = {}",
+        if features.is_empty() {
+            "none".to_string()
+        } else {
+            features.join(", ")
+        }
+    );
     println!("  hash_kind      = {}", m.hash_kind);
+    println!("  bucket_routing = {}", m.bucket_routing());
+    println!(
+        "  key_transform  = {}",
+        if m.key_transform.is_empty() {
+            "none"
+        } else {
+            m.key_transform.as_str()
+        }
+    );
+    println!(
+        "  value_transform = {}",
+        if m.value_transform.is_empty() {
+            "none"
+        } else {
+            m.value_transform.as_str()
+        }
+    );
     println!("  checksum_kind  = {}", m.checksum_kind);
     println!("  codec_default  = {}", m.codec_default);
     println!("  next_page_id   = {}", m.next_page_id);
     println!("  last_lsn       = {}", m.last_lsn);
     println!("  clean_shutdown = {}", m.clean_shutdown);
+    println!(
+        "  db_uuid        = {}",
+        if ident.db_uuid.is_empty() {
+            "(not assigned)"
+        } else {
+            ident.db_uuid.as_str()
+        }
+    );
+    if ident.created_unix_ms != 0 {
+        println!("  created_ms     = {}", ident.created_unix_ms);
+    }
+    if !ident.created_by.is_empty() {
+        println!("  created_by     = {}", ident.created_by);
+    }
 
     // TDE статус
     println!("TDE:");
@@ -219,11 +305,37 @@ pub fn exec_with_json(path: PathBuf, json: bool) -> Result<()> {
         "  kid            = {}",
         tde_kid.as_deref().unwrap_or("(default/provider)")
     );
+    if !m.tde_check.is_empty() {
+        println!("  key_check      = sealed (kid={})", m.tde_check_kid);
+    }
+    if let Some(u) = &tde_kid_pages {
+        let kids: Vec<String> = u
+            .kids
+            .iter()
+            .map(|k| format!("{}:{}{}", k.kid, k.pages, if k.current { "*" } else { "" }))
+            .collect();
+        println!(
+            "  kid_pages      = {} (untagged={}, not current={})",
+            if kids.is_empty() {
+                "-".to_string()
+            } else {
+                kids.join(",")
+            },
+            u.untagged_pages,
+            u.pages_not_current()
+        );
+    }
     if !tde_epochs.is_empty() {
         println!("  epochs total   = {}", tde_epochs.len());
         if let Some((since, kid)) = tde_epochs.last() {
             println!("  last_epoch     = (since_lsn={}, kid={})", since, kid);
         }
+        if tde_aad == "v2" {
+            println!("  aad            = v2 (since_lsn={})", m.tde_aad_since_lsn);
+        }
+        if !tde_epoch_kids.is_empty() {
+            println!("  epoch_keys     = {}", tde_epoch_kids.join(","));
+        }
     }
 
     // In-memory keydir (ускоритель get/exists/scan)
@@ -236,6 +348,7 @@ pub fn exec_with_json(path: PathBuf, json: bool) -> Result<()> {
             "absent"
         }
     );
+    println!("  crc32c_hw      = {}", crc32c_hw);
 
     // Bloom side-car
     println!("Bloom:");
@@ -280,6 +393,15 @@ pub fn exec_with_json(path: PathBuf, json: bool) -> Result<()> {
     println!("  wal_truncations         = {}", ms.wal_truncations);
     println!("  wal_pending_max_lsn     = {}", ms.wal_pending_max_lsn);
     println!("  wal_flushed_lsn         = {}", ms.wal_flushed_lsn);
+    println!(
+        "  heads_update            = frames={} entries={} skipped={} bytes={} (legacy {}, saved {:.1}%)",
+        ms.heads_update_frames,
+        ms.heads_update_entries,
+        ms.heads_update_skipped,
+        ms.heads_update_bytes,
+        ms.heads_update_bytes_legacy,
+        ms.heads_update_savings_ratio() * 100.0
+    );
 
     println!("  page_cache_hits         = {}", ms.page_cache_hits);
     println!("  page_cache_misses       = {}", ms.page_cache_misses);
@@ -296,6 +418,11 @@ pub fn exec_with_json(path: PathBuf, json: bool) -> Result<()> {
         "  page_cache_invalidations= {}",
         page_cache_invalidations_total()
     );
+    println!(
+        "  page_cache_policy       = {} (locked {} B)",
+        page_cache_policy(),
+        ms.page_cache_locked_bytes
+    );
 
     println!("  keydir_hits             = {}", ms.keydir_hits);
     println!("  keydir_misses           = {}", ms.keydir_misses);
@@ -326,6 +453,10 @@ pub fn exec_with_json(path: PathBuf, json: bool) -> Result<()> {
         "  bloom tests/neg/pos/skip= {}/{}/{}/{}",
         ms.bloom_tests, ms.bloom_negative, ms.bloom_positive, ms.bloom_skipped_stale
     );
+    println!(
+        "  bloom auto-refresh      = {} ({} buckets)",
+        ms.bloom_auto_refreshes, ms.bloom_auto_refresh_buckets
+    );
     println!("  pack_pages              = {}", ms.pack_pages);
     println!("  pack_records            = {}", ms.pack_records);
     println!("  pack_pages_single       = {}", ms.pack_pages_single);
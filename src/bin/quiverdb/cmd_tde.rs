@@ -58,6 +58,8 @@ pub fn exec_rotate(path: PathBuf, requested_kid: String) -> Result<()> {
             db.pager.ensure_tde_key().context(
                 "ensure TDE key via KMS+KeyRing (set P1_KMS_KEK_HEX or P1_KMS_KEK_BASE64)",
             )?;
+            // NEW: проверочный блок meta — под новым KID
+            db.pager.seal_tde_key_check()?;
 
             // 6) epoch: эффективна с next LSN
             let since_lsn = db.pager.meta.last_lsn.saturating_add(1);
@@ -99,6 +101,7 @@ pub fn exec_rotate(path: PathBuf, requested_kid: String) -> Result<()> {
     db.pager
         .ensure_tde_key()
         .context("ensure TDE key via EnvKeyProvider (set P1_TDE_KEY_HEX or P1_TDE_KEY_BASE64)")?;
+    db.pager.seal_tde_key_check()?;
 
     // epoch
     let since_lsn = db.pager.meta.last_lsn.saturating_add(1);
@@ -0,0 +1,26 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+
+use crate::output::{emit, OutputFormat};
+
+/// CLI: trim — вернуть ФС место страниц free-листа (punch hole, writer-only).
+pub fn exec(path: PathBuf, fmt: OutputFormat) -> Result<()> {
+    let mut db = Db::open(&path)?;
+    let rep = db.trim_free_pages()?;
+    if !emit(fmt, &rep)? {
+        if rep.supported {
+            println!(
+                "Trim: punched {} of {} free page(s), {} byte(s) returned to the filesystem",
+                rep.punched_pages, rep.free_pages, rep.punched_bytes
+            );
+        } else {
+            println!(
+                "Trim: filesystem does not support hole punching ({} free page(s) kept)",
+                rep.free_pages
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+use QuiverDB::util::now_secs;
+
+use crate::exit_code::{CliError, ErrorKind};
+use crate::output::{emit, OutputFormat};
+
+#[derive(Serialize)]
+struct TrashRow {
+    bucket: u32,
+    key: String,
+    value_len: u64,
+    purge_after_sec: u32,
+    expires_in_s: u32,
+}
+
+/// CLI: undelete — вернуть значение из корзины (Db::undelete) или показать корзину (--list).
+/// Ключа нет в корзине — ошибка not_found (код 3).
+pub fn exec(path: PathBuf, key: Option<String>, list: bool, fmt: OutputFormat) -> Result<()> {
+    if list {
+        let db = Db::open_ro(&path)?;
+        let now = now_secs();
+        let rows: Vec<TrashRow> = db
+            .trash_entries()?
+            .into_iter()
+            .map(|e| TrashRow {
+                bucket: e.bucket,
+                key: String::from_utf8_lossy(&e.key).into_owned(),
+                value_len: e.value_len,
+                purge_after_sec: e.purge_after_sec,
+                expires_in_s: e.purge_after_sec.saturating_sub(now),
+            })
+            .collect();
+        if emit(fmt, &rows)? {
+            return Ok(());
+        }
+        if rows.is_empty() {
+            println!("(trash is empty)");
+            return Ok(());
+        }
+        println!(
+            "  {:<32}  {:>10}  {:>12}  bucket",
+            "key", "value_len", "expires_in_s"
+        );
+        for r in rows {
+            println!(
+                "  {:<32}  {:>10}  {:>12}  {}",
+                r.key, r.value_len, r.expires_in_s, r.bucket
+            );
+        }
+        return Ok(());
+    }
+
+    let key = key.unwrap_or_default();
+    let mut db = Db::open(&path)?;
+    if !db.undelete(key.as_bytes())? {
+        return Err(CliError::new(
+            ErrorKind::NotFound,
+            format!(
+                "'{}' is not in trash (live, hard-deleted or grace period expired)",
+                key
+            ),
+        )
+        .into());
+    }
+    if !emit(fmt, &serde_json::json!({ "key": key, "restored": true }))? {
+        println!("RESTORED '{}'", key);
+    }
+    Ok(())
+}
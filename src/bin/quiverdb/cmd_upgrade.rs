@@ -0,0 +1,50 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use QuiverDB::migrate::{upgrade_with_progress, MigrateOptions, StepStatus};
+
+/// CLI: upgrade — миграция формата БД (прогресс шагов — в stderr).
+pub fn exec(path: PathBuf, to: String, dry_run: bool, json: bool) -> Result<()> {
+    let opts = MigrateOptions {
+        dry_run,
+        ..Default::default()
+    };
+    let rep = upgrade_with_progress(&path, &to, &opts, |p| {
+        if p.pages_total > 0 {
+            eprintln!(
+                "[upgrade] {}: {}/{} pages",
+                p.step, p.pages_done, p.pages_total
+            );
+        }
+    })
+    .with_context(|| format!("upgrade {} to {}", path.display(), to))?;
+
+    if json {
+        println!("{}", serde_json::to_string(&rep)?);
+        return Ok(());
+    }
+    println!(
+        "Upgrade {} -> {}{}:",
+        path.display(),
+        rep.target,
+        if rep.dry_run { " (dry-run)" } else { "" }
+    );
+    for s in &rep.steps {
+        let status = match s.status {
+            StepStatus::Applied => "applied",
+            StepStatus::AlreadyApplied => "up-to-date",
+            StepStatus::WouldApply => "would apply",
+        };
+        println!("  {:<18} {:<12} {}", s.id, status, s.description);
+        if s.pages_scanned > 0 {
+            println!(
+                "  {:<18} pages_scanned={} rewritten={} legacy={}",
+                "", s.pages_scanned, s.pages_rewritten, s.legacy_pages
+            );
+        }
+        if !s.legacy_sample.is_empty() {
+            println!("  {:<18} legacy page ids: {:?}", "", s.legacy_sample);
+        }
+    }
+    Ok(())
+}
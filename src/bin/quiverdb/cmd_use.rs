@@ -0,0 +1,74 @@
+use anyhow::Result;
+use serde_json::json;
+use std::path::PathBuf;
+
+use super::user_config::{self, UserConfig};
+
+/// CLI: use — записать/удалить/показать БД по умолчанию (см. user_config.rs).
+/// Путь без meta записывается с [WARN]: БД можно создать позже (`quiverdb init`).
+pub fn exec(path: Option<PathBuf>, clear: bool, json: bool) -> Result<()> {
+    if clear {
+        let mut cfg = user_config::load()?;
+        let old = cfg.default_path.take();
+        let file = user_config::save(&cfg)?;
+        if json {
+            let v = json!({ "cleared": old, "config_file": file });
+            println!("{}", serde_json::to_string_pretty(&v)?);
+        } else {
+            match old {
+                Some(p) => println!("cleared default DB {} ({})", p.display(), file.display()),
+                None => println!("no default DB recorded ({})", file.display()),
+            }
+        }
+        return Ok(());
+    }
+
+    let Some(path) = path else {
+        let cur = user_config::default_path();
+        let file = user_config::config_file();
+        if json {
+            let v = json!({
+                "default_path": cur.as_ref().map(|(p, _)| p),
+                "source": cur.as_ref().map(|(_, s)| *s),
+                "config_file": file,
+            });
+            println!("{}", serde_json::to_string_pretty(&v)?);
+        } else {
+            match cur {
+                Some((p, src)) => println!("default DB: {} (from {})", p.display(), src),
+                None => {
+                    println!("default DB: not set (use `quiverdb use <path>` or QUIVERDB_PATH)")
+                }
+            }
+        }
+        return Ok(());
+    };
+
+    let abs = user_config::absolutize(&path)?;
+    if !abs.join("meta").exists() {
+        eprintln!(
+            "[WARN] {} is not an initialized QuiverDB (no meta); recording anyway",
+            abs.display()
+        );
+    }
+    let mut cfg = user_config::load().unwrap_or_else(|e| {
+        eprintln!("[WARN] user config: {:#}; rewriting", e);
+        UserConfig::default()
+    });
+    cfg.default_path = Some(abs.clone());
+    let file = user_config::save(&cfg)?;
+    if user_config::default_path().map(|(_, s)| s) == Some("env") {
+        eprintln!("[WARN] QUIVERDB_PATH is set and takes precedence over the recorded default");
+    }
+    if json {
+        let v = json!({ "default_path": abs, "config_file": file });
+        println!("{}", serde_json::to_string_pretty(&v)?);
+    } else {
+        println!(
+            "default DB: {} (saved to {})",
+            abs.display(),
+            file.display()
+        );
+    }
+    Ok(())
+}
@@ -4,12 +4,14 @@ use std::path::PathBuf;
 use QuiverDB::db::vacuum::VacuumSummary;
 use QuiverDB::db::Db;
 
+use crate::output::{emit, OutputFormat};
+
 /// CLI: vacuum — комбинированная операция обслуживания:
 /// 1) Компактация всех бакетов (tail-wins без tombstone/expired)
 /// 2) Очистка сиротских OVERFLOW-страниц
 ///
-/// Требует writer (эксклюзивный lock). Вывод — текст/JSON.
-pub fn exec(path: PathBuf, json: bool) -> Result<()> {
+/// Требует writer (эксклюзивный lock). Вывод — --output table|plain|json.
+pub fn exec(path: PathBuf, fmt: OutputFormat) -> Result<()> {
     let mut db =
         Db::open(&path).with_context(|| format!("open writer DB at {}", path.display()))?;
 
@@ -17,25 +19,7 @@ pub fn exec(path: PathBuf, json: bool) -> Result<()> {
         .vacuum_all()
         .with_context(|| "vacuum_all (compaction + sweep orphan overflow)")?;
 
-    if json {
-        println!(
-            "{{\
-                \"buckets_total\":{},\
-                \"buckets_compacted\":{},\
-                \"old_chain_len_sum\":{},\
-                \"keys_kept_sum\":{},\
-                \"keys_deleted_sum\":{},\
-                \"pages_written_sum\":{},\
-                \"overflow_pages_freed\":{}\
-            }}",
-            sum.compaction.buckets_total,
-            sum.compaction.buckets_compacted,
-            sum.compaction.old_chain_len_sum,
-            sum.compaction.keys_kept_sum,
-            sum.compaction.keys_deleted_sum,
-            sum.compaction.pages_written_sum,
-            sum.overflow_pages_freed
-        );
+    if emit(fmt, &sum)? {
         return Ok(());
     }
 
@@ -0,0 +1,74 @@
+use anyhow::{anyhow, Context, Result};
+use fs2::FileExt;
+use std::fs::OpenOptions;
+use std::path::PathBuf;
+
+use QuiverDB::pager::Pager;
+use QuiverDB::wal::SalvageReport;
+
+/// CLI: wal-salvage
+/// - Требует эксклюзивный lock (<root>/LOCK), как checkpoint: writer не должен быть активен.
+/// - Без --apply ничего не меняет на диске.
+pub fn exec(root: PathBuf, apply: bool, json: bool) -> Result<()> {
+    if !root.exists() {
+        return Err(anyhow!("DB root does not exist: {}", root.display()));
+    }
+
+    let lock_path = root.join("LOCK");
+    let lock = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(false)
+        .open(&lock_path)
+        .with_context(|| format!("open lock file {}", lock_path.display()))?;
+    lock.lock_exclusive()
+        .with_context(|| format!("lock_exclusive {}", lock_path.display()))?;
+
+    let rep = Pager::wal_salvage_with_pager(&root, apply)?;
+    print_report(&rep, json);
+    Ok(())
+}
+
+fn print_report(rep: &SalvageReport, json: bool) {
+    if json {
+        println!("{}", serde_json::to_string(rep).unwrap());
+        return;
+    }
+    println!("WAL salvage:");
+    println!("  wal_len           = {}", rep.wal_len);
+    println!("  frames_ok         = {}", rep.frames_ok);
+    println!("  skipped_regions   = {}", rep.skipped.len());
+    println!("  skipped_bytes     = {}", rep.skipped_bytes);
+    for r in &rep.skipped {
+        println!(
+            "    [{}..{}) {} bytes, reason={}",
+            r.start,
+            r.end,
+            r.end - r.start,
+            r.reason
+        );
+    }
+    println!("  batches_intact    = {}", rep.batches_intact);
+    println!("  batches_damaged   = {}", rep.batches_damaged);
+    for b in rep.batches.iter().filter(|b| !b.intact) {
+        println!(
+            "    damaged batch at {} (start_lsn={}, commit_lsn={}, pages={})",
+            b.begin_pos, b.start_lsn, b.commit_lsn, b.pages
+        );
+    }
+    println!("  orphan_frames     = {}", rep.orphan_frames);
+    println!("  salvageable_pages = {}", rep.salvageable_pages);
+    println!("  max_intact_lsn    = {}", rep.max_intact_lsn);
+    if rep.applied {
+        println!("Applied:");
+        println!("  batches           = {}", rep.applied_batches);
+        println!("  pages             = {}", rep.applied_pages);
+        println!("  heads_updates     = {}", rep.applied_heads_updates);
+        if let Some(p) = &rep.backup_path {
+            println!("  wal_backup        = {}", p);
+        }
+    } else {
+        println!("(dry-run; re-run with --apply to apply intact batches)");
+    }
+}
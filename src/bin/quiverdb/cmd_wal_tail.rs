@@ -0,0 +1,134 @@
+use anyhow::{anyhow, Context, Result};
+use serde_json::json;
+use std::fs::OpenOptions;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use QuiverDB::wal::reader::{WalRecord, WalStreamReader};
+use QuiverDB::wal::{
+    parse_expiry_payload, wal_path, CommitTimestamp, WAL_HDR_SIZE, WAL_MAGIC, WAL_REC_BEGIN,
+    WAL_REC_COMMIT, WAL_REC_EXPIRY, WAL_REC_HEADS_UPDATE, WAL_REC_IDEMPOTENCY, WAL_REC_PAGE_DELTA,
+    WAL_REC_PAGE_IMAGE, WAL_REC_TRUNCATE,
+};
+
+/// CLI: wal-tail — read-only просмотр кадров WAL.
+/// - Без --follow: один проход до конца файла (частичный хвост — EOF).
+/// - С --follow: опрос раз в 200 мс; если файл усох (ротация/checkpoint) — чтение с начала.
+pub fn exec(
+    path: Option<PathBuf>,
+    file: Option<PathBuf>,
+    since_lsn: Option<u64>,
+    commits_only: bool,
+    follow: bool,
+    json: bool,
+) -> Result<()> {
+    let src = match (path, file) {
+        (Some(p), None) => wal_path(&p),
+        (None, Some(f)) => f,
+        _ => return Err(anyhow!("specify exactly one of --path or --file")),
+    };
+    let mut f = OpenOptions::new()
+        .read(true)
+        .open(&src)
+        .with_context(|| format!("open WAL {}", src.display()))?;
+
+    let mut hdr = [0u8; WAL_HDR_SIZE];
+    std::io::Read::read_exact(&mut f, &mut hdr)
+        .with_context(|| format!("read WAL header {}", src.display()))?;
+    if &hdr[..8] != WAL_MAGIC {
+        return Err(anyhow!("bad WAL magic in {}", src.display()));
+    }
+
+    let mut rdr = WalStreamReader::new();
+    let mut pos = WAL_HDR_SIZE as u64;
+    loop {
+        let len = f.metadata()?.len();
+        if len < pos {
+            // WAL усечён — начинаем с начала
+            pos = WAL_HDR_SIZE as u64;
+            rdr.reset_stream();
+        }
+        while let Some((rec, next)) = rdr.read_next(&mut f, pos, len)? {
+            pos = next;
+            if since_lsn.is_some_and(|s| rec.lsn <= s) {
+                continue;
+            }
+            if commits_only && rec.rec_type != WAL_REC_COMMIT {
+                continue;
+            }
+            print_record(&rec, json);
+        }
+        if !follow {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    }
+}
+
+fn type_name(t: u8) -> &'static str {
+    match t {
+        WAL_REC_BEGIN => "BEGIN",
+        WAL_REC_PAGE_IMAGE => "PAGE_IMAGE",
+        WAL_REC_PAGE_DELTA => "PAGE_DELTA",
+        WAL_REC_COMMIT => "COMMIT",
+        WAL_REC_TRUNCATE => "TRUNCATE",
+        WAL_REC_HEADS_UPDATE => "HEADS_UPDATE",
+        WAL_REC_IDEMPOTENCY => "IDEMPOTENCY",
+        WAL_REC_EXPIRY => "EXPIRY",
+        _ => "UNKNOWN",
+    }
+}
+
+fn print_record(rec: &WalRecord, json: bool) {
+    let ts = if rec.rec_type == WAL_REC_COMMIT {
+        CommitTimestamp::decode(&rec.payload)
+    } else {
+        None
+    };
+    if json {
+        let mut o = json!({
+            "pos": rec.pos,
+            "type": type_name(rec.rec_type),
+            "lsn": rec.lsn,
+            "page_id": rec.page_id,
+            "len": rec.payload.len(),
+        });
+        if let Some(ts) = ts {
+            o["commit_wall_ms"] = json!(ts.wall_ms);
+            o["commit_mono_ns"] = json!(ts.mono_ns);
+        }
+        if rec.rec_type == WAL_REC_EXPIRY {
+            let keys: Vec<serde_json::Value> = parse_expiry_payload(&rec.payload, rec.lsn)
+                .into_iter()
+                .map(|e| {
+                    json!({
+                        "bucket": e.bucket,
+                        "key": String::from_utf8_lossy(&e.key),
+                        "expired_at": e.expired_at,
+                    })
+                })
+                .collect();
+            o["expired"] = json!(keys);
+        }
+        println!("{}", o);
+        return;
+    }
+    match ts {
+        Some(ts) => println!(
+            "pos={} type={} lsn={} commit_wall_ms={} commit_mono_ns={}",
+            rec.pos,
+            type_name(rec.rec_type),
+            rec.lsn,
+            ts.wall_ms,
+            ts.mono_ns
+        ),
+        None => println!(
+            "pos={} type={} lsn={} page_id={} len={}",
+            rec.pos,
+            type_name(rec.rec_type),
+            rec.lsn,
+            rec.page_id,
+            rec.payload.len()
+        ),
+    }
+}
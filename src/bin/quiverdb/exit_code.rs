@@ -0,0 +1,225 @@
+//! exit_code — контракт кодов выхода CLI и машиночитаемые объекты ошибок.
+//!
+//! Коды (стабильны, документированы в README):
+//! - 0 ok             — успех;
+//! - 1 error          — прочая ошибка;
+//! - 2 usage          — неверные аргументы (clap);
+//! - 3 not_found      — ключ не найден (get/exists);
+//! - 4 path_not_found — нет БД по пути или нужного файла;
+//! - 5 locked         — БД занята другим хэндлом (P1_LOCK_NOWAIT=1, иначе open ждёт) или заморожена (Db::freeze);
+//! - 6 corruption     — нарушение целостности (CRC/AEAD tag, битые данные);
+//! - 7 io             — прочие ошибки ввода-вывода;
+//! - 8 incompatible   — формат БД не поддерживается этой сборкой (meta version, required features);
+//! - 9 config         — некорректное поле/значение конфигурации.
+//!
+//! В режиме --output json (или legacy --json) ошибка печатается в stdout одним объектом
+//! `{"code","kind","message","path"}`; иначе — `error: ...` в stderr.
+//!
+//! Классификация: явный CliError команды → DbLockedError / DbFrozenError / ForeignStreamError /
+//! RestoreConflictError / ManifestSignatureError / ReadOnlyError / ResourceBudgetError /
+//! DbPoisonedError / KeyTransformMismatchError / ValueTransformMismatchError /
+//! ScanCursorInvalidError / TdeKeyError → io::ErrorKind в цепочке ошибок → эвристика по тексту сообщения.
+
+use serde::Serialize;
+use std::fmt;
+use std::io::ErrorKind as IoKind;
+use std::path::{Path, PathBuf};
+
+use QuiverDB::backup::RestoreConflictError;
+use QuiverDB::budget::ResourceBudgetError;
+use QuiverDB::crypto::TdeKeyError;
+use QuiverDB::db::{
+    DbFrozenError, DbLockedError, DbPoisonedError, KeyTransformMismatchError, ReadOnlyError,
+    ScanCursorInvalidError, ValueTransformMismatchError,
+};
+use QuiverDB::snapstore::{ManifestSignatureError, SignatureStatus};
+use QuiverDB::wal::state::ForeignStreamError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Error,
+    Usage,
+    NotFound,
+    PathNotFound,
+    Locked,
+    Corruption,
+    Io,
+    Incompatible,
+    Config,
+}
+
+impl ErrorKind {
+    pub fn code(self) -> i32 {
+        match self {
+            ErrorKind::Error => 1,
+            ErrorKind::Usage => 2,
+            ErrorKind::NotFound => 3,
+            ErrorKind::PathNotFound => 4,
+            ErrorKind::Locked => 5,
+            ErrorKind::Corruption => 6,
+            ErrorKind::Io => 7,
+            ErrorKind::Incompatible => 8,
+            ErrorKind::Config => 9,
+        }
+    }
+}
+
+/// Ошибка с явным видом (команда знает, что произошло: например, ключ не найден).
+#[derive(Debug)]
+pub struct CliError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub path: Option<PathBuf>,
+}
+
+impl CliError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            path: None,
+        }
+    }
+
+    pub fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_path_buf());
+        self
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Объект ошибки для --output json.
+#[derive(Debug, Serialize)]
+pub struct ErrorObject {
+    pub code: i32,
+    pub kind: ErrorKind,
+    pub message: String,
+    pub path: Option<String>,
+}
+
+impl ErrorObject {
+    /// Классифицировать ошибку команды; `db_path` — путь БД команды (если у ошибки нет своего).
+    pub fn from_error(e: &anyhow::Error, db_path: Option<&Path>) -> Self {
+        let (kind, path) = classify(e);
+        let path = path.or_else(|| db_path.map(Path::to_path_buf));
+        Self {
+            code: kind.code(),
+            kind,
+            message: format!("{:#}", e),
+            path: path.map(|p| p.display().to_string()),
+        }
+    }
+
+    pub fn usage(message: String) -> Self {
+        Self {
+            code: ErrorKind::Usage.code(),
+            kind: ErrorKind::Usage,
+            message,
+            path: None,
+        }
+    }
+
+    /// Напечатать (json — stdout, иначе stderr) и вернуть код выхода.
+    pub fn report(&self, json: bool) -> i32 {
+        if json {
+            match serde_json::to_string_pretty(self) {
+                Ok(s) => println!("{s}"),
+                Err(_) => eprintln!("error: {}", self.message),
+            }
+        } else {
+            eprintln!("error: {}", self.message);
+        }
+        self.code
+    }
+}
+
+fn classify(e: &anyhow::Error) -> (ErrorKind, Option<PathBuf>) {
+    for cause in e.chain() {
+        if let Some(c) = cause.downcast_ref::<CliError>() {
+            return (c.kind, c.path.clone());
+        }
+        if let Some(l) = cause.downcast_ref::<DbLockedError>() {
+            return (ErrorKind::Locked, Some(l.path.clone()));
+        }
+        if let Some(f) = cause.downcast_ref::<DbFrozenError>() {
+            return (ErrorKind::Locked, Some(f.path.clone()));
+        }
+        if let Some(f) = cause.downcast_ref::<ForeignStreamError>() {
+            return (ErrorKind::Error, Some(f.path.clone()));
+        }
+        if let Some(f) = cause.downcast_ref::<RestoreConflictError>() {
+            return (ErrorKind::Error, Some(f.path.clone()));
+        }
+        if let Some(s) = cause.downcast_ref::<ManifestSignatureError>() {
+            // Изменённый манифест — порча; отсутствие/чужая подпись — отказ политики
+            let kind = match s.status {
+                SignatureStatus::Invalid(_) => ErrorKind::Corruption,
+                _ => ErrorKind::Error,
+            };
+            return (kind, Some(s.path.clone()));
+        }
+        if let Some(r) = cause.downcast_ref::<ReadOnlyError>() {
+            return (ErrorKind::Error, Some(r.path.clone()));
+        }
+        if let Some(b) = cause.downcast_ref::<ResourceBudgetError>() {
+            return (ErrorKind::Error, Some(b.path.clone()));
+        }
+        if let Some(p) = cause.downcast_ref::<DbPoisonedError>() {
+            return (ErrorKind::Error, Some(p.path.clone()));
+        }
+        // Ключи БД в другой канонической форме — открывать этой сборкой/конфигом нельзя
+        if let Some(k) = cause.downcast_ref::<KeyTransformMismatchError>() {
+            return (ErrorKind::Incompatible, Some(k.path.clone()));
+        }
+        // Значения БД закодированы другой трансформацией (или словаря нет)
+        if let Some(v) = cause.downcast_ref::<ValueTransformMismatchError>() {
+            return (ErrorKind::Incompatible, Some(v.path.clone()));
+        }
+        // Курсор scan --cursor устарел или испорчен — аргумент команды, скан начинают заново
+        if let Some(c) = cause.downcast_ref::<ScanCursorInvalidError>() {
+            return (ErrorKind::Usage, Some(c.path.clone()));
+        }
+        // Ключ TDE не настроен или не тот — исправляется конфигурацией ключей
+        if let Some(k) = cause.downcast_ref::<TdeKeyError>() {
+            return (ErrorKind::Config, Some(k.path.clone()));
+        }
+    }
+    let msg = format!("{:#}", e).to_ascii_lowercase();
+    // Текстовые признаки важнее io-вида: ошибки целостности часто приходят как InvalidData
+    if msg.contains("checksum mismatch")
+        || msg.contains("tag mismatch")
+        || msg.contains("corrupt")
+        || msg.contains("bad meta magic")
+    {
+        return (ErrorKind::Corruption, None);
+    }
+    if msg.contains("unsupported meta version") || msg.contains("requires features unsupported") {
+        return (ErrorKind::Incompatible, None);
+    }
+    if msg.contains("config field") || msg.contains("invalid config") {
+        return (ErrorKind::Config, None);
+    }
+    for cause in e.chain() {
+        if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+            let kind = match io.kind() {
+                IoKind::NotFound => ErrorKind::PathNotFound,
+                IoKind::WouldBlock => ErrorKind::Locked,
+                IoKind::InvalidData | IoKind::UnexpectedEof => ErrorKind::Corruption,
+                _ => ErrorKind::Io,
+            };
+            return (kind, None);
+        }
+    }
+    if msg.contains("does not exist") || msg.contains("not found at") {
+        return (ErrorKind::PathNotFound, None);
+    }
+    (ErrorKind::Error, None)
+}
@@ -8,11 +8,15 @@ mod cmd_bloom;
 mod cmd_checkpoint;
 mod cmd_compact;
 mod cmd_del;
+// NEW: undelete из корзины (trash-режим)
 mod cmd_doctor;
 mod cmd_exists;
 mod cmd_get;
 mod cmd_init;
 mod cmd_maint;
+mod cmd_undelete;
+// NEW: freeze/thaw для снапшотов тома
+mod cmd_freeze;
 mod cmd_put;
 mod cmd_scan;
 mod cmd_status;
@@ -23,26 +27,128 @@ mod util;
 // NEW: CDC modules
 mod cmd_cdc_apply;
 mod cmd_cdc_ship;
+// NEW: fan-out ship-сервер
+mod cmd_cdc_serve;
+// NEW: именованные курсоры подписчиков cdc-serve
+mod cmd_cdc_cursors;
 // NEW: Snapshots (2.2)
 mod cmd_snapshot;
 // NEW: Snapshot restore (persisted)
 mod cmd_snapshot_restore;
+// NEW: Scrub (read-only integrity scrubber)
+mod cmd_scrub;
+// NEW: WAL salvage
+mod cmd_wal_salvage;
+// NEW: WAL tail
+mod cmd_wal_tail;
+// NEW: live clone
+mod cmd_clone;
+// NEW: read-only admin UI (feature "admin-ui")
+mod cmd_admin_ui;
+// NEW: read-only FUSE mount (feature "fuse")
+mod cmd_mount;
+// NEW: streaming backup/restore
+mod cmd_backup;
+// NEW: hot key prefixes
+mod cmd_hot_keys;
+// NEW: space usage breakdown
+mod cmd_du;
+// NEW: история изменений ключа (history.bin + WAL)
+mod cmd_history;
+// NEW: digest'ы бакетов и сверка реплик
+mod cmd_digest;
+// NEW: punch hole для free-листа
+mod cmd_trim;
+// NEW: writable-ветки снапшотов
+mod cmd_branch;
+// NEW: sorted export
+mod cmd_export;
+mod cmd_upgrade;
+// NEW: effective config dump
+mod cmd_config_show;
+// NEW: общий слой вывода (--output table|plain|json)
+mod output;
+// NEW: коды выхода и JSON-объекты ошибок
+mod exit_code;
+// NEW: БД по умолчанию (QUIVERDB_PATH / `quiverdb use`)
+mod cmd_use;
+// NEW: обучение словаря zstd для значений
+mod cmd_dict_train;
+mod user_config;
+
+use std::cell::Cell;
+
+use exit_code::ErrorObject;
+use output::OutputFormat;
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("error: {:#}", e);
-        std::process::exit(1);
+    // QUIVERDB_PATH из `quiverdb use`, если ENV не задан — до разбора (clap читает ENV)
+    user_config::apply_default_path();
+    let cli = match cli::Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            use clap::error::ErrorKind as ClapKind;
+            if matches!(e.kind(), ClapKind::DisplayHelp | ClapKind::DisplayVersion) {
+                e.exit();
+            }
+            if !args_want_json() {
+                // человекочитаемая ошибка clap (с подсказками), код 2
+                e.exit();
+            }
+            let msg = e.to_string();
+            let msg = msg.lines().next().unwrap_or_default();
+            let msg = msg.strip_prefix("error: ").unwrap_or(msg).to_string();
+            std::process::exit(ErrorObject::usage(msg).report(true));
+        }
+    };
+    let db_path = cli.cmd.db_path().map(|p| p.to_path_buf());
+    let json_mode = Cell::new(cli.output == Some(OutputFormat::Json));
+    if let Err(e) = run(cli, &json_mode) {
+        let obj = ErrorObject::from_error(&e, db_path.as_deref());
+        std::process::exit(obj.report(json_mode.get()));
     }
 }
 
-fn run() -> Result<()> {
-    let cli = cli::Cli::parse();
+/// JSON-режим по сырым аргументам (для ошибок разбора, когда Cli недоступен).
+fn args_want_json() -> bool {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    args.iter().enumerate().any(|(i, a)| {
+        a == "--json"
+            || a == "--output=json"
+            || (a == "--output" && args.get(i + 1).map(|v| v == "json").unwrap_or(false))
+    })
+}
+
+fn run(cli: cli::Cli, json_mode: &Cell<bool>) -> Result<()> {
+    let output = cli.output;
+    // Формат отчёта: --output, иначе legacy --json команды
+    let fmt = |json: bool| {
+        let f = OutputFormat::resolve(output, json);
+        json_mode.set(f.is_json());
+        f
+    };
+    // Команды без plain/table-отчёта: --output json включает их JSON-режим
+    let json_of = |json: bool| fmt(json).is_json();
+    // Прогресс open-time восстановления (WAL replay) печатаем в stderr
+    util::install_recovery_progress_printer();
     match cli.cmd {
         cli::Cmd::Init {
             path,
             page_size,
             buckets,
-        } => cmd_init::exec(path, page_size, buckets),
+            bucket_key_delimiter,
+            bucket_key_prefix_len,
+            key_transform,
+            value_dict,
+        } => cmd_init::exec(
+            path,
+            page_size,
+            buckets,
+            bucket_key_delimiter,
+            bucket_key_prefix_len,
+            key_transform,
+            value_dict,
+        ),
 
         cli::Cmd::Put {
             path,
@@ -51,12 +157,19 @@ fn run() -> Result<()> {
             value_file,
         } => cmd_put::exec(path, key, value, value_file),
 
-        cli::Cmd::Get { path, key, out } => cmd_get::exec(path, key, out),
+        cli::Cmd::Get { path, key, out } => cmd_get::exec(path, key, out, json_of(false)),
 
-        cli::Cmd::Exists { path, key } => cmd_exists::exec(path, key),
+        cli::Cmd::Exists { path, key } => cmd_exists::exec(path, key, json_of(false)),
 
         cli::Cmd::Del { path, key } => cmd_del::exec(path, key),
 
+        cli::Cmd::Undelete {
+            path,
+            key,
+            list,
+            json,
+        } => cmd_undelete::exec(path, key, list, fmt(json)),
+
         cli::Cmd::Batch {
             path,
             ops_file,
@@ -68,20 +181,32 @@ fn run() -> Result<()> {
             prefix,
             json,
             stream,
-        } => cmd_scan::exec(path, prefix, json, stream),
+            keys_only,
+            limit,
+            cursor,
+        } => match limit {
+            // NEW: постраничный скан со стабильным курсором
+            Some(limit) => cmd_scan::exec_page(path, prefix, json_of(json), limit, cursor),
+            None => cmd_scan::exec(path, prefix, json_of(json), stream, keys_only),
+        },
 
-        // Status supports --json flag
-        cli::Cmd::Status { path, json } => cmd_status::exec_with_json(path, json),
+        // Status: --output / --json
+        cli::Cmd::Status { path, json } => cmd_status::exec(path, fmt(json)),
 
         cli::Cmd::Sweep { path } => cmd_sweep::exec(path),
+        cli::Cmd::Trim { path, json } => cmd_trim::exec(path, fmt(json)),
 
-        cli::Cmd::Doctor { path, json } => cmd_doctor::exec(path, json),
+        cli::Cmd::Doctor {
+            path,
+            json,
+            fix_alloc,
+        } => cmd_doctor::exec(path, fix_alloc, fmt(json)),
 
         cli::Cmd::Checkpoint { path } => cmd_checkpoint::exec(path),
 
-        cli::Cmd::Compact { path, bucket, json } => cmd_compact::exec(path, bucket, json),
+        cli::Cmd::Compact { path, bucket, json } => cmd_compact::exec(path, bucket, fmt(json)),
 
-        cli::Cmd::Vacuum { path, json } => cmd_vacuum::exec(path, json),
+        cli::Cmd::Vacuum { path, json } => cmd_vacuum::exec(path, fmt(json)),
 
         cli::Cmd::Bloom {
             path,
@@ -99,16 +224,69 @@ fn run() -> Result<()> {
             max_buckets,
             sweep,
             json,
-        } => cmd_maint::exec(path, max_buckets, sweep, json),
+        } => cmd_maint::exec(path, max_buckets, sweep, fmt(json)),
+
+        // NEW: maintenance daemon (auto-maintenance + плановые снапшоты)
+        cli::Cmd::MaintDaemon {
+            path,
+            interval,
+            max_buckets,
+            sweep,
+            snapshot_interval,
+            snapshot_retention,
+            once,
+            offsite,
+        } => cmd_maint::exec_daemon(
+            path,
+            interval,
+            max_buckets,
+            sweep,
+            snapshot_interval,
+            snapshot_retention,
+            once,
+            offsite,
+            fmt(false),
+        ),
+
+        cli::Cmd::Freeze {
+            path,
+            timeout,
+            exec,
+            json,
+        } => cmd_freeze::exec(path, timeout, exec, fmt(json)),
 
         // NEW: CDC commands wiring
-        cli::Cmd::CdcApply { path, from } => cmd_cdc_apply::exec(path, from),
+        cli::Cmd::CdcApply { path, from, force } => cmd_cdc_apply::exec(path, from, force),
 
         cli::Cmd::CdcShip {
             path,
             to,
             since_lsn,
-        } => cmd_cdc_ship::exec(path, to, since_lsn),
+            filter_prefix,
+            filter_buckets,
+        } => cmd_cdc_ship::exec(path, to, since_lsn, filter_prefix, filter_buckets),
+
+        cli::Cmd::CdcServe {
+            path,
+            listen,
+            max_subscribers,
+            buffer_mb,
+            cursor_retention_hours,
+        } => cmd_cdc_serve::exec(
+            path,
+            listen,
+            max_subscribers,
+            buffer_mb,
+            cursor_retention_hours,
+        ),
+
+        cli::Cmd::CdcCursors {
+            path,
+            reset,
+            lsn,
+            delete,
+            json,
+        } => cmd_cdc_cursors::exec(path, reset, lsn, delete, fmt(json)),
 
         // NEW: Snapshots (2.2)
         cli::Cmd::SnapshotCreate {
@@ -116,11 +294,34 @@ fn run() -> Result<()> {
             message,
             label,
             parent,
-        } => cmd_snapshot::exec_create(path, message, label, parent),
+            with_sidecars,
+            sign_kid,
+        } => cmd_snapshot::exec_create(
+            path,
+            message,
+            label,
+            parent,
+            with_sidecars,
+            sign_kid,
+            fmt(false),
+        ),
 
-        cli::Cmd::SnapshotList { path, json } => cmd_snapshot::exec_list(path, json),
+        cli::Cmd::SnapshotList { path, json } => cmd_snapshot::exec_list(path, fmt(json)),
 
-        cli::Cmd::SnapshotInspect { path, id, json } => cmd_snapshot::exec_inspect(path, id, json),
+        cli::Cmd::SnapshotInspect {
+            path,
+            id,
+            require_signature,
+            json,
+        } => cmd_snapshot::exec_inspect(path, id, require_signature, fmt(json)),
+
+        cli::Cmd::SnapshotVerify {
+            path,
+            id,
+            sample,
+            full,
+            json,
+        } => cmd_snapshot::exec_verify(path, id, sample, full, fmt(json)),
 
         // NEW: Snapshot restore
         cli::Cmd::SnapshotRestore {
@@ -128,9 +329,156 @@ fn run() -> Result<()> {
             src,
             id,
             verify,
-        } => cmd_snapshot_restore::exec(path, src, id, verify),
+            force,
+            require_signature,
+        } => cmd_snapshot_restore::exec(path, src, id, verify, force, require_signature),
 
         // NEW: Snapshot delete
         cli::Cmd::SnapshotDelete { path, id } => cmd_snapshot::exec_delete(path, id),
+
+        // NEW: writable-ветки
+        cli::Cmd::Branch {
+            path,
+            src,
+            from_snapshot,
+            json,
+        } => cmd_branch::exec(path, src, from_snapshot, fmt(json)),
+        cli::Cmd::BranchDetach { path, json } => cmd_branch::exec_detach(path, fmt(json)),
+
+        // NEW: Scrub
+        cli::Cmd::Scrub {
+            path,
+            rate_mb,
+            max_pages,
+            continuous,
+            interval_secs,
+            reset,
+            json,
+        } => cmd_scrub::exec(
+            path,
+            rate_mb,
+            max_pages,
+            continuous,
+            interval_secs,
+            reset,
+            json_of(json),
+        ),
+
+        // NEW: WAL tail
+        cli::Cmd::WalTail {
+            path,
+            file,
+            since_lsn,
+            commits_only,
+            follow,
+            json,
+        } => {
+            // path конфликтует с --file, поэтому ENV подставляется здесь, а не через clap
+            let path = match (&path, &file) {
+                (None, None) => user_config::default_path().map(|(p, _)| p),
+                _ => path,
+            };
+            cmd_wal_tail::exec(path, file, since_lsn, commits_only, follow, json_of(json))
+        }
+
+        // NEW: WAL salvage
+        cli::Cmd::WalSalvage { path, apply, json } => {
+            cmd_wal_salvage::exec(path, apply, json_of(json))
+        }
+
+        // NEW: live clone
+        cli::Cmd::Clone {
+            src,
+            dst,
+            pause_wait_ms,
+            max_rounds,
+            no_verify,
+            json,
+        } => cmd_clone::exec(
+            src,
+            dst,
+            pause_wait_ms,
+            max_rounds,
+            !no_verify,
+            json_of(json),
+        ),
+
+        // NEW: read-only admin UI
+        cli::Cmd::AdminUi { path, addr } => cmd_admin_ui::exec(path, addr),
+
+        // NEW: read-only FUSE mount
+        cli::Cmd::Mount {
+            path,
+            mountpoint,
+            prefix,
+            delimiter,
+        } => cmd_mount::exec(path, mountpoint, prefix, delimiter),
+
+        // NEW: hot key prefixes
+        cli::Cmd::HotKeys { path, top, json } => cmd_hot_keys::exec(path, top, json_of(json)),
+
+        cli::Cmd::Du { path, top, json } => cmd_du::exec(path, top, fmt(json)),
+        cli::Cmd::DictTrain {
+            path,
+            sample,
+            out,
+            max_dict_kb,
+            level,
+            json,
+        } => cmd_dict_train::exec(path, sample, out, max_dict_kb, level, json_of(json)),
+        cli::Cmd::History {
+            path,
+            key,
+            limit,
+            json,
+        } => cmd_history::exec(path, key, limit, fmt(json)),
+        cli::Cmd::Digest {
+            path,
+            bucket,
+            compare,
+            json,
+        } => cmd_digest::exec(path, bucket, compare, fmt(json)),
+
+        // NEW: streaming backup/restore
+        cli::Cmd::Backup {
+            path,
+            out,
+            since_lsn,
+            compress,
+            with_sidecars,
+            json,
+        } => cmd_backup::exec_backup(path, out, since_lsn, compress, with_sidecars, json_of(json)),
+        cli::Cmd::Restore {
+            path,
+            from,
+            verify,
+            force,
+            json,
+        } => cmd_backup::exec_restore(path, from, verify, force, json_of(json)),
+
+        // NEW: sorted export
+        cli::Cmd::ExportSorted {
+            path,
+            out,
+            mem_mb,
+            tmp_dir,
+            prefix,
+            json,
+        } => cmd_export::exec(path, out, mem_mb, tmp_dir, prefix, json_of(json)),
+        cli::Cmd::Upgrade {
+            path,
+            to,
+            dry_run,
+            json,
+        } => cmd_upgrade::exec(path, to, dry_run, json_of(json)),
+        // NEW: БД по умолчанию
+        cli::Cmd::Use { path, clear, json } => cmd_use::exec(path, clear, json_of(json)),
+
+        cli::Cmd::ConfigShow {
+            path,
+            config,
+            set,
+            json,
+        } => cmd_config_show::exec(path, config, set, json_of(json)),
     }
 }
@@ -0,0 +1,115 @@
+//! output — общий слой машиночитаемого вывода CLI (глобальный флаг --output table|plain|json).
+//!
+//! Отчёт команды — serde-структура (или serde_json::Value); слой сериализует её сам, без
+//! ручной сборки JSON-строк:
+//! - table (по умолчанию) — человекочитаемый вывод команды (печатает сама команда);
+//! - plain — плоские строки `key=value` (вложенные поля через точку, элементы массива — key.N;
+//!   корневой массив скаляров — по элементу на строку), удобно для grep/awk;
+//! - json — один pretty JSON-документ.
+//!
+//! Старые флаги --json остаются синонимом --output json; явный --output имеет приоритет.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{ErrorKind, Write};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Человекочитаемый вывод команды
+    #[default]
+    Table,
+    /// Строки key=value
+    Plain,
+    /// JSON-документ
+    Json,
+}
+
+impl OutputFormat {
+    /// Итоговый формат: --output, иначе legacy --json, иначе table.
+    pub fn resolve(output: Option<OutputFormat>, json_flag: bool) -> Self {
+        match output {
+            Some(f) => f,
+            None if json_flag => OutputFormat::Json,
+            None => OutputFormat::Table,
+        }
+    }
+
+    pub fn is_json(self) -> bool {
+        self == OutputFormat::Json
+    }
+}
+
+/// Напечатать отчёт в формате plain/json. Для table возвращает false — команда печатает
+/// свой человекочитаемый вывод сама.
+pub fn emit<T: Serialize + ?Sized>(fmt: OutputFormat, report: &T) -> Result<bool> {
+    match fmt {
+        OutputFormat::Table => Ok(false),
+        OutputFormat::Json => {
+            write_lines(&[serde_json::to_string_pretty(report)?])?;
+            Ok(true)
+        }
+        OutputFormat::Plain => {
+            write_lines(&plain_lines(&serde_json::to_value(report)?))?;
+            Ok(true)
+        }
+    }
+}
+
+/// Печать в stdout; закрытый потребитель (`| head`) — не ошибка.
+fn write_lines(lines: &[String]) -> Result<()> {
+    let mut out = std::io::stdout().lock();
+    for line in lines {
+        if let Err(e) = writeln!(out, "{line}") {
+            if e.kind() == ErrorKind::BrokenPipe {
+                return Ok(());
+            }
+            return Err(e.into());
+        }
+    }
+    Ok(())
+}
+
+/// Плоское представление значения (см. заголовок модуля).
+pub fn plain_lines(v: &Value) -> Vec<String> {
+    let mut out = Vec::new();
+    match v {
+        Value::Array(items) if items.iter().all(|x| !x.is_object() && !x.is_array()) => {
+            out.extend(items.iter().map(scalar));
+        }
+        _ => flatten("", v, &mut out),
+    }
+    out
+}
+
+fn flatten(prefix: &str, v: &Value, out: &mut Vec<String>) {
+    let join = |k: &str| {
+        if prefix.is_empty() {
+            k.to_string()
+        } else {
+            format!("{prefix}.{k}")
+        }
+    };
+    match v {
+        Value::Object(map) => {
+            for (k, x) in map {
+                flatten(&join(k), x, out);
+            }
+        }
+        Value::Array(items) => {
+            for (i, x) in items.iter().enumerate() {
+                flatten(&join(&i.to_string()), x, out);
+            }
+        }
+        _ => out.push(format!("{}={}", prefix, scalar(v))),
+    }
+}
+
+fn scalar(v: &Value) -> String {
+    match v {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
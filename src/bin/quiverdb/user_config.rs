@@ -0,0 +1,116 @@
+//! user_config — путь к БД по умолчанию для команд CLI.
+//!
+//! Источники --path (по приоритету):
+//! 1) явный --path;
+//! 2) ENV QUIVERDB_PATH;
+//! 3) путь, записанный `quiverdb use <path>` в пользовательский конфиг.
+//!
+//! Пункт 3 реализован через 2: до разбора аргументов main выставляет QUIVERDB_PATH из
+//! конфига (если ENV не задан), и clap подставляет его во все --path (`env = PATH_ENV`).
+//!
+//! Файл конфига: `<dir>/cli.json` вида `{"default_path": "/abs/db"}`, где dir —
+//! QUIVERDB_CONFIG_DIR, иначе $XDG_CONFIG_HOME/quiverdb, иначе ~/.config/quiverdb
+//! (Windows: %APPDATA%\quiverdb).
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// ENV с путём к БД по умолчанию.
+pub const PATH_ENV: &str = "QUIVERDB_PATH";
+
+const CONFIG_FILE: &str = "cli.json";
+
+/// QUIVERDB_PATH выставлен из конфига (а не пользователем) — для `quiverdb use` без аргументов.
+static APPLIED_FROM_FILE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UserConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_path: Option<PathBuf>,
+}
+
+/// Каталог пользовательского конфига CLI (None — не удалось определить домашний каталог).
+pub fn config_dir() -> Option<PathBuf> {
+    let non_empty = |k: &str| std::env::var_os(k).filter(|v| !v.is_empty());
+    if let Some(d) = non_empty("QUIVERDB_CONFIG_DIR") {
+        return Some(PathBuf::from(d));
+    }
+    if let Some(d) = non_empty("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(d).join("quiverdb"));
+    }
+    if let Some(h) = non_empty("HOME") {
+        return Some(PathBuf::from(h).join(".config").join("quiverdb"));
+    }
+    non_empty("APPDATA").map(|d| PathBuf::from(d).join("quiverdb"))
+}
+
+pub fn config_file() -> Option<PathBuf> {
+    config_dir().map(|d| d.join(CONFIG_FILE))
+}
+
+/// Прочитать конфиг. Нет файла — пустой конфиг.
+pub fn load() -> Result<UserConfig> {
+    let Some(p) = config_file() else {
+        return Ok(UserConfig::default());
+    };
+    match std::fs::read(&p) {
+        Ok(buf) => serde_json::from_slice(&buf).with_context(|| format!("parse {}", p.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(UserConfig::default()),
+        Err(e) => Err(e).with_context(|| format!("read {}", p.display())),
+    }
+}
+
+/// Записать конфиг атомарно (tmp + rename). Возвращает путь файла.
+pub fn save(cfg: &UserConfig) -> Result<PathBuf> {
+    let p = config_file().context("cannot determine user config dir (set QUIVERDB_CONFIG_DIR)")?;
+    if let Some(dir) = p.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+    }
+    let tmp = p.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(cfg)?)
+        .with_context(|| format!("write {}", tmp.display()))?;
+    std::fs::rename(&tmp, &p).with_context(|| format!("rename {}", p.display()))?;
+    Ok(p)
+}
+
+/// До разбора аргументов: QUIVERDB_PATH не задан — взять путь из конфига.
+/// Битый конфиг — [WARN], без пути по умолчанию.
+pub fn apply_default_path() {
+    if std::env::var_os(PATH_ENV).is_some() {
+        return;
+    }
+    match load() {
+        Ok(UserConfig {
+            default_path: Some(p),
+        }) => {
+            std::env::set_var(PATH_ENV, &p);
+            APPLIED_FROM_FILE.store(true, Ordering::Relaxed);
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("[WARN] user config: {:#}", e),
+    }
+}
+
+/// Путь по умолчанию и его источник ("env" | "config"); None — не задан.
+pub fn default_path() -> Option<(PathBuf, &'static str)> {
+    let p = std::env::var_os(PATH_ENV).filter(|v| !v.is_empty())?;
+    let src = if APPLIED_FROM_FILE.load(Ordering::Relaxed) {
+        "config"
+    } else {
+        "env"
+    };
+    Some((PathBuf::from(p), src))
+}
+
+/// Абсолютный путь без требования существования (canonicalize, если путь есть).
+pub fn absolutize(p: &Path) -> Result<PathBuf> {
+    if let Ok(c) = std::fs::canonicalize(p) {
+        return Ok(c);
+    }
+    if p.is_absolute() {
+        return Ok(p.to_path_buf());
+    }
+    Ok(std::env::current_dir()?.join(p))
+}
@@ -3,6 +3,8 @@ use std::fs::OpenOptions;
 use std::io::Read;
 use std::path::PathBuf;
 
+use QuiverDB::wal::{set_default_recovery_progress, RecoveryProgress, RecoveryProgressHook};
+
 pub fn decode_value_arg(arg: &str) -> Result<(Vec<u8>, &'static str)> {
     if arg == "-" {
         let mut buf = Vec::new();
@@ -84,3 +86,40 @@ pub fn read_all(p: &PathBuf) -> Result<Vec<u8>> {
     f.read_to_end(&mut buf)?;
     Ok(buf)
 }
+
+/// Печать прогресса восстановления (WAL replay при открытии writer'а) в stderr.
+/// Отключается через P1_RECOVERY_PROGRESS=0.
+pub fn install_recovery_progress_printer() {
+    let off = std::env::var("P1_RECOVERY_PROGRESS")
+        .ok()
+        .map(|s| s.to_ascii_lowercase())
+        .map(|s| s == "0" || s == "false" || s == "off" || s == "no")
+        .unwrap_or(false);
+    if off {
+        return;
+    }
+    let hook = RecoveryProgressHook::new(|p: &RecoveryProgress| {
+        if p.done {
+            eprintln!(
+                "recovery: done — frames={} pages={} lsn={} skipped={}B in {} ms",
+                p.frames_applied, p.pages_applied, p.current_lsn, p.bytes_skipped, p.elapsed_ms
+            );
+            return;
+        }
+        let eta = p
+            .eta_ms
+            .map(|ms| format!("{:.1}s", ms as f64 / 1000.0))
+            .unwrap_or_else(|| "?".to_string());
+        eprintln!(
+            "recovery: {:5.1}% ({}/{} bytes, skipped={}) frames={} lsn={} eta={}",
+            p.fraction() * 100.0,
+            p.bytes_done,
+            p.bytes_total,
+            p.bytes_skipped,
+            p.frames_applied,
+            p.current_lsn,
+            eta
+        );
+    });
+    set_default_recovery_progress(Some(hook));
+}
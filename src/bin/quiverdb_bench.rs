@@ -173,6 +173,14 @@ struct Opt {
     /// Enable TDE (AES‑GCM tag in trailer) to measure overhead.
     #[arg(long, default_value_t = false)]
     tde: bool,
+
+    /// Threads for the metrics contention phase (0 = skip): sharded counters vs one shared atomic
+    #[arg(long, default_value_t = 0)]
+    metrics_threads: usize,
+
+    /// Counter increments per thread in the metrics contention phase
+    #[arg(long, default_value_t = 1_000_000)]
+    metrics_ops: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -319,6 +327,15 @@ fn run() -> Result<()> {
     let dir = Directory::open(&opt.path)?;
     let snap = QuiverDB::metrics::snapshot();
 
+    // Phase F: contention on global metrics (после снимка — не искажает счётчики отчёта)
+    if opt.metrics_threads > 0 {
+        println!(
+            "==> Phase: metrics_contention ({} threads x {} ops)",
+            opt.metrics_threads, opt.metrics_ops
+        );
+        phases.extend(phase_metrics_contention(&opt)?);
+    }
+
     let report = BenchReport {
         phases,
         segments_bytes,
@@ -616,6 +633,76 @@ fn phase_big_get(opt: &Opt, _big_size: usize) -> Result<PhaseStats> {
     Ok(stats)
 }
 
+/// Параллельные инкременты горячего счётчика (record_cache_hit, шардированный) против
+/// одного общего AtomicU64; параллельно крутится читатель snapshot(). Проверяет, что
+/// сумма по шардам сходится с числом инкрементов.
+fn phase_metrics_contention(opt: &Opt) -> Result<Vec<PhaseStats>> {
+    use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+    static SHARED: AtomicU64 = AtomicU64::new(0);
+    let threads = opt.metrics_threads;
+    let ops = opt.metrics_ops;
+    let total = threads as u64 * ops;
+
+    let run = |name: &str, inc: fn()| -> (PhaseStats, u64) {
+        let done = AtomicBool::new(false);
+        let mut snapshots = 0u64;
+        let start = Instant::now();
+        std::thread::scope(|s| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    s.spawn(move || {
+                        for _ in 0..ops {
+                            inc();
+                        }
+                    })
+                })
+                .collect();
+            let reader = s.spawn(|| {
+                let mut n = 0u64;
+                while !done.load(Ordering::Relaxed) {
+                    std::hint::black_box(QuiverDB::metrics::snapshot());
+                    n += 1;
+                }
+                n
+            });
+            for w in workers {
+                w.join().ok();
+            }
+            done.store(true, Ordering::Relaxed);
+            snapshots = reader.join().unwrap_or(0);
+        });
+        let elapsed = start.elapsed();
+        let st = stats(name, total, elapsed, &mut []);
+        print_phase_summary(&st);
+        (st, snapshots)
+    };
+
+    let before = QuiverDB::metrics::snapshot().page_cache_hits;
+    let (sharded, snaps) = run("metrics_sharded", QuiverDB::metrics::record_cache_hit);
+    let counted = QuiverDB::metrics::snapshot().page_cache_hits - before;
+    if counted != total {
+        return Err(anyhow!(
+            "metrics_contention: sharded counter lost increments ({} != {})",
+            counted,
+            total
+        ));
+    }
+    println!("    concurrent snapshots: {}", snaps);
+
+    SHARED.store(0, Ordering::Relaxed);
+    let (atomic, _) = run("metrics_atomic", || {
+        SHARED.fetch_add(1, Ordering::Relaxed);
+    });
+    if atomic.tput_ops > 0.0 {
+        println!(
+            "    sharded/atomic throughput: {:.2}x",
+            sharded.tput_ops / atomic.tput_ops
+        );
+    }
+    Ok(vec![sharded, atomic])
+}
+
 // ---------- helpers ----------
 
 fn print_phase_summary(p: &PhaseStats) {
@@ -74,6 +74,13 @@ fn run() -> Result<()> {
             continue;
         }
 
+        // NEW: read-only admin UI (/admin, /admin/api/*) — фича "admin-ui"
+        #[cfg(feature = "admin-ui")]
+        if let Some(r) = QuiverDB::admin_ui::route(opt.path.as_deref(), &method, &url) {
+            let _ = rq.respond(QuiverDB::admin_ui::to_http(r));
+            continue;
+        }
+
         let resp = Response::from_string("not found\n").with_status_code(404);
         let _ = rq.respond(resp);
     }
@@ -323,6 +330,23 @@ fn build_metrics(path: &Option<PathBuf>) -> Result<String> {
         m.bloom_skipped_stale
     ));
 
+    out.push_str(
+        "# HELP quiverdb_bloom_auto_refreshes Automatic bloom refreshes of touched buckets.\n",
+    );
+    out.push_str("# TYPE quiverdb_bloom_auto_refreshes counter\n");
+    out.push_str(&format!(
+        "quiverdb_bloom_auto_refreshes {}\n",
+        m.bloom_auto_refreshes
+    ));
+    out.push_str(
+        "# HELP quiverdb_bloom_auto_refresh_buckets Buckets rebuilt by automatic bloom refresh.\n",
+    );
+    out.push_str("# TYPE quiverdb_bloom_auto_refresh_buckets counter\n");
+    out.push_str(&format!(
+        "quiverdb_bloom_auto_refresh_buckets {}\n",
+        m.bloom_auto_refresh_buckets
+    ));
+
     let (bc_cap, bc_len) = bloom_cache_stats();
     let (bc_hits, bc_miss) = bloom_cache_counters();
 
@@ -359,6 +383,752 @@ fn build_metrics(path: &Option<PathBuf>) -> Result<String> {
         m.lazy_compact_pages_written
     ));
 
+    // --- Scrub ---
+    out.push_str("# HELP quiverdb_scrub_pages_verified Pages verified by scrub.\n");
+    out.push_str("# TYPE quiverdb_scrub_pages_verified counter\n");
+    out.push_str(&format!(
+        "quiverdb_scrub_pages_verified {}\n",
+        m.scrub_pages_verified
+    ));
+
+    out.push_str("# HELP quiverdb_scrub_failures Pages failing CRC/AEAD/IO checks during scrub.\n");
+    out.push_str("# TYPE quiverdb_scrub_failures counter\n");
+    out.push_str(&format!("quiverdb_scrub_failures {}\n", m.scrub_failures));
+
+    out.push_str("# HELP quiverdb_scrub_passes_completed Completed full scrub passes.\n");
+    out.push_str("# TYPE quiverdb_scrub_passes_completed counter\n");
+    out.push_str(&format!(
+        "quiverdb_scrub_passes_completed {}\n",
+        m.scrub_passes_completed
+    ));
+
+    // --- Page cache prewarm ---
+    out.push_str("# HELP quiverdb_prewarm_runs Page cache prewarm runs.\n");
+    out.push_str("# TYPE quiverdb_prewarm_runs counter\n");
+    out.push_str(&format!("quiverdb_prewarm_runs {}\n", m.prewarm_runs));
+
+    out.push_str("# HELP quiverdb_prewarm_pages_loaded Pages loaded into cache by prewarm.\n");
+    out.push_str("# TYPE quiverdb_prewarm_pages_loaded counter\n");
+    out.push_str(&format!(
+        "quiverdb_prewarm_pages_loaded {}\n",
+        m.prewarm_pages_loaded
+    ));
+
+    out.push_str("# HELP quiverdb_prewarm_hits First cache hits on prewarmed pages.\n");
+    out.push_str("# TYPE quiverdb_prewarm_hits counter\n");
+    out.push_str(&format!("quiverdb_prewarm_hits {}\n", m.prewarm_hits));
+
+    // --- Readahead ---
+    out.push_str(
+        "# HELP quiverdb_readahead_fills Readahead window fills (batched sequential reads).\n",
+    );
+    out.push_str("# TYPE quiverdb_readahead_fills counter\n");
+    out.push_str(&format!("quiverdb_readahead_fills {}\n", m.readahead_fills));
+
+    out.push_str("# HELP quiverdb_readahead_pages_read Pages read by readahead window fills.\n");
+    out.push_str("# TYPE quiverdb_readahead_pages_read counter\n");
+    out.push_str(&format!(
+        "quiverdb_readahead_pages_read {}\n",
+        m.readahead_pages_read
+    ));
+
+    out.push_str("# HELP quiverdb_readahead_hits Chain pages served from the readahead window.\n");
+    out.push_str("# TYPE quiverdb_readahead_hits counter\n");
+    out.push_str(&format!("quiverdb_readahead_hits {}\n", m.readahead_hits));
+
+    // --- Commit pipelining ---
+    out.push_str("# HELP quiverdb_commit_pipelined_batches Batches committed with WAL fsync overlapped with segment writes.\n");
+    out.push_str("# TYPE quiverdb_commit_pipelined_batches counter\n");
+    out.push_str(&format!(
+        "quiverdb_commit_pipelined_batches {}\n",
+        m.commit_pipelined_batches
+    ));
+
+    // --- WAL recovery ---
+    out.push_str(
+        "# HELP quiverdb_recovery_replays WAL replays performed at open (unclean shutdown).\n",
+    );
+    out.push_str("# TYPE quiverdb_recovery_replays counter\n");
+    out.push_str(&format!(
+        "quiverdb_recovery_replays {}\n",
+        m.recovery_replays
+    ));
+    out.push_str(
+        "# HELP quiverdb_recovery_frames_applied WAL frames processed during open-time recovery.\n",
+    );
+    out.push_str("# TYPE quiverdb_recovery_frames_applied counter\n");
+    out.push_str(&format!(
+        "quiverdb_recovery_frames_applied {}\n",
+        m.recovery_frames_applied
+    ));
+    out.push_str("# HELP quiverdb_recovery_bytes_skipped WAL bytes skipped via the replay checkpoint index.\n");
+    out.push_str("# TYPE quiverdb_recovery_bytes_skipped counter\n");
+    out.push_str(&format!(
+        "quiverdb_recovery_bytes_skipped {}\n",
+        m.recovery_bytes_skipped
+    ));
+
+    // --- idempotent batches ---
+    out.push_str("# HELP quiverdb_idem_batches_skipped Batches skipped because their idempotency token was already committed (writer and follower apply).\n");
+    out.push_str("# TYPE quiverdb_idem_batches_skipped counter\n");
+    out.push_str(&format!(
+        "quiverdb_idem_batches_skipped {}\n",
+        m.idem_batches_skipped
+    ));
+    out.push_str(
+        "# HELP quiverdb_idem_tokens_recorded Idempotency tokens recorded in the token ring.\n",
+    );
+    out.push_str("# TYPE quiverdb_idem_tokens_recorded counter\n");
+    out.push_str(&format!(
+        "quiverdb_idem_tokens_recorded {}\n",
+        m.idem_tokens_recorded
+    ));
+
+    // --- TTL expiry events ---
+    out.push_str(
+        "# HELP quiverdb_expiry_events_emitted Keys purged by TTL reported to expiry subscribers\n",
+    );
+    out.push_str("# TYPE quiverdb_expiry_events_emitted counter\n");
+    out.push_str(&format!(
+        "quiverdb_expiry_events_emitted {}\n",
+        m.expiry_events_emitted
+    ));
+    out.push_str("# HELP quiverdb_expiry_frames_written EXPIRY frames written to the WAL\n");
+    out.push_str("# TYPE quiverdb_expiry_frames_written counter\n");
+    out.push_str(&format!(
+        "quiverdb_expiry_frames_written {}\n",
+        m.expiry_frames_written
+    ));
+
+    // --- HEADS_UPDATE coalescing ---
+    out.push_str("# HELP quiverdb_heads_update_frames HEADS_UPDATE frames written to the WAL\n");
+    out.push_str("# TYPE quiverdb_heads_update_frames counter\n");
+    out.push_str(&format!(
+        "quiverdb_heads_update_frames {}\n",
+        m.heads_update_frames
+    ));
+    out.push_str(
+        "# HELP quiverdb_heads_update_entries Bucket head entries written in HEADS_UPDATE frames\n",
+    );
+    out.push_str("# TYPE quiverdb_heads_update_entries counter\n");
+    out.push_str(&format!(
+        "quiverdb_heads_update_entries {}\n",
+        m.heads_update_entries
+    ));
+    out.push_str("# HELP quiverdb_heads_update_skipped Duplicate or unchanged head updates dropped before the WAL\n");
+    out.push_str("# TYPE quiverdb_heads_update_skipped counter\n");
+    out.push_str(&format!(
+        "quiverdb_heads_update_skipped {}\n",
+        m.heads_update_skipped
+    ));
+    out.push_str("# HELP quiverdb_heads_update_bytes HEADS_UPDATE payload bytes written\n");
+    out.push_str("# TYPE quiverdb_heads_update_bytes counter\n");
+    out.push_str(&format!(
+        "quiverdb_heads_update_bytes {}\n",
+        m.heads_update_bytes
+    ));
+    out.push_str("# HELP quiverdb_heads_update_bytes_legacy HEADS_UPDATE payload bytes the legacy 12 B/entry encoding would use\n");
+    out.push_str("# TYPE quiverdb_heads_update_bytes_legacy counter\n");
+    out.push_str(&format!(
+        "quiverdb_heads_update_bytes_legacy {}\n",
+        m.heads_update_bytes_legacy
+    ));
+
+    // --- Slow IO (stall detector) ---
+    out.push_str("# HELP quiverdb_io_stalls_total WAL fsync / segment IO operations slower than the stall threshold\n");
+    out.push_str("# TYPE quiverdb_io_stalls_total counter\n");
+    out.push_str(&format!("quiverdb_io_stalls_total {}\n", m.io_stalls_total));
+    out.push_str("# HELP quiverdb_io_stall_ms_total Total duration of slow IO operations (ms)\n");
+    out.push_str("# TYPE quiverdb_io_stall_ms_total counter\n");
+    out.push_str(&format!(
+        "quiverdb_io_stall_ms_total {}\n",
+        m.io_stall_ms_total
+    ));
+    out.push_str("# HELP quiverdb_io_stall_max_ms Longest slow IO operation (ms)\n");
+    out.push_str("# TYPE quiverdb_io_stall_max_ms gauge\n");
+    out.push_str(&format!("quiverdb_io_stall_max_ms {}\n", m.io_stall_max_ms));
+
+    // NEW: maintenance scheduler (maint-daemon)
+    out.push_str("# HELP quiverdb_maint_runs Auto-maintenance runs started by the scheduler\n");
+    out.push_str("# TYPE quiverdb_maint_runs counter\n");
+    out.push_str(&format!("quiverdb_maint_runs {}\n", m.maint_runs));
+    out.push_str("# HELP quiverdb_maint_snapshots_created Scheduled snapshots created\n");
+    out.push_str("# TYPE quiverdb_maint_snapshots_created counter\n");
+    out.push_str(&format!(
+        "quiverdb_maint_snapshots_created {}\n",
+        m.maint_snapshots_created
+    ));
+    out.push_str("# HELP quiverdb_maint_snapshots_failed Scheduled snapshots that failed\n");
+    out.push_str("# TYPE quiverdb_maint_snapshots_failed counter\n");
+    out.push_str(&format!(
+        "quiverdb_maint_snapshots_failed {}\n",
+        m.maint_snapshots_failed
+    ));
+    out.push_str(
+        "# HELP quiverdb_maint_snapshots_pruned Scheduled snapshots deleted by retention\n",
+    );
+    out.push_str("# TYPE quiverdb_maint_snapshots_pruned counter\n");
+    out.push_str(&format!(
+        "quiverdb_maint_snapshots_pruned {}\n",
+        m.maint_snapshots_pruned
+    ));
+    out.push_str("# HELP quiverdb_backup_sched_runs Offsite backups started by the scheduler\n");
+    out.push_str("# TYPE quiverdb_backup_sched_runs counter\n");
+    out.push_str(&format!(
+        "quiverdb_backup_sched_runs {}\n",
+        m.backup_sched_runs
+    ));
+    out.push_str("# HELP quiverdb_backup_sched_failures Offsite backup or lifecycle failures\n");
+    out.push_str("# TYPE quiverdb_backup_sched_failures counter\n");
+    out.push_str(&format!(
+        "quiverdb_backup_sched_failures {}\n",
+        m.backup_sched_failures
+    ));
+    out.push_str(
+        "# HELP quiverdb_backup_sched_pruned Offsite backups deleted by the lifecycle policy\n",
+    );
+    out.push_str("# TYPE quiverdb_backup_sched_pruned counter\n");
+    out.push_str(&format!(
+        "quiverdb_backup_sched_pruned {}\n",
+        m.backup_sched_pruned
+    ));
+    out.push_str("# HELP quiverdb_restore_tests_ok Successful offsite restore tests\n");
+    out.push_str("# TYPE quiverdb_restore_tests_ok counter\n");
+    out.push_str(&format!(
+        "quiverdb_restore_tests_ok {}\n",
+        m.restore_tests_ok
+    ));
+    out.push_str("# HELP quiverdb_restore_tests_failed Failed offsite restore tests\n");
+    out.push_str("# TYPE quiverdb_restore_tests_failed counter\n");
+    out.push_str(&format!(
+        "quiverdb_restore_tests_failed {}\n",
+        m.restore_tests_failed
+    ));
+    out.push_str("# HELP quiverdb_db_freezes Db::freeze calls (external volume snapshots)\n");
+    out.push_str("# TYPE quiverdb_db_freezes counter\n");
+    out.push_str(&format!("quiverdb_db_freezes {}\n", m.db_freezes));
+    out.push_str("# HELP quiverdb_db_frozen_ms_total Total time writers spent frozen (ms)\n");
+    out.push_str("# TYPE quiverdb_db_frozen_ms_total counter\n");
+    out.push_str(&format!(
+        "quiverdb_db_frozen_ms_total {}\n",
+        m.db_frozen_ms_total
+    ));
+    out.push_str(
+        "# HELP quiverdb_commits_rejected_frozen Commits rejected while the DB was frozen\n",
+    );
+    out.push_str("# TYPE quiverdb_commits_rejected_frozen counter\n");
+    out.push_str(&format!(
+        "quiverdb_commits_rejected_frozen {}\n",
+        m.commits_rejected_frozen
+    ));
+    out.push_str("# HELP quiverdb_writes_rejected_readonly Writes rejected on read-only handles\n");
+    out.push_str("# TYPE quiverdb_writes_rejected_readonly counter\n");
+    out.push_str(&format!(
+        "quiverdb_writes_rejected_readonly {}\n",
+        m.writes_rejected_readonly
+    ));
+    out.push_str("# HELP quiverdb_seg_grow_ops Segment file extensions\n");
+    out.push_str("# TYPE quiverdb_seg_grow_ops counter\n");
+    out.push_str(&format!("quiverdb_seg_grow_ops {}\n", m.seg_grow_ops));
+    out.push_str("# HELP quiverdb_seg_grow_bytes Bytes added by segment extensions\n");
+    out.push_str("# TYPE quiverdb_seg_grow_bytes counter\n");
+    out.push_str(&format!("quiverdb_seg_grow_bytes {}\n", m.seg_grow_bytes));
+    out.push_str(
+        "# HELP quiverdb_seg_grow_stall_us_total Time spent extending segment files (us)\n",
+    );
+    out.push_str("# TYPE quiverdb_seg_grow_stall_us_total counter\n");
+    out.push_str(&format!(
+        "quiverdb_seg_grow_stall_us_total {}\n",
+        m.seg_grow_stall_us_total
+    ));
+    out.push_str("# HELP quiverdb_seg_grow_stall_max_us Longest single segment extension (us)\n");
+    out.push_str("# TYPE quiverdb_seg_grow_stall_max_us gauge\n");
+    out.push_str(&format!(
+        "quiverdb_seg_grow_stall_max_us {}\n",
+        m.seg_grow_stall_max_us
+    ));
+    out.push_str("# HELP quiverdb_seg_fallocate_fallbacks Segment extensions that fell back from fallocate to set_len\n");
+    out.push_str("# TYPE quiverdb_seg_fallocate_fallbacks counter\n");
+    out.push_str(&format!(
+        "quiverdb_seg_fallocate_fallbacks {}\n",
+        m.seg_fallocate_fallbacks
+    ));
+    out.push_str(
+        "# HELP quiverdb_punch_hole_ops Freed pages returned to the filesystem (punch hole)\n",
+    );
+    out.push_str("# TYPE quiverdb_punch_hole_ops counter\n");
+    out.push_str(&format!("quiverdb_punch_hole_ops {}\n", m.punch_hole_ops));
+    out.push_str(
+        "# HELP quiverdb_punch_hole_bytes Bytes returned to the filesystem by punch hole\n",
+    );
+    out.push_str("# TYPE quiverdb_punch_hole_bytes counter\n");
+    out.push_str(&format!(
+        "quiverdb_punch_hole_bytes {}\n",
+        m.punch_hole_bytes
+    ));
+    out.push_str("# HELP quiverdb_punch_hole_unsupported Punch hole attempts rejected by the filesystem/platform\n");
+    out.push_str("# TYPE quiverdb_punch_hole_unsupported counter\n");
+    out.push_str(&format!(
+        "quiverdb_punch_hole_unsupported {}\n",
+        m.punch_hole_unsupported
+    ));
+    out.push_str(
+        "# HELP quiverdb_branch_page_fetches Branch pages read from the snapshot's SnapStore\n",
+    );
+    out.push_str("# TYPE quiverdb_branch_page_fetches counter\n");
+    out.push_str(&format!(
+        "quiverdb_branch_page_fetches {}\n",
+        m.branch_page_fetches
+    ));
+    out.push_str(
+        "# HELP quiverdb_history_index_runs WAL history index passes that appended entries\n",
+    );
+    out.push_str("# TYPE quiverdb_history_index_runs counter\n");
+    out.push_str(&format!(
+        "quiverdb_history_index_runs {}\n",
+        m.history_index_runs
+    ));
+    out.push_str(
+        "# HELP quiverdb_history_entries_indexed Key history entries appended to history.bin\n",
+    );
+    out.push_str("# TYPE quiverdb_history_entries_indexed counter\n");
+    out.push_str(&format!(
+        "quiverdb_history_entries_indexed {}\n",
+        m.history_entries_indexed
+    ));
+    out.push_str(
+        "# HELP quiverdb_resource_budget_denials Memory reservations refused by the resource budget\n",
+    );
+    out.push_str("# TYPE quiverdb_resource_budget_denials counter\n");
+    out.push_str(&format!(
+        "quiverdb_resource_budget_denials {}\n",
+        m.resource_budget_denials
+    ));
+    out.push_str(
+        "# HELP quiverdb_resource_budget_fd_denials File handles refused by the resource budget\n",
+    );
+    out.push_str("# TYPE quiverdb_resource_budget_fd_denials counter\n");
+    out.push_str(&format!(
+        "quiverdb_resource_budget_fd_denials {}\n",
+        m.resource_budget_fd_denials
+    ));
+    out.push_str(
+        "# HELP quiverdb_resource_budget_degradations Subsystems degraded to fit the resource budget\n",
+    );
+    out.push_str("# TYPE quiverdb_resource_budget_degradations counter\n");
+    out.push_str(&format!(
+        "quiverdb_resource_budget_degradations {}\n",
+        m.resource_budget_degradations
+    ));
+    out.push_str("# HELP quiverdb_budget_memory_bytes Bytes charged to the resource budget\n");
+    out.push_str("# TYPE quiverdb_budget_memory_bytes gauge\n");
+    out.push_str(&format!(
+        "quiverdb_budget_memory_bytes {}\n",
+        m.budget_memory_bytes
+    ));
+    out.push_str("# HELP quiverdb_budget_open_fds File handles charged to the resource budget\n");
+    out.push_str("# TYPE quiverdb_budget_open_fds gauge\n");
+    out.push_str(&format!("quiverdb_budget_open_fds {}\n", m.budget_open_fds));
+    out.push_str(
+        "# HELP quiverdb_cdc_verify_checks Follower digest cross-checks against the leader\n",
+    );
+    out.push_str("# TYPE quiverdb_cdc_verify_checks counter\n");
+    out.push_str(&format!(
+        "quiverdb_cdc_verify_checks {}\n",
+        m.cdc_verify_checks
+    ));
+    out.push_str(
+        "# HELP quiverdb_cdc_verify_divergent_buckets Buckets whose follower digest differed from the leader\n",
+    );
+    out.push_str("# TYPE quiverdb_cdc_verify_divergent_buckets counter\n");
+    out.push_str(&format!(
+        "quiverdb_cdc_verify_divergent_buckets {}\n",
+        m.cdc_verify_divergent_buckets
+    ));
+    out.push_str(
+        "# HELP quiverdb_cdc_verify_deferred Digest cross-checks deferred (leader moved past the LSN)\n",
+    );
+    out.push_str("# TYPE quiverdb_cdc_verify_deferred counter\n");
+    out.push_str(&format!(
+        "quiverdb_cdc_verify_deferred {}\n",
+        m.cdc_verify_deferred
+    ));
+    out.push_str(
+        "# HELP quiverdb_cdc_resync_buckets Follower buckets replaced with the leader's state\n",
+    );
+    out.push_str("# TYPE quiverdb_cdc_resync_buckets counter\n");
+    out.push_str(&format!(
+        "quiverdb_cdc_resync_buckets {}\n",
+        m.cdc_resync_buckets
+    ));
+    out.push_str("# HELP quiverdb_cdc_resync_pages Pages written by bucket resyncs\n");
+    out.push_str("# TYPE quiverdb_cdc_resync_pages counter\n");
+    out.push_str(&format!(
+        "quiverdb_cdc_resync_pages {}\n",
+        m.cdc_resync_pages
+    ));
+    out.push_str(
+        "# HELP quiverdb_cdc_resync_failed Bucket resyncs that failed or still diverged afterwards\n",
+    );
+    out.push_str("# TYPE quiverdb_cdc_resync_failed counter\n");
+    out.push_str(&format!(
+        "quiverdb_cdc_resync_failed {}\n",
+        m.cdc_resync_failed
+    ));
+    out.push_str(
+        "# HELP quiverdb_cdc_resync_deferred Bucket resyncs deferred (leader moved past the LSN)\n",
+    );
+    out.push_str("# TYPE quiverdb_cdc_resync_deferred counter\n");
+    out.push_str(&format!(
+        "quiverdb_cdc_resync_deferred {}\n",
+        m.cdc_resync_deferred
+    ));
+    out.push_str("# HELP quiverdb_auto_batch_flushes Batches flushed by AutoBatcher\n");
+    out.push_str("# TYPE quiverdb_auto_batch_flushes counter\n");
+    out.push_str(&format!(
+        "quiverdb_auto_batch_flushes {}\n",
+        m.auto_batch_flushes
+    ));
+    out.push_str("# HELP quiverdb_auto_batch_ops Operations flushed by AutoBatcher\n");
+    out.push_str("# TYPE quiverdb_auto_batch_ops counter\n");
+    out.push_str(&format!("quiverdb_auto_batch_ops {}\n", m.auto_batch_ops));
+    out.push_str(
+        "# HELP quiverdb_auto_batch_drop_errors AutoBatcher flushes on drop that failed (queued ops lost)\n",
+    );
+    out.push_str("# TYPE quiverdb_auto_batch_drop_errors counter\n");
+    out.push_str(&format!(
+        "quiverdb_auto_batch_drop_errors {}\n",
+        m.auto_batch_drop_errors
+    ));
+    out.push_str(
+        "# HELP quiverdb_wal_coalesce_window_us Current adaptive group-commit window (microseconds)\n",
+    );
+    out.push_str("# TYPE quiverdb_wal_coalesce_window_us gauge\n");
+    out.push_str(&format!(
+        "quiverdb_wal_coalesce_window_us {}\n",
+        m.wal_coalesce_window_us
+    ));
+    out.push_str(
+        "# HELP quiverdb_wal_coalesce_p99_us Commit latency p99 seen by the adaptive window (microseconds)\n",
+    );
+    out.push_str("# TYPE quiverdb_wal_coalesce_p99_us gauge\n");
+    out.push_str(&format!(
+        "quiverdb_wal_coalesce_p99_us {}\n",
+        m.wal_coalesce_p99_us
+    ));
+    out.push_str(
+        "# HELP quiverdb_wal_coalesce_grow Adaptive window decisions that grew the window\n",
+    );
+    out.push_str("# TYPE quiverdb_wal_coalesce_grow counter\n");
+    out.push_str(&format!(
+        "quiverdb_wal_coalesce_grow {}\n",
+        m.wal_coalesce_grow
+    ));
+    out.push_str(
+        "# HELP quiverdb_wal_coalesce_shrink Adaptive window decisions that shrank the window\n",
+    );
+    out.push_str("# TYPE quiverdb_wal_coalesce_shrink counter\n");
+    out.push_str(&format!(
+        "quiverdb_wal_coalesce_shrink {}\n",
+        m.wal_coalesce_shrink
+    ));
+    out.push_str(
+        "# HELP quiverdb_wal_coalesce_hold Adaptive window decisions that kept the window\n",
+    );
+    out.push_str("# TYPE quiverdb_wal_coalesce_hold counter\n");
+    out.push_str(&format!(
+        "quiverdb_wal_coalesce_hold {}\n",
+        m.wal_coalesce_hold
+    ));
+    out.push_str(
+        "# HELP quiverdb_db_poisoned Writer handles poisoned by a panic in a commit-critical section\n",
+    );
+    out.push_str("# TYPE quiverdb_db_poisoned counter\n");
+    out.push_str(&format!("quiverdb_db_poisoned {}\n", m.db_poisoned));
+    out.push_str(
+        "# HELP quiverdb_writes_rejected_poisoned Writes refused because the handle is poisoned\n",
+    );
+    out.push_str("# TYPE quiverdb_writes_rejected_poisoned counter\n");
+    out.push_str(&format!(
+        "quiverdb_writes_rejected_poisoned {}\n",
+        m.writes_rejected_poisoned
+    ));
+    out.push_str(
+        "# HELP quiverdb_alloc_double_use_pages Pages found double-used by the allocator check (free and in a chain, duplicate free, cross-linked)\n",
+    );
+    out.push_str("# TYPE quiverdb_alloc_double_use_pages counter\n");
+    out.push_str(&format!(
+        "quiverdb_alloc_double_use_pages {}\n",
+        m.alloc_double_use_pages
+    ));
+    out.push_str(
+        "# HELP quiverdb_alloc_freelist_rebuilds Free list rebuilds from reachability (alloc_check_fix)\n",
+    );
+    out.push_str("# TYPE quiverdb_alloc_freelist_rebuilds counter\n");
+    out.push_str(&format!(
+        "quiverdb_alloc_freelist_rebuilds {}\n",
+        m.alloc_freelist_rebuilds
+    ));
+    out.push_str("# HELP quiverdb_seg_fsync_batches Commit batches that fsynced data segments\n");
+    out.push_str("# TYPE quiverdb_seg_fsync_batches counter\n");
+    out.push_str(&format!(
+        "quiverdb_seg_fsync_batches {}\n",
+        m.seg_fsync_batches
+    ));
+    out.push_str(
+        "# HELP quiverdb_seg_fsync_calls Segment fsync/syncfs system calls issued by commit batches\n",
+    );
+    out.push_str("# TYPE quiverdb_seg_fsync_calls counter\n");
+    out.push_str(&format!("quiverdb_seg_fsync_calls {}\n", m.seg_fsync_calls));
+    out.push_str(
+        "# HELP quiverdb_seg_syncfs_calls Batches whose segments were flushed with a single syncfs\n",
+    );
+    out.push_str("# TYPE quiverdb_seg_syncfs_calls counter\n");
+    out.push_str(&format!(
+        "quiverdb_seg_syncfs_calls {}\n",
+        m.seg_syncfs_calls
+    ));
+    out.push_str(
+        "# HELP quiverdb_seg_fsync_last_batch Segment sync system calls in the last commit batch\n",
+    );
+    out.push_str("# TYPE quiverdb_seg_fsync_last_batch gauge\n");
+    out.push_str(&format!(
+        "quiverdb_seg_fsync_last_batch {}\n",
+        m.seg_fsync_last_batch
+    ));
+    out.push_str(
+        "# HELP quiverdb_scan_prefix_single_bucket Prefix scans narrowed to a single bucket by prefix routing\n",
+    );
+    out.push_str("# TYPE quiverdb_scan_prefix_single_bucket counter\n");
+    out.push_str(&format!(
+        "quiverdb_scan_prefix_single_bucket {}\n",
+        m.scan_prefix_single_bucket
+    ));
+    out.push_str("# HELP quiverdb_scan_page_calls Db::scan_page calls\n");
+    out.push_str("# TYPE quiverdb_scan_page_calls counter\n");
+    out.push_str(&format!("quiverdb_scan_page_calls {}\n", m.scan_page_calls));
+    out.push_str(
+        "# HELP quiverdb_scan_cursor_invalid Scan cursors rejected as stale or malformed\n",
+    );
+    out.push_str("# TYPE quiverdb_scan_cursor_invalid counter\n");
+    out.push_str(&format!(
+        "quiverdb_scan_cursor_invalid {}\n",
+        m.scan_cursor_invalid
+    ));
+    out.push_str(
+        "# HELP quiverdb_cdc_subscribe_events Change events yielded by in-process CDC streams\n",
+    );
+    out.push_str("# TYPE quiverdb_cdc_subscribe_events counter\n");
+    out.push_str(&format!(
+        "quiverdb_cdc_subscribe_events {}\n",
+        m.cdc_subscribe_events
+    ));
+    out.push_str("# HELP quiverdb_cdc_subscribe_gaps Gaps detected by in-process CDC streams\n");
+    out.push_str("# TYPE quiverdb_cdc_subscribe_gaps counter\n");
+    out.push_str(&format!(
+        "quiverdb_cdc_subscribe_gaps {}\n",
+        m.cdc_subscribe_gaps
+    ));
+    out.push_str(
+        "# HELP quiverdb_user_meta_writes User metadata area rewrites (meta_put/meta_del/meta_update)\n",
+    );
+    out.push_str("# TYPE quiverdb_user_meta_writes counter\n");
+    out.push_str(&format!(
+        "quiverdb_user_meta_writes {}\n",
+        m.user_meta_writes
+    ));
+    out.push_str(
+        "# HELP quiverdb_tde_key_check_failures TDE key missing or rejected by the meta check block at open\n",
+    );
+    out.push_str("# TYPE quiverdb_tde_key_check_failures counter\n");
+    out.push_str(&format!(
+        "quiverdb_tde_key_check_failures {}\n",
+        m.tde_key_check_failures
+    ));
+    out.push_str(
+        "# HELP quiverdb_tde_page_kid_missing Page reads refused because the page's TDE KID key is not loaded\n",
+    );
+    out.push_str("# TYPE quiverdb_tde_page_kid_missing counter\n");
+    out.push_str(&format!(
+        "quiverdb_tde_page_kid_missing {}\n",
+        m.tde_page_kid_missing
+    ));
+    out.push_str(
+        "# HELP quiverdb_bloom_replay_reconciled Bloom filters brought to fresh after WAL replay\n",
+    );
+    out.push_str("# TYPE quiverdb_bloom_replay_reconciled counter\n");
+    out.push_str(&format!(
+        "quiverdb_bloom_replay_reconciled {}\n",
+        m.bloom_replay_reconciled
+    ));
+    out.push_str(
+        "# HELP quiverdb_bloom_replay_invalidated Bloom filters invalidated on open (last_lsn ahead of the database)\n",
+    );
+    out.push_str("# TYPE quiverdb_bloom_replay_invalidated counter\n");
+    out.push_str(&format!(
+        "quiverdb_bloom_replay_invalidated {}\n",
+        m.bloom_replay_invalidated
+    ));
+    out.push_str(
+        "# HELP quiverdb_maint_pinned_pages_kept Pages sweep/alloc-fix kept out of the free list for scan cursors or in-progress snapshots\n",
+    );
+    out.push_str("# TYPE quiverdb_maint_pinned_pages_kept counter\n");
+    out.push_str(&format!(
+        "quiverdb_maint_pinned_pages_kept {}\n",
+        m.maint_pinned_pages_kept
+    ));
+    out.push_str("# HELP quiverdb_hot_get_hits Gets answered from the hot-get location cache\n");
+    out.push_str("# TYPE quiverdb_hot_get_hits counter\n");
+    out.push_str(&format!("quiverdb_hot_get_hits {}\n", m.hot_get_hits));
+    out.push_str(
+        "# HELP quiverdb_hot_get_misses Gets with the hot-get cache enabled that missed it\n",
+    );
+    out.push_str("# TYPE quiverdb_hot_get_misses counter\n");
+    out.push_str(&format!("quiverdb_hot_get_misses {}\n", m.hot_get_misses));
+    out.push_str(
+        "# HELP quiverdb_hot_get_invalidations Hot-get cache entries dropped (bucket written, head or page LSN changed)\n",
+    );
+    out.push_str("# TYPE quiverdb_hot_get_invalidations counter\n");
+    out.push_str(&format!(
+        "quiverdb_hot_get_invalidations {}\n",
+        m.hot_get_invalidations
+    ));
+    out.push_str(
+        "# HELP quiverdb_reflink_files Files or page ranges duplicated by reflink instead of a byte copy\n",
+    );
+    out.push_str("# TYPE quiverdb_reflink_files counter\n");
+    out.push_str(&format!("quiverdb_reflink_files {}\n", m.reflink_files));
+    out.push_str("# HELP quiverdb_reflink_bytes Bytes duplicated by reflink\n");
+    out.push_str("# TYPE quiverdb_reflink_bytes counter\n");
+    out.push_str(&format!("quiverdb_reflink_bytes {}\n", m.reflink_bytes));
+    out.push_str(
+        "# HELP quiverdb_reflink_fallbacks Reflink attempts that fell back to copying bytes\n",
+    );
+    out.push_str("# TYPE quiverdb_reflink_fallbacks counter\n");
+    out.push_str(&format!(
+        "quiverdb_reflink_fallbacks {}\n",
+        m.reflink_fallbacks
+    ));
+    out.push_str(
+        "# HELP quiverdb_tls_cert_reloads TLS listener certificate reloads after file changes\n",
+    );
+    out.push_str("# TYPE quiverdb_tls_cert_reloads counter\n");
+    out.push_str(&format!(
+        "quiverdb_tls_cert_reloads {}\n",
+        m.tls_cert_reloads
+    ));
+    out.push_str(
+        "# HELP quiverdb_tls_cert_reload_errors TLS certificate changes that failed to load\n",
+    );
+    out.push_str("# TYPE quiverdb_tls_cert_reload_errors counter\n");
+    out.push_str(&format!(
+        "quiverdb_tls_cert_reload_errors {}\n",
+        m.tls_cert_reload_errors
+    ));
+    out.push_str("# HELP quiverdb_tls_cert_expiry_warnings TLS certificate expiry warnings\n");
+    out.push_str("# TYPE quiverdb_tls_cert_expiry_warnings counter\n");
+    out.push_str(&format!(
+        "quiverdb_tls_cert_expiry_warnings {}\n",
+        m.tls_cert_expiry_warnings
+    ));
+    out.push_str(
+        "# HELP quiverdb_tls_cert_not_after_unix notAfter of the serving TLS certificate\n",
+    );
+    out.push_str("# TYPE quiverdb_tls_cert_not_after_unix gauge\n");
+    out.push_str(&format!(
+        "quiverdb_tls_cert_not_after_unix {}\n",
+        m.tls_cert_not_after_unix
+    ));
+    out.push_str(
+        "# HELP quiverdb_tls_client_cert_reloads Follower TLS client identity reloads after file changes\n",
+    );
+    out.push_str("# TYPE quiverdb_tls_client_cert_reloads counter\n");
+    out.push_str(&format!(
+        "quiverdb_tls_client_cert_reloads {}\n",
+        m.tls_client_cert_reloads
+    ));
+    out.push_str(
+        "# HELP quiverdb_tls_client_cert_reload_errors Follower TLS client identity changes that failed to load\n",
+    );
+    out.push_str("# TYPE quiverdb_tls_client_cert_reload_errors counter\n");
+    out.push_str(&format!(
+        "quiverdb_tls_client_cert_reload_errors {}\n",
+        m.tls_client_cert_reload_errors
+    ));
+    out.push_str(
+        "# HELP quiverdb_tls_client_cert_expiry_warnings Follower TLS client certificate expiry warnings\n",
+    );
+    out.push_str("# TYPE quiverdb_tls_client_cert_expiry_warnings counter\n");
+    out.push_str(&format!(
+        "quiverdb_tls_client_cert_expiry_warnings {}\n",
+        m.tls_client_cert_expiry_warnings
+    ));
+    out.push_str(
+        "# HELP quiverdb_tls_client_cert_not_after_unix notAfter of the follower TLS client certificate\n",
+    );
+    out.push_str("# TYPE quiverdb_tls_client_cert_not_after_unix gauge\n");
+    out.push_str(&format!(
+        "quiverdb_tls_client_cert_not_after_unix {}\n",
+        m.tls_client_cert_not_after_unix
+    ));
+    out.push_str(
+        "# HELP quiverdb_trash_deletes Deletes that kept the value in trash (trash_grace_secs)\n",
+    );
+    out.push_str("# TYPE quiverdb_trash_deletes counter\n");
+    out.push_str(&format!("quiverdb_trash_deletes {}\n", m.trash_deletes));
+    out.push_str("# HELP quiverdb_undeletes Values restored from trash (Db::undelete)\n");
+    out.push_str("# TYPE quiverdb_undeletes counter\n");
+    out.push_str(&format!("quiverdb_undeletes {}\n", m.undeletes));
+    out.push_str(
+        "# HELP quiverdb_page_cache_mlock_rejects Pages not cached: mlock failed or mlock cap reached\n",
+    );
+    out.push_str("# TYPE quiverdb_page_cache_mlock_rejects counter\n");
+    out.push_str(&format!(
+        "quiverdb_page_cache_mlock_rejects {}\n",
+        m.page_cache_mlock_rejects
+    ));
+    out.push_str(
+        "# HELP quiverdb_page_cache_unseal_failures Sealed cache entries dropped on tag mismatch\n",
+    );
+    out.push_str("# TYPE quiverdb_page_cache_unseal_failures counter\n");
+    out.push_str(&format!(
+        "quiverdb_page_cache_unseal_failures {}\n",
+        m.page_cache_unseal_failures
+    ));
+    out.push_str(
+        "# HELP quiverdb_page_cache_locked_bytes Page cache bytes locked in RAM (mlock)\n",
+    );
+    out.push_str("# TYPE quiverdb_page_cache_locked_bytes gauge\n");
+    out.push_str(&format!(
+        "quiverdb_page_cache_locked_bytes {}\n",
+        m.page_cache_locked_bytes
+    ));
+    let hs = QuiverDB::crypto::hardening_status();
+    out.push_str(
+        "# HELP quiverdb_crypto_locked_keys Key buffers locked in RAM (crypto hardening)\n",
+    );
+    out.push_str("# TYPE quiverdb_crypto_locked_keys gauge\n");
+    out.push_str(&format!(
+        "quiverdb_crypto_locked_keys {}\n",
+        hs.locked_regions
+    ));
+    out.push_str(
+        "# HELP quiverdb_crypto_mlock_failures Key buffers left unlocked (mlock refused)\n",
+    );
+    out.push_str("# TYPE quiverdb_crypto_mlock_failures counter\n");
+    out.push_str(&format!(
+        "quiverdb_crypto_mlock_failures {}\n",
+        hs.mlock_failures
+    ));
+    out.push_str(
+        "# HELP quiverdb_tde_epoch_key_verifies Pages verified with a previous epoch's KID key\n",
+    );
+    out.push_str("# TYPE quiverdb_tde_epoch_key_verifies counter\n");
+    out.push_str(&format!(
+        "quiverdb_tde_epoch_key_verifies {}\n",
+        m.tde_epoch_key_verifies
+    ));
+
     // --- Optional DB info from --path ---
     if let Some(root) = path {
         if root.exists() {
@@ -8,17 +8,23 @@
 //!   Это устраняет дубли для разных представлений одного и того же пути (относительные/абсолютные, symlink).
 //!
 //! Управление ёмкостью: ENV P1_BLOOM_CACHE_BUCKETS (по умолчанию 8; 0 — выключено).
+//! NEW: программно — bloom_cache_configure(cap) (DbManager задаёт общую ёмкость на все БД).
+//! NEW: байты бакетов учитываются в ResourceBudget (budget.rs, Subsystem::BloomCache): при тесном
+//! бюджете вытесняются старые бакеты, не поместившийся бакет не кэшируется.
 //!
 //! Публичный API:
 //! - bloom_cache_get(path, bucket, last_lsn) -> Option<Vec<u8>>
 //! - bloom_cache_put(path, bucket, last_lsn, bits)
 //! - bloom_cache_stats() -> (capacity, entries)
 //! - bloom_cache_counters() -> (hits, misses)
+//! - NEW: bloom_cache_configure(cap) — задать ёмкость (бакетов), лишнее вытесняется
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Mutex, OnceLock};
 
+use crate::budget::{self, Subsystem};
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 struct BloomCacheKey {
     db_id: u64,
@@ -201,6 +207,7 @@ impl BloomCache {
         let mut val = Vec::new();
 
         if let Some(mut node) = self.map.remove(&head_key) {
+            budget::release(Subsystem::BloomCache, node.value.len() as u64);
             val = node.value;
             let next = node.next.take();
             match next {
@@ -239,6 +246,8 @@ impl BloomCache {
             return;
         }
         if let Some(n) = self.map.get_mut(&key) {
+            budget::charge(Subsystem::BloomCache, bits.len() as u64);
+            budget::release(Subsystem::BloomCache, n.value.len() as u64);
             n.value = bits;
             self.move_to_back(&key);
             return;
@@ -246,6 +255,11 @@ impl BloomCache {
         while self.map.len() >= self.cap {
             let _ = self.pop_front();
         }
+        while !budget::try_charge(Subsystem::BloomCache, bits.len() as u64) {
+            if self.pop_front().is_none() {
+                return;
+            }
+        }
         self.map.insert(
             key,
             Node {
@@ -257,6 +271,16 @@ impl BloomCache {
         self.push_back(key);
     }
 
+    fn configure(&mut self, cap: usize) {
+        self.cap = cap;
+        self.inited = true;
+        while self.map.len() > self.cap {
+            if self.pop_front().is_none() {
+                break;
+            }
+        }
+    }
+
     fn stats(&mut self) -> (usize, usize) {
         self.init_if_needed();
         (self.cap, self.map.len())
@@ -308,6 +332,13 @@ pub fn bloom_cache_put(path: &Path, bucket: u32, last_lsn: u64, bits: Vec<u8>) {
     }
 }
 
+/// Задать ёмкость кэша (бакетов; 0 — выключить), перекрывает ENV P1_BLOOM_CACHE_BUCKETS.
+pub fn bloom_cache_configure(cap: usize) {
+    if let Ok(mut cg) = cache_lock().lock() {
+        cg.configure(cap);
+    }
+}
+
 /// Статистика кэша (capacity, entries).
 pub fn bloom_cache_stats() -> (usize, usize) {
     if let Ok(mut cg) = cache_lock().lock() {
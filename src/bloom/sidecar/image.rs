@@ -0,0 +1,100 @@
+//! bloom/sidecar/image — образ bloom.bin целиком для снапшотов/бэкапов (instant-ready restore).
+//!
+//! BloomImage::read(root) снимает файл под bloom-lock (консистентные заголовок и тело);
+//! install(root, last_lsn) кладёт образ в другой корень (tmp + replace_file) с переписанным
+//! last_lsn. Restore ставит образ, только если он покрывает ровно восстановленное состояние
+//! (covers: те же buckets и last_lsn == LSN снапшота/бэкапа) — иначе фильтр мог бы дать
+//! ложноотрицательный ответ, и он пропускается (sidecar перестраивается как обычно).
+//! Поддерживается только v2 (v1 не несёт last_lsn).
+
+use anyhow::{anyhow, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use super::{
+    lock_bloom_file, HDR_SIZE_V2_USIZE, MAGIC, OFF_BUCKETS, OFF_BYTES_PER_BUCKET, OFF_LAST_LSN,
+    OFF_MAGIC, OFF_VERSION, VERSION_V2,
+};
+
+/// Имя файла sidecar'а в корне БД.
+pub const BLOOM_FILE: &str = "bloom.bin";
+
+/// Снимок bloom.bin (заголовок + тело).
+#[derive(Debug, Clone)]
+pub struct BloomImage {
+    pub bytes: Vec<u8>,
+    pub buckets: u32,
+    pub last_lsn: u64,
+}
+
+impl BloomImage {
+    /// Разобрать и проверить образ (magic, v2, длина тела).
+    pub fn parse(bytes: Vec<u8>) -> Result<Self> {
+        if bytes.len() < HDR_SIZE_V2_USIZE || &bytes[OFF_MAGIC..OFF_MAGIC + 8] != MAGIC {
+            return Err(anyhow!("bloom image: bad magic/header"));
+        }
+        let version = LittleEndian::read_u32(&bytes[OFF_VERSION..OFF_VERSION + 4]);
+        if version != VERSION_V2 {
+            return Err(anyhow!("bloom image: unsupported version {}", version));
+        }
+        let buckets = LittleEndian::read_u32(&bytes[OFF_BUCKETS..OFF_BUCKETS + 4]);
+        let bpb = LittleEndian::read_u32(&bytes[OFF_BYTES_PER_BUCKET..OFF_BYTES_PER_BUCKET + 4]);
+        let want = HDR_SIZE_V2_USIZE as u64 + buckets as u64 * bpb as u64;
+        if bytes.len() as u64 != want {
+            return Err(anyhow!(
+                "bloom image: length {} != header+body {}",
+                bytes.len(),
+                want
+            ));
+        }
+        let last_lsn = LittleEndian::read_u64(&bytes[OFF_LAST_LSN..OFF_LAST_LSN + 8]);
+        Ok(Self {
+            bytes,
+            buckets,
+            last_lsn,
+        })
+    }
+
+    /// Снять образ bloom.bin корня (None — файла нет). Битый/v1 файл — ошибка.
+    pub fn read(root: &Path) -> Result<Option<Self>> {
+        let path = root.join(BLOOM_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let _lk = lock_bloom_file(root)?;
+        let bytes = std::fs::read(&path).with_context(|| format!("read {}", path.display()))?;
+        Self::parse(bytes)
+            .with_context(|| format!("bloom {}", path.display()))
+            .map(Some)
+    }
+
+    /// Образ описывает ровно состояние БД с `buckets` бакетами на LSN `lsn`.
+    #[inline]
+    pub fn covers(&self, buckets: u32, lsn: u64) -> bool {
+        self.buckets == buckets && self.last_lsn == lsn
+    }
+
+    /// Установить образ как <root>/bloom.bin с last_lsn в заголовке.
+    pub fn install(&self, root: &Path, last_lsn: u64) -> Result<()> {
+        let mut bytes = self.bytes.clone();
+        LittleEndian::write_u64(&mut bytes[OFF_LAST_LSN..OFF_LAST_LSN + 8], last_lsn);
+        let _lk = lock_bloom_file(root)?;
+        let path = root.join(BLOOM_FILE);
+        let tmp = root.join(format!("{}.tmp", BLOOM_FILE));
+        {
+            let mut f = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&tmp)
+                .with_context(|| format!("open {}", tmp.display()))?;
+            f.write_all(&bytes)?;
+            f.sync_all()?;
+        }
+        crate::util::fsx::replace_file(&tmp, &path)
+            .with_context(|| format!("rename {} -> {}", tmp.display(), path.display()))?;
+        Ok(())
+    }
+}
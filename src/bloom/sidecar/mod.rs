@@ -3,6 +3,7 @@
 //! Разнесение:
 //! - open.rs — логика открытия/создания sidecar (open_ro/open/create/open_or_create_for_db).
 //! - ops.rs  — операции (rebuild_all/rebuild_bucket/test/update/set_last_lsn/is_fresh_for_db).
+//! - image.rs — образ bloom.bin целиком (снапшоты/бэкапы, restore без холодного старта).
 //! - mod.rs  — общие типы, константы формата, ENV тумблеры, lock, view и низкоуровневые helpers.
 //!
 //! Формат (LE) v2 (P2BLM01):
@@ -18,6 +19,9 @@
 //!
 //! last_lsn в заголовке должен совпадать с meta.last_lsn БД (fresh state).
 //! Любые изменения — под файловым lock и с sync_all().
+//!
+//! NEW: RAM-копия body учитывается в ResourceBudget (budget.rs, Subsystem::BloomRam): если она не
+//! помещается, view деградирует в mmap; RO-хэндл держится, только пока есть дескриптор в бюджете.
 
 use anyhow::{anyhow, Context, Result};
 use byteorder::{ByteOrder, LittleEndian};
@@ -28,6 +32,8 @@ use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
+use crate::budget::{self, FdLease, Subsystem};
+
 // Дадим доступ подмодулям к cache API через re-export из верхнего модуля bloom::cache.
 pub use super::cache::{bloom_cache_get, bloom_cache_put};
 
@@ -110,6 +116,28 @@ pub struct BloomSidecar {
     // MMAP view на ВЕСЬ файл (если выбрали mmap режим)
     // Индексация body учитывает hdr_size.
     pub(crate) mmap: Option<Mmap>,
+
+    // NEW: учёт в ResourceBudget — байты RAM-копии body и дескриптор f_ro
+    pub(crate) ram_charged: u64,
+    pub(crate) _fd_lease: Option<FdLease>,
+}
+
+impl Drop for BloomSidecar {
+    fn drop(&mut self) {
+        budget::release(Subsystem::BloomRam, self.ram_charged);
+    }
+}
+
+/// RO-хэндл bloom.bin, если бюджет дескрипторов позволяет держать его открытым.
+pub(super) fn open_ro_handle(path: &Path) -> (Option<Mutex<File>>, Option<FdLease>) {
+    let Some(lease) = budget::try_acquire_fd() else {
+        budget::note_degradation();
+        return (None, None);
+    };
+    match OpenOptions::new().read(true).open(path) {
+        Ok(file) => (Some(Mutex::new(file)), Some(lease)),
+        Err(_) => (None, None),
+    }
 }
 
 // -------------------- общие helpers и view‑логика (для подмодулей) --------------------
@@ -121,56 +149,77 @@ impl BloomSidecar {
         let total_body_len =
             (self.meta.buckets as usize).saturating_mul(self.meta.bytes_per_bucket as usize);
 
+        self.mmap = None;
+        self.body = None;
+        budget::release(Subsystem::BloomRam, std::mem::take(&mut self.ram_charged));
+
         // При пустом body ничего не отображаем и не держим RAM‑копию
         if total_body_len == 0 {
-            self.mmap = None;
-            self.body = None;
             return Ok(());
         }
 
-        if bloom_mmap_enabled() {
-            if let Some(ref ro) = self.f_ro {
-                // Защищённо достаём метаданные и мэпим ВЕСЬ файл от offset=0
-                let file_guard = ro.lock().map_err(|_| anyhow!("bloom ro handle poisoned"))?;
-                let flen = file_guard.metadata()?.len() as usize;
-
-                // Файл должен содержать заголовок + всё body
-                if flen >= self.hdr_size_usize.saturating_add(total_body_len) {
-                    // map whole file (offset=0) — безопасно для всех платформ
-                    let mmap = unsafe {
-                        MmapOptions::new()
-                            .offset(0)
-                            .len(flen)
-                            .map(&*file_guard)
-                            .map_err(|e| anyhow!("bloom mmap reload: {}", e))?
-                    };
-                    self.mmap = Some(mmap);
-                    self.body = None;
-                    return Ok(());
-                }
-                // Если файл короче ожидаемого — fallback на RAM‑чтение body
-                drop(file_guard);
-            }
-            // Нет ro‑хэндла — fallback RAM
+        // RAM режим: читаем только body — если копия помещается в ResourceBudget
+        let mmap_mode = bloom_mmap_enabled();
+        if !mmap_mode && budget::try_charge(Subsystem::BloomRam, total_body_len as u64) {
+            self.ram_charged = total_body_len as u64;
             self.body = Some(read_body_from_path(
                 &self.path,
                 self.hdr_size_u64,
                 total_body_len,
             )?);
-            self.mmap = None;
+            return Ok(());
+        }
+        if !mmap_mode {
+            // NEW: бюджет тесен — вместо RAM-копии mmap
+            budget::note_degradation();
+        }
+
+        if let Some(mmap) = self.map_whole_file(total_body_len)? {
+            self.mmap = Some(mmap);
             return Ok(());
         }
 
-        // RAM режим: читаем только body.
+        // Файл короче ожидаемого — fallback на RAM‑чтение body (учитывается сверх лимита)
+        budget::charge(Subsystem::BloomRam, total_body_len as u64);
+        self.ram_charged = total_body_len as u64;
         self.body = Some(read_body_from_path(
             &self.path,
             self.hdr_size_u64,
             total_body_len,
         )?);
-        self.mmap = None;
         Ok(())
     }
 
+    /// Отобразить ВЕСЬ файл от offset=0 (page-aligned); None — файл короче заголовка + body.
+    /// Без RO-хэндла файл открывается на время отображения (mmap переживает close).
+    fn map_whole_file(&self, total_body_len: usize) -> Result<Option<Mmap>> {
+        let tmp;
+        let guard;
+        let file: &File = match self.f_ro {
+            Some(ref ro) => {
+                guard = ro.lock().map_err(|_| anyhow!("bloom ro handle poisoned"))?;
+                &guard
+            }
+            None => {
+                tmp = OpenOptions::new().read(true).open(&self.path)?;
+                &tmp
+            }
+        };
+        let flen = file.metadata()?.len() as usize;
+        if flen < self.hdr_size_usize.saturating_add(total_body_len) {
+            return Ok(None);
+        }
+        // map whole file (offset=0) — безопасно для всех платформ
+        let mmap = unsafe {
+            MmapOptions::new()
+                .offset(0)
+                .len(flen)
+                .map(file)
+                .map_err(|e| anyhow!("bloom mmap reload: {}", e))?
+        };
+        Ok(Some(mmap))
+    }
+
     // Записать в заголовок last_lsn (v2) под открытым файловым хэндлом — общая логика
     pub(crate) fn write_header_last_lsn_locked(
         &mut self,
@@ -291,3 +340,7 @@ fn xxh64(data: &[u8], seed: u64) -> u64 {
 
 pub mod open;
 pub mod ops;
+// NEW: образ bloom.bin для снапшотов/бэкапов
+pub mod image;
+
+pub use image::{BloomImage, BLOOM_FILE};
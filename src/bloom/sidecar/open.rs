@@ -10,19 +10,16 @@
 
 use anyhow::{anyhow, Context, Result};
 use byteorder::{ByteOrder, LittleEndian};
-use memmap2::{Mmap, MmapOptions};
 use std::fs::OpenOptions;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
-use std::sync::Mutex;
 
 use crate::db::core::Db;
 
 use super::{
-    bloom_mmap_enabled, read_body_from_path, BloomMeta, BloomSidecar, HDR_SIZE_V1_U64,
-    HDR_SIZE_V1_USIZE, HDR_SIZE_V2_U64, HDR_SIZE_V2_USIZE, MAGIC, OFF_BUCKETS,
-    OFF_BYTES_PER_BUCKET, OFF_K_HASHES, OFF_LAST_LSN, OFF_MAGIC, OFF_SEED1, OFF_SEED2, OFF_VERSION,
-    VERSION_V1, VERSION_V2,
+    open_ro_handle, BloomMeta, BloomSidecar, HDR_SIZE_V1_U64, HDR_SIZE_V1_USIZE, HDR_SIZE_V2_U64,
+    HDR_SIZE_V2_USIZE, MAGIC, OFF_BUCKETS, OFF_BYTES_PER_BUCKET, OFF_K_HASHES, OFF_LAST_LSN,
+    OFF_MAGIC, OFF_SEED1, OFF_SEED2, OFF_VERSION, VERSION_V1, VERSION_V2,
 };
 
 impl BloomSidecar {
@@ -85,45 +82,10 @@ impl BloomSidecar {
             0
         };
 
-        // RO-хэндл для mmap/fallback чтений
-        let f_ro = match OpenOptions::new().read(true).open(&path) {
-            Ok(file) => Some(Mutex::new(file)),
-            Err(_) => None,
-        };
-
-        // Выбор RAM/MMAP
-        let total_body_len = (buckets as usize).saturating_mul(bytes_per_bucket as usize);
-
-        let mut body_opt: Option<Box<[u8]>> = None;
-        let mut mmap_opt: Option<Mmap> = None;
+        // RO-хэндл для mmap/fallback чтений (если есть дескриптор в ResourceBudget)
+        let (f_ro, fd_lease) = open_ro_handle(&path);
 
-        if bloom_mmap_enabled() && total_body_len > 0 {
-            if let Some(ro) = &f_ro {
-                let file = ro.lock().map_err(|_| anyhow!("bloom ro handle poisoned"))?;
-                // Новый безопасный режим: мапим ВЕСЬ файл от offset=0 (page-aligned)
-                let flen = file.metadata()?.len() as usize;
-                if flen >= hdr_size_usize + total_body_len {
-                    let mmap = unsafe {
-                        MmapOptions::new()
-                            .offset(0)
-                            .len(flen)
-                            .map(&*file)
-                            .map_err(|e| anyhow!("bloom mmap: {}", e))?
-                    };
-                    mmap_opt = Some(mmap);
-                } else {
-                    // fallback: читаем body в RAM
-                    body_opt = Some(read_body_from_path(&path, hdr_size_u64, total_body_len)?);
-                }
-            } else {
-                body_opt = Some(read_body_from_path(&path, hdr_size_u64, total_body_len)?);
-            }
-        } else if total_body_len > 0 {
-            // RAM режим
-            body_opt = Some(read_body_from_path(&path, hdr_size_u64, total_body_len)?);
-        }
-
-        Ok(Self {
+        let mut sc = Self {
             root: root.to_path_buf(),
             path,
             meta: BloomMeta {
@@ -138,9 +100,14 @@ impl BloomSidecar {
             hdr_size_usize,
             version,
             f_ro,
-            body: body_opt,
-            mmap: mmap_opt,
-        })
+            body: None,
+            mmap: None,
+            ram_charged: 0,
+            _fd_lease: fd_lease,
+        };
+        // Выбор RAM/MMAP
+        sc.reload_views()?;
+        Ok(sc)
     }
 
     /// Создать новый bloom.bin (v2).
@@ -191,41 +158,9 @@ impl BloomSidecar {
         let _ = f.sync_all();
 
         // RO‑handle и view
-        let f_ro = match OpenOptions::new().read(true).open(&path) {
-            Ok(file) => Some(Mutex::new(file)),
-            Err(_) => None,
-        };
-
-        let total_body_len = (meta.buckets as usize).saturating_mul(meta.bytes_per_bucket as usize);
-
-        let mut body_opt: Option<Box<[u8]>> = None;
-        let mut mmap_opt: Option<Mmap> = None;
+        let (f_ro, fd_lease) = open_ro_handle(&path);
 
-        if bloom_mmap_enabled() && total_body_len > 0 {
-            if let Some(ro) = &f_ro {
-                let file = ro.lock().map_err(|_| anyhow!("bloom ro handle poisoned"))?;
-                // Новый безопасный режим: мапим ВЕСЬ файл от offset=0
-                let flen = file.metadata()?.len() as usize;
-                if flen >= HDR_SIZE_V2_USIZE + total_body_len {
-                    let mmap = unsafe {
-                        MmapOptions::new()
-                            .offset(0)
-                            .len(flen)
-                            .map(&*file)
-                            .map_err(|e| anyhow!("bloom mmap: {}", e))?
-                    };
-                    mmap_opt = Some(mmap);
-                } else {
-                    body_opt = Some(read_body_from_path(&path, HDR_SIZE_V2_U64, total_body_len)?);
-                }
-            } else {
-                body_opt = Some(read_body_from_path(&path, HDR_SIZE_V2_U64, total_body_len)?);
-            }
-        } else if total_body_len > 0 {
-            body_opt = Some(read_body_from_path(&path, HDR_SIZE_V2_U64, total_body_len)?);
-        }
-
-        Ok(Self {
+        let mut sc = Self {
             root: root.to_path_buf(),
             path,
             meta,
@@ -233,9 +168,13 @@ impl BloomSidecar {
             hdr_size_usize: HDR_SIZE_V2_USIZE,
             version: VERSION_V2,
             f_ro,
-            body: body_opt,
-            mmap: mmap_opt,
-        })
+            body: None,
+            mmap: None,
+            ram_charged: 0,
+            _fd_lease: fd_lease,
+        };
+        sc.reload_views()?;
+        Ok(sc)
     }
 
     /// Открыть или создать bloom.bin (v2) под параметры по умолчанию.
@@ -1,10 +1,15 @@
 //! bloom/sidecar/ops — операции над BloomSidecar:
 //! - rebuild_all / rebuild_bucket
 //! - test
-//! - update_bucket_bits
+//! - update_bucket_bits / update_buckets_bits
 //! - set_last_lsn
 //! - is_fresh_for_db
 //!
+//! NEW: update_buckets_bits пишет биты всех бакетов батча и только затем, после fsync,
+//! заголовок last_lsn — одним шагом. Сбой посреди обновления оставляет фильтр устаревшим
+//! (прежний last_lsn), а не «свежим» без ключей части бакетов; реплей WAL доводит его до
+//! свежего (Db::reconcile_bloom_after_replay, db/bloom_refresh.rs).
+//!
 //! Этот модуль подключён из mod.rs. Общие типы/константы/хелперы доступны через super::*.
 
 use anyhow::{anyhow, Result};
@@ -220,7 +225,17 @@ impl BloomSidecar {
         keys: &[&[u8]],
         new_last_lsn: u64,
     ) -> Result<()> {
-        if bucket >= self.meta.buckets {
+        self.update_buckets_bits(&[(bucket, keys.to_vec())], new_last_lsn)
+    }
+
+    /// Delta‑update нескольких бакетов: сначала биты всех бакетов (fsync), затем один раз
+    /// last_lsn заголовка — фильтр объявляется свежим только целиком.
+    pub fn update_buckets_bits(
+        &mut self,
+        updates: &[(u32, Vec<&[u8]>)],
+        new_last_lsn: u64,
+    ) -> Result<()> {
+        if let Some(&(bucket, _)) = updates.iter().find(|(b, _)| *b >= self.meta.buckets) {
             return Err(anyhow!(
                 "bucket {} out of range 0..{}",
                 bucket,
@@ -231,25 +246,30 @@ impl BloomSidecar {
         let _lk = lock_bloom_file(&self.root)?;
         let mut f = OpenOptions::new().read(true).write(true).open(&self.path)?;
 
-        // Read current bits
-        let mut bits = vec![0u8; self.meta.bytes_per_bucket as usize];
-        let off = self.hdr_size_u64 + (bucket as u64) * (self.meta.bytes_per_bucket as u64);
-        f.seek(SeekFrom::Start(off))?;
-        f.read_exact(&mut bits)?;
+        let bpb = self.meta.bytes_per_bucket as usize;
+        let mut written: Vec<(u32, Vec<u8>)> = Vec::with_capacity(updates.len());
+        for (bucket, keys) in updates {
+            // Read current bits
+            let mut bits = vec![0u8; bpb];
+            let off = self.hdr_size_u64 + (*bucket as u64) * (bpb as u64);
+            f.seek(SeekFrom::Start(off))?;
+            f.read_exact(&mut bits)?;
 
-        // Apply keys
-        for k in keys {
-            if !k.is_empty() {
-                self.add_key_to_bits(k, &mut bits);
+            // Apply keys
+            for k in keys {
+                if !k.is_empty() {
+                    self.add_key_to_bits(k, &mut bits);
+                }
             }
-        }
 
-        // Write bits
-        f.seek(SeekFrom::Start(off))?;
-        f.write_all(&bits)?;
+            // Write bits
+            f.seek(SeekFrom::Start(off))?;
+            f.write_all(&bits)?;
+            written.push((*bucket, bits));
+        }
         let _ = f.sync_all();
 
-        // Update header.last_lsn (v2)
+        // Update header.last_lsn (v2) — только после долговечных битов всех бакетов
         if self.version == super::VERSION_V2 {
             self.write_header_last_lsn_locked(&mut f, new_last_lsn)?;
             let _ = f.sync_all();
@@ -258,10 +278,11 @@ impl BloomSidecar {
 
         // Обновим RAM/mmap view и LRU
         self.reload_views()?;
-        bloom_cache_put(&self.path, bucket, self.meta.last_lsn, bits);
-
-        // NEW: учёт delta‑обновления Bloom — считаем байты на бакет
-        record_bloom_update(self.meta.bytes_per_bucket as u64);
+        for (bucket, bits) in written {
+            bloom_cache_put(&self.path, bucket, self.meta.last_lsn, bits);
+            // NEW: учёт delta‑обновления Bloom — считаем байты на бакет
+            record_bloom_update(self.meta.bytes_per_bucket as u64);
+        }
 
         Ok(())
     }
@@ -0,0 +1,350 @@
+//! budget — процессный бюджет ресурсов: память кэшей/буферов и долгоживущие дескрипторы.
+//!
+//! Page cache, value cache, LRU bloom-бакетов, RAM-копии bloom.bin, in-memory keydir и буферы
+//! записи сегментов раньше выбирали размер каждый сам по себе. ResourceBudget задаёт общий
+//! потолок, а подсистемы регистрируют в нём свои байты (Subsystem) и дескрипторы.
+//!
+//! Лимиты (0 — без лимита; учёт ведётся всегда, для метрик):
+//! - memory_bytes — сумма байтов всех подсистем (ENV P1_MEMORY_BUDGET_BYTES,
+//!   QuiverConfig::memory_budget_bytes);
+//! - max_fds — долгоживущие дескрипторы: LOCK каждого открытого Db и RO-хэндлы bloom.bin
+//!   (ENV P1_MAX_OPEN_FDS, QuiverConfig::max_open_fds).
+//!
+//! Бюджет процесс-широкий, как и кэши: задаётся set_resource_budget (или первым open с
+//! ненулевыми полями конфига), иначе лениво из ENV.
+//!
+//! Деградация при тесном бюджете (вместо ошибок):
+//! - page cache / value cache / LRU bloom — вытесняют СВОИ записи, пока новая не поместится;
+//!   не помещается — просто не кэшируют (prewarm вытеснять не может);
+//! - bloom.bin — RAM-копия body заменяется mmap (тест битов идёт через page cache ОС/LRU);
+//!   без свободного дескриптора RO-хэндл не держится (чтения открывают файл на время);
+//! - in-memory keydir — не строится, чтения идут по цепочкам (как при P1_MEM_KEYDIR=0);
+//! - буфер записи сегментов (P1_SEG_WRITE_BUF_MB) — уменьшается вдвое, до одной страницы.
+//!
+//! Жёсткий отказ только один: открытие Db при исчерпанном max_fds — ResourceBudgetError
+//! (достаётся из anyhow через downcast_ref).
+//!
+//! Метрики: resource_budget_denials / resource_budget_fd_denials / resource_budget_degradations,
+//! текущие budget_memory_bytes / budget_open_fds.
+
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Once;
+
+use crate::metrics::{record_budget_degradation, record_budget_denial, record_budget_fd_denial};
+
+/// Лимиты бюджета (0 — без лимита).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ResourceBudget {
+    pub memory_bytes: u64,
+    pub max_fds: u64,
+}
+
+impl ResourceBudget {
+    /// Без лимитов.
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// ENV: P1_MEMORY_BUDGET_BYTES, P1_MAX_OPEN_FDS.
+    pub fn from_env() -> Self {
+        let num = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|s| s.trim().parse::<u64>().ok())
+                .unwrap_or(0)
+        };
+        Self {
+            memory_bytes: num("P1_MEMORY_BUDGET_BYTES"),
+            max_fds: num("P1_MAX_OPEN_FDS"),
+        }
+    }
+
+    pub fn with_memory_bytes(mut self, bytes: u64) -> Self {
+        self.memory_bytes = bytes;
+        self
+    }
+
+    pub fn with_max_fds(mut self, n: u64) -> Self {
+        self.max_fds = n;
+        self
+    }
+}
+
+/// Подсистема, чьи байты учитываются в бюджете.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Subsystem {
+    PageCache,
+    ValueCache,
+    BloomCache,
+    BloomRam,
+    Keydir,
+    WriteBuffers,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 6] = [
+        Subsystem::PageCache,
+        Subsystem::ValueCache,
+        Subsystem::BloomCache,
+        Subsystem::BloomRam,
+        Subsystem::Keydir,
+        Subsystem::WriteBuffers,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Subsystem::PageCache => "page_cache",
+            Subsystem::ValueCache => "value_cache",
+            Subsystem::BloomCache => "bloom_cache",
+            Subsystem::BloomRam => "bloom_ram",
+            Subsystem::Keydir => "keydir",
+            Subsystem::WriteBuffers => "write_buffers",
+        }
+    }
+
+    fn idx(self) -> usize {
+        self as usize
+    }
+}
+
+/// Текущее потребление (снимок).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ResourceUsage {
+    pub budget: ResourceBudget,
+    pub memory_bytes: u64,
+    pub open_fds: u64,
+    /// (подсистема, байты) в порядке Subsystem::ALL.
+    pub subsystems: Vec<(&'static str, u64)>,
+}
+
+impl ResourceUsage {
+    /// Байты одной подсистемы.
+    pub fn bytes_of(&self, sub: Subsystem) -> u64 {
+        self.subsystems
+            .iter()
+            .find(|(n, _)| *n == sub.as_str())
+            .map(|(_, b)| *b)
+            .unwrap_or(0)
+    }
+}
+
+/// Открытие Db отвергнуто: исчерпан лимит дескрипторов. Достаётся из anyhow через downcast_ref.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceBudgetError {
+    pub path: PathBuf,
+    pub limit: u64,
+    pub in_use: u64,
+}
+
+impl std::fmt::Display for ResourceBudgetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "resource budget exhausted opening {}: {} of {} file handles in use (max_open_fds)",
+            self.path.display(),
+            self.in_use,
+            self.limit
+        )
+    }
+}
+
+impl std::error::Error for ResourceBudgetError {}
+
+// -------------------- состояние --------------------
+
+static INIT: Once = Once::new();
+static MEMORY_LIMIT: AtomicU64 = AtomicU64::new(0);
+static FD_LIMIT: AtomicU64 = AtomicU64::new(0);
+
+static MEMORY_USED: AtomicU64 = AtomicU64::new(0);
+static FDS_USED: AtomicU64 = AtomicU64::new(0);
+static SUB_USED: [AtomicU64; 6] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+
+fn ensure_init() {
+    INIT.call_once(|| store_budget(ResourceBudget::from_env()));
+}
+
+fn store_budget(b: ResourceBudget) {
+    MEMORY_LIMIT.store(b.memory_bytes, Ordering::Relaxed);
+    FD_LIMIT.store(b.max_fds, Ordering::Relaxed);
+}
+
+/// Задать бюджет процесса целиком (0 в поле — без лимита). Уже занятое не отбирается:
+/// подсистемы ужимаются при следующих вставках.
+pub fn set_resource_budget(b: ResourceBudget) {
+    INIT.call_once(|| {});
+    store_budget(b);
+}
+
+/// Текущий бюджет (ENV, если не задан программно).
+pub fn resource_budget() -> ResourceBudget {
+    ensure_init();
+    ResourceBudget {
+        memory_bytes: MEMORY_LIMIT.load(Ordering::Relaxed),
+        max_fds: FD_LIMIT.load(Ordering::Relaxed),
+    }
+}
+
+/// Применить ненулевые поля конфига (open_*; нулевые поля бюджет не трогают).
+pub(crate) fn apply_config(memory_bytes: u64, max_fds: u64) {
+    if memory_bytes == 0 && max_fds == 0 {
+        return;
+    }
+    let mut b = resource_budget();
+    if memory_bytes > 0 {
+        b.memory_bytes = memory_bytes;
+    }
+    if max_fds > 0 {
+        b.max_fds = max_fds;
+    }
+    set_resource_budget(b);
+}
+
+/// Снимок потребления.
+pub fn resource_usage() -> ResourceUsage {
+    ResourceUsage {
+        budget: resource_budget(),
+        memory_bytes: MEMORY_USED.load(Ordering::Relaxed),
+        open_fds: FDS_USED.load(Ordering::Relaxed),
+        subsystems: Subsystem::ALL
+            .iter()
+            .map(|s| (s.as_str(), SUB_USED[s.idx()].load(Ordering::Relaxed)))
+            .collect(),
+    }
+}
+
+/// Сколько байтов ещё помещается (None — лимита нет).
+pub fn memory_headroom() -> Option<u64> {
+    let limit = resource_budget().memory_bytes;
+    (limit > 0).then(|| limit.saturating_sub(MEMORY_USED.load(Ordering::Relaxed)))
+}
+
+// -------------------- память --------------------
+
+/// Зарезервировать bytes за подсистемой; false — не помещается в memory_bytes.
+pub fn try_charge(sub: Subsystem, bytes: u64) -> bool {
+    let limit = resource_budget().memory_bytes;
+    let ok = MEMORY_USED
+        .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+            let next = used.saturating_add(bytes);
+            (limit == 0 || next <= limit).then_some(next)
+        })
+        .is_ok();
+    if ok {
+        SUB_USED[sub.idx()].fetch_add(bytes, Ordering::Relaxed);
+    } else {
+        record_budget_denial();
+    }
+    ok
+}
+
+/// Учесть bytes без проверки лимита (память, без которой подсистема не работает).
+pub fn charge(sub: Subsystem, bytes: u64) {
+    MEMORY_USED.fetch_add(bytes, Ordering::AcqRel);
+    SUB_USED[sub.idx()].fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// Вернуть ранее учтённые bytes.
+pub fn release(sub: Subsystem, bytes: u64) {
+    if bytes == 0 {
+        return;
+    }
+    let sat_sub = |v: u64| Some(v.saturating_sub(bytes));
+    let _ = MEMORY_USED.fetch_update(Ordering::AcqRel, Ordering::Acquire, sat_sub);
+    let _ = SUB_USED[sub.idx()].fetch_update(Ordering::AcqRel, Ordering::Acquire, sat_sub);
+}
+
+/// Отметить деградацию подсистемы из-за бюджета (метрика).
+pub(crate) fn note_degradation() {
+    record_budget_degradation();
+}
+
+/// Временная аренда байтов (RAII): освобождается в Drop.
+#[derive(Debug)]
+pub struct MemoryLease {
+    sub: Subsystem,
+    bytes: u64,
+}
+
+impl MemoryLease {
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+impl Drop for MemoryLease {
+    fn drop(&mut self) {
+        release(self.sub, self.bytes);
+    }
+}
+
+/// Арендовать до `want` байтов: при нехватке размер делится пополам до `min`;
+/// `min` выдаётся всегда (сверх лимита, как charge).
+pub fn lease(sub: Subsystem, want: u64, min: u64) -> MemoryLease {
+    let min = min.min(want);
+    let mut n = want;
+    while n > min {
+        if try_charge(sub, n) {
+            if n < want {
+                note_degradation();
+            }
+            return MemoryLease { sub, bytes: n };
+        }
+        n = (n / 2).max(min);
+    }
+    if min < want {
+        note_degradation();
+    }
+    charge(sub, min);
+    MemoryLease { sub, bytes: min }
+}
+
+// -------------------- дескрипторы --------------------
+
+/// Учтённый дескриптор (RAII): освобождается в Drop.
+#[derive(Debug)]
+pub struct FdLease(());
+
+impl Drop for FdLease {
+    fn drop(&mut self) {
+        let _ = FDS_USED.fetch_update(Ordering::AcqRel, Ordering::Acquire, |v| {
+            Some(v.saturating_sub(1))
+        });
+    }
+}
+
+/// Занять дескриптор; None — лимит max_fds исчерпан.
+pub fn try_acquire_fd() -> Option<FdLease> {
+    let limit = resource_budget().max_fds;
+    let ok = FDS_USED
+        .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+            (limit == 0 || used < limit).then_some(used + 1)
+        })
+        .is_ok();
+    if !ok {
+        record_budget_fd_denial();
+        return None;
+    }
+    Some(FdLease(()))
+}
+
+/// Дескриптор под LOCK открываемого Db; при исчерпании — ResourceBudgetError.
+pub(crate) fn acquire_db_fd(root: &std::path::Path) -> anyhow::Result<FdLease> {
+    try_acquire_fd().ok_or_else(|| {
+        ResourceBudgetError {
+            path: root.to_path_buf(),
+            limit: resource_budget().max_fds,
+            in_use: FDS_USED.load(Ordering::Relaxed),
+        }
+        .into()
+    })
+}
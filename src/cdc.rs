@@ -0,0 +1,526 @@
+//! cdc — асинхронный поток логических CDC-событий живой БД для встраивающих сервисов
+//! (фича "async").
+//!
+//! cdc::subscribe(path, since_lsn) хвостит WAL живой БД в том же процессе — без CLI cdc-ship и
+//! TCP — и отдаёт закоммиченные изменения как ChangeEvent (put / del / expire) в порядке WAL.
+//! Блокировку БД поток не берёт и пишущему хэндлу не мешает.
+//!
+//! Разбор (как у wal/history.rs — только батчи BEGIN..COMMIT с COMMIT LSN > since_lsn):
+//! - записи KV-страниц кадров PAGE_IMAGE — put / del (tombstone); значение OVERFLOW собирается из
+//!   страниц того же батча (plain/zstd), иначе value = None; страницы области метаданных
+//!   приложения (Db::meta_put) пропускаются;
+//! - ключи логического кадра EXPIRY (QuiverConfig::wal_expiry_events) — expire;
+//! - порядок внутри батча Db::batch — по бакетам в порядке первой операции бакета, внутри
+//!   бакета — в порядке операций (db/batch.rs пишет страницы бакетов в этом порядке);
+//! - повтор той же версии ключа в кадрах одного батча выдаётся один раз; между батчами поток
+//!   состояния не держит: копии компактации/vacuum приходят как повторный put с тем же
+//!   значением (upsert-потребителю это безразлично), повторный put того же значения — тоже.
+//!
+//! Ротация WAL (WAL_ROTATE_SIZE, checkpoint, закрытие writer'а) усекает файл: поток
+//! перечитывает его с начала, пропуская уже выданные батчи по LSN. Если первый батч после
+//! since_lsn начинается не с since_lsn+1 (WAL усечён раньше, чем поток его дочитал, либо
+//! bulk load мимо WAL), поток выдаёт ChangeStreamGapError и продолжает дальше. Гарантированную
+//! доставку с персистентным курсором дают cdc-ship / cdc-serve; delete_prefix (range tombstones)
+//! в WAL не попадает и потоком не виден.
+//!
+//! NEW: значения БД со встроенной трансформацией значений (db/value_transform.rs, id из meta)
+//! раскодируются; с пользовательской трансформацией ChangeEvent::value — байты хранения.
+//! Ошибка декодирования — Err вместо события, поток не завершается.
+//!
+//! ChangeStream не привязан к рантайму: реализует futures_core::Stream
+//! (Item = Result<ChangeEvent>; StreamExt и комбинаторы futures/tokio-stream работают напрямую),
+//! плюс async-метод next() и неблокирующий try_next() без внешних трейтов. Пробуждение —
+//! фоновый поток-наблюдатель, который раз в poll_interval сверяет длину WAL и будит
+//! зарегистрированный Waker (поток завершается вместе с ChangeStream).
+
+use anyhow::{anyhow, Context as _, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::hash::Hasher;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use crate::db::user_meta::is_user_meta_page;
+use crate::db::value_transform::{builtin_value_transform, ValueTransform};
+use crate::dir::NO_PAGE;
+use crate::metrics::{record_cdc_subscribe_events, record_cdc_subscribe_gap};
+use crate::page::kv::kv_for_each_record;
+use crate::page::{ovf_header_read_v3, OFF_TYPE, OVF_HDR_MIN, PAGE_MAGIC, PAGE_TYPE_KV_RH3};
+use crate::util::decode_ovf_placeholder_v3;
+use crate::wal::reader::WalStreamReader;
+use crate::wal::{
+    parse_expiry_payload, wal_path, CommitTimestamp, WAL_HDR_SIZE, WAL_MAGIC, WAL_REC_BEGIN,
+    WAL_REC_COMMIT, WAL_REC_EXPIRY, WAL_REC_PAGE_IMAGE,
+};
+
+/// Интервал проверки WAL наблюдателем по умолчанию.
+pub const CDC_SUBSCRIBE_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Вид логического изменения.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeOp {
+    Put,
+    Del,
+    Expire,
+}
+
+/// Логическое изменение ключа из закоммиченного батча.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeEvent {
+    pub op: ChangeOp,
+    /// Ключ в канонической форме (как лежит в БД).
+    pub key: Vec<u8>,
+    /// Put — значение (None — OVERFLOW-цепочки нет в батче); Del/Expire — None.
+    pub value: Option<Vec<u8>>,
+    /// Put — TTL записи (0 — бессрочно); Del — срок корзины (trash); Expire — момент истечения.
+    pub expires_at_sec: u32,
+    /// LSN страницы с записью (Expire — LSN кадра).
+    pub lsn: u64,
+    /// LSN COMMIT'а батча.
+    pub batch_lsn: u64,
+    /// Время коммита (unix ms; 0 — COMMIT без метки времени).
+    pub commit_unix_ms: u64,
+}
+
+/// Между since_lsn и следующим батчем WAL есть пропуск: часть изменений потоком не выдана.
+/// Приходит элементом потока (Err), поток продолжается.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeStreamGapError {
+    pub path: PathBuf,
+    /// Последний выданный (или заданный) LSN.
+    pub after_lsn: u64,
+    /// BEGIN LSN следующего прочитанного батча.
+    pub next_lsn: u64,
+}
+
+impl fmt::Display for ChangeStreamGapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "CDC stream gap at {}: next batch starts at LSN {}, expected {} \
+             (WAL was truncated before it was read, or a bulk load bypassed the WAL)",
+            self.path.display(),
+            self.next_lsn,
+            self.after_lsn.wrapping_add(1)
+        )
+    }
+}
+
+impl std::error::Error for ChangeStreamGapError {}
+
+/// Подписаться на изменения БД в path с COMMIT LSN > since_lsn (0 — всё, что есть в WAL).
+/// ChangeStream — futures_core::Stream<Item = Result<ChangeEvent>>.
+pub fn subscribe(path: &Path, since_lsn: u64) -> Result<ChangeStream> {
+    if !path.join("meta").exists() {
+        return Err(anyhow!("no database at {}", path.display()));
+    }
+    let id = crate::meta::read_meta(path)?.value_transform;
+    let values = match id.is_empty() {
+        true => None,
+        false => builtin_value_transform(path, &id)?,
+    };
+    Ok(ChangeStream {
+        root: path.to_path_buf(),
+        since_lsn,
+        check_gap: since_lsn > 0,
+        file: None,
+        pos: WAL_HDR_SIZE as u64,
+        rdr: WalStreamReader::new(),
+        batch: Batch::default(),
+        ready: VecDeque::new(),
+        values,
+        poll_interval: CDC_SUBSCRIBE_POLL_INTERVAL,
+        watch: None,
+        done: false,
+    })
+}
+
+/// Поток ChangeEvent (см. модульный комментарий). После ошибки чтения WAL поток завершается;
+/// ChangeStreamGapError — не завершает.
+pub struct ChangeStream {
+    root: PathBuf,
+    /// COMMIT LSN последнего выданного батча.
+    since_lsn: u64,
+    /// Проверять непрерывность перед следующим батчем (since задан или был reset WAL).
+    check_gap: bool,
+    file: Option<File>,
+    pos: u64,
+    rdr: WalStreamReader,
+    batch: Batch,
+    ready: VecDeque<Result<ChangeEvent>>,
+    /// Встроенная трансформация значений БД (None — значения как в WAL).
+    values: Option<Arc<dyn ValueTransform>>,
+    poll_interval: Duration,
+    watch: Option<Arc<Watch>>,
+    done: bool,
+}
+
+/// Записи текущего (ещё не закоммиченного) батча.
+#[derive(Default)]
+struct Batch {
+    open: bool,
+    begin_lsn: u64,
+    records: Vec<RawRecord>,
+    ovf_pages: HashMap<u64, Vec<u8>>,
+}
+
+struct RawRecord {
+    op: ChangeOp,
+    key: Vec<u8>,
+    value: Vec<u8>,
+    expires_at_sec: u32,
+    vflags: u8,
+    lsn: u64,
+}
+
+/// Общее состояние с потоком-наблюдателем.
+struct Watch {
+    waker: Mutex<Option<Waker>>,
+    seen_len: AtomicU64,
+    closed: AtomicBool,
+}
+
+impl ChangeStream {
+    /// Интервал проверки WAL наблюдателем (по умолчанию CDC_SUBSCRIBE_POLL_INTERVAL).
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval.max(Duration::from_millis(1));
+        self
+    }
+
+    /// COMMIT LSN последнего выданного батча (продолжение после переподписки).
+    pub fn last_lsn(&self) -> u64 {
+        self.since_lsn
+    }
+
+    /// Следующее событие без ожидания: Ok(None) — новых закоммиченных изменений пока нет.
+    pub fn try_next(&mut self) -> Result<Option<ChangeEvent>> {
+        if self.ready.is_empty() && !self.done {
+            if let Err(e) = self.fill() {
+                self.done = true;
+                return Err(e);
+            }
+        }
+        self.ready.pop_front().transpose()
+    }
+
+    /// futures_core::Stream::poll_next без Pin: Pending — ждём роста WAL (разбудит наблюдатель).
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<ChangeEvent>>> {
+        if self.done && self.ready.is_empty() {
+            return Poll::Ready(None);
+        }
+        // Waker регистрируется до чтения: рост WAL между чтением и Pending не теряется
+        let watch = self.watch();
+        *watch.waker.lock().unwrap_or_else(|e| e.into_inner()) = Some(cx.waker().clone());
+        match self.try_next() {
+            Ok(Some(ev)) => Poll::Ready(Some(Ok(ev))),
+            Ok(None) => Poll::Pending,
+            Err(e) => Poll::Ready(Some(Err(e))),
+        }
+    }
+
+    /// Следующее событие (ждёт, пока оно появится). None — поток завершён.
+    pub async fn next(&mut self) -> Option<Result<ChangeEvent>> {
+        std::future::poll_fn(|cx| self.poll_next(cx)).await
+    }
+
+    fn watch(&mut self) -> Arc<Watch> {
+        if let Some(w) = self.watch.as_ref() {
+            return w.clone();
+        }
+        let w = Arc::new(Watch {
+            waker: Mutex::new(None),
+            seen_len: AtomicU64::new(self.pos),
+            closed: AtomicBool::new(false),
+        });
+        let (shared, wal, interval) = (w.clone(), wal_path(&self.root), self.poll_interval);
+        std::thread::spawn(move || {
+            while !shared.closed.load(Ordering::Acquire) {
+                std::thread::sleep(interval);
+                let len = std::fs::metadata(&wal).map(|m| m.len()).unwrap_or(0);
+                if len != shared.seen_len.load(Ordering::Acquire) {
+                    let waker = shared
+                        .waker
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .take();
+                    if let Some(waker) = waker {
+                        waker.wake();
+                    }
+                }
+            }
+        });
+        self.watch = Some(w.clone());
+        w
+    }
+
+    /// Дочитать WAL до конца, переложив закоммиченные батчи в ready.
+    fn fill(&mut self) -> Result<()> {
+        let wp = wal_path(&self.root);
+        if self.file.is_none() {
+            let mut f = match OpenOptions::new().read(true).open(&wp) {
+                Ok(f) => f,
+                // WAL ещё не создан — изменений нет
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+                Err(e) => return Err(anyhow!("open WAL {}: {}", wp.display(), e)),
+            };
+            if f.metadata()?.len() < WAL_HDR_SIZE as u64 {
+                return Ok(());
+            }
+            let mut hdr = [0u8; WAL_HDR_SIZE];
+            f.read_exact(&mut hdr)
+                .with_context(|| format!("read WAL header {}", wp.display()))?;
+            if &hdr[..8] != WAL_MAGIC {
+                return Err(anyhow!("bad WAL magic in {}", wp.display()));
+            }
+            self.file = Some(f);
+            self.reset_position();
+        }
+
+        let mut retried = false;
+        loop {
+            let f = self.file.as_mut().expect("WAL file is open");
+            let len = f.metadata()?.len();
+            if len < self.pos {
+                // WAL усечён (ротация/checkpoint) — сначала, уже выданное отсеет LSN
+                self.reset_position();
+                continue;
+            }
+            match self.rdr.read_next(f, self.pos, len) {
+                Ok(Some((rec, next))) => {
+                    self.pos = next;
+                    self.on_record(rec.rec_type, rec.lsn, rec.page_id, rec.payload);
+                }
+                Ok(None) => {
+                    if let Some(w) = self.watch.as_ref() {
+                        w.seen_len.store(len, Ordering::Release);
+                    }
+                    return Ok(());
+                }
+                // Усечение с последующим ростом за нашу позицию: позиция смотрит в середину кадра
+                Err(_) if !retried => {
+                    retried = true;
+                    self.reset_position();
+                }
+                Err(e) => {
+                    return Err(e.context(format!("read WAL {} at {}", wp.display(), self.pos)))
+                }
+            }
+        }
+    }
+
+    fn reset_position(&mut self) {
+        if self.pos != WAL_HDR_SIZE as u64 {
+            // Что-то могло быть усечено до прочтения — следующий батч сверим с since_lsn
+            self.check_gap = true;
+        }
+        self.pos = WAL_HDR_SIZE as u64;
+        self.rdr.reset_stream();
+        self.batch = Batch::default();
+    }
+
+    fn on_record(&mut self, rec_type: u8, lsn: u64, page_id: u64, payload: Vec<u8>) {
+        match rec_type {
+            WAL_REC_BEGIN => {
+                self.batch = Batch {
+                    open: true,
+                    begin_lsn: lsn,
+                    ..Default::default()
+                };
+            }
+            WAL_REC_PAGE_IMAGE if self.batch.open => {
+                if is_kv_page(&payload) {
+                    collect_kv_records(&payload, lsn, &mut self.batch.records);
+                } else if ovf_header_read_v3(&payload).is_ok() {
+                    self.batch.ovf_pages.insert(page_id, payload);
+                }
+            }
+            WAL_REC_EXPIRY if self.batch.open => {
+                for ev in parse_expiry_payload(&payload, lsn) {
+                    self.batch.records.push(RawRecord {
+                        op: ChangeOp::Expire,
+                        key: ev.key,
+                        value: Vec::new(),
+                        expires_at_sec: ev.expired_at,
+                        vflags: 0,
+                        lsn,
+                    });
+                }
+            }
+            WAL_REC_COMMIT if self.batch.open => {
+                let batch = std::mem::take(&mut self.batch);
+                if lsn > self.since_lsn {
+                    let ts = CommitTimestamp::decode(&payload)
+                        .map(|t| t.wall_ms)
+                        .unwrap_or(0);
+                    self.publish(batch, lsn, ts);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn publish(&mut self, batch: Batch, batch_lsn: u64, commit_unix_ms: u64) {
+        if self.check_gap && batch.begin_lsn > self.since_lsn.wrapping_add(1) {
+            record_cdc_subscribe_gap();
+            self.ready.push_back(Err(anyhow!(ChangeStreamGapError {
+                path: self.root.clone(),
+                after_lsn: self.since_lsn,
+                next_lsn: batch.begin_lsn,
+            })));
+        }
+        self.check_gap = false;
+        self.since_lsn = batch_lsn;
+
+        let mut n = 0u64;
+        // xxhash64(ключ) → дайджест последней версии в этом батче (повторные кадры батча)
+        let mut last_digest: HashMap<u64, u64> = HashMap::new();
+        for r in batch.records {
+            let kh = key_hash(&r.key);
+            let value = match r.op {
+                ChangeOp::Expire => {
+                    last_digest.remove(&kh);
+                    None
+                }
+                ChangeOp::Put | ChangeOp::Del => {
+                    let digest = value_digest(&r.value, r.expires_at_sec, r.vflags);
+                    // Та же версия уже выдана в этом батче
+                    if last_digest.insert(kh, digest) == Some(digest) {
+                        continue;
+                    }
+                    match r.op {
+                        ChangeOp::Put => match decode_ovf_placeholder_v3(&r.value) {
+                            Some((total, head)) => {
+                                assemble_overflow(&batch.ovf_pages, head, total as usize)
+                            }
+                            None => Some(r.value),
+                        },
+                        _ => None,
+                    }
+                }
+            };
+            let value = match (value, self.values.as_ref()) {
+                (Some(v), Some(t)) => match t.decode(&v) {
+                    Ok(d) => Some(d.into_owned()),
+                    Err(e) => {
+                        self.ready
+                            .push_back(Err(e.context(format!("cdc value at lsn {}", r.lsn))));
+                        continue;
+                    }
+                },
+                (v, _) => v,
+            };
+            self.ready.push_back(Ok(ChangeEvent {
+                op: r.op,
+                key: r.key,
+                value,
+                expires_at_sec: r.expires_at_sec,
+                lsn: r.lsn,
+                batch_lsn,
+                commit_unix_ms,
+            }));
+            n += 1;
+        }
+        record_cdc_subscribe_events(n);
+    }
+}
+
+impl futures_core::Stream for ChangeStream {
+    type Item = Result<ChangeEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        ChangeStream::poll_next(self.get_mut(), cx)
+    }
+}
+
+impl Drop for ChangeStream {
+    fn drop(&mut self) {
+        if let Some(w) = self.watch.as_ref() {
+            w.closed.store(true, Ordering::Release);
+        }
+    }
+}
+
+impl fmt::Debug for ChangeStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChangeStream")
+            .field("root", &self.root)
+            .field("since_lsn", &self.since_lsn)
+            .field("pos", &self.pos)
+            .field("ready", &self.ready.len())
+            .finish()
+    }
+}
+
+// ---------- internals ----------
+
+fn is_kv_page(page: &[u8]) -> bool {
+    page.len() >= OFF_TYPE + 2
+        && &page[0..4] == PAGE_MAGIC
+        && LittleEndian::read_u16(&page[OFF_TYPE..OFF_TYPE + 2]) == PAGE_TYPE_KV_RH3
+        // Страницы метаданных приложения (Db::meta_put) — не пользовательские ключи
+        && !is_user_meta_page(page)
+}
+
+/// Записи KV-страницы в порядке старые → новые.
+fn collect_kv_records(page: &[u8], lsn: u64, out: &mut Vec<RawRecord>) {
+    let first = out.len();
+    kv_for_each_record(page, |k, v, expires_at_sec, vflags| {
+        let tomb = (vflags & 0x1) == 1;
+        out.push(RawRecord {
+            op: if tomb { ChangeOp::Del } else { ChangeOp::Put },
+            key: k.to_vec(),
+            value: if tomb { Vec::new() } else { v.to_vec() },
+            expires_at_sec,
+            vflags,
+            lsn,
+        });
+    });
+    // kv_for_each_record обходит слоты новые → старые
+    out[first..].reverse();
+}
+
+fn key_hash(key: &[u8]) -> u64 {
+    let mut h = twox_hash::XxHash64::with_seed(0);
+    h.write(key);
+    h.finish()
+}
+
+fn value_digest(v: &[u8], expires_at_sec: u32, vflags: u8) -> u64 {
+    let mut h = twox_hash::XxHash64::with_seed(0);
+    h.write(v);
+    h.write_u32(expires_at_sec);
+    h.write_u8(vflags);
+    h.finish()
+}
+
+/// Значение OVERFLOW-цепочки из страниц батча (None — страницы нет в батче или она битая).
+fn assemble_overflow(pages: &HashMap<u64, Vec<u8>>, mut pid: u64, total: usize) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(total.min(8 * 1024 * 1024));
+    let mut guard = 0usize;
+    while pid != NO_PAGE {
+        guard += 1;
+        if guard > pages.len() {
+            return None;
+        }
+        let page = pages.get(&pid)?;
+        let h = ovf_header_read_v3(page).ok()?;
+        let chunk = page.get(OVF_HDR_MIN..OVF_HDR_MIN + h.chunk_len as usize)?;
+        match h.codec_id {
+            0 => out.extend_from_slice(chunk),
+            1 => out.extend_from_slice(&zstd::stream::decode_all(chunk).ok()?),
+            _ => return None,
+        }
+        if out.len() > total {
+            return None;
+        }
+        pid = h.next_page_id;
+    }
+    (out.len() == total).then_some(out)
+}
@@ -14,6 +14,98 @@
 //! - tde_enabled (ENV P1_TDE_ENABLED)
 //! - tde_kid (ENV P1_TDE_KID) — KID to use (e.g., with EnvKeyProvider)
 //!
+//! NEW: cache_prewarm (ENV P1_CACHE_PREWARM) — hot page list is saved on clean shutdown and
+//! the page cache is prewarmed in the background on open.
+//!
+//! NEW: wal_expiry_events (ENV P1_WAL_EXPIRY_EVENTS) — компактация пишет в WAL логический кадр
+//! EXPIRY с ключами, вычищенными по TTL (для follower'ов и CDC-потребителей).
+//!
+//! NEW: wal_heads_delta (ENV P1_WAL_HEADS_DELTA) — opt-in компактного delta-формата HEADS_UPDATE
+//! (wal/heads.rs): writer проставляет required-бит FEATURE_HEADS_DELTA в meta, после чего пишет
+//! delta; старые сборки (и follower'ы, читающие WAL напрямую) такую БД не откроют.
+//!
+//! NEW: hot_prefix_sample/hot_prefix_len (ENV P1_HOT_PREFIX_SAMPLE / P1_HOT_PREFIX_LEN) — выборочный
+//! профайлер обращений по префиксам ключей (count-min sketch), см. Db::hot_prefixes.
+//!
+//! NEW: io_stall_ms/io_stall_log (ENV P1_IO_STALL_MS / P1_IO_STALL_LOG) — детектор медленного IO
+//! (fsync WAL, запись сегментов) с порогом; процесс‑глобально, см. Db::last_io_stalls.
+//!
+//! NEW: verify_heads_on_open (ENV P1_VERIFY_HEADS_ON_OPEN) — writer проверяет страницы-головы
+//! бакетов при каждом открытии (по умолчанию — только после некорректного завершения) и чинит
+//! нечитаемые из образов WAL, см. Db::open_repair_report.
+//!
+//! NEW: compaction_keep_versions (ENV P1_COMPACTION_KEEP_VERSIONS) — компактация сохраняет до M
+//! живых версий ключа (история для Db::get_versions), по умолчанию только новейшую.
+//!
+//! NEW: validate/normalize (config::validate) — QuiverConfig::validate() возвращает структурированные
+//! предупреждения/ошибки, normalize() приводит значения к допустимым диапазонам (open_* вызывает его
+//! и пишет [WARN] по каждому clamp); resolve_effective_config — слои defaults/ENV/файл/CLI с источником
+//! каждого поля (quiverdb config-show).
+//!
+//! NEW: bloom_auto_refresh (ENV P1_BLOOM_AUTO_REFRESH="N,M") — writer сам обновляет bloom.bin
+//! по затронутым бакетам после N пропусков устаревшего фильтра или дрейфа last_lsn на M,
+//! см. db/bloom_refresh.rs.
+//!
+//! NEW: snapshot_interval_secs/snapshot_retention (ENV P1_SNAPSHOT_INTERVAL_SECS / P1_SNAPSHOT_RETENTION) —
+//! плановые persisted-снапшоты в `quiverdb maint-daemon` и сколько из них хранить,
+//! см. db/maint_sched.rs.
+//!
+//! NEW: trash_grace_secs (ENV P1_TRASH_GRACE_SECS) — trash-режим del(): tombstone хранит срок,
+//! до которого прежнее значение можно вернуть (Db::undelete), компактация вычищает его позже,
+//! см. db/trash.rs.
+//!
+//! NEW: page_cache_policy/page_cache_mlock_max_bytes (ENV P1_PAGE_CACHE_POLICY /
+//! P1_PAGE_CACHE_MLOCK_MAX_BYTES) — как page cache хранит страницы: plaintext | ciphertext
+//! (запечатаны эфемерным ключом процесса) | locked (mlock + зануление при вытеснении, с лимитом),
+//! см. pager/cache.rs.
+//!
+//! NEW: tde_key_provider — свой источник ключей TDE (например, CompositeKeyProvider с маршрутами
+//! по KID); не читается из ENV — без него используется CompositeKeyProvider::from_env,
+//! см. crypto/composite.rs.
+//!
+//! NEW: tde_aad_v2 (ENV P1_TDE_AAD_V2, по умолчанию on) — writer с TDE включает AAD v2 для
+//! AEAD-трейлеров (page_id, тип страницы, db_uuid; meta-флаг tde_aad_v2, новые страницы),
+//! см. page/checksum.rs.
+//!
+//! NEW: segment_growth/segment_grow_chunk_bytes/segment_fallocate (ENV P1_SEGMENT_GROWTH /
+//! P1_SEGMENT_GROW_CHUNK_BYTES / P1_SEGMENT_FALLOCATE) — как растут файлы сегментов: exact | chunk
+//! (кусками) | full (сразу весь сегмент), с резервированием блоков без разреженных файлов,
+//! см. pager/alloc.rs.
+//!
+//! NEW: punch_holes (ENV P1_PUNCH_HOLES) — освобождённые страницы (free-лист, sweep) сразу
+//! возвращаются ФС через fallocate(PUNCH_HOLE) на Linux; без поддержки ФС — no-op, см. pager/trim.rs.
+//!
+//! NEW: snapshot_sign_kid (ENV P1_SNAPSHOT_SIGN_KID) — persisted-снапшоты подписываются Ed25519
+//! ключом, выведенным из ключа KeyProvider для этого KID, см. snapstore/signature.rs.
+//!
+//! NEW: history_index (ENV P1_HISTORY_INDEX) — перед усечением WAL закоммиченные батчи
+//! индексируются в sidecar history.bin (key-hash → LSN/op), см. wal/history.rs.
+//!
+//! NEW: memory_budget_bytes / max_open_fds (ENV P1_MEMORY_BUDGET_BYTES / P1_MAX_OPEN_FDS) —
+//! процессный ResourceBudget для кэшей/буферов и дескрипторов (budget.rs); 0 — не задавать.
+//!
+//! NEW: recovery_progress — callback with open-time WAL recovery progress (not read from env;
+//! falls back to the process-wide default, see wal::set_default_recovery_progress).
+//!
+//! NEW: wal_coalesce_adaptive / wal_coalesce_p99_budget_us (ENV P1_WAL_COALESCE_ADAPTIVE /
+//! P1_WAL_COALESCE_P99_BUDGET_US) — адаптивное окно group-commit под бюджет p99 латентности
+//! коммита (wal/coalesce.rs).
+//!
+//! NEW: seg_syncfs (ENV P1_SEG_SYNCFS) — один syncfs(2) вместо fsync каждого сегмента батча
+//! (pager/segsync.rs).
+//!
+//! NEW: key_transform — каноникализация ключей на границе Db (db/key_transform.rs); не читается
+//! из ENV — встроенные трансформации восстанавливаются по id из meta.
+//!
+//! NEW: value_transform — кодирование значений на пути хранения (db/value_transform.rs); не
+//! читается из ENV — встроенная (словарь zstd) восстанавливается по id из meta.
+//!
+//! NEW: scan_cursor_pin_secs (ENV P1_SCAN_CURSOR_PIN_SECS) — пока курсор Db::scan_page свежее
+//! этого срока, sweep/alloc_check_fix не освобождают страницы закреплённой им цепочки.
+//!
+//! NEW: hot_get_cache_entries (ENV P1_HOT_GET_CACHE_ENTRIES) — микро-кэш локаций горячих ключей
+//! для get (db/hot_get.rs); 0 — выключен.
+//!
 //! Performance-oriented defaults:
 //! - wal_coalesce_ms = 0 (no artificial delay before fsync)
 //! - data_fsync = false (do not fsync data segments on every commit; durability relies on WAL)
@@ -21,6 +113,22 @@
 //!   All of the above can be overridden via ENV or builder.
 
 use std::fmt;
+use std::sync::Arc;
+
+use crate::crypto::{KeyProvider, KeyProviderRef};
+use crate::db::key_transform::{KeyTransform, KeyTransformRef};
+use crate::db::value_transform::{ValueTransform, ValueTransformRef};
+
+use crate::pager::alloc::{SegmentGrowth, DEFAULT_SEGMENT_GROW_CHUNK_BYTES};
+use crate::pager::cache::{PageCachePolicy, DEFAULT_PAGE_CACHE_MLOCK_MAX_BYTES};
+use crate::wal::{RecoveryProgress, RecoveryProgressHook};
+
+// NEW: проверка/нормализация и эффективный конфиг
+pub mod validate;
+pub use validate::{
+    resolve_effective_config, ConfigField, ConfigIssue, ConfigSource, ConfigValidation,
+    EffectiveConfig, EffectiveEntry, Severity, CONFIG_FIELDS,
+};
 
 /// Top-level configuration for QuiverDB (writer/reader).
 /// Backward-compatible with env-based configuration used so far.
@@ -64,6 +172,142 @@ pub struct QuiverConfig {
     /// Optional key identifier (KID) to use when TDE is enabled.
     /// Env: P1_TDE_KID = "default" (string). If not provided, provider's default is used.
     pub tde_kid: Option<String>,
+
+    /// Key source for TDE (e.g., a CompositeKeyProvider routing KIDs to env/static/KMS sources).
+    /// If None, CompositeKeyProvider::from_env is used (keyring → env → P1_TDE_KEYS).
+    pub tde_key_provider: Option<KeyProviderRef>,
+
+    /// Bind AEAD trailers to page_id, page type and the DB UUID (AAD v2) on TDE writers.
+    /// Enabled once per DB (required feature flag; older builds cannot open it afterwards);
+    /// pages written before keep AAD v1. Env: P1_TDE_AAD_V2=0|1|true|false (default true)
+    pub tde_aad_v2: bool,
+
+    // ---------- Page cache prewarm ----------
+    /// Persist hot page ids on clean shutdown and prewarm the page cache asynchronously at open.
+    /// Env: P1_CACHE_PREWARM = 0|1|true|false (default false)
+    pub cache_prewarm: bool,
+
+    // ---------- TTL expiry events ----------
+    /// Log keys purged by TTL during compaction as an EXPIRY frame in the WAL.
+    /// Env: P1_WAL_EXPIRY_EVENTS = 0|1|true|false (default false)
+    pub wal_expiry_events: bool,
+
+    /// Opt in to the compact delta HEADS_UPDATE encoding (sets the required meta feature bit
+    /// `heads_delta`; builds without delta support will refuse to open the database).
+    /// Env: P1_WAL_HEADS_DELTA = 0|1|true|false (default false)
+    pub wal_heads_delta: bool,
+
+    /// Live versions per key retained by compaction (1 = newest only; history for Db::get_versions).
+    /// Env: P1_COMPACTION_KEEP_VERSIONS = N (default 1)
+    pub compaction_keep_versions: u32,
+
+    // ---------- Hot key-prefix profiler ----------
+    /// Sample 1 of N kv operations into the per-prefix access profiler (0 = disabled).
+    /// Env: P1_HOT_PREFIX_SAMPLE = N (default 0)
+    pub hot_prefix_sample: u32,
+    /// Key prefix length (bytes) the profiler aggregates by.
+    /// Env: P1_HOT_PREFIX_LEN = N (default 8)
+    pub hot_prefix_len: usize,
+
+    // ---------- Slow IO (stall detector) ----------
+    /// WAL fsync / segment write slower than this is recorded as a stall (0 = disabled).
+    /// Process-wide (applied on open). Env: P1_IO_STALL_MS (default 1000)
+    pub io_stall_ms: u64,
+    /// Also log each stall to stderr as [WARN].
+    /// Env: P1_IO_STALL_LOG=1|true|yes|on (default false)
+    pub io_stall_log: bool,
+
+    // ---------- Bloom ----------
+    /// Automatic refresh of bloom.bin on the writer (only touched buckets are rebuilt).
+    /// Env: P1_BLOOM_AUTO_REFRESH = off | N | N,M (default off)
+    pub bloom_auto_refresh: BloomAutoRefresh,
+
+    // ---------- Recovery ----------
+    /// Writer checks bucket head pages on every open (not only after an unclean shutdown)
+    /// and repairs unreadable ones from WAL images. Env: P1_VERIFY_HEADS_ON_OPEN=1|true|yes|on
+    pub verify_heads_on_open: bool,
+    /// Progress callback for open-time WAL recovery (frames, bytes, LSN, ETA).
+    /// If None, the process-wide default hook is used (if any).
+    pub recovery_progress: Option<RecoveryProgressHook>,
+
+    // ---------- Scheduled snapshots (maint-daemon) ----------
+    /// Interval between scheduled persisted snapshots in seconds (0 = disabled).
+    /// Env: P1_SNAPSHOT_INTERVAL_SECS (default 0)
+    pub snapshot_interval_secs: u64,
+    /// How many scheduled snapshots to keep; older ones are deleted (0 = keep all).
+    /// Env: P1_SNAPSHOT_RETENTION (default 0)
+    pub snapshot_retention: u32,
+
+    // ---------- Trash / undelete ----------
+    /// Grace period for deleted values in seconds (0 = off, del() is permanent).
+    /// While it lasts, Db::undelete restores the previous value; compaction purges it afterwards.
+    /// Env: P1_TRASH_GRACE_SECS (default 0)
+    pub trash_grace_secs: u64,
+
+    // ---------- Page cache storage policy ----------
+    /// How the process-wide page cache keeps pages in memory (plaintext | ciphertext | locked).
+    /// Env: P1_PAGE_CACHE_POLICY (default plaintext)
+    pub page_cache_policy: PageCachePolicy,
+    /// Cap on mlock'ed cache memory for the `locked` policy; pages over the cap are not cached.
+    /// Env: P1_PAGE_CACHE_MLOCK_MAX_BYTES (default 64 MiB)
+    pub page_cache_mlock_max_bytes: u64,
+
+    // ---------- Segment growth ----------
+    /// How data segment files grow on allocation (exact | chunk | full).
+    /// Env: P1_SEGMENT_GROWTH (default exact)
+    pub segment_growth: SegmentGrowth,
+    /// Growth step for the `chunk` policy (rounded up to a whole page).
+    /// Env: P1_SEGMENT_GROW_CHUNK_BYTES (default 1 MiB)
+    pub segment_grow_chunk_bytes: u64,
+    /// Reserve blocks on growth (posix_fallocate) instead of extending a sparse file.
+    /// Falls back to set_len where unsupported. Env: P1_SEGMENT_FALLOCATE (default false)
+    pub segment_fallocate: bool,
+    /// Punch holes for pages entering the free list (fallocate PUNCH_HOLE on Linux), so
+    /// segments return space without vacuum. No-op on filesystems without support.
+    /// Env: P1_PUNCH_HOLES (default false)
+    pub punch_holes: bool,
+    /// Sign persisted snapshot manifests (Ed25519) with a key derived from the KeyProvider key
+    /// of this KID. None — manifests are not signed. Env: P1_SNAPSHOT_SIGN_KID
+    pub snapshot_sign_kid: Option<String>,
+    /// Index committed WAL batches into <root>/history.bin before the WAL is truncated
+    /// (rotation, close, open, checkpoint), for `quiverdb history`. Env: P1_HISTORY_INDEX (default false)
+    pub history_index: bool,
+    /// Process-wide memory budget (bytes) shared by page/value/bloom caches, bloom RAM copies,
+    /// the in-memory keydir and segment write buffers; subsystems degrade to fit it.
+    /// 0 — leave the process budget as is. Env: P1_MEMORY_BUDGET_BYTES (default 0)
+    pub memory_budget_bytes: u64,
+    /// Process-wide limit of long-lived file handles (Db LOCK files, bloom.bin RO handles);
+    /// opening a Db beyond it fails with ResourceBudgetError. 0 — leave as is.
+    /// Env: P1_MAX_OPEN_FDS (default 0)
+    pub max_open_fds: u64,
+    /// Adaptive group-commit window: tuned from observed commit arrival rate and fsync latency
+    /// to keep commit p99 within wal_coalesce_p99_budget_us; wal_coalesce_ms becomes the ceiling
+    /// (0 — bounded by the budget only). Env: P1_WAL_COALESCE_ADAPTIVE (default false)
+    pub wal_coalesce_adaptive: bool,
+    /// Commit latency p99 target for the adaptive window, microseconds.
+    /// Env: P1_WAL_COALESCE_P99_BUDGET_US (default 5000)
+    pub wal_coalesce_p99_budget_us: u64,
+    /// Segment fsync of a batch that dirtied several segment files on one filesystem is done with a
+    /// single syncfs(2) instead of one fsync per segment (Linux; elsewhere — per-segment fsync).
+    /// syncfs also flushes unrelated dirty data of that filesystem. Env: P1_SEG_SYNCFS (default false)
+    pub seg_syncfs: bool,
+    /// How long a Db::scan_page cursor pins the bucket chain it points into, seconds: while
+    /// pinned, sweep and alloc_check_fix keep its pages. 0 disables pinning.
+    /// Env: P1_SCAN_CURSOR_PIN_SECS (default 300)
+    pub scan_cursor_pin_secs: u64,
+    /// Per-handle hot-get cache size (entries): key -> (page, offset) of its newest record, so a
+    /// repeated get skips the chain walk and page record search while the bucket head and page LSN
+    /// are unchanged. 0 disables it (default).
+    pub hot_get_cache_entries: usize,
+
+    /// Key canonicalization applied to every key at the Db boundary (recorded in meta; opening
+    /// with a different transform fails). If None, a built-in transform recorded in meta is used.
+    pub key_transform: Option<KeyTransformRef>,
+
+    /// Value encoding applied at write and inverted at read (e.g. ZstdDictValues; recorded in
+    /// meta, opening with a different transform fails). If None, a built-in transform recorded
+    /// in meta is used.
+    pub value_transform: Option<ValueTransformRef>,
 }
 
 impl Default for QuiverConfig {
@@ -84,6 +328,49 @@ impl Default for QuiverConfig {
             // TDE defaults
             tde_enabled: false,
             tde_kid: None,
+            tde_key_provider: None,
+            tde_aad_v2: true,
+            key_transform: None,
+            value_transform: None,
+
+            cache_prewarm: false,
+
+            wal_expiry_events: false,
+            wal_heads_delta: false,
+            compaction_keep_versions: 1,
+
+            hot_prefix_sample: 0,
+            hot_prefix_len: 8,
+
+            io_stall_ms: 1000,
+            io_stall_log: false,
+
+            bloom_auto_refresh: BloomAutoRefresh::default(),
+
+            verify_heads_on_open: false,
+            recovery_progress: None,
+
+            snapshot_interval_secs: 0,
+            snapshot_retention: 0,
+
+            trash_grace_secs: 0,
+
+            page_cache_policy: PageCachePolicy::Plaintext,
+            page_cache_mlock_max_bytes: DEFAULT_PAGE_CACHE_MLOCK_MAX_BYTES,
+
+            segment_growth: SegmentGrowth::Exact,
+            segment_grow_chunk_bytes: DEFAULT_SEGMENT_GROW_CHUNK_BYTES,
+            segment_fallocate: false,
+            punch_holes: false,
+            snapshot_sign_kid: None,
+            history_index: false,
+            memory_budget_bytes: 0,
+            max_open_fds: 0,
+            wal_coalesce_adaptive: false,
+            wal_coalesce_p99_budget_us: 5_000,
+            seg_syncfs: false,
+            scan_cursor_pin_secs: 300,
+            hot_get_cache_entries: 0,
         }
     }
 }
@@ -147,6 +434,162 @@ impl QuiverConfig {
             }
         }
 
+        // ----- Page cache prewarm -----
+        if let Ok(v) = std::env::var("P1_CACHE_PREWARM") {
+            let s = v.trim().to_ascii_lowercase();
+            cfg.cache_prewarm = s == "1" || s == "true" || s == "yes" || s == "on";
+        }
+
+        // ----- TTL expiry events -----
+        if let Ok(v) = std::env::var("P1_WAL_EXPIRY_EVENTS") {
+            let s = v.trim().to_ascii_lowercase();
+            cfg.wal_expiry_events = s == "1" || s == "true" || s == "yes" || s == "on";
+        }
+        if let Ok(v) = std::env::var("P1_WAL_HEADS_DELTA") {
+            let s = v.trim().to_ascii_lowercase();
+            cfg.wal_heads_delta = s == "1" || s == "true" || s == "yes" || s == "on";
+        }
+        if let Ok(v) = std::env::var("P1_COMPACTION_KEEP_VERSIONS") {
+            if let Ok(n) = v.trim().parse::<u32>() {
+                cfg.compaction_keep_versions = n.max(1);
+            }
+        }
+
+        // ----- Hot key-prefix profiler -----
+        if let Ok(v) = std::env::var("P1_HOT_PREFIX_SAMPLE") {
+            if let Ok(n) = v.trim().parse::<u32>() {
+                cfg.hot_prefix_sample = n;
+            }
+        }
+        if let Ok(v) = std::env::var("P1_HOT_PREFIX_LEN") {
+            if let Ok(n) = v.trim().parse::<usize>() {
+                if n > 0 {
+                    cfg.hot_prefix_len = n;
+                }
+            }
+        }
+
+        // ----- Slow IO -----
+        if let Ok(v) = std::env::var("P1_IO_STALL_MS") {
+            if let Ok(n) = v.trim().parse::<u64>() {
+                cfg.io_stall_ms = n;
+            }
+        }
+        if let Ok(v) = std::env::var("P1_IO_STALL_LOG") {
+            let s = v.trim().to_ascii_lowercase();
+            cfg.io_stall_log = s == "1" || s == "true" || s == "yes" || s == "on";
+        }
+
+        // ----- Bloom -----
+        if let Ok(v) = std::env::var("P1_BLOOM_AUTO_REFRESH") {
+            if let Some(p) = BloomAutoRefresh::parse(&v) {
+                cfg.bloom_auto_refresh = p;
+            }
+        }
+
+        // ----- Recovery -----
+        if let Ok(v) = std::env::var("P1_VERIFY_HEADS_ON_OPEN") {
+            let s = v.trim().to_ascii_lowercase();
+            cfg.verify_heads_on_open = s == "1" || s == "true" || s == "yes" || s == "on";
+        }
+
+        // ----- Scheduled snapshots -----
+        if let Ok(v) = std::env::var("P1_SNAPSHOT_INTERVAL_SECS") {
+            if let Ok(n) = v.trim().parse::<u64>() {
+                cfg.snapshot_interval_secs = n;
+            }
+        }
+        if let Ok(v) = std::env::var("P1_SNAPSHOT_RETENTION") {
+            if let Ok(n) = v.trim().parse::<u32>() {
+                cfg.snapshot_retention = n;
+            }
+        }
+
+        // ----- Trash / undelete -----
+        if let Ok(v) = std::env::var("P1_TRASH_GRACE_SECS") {
+            if let Ok(n) = v.trim().parse::<u64>() {
+                cfg.trash_grace_secs = n;
+            }
+        }
+
+        // ----- Page cache storage policy -----
+        if let Ok(v) = std::env::var("P1_PAGE_CACHE_POLICY") {
+            if let Some(p) = PageCachePolicy::parse(&v) {
+                cfg.page_cache_policy = p;
+            }
+        }
+        if let Ok(v) = std::env::var("P1_PAGE_CACHE_MLOCK_MAX_BYTES") {
+            if let Ok(n) = v.trim().parse::<u64>() {
+                cfg.page_cache_mlock_max_bytes = n;
+            }
+        }
+        // ----- Segment growth -----
+        if let Ok(v) = std::env::var("P1_SEGMENT_GROWTH") {
+            if let Some(g) = SegmentGrowth::parse(&v) {
+                cfg.segment_growth = g;
+            }
+        }
+        if let Ok(v) = std::env::var("P1_SEGMENT_GROW_CHUNK_BYTES") {
+            if let Ok(n) = v.trim().parse::<u64>() {
+                cfg.segment_grow_chunk_bytes = n;
+            }
+        }
+        if let Ok(v) = std::env::var("P1_SEGMENT_FALLOCATE") {
+            let s = v.trim().to_ascii_lowercase();
+            cfg.segment_fallocate = s == "1" || s == "true" || s == "yes" || s == "on";
+        }
+        if let Ok(v) = std::env::var("P1_PUNCH_HOLES") {
+            let s = v.trim().to_ascii_lowercase();
+            cfg.punch_holes = s == "1" || s == "true" || s == "yes" || s == "on";
+        }
+        if let Ok(v) = std::env::var("P1_SNAPSHOT_SIGN_KID") {
+            let s = v.trim();
+            if !s.is_empty() {
+                cfg.snapshot_sign_kid = Some(s.to_string());
+            }
+        }
+        if let Ok(v) = std::env::var("P1_HISTORY_INDEX") {
+            let s = v.trim().to_ascii_lowercase();
+            cfg.history_index = s == "1" || s == "true" || s == "yes" || s == "on";
+        }
+        if let Ok(v) = std::env::var("P1_MEMORY_BUDGET_BYTES") {
+            if let Ok(n) = v.trim().parse::<u64>() {
+                cfg.memory_budget_bytes = n;
+            }
+        }
+        if let Ok(v) = std::env::var("P1_MAX_OPEN_FDS") {
+            if let Ok(n) = v.trim().parse::<u64>() {
+                cfg.max_open_fds = n;
+            }
+        }
+        if let Ok(v) = std::env::var("P1_WAL_COALESCE_ADAPTIVE") {
+            let s = v.trim().to_ascii_lowercase();
+            cfg.wal_coalesce_adaptive = s == "1" || s == "true" || s == "yes" || s == "on";
+        }
+        if let Ok(v) = std::env::var("P1_WAL_COALESCE_P99_BUDGET_US") {
+            if let Ok(n) = v.trim().parse::<u64>() {
+                cfg.wal_coalesce_p99_budget_us = n;
+            }
+        }
+        if let Ok(v) = std::env::var("P1_SEG_SYNCFS") {
+            let s = v.trim().to_ascii_lowercase();
+            cfg.seg_syncfs = s == "1" || s == "true" || s == "yes" || s == "on";
+        }
+        if let Ok(v) = std::env::var("P1_SCAN_CURSOR_PIN_SECS") {
+            if let Ok(n) = v.trim().parse::<u64>() {
+                cfg.scan_cursor_pin_secs = n;
+            }
+        }
+        if let Ok(v) = std::env::var("P1_HOT_GET_CACHE_ENTRIES") {
+            if let Ok(n) = v.trim().parse::<usize>() {
+                cfg.hot_get_cache_entries = n;
+            }
+        }
+        if let Ok(v) = std::env::var("P1_TDE_AAD_V2") {
+            let s = v.trim().to_ascii_lowercase();
+            cfg.tde_aad_v2 = s == "1" || s == "true" || s == "yes" || s == "on";
+        }
+
         cfg
     }
 
@@ -206,6 +649,214 @@ impl QuiverConfig {
         self
     }
 
+    /// Enable/disable AAD v2 (page_id, page type, DB UUID) for AEAD trailers on TDE writers.
+    pub fn with_tde_aad_v2(mut self, on: bool) -> Self {
+        self.tde_aad_v2 = on;
+        self
+    }
+
+    /// Set a custom TDE key source (KID → key), e.g. a CompositeKeyProvider.
+    pub fn with_tde_key_provider(mut self, provider: Arc<dyn KeyProvider>) -> Self {
+        self.tde_key_provider = Some(KeyProviderRef::new(provider));
+        self
+    }
+
+    /// Canonicalize keys with this transform (e.g. LowercaseKeys) in every Db method.
+    pub fn with_key_transform(mut self, transform: Arc<dyn KeyTransform>) -> Self {
+        self.key_transform = Some(KeyTransformRef::new(transform));
+        self
+    }
+
+    /// Encode stored values with this transform (e.g. ZstdDictValues) in every Db method.
+    pub fn with_value_transform(mut self, transform: Arc<dyn ValueTransform>) -> Self {
+        self.value_transform = Some(ValueTransformRef::new(transform));
+        self
+    }
+
+    // ----- Page cache prewarm -----
+
+    /// Enable/disable hot page list persistence + background cache prewarm at open.
+    pub fn with_cache_prewarm(mut self, on: bool) -> Self {
+        self.cache_prewarm = on;
+        self
+    }
+
+    // ----- TTL expiry events -----
+
+    /// Enable/disable EXPIRY frames in the WAL for keys purged by TTL.
+    pub fn with_wal_expiry_events(mut self, on: bool) -> Self {
+        self.wal_expiry_events = on;
+        self
+    }
+
+    /// Opt in to the delta HEADS_UPDATE encoding (sets the `heads_delta` meta feature bit).
+    pub fn with_wal_heads_delta(mut self, on: bool) -> Self {
+        self.wal_heads_delta = on;
+        self
+    }
+
+    /// Number of live versions per key kept by compaction (values < 1 are treated as 1).
+    pub fn with_compaction_keep_versions(mut self, n: u32) -> Self {
+        self.compaction_keep_versions = n.max(1);
+        self
+    }
+
+    // ----- Hot key-prefix profiler -----
+
+    /// Sample 1 of `every` kv operations into the hot-prefix profiler (0 disables it).
+    pub fn with_hot_prefix_sample(mut self, every: u32) -> Self {
+        self.hot_prefix_sample = every;
+        self
+    }
+
+    /// Key prefix length (bytes) used by the hot-prefix profiler.
+    pub fn with_hot_prefix_len(mut self, len: usize) -> Self {
+        self.hot_prefix_len = len.max(1);
+        self
+    }
+
+    // ----- Slow IO -----
+
+    /// Stall threshold for WAL fsync / segment writes in milliseconds (0 disables the detector).
+    pub fn with_io_stall_ms(mut self, ms: u64) -> Self {
+        self.io_stall_ms = ms;
+        self
+    }
+
+    /// Log each detected stall to stderr.
+    pub fn with_io_stall_log(mut self, on: bool) -> Self {
+        self.io_stall_log = on;
+        self
+    }
+
+    // ----- Recovery -----
+
+    /// Check (and repair from WAL) bucket head pages on every writer open.
+    /// Automatic bloom.bin refresh policy (BloomAutoRefresh::default() = off).
+    pub fn with_bloom_auto_refresh(mut self, policy: BloomAutoRefresh) -> Self {
+        self.bloom_auto_refresh = policy;
+        self
+    }
+
+    pub fn with_verify_heads_on_open(mut self, on: bool) -> Self {
+        self.verify_heads_on_open = on;
+        self
+    }
+
+    /// Set a progress callback for open-time WAL recovery.
+    pub fn with_recovery_progress<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&RecoveryProgress) + Send + Sync + 'static,
+    {
+        self.recovery_progress = Some(RecoveryProgressHook::new(f));
+        self
+    }
+
+    // ----- Scheduled snapshots -----
+
+    /// Interval between scheduled snapshots in seconds (0 disables them).
+    pub fn with_snapshot_interval_secs(mut self, secs: u64) -> Self {
+        self.snapshot_interval_secs = secs;
+        self
+    }
+
+    /// Number of scheduled snapshots to keep (0 keeps all).
+    pub fn with_snapshot_retention(mut self, keep: u32) -> Self {
+        self.snapshot_retention = keep;
+        self
+    }
+
+    /// Keep deleted values restorable for `secs` seconds (0 disables trash mode).
+    pub fn with_trash_grace_secs(mut self, secs: u64) -> Self {
+        self.trash_grace_secs = secs;
+        self
+    }
+
+    /// Page cache storage policy (process-wide, applied on open).
+    pub fn with_page_cache_policy(mut self, policy: PageCachePolicy) -> Self {
+        self.page_cache_policy = policy;
+        self
+    }
+
+    /// Cap on mlock'ed page cache memory (locked policy).
+    pub fn with_page_cache_mlock_max_bytes(mut self, bytes: u64) -> Self {
+        self.page_cache_mlock_max_bytes = bytes;
+        self
+    }
+
+    /// Segment growth policy (chunk_bytes is used by SegmentGrowth::Chunk).
+    pub fn with_segment_growth(mut self, growth: SegmentGrowth, chunk_bytes: u64) -> Self {
+        self.segment_growth = growth;
+        self.segment_grow_chunk_bytes = chunk_bytes;
+        self
+    }
+
+    /// Reserve segment blocks on growth instead of extending sparse files.
+    pub fn with_segment_fallocate(mut self, on: bool) -> Self {
+        self.segment_fallocate = on;
+        self
+    }
+
+    /// Punch holes for freed pages (return space to the filesystem immediately).
+    pub fn with_punch_holes(mut self, on: bool) -> Self {
+        self.punch_holes = on;
+        self
+    }
+
+    /// Sign persisted snapshot manifests with the key of this KID (None — unsigned).
+    pub fn with_snapshot_sign_kid<S: Into<String>>(mut self, kid: Option<S>) -> Self {
+        self.snapshot_sign_kid = kid.map(Into::into);
+        self
+    }
+
+    /// Index WAL batches into the key history sidecar before WAL truncation.
+    pub fn with_history_index(mut self, on: bool) -> Self {
+        self.history_index = on;
+        self
+    }
+
+    /// Process-wide memory budget for caches and buffers (bytes, 0 — unchanged).
+    pub fn with_memory_budget_bytes(mut self, bytes: u64) -> Self {
+        self.memory_budget_bytes = bytes;
+        self
+    }
+
+    /// Process-wide limit of long-lived file handles (0 — unchanged).
+    pub fn with_max_open_fds(mut self, n: u64) -> Self {
+        self.max_open_fds = n;
+        self
+    }
+
+    /// Adapt the group-commit window to load (wal_coalesce_ms becomes the ceiling).
+    pub fn with_wal_coalesce_adaptive(mut self, on: bool) -> Self {
+        self.wal_coalesce_adaptive = on;
+        self
+    }
+
+    /// Commit latency p99 target for the adaptive group-commit window (microseconds).
+    pub fn with_wal_coalesce_p99_budget_us(mut self, n: u64) -> Self {
+        self.wal_coalesce_p99_budget_us = n;
+        self
+    }
+
+    /// Batch segment fsync via one syncfs(2) when segments share a filesystem.
+    pub fn with_seg_syncfs(mut self, on: bool) -> Self {
+        self.seg_syncfs = on;
+        self
+    }
+
+    /// Pin lease of scan_page cursors (0 — no pinning).
+    pub fn with_scan_cursor_pin_secs(mut self, n: u64) -> Self {
+        self.scan_cursor_pin_secs = n;
+        self
+    }
+
+    /// Enable the per-handle hot-get cache of up to N key locations.
+    pub fn with_hot_get_cache_entries(mut self, n: usize) -> Self {
+        self.hot_get_cache_entries = n;
+        self
+    }
+
     /// Finish the builder and obtain the configuration.
     pub fn build(self) -> Self {
         self
@@ -225,7 +876,40 @@ impl fmt::Display for QuiverConfig {
              snapstore_dir: {}, \
              snap_dedup: {}, \
              tde_enabled: {}, \
-             tde_kid: {} \
+             tde_kid: {}, \
+             cache_prewarm: {}, \
+             wal_expiry_events: {}, \
+             wal_heads_delta: {}, \
+             compaction_keep_versions: {}, \
+             hot_prefix_sample: {}, \
+             hot_prefix_len: {}, \
+             io_stall_ms: {}, \
+             io_stall_log: {}, \
+             bloom_auto_refresh: {}, \
+             verify_heads_on_open: {}, \
+             recovery_progress: {}, \
+             snapshot_interval_secs: {}, \
+             snapshot_retention: {}, \
+             trash_grace_secs: {}, \
+             page_cache_policy: {}, \
+             page_cache_mlock_max_bytes: {}, \
+             segment_growth: {}, \
+             segment_grow_chunk_bytes: {}, \
+             segment_fallocate: {}, \
+             punch_holes: {}, \
+             snapshot_sign_kid: {}, \
+             history_index: {}, \
+             memory_budget_bytes: {}, \
+             max_open_fds: {}, \
+             wal_coalesce_adaptive: {}, \
+             wal_coalesce_p99_budget_us: {}, \
+             seg_syncfs: {}, \
+             scan_cursor_pin_secs: {}, \
+             hot_get_cache_entries: {}, \
+             tde_key_provider: {}, \
+             tde_aad_v2: {}, \
+             key_transform: {}, \
+             value_transform: {} \
              }}",
             self.wal_coalesce_ms,
             self.data_fsync,
@@ -244,10 +928,103 @@ impl fmt::Display for QuiverConfig {
                 .as_ref()
                 .map(|s| s.as_str())
                 .unwrap_or("default(provider)"),
+            self.cache_prewarm,
+            self.wal_expiry_events,
+            self.wal_heads_delta,
+            self.compaction_keep_versions,
+            self.hot_prefix_sample,
+            self.hot_prefix_len,
+            self.io_stall_ms,
+            self.io_stall_log,
+            self.bloom_auto_refresh,
+            self.verify_heads_on_open,
+            if self.recovery_progress.is_some() {
+                "set"
+            } else {
+                "none"
+            },
+            self.snapshot_interval_secs,
+            self.snapshot_retention,
+            self.trash_grace_secs,
+            self.page_cache_policy,
+            self.page_cache_mlock_max_bytes,
+            self.segment_growth,
+            self.segment_grow_chunk_bytes,
+            self.segment_fallocate,
+            self.punch_holes,
+            self.snapshot_sign_kid.as_deref().unwrap_or("none"),
+            self.history_index,
+            self.memory_budget_bytes,
+            self.max_open_fds,
+            self.wal_coalesce_adaptive,
+            self.wal_coalesce_p99_budget_us,
+            self.seg_syncfs,
+            self.scan_cursor_pin_secs,
+            self.hot_get_cache_entries,
+            if self.tde_key_provider.is_some() {
+                "custom"
+            } else {
+                "default(env)"
+            },
+            self.tde_aad_v2,
+            self.key_transform
+                .as_ref()
+                .map(|t| t.0.id())
+                .unwrap_or_else(|| "none".to_string()),
+            self.value_transform
+                .as_ref()
+                .map(|t| t.0.id())
+                .unwrap_or_else(|| "none".to_string()),
         )
     }
 }
 
+/// Политика автоматического обновления bloom.bin (QuiverConfig::bloom_auto_refresh).
+/// Условия независимы, 0 — условие выключено; по умолчанию выключено всё.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BloomAutoRefresh {
+    /// Обновить после N пропусков фильтра (bloom_skipped_stale) на этом хэндле.
+    pub skipped_stale: u64,
+    /// Обновить, когда last_lsn БД обогнал last_lsn фильтра на M и более.
+    pub lsn_drift: u64,
+}
+
+impl BloomAutoRefresh {
+    pub fn new(skipped_stale: u64, lsn_drift: u64) -> Self {
+        Self {
+            skipped_stale,
+            lsn_drift,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.skipped_stale > 0 || self.lsn_drift > 0
+    }
+
+    /// Разбор строки "off" | "" | "N" | "N,M". None — неверный формат.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        if s.is_empty() || s == "off" || s == "0" || s == "false" || s == "no" {
+            return Some(Self::default());
+        }
+        let (n, m) = match s.split_once(',') {
+            Some((n, m)) => (n.trim().parse().ok()?, m.trim().parse().ok()?),
+            None => (s.parse().ok()?, 0),
+        };
+        Some(Self::new(n, m))
+    }
+}
+
+impl fmt::Display for BloomAutoRefresh {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_enabled() {
+            write!(f, "{},{}", self.skipped_stale, self.lsn_drift)
+        } else {
+            f.write_str("off")
+        }
+    }
+}
+
 /// Lightweight builder that produces a QuiverConfig.
 /// Db will expose `Db::builder()` returning this builder.
 #[derive(Clone, Debug)]
@@ -325,6 +1102,192 @@ impl DbBuilder {
         self
     }
 
+    pub fn tde_aad_v2(mut self, on: bool) -> Self {
+        self.cfg.tde_aad_v2 = on;
+        self
+    }
+
+    pub fn tde_key_provider(mut self, provider: Arc<dyn KeyProvider>) -> Self {
+        self.cfg.tde_key_provider = Some(KeyProviderRef::new(provider));
+        self
+    }
+
+    pub fn key_transform(mut self, transform: Arc<dyn KeyTransform>) -> Self {
+        self.cfg.key_transform = Some(KeyTransformRef::new(transform));
+        self
+    }
+
+    pub fn value_transform(mut self, transform: Arc<dyn ValueTransform>) -> Self {
+        self.cfg.value_transform = Some(ValueTransformRef::new(transform));
+        self
+    }
+
+    // ----- Page cache prewarm -----
+
+    pub fn cache_prewarm(mut self, on: bool) -> Self {
+        self.cfg.cache_prewarm = on;
+        self
+    }
+
+    // ----- TTL expiry events -----
+
+    pub fn wal_expiry_events(mut self, on: bool) -> Self {
+        self.cfg.wal_expiry_events = on;
+        self
+    }
+
+    pub fn wal_heads_delta(mut self, on: bool) -> Self {
+        self.cfg.wal_heads_delta = on;
+        self
+    }
+
+    pub fn compaction_keep_versions(mut self, n: u32) -> Self {
+        self.cfg.compaction_keep_versions = n.max(1);
+        self
+    }
+
+    // ----- Hot key-prefix profiler -----
+
+    pub fn hot_prefix_sample(mut self, every: u32) -> Self {
+        self.cfg.hot_prefix_sample = every;
+        self
+    }
+
+    pub fn hot_prefix_len(mut self, len: usize) -> Self {
+        self.cfg.hot_prefix_len = len.max(1);
+        self
+    }
+
+    // ----- Slow IO -----
+
+    pub fn io_stall_ms(mut self, ms: u64) -> Self {
+        self.cfg.io_stall_ms = ms;
+        self
+    }
+
+    pub fn io_stall_log(mut self, on: bool) -> Self {
+        self.cfg.io_stall_log = on;
+        self
+    }
+
+    // ----- Recovery -----
+
+    pub fn bloom_auto_refresh(mut self, policy: BloomAutoRefresh) -> Self {
+        self.cfg.bloom_auto_refresh = policy;
+        self
+    }
+
+    pub fn verify_heads_on_open(mut self, on: bool) -> Self {
+        self.cfg.verify_heads_on_open = on;
+        self
+    }
+
+    pub fn recovery_progress<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&RecoveryProgress) + Send + Sync + 'static,
+    {
+        self.cfg.recovery_progress = Some(RecoveryProgressHook::new(f));
+        self
+    }
+
+    // ----- Scheduled snapshots -----
+
+    pub fn snapshot_interval_secs(mut self, secs: u64) -> Self {
+        self.cfg.snapshot_interval_secs = secs;
+        self
+    }
+
+    pub fn snapshot_retention(mut self, keep: u32) -> Self {
+        self.cfg.snapshot_retention = keep;
+        self
+    }
+
+    // ----- Trash / undelete -----
+
+    pub fn trash_grace_secs(mut self, secs: u64) -> Self {
+        self.cfg.trash_grace_secs = secs;
+        self
+    }
+
+    // ----- Page cache storage policy -----
+
+    pub fn page_cache_policy(mut self, policy: PageCachePolicy) -> Self {
+        self.cfg.page_cache_policy = policy;
+        self
+    }
+
+    pub fn page_cache_mlock_max_bytes(mut self, bytes: u64) -> Self {
+        self.cfg.page_cache_mlock_max_bytes = bytes;
+        self
+    }
+
+    // ----- Segment growth -----
+
+    pub fn segment_growth(mut self, growth: SegmentGrowth) -> Self {
+        self.cfg.segment_growth = growth;
+        self
+    }
+
+    pub fn segment_grow_chunk_bytes(mut self, bytes: u64) -> Self {
+        self.cfg.segment_grow_chunk_bytes = bytes;
+        self
+    }
+
+    pub fn segment_fallocate(mut self, on: bool) -> Self {
+        self.cfg.segment_fallocate = on;
+        self
+    }
+
+    pub fn punch_holes(mut self, on: bool) -> Self {
+        self.cfg.punch_holes = on;
+        self
+    }
+
+    pub fn snapshot_sign_kid<S: Into<String>>(mut self, kid: Option<S>) -> Self {
+        self.cfg.snapshot_sign_kid = kid.map(Into::into);
+        self
+    }
+
+    pub fn history_index(mut self, on: bool) -> Self {
+        self.cfg.history_index = on;
+        self
+    }
+
+    pub fn memory_budget_bytes(mut self, bytes: u64) -> Self {
+        self.cfg.memory_budget_bytes = bytes;
+        self
+    }
+
+    pub fn max_open_fds(mut self, n: u64) -> Self {
+        self.cfg.max_open_fds = n;
+        self
+    }
+
+    pub fn wal_coalesce_adaptive(mut self, on: bool) -> Self {
+        self.cfg.wal_coalesce_adaptive = on;
+        self
+    }
+
+    pub fn wal_coalesce_p99_budget_us(mut self, n: u64) -> Self {
+        self.cfg.wal_coalesce_p99_budget_us = n;
+        self
+    }
+
+    pub fn seg_syncfs(mut self, on: bool) -> Self {
+        self.cfg.seg_syncfs = on;
+        self
+    }
+
+    pub fn scan_cursor_pin_secs(mut self, n: u64) -> Self {
+        self.cfg.scan_cursor_pin_secs = n;
+        self
+    }
+
+    pub fn hot_get_cache_entries(mut self, n: usize) -> Self {
+        self.cfg.hot_get_cache_entries = n;
+        self
+    }
+
     /// Finish the builder and obtain the configuration.
     pub fn build(self) -> QuiverConfig {
         self.cfg
@@ -0,0 +1,706 @@
+//! config/validate — проверка, нормализация и «эффективный» конфиг с источником каждого поля.
+//!
+//! - QuiverConfig::validate() — структурированные предупреждения/ошибки (ConfigIssue), ничего не меняет;
+//! - QuiverConfig::normalize() — clamp бессмысленных значений (page_cache_pages=usize::MAX и т.п.),
+//!   возвращает, что было поправлено; open_* вызывает его и пишет [WARN] по каждому clamp;
+//! - resolve_effective_config(file, overrides) — слои defaults → ENV → JSON-файл → CLI (--set k=v)
+//!   с provenance для каждого поля (quiverdb config-show).
+//!
+//! Поля перечислены в CONFIG_FIELDS (имя, ENV); set_field/field_value — строковый доступ к ним
+//! для файла и CLI (строгий разбор: неверное значение — ошибка, в отличие от from_env).
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::path::Path;
+
+use super::QuiverConfig;
+
+/// Верхняя граница page_cache_pages (16M страниц — 64 GiB при 4 KiB).
+pub const MAX_PAGE_CACHE_PAGES: usize = 1 << 24;
+/// Верхняя граница окна group-commit.
+pub const MAX_WAL_COALESCE_MS: u64 = 10_000;
+/// Верхняя граница compaction_keep_versions.
+pub const MAX_COMPACTION_KEEP_VERSIONS: u32 = 1024;
+/// Верхняя граница hot_prefix_len (длина ключа — u16, но профайлер агрегирует короткие префиксы).
+pub const MAX_HOT_PREFIX_LEN: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// Замечание к конфигу.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigIssue {
+    pub field: &'static str,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl ConfigIssue {
+    fn warn(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+
+    fn error(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+}
+
+/// Итог validate().
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ConfigValidation {
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl ConfigValidation {
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|i| i.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ConfigIssue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Warning)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ConfigIssue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Error)
+    }
+}
+
+/// Откуда взято значение поля.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    Default,
+    Env,
+    File,
+    Cli,
+}
+
+impl ConfigSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigSource::Default => "default",
+            ConfigSource::Env => "env",
+            ConfigSource::File => "file",
+            ConfigSource::Cli => "cli",
+        }
+    }
+}
+
+/// Поле конфига, доступное из ENV/файла/CLI.
+#[derive(Debug, Clone, Copy)]
+pub struct ConfigField {
+    pub name: &'static str,
+    pub env: &'static str,
+}
+
+pub const CONFIG_FIELDS: &[ConfigField] = &[
+    ConfigField {
+        name: "wal_coalesce_ms",
+        env: "P1_WAL_COALESCE_MS",
+    },
+    ConfigField {
+        name: "data_fsync",
+        env: "P1_DATA_FSYNC",
+    },
+    ConfigField {
+        name: "page_cache_pages",
+        env: "P1_PAGE_CACHE_PAGES",
+    },
+    ConfigField {
+        name: "ovf_threshold_bytes",
+        env: "P1_OVF_THRESHOLD_BYTES",
+    },
+    ConfigField {
+        name: "snap_persist",
+        env: "P1_SNAP_PERSIST",
+    },
+    ConfigField {
+        name: "snapstore_dir",
+        env: "P1_SNAPSTORE_DIR",
+    },
+    ConfigField {
+        name: "snap_dedup",
+        env: "P1_SNAP_DEDUP",
+    },
+    ConfigField {
+        name: "tde_enabled",
+        env: "P1_TDE_ENABLED",
+    },
+    ConfigField {
+        name: "tde_kid",
+        env: "P1_TDE_KID",
+    },
+    ConfigField {
+        name: "cache_prewarm",
+        env: "P1_CACHE_PREWARM",
+    },
+    ConfigField {
+        name: "wal_expiry_events",
+        env: "P1_WAL_EXPIRY_EVENTS",
+    },
+    ConfigField {
+        name: "wal_heads_delta",
+        env: "P1_WAL_HEADS_DELTA",
+    },
+    ConfigField {
+        name: "compaction_keep_versions",
+        env: "P1_COMPACTION_KEEP_VERSIONS",
+    },
+    ConfigField {
+        name: "hot_prefix_sample",
+        env: "P1_HOT_PREFIX_SAMPLE",
+    },
+    ConfigField {
+        name: "hot_prefix_len",
+        env: "P1_HOT_PREFIX_LEN",
+    },
+    ConfigField {
+        name: "io_stall_ms",
+        env: "P1_IO_STALL_MS",
+    },
+    ConfigField {
+        name: "io_stall_log",
+        env: "P1_IO_STALL_LOG",
+    },
+    ConfigField {
+        name: "bloom_auto_refresh",
+        env: "P1_BLOOM_AUTO_REFRESH",
+    },
+    ConfigField {
+        name: "verify_heads_on_open",
+        env: "P1_VERIFY_HEADS_ON_OPEN",
+    },
+    ConfigField {
+        name: "snapshot_interval_secs",
+        env: "P1_SNAPSHOT_INTERVAL_SECS",
+    },
+    ConfigField {
+        name: "snapshot_retention",
+        env: "P1_SNAPSHOT_RETENTION",
+    },
+    ConfigField {
+        name: "trash_grace_secs",
+        env: "P1_TRASH_GRACE_SECS",
+    },
+    ConfigField {
+        name: "page_cache_policy",
+        env: "P1_PAGE_CACHE_POLICY",
+    },
+    ConfigField {
+        name: "page_cache_mlock_max_bytes",
+        env: "P1_PAGE_CACHE_MLOCK_MAX_BYTES",
+    },
+    ConfigField {
+        name: "tde_aad_v2",
+        env: "P1_TDE_AAD_V2",
+    },
+    ConfigField {
+        name: "segment_growth",
+        env: "P1_SEGMENT_GROWTH",
+    },
+    ConfigField {
+        name: "segment_grow_chunk_bytes",
+        env: "P1_SEGMENT_GROW_CHUNK_BYTES",
+    },
+    ConfigField {
+        name: "segment_fallocate",
+        env: "P1_SEGMENT_FALLOCATE",
+    },
+    ConfigField {
+        name: "punch_holes",
+        env: "P1_PUNCH_HOLES",
+    },
+    ConfigField {
+        name: "snapshot_sign_kid",
+        env: "P1_SNAPSHOT_SIGN_KID",
+    },
+    ConfigField {
+        name: "history_index",
+        env: "P1_HISTORY_INDEX",
+    },
+    ConfigField {
+        name: "memory_budget_bytes",
+        env: "P1_MEMORY_BUDGET_BYTES",
+    },
+    ConfigField {
+        name: "max_open_fds",
+        env: "P1_MAX_OPEN_FDS",
+    },
+    ConfigField {
+        name: "wal_coalesce_adaptive",
+        env: "P1_WAL_COALESCE_ADAPTIVE",
+    },
+    ConfigField {
+        name: "wal_coalesce_p99_budget_us",
+        env: "P1_WAL_COALESCE_P99_BUDGET_US",
+    },
+    ConfigField {
+        name: "seg_syncfs",
+        env: "P1_SEG_SYNCFS",
+    },
+    ConfigField {
+        name: "scan_cursor_pin_secs",
+        env: "P1_SCAN_CURSOR_PIN_SECS",
+    },
+    ConfigField {
+        name: "hot_get_cache_entries",
+        env: "P1_HOT_GET_CACHE_ENTRIES",
+    },
+];
+
+/// Одно поле эффективного конфига.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EffectiveEntry {
+    pub field: &'static str,
+    pub env: &'static str,
+    pub value: String,
+    pub source: ConfigSource,
+}
+
+/// Эффективный (слитый и нормализованный) конфиг.
+#[derive(Debug, Clone)]
+pub struct EffectiveConfig {
+    pub config: QuiverConfig,
+    pub entries: Vec<EffectiveEntry>,
+    /// Что поправил normalize() + замечания validate() после нормализации.
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl QuiverConfig {
+    /// Проверить конфиг (без изменений).
+    pub fn validate(&self) -> ConfigValidation {
+        let mut v = Vec::new();
+        if self.page_cache_pages > MAX_PAGE_CACHE_PAGES {
+            v.push(ConfigIssue::warn(
+                "page_cache_pages",
+                format!(
+                    "{} pages is beyond the supported maximum {} (clamped on open)",
+                    self.page_cache_pages, MAX_PAGE_CACHE_PAGES
+                ),
+            ));
+        }
+        if self.wal_coalesce_ms > MAX_WAL_COALESCE_MS {
+            v.push(ConfigIssue::warn(
+                "wal_coalesce_ms",
+                format!(
+                    "{} ms group-commit window stalls every commit; maximum is {} ms (clamped on open)",
+                    self.wal_coalesce_ms, MAX_WAL_COALESCE_MS
+                ),
+            ));
+        }
+        if self.wal_coalesce_ms > 0 && env_flag("P1_WAL_DISABLE_FSYNC") {
+            v.push(ConfigIssue::warn(
+                "wal_coalesce_ms",
+                "group-commit window has no effect: WAL fsync is disabled (P1_WAL_DISABLE_FSYNC)",
+            ));
+        }
+        if self.wal_coalesce_adaptive && self.wal_coalesce_p99_budget_us == 0 {
+            v.push(ConfigIssue::warn(
+                "wal_coalesce_p99_budget_us",
+                "0 µs p99 budget keeps the adaptive window closed (reset to 5000 on open)",
+            ));
+        }
+        if self.wal_coalesce_adaptive && env_flag("P1_WAL_DISABLE_FSYNC") {
+            v.push(ConfigIssue::warn(
+                "wal_coalesce_adaptive",
+                "adaptive group-commit window has no effect: WAL fsync is disabled (P1_WAL_DISABLE_FSYNC)",
+            ));
+        }
+        if self.ovf_threshold_bytes == Some(0) {
+            v.push(ConfigIssue::warn(
+                "ovf_threshold_bytes",
+                "0 would move every value to OVERFLOW pages; treated as default (page_size/4)",
+            ));
+        }
+        if self
+            .snapstore_dir
+            .as_deref()
+            .is_some_and(|s| s.trim().is_empty())
+        {
+            v.push(ConfigIssue::error(
+                "snapstore_dir",
+                "empty path (unset it to use <root>/.snapstore)",
+            ));
+        }
+        if self.snap_dedup && !self.snap_persist {
+            v.push(ConfigIssue::warn(
+                "snap_dedup",
+                "has no effect without snap_persist",
+            ));
+        }
+        if self.tde_kid.is_some() && !self.tde_enabled {
+            v.push(ConfigIssue::warn(
+                "tde_kid",
+                "is ignored while tde_enabled=false",
+            ));
+        }
+        if self.tde_kid.as_deref().is_some_and(|s| s.trim().is_empty()) {
+            v.push(ConfigIssue::error("tde_kid", "empty KID"));
+        }
+        if self
+            .snapshot_sign_kid
+            .as_deref()
+            .is_some_and(|s| s.trim().is_empty())
+        {
+            v.push(ConfigIssue::error("snapshot_sign_kid", "empty KID"));
+        }
+        if self.compaction_keep_versions == 0 {
+            v.push(ConfigIssue::warn(
+                "compaction_keep_versions",
+                "0 is treated as 1 (newest version only)",
+            ));
+        } else if self.compaction_keep_versions > MAX_COMPACTION_KEEP_VERSIONS {
+            v.push(ConfigIssue::warn(
+                "compaction_keep_versions",
+                format!(
+                    "{} versions per key is beyond the maximum {} (clamped on open)",
+                    self.compaction_keep_versions, MAX_COMPACTION_KEEP_VERSIONS
+                ),
+            ));
+        }
+        if self.hot_prefix_len == 0 || self.hot_prefix_len > MAX_HOT_PREFIX_LEN {
+            v.push(ConfigIssue::warn(
+                "hot_prefix_len",
+                format!(
+                    "{} is outside 1..={} (clamped on open)",
+                    self.hot_prefix_len, MAX_HOT_PREFIX_LEN
+                ),
+            ));
+        }
+        if self.page_cache_policy == crate::pager::cache::PageCachePolicy::Locked
+            && self.page_cache_mlock_max_bytes == 0
+        {
+            v.push(ConfigIssue::warn(
+                "page_cache_mlock_max_bytes",
+                "0 with page_cache_policy=locked: no page is ever cached",
+            ));
+        }
+        if self.segment_growth == crate::pager::SegmentGrowth::Chunk {
+            if self.segment_grow_chunk_bytes == 0 {
+                v.push(ConfigIssue::warn(
+                    "segment_grow_chunk_bytes",
+                    "0 with segment_growth=chunk: segments grow one page at a time",
+                ));
+            } else if self.segment_grow_chunk_bytes > crate::pager::SEGMENT_SIZE {
+                v.push(ConfigIssue::warn(
+                    "segment_grow_chunk_bytes",
+                    format!(
+                        "{} is beyond the segment size {} (growth stops at the segment end)",
+                        self.segment_grow_chunk_bytes,
+                        crate::pager::SEGMENT_SIZE
+                    ),
+                ));
+            }
+        }
+        if self.punch_holes && self.segment_fallocate {
+            v.push(ConfigIssue::warn(
+                "punch_holes",
+                "with segment_fallocate: freed pages give back the blocks reserved for them",
+            ));
+        }
+        if self.io_stall_log && self.io_stall_ms == 0 {
+            v.push(ConfigIssue::warn(
+                "io_stall_log",
+                "has no effect: the stall detector is disabled (io_stall_ms=0)",
+            ));
+        }
+        if self.snapshot_retention > 0 && self.snapshot_interval_secs == 0 {
+            v.push(ConfigIssue::warn(
+                "snapshot_retention",
+                "has no effect: scheduled snapshots are disabled (snapshot_interval_secs=0)",
+            ));
+        }
+        ConfigValidation { issues: v }
+    }
+
+    /// Привести значения к допустимым диапазонам. Возвращает поправленные поля.
+    pub fn normalize(&mut self) -> Vec<ConfigIssue> {
+        let mut fixed = Vec::new();
+        if self.page_cache_pages > MAX_PAGE_CACHE_PAGES {
+            fixed.push(clamped(
+                "page_cache_pages",
+                self.page_cache_pages,
+                MAX_PAGE_CACHE_PAGES,
+            ));
+            self.page_cache_pages = MAX_PAGE_CACHE_PAGES;
+        }
+        if self.wal_coalesce_ms > MAX_WAL_COALESCE_MS {
+            fixed.push(clamped(
+                "wal_coalesce_ms",
+                self.wal_coalesce_ms,
+                MAX_WAL_COALESCE_MS,
+            ));
+            self.wal_coalesce_ms = MAX_WAL_COALESCE_MS;
+        }
+        if self.wal_coalesce_adaptive && self.wal_coalesce_p99_budget_us == 0 {
+            fixed.push(ConfigIssue::warn(
+                "wal_coalesce_p99_budget_us",
+                "0 reset to default (5000)",
+            ));
+            self.wal_coalesce_p99_budget_us = 5_000;
+        }
+        if self.ovf_threshold_bytes == Some(0) {
+            fixed.push(ConfigIssue::warn(
+                "ovf_threshold_bytes",
+                "0 reset to default (page_size/4)",
+            ));
+            self.ovf_threshold_bytes = None;
+        }
+        let kv = self
+            .compaction_keep_versions
+            .clamp(1, MAX_COMPACTION_KEEP_VERSIONS);
+        if kv != self.compaction_keep_versions {
+            fixed.push(clamped(
+                "compaction_keep_versions",
+                self.compaction_keep_versions,
+                kv,
+            ));
+            self.compaction_keep_versions = kv;
+        }
+        let pl = self.hot_prefix_len.clamp(1, MAX_HOT_PREFIX_LEN);
+        if pl != self.hot_prefix_len {
+            fixed.push(clamped("hot_prefix_len", self.hot_prefix_len, pl));
+            self.hot_prefix_len = pl;
+        }
+        fixed
+    }
+
+    /// normalize() с [WARN] в stderr по каждому clamp (open_*).
+    pub(crate) fn normalize_for_open(&mut self) {
+        for i in self.normalize() {
+            eprintln!("[WARN] config {}: {}", i.field, i.message);
+        }
+    }
+
+    /// Установить поле по имени из строки (JSON-файл, CLI --set). Пустая строка — None
+    /// для опциональных полей.
+    pub fn set_field(&mut self, name: &str, value: &str) -> Result<()> {
+        let v = value.trim();
+        match name {
+            "wal_coalesce_ms" => self.wal_coalesce_ms = parse_num(name, v)?,
+            "data_fsync" => self.data_fsync = parse_bool(name, v)?,
+            "page_cache_pages" => self.page_cache_pages = parse_num(name, v)?,
+            "ovf_threshold_bytes" => {
+                self.ovf_threshold_bytes = if v.is_empty() {
+                    None
+                } else {
+                    Some(parse_num(name, v)?)
+                }
+            }
+            "snap_persist" => self.snap_persist = parse_bool(name, v)?,
+            "snapstore_dir" => self.snapstore_dir = (!v.is_empty()).then(|| v.to_string()),
+            "snap_dedup" => self.snap_dedup = parse_bool(name, v)?,
+            "tde_enabled" => self.tde_enabled = parse_bool(name, v)?,
+            "tde_kid" => self.tde_kid = (!v.is_empty()).then(|| v.to_string()),
+            "cache_prewarm" => self.cache_prewarm = parse_bool(name, v)?,
+            "wal_expiry_events" => self.wal_expiry_events = parse_bool(name, v)?,
+            "wal_heads_delta" => self.wal_heads_delta = parse_bool(name, v)?,
+            "compaction_keep_versions" => self.compaction_keep_versions = parse_num(name, v)?,
+            "hot_prefix_sample" => self.hot_prefix_sample = parse_num(name, v)?,
+            "hot_prefix_len" => self.hot_prefix_len = parse_num(name, v)?,
+            "io_stall_ms" => self.io_stall_ms = parse_num(name, v)?,
+            "io_stall_log" => self.io_stall_log = parse_bool(name, v)?,
+            "bloom_auto_refresh" => {
+                self.bloom_auto_refresh = super::BloomAutoRefresh::parse(v).ok_or_else(|| {
+                    anyhow!(
+                        "config field '{}': '{}' is not 'off', 'N' or 'N,M'",
+                        name,
+                        v
+                    )
+                })?
+            }
+            "verify_heads_on_open" => self.verify_heads_on_open = parse_bool(name, v)?,
+            "snapshot_interval_secs" => self.snapshot_interval_secs = parse_num(name, v)?,
+            "snapshot_retention" => self.snapshot_retention = parse_num(name, v)?,
+            "trash_grace_secs" => self.trash_grace_secs = parse_num(name, v)?,
+            "page_cache_policy" => {
+                self.page_cache_policy =
+                    crate::pager::cache::PageCachePolicy::parse(v).ok_or_else(|| {
+                        anyhow!(
+                            "config field '{}': '{}' is not 'plaintext', 'ciphertext' or 'locked'",
+                            name,
+                            v
+                        )
+                    })?
+            }
+            "page_cache_mlock_max_bytes" => self.page_cache_mlock_max_bytes = parse_num(name, v)?,
+            "tde_aad_v2" => self.tde_aad_v2 = parse_bool(name, v)?,
+            "segment_growth" => {
+                self.segment_growth = crate::pager::SegmentGrowth::parse(v).ok_or_else(|| {
+                    anyhow!(
+                        "config field '{}': '{}' is not 'exact', 'chunk' or 'full'",
+                        name,
+                        v
+                    )
+                })?
+            }
+            "segment_grow_chunk_bytes" => self.segment_grow_chunk_bytes = parse_num(name, v)?,
+            "segment_fallocate" => self.segment_fallocate = parse_bool(name, v)?,
+            "punch_holes" => self.punch_holes = parse_bool(name, v)?,
+            "snapshot_sign_kid" => self.snapshot_sign_kid = (!v.is_empty()).then(|| v.to_string()),
+            "history_index" => self.history_index = parse_bool(name, v)?,
+            "memory_budget_bytes" => self.memory_budget_bytes = parse_num(name, v)?,
+            "max_open_fds" => self.max_open_fds = parse_num(name, v)?,
+            "wal_coalesce_adaptive" => self.wal_coalesce_adaptive = parse_bool(name, v)?,
+            "wal_coalesce_p99_budget_us" => self.wal_coalesce_p99_budget_us = parse_num(name, v)?,
+            "seg_syncfs" => self.seg_syncfs = parse_bool(name, v)?,
+            "scan_cursor_pin_secs" => self.scan_cursor_pin_secs = parse_num(name, v)?,
+            "hot_get_cache_entries" => self.hot_get_cache_entries = parse_num(name, v)?,
+            _ => return Err(anyhow!("unknown config field '{}'", name)),
+        }
+        Ok(())
+    }
+
+    /// Значение поля по имени (для config-show). None — поле неизвестно.
+    pub fn field_value(&self, name: &str) -> Option<String> {
+        let opt = |o: &Option<String>| o.clone().unwrap_or_default();
+        Some(match name {
+            "wal_coalesce_ms" => self.wal_coalesce_ms.to_string(),
+            "data_fsync" => self.data_fsync.to_string(),
+            "page_cache_pages" => self.page_cache_pages.to_string(),
+            "ovf_threshold_bytes" => self
+                .ovf_threshold_bytes
+                .map(|n| n.to_string())
+                .unwrap_or_default(),
+            "snap_persist" => self.snap_persist.to_string(),
+            "snapstore_dir" => opt(&self.snapstore_dir),
+            "snap_dedup" => self.snap_dedup.to_string(),
+            "tde_enabled" => self.tde_enabled.to_string(),
+            "tde_kid" => opt(&self.tde_kid),
+            "cache_prewarm" => self.cache_prewarm.to_string(),
+            "wal_expiry_events" => self.wal_expiry_events.to_string(),
+            "wal_heads_delta" => self.wal_heads_delta.to_string(),
+            "compaction_keep_versions" => self.compaction_keep_versions.to_string(),
+            "hot_prefix_sample" => self.hot_prefix_sample.to_string(),
+            "hot_prefix_len" => self.hot_prefix_len.to_string(),
+            "io_stall_ms" => self.io_stall_ms.to_string(),
+            "io_stall_log" => self.io_stall_log.to_string(),
+            "bloom_auto_refresh" => self.bloom_auto_refresh.to_string(),
+            "verify_heads_on_open" => self.verify_heads_on_open.to_string(),
+            "snapshot_interval_secs" => self.snapshot_interval_secs.to_string(),
+            "snapshot_retention" => self.snapshot_retention.to_string(),
+            "trash_grace_secs" => self.trash_grace_secs.to_string(),
+            "page_cache_policy" => self.page_cache_policy.to_string(),
+            "page_cache_mlock_max_bytes" => self.page_cache_mlock_max_bytes.to_string(),
+            "tde_aad_v2" => self.tde_aad_v2.to_string(),
+            "segment_growth" => self.segment_growth.to_string(),
+            "segment_grow_chunk_bytes" => self.segment_grow_chunk_bytes.to_string(),
+            "segment_fallocate" => self.segment_fallocate.to_string(),
+            "punch_holes" => self.punch_holes.to_string(),
+            "snapshot_sign_kid" => opt(&self.snapshot_sign_kid),
+            "history_index" => self.history_index.to_string(),
+            "memory_budget_bytes" => self.memory_budget_bytes.to_string(),
+            "max_open_fds" => self.max_open_fds.to_string(),
+            "wal_coalesce_adaptive" => self.wal_coalesce_adaptive.to_string(),
+            "wal_coalesce_p99_budget_us" => self.wal_coalesce_p99_budget_us.to_string(),
+            "seg_syncfs" => self.seg_syncfs.to_string(),
+            "scan_cursor_pin_secs" => self.scan_cursor_pin_secs.to_string(),
+            "hot_get_cache_entries" => self.hot_get_cache_entries.to_string(),
+            _ => return None,
+        })
+    }
+}
+
+/// Слить слои defaults → ENV → JSON-файл ({"field": value, ...}) → overrides (CLI)
+/// и нормализовать результат. Неизвестное поле или неверное значение — ошибка.
+pub fn resolve_effective_config(
+    file: Option<&Path>,
+    overrides: &[(String, String)],
+) -> Result<EffectiveConfig> {
+    let mut cfg = QuiverConfig::from_env();
+    let mut sources: Vec<ConfigSource> = CONFIG_FIELDS
+        .iter()
+        .map(|f| {
+            if std::env::var_os(f.env).is_some() {
+                ConfigSource::Env
+            } else {
+                ConfigSource::Default
+            }
+        })
+        .collect();
+
+    if let Some(p) = file {
+        let bytes = std::fs::read(p).with_context(|| format!("read config {}", p.display()))?;
+        let obj: serde_json::Map<String, serde_json::Value> = serde_json::from_slice(&bytes)
+            .with_context(|| format!("parse config {} (expected a JSON object)", p.display()))?;
+        for (k, v) in obj.iter() {
+            let s = match v {
+                serde_json::Value::Null => String::new(),
+                serde_json::Value::String(s) => s.clone(),
+                serde_json::Value::Bool(_) | serde_json::Value::Number(_) => v.to_string(),
+                _ => return Err(anyhow!("config {}: '{}' must be a scalar", p.display(), k)),
+            };
+            cfg.set_field(k, &s)
+                .with_context(|| format!("config {}", p.display()))?;
+            mark(&mut sources, k, ConfigSource::File);
+        }
+    }
+    for (k, v) in overrides {
+        cfg.set_field(k, v).context("--set")?;
+        mark(&mut sources, k, ConfigSource::Cli);
+    }
+
+    let mut issues = cfg.normalize();
+    issues.extend(cfg.validate().issues);
+    let entries = CONFIG_FIELDS
+        .iter()
+        .zip(sources)
+        .map(|(f, source)| EffectiveEntry {
+            field: f.name,
+            env: f.env,
+            value: cfg.field_value(f.name).unwrap_or_default(),
+            source,
+        })
+        .collect();
+    Ok(EffectiveConfig {
+        config: cfg,
+        entries,
+        issues,
+    })
+}
+
+fn mark(sources: &mut [ConfigSource], name: &str, src: ConfigSource) {
+    if let Some(i) = CONFIG_FIELDS.iter().position(|f| f.name == name) {
+        sources[i] = src;
+    }
+}
+
+fn clamped<T: std::fmt::Display>(field: &'static str, from: T, to: T) -> ConfigIssue {
+    ConfigIssue::warn(field, format!("{} clamped to {}", from, to))
+}
+
+fn parse_num<T: std::str::FromStr>(name: &str, v: &str) -> Result<T> {
+    v.parse::<T>()
+        .map_err(|_| anyhow!("config field '{}': '{}' is not a valid number", name, v))
+}
+
+fn parse_bool(name: &str, v: &str) -> Result<bool> {
+    match v.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(anyhow!("config field '{}': '{}' is not a boolean", name, v)),
+    }
+}
+
+fn env_flag(name: &str) -> bool {
+    std::env::var(name)
+        .map(|s| {
+            let s = s.trim().to_ascii_lowercase();
+            s == "1" || s == "true" || s == "yes" || s == "on"
+        })
+        .unwrap_or(false)
+}
@@ -0,0 +1,274 @@
+//! crypto/composite — цепочка провайдеров ключей с маршрутизацией по KID.
+//!
+//! Db использует один KeyProvider, а после ротации (key_journal) страницы старых эпох подписаны
+//! ключами других KID, которые могут жить в других источниках (ENV, статические ключи, KMS +
+//! keyring). CompositeKeyProvider объединяет источники:
+//! - маршрут (KidRoute) — какие KID обслуживает источник: точный KID, префикс ("legacy-*") или все;
+//! - приоритет — порядок добавления: key(kid) опрашивает подходящие источники по очереди, первый
+//!   успешный ответ выигрывает; ошибка источника не останавливает поиск (следующий по приоритету),
+//!   итоговая ошибка перечисляет отказы всех источников.
+//!
+//! CompositeKeyProvider::from_env(root) — цепочка по умолчанию (её же использует Pager, если
+//! пользовательский провайдер не задан, см. QuiverConfig::tde_key_provider):
+//! - default_kid — ENV P1_TDE_KID (иначе "default");
+//! - ENV P1_TDE_KEY_SOURCES — порядок источников через запятую (по умолчанию "keyring,env,static");
+//! - keyring — обёрнутые DEK из <root>/keyring.bin, unwrap через EnvKmsProvider (P1_KMS_KEK_*);
+//! - env — EnvKeyProvider (P1_TDE_KEY_HEX/P1_TDE_KEY_BASE64, P1_TDE_KID);
+//! - static — ENV P1_TDE_KEYS="kid1=<hex|base64>,kid2=..." (ключи прежних эпох на время миграции).
+
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use zeroize::Zeroize;
+
+use super::keyring::KeyRingProvider;
+use super::{
+    decode_base64_trimmed, decode_hex_trimmed, slice32, EnvKeyProvider, EnvKmsProvider,
+    KeyMaterial, KeyProvider, KmsProvider, StaticKeyProvider,
+};
+
+/// Порядок источников по умолчанию (ENV P1_TDE_KEY_SOURCES).
+pub const DEFAULT_KEY_SOURCES: &str = "keyring,env,static";
+
+/// Какие KID обслуживает источник.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KidRoute {
+    /// Любой KID.
+    Any,
+    /// Ровно этот KID.
+    Exact(String),
+    /// KID с этим префиксом.
+    Prefix(String),
+}
+
+impl KidRoute {
+    /// "*" — Any, "abc*" — Prefix("abc"), иначе Exact.
+    pub fn parse(s: &str) -> Self {
+        let s = s.trim();
+        if s == "*" {
+            KidRoute::Any
+        } else if let Some(p) = s.strip_suffix('*') {
+            KidRoute::Prefix(p.to_string())
+        } else {
+            KidRoute::Exact(s.to_string())
+        }
+    }
+
+    pub fn matches(&self, kid: &str) -> bool {
+        match self {
+            KidRoute::Any => true,
+            KidRoute::Exact(k) => k == kid,
+            KidRoute::Prefix(p) => kid.starts_with(p.as_str()),
+        }
+    }
+}
+
+impl fmt::Display for KidRoute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KidRoute::Any => f.write_str("*"),
+            KidRoute::Exact(k) => f.write_str(k),
+            KidRoute::Prefix(p) => write!(f, "{}*", p),
+        }
+    }
+}
+
+struct Source {
+    name: String,
+    route: KidRoute,
+    provider: Arc<dyn KeyProvider>,
+}
+
+/// Провайдер-цепочка: KID → первый подходящий источник (по приоритету), который знает ключ.
+#[derive(Default)]
+pub struct CompositeKeyProvider {
+    sources: Vec<Source>,
+    default_kid: Option<String>,
+}
+
+impl CompositeKeyProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Добавить источник для всех KID (приоритет ниже уже добавленных).
+    pub fn with_provider<S: Into<String>>(self, name: S, provider: Arc<dyn KeyProvider>) -> Self {
+        self.with_route(name, KidRoute::Any, provider)
+    }
+
+    /// Добавить источник для KID, подходящих под route (приоритет ниже уже добавленных).
+    pub fn with_route<S: Into<String>>(
+        mut self,
+        name: S,
+        route: KidRoute,
+        provider: Arc<dyn KeyProvider>,
+    ) -> Self {
+        self.sources.push(Source {
+            name: name.into(),
+            route,
+            provider,
+        });
+        self
+    }
+
+    /// KID по умолчанию (иначе — default_kid первого источника, затем "default").
+    pub fn with_default_kid<S: Into<String>>(mut self, kid: S) -> Self {
+        self.default_kid = Some(kid.into());
+        self
+    }
+
+    /// Цепочка из окружения (порядок — ENV P1_TDE_KEY_SOURCES, см. заголовок модуля).
+    /// Не настроенные источники (нет keyring.bin, нет ключа в ENV) пропускаются.
+    pub fn from_env(root: &Path) -> Result<Self> {
+        let order = std::env::var("P1_TDE_KEY_SOURCES")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_KEY_SOURCES.to_string());
+        let mut out = Self::new();
+        if let Ok(k) = std::env::var("P1_TDE_KID") {
+            out.default_kid = Some(k);
+        }
+        for name in order.split(',').map(|s| s.trim().to_ascii_lowercase()) {
+            match name.as_str() {
+                "" => {}
+                "keyring" => {
+                    if root.join("keyring.bin").exists() {
+                        // KEK может отсутствовать: тогда провайдер объяснит это для KID из keyring
+                        let kms = EnvKmsProvider::from_env()
+                            .ok()
+                            .map(|k| Arc::new(k) as Arc<dyn KmsProvider>);
+                        out =
+                            out.with_provider("keyring", Arc::new(KeyRingProvider::new(root, kms)));
+                    }
+                }
+                "env" => {
+                    if let Ok(p) = EnvKeyProvider::from_env() {
+                        out = out.with_provider("env", Arc::new(p));
+                    }
+                }
+                "static" => {
+                    if let Ok(spec) = std::env::var("P1_TDE_KEYS") {
+                        for (kid, key) in parse_static_keys(&spec)? {
+                            out = out.with_route(
+                                "static",
+                                KidRoute::Exact(kid.clone()),
+                                Arc::new(StaticKeyProvider::new(kid, key)),
+                            );
+                        }
+                    }
+                }
+                other => {
+                    return Err(anyhow!(
+                        "P1_TDE_KEY_SOURCES: unknown key source '{}' (expected keyring|env|static)",
+                        other
+                    ))
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Источники в порядке приоритета: (имя, маршрут).
+    pub fn sources(&self) -> Vec<(String, String)> {
+        self.sources
+            .iter()
+            .map(|s| (s.name.clone(), s.route.to_string()))
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Найти ключ KID: (имя источника, материал).
+    pub fn resolve(&self, kid: &str) -> Result<(String, KeyMaterial)> {
+        let mut errs: Vec<String> = Vec::new();
+        for s in self.sources.iter().filter(|s| s.route.matches(kid)) {
+            match s.provider.key(kid) {
+                Ok(km) => return Ok((s.name.clone(), km)),
+                Err(e) => errs.push(format!("{}: {:#}", s.name, e)),
+            }
+        }
+        if errs.is_empty() {
+            return Err(anyhow!(
+                "no key source for KID '{}' (configure P1_TDE_KEY_HEX/P1_TDE_KEY_BASE64, \
+                 P1_TDE_KEYS or keyring.bin + P1_KMS_KEK_*)",
+                kid
+            ));
+        }
+        Err(anyhow!("no key for KID '{}' ({})", kid, errs.join("; ")))
+    }
+}
+
+impl KeyProvider for CompositeKeyProvider {
+    fn key(&self, kid: &str) -> Result<KeyMaterial> {
+        self.resolve(kid).map(|(_, km)| km)
+    }
+
+    fn default_kid(&self) -> &str {
+        if let Some(k) = self.default_kid.as_deref() {
+            return k;
+        }
+        self.sources
+            .first()
+            .map(|s| s.provider.default_kid())
+            .unwrap_or("default")
+    }
+}
+
+impl fmt::Debug for CompositeKeyProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompositeKeyProvider")
+            .field("sources", &self.sources())
+            .field("default_kid", &self.default_kid)
+            .finish()
+    }
+}
+
+/// Пользовательский провайдер ключей в QuiverConfig (Clone/Debug для конфига).
+#[derive(Clone)]
+pub struct KeyProviderRef(pub Arc<dyn KeyProvider>);
+
+impl KeyProviderRef {
+    pub fn new(p: Arc<dyn KeyProvider>) -> Self {
+        Self(p)
+    }
+}
+
+impl fmt::Debug for KeyProviderRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "KeyProviderRef(default_kid={})", self.0.default_kid())
+    }
+}
+
+/// P1_TDE_KEYS: "kid=<hex|base64>[,kid=...]".
+fn parse_static_keys(spec: &str) -> Result<Vec<(String, [u8; 32])>> {
+    let mut out = Vec::new();
+    for (i, item) in spec
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .enumerate()
+    {
+        // В сообщениях об ошибках не печатаем сам ключ
+        let (kid, enc) = item
+            .split_once('=')
+            .ok_or_else(|| anyhow!("P1_TDE_KEYS: entry #{}: expected kid=<hex|base64>", i + 1))?;
+        let kid = kid.trim();
+        if kid.is_empty() {
+            return Err(anyhow!("P1_TDE_KEYS: entry #{}: empty KID", i + 1));
+        }
+        let mut bytes = if enc.trim().len() == 64 {
+            decode_hex_trimmed(enc)
+        } else {
+            decode_base64_trimmed(enc)
+        }
+        .map_err(|e| anyhow!("P1_TDE_KEYS: key for KID '{}': {}", kid, e))?;
+        let key = slice32(&bytes);
+        bytes.zeroize();
+        let key = key.map_err(|e| anyhow!("P1_TDE_KEYS: KID '{}': {}", kid, e))?;
+        out.push((kid.to_string(), key));
+    }
+    Ok(out)
+}
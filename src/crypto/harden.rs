@@ -0,0 +1,221 @@
+//! crypto/harden — защита ключевого материала в памяти (mlock + исключение из core dump).
+//!
+//! KeyMaterial и провайдеры обнуляют ключи в Drop, но до этого ключи лежат в обычной памяти:
+//! могут уйти в swap и попасть в core dump. Процесс-широкий переключатель hardening'а
+//! (set_memory_hardening / ENV P1_CRYPTO_HARDEN=1, по умолчанию выключен) включает для
+//! долгоживущих ключей (SecretKey32: ключ Pager'а, StaticKeyProvider/EnvKeyProvider/EnvKmsProvider):
+//! - отдельную выровненную по странице область (ключ не делит страницу с чужими данными);
+//! - mlock этой страницы (не уходит в swap);
+//! - madvise(MADV_DONTDUMP) (Linux) — страница не попадает в core dump.
+//!
+//! Graceful fallback: если mlock не удался (RLIMIT_MEMLOCK мал, нет прав) или madvise
+//! недоступен — ключ работает как обычно, отказ считается (hardening_status) и один раз
+//! пишется [WARN]. На не-unix платформах hardening — no-op.
+//!
+//! Переключатель влияет на SecretKey32, созданные после изменения; уже созданные не меняются.
+//! Транзитные копии (KeyMaterial по значению, стек) не защищены — только обнуляются.
+
+use std::fmt;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use zeroize::Zeroize;
+
+/// Размер защищаемой области (одна страница памяти на ключ).
+const SECURE_PAGE: usize = 4096;
+
+static HARDEN_ON: AtomicBool = AtomicBool::new(false);
+static HARDEN_INIT: OnceLock<()> = OnceLock::new();
+static LOCKED_REGIONS: AtomicU64 = AtomicU64::new(0);
+static MLOCK_FAILURES: AtomicU64 = AtomicU64::new(0);
+static DONTDUMP_FAILURES: AtomicU64 = AtomicU64::new(0);
+static WARN_ONCE: OnceLock<()> = OnceLock::new();
+
+fn init_from_env() {
+    HARDEN_INIT.get_or_init(|| {
+        if let Ok(v) = std::env::var("P1_CRYPTO_HARDEN") {
+            let s = v.trim().to_ascii_lowercase();
+            HARDEN_ON.store(
+                s == "1" || s == "true" || s == "yes" || s == "on",
+                Ordering::Relaxed,
+            );
+        }
+    });
+}
+
+/// Включить/выключить hardening ключевого материала (процесс-широко; перекрывает ENV).
+pub fn set_memory_hardening(on: bool) {
+    init_from_env();
+    HARDEN_ON.store(on, Ordering::Relaxed);
+}
+
+/// Включён ли hardening (ENV P1_CRYPTO_HARDEN читается при первом обращении).
+pub fn memory_hardening_enabled() -> bool {
+    init_from_env();
+    HARDEN_ON.load(Ordering::Relaxed)
+}
+
+/// Состояние hardening'а (диагностика).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HardeningStatus {
+    pub enabled: bool,
+    /// Живых SecretKey32 под mlock.
+    pub locked_regions: u64,
+    pub locked_bytes: u64,
+    /// Отказы mlock (RLIMIT_MEMLOCK/права) — ключи остались в обычной памяти.
+    pub mlock_failures: u64,
+    /// Отказы madvise(MADV_DONTDUMP) (или платформа без поддержки).
+    pub dontdump_failures: u64,
+}
+
+pub fn hardening_status() -> HardeningStatus {
+    let regions = LOCKED_REGIONS.load(Ordering::Relaxed);
+    HardeningStatus {
+        enabled: memory_hardening_enabled(),
+        locked_regions: regions,
+        locked_bytes: regions * SECURE_PAGE as u64,
+        mlock_failures: MLOCK_FAILURES.load(Ordering::Relaxed),
+        dontdump_failures: DONTDUMP_FAILURES.load(Ordering::Relaxed),
+    }
+}
+
+fn warn_once(what: &str) {
+    if WARN_ONCE.set(()).is_ok() {
+        eprintln!(
+            "[WARN] crypto hardening: {} failed; key material stays in regular memory \
+             (raise RLIMIT_MEMLOCK / check permissions)",
+            what
+        );
+    }
+}
+
+// ---------------- platform helpers ----------------
+
+/// mlock диапазона. false — отказ (или платформа без mlock).
+#[cfg(unix)]
+pub(crate) fn mlock_region(b: &[u8]) -> bool {
+    // SAFETY: диапазон принадлежит живому буферу; mlock не меняет его содержимое.
+    unsafe { libc::mlock(b.as_ptr() as *const libc::c_void, b.len()) == 0 }
+}
+
+#[cfg(unix)]
+pub(crate) fn munlock_region(b: &[u8]) {
+    // SAFETY: см. mlock_region.
+    unsafe {
+        libc::munlock(b.as_ptr() as *const libc::c_void, b.len());
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn mlock_region(_b: &[u8]) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+pub(crate) fn munlock_region(_b: &[u8]) {}
+
+/// Исключить (dump=false) или вернуть (dump=true) страницы диапазона в core dump.
+/// Диапазон должен быть выровнен по странице.
+#[cfg(target_os = "linux")]
+fn set_dumpable(b: &[u8], dump: bool) -> bool {
+    let advice = if dump {
+        libc::MADV_DODUMP
+    } else {
+        libc::MADV_DONTDUMP
+    };
+    // SAFETY: выровненный по странице диапазон живой аллокации; advice не меняет содержимое.
+    unsafe { libc::madvise(b.as_ptr() as *mut libc::c_void, b.len(), advice) == 0 }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_dumpable(_b: &[u8], _dump: bool) -> bool {
+    false
+}
+
+// ---------------- SecretKey32 ----------------
+
+#[repr(C, align(4096))]
+struct SecurePage([u8; SECURE_PAGE]);
+
+/// 32-байтный ключ в собственной странице памяти: обнуляется в Drop; при включённом
+/// hardening'е — под mlock и вне core dump.
+pub struct SecretKey32 {
+    page: Box<SecurePage>,
+    locked: bool,
+    dontdump: bool,
+}
+
+impl SecretKey32 {
+    pub fn new(mut key: [u8; 32]) -> Self {
+        let mut s = Self {
+            page: Box::new(SecurePage([0u8; SECURE_PAGE])),
+            locked: false,
+            dontdump: false,
+        };
+        if memory_hardening_enabled() {
+            // Сначала защищаем пустую страницу, затем копируем ключ
+            s.locked = mlock_region(&s.page.0);
+            if s.locked {
+                LOCKED_REGIONS.fetch_add(1, Ordering::Relaxed);
+            } else {
+                MLOCK_FAILURES.fetch_add(1, Ordering::Relaxed);
+                warn_once("mlock");
+            }
+            s.dontdump = set_dumpable(&s.page.0, false);
+            if !s.dontdump {
+                DONTDUMP_FAILURES.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        s.page.0[..32].copy_from_slice(&key);
+        key.zeroize();
+        s
+    }
+
+    /// Под mlock ли ключ.
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Исключён ли ключ из core dump.
+    pub fn is_dontdump(&self) -> bool {
+        self.dontdump
+    }
+}
+
+impl Deref for SecretKey32 {
+    type Target = [u8; 32];
+
+    fn deref(&self) -> &[u8; 32] {
+        (&self.page.0[..32]).try_into().expect("32-byte key slot")
+    }
+}
+
+impl Clone for SecretKey32 {
+    fn clone(&self) -> Self {
+        Self::new(**self)
+    }
+}
+
+impl fmt::Debug for SecretKey32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretKey32")
+            .field("key", &"<redacted>")
+            .field("locked", &self.locked)
+            .finish()
+    }
+}
+
+impl Drop for SecretKey32 {
+    fn drop(&mut self) {
+        self.page.0.zeroize();
+        if self.dontdump {
+            set_dumpable(&self.page.0, true);
+        }
+        if self.locked {
+            munlock_region(&self.page.0);
+            LOCKED_REGIONS.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! crypto/key_check — проверочный блок ключа TDE (known-ciphertext probe в meta).
+//!
+//! Блок = nonce(12) || AES-256-GCM(KEY_CHECK_PLAINTEXT) || tag(16), AAD = "P2KCHK01" || KID.
+//! Writer запечатывает его ключом текущего KID (при open и после tde-rotate); при open
+//! (writer и RO) ensure_tde_key расшифровывает блок загруженным ключом: отсутствующий или
+//! неверный ключ отвергается сразу ошибкой TdeKeyError, а не AEAD-ошибками страниц позже.
+
+use aes_gcm::{
+    aead::{AeadInPlace, KeyInit},
+    Aes256Gcm, Key, Nonce,
+};
+use anyhow::{anyhow, Result};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::fmt;
+use std::path::PathBuf;
+
+const KEY_CHECK_AAD_MAGIC: &[u8; 8] = b"P2KCHK01";
+const KEY_CHECK_PLAINTEXT: &[u8] = b"QuiverDB TDE key check v1";
+
+/// Длина блока на диске.
+pub const KEY_CHECK_LEN: usize = 12 + KEY_CHECK_PLAINTEXT.len() + 16;
+
+/// Запечатать проверочный блок ключом `key` под `kid`.
+pub fn seal_key_check(key: &[u8; 32], kid: &str) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let mut buf = KEY_CHECK_PLAINTEXT.to_vec();
+    let tag = cipher
+        .encrypt_in_place_detached(Nonce::from_slice(&nonce), &build_aad(kid), &mut buf)
+        .map_err(|e| anyhow!("key check aes-gcm: {}", e))?;
+    let mut out = Vec::with_capacity(KEY_CHECK_LEN);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&buf);
+    out.extend_from_slice(tag.as_slice());
+    Ok(out)
+}
+
+/// Подходит ли `key` к блоку, запечатанному под `kid`.
+pub fn verify_key_check(key: &[u8; 32], kid: &str, block: &[u8]) -> bool {
+    if block.len() != KEY_CHECK_LEN {
+        return false;
+    }
+    let (nonce, rest) = block.split_at(12);
+    let (ct, tag) = rest.split_at(rest.len() - 16);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let mut buf = ct.to_vec();
+    cipher
+        .decrypt_in_place_detached(
+            Nonce::from_slice(nonce),
+            &build_aad(kid),
+            &mut buf,
+            aes_gcm::Tag::from_slice(tag),
+        )
+        .is_ok()
+        && buf == KEY_CHECK_PLAINTEXT
+}
+
+fn build_aad(kid: &str) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(8 + kid.len());
+    aad.extend_from_slice(KEY_CHECK_AAD_MAGIC);
+    aad.extend_from_slice(kid.as_bytes());
+    aad
+}
+
+/// Ключ TDE недоступен или не тот, которым зашифрована БД. Достаётся из anyhow через downcast_ref.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TdeKeyError {
+    pub path: PathBuf,
+    pub kid: String,
+    /// true — провайдер не выдал ключ; false — ключ не прошёл проверочный блок.
+    pub missing: bool,
+    /// Причина от провайдера (для missing).
+    pub detail: String,
+}
+
+impl fmt::Display for TdeKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "wrong or missing TDE key (kid={}) at {}: ",
+            self.kid,
+            self.path.display()
+        )?;
+        if self.missing {
+            write!(f, "key not available: {}", self.detail)
+        } else {
+            write!(f, "key does not match the check block in meta")
+        }
+    }
+}
+
+impl std::error::Error for TdeKeyError {}
@@ -13,7 +13,11 @@
 //! - list()         — диагностический список (kid, blob_len).
 //!
 //! Замечание: формат простой и предназначен для небольшого числа KID‑эпох.
+//!
+//! NEW: KeyRingProvider — KeyProvider поверх keyring (unwrap DEK через KmsProvider); источник
+//! "keyring" в CompositeKeyProvider (crypto/composite.rs).
 
+use crate::util::fsx::{fsync_parent_dir, open_tmp_for_write, replace_file};
 use anyhow::{anyhow, Context, Result};
 use byteorder::{ByteOrder, LittleEndian};
 use fs2::FileExt;
@@ -21,6 +25,10 @@ use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use zeroize::Zeroize;
+
+use super::{KeyMaterial, KeyProvider, KmsProvider};
 
 const MAGIC: &[u8; 8] = b"P2KEYR01";
 const VERSION: u32 = 1;
@@ -47,22 +55,6 @@ fn lock_keyring(root: &Path) -> Result<File> {
     Ok(f)
 }
 
-#[cfg(unix)]
-fn fsync_parent_dir(path: &Path) -> std::io::Result<()> {
-    use std::fs::File;
-    if let Some(parent) = path.parent() {
-        if !parent.as_os_str().is_empty() {
-            let dir = File::open(parent)?;
-            dir.sync_all()?;
-        }
-    }
-    Ok(())
-}
-#[cfg(not(unix))]
-fn fsync_parent_dir(_path: &Path) -> std::io::Result<()> {
-    Ok(())
-}
-
 pub struct KeyRing {
     root: PathBuf,
     path: PathBuf,
@@ -198,12 +190,8 @@ impl KeyRing {
             self.path.file_name().unwrap().to_string_lossy()
         ));
         let _ = std::fs::remove_file(&tmp);
-        let mut tf = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(&tmp)
-            .with_context(|| format!("open tmp {}", tmp.display()))?;
+        let mut tf =
+            open_tmp_for_write(&tmp).with_context(|| format!("open tmp {}", tmp.display()))?;
 
         // header
         tf.write_all(MAGIC)?;
@@ -229,7 +217,7 @@ impl KeyRing {
         let _ = tf.sync_all();
 
         // rename + fsync parent
-        std::fs::rename(&tmp, &self.path)?;
+        replace_file(&tmp, &self.path)?;
         let _ = fsync_parent_dir(&self.path);
 
         Ok(())
@@ -305,3 +293,53 @@ impl KeyRing {
         Ok(out)
     }
 }
+
+/// KeyProvider поверх keyring.bin: DEK для KID раскрывается через KMS при каждом key().
+/// kms=None — KEK не настроен: для KID из keyring key() вернёт ошибку с подсказкой.
+pub struct KeyRingProvider {
+    root: PathBuf,
+    kms: Option<Arc<dyn KmsProvider>>,
+}
+
+impl KeyRingProvider {
+    pub fn new(root: &Path, kms: Option<Arc<dyn KmsProvider>>) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            kms,
+        }
+    }
+}
+
+impl KeyProvider for KeyRingProvider {
+    fn key(&self, kid: &str) -> Result<KeyMaterial> {
+        let kr = KeyRing::open(&self.root)?;
+        let wrapped = kr
+            .get(kid)?
+            .ok_or_else(|| anyhow!("KID '{}' not in keyring", kid))?;
+        let kms = self.kms.as_ref().ok_or_else(|| {
+            anyhow!(
+                "KID '{}' is in keyring, but KMS KEK is not set (P1_KMS_KEK_HEX or P1_KMS_KEK_BASE64)",
+                kid
+            )
+        })?;
+        let (_kek_kid, mut dek) = kms
+            .unwrap(&wrapped)
+            .with_context(|| format!("KMS unwrap for KID '{}'", kid))?;
+        if dek.len() != 32 {
+            let n = dek.len();
+            dek.zeroize();
+            return Err(anyhow!("unwrapped DEK must be 32 bytes, got {}", n));
+        }
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&dek);
+        dek.zeroize();
+        Ok(KeyMaterial {
+            kid: kid.to_string(),
+            key,
+        })
+    }
+
+    fn default_kid(&self) -> &str {
+        "default"
+    }
+}
@@ -32,6 +32,8 @@ use rand::rngs::OsRng;
 use rand::RngCore;
 use zeroize::Zeroize;
 
+use super::SecretKey32;
+
 const MAGIC: &[u8; 8] = b"P2KMS01\0";
 const VERSION_V1: u32 = 1;
 const AAD_PREFIX: &[u8; 7] = b"P2KMS01";
@@ -56,7 +58,7 @@ pub trait KmsProvider: Send + Sync {
 #[derive(Clone, Debug)]
 pub struct EnvKmsProvider {
     kid: String,
-    kek: [u8; 32],
+    kek: SecretKey32,
 }
 
 impl EnvKmsProvider {
@@ -65,12 +67,12 @@ impl EnvKmsProvider {
         // Порядок: HEX -> BASE64
         if let Ok(hex) = std::env::var("P1_KMS_KEK_HEX") {
             let v = decode_hex_trimmed(&hex)?;
-            let kek = slice32(&v)?;
+            let kek = SecretKey32::new(slice32(&v)?);
             return Ok(Self { kid, kek });
         }
         if let Ok(b64) = std::env::var("P1_KMS_KEK_BASE64") {
             let v = decode_base64_trimmed(&b64)?;
-            let kek = slice32(&v)?;
+            let kek = SecretKey32::new(slice32(&v)?);
             return Ok(Self { kid, kek });
         }
         Err(anyhow!(
@@ -92,7 +94,7 @@ impl KmsProvider for EnvKmsProvider {
         let mut nonce = [0u8; 12];
         OsRng.fill_bytes(&mut nonce);
 
-        let key = Key::<Aes256Gcm>::from_slice(&self.kek);
+        let key = Key::<Aes256Gcm>::from_slice(&self.kek[..]);
         let cipher = Aes256Gcm::new(key);
         let aad = build_aad(kek_kid);
 
@@ -163,7 +165,7 @@ impl KmsProvider for EnvKmsProvider {
             return Err(anyhow!("unknown KEK KID '{}'", kid));
         }
 
-        let key = Key::<Aes256Gcm>::from_slice(&self.kek);
+        let key = Key::<Aes256Gcm>::from_slice(&self.kek[..]);
         let cipher = Aes256Gcm::new(key);
         let aad = build_aad(&kid);
 
@@ -178,7 +180,7 @@ impl KmsProvider for EnvKmsProvider {
 
 impl Drop for EnvKmsProvider {
     fn drop(&mut self) {
-        self.kek.zeroize();
+        // kek обнуляет SecretKey32
         self.kid.zeroize();
     }
 }
@@ -14,6 +14,12 @@
 //! - безопасное обнуление ключей (Zeroize) в Drop провайдеров и KeyMaterial.
 //! - KMS skeleton для envelope-обёртки DEK (см. модуль kms).
 //! - KeyRing — стор для обёрнутых DEK (kms-оболочки) по KID.
+//! - NEW: harden — mlock / MADV_DONTDUMP для долгоживущих ключей (SecretKey32), процесс-широкий
+//!   переключатель set_memory_hardening (ENV P1_CRYPTO_HARDEN); провайдеры держат ключи в SecretKey32.
+//! - NEW: composite — CompositeKeyProvider: цепочка источников (keyring/env/static/свои) с
+//!   маршрутизацией по KID и приоритетом; KeyRingProvider — keyring как KeyProvider.
+//! - NEW: key_check — проверочный блок ключа TDE в meta: неверный/отсутствующий ключ
+//!   отвергается при open ошибкой TdeKeyError.
 //!
 //! Использование:
 //!   let kid = kp.default_kid().to_string();
@@ -35,7 +41,21 @@ pub use kms::{EnvKmsProvider, KmsProvider};
 
 // KeyRing — стор для обёрнутых DEK
 pub mod keyring;
-pub use keyring::KeyRing;
+pub use keyring::{KeyRing, KeyRingProvider};
+
+// NEW: цепочка провайдеров с маршрутизацией по KID
+pub mod composite;
+pub use composite::{CompositeKeyProvider, KeyProviderRef, KidRoute, DEFAULT_KEY_SOURCES};
+
+// NEW: hardening памяти ключей (mlock + исключение из core dump)
+pub mod harden;
+pub use harden::{
+    hardening_status, memory_hardening_enabled, set_memory_hardening, HardeningStatus, SecretKey32,
+};
+
+// NEW: проверочный блок ключа TDE (known-ciphertext probe в meta)
+pub mod key_check;
+pub use key_check::{seal_key_check, verify_key_check, TdeKeyError};
 
 /// 32-байтный материал ключа + его KID (идентификатор).
 #[derive(Clone, Debug)]
@@ -64,14 +84,14 @@ pub trait KeyProvider: Send + Sync {
 #[derive(Clone, Debug)]
 pub struct StaticKeyProvider {
     kid: String,
-    key: [u8; 32],
+    key: SecretKey32,
 }
 
 impl StaticKeyProvider {
     pub fn new<S: Into<String>>(kid: S, key: [u8; 32]) -> Self {
         Self {
             kid: kid.into(),
-            key,
+            key: SecretKey32::new(key),
         }
     }
 }
@@ -81,7 +101,7 @@ impl KeyProvider for StaticKeyProvider {
         if kid == self.kid {
             Ok(KeyMaterial {
                 kid: self.kid.clone(),
-                key: self.key,
+                key: *self.key,
             })
         } else {
             Err(anyhow!("unknown KID '{}'", kid))
@@ -94,7 +114,7 @@ impl KeyProvider for StaticKeyProvider {
 
 impl Drop for StaticKeyProvider {
     fn drop(&mut self) {
-        self.key.zeroize();
+        // key обнуляет SecretKey32
         self.kid.zeroize();
     }
 }
@@ -106,7 +126,7 @@ impl Drop for StaticKeyProvider {
 #[derive(Clone, Debug)]
 pub struct EnvKeyProvider {
     kid: String,
-    key: [u8; 32],
+    key: SecretKey32,
 }
 
 impl EnvKeyProvider {
@@ -114,12 +134,12 @@ impl EnvKeyProvider {
         let kid = std::env::var("P1_TDE_KID").unwrap_or_else(|_| "default".to_string());
         if let Ok(hex) = std::env::var("P1_TDE_KEY_HEX") {
             let key_vec = decode_hex_trimmed(&hex)?;
-            let key = slice32(&key_vec)?;
+            let key = SecretKey32::new(slice32(&key_vec)?);
             return Ok(Self { kid, key });
         }
         if let Ok(b64) = std::env::var("P1_TDE_KEY_BASE64") {
             let key_vec = decode_base64_trimmed(&b64)?;
-            let key = slice32(&key_vec)?;
+            let key = SecretKey32::new(slice32(&key_vec)?);
             return Ok(Self { kid, key });
         }
         Err(anyhow!(
@@ -133,7 +153,7 @@ impl KeyProvider for EnvKeyProvider {
         if kid == self.kid {
             Ok(KeyMaterial {
                 kid: self.kid.clone(),
-                key: self.key,
+                key: *self.key,
             })
         } else {
             Err(anyhow!("unknown KID '{}'", kid))
@@ -146,7 +166,7 @@ impl KeyProvider for EnvKeyProvider {
 
 impl Drop for EnvKeyProvider {
     fn drop(&mut self) {
-        self.key.zeroize();
+        // key обнуляет SecretKey32
         self.kid.zeroize();
     }
 }
@@ -0,0 +1,337 @@
+//! db/alloc_check — детектор двойного использования страниц аллокатором (doctor).
+//!
+//! Страница, одновременно лежащая в free-листе и в цепочке, рано или поздно будет выдана
+//! allocate_one_page под новую запись и перезапишет живые данные. Проверка строит карту
+//! владельцев по достижимости:
+//! - KV-цепочки бакетов от голов каталога;
+//! - OVERFLOW-цепочки, на которые ссылаются записи этих страниц (как sweep_orphan_overflow —
+//!   включая затенённые версии: их плейсхолдеры всё ещё на странице);
+//! - NEW: цепочка области метаданных приложения (meta.user_meta_head, db/user_meta.rs).
+//!
+//! и сверяет её с free-листом. Находки:
+//! - free_and_used — страница в free-листе и в цепочке (двойное использование);
+//! - free_duplicates / free_out_of_range — повтор page_id в листе / page_id ≥ next_page_id;
+//! - cross_linked — страница в двух разных цепочках (две KV-цепочки, KV и OVERFLOW, две
+//!   OVERFLOW-цепочки с разными головами). Обход цепочки на такой странице останавливается:
+//!   её хвост уже учтён первым владельцем;
+//! - unreferenced_pages — ни в цепочке, ни в листе (остатки старых цепочек после компактации,
+//!   неудачные аллокации) — не ошибка, ремонт возвращает их в лист;
+//! - broken_chains — обход оборвался (ошибка чтения, страница не того типа, петля, page_id вне
+//!   диапазона).
+//!
+//! Ремонт (Db::alloc_check_fix, writer) пересобирает free-лист из достижимости: все
+//! страницы [0, next_page_id) без владельца, без повторов (FreeList::replace_all — атомарно).
+//! Перекрёстные ссылки ремонт не чинит (это порча данных — см. doctor/fsck). При broken_chains > 0
+//! ремонт отказывает: карта неполна, и в лист попали бы живые страницы. Как и sweep, ремонт
+//! рассчитан на отсутствие читателей, закрепивших старые головы (Db::open_ro в других процессах);
+//! цепочки, закреплённые курсорами Db::scan_page этого хэндла, ремонт сохраняет. NEW: как и
+//! страницы снапшотов, создаваемых сейчас (snapstore/pins); pin в ожидании голов — отказ.
+
+use anyhow::{anyhow, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::dir::NO_PAGE;
+use crate::free::FreeList;
+use crate::metrics::{
+    record_alloc_double_use, record_alloc_freelist_rebuild, record_maint_pinned_pages_kept,
+};
+use crate::page::kv::kv_for_each_record;
+use crate::page::ovf::chain::OVF_MAX_CHAIN_PAGES_GUARD;
+use crate::page::{kv_header_read_v3, ovf_header_read_v3, OFF_TYPE, PAGE_MAGIC, PAGE_TYPE_KV_RH3};
+use crate::util::decode_ovf_placeholder_v3;
+
+use super::core::Db;
+use super::maintenance::PinnedPages;
+
+/// Предел примеров каждого вида находок в отчёте (счётчики — полные).
+pub const ALLOC_CHECK_MAX_SAMPLES: usize = 1000;
+
+/// Владелец страницы по достижимости.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PageOwner {
+    /// KV-страница цепочки бакета.
+    Bucket { bucket: u32 },
+    /// Страница OVERFLOW-цепочки с головой head (ссылка — из записи бакета).
+    Overflow { bucket: u32, head: u64 },
+    /// NEW: страница области метаданных приложения (Db::meta_put).
+    UserMeta,
+}
+
+/// Страница в free-листе, достижимая из цепочки.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FreeDoubleUse {
+    pub page_id: u64,
+    pub owner: PageOwner,
+}
+
+/// Страница, на которую вышли две разные цепочки.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CrossLink {
+    pub page_id: u64,
+    pub first: PageOwner,
+    pub second: PageOwner,
+}
+
+/// Итог пересборки free-листа.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct AllocFixReport {
+    pub free_before: u64,
+    pub free_after: u64,
+}
+
+/// Отчёт проверки аллокатора (Db::alloc_check; поле alloc в DoctorReport).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AllocCheckReport {
+    pub pages_total: u64,
+    pub reachable_pages: u64,
+    pub free_entries: u64,
+    pub free_and_used: u64,
+    pub free_duplicates: u64,
+    pub free_out_of_range: u64,
+    pub cross_linked: u64,
+    pub unreferenced_pages: u64,
+    pub broken_chains: u64,
+    /// До ALLOC_CHECK_MAX_SAMPLES.
+    pub free_and_used_samples: Vec<FreeDoubleUse>,
+    /// До ALLOC_CHECK_MAX_SAMPLES.
+    pub cross_link_samples: Vec<CrossLink>,
+    /// Some — отчёт ремонта (Db::alloc_check_fix); находки выше — состояние до него.
+    pub fix: Option<AllocFixReport>,
+}
+
+impl AllocCheckReport {
+    /// Страниц с двойным использованием (в листе и в цепочке, повтор в листе, в двух цепочках).
+    pub fn double_use(&self) -> u64 {
+        self.free_and_used + self.free_duplicates + self.cross_linked
+    }
+
+    /// Нет двойного использования и мусора в free-листе.
+    pub fn is_clean(&self) -> bool {
+        self.double_use() == 0 && self.free_out_of_range == 0
+    }
+
+    pub fn print_text(&self) {
+        println!("  alloc:");
+        println!("    reachable_pages    = {}", self.reachable_pages);
+        println!("    free_entries       = {}", self.free_entries);
+        println!("    free_and_used      = {}", self.free_and_used);
+        println!("    free_duplicates    = {}", self.free_duplicates);
+        println!("    free_out_of_range  = {}", self.free_out_of_range);
+        println!("    cross_linked       = {}", self.cross_linked);
+        println!("    unreferenced_pages = {}", self.unreferenced_pages);
+        println!("    broken_chains      = {}", self.broken_chains);
+        for d in self.free_and_used_samples.iter().take(10) {
+            println!(
+                "    double-use: page {} free and in {:?}",
+                d.page_id, d.owner
+            );
+        }
+        for c in self.cross_link_samples.iter().take(10) {
+            println!(
+                "    cross-link: page {} in {:?} and {:?}",
+                c.page_id, c.first, c.second
+            );
+        }
+        if let Some(f) = &self.fix {
+            println!(
+                "    fix: free list rebuilt ({} -> {} entries)",
+                f.free_before, f.free_after
+            );
+        }
+    }
+}
+
+impl Db {
+    /// Проверка двойного использования страниц (только чтение; RO и writer).
+    pub fn alloc_check(&self) -> Result<AllocCheckReport> {
+        let (rep, _owners) = self.alloc_scan()?;
+        record_alloc_double_use(rep.double_use());
+        Ok(rep)
+    }
+
+    /// Проверка + пересборка free-листа из достижимости (writer). Отказ при broken_chains > 0.
+    pub fn alloc_check_fix(&mut self) -> Result<AllocCheckReport> {
+        self.pager.ensure_writable("alloc_check_fix")?;
+        let (mut rep, owners) = self.alloc_scan()?;
+        record_alloc_double_use(rep.double_use());
+        if rep.broken_chains > 0 {
+            return Err(anyhow!(
+                "alloc fix refused: {} chain(s) could not be walked completely (run doctor)",
+                rep.broken_chains
+            ));
+        }
+        // NEW: страницы цепочек курсоров scan_page и создаваемых снапшотов в лист не возвращаем
+        let pinned = match self.maint_pinned_pages()? {
+            PinnedPages::Pages(p) => p,
+            PinnedPages::SnapshotPending(id) => {
+                return Err(anyhow!(
+                    "alloc fix refused: snapshot {} is being created (retry when it finishes)",
+                    id
+                ))
+            }
+        };
+        let unowned = (0..rep.pages_total).filter(|pid| !owners.contains_key(pid));
+        let (kept, free): (Vec<u64>, Vec<u64>) = unowned.partition(|pid| pinned.contains(pid));
+        record_maint_pinned_pages_kept(kept.len() as u64);
+        FreeList::replace_all(&self.root, &free)?;
+        record_alloc_freelist_rebuild();
+        rep.fix = Some(AllocFixReport {
+            free_before: rep.free_entries,
+            free_after: free.len() as u64,
+        });
+        Ok(rep)
+    }
+
+    fn alloc_scan(&self) -> Result<(AllocCheckReport, HashMap<u64, PageOwner>)> {
+        let ps = self.pager.meta.page_size as usize;
+        let mut rep = AllocCheckReport {
+            pages_total: self.pager.meta.next_page_id,
+            ..Default::default()
+        };
+        let mut owners: HashMap<u64, PageOwner> = HashMap::new();
+        let mut ovf_walked: HashSet<u64> = HashSet::new();
+        let mut page = vec![0u8; ps];
+
+        for bucket in 0..self.dir.bucket_count {
+            let owner = PageOwner::Bucket { bucket };
+            let mut ovf_heads: Vec<u64> = Vec::new();
+            let mut pid = self.dir.head(bucket)?;
+            while pid != NO_PAGE {
+                if !claim(&mut rep, &mut owners, pid, owner) {
+                    break;
+                }
+                if self.pager.read_page(pid, &mut page).is_err() || !is_kv_page(&page) {
+                    rep.broken_chains += 1;
+                    break;
+                }
+                kv_for_each_record(&page, |_k, v, _exp, vflags| {
+                    if (vflags & 0x1) != 0 {
+                        return;
+                    }
+                    if let Some((_total, head)) = decode_ovf_placeholder_v3(v) {
+                        ovf_heads.push(head);
+                    }
+                });
+                pid = match kv_header_read_v3(&page) {
+                    Ok(h) => h.next_page_id,
+                    Err(_) => {
+                        rep.broken_chains += 1;
+                        break;
+                    }
+                };
+            }
+
+            // Одна и та же голова из нескольких записей — та же цепочка, не двойное использование
+            for head in ovf_heads {
+                if !ovf_walked.insert(head) {
+                    continue;
+                }
+                let owner = PageOwner::Overflow { bucket, head };
+                let mut cur = head;
+                let mut guard = 0usize;
+                while cur != NO_PAGE {
+                    guard += 1;
+                    if guard > OVF_MAX_CHAIN_PAGES_GUARD {
+                        rep.broken_chains += 1;
+                        break;
+                    }
+                    if !claim(&mut rep, &mut owners, cur, owner) {
+                        break;
+                    }
+                    if self.pager.read_page(cur, &mut page).is_err() {
+                        rep.broken_chains += 1;
+                        break;
+                    }
+                    cur = match ovf_header_read_v3(&page) {
+                        Ok(h) => h.next_page_id,
+                        Err(_) => {
+                            rep.broken_chains += 1;
+                            break;
+                        }
+                    };
+                }
+            }
+        }
+        // NEW: область метаданных приложения — вне каталога, голова в meta
+        match self.user_meta_pages() {
+            Ok(pids) => {
+                for pid in pids {
+                    if !claim(&mut rep, &mut owners, pid, PageOwner::UserMeta) {
+                        break;
+                    }
+                }
+            }
+            Err(_) => rep.broken_chains += 1,
+        }
+        rep.reachable_pages = owners.len() as u64;
+
+        let free = match FreeList::open(&self.root) {
+            Ok(fl) => fl.list()?,
+            Err(_) => Vec::new(),
+        };
+        rep.free_entries = free.len() as u64;
+        let mut in_free: HashSet<u64> = HashSet::with_capacity(free.len());
+        for pid in free {
+            if pid >= rep.pages_total {
+                rep.free_out_of_range += 1;
+            } else if !in_free.insert(pid) {
+                rep.free_duplicates += 1;
+            } else if let Some(&owner) = owners.get(&pid) {
+                rep.free_and_used += 1;
+                if rep.free_and_used_samples.len() < ALLOC_CHECK_MAX_SAMPLES {
+                    rep.free_and_used_samples.push(FreeDoubleUse {
+                        page_id: pid,
+                        owner,
+                    });
+                }
+            }
+        }
+        rep.unreferenced_pages = (0..rep.pages_total)
+            .filter(|pid| !owners.contains_key(pid) && !in_free.contains(pid))
+            .count() as u64;
+        Ok((rep, owners))
+    }
+}
+
+/// Закрепить страницу за владельцем; false — обход цепочки надо остановить
+/// (page_id вне диапазона/петля — broken, чужая страница — cross-link).
+fn claim(
+    rep: &mut AllocCheckReport,
+    owners: &mut HashMap<u64, PageOwner>,
+    pid: u64,
+    owner: PageOwner,
+) -> bool {
+    if pid >= rep.pages_total {
+        rep.broken_chains += 1;
+        return false;
+    }
+    match owners.get(&pid) {
+        None => {
+            owners.insert(pid, owner);
+            true
+        }
+        Some(&first) if first == owner => {
+            rep.broken_chains += 1;
+            false
+        }
+        Some(&first) => {
+            rep.cross_linked += 1;
+            if rep.cross_link_samples.len() < ALLOC_CHECK_MAX_SAMPLES {
+                rep.cross_link_samples.push(CrossLink {
+                    page_id: pid,
+                    first,
+                    second: owner,
+                });
+            }
+            false
+        }
+    }
+}
+fn is_kv_page(page: &[u8]) -> bool {
+    page.len() >= OFF_TYPE + 2
+        && &page[..4] == PAGE_MAGIC
+        && LittleEndian::read_u16(&page[OFF_TYPE..OFF_TYPE + 2]) == PAGE_TYPE_KV_RH3
+}
@@ -0,0 +1,210 @@
+//! db/auto_batch — AutoBatcher: очередь put/del с автоматическим сбросом батчем.
+//!
+//! Обёртка над &mut Db для кода, который пишет по одной операции, но хочет стоимость батча
+//! (один WAL-коммит, KV-packing, см. batch.rs) без перестройки под замыкание Db::batch.
+//! Операции копятся в очереди и уходят одним Db::batch, когда срабатывает любой из порогов
+//! AutoBatchLimits: число операций, суммарный размер ключей+значений или возраст первой
+//! операции в очереди. Явный сброс — flush(); при drop очередь тоже сбрасывается (ошибка
+//! такого сброса только логируется — используйте finish(), чтобы её получить).
+//!
+//! Фонового потока нет: возраст очереди проверяется при каждой put/del и в tick() (для
+//! приложений с паузами между записями). До сброса операции не видны Db::get; get() самого
+//! AutoBatcher учитывает очередь (read-your-writes).
+//!
+//! Атомарность — на уровне одного сброса: упавший батч не применяет ни одной операции и
+//! оставляет их в очереди (повторный flush попробует снова).
+
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+use crate::metrics::{record_auto_batch_drop_error, record_auto_batch_flush};
+
+use super::core::Db;
+
+/// Пороги автоматического сброса (0 / Duration::ZERO — порог выключен).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoBatchLimits {
+    /// Операций в очереди.
+    pub max_ops: usize,
+    /// Суммарный размер ключей и значений в очереди, байт.
+    pub max_bytes: usize,
+    /// Возраст первой операции в очереди.
+    pub max_delay: Duration,
+}
+
+impl Default for AutoBatchLimits {
+    fn default() -> Self {
+        Self {
+            max_ops: 1024,
+            max_bytes: 4 << 20,
+            max_delay: Duration::from_millis(10),
+        }
+    }
+}
+
+/// Итог одного сброса.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AutoBatchFlush {
+    pub ops: usize,
+    pub bytes: usize,
+}
+
+/// Очередь операций над Db с автоматическим сбросом (см. модульный комментарий).
+pub struct AutoBatcher<'a> {
+    db: &'a mut Db,
+    limits: AutoBatchLimits,
+    /// (ключ, Some(значение) — put / None — del) в порядке вызовов.
+    ops: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+    bytes: usize,
+    first_at: Option<Instant>,
+    flushes: u64,
+}
+
+impl Db {
+    /// AutoBatcher над этим хэндлом (только writer).
+    pub fn auto_batcher(&mut self, limits: AutoBatchLimits) -> Result<AutoBatcher<'_>> {
+        self.pager.ensure_writable("auto_batcher")?;
+        Ok(AutoBatcher {
+            db: self,
+            limits,
+            ops: Vec::new(),
+            bytes: 0,
+            first_at: None,
+            flushes: 0,
+        })
+    }
+}
+
+impl<'a> AutoBatcher<'a> {
+    /// Поставить put в очередь; Some — очередь сброшена этим вызовом.
+    pub fn put(&mut self, key: &[u8], value: &[u8]) -> Result<Option<AutoBatchFlush>> {
+        self.push(key, Some(value))
+    }
+
+    /// Поставить del в очередь; Some — очередь сброшена этим вызовом.
+    pub fn del(&mut self, key: &[u8]) -> Result<Option<AutoBatchFlush>> {
+        self.push(key, None)
+    }
+
+    /// Значение с учётом очереди: последняя операция над ключом в очереди, иначе Db::get.
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let key = self.db.canonical_key(key);
+        let key = key.as_ref();
+        match self.ops.iter().rev().find(|(k, _)| k.as_slice() == key) {
+            Some((_, v)) => Ok(v.clone()),
+            None => self.db.get(key),
+        }
+    }
+
+    /// Сбросить очередь, если первая операция ждёт дольше max_delay.
+    pub fn tick(&mut self) -> Result<Option<AutoBatchFlush>> {
+        if self.delay_expired() {
+            return self.flush().map(Some);
+        }
+        Ok(None)
+    }
+
+    /// Сбросить очередь одним батчем (пустая очередь — no-op).
+    pub fn flush(&mut self) -> Result<AutoBatchFlush> {
+        if self.ops.is_empty() {
+            return Ok(AutoBatchFlush::default());
+        }
+        let ops = &self.ops;
+        self.db.batch(|b| {
+            for (k, v) in ops {
+                match v {
+                    Some(v) => b.put(k, v)?,
+                    None => {
+                        b.del(k)?;
+                    }
+                }
+            }
+            Ok(())
+        })?;
+        let rep = AutoBatchFlush {
+            ops: self.ops.len(),
+            bytes: self.bytes,
+        };
+        self.ops.clear();
+        self.bytes = 0;
+        self.first_at = None;
+        self.flushes += 1;
+        record_auto_batch_flush(rep.ops as u64);
+        Ok(rep)
+    }
+
+    /// Сбросить очередь и вернуть ошибку сброса (в отличие от drop).
+    pub fn finish(mut self) -> Result<AutoBatchFlush> {
+        self.flush()
+    }
+
+    /// Операций в очереди.
+    pub fn pending(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Байт (ключи + значения) в очереди.
+    pub fn pending_bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Сбросов с момента создания.
+    pub fn flushes(&self) -> u64 {
+        self.flushes
+    }
+
+    pub fn limits(&self) -> AutoBatchLimits {
+        self.limits
+    }
+
+    /// Хэндл для чтения (очередь не учитывается).
+    pub fn db(&self) -> &Db {
+        self.db
+    }
+
+    fn push(&mut self, key: &[u8], value: Option<&[u8]>) -> Result<Option<AutoBatchFlush>> {
+        // Очередь хранит канонические ключи — get() сверяется с ними
+        let key = self.db.canonical_key(key).into_owned();
+        let key = key.as_slice();
+        if key.len() > u16::MAX as usize {
+            return Err(anyhow::anyhow!("key too long (> u16::MAX)"));
+        }
+        // Просроченная очередь уходит до новой операции: её возраст не зависит от этой записи
+        let expired = if self.delay_expired() {
+            Some(self.flush()?)
+        } else {
+            None
+        };
+        self.bytes += key.len() + value.map_or(0, |v| v.len());
+        self.ops.push((key.to_vec(), value.map(|v| v.to_vec())));
+        self.first_at.get_or_insert_with(Instant::now);
+
+        let l = self.limits;
+        if (l.max_ops > 0 && self.ops.len() >= l.max_ops)
+            || (l.max_bytes > 0 && self.bytes >= l.max_bytes)
+        {
+            return self.flush().map(Some);
+        }
+        Ok(expired)
+    }
+
+    fn delay_expired(&self) -> bool {
+        match self.first_at {
+            Some(t) => !self.limits.max_delay.is_zero() && t.elapsed() >= self.limits.max_delay,
+            None => false,
+        }
+    }
+}
+
+impl Drop for AutoBatcher<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            record_auto_batch_drop_error();
+            eprintln!(
+                "[WARN] auto-batcher: flush on drop failed, {} op(s) lost: {:#}",
+                self.ops.len(),
+                e
+            );
+        }
+    }
+}
@@ -12,13 +12,16 @@
 //! - NEW: (опционально) Lazy compaction после коммита батча — если включено ENV
 //!        P1_LAZY_COMPACT_ON_WRITE=1 и длина цепочки достигла порога (см. maintenance.rs).
 //!
+//! - NEW: idempotency-токены — Db::batch_idempotent(token, ..) пропускает батч, если батч с тем же
+//!   токеном уже закоммичен (кольцо последних токенов, см. wal/idempotency.rs).
+//!   Токен (digest) уходит в WAL кадром IDEMPOTENCY — его учитывают реплей и cdc-apply.
+//!
 //! ENV:
 //! - P1_PACK_THRESHOLD_BYTES (usize): порог «малой» записи для упаковки (default = ps/8).
 //! - P1_LAZY_COMPACT_ON_WRITE=1|true|yes|on — включить ленивую компактацию по факту записи.
 //!   Порог задаётся ENV P1_LAZY_COMPACT_THRESHOLD (см. maintenance.rs; default 64).
 
 use anyhow::{anyhow, Result};
-use byteorder::{ByteOrder, LittleEndian};
 use std::collections::HashMap;
 use std::sync::OnceLock;
 
@@ -31,8 +34,11 @@ use crate::page::{
 use crate::metrics::{record_bloom_update, record_pack_page};
 // NEW: bloom side-car для delta-update
 use crate::bloom::BloomSidecar;
+use crate::metrics::record_idem_batch_skipped;
+use crate::wal::{idem_digest, IdemDigest};
 
 use super::core::Db;
+use super::kv::OvfChainPages;
 
 // ---------------- ENV helpers ----------------
 
@@ -48,6 +54,15 @@ fn lazy_compact_on_write() -> bool {
     })
 }
 
+/// Порог «малой» записи для inline-упаковки (P1_PACK_THRESHOLD_BYTES, default = ps/8);
+/// значения больше порога уходят в OVERFLOW.
+pub(crate) fn pack_threshold_bytes(ps: usize) -> usize {
+    std::env::var("P1_PACK_THRESHOLD_BYTES")
+        .ok()
+        .and_then(|s| s.trim().parse::<usize>().ok())
+        .unwrap_or_else(|| std::cmp::max(1, ps / 8))
+}
+
 // ---------------- Pending ops model ----------------
 
 #[derive(Clone)]
@@ -65,6 +80,8 @@ struct PendingOp {
 pub struct Batch<'a> {
     db: &'a mut Db,
     pending_ops: Vec<PendingOp>,
+    // NEW: digest idempotency-токена (Db::batch_idempotent)
+    idem: Option<IdemDigest>,
 }
 
 impl Db {
@@ -73,13 +90,34 @@ impl Db {
     where
         F: FnOnce(&mut Batch<'_>) -> Result<()>,
     {
-        if self.readonly {
-            return Err(anyhow!("Db is read-only"));
-        }
+        self.pager.ensure_writable("batch")?;
         let mut b = Batch::new(self);
         f(&mut b)?;
         b.finish()
     }
+
+    /// Batch с idempotency-токеном: если батч с тем же токеном уже закоммичен
+    /// (в пределах окна кольца P1_IDEM_TOKENS), f не вызывается и возвращается Ok(false).
+    /// Иначе батч коммитится вместе с токеном и возвращается Ok(true).
+    pub fn batch_idempotent<F>(&mut self, token: &[u8], f: F) -> Result<bool>
+    where
+        F: FnOnce(&mut Batch<'_>) -> Result<()>,
+    {
+        self.pager.ensure_writable("batch_idempotent")?;
+        if token.is_empty() {
+            return Err(anyhow!("idempotency token must not be empty"));
+        }
+        let digest = idem_digest(token);
+        if self.idem_tokens_mut()?.contains(&digest).is_some() {
+            record_idem_batch_skipped();
+            return Ok(false);
+        }
+        let mut b = Batch::new(self);
+        b.idem = Some(digest);
+        f(&mut b)?;
+        b.finish()?;
+        Ok(true)
+    }
 }
 
 impl<'a> Batch<'a> {
@@ -87,20 +125,25 @@ impl<'a> Batch<'a> {
         Self {
             db,
             pending_ops: Vec::new(),
+            idem: None,
         }
     }
 
     /// put внутри batch: буферизация операции (без немедленной аллокации).
     pub fn put(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        let key = self.db.canonical_key(key);
+        let key = key.as_ref();
         if key.len() > u16::MAX as usize {
             return Err(anyhow!("key too long (> u16::MAX)"));
         }
+        let value = self.db.encode_value(value)?.into_owned();
+        self.db.hot_write(key);
         let bucket = self.db.dir.bucket_of_key(key, self.db.pager.meta.hash_kind);
         self.pending_ops.push(PendingOp {
             bucket,
             kind: OpKind::Put {
                 key: key.to_vec(),
-                value: value.to_vec(),
+                value,
             },
         });
         Ok(())
@@ -108,9 +151,12 @@ impl<'a> Batch<'a> {
 
     /// del внутри batch: буферизация tombstone‑операции.
     pub fn del(&mut self, key: &[u8]) -> Result<bool> {
+        let key = self.db.canonical_key(key);
+        let key = key.as_ref();
         if key.len() > u16::MAX as usize {
             return Err(anyhow!("key too long (> u16::MAX)"));
         }
+        self.db.hot_write(key);
         let bucket = self.db.dir.bucket_of_key(key, self.db.pager.meta.hash_kind);
         let existed = self.db.dir.head(bucket)? != NO_PAGE;
 
@@ -122,24 +168,49 @@ impl<'a> Batch<'a> {
     }
 
     /// Завершить batch: сборка OVF и KV страниц с упаковкой + один батч коммита.
+    /// NEW: критическая секция — паника внутри отравляет хэндл (db/poison.rs).
     pub fn finish(mut self) -> Result<()> {
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| self.finish_unguarded())) {
+            Ok(r) => r,
+            Err(payload) => {
+                self.db.pager.poison("batch", payload.as_ref());
+                std::panic::resume_unwind(payload)
+            }
+        }
+    }
+
+    fn finish_unguarded(&mut self) -> Result<()> {
         if self.pending_ops.is_empty() {
             return Ok(());
         }
 
         let ps = self.db.pager.meta.page_size as usize;
-        let pack_threshold = std::env::var("P1_PACK_THRESHOLD_BYTES")
-            .ok()
-            .and_then(|s| s.trim().parse::<usize>().ok())
-            .unwrap_or_else(|| std::cmp::max(1, ps / 8));
+        let pack_threshold = pack_threshold_bytes(ps);
 
         // ВАЖНО: не двигаем частично self — изымаем вектор операций целиком и оставляем пустой.
         let ops_owned: Vec<PendingOp> = std::mem::take(&mut self.pending_ops);
-
-        // 1) Сгруппируем операции по бакетам.
+        // NEW: операции батча для per-handle счётчиков (учитываются только после коммита)
+        let op_counts: Vec<(usize, Option<usize>)> = ops_owned
+            .iter()
+            .map(|op| match &op.kind {
+                OpKind::Put { key, value } => (key.len(), Some(value.len())),
+                OpKind::Del { key } => (key.len(), None),
+            })
+            .collect();
+
+        // 1) Сгруппируем операции по бакетам. Бакеты — в порядке первой операции: порядок
+        //    страниц в WAL (и событий CDC, см. cdc.rs) не зависит от порядка обхода HashMap.
         let mut by_bucket: HashMap<u32, Vec<PendingOp>> = HashMap::new();
+        let mut bucket_order: Vec<u32> = Vec::new();
         for op in ops_owned {
-            by_bucket.entry(op.bucket).or_default().push(op);
+            let bucket = op.bucket;
+            by_bucket
+                .entry(bucket)
+                .or_insert_with(|| {
+                    bucket_order.push(bucket);
+                    Vec::new()
+                })
+                .push(op);
         }
 
         // 2) Общие накопители для коммита: страницы (OVF+KV) и обновления голов.
@@ -149,13 +220,17 @@ impl<'a> Batch<'a> {
         // NEW: ключи для bloom delta-update (только put-операции)
         let mut bloom_keys: HashMap<u32, Vec<Vec<u8>>> = HashMap::new();
 
+        // NEW: срок корзины для tombstone'ов батча (0 — trash выключен, db/trash.rs)
+        let trash_deadline = self.db.trash_deadline();
+        let mut trash_deletes = 0u64;
+
         // 3) Обработаем каждый бакет отдельно.
-        for (&bucket, ops) in &by_bucket {
+        for &bucket in &bucket_order {
+            let ops = &by_bucket[&bucket];
             let prev_head = self.db.dir.head(bucket)?;
-            let mut current_head = prev_head;
 
-            // Буфер записей для текущей KV‑страницы (packer)
-            let mut packer = KvPagePacker::new(ps);
+            // Буфер записей для текущей KV‑страницы (packer) и голова цепочки бакета
+            let mut pack = BucketPack::new(ps, &mut pages_to_commit, prev_head);
 
             for op in ops.iter() {
                 match &op.kind {
@@ -168,55 +243,39 @@ impl<'a> Batch<'a> {
 
                         if v.len() > pack_threshold {
                             // Большое значение → OVERFLOW + placeholder
-                            let (ovf_head, mut ovf_pages) =
-                                self.build_overflow_chain_pages_full(v)?;
-                            pages_to_commit.append(&mut ovf_pages);
+                            let (ovf_head, mut ovf_pages) = self.db.pack_build_ovf_chain(v)?;
+                            pack.pages.append(&mut ovf_pages);
 
                             let placeholder = make_ovf_placeholder_v3(v.len() as u64, ovf_head);
-                            self.ensure_add_kv(
-                                &mut packer,
-                                &mut pages_to_commit,
-                                &mut current_head,
-                                k,
-                                placeholder.as_slice(),
-                                0,
-                                0,
-                            )?;
+                            self.db
+                                .pack_add_kv(&mut pack, k, placeholder.as_slice(), 0, 0)?;
                         } else {
                             // Малая запись → inline в packer (с возможным авто‑fallback в OVF при нехватке места)
-                            self.ensure_add_kv(
-                                &mut packer,
-                                &mut pages_to_commit,
-                                &mut current_head,
-                                k,
-                                v,
-                                0,
-                                0,
-                            )?;
+                            self.db.pack_add_kv(&mut pack, k, v, 0, 0)?;
                         }
                     }
                     OpKind::Del { key } => {
-                        // Tombstone — короткая запись, пакуем нормально (value=[])
+                        // Tombstone — короткая запись, пакуем нормально (value=[]);
+                        // в trash-режиме expires_at_sec — срок корзины (db/trash.rs)
                         let k = key.as_slice();
-                        self.ensure_add_kv(
-                            &mut packer,
-                            &mut pages_to_commit,
-                            &mut current_head,
+                        self.db.pack_add_kv(
+                            &mut pack,
                             k,
                             &[],
-                            0,
+                            trash_deadline,
                             1, // tombstone
                         )?;
+                        trash_deletes += u64::from(trash_deadline != 0);
                     }
                 }
             }
 
             // Сбросим хвостовой packer (если набралось что‑то)
-            self.flush_packer_to_pages(&mut packer, &mut pages_to_commit, &mut current_head)?;
+            self.db.pack_flush_page(&mut pack)?;
 
             // Если создали новые KV страницы — зафиксируем новую голову
-            if current_head != prev_head {
-                new_heads.insert(bucket, current_head);
+            if pack.head != prev_head {
+                new_heads.insert(bucket, pack.head);
             }
         }
 
@@ -230,43 +289,84 @@ impl<'a> Batch<'a> {
             for_commit.push((*pid, buf.as_mut_slice()));
         }
 
-        let mut updates: Vec<(u32, u64)> = new_heads.into_iter().collect();
-        updates.sort_by_key(|e| e.0);
+        let updates: Vec<(u32, u64)> = new_heads.into_iter().collect();
+        let updates = self.db.dir.coalesce_head_updates(&updates)?;
+        // NEW: бакеты, затронутые до батча мимо bloom (db/bloom_refresh.rs)
+        let bloom_pending = self.db.pager.bloom_refresh.touched_snapshot();
+
+        self.db.pager.commit_pages_batch_with_heads_idem(
+            &mut for_commit,
+            &updates,
+            self.idem.as_ref(),
+        )?;
+
+        for (klen, vlen) in op_counts {
+            match vlen {
+                Some(v) => self.db.pager.stats.note_put(klen, v),
+                None => self.db.pager.stats.note_del(),
+            }
+        }
+        if trash_deletes > 0 {
+            crate::metrics::record_trash_deletes(trash_deletes);
+        }
 
-        self.db
-            .pager
-            .commit_pages_batch_with_heads(&mut for_commit, &updates)?;
+        // NEW: токен закоммиченного батча — в кольцо (после fsync WAL; при крахе до этой точки
+        // токен восстановит реплей по кадру IDEMPOTENCY)
+        if let Some(d) = self.idem {
+            let lsn = self.db.pager.meta.last_lsn;
+            self.db.idem_tokens_mut()?.record(d, lsn)?;
+        }
 
         // 5) Мгновенная видимость читателям
         if !updates.is_empty() {
             self.db.dir.set_heads_bulk(&updates)?;
+            let buckets: Vec<u32> = updates.iter().map(|(b, _)| *b).collect();
+            self.db.hot_get_invalidate_buckets(&buckets);
         }
 
-        // 6) NEW: Bloom delta-update (best-effort) — без двойного учёта метрик
+        // 6) NEW: Bloom delta-update (best-effort) — без двойного учёта метрик.
+        //    Бакеты, затронутые раньше мимо bloom, сначала перестраиваются — иначе фильтр
+        //    объявлен бы свежим без их ключей.
         if !updates.is_empty() {
             if !bloom_keys.is_empty() {
                 if let Ok(mut sidecar) = BloomSidecar::open_or_create_for_db(&self.db, 4096, 6) {
                     let new_lsn = self.db.pager.meta.last_lsn;
-
-                    for (bucket, keys_vec) in bloom_keys.into_iter() {
-                        // соберём &[&[u8]]
-                        let key_slices: Vec<&[u8]> =
-                            keys_vec.iter().map(|k| k.as_slice()).collect();
-                        // Метрика record_bloom_update(..) уже учитывается внутри update_bucket_bits()
-                        let _ = sidecar.update_bucket_bits(bucket, &key_slices, new_lsn);
+                    let pending_ok = self
+                        .db
+                        .bloom_rebuild_pending(&mut sidecar, &bloom_pending, &[])
+                        .is_ok();
+
+                    // Все бакеты одним шагом: last_lsn — после битов всех бакетов
+                    // (метрика record_bloom_update(..) учитывается внутри)
+                    let updates: Vec<(u32, Vec<&[u8]>)> = bloom_keys
+                        .iter()
+                        .map(|(bucket, keys_vec)| {
+                            (*bucket, keys_vec.iter().map(|k| k.as_slice()).collect())
+                        })
+                        .collect();
+                    let _ = sidecar.update_buckets_bits(&updates, new_lsn);
+                    if pending_ok {
+                        self.db.bloom_mark_synced(new_lsn);
                     }
                 }
             } else {
                 // Delete-only batch: сделаем фильтр “fresh”, обновив только last_lsn
                 if let Ok(mut sidecar) = BloomSidecar::open_or_create_for_db(&self.db, 4096, 6) {
                     let new_lsn = self.db.pager.meta.last_lsn;
-                    if sidecar.set_last_lsn(new_lsn).is_ok() {
+                    if self
+                        .db
+                        .bloom_rebuild_pending(&mut sidecar, &bloom_pending, &[])
+                        .is_ok()
+                        && sidecar.set_last_lsn(new_lsn).is_ok()
+                    {
                         // метрика — без байтового апдейта
                         record_bloom_update(0);
+                        self.db.bloom_mark_synced(new_lsn);
                     }
                 }
             }
         }
+        self.db.maybe_auto_refresh_bloom();
 
         // 7) NEW: ленивый вызов компактора по затронутым бакетам (если включено)
         if lazy_compact_on_write() && !updates.is_empty() {
@@ -277,26 +377,41 @@ impl<'a> Batch<'a> {
 
         Ok(())
     }
+}
+
+// ---------------- page building (общие с bulk load) ----------------
 
-    // ---------- helpers ----------
+/// Упаковка записей одного бакета: packer текущей KV‑страницы, голова цепочки бакета и
+/// общий накопитель страниц коммита (OVF+KV).
+pub(crate) struct BucketPack<'p> {
+    pub(crate) packer: KvPagePacker,
+    pub(crate) pages: &'p mut Vec<(u64, Vec<u8>)>,
+    pub(crate) head: u64,
+}
 
+impl<'p> BucketPack<'p> {
+    pub(crate) fn new(ps: usize, pages: &'p mut Vec<(u64, Vec<u8>)>, head: u64) -> Self {
+        Self {
+            packer: KvPagePacker::new(ps),
+            pages,
+            head,
+        }
+    }
+}
+
+impl Db {
     /// Сбросить текущий packer в страницу, подвесить к текущей голове и очистить packer.
-    fn flush_packer_to_pages(
-        &mut self,
-        packer: &mut KvPagePacker,
-        pages_acc: &mut Vec<(u64, Vec<u8>)>,
-        cur_head: &mut u64,
-    ) -> Result<()> {
-        if packer.is_empty() {
+    pub(crate) fn pack_flush_page(&mut self, pack: &mut BucketPack<'_>) -> Result<()> {
+        if pack.packer.is_empty() {
             return Ok(());
         }
         // Метрики: сколько записей упаковано на страницу
-        let slots = packer.len() as u64;
+        let slots = pack.packer.len() as u64;
 
-        let pid = self.db.pager.allocate_one_page()?;
-        let page = packer.finalize_into_page(pid, *cur_head, 0)?;
-        pages_acc.push((pid, page));
-        *cur_head = pid;
+        let pid = self.pager.allocate_one_page()?;
+        let page = pack.packer.finalize_into_page(pid, pack.head, 0)?;
+        pack.pages.push((pid, page));
+        pack.head = pid;
 
         // Запишем метрику для этой страницы упаковки
         record_pack_page(slots);
@@ -309,11 +424,9 @@ impl<'a> Batch<'a> {
     ///
     /// NEW: если запись не помещается даже на пустую страницу (после flush) и это не tombstone,
     /// автоматически уходим в OVERFLOW (строим цепочку и кладём placeholder).
-    fn ensure_add_kv(
+    pub(crate) fn pack_add_kv(
         &mut self,
-        packer: &mut KvPagePacker,
-        pages_acc: &mut Vec<(u64, Vec<u8>)>,
-        cur_head: &mut u64,
+        pack: &mut BucketPack<'_>,
         key: &[u8],
         value: &[u8],
         expires_at_sec: u32,
@@ -325,26 +438,26 @@ impl<'a> Batch<'a> {
             expires_at_sec,
             vflags,
         };
-        if packer.try_add(item) {
+        if pack.packer.try_add(item) {
             return Ok(());
         }
 
         // Не влезло — сбросим страницу и повторим
-        self.flush_packer_to_pages(packer, pages_acc, cur_head)?;
+        self.pack_flush_page(pack)?;
         let item2 = KvPackItem {
             key: key.to_vec(),
             value: value.to_vec(),
             expires_at_sec,
             vflags,
         };
-        if packer.try_add(item2) {
+        if pack.packer.try_add(item2) {
             return Ok(());
         }
 
         // Всё ещё не влезает: если это не tombstone и value непустой — auto‑fallback в OVERFLOW
         if vflags == 0 && !value.is_empty() {
-            let (ovf_head, mut ovf_pages) = self.build_overflow_chain_pages_full(value)?;
-            pages_acc.append(&mut ovf_pages);
+            let (ovf_head, mut ovf_pages) = self.pack_build_ovf_chain(value)?;
+            pack.pages.append(&mut ovf_pages);
 
             let placeholder = make_ovf_placeholder_v3(value.len() as u64, ovf_head);
             let ph_item = KvPackItem {
@@ -355,19 +468,19 @@ impl<'a> Batch<'a> {
             };
 
             // Попробуем снова (на этой же пустой странице — после flush)
-            if packer.try_add(ph_item) {
+            if pack.packer.try_add(ph_item) {
                 return Ok(());
             }
 
             // Если вдруг placeholder не влез (чрезвычайно маловероятно) — сбросим ещё одну страницу и добавим
-            self.flush_packer_to_pages(packer, pages_acc, cur_head)?;
+            self.pack_flush_page(pack)?;
             let ph_item2 = KvPackItem {
                 key: key.to_vec(),
                 value: make_ovf_placeholder_v3(value.len() as u64, ovf_head),
                 expires_at_sec,
                 vflags: 0,
             };
-            if packer.try_add(ph_item2) {
+            if pack.packer.try_add(ph_item2) {
                 return Ok(());
             }
         }
@@ -379,11 +492,8 @@ impl<'a> Batch<'a> {
 
     /// Построить OVERFLOW3‑цепочку целиком в памяти (все страницы),
     /// вернуть (head_pid, pages).
-    fn build_overflow_chain_pages_full(
-        &mut self,
-        value: &[u8],
-    ) -> Result<(u64, Vec<(u64, Vec<u8>)>)> {
-        let ps = self.db.pager.meta.page_size as usize;
+    pub(crate) fn pack_build_ovf_chain(&mut self, value: &[u8]) -> Result<OvfChainPages> {
+        let ps = self.pager.meta.page_size as usize;
         let header_min = OVF_HDR_MIN;
         let cap = ps - header_min - TRAILER_LEN;
         if cap == 0 {
@@ -391,10 +501,10 @@ impl<'a> Batch<'a> {
         }
         let chunks: Vec<&[u8]> = value.chunks(cap).collect();
         let n = chunks.len();
-        let start_pid = self.db.pager.allocate_pages(n as u64)?;
+        let start_pid = self.pager.allocate_pages(n as u64)?;
 
         let mut out: Vec<(u64, Vec<u8>)> = Vec::with_capacity(n);
-        let codec_default = self.db.pager.meta.codec_default;
+        let codec_default = self.pager.meta.codec_default;
 
         for i in 0..n {
             let pid = start_pid + i as u64;
@@ -431,11 +541,5 @@ impl<'a> Batch<'a> {
 // ----------------- TLV placeholder (OVF_CHAIN) -----------------
 
 fn make_ovf_placeholder_v3(total_len: u64, head_pid: u64) -> Vec<u8> {
-    // [tag u8=0x01][len u8=16][total_len u64][head_pid u64]
-    let mut out = vec![0u8; 1 + 1 + 16];
-    out[0] = 0x01;
-    out[1] = 16;
-    LittleEndian::write_u64(&mut out[2..10], total_len);
-    LittleEndian::write_u64(&mut out[10..18], head_pid);
-    out
+    crate::util::encode_ovf_placeholder_v3(total_len, head_pid)
 }
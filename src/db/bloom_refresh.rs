@@ -0,0 +1,246 @@
+//! db/bloom_refresh — автоматическое обновление bloom.bin на writer'е (QuiverConfig::bloom_auto_refresh).
+//!
+//! Одиночные put/del (и прочие коммиты мимо Batch) не трогают bloom.bin: после них фильтр
+//! устаревает (last_lsn фильтра != last_lsn БД), читатели пропускают его (bloom_skipped_stale),
+//! и быстрый путь промахов пропадает до ручного `quiverdb bloom`.
+//!
+//! Здесь:
+//! - Pager отмечает бакеты из HEADS_UPDATE каждого коммита (BloomRefreshState::note_touched);
+//! - после коммита и при пропуске фильтра writer проверяет политику: N пропусков на хэндле или
+//!   дрейф last_lsn на M — и перестраивает биты только затронутых бакетов по их цепочкам
+//!   (BloomSidecar::rebuild_bucket), затем выставляет last_lsn фильтра (снова fresh);
+//! - если фильтр был устаревшим уже при открытии (затронутые бакеты неизвестны) — первый
+//!   проход перестраивает все бакеты;
+//! - Batch и компактация перед тем, как объявить фильтр свежим, перестраивают ранее затронутые
+//!   бакеты (их дельта покрывает только собственные ключи).
+//!
+//! Обновление best-effort: ошибка пишется [WARN] и не валит операцию. RO-хэндлы bloom.bin не
+//! пишут — свежий фильтр они подхватывают через Db::refresh.
+//!
+//! NEW: сверка после краха (Db::reconcile_bloom_after_replay, open writer'а после реплея WAL).
+//! last_lsn фильтра пишется только после долговечного коммита WAL и битов всех бакетов
+//! (BloomSidecar::update_buckets_bits), поэтому после краха фильтр либо свежий на своём
+//! last_lsn B, либо отстаёт. Если реплей прочитал WAL с LSN не позже B+1, бакеты с
+//! HEADS_UPDATE новее B известны точно — их биты перестраиваются по цепочкам, и фильтр
+//! снова свежий на last_lsn БД. Фильтр с B впереди БД (WAL потерян/подменён) сбрасывается
+//! в устаревший (last_lsn=0). keydir (mem_keydir) живёт только в памяти RO-хэндлов и
+//! строится заново при открытии — сверка ему не нужна.
+
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::bloom::BloomSidecar;
+use crate::metrics::{
+    record_bloom_auto_refresh, record_bloom_replay_invalidated, record_bloom_replay_reconciled,
+    record_bloom_skipped_stale,
+};
+use crate::wal::ReplayTouched;
+
+use super::core::Db;
+
+/// Состояние авто-обновления (поле Pager: коммиты отмечают затронутые бакеты).
+#[derive(Debug, Default)]
+pub(crate) struct BloomRefreshState {
+    /// Бакеты, изменённые после последней синхронизации фильтра.
+    touched: Mutex<BTreeSet<u32>>,
+    /// Фильтр устарел до открытия хэндла — затронутые бакеты неизвестны.
+    unknown: AtomicBool,
+    /// last_lsn фильтра на момент последней синхронизации.
+    bloom_lsn: AtomicU64,
+    /// Пропуски фильтра с последней синхронизации.
+    skips: AtomicU64,
+    running: AtomicBool,
+}
+
+impl BloomRefreshState {
+    pub(crate) fn note_touched(&self, updates: &[(u32, u64)]) {
+        if updates.is_empty() {
+            return;
+        }
+        let mut t = self.touched.lock().unwrap();
+        t.extend(updates.iter().map(|&(b, _)| b));
+    }
+
+    /// Затронутые бакеты (снимок до коммита — для Batch/компактации).
+    pub(crate) fn touched_snapshot(&self) -> BTreeSet<u32> {
+        self.touched.lock().unwrap().clone()
+    }
+
+    fn mark_synced(&self, lsn: u64) {
+        self.touched.lock().unwrap().clear();
+        self.bloom_lsn.store(lsn, Ordering::Relaxed);
+        self.skips.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Db {
+    /// Сверка bloom.bin с реплеем WAL при открытии writer'а (см. модуль), best-effort.
+    pub(crate) fn reconcile_bloom_after_replay(&self, touched: &ReplayTouched) {
+        if self.readonly || !self.root.join("bloom.bin").exists() {
+            return;
+        }
+        if let Err(e) = self.reconcile_bloom_inner(touched) {
+            eprintln!("[WARN] bloom reconcile after WAL replay: {:#}", e);
+        }
+    }
+
+    fn reconcile_bloom_inner(&self, touched: &ReplayTouched) -> Result<()> {
+        let mut sc = BloomSidecar::open(&self.root)?;
+        let b = sc.last_lsn();
+        let m = self.pager.meta.last_lsn;
+        if b > m {
+            sc.set_last_lsn(0)?;
+            record_bloom_replay_invalidated();
+            return Ok(());
+        }
+        let covered = touched.first_lsn != 0 && touched.first_lsn <= b.saturating_add(1);
+        if b == 0 || b == m || !covered || sc.buckets() != self.dir.bucket_count {
+            return Ok(());
+        }
+        for (&bucket, _) in touched.bucket_lsn.iter().filter(|(_, &lsn)| lsn > b) {
+            sc.rebuild_bucket(self, bucket)?;
+        }
+        sc.set_last_lsn(m)?;
+        record_bloom_replay_reconciled();
+        Ok(())
+    }
+
+    /// Инициализация при открытии writer'а: last_lsn фильтра и признак «устарел до открытия».
+    pub(crate) fn init_bloom_refresh(&self) {
+        if self.readonly || !self.bloom_auto_refresh.is_enabled() {
+            return;
+        }
+        if !self.root.join("bloom.bin").exists() {
+            return;
+        }
+        let st = &self.pager.bloom_refresh;
+        match BloomSidecar::open_ro(&self.root) {
+            Ok(sc) => {
+                st.bloom_lsn.store(sc.last_lsn(), Ordering::Relaxed);
+                if sc.last_lsn() != self.pager.meta.last_lsn {
+                    st.unknown.store(true, Ordering::Relaxed);
+                }
+            }
+            Err(_) => st.unknown.store(true, Ordering::Relaxed),
+        }
+    }
+
+    /// Пропуск bloom-фильтра на пути чтения: глобальная метрика + счётчик политики.
+    pub(crate) fn note_bloom_skipped_stale(&self) {
+        record_bloom_skipped_stale();
+        let p = self.bloom_auto_refresh;
+        if self.readonly || p.skipped_stale == 0 {
+            return;
+        }
+        let n = self
+            .pager
+            .bloom_refresh
+            .skips
+            .fetch_add(1, Ordering::Relaxed)
+            + 1;
+        if n >= p.skipped_stale {
+            self.maybe_auto_refresh_bloom();
+        }
+    }
+
+    /// Проверить политику и при срабатывании обновить фильтр (best-effort).
+    pub(crate) fn maybe_auto_refresh_bloom(&self) {
+        let p = self.bloom_auto_refresh;
+        if self.readonly || !p.is_enabled() {
+            return;
+        }
+        let st = &self.pager.bloom_refresh;
+        let drift = self
+            .pager
+            .meta
+            .last_lsn
+            .saturating_sub(st.bloom_lsn.load(Ordering::Relaxed));
+        let by_skips = p.skipped_stale > 0 && st.skips.load(Ordering::Relaxed) >= p.skipped_stale;
+        let by_drift = p.lsn_drift > 0 && drift >= p.lsn_drift;
+        if !by_skips && !by_drift {
+            return;
+        }
+        if let Err(e) = self.refresh_bloom_touched() {
+            eprintln!("[WARN] bloom auto-refresh: {:#}", e);
+            // не повторять на каждой операции: следующий шанс — после нового порога
+            st.skips.store(0, Ordering::Relaxed);
+            st.bloom_lsn
+                .store(self.pager.meta.last_lsn, Ordering::Relaxed);
+        }
+    }
+
+    /// Перестроить биты бакетов, затронутых с последней синхронизации, и сделать фильтр
+    /// свежим (last_lsn = last_lsn БД). Возвращает число перестроенных бакетов.
+    /// Нет bloom.bin или число бакетов не совпадает — ничего не делает (нужен `quiverdb bloom`).
+    pub fn refresh_bloom_touched(&self) -> Result<usize> {
+        self.pager.ensure_writable("refresh_bloom_touched")?;
+        let st = &self.pager.bloom_refresh;
+        if st
+            .running
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Ok(0);
+        }
+        let res = self.refresh_bloom_touched_locked();
+        st.running.store(false, Ordering::Release);
+        res
+    }
+
+    fn refresh_bloom_touched_locked(&self) -> Result<usize> {
+        let st = &self.pager.bloom_refresh;
+        let lsn = self.pager.meta.last_lsn;
+        if !self.root.join("bloom.bin").exists() {
+            st.mark_synced(lsn);
+            return Ok(0);
+        }
+        let mut sc = BloomSidecar::open(&self.root)?;
+        if sc.buckets() != self.dir.bucket_count {
+            st.mark_synced(lsn);
+            return Ok(0);
+        }
+
+        let rebuilt = if st.unknown.load(Ordering::Relaxed) {
+            sc.rebuild_all(self)?;
+            st.unknown.store(false, Ordering::Relaxed);
+            self.dir.bucket_count as usize
+        } else {
+            let touched = st.touched_snapshot();
+            for &b in &touched {
+                sc.rebuild_bucket(self, b)?;
+            }
+            if touched.is_empty() && sc.last_lsn() == lsn {
+                st.mark_synced(lsn);
+                return Ok(0);
+            }
+            touched.len()
+        };
+        sc.set_last_lsn(lsn)?;
+        st.mark_synced(lsn);
+        record_bloom_auto_refresh(rebuilt as u64);
+        Ok(rebuilt)
+    }
+
+    /// Batch/компактация: перед дельта-обновлением, которое объявит фильтр свежим, перестроить
+    /// бакеты, затронутые до их коммита (`pending`, кроме уже покрытых дельтой `covered`).
+    pub(crate) fn bloom_rebuild_pending(
+        &self,
+        sc: &mut BloomSidecar,
+        pending: &BTreeSet<u32>,
+        covered: &[u32],
+    ) -> Result<()> {
+        for &b in pending {
+            if !covered.contains(&b) && b < sc.buckets() {
+                sc.rebuild_bucket(self, b)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Фильтр синхронизирован на last_lsn (после дельта-обновления Batch/компактации).
+    pub(crate) fn bloom_mark_synced(&self, lsn: u64) {
+        self.pager.bloom_refresh.mark_synced(lsn);
+    }
+}
@@ -0,0 +1,347 @@
+//! db/bulk — bulk load: массовая загрузка несортированного потока KV-пар мимо per-put пути.
+//!
+//! Db::bulk_load() возвращает BulkLoader (builder):
+//! - put(k, v) раскладывает пары по партициям бакетов (bucket % partitions) в памяти; при
+//!   превышении mem_budget все партиции дописываются в spill-файлы (tmp_dir);
+//! - finish() обрабатывает партиции по одной: дедупликация внутри загрузки (последний put
+//!   побеждает), плотная упаковка KV-страниц бакета (KvPagePacker, большие значения — OVERFLOW3),
+//!   первая страница подвешивается к текущей голове бакета (существующие данные остаются
+//!   глубже в цепочке, загруженные — новее);
+//! - страницы пишутся чанками обычными WAL-батчами (IMAGE-кадры); головы устанавливаются ОДНИМ
+//!   батчем HEADS_UPDATE в конце.
+//!
+//! NEW: wal_images(false) — быстрый режим: страницы идут напрямую в сегменты
+//! (Pager::write_pages_unlogged: без IMAGE-кадров, с обязательным fsync данных).
+//!
+//! Атомарность: до финального HEADS_UPDATE новые страницы ни на что не ссылаются — сбой посреди
+//! загрузки оставляет прежнее видимое состояние (страницы-сироты освобождает sweep/vacuum).
+//!
+//! ВАЖНО (CDC/реплики): в режиме wal_images(false) WAL не описывает загруженные страницы —
+//! follower, применяющий WAL, получит только HEADS_UPDATE и висячие головы. Поэтому default —
+//! wal_images(true); быстрый режим допустим только для БД без CDC/реплик (либо с пересозданием
+//! follower из бэкапа/снапшота после загрузки).
+
+use anyhow::{anyhow, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+use crate::bloom::BloomSidecar;
+use crate::page::{OFF_TYPE, PAGE_TYPE_OVERFLOW3};
+
+use super::batch::{pack_threshold_bytes, BucketPack};
+use super::core::Db;
+
+/// Итог bulk load.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BulkLoadReport {
+    /// Пар принято put().
+    pub pairs_in: u64,
+    /// Пар записано (после дедупликации внутри загрузки).
+    pub pairs_written: u64,
+    pub buckets_touched: u64,
+    pub kv_pages: u64,
+    pub ovf_pages: u64,
+    /// Сколько раз буфер сбрасывался в spill-файлы.
+    pub spill_flushes: u64,
+    pub spilled_bytes: u64,
+    /// LSN батча HEADS_UPDATE.
+    pub lsn: u64,
+}
+
+type Rec = (u32, Vec<u8>, Vec<u8>);
+
+/// Builder массовой загрузки (см. модульный комментарий).
+pub struct BulkLoader<'a> {
+    db: &'a mut Db,
+    mem_budget_bytes: usize,
+    tmp_dir: Option<PathBuf>,
+    partitions: u32,
+    chunk_pages: usize,
+    wal_images: bool,
+
+    mem: Vec<Vec<Rec>>,
+    mem_bytes: usize,
+    spill_paths: Vec<Option<PathBuf>>,
+    // каталог spill по умолчанию (<root>/.bulk_tmp), созданный загрузкой — удаляется в Drop
+    created_tmp: Option<PathBuf>,
+    rep: BulkLoadReport,
+}
+
+impl Db {
+    /// Начать bulk load (только writer).
+    pub fn bulk_load(&mut self) -> Result<BulkLoader<'_>> {
+        self.pager.ensure_writable("bulk_load")?;
+        Ok(BulkLoader {
+            db: self,
+            mem_budget_bytes: 256 << 20,
+            tmp_dir: None,
+            partitions: 64,
+            chunk_pages: 4096,
+            wal_images: true,
+            mem: Vec::new(),
+            mem_bytes: 0,
+            spill_paths: Vec::new(),
+            created_tmp: None,
+            rep: BulkLoadReport::default(),
+        })
+    }
+}
+
+impl<'a> BulkLoader<'a> {
+    /// Бюджет памяти буфера пар (байт; default 256 MiB). Сверх него — spill во временные файлы.
+    pub fn mem_budget(mut self, bytes: usize) -> Self {
+        self.mem_budget_bytes = bytes.max(1);
+        self
+    }
+
+    /// Каталог spill-файлов (default — <root>/.bulk_tmp).
+    pub fn tmp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.tmp_dir = Some(dir.into());
+        self
+    }
+
+    /// Число партиций spill (default 64; не больше числа бакетов). Память finish() ≈ 1/partitions
+    /// от объёма загрузки.
+    pub fn partitions(mut self, n: u32) -> Self {
+        self.partitions = n.max(1);
+        self
+    }
+
+    /// Страниц в одном чанке записи (default 4096).
+    pub fn chunk_pages(mut self, n: usize) -> Self {
+        self.chunk_pages = n.max(1);
+        self
+    }
+
+    /// Писать страницы через WAL (IMAGE-кадры, чанками). Default true; false — быстрее, но только
+    /// для БД без CDC/реплик (follower не получит страниц).
+    pub fn wal_images(mut self, on: bool) -> Self {
+        self.wal_images = on;
+        self
+    }
+
+    /// Добавить пару (порядок произвольный; повторный ключ — побеждает последний put).
+    pub fn put(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        let key = self.db.canonical_key(key);
+        let key = key.as_ref();
+        if key.len() > u16::MAX as usize {
+            return Err(anyhow!("key too long (> u16::MAX)"));
+        }
+        let value = self.db.encode_value(value)?;
+        if self.mem.is_empty() {
+            let parts = self.partitions.min(self.db.dir.bucket_count.max(1)) as usize;
+            self.mem = (0..parts).map(|_| Vec::new()).collect();
+            self.spill_paths = vec![None; parts];
+        }
+        let bucket = self.db.dir.bucket_of_key(key, self.db.pager.meta.hash_kind);
+        let part = bucket as usize % self.mem.len();
+        self.mem_bytes += key.len() + value.len() + 64;
+        self.mem[part].push((bucket, key.to_vec(), value.to_vec()));
+        self.rep.pairs_in += 1;
+        if self.mem_bytes >= self.mem_budget_bytes {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Добавить пары из итератора.
+    pub fn extend<I, K, V>(&mut self, pairs: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        for (k, v) in pairs {
+            self.put(k.as_ref(), v.as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Записать страницы и установить головы одним HEADS_UPDATE.
+    pub fn finish(mut self) -> Result<BulkLoadReport> {
+        let ps = self.db.pager.meta.page_size as usize;
+        let pack_threshold = pack_threshold_bytes(ps);
+        let parts = self.mem.len();
+
+        let mut heads: Vec<(u32, u64)> = Vec::new();
+        let mut pending: Vec<(u64, Vec<u8>)> = Vec::new();
+        let mut bloom = BloomSidecar::open_or_create_for_db(&*self.db, 4096, 6).ok();
+
+        for part in 0..parts {
+            // 1) Собрать партицию: spill-файл (в порядке put), затем буфер памяти
+            let mut by_bucket: HashMap<u32, HashMap<Vec<u8>, Vec<u8>>> = HashMap::new();
+            if let Some(p) = self.spill_paths[part].take() {
+                read_spill(&p, |b, k, v| {
+                    by_bucket.entry(b).or_default().insert(k, v);
+                })?;
+                let _ = std::fs::remove_file(&p);
+            }
+            for (b, k, v) in std::mem::take(&mut self.mem[part]) {
+                by_bucket.entry(b).or_default().insert(k, v);
+            }
+
+            let mut buckets: Vec<u32> = by_bucket.keys().copied().collect();
+            buckets.sort_unstable();
+
+            // 2) Упаковать бакеты партиции
+            for bucket in buckets {
+                let pairs = by_bucket.remove(&bucket).unwrap_or_default();
+                let prev_head = self.db.dir.head(bucket)?;
+                let before = pending.len();
+                let mut pack = BucketPack::new(ps, &mut pending, prev_head);
+
+                for (k, v) in &pairs {
+                    if v.len() > pack_threshold {
+                        let (ovf_head, mut ovf_pages) = self.db.pack_build_ovf_chain(v)?;
+                        pack.pages.append(&mut ovf_pages);
+                        let ph = crate::util::encode_ovf_placeholder_v3(v.len() as u64, ovf_head);
+                        self.db.pack_add_kv(&mut pack, k, &ph, 0, 0)?;
+                    } else {
+                        self.db.pack_add_kv(&mut pack, k, v, 0, 0)?;
+                    }
+                }
+                self.db.pack_flush_page(&mut pack)?;
+                let cur_head = pack.head;
+                for (_, page) in &pending[before..] {
+                    if LittleEndian::read_u16(&page[OFF_TYPE..OFF_TYPE + 2]) == PAGE_TYPE_OVERFLOW3
+                    {
+                        self.rep.ovf_pages += 1;
+                    } else {
+                        self.rep.kv_pages += 1;
+                    }
+                }
+
+                if cur_head != prev_head {
+                    heads.push((bucket, cur_head));
+                }
+                self.rep.pairs_written += pairs.len() as u64;
+                self.rep.buckets_touched += 1;
+
+                if let Some(sc) = bloom.as_mut() {
+                    let keys: Vec<&[u8]> = pairs.keys().map(|k| k.as_slice()).collect();
+                    let lsn = self.db.pager.meta.last_lsn;
+                    let _ = sc.update_bucket_bits(bucket, &keys, lsn);
+                }
+
+                if pending.len() >= self.chunk_pages {
+                    self.write_chunk(&mut pending)?;
+                }
+            }
+        }
+        self.write_chunk(&mut pending)?;
+
+        // 3) Один батч HEADS_UPDATE (собственный LSN) и мгновенная видимость
+        // NEW: только реально изменённые бакеты (дубли/совпадающие головы — не в WAL)
+        let heads = self.db.dir.coalesce_head_updates(&heads)?;
+        if !heads.is_empty() {
+            self.db
+                .pager
+                .commit_pages_batch_with_heads(&mut [], &heads)?;
+            self.db.dir.set_heads_bulk(&heads)?;
+            let buckets: Vec<u32> = heads.iter().map(|(b, _)| *b).collect();
+            self.db.hot_get_invalidate_buckets(&buckets);
+        }
+        self.rep.lsn = self.db.pager.meta.last_lsn;
+        if let Some(sc) = bloom.as_mut() {
+            let _ = sc.set_last_lsn(self.rep.lsn);
+        }
+        Ok(std::mem::take(&mut self.rep))
+    }
+
+    fn write_chunk(&mut self, pending: &mut Vec<(u64, Vec<u8>)>) -> Result<()> {
+        if pending.is_empty() {
+            return Ok(());
+        }
+        let mut refs: Vec<(u64, &mut [u8])> = pending
+            .iter_mut()
+            .map(|(pid, buf)| (*pid, buf.as_mut_slice()))
+            .collect();
+        if self.wal_images {
+            self.db.pager.commit_pages_batch(&mut refs)?;
+        } else {
+            self.db.pager.write_pages_unlogged(&mut refs)?;
+        }
+        pending.clear();
+        Ok(())
+    }
+
+    fn spill(&mut self) -> Result<()> {
+        let dir = self
+            .tmp_dir
+            .clone()
+            .unwrap_or_else(|| self.db.root.join(".bulk_tmp"));
+        if self.tmp_dir.is_none() && self.created_tmp.is_none() && !dir.exists() {
+            self.created_tmp = Some(dir.clone());
+        }
+        std::fs::create_dir_all(&dir).with_context(|| format!("create {}", dir.display()))?;
+        for part in 0..self.mem.len() {
+            if self.mem[part].is_empty() {
+                continue;
+            }
+            let path = self.spill_paths[part]
+                .get_or_insert_with(|| {
+                    dir.join(format!("bulk-{}-{:04}.spill", std::process::id(), part))
+                })
+                .clone();
+            let f = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .with_context(|| format!("open spill {}", path.display()))?;
+            let mut w = BufWriter::new(f);
+            for (b, k, v) in self.mem[part].drain(..) {
+                let mut hdr = [0u8; 12];
+                LittleEndian::write_u32(&mut hdr[0..4], b);
+                LittleEndian::write_u32(&mut hdr[4..8], k.len() as u32);
+                LittleEndian::write_u32(&mut hdr[8..12], v.len() as u32);
+                w.write_all(&hdr)?;
+                w.write_all(&k)?;
+                w.write_all(&v)?;
+                self.rep.spilled_bytes += (12 + k.len() + v.len()) as u64;
+            }
+            w.flush()?;
+        }
+        self.mem_bytes = 0;
+        self.rep.spill_flushes += 1;
+        Ok(())
+    }
+}
+
+impl Drop for BulkLoader<'_> {
+    fn drop(&mut self) {
+        // Незавершённая (или прерванная ошибкой) загрузка: убрать spill-файлы
+        for p in self.spill_paths.iter().flatten() {
+            let _ = std::fs::remove_file(p);
+        }
+        if let Some(d) = self.created_tmp.take() {
+            let _ = std::fs::remove_dir(d);
+        }
+    }
+}
+
+/// Spill-запись: [u32 bucket][u32 klen][u32 vlen][key][value] (LE).
+fn read_spill<F>(path: &std::path::Path, mut cb: F) -> Result<()>
+where
+    F: FnMut(u32, Vec<u8>, Vec<u8>),
+{
+    let f = File::open(path).with_context(|| format!("open spill {}", path.display()))?;
+    let mut r = BufReader::new(f);
+    loop {
+        let mut hdr = [0u8; 12];
+        match r.read_exact(&mut hdr) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e).with_context(|| format!("read spill {}", path.display())),
+        }
+        let b = LittleEndian::read_u32(&hdr[0..4]);
+        let mut k = vec![0u8; LittleEndian::read_u32(&hdr[4..8]) as usize];
+        let mut v = vec![0u8; LittleEndian::read_u32(&hdr[8..12]) as usize];
+        r.read_exact(&mut k)?;
+        r.read_exact(&mut v)?;
+        cb(b, k, v);
+    }
+}
@@ -0,0 +1,478 @@
+//! db/clone — консистентная копия «живой» БД (live clone) без backup+restore.
+//!
+//! Алгоритм (writer источника может работать в другом процессе — блокировки не нужны):
+//! 1. Точка начала: tailer читает текущее содержимое WAL источника (всё с момента последней
+//!    ротации — страницы до неё гарантированно уже лежат в сегментах) и дальше в фоновом потоке
+//!    дописывает новые кадры в WAL клона (<dst>/wal-000001.log).
+//! 2. Копия файлов: meta, каталог, сегменты и прочие side-car'ы копируются как есть
+//!    (страницы могут оказаться «порванными» — их перекроет WAL).
+//! 3. Переключение: короткая пауза writer'а — clone пытается взять shared LOCK источника
+//!    (до CloneOptions::pause_wait_ms). Пока он удерживается, новый writer не откроется,
+//!    а финальный догон WAL точен. Если активный writer не отпустил LOCK — финальный догон
+//!    выполняется без паузы: любые байты, которые clone успел скопировать, уже описаны кадрами
+//!    WAL, прочитанными после копирования.
+//! 4. На клоне: meta получает next_page_id/last_lsn по фактическим страницам, clean_shutdown=false,
+//!    затем обычный WAL‑реплей с LSN‑гейтингом применяет захваченную дельту.
+//! 5. Проверка (verify): LSN страниц клона не превышает захваченный, головы бакетов читаются.
+//!    Если writer успел провернуть ротацию WAL между опросами (потеря кадров) — раунд
+//!    повторяется (CloneOptions::max_rounds).
+//!
+//! Не копируются: LOCK, WAL и его salvage‑копии, .wal_replay.idx, CDC‑маркеры (.heads_lsn.bin,
+//! .cdc_seq.bin, .stream_id.bin — клон — новый поток), free‑лист (правится вне WAL; освобождённые
+//! страницы клона просто не переиспользуются), bloom.bin (пересоберите `quiverdb bloom`),
+//! scrub‑состояние и .snapstore/.
+//!
+//! NEW: на ФС с reflink (XFS/btrfs/APFS; проба src → dst, util/reflink.rs) файлы клонируются
+//! мгновенно вместо копирования байтов; иначе — обычная копия (CloneReport::files_reflinked = 0).
+//!
+//! NEW: writable-ветку снапшота (snapstore/branch.rs) клонировать нельзя — её сегменты неполные;
+//! сначала branch-detach.
+
+use anyhow::{anyhow, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use fs2::FileExt;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::dir::NO_PAGE;
+use crate::meta::{adopt_user_meta_head, read_meta, write_meta_overwrite};
+use crate::page::{
+    KV_HDR_MIN, KV_OFF_LSN, OFF_TYPE, OVF_OFF_LSN, PAGE_MAGIC, PAGE_TYPE_KV_RH3,
+    PAGE_TYPE_OVERFLOW3,
+};
+use crate::pager::{Pager, DATA_SEG_EXT, DATA_SEG_PREFIX, SEGMENT_SIZE};
+use crate::util::reflink::{clone_file, reflink_supported, FileCopyMethod};
+use crate::wal::encode::write_record;
+use crate::wal::reader::WalStreamReader;
+use crate::wal::{
+    generate_stream_id, wal_path, write_wal_file_header_with_stream_id, WAL_FILE, WAL_HDR_SIZE,
+    WAL_REC_BEGIN,
+};
+
+use super::core::{Db, LOCK_FILE};
+
+/// Параметры live clone.
+#[derive(Debug, Clone)]
+pub struct CloneOptions {
+    /// Период опроса WAL источника фоновым tailer'ом (мс).
+    pub poll_ms: u64,
+    /// Сколько ждать shared LOCK источника на финальном переключении (мс). 0 — одна попытка.
+    pub pause_wait_ms: u64,
+    /// Сколько раз повторять копирование, если дельта WAL потеряна (ротация между опросами).
+    pub max_rounds: u32,
+    /// Проверить клон после реплея (LSN страниц и головы бакетов).
+    pub verify: bool,
+}
+
+impl Default for CloneOptions {
+    fn default() -> Self {
+        Self {
+            poll_ms: 5,
+            pause_wait_ms: 2000,
+            max_rounds: 3,
+            verify: true,
+        }
+    }
+}
+
+/// Отчёт live clone.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CloneReport {
+    pub src: String,
+    pub dst: String,
+    pub rounds: u32,
+    pub files_copied: u64,
+    pub bytes_copied: u64,
+    /// NEW: сколько из files_copied склонировано reflink'ом (без копирования байтов).
+    pub files_reflinked: u64,
+    pub wal_frames_captured: u64,
+    pub wal_bytes_captured: u64,
+    pub wal_rotations_seen: u64,
+    /// true — финальный догон выполнен под shared LOCK источника (writer на паузе).
+    pub writer_paused: bool,
+    pub pause_ms: u64,
+    pub last_lsn: u64,
+    pub next_page_id: u64,
+    pub heads_checked: u64,
+    pub elapsed_ms: u64,
+}
+
+/// Сделать консистентную копию БД src в dst (dst не должен существовать или должен быть пуст).
+pub fn clone_live(src: &Path, dst: &Path, opts: &CloneOptions) -> Result<CloneReport> {
+    let t0 = Instant::now();
+    read_meta(src).with_context(|| format!("read meta of source {}", src.display()))?;
+    // NEW: сегменты ветки неполные (страницы снапшота живут в SnapStore)
+    if src.join(crate::snapstore::BRANCH_FILE).exists() {
+        return Err(anyhow!(
+            "{} is a snapshot branch; run `quiverdb branch-detach` before cloning",
+            src.display()
+        ));
+    }
+    ensure_empty_dst(dst)?;
+
+    let mut last_err = None;
+    for round in 1..=opts.max_rounds.max(1) {
+        match clone_round(src, dst, opts) {
+            Ok(mut rep) => {
+                rep.rounds = round;
+                rep.src = src.display().to_string();
+                rep.dst = dst.display().to_string();
+                rep.elapsed_ms = t0.elapsed().as_millis() as u64;
+                return Ok(rep);
+            }
+            Err(e) => {
+                clear_dir(dst)?;
+                last_err = Some(e);
+            }
+        }
+    }
+    Err(last_err.unwrap().context(format!(
+        "clone {} -> {} failed",
+        src.display(),
+        dst.display()
+    )))
+}
+
+fn clone_round(src: &Path, dst: &Path, opts: &CloneOptions) -> Result<CloneReport> {
+    let mut rep = CloneReport::default();
+    fs::create_dir_all(dst).with_context(|| format!("create {}", dst.display()))?;
+
+    // [1] Точка начала: текущее содержимое WAL + фоновый tailer
+    let mut tailer = WalTailer::new(src, dst)?;
+    tailer.poll()?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop2 = stop.clone();
+    let poll = Duration::from_millis(opts.poll_ms.max(1));
+    let handle = std::thread::Builder::new()
+        .name("quiverdb-clone-tail".into())
+        .spawn(move || -> Result<WalTailer> {
+            while !stop2.load(Ordering::Relaxed) {
+                tailer.poll()?;
+                std::thread::sleep(poll);
+            }
+            Ok(tailer)
+        })?;
+
+    // [2] Копия файлов (сегменты — последними)
+    let copy_res = copy_files(src, dst, &mut rep);
+    stop.store(true, Ordering::Relaxed);
+    let tail_res = handle
+        .join()
+        .map_err(|_| anyhow!("clone WAL tailer panicked"))?;
+    copy_res?;
+    let mut tailer = tail_res?;
+
+    // [3] Переключение: пауза writer'а (shared LOCK) + финальный догон WAL
+    let tp = Instant::now();
+    let lock = try_lock_shared_for(src, Duration::from_millis(opts.pause_wait_ms))?;
+    rep.writer_paused = lock.is_some();
+    // NEW: голова области метаданных — до догона: её страницы закоммичены раньше записи meta
+    let user_meta_head = read_meta(src)?.user_meta_head;
+    tailer.poll()?;
+    drop(lock);
+    rep.pause_ms = tp.elapsed().as_millis() as u64;
+    tailer.finish()?;
+    rep.wal_frames_captured = tailer.frames;
+    rep.wal_bytes_captured = tailer.bytes;
+    rep.wal_rotations_seen = tailer.rotations;
+
+    // [4] meta клона по фактическим страницам + реплей дельты
+    let (page_end, max_page_lsn) = scan_pages(dst)?;
+    if opts.verify && tailer.max_lsn > 0 && max_page_lsn > tailer.max_lsn {
+        return Err(anyhow!(
+            "source WAL rotated before the clone captured it (page lsn {} > captured lsn {})",
+            max_page_lsn,
+            tailer.max_lsn
+        ));
+    }
+    let mut m = read_meta(dst)?;
+    m.next_page_id = m.next_page_id.max(page_end);
+    m.last_lsn = m.last_lsn.max(max_page_lsn);
+    m.clean_shutdown = false;
+    adopt_user_meta_head(&mut m, Some(user_meta_head));
+    write_meta_overwrite(dst, &m)?;
+    Pager::wal_replay_with_pager(dst)?;
+
+    // [5] Проверка
+    let db = Db::open_ro(dst).with_context(|| format!("open clone {}", dst.display()))?;
+    if opts.verify {
+        let ps = db.pager.meta.page_size as usize;
+        let mut buf = vec![0u8; ps];
+        for b in 0..db.dir.bucket_count {
+            let head = db.dir.head(b)?;
+            if head == NO_PAGE {
+                continue;
+            }
+            db.pager
+                .read_page(head, &mut buf)
+                .with_context(|| format!("clone verify: bucket {} head page {}", b, head))?;
+            rep.heads_checked += 1;
+        }
+    }
+    rep.last_lsn = db.pager.meta.last_lsn;
+    rep.next_page_id = db.pager.meta.next_page_id;
+    Ok(rep)
+}
+
+// -------------------- WAL tailer --------------------
+
+/// Последовательный захват кадров WAL источника в WAL клона (с учётом ротаций).
+struct WalTailer {
+    src_wal: PathBuf,
+    src: Option<File>,
+    out: File,
+    pos: u64,
+    reader: WalStreamReader,
+    max_lsn: u64,
+    frames: u64,
+    bytes: u64,
+    rotations: u64,
+}
+
+impl WalTailer {
+    fn new(src: &Path, dst: &Path) -> Result<Self> {
+        let out_path = dst.join(WAL_FILE);
+        let mut out = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .truncate(true)
+            .open(&out_path)
+            .with_context(|| format!("create {}", out_path.display()))?;
+        // Клон — новый поток WAL
+        write_wal_file_header_with_stream_id(&mut out, generate_stream_id())?;
+        Ok(Self {
+            src_wal: wal_path(src),
+            src: None,
+            out,
+            pos: WAL_HDR_SIZE as u64,
+            reader: WalStreamReader::new(),
+            max_lsn: 0,
+            frames: 0,
+            bytes: 0,
+            rotations: 0,
+        })
+    }
+
+    fn poll(&mut self) -> Result<()> {
+        if self.src.is_none() {
+            match File::open(&self.src_wal) {
+                Ok(f) => self.src = Some(f),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+                Err(e) => return Err(e).context(format!("open {}", self.src_wal.display())),
+            }
+        }
+        let mut f = self.src.take().unwrap();
+        let r = self.drain(&mut f);
+        self.src = Some(f);
+        r
+    }
+
+    fn drain(&mut self, f: &mut File) -> Result<()> {
+        let len = f.metadata()?.len();
+        if len < self.pos {
+            self.restart();
+        }
+        loop {
+            match self.reader.read_next(f, self.pos, len) {
+                Ok(Some((rec, next))) => {
+                    if rec.rec_type == WAL_REC_BEGIN
+                        && self.max_lsn > 0
+                        && rec.lsn > self.max_lsn.saturating_add(1)
+                    {
+                        return Err(anyhow!(
+                            "source WAL rotated before the clone captured it (gap {}..{})",
+                            self.max_lsn + 1,
+                            rec.lsn
+                        ));
+                    }
+                    write_record(
+                        &mut self.out,
+                        rec.rec_type,
+                        rec.lsn,
+                        rec.page_id,
+                        &rec.payload,
+                    )?;
+                    self.max_lsn = self.max_lsn.max(rec.lsn);
+                    self.frames += 1;
+                    self.bytes += next - self.pos;
+                    self.pos = next;
+                }
+                Ok(None) => return Ok(()),
+                Err(_) => {
+                    // Либо кадр дописывается прямо сейчас (дочитаем на следующем опросе),
+                    // либо WAL ротирован и снова вырос за pos.
+                    if self.rotated_since(f, len)? {
+                        self.restart();
+                        continue;
+                    }
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// WAL ротирован, если первый кадр файла новее всего захваченного.
+    fn rotated_since(&self, f: &mut File, len: u64) -> Result<bool> {
+        let mut rdr = WalStreamReader::new();
+        Ok(match rdr.read_next(f, WAL_HDR_SIZE as u64, len) {
+            Ok(Some((rec, _))) => rec.lsn > self.max_lsn,
+            _ => false,
+        })
+    }
+
+    fn restart(&mut self) {
+        self.pos = WAL_HDR_SIZE as u64;
+        self.reader = WalStreamReader::new();
+        self.rotations += 1;
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.out.flush()?;
+        self.out.sync_all()?;
+        Ok(())
+    }
+}
+
+// -------------------- files --------------------
+
+/// Файлы корня, которые clone не переносит (см. заголовок модуля).
+fn skip_file(name: &str) -> bool {
+    const SKIP: &[&str] = &[
+        LOCK_FILE,
+        "free",
+        "bloom.bin",
+        ".wal_replay.idx",
+        ".heads_lsn.bin",
+        ".cdc_seq.bin",
+        ".stream_id.bin",
+        ".scrub_state.json",
+        ".scrub_alerts.jsonl",
+        ".maint_audit.jsonl",
+        ".freeze.json",
+    ];
+    SKIP.contains(&name)
+        || name.starts_with("wal-")
+        || name.ends_with(".lock")
+        || name.ends_with(".tmp")
+}
+
+fn copy_files(src: &Path, dst: &Path, rep: &mut CloneReport) -> Result<()> {
+    let mut names: Vec<String> = Vec::new();
+    for e in fs::read_dir(src).with_context(|| format!("read_dir {}", src.display()))? {
+        let e = e?;
+        if !e.file_type()?.is_file() {
+            continue;
+        }
+        if let Some(n) = e.file_name().to_str() {
+            if !skip_file(n) {
+                names.push(n.to_string());
+            }
+        }
+    }
+    // Сначала мелкие файлы (meta, каталог, keyring...), затем сегменты
+    names.sort_by_key(|n| (n.starts_with(DATA_SEG_PREFIX), n.clone()));
+    let reflink = reflink_supported(src, dst);
+    for n in names {
+        let (bytes, how) = clone_file(&src.join(&n), &dst.join(&n), reflink)
+            .with_context(|| format!("copy {} -> {}", src.join(&n).display(), dst.display()))?;
+        rep.files_copied += 1;
+        rep.bytes_copied += bytes;
+        if how == FileCopyMethod::Reflink {
+            rep.files_reflinked += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Верхняя граница page_id+1 и максимальный LSN по страницам с валидной MAGIC
+/// (meta живого writer'а на диске устаревает: next_page_id/last_lsn пишутся при закрытии).
+pub(crate) fn scan_pages(root: &Path) -> Result<(u64, u64)> {
+    let m = read_meta(root)?;
+    let ps = m.page_size as u64;
+    let pps = (SEGMENT_SIZE / ps).max(1);
+    let mut end = 0u64;
+    let mut max_lsn = 0u64;
+    let mut hdr = vec![0u8; KV_HDR_MIN];
+    let mut seg_no = 1u64;
+    loop {
+        let p = root.join(format!("{}{:06}.{}", DATA_SEG_PREFIX, seg_no, DATA_SEG_EXT));
+        let mut f = match File::open(&p) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+            Err(e) => return Err(e).context(format!("open {}", p.display())),
+        };
+        let pages = f.metadata()?.len() / ps;
+        for i in 0..pages {
+            f.seek(SeekFrom::Start(i * ps))?;
+            f.read_exact(&mut hdr)?;
+            if &hdr[..4] != PAGE_MAGIC {
+                continue;
+            }
+            end = end.max((seg_no - 1) * pps + i + 1);
+            let ptype = LittleEndian::read_u16(&hdr[OFF_TYPE..OFF_TYPE + 2]);
+            let lsn = match ptype {
+                t if t == PAGE_TYPE_KV_RH3 => {
+                    LittleEndian::read_u64(&hdr[KV_OFF_LSN..KV_OFF_LSN + 8])
+                }
+                t if t == PAGE_TYPE_OVERFLOW3 => {
+                    LittleEndian::read_u64(&hdr[OVF_OFF_LSN..OVF_OFF_LSN + 8])
+                }
+                _ => 0,
+            };
+            max_lsn = max_lsn.max(lsn);
+        }
+        seg_no += 1;
+    }
+    Ok((end, max_lsn))
+}
+
+/// Shared LOCK источника: новый writer не сможет открыть БД, пока lock удерживается.
+fn try_lock_shared_for(src: &Path, wait: Duration) -> Result<Option<File>> {
+    let p = src.join(LOCK_FILE);
+    let f = match OpenOptions::new().read(true).write(true).open(&p) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).context(format!("open {}", p.display())),
+    };
+    let deadline = Instant::now() + wait;
+    loop {
+        if FileExt::try_lock_shared(&f).is_ok() {
+            return Ok(Some(f));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn ensure_empty_dst(dst: &Path) -> Result<()> {
+    if dst.exists() && fs::read_dir(dst)?.next().is_some() {
+        return Err(anyhow!(
+            "clone destination {} exists and is not empty",
+            dst.display()
+        ));
+    }
+    Ok(())
+}
+
+fn clear_dir(dst: &Path) -> Result<()> {
+    if !dst.exists() {
+        return Ok(());
+    }
+    for e in fs::read_dir(dst)? {
+        let p = e?.path();
+        if p.is_dir() {
+            fs::remove_dir_all(&p)?;
+        } else {
+            fs::remove_file(&p)?;
+        }
+    }
+    Ok(())
+}
@@ -1,22 +1,31 @@
 use anyhow::Result;
 use byteorder::{ByteOrder, LittleEndian};
-use std::collections::HashMap;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 
 use crate::dir::NO_PAGE;
 use crate::page::kv::kv_for_each_record; // packed-aware обход “новые → старые”
 use crate::page::kv_pack::{KvPackItem, KvPagePacker};
+use crate::page::ovf::chain::read_overflow_chain;
 use crate::page::{kv_header_read_v3, KV_HDR_MIN, PAGE_MAGIC, PAGE_TYPE_KV_RH3, TRAILER_LEN};
-use crate::util::now_secs;
+use crate::util::{decode_ovf_placeholder_v3, now_secs};
 // Bloom side-car для delta-update после компактации
 use crate::bloom::BloomSidecar;
 // NEW: метрики компактации
 use crate::metrics::{
-    record_compaction_keys_deleted, record_compaction_keys_selected, record_compaction_pages_packed,
+    record_compaction_keys_deleted, record_compaction_keys_selected,
+    record_compaction_pages_packed, record_expiry_frame_written,
 };
+// NEW: события истечения TTL
+use crate::wal::{encode_expiry_payload, ExpiryEvent};
 
+use super::compaction_filter::{CompactionRecord, FilterDecision};
 use super::core::Db;
+use super::kv::make_ovf_placeholder_v3;
+use super::trash::is_soft_tombstone;
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct CompactBucketReport {
     pub bucket: u32,
     pub old_chain_len: u64,
@@ -24,9 +33,17 @@ pub struct CompactBucketReport {
     pub keys_deleted: u64,
     pub pages_written: u64,
     pub new_head: u64,
+    /// NEW: ключи, вычищенные по TTL (опубликованы как ExpiryEvent)
+    pub keys_expired: u64,
+    /// NEW: ключи, удалённые фильтром компактации (FilterDecision::Remove)
+    pub keys_filtered: u64,
+    /// NEW: значения, переписанные фильтром компактации (FilterDecision::ChangeValue)
+    pub values_rewritten: u64,
+    /// NEW: удалённые ключи, оставленные в корзине до истечения срока (db/trash.rs)
+    pub keys_trashed: u64,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct CompactSummary {
     pub buckets_total: u32,
     pub buckets_compacted: u32,
@@ -34,6 +51,10 @@ pub struct CompactSummary {
     pub keys_kept_sum: u64,
     pub keys_deleted_sum: u64,
     pub pages_written_sum: u64,
+    pub keys_expired_sum: u64,
+    pub keys_filtered_sum: u64,
+    pub values_rewritten_sum: u64,
+    pub keys_trashed_sum: u64,
 }
 
 impl Db {
@@ -44,7 +65,31 @@ impl Db {
     /// - Результат упаковывается в KV‑страницы через KvPagePacker (несколько записей на страницу).
     /// - После коммита выполняется Bloom delta‑update по валидным ключам и выставляется fresh last_lsn.
     /// - NEW: метрики компактации (выбранные/удалённые ключи и упакованные страницы).
+    /// - NEW: ключи, самая новая версия которых истекла, публикуются как ExpiryEvent
+    ///   (см. db/expiry.rs); при wal_expiry_events — ещё и кадром EXPIRY в том же WAL-батче.
+    /// - NEW: зарегистрированный фильтр компактации (Db::set_compaction_filter) решает судьбу
+    ///   каждой живой записи: Keep / Remove / ChangeValue.
+    /// - NEW: при compaction_keep_versions = M > 1 сохраняются ещё до M-1 более старых живых версий
+    ///   ключа (до первого tombstone; истёкшие пропускаются). Версии пишутся «поколениями»:
+    ///   старшие — на страницы глубже, новейшие — в голову, так что get по-прежнему видит новейшую.
+    /// - NEW: записи под range tombstone (Db::delete_prefix) вычищаются как удалённые; после
+    ///   компактации бакета маркеры, существовавшие до неё, для него разрешены.
+    /// - NEW: мягкий tombstone (trash-режим, db/trash.rs) с неистёкшим сроком переносится вместе
+    ///   с живой версией под ним (она — на страницу глубже), чтобы Db::undelete мог её вернуть;
+    ///   после срока ключ вычищается как обычный tombstone.
     pub fn compact_bucket(&mut self, bucket: u32) -> Result<CompactBucketReport> {
+        self.pager.ensure_writable("compact_bucket")?;
+        // NEW: фильтр компактации и подписчики EXPIRY — пользовательский код (db/poison.rs)
+        self.critical("compact_bucket", |db| {
+            let rep = db.compact_bucket_chain(bucket)?;
+            db.resolve_range_tombstones(bucket)?;
+            Ok(rep)
+        })
+    }
+
+    fn compact_bucket_chain(&mut self, bucket: u32) -> Result<CompactBucketReport> {
+        // NEW: локации hot-get кэша бакета — до перезаписи цепочки (db/hot_get.rs)
+        self.hot_get_invalidate_buckets(&[bucket]);
         let mut rep = CompactBucketReport {
             bucket,
             ..Default::default()
@@ -59,8 +104,16 @@ impl Db {
         let now = now_secs();
 
         // Сбор финального состояния ключей в один проход:
-        // key -> Some(value bytes) (Selected) или None (Deleted).
-        let mut final_map: HashMap<Vec<u8>, Option<Vec<u8>>> = HashMap::new();
+        // key -> Some((value bytes, expires_at_sec)) (Selected) или None (Deleted).
+        let mut final_map: HashMap<Vec<u8>, Option<(Vec<u8>, u32)>> = HashMap::new();
+        // Ключи, первое (самое новое) вхождение которых истекло: key -> expires_at_sec.
+        let want_expiry = self.expiry_events_wanted();
+        let mut expired: HashMap<Vec<u8>, u32> = HashMap::new();
+        // Более старые живые версии ключа (новые → старые), если keep_versions > 1.
+        let keep_versions = self.compaction_keep_versions.max(1);
+        let mut history: HashMap<Vec<u8>, OlderVersions> = HashMap::new();
+        // Удалённые мягким tombstone'ом ключи в пределах срока корзины (db/trash.rs).
+        let mut trash: HashMap<Vec<u8>, TrashHold> = HashMap::new();
 
         let mut pid = head;
         let mut page = vec![0u8; ps];
@@ -78,12 +131,31 @@ impl Db {
             rep.old_chain_len = rep.old_chain_len.saturating_add(1);
 
             let h = kv_header_read_v3(&page)?;
+            let page_lsn = h.lsn;
             // Обходим записи "новые → старые".
             let mut touched = false;
             kv_for_each_record(&page, |k, v, expires_at_sec, vflags| {
                 touched = true;
-                // Если уже принято решение по ключу — пропускаем.
-                if final_map.contains_key(k) {
+                // Запись под range tombstone — как tombstone
+                let under_cut = self.range_cut(k).is_some_and(|cut| page_lsn <= cut);
+                let vflags = if under_cut { vflags | 0x1 } else { vflags };
+                // Если уже принято решение по ключу — пропускаем (или копим историю).
+                if let Some(st) = final_map.get(k) {
+                    if keep_versions > 1 && st.is_some() {
+                        note_older_version(
+                            &mut history,
+                            keep_versions,
+                            k,
+                            v,
+                            expires_at_sec,
+                            vflags,
+                            now,
+                        );
+                    } else if st.is_none() {
+                        if let Some(t) = trash.get_mut(k) {
+                            t.note(v, expires_at_sec, vflags, now);
+                        }
+                    }
                     return;
                 }
 
@@ -91,6 +163,9 @@ impl Db {
                 if is_tomb {
                     // Tombstone имеет приоритет — фиксируем удаление
                     final_map.insert(k.to_vec(), None);
+                    if !under_cut && is_soft_tombstone(vflags, expires_at_sec, now) {
+                        trash.insert(k.to_vec(), TrashHold::new(expires_at_sec));
+                    }
                     return;
                 }
 
@@ -98,7 +173,9 @@ impl Db {
                 let ttl_ok = expires_at_sec == 0 || now < expires_at_sec;
                 if ttl_ok {
                     // Валидное значение — переносим как есть (включая плейсхолдер OVERFLOW).
-                    final_map.insert(k.to_vec(), Some(v.to_vec()));
+                    final_map.insert(k.to_vec(), Some((v.to_vec(), expires_at_sec)));
+                } else if want_expiry && !expired.contains_key(k) {
+                    expired.insert(k.to_vec(), expires_at_sec);
                 }
             });
 
@@ -112,20 +189,48 @@ impl Db {
                     let klen = LittleEndian::read_u16(&page[off..off + 2]) as usize;
                     let vlen = LittleEndian::read_u32(&page[off + 2..off + 6]) as usize;
                     let expires_at_sec = LittleEndian::read_u32(&page[off + 6..off + 10]);
-                    let vflags = page[off + 10];
+                    let mut vflags = page[off + 10];
                     let base = off + 11;
                     let end = base.saturating_add(klen).saturating_add(vlen);
                     if end <= data_end {
                         let key = &page[base..base + klen];
-                        if !final_map.contains_key(key) {
+                        let under_cut = self.range_cut(key).is_some_and(|cut| page_lsn <= cut);
+                        if under_cut {
+                            vflags |= 0x1;
+                        }
+                        if let Some(None) = final_map.get(key) {
+                            if let Some(t) = trash.get_mut(key) {
+                                let val = &page[base + klen..base + klen + vlen];
+                                t.note(val, expires_at_sec, vflags, now);
+                            }
+                        } else if let Some(Some(_)) = final_map.get(key) {
+                            if keep_versions > 1 {
+                                let val = &page[base + klen..base + klen + vlen];
+                                note_older_version(
+                                    &mut history,
+                                    keep_versions,
+                                    key,
+                                    val,
+                                    expires_at_sec,
+                                    vflags,
+                                    now,
+                                );
+                            }
+                        } else if !final_map.contains_key(key) {
                             let is_tomb = (vflags & 0x1) == 1;
                             if is_tomb {
                                 final_map.insert(key.to_vec(), None);
+                                if !under_cut && is_soft_tombstone(vflags, expires_at_sec, now) {
+                                    trash.insert(key.to_vec(), TrashHold::new(expires_at_sec));
+                                }
                             } else {
                                 let ttl_ok = expires_at_sec == 0 || now < expires_at_sec;
                                 if ttl_ok {
                                     let val = &page[base + klen..base + klen + vlen];
-                                    final_map.insert(key.to_vec(), Some(val.to_vec()));
+                                    final_map
+                                        .insert(key.to_vec(), Some((val.to_vec(), expires_at_sec)));
+                                } else if want_expiry && !expired.contains_key(key) {
+                                    expired.insert(key.to_vec(), expires_at_sec);
                                 }
                             }
                         }
@@ -136,6 +241,57 @@ impl Db {
             pid = h.next_page_id;
         }
 
+        // Фильтр компактации: решение по каждой живой записи (OVERFLOW — целым значением).
+        // Новые OVERFLOW-цепочки переписанных значений коммитятся тем же батчем.
+        let mut filtered_out: HashSet<Vec<u8>> = HashSet::new();
+        let mut ovf_pages: Vec<(u64, Vec<u8>)> = Vec::new();
+        if let Some(filter) = self.compaction_filter.clone() {
+            let mut changed: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+            for (k, st) in final_map.iter() {
+                let (v, exp) = match st {
+                    Some((v, exp)) => (v, *exp),
+                    None => continue,
+                };
+                let full: Cow<[u8]> = match decode_ovf_placeholder_v3(v) {
+                    Some((total, ovf_head)) => {
+                        Cow::Owned(read_overflow_chain(&self.pager, ovf_head, total as usize)?)
+                    }
+                    None => Cow::Borrowed(v.as_slice()),
+                };
+                let rec = CompactionRecord {
+                    bucket,
+                    key: k,
+                    value: &full,
+                    expires_at_sec: exp,
+                };
+                match filter.filter(&rec) {
+                    FilterDecision::Keep => {}
+                    FilterDecision::Remove => {
+                        filtered_out.insert(k.clone());
+                    }
+                    FilterDecision::ChangeValue(nv) => changed.push((k.clone(), nv)),
+                }
+            }
+            for k in filtered_out.iter() {
+                final_map.remove(k);
+                history.remove(k);
+            }
+            rep.keys_filtered = filtered_out.len() as u64;
+            rep.values_rewritten = changed.len() as u64;
+            for (k, nv) in changed {
+                let stored = if self.inline_fits_one_record(ps, &k, &nv) {
+                    nv
+                } else {
+                    let (ovf_head, pages) = self.build_overflow_chain_pages(&nv)?;
+                    ovf_pages.extend(pages);
+                    make_ovf_placeholder_v3(nv.len() as u64, ovf_head)
+                };
+                if let Some(Some((v, _))) = final_map.get_mut(&k) {
+                    *v = stored;
+                }
+            }
+        }
+
         // Подсчёты итогов (kept/deleted)
         let mut keys_kept = 0u64;
         let mut keys_deleted = 0u64;
@@ -148,14 +304,60 @@ impl Db {
         rep.keys_kept = keys_kept;
         rep.keys_deleted = keys_deleted;
 
+        // Корзина: мягкий tombstone + живая версия под ним; без версии возвращать нечего.
+        let mut trash_kept: Vec<(Vec<u8>, u32, Vec<u8>, u32)> = trash
+            .into_iter()
+            .filter_map(|(k, t)| t.value.map(|(v, exp)| (k, t.deadline, v, exp)))
+            .collect();
+        trash_kept.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        rep.keys_trashed = trash_kept.len() as u64;
+
         // Метрики компактации: итоговое число выбранных/удалённых ключей
         record_compaction_keys_selected(keys_kept);
         record_compaction_keys_deleted(keys_deleted);
 
-        // Если не осталось валидных значений — head = NO_PAGE.
-        if keys_kept == 0 {
-            self.dir.set_head(bucket, NO_PAGE)?;
+        // События истечения: истёкшая новейшая версия, живой версии глубже нет.
+        // purge_lsn проставляется после коммита.
+        let mut expiry_events: Vec<ExpiryEvent> = expired
+            .into_iter()
+            .filter(|(k, _)| {
+                !matches!(final_map.get(k), Some(Some(_))) && !filtered_out.contains(k)
+            })
+            .map(|(key, expired_at)| ExpiryEvent {
+                bucket,
+                key,
+                expired_at,
+                purge_lsn: 0,
+            })
+            .collect();
+        expiry_events.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+        rep.keys_expired = expiry_events.len() as u64;
+        let expiry_payload = if self.wal_expiry_events {
+            encode_expiry_payload(&expiry_events)
+        } else {
+            Vec::new()
+        };
+
+        // Если не осталось валидных значений (и корзины) — head = NO_PAGE.
+        if keys_kept == 0 && trash_kept.is_empty() {
+            if expiry_payload.is_empty() {
+                self.dir.set_head(bucket, NO_PAGE)?;
+            } else {
+                // Кадр EXPIRY должен попасть в WAL — пустой батч с HEADS_UPDATE
+                let updates = self.dir.coalesce_head_updates(&[(bucket, NO_PAGE)])?;
+                self.pager.commit_pages_batch_with_heads_expiry(
+                    &mut [],
+                    &updates,
+                    &expiry_payload,
+                )?;
+                record_expiry_frame_written();
+                self.dir.set_head(bucket, NO_PAGE)?;
+                for ev in expiry_events.iter_mut() {
+                    ev.purge_lsn = self.pager.meta.last_lsn;
+                }
+            }
             rep.new_head = NO_PAGE;
+            self.publish_expiry_events(&expiry_events);
             return Ok(rep);
         }
 
@@ -163,14 +365,34 @@ impl Db {
         let mut packer = KvPagePacker::new(ps);
         let mut pages: Vec<(u64, Vec<u8>)> = Vec::new();
         let mut current_head: u64 = NO_PAGE;
+        let mut last_gen: usize = usize::MAX;
 
         // Для стабильности порядка отсортируем ключи по лексикографическому порядку.
         let mut selected: Vec<(Vec<u8>, Vec<u8>)> = final_map
             .into_iter()
-            .filter_map(|(k, st)| st.map(|v| (k, v)))
+            .filter_map(|(k, st)| st.map(|(v, _)| (k, v)))
             .collect();
         selected.sort_unstable_by(|a, b| a.0.cmp(&b.0));
 
+        // Поколения записи: старшие версии (rank M-1..1) — раньше, т.е. глубже в цепочке;
+        // новейшие (selected) — последними, в голову.
+        let mut generations: Vec<Vec<(Vec<u8>, Vec<u8>)>> = Vec::new();
+        for rank in (1..keep_versions).rev() {
+            let gen: Vec<(Vec<u8>, Vec<u8>)> = selected
+                .iter()
+                .filter_map(|(k, _)| {
+                    history
+                        .get(k)
+                        .and_then(|h| h.values.get(rank - 1))
+                        .map(|v| (k.clone(), v.clone()))
+                })
+                .collect();
+            if !gen.is_empty() {
+                generations.push(gen);
+            }
+        }
+        drop(history);
+
         // Помощник: сбросить packer в новую страницу и подвесить к текущей голове.
         let flush_page = |packer: &mut KvPagePacker,
                           pages_acc: &mut Vec<(u64, Vec<u8>)>,
@@ -189,22 +411,50 @@ impl Db {
             Ok(())
         };
 
-        for (k, vbytes) in selected.iter() {
+        // Записи в порядке «глубже → ближе к голове»: (поколение, key, value, expires, vflags).
+        // Значения из корзины — самое глубокое поколение; их мягкие tombstone'ы — в голове.
+        const TRASH_GEN: usize = usize::MAX - 1;
+        type Rec<'a> = (usize, &'a [u8], &'a [u8], u32, u8);
+        let records: Vec<Rec> = trash_kept
+            .iter()
+            .map(|(k, _, v, exp)| (TRASH_GEN, k.as_slice(), v.as_slice(), *exp, 0u8))
+            .chain(generations.iter().enumerate().flat_map(|(gi, g)| {
+                g.iter()
+                    .map(move |(k, v)| (gi, k.as_slice(), v.as_slice(), 0u32, 0u8))
+            }))
+            .chain(
+                selected
+                    .iter()
+                    .map(|(k, v)| (usize::MAX, k.as_slice(), v.as_slice(), 0u32, 0u8)),
+            )
+            .chain(
+                trash_kept
+                    .iter()
+                    .map(|(k, deadline, _, _)| (usize::MAX, k.as_slice(), &[][..], *deadline, 1u8)),
+            )
+            .collect();
+
+        for (gi, k, vbytes, expires_at_sec, vflags) in records {
+            // Граница поколения: версии разных рангов не делят страницу
+            if gi != last_gen {
+                flush_page(&mut packer, &mut pages, &mut current_head, self)?;
+                last_gen = gi;
+            }
             let item = KvPackItem {
-                key: k.clone(),
-                value: vbytes.clone(),
-                expires_at_sec: 0,
-                vflags: 0,
+                key: k.to_vec(),
+                value: vbytes.to_vec(),
+                expires_at_sec,
+                vflags,
             };
             if !packer.try_add(item) {
                 // Текущая страница переполнена — сбросим и начнём новую
                 flush_page(&mut packer, &mut pages, &mut current_head, self)?;
                 // После flush элемент должен поместиться (иначе это сверхстраничная запись — маловероятно)
                 let item2 = KvPackItem {
-                    key: k.clone(),
-                    value: vbytes.clone(),
-                    expires_at_sec: 0,
-                    vflags: 0,
+                    key: k.to_vec(),
+                    value: vbytes.to_vec(),
+                    expires_at_sec,
+                    vflags,
                 };
                 if !packer.try_add(item2) {
                     // Чрезвычайно редкий случай: запись не помещается даже на пустую страницу.
@@ -212,7 +462,13 @@ impl Db {
                     let pid_new = self.pager.allocate_one_page()?;
                     let mut page = vec![0u8; ps];
                     crate::page::kv_init_v3(&mut page, pid_new, 0)?;
-                    self.write_single_record_kv_page(&mut page, k, vbytes)?;
+                    self.write_single_record_kv_page_with_flags(
+                        &mut page,
+                        k,
+                        vbytes,
+                        expires_at_sec,
+                        vflags,
+                    )?;
                     {
                         let mut h = kv_header_read_v3(&mut page)?;
                         h.next_page_id = current_head;
@@ -230,14 +486,32 @@ impl Db {
         flush_page(&mut packer, &mut pages, &mut current_head, self)?;
 
         // Коммит одним батчем + обновление головы
-        let mut for_commit: Vec<(u64, &mut [u8])> = Vec::with_capacity(pages.len());
+        // OVERFLOW-цепочки переписанных фильтром значений — впереди KV-страниц (как в put)
+        let mut for_commit: Vec<(u64, &mut [u8])> =
+            Vec::with_capacity(ovf_pages.len() + pages.len());
+        for (pid, buf) in ovf_pages.iter_mut() {
+            for_commit.push((*pid, buf.as_mut_slice()));
+        }
         for (pid, buf) in pages.iter_mut() {
             for_commit.push((*pid, buf.as_mut_slice()));
         }
-        let updates = vec![(bucket, current_head)];
-        self.pager
-            .commit_pages_batch_with_heads(&mut for_commit, &updates)?;
+        // NEW: неизменённая голова (нечего переписывать) в HEADS_UPDATE не попадает
+        let updates = self.dir.coalesce_head_updates(&[(bucket, current_head)])?;
+        // NEW: бакеты, затронутые до компактации мимо bloom (db/bloom_refresh.rs)
+        let bloom_pending = self.pager.bloom_refresh.touched_snapshot();
+        self.pager.commit_pages_batch_with_heads_expiry(
+            &mut for_commit,
+            &updates,
+            &expiry_payload,
+        )?;
+        if !expiry_payload.is_empty() {
+            record_expiry_frame_written();
+        }
         self.dir.set_head(bucket, current_head)?;
+        for ev in expiry_events.iter_mut() {
+            ev.purge_lsn = self.pager.meta.last_lsn;
+        }
+        self.publish_expiry_events(&expiry_events);
 
         rep.pages_written = for_commit.len() as u64;
         rep.new_head = current_head;
@@ -247,7 +521,16 @@ impl Db {
             if let Ok(mut sidecar) = BloomSidecar::open_or_create_for_db(self, 4096, 6) {
                 let new_lsn = self.pager.meta.last_lsn;
                 let key_slices: Vec<&[u8]> = selected.iter().map(|(k, _)| k.as_slice()).collect();
-                let _ = sidecar.update_bucket_bits(bucket, &key_slices, new_lsn);
+                let pending_ok = self
+                    .bloom_rebuild_pending(&mut sidecar, &bloom_pending, &[bucket])
+                    .is_ok();
+                if sidecar
+                    .update_bucket_bits(bucket, &key_slices, new_lsn)
+                    .is_ok()
+                    && pending_ok
+                {
+                    self.bloom_mark_synced(new_lsn);
+                }
             }
         }
 
@@ -256,6 +539,7 @@ impl Db {
 
     /// Компактация всей БД.
     pub fn compact_all(&mut self) -> Result<CompactSummary> {
+        self.pager.ensure_writable("compact_all")?;
         let mut sum = CompactSummary {
             buckets_total: self.dir.bucket_count,
             ..Default::default()
@@ -273,7 +557,70 @@ impl Db {
             sum.keys_kept_sum += rep.keys_kept;
             sum.keys_deleted_sum += rep.keys_deleted;
             sum.pages_written_sum += rep.pages_written;
+            sum.keys_expired_sum += rep.keys_expired;
+            sum.keys_filtered_sum += rep.keys_filtered;
+            sum.values_rewritten_sum += rep.values_rewritten;
+            sum.keys_trashed_sum += rep.keys_trashed;
         }
         Ok(sum)
     }
 }
+
+/// Ключ в корзине: срок мягкого tombstone'а и живая версия под ним (если нашлась).
+struct TrashHold {
+    deadline: u32,
+    value: Option<(Vec<u8>, u32)>,
+    /// Под мягким tombstone'ом встречен ещё один tombstone — возвращать нечего.
+    closed: bool,
+}
+
+impl TrashHold {
+    fn new(deadline: u32) -> Self {
+        Self {
+            deadline,
+            value: None,
+            closed: false,
+        }
+    }
+
+    /// Учесть более старое вхождение ключа под мягким tombstone'ом.
+    fn note(&mut self, v: &[u8], expires_at_sec: u32, vflags: u8, now: u32) {
+        if self.closed || self.value.is_some() {
+            return;
+        }
+        if (vflags & 0x1) == 1 {
+            self.closed = true;
+        } else if expires_at_sec == 0 || now < expires_at_sec {
+            self.value = Some((v.to_vec(), expires_at_sec));
+        }
+    }
+}
+
+/// Более старые живые версии ключа, собранные при обходе цепочки.
+#[derive(Default)]
+struct OlderVersions {
+    values: Vec<Vec<u8>>,
+    /// Встречен tombstone — глубже история не продолжается.
+    closed: bool,
+}
+
+/// Учесть более старое вхождение ключа, новейшая версия которого уже выбрана.
+fn note_older_version(
+    history: &mut HashMap<Vec<u8>, OlderVersions>,
+    keep_versions: usize,
+    k: &[u8],
+    v: &[u8],
+    expires_at_sec: u32,
+    vflags: u8,
+    now: u32,
+) {
+    let h = history.entry(k.to_vec()).or_default();
+    if h.closed || h.values.len() + 1 >= keep_versions {
+        return;
+    }
+    if (vflags & 0x1) == 1 {
+        h.closed = true;
+    } else if expires_at_sec == 0 || now < expires_at_sec {
+        h.values.push(v.to_vec());
+    }
+}
@@ -0,0 +1,75 @@
+//! db/compaction_filter — пользовательский фильтр записей при компактации (аналог RocksDB
+//! compaction filter).
+//!
+//! Фильтр регистрируется на writer-хэндле (Db::set_compaction_filter) и вызывается
+//! compact_bucket/compact_all/vacuum_all для каждой живой записи (новейшая версия ключа,
+//! не tombstone, не истёкшая по TTL). Решение:
+//! - Keep — запись переносится как есть;
+//! - Remove — ключ исчезает из бакета (старые версии глубже по цепочке тоже — цепочка
+//!   переписывается целиком);
+//! - ChangeValue(v) — новое значение (inline или новая OVERFLOW-цепочка; старая цепочка
+//!   становится сиротой и освобождается sweep'ом вакуума).
+//!
+//! Значения OVERFLOW фильтр видит целиком (цепочка читается только при зарегистрированном фильтре).
+//! Фильтр вызывается из потока компактации; паника фильтра прерывает компактацию бакета до коммита.
+
+use std::sync::Arc;
+
+use super::core::Db;
+
+/// Живая запись, предъявляемая фильтру.
+#[derive(Debug, Clone, Copy)]
+pub struct CompactionRecord<'a> {
+    pub bucket: u32,
+    pub key: &'a [u8],
+    pub value: &'a [u8],
+    /// 0 — бессрочно.
+    pub expires_at_sec: u32,
+}
+
+/// Решение фильтра по записи.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterDecision {
+    Keep,
+    Remove,
+    ChangeValue(Vec<u8>),
+}
+
+/// Фильтр компактации. Реализован для замыканий `Fn(&CompactionRecord) -> FilterDecision`.
+pub trait CompactionFilter: Send + Sync {
+    fn filter(&self, rec: &CompactionRecord<'_>) -> FilterDecision;
+
+    /// Имя для логов/отчётов.
+    fn name(&self) -> &str {
+        "compaction_filter"
+    }
+}
+
+impl<F> CompactionFilter for F
+where
+    F: Fn(&CompactionRecord<'_>) -> FilterDecision + Send + Sync,
+{
+    fn filter(&self, rec: &CompactionRecord<'_>) -> FilterDecision {
+        self(rec)
+    }
+}
+
+impl Db {
+    /// Зарегистрировать фильтр компактации (заменяет предыдущий).
+    pub fn set_compaction_filter<F>(&mut self, filter: F)
+    where
+        F: CompactionFilter + 'static,
+    {
+        self.compaction_filter = Some(Arc::new(filter));
+    }
+
+    /// Снять фильтр компактации.
+    pub fn clear_compaction_filter(&mut self) {
+        self.compaction_filter = None;
+    }
+
+    /// Имя зарегистрированного фильтра (None — фильтра нет).
+    pub fn compaction_filter_name(&self) -> Option<&str> {
+        self.compaction_filter.as_deref().map(|f| f.name())
+    }
+}
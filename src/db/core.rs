@@ -7,19 +7,38 @@
 //! - Writer-only обёртки для обновления голов каталога:
 //!   Db::set_dir_head / Db::set_dir_heads_bulk (используйте в тестах/админ‑скриптах).
 //!   Прямой вызов Directory::set_head/set_heads_bulk теперь закрыт во внешнем API (pub(crate)).
-
-use anyhow::{anyhow, Context, Result};
+//! - NEW: cache prewarm — при cache_prewarm=true writer в Drop сохраняет список горячих страниц
+//!   (pager/prewarm.rs), а open_* запускает фоновый прогрев (Db::wait_prewarm — дождаться).
+//! - NEW: кольцо idempotency-токенов батчей (wal/idempotency.rs), см. Db::batch_idempotent.
+//! - NEW: подписчики событий истечения TTL (db/expiry.rs), см. Db::subscribe_expiry.
+//! - NEW: профайлер горячих префиксов (db/hotkeys.rs) — writer сохраняет топ в Drop.
+//! - NEW: фильтр компактации (db/compaction_filter.rs), см. Db::set_compaction_filter.
+//! - NEW: трансформация ключей (db/key_transform.rs) — канонические ключи во всех публичных методах.
+//! - NEW: трансформация значений (db/value_transform.rs) — encode при записи, decode при чтении.
+//! - NEW: live-refresh RO-хэндла (db/refresh.rs), см. Db::refresh.
+//! - NEW: детектор медленного IO (util/iostall.rs), см. Db::last_io_stalls.
+//! - NEW: open-time проверка/ремонт голов каталога из WAL (db/torn.rs), см. Db::open_repair_report.
+//! - NEW: range tombstones (db/range_del.rs), см. Db::delete_prefix.
+//! - NEW: ENV P1_LOCK_NOWAIT=1 — open_* не ждёт занятый LOCK, а сразу возвращает DbLockedError.
+//! - NEW: ResourceBudget (budget.rs): LOCK занимает дескриптор бюджета, keydir — байты
+//!   (MemKeyDir::charge_budget; не поместился — keydir не строится).
+//! - NEW: отравленный хэндл (db/poison.rs) не закрывается чисто: Drop не усекает WAL и не ставит
+//!   clean_shutdown — следующий open реплеит WAL.
+
+use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::fs::OpenOptions;
 use std::path::{Path, PathBuf};
 // NEW: постоянный RO-хэндл bloom.bin (ускорение get_miss/exists_miss)
 use std::sync::Arc;
 
-use crate::dir::Directory;
+use crate::dir::{BucketRouting, Directory};
 use crate::meta::{init_meta_v4, write_meta_overwrite};
 use crate::pager::Pager;
 // NEW: импорт BloomSidecar для поля Db
 use crate::bloom::BloomSidecar;
+use crate::budget::{self, FdLease, Subsystem};
+use crate::wal::{idem_digest, IdemTokens};
 
 pub(crate) const LOCK_FILE: &str = "LOCK";
 
@@ -34,6 +53,68 @@ pub(crate) fn open_lock_file(root: &Path) -> Result<std::fs::File> {
     Ok(f)
 }
 
+/// LOCK занят другим хэндлом (open_* при P1_LOCK_NOWAIT=1). Достаётся из anyhow через downcast_ref.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbLockedError {
+    pub path: PathBuf,
+    /// true — нужен был эксклюзивный lock (writer), false — разделяемый (RO).
+    pub exclusive: bool,
+}
+
+impl std::fmt::Display for DbLockedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "database is locked: {} is held by another {}",
+            self.path.display(),
+            if self.exclusive { "handle" } else { "writer" }
+        )
+    }
+}
+
+impl std::error::Error for DbLockedError {}
+
+/// Взять LOCK (exclusive — writer, shared — RO). По умолчанию ждёт освобождения;
+/// при P1_LOCK_NOWAIT=1|true|yes|on занятый LOCK — сразу DbLockedError.
+pub(crate) fn acquire_db_lock(f: &std::fs::File, root: &Path, exclusive: bool) -> Result<()> {
+    use fs2::FileExt;
+    let p = root.join(LOCK_FILE);
+    let what = if exclusive {
+        "lock_exclusive"
+    } else {
+        "lock_shared"
+    };
+    if !lock_nowait_env() {
+        let r = if exclusive {
+            f.lock_exclusive()
+        } else {
+            f.lock_shared()
+        };
+        return r.with_context(|| format!("{} {}", what, p.display()));
+    }
+    let r = if exclusive {
+        f.try_lock_exclusive()
+    } else {
+        FileExt::try_lock_shared(f)
+    };
+    match r {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == fs2::lock_contended_error().kind() => {
+            Err(DbLockedError { path: p, exclusive }.into())
+        }
+        Err(e) => Err(e).with_context(|| format!("{} {}", what, p.display())),
+    }
+}
+
+fn lock_nowait_env() -> bool {
+    std::env::var("P1_LOCK_NOWAIT")
+        .map(|s| {
+            let s = s.trim().to_ascii_lowercase();
+            s == "1" || s == "true" || s == "yes" || s == "on"
+        })
+        .unwrap_or(false)
+}
+
 /// Локация ключа для in‑memory keydir: (page_id, off).
 /// pid = NO_PAGE означает tombstone (ключ отсутствует).
 #[derive(Clone, Copy, Debug)]
@@ -49,6 +130,14 @@ pub struct MemKeyLoc {
 pub struct MemKeyDir {
     // buckets == maps.len()
     maps: Vec<HashMap<Vec<u8>, MemKeyLoc>>,
+    // NEW: байты, учтённые в ResourceBudget (Subsystem::Keydir)
+    charged: u64,
+}
+
+impl Drop for MemKeyDir {
+    fn drop(&mut self) {
+        budget::release(Subsystem::Keydir, self.charged);
+    }
 }
 
 impl MemKeyDir {
@@ -57,7 +146,7 @@ impl MemKeyDir {
         for _ in 0..buckets {
             maps.push(HashMap::new());
         }
-        Self { maps }
+        Self { maps, charged: 0 }
     }
 
     #[inline]
@@ -109,6 +198,27 @@ impl MemKeyDir {
         }
     }
 
+    /// Оценка занимаемой памяти: ключ + MemKeyLoc + служебные байты HashMap/Vec на запись.
+    pub fn estimated_bytes(&self) -> u64 {
+        const PER_ENTRY: u64 = 48;
+        self.maps
+            .iter()
+            .flat_map(|m| m.keys())
+            .map(|k| k.len() as u64 + PER_ENTRY)
+            .sum()
+    }
+
+    /// Переучесть keydir в ResourceBudget по текущему размеру; false — не помещается.
+    pub(crate) fn charge_budget(&mut self) -> bool {
+        budget::release(Subsystem::Keydir, std::mem::take(&mut self.charged));
+        let need = self.estimated_bytes();
+        if !budget::try_charge(Subsystem::Keydir, need) {
+            return false;
+        }
+        self.charged = need;
+        true
+    }
+
     /// Обход всех пар (bucket, key, pid). NO_PAGE не фильтруется — решает вызывающий код.
     #[inline]
     pub fn for_each<F: FnMut(u32, &[u8], u64)>(&self, mut f: F) {
@@ -133,6 +243,23 @@ impl MemKeyDir {
         }
     }
 
+    /// NEW: обход одного бакета (key, pid), опционально по префиксу.
+    #[inline]
+    pub fn for_each_in_bucket<F: FnMut(&[u8], u64)>(
+        &self,
+        bucket: u32,
+        prefix: Option<&[u8]>,
+        mut f: F,
+    ) {
+        if let Some(map) = self.maps.get(bucket as usize) {
+            for (k, loc) in map.iter() {
+                if prefix.is_none_or(|p| k.starts_with(p)) {
+                    f(k.as_slice(), loc.pid);
+                }
+            }
+        }
+    }
+
     /// Обход всех пар (bucket, key, pid, off).
     #[inline]
     pub fn for_each_with_off<F: FnMut(u32, &[u8], MemKeyLoc)>(&self, mut f: F) {
@@ -167,6 +294,8 @@ pub struct Db {
     pub pager: Pager,
     pub dir: Directory,
     pub(crate) _lock: std::fs::File, // держим дескриптор
+    // NEW: дескриптор LOCK в ResourceBudget (max_open_fds)
+    pub(crate) _fd_lease: FdLease,
     pub(crate) readonly: bool,
 
     // optional in‑memory keydir (RO‑ускорение get/exists/scan). Если Some — используем.
@@ -175,10 +304,79 @@ pub struct Db {
     // NEW: кэшированный RO‑хэндл bloom.bin (для тестов exists/get-miss без лишних open()).
     // Заполняется в open_ro_*; в writer-режиме обычно None.
     pub(crate) bloom_ro: Option<Arc<BloomSidecar>>,
+
+    // NEW: cache prewarm (QuiverConfig::cache_prewarm) и фоновая задача прогрева
+    pub(crate) cache_prewarm: bool,
+    pub(crate) prewarm_job: Option<std::thread::JoinHandle<u64>>,
+
+    // NEW: кольцо idempotency-токенов батчей (<root>/.idem_tokens.bin), загружается лениво
+    pub(crate) idem_tokens: Option<IdemTokens>,
+
+    // NEW: события истечения TTL — кадр EXPIRY в WAL (QuiverConfig::wal_expiry_events)
+    // и подписчики in-process (Db::subscribe_expiry)
+    pub(crate) wal_expiry_events: bool,
+    pub(crate) expiry_subs: std::sync::Mutex<Vec<std::sync::mpsc::Sender<crate::wal::ExpiryEvent>>>,
+
+    // NEW: выборочный профайлер обращений по префиксам (QuiverConfig::hot_prefix_sample > 0)
+    pub(crate) hot_prefix: Option<super::hotkeys::HotPrefixProfiler>,
+
+    // NEW: пользовательский фильтр записей для compact_bucket/compact_all/vacuum_all
+    pub(crate) compaction_filter: Option<Arc<dyn super::compaction_filter::CompactionFilter>>,
+
+    // NEW: генерация heads.gen на момент последнего Db::refresh (RO; см. db/refresh.rs)
+    pub(crate) refresh_heads_gen: Option<u64>,
+
+    // NEW: отчёт open-time проверки голов каталога (db/torn.rs); None — проверка не выполнялась
+    pub(crate) torn_report: Option<super::torn::TornPageReport>,
+
+    // NEW: сколько живых версий ключа сохраняет компактация (QuiverConfig::compaction_keep_versions)
+    pub(crate) compaction_keep_versions: usize,
+
+    // NEW: активные range tombstones (<root>/range_tombstones.json), см. Db::delete_prefix
+    pub(crate) range_tombstones: super::range_del::RangeTombstoneSet,
+
+    // NEW: политика авто-обновления bloom.bin (QuiverConfig::bloom_auto_refresh, db/bloom_refresh.rs)
+    pub(crate) bloom_auto_refresh: crate::config::BloomAutoRefresh,
+
+    // NEW: trash-режим del() (QuiverConfig::trash_grace_secs, db/trash.rs); 0 — выключен
+    pub(crate) trash_grace_secs: u64,
+
+    // NEW: KID подписи persisted-снапшотов (QuiverConfig::snapshot_sign_kid, snapstore/signature.rs)
+    pub(crate) snapshot_sign_kid: Option<String>,
+
+    // NEW: каноникализация ключей на границе API (QuiverConfig::key_transform, db/key_transform.rs)
+    pub(crate) key_transform: Option<Arc<dyn super::key_transform::KeyTransform>>,
+
+    // NEW: кодирование значений на пути хранения (QuiverConfig::value_transform, db/value_transform.rs)
+    pub(crate) value_transform: Option<Arc<dyn super::value_transform::ValueTransform>>,
+
+    // NEW: закрепления цепочек курсорами Db::scan_page (QuiverConfig::scan_cursor_pin_secs)
+    pub(crate) scan_pins: super::scan_page::ScanPins,
+    pub(crate) scan_cursor_pin_secs: u64,
+
+    // NEW: микро-кэш локаций горячих ключей для get (QuiverConfig::hot_get_cache_entries)
+    pub(crate) hot_get: Option<super::hot_get::HotGetCache>,
+
+    // NEW: конфигурация открытия (после normalize_for_open) — для Db::reopen
+    pub(crate) open_cfg: crate::config::QuiverConfig,
 }
 
 impl Db {
+    /// Маршрутизация ключей — из ENV (P1_BUCKET_KEY_DELIMITER / P1_BUCKET_KEY_PREFIX_LEN),
+    /// по умолчанию — по всему ключу.
     pub fn init(root: &Path, page_size: u32, buckets: u32) -> Result<()> {
+        Self::init_with_routing(root, page_size, buckets, BucketRouting::from_env()?)
+    }
+
+    /// NEW: init с явной маршрутизацией ключей в бакеты (dir/routing.rs); она фиксируется в meta.
+    pub fn init_with_routing(
+        root: &Path,
+        page_size: u32,
+        buckets: u32,
+        routing: BucketRouting,
+    ) -> Result<()> {
+        routing.validate()?;
+        let root = &crate::util::fsx::long_path(root);
         if !root.exists() {
             std::fs::create_dir_all(root)
                 .with_context(|| format!("create root {}", root.display()))?;
@@ -186,11 +384,15 @@ impl Db {
         init_meta_v4(
             root,
             page_size,
-            crate::meta::HASH_KIND_XX64_SEED0,
+            routing.to_hash_kind(),
             crate::meta::CODEC_NONE,
             crate::meta::CKSUM_CRC32C,
         )?;
-        Directory::create(root, buckets)?;
+        let dir = Directory::create(root, buckets)?;
+        // NEW: страничный каталог — required-бит (старые сборки не откроют такую БД)
+        if dir.is_paged() {
+            crate::meta::add_features(root, crate::meta::FEATURE_PAGED_DIR)?;
+        }
         Ok(())
     }
 
@@ -210,6 +412,18 @@ impl Db {
         self.mem_keydir.is_some()
     }
 
+    /// NEW: последние медленные IO-операции (fsync WAL / запись сегментов) файлов этой БД,
+    /// включая ещё не завершившиеся (in_flight). Порог — QuiverConfig::io_stall_ms.
+    pub fn last_io_stalls(&self) -> Vec<crate::util::iostall::IoStallEvent> {
+        crate::util::iostall::recent_io_stalls(Some(&self.root))
+    }
+
+    /// NEW: идентичность БД — db_uuid и провенанс (created_unix_ms, created_by) из meta.
+    /// У RO-хэндла БД старого формата (writer ещё не назначил UUID) db_uuid пуст.
+    pub fn identity(&self) -> crate::meta::DbIdentity {
+        self.pager.meta.identity()
+    }
+
     /// Новый быстрый путь: получить локацию (pid, off).
     #[inline]
     pub(crate) fn mem_keydir_get_loc(&self, bucket: u32, key: &[u8]) -> Option<MemKeyLoc> {
@@ -275,25 +489,49 @@ impl Db {
         }
     }
 
+    // -------- cache prewarm --------
+
+    /// Дождаться завершения фонового прогрева кэша (если он был запущен при open).
+    /// Возвращает число страниц, загруженных prewarm'ом.
+    pub fn wait_prewarm(&mut self) -> Option<u64> {
+        self.prewarm_job.take().and_then(|h| h.join().ok())
+    }
+
+    // -------- idempotency tokens --------
+
+    /// Кольцо idempotency-токенов (лениво загружается при первом обращении).
+    pub(crate) fn idem_tokens_mut(&mut self) -> Result<&mut IdemTokens> {
+        if self.idem_tokens.is_none() {
+            self.idem_tokens = Some(IdemTokens::open(&self.root)?);
+        }
+        Ok(self.idem_tokens.as_mut().unwrap())
+    }
+
+    /// Был ли уже закоммичен батч с этим idempotency-токеном (в пределах окна кольца).
+    /// Возвращает LSN коммита батча.
+    pub fn idempotency_token_lsn(&mut self, token: &[u8]) -> Result<Option<u64>> {
+        let d = idem_digest(token);
+        Ok(self.idem_tokens_mut()?.contains(&d))
+    }
+
     // -------- writer-only directory head updates (public wrappers) --------
 
     /// Writer-only: установить голову бакета напрямую (админ/тестовые задачи).
     /// Атомарно обновляет shard (tmp+rename с CRC). Возвращает Err в RO-режиме.
     pub fn set_dir_head(&mut self, bucket: u32, page_id: u64) -> Result<()> {
-        if self.readonly {
-            return Err(anyhow!("set_dir_head: Db is read-only (writer-only op)"));
-        }
+        self.pager.ensure_writable("set_dir_head")?;
+        self.pager.ensure_not_frozen()?;
+        self.hot_get_invalidate_buckets(&[bucket]);
         self.dir.set_head(bucket, page_id)
     }
 
     /// Writer-only: атомарно обновить несколько голов за один проход.
     /// Возвращает Err в RO-режиме.
     pub fn set_dir_heads_bulk(&mut self, updates: &[(u32, u64)]) -> Result<()> {
-        if self.readonly {
-            return Err(anyhow!(
-                "set_dir_heads_bulk: Db is read-only (writer-only op)"
-            ));
-        }
+        self.pager.ensure_writable("set_dir_heads_bulk")?;
+        self.pager.ensure_not_frozen()?;
+        let buckets: Vec<u32> = updates.iter().map(|(b, _)| *b).collect();
+        self.hot_get_invalidate_buckets(&buckets);
         self.dir.set_heads_bulk(updates)
     }
 }
@@ -305,6 +543,39 @@ impl Drop for Db {
             return;
         }
 
+        // 0'') NEW: маркер заморозки (Db::freeze) после закрытия не нужен.
+        super::freeze::clear_freeze_marker(&self.root);
+
+        // 0''') NEW: после паники в критической секции in-memory состояние не доверенное —
+        // оставляем WAL для реплея на следующем open.
+        if let Some(p) = &self.pager.poisoned {
+            eprintln!(
+                "[WARN] {}: closing poisoned handle (panic in {}); WAL kept for replay on next open",
+                self.root.display(),
+                p.op
+            );
+            return;
+        }
+
+        // 0') Профиль горячих префиксов (best-effort).
+        let _ = self.save_hot_prefixes();
+
+        // 0) Снимок горячих страниц для prewarm на следующем open (best-effort).
+        if self.cache_prewarm {
+            let _ = self.wait_prewarm();
+            let hot = crate::pager::cache::page_cache_hot_pages(self.pager.db_id, usize::MAX);
+            if !hot.is_empty() {
+                let _ = crate::pager::prewarm::write_hot_pages(&self.root, &hot);
+            }
+        }
+
+        // 1') NEW: история ключей — батчи WAL в history.bin до усечения (best-effort).
+        if self.pager.history_index() {
+            if let Err(e) = crate::wal::index_wal_history(&self.root) {
+                eprintln!("[WARN] history index on close failed: {:#}", e);
+            }
+        }
+
         // 1) Усечём WAL до заголовка (идемпотентно). Ошибки игнорируем в Drop.
         let _ = (|| -> anyhow::Result<()> {
             let mut wal = crate::wal::Wal::open_for_append(&self.root)?;
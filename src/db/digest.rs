@@ -0,0 +1,206 @@
+//! db/digest — digest живых ключей бакета для сверки реплик (CDC verify).
+//!
+//! Digest бакета — (live_keys, hash): hash = xxh3 по отсортированным xxh3-хэшам живых записей,
+//! запись — (ключ, раскрытое значение, expires_at_sec). Живость — те же правила, что у скана
+//! (tail-wins, tombstone, range tombstone, TTL), но на заданный `now`: обе стороны считают при
+//! одном и том же now, и запись, истекающая между их проходами, не даёт ложного расхождения.
+//! expires_at входит в хэш — дрейф TTL (потерянный/лишний срок) виден, даже если значения равны.
+//!
+//! Значения сравниваются логически: OVERFLOW раскрывается, так что физическая раскладка
+//! (упаковка страниц, компактация на одной из сторон) на digest не влияет.
+//!
+//! Источник: Db::bucket_digests (любой хэндл) или bucket_digests_at (путь без LOCK — сторона
+//! leader'а рядом с живым writer'ом, см. cdc-serve).
+//!
+//! NEW (anti-entropy): Db::bucket_digest(bucket) -> (lsn, hash) и дерево digest'ов всей БД
+//! (Db::digest_tree): листья — digest'ы бакетов, узел — xxh3 хэшей DIGEST_TREE_FANOUT детей,
+//! корень — digest всей БД. DigestTree::diff спускается только в различающиеся поддеревья и
+//! возвращает бакеты, которые нужно пересинхронизировать (quiverdb digest --compare).
+//! lsn — meta.last_lsn хэндла (у RO рядом с живым writer'ом он может отставать).
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::util::now_secs;
+
+use super::core::Db;
+
+/// Ветвление дерева digest'ов (детей у внутреннего узла).
+pub const DIGEST_TREE_FANOUT: usize = 16;
+
+/// Digest одного бакета.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BucketDigest {
+    pub bucket: u32,
+    pub live_keys: u64,
+    /// 0 — в бакете нет живых ключей.
+    pub hash: u64,
+}
+
+impl Db {
+    /// Digest живых ключей бакетов `buckets` на момент `now` (unix-секунды, для TTL).
+    pub fn bucket_digests(&self, buckets: &[u32], now: u32) -> Result<Vec<BucketDigest>> {
+        let ps = self.pager.meta.page_size as usize;
+        let mut page = vec![0u8; ps];
+        // Снимок голов: все бакеты — от одного состояния каталога
+        let heads = self.dir.heads_snapshot()?;
+        let mut out = Vec::with_capacity(buckets.len());
+        for &b in buckets {
+            let head = *heads.get(b as usize).ok_or_else(|| {
+                anyhow!(
+                    "bucket_digests: bucket {} out of range (buckets={})",
+                    b,
+                    heads.len()
+                )
+            })?;
+            let mut hashes: Vec<u64> = Vec::new();
+            self.for_each_live_in_bucket(b, head, now, &mut page, |k, v, exp| {
+                hashes.push(record_hash(k, v, exp));
+            })?;
+            out.push(BucketDigest {
+                bucket: b,
+                live_keys: hashes.len() as u64,
+                hash: fold_hashes(&mut hashes),
+            });
+        }
+        Ok(out)
+    }
+
+    /// Digest живых ключей бакета на текущий момент: (meta.last_lsn, hash).
+    pub fn bucket_digest(&self, bucket: u32) -> Result<(u64, u64)> {
+        let d = self.bucket_digests(&[bucket], now_secs())?;
+        Ok((self.pager.meta.last_lsn, d[0].hash))
+    }
+
+    /// Дерево digest'ов всех бакетов на текущий момент.
+    pub fn digest_tree(&self) -> Result<DigestTree> {
+        self.digest_tree_at(now_secs())
+    }
+
+    /// Дерево digest'ов на момент `now` (две реплики сравнимы только при одном now).
+    pub fn digest_tree_at(&self, now: u32) -> Result<DigestTree> {
+        let all: Vec<u32> = (0..self.dir.bucket_count).collect();
+        let leaves = self.bucket_digests(&all, now)?;
+        Ok(DigestTree::from_leaves(
+            self.pager.meta.last_lsn,
+            now,
+            leaves,
+        ))
+    }
+}
+
+/// Дерево digest'ов БД (Merkle-подобное, ветвление DIGEST_TREE_FANOUT).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DigestTree {
+    pub lsn: u64,
+    pub now: u32,
+    /// Листья — digest'ы бакетов 0..N по порядку.
+    pub leaves: Vec<BucketDigest>,
+    /// Внутренние уровни снизу вверх: levels[0] — узлы над листьями, последний — [корень].
+    pub levels: Vec<Vec<u64>>,
+}
+
+impl DigestTree {
+    pub fn from_leaves(lsn: u64, now: u32, leaves: Vec<BucketDigest>) -> Self {
+        let mut levels: Vec<Vec<u64>> = Vec::new();
+        let mut below: Vec<u64> = leaves.iter().map(|d| d.hash).collect();
+        loop {
+            let level: Vec<u64> = below.chunks(DIGEST_TREE_FANOUT).map(node_hash).collect();
+            let done = level.len() <= 1;
+            below = level.clone();
+            levels.push(level);
+            if done {
+                break;
+            }
+        }
+        Self {
+            lsn,
+            now,
+            leaves,
+            levels,
+        }
+    }
+
+    /// Digest всей БД.
+    pub fn root(&self) -> u64 {
+        self.levels
+            .last()
+            .and_then(|l| l.first())
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Бакеты, digest'ы которых у self и other различаются (по возрастанию). Сравниваются
+    /// только узлы различающихся поддеревьев. Деревья с разным числом бакетов несравнимы.
+    pub fn diff(&self, other: &DigestTree) -> Result<Vec<u32>> {
+        if self.leaves.len() != other.leaves.len() {
+            return Err(anyhow!(
+                "digest trees are not comparable: {} vs {} buckets",
+                self.leaves.len(),
+                other.leaves.len()
+            ));
+        }
+        // индексы различающихся узлов текущего уровня (сверху вниз)
+        let mut cand: Vec<usize> = vec![0];
+        for lvl in (0..self.levels.len()).rev() {
+            let below = match lvl {
+                0 => self.leaves.len(),
+                _ => self.levels[lvl - 1].len(),
+            };
+            let mut next = Vec::new();
+            for &i in &cand {
+                if self.levels[lvl].get(i) == other.levels[lvl].get(i) {
+                    continue;
+                }
+                let end = ((i + 1) * DIGEST_TREE_FANOUT).min(below);
+                next.extend(i * DIGEST_TREE_FANOUT..end);
+            }
+            cand = next;
+        }
+        Ok(cand
+            .into_iter()
+            .filter(|&b| self.leaves[b] != other.leaves[b])
+            .map(|b| b as u32)
+            .collect())
+    }
+}
+
+/// Digest бакетов БД по пути без LOCK (Db::open_ro_unlocked) — для процесса рядом с живым
+/// writer'ом. Конкурентные коммиты могут исказить результат: вызывающий перепроверяет, что
+/// состояние источника не сдвинулось за время подсчёта.
+pub fn bucket_digests_at(root: &Path, buckets: &[u32], now: u32) -> Result<Vec<BucketDigest>> {
+    Db::open_ro_unlocked(root)?.bucket_digests(buckets, now)
+}
+
+fn record_hash(key: &[u8], value: &[u8], expires_at_sec: u32) -> u64 {
+    let mut buf = Vec::with_capacity(key.len() + value.len() + 12);
+    buf.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    buf.extend_from_slice(key);
+    buf.extend_from_slice(&expires_at_sec.to_le_bytes());
+    buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    buf.extend_from_slice(value);
+    twox_hash::xxh3::hash64(&buf)
+}
+
+/// Хэш внутреннего узла: xxh3 хэшей детей по порядку.
+fn node_hash(children: &[u64]) -> u64 {
+    let mut buf = Vec::with_capacity(children.len() * 8);
+    for h in children {
+        buf.extend_from_slice(&h.to_le_bytes());
+    }
+    twox_hash::xxh3::hash64(&buf)
+}
+
+/// xxh3 по отсортированным хэшам записей (порядок обхода цепочки не важен).
+fn fold_hashes(hashes: &mut [u64]) -> u64 {
+    if hashes.is_empty() {
+        return 0;
+    }
+    hashes.sort_unstable();
+    let mut buf = Vec::with_capacity(hashes.len() * 8);
+    for h in hashes.iter() {
+        buf.extend_from_slice(&h.to_le_bytes());
+    }
+    twox_hash::xxh3::hash64(&buf)
+}
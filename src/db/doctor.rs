@@ -29,15 +29,24 @@
 //! Вывод:
 //! - doctor(json=false) — человекочитаемый отчёт;
 //! - doctor(json=true)  — JSON-объект на одной строке.
+//! - NEW: doctor_report() — тот же отчёт структурой (serde) без печати (CLI --output).
+//! - NEW: типизация сначала по версии формата (page::page_version): страницы P2PG не v3
+//!   считаются legacy_pages (а не kv/overflow по совпавшему коду типа) — их чинит upgrade/export.
+//! - NEW: проверка аллокатора (db/alloc_check.rs) — поле alloc: страницы одновременно в free-листе
+//!   и в цепочке, в двух цепочках; ремонт — Db::alloc_check_fix (CLI doctor --fix-alloc).
 
 use anyhow::{Context, Result};
 use byteorder::{ByteOrder, LittleEndian};
+use serde::Serialize;
 use std::fs::OpenOptions;
 use std::io::{Read, Seek, SeekFrom};
 
-use crate::page::{OFF_TYPE, PAGE_MAGIC, PAGE_TYPE_KV_RH3, PAGE_TYPE_OVERFLOW3, TRAILER_LEN};
+use crate::page::{
+    page_is_legacy, OFF_TYPE, PAGE_MAGIC, PAGE_TYPE_KV_RH3, PAGE_TYPE_OVERFLOW3, TRAILER_LEN,
+};
 use crate::pager::{DATA_SEG_EXT, DATA_SEG_PREFIX, SEGMENT_SIZE};
 
+use super::alloc_check::AllocCheckReport;
 use super::core::Db;
 
 // --- локальные чтения ENV для строгих режимов (для отчёта) ---
@@ -69,9 +78,82 @@ fn tde_strict_env() -> bool {
         .unwrap_or(false)
 }
 
+/// Строгие режимы, действовавшие при скане.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DoctorStrict {
+    pub doctor_strict: bool,
+    pub zero_checksum_strict: bool,
+    pub tde_strict: bool,
+}
+
+/// Отчёт doctor-скана (Db::doctor_report).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DoctorReport {
+    /// Режим верификации трейлера: "crc" | "aead".
+    pub mode: String,
+    pub strict: DoctorStrict,
+    pub pages_total: u64,
+    pub ok_pages: u64,
+    /// Информативен только в CRC-режиме; в AEAD остаётся 0.
+    pub zero_checksum: u64,
+    pub crc_fail: u64,
+    pub io_fail: u64,
+    pub kv_pages: u64,
+    pub overflow_pages: u64,
+    /// Страницы P2PG старого формата (версия != v3).
+    pub legacy_pages: u64,
+    pub other_magic: u64,
+    pub no_magic: u64,
+    /// NEW: двойное использование страниц аллокатором.
+    pub alloc: AllocCheckReport,
+}
+
+impl DoctorReport {
+    pub fn is_aead(&self) -> bool {
+        self.mode == "aead"
+    }
+
+    /// Человекочитаемый отчёт (как doctor(false)).
+    pub fn print_text(&self) {
+        println!(
+            "Doctor report (doctor_strict={}, mode={}, zero_checksum_strict={}, tde_strict={}):",
+            self.strict.doctor_strict,
+            self.mode,
+            self.strict.zero_checksum_strict,
+            self.strict.tde_strict
+        );
+        println!("  pages_total    = {}", self.pages_total);
+        println!("  ok_pages       = {}", self.ok_pages);
+        if self.is_aead() {
+            println!("  zero_checksum  = (n/a for AEAD)");
+        } else {
+            println!("  zero_checksum  = {}", self.zero_checksum);
+        }
+        println!("  crc_fail       = {}", self.crc_fail);
+        println!("  io_fail        = {}", self.io_fail);
+        println!("  kv_pages       = {}", self.kv_pages);
+        println!("  overflow_pages = {}", self.overflow_pages);
+        println!("  legacy_pages   = {}", self.legacy_pages);
+        println!("  other_magic    = {}", self.other_magic);
+        println!("  no_magic       = {}", self.no_magic);
+        self.alloc.print_text();
+    }
+}
+
 impl Db {
     /// Doctor-скан: проверка CRC/IO, типизация страниц и отчёт (json=false|true).
     pub fn doctor(&self, json: bool) -> Result<()> {
+        let rep = self.doctor_report()?;
+        if json {
+            println!("{}", serde_json::to_string(&rep)?);
+        } else {
+            rep.print_text();
+        }
+        Ok(())
+    }
+
+    /// Doctor-скан без печати: отчёт структурой.
+    pub fn doctor_report(&self) -> Result<DoctorReport> {
         let ps = self.pager.meta.page_size as usize;
         let pages_total = self.pager.meta.next_page_id;
 
@@ -90,6 +172,7 @@ impl Db {
 
         let mut kv_pages = 0u64;
         let mut ovf_pages = 0u64;
+        let mut legacy_pages = 0u64;
         let mut other_magic = 0u64;
         let mut no_magic = 0u64;
 
@@ -105,6 +188,7 @@ impl Db {
                         &buf,
                         &mut kv_pages,
                         &mut ovf_pages,
+                        &mut legacy_pages,
                         &mut other_magic,
                         &mut no_magic,
                     );
@@ -147,6 +231,7 @@ impl Db {
                             &raw,
                             &mut kv_pages,
                             &mut ovf_pages,
+                            &mut legacy_pages,
                             &mut other_magic,
                             &mut no_magic,
                         );
@@ -157,56 +242,25 @@ impl Db {
             }
         }
 
-        if json {
-            println!(
-                "{{\
-                    \"mode\":\"{}\",\
-                    \"strict\":{{\"doctor_strict\":{},\"zero_checksum_strict\":{},\"tde_strict\":{}}},\
-                    \"pages_total\":{},\
-                    \"ok_pages\":{},\
-                    \"zero_checksum\":{},\
-                    \"crc_fail\":{},\
-                    \"io_fail\":{},\
-                    \"kv_pages\":{},\
-                    \"overflow_pages\":{},\
-                    \"other_magic\":{},\
-                    \"no_magic\":{}\
-                }}",
-                mode_str,
+        Ok(DoctorReport {
+            mode: mode_str.to_string(),
+            strict: DoctorStrict {
                 doctor_strict,
-                zero_cksum_strict,
+                zero_checksum_strict: zero_cksum_strict,
                 tde_strict,
-                pages_total,
-                ok_pages,
-                // zero_checksum информативен только в CRC-режиме; в AEAD остаётся 0
-                zero_checksum,
-                crc_fail,
-                io_fail,
-                kv_pages,
-                ovf_pages,
-                other_magic,
-                no_magic
-            );
-        } else {
-            println!(
-                "Doctor report (doctor_strict={}, mode={}, zero_checksum_strict={}, tde_strict={}):",
-                doctor_strict, mode_str, zero_cksum_strict, tde_strict
-            );
-            println!("  pages_total    = {}", pages_total);
-            println!("  ok_pages       = {}", ok_pages);
-            if mode_aead {
-                println!("  zero_checksum  = (n/a for AEAD)");
-            } else {
-                println!("  zero_checksum  = {}", zero_checksum);
-            }
-            println!("  crc_fail       = {}", crc_fail);
-            println!("  io_fail        = {}", io_fail);
-            println!("  kv_pages       = {}", kv_pages);
-            println!("  overflow_pages = {}", ovf_pages);
-            println!("  other_magic    = {}", other_magic);
-            println!("  no_magic       = {}", no_magic);
-        }
-        Ok(())
+            },
+            pages_total,
+            ok_pages,
+            zero_checksum,
+            crc_fail,
+            io_fail,
+            kv_pages,
+            overflow_pages: ovf_pages,
+            legacy_pages,
+            other_magic,
+            no_magic,
+            alloc: self.alloc_check()?,
+        })
     }
 }
 
@@ -216,6 +270,7 @@ fn classify_bytes(
     page: &[u8],
     kv_pages: &mut u64,
     ovf_pages: &mut u64,
+    legacy_pages: &mut u64,
     other_magic: &mut u64,
     no_magic: &mut u64,
 ) {
@@ -227,6 +282,11 @@ fn classify_bytes(
         *no_magic += 1;
         return;
     }
+    // version-dispatch: коды типов v3 у legacy-страниц ничего не значат
+    if page_is_legacy(page) {
+        *legacy_pages += 1;
+        return;
+    }
     let page_type = LittleEndian::read_u16(&page[OFF_TYPE..OFF_TYPE + 2]);
     match page_type {
         t if t == PAGE_TYPE_KV_RH3 => *kv_pages += 1,
@@ -0,0 +1,424 @@
+//! db/du — разбивка занятого места по категориям (quiverdb du).
+//!
+//! Db::space_usage(top) сводит вместе:
+//! - скан цепочек бакетов: записи KV делятся на живые (новейшая версия ключа, не tombstone,
+//!   не истёк TTL, не под range tombstone) и мёртвые (старые версии, tombstone'ы, истёкшие —
+//!   ждут компактации); OVERFLOW-цепочки — по тому, на чью запись они ссылаются;
+//! - полный проход страниц [0 .. next_page_id): OVERFLOW вне цепочек (сироты, см. sweep) и
+//!   прочие недостижимые страницы;
+//! - free-лист (<root>/free);
+//! - файлы: сегменты (длина и реально занятые блоки), WAL, bloom.bin, SnapStore (объекты и
+//!   манифесты, P1_SNAPSTORE_DIR учитывается), остальное в корне.
+//!
+//! Байты записей — длины ключа и значения (для OVERFLOW — плейсхолдер); страницы — page_size.
+//! Отчёт read-only: работает на Db::open_ro.
+
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::dir::NO_PAGE;
+use crate::free::FreeList;
+use crate::page::kv::kv_for_each_record;
+use crate::page::ovf::chain::OVF_MAX_CHAIN_PAGES_GUARD;
+use crate::page::{kv_header_read_v3, ovf_header_read_v3, OFF_TYPE, PAGE_MAGIC, PAGE_TYPE_KV_RH3};
+use crate::pager::{DATA_SEG_EXT, DATA_SEG_PREFIX};
+use crate::snapstore::resolve_snapstore_dir;
+use crate::util::{decode_ovf_placeholder_v3, now_secs};
+
+use super::core::Db;
+use super::maintenance::read_ovf_next_pid_silent;
+
+/// Место, занятое одним бакетом (Db::space_usage, top consumers).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BucketUsage {
+    pub bucket: u32,
+    pub kv_pages: u64,
+    pub overflow_pages: u64,
+    pub live_keys: u64,
+    pub live_bytes: u64,
+    pub dead_records: u64,
+    pub dead_bytes: u64,
+    /// (kv_pages + overflow_pages) * page_size.
+    pub total_bytes: u64,
+}
+
+/// Файлы SnapStore.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SnapstoreUsage {
+    pub dir: String,
+    pub snapshots: u64,
+    pub objects: u64,
+    pub objects_bytes: u64,
+    pub manifests_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Разбивка занятого места (quiverdb du).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SpaceUsage {
+    pub page_size: u32,
+    pub pages_total: u64,
+
+    // ----- страницы (по скану) -----
+    pub kv_pages: u64,
+    /// Живые записи KV: ключ + значение (плейсхолдер для OVERFLOW).
+    pub live_keys: u64,
+    pub live_bytes: u64,
+    /// Старые версии, tombstone'ы, истёкшие TTL и записи под range tombstone.
+    pub dead_records: u64,
+    pub dead_bytes: u64,
+    /// Незанятая часть KV-страниц (заголовки, слоты, свободное место).
+    pub kv_slack_bytes: u64,
+    /// OVERFLOW-цепочки живых записей.
+    pub overflow_live_pages: u64,
+    /// OVERFLOW-цепочки мёртвых записей (уйдут при компактации + sweep).
+    pub overflow_dead_pages: u64,
+    /// OVERFLOW вне всех цепочек (освободит sweep).
+    pub overflow_orphan_pages: u64,
+    /// NEW: страницы области метаданных приложения (Db::meta_put).
+    pub user_meta_pages: u64,
+    pub free_pages: u64,
+    /// Прочие страницы: не в цепочках, не OVERFLOW, не в free-листе (например, старые KV).
+    pub unreachable_pages: u64,
+
+    // ----- файлы -----
+    pub segments: u64,
+    pub segments_bytes: u64,
+    /// Реально занятые блоки (Unix; иначе = segments_bytes). Меньше — разреженные файлы.
+    pub segments_allocated_bytes: u64,
+    pub wal_bytes: u64,
+    pub bloom_bytes: u64,
+    pub snapstore: SnapstoreUsage,
+    /// Остальные файлы в корне (meta, dir, free, sidecar'ы, курсоры).
+    pub other_bytes: u64,
+    pub total_bytes: u64,
+
+    /// Бакеты с наибольшим занятым местом (по убыванию total_bytes).
+    pub top_buckets: Vec<BucketUsage>,
+}
+
+impl SpaceUsage {
+    /// Человекочитаемый отчёт.
+    pub fn print_text(&self) {
+        let ps = self.page_size as u64;
+        println!("Space usage (page_size={}):", self.page_size);
+        println!("  total                = {}", human(self.total_bytes));
+        println!(
+            "  segments             = {} ({} files, {} allocated)",
+            human(self.segments_bytes),
+            self.segments,
+            human(self.segments_allocated_bytes)
+        );
+        println!(
+            "    live kv            = {} ({} keys, {} pages)",
+            human(self.live_bytes),
+            self.live_keys,
+            self.kv_pages
+        );
+        println!(
+            "    dead kv            = {} ({} records, awaiting compaction)",
+            human(self.dead_bytes),
+            self.dead_records
+        );
+        println!("    kv page slack      = {}", human(self.kv_slack_bytes));
+        println!(
+            "    overflow live      = {} ({} pages)",
+            human(self.overflow_live_pages * ps),
+            self.overflow_live_pages
+        );
+        println!(
+            "    overflow dead      = {} ({} pages)",
+            human(self.overflow_dead_pages * ps),
+            self.overflow_dead_pages
+        );
+        println!(
+            "    overflow orphan    = {} ({} pages)",
+            human(self.overflow_orphan_pages * ps),
+            self.overflow_orphan_pages
+        );
+        println!(
+            "    user metadata      = {} ({} pages)",
+            human(self.user_meta_pages * ps),
+            self.user_meta_pages
+        );
+        println!(
+            "    free pages         = {} ({} pages)",
+            human(self.free_pages * ps),
+            self.free_pages
+        );
+        println!(
+            "    unreachable        = {} ({} pages)",
+            human(self.unreachable_pages * ps),
+            self.unreachable_pages
+        );
+        println!("  wal                  = {}", human(self.wal_bytes));
+        println!("  bloom                = {}", human(self.bloom_bytes));
+        println!(
+            "  snapstore            = {} ({} snapshots, {} objects)",
+            human(self.snapstore.total_bytes),
+            self.snapstore.snapshots,
+            self.snapstore.objects
+        );
+        println!("  other                = {}", human(self.other_bytes));
+        if !self.top_buckets.is_empty() {
+            println!("  top buckets:");
+            for b in &self.top_buckets {
+                println!(
+                    "    bucket {:>6}  {:>10}  kv_pages={} ovf_pages={} live={} dead={}",
+                    b.bucket,
+                    human(b.total_bytes),
+                    b.kv_pages,
+                    b.overflow_pages,
+                    human(b.live_bytes),
+                    human(b.dead_bytes)
+                );
+            }
+        }
+    }
+}
+
+impl Db {
+    /// Разбивка занятого места; top — сколько самых больших бакетов вернуть (0 — ни одного).
+    pub fn space_usage(&self, top: usize) -> Result<SpaceUsage> {
+        let ps = self.pager.meta.page_size as usize;
+        let now = now_secs();
+        let mut u = SpaceUsage {
+            page_size: self.pager.meta.page_size,
+            pages_total: self.pager.meta.next_page_id,
+            ..Default::default()
+        };
+        let mut page = vec![0u8; ps];
+        // Страницы, учтённые сканом цепочек (KV + OVERFLOW)
+        let mut seen_pages: HashSet<u64> = HashSet::new();
+        let mut buckets: Vec<BucketUsage> = Vec::new();
+
+        for bucket in 0..self.dir.bucket_count {
+            let mut bu = BucketUsage {
+                bucket,
+                ..Default::default()
+            };
+            // Первое (новейшее) вхождение ключа решает его судьбу; остальные — мёртвые
+            let mut keys: HashSet<Vec<u8>> = HashSet::new();
+            let mut live_ovf: Vec<u64> = Vec::new();
+            let mut dead_ovf: Vec<u64> = Vec::new();
+            let mut pid = self.dir.head(bucket)?;
+            while pid != NO_PAGE && seen_pages.insert(pid) {
+                if self.pager.read_page(pid, &mut page).is_err()
+                    || &page[0..4] != PAGE_MAGIC
+                    || LittleEndian::read_u16(&page[OFF_TYPE..OFF_TYPE + 2]) != PAGE_TYPE_KV_RH3
+                {
+                    break;
+                }
+                let h = kv_header_read_v3(&page)?;
+                bu.kv_pages += 1;
+                let mut rec_bytes = 0u64;
+                kv_for_each_record(&page, |k, v, expires_at_sec, vflags| {
+                    let bytes = (k.len() + v.len()) as u64;
+                    rec_bytes += bytes;
+                    let newest = keys.insert(k.to_vec());
+                    let tomb = (vflags & 0x1) == 1;
+                    let expired = expires_at_sec != 0 && now >= expires_at_sec;
+                    let under_cut = self.range_cut(k).is_some_and(|c| h.lsn <= c);
+                    let live = newest && !tomb && !expired && !under_cut;
+                    if live {
+                        bu.live_keys += 1;
+                        bu.live_bytes += bytes;
+                    } else {
+                        bu.dead_records += 1;
+                        bu.dead_bytes += bytes;
+                    }
+                    if !tomb {
+                        if let Some((_, head)) = decode_ovf_placeholder_v3(v) {
+                            if live {
+                                live_ovf.push(head);
+                            } else {
+                                dead_ovf.push(head);
+                            }
+                        }
+                    }
+                });
+                u.kv_slack_bytes += (ps as u64).saturating_sub(rec_bytes);
+                pid = h.next_page_id;
+            }
+            // Живые цепочки — первыми: страница, общая с мёртвой записью, считается живой
+            for head in live_ovf {
+                let n = self.mark_ovf_chain(head, ps, &mut seen_pages);
+                bu.overflow_pages += n;
+                u.overflow_live_pages += n;
+            }
+            for head in dead_ovf {
+                let n = self.mark_ovf_chain(head, ps, &mut seen_pages);
+                bu.overflow_pages += n;
+                u.overflow_dead_pages += n;
+            }
+            bu.total_bytes = (bu.kv_pages + bu.overflow_pages) * ps as u64;
+            u.kv_pages += bu.kv_pages;
+            u.live_keys += bu.live_keys;
+            u.live_bytes += bu.live_bytes;
+            u.dead_records += bu.dead_records;
+            u.dead_bytes += bu.dead_bytes;
+            if bu.total_bytes > 0 {
+                buckets.push(bu);
+            }
+        }
+
+        // NEW: область метаданных приложения — вне каталога (db/user_meta.rs)
+        for pid in self.user_meta_pages().unwrap_or_default() {
+            if seen_pages.insert(pid) {
+                u.user_meta_pages += 1;
+            }
+        }
+
+        // Free-лист и страницы вне цепочек
+        let free: HashSet<u64> = match FreeList::open(&self.root) {
+            Ok(fl) => fl.list().unwrap_or_default().into_iter().collect(),
+            Err(_) => HashSet::new(),
+        };
+        u.free_pages = free.len() as u64;
+        for pid in 0..u.pages_total {
+            if seen_pages.contains(&pid) || free.contains(&pid) {
+                continue;
+            }
+            if self.pager.read_page(pid, &mut page).is_ok() && ovf_header_read_v3(&page).is_ok() {
+                u.overflow_orphan_pages += 1;
+            } else {
+                u.unreachable_pages += 1;
+            }
+        }
+
+        buckets.sort_by(|a, b| {
+            b.total_bytes
+                .cmp(&a.total_bytes)
+                .then(a.bucket.cmp(&b.bucket))
+        });
+        buckets.truncate(top);
+        u.top_buckets = buckets;
+
+        self.fill_file_usage(&mut u)?;
+        Ok(u)
+    }
+
+    /// Пометить страницы OVERFLOW-цепочки; возвращает число впервые учтённых страниц.
+    fn mark_ovf_chain(&self, head: u64, ps: usize, seen: &mut HashSet<u64>) -> u64 {
+        let mut n = 0u64;
+        let mut cur = head;
+        let mut guard = 0usize;
+        while cur != NO_PAGE && cur < self.pager.meta.next_page_id {
+            guard += 1;
+            if guard > OVF_MAX_CHAIN_PAGES_GUARD || !seen.insert(cur) {
+                break;
+            }
+            n += 1;
+            cur = read_ovf_next_pid_silent(self, cur, ps).unwrap_or(NO_PAGE);
+        }
+        n
+    }
+
+    fn fill_file_usage(&self, u: &mut SpaceUsage) -> Result<()> {
+        let snap_dir = resolve_snapstore_dir(&self.root);
+        let seg_prefix = DATA_SEG_PREFIX;
+        let seg_suffix = format!(".{}", DATA_SEG_EXT);
+        for e in std::fs::read_dir(&self.root)? {
+            let e = e?;
+            let md = match e.metadata() {
+                Ok(md) => md,
+                Err(_) => continue,
+            };
+            if md.is_dir() {
+                continue;
+            }
+            let name = e.file_name().to_string_lossy().into_owned();
+            let len = md.len();
+            if name.starts_with(seg_prefix) && name.ends_with(&seg_suffix) {
+                u.segments += 1;
+                u.segments_bytes += len;
+                u.segments_allocated_bytes += allocated_bytes(&md);
+            } else if name.starts_with("wal-") {
+                u.wal_bytes += len;
+            } else if name == "bloom.bin" {
+                u.bloom_bytes += len;
+            } else {
+                u.other_bytes += len;
+            }
+        }
+        u.snapstore = snapstore_usage(&snap_dir);
+        // SnapStore вне корня (P1_SNAPSTORE_DIR) в total тоже входит: это место этой БД
+        u.total_bytes = u.segments_bytes
+            + u.wal_bytes
+            + u.bloom_bytes
+            + u.other_bytes
+            + u.snapstore.total_bytes;
+        Ok(())
+    }
+}
+
+fn snapstore_usage(dir: &Path) -> SnapstoreUsage {
+    let mut s = SnapstoreUsage {
+        dir: dir.display().to_string(),
+        ..Default::default()
+    };
+    if !dir.exists() {
+        return s;
+    }
+    let objects = dir_usage(&dir.join("objects"));
+    s.objects = objects.0;
+    s.objects_bytes = objects.1;
+    let manifests = dir_usage(&dir.join("manifests"));
+    s.snapshots = manifests.0;
+    s.manifests_bytes = manifests.1;
+    s.total_bytes = dir_usage(dir).1;
+    s
+}
+
+/// (число файлов, байт) в каталоге рекурсивно.
+fn dir_usage(dir: &Path) -> (u64, u64) {
+    let mut files = 0u64;
+    let mut bytes = 0u64;
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(d) = stack.pop() {
+        let Ok(rd) = std::fs::read_dir(&d) else {
+            continue;
+        };
+        for e in rd.flatten() {
+            let Ok(md) = e.metadata() else {
+                continue;
+            };
+            if md.is_dir() {
+                stack.push(e.path());
+            } else {
+                files += 1;
+                bytes += md.len();
+            }
+        }
+    }
+    (files, bytes)
+}
+
+#[cfg(unix)]
+fn allocated_bytes(md: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    md.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated_bytes(md: &std::fs::Metadata) -> u64 {
+    md.len()
+}
+
+fn human(b: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut v = b as f64;
+    let mut i = 0;
+    while v >= 1024.0 && i + 1 < UNITS.len() {
+        v /= 1024.0;
+        i += 1;
+    }
+    if i == 0 {
+        format!("{} B", b)
+    } else {
+        format!("{:.1} {}", v, UNITS[i])
+    }
+}
@@ -0,0 +1,147 @@
+//! db/estimate — приблизительное число ключей под префиксом (Db::estimate_count_prefix).
+//!
+//! Для пагинации в UI и планирования ёмкости без полного скана:
+//! - in-memory keydir (RO): счёт по keydir без чтения страниц (мёртвые под range tombstone
+//!   проверяются точечно). Ключи, чей TTL истёк после построения keydir, ещё учитываются.
+//! - иначе — выборка бакетов: равномерно разнесённые бакеты обходятся keys-only сканом
+//!   (значения не читаются), результат экстраполируется на все бакеты. Ключи распределены
+//!   по бакетам хэшем, поэтому префиксные ключи ложатся равномерно.
+//!
+//! Границы — ~95% доверительный интервал (z = 1.96) по дисперсии счётов в выбранных бакетах
+//! с поправкой на конечную совокупность; нижняя граница не меньше реально найденных ключей.
+//! Если выбраны все бакеты, результат точный (low == high == estimate).
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::dir::NO_PAGE;
+use crate::util::now_secs;
+
+use super::core::Db;
+
+/// Бакетов в выборке по умолчанию (Db::estimate_count_prefix).
+pub const DEFAULT_ESTIMATE_SAMPLE_BUCKETS: u32 = 64;
+
+/// Оценка числа ключей (Db::estimate_count_prefix).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CountEstimate {
+    pub estimate: u64,
+    /// Нижняя/верхняя граница ~95% интервала.
+    pub low: u64,
+    pub high: u64,
+    /// true — посчитано по всем бакетам (без экстраполяции).
+    pub exact: bool,
+    /// "keydir" | "sample"
+    pub method: &'static str,
+    pub buckets_sampled: u32,
+    pub buckets_total: u32,
+    /// Живых ключей, реально найденных в выбранных бакетах.
+    pub keys_seen: u64,
+}
+
+impl Db {
+    /// Приблизительное число живых ключей с префиксом (пустой префикс — вся БД).
+    pub fn estimate_count_prefix(&self, prefix: &[u8]) -> Result<CountEstimate> {
+        self.estimate_count_prefix_sampled(prefix, DEFAULT_ESTIMATE_SAMPLE_BUCKETS)
+    }
+
+    /// То же с явным размером выборки (бакетов). sample_buckets >= bucket_count — точный счёт.
+    pub fn estimate_count_prefix_sampled(
+        &self,
+        prefix: &[u8],
+        sample_buckets: u32,
+    ) -> Result<CountEstimate> {
+        let prefix = self.canonical_prefix(prefix)?;
+        let prefix = prefix.as_ref();
+        let now = now_secs();
+        if self.has_mem_keydir() {
+            let mut n = 0u64;
+            let mut err = None;
+            self.mem_keydir_for_each_prefix(prefix, |_b, k, pid| {
+                if pid == NO_PAGE || err.is_some() {
+                    return;
+                }
+                let live = match self.range_cut(k) {
+                    Some(cut) => self.exists_above_range_cut(k, cut, now),
+                    None => Ok(true),
+                };
+                match live {
+                    Ok(true) => n += 1,
+                    Ok(false) => {}
+                    Err(e) => err = Some(e),
+                }
+            });
+            if let Some(e) = err {
+                return Err(e);
+            }
+            let total = self.dir.bucket_count;
+            return Ok(CountEstimate {
+                estimate: n,
+                low: n,
+                high: n,
+                exact: false,
+                method: "keydir",
+                buckets_sampled: total,
+                buckets_total: total,
+                keys_seen: n,
+            });
+        }
+
+        // Головы закрепляются на старте, как у chain-скана
+        let heads = self.dir.heads_snapshot()?;
+        let total = heads.len() as u32;
+        let s = sample_buckets.clamp(1, total.max(1)).min(total);
+        let mut page = vec![0u8; self.pager.meta.page_size as usize];
+        let mut counts = Vec::with_capacity(s as usize);
+        for i in 0..s {
+            // Равномерно разнесённые бакеты (детерминированно)
+            let b = ((i as u64 * total as u64) / s as u64) as u32;
+            counts.push(self.count_bucket_keys(b, heads[b as usize], prefix, now, &mut page)?);
+        }
+        Ok(extrapolate(&counts, total))
+    }
+}
+
+/// Экстраполяция счётов выбранных бакетов на все `total` бакетов.
+fn extrapolate(counts: &[u64], total: u32) -> CountEstimate {
+    let s = counts.len() as u32;
+    let seen: u64 = counts.iter().sum();
+    if s == 0 || s >= total {
+        return CountEstimate {
+            estimate: seen,
+            low: seen,
+            high: seen,
+            exact: true,
+            method: "sample",
+            buckets_sampled: s,
+            buckets_total: total,
+            keys_seen: seen,
+        };
+    }
+    let n = s as f64;
+    let big_n = total as f64;
+    let mean = seen as f64 / n;
+    // Выборочная дисперсия; для одного бакета — пуассоновское приближение (var = mean)
+    let var = if s > 1 {
+        counts
+            .iter()
+            .map(|&c| (c as f64 - mean).powi(2))
+            .sum::<f64>()
+            / (n - 1.0)
+    } else {
+        mean
+    };
+    let est = big_n * mean;
+    let se = big_n * (var / n * (1.0 - n / big_n)).sqrt();
+    let margin = 1.96 * se;
+    CountEstimate {
+        estimate: est.round() as u64,
+        low: ((est - margin).floor().max(0.0) as u64).max(seen),
+        high: (est + margin).ceil() as u64,
+        exact: false,
+        method: "sample",
+        buckets_sampled: s,
+        buckets_total: total,
+        keys_seen: seen,
+    }
+}
@@ -26,12 +26,13 @@ use byteorder::{ByteOrder, LittleEndian};
 use crate::bloom::BloomSidecar;
 use crate::dir::NO_PAGE;
 use crate::metrics::{
-    record_bloom_negative, record_bloom_positive, record_bloom_skipped_stale, record_keydir_hit,
-    record_keydir_miss, record_ttl_skipped,
+    record_bloom_negative, record_bloom_positive, record_keydir_hit, record_keydir_miss,
+    record_ttl_skipped,
 };
 use crate::page::common::KV_SLOT_SIZE;
 use crate::page::kv::{kv_for_each_record, kv_read_record_at_checked};
 use crate::page::{kv_header_read_v3, PAGE_MAGIC, PAGE_TYPE_KV_RH3, TRAILER_LEN};
+use crate::pager::io::ReadaheadWindow;
 use crate::util::now_secs;
 
 use super::core::{Db, MemKeyLoc};
@@ -39,11 +40,19 @@ use super::core::{Db, MemKeyLoc};
 impl Db {
     /// Быстрый presence‑check с keydir/bloom fast‑path.
     pub fn exists(&self, key: &[u8]) -> Result<bool> {
+        let key = self.canonical_key(key);
+        let key = key.as_ref();
+        self.hot_read(key);
         let bucket = self.dir.bucket_of_key(key, self.pager.meta.hash_kind);
         let ps = self.pager.meta.page_size as usize;
         let mut page_buf = vec![0u8; ps];
         let now = now_secs();
 
+        // NEW: ключ под range tombstone (Db::delete_prefix)
+        if let Some(cut) = self.range_cut(key) {
+            return self.exists_above_range_cut(key, cut, now);
+        }
+
         // ---------- Fast path: in‑memory keydir с оффсетом ----------
         if let Some(loc) = self.mem_keydir_get_loc(bucket, key) {
             record_keydir_hit();
@@ -78,10 +87,10 @@ impl Db {
                     return Ok(false);
                 }
             } else {
-                record_bloom_skipped_stale();
+                self.note_bloom_skipped_stale();
             }
         } else {
-            record_bloom_skipped_stale();
+            self.note_bloom_skipped_stale();
         }
 
         // ---------- Обычный обход цепочки ----------
@@ -161,9 +170,10 @@ impl Db {
     ) -> Result<bool> {
         let ps = self.pager.meta.page_size as usize;
         debug_assert_eq!(page_buf.len(), ps);
+        let mut ra = ReadaheadWindow::new();
 
         while pid != NO_PAGE {
-            self.pager.read_page(pid, page_buf)?;
+            self.pager.read_page_ra(pid, page_buf, &mut ra)?;
             if &page_buf[0..4] != PAGE_MAGIC {
                 break;
             }
@@ -0,0 +1,151 @@
+//! db/expiry — события истечения TTL (инвалидация кэшей по TTL).
+//!
+//! Отдельного TTL-reaper'а нет: истёкшие записи физически вычищаются компактацией
+//! (compact_bucket/compact_all/vacuum). В этот момент Db публикует ExpiryEvent
+//! (bucket, key, expired_at, purge_lsn) всем подписчикам Db::subscribe_expiry, а при
+//! QuiverConfig::wal_expiry_events ещё и пишет кадр EXPIRY в WAL-батч компактации —
+//! follower (cdc-apply) публикует те же события своим подписчикам.
+//!
+//! Семантика:
+//! - событие выпускается для ключа, чья самая новая версия истекла и который после
+//!   компактации отсутствует (более старая живая версия под истёкшей — не событие);
+//! - доставка in-process, best-effort: отключившиеся Receiver'ы удаляются при следующей публикации.
+//!
+//! NEW: возобновление после простоя — Db::subscribe_expiry_since(since_lsn): сначала события
+//! с purge_lsn > since_lsn, декодированные из кадров EXPIRY текущего WAL (только закоммиченные
+//! батчи), затем живая доставка. Если WAL уже не покрывает диапазон (усечён при open/ротации,
+//! либо wal_expiry_events выключен) — ошибка: подписчику нужна полная ресинхронизация.
+
+use anyhow::{anyhow, Context, Result};
+use std::fs::OpenOptions;
+use std::io::Read;
+use std::sync::mpsc::{channel, Receiver};
+
+use crate::metrics::record_expiry_events_emitted;
+use crate::wal::reader::WalStreamReader;
+use crate::wal::{
+    parse_expiry_payload, wal_path, ExpiryEvent, WAL_HDR_SIZE, WAL_MAGIC, WAL_REC_BEGIN,
+    WAL_REC_COMMIT, WAL_REC_EXPIRY,
+};
+
+use super::core::Db;
+
+impl Db {
+    /// Подписаться на события истечения TTL этого хэндла.
+    pub fn subscribe_expiry(&self) -> Receiver<ExpiryEvent> {
+        let (tx, rx) = channel();
+        self.expiry_subs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(tx);
+        rx
+    }
+
+    /// Подписаться с возобновлением: события с purge_lsn > since_lsn из WAL, затем живые.
+    /// Ошибка, если WAL не покрывает (since_lsn, last_lsn].
+    pub fn subscribe_expiry_since(&self, since_lsn: u64) -> Result<Receiver<ExpiryEvent>> {
+        let last_lsn = self.pager.meta.last_lsn;
+        let missed = if since_lsn >= last_lsn {
+            Vec::new()
+        } else {
+            if !self.wal_expiry_events {
+                return Err(anyhow!(
+                    "cannot resume expiry events from LSN {}: EXPIRY frames are not logged \
+                     (enable wal_expiry_events); current LSN is {}",
+                    since_lsn,
+                    last_lsn
+                ));
+            }
+            let (oldest, events) = self.read_wal_expiry_events(since_lsn)?;
+            // WAL начинается с батча oldest: покрыто всё после oldest-1
+            let covered_from = oldest.map(|l| l.saturating_sub(1)).unwrap_or(last_lsn);
+            if since_lsn < covered_from {
+                return Err(anyhow!(
+                    "cannot resume expiry events from LSN {}: WAL only covers LSN > {} \
+                     (truncated on open or rotation); resync required",
+                    since_lsn,
+                    covered_from
+                ));
+            }
+            events
+        };
+
+        let (tx, rx) = channel();
+        for ev in missed {
+            // Receiver ещё у нас — send не может не удаться
+            let _ = tx.send(ev);
+        }
+        self.expiry_subs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(tx);
+        Ok(rx)
+    }
+
+    /// Кадры EXPIRY закоммиченных батчей текущего WAL с lsn > since_lsn.
+    /// Возвращает (LSN первого BEGIN в файле, события).
+    fn read_wal_expiry_events(&self, since_lsn: u64) -> Result<(Option<u64>, Vec<ExpiryEvent>)> {
+        let p = wal_path(&self.root);
+        let mut f = match OpenOptions::new().read(true).open(&p) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((None, Vec::new())),
+            Err(e) => return Err(e).with_context(|| format!("open WAL {}", p.display())),
+        };
+        let len = f.metadata()?.len();
+        if len < WAL_HDR_SIZE as u64 {
+            return Ok((None, Vec::new()));
+        }
+        let mut hdr = [0u8; WAL_HDR_SIZE];
+        f.read_exact(&mut hdr)
+            .with_context(|| format!("read WAL header {}", p.display()))?;
+        if &hdr[..8] != WAL_MAGIC {
+            return Err(anyhow!("bad WAL magic in {}", p.display()));
+        }
+
+        let mut rdr = WalStreamReader::new();
+        let mut pos = WAL_HDR_SIZE as u64;
+        let mut oldest: Option<u64> = None;
+        let mut out = Vec::new();
+        // EXPIRY текущего батча ждёт COMMIT (недописанный хвост не доставляется)
+        let mut pending: Vec<ExpiryEvent> = Vec::new();
+        while let Some((rec, next)) = rdr.read_next(&mut f, pos, len)? {
+            pos = next;
+            match rec.rec_type {
+                WAL_REC_BEGIN => {
+                    oldest.get_or_insert(rec.lsn);
+                    pending.clear();
+                }
+                WAL_REC_EXPIRY if rec.lsn > since_lsn => {
+                    pending.extend(parse_expiry_payload(&rec.payload, rec.lsn));
+                }
+                WAL_REC_COMMIT => out.append(&mut pending),
+                _ => {}
+            }
+        }
+        Ok((oldest, out))
+    }
+
+    /// Есть ли потребители событий (подписчики или кадр EXPIRY в WAL).
+    #[inline]
+    pub(crate) fn expiry_events_wanted(&self) -> bool {
+        self.wal_expiry_events || self.has_expiry_subscribers()
+    }
+
+    fn has_expiry_subscribers(&self) -> bool {
+        !self
+            .expiry_subs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_empty()
+    }
+
+    /// Разослать события подписчикам (используется компактацией и apply-путём follower'а).
+    pub fn publish_expiry_events(&self, events: &[ExpiryEvent]) {
+        if events.is_empty() {
+            return;
+        }
+        let mut subs = self.expiry_subs.lock().unwrap_or_else(|e| e.into_inner());
+        subs.retain(|tx| events.iter().all(|ev| tx.send(ev.clone()).is_ok()));
+        record_expiry_events_emitted(events.len() as u64);
+    }
+}
@@ -0,0 +1,171 @@
+//! freeze — заморозка writer'а для внешних снапшотов тома (LVM/EBS/ZFS) без остановки процесса.
+//!
+//! Db::freeze():
+//! - fsync WAL (включая отложенный group commit) и всех сегментов данных (даже при data_fsync=false);
+//! - запись актуальной meta (last_lsn / next_page_id — в рабочем режиме meta на диске отстаёт);
+//! - маркер консистентной точки `<root>/.freeze.json` (FreezeInfo, tmp+rename+fsync каталога).
+//!
+//! Пока хэндл заморожен, любые коммиты (put/del/batch/компактация/bulk load/set_dir_head) сразу
+//! возвращают DbFrozenError (достаётся через downcast_ref); чтения работают как обычно.
+//! Db::thaw() удаляет маркер и снова разрешает коммиты.
+//!
+//! Снапшот тома, снятый между freeze и thaw, содержит маркер: по нему видно, что образ снят в
+//! консистентной точке и на каком LSN. Такой образ открывается как после сбоя (WAL-реплей
+//! идемпотентен), потерь нет. Маркер, найденный writer'ом при open, устарел и удаляется;
+//! Drop замороженного Db — обычное закрытие.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::core::Db;
+use crate::meta::write_meta_overwrite;
+use crate::metrics::{record_db_freeze, record_db_thaw};
+use crate::util::fsx::{fsync_parent_dir, open_tmp_for_write, replace_file};
+use crate::wal::{wal_path, Wal};
+
+pub const FREEZE_MARKER_FILE: &str = ".freeze.json";
+
+pub fn freeze_marker_path(root: &Path) -> PathBuf {
+    root.join(FREEZE_MARKER_FILE)
+}
+
+/// Консистентная точка, зафиксированная Db::freeze (содержимое `<root>/.freeze.json`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FreezeInfo {
+    /// Последний закоммиченный LSN: всё до него включительно — на диске.
+    pub lsn: u64,
+    pub next_page_id: u64,
+    pub frozen_unix_ms: u64,
+    pub pid: u32,
+    /// Сколько сегментов данных синхронизировано.
+    pub segments_synced: u64,
+}
+
+/// Коммит отклонён: хэндл заморожен (Db::freeze). Достаётся из anyhow через downcast_ref.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbFrozenError {
+    pub path: PathBuf,
+    /// LSN консистентной точки заморозки.
+    pub lsn: u64,
+}
+
+impl std::fmt::Display for DbFrozenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "database is frozen: {} (consistent point lsn={}); commits resume after thaw",
+            self.path.display(),
+            self.lsn
+        )
+    }
+}
+
+impl std::error::Error for DbFrozenError {}
+
+impl Db {
+    /// Заморозить writer: сбросить WAL и данные на диск, записать meta и маркер консистентной
+    /// точки, запретить коммиты до thaw(). Повторный вызов — no-op (возвращает ту же точку).
+    pub fn freeze(&mut self) -> Result<FreezeInfo> {
+        self.pager.ensure_writable("freeze")?;
+        if let Some(info) = &self.pager.frozen {
+            return Ok(info.clone());
+        }
+
+        // 1) WAL: отложенный fsync (group commit / коалессация) + сам файл
+        let mut wal = Wal::open_for_append(&self.root)?;
+        wal.fsync()?;
+        let wp = wal_path(&self.root);
+        std::fs::File::open(&wp)
+            .and_then(|f| f.sync_all())
+            .with_context(|| format!("fsync {}", wp.display()))?;
+
+        // 2) Сегменты данных
+        let segments_synced = self.pager.sync_all_segments()?;
+
+        // 3) Актуальная meta (clean_shutdown остаётся false: процесс продолжает работу)
+        write_meta_overwrite(&self.root, &self.pager.meta)?;
+
+        // 4) Маркер консистентной точки
+        let info = FreezeInfo {
+            lsn: self.pager.meta.last_lsn,
+            next_page_id: self.pager.meta.next_page_id,
+            frozen_unix_ms: now_unix_ms(),
+            pid: std::process::id(),
+            segments_synced,
+        };
+        write_freeze_marker(&self.root, &info)?;
+
+        self.pager.frozen = Some(info.clone());
+        record_db_freeze();
+        Ok(info)
+    }
+
+    /// Разморозить: удалить маркер и разрешить коммиты. false — хэндл не был заморожен.
+    pub fn thaw(&mut self) -> Result<bool> {
+        let Some(info) = self.pager.frozen.take() else {
+            return Ok(false);
+        };
+        let p = freeze_marker_path(&self.root);
+        match std::fs::remove_file(&p) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                self.pager.frozen = Some(info);
+                return Err(e).with_context(|| format!("remove {}", p.display()));
+            }
+            _ => {}
+        }
+        record_db_thaw(now_unix_ms().saturating_sub(info.frozen_unix_ms));
+        Ok(true)
+    }
+
+    /// Заморожен ли хэндл.
+    pub fn is_frozen(&self) -> bool {
+        self.pager.frozen.is_some()
+    }
+
+    /// Точка текущей заморозки (None — хэндл не заморожен).
+    pub fn frozen_at(&self) -> Option<&FreezeInfo> {
+        self.pager.frozen.as_ref()
+    }
+}
+
+/// Прочитать маркер заморозки (например, в корне, восстановленном из снапшота тома).
+pub fn read_freeze_marker(root: &Path) -> Result<Option<FreezeInfo>> {
+    let p = freeze_marker_path(root);
+    let s = match std::fs::read_to_string(&p) {
+        Ok(s) => s,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("read {}", p.display())),
+    };
+    let info = serde_json::from_str(&s).with_context(|| format!("parse {}", p.display()))?;
+    Ok(Some(info))
+}
+
+/// Удалить устаревший маркер (writer open / Drop). Ошибки игнорируются.
+pub(crate) fn clear_freeze_marker(root: &Path) {
+    let _ = std::fs::remove_file(freeze_marker_path(root));
+}
+
+fn write_freeze_marker(root: &Path, info: &FreezeInfo) -> Result<()> {
+    use std::io::Write;
+    let p = freeze_marker_path(root);
+    let tmp = root.join(format!("{}.tmp", FREEZE_MARKER_FILE));
+    {
+        let mut f =
+            open_tmp_for_write(&tmp).with_context(|| format!("create {}", tmp.display()))?;
+        f.write_all(&serde_json::to_vec_pretty(info)?)?;
+        f.sync_all()?;
+    }
+    replace_file(&tmp, &p)
+        .with_context(|| format!("rename {} -> {}", tmp.display(), p.display()))?;
+    let _ = fsync_parent_dir(&p);
+    Ok(())
+}
+
+fn now_unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,227 @@
+//! db/hot_get — микро-кэш локаций горячих ключей для get (QuiverConfig::hot_get_cache_entries).
+//!
+//! Даже с keydir каждый get заново разбирает заголовок и ищет запись на странице, а без keydir —
+//! идёт по цепочке бакета. Кэш хэндла хранит для недавно прочитанных ключей локацию решающей
+//! записи: (голова бакета, page_id, offset, LSN страницы). Повторный get читает ровно эту запись,
+//! если:
+//! - голова бакета не сменилась — в бакет ничего не писали (любая запись ставит новую голову:
+//!   put/del/батч/bulk load/компактация; writer другого процесса — тоже);
+//! - LSN страницы прежний и по offset лежит тот же ключ.
+//!
+//! Иначе запись кэша снимается (инвалидация) и get идёт обычным путём, который заполняет кэш
+//! заново. Записи этого хэндла дополнительно снимают записи своих бакетов сразу
+//! (Db::hot_get_invalidate_buckets). Tombstone кэшируется как отрицательный ответ; протухшая по TTL
+//! запись — промах. Значения не кэшируются (OVERFLOW — в value cache), только локации.
+//!
+//! Ёмкость — десятки/сотни ключей: при переполнении вытесняется давно не читанный (линейный
+//! поиск минимума). Метрики: hot_get_hits / hot_get_misses / hot_get_invalidations.
+
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::metrics::{record_hot_get_hit, record_hot_get_invalidated, record_hot_get_miss};
+use crate::page::kv::kv_read_record_at_checked;
+use crate::page::{kv_header_read_v3, PAGE_MAGIC, PAGE_TYPE_KV_RH3, TRAILER_LEN};
+
+use super::core::Db;
+
+/// Локация решающей записи ключа.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct HotGetLoc {
+    pub bucket: u32,
+    /// Голова бакета, при которой запись была решающей.
+    pub head: u64,
+    pub pid: u64,
+    pub off: u32,
+    pub page_lsn: u64,
+}
+
+#[derive(Debug)]
+struct Slot {
+    loc: HotGetLoc,
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    map: HashMap<Vec<u8>, Slot>,
+    tick: u64,
+}
+
+/// Кэш хэндла (под Mutex: get идёт по &self).
+#[derive(Debug)]
+pub(crate) struct HotGetCache {
+    cap: usize,
+    inner: Mutex<Inner>,
+}
+
+/// Ответ кэша.
+pub(crate) enum HotGetHit {
+    /// Живая запись: сырое значение (inline или OVERFLOW placeholder).
+    Value(Vec<u8>),
+    Tombstone,
+}
+
+impl HotGetCache {
+    pub(crate) fn new(cap: usize) -> Self {
+        Self {
+            cap,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn get(&self, key: &[u8]) -> Option<HotGetLoc> {
+        let mut g = self.lock();
+        g.tick += 1;
+        let tick = g.tick;
+        g.map.get_mut(key).map(|s| {
+            s.used = tick;
+            s.loc
+        })
+    }
+
+    pub(crate) fn insert(&self, key: &[u8], loc: HotGetLoc) {
+        let mut g = self.lock();
+        g.tick += 1;
+        let used = g.tick;
+        if g.map.len() >= self.cap && !g.map.contains_key(key) {
+            let victim = g
+                .map
+                .iter()
+                .min_by_key(|(_, s)| s.used)
+                .map(|(k, _)| k.clone());
+            if let Some(k) = victim {
+                g.map.remove(&k);
+            }
+        }
+        g.map.insert(key.to_vec(), Slot { loc, used });
+    }
+
+    fn remove(&self, key: &[u8]) {
+        if self.lock().map.remove(key).is_some() {
+            record_hot_get_invalidated(1);
+        }
+    }
+
+    fn remove_buckets(&self, buckets: &[u32]) {
+        let mut g = self.lock();
+        let before = g.map.len();
+        g.map.retain(|_, s| !buckets.contains(&s.loc.bucket));
+        record_hot_get_invalidated((before - g.map.len()) as u64);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.lock().map.len()
+    }
+}
+
+impl Db {
+    /// Число ключей в hot-get кэше хэндла (0 — кэш выключен).
+    pub fn hot_get_cache_len(&self) -> usize {
+        self.hot_get.as_ref().map_or(0, |c| c.len())
+    }
+
+    /// Ответ по кэшированной локации (None — промах: ключа нет в кэше или локация устарела).
+    pub(crate) fn hot_get_lookup(
+        &self,
+        bucket: u32,
+        key: &[u8],
+        now: u32,
+        page_buf: &mut [u8],
+    ) -> Option<HotGetHit> {
+        let cache = self.hot_get.as_ref()?;
+        let Some(loc) = cache.get(key) else {
+            record_hot_get_miss();
+            return None;
+        };
+        match self.hot_get_read_at(bucket, key, loc, now, page_buf) {
+            Some(hit) => {
+                record_hot_get_hit();
+                Some(hit)
+            }
+            None => {
+                cache.remove(key);
+                record_hot_get_miss();
+                None
+            }
+        }
+    }
+
+    fn hot_get_read_at(
+        &self,
+        bucket: u32,
+        key: &[u8],
+        loc: HotGetLoc,
+        now: u32,
+        page_buf: &mut [u8],
+    ) -> Option<HotGetHit> {
+        if loc.bucket != bucket || self.dir.head(bucket).ok()? != loc.head {
+            return None;
+        }
+        self.pager.read_page(loc.pid, page_buf).ok()?;
+        if &page_buf[0..4] != PAGE_MAGIC
+            || LittleEndian::read_u16(&page_buf[6..8]) != PAGE_TYPE_KV_RH3
+        {
+            return None;
+        }
+        let hdr = kv_header_read_v3(page_buf).ok()?;
+        if hdr.lsn != loc.page_lsn {
+            return None;
+        }
+        let data_end = hot_get_data_end(hdr.table_slots, page_buf.len());
+        let (k, v, expires_at_sec, vflags) =
+            kv_read_record_at_checked(page_buf, loc.off as usize, data_end)?;
+        if k != key {
+            return None;
+        }
+        if (vflags & 0x1) == 1 {
+            return Some(HotGetHit::Tombstone);
+        }
+        if expires_at_sec != 0 && now >= expires_at_sec {
+            return None;
+        }
+        Some(HotGetHit::Value(v.to_vec()))
+    }
+
+    /// Запомнить решающую запись ключа (страница уже в page_buf).
+    pub(crate) fn hot_get_fill(&self, key: &[u8], head: u64, pid: u64, page: &[u8], now: u32) {
+        let Some(cache) = self.hot_get.as_ref() else {
+            return;
+        };
+        let Ok(hdr) = kv_header_read_v3(page) else {
+            return;
+        };
+        if let Some(off) = super::read_page::locate_decision_on_page(page, key, now) {
+            let bucket = self.dir.bucket_of_key(key, self.pager.meta.hash_kind);
+            cache.insert(
+                key,
+                HotGetLoc {
+                    bucket,
+                    head,
+                    pid,
+                    off: off as u32,
+                    page_lsn: hdr.lsn,
+                },
+            );
+        }
+    }
+
+    /// Снять записи кэша по бакетам, в которые пишет этот хэндл.
+    pub(crate) fn hot_get_invalidate_buckets(&self, buckets: &[u32]) {
+        if let Some(cache) = self.hot_get.as_ref() {
+            if !buckets.is_empty() {
+                cache.remove_buckets(buckets);
+            }
+        }
+    }
+}
+
+#[inline]
+fn hot_get_data_end(table_slots: u32, ps: usize) -> usize {
+    ps.saturating_sub(TRAILER_LEN + table_slots as usize * crate::page::common::KV_SLOT_SIZE)
+}
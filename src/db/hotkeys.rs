@@ -0,0 +1,287 @@
+//! db/hotkeys — выборочный профайлер обращений по префиксам ключей (поиск «горячих» ключей).
+//!
+//! Включается QuiverConfig::hot_prefix_sample = N (ENV P1_HOT_PREFIX_SAMPLE): каждая N-я операция
+//! get/exists/put/del (включая операции батчей) учитывается по первым hot_prefix_len байтам ключа.
+//!
+//! Устройство:
+//! - count-min sketch (4 × 4096 атомарных u32) отдельно для чтений и записей — оценка частоты
+//!   любого префикса с фиксированной памятью (~128 KiB), без блокировок на горячем пути;
+//! - небольшой набор кандидатов (heavy hitters, до 256 префиксов) под Mutex — трогается только
+//!   на выбранных операциях; при переполнении вытесняется кандидат с минимальной оценкой;
+//! - оценки масштабируются на N (≈ реальное число операций, с верхней погрешностью CMS).
+//!
+//! Db::hot_prefixes(top_n) — текущий топ хэндла. Writer при закрытии сохраняет топ в
+//! <root>/.hot_prefixes.json (RO-хэндлы — явным Db::save_hot_prefixes); `quiverdb hot-keys`
+//! читает этот файл.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::util::fsx::{open_tmp_for_write, replace_file};
+
+use super::core::Db;
+
+pub const HOT_PREFIXES_FILE: &str = ".hot_prefixes.json";
+
+const CMS_DEPTH: usize = 4;
+const CMS_WIDTH: usize = 4096;
+const MAX_CANDIDATES: usize = 256;
+
+/// Префикс ключа и оценка числа обращений к нему.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotPrefix {
+    /// Префикс в hex (ключи бинарные).
+    pub prefix_hex: String,
+    /// Префикс как UTF-8 (lossy) — для чтения человеком.
+    pub prefix: String,
+    pub reads: u64,
+    pub writes: u64,
+}
+
+impl HotPrefix {
+    pub fn total(&self) -> u64 {
+        self.reads + self.writes
+    }
+}
+
+/// Снимок профиля (содержимое .hot_prefixes.json).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HotPrefixReport {
+    pub sample_every: u32,
+    pub prefix_len: usize,
+    pub ops_seen: u64,
+    pub ops_sampled: u64,
+    pub created_unix_ms: u64,
+    pub prefixes: Vec<HotPrefix>,
+}
+
+struct CountMin {
+    cells: Vec<AtomicU32>,
+}
+
+impl CountMin {
+    fn new() -> Self {
+        Self {
+            cells: (0..CMS_DEPTH * CMS_WIDTH)
+                .map(|_| AtomicU32::new(0))
+                .collect(),
+        }
+    }
+
+    #[inline]
+    fn slots(h: u64) -> [usize; CMS_DEPTH] {
+        // double hashing: h1 + i*h2
+        let (h1, h2) = (h as u32 as usize, ((h >> 32) as usize) | 1);
+        let mut out = [0usize; CMS_DEPTH];
+        for (i, o) in out.iter_mut().enumerate() {
+            *o = i * CMS_WIDTH + (h1.wrapping_add(i.wrapping_mul(h2)) % CMS_WIDTH);
+        }
+        out
+    }
+
+    /// Инкремент и новая оценка.
+    fn add(&self, h: u64) -> u32 {
+        let mut est = u32::MAX;
+        for s in Self::slots(h) {
+            let v = self.cells[s]
+                .fetch_add(1, Ordering::Relaxed)
+                .saturating_add(1);
+            est = est.min(v);
+        }
+        est
+    }
+
+    fn estimate(&self, h: u64) -> u32 {
+        Self::slots(h)
+            .iter()
+            .map(|&s| self.cells[s].load(Ordering::Relaxed))
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+/// Профайлер одного Db-хэндла.
+pub(crate) struct HotPrefixProfiler {
+    sample_every: u32,
+    prefix_len: usize,
+    seq: AtomicU64,
+    sampled: AtomicU64,
+    reads: CountMin,
+    writes: CountMin,
+    candidates: Mutex<HashMap<Vec<u8>, u64>>,
+}
+
+impl HotPrefixProfiler {
+    pub(crate) fn new(sample_every: u32, prefix_len: usize) -> Self {
+        Self {
+            sample_every: sample_every.max(1),
+            prefix_len: prefix_len.max(1),
+            seq: AtomicU64::new(0),
+            sampled: AtomicU64::new(0),
+            reads: CountMin::new(),
+            writes: CountMin::new(),
+            candidates: Mutex::new(HashMap::new()),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn record_read(&self, key: &[u8]) {
+        self.record(key, false);
+    }
+
+    #[inline]
+    pub(crate) fn record_write(&self, key: &[u8]) {
+        self.record(key, true);
+    }
+
+    #[inline]
+    fn record(&self, key: &[u8], write: bool) {
+        let n = self.seq.fetch_add(1, Ordering::Relaxed);
+        if !n.is_multiple_of(self.sample_every as u64) {
+            return;
+        }
+        self.sampled.fetch_add(1, Ordering::Relaxed);
+        let prefix = &key[..key.len().min(self.prefix_len)];
+        let h = hash_prefix(prefix);
+        let (hit, other) = if write {
+            (&self.writes, &self.reads)
+        } else {
+            (&self.reads, &self.writes)
+        };
+        let est = hit.add(h) as u64 + other.estimate(h) as u64;
+
+        let mut cand = self.candidates.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(v) = cand.get_mut(prefix) {
+            *v = est;
+            return;
+        }
+        if cand.len() < MAX_CANDIDATES {
+            cand.insert(prefix.to_vec(), est);
+            return;
+        }
+        // Вытесняем слабейшего кандидата, если новый префикс «тяжелее»
+        let (min_key, min_est) = match cand.iter().min_by_key(|(_, v)| **v) {
+            Some((k, v)) => (k.clone(), *v),
+            None => return,
+        };
+        if est > min_est {
+            cand.remove(&min_key);
+            cand.insert(prefix.to_vec(), est);
+        }
+    }
+
+    pub(crate) fn report(&self, top_n: usize) -> HotPrefixReport {
+        let scale = self.sample_every as u64;
+        let keys: Vec<Vec<u8>> = {
+            let cand = self.candidates.lock().unwrap_or_else(|e| e.into_inner());
+            cand.keys().cloned().collect()
+        };
+        let mut prefixes: Vec<HotPrefix> = keys
+            .into_iter()
+            .map(|p| {
+                let h = hash_prefix(&p);
+                HotPrefix {
+                    prefix_hex: to_hex(&p),
+                    prefix: String::from_utf8_lossy(&p).into_owned(),
+                    reads: self.reads.estimate(h) as u64 * scale,
+                    writes: self.writes.estimate(h) as u64 * scale,
+                }
+            })
+            .collect();
+        prefixes.sort_by(|a, b| {
+            b.total()
+                .cmp(&a.total())
+                .then_with(|| a.prefix_hex.cmp(&b.prefix_hex))
+        });
+        prefixes.truncate(top_n);
+        HotPrefixReport {
+            sample_every: self.sample_every,
+            prefix_len: self.prefix_len,
+            ops_seen: self.seq.load(Ordering::Relaxed),
+            ops_sampled: self.sampled.load(Ordering::Relaxed),
+            created_unix_ms: now_ms(),
+            prefixes,
+        }
+    }
+}
+
+impl Db {
+    #[inline]
+    pub(crate) fn hot_read(&self, key: &[u8]) {
+        if let Some(p) = self.hot_prefix.as_ref() {
+            p.record_read(key);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn hot_write(&self, key: &[u8]) {
+        if let Some(p) = self.hot_prefix.as_ref() {
+            p.record_write(key);
+        }
+    }
+
+    /// Топ-N префиксов ключей по оценке числа обращений (None — профайлер выключен,
+    /// см. QuiverConfig::hot_prefix_sample).
+    pub fn hot_prefixes(&self, top_n: usize) -> Option<Vec<HotPrefix>> {
+        self.hot_prefix.as_ref().map(|p| p.report(top_n).prefixes)
+    }
+
+    /// Сохранить текущий профиль в <root>/.hot_prefixes.json. Writer делает это сам при закрытии.
+    pub fn save_hot_prefixes(&self) -> Result<Option<PathBuf>> {
+        let prof = match self.hot_prefix.as_ref() {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        let rep = prof.report(MAX_CANDIDATES);
+        write_hot_prefixes(&self.root, &rep).map(Some)
+    }
+}
+
+/// Прочитать сохранённый профиль (None — файла нет).
+pub fn read_hot_prefixes(root: &Path) -> Result<Option<HotPrefixReport>> {
+    let p = root.join(HOT_PREFIXES_FILE);
+    let bytes = match std::fs::read(&p) {
+        Ok(b) => b,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("read {}", p.display())),
+    };
+    let rep = serde_json::from_slice(&bytes).with_context(|| format!("parse {}", p.display()))?;
+    Ok(Some(rep))
+}
+
+pub(crate) fn write_hot_prefixes(root: &Path, rep: &HotPrefixReport) -> Result<PathBuf> {
+    let path = root.join(HOT_PREFIXES_FILE);
+    let tmp = root.join(format!("{}.tmp", HOT_PREFIXES_FILE));
+    {
+        let mut f = open_tmp_for_write(&tmp).with_context(|| format!("open {}", tmp.display()))?;
+        f.write_all(&serde_json::to_vec_pretty(rep)?)?;
+        let _ = f.sync_all();
+    }
+    replace_file(&tmp, &path)
+        .with_context(|| format!("rename {} -> {}", tmp.display(), path.display()))?;
+    Ok(path)
+}
+
+#[inline]
+fn hash_prefix(p: &[u8]) -> u64 {
+    let mut h = twox_hash::XxHash64::with_seed(0x4B07_9E11);
+    h.write(p);
+    h.finish()
+}
+
+fn to_hex(b: &[u8]) -> String {
+    b.iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
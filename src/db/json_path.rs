@@ -0,0 +1,208 @@
+//! db/json_path — проекция JSON-значений по пути (Db::get_json_path, фича "json").
+//!
+//! Значение ключа разбирается потоково (serde_json::Deserializer) и материализуется только
+//! поддерево по пути: соседние поля и элементы массивов пропускаются без построения дерева
+//! (IgnoredAny). Наружу (FFI/сеть) уходит только выбранное поле, а не весь блоб.
+//!
+//! Синтаксис пути (подмножество JSONPath): `$` — корень; `.name` — поле объекта;
+//! `["name"]` / `['name']` — поле с произвольными символами; `[N]` — элемент массива.
+//! Ведущий `$` можно опустить: "user.name" == "$.user.name".
+//!
+//! Нет ключа или нет пути (поля нет, индекс за концом, тип узла не объект/массив) — None.
+//! Значение не JSON — ошибка.
+
+use anyhow::{anyhow, Context, Result};
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+
+use super::core::Db;
+
+/// Разобранный путь проекции (JsonPath::parse).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    segs: Vec<JsonPathSeg>,
+}
+
+/// Шаг пути.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonPathSeg {
+    Field(String),
+    Index(usize),
+}
+
+impl JsonPath {
+    /// Разобрать путь ("$.a.b[0]", "a['x.y']"); пустой путь или "$" — всё значение.
+    pub fn parse(path: &str) -> Result<Self> {
+        let s = path.trim();
+        let bad = |why: &str| anyhow!("invalid JSON path {:?}: {}", path, why);
+        let (mut rest, mut first) = match s.strip_prefix('$') {
+            Some(r) => (r, false),
+            None => (s, true),
+        };
+        let mut segs = Vec::new();
+        while !rest.is_empty() {
+            if let Some(r) = rest.strip_prefix('[') {
+                let close = r.find(']').ok_or_else(|| bad("unclosed '['"))?;
+                let inner = &r[..close];
+                let quoted = inner.len() >= 2
+                    && (inner.starts_with('"') && inner.ends_with('"')
+                        || inner.starts_with('\'') && inner.ends_with('\''));
+                if quoted {
+                    segs.push(JsonPathSeg::Field(inner[1..inner.len() - 1].to_string()));
+                } else {
+                    let i = inner
+                        .parse::<usize>()
+                        .map_err(|_| bad("expected array index or quoted field in [...]"))?;
+                    segs.push(JsonPathSeg::Index(i));
+                }
+                rest = &r[close + 1..];
+            } else {
+                let r = match rest.strip_prefix('.') {
+                    Some(r) => r,
+                    None if first => rest,
+                    None => return Err(bad("expected '.' or '['")),
+                };
+                let end = r.find(['.', '[']).unwrap_or(r.len());
+                if end == 0 {
+                    return Err(bad("empty field name"));
+                }
+                segs.push(JsonPathSeg::Field(r[..end].to_string()));
+                rest = &r[end..];
+            }
+            first = false;
+        }
+        Ok(Self { segs })
+    }
+
+    pub fn segments(&self) -> &[JsonPathSeg] {
+        &self.segs
+    }
+
+    /// Выбрать поддерево из JSON-текста (None — пути нет).
+    pub fn select(&self, json: &[u8]) -> Result<Option<Value>> {
+        let mut de = serde_json::Deserializer::from_slice(json);
+        let v = Select(&self.segs).deserialize(&mut de)?;
+        de.end()?;
+        Ok(v)
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for s in &self.segs {
+            match s {
+                JsonPathSeg::Field(n) if !n.is_empty() && !n.contains(['.', '[', ']']) => {
+                    write!(f, ".{}", n)?
+                }
+                JsonPathSeg::Field(n) => write!(f, "[{:?}]", n)?,
+                JsonPathSeg::Index(i) => write!(f, "[{}]", i)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Db {
+    /// Поле JSON-значения ключа по пути (см. модуль). None — нет ключа или нет пути.
+    pub fn get_json_path(&self, key: &[u8], path: &str) -> Result<Option<Value>> {
+        let p = JsonPath::parse(path)?;
+        self.get_json_path_with(key, &p)
+    }
+
+    /// То же с заранее разобранным путём (горячие циклы).
+    pub fn get_json_path_with(&self, key: &[u8], path: &JsonPath) -> Result<Option<Value>> {
+        let Some(v) = self.get(key)? else {
+            return Ok(None);
+        };
+        path.select(&v).with_context(|| {
+            format!(
+                "value of key {:?} is not valid JSON",
+                String::from_utf8_lossy(key)
+            )
+        })
+    }
+}
+
+// ---------------- streaming selection ----------------
+
+/// Seed: материализует узел по оставшемуся пути, остальное пропускает.
+struct Select<'a>(&'a [JsonPathSeg]);
+
+impl<'de> DeserializeSeed<'de> for Select<'_> {
+    type Value = Option<Value>;
+
+    fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+        if self.0.is_empty() {
+            Value::deserialize(d).map(Some)
+        } else {
+            d.deserialize_any(self)
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for Select<'_> {
+    type Value = Option<Value>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let (seg, rest) = (&self.0[0], &self.0[1..]);
+        let mut found = None;
+        while let Some(k) = map.next_key::<String>()? {
+            match seg {
+                // дубли ключей: последний выигрывает (как serde_json::Value)
+                JsonPathSeg::Field(name) if *name == k => {
+                    found = map.next_value_seed(Select(rest))?
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let (seg, rest) = (&self.0[0], &self.0[1..]);
+        let mut found = None;
+        let mut i = 0usize;
+        loop {
+            let hit = matches!(seg, JsonPathSeg::Index(n) if *n == i);
+            if hit {
+                match seq.next_element_seed(Select(rest))? {
+                    Some(v) => found = v,
+                    None => break,
+                }
+            } else if seq.next_element::<IgnoredAny>()?.is_none() {
+                break;
+            }
+            i += 1;
+        }
+        Ok(found)
+    }
+
+    // Скаляр на месте объекта/массива — пути нет
+    fn visit_bool<E>(self, _v: bool) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+    fn visit_i64<E>(self, _v: i64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+    fn visit_u64<E>(self, _v: u64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+    fn visit_f64<E>(self, _v: f64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+    fn visit_str<E>(self, _v: &str) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+}
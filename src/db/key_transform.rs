@@ -0,0 +1,314 @@
+//! db/key_transform — каноникализация ключей на границе Db (KeyTransform).
+//!
+//! Трансформация регистрируется в конфиге (QuiverConfig::key_transform / DbBuilder::key_transform)
+//! и применяется ко всем ключам публичного API: put/get/del/exists, get_many/exists_many, Batch,
+//! AutoBatcher, BulkLoader, delete_prefix, get_versions/key_history, undelete, сканы и оценки по
+//! префиксу. Обойти её через Db нельзя; в БД лежит только каноническая форма, и сканы
+//! возвращают именно её (обратного преобразования нет).
+//!
+//! Контракт: canonical_key детерминирована и идемпотентна (canonical(canonical(k)) ==
+//! canonical(k)) — CDC, import и undelete повторно подают уже канонические ключи.
+//! canonical_prefix переводит префикс скана в префикс канонических ключей; None — такой префикс
+//! не выражается (скан по нему — ошибка).
+//!
+//! Встроенные трансформации (builtin_key_transform восстанавливает их по id из meta — открытие
+//! без явной регистрации работает, включая CLI):
+//! - LowercaseKeys — ASCII lowercase ("lowercase");
+//! - StripNamespace — срезать ведущий namespace, повторно ("strip_namespace:<hex>");
+//! - HashLongKeys — ключ длиннее max_len → первые max_len-16 байт + SHA-256(ключ)[..16]
+//!   ("hash_long:<max_len>").
+//!
+//! Идентификатор фиксируется в meta (key_transform + required-бит FEATURE_KEY_TRANSFORM) при
+//! первом открытии writer'ом пустой БД (next_page_id == 0) и едет с backup/snapshot/clone
+//! (DbIdentity). Открытие с другой трансформацией, без неё (кроме встроенных) или с
+//! трансформацией для БД без неё — KeyTransformMismatchError.
+
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::meta::{record_key_transform, MetaHeader};
+
+use super::core::Db;
+
+/// Каноникализация ключей (см. модульный комментарий).
+pub trait KeyTransform: Send + Sync {
+    /// Стабильный идентификатор (пишется в meta, до KEY_TRANSFORM_ID_MAX байт).
+    fn id(&self) -> String;
+
+    /// Каноническая форма ключа.
+    fn canonical_key<'a>(&self, key: &'a [u8]) -> Cow<'a, [u8]>;
+
+    /// Префикс канонических ключей для префикса скана (None — не выражается).
+    fn canonical_prefix<'a>(&self, prefix: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+        Some(self.canonical_key(prefix))
+    }
+}
+
+/// Трансформация в QuiverConfig (Clone/Debug для конфига).
+#[derive(Clone)]
+pub struct KeyTransformRef(pub Arc<dyn KeyTransform>);
+
+impl KeyTransformRef {
+    pub fn new(t: Arc<dyn KeyTransform>) -> Self {
+        Self(t)
+    }
+}
+
+impl fmt::Debug for KeyTransformRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "KeyTransformRef({})", self.0.id())
+    }
+}
+
+/// Трансформация конфига не совпадает с записанной в meta. Достаётся из anyhow через downcast_ref.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyTransformMismatchError {
+    pub path: PathBuf,
+    /// Из meta ("" — нет).
+    pub recorded: String,
+    /// Из конфига ("" — нет).
+    pub configured: String,
+}
+
+impl fmt::Display for KeyTransformMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |s: &str| {
+            if s.is_empty() {
+                "none".to_string()
+            } else {
+                s.to_string()
+            }
+        };
+        write!(
+            f,
+            "key transform mismatch at {}: DB uses {}, handle configured with {}",
+            self.path.display(),
+            show(&self.recorded),
+            show(&self.configured)
+        )
+    }
+}
+
+impl std::error::Error for KeyTransformMismatchError {}
+
+// -------------------- встроенные трансформации --------------------
+
+/// ASCII lowercase.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LowercaseKeys;
+
+impl KeyTransform for LowercaseKeys {
+    fn id(&self) -> String {
+        "lowercase".to_string()
+    }
+
+    fn canonical_key<'a>(&self, key: &'a [u8]) -> Cow<'a, [u8]> {
+        if key.iter().any(u8::is_ascii_uppercase) {
+            Cow::Owned(key.to_ascii_lowercase())
+        } else {
+            Cow::Borrowed(key)
+        }
+    }
+}
+
+/// Срезать ведущий namespace (повторно — идемпотентно: "ns/ns/k" → "k").
+#[derive(Debug, Clone)]
+pub struct StripNamespace {
+    ns: Vec<u8>,
+}
+
+impl StripNamespace {
+    pub fn new(ns: &[u8]) -> Result<Self> {
+        // hex в id: 2 байта на байт namespace
+        let max = (crate::meta::KEY_TRANSFORM_ID_MAX - "strip_namespace:".len()) / 2;
+        if ns.is_empty() || ns.len() > max {
+            return Err(anyhow!("namespace must be 1..={} bytes", max));
+        }
+        Ok(Self { ns: ns.to_vec() })
+    }
+
+    fn strip<'a>(&self, mut k: &'a [u8]) -> &'a [u8] {
+        while let Some(rest) = k.strip_prefix(self.ns.as_slice()) {
+            k = rest;
+        }
+        k
+    }
+}
+
+impl KeyTransform for StripNamespace {
+    fn id(&self) -> String {
+        let hex: String = self.ns.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("strip_namespace:{}", hex)
+    }
+
+    fn canonical_key<'a>(&self, key: &'a [u8]) -> Cow<'a, [u8]> {
+        Cow::Borrowed(self.strip(key))
+    }
+
+    fn canonical_prefix<'a>(&self, prefix: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+        let p = self.strip(prefix);
+        // Префикс внутри namespace ("ns" от "nsx/"): под ним — любой канонический ключ
+        if self.ns.starts_with(p) {
+            return Some(Cow::Borrowed(&[]));
+        }
+        Some(Cow::Borrowed(p))
+    }
+}
+
+/// Длинный ключ → усечённый префикс + SHA-256 (ключи до max_len не меняются).
+#[derive(Debug, Clone, Copy)]
+pub struct HashLongKeys {
+    max_len: usize,
+}
+
+/// Байт хэша в хвосте усечённого ключа.
+const HASH_LONG_DIGEST_LEN: usize = 16;
+
+impl HashLongKeys {
+    /// max_len — 32..=u16::MAX.
+    pub fn new(max_len: usize) -> Result<Self> {
+        if !(2 * HASH_LONG_DIGEST_LEN..=u16::MAX as usize).contains(&max_len) {
+            return Err(anyhow!(
+                "hash_long max_len must be {}..={}, got {}",
+                2 * HASH_LONG_DIGEST_LEN,
+                u16::MAX,
+                max_len
+            ));
+        }
+        Ok(Self { max_len })
+    }
+
+    fn head_len(&self) -> usize {
+        self.max_len - HASH_LONG_DIGEST_LEN
+    }
+}
+
+impl KeyTransform for HashLongKeys {
+    fn id(&self) -> String {
+        format!("hash_long:{}", self.max_len)
+    }
+
+    fn canonical_key<'a>(&self, key: &'a [u8]) -> Cow<'a, [u8]> {
+        if key.len() <= self.max_len {
+            return Cow::Borrowed(key);
+        }
+        let digest = Sha256::digest(key);
+        let mut out = Vec::with_capacity(self.max_len);
+        out.extend_from_slice(&key[..self.head_len()]);
+        out.extend_from_slice(&digest[..HASH_LONG_DIGEST_LEN]);
+        Cow::Owned(out)
+    }
+
+    fn canonical_prefix<'a>(&self, prefix: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+        // Дальше head_len у длинных ключей — хэш: префикс не сопоставить
+        (prefix.len() <= self.head_len()).then_some(Cow::Borrowed(prefix))
+    }
+}
+
+/// Встроенная трансформация по идентификатору из meta.
+pub fn builtin_key_transform(id: &str) -> Option<Arc<dyn KeyTransform>> {
+    if id == "lowercase" {
+        return Some(Arc::new(LowercaseKeys));
+    }
+    if let Some(hex) = id.strip_prefix("strip_namespace:") {
+        if hex.len() % 2 != 0 || !hex.is_ascii() {
+            return None;
+        }
+        let ns: Option<Vec<u8>> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+            .collect();
+        return StripNamespace::new(&ns?)
+            .ok()
+            .map(|t| Arc::new(t) as Arc<dyn KeyTransform>);
+    }
+    if let Some(n) = id.strip_prefix("hash_long:") {
+        return HashLongKeys::new(n.parse().ok()?)
+            .ok()
+            .map(|t| Arc::new(t) as Arc<dyn KeyTransform>);
+    }
+    None
+}
+
+/// Трансформация хэндла по meta и конфигу (writer фиксирует её в пустой БД).
+pub(crate) fn resolve_key_transform(
+    root: &Path,
+    meta: &mut MetaHeader,
+    configured: Option<&KeyTransformRef>,
+    writer: bool,
+) -> Result<Option<Arc<dyn KeyTransform>>> {
+    let configured = configured.map(|r| r.0.clone());
+    let configured_id = configured.as_ref().map(|t| t.id()).unwrap_or_default();
+    let mismatch = || KeyTransformMismatchError {
+        path: root.to_path_buf(),
+        recorded: meta.key_transform.clone(),
+        configured: configured_id.clone(),
+    };
+    match configured {
+        None if meta.key_transform.is_empty() => Ok(None),
+        None => match builtin_key_transform(&meta.key_transform) {
+            Some(t) => Ok(Some(t)),
+            None => Err(mismatch().into()),
+        },
+        Some(t) if configured_id == meta.key_transform => Ok(Some(t)),
+        // Пустая БД (ещё ни одной страницы) принимает трансформацию writer'а
+        Some(t) if meta.key_transform.is_empty() && writer && meta.next_page_id == 0 => {
+            let m = record_key_transform(root, &configured_id)?;
+            meta.key_transform = m.key_transform;
+            meta.flags = m.flags;
+            Ok(Some(t))
+        }
+        Some(_) => Err(mismatch().into()),
+    }
+}
+
+impl Db {
+    /// Идентификатор трансформации ключей хэндла (None — ключи хранятся как есть).
+    pub fn key_transform_id(&self) -> Option<String> {
+        self.key_transform.as_ref().map(|t| t.id())
+    }
+
+    /// Каноническая форма ключа — так он лежит в БД.
+    pub fn canonical_key<'k>(&self, key: &'k [u8]) -> Cow<'k, [u8]> {
+        match self.key_transform.as_ref() {
+            Some(t) => t.canonical_key(key),
+            None => Cow::Borrowed(key),
+        }
+    }
+
+    /// Префикс канонических ключей для префикса скана.
+    pub(crate) fn canonical_prefix<'p>(&self, prefix: &'p [u8]) -> Result<Cow<'p, [u8]>> {
+        match self.key_transform.as_ref() {
+            Some(t) => t.canonical_prefix(prefix).ok_or_else(|| {
+                anyhow!(
+                    "prefix {:?} cannot be matched under key transform {}",
+                    String::from_utf8_lossy(prefix),
+                    t.id()
+                )
+            }),
+            None => Ok(Cow::Borrowed(prefix)),
+        }
+    }
+
+    /// Канонические формы набора ключей (None — трансформации нет, ключи как есть).
+    pub(crate) fn canonical_keys(&self, keys: &[&[u8]]) -> Option<Vec<Vec<u8>>> {
+        let t = self.key_transform.as_ref()?;
+        Some(
+            keys.iter()
+                .map(|k| t.canonical_key(k).into_owned())
+                .collect(),
+        )
+    }
+
+    /// canonical_prefix для Option-префикса.
+    pub(crate) fn canonical_prefix_opt<'p>(
+        &self,
+        prefix: Option<&'p [u8]>,
+    ) -> Result<Option<Cow<'p, [u8]>>> {
+        prefix.map(|p| self.canonical_prefix(p)).transpose()
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! Что внутри:
 //! - put: для малых значений — одна KV‑страница; для больших — OVERFLOW3 цепочка + KV.
-//! - del: пишет tombstone.
+//! - del: пишет tombstone (в trash-режиме — мягкий, со сроком корзины; см. db/trash.rs).
 //! - get: tail-wins, tombstone приоритетен, read-side TTL; разворачивает OVERFLOW placeholder.
 //!
 //! Fast paths (read):
@@ -13,20 +13,24 @@
 //! - Bloom positive → лёгкий префетч головы (pager.prefetch_page(head)).
 //!
 //! NEW: value cache для OVERFLOW — перед чтением цепочки пробуем кэш, после чтения кладём в кэш.
+//! NEW: обход цепочки бакета идёт через readahead-окно (Pager::read_page_ra).
+//! NEW: put/del/get учитываются в per-handle счётчиках и хуке инструментирования (db/stats.rs).
+//! NEW: put кодирует значение трансформацией хэндла, get декодирует (db/value_transform.rs).
+//! NEW: hot-get кэш локаций (db/hot_get.rs) — до keydir/bloom; put/del снимают записи бакета.
 
 use anyhow::{anyhow, Result};
 use byteorder::{ByteOrder, LittleEndian};
+use std::time::Instant;
 
 use crate::bloom::BloomSidecar;
 use crate::dir::NO_PAGE;
-use crate::metrics::{
-    record_bloom_negative, record_bloom_positive, record_bloom_skipped_stale, record_ttl_skipped,
-};
+use crate::metrics::{record_bloom_negative, record_bloom_positive, record_ttl_skipped};
 use crate::page::ovf::chain as page_ovf_chain;
 use crate::page::{
     kv_header_read_v3, kv_header_write_v3, kv_init_v3, ovf_header_read_v3, ovf_header_write_v3,
     ovf_init_v3, KV_HDR_MIN, OVF_HDR_MIN, PAGE_MAGIC, PAGE_TYPE_KV_RH3, TRAILER_LEN,
 };
+use crate::pager::io::ReadaheadWindow;
 use crate::util::{decode_ovf_placeholder_v3, now_secs};
 
 // Общие хелперы чтения одной страницы (скан newest→oldest)
@@ -39,20 +43,36 @@ use crate::page::kv::kv_read_record_at_checked;
 use crate::pager::value_cache::{value_cache_get, value_cache_put};
 
 use super::core::{Db, MemKeyLoc};
+use super::hot_get::HotGetHit;
+use super::stats::{GetEvent, PutEvent};
+
+/// OVERFLOW3‑цепочка, собранная в памяти: (head_pid, страницы).
+pub(crate) type OvfChainPages = (u64, Vec<(u64, Vec<u8>)>);
 
 // ----------------- публичные методы -----------------
 
 impl Db {
     /// Записать ключ/значение.
     pub fn put(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
-        if self.readonly {
-            return Err(anyhow!("Db is read-only"));
-        }
+        let key = self.canonical_key(key);
+        let key = key.as_ref();
+        let t0 = self.pager.instrumentation.as_ref().map(|_| Instant::now());
+        let stored = self.encode_value(value)?;
+        self.critical("put", |db| db.put_uninstrumented(key, &stored))?;
+        self.note_put(key, value.len(), false, t0);
+        self.maybe_auto_refresh_bloom();
+        Ok(())
+    }
+
+    fn put_uninstrumented(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.pager.ensure_writable("put")?;
         if key.len() > u16::MAX as usize {
             return Err(anyhow!("key too long (> u16::MAX)"));
         }
+        self.hot_write(key);
 
         let bucket = self.dir.bucket_of_key(key, self.pager.meta.hash_kind);
+        self.hot_get_invalidate_buckets(&[bucket]);
         let old_head = self.dir.head(bucket)?;
         let ps = self.pager.meta.page_size as usize;
 
@@ -104,13 +124,23 @@ impl Db {
 
     /// Удалить ключ — пишет tombstone.
     pub fn del(&mut self, key: &[u8]) -> Result<bool> {
-        if self.readonly {
-            return Err(anyhow!("Db is read-only"));
-        }
+        let key = self.canonical_key(key);
+        let key = key.as_ref();
+        let t0 = self.pager.instrumentation.as_ref().map(|_| Instant::now());
+        let existed = self.critical("del", |db| db.del_uninstrumented(key))?;
+        self.note_put(key, 0, true, t0);
+        self.maybe_auto_refresh_bloom();
+        Ok(existed)
+    }
+
+    fn del_uninstrumented(&mut self, key: &[u8]) -> Result<bool> {
+        self.pager.ensure_writable("del")?;
         if key.len() > u16::MAX as usize {
             return Err(anyhow!("key too long (> u16::MAX)"));
         }
+        self.hot_write(key);
         let bucket = self.dir.bucket_of_key(key, self.pager.meta.hash_kind);
+        self.hot_get_invalidate_buckets(&[bucket]);
         let old_head = self.dir.head(bucket)?;
         let existed = old_head != NO_PAGE;
 
@@ -118,7 +148,9 @@ impl Db {
         let new_pid = self.pager.allocate_one_page()?;
         let mut page = vec![0u8; ps];
         kv_init_v3(&mut page, new_pid, 0)?;
-        self.write_single_record_kv_page_with_flags(&mut page, key, &[], 0, 1)?;
+        // NEW: срок корзины в expires_at_sec tombstone'а (0 — trash выключен)
+        let deadline = self.trash_deadline();
+        self.write_single_record_kv_page_with_flags(&mut page, key, &[], deadline, 1)?;
         {
             let mut h = kv_header_read_v3(&mut page)?;
             h.next_page_id = old_head;
@@ -130,25 +162,66 @@ impl Db {
         self.pager
             .commit_pages_batch_with_heads(&mut for_commit, &updates)?;
         self.dir.set_head(bucket, new_pid)?;
+        if deadline != 0 {
+            crate::metrics::record_trash_deletes(1);
+        }
         Ok(existed)
     }
 
     /// Получить значение по ключу.
     pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let key = self.canonical_key(key);
+        let key = key.as_ref();
+        let t0 = self.pager.instrumentation.as_ref().map(|_| Instant::now());
+        let v = match self.get_uninstrumented(key)? {
+            Some(v) => Some(self.decode_value(v)?),
+            None => None,
+        };
+        let value_len = v.as_ref().map(|v| v.len());
+        self.pager.stats.note_get(value_len);
+        if let (Some(h), Some(t0)) = (self.pager.instrumentation.as_ref(), t0) {
+            h.on_get(&GetEvent {
+                key,
+                value_len,
+                duration: t0.elapsed(),
+            });
+        }
+        Ok(v)
+    }
+
+    fn get_uninstrumented(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.hot_read(key);
         let bucket = self.dir.bucket_of_key(key, self.pager.meta.hash_kind);
         let ps = self.pager.meta.page_size as usize;
         let now = now_secs();
 
+        // NEW: ключ под range tombstone (Db::delete_prefix)
+        if let Some(cut) = self.range_cut(key) {
+            return self.get_above_range_cut(key, cut, now);
+        }
+
         // Единый переиспользуемый буфер страницы
         let mut page_buf = vec![0u8; ps];
 
+        // NEW: hot-get кэш — локация решающей записи при неизменной голове бакета и LSN страницы
+        match self.hot_get_lookup(bucket, key, now, &mut page_buf) {
+            Some(HotGetHit::Value(v)) => return Ok(Some(self.expand_value_cached(&v)?)),
+            Some(HotGetHit::Tombstone) => return Ok(None),
+            None => {}
+        }
+        // Голова до обхода: с ней кэш сверяет найденную локацию
+        let hot_head = match self.hot_get {
+            Some(_) => Some(self.dir.head(bucket)?),
+            None => None,
+        };
+
         // Быстрый путь: in‑memory keydir с оффсетом
         if let Some(loc) = self.mem_keydir_get_loc(bucket, key) {
             if loc.pid == NO_PAGE {
                 return Ok(None);
             }
             if let Some(v) =
-                self.get_from_pid_off_or_fallback_with_buf(key, loc, now, &mut page_buf)?
+                self.get_from_pid_off_or_fallback_with_buf(key, loc, now, &mut page_buf, hot_head)?
             {
                 return Ok(Some(v));
             }
@@ -170,10 +243,10 @@ impl Db {
                     return Ok(None);
                 }
             } else {
-                record_bloom_skipped_stale();
+                self.note_bloom_skipped_stale();
             }
         } else {
-            record_bloom_skipped_stale();
+            self.note_bloom_skipped_stale();
         }
 
         // Обычный обход цепочки
@@ -181,15 +254,32 @@ impl Db {
         if pid == NO_PAGE {
             return Ok(None);
         }
-        self.get_from_chain_starting_at_with_buf(key, pid, now, &mut page_buf)
+        self.get_from_chain_starting_at_with_buf(key, pid, now, &mut page_buf, hot_head)
     }
 }
 
 // ----------------- приватные помощники без логирования -----------------
 
 impl Db {
+    /// NEW: per-handle счётчики put/del и хук on_put (db/stats.rs).
+    fn note_put(&self, key: &[u8], value_len: usize, tombstone: bool, t0: Option<Instant>) {
+        if tombstone {
+            self.pager.stats.note_del();
+        } else {
+            self.pager.stats.note_put(key.len(), value_len);
+        }
+        if let (Some(h), Some(t0)) = (self.pager.instrumentation.as_ref(), t0) {
+            h.on_put(&PutEvent {
+                key,
+                value_len,
+                tombstone,
+                duration: t0.elapsed(),
+            });
+        }
+    }
+
     // Подходит ли запись целиком (одна) в KV‑страницу (без слотов)?
-    fn inline_fits_one_record(&self, ps: usize, key: &[u8], value: &[u8]) -> bool {
+    pub(crate) fn inline_fits_one_record(&self, ps: usize, key: &[u8], value: &[u8]) -> bool {
         if let Some(thr) = self.pager.ovf_threshold_bytes {
             if value.len() > thr {
                 return false;
@@ -238,7 +328,7 @@ impl Db {
         Ok(())
     }
 
-    fn build_overflow_chain_pages(&mut self, value: &[u8]) -> Result<(u64, Vec<(u64, Vec<u8>)>)> {
+    pub(crate) fn build_overflow_chain_pages(&mut self, value: &[u8]) -> Result<OvfChainPages> {
         let ps = self.pager.meta.page_size as usize;
         let header_min = OVF_HDR_MIN;
         let cap = ps - header_min - TRAILER_LEN;
@@ -294,16 +384,17 @@ impl Db {
         loc: MemKeyLoc,
         now: u32,
         page_buf: &mut [u8],
+        hot_head: Option<u64>,
     ) -> Result<Option<Vec<u8>>> {
         let ps = self.pager.meta.page_size as usize;
 
         self.pager.read_page(loc.pid, page_buf)?;
         if &page_buf[0..4] != PAGE_MAGIC {
-            return self.get_from_chain_starting_at_with_buf(key, loc.pid, now, page_buf);
+            return self.get_from_chain_starting_at_with_buf(key, loc.pid, now, page_buf, hot_head);
         }
         let ptype = LittleEndian::read_u16(&page_buf[6..8]);
         if ptype != PAGE_TYPE_KV_RH3 {
-            return self.get_from_chain_starting_at_with_buf(key, loc.pid, now, page_buf);
+            return self.get_from_chain_starting_at_with_buf(key, loc.pid, now, page_buf, hot_head);
         }
 
         let hdr = kv_header_read_v3(page_buf)?;
@@ -316,7 +407,11 @@ impl Db {
             kv_read_record_at_checked(page_buf, off_usize, data_end)
         {
             if k != key {
-                return self.get_from_chain_starting_at_with_buf(key, loc.pid, now, page_buf);
+                return self
+                    .get_from_chain_starting_at_with_buf(key, loc.pid, now, page_buf, hot_head);
+            }
+            if let Some(head) = hot_head {
+                self.hot_get_fill(key, head, loc.pid, page_buf, now);
             }
 
             if (vflags & 0x1) == 1 {
@@ -325,7 +420,8 @@ impl Db {
 
             if expires_at_sec != 0 && now >= expires_at_sec {
                 record_ttl_skipped();
-                return self.get_from_chain_starting_at_with_buf(key, next, now, page_buf);
+                return self
+                    .get_from_chain_starting_at_with_buf(key, next, now, page_buf, hot_head);
             }
 
             // Валидная запись: inline либо OVERFLOW placeholder
@@ -334,7 +430,7 @@ impl Db {
         }
 
         // Если запись не прочиталась по оффсету — fallback
-        self.get_from_chain_starting_at_with_buf(key, loc.pid, now, page_buf)
+        self.get_from_chain_starting_at_with_buf(key, loc.pid, now, page_buf, hot_head)
     }
 
     /// Линейный обход цепочки от произвольного pid с TTL/tombstone семантикой.
@@ -344,12 +440,14 @@ impl Db {
         mut pid: u64,
         now: u32,
         page_buf: &mut [u8],
+        hot_head: Option<u64>,
     ) -> Result<Option<Vec<u8>>> {
         let ps = self.pager.meta.page_size as usize;
         debug_assert_eq!(page_buf.len(), ps);
+        let mut ra = ReadaheadWindow::new();
 
         while pid != NO_PAGE {
-            self.pager.read_page(pid, page_buf)?;
+            self.pager.read_page_ra(pid, page_buf, &mut ra)?;
             if &page_buf[0..4] != PAGE_MAGIC {
                 break;
             }
@@ -361,7 +459,11 @@ impl Db {
             let h = kv_header_read_v3(page_buf)?;
             let next = h.next_page_id;
 
-            match decide_value_on_page(page_buf, key, now) {
+            let decision = decide_value_on_page(page_buf, key, now);
+            if let (Some(head), false) = (hot_head, matches!(decision, DecideOnPage::Continue)) {
+                self.hot_get_fill(key, head, pid, page_buf, now);
+            }
+            match decision {
                 DecideOnPage::Tombstone => return Ok(None),
                 DecideOnPage::Valid(v) => return Ok(Some(v)),
                 DecideOnPage::NeedOverflow {
@@ -403,13 +505,8 @@ impl Db {
 
 // ----------------- TLV placeholder (OVF_CHAIN) -----------------
 
-fn make_ovf_placeholder_v3(total_len: u64, head_pid: u64) -> Vec<u8> {
-    let mut out = vec![0u8; 1 + 1 + 16];
-    out[0] = 0x01;
-    out[1] = 16;
-    LittleEndian::write_u64(&mut out[2..10], total_len);
-    LittleEndian::write_u64(&mut out[10..18], head_pid);
-    out
+pub(crate) fn make_ovf_placeholder_v3(total_len: u64, head_pid: u64) -> Vec<u8> {
+    crate::util::encode_ovf_placeholder_v3(total_len, head_pid)
 }
 
 fn compress_zstd(bytes: &[u8]) -> Result<Vec<u8>> {
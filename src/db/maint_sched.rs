@@ -0,0 +1,388 @@
+//! db/maint_sched — планировщик обслуживания (`quiverdb maint-daemon`).
+//!
+//! Задачи планировщика (каждая со своим интервалом, 0 — выключена):
+//! - auto-maintenance: Db::auto_maintenance(max_buckets, sweep);
+//! - snapshot: persisted-снапшот (SnapshotManager, с образом bloom.bin), метка "scheduled",
+//!   parent — предыдущий плановый снапшот. Интервал/retention — QuiverConfig::snapshot_interval_secs
+//!   и QuiverConfig::snapshot_retention (ENV P1_SNAPSHOT_INTERVAL_SECS / P1_SNAPSHOT_RETENTION).
+//! - prune: после каждого планового снапшота хранятся только N новейших плановых снапшотов;
+//!   снапшоты, созданные вручную (без метки "scheduled"), retention не трогает.
+//!
+//! Время последнего планового снапшота берётся из манифестов (created_unix_ms), поэтому
+//! перезапуск демона не создаёт внеочередной снапшот.
+//!
+//! NEW: offsite-бэкапы — MaintScheduler::with_backup(BackupScheduler) (см. backup_sched.rs)
+//! выполняются на тех же тиках; итоги — в MaintTickReport::offsite и в том же audit log.
+//!
+//! Итог каждой задачи пишется в метрики (maint_*) и JSON-строкой в audit log
+//! <root>/.maint_audit.jsonl (MaintAuditEntry). Ошибка задачи не останавливает демон:
+//! она попадает в отчёт тика, метрики и audit log.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::backup_sched::{BackupScheduler, BackupTickReport};
+use crate::config::QuiverConfig;
+use crate::metrics::{
+    record_maint_run, record_maint_snapshot_created, record_maint_snapshot_failed,
+    record_maint_snapshots_pruned,
+};
+use crate::snapstore::{list_manifests, read_manifest, SnapshotManager};
+
+use super::core::Db;
+
+pub const MAINT_AUDIT_FILE: &str = ".maint_audit.jsonl";
+
+/// Метка плановых снапшотов (только они подпадают под retention).
+pub const SCHEDULED_SNAPSHOT_LABEL: &str = "scheduled";
+
+#[inline]
+pub fn maint_audit_path(root: &Path) -> PathBuf {
+    root.join(MAINT_AUDIT_FILE)
+}
+
+/// Расписание задач обслуживания.
+#[derive(Debug, Clone)]
+pub struct MaintSchedule {
+    /// Период auto-maintenance (0 — выключено).
+    pub maint_interval_secs: u64,
+    /// Сколько непустых бакетов компактировать за запуск.
+    pub max_buckets: u32,
+    /// Sweep сиротских OVERFLOW после компактации.
+    pub sweep: bool,
+    /// Период плановых снапшотов (0 — выключено).
+    pub snapshot_interval_secs: u64,
+    /// Сколько плановых снапшотов хранить (0 — все).
+    pub snapshot_retention: u32,
+}
+
+impl Default for MaintSchedule {
+    fn default() -> Self {
+        Self {
+            maint_interval_secs: 0,
+            max_buckets: 32,
+            sweep: false,
+            snapshot_interval_secs: 0,
+            snapshot_retention: 0,
+        }
+    }
+}
+
+impl MaintSchedule {
+    /// Снапшотная часть расписания из конфига (snapshot_interval_secs/snapshot_retention).
+    pub fn from_config(cfg: &QuiverConfig) -> Self {
+        Self {
+            snapshot_interval_secs: cfg.snapshot_interval_secs,
+            snapshot_retention: cfg.snapshot_retention,
+            ..Self::default()
+        }
+    }
+
+    /// Без auto-maintenance и снапшотов (offsite-бэкапы подключаются отдельно, with_backup).
+    pub fn is_empty(&self) -> bool {
+        self.maint_interval_secs == 0 && self.snapshot_interval_secs == 0
+    }
+}
+
+/// Запись audit log (<root>/.maint_audit.jsonl).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintAuditEntry {
+    /// Unix-время (сек).
+    pub ts: u64,
+    /// "auto_maint" | "snapshot" | "snapshot_prune" | "backup" | "backup_prune" | "restore_test".
+    pub task: String,
+    pub ok: bool,
+    /// Id снапшота или имя offsite-бэкапа.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_id: Option<String>,
+    pub detail: String,
+}
+
+/// Итог одного тика планировщика.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MaintTickReport {
+    /// auto-maintenance: (buckets_compacted, overflow_pages_freed), если запускалась успешно.
+    pub auto_maint: Option<(u32, usize)>,
+    /// Id созданного планового снапшота.
+    pub snapshot_created: Option<String>,
+    /// Id снапшотов, удалённых по retention.
+    pub snapshots_pruned: Vec<String>,
+    /// Offsite-бэкапы (backup_sched), если планировщик подключён и что-то выполнял.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offsite: Option<BackupTickReport>,
+    /// Ошибки задач (задача: ошибка).
+    pub errors: Vec<String>,
+}
+
+/// Планировщик: хранит время последних запусков и выполняет созревшие задачи.
+pub struct MaintScheduler {
+    sched: MaintSchedule,
+    last_maint_ms: Option<u64>,
+    /// Последний плановый снапшот (parent следующего).
+    last_snapshot_id: Option<String>,
+    /// Время последней попытки снапшота (успешной или нет).
+    last_snapshot_ms: Option<u64>,
+    /// NEW: offsite-бэкапы (backup_sched::BackupScheduler).
+    backup: Option<BackupScheduler>,
+}
+
+impl MaintScheduler {
+    /// Создать планировщик; последний плановый снапшот ищется в манифестах `root`.
+    pub fn new(root: &Path, sched: MaintSchedule) -> Result<Self> {
+        let last = scheduled_snapshots(root)?.pop();
+        Ok(Self {
+            sched,
+            last_maint_ms: None,
+            last_snapshot_ms: last.as_ref().map(|(_, ms)| *ms),
+            last_snapshot_id: last.map(|(id, _)| id),
+            backup: None,
+        })
+    }
+
+    /// Подключить планировщик offsite-бэкапов (выполняется на тех же тиках).
+    pub fn with_backup(mut self, backup: BackupScheduler) -> Self {
+        self.backup = Some(backup);
+        self
+    }
+
+    pub fn backup(&self) -> Option<&BackupScheduler> {
+        self.backup.as_ref()
+    }
+
+    pub fn schedule(&self) -> &MaintSchedule {
+        &self.sched
+    }
+
+    /// Выполнить созревшие задачи (текущее время).
+    pub fn tick(&mut self, db: &mut Db) -> Result<MaintTickReport> {
+        self.tick_at(db, now_unix_ms())
+    }
+
+    /// Выполнить задачи, созревшие к моменту `now_ms` (Unix ms).
+    /// Err — только для read-only хэндла; сбои задач возвращаются в отчёте.
+    pub fn tick_at(&mut self, db: &mut Db, now_ms: u64) -> Result<MaintTickReport> {
+        db.pager.ensure_writable("maintenance scheduler")?;
+        let mut rep = MaintTickReport::default();
+
+        if due(self.last_maint_ms, self.sched.maint_interval_secs, now_ms) {
+            self.last_maint_ms = Some(now_ms);
+            record_maint_run();
+            match db.auto_maintenance(self.sched.max_buckets, self.sched.sweep) {
+                Ok(s) => {
+                    rep.auto_maint = Some((s.buckets_compacted, s.overflow_pages_freed));
+                    audit(
+                        db,
+                        "auto_maint",
+                        true,
+                        None,
+                        format!(
+                            "buckets_scanned={} buckets_compacted={} pages_written={} overflow_freed={}",
+                            s.buckets_scanned,
+                            s.buckets_compacted,
+                            s.pages_written_sum,
+                            s.overflow_pages_freed
+                        ),
+                    );
+                }
+                Err(e) => {
+                    rep.errors.push(format!("auto_maint: {:#}", e));
+                    audit(db, "auto_maint", false, None, format!("{:#}", e));
+                }
+            }
+        }
+
+        if due(
+            self.last_snapshot_ms,
+            self.sched.snapshot_interval_secs,
+            now_ms,
+        ) {
+            self.run_snapshot(db, now_ms, &mut rep);
+        }
+
+        if let Some(b) = self.backup.as_mut() {
+            let br = b.tick_at(db, now_ms);
+            rep.errors.extend(br.errors.iter().cloned());
+            if br.backup.is_some() || br.restore_test.is_some() || !br.errors.is_empty() {
+                rep.offsite = Some(br);
+            }
+        }
+
+        Ok(rep)
+    }
+
+    /// Через сколько созреет ближайшая задача (None — расписание пустое).
+    pub fn next_due_in(&self, now_ms: u64) -> Option<Duration> {
+        let next = |last: Option<u64>, every_secs: u64| -> Option<u64> {
+            (every_secs > 0).then(|| {
+                last.map(|l| (l + every_secs * 1000).saturating_sub(now_ms))
+                    .unwrap_or(0)
+            })
+        };
+        let a = next(self.last_maint_ms, self.sched.maint_interval_secs);
+        let b = next(self.last_snapshot_ms, self.sched.snapshot_interval_secs);
+        let c = self.backup.as_ref().and_then(|b| b.next_due_in_ms(now_ms));
+        [a, b, c]
+            .into_iter()
+            .flatten()
+            .min()
+            .map(Duration::from_millis)
+    }
+
+    fn run_snapshot(&mut self, db: &Db, now_ms: u64, rep: &mut MaintTickReport) {
+        // повтор после сбоя — через интервал, а не на каждом тике
+        self.last_snapshot_ms = Some(now_ms);
+        let parent = self.last_snapshot_id.clone();
+        let msg = "scheduled by maint-daemon";
+        match SnapshotManager::create_persisted_with_sidecars(
+            db,
+            Some(msg),
+            &[SCHEDULED_SNAPSHOT_LABEL],
+            parent.as_deref(),
+        ) {
+            Ok(id) => {
+                record_maint_snapshot_created();
+                audit(
+                    db,
+                    "snapshot",
+                    true,
+                    Some(id.clone()),
+                    format!("lsn={}", db.pager.meta.last_lsn),
+                );
+                self.last_snapshot_id = Some(id.clone());
+                rep.snapshot_created = Some(id);
+            }
+            Err(e) => {
+                record_maint_snapshot_failed();
+                rep.errors.push(format!("snapshot: {:#}", e));
+                audit(db, "snapshot", false, None, format!("{:#}", e));
+                return;
+            }
+        }
+
+        match prune_scheduled_snapshots(&db.root, self.sched.snapshot_retention) {
+            Ok(pruned) => {
+                if !pruned.is_empty() {
+                    record_maint_snapshots_pruned(pruned.len() as u64);
+                }
+                for id in &pruned {
+                    audit(
+                        db,
+                        "snapshot_prune",
+                        true,
+                        Some(id.clone()),
+                        format!("retention={}", self.sched.snapshot_retention),
+                    );
+                }
+                rep.snapshots_pruned = pruned;
+            }
+            Err(e) => {
+                rep.errors.push(format!("snapshot_prune: {:#}", e));
+                audit(db, "snapshot_prune", false, None, format!("{:#}", e));
+            }
+        }
+    }
+}
+
+/// Плановые снапшоты (метка "scheduled"): (id, created_unix_ms), старые → новые.
+pub fn scheduled_snapshots(root: &Path) -> Result<Vec<(String, u64)>> {
+    let mut out = Vec::new();
+    for id in list_manifests(root)? {
+        let m = read_manifest(root, &id).with_context(|| format!("read manifest {}", id))?;
+        if m.meta.labels.iter().any(|l| l == SCHEDULED_SNAPSHOT_LABEL) {
+            out.push((m.meta.created_unix_ms, m.meta.lsn, id));
+        }
+    }
+    // одна миллисекунда у соседних снапшотов — порядок по LSN
+    out.sort();
+    Ok(out.into_iter().map(|(ms, _, id)| (id, ms)).collect())
+}
+
+/// Удалить плановые снапшоты сверх `keep` новейших (0 — хранить все). Возвращает удалённые id.
+pub fn prune_scheduled_snapshots(root: &Path, keep: u32) -> Result<Vec<String>> {
+    if keep == 0 {
+        return Ok(Vec::new());
+    }
+    let all = scheduled_snapshots(root)?;
+    let excess = all.len().saturating_sub(keep as usize);
+    let mut pruned = Vec::with_capacity(excess);
+    for (id, _) in all.into_iter().take(excess) {
+        SnapshotManager::delete_persisted(root, &id)
+            .with_context(|| format!("delete scheduled snapshot {}", id))?;
+        pruned.push(id);
+    }
+    Ok(pruned)
+}
+
+/// Прочитать audit log (нет файла — пусто; битые строки пропускаются).
+pub fn read_maint_audit(root: &Path) -> Result<Vec<MaintAuditEntry>> {
+    let p = maint_audit_path(root);
+    let f = match std::fs::File::open(&p) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("open {}", p.display())),
+    };
+    let mut out = Vec::new();
+    for line in BufReader::new(f).lines() {
+        let line = line?;
+        if let Ok(e) = serde_json::from_str::<MaintAuditEntry>(&line) {
+            out.push(e);
+        }
+    }
+    Ok(out)
+}
+
+// -------------------- helpers --------------------
+
+#[inline]
+fn due(last_ms: Option<u64>, every_secs: u64, now_ms: u64) -> bool {
+    every_secs > 0
+        && last_ms
+            .map(|l| now_ms >= l.saturating_add(every_secs.saturating_mul(1000)))
+            .unwrap_or(true)
+}
+
+/// Дописать запись в audit log (best-effort: сбой записи — [WARN], задача не падает).
+fn audit(db: &Db, task: &str, ok: bool, snapshot_id: Option<String>, detail: String) {
+    if let Err(err) = append_maint_audit(&db.root, task, ok, snapshot_id, detail) {
+        eprintln!("[WARN] maint audit log: {:#}", err);
+    }
+}
+
+/// Дописать запись в <root>/.maint_audit.jsonl (используется и backup_sched).
+pub fn append_maint_audit(
+    root: &Path,
+    task: &str,
+    ok: bool,
+    snapshot_id: Option<String>,
+    detail: String,
+) -> Result<()> {
+    let e = MaintAuditEntry {
+        ts: now_unix_ms() / 1000,
+        task: task.to_string(),
+        ok,
+        snapshot_id,
+        detail,
+    };
+    let p = maint_audit_path(root);
+    let mut f = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&p)
+        .with_context(|| format!("open {}", p.display()))?;
+    let mut line = serde_json::to_vec(&e)?;
+    line.push(b'\n');
+    f.write_all(&line)?;
+    let _ = f.sync_all();
+    Ok(())
+}
+
+fn now_unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
@@ -8,9 +8,15 @@
 //!   (tail‑wins без tombstone/expired) и, опционально, sweep сиротских OVERFLOW.
 //! - NEW: Lazy compaction — Db::lazy_compact_bucket_if_needed(bucket) запускает компактацию
 //!   бакета, если длина его цепочки ≥ порога (ENV P1_LAZY_COMPACT_THRESHOLD, по умолчанию 64).
+//! - NEW: Db::trim_free_pages(): punch hole для всех страниц free-листа (pager/trim.rs); при
+//!   QuiverConfig::punch_holes sweep и free_page делают это сразу для каждой освобождённой страницы.
+//! - NEW: sweep не освобождает OVERFLOW цепочек, закреплённых курсорами Db::scan_page (db/scan_page.rs).
+//! - NEW: ...и цепочек создаваемых persisted-снапшотов (реестр snapstore/pins.rs, в том числе из
+//!   другого процесса); пока снапшот снимает головы, sweep пропускается (Db::maint_pinned_pages).
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use byteorder::{ByteOrder, LittleEndian};
+use serde::Serialize;
 use std::collections::HashSet;
 use std::sync::OnceLock;
 
@@ -24,12 +30,34 @@ use crate::page::kv::kv_for_each_record;
 use crate::util::decode_ovf_placeholder_v3;
 // NEW: единый guard‑лимит для длины OVERFLOW‑цепочек
 use crate::page::ovf::chain::OVF_MAX_CHAIN_PAGES_GUARD;
+use crate::pager::TrimReport;
+use crate::snapstore::pins::active_snapshot_pins;
 
 use super::core::Db;
 // отчёты компактора
 use super::compaction::CompactBucketReport;
 
+/// Страницы, которые обслуживание не возвращает в free-лист (Db::maint_pinned_pages).
+pub(crate) enum PinnedPages {
+    /// Цепочки курсоров scan_page и создаваемых снапшотов.
+    Pages(HashSet<u64>),
+    /// Снапшот (id) ещё снимает головы — освобождать нельзя ничего.
+    SnapshotPending(String),
+}
+
 impl Db {
+    /// Закреплённые страницы: курсоры scan_page (in-process) и реестр создаваемых снапшотов.
+    pub(crate) fn maint_pinned_pages(&self) -> Result<PinnedPages> {
+        let mut heads = self.scan_pins.live_heads();
+        for pin in active_snapshot_pins(&self.root)? {
+            match pin.heads {
+                Some(h) => heads.extend(h),
+                None => return Ok(PinnedPages::SnapshotPending(pin.id)),
+            }
+        }
+        Ok(PinnedPages::Pages(self.pages_reachable_from(heads)))
+    }
+
     /// Печать сводки по БД. JSON-вариант включается ENV P1_DBSTATS_JSON=1|true|yes|on.
     pub fn print_stats(&self) -> Result<()> {
         let json = std::env::var("P1_DBSTATS_JSON")
@@ -194,14 +222,24 @@ impl Db {
     /// Writer‑операция: собрать и освободить "сиротские" OVERFLOW3 страницы.
     /// Возвращает число освобождённых страниц.
     pub fn sweep_orphan_overflow(&mut self) -> Result<usize> {
-        if self.readonly {
-            return Err(anyhow!("sweep_orphan_overflow: Db is read-only"));
-        }
+        self.pager.ensure_writable("sweep_orphan_overflow")?;
         metrics::record_sweep_orphan_run();
 
         let ps = self.pager.meta.page_size as usize;
         let total_pages = self.pager.meta.next_page_id;
 
+        // NEW: страницы цепочек курсоров scan_page и создаваемых снапшотов не трогаем
+        let pinned = match self.maint_pinned_pages()? {
+            PinnedPages::Pages(p) => p,
+            PinnedPages::SnapshotPending(id) => {
+                eprintln!(
+                    "[INFO] sweep_orphan_overflow skipped: snapshot {} is pinning its heads",
+                    id
+                );
+                return Ok(0);
+            }
+        };
+
         // 1) Сбор "помеченных" overflow страниц, достижимых из KV цепочек (по placeholder’ам).
         let mut marked: HashSet<u64> = HashSet::new();
 
@@ -253,6 +291,7 @@ impl Db {
 
         // 2) Обход всех страниц: если это OVERFLOW3 и не помечена — освобождаем.
         let mut freed = 0usize;
+        let mut kept = 0u64;
 
         for pid in 0..total_pages {
             if marked.contains(&pid) {
@@ -262,15 +301,31 @@ impl Db {
             if self.pager.read_page(pid, &mut buf).is_ok() {
                 if let Ok(h) = ovf_header_read_v3(&buf) {
                     let _ = h; // факт, что это OVF‑страница
+                    if pinned.contains(&pid) {
+                        kept += 1;
+                        continue;
+                    }
                     self.pager.free_page(pid)?;
                     freed += 1;
                 }
             }
         }
 
+        metrics::record_maint_pinned_pages_kept(kept);
         Ok(freed)
     }
 
+    /// Writer‑операция: вернуть ФС блоки всех страниц free‑листа (punch hole).
+    /// Работает независимо от QuiverConfig::punch_holes; без поддержки ФС — supported=false.
+    pub fn trim_free_pages(&mut self) -> Result<TrimReport> {
+        self.pager.ensure_writable("trim_free_pages")?;
+        let pids = match FreeList::open(&self.root) {
+            Ok(fl) => fl.list()?,
+            Err(_) => Vec::new(),
+        };
+        self.pager.punch_pages(&pids)
+    }
+
     // -------------------- helpers (методы Db) --------------------
 
     /// Статистика по длинам цепочек (head→tail) среди непустых bucket’ов.
@@ -366,9 +421,8 @@ impl Db {
         &mut self,
         bucket: u32,
     ) -> Result<Option<CompactBucketReport>> {
-        if self.readonly {
-            return Err(anyhow!("lazy_compact_bucket_if_needed: Db is read-only"));
-        }
+        self.pager
+            .ensure_writable("lazy_compact_bucket_if_needed")?;
         let threshold = lazy_compact_threshold();
         if threshold == 0 {
             return Ok(None);
@@ -391,7 +445,7 @@ impl Db {
 // -------------------- helpers (статические) --------------------
 
 /// Прочитать next_page_id из OVERFLOW3 страницы pid, игнорируя ошибки (NO_PAGE при ошибке).
-fn read_ovf_next_pid_silent(db: &Db, pid: u64, ps: usize) -> Result<u64> {
+pub(super) fn read_ovf_next_pid_silent(db: &Db, pid: u64, ps: usize) -> Result<u64> {
     let mut page = vec![0u8; ps];
     db.pager.read_page(pid, &mut page)?;
     let h = ovf_header_read_v3(&page)?;
@@ -410,7 +464,7 @@ fn free_pages_count(root: &std::path::Path) -> Result<u64> {
 // -------------------- NEW: авто‑обслуживание --------------------
 
 /// Сводка авто‑обслуживания: компактация части бакетов и sweep сиротских OVERFLOW.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct AutoMaintSummary {
     /// Сколько непустых бакетов было отсканировано (head != NO_PAGE).
     pub buckets_scanned: u32,
@@ -432,9 +486,7 @@ impl Db {
         max_buckets: u32,
         do_sweep: bool,
     ) -> Result<AutoMaintSummary> {
-        if self.readonly {
-            return Err(anyhow!("auto_maintenance: Db is read-only"));
-        }
+        self.pager.ensure_writable("auto_maintenance")?;
 
         let mut scanned = 0u32;
         let mut compacted = 0u32;
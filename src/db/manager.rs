@@ -0,0 +1,437 @@
+//! db/manager — несколько БД в одном процессе с общими ресурсами (DbManager).
+//!
+//! Page cache, bloom cache и value cache в QuiverDB процесс-широкие (ключ — db_id), но каждый
+//! Db::open_with_config заново конфигурирует page cache под свой QuiverConfig: при десятках
+//! per-tenant БД последний open задаёт ёмкость всем, а одна «шумная» БД вытесняет страницы
+//! остальных. DbManager:
+//! - один раз конфигурирует общие кэши (ManagerConfig::page_cache_pages / bloom_cache_buckets);
+//!   хэндлы открываются с page_cache_pages=0 и кэш не трогают;
+//! - задаёт квоту per-DB (DbQuota::page_cache_pages → page_cache_set_quota): БД на квоте
+//!   вытесняет свои страницы, а не чужие;
+//! - требует одинаковый page_size у всех БД (общий кэш сбрасывается при смене геометрии);
+//! - выдаёт хэндлы по имени (ManagedDb = Arc<Mutex<Db>>);
+//! - bulk-операции open_all / open_all_in / close_all выполняются пулом из ManagerConfig::workers
+//!   потоков (open — реплей WAL, close — усечение WAL и запись meta), stats — агрегат DbStats.
+//!
+//! close(name) убирает хэндл из менеджера и снимает квоту; сама БД закрывается (Drop), когда
+//! отпущен последний клон ManagedDb.
+
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::bloom::{bloom_cache_configure, bloom_cache_stats};
+use crate::config::QuiverConfig;
+use crate::meta::read_meta;
+use crate::pager::cache::{
+    page_cache_capacity, page_cache_db_len, page_cache_len, page_cache_set_quota,
+};
+use crate::pager::io::page_cache_configure;
+
+use super::core::Db;
+use super::stats::DbStats;
+
+/// Хэндл БД, выданный менеджером.
+pub type ManagedDb = Arc<Mutex<Db>>;
+
+/// Квоты одной БД в общих ресурсах.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DbQuota {
+    /// Максимум страниц БД в общем page cache (None — без отдельного лимита).
+    pub page_cache_pages: Option<usize>,
+}
+
+/// Настройки менеджера.
+#[derive(Clone, Debug)]
+pub struct ManagerConfig {
+    /// Шаблон конфигурации хэндлов (page_cache_pages в нём игнорируется).
+    pub base: QuiverConfig,
+    /// Ёмкость общего page cache (страниц; 0 — выключен).
+    pub page_cache_pages: usize,
+    /// Ёмкость общего bloom cache (бакетов); None — оставить как есть (ENV P1_BLOOM_CACHE_BUCKETS).
+    pub bloom_cache_buckets: Option<usize>,
+    /// Квота по умолчанию для open/open_ro/open_all.
+    pub default_quota: DbQuota,
+    /// Потоков для bulk-операций (минимум 1).
+    pub workers: usize,
+}
+
+impl Default for ManagerConfig {
+    fn default() -> Self {
+        Self::from_config(QuiverConfig::default())
+    }
+}
+
+impl ManagerConfig {
+    /// Шаблон из ENV (QuiverConfig::from_env); ёмкость page cache — из него же.
+    pub fn from_env() -> Self {
+        Self::from_config(QuiverConfig::from_env())
+    }
+
+    pub fn from_config(base: QuiverConfig) -> Self {
+        let workers = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(4);
+        Self {
+            page_cache_pages: base.page_cache_pages,
+            base,
+            bloom_cache_buckets: None,
+            default_quota: DbQuota::default(),
+            workers,
+        }
+    }
+
+    pub fn with_page_cache_pages(mut self, pages: usize) -> Self {
+        self.page_cache_pages = pages;
+        self
+    }
+
+    pub fn with_bloom_cache_buckets(mut self, buckets: usize) -> Self {
+        self.bloom_cache_buckets = Some(buckets);
+        self
+    }
+
+    pub fn with_default_quota(mut self, quota: DbQuota) -> Self {
+        self.default_quota = quota;
+        self
+    }
+
+    pub fn with_workers(mut self, n: usize) -> Self {
+        self.workers = n.max(1);
+        self
+    }
+}
+
+/// Итог open_all / open_all_in.
+#[derive(Debug, Clone, Default)]
+pub struct OpenAllReport {
+    pub opened: Vec<String>,
+    /// (имя, ошибка)
+    pub failed: Vec<(String, String)>,
+}
+
+/// Состояние одной БД в ManagerStats.
+#[derive(Debug, Clone)]
+pub struct ManagedDbStats {
+    pub name: String,
+    pub root: PathBuf,
+    pub read_only: bool,
+    /// Страниц БД в общем page cache сейчас.
+    pub page_cache_pages: usize,
+    pub quota: DbQuota,
+    pub stats: DbStats,
+}
+
+/// Агрегированная статистика менеджера.
+#[derive(Debug, Clone, Default)]
+pub struct ManagerStats {
+    pub dbs: Vec<ManagedDbStats>,
+    /// Сумма per-handle счётчиков всех БД.
+    pub total: DbStats,
+    pub page_cache_capacity: usize,
+    pub page_cache_len: usize,
+    pub bloom_cache_capacity: usize,
+    pub bloom_cache_len: usize,
+}
+
+struct Entry {
+    root: PathBuf,
+    read_only: bool,
+    quota: DbQuota,
+    db_id: u64,
+    db: ManagedDb,
+}
+
+#[derive(Default)]
+struct Inner {
+    dbs: BTreeMap<String, Entry>,
+    /// page_size общего page cache (задаётся первой открытой БД).
+    page_size: Option<u32>,
+}
+
+/// Менеджер БД процесса с общими кэшами и квотами (см. заголовок модуля).
+pub struct DbManager {
+    cfg: ManagerConfig,
+    inner: Mutex<Inner>,
+}
+
+impl DbManager {
+    pub fn new(cfg: ManagerConfig) -> Self {
+        if let Some(n) = cfg.bloom_cache_buckets {
+            bloom_cache_configure(n);
+        }
+        Self {
+            cfg,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    pub fn config(&self) -> &ManagerConfig {
+        &self.cfg
+    }
+
+    /// Открыть writer под именем `name` (квота по умолчанию).
+    pub fn open(&self, name: &str, root: &Path) -> Result<ManagedDb> {
+        self.open_with_quota(name, root, false, self.cfg.default_quota)
+    }
+
+    /// Открыть read-only хэндл под именем `name` (квота по умолчанию).
+    pub fn open_ro(&self, name: &str, root: &Path) -> Result<ManagedDb> {
+        self.open_with_quota(name, root, true, self.cfg.default_quota)
+    }
+
+    /// Открыть БД с явной квотой. Имя уже открыто с тем же корнем и режимом — возвращается
+    /// прежний хэндл; с другим корнем/режимом — ошибка.
+    pub fn open_with_quota(
+        &self,
+        name: &str,
+        root: &Path,
+        read_only: bool,
+        quota: DbQuota,
+    ) -> Result<ManagedDb> {
+        let page_size = read_meta(root)
+            .with_context(|| format!("read meta at {}", root.display()))?
+            .page_size;
+        {
+            let inner = self.inner.lock().unwrap();
+            if let Some(e) = inner.dbs.get(name) {
+                if e.root == root && e.read_only == read_only {
+                    return Ok(e.db.clone());
+                }
+                return Err(anyhow!(
+                    "DbManager: '{}' is already open at {} ({})",
+                    name,
+                    e.root.display(),
+                    if e.read_only { "read-only" } else { "writer" }
+                ));
+            }
+            check_page_size(&inner, root, page_size)?;
+        }
+
+        // Открытие (реплей WAL) — вне лока менеджера; общий page cache хэндл не трогает
+        let mut cfg = self.cfg.base.clone();
+        cfg.page_cache_pages = 0;
+        let db = if read_only {
+            Db::open_ro_with_config(root, cfg)
+        } else {
+            Db::open_with_config(root, cfg)
+        }
+        .with_context(|| format!("DbManager: open '{}' at {}", name, root.display()))?;
+        let db_id = db.pager.db_id;
+
+        let mut inner = self.inner.lock().unwrap();
+        if inner.dbs.contains_key(name) {
+            return Err(anyhow!("DbManager: '{}' was opened concurrently", name));
+        }
+        check_page_size(&inner, root, page_size)?;
+        if inner.page_size.is_none() {
+            page_cache_configure(page_size as usize, self.cfg.page_cache_pages);
+            inner.page_size = Some(page_size);
+        }
+        page_cache_set_quota(db_id, quota.page_cache_pages);
+        let db = Arc::new(Mutex::new(db));
+        inner.dbs.insert(
+            name.to_string(),
+            Entry {
+                root: root.to_path_buf(),
+                read_only,
+                quota,
+                db_id,
+                db: db.clone(),
+            },
+        );
+        Ok(db)
+    }
+
+    /// Хэндл по имени.
+    pub fn get(&self, name: &str) -> Option<ManagedDb> {
+        self.inner
+            .lock()
+            .unwrap()
+            .dbs
+            .get(name)
+            .map(|e| e.db.clone())
+    }
+
+    /// Имена открытых БД (по возрастанию).
+    pub fn names(&self) -> Vec<String> {
+        self.inner.lock().unwrap().dbs.keys().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().dbs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Сменить квоту открытой БД (лишние страницы вытесняются сразу).
+    pub fn set_quota(&self, name: &str, quota: DbQuota) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        let e = inner
+            .dbs
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("DbManager: '{}' is not open", name))?;
+        e.quota = quota;
+        page_cache_set_quota(e.db_id, quota.page_cache_pages);
+        Ok(())
+    }
+
+    /// Убрать БД из менеджера (false — не была открыта). Db закрывается, когда отпущен
+    /// последний клон хэндла.
+    pub fn close(&self, name: &str) -> bool {
+        let e = self.inner.lock().unwrap().dbs.remove(name);
+        match e {
+            Some(e) => {
+                page_cache_set_quota(e.db_id, None);
+                drop(e);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Открыть набор БД (имя, корень) пулом потоков. Ошибки отдельных БД — в отчёте.
+    pub fn open_all(&self, dbs: &[(String, PathBuf)], read_only: bool) -> OpenAllReport {
+        let results = self.run_pool(dbs, |(name, root)| {
+            self.open_with_quota(name, root, read_only, self.cfg.default_quota)
+                .map(|_| ())
+        });
+        let mut rep = OpenAllReport::default();
+        for ((name, _), r) in dbs.iter().zip(results) {
+            match r {
+                Ok(()) => rep.opened.push(name.clone()),
+                Err(e) => rep.failed.push((name.clone(), format!("{:#}", e))),
+            }
+        }
+        rep
+    }
+
+    /// Открыть все БД-подкаталоги `parent` (с файлом meta); имя — имя подкаталога.
+    pub fn open_all_in(&self, parent: &Path, read_only: bool) -> Result<OpenAllReport> {
+        let mut dbs = Vec::new();
+        for e in
+            std::fs::read_dir(parent).with_context(|| format!("read_dir {}", parent.display()))?
+        {
+            let e = e?;
+            let p = e.path();
+            if p.is_dir() && p.join("meta").is_file() {
+                dbs.push((e.file_name().to_string_lossy().into_owned(), p));
+            }
+        }
+        dbs.sort();
+        Ok(self.open_all(&dbs, read_only))
+    }
+
+    /// Закрыть все БД (Drop хэндлов — пулом потоков). Возвращает число убранных БД.
+    pub fn close_all(&self) -> usize {
+        let entries: Vec<Entry> = {
+            let mut inner = self.inner.lock().unwrap();
+            std::mem::take(&mut inner.dbs).into_values().collect()
+        };
+        let n = entries.len();
+        for e in &entries {
+            page_cache_set_quota(e.db_id, None);
+        }
+        let slots: Vec<Mutex<Option<Entry>>> =
+            entries.into_iter().map(|e| Mutex::new(Some(e))).collect();
+        self.run_pool(&slots, |slot| {
+            drop(slot.lock().unwrap().take());
+            Ok(())
+        });
+        n
+    }
+
+    /// Статистика по всем БД и общим кэшам.
+    pub fn stats(&self) -> ManagerStats {
+        let snapshot: Vec<(String, PathBuf, bool, DbQuota, u64, ManagedDb)> = {
+            let inner = self.inner.lock().unwrap();
+            inner
+                .dbs
+                .iter()
+                .map(|(n, e)| {
+                    (
+                        n.clone(),
+                        e.root.clone(),
+                        e.read_only,
+                        e.quota,
+                        e.db_id,
+                        e.db.clone(),
+                    )
+                })
+                .collect()
+        };
+        let (bloom_cap, bloom_len) = bloom_cache_stats();
+        let mut out = ManagerStats {
+            page_cache_capacity: page_cache_capacity(),
+            page_cache_len: page_cache_len(),
+            bloom_cache_capacity: bloom_cap,
+            bloom_cache_len: bloom_len,
+            ..Default::default()
+        };
+        for (name, root, read_only, quota, db_id, db) in snapshot {
+            let stats = db.lock().unwrap().stats();
+            out.total += stats;
+            out.dbs.push(ManagedDbStats {
+                name,
+                root,
+                read_only,
+                page_cache_pages: page_cache_db_len(db_id),
+                quota,
+                stats,
+            });
+        }
+        out
+    }
+
+    /// Выполнить f для каждого элемента пулом из cfg.workers потоков (порядок результатов — входной).
+    fn run_pool<T: Sync, F>(&self, items: &[T], f: F) -> Vec<Result<()>>
+    where
+        F: Fn(&T) -> Result<()> + Sync,
+    {
+        let workers = self.cfg.workers.max(1).min(items.len().max(1));
+        let next = std::sync::atomic::AtomicUsize::new(0);
+        let results: Vec<Mutex<Option<Result<()>>>> =
+            items.iter().map(|_| Mutex::new(None)).collect();
+        std::thread::scope(|s| {
+            for _ in 0..workers {
+                s.spawn(|| loop {
+                    let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    if i >= items.len() {
+                        break;
+                    }
+                    *results[i].lock().unwrap() = Some(f(&items[i]));
+                });
+            }
+        });
+        results
+            .into_iter()
+            .map(|r| {
+                r.into_inner()
+                    .unwrap()
+                    .unwrap_or_else(|| Err(anyhow!("not run")))
+            })
+            .collect()
+    }
+}
+
+impl Drop for DbManager {
+    fn drop(&mut self) {
+        self.close_all();
+    }
+}
+
+fn check_page_size(inner: &Inner, root: &Path, page_size: u32) -> Result<()> {
+    match inner.page_size {
+        Some(ps) if ps != page_size => Err(anyhow!(
+            "DbManager: {} has page_size {}, the shared page cache uses {}",
+            root.display(),
+            page_size,
+            ps
+        )),
+        _ => Ok(()),
+    }
+}
@@ -10,9 +10,39 @@
 //! - maintenance.rs — обслуживание: sweep orphan overflow, print_stats, doctor-сканер
 //! - doctor.rs      — doctor-скан (CRC/IO) с JSON-отчётом
 //! - compaction.rs  — онлайн-компактация цепочек (bucket/all)
+//! - alloc_check.rs — детектор двойного использования страниц (free-лист vs цепочки) и ремонт
+//! - poison.rs      — panic-safety: отравление хэндла после паники в критической секции
+//!   (DbPoisonedError, Db::reopen)
 //! - vacuum.rs      — вакуум: compaction_all + sweep_orphan_overflow
 //! - read_page.rs   — общие хелперы per‑page чтения (newest→oldest, TTL/tombstone/placeholder)
 //! - multi.rs       — векторные операции get_many/exists_many (новое)
+//! - scrub.rs       — фоновый read-only scrub (CRC/AEAD) с персистентным курсором и алертами
+//! - clone.rs       — live clone: копия файлов + догон WAL-дельты + короткая пауза writer'а
+//! - expiry.rs      — подписка на события истечения TTL (ключи, вычищенные компактацией)
+//! - hotkeys.rs     — выборочный профайлер обращений по префиксам ключей (count-min sketch)
+//! - compaction_filter.rs — пользовательский фильтр записей (drop/rewrite) для compaction/vacuum
+//! - refresh.rs     — live-refresh RO-хэндла (meta/bloom/keydir), Db::refresh
+//! - bulk.rs        — bulk load мимо per-put пути (партиции/spill, прямые страницы, один HEADS_UPDATE)
+//! - torn.rs        — open-time проверка голов каталога и ремонт порванных страниц из образов WAL
+//! - versions.rs    — история версий ключа (Db::get_versions) и их удержание компактацией
+//! - range_del.rs   — удаление по префиксу одним range tombstone (Db::delete_prefix)
+//! - bloom_refresh.rs — авто-обновление bloom.bin по затронутым бакетам (QuiverConfig::bloom_auto_refresh)
+//! - scan_page.rs   — постраничный скан Db::scan_page со стабильным курсором и закреплением цепочек
+//! - stats.rs       — per-handle счётчики (Db::stats) и хуки инструментирования (Db::set_instrumentation)
+//! - maint_sched.rs — планировщик обслуживания (auto-maintenance, плановые снапшоты + retention, audit log)
+//! - freeze.rs      — freeze/thaw writer'а для внешних снапшотов тома (Db::freeze/Db::thaw)
+//! - trash.rs       — trash-режим del() (мягкие tombstone'ы со сроком), Db::undelete
+//! - estimate.rs    — приблизительный счёт ключей под префиксом (keydir / выборка бакетов)
+//! - du.rs          — разбивка занятого места по категориям и бакетам (Db::space_usage)
+//! - manager.rs     — DbManager: много БД в процессе с общими кэшами, квотами и bulk open/close
+//! - digest.rs      — digest живых ключей бакета и дерево digest'ов БД (сверка реплик, anti-entropy)
+//! - resync.rs      — состояние бакета в раскладке источника и замена цепочки follower'а (CDC resync)
+//! - auto_batch.rs  — AutoBatcher: очередь put/del со сбросом батчем по числу операций/байтам/времени
+//! - user_meta.rs   — область метаданных приложения (Db::meta_put/meta_get) в отдельной цепочке страниц
+//! - tde_kids.rs    — число страниц по KID подписи TDE (прогресс ротации, Db::tde_kid_pages)
+//! - value_transform.rs — кодирование значений на пути хранения (ValueTransform, словарь zstd)
+//! - json_path.rs   — проекция JSON-значения по пути (Db::get_json_path, фича "json")
+//! - hot_get.rs     — микро-кэш локаций горячих ключей для get (QuiverConfig::hot_get_cache_entries)
 
 pub mod batch;
 pub mod compaction;
@@ -28,5 +58,96 @@ pub mod vacuum;
 pub mod read_page;
 // NEW: векторные операции (get_many/exists_many)
 pub mod multi;
+// NEW: scrub (read-only проверка трейлеров всех страниц)
+pub mod scrub;
+// NEW: live clone (консистентная копия работающей БД)
+pub mod clone;
+// NEW: события истечения TTL (subscribe_expiry)
+pub mod expiry;
+// NEW: профайлер горячих префиксов ключей (hot_prefixes)
+pub mod hotkeys;
+// NEW: фильтры компактации (set_compaction_filter)
+pub mod compaction_filter;
+// NEW: live-refresh RO-хэндла (Db::refresh)
+pub mod refresh;
+// NEW: bulk load (Db::bulk_load)
+pub mod bulk;
+// NEW: ремонт порванных страниц-голов при открытии (Db::open_repair_report)
+pub mod torn;
+// NEW: история версий ключа (Db::get_versions)
+pub mod versions;
+// NEW: range tombstones (Db::delete_prefix)
+pub mod range_del;
+// NEW: per-handle статистика и хуки инструментирования
+pub mod stats;
+// NEW: авто-обновление bloom.bin (QuiverConfig::bloom_auto_refresh)
+pub mod bloom_refresh;
+// NEW: планировщик обслуживания (quiverdb maint-daemon)
+pub mod maint_sched;
+// NEW: заморозка коммитов для снапшотов тома (Db::freeze)
+pub mod freeze;
+// NEW: корзина удалённых значений (Db::undelete)
+pub mod trash;
+// NEW: оценка числа ключей под префиксом (Db::estimate_count_prefix)
+pub mod estimate;
+// NEW: разбивка занятого места (Db::space_usage, quiverdb du)
+pub mod du;
+// NEW: менеджер нескольких БД с общими ресурсами (DbManager)
+pub mod manager;
+// NEW: digest бакетов для сверки follower'а с leader'ом (CDC verify)
+pub mod digest;
+// NEW: пересинхронизация разошедшегося бакета (CDC resync)
+pub mod resync;
+// NEW: AutoBatcher (батчинг без замыкания Db::batch)
+pub mod auto_batch;
+// NEW: panic-safety (отравленный хэндл)
+pub mod poison;
+// NEW: детектор двойного использования страниц (doctor)
+pub mod alloc_check;
+// NEW: каноникализация ключей на границе Db (KeyTransform)
+pub mod key_transform;
+// NEW: постраничный скан со стабильным курсором (HTTP-пагинация)
+pub mod scan_page;
+// NEW: метаданные приложения вне пространства ключей (Db::meta_put/meta_get)
+pub mod user_meta;
+// NEW: страницы по KID подписи (per-page KID, прогресс ротации)
+pub mod tde_kids;
+// NEW: кодирование значений на пути хранения (ValueTransform)
+pub mod value_transform;
+// NEW: проекция JSON-значений по пути (фича "json")
+#[cfg(feature = "json")]
+pub mod json_path;
+// NEW: кэш локаций горячих ключей для повторных get
+pub mod hot_get;
 
-pub use core::Db;
+pub use alloc_check::{AllocCheckReport, AllocFixReport, CrossLink, FreeDoubleUse, PageOwner};
+pub use auto_batch::{AutoBatchFlush, AutoBatchLimits, AutoBatcher};
+pub use core::{Db, DbLockedError};
+pub use digest::{BucketDigest, DigestTree};
+pub use du::{BucketUsage, SnapstoreUsage, SpaceUsage};
+pub use estimate::CountEstimate;
+pub use freeze::{DbFrozenError, FreezeInfo};
+#[cfg(feature = "json")]
+pub use json_path::{JsonPath, JsonPathSeg};
+pub use key_transform::{
+    builtin_key_transform, HashLongKeys, KeyTransform, KeyTransformMismatchError, KeyTransformRef,
+    LowercaseKeys, StripNamespace,
+};
+pub use manager::{
+    DbManager, DbQuota, ManagedDb, ManagedDbStats, ManagerConfig, ManagerStats, OpenAllReport,
+};
+pub use poison::{DbPoisonedError, PoisonInfo};
+pub use resync::{BucketResyncReport, BucketState};
+// NEW: гейт записи read-only хэндла (Pager::ensure_writable)
+pub use crate::pager::ReadOnlyError;
+pub use maint_sched::{MaintAuditEntry, MaintSchedule, MaintScheduler, MaintTickReport};
+pub use scan::{KeyScanIter, ScanIter};
+pub use scan_page::{ScanCursorInvalidError, ScanPageResult};
+pub use stats::{CommitEvent, DbInstrumentation, DbStats, GetEvent, PutEvent};
+pub use tde_kids::{KidPages, TdeKidUsage};
+pub use trash::TrashEntry;
+pub use user_meta::USER_META_MAX_BYTES;
+pub use value_transform::{
+    builtin_value_transform, train_value_dictionary, ValueDictVersion, ValueTransform,
+    ValueTransformMismatchError, ValueTransformRef, ZstdDictValues, VALUE_DICT_FILE,
+};
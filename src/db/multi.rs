@@ -11,13 +11,24 @@
 //! - TTL read‑side: expires_at_sec == 0 (бессрочно) или now < expires_at_sec.
 //! - Внутристраничный поиск — packed‑aware newest→oldest.
 //! - OVERFLOW placeholder: get_many разворачивает цепочку (с value cache); exists_many — считает “present” без разворота.
+//!
+//! NEW: векторный путь по бакетам для ключей без keydir-локации:
+//! - сначала весь набор проверяется bloom-фильтром (свежий RO-sidecar) — отрицательные сразу None/false;
+//! - оставшиеся ключи группируются по бакету, голова каждого бакета читается один раз;
+//! - цепочка бакета обходится ОДИН раз: на каждой странице решаются все ещё не найденные ключи
+//!   бакета, обход прекращается, когда решены все (или цепочка кончилась).
+//!   Для N ключей одного бакета — не более длины цепочки чтений страниц вместо ~N×длина.
 
 use anyhow::Result;
 use byteorder::{ByteOrder, LittleEndian};
 use std::collections::BTreeMap;
 
-use crate::db::read_page::{decide_value_on_page, DecideOnPage};
+use crate::bloom::BloomSidecar;
+use crate::db::read_page::{
+    decide_exists_on_page, decide_value_on_page, DecideExists, DecideOnPage,
+};
 use crate::dir::NO_PAGE;
+use crate::metrics::{record_bloom_negative, record_bloom_positive, record_bloom_skipped_stale};
 use crate::page::kv::kv_read_record_at_checked;
 use crate::page::ovf::chain as page_ovf_chain;
 use crate::page::{kv_header_read_v3, PAGE_MAGIC, PAGE_TYPE_KV_RH3, TRAILER_LEN};
@@ -29,7 +40,23 @@ use super::core::Db;
 
 impl Db {
     /// Векторный get: семантика как у одиночного get().
-    pub fn get_many<'a>(&self, keys: &[&'a [u8]]) -> Result<Vec<Option<Vec<u8>>>> {
+    pub fn get_many(&self, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>> {
+        let out = match self.canonical_keys(keys) {
+            Some(canon) => {
+                let refs: Vec<&[u8]> = canon.iter().map(Vec::as_slice).collect();
+                self.get_many_canonical(&refs)
+            }
+            None => self.get_many_canonical(keys),
+        }?;
+        if self.value_transform.is_none() {
+            return Ok(out);
+        }
+        out.into_iter()
+            .map(|v| v.map(|v| self.decode_value(v)).transpose())
+            .collect()
+    }
+
+    fn get_many_canonical(&self, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>> {
         let now = now_secs();
         let ps = self.pager.meta.page_size as usize;
 
@@ -45,9 +72,16 @@ impl Db {
         }
 
         let mut by_pid: BTreeMap<u64, Vec<Req>> = BTreeMap::new();
+        let mut by_bucket: BTreeMap<u32, Vec<(usize, &[u8])>> = BTreeMap::new();
         let mut out: Vec<Option<Vec<u8>>> = vec![None; keys.len()];
+        let bloom = self.fresh_bloom_for_multi();
 
         for (i, key) in keys.iter().enumerate() {
+            // Ключ под range tombstone — отдельный обход до страницы маркера
+            if let Some(cut) = self.range_cut(key) {
+                out[i] = self.get_above_range_cut(key, cut, now)?;
+                continue;
+            }
             let bucket = self.dir.bucket_of_key(key, self.pager.meta.hash_kind);
 
             if let Some(loc) = self.mem_keydir_get_loc(bucket, key) {
@@ -63,24 +97,15 @@ impl Db {
                     off: loc.off,
                     _bucket: bucket,
                 });
-            } else {
-                // нет keydir или нет записи — fallback на head
-                let pid = self.dir.head(bucket)?;
-                if pid == NO_PAGE {
-                    out[i] = None;
-                    continue;
-                }
-                by_pid.entry(pid).or_default().push(Req {
-                    idx: i,
-                    key,
-                    _pid: pid,
-                    off: 0,
-                    _bucket: bucket,
-                });
+            } else if bloom_maybe(bloom, bucket, key)? {
+                // NEW: нет keydir-локации — один обход цепочки на бакет (см. ниже)
+                by_bucket.entry(bucket).or_default().push((i, *key));
             }
         }
 
         let mut page_buf = vec![0u8; ps];
+        // Отдельный буфер для обхода цепочки: page_buf должен пережить все запросы группы
+        let mut chain_buf = vec![0u8; ps];
 
         for (pid, reqs) in by_pid.into_iter() {
             // Считываем страницу один раз
@@ -123,7 +148,7 @@ impl Db {
                             if exp != 0 && now >= exp {
                                 // протухло → fallback с хвоста страницы
                                 out[idx] =
-                                    self.scan_chain_get(req.key, next_pid, now, &mut page_buf)?;
+                                    self.scan_chain_get(req.key, next_pid, now, &mut chain_buf)?;
                                 continue;
                             }
                             // Валидно: inline или OVERFLOW placeholder (+ value cache)
@@ -149,32 +174,51 @@ impl Db {
                         head_pid,
                     } => {
                         // value cache
-                        if let Some(cached) = value_cache_get(self.pager.db_id, head_pid, total_len)
-                        {
-                            out[idx] = Some(cached);
-                        } else {
-                            let val = page_ovf_chain::read_overflow_chain(
-                                &self.pager,
-                                head_pid,
-                                total_len,
-                            )?;
-                            value_cache_put(self.pager.db_id, head_pid, total_len, &val);
-                            out[idx] = Some(val);
-                        }
+                        out[idx] = Some(self.read_overflow_cached(head_pid, total_len)?);
                     }
                     DecideOnPage::Continue => {
                         // 3) Fallback: продолжаем цепочку
-                        out[idx] = self.scan_chain_get(req.key, next_pid, now, &mut page_buf)?;
+                        out[idx] = self.scan_chain_get(req.key, next_pid, now, &mut chain_buf)?;
                     }
                 }
             }
         }
 
+        // NEW: по одному обходу цепочки на бакет
+        for (bucket, reqs) in by_bucket {
+            let head = self.dir.head(bucket)?;
+            self.walk_chain_multi(head, reqs, &mut page_buf, &mut out, |db, page, key| {
+                Ok(match decide_value_on_page(page, key, now) {
+                    DecideOnPage::Tombstone => Some(None),
+                    DecideOnPage::Valid(v) => Some(Some(v)),
+                    DecideOnPage::NeedOverflow {
+                        total_len,
+                        head_pid,
+                    } => Some(Some(db.read_overflow_cached(head_pid, total_len)?)),
+                    DecideOnPage::Continue => None,
+                })
+            })?;
+        }
+
+        // NEW: per-handle счётчики (db/stats.rs)
+        for v in &out {
+            self.pager.stats.note_get(v.as_ref().map(|v| v.len()));
+        }
         Ok(out)
     }
 
     /// Векторный exists: семантика как у одиночного exists().
-    pub fn exists_many<'a>(&self, keys: &[&'a [u8]]) -> Result<Vec<bool>> {
+    pub fn exists_many(&self, keys: &[&[u8]]) -> Result<Vec<bool>> {
+        match self.canonical_keys(keys) {
+            Some(canon) => {
+                let refs: Vec<&[u8]> = canon.iter().map(Vec::as_slice).collect();
+                self.exists_many_canonical(&refs)
+            }
+            None => self.exists_many_canonical(keys),
+        }
+    }
+
+    fn exists_many_canonical(&self, keys: &[&[u8]]) -> Result<Vec<bool>> {
         let now = now_secs();
         let ps = self.pager.meta.page_size as usize;
 
@@ -188,9 +232,16 @@ impl Db {
         }
 
         let mut by_pid: BTreeMap<u64, Vec<Req>> = BTreeMap::new();
+        let mut by_bucket: BTreeMap<u32, Vec<(usize, &[u8])>> = BTreeMap::new();
         let mut out: Vec<bool> = vec![false; keys.len()];
+        let bloom = self.fresh_bloom_for_multi();
 
         for (i, key) in keys.iter().enumerate() {
+            // Ключ под range tombstone — отдельный обход до страницы маркера
+            if let Some(cut) = self.range_cut(key) {
+                out[i] = self.exists_above_range_cut(key, cut, now)?;
+                continue;
+            }
             let bucket = self.dir.bucket_of_key(key, self.pager.meta.hash_kind);
 
             if let Some(loc) = self.mem_keydir_get_loc(bucket, key) {
@@ -205,23 +256,14 @@ impl Db {
                     off: loc.off,
                     _bucket: bucket,
                 });
-            } else {
-                let pid = self.dir.head(bucket)?;
-                if pid == NO_PAGE {
-                    out[i] = false;
-                    continue;
-                }
-                by_pid.entry(pid).or_default().push(Req {
-                    idx: i,
-                    key,
-                    _pid: pid,
-                    off: 0,
-                    _bucket: bucket,
-                });
+            } else if bloom_maybe(bloom, bucket, key)? {
+                by_bucket.entry(bucket).or_default().push((i, *key));
             }
         }
 
         let mut page_buf = vec![0u8; ps];
+        // Отдельный буфер для обхода цепочки: page_buf должен пережить все запросы группы
+        let mut chain_buf = vec![0u8; ps];
 
         for (pid, reqs) in by_pid.into_iter() {
             self.pager.read_page(pid, &mut page_buf)?;
@@ -258,7 +300,7 @@ impl Db {
                             }
                             if exp != 0 && now >= exp {
                                 out[idx] =
-                                    self.scan_chain_exists(req.key, next_pid, now, &mut page_buf)?;
+                                    self.scan_chain_exists(req.key, next_pid, now, &mut chain_buf)?;
                                 continue;
                             }
                             // Валидная запись на off — present
@@ -268,16 +310,29 @@ impl Db {
                     }
                 }
 
-                match crate::db::read_page::decide_exists_on_page(&page_buf, req.key, now) {
-                    crate::db::read_page::DecideExists::Tombstone => out[idx] = false,
-                    crate::db::read_page::DecideExists::Present => out[idx] = true,
-                    crate::db::read_page::DecideExists::Continue => {
-                        out[idx] = self.scan_chain_exists(req.key, next_pid, now, &mut page_buf)?;
+                match decide_exists_on_page(&page_buf, req.key, now) {
+                    DecideExists::Tombstone => out[idx] = false,
+                    DecideExists::Present => out[idx] = true,
+                    DecideExists::Continue => {
+                        out[idx] =
+                            self.scan_chain_exists(req.key, next_pid, now, &mut chain_buf)?;
                     }
                 }
             }
         }
 
+        // NEW: по одному обходу цепочки на бакет
+        for (bucket, reqs) in by_bucket {
+            let head = self.dir.head(bucket)?;
+            self.walk_chain_multi(head, reqs, &mut page_buf, &mut out, |_, page, key| {
+                Ok(match decide_exists_on_page(page, key, now) {
+                    DecideExists::Tombstone => Some(false),
+                    DecideExists::Present => Some(true),
+                    DecideExists::Continue => None,
+                })
+            })?;
+        }
+
         Ok(out)
     }
 
@@ -314,13 +369,7 @@ impl Db {
                     head_pid,
                 } => {
                     // value cache
-                    if let Some(cached) = value_cache_get(self.pager.db_id, head_pid, total_len) {
-                        return Ok(Some(cached));
-                    }
-                    let val =
-                        page_ovf_chain::read_overflow_chain(&self.pager, head_pid, total_len)?;
-                    value_cache_put(self.pager.db_id, head_pid, total_len, &val);
-                    return Ok(Some(val));
+                    return Ok(Some(self.read_overflow_cached(head_pid, total_len)?));
                 }
                 DecideOnPage::Continue => {
                     pid = next;
@@ -353,10 +402,10 @@ impl Db {
             let h = kv_header_read_v3(&page_buf)?;
             let next = h.next_page_id;
 
-            match crate::db::read_page::decide_exists_on_page(page_buf, key, now) {
-                crate::db::read_page::DecideExists::Tombstone => return Ok(false),
-                crate::db::read_page::DecideExists::Present => return Ok(true),
-                crate::db::read_page::DecideExists::Continue => {
+            match decide_exists_on_page(page_buf, key, now) {
+                DecideExists::Tombstone => return Ok(false),
+                DecideExists::Present => return Ok(true),
+                DecideExists::Continue => {
                     pid = next;
                 }
             }
@@ -364,6 +413,62 @@ impl Db {
         Ok(false)
     }
 
+    /// NEW: один обход цепочки от head для всех ключей бакета.
+    ///
+    /// decide(page, key) → Some(результат) — ключ решён на этой странице, None — искать глубже.
+    /// Нерешённые к концу цепочки ключи сохраняют значение out по умолчанию (None/false).
+    fn walk_chain_multi<T, F>(
+        &self,
+        mut pid: u64,
+        mut pending: Vec<(usize, &[u8])>,
+        page_buf: &mut [u8],
+        out: &mut [T],
+        mut decide: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&Self, &[u8], &[u8]) -> Result<Option<T>>,
+    {
+        while pid != NO_PAGE && !pending.is_empty() {
+            self.pager.read_page(pid, page_buf)?;
+            if &page_buf[0..4] != PAGE_MAGIC
+                || LittleEndian::read_u16(&page_buf[6..8]) != PAGE_TYPE_KV_RH3
+            {
+                break;
+            }
+            let next = kv_header_read_v3(page_buf)?.next_page_id;
+            let mut still = Vec::with_capacity(pending.len());
+            for (idx, key) in pending {
+                match decide(self, page_buf, key)? {
+                    Some(v) => out[idx] = v,
+                    None => still.push((idx, key)),
+                }
+            }
+            pending = still;
+            pid = next;
+        }
+        Ok(())
+    }
+
+    /// NEW: свежий RO bloom-sidecar для пред-фильтрации батча (None — фильтр не используется).
+    fn fresh_bloom_for_multi(&self) -> Option<&BloomSidecar> {
+        match self.bloom_ro.as_deref() {
+            Some(sc) if sc.buckets() == self.dir.bucket_count && sc.is_fresh_for_db(self) => {
+                Some(sc)
+            }
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn read_overflow_cached(&self, head_pid: u64, total_len: usize) -> Result<Vec<u8>> {
+        if let Some(cached) = value_cache_get(self.pager.db_id, head_pid, total_len) {
+            return Ok(cached);
+        }
+        let val = page_ovf_chain::read_overflow_chain(&self.pager, head_pid, total_len)?;
+        value_cache_put(self.pager.db_id, head_pid, total_len, &val);
+        Ok(val)
+    }
+
     #[inline]
     fn expand_value_cached_for_multi(&self, v: &[u8]) -> Result<Vec<u8>> {
         if let Some((total_len, head_pid)) = decode_ovf_placeholder_v3(v) {
@@ -382,6 +487,22 @@ impl Db {
 
 // -------------------- локальные хелперы --------------------
 
+/// Bloom-тест ключа батча: false — ключа точно нет. Без свежего фильтра — всегда true.
+#[inline]
+fn bloom_maybe(bloom: Option<&BloomSidecar>, bucket: u32, key: &[u8]) -> Result<bool> {
+    let Some(sc) = bloom else {
+        record_bloom_skipped_stale();
+        return Ok(true);
+    };
+    if sc.test(bucket, key)? {
+        record_bloom_positive();
+        Ok(true)
+    } else {
+        record_bloom_negative();
+        Ok(false)
+    }
+}
+
 #[inline]
 fn data_end_for_page(hdr: &crate::page::kv::KvHeaderV3, ps: usize) -> Option<usize> {
     if hdr.table_slots == 0 {
@@ -1,42 +1,108 @@
 //! db/open — открытие Db (writer/read-only) с конфигом и блокировками.
-
-use anyhow::{Context, Result};
-use fs2::FileExt;
+//!
+//! NEW: при cfg.cache_prewarm=true после открытия запускается фоновый прогрев page cache.
+//! NEW: реплей WAL writer'а сообщает прогресс через cfg.recovery_progress
+//! (или процессный дефолт wal::default_recovery_progress).
+//! NEW: оба хэндла включают кеш голов каталога (Directory::enable_heads_cache, heads.gen).
+//! NEW: writer после некорректного завершения (или при cfg.verify_heads_on_open) проверяет
+//! страницы-головы бакетов и чинит нечитаемые из образов реплея WAL (db/torn.rs).
+//! NEW: writer проставляет в meta feature flags используемых возможностей (meta::add_features).
+//! NEW: оба хэндла загружают range tombstones (db/range_del.rs); writer поднимает last_lsn
+//! не ниже lsn последнего маркера.
+//! NEW: LOCK берётся через acquire_db_lock (P1_LOCK_NOWAIT=1 — DbLockedError вместо ожидания).
+//! NEW: writer назначает db_uuid meta старого формата (meta::ensure_db_uuid).
+//! NEW: конфиг нормализуется до открытия (QuiverConfig::normalize, [WARN] по каждому clamp).
+//! NEW: ненулевые memory_budget_bytes / max_open_fds задают процессный ResourceBudget (budget.rs);
+//! LOCK занимает дескриптор бюджета — при исчерпании open_* возвращает ResourceBudgetError.
+//! NEW: оба хэндла сверяют cfg.key_transform с meta (db/key_transform.rs); writer фиксирует
+//! трансформацию в пустой БД.
+//! NEW: то же для cfg.value_transform (db/value_transform.rs).
+//! NEW: при TDE оба хэндла сверяют ключ с проверочным блоком meta до чтения страниц
+//! (TdeKeyError — ключ отсутствует или не тот); writer запечатывает блок ключом текущего KID.
+
+use anyhow::Result;
 use std::path::Path;
 use std::sync::Arc;
 
+use crate::budget;
 use crate::config::QuiverConfig;
 use crate::dir::{Directory, NO_PAGE};
-use crate::meta::set_clean_shutdown;
+use crate::meta::{
+    add_features, enable_tde_aad_v2, ensure_db_uuid, read_meta, set_clean_shutdown, CODEC_ZSTD,
+    FEATURE_HEADS_DELTA, FEATURE_HEADS_GEN, FEATURE_KV_PACKING, FEATURE_OVF_ZSTD,
+    FEATURE_PAGED_DIR, FEATURE_TDE, FEATURE_TDE_AAD_V2, FEATURE_WAL_EXPIRY,
+};
 use crate::pager::Pager;
-use crate::wal::{Wal, WalGroupCfg};
+use crate::wal::{default_recovery_progress, Wal, WalGroupCfg};
 
 // программная конфигурация процессного page cache
+use crate::pager::cache::page_cache_set_policy;
 use crate::pager::io::page_cache_configure;
+use crate::util::iostall::configure_io_stall;
 
-use super::core::{open_lock_file, Db, MemKeyLoc, LOCK_FILE};
+use super::core::{acquire_db_lock, open_lock_file, Db, MemKeyLoc};
+use super::key_transform::resolve_key_transform;
+use super::range_del::RangeTombstoneSet;
+use super::torn::REPAIR_IMAGES_CAP_BYTES;
+use super::value_transform::resolve_value_transform;
 
 use crate::page::{kv_header_read_v3, PAGE_MAGIC, PAGE_TYPE_KV_RH3};
 use byteorder::{ByteOrder, LittleEndian};
 // packed-aware helpers (с оффсетами)
 use crate::page::kv::kv_for_each_record_with_off;
 // Bloom sidecar
+use super::hot_get::HotGetCache;
+use super::hotkeys::HotPrefixProfiler;
 use crate::bloom::BloomSidecar;
+use crate::util::fsx::long_path;
 use crate::util::now_secs;
 
 impl Db {
-    pub fn open_with_config(root: &Path, cfg: QuiverConfig) -> Result<Self> {
+    pub fn open_with_config(root: &Path, mut cfg: QuiverConfig) -> Result<Self> {
+        // Windows: длинные пути (> MAX_PATH) через префикс \\?\ ; на Unix — no-op
+        let root = &long_path(root);
+        cfg.normalize_for_open();
+        budget::apply_config(cfg.memory_budget_bytes, cfg.max_open_fds);
+        let fd_lease = budget::acquire_db_fd(root)?;
         let lock = open_lock_file(root)?;
-        lock.lock_exclusive()
-            .with_context(|| format!("lock_exclusive {}", root.join(LOCK_FILE).display()))?;
-
-        Pager::wal_replay_with_pager(root)?;
+        acquire_db_lock(&lock, root, true)?;
+
+        let progress = cfg
+            .recovery_progress
+            .clone()
+            .or_else(default_recovery_progress);
+        let verify_heads = cfg.verify_heads_on_open || !read_meta(root)?.clean_shutdown;
+        let keep = if verify_heads {
+            REPAIR_IMAGES_CAP_BYTES
+        } else {
+            0
+        };
+        // NEW: реплей усекает WAL — неиндексированные батчи сначала в history.bin
+        if cfg.history_index {
+            if let Err(e) = crate::wal::index_wal_history(root) {
+                eprintln!("[WARN] history index before WAL replay failed: {:#}", e);
+            }
+        }
+        let images = Pager::wal_replay_with_pager_images(root, progress.as_ref(), keep)?;
         set_clean_shutdown(root, false)?;
+        // NEW: маркер заморозки от прежнего процесса (или из снапшота тома) устарел
+        super::freeze::clear_freeze_marker(root);
 
+        // NEW: детектор медленного IO (процесс‑глобальный порог, как page cache)
+        configure_io_stall(cfg.io_stall_ms, cfg.io_stall_log);
         let mut pager = Pager::open(root)?;
         pager.set_data_fsync(cfg.data_fsync);
         pager.set_tde_config(cfg.tde_enabled, cfg.tde_kid.clone());
+        pager.set_key_provider(cfg.tde_key_provider.as_ref().map(|p| p.0.clone()));
         pager.set_ovf_threshold_bytes(cfg.ovf_threshold_bytes);
+        pager.set_segment_growth(
+            cfg.segment_growth,
+            cfg.segment_grow_chunk_bytes,
+            cfg.segment_fallocate,
+        );
+        pager.set_punch_holes(cfg.punch_holes);
+        pager.set_seg_syncfs(cfg.seg_syncfs);
+        pager.set_history_index(cfg.history_index);
         if pager.tde_enabled {
             pager.ensure_tde_key()?;
         }
@@ -44,35 +110,143 @@ impl Db {
         if cfg.page_cache_pages > 0 {
             page_cache_configure(pager.meta.page_size as usize, cfg.page_cache_pages);
         }
+        page_cache_set_policy(cfg.page_cache_policy, cfg.page_cache_mlock_max_bytes);
 
         let _ = Wal::set_group_config(
             root,
             WalGroupCfg {
                 coalesce_ms: cfg.wal_coalesce_ms,
+                adaptive: cfg.wal_coalesce_adaptive,
+                p99_budget_us: cfg.wal_coalesce_p99_budget_us,
             },
         );
 
-        let dir = Directory::open(root)?;
-        Ok(Self {
+        let mut dir = Directory::open(root)?;
+        // NEW: кеш голов + heads.gen — writer продвигает генерацию, RO-читатели видят обновления
+        let mut features = FEATURE_KV_PACKING;
+        if let Err(e) = dir.enable_heads_cache(true) {
+            eprintln!("[WARN] directory heads cache disabled: {:#}", e);
+        } else {
+            features |= FEATURE_HEADS_GEN;
+        }
+        // NEW: feature flags — старые сборки с другим набором required-битов не откроют БД
+        if pager.tde_enabled {
+            features |= FEATURE_TDE;
+        }
+        if pager.meta.codec_default == CODEC_ZSTD {
+            features |= FEATURE_OVF_ZSTD;
+        }
+        if cfg.wal_expiry_events {
+            features |= FEATURE_WAL_EXPIRY;
+        }
+        if dir.is_paged() {
+            features |= FEATURE_PAGED_DIR;
+        }
+        // NEW: delta HEADS_UPDATE — только по явному opt-in (бит остаётся и после выключения)
+        if cfg.wal_heads_delta {
+            features |= FEATURE_HEADS_DELTA;
+        }
+        pager.meta.flags = add_features(root, features)?;
+        // NEW: meta старого формата без db_uuid — назначаем один раз
+        if !pager.meta.has_db_uuid() {
+            pager.meta.db_uuid = ensure_db_uuid(root)?.db_uuid;
+        }
+        // NEW: AAD v2 (page_id, тип, db_uuid) для новых AEAD-трейлеров — один раз на БД
+        if pager.tde_enabled && cfg.tde_aad_v2 && pager.meta.flags & FEATURE_TDE_AAD_V2 == 0 {
+            // реплей уже поднял last_lsn до максимума WAL: все прежние страницы — ниже since
+            let since = pager.meta.last_lsn + 1;
+            let m = enable_tde_aad_v2(root, since)?;
+            pager.meta.flags = m.flags;
+            pager.meta.db_uuid = m.db_uuid;
+            pager.meta.tde_aad_since_lsn = m.tde_aad_since_lsn;
+        }
+        // NEW: проверочный блок ключа TDE — запечатываем ключом текущего KID (ключ уже сверен)
+        pager.seal_tde_key_check()?;
+        // NEW: трансформация ключей — сверка с meta (пустая БД её принимает)
+        let key_transform =
+            resolve_key_transform(root, &mut pager.meta, cfg.key_transform.as_ref(), true)?;
+        let value_transform =
+            resolve_value_transform(root, &mut pager.meta, cfg.value_transform.as_ref(), true)?;
+        let mut db = Self {
             root: root.to_path_buf(),
             pager,
             dir,
             _lock: lock,
+            _fd_lease: fd_lease,
             readonly: false,
             mem_keydir: None,
             bloom_ro: None,
-        })
+            cache_prewarm: cfg.cache_prewarm,
+            prewarm_job: None,
+            idem_tokens: None,
+            wal_expiry_events: cfg.wal_expiry_events,
+            expiry_subs: Default::default(),
+            hot_prefix: (cfg.hot_prefix_sample > 0)
+                .then(|| HotPrefixProfiler::new(cfg.hot_prefix_sample, cfg.hot_prefix_len)),
+            compaction_filter: None,
+            refresh_heads_gen: None,
+            torn_report: None,
+            compaction_keep_versions: cfg.compaction_keep_versions.max(1) as usize,
+            range_tombstones: RangeTombstoneSet::load(root)?,
+            bloom_auto_refresh: cfg.bloom_auto_refresh,
+            trash_grace_secs: cfg.trash_grace_secs,
+            snapshot_sign_kid: cfg.snapshot_sign_kid.clone(),
+            key_transform,
+            value_transform,
+            scan_pins: Default::default(),
+            scan_cursor_pin_secs: cfg.scan_cursor_pin_secs,
+            hot_get: (cfg.hot_get_cache_entries > 0)
+                .then(|| HotGetCache::new(cfg.hot_get_cache_entries)),
+            open_cfg: cfg.clone(),
+        };
+        // Страницы после маркера должны получить lsn > lsn маркера, даже если meta отстала
+        let rt_lsn = db.range_tombstones.max_lsn();
+        if db.pager.meta.last_lsn < rt_lsn {
+            db.pager.meta.last_lsn = rt_lsn;
+        }
+        if verify_heads {
+            db.repair_torn_heads(&images)?;
+        }
+        // NEW: bloom.bin после краха — довести до свежего по покрытию реплея
+        db.reconcile_bloom_after_replay(&images.touched);
+        drop(images);
+        db.init_bloom_refresh();
+        db.start_prewarm_if_enabled();
+        Ok(db)
     }
 
     pub fn open_ro_with_config(root: &Path, cfg: QuiverConfig) -> Result<Self> {
+        Self::open_ro_inner(root, cfg, true)
+    }
+
+    /// RO-хэндл без shared LOCK — чтение рядом с живым writer'ом другого процесса
+    /// (digest бакетов на стороне cdc-serve). Цепочки читаются от снимка голов; страницы
+    /// цепочек не переписываются на месте, но reuse свободных страниц writer'ом возможен —
+    /// результат годится только для сверок, перепроверяемых вызывающим (стабильность LSN).
+    pub(crate) fn open_ro_unlocked(root: &Path) -> Result<Self> {
+        Self::open_ro_inner(root, QuiverConfig::from_env(), false)
+    }
+
+    fn open_ro_inner(root: &Path, mut cfg: QuiverConfig, take_lock: bool) -> Result<Self> {
+        // Windows: длинные пути (> MAX_PATH) через префикс \\?\ ; на Unix — no-op
+        let root = &long_path(root);
+        cfg.normalize_for_open();
+        budget::apply_config(cfg.memory_budget_bytes, cfg.max_open_fds);
+        let fd_lease = budget::acquire_db_fd(root)?;
         let lock = open_lock_file(root)?;
-        lock.lock_shared()
-            .with_context(|| format!("lock_shared {}", root.join(LOCK_FILE).display()))?;
+        if take_lock {
+            acquire_db_lock(&lock, root, false)?;
+        }
 
+        // NEW: детектор медленного IO (процесс‑глобальный порог, как page cache)
+        configure_io_stall(cfg.io_stall_ms, cfg.io_stall_log);
         let mut pager = Pager::open(root)?;
         pager.set_data_fsync(cfg.data_fsync);
         pager.set_tde_config(cfg.tde_enabled, cfg.tde_kid.clone());
+        pager.set_key_provider(cfg.tde_key_provider.as_ref().map(|p| p.0.clone()));
         pager.set_ovf_threshold_bytes(cfg.ovf_threshold_bytes);
+        // NEW: гейт записи на уровне pager'а — любой мутирующий путь RO-хэндла отвергается
+        pager.set_read_only(true);
         if pager.tde_enabled {
             pager.ensure_tde_key()?;
         }
@@ -80,17 +254,62 @@ impl Db {
         if cfg.page_cache_pages > 0 {
             page_cache_configure(pager.meta.page_size as usize, cfg.page_cache_pages);
         }
-
-        let dir = Directory::open(root)?;
+        page_cache_set_policy(cfg.page_cache_policy, cfg.page_cache_mlock_max_bytes);
+
+        let mut dir = Directory::open(root)?;
+        // NEW: кеш голов с проверкой генерации heads.gen (нет файла — чтение голов с диска)
+        let _ = dir.enable_heads_cache(false);
+        // NEW: трансформация ключей — сверка с meta. Unlocked-хэндл (digest) ключей API не
+        // принимает — расхождение конфига ему не мешает.
+        let key_transform =
+            match resolve_key_transform(root, &mut pager.meta, cfg.key_transform.as_ref(), false) {
+                Ok(t) => t,
+                Err(_) if !take_lock => None,
+                Err(e) => return Err(e),
+            };
+        let value_transform = match resolve_value_transform(
+            root,
+            &mut pager.meta,
+            cfg.value_transform.as_ref(),
+            false,
+        ) {
+            Ok(t) => t,
+            Err(_) if !take_lock => None,
+            Err(e) => return Err(e),
+        };
         let mut db = Self {
             root: root.to_path_buf(),
             pager,
             dir,
             _lock: lock,
+            _fd_lease: fd_lease,
             readonly: true,
             mem_keydir: None,
             bloom_ro: None,
+            cache_prewarm: cfg.cache_prewarm,
+            prewarm_job: None,
+            idem_tokens: None,
+            wal_expiry_events: cfg.wal_expiry_events,
+            expiry_subs: Default::default(),
+            hot_prefix: (cfg.hot_prefix_sample > 0)
+                .then(|| HotPrefixProfiler::new(cfg.hot_prefix_sample, cfg.hot_prefix_len)),
+            compaction_filter: None,
+            refresh_heads_gen: None,
+            torn_report: None,
+            compaction_keep_versions: cfg.compaction_keep_versions.max(1) as usize,
+            range_tombstones: RangeTombstoneSet::load(root)?,
+            bloom_auto_refresh: cfg.bloom_auto_refresh,
+            trash_grace_secs: cfg.trash_grace_secs,
+            snapshot_sign_kid: cfg.snapshot_sign_kid.clone(),
+            key_transform,
+            value_transform,
+            scan_pins: Default::default(),
+            scan_cursor_pin_secs: cfg.scan_cursor_pin_secs,
+            hot_get: (cfg.hot_get_cache_entries > 0)
+                .then(|| HotGetCache::new(cfg.hot_get_cache_entries)),
+            open_cfg: cfg.clone(),
         };
+        db.refresh_heads_gen = db.dir.heads_generation();
 
         db.rebuild_mem_keydir_if_enabled()?;
 
@@ -101,6 +320,7 @@ impl Db {
             }
         }
 
+        db.start_prewarm_if_enabled();
         Ok(db)
     }
 
@@ -115,11 +335,29 @@ impl Db {
     }
 }
 
+// -------------------- cache prewarm --------------------
+
+impl Db {
+    /// Фоновый прогрев page cache по <root>/.hot_pages.bin (только если кэш включён).
+    fn start_prewarm_if_enabled(&mut self) {
+        if !self.cache_prewarm {
+            return;
+        }
+        crate::pager::cache::page_cache_init(self.pager.meta.page_size as usize);
+        if crate::pager::cache::page_cache_capacity() == 0 {
+            return;
+        }
+        self.prewarm_job = self.pager.spawn_prewarm();
+    }
+}
+
 // -------------------- keydir builder (RO) --------------------
 
 impl Db {
     /// Построить in‑memory keydir, если включено (по умолчанию включено).
     /// Отключается ENV P1_MEM_KEYDIR=0|false|off|no.
+    /// NEW: нечитаемая страница цепочки не валит open_ro — keydir снимается ([WARN]),
+    /// чтения идут по цепочкам и получают ошибку только при доступе к сбойному бакету.
     fn rebuild_mem_keydir_if_enabled(&mut self) -> Result<()> {
         let on = std::env::var("P1_MEM_KEYDIR")
             .ok()
@@ -129,7 +367,11 @@ impl Db {
         if !on {
             return Ok(());
         }
-        self.rebuild_mem_keydir()
+        if let Err(e) = self.rebuild_mem_keydir() {
+            eprintln!("[WARN] in-memory keydir disabled: {:#}", e);
+            self.mem_keydir = None;
+        }
+        Ok(())
     }
 
     /// Перестроить keydir в один проход по цепочке (head→tail).
@@ -138,7 +380,7 @@ impl Db {
     /// - Если по ключу уже принято решение — дальше его игнорируем.
     /// - Tombstone → записать (pid=NO_PAGE, off=0) в keydir (ускоритель отрицательных результатов).
     /// - Валидная запись (TTL ок) → записать (pid текущей страницы, off смещение записи) в keydir.
-    fn rebuild_mem_keydir(&mut self) -> Result<()> {
+    pub(super) fn rebuild_mem_keydir(&mut self) -> Result<()> {
         use std::collections::HashSet;
 
         self.ensure_mem_keydir();
@@ -211,6 +453,17 @@ impl Db {
             }
         }
 
+        // NEW: keydir не помещается в ResourceBudget — снимаем, чтения идут по цепочкам
+        if let Some(kd) = self.mem_keydir.as_mut() {
+            if !kd.charge_budget() {
+                eprintln!(
+                    "[INFO] in-memory keydir skipped: {} bytes do not fit the resource budget",
+                    kd.estimated_bytes()
+                );
+                budget::note_degradation();
+                self.mem_keydir = None;
+            }
+        }
         Ok(())
     }
 }
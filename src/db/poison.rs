@@ -0,0 +1,100 @@
+//! db/poison — panic-safety writer'а: «отравленный» хэндл после паники в критической секции.
+//!
+//! Паника посреди коммита (WAL записан, сегменты — частично; in-memory meta/каталог/аллокатор
+//! не обновлены) оставляет хэндл в несогласованном состоянии, а LOCK остаётся у процесса.
+//! Критические секции завёрнуты в catch_unwind (Pager::critical):
+//! - коммиты Pager (commit_page, commit_pages_batch*, write_pages_unlogged), включая хук
+//!   DbInstrumentation::on_commit;
+//! - многошаговые операции Db: put/del, Batch::finish, compact_bucket (фильтр компактации и
+//!   подписчики EXPIRY — пользовательский код), vacuum_all.
+//!
+//! Паника в секции помечает хэндл (PoisonInfo: операция + сообщение паники) и продолжает
+//! раскрутку — вызывающий видит ту же панику. Дальше любая запись (ensure_writable) возвращает
+//! DbPoisonedError (downcast_ref); чтения работают, но могут не видеть последний коммит.
+//! Drop отравленного хэндла не усекает WAL и не ставит clean_shutdown — следующий open
+//! реплеит WAL и проверяет головы (db/torn.rs). Db::reopen() делает это на месте — с той же
+//! QuiverConfig, с которой хэндл был открыт (TDE-ключ, трансформации, кэши).
+//!
+//! Паника в замыкании Db::batch до finish ничего не пишет (операции только буферизуются):
+//! хэндл не отравляется, батч отбрасывается.
+
+use anyhow::Result;
+use std::any::Any;
+use std::path::PathBuf;
+
+use super::core::Db;
+
+/// Причина отравления хэндла.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoisonInfo {
+    /// Критическая секция, в которой случилась паника.
+    pub op: &'static str,
+    /// Сообщение паники (если это строка).
+    pub message: String,
+}
+
+/// Запись отклонена: хэндл отравлен паникой в критической секции. Достаётся из anyhow через
+/// downcast_ref; нужен reopen (Db::reopen или drop + open).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbPoisonedError {
+    pub path: PathBuf,
+    pub op: &'static str,
+    pub message: String,
+}
+
+impl std::fmt::Display for DbPoisonedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "database handle is poisoned: {} (panic in {}: {}); reopen to replay the WAL",
+            self.path.display(),
+            self.op,
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for DbPoisonedError {}
+
+impl Db {
+    /// Причина отравления (None — хэндл исправен).
+    pub fn poisoned(&self) -> Option<&PoisonInfo> {
+        self.pager.poisoned.as_ref()
+    }
+
+    /// Закрыть хэндл и открыть writer заново: для отравленного хэндла — с реплеем WAL.
+    /// Используется конфигурация исходного open_*/open_with_config; хуки, заданные после
+    /// открытия (set_instrumentation, set_compaction_filter, подписчики), не переносятся.
+    pub fn reopen(self) -> Result<Db> {
+        let root = self.root.clone();
+        let cfg = self.open_cfg.clone();
+        drop(self);
+        Db::open_with_config(&root, cfg)
+    }
+
+    /// Выполнить многошаговую операцию как критическую секцию (см. модульный комментарий).
+    pub(crate) fn critical<T>(
+        &mut self,
+        op: &'static str,
+        f: impl FnOnce(&mut Db) -> Result<T>,
+    ) -> Result<T> {
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(self))) {
+            Ok(r) => r,
+            Err(payload) => {
+                self.pager.poison(op, payload.as_ref());
+                std::panic::resume_unwind(payload)
+            }
+        }
+    }
+}
+
+/// Сообщение паники из payload (&str / String), иначе заглушка.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        return s.to_string();
+    }
+    if let Some(s) = payload.downcast_ref::<String>() {
+        return s.clone();
+    }
+    "<non-string panic payload>".to_string()
+}
@@ -0,0 +1,244 @@
+//! db/range_del — удаление ключей по префиксу одним range tombstone (Db::delete_prefix).
+//!
+//! Вместо перечисления и tombstone'а каждого ключа delete_prefix(prefix) фиксирует маркер
+//! {prefix, lsn}, где lsn — last_lsn на момент вызова. У записей нет собственного LSN, но каждая
+//! страница несёт LSN коммита, а put/del/batch всегда пишут новые страницы: запись с ключом под
+//! префиксом на странице с page_lsn <= lsn маркера считается удалённой, более новые — живые.
+//!
+//! Read path (get/exists/get_many/exists_many/scan_*/get_versions): при совпадении префикса
+//! обход цепочки останавливается на первой странице с page_lsn <= lsn маркера (tombstone).
+//! Компактация физически вычищает такие записи; маркер снимается, когда все бакеты
+//! компактированы после его появления (учёт в памяти — после рестарта нужен новый проход).
+//!
+//! Маркеры хранятся в <root>/range_tombstones.json (tmp + rename) и перечитываются при open
+//! и Db::refresh. Ограничение: они не передаются через CDC/WAL-ship и постраничные бэкапы —
+//! перед их снятием выполните compact_all (или vacuum).
+
+use anyhow::{Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::db::read_page::{decide_value_on_page, DecideOnPage};
+use crate::dir::NO_PAGE;
+use crate::page::ovf::chain::read_overflow_chain;
+use crate::page::{kv_header_read_v3, OFF_TYPE, PAGE_MAGIC, PAGE_TYPE_KV_RH3};
+
+use super::core::Db;
+
+pub const RANGE_TOMBSTONES_FILE: &str = "range_tombstones.json";
+
+/// Маркер удаления всех ключей с префиксом, записанных не позже `lsn`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeTombstone {
+    pub prefix: Vec<u8>,
+    pub lsn: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RangeTombstonesFile {
+    version: u32,
+    tombstones: Vec<RangeTombstone>,
+}
+
+/// Активные маркеры + бакеты, компактированные после их появления (только в памяти).
+#[derive(Debug, Default)]
+pub(crate) struct RangeTombstoneSet {
+    items: Vec<RangeTombstone>,
+    resolved: Vec<Vec<bool>>,
+}
+
+impl RangeTombstoneSet {
+    pub(crate) fn load(root: &Path) -> Result<Self> {
+        let p = range_tombstones_path(root);
+        if !p.exists() {
+            return Ok(Self::default());
+        }
+        let bytes = std::fs::read(&p).with_context(|| format!("read {}", p.display()))?;
+        let f: RangeTombstonesFile = serde_json::from_slice(&bytes)
+            .with_context(|| format!("parse range tombstones {}", p.display()))?;
+        let resolved = vec![Vec::new(); f.tombstones.len()];
+        Ok(Self {
+            items: f.tombstones,
+            resolved,
+        })
+    }
+
+    fn store(&self, root: &Path) -> Result<()> {
+        let p = range_tombstones_path(root);
+        if self.items.is_empty() {
+            if p.exists() {
+                std::fs::remove_file(&p).with_context(|| format!("remove {}", p.display()))?;
+            }
+            return Ok(());
+        }
+        let tmp = root.join(format!("{}.tmp", RANGE_TOMBSTONES_FILE));
+        let f = RangeTombstonesFile {
+            version: 1,
+            tombstones: self.items.clone(),
+        };
+        {
+            let mut fh = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&tmp)
+                .with_context(|| format!("open {}", tmp.display()))?;
+            fh.write_all(&serde_json::to_vec_pretty(&f)?)?;
+            fh.sync_all()?;
+        }
+        crate::util::fsx::replace_file(&tmp, &p)
+            .with_context(|| format!("rename {} -> {}", tmp.display(), p.display()))?;
+        Ok(())
+    }
+
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Наибольший lsn маркера, префикс которого покрывает ключ.
+    #[inline]
+    pub(crate) fn cut_for(&self, key: &[u8]) -> Option<u64> {
+        self.items
+            .iter()
+            .filter(|t| key.starts_with(&t.prefix))
+            .map(|t| t.lsn)
+            .max()
+    }
+
+    pub(crate) fn max_lsn(&self) -> u64 {
+        self.items.iter().map(|t| t.lsn).max().unwrap_or(0)
+    }
+}
+
+pub(crate) fn range_tombstones_path(root: &Path) -> PathBuf {
+    root.join(RANGE_TOMBSTONES_FILE)
+}
+
+impl Db {
+    /// Удалить все ключи с префиксом одним range tombstone (O(1) вместо tombstone на ключ).
+    /// Возвращает lsn маркера: записи с page_lsn <= lsn под префиксом считаются удалёнными.
+    /// Пустой префикс удаляет все ключи.
+    pub fn delete_prefix(&mut self, prefix: &[u8]) -> Result<u64> {
+        self.pager.ensure_writable("delete_prefix")?;
+        let prefix = self.canonical_prefix(prefix)?.into_owned();
+        let prefix = prefix.as_slice();
+        let lsn = self.pager.meta.last_lsn;
+        let set = &mut self.range_tombstones;
+        match set.items.iter().position(|t| t.prefix == prefix) {
+            Some(i) => {
+                set.items[i].lsn = lsn;
+                set.resolved[i].clear();
+            }
+            None => {
+                set.items.push(RangeTombstone {
+                    prefix: prefix.to_vec(),
+                    lsn,
+                });
+                set.resolved.push(Vec::new());
+            }
+        }
+        self.range_tombstones.store(&self.root)?;
+        Ok(lsn)
+    }
+
+    /// Активные (ещё не снятые компактацией) range tombstones.
+    pub fn range_tombstones(&self) -> Vec<RangeTombstone> {
+        self.range_tombstones.items.clone()
+    }
+
+    /// lsn маркера, покрывающего ключ (None — ключ не под range tombstone).
+    #[inline]
+    pub(crate) fn range_cut(&self, key: &[u8]) -> Option<u64> {
+        if self.range_tombstones.is_empty() {
+            return None;
+        }
+        self.range_tombstones.cut_for(key)
+    }
+
+    /// Перечитать маркеры с диска (RO refresh).
+    pub(crate) fn reload_range_tombstones(&mut self) -> Result<()> {
+        self.range_tombstones = RangeTombstoneSet::load(&self.root)?;
+        Ok(())
+    }
+
+    /// Бакет компактирован: все маркеры, существовавшие до компактации, для него разрешены.
+    /// Маркер, разрешённый во всех бакетах, снимается (и файл перезаписывается).
+    pub(crate) fn resolve_range_tombstones(&mut self, bucket: u32) -> Result<()> {
+        if self.range_tombstones.is_empty() {
+            return Ok(());
+        }
+        let buckets = self.dir.bucket_count as usize;
+        let set = &mut self.range_tombstones;
+        let mut retired = false;
+        let mut i = 0;
+        while i < set.items.len() {
+            let r = &mut set.resolved[i];
+            if r.len() != buckets {
+                r.resize(buckets, false);
+            }
+            r[bucket as usize] = true;
+            if r.iter().all(|&b| b) {
+                set.items.remove(i);
+                set.resolved.remove(i);
+                retired = true;
+            } else {
+                i += 1;
+            }
+        }
+        if retired {
+            self.range_tombstones.store(&self.root)?;
+        }
+        Ok(())
+    }
+
+    /// Решение по ключу под маркером: обход цепочки до первой страницы с page_lsn <= cut.
+    fn decide_above_range_cut(&self, key: &[u8], cut: u64, now: u32) -> Result<DecideOnPage> {
+        let bucket = self.dir.bucket_of_key(key, self.pager.meta.hash_kind);
+        let mut pid = self.dir.head(bucket)?;
+        let mut page = vec![0u8; self.pager.meta.page_size as usize];
+        while pid != NO_PAGE {
+            self.pager.read_page(pid, &mut page)?;
+            if &page[0..4] != PAGE_MAGIC
+                || LittleEndian::read_u16(&page[OFF_TYPE..OFF_TYPE + 2]) != PAGE_TYPE_KV_RH3
+            {
+                break;
+            }
+            let h = kv_header_read_v3(&page)?;
+            if h.lsn <= cut {
+                return Ok(DecideOnPage::Tombstone);
+            }
+            match decide_value_on_page(&page, key, now) {
+                DecideOnPage::Continue => pid = h.next_page_id,
+                d => return Ok(d),
+            }
+        }
+        Ok(DecideOnPage::Continue)
+    }
+
+    pub(crate) fn get_above_range_cut(
+        &self,
+        key: &[u8],
+        cut: u64,
+        now: u32,
+    ) -> Result<Option<Vec<u8>>> {
+        match self.decide_above_range_cut(key, cut, now)? {
+            DecideOnPage::Valid(v) => Ok(Some(v)),
+            DecideOnPage::NeedOverflow {
+                total_len,
+                head_pid,
+            } => Ok(Some(read_overflow_chain(&self.pager, head_pid, total_len)?)),
+            DecideOnPage::Tombstone | DecideOnPage::Continue => Ok(None),
+        }
+    }
+
+    pub(crate) fn exists_above_range_cut(&self, key: &[u8], cut: u64, now: u32) -> Result<bool> {
+        Ok(matches!(
+            self.decide_above_range_cut(key, cut, now)?,
+            DecideOnPage::Valid(_) | DecideOnPage::NeedOverflow { .. }
+        ))
+    }
+}
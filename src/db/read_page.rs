@@ -8,13 +8,15 @@
 //! API:
 //! - decide_value_on_page(page, key, now) -> DecideOnPage
 //! - decide_exists_on_page(page, key, now) -> DecideExists
+//! - NEW: locate_decision_on_page(page, key, now) -> offset записи, которую выбрал бы
+//!   decide_value_on_page (hot-get кэш, db/hot_get.rs)
 //!
 //! Безопасность:
 //! - Если страница не KV_RH3 или MAGIC не совпадает — возвращает Continue.
 //! - Обход используется через kv_for_each_record (packed‑aware; reverse слоты).
 
 use crate::metrics::record_ttl_skipped;
-use crate::page::kv::kv_for_each_record;
+use crate::page::kv::{kv_for_each_record, kv_for_each_record_with_off};
 use crate::page::{OFF_TYPE, PAGE_MAGIC, PAGE_TYPE_KV_RH3};
 use crate::util::decode_ovf_placeholder_v3;
 use byteorder::{ByteOrder, LittleEndian};
@@ -125,6 +127,25 @@ pub fn decide_exists_on_page(page: &[u8], key: &[u8], now: u32) -> DecideExists
     decision
 }
 
+/// Offset решающей записи ключа на странице (tombstone или первая живая по TTL), как у
+/// decide_value_on_page; None — на странице решения нет. TTL-пропуски не учитываются в метриках.
+#[inline]
+pub fn locate_decision_on_page(page: &[u8], key: &[u8], now: u32) -> Option<usize> {
+    if !is_kv_page(page) {
+        return None;
+    }
+    let mut found = None;
+    kv_for_each_record_with_off(page, |off, k, _v, expires_at_sec, vflags| {
+        if found.is_some() || k != key {
+            return;
+        }
+        if (vflags & 0x1) == 1 || expires_at_sec == 0 || now < expires_at_sec {
+            found = Some(off);
+        }
+    });
+    found
+}
+
 // ---------- helpers ----------
 
 #[inline]
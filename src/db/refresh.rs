@@ -0,0 +1,92 @@
+//! db/refresh — live-refresh RO-хэндла: перечитать meta и обновить bloom/keydir views.
+//!
+//! open_ro фиксирует meta (last_lsn/next_page_id), bloom-заголовок и in-memory keydir на момент
+//! открытия. Если файлы БД продвинулись под долгоживущим читателем (например, каталог обновлён
+//! внешним процессом без LOCK — rsync/CDC-применение в тот же каталог), снимок устаревает:
+//! - bloom считается stale навсегда (meta.last_lsn не растёт), либо, хуже, fresh: заголовок
+//!   bloom был прочитан при открытии и совпадает со старым meta, хотя тело уже другое;
+//! - keydir указывает на старые страницы/смещения.
+//!
+//! Db::refresh() (по запросу) перечитывает meta с диска и при продвижении last_lsn/next_page_id
+//! или смене генерации heads.gen: обновляет pager.meta, переоткрывает bloom.bin (свежесть снова
+//! сравнивается с актуальным last_lsn), перестраивает keydir (если он был построен) и сбрасывает
+//! page cache (страницы могли быть переписаны другим процессом). На writer-хэндле — no-op:
+//! его meta авторитетна.
+//! NEW: range tombstones (db/range_del.rs) перечитываются при каждом вызове — delete_prefix
+//! не продвигает last_lsn.
+
+use anyhow::Result;
+use std::sync::Arc;
+
+use crate::bloom::BloomSidecar;
+use crate::pager::cache::{page_cache_capacity, page_cache_clear};
+
+use super::core::Db;
+
+/// Итог Db::refresh().
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RefreshReport {
+    /// Что-то изменилось (иначе остальные флаги false).
+    pub changed: bool,
+    pub last_lsn_before: u64,
+    pub last_lsn_after: u64,
+    pub next_page_id_before: u64,
+    pub next_page_id_after: u64,
+    /// bloom.bin переоткрыт (или снят, если файла больше нет / он не читается).
+    pub bloom_reloaded: bool,
+    /// in-memory keydir перестроен.
+    pub keydir_rebuilt: bool,
+}
+
+impl Db {
+    /// Перечитать meta и обновить bloom/keydir views RO-хэндла, если БД продвинулась.
+    /// Writer — no-op (changed=false).
+    pub fn refresh(&mut self) -> Result<RefreshReport> {
+        let mut rep = RefreshReport {
+            last_lsn_before: self.pager.meta.last_lsn,
+            last_lsn_after: self.pager.meta.last_lsn,
+            next_page_id_before: self.pager.meta.next_page_id,
+            next_page_id_after: self.pager.meta.next_page_id,
+            ..Default::default()
+        };
+        if !self.readonly {
+            return Ok(rep);
+        }
+
+        self.reload_range_tombstones()?;
+        let m = crate::meta::read_meta(&self.root)?;
+        let heads_gen = self.dir.heads_generation();
+        let advanced = m.last_lsn != self.pager.meta.last_lsn
+            || m.next_page_id != self.pager.meta.next_page_id;
+        let heads_moved = heads_gen.is_some() && heads_gen != self.refresh_heads_gen;
+        self.refresh_heads_gen = heads_gen;
+        if !advanced && !heads_moved {
+            return Ok(rep);
+        }
+        rep.changed = true;
+
+        self.pager.meta.last_lsn = m.last_lsn;
+        self.pager.meta.next_page_id = m.next_page_id;
+        self.pager.meta.clean_shutdown = m.clean_shutdown;
+        rep.last_lsn_after = m.last_lsn;
+        rep.next_page_id_after = m.next_page_id;
+
+        if page_cache_capacity() > 0 {
+            page_cache_clear();
+        }
+
+        let had_bloom = self.bloom_ro.is_some();
+        self.bloom_ro = if self.root.join("bloom.bin").exists() {
+            BloomSidecar::open_ro(&self.root).ok().map(Arc::new)
+        } else {
+            None
+        };
+        rep.bloom_reloaded = had_bloom || self.bloom_ro.is_some();
+
+        if self.mem_keydir.is_some() {
+            self.rebuild_mem_keydir()?;
+            rep.keydir_rebuilt = true;
+        }
+        Ok(rep)
+    }
+}
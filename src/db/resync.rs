@@ -0,0 +1,228 @@
+//! db/resync — состояние бакета для точечной пересинхронизации реплики (CDC resync).
+//!
+//! Follower — постраничное зеркало leader'а: page_id и LSN страниц у них общие, и следующие
+//! страницы leader'а ссылаются (next_page_id) на его цепочку. Поэтому состояние бакета
+//! передаётся в раскладке leader'а: образы KV-страниц цепочки от головы и OVERFLOW-цепочек
+//! записей на них — ровно то, где лежат живые записи бакета на снятом LSN, плюс голова и
+//! digest (db/digest.rs) на том же now. Собрать записи в собственные страницы follower не
+//! может: его page_id разошлись бы с теми, что leader выделит дальше.
+//!
+//! - Db::bucket_state(bucket, now) / bucket_state_at(root, ...) (без LOCK — рядом с живым
+//!   writer'ом; вызывающий проверяет, что WAL не сдвинулся) — снять состояние;
+//! - Db::apply_bucket_state(state, now) — заменить цепочку бакета: все страницы пишутся как есть
+//!   (LSN leader'а сохраняется — LSN-гейтинг следующих кадров потока работает как обычно),
+//!   fsync данных, затем голова одним set_heads_bulk; результат сверяется с digest'ом leader'а.
+//!
+//! Старая цепочка follower'а не освобождается: её страницы либо перезаписаны образами leader'а,
+//! либо остаются сиротами до sweep/vacuum (как после компактации).
+
+use anyhow::{anyhow, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::dir::NO_PAGE;
+use crate::page::kv::kv_for_each_record;
+use crate::page::{
+    kv_header_read_v3, ovf_header_read_v3, OFF_TYPE, PAGE_MAGIC, PAGE_TYPE_KV_RH3,
+    PAGE_TYPE_OVERFLOW3,
+};
+use crate::util::decode_ovf_placeholder_v3;
+
+use super::core::Db;
+use super::digest::BucketDigest;
+
+/// Предел страниц в одном состоянии бакета (KV + OVERFLOW).
+pub const RESYNC_MAX_PAGES: usize = 65_536;
+
+/// Состояние бакета на LSN источника (см. модульный комментарий).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BucketState {
+    pub bucket: u32,
+    pub lsn: u64,
+    /// NO_PAGE — бакет пуст.
+    pub head: u64,
+    /// (page_id, образ страницы как на диске источника).
+    pub pages: Vec<(u64, Vec<u8>)>,
+    pub digest: BucketDigest,
+}
+
+/// Итог замены цепочки бакета.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BucketResyncReport {
+    pub bucket: u32,
+    pub lsn: u64,
+    pub old_head: u64,
+    pub new_head: u64,
+    pub pages_written: u64,
+    /// Digest бакета после замены совпал с digest'ом источника.
+    pub digest_ok: bool,
+}
+
+impl Db {
+    /// Снять состояние бакета: цепочка KV-страниц от головы, OVERFLOW-цепочки её записей
+    /// и digest живых ключей на момент `now`. lsn — meta.last_lsn хэндла.
+    pub fn bucket_state(&self, bucket: u32, now: u32) -> Result<BucketState> {
+        let ps = self.pager.meta.page_size as usize;
+        let heads = self.dir.heads_snapshot()?;
+        let head = *heads.get(bucket as usize).ok_or_else(|| {
+            anyhow!(
+                "bucket_state: bucket {} out of range (buckets={})",
+                bucket,
+                heads.len()
+            )
+        })?;
+        let mut pages: Vec<(u64, Vec<u8>)> = Vec::new();
+        let mut seen: HashSet<u64> = HashSet::new();
+        let mut ovf_heads: Vec<u64> = Vec::new();
+
+        let mut pid = head;
+        while pid != NO_PAGE {
+            let mut page = vec![0u8; ps];
+            self.pager.read_page(pid, &mut page)?;
+            if page_type(&page) != Some(PAGE_TYPE_KV_RH3) {
+                return Err(anyhow!(
+                    "bucket_state: page {} in chain of bucket {} is not a KV page",
+                    pid,
+                    bucket
+                ));
+            }
+            let next = kv_header_read_v3(&page)?.next_page_id;
+            kv_for_each_record(&page, |_k, v, _exp, _vflags| {
+                if let Some((_total, ovf)) = decode_ovf_placeholder_v3(v) {
+                    ovf_heads.push(ovf);
+                }
+            });
+            push_page(&mut pages, &mut seen, bucket, pid, page)?;
+            pid = next;
+        }
+
+        // OVERFLOW-цепочки; цепочка, переиспользованная под другой тип (значение давно
+        // перезаписано и освобождено), обрывается — get её всё равно не читает
+        for mut pid in ovf_heads {
+            while pid != NO_PAGE && !seen.contains(&pid) {
+                let mut page = vec![0u8; ps];
+                if self.pager.read_page(pid, &mut page).is_err()
+                    || page_type(&page) != Some(PAGE_TYPE_OVERFLOW3)
+                {
+                    break;
+                }
+                let next = ovf_header_read_v3(&page)?.next_page_id;
+                push_page(&mut pages, &mut seen, bucket, pid, page)?;
+                pid = next;
+            }
+        }
+
+        let digest = self.bucket_digests(&[bucket], now)?[0];
+        Ok(BucketState {
+            bucket,
+            lsn: self.pager.meta.last_lsn,
+            head,
+            pages,
+            digest,
+        })
+    }
+
+    /// Заменить цепочку бакета состоянием источника (follower; только writer).
+    /// Digest после замены считается на том же `now`, что и у источника.
+    pub fn apply_bucket_state(
+        &mut self,
+        state: &BucketState,
+        now: u32,
+    ) -> Result<BucketResyncReport> {
+        self.pager.ensure_writable("apply_bucket_state")?;
+        let ps = self.pager.meta.page_size as usize;
+        if state.bucket >= self.dir.bucket_count {
+            return Err(anyhow!(
+                "apply_bucket_state: bucket {} out of range (buckets={})",
+                state.bucket,
+                self.dir.bucket_count
+            ));
+        }
+        // Проверка до первой записи: состояние либо применяется целиком, либо не трогается
+        for (pid, img) in &state.pages {
+            if img.len() != ps {
+                return Err(anyhow!(
+                    "apply_bucket_state: page {} has {} bytes (page_size={})",
+                    pid,
+                    img.len(),
+                    ps
+                ));
+            }
+            if !matches!(
+                page_type(img),
+                Some(PAGE_TYPE_KV_RH3) | Some(PAGE_TYPE_OVERFLOW3)
+            ) {
+                return Err(anyhow!(
+                    "apply_bucket_state: page {} is not a KV/OVERFLOW page",
+                    pid
+                ));
+            }
+        }
+        if state.head != NO_PAGE && !state.pages.iter().any(|(pid, _)| *pid == state.head) {
+            return Err(anyhow!(
+                "apply_bucket_state: head page {} of bucket {} is missing from the state",
+                state.head,
+                state.bucket
+            ));
+        }
+
+        let old_head = self.dir.head(state.bucket)?;
+        for (pid, img) in &state.pages {
+            self.pager.write_page_raw_with_fsync(*pid, img, false)?;
+        }
+        if self.pager.data_fsync {
+            self.pager.sync_all_segments()?;
+        }
+        self.set_dir_heads_bulk(&[(state.bucket, state.head)])?;
+
+        let digest = self.bucket_digests(&[state.bucket], now)?[0];
+        Ok(BucketResyncReport {
+            bucket: state.bucket,
+            lsn: state.lsn,
+            old_head,
+            new_head: state.head,
+            pages_written: state.pages.len() as u64,
+            digest_ok: digest == state.digest,
+        })
+    }
+}
+
+/// Состояние бакета БД по пути без LOCK (Db::open_ro_unlocked) — для процесса рядом с живым
+/// writer'ом; как и у bucket_digests_at, вызывающий перепроверяет, что источник не сдвинулся.
+pub fn bucket_state_at(root: &Path, bucket: u32, now: u32) -> Result<BucketState> {
+    Db::open_ro_unlocked(root)?.bucket_state(bucket, now)
+}
+
+fn page_type(page: &[u8]) -> Option<u16> {
+    if page.len() < OFF_TYPE + 2 || &page[..4] != PAGE_MAGIC {
+        return None;
+    }
+    Some(LittleEndian::read_u16(&page[OFF_TYPE..OFF_TYPE + 2]))
+}
+
+fn push_page(
+    pages: &mut Vec<(u64, Vec<u8>)>,
+    seen: &mut HashSet<u64>,
+    bucket: u32,
+    pid: u64,
+    page: Vec<u8>,
+) -> Result<()> {
+    if !seen.insert(pid) {
+        return Err(anyhow!(
+            "bucket_state: page {} repeats in bucket {} (chain loop)",
+            pid,
+            bucket
+        ));
+    }
+    if pages.len() >= RESYNC_MAX_PAGES {
+        return Err(anyhow!(
+            "bucket_state: bucket {} exceeds {} pages",
+            bucket,
+            RESYNC_MAX_PAGES
+        ));
+    }
+    pages.push((pid, page));
+    Ok(())
+}
@@ -9,6 +9,38 @@
 //! NEW (perf):
 //! - Один буфер страницы на весь вызов (и для keydir‑fast‑path, и для chain‑scan), без переаллоков.
 //!
+//! NEW (scan snapshot): chain-скан закрепляет головы всех бакетов на старте
+//! (Directory::heads_snapshot) и обходит цепочки от них. Страницы цепочек не переписываются
+//! (put/compaction пишут новые), поэтому результат — состояние на момент начала скана, даже
+//! если каталог продвинулся под читателем по ходу (внешнее применение CDC/rsync, см. db/refresh.rs).
+//! Нечитаемое OVERFLOW-значение теперь — ошибка скана (раньше запись молча пропускалась):
+//! частичный результат не выдаётся за согласованный.
+//!
+//! NEW: ключи под range tombstone (Db::delete_prefix) пропускаются, если их страница не новее маркера.
+//!
+//! NEW (key transform): префикс публичных сканов переводится в префикс канонических ключей
+//! (Db::canonical_prefix, db/key_transform.rs); сканы отдают канонические ключи.
+//!
+//! NEW (scan iterator): Db::scan_iter / Db::scan_prefix_iter — ленивый ScanIter с ограниченной
+//! памятью: головы закрепляются при создании (heads_snapshot), бакеты сканируются по одному
+//! (в памяти — найденные пары и решения только текущего бакета). Те же fast-path'ы, что у
+//! scan_prefix: при in-memory keydir берётся префиксный обход keydir по бакету, иначе — цепочка.
+//! Состояние решений chain-скана теперь тоже per-bucket (ключ живёт ровно в одном бакете).
+//!
+//! NEW (keys-only): Db::scan_keys / scan_keys_stream / scan_keys_iter — только ключи. Значения не
+//! материализуются (placeholder не раскрывается, OVERFLOW-цепочки не читаются); решения по
+//! ключам (tombstone, TTL, range tombstone) — те же, что у обычного скана.
+//!
+//! NEW (bucket routing): при маршрутизации по префиксу ключа (dir/routing.rs) префикс, задающий
+//! маршрутный ключ (Db::prefix_bucket), сводит scan_prefix / scan_stream / scan_keys* / итераторы
+//! к одному бакету — и chain-, и keydir-путь (метрика scan_prefix_single_bucket).
+//!
+//! NEW (value transform): публичные сканы и ScanIter отдают декодированные значения
+//! (Db::decode_value, db/value_transform.rs); ошибка декодирования — ошибка скана.
+//!
+//! NEW (kvfs): for_each_live_key_in_bucket (ключ + page_id решающей записи от закреплённой головы)
+//! и get_from_page (значение от этой страницы) — lazy-чтение снимка в kvfs (quiverdb mount).
+//!
 //! Семантика неизменна:
 //! - tail-wins: идём от head к хвосту, "побеждает" первый валидный (не tombstone, не истёкший TTL).
 //! - Tombstone имеет приоритет.
@@ -16,11 +48,12 @@
 
 use anyhow::Result;
 use byteorder::{ByteOrder, LittleEndian};
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use crate::dir::NO_PAGE;
-use crate::metrics::record_ttl_skipped;
+use crate::metrics::{record_scan_prefix_single_bucket, record_ttl_skipped};
 use crate::page::{kv_header_read_v3, KV_HDR_MIN, PAGE_MAGIC, PAGE_TYPE_KV_RH3};
+use crate::pager::io::ReadaheadWindow;
 // NEW: packed-aware точечный поиск (подмодуль kv)
 use crate::page::kv::kv_find_record_by_key;
 // Для безопасной ручной итерации по слотам
@@ -36,38 +69,144 @@ impl Db {
     /// Собрать все пары (ключ, значение) по всем бакетам.
     /// Если ключ встречается несколько раз в цепочке, "побеждает" первый валидный от head.
     pub fn scan_all(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
-        if self.has_mem_keydir() {
+        let pairs = if self.has_mem_keydir() {
             self.scan_materialized_via_keydir(None)
         } else {
             self.scan_materialized_via_chains(None)
-        }
+        }?;
+        self.decode_pairs(pairs)
     }
 
     /// Собрать пары (ключ, значение) только для ключей с заданным префиксом.
     pub fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
-        if self.has_mem_keydir() {
+        let prefix = self.canonical_prefix(prefix)?;
+        let prefix = prefix.as_ref();
+        let pairs = if self.has_mem_keydir() {
             self.scan_materialized_via_keydir(Some(prefix))
         } else {
             self.scan_materialized_via_chains(Some(prefix))
-        }
+        }?;
+        self.decode_pairs(pairs)
     }
 
     /// Потоковый скан: вызывает cb для каждой финальной пары.
     /// Если prefix=None — полный скан; иначе — только ключи с заданным префиксом.
-    pub fn scan_stream<F>(&self, prefix: Option<&[u8]>, cb: F) -> Result<()>
+    pub fn scan_stream<F>(&self, prefix: Option<&[u8]>, mut cb: F) -> Result<()>
     where
         F: FnMut(&[u8], &[u8]),
     {
+        let prefix = self.canonical_prefix_opt(prefix)?;
+        let prefix = prefix.as_deref();
+        if self.value_transform.is_none() {
+            return if self.has_mem_keydir() {
+                self.scan_stream_via_keydir(prefix, cb)
+            } else {
+                self.scan_stream_via_chains(prefix, cb)
+            };
+        }
+        // Декодирование в cb: после первой ошибки остальные пары пропускаются
+        let mut err = None;
+        let mut decoded = |k: &[u8], v: &[u8]| {
+            if err.is_none() {
+                match self.decode_value(v.to_vec()) {
+                    Ok(v) => cb(k, &v),
+                    Err(e) => err = Some(e),
+                }
+            }
+        };
+        if self.has_mem_keydir() {
+            self.scan_stream_via_keydir(prefix, &mut decoded)?;
+        } else {
+            self.scan_stream_via_chains(prefix, &mut decoded)?;
+        }
+        match err {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// NEW: ленивый итератор по всем живым парам (см. ScanIter).
+    pub fn scan_iter(&self) -> Result<ScanIter<'_>> {
+        ScanIter::new(self, None, false)
+    }
+
+    /// NEW: ленивый итератор по парам с префиксом ключа (см. ScanIter).
+    pub fn scan_prefix_iter(&self, prefix: &[u8]) -> Result<ScanIter<'_>> {
+        let prefix = self.canonical_prefix(prefix)?.into_owned();
+        ScanIter::new(self, Some(prefix), false)
+    }
+
+    /// NEW: все живые ключи (prefix=None — полный скан) без чтения значений.
+    pub fn scan_keys(&self, prefix: Option<&[u8]>) -> Result<Vec<Vec<u8>>> {
+        let mut out = Vec::new();
+        self.scan_keys_stream(prefix, |k| out.push(k.to_vec()))?;
+        Ok(out)
+    }
+
+    /// NEW: потоковый keys-only скан: cb для каждого живого ключа.
+    pub fn scan_keys_stream<F>(&self, prefix: Option<&[u8]>, mut cb: F) -> Result<()>
+    where
+        F: FnMut(&[u8]),
+    {
+        let prefix = self.canonical_prefix_opt(prefix)?;
+        let prefix = prefix.as_deref();
         if self.has_mem_keydir() {
-            self.scan_stream_via_keydir(prefix, cb)
+            self.scan_stream_via_keydir_mode(prefix, true, |k, _| cb(k))
         } else {
-            self.scan_stream_via_chains(prefix, cb)
+            self.scan_stream_via_chains_mode(prefix, true, |k, _| cb(k))
         }
     }
 
+    /// NEW: ленивый keys-only итератор (см. KeyScanIter).
+    pub fn scan_keys_iter(&self, prefix: Option<&[u8]>) -> Result<KeyScanIter<'_>> {
+        Ok(KeyScanIter {
+            inner: ScanIter::new(
+                self,
+                self.canonical_prefix_opt(prefix)?.map(|p| p.into_owned()),
+                true,
+            )?,
+        })
+    }
+
+    /// NEW: единственный бакет, в котором могут лежать ключи с префиксом (маршрутизация по
+    /// префиксу ключа, dir/routing.rs); None — префиксу соответствуют ключи разных бакетов.
+    pub fn prefix_bucket(&self, prefix: &[u8]) -> Option<u32> {
+        let prefix = self.canonical_prefix(prefix).ok()?;
+        self.prefix_bucket_canonical(&prefix)
+    }
+
+    fn prefix_bucket_canonical(&self, prefix: &[u8]) -> Option<u32> {
+        let route = self.pager.meta.bucket_routing().route_of_prefix(prefix)?;
+        Some(crate::dir::bucket_for_key(
+            route,
+            crate::meta::HASH_KIND_XX64_SEED0,
+            self.dir.bucket_count,
+        ))
+    }
+
+    /// prefix_bucket для скана (с учётом метрики).
+    pub(super) fn scan_single_bucket(&self, prefix: Option<&[u8]>) -> Option<u32> {
+        let b = self.prefix_bucket_canonical(prefix?)?;
+        record_scan_prefix_single_bucket();
+        Some(b)
+    }
+
     // -------------------- keydir fast-path (с единым буфером) --------------------
 
-    fn scan_stream_via_keydir<F>(&self, prefix: Option<&[u8]>, mut cb: F) -> Result<()>
+    fn scan_stream_via_keydir<F>(&self, prefix: Option<&[u8]>, cb: F) -> Result<()>
+    where
+        F: FnMut(&[u8], &[u8]),
+    {
+        self.scan_stream_via_keydir_mode(prefix, false, cb)
+    }
+
+    /// keys_only: cb получает пустое значение, значения не читаются.
+    fn scan_stream_via_keydir_mode<F>(
+        &self,
+        prefix: Option<&[u8]>,
+        keys_only: bool,
+        mut cb: F,
+    ) -> Result<()>
     where
         F: FnMut(&[u8], &[u8]),
     {
@@ -75,29 +214,33 @@ impl Db {
         let ps = self.pager.meta.page_size as usize;
         let mut page_buf = vec![0u8; ps];
 
+        let single = self.scan_single_bucket(prefix);
         match prefix {
             None => {
                 self.mem_keydir_for_each(|_b, k, pid| {
                     if pid == NO_PAGE {
                         return;
                     }
-                    if let Ok(Some(v)) =
-                        self.value_from_pid_or_fallback_with_buf(k, pid, now, &mut page_buf)
-                    {
-                        cb(k, &v);
-                    }
+                    self.emit_from_pid_with_buf(k, pid, now, keys_only, &mut page_buf, &mut cb);
                 });
             }
+            Some(pref) if single.is_some() => {
+                let b = single.unwrap_or(0);
+                if let Some(kd) = self.mem_keydir.as_ref() {
+                    kd.for_each_in_bucket(b, Some(pref), |k, pid| {
+                        if pid == NO_PAGE {
+                            return;
+                        }
+                        self.emit_from_pid_with_buf(k, pid, now, keys_only, &mut page_buf, &mut cb);
+                    });
+                }
+            }
             Some(pref) => {
                 self.mem_keydir_for_each_prefix(pref, |_b, k, pid| {
                     if pid == NO_PAGE {
                         return;
                     }
-                    if let Ok(Some(v)) =
-                        self.value_from_pid_or_fallback_with_buf(k, pid, now, &mut page_buf)
-                    {
-                        cb(k, &v);
-                    }
+                    self.emit_from_pid_with_buf(k, pid, now, keys_only, &mut page_buf, &mut cb);
                 });
             }
         }
@@ -115,12 +258,58 @@ impl Db {
 
     // -------------------- chain-based (packed-aware, единый буфер) --------------------
 
-    fn scan_stream_via_chains<F>(&self, prefix: Option<&[u8]>, mut cb: F) -> Result<()>
+    fn scan_stream_via_chains<F>(&self, prefix: Option<&[u8]>, cb: F) -> Result<()>
+    where
+        F: FnMut(&[u8], &[u8]),
+    {
+        self.scan_stream_via_chains_mode(prefix, false, cb)
+    }
+
+    fn scan_stream_via_chains_mode<F>(
+        &self,
+        prefix: Option<&[u8]>,
+        keys_only: bool,
+        mut cb: F,
+    ) -> Result<()>
     where
         F: FnMut(&[u8], &[u8]),
     {
         let ps = self.pager.meta.page_size as usize;
-        let now = now_secs();
+        let mut page = vec![0u8; ps];
+        let opts = ChainScan {
+            prefix,
+            now: now_secs(),
+            keys_only,
+        };
+        // Снимок голов на момент начала скана (point-in-time)
+        let heads = self.dir.heads_snapshot()?;
+        if let Some(b) = self.scan_single_bucket(prefix) {
+            let head = heads[b as usize];
+            return self.scan_bucket_chain(b, head, &opts, &mut page, &mut |k, v, _, _| cb(k, v));
+        }
+        for (b, &head) in heads.iter().enumerate() {
+            self.scan_bucket_chain(b as u32, head, &opts, &mut page, &mut |k, v, _, _| cb(k, v))?;
+        }
+        Ok(())
+    }
+
+    /// Обход одной цепочки бакета от закреплённой головы (tail-wins, tombstone приоритетен).
+    /// Состояние решений — только по ключам этого бакета (ключ живёт ровно в одном бакете),
+    /// поэтому память скана ограничена размером бакета, а не всей БД.
+    /// cb получает (ключ, значение, expires_at_sec, page_id страницы решающей записи).
+    fn scan_bucket_chain<F>(
+        &self,
+        b: u32,
+        head: u64,
+        opts: &ChainScan<'_>,
+        page: &mut [u8],
+        cb: &mut F,
+    ) -> Result<()>
+    where
+        F: FnMut(&[u8], &[u8], u32, u64),
+    {
+        let ps = page.len();
+        let (prefix, now) = (opts.prefix, opts.now);
 
         // финальное состояние для ключей:
         enum State {
@@ -129,62 +318,164 @@ impl Db {
         }
 
         let mut state: HashMap<Vec<u8>, State> = HashMap::new();
-        let mut page = vec![0u8; ps];
-
-        for b in 0..self.dir.bucket_count {
-            let mut pid = self.dir.head(b)?;
-            while pid != NO_PAGE {
-                self.pager.read_page(pid, &mut page)?;
-                if &page[0..4] != PAGE_MAGIC {
-                    break;
-                }
-                let ptype = LittleEndian::read_u16(&page[6..8]);
-                if ptype != PAGE_TYPE_KV_RH3 {
-                    break;
-                }
+        let mut value_err: Option<anyhow::Error> = None;
+        let mut pid = head;
+        let mut ra = ReadaheadWindow::new();
+        while pid != NO_PAGE {
+            self.pager.read_page_ra(pid, page, &mut ra)?;
+            if &page[0..4] != PAGE_MAGIC {
+                break;
+            }
+            let ptype = LittleEndian::read_u16(&page[6..8]);
+            if ptype != PAGE_TYPE_KV_RH3 {
+                break;
+            }
 
-                let h = kv_header_read_v3(&page)?;
-                // Верхняя граница data-area
-                let data_end = data_end_for_page(&h, ps).unwrap_or(0);
+            let h = kv_header_read_v3(page)?;
+            // Верхняя граница data-area
+            let data_end = data_end_for_page(&h, ps).unwrap_or(0);
+            let page_lsn = h.lsn;
 
-                // Обход всех записей страницы в порядке "новые → старые"
-                for_each_records_newest_first(&page, data_end, |k, v, expires_at_sec, vflags| {
-                    // Если по ключу уже принято решение — пропускаем
-                    if state.contains_key(k) {
+            // Обход всех записей страницы в порядке "новые → старые"
+            for_each_records_newest_first(page, data_end, |k, v, expires_at_sec, vflags| {
+                // Если по ключу уже принято решение — пропускаем
+                if state.contains_key(k) {
+                    return;
+                }
+                // Префиксный фильтр
+                if let Some(pref) = prefix {
+                    if !k.starts_with(pref) {
                         return;
                     }
-                    // Префиксный фильтр
-                    if let Some(pref) = prefix {
-                        if !k.starts_with(pref) {
-                            return;
-                        }
-                    }
-
-                    let is_tomb = (vflags & 0x1) == 1;
-                    let ttl_ok = expires_at_sec == 0 || now < expires_at_sec;
+                }
 
-                    if is_tomb {
-                        // Tombstone имеет приоритет — фиксируем удаление
-                        state.insert(k.to_vec(), State::Deleted);
-                    } else if ttl_ok {
-                        // Валидная запись → раскрываем placeholder при необходимости
-                        if let Ok(value_bytes) = self.expand_value_if_needed(v) {
-                            cb(k, &value_bytes);
+                // NEW: запись под range tombstone (Db::delete_prefix) — удалена
+                let under_cut = self.range_cut(k).is_some_and(|cut| page_lsn <= cut);
+                let is_tomb = (vflags & 0x1) == 1 || under_cut;
+                let ttl_ok = expires_at_sec == 0 || now < expires_at_sec;
+
+                if is_tomb {
+                    // Tombstone имеет приоритет — фиксируем удаление
+                    state.insert(k.to_vec(), State::Deleted);
+                } else if ttl_ok && opts.keys_only {
+                    // keys-only: значение (и OVERFLOW-цепочку) не трогаем
+                    cb(k, &[], expires_at_sec, pid);
+                    state.insert(k.to_vec(), State::Selected);
+                } else if ttl_ok {
+                    // Валидная запись → раскрываем placeholder при необходимости
+                    match self.expand_value_if_needed(v) {
+                        Ok(value_bytes) => {
+                            cb(k, &value_bytes, expires_at_sec, pid);
                             state.insert(k.to_vec(), State::Selected);
                         }
-                    } else {
-                        // Протухшая запись — считаем метрику и ищем глубже
-                        record_ttl_skipped();
+                        Err(e) => {
+                            if value_err.is_none() {
+                                value_err = Some(e.context(format!(
+                                    "scan: bucket {} page {}: value is unreadable",
+                                    b, pid
+                                )));
+                            }
+                        }
                     }
-                });
+                } else {
+                    // Протухшая запись — считаем метрику и ищем глубже
+                    record_ttl_skipped();
+                }
+            });
 
-                pid = h.next_page_id;
+            if let Some(e) = value_err.take() {
+                return Err(e);
             }
+            pid = h.next_page_id;
         }
-
         Ok(())
     }
 
+    /// Число живых ключей с префиксом в одном бакете (keys-only обход от головы head).
+    pub(crate) fn count_bucket_keys(
+        &self,
+        b: u32,
+        head: u64,
+        prefix: &[u8],
+        now: u32,
+        page: &mut [u8],
+    ) -> Result<u64> {
+        if head == NO_PAGE {
+            return Ok(0);
+        }
+        let opts = ChainScan {
+            prefix: Some(prefix),
+            now,
+            keys_only: true,
+        };
+        let mut n = 0u64;
+        self.scan_bucket_chain(b, head, &opts, page, &mut |_k: &[u8], _v: &[u8], _e, _p| {
+            n += 1
+        })?;
+        Ok(n)
+    }
+
+    /// Живые записи одного бакета (ключ, раскрытое значение, expires_at_sec) от головы head;
+    /// живость — на момент now (digest сравнивает реплики при одном и том же now).
+    pub(crate) fn for_each_live_in_bucket<F>(
+        &self,
+        b: u32,
+        head: u64,
+        now: u32,
+        page: &mut [u8],
+        mut cb: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&[u8], &[u8], u32),
+    {
+        if head == NO_PAGE {
+            return Ok(());
+        }
+        let opts = ChainScan {
+            prefix: None,
+            now,
+            keys_only: false,
+        };
+        self.scan_bucket_chain(b, head, &opts, page, &mut |k, v, e, _| cb(k, v, e))
+    }
+
+    /// NEW (kvfs): живые ключи одного бакета (keys-only) от закреплённой головы head вместе с
+    /// page_id страницы решающей записи — точкой входа для get_from_page.
+    #[cfg(feature = "fuse")]
+    pub(crate) fn for_each_live_key_in_bucket<F>(
+        &self,
+        b: u32,
+        head: u64,
+        now: u32,
+        page: &mut [u8],
+        mut cb: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&[u8], u64),
+    {
+        if head == NO_PAGE {
+            return Ok(());
+        }
+        let opts = ChainScan {
+            prefix: None,
+            now,
+            keys_only: true,
+        };
+        self.scan_bucket_chain(b, head, &opts, page, &mut |k, _v, _e, pid| cb(k, pid))
+    }
+
+    /// NEW (kvfs): значение (канонического) ключа по цепочке, начиная с pid — страницы решающей
+    /// записи или закреплённой головы бакета. OVERFLOW раскрыт, value transform декодирован;
+    /// None — tombstone, ключа нет или TTL истёк.
+    #[cfg(feature = "fuse")]
+    pub(crate) fn get_from_page(&self, key: &[u8], pid: u64) -> Result<Option<Vec<u8>>> {
+        let mut page = vec![0u8; self.pager.meta.page_size as usize];
+        match self.value_from_pid_or_fallback_with_buf(key, pid, now_secs(), &mut page)? {
+            Some(v) => Ok(Some(self.decode_value(v)?)),
+            None => Ok(None),
+        }
+    }
+
     fn scan_materialized_via_chains(
         &self,
         prefix: Option<&[u8]>,
@@ -206,9 +497,12 @@ impl Db {
         now: u32,
         page_buf: &mut [u8],
     ) -> Result<Option<Vec<u8>>> {
+        if let Some(cut) = self.range_cut(key) {
+            return self.get_above_range_cut(key, cut, now);
+        }
         let mut cur = pid;
         loop {
-            match self.read_one_kv_page_decide_with_buf(key, cur, now, page_buf)? {
+            match self.read_one_kv_page_decide_with_buf(key, cur, now, true, page_buf)? {
                 DecideResult::Value(v, _next) => return Ok(Some(v)),
                 DecideResult::Tombstone(_next) => return Ok(None),
                 DecideResult::Continue(next) => {
@@ -221,13 +515,65 @@ impl Db {
         }
     }
 
+    /// keys-only вариант: жив ли ключ, начиная с pid (значение не раскрывается).
+    fn key_live_from_pid_with_buf(
+        &self,
+        key: &[u8],
+        pid: u64,
+        now: u32,
+        page_buf: &mut [u8],
+    ) -> Result<bool> {
+        if let Some(cut) = self.range_cut(key) {
+            return self.exists_above_range_cut(key, cut, now);
+        }
+        let mut cur = pid;
+        loop {
+            match self.read_one_kv_page_decide_with_buf(key, cur, now, false, page_buf)? {
+                DecideResult::Value(..) => return Ok(true),
+                DecideResult::Tombstone(_) => return Ok(false),
+                DecideResult::Continue(next) => {
+                    if next == NO_PAGE {
+                        return Ok(false);
+                    }
+                    cur = next;
+                }
+            }
+        }
+    }
+
+    /// keydir-путь: отдать ключ (и значение, если !keys_only) в cb; ошибки чтения — пропуск ключа.
+    #[inline]
+    fn emit_from_pid_with_buf<F>(
+        &self,
+        key: &[u8],
+        pid: u64,
+        now: u32,
+        keys_only: bool,
+        page_buf: &mut [u8],
+        cb: &mut F,
+    ) where
+        F: FnMut(&[u8], &[u8]),
+    {
+        if keys_only {
+            if let Ok(true) = self.key_live_from_pid_with_buf(key, pid, now, page_buf) {
+                cb(key, &[]);
+            }
+        } else if let Ok(Some(v)) =
+            self.value_from_pid_or_fallback_with_buf(key, pid, now, page_buf)
+        {
+            cb(key, &v);
+        }
+    }
+
     /// Решение по одной KV‑странице для конкретного ключа (packed-aware, использует kv_find_record_by_key).
+    /// expand=false — значение не раскрывается (DecideResult::Value с пустым вектором).
     #[inline]
     fn read_one_kv_page_decide_with_buf(
         &self,
         key: &[u8],
         pid: u64,
         now: u32,
+        expand: bool,
         page_buf: &mut [u8],
     ) -> Result<DecideResult> {
         if pid == NO_PAGE {
@@ -257,6 +603,9 @@ impl Db {
                 record_ttl_skipped();
                 return Ok(DecideResult::Continue(next));
             }
+            if !expand {
+                return Ok(DecideResult::Value(Vec::new(), next));
+            }
             let val = self.expand_value_if_needed(v)?;
             return Ok(DecideResult::Value(val, next));
         }
@@ -266,7 +615,7 @@ impl Db {
 
     /// Раскрыть значение (OVERFLOW placeholder → байты).
     #[inline]
-    fn expand_value_if_needed(&self, v: &[u8]) -> Result<Vec<u8>> {
+    pub(super) fn expand_value_if_needed(&self, v: &[u8]) -> Result<Vec<u8>> {
         if let Some((total_len, head_pid)) = decode_ovf_placeholder_v3(v) {
             page_ovf_chain::read_overflow_chain(&self.pager, head_pid, total_len as usize)
         } else {
@@ -275,8 +624,146 @@ impl Db {
     }
 }
 
+// -------------------- ScanIter --------------------
+
+/// Ленивый скан (Db::scan_iter / Db::scan_prefix_iter): бакет за бакетом от голов,
+/// закреплённых при создании итератора. Порядок — по бакетам (не по ключу).
+/// После первой ошибки итератор завершается.
+pub struct ScanIter<'a> {
+    db: &'a Db,
+    prefix: Option<Vec<u8>>,
+    /// Закреплённые головы (chain-путь); None — keydir-путь.
+    heads: Option<Vec<u64>>,
+    next_bucket: u32,
+    buckets: u32,
+    now: u32,
+    page: Vec<u8>,
+    pending: VecDeque<(Vec<u8>, Vec<u8>)>,
+    /// keys-only (KeyScanIter): значения в pending пустые.
+    keys_only: bool,
+    done: bool,
+}
+
+impl<'a> ScanIter<'a> {
+    fn new(db: &'a Db, prefix: Option<Vec<u8>>, keys_only: bool) -> Result<Self> {
+        let heads = if db.has_mem_keydir() {
+            None
+        } else {
+            Some(db.dir.heads_snapshot()?)
+        };
+        let mut buckets = heads
+            .as_ref()
+            .map_or(db.dir.bucket_count, |h| h.len() as u32);
+        let mut next_bucket = 0;
+        // NEW: префикс задаёт маршрутный ключ — обходим только его бакет
+        if let Some(b) = db.scan_single_bucket(prefix.as_deref()) {
+            next_bucket = b;
+            buckets = b + 1;
+        }
+        Ok(Self {
+            db,
+            prefix,
+            buckets,
+            heads,
+            next_bucket,
+            now: now_secs(),
+            page: vec![0u8; db.pager.meta.page_size as usize],
+            pending: VecDeque::new(),
+            keys_only,
+            done: false,
+        })
+    }
+
+    /// Просканировать следующий бакет в pending.
+    fn fill_bucket(&mut self, b: u32) -> Result<()> {
+        let db = self.db;
+        let prefix = self.prefix.as_deref();
+        let pending = &mut self.pending;
+        let mut push = |k: &[u8], v: &[u8]| pending.push_back((k.to_vec(), v.to_vec()));
+        match self.heads.as_ref() {
+            Some(heads) => {
+                let head = heads[b as usize];
+                if head != NO_PAGE {
+                    let opts = ChainScan {
+                        prefix,
+                        now: self.now,
+                        keys_only: self.keys_only,
+                    };
+                    db.scan_bucket_chain(b, head, &opts, &mut self.page, &mut |k, v, _, _| {
+                        push(k, v)
+                    })?;
+                }
+            }
+            None => {
+                // keydir fast-path: как scan_stream_via_keydir, только один бакет
+                let (now, keys_only) = (self.now, self.keys_only);
+                let page = &mut self.page;
+                if let Some(kd) = db.mem_keydir.as_ref() {
+                    kd.for_each_in_bucket(b, prefix, |k, pid| {
+                        if pid == NO_PAGE {
+                            return;
+                        }
+                        db.emit_from_pid_with_buf(k, pid, now, keys_only, page, &mut push);
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for ScanIter<'_> {
+    type Item = Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((k, v)) = self.pending.pop_front() {
+                if self.keys_only {
+                    return Some(Ok((k, v)));
+                }
+                let r = self.db.decode_value(v).map(|v| (k, v));
+                if r.is_err() {
+                    self.done = true;
+                    self.pending.clear();
+                }
+                return Some(r);
+            }
+            if self.done || self.next_bucket >= self.buckets {
+                return None;
+            }
+            let b = self.next_bucket;
+            self.next_bucket += 1;
+            if let Err(e) = self.fill_bucket(b) {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// Ленивый keys-only скан (Db::scan_keys_iter): как ScanIter, но без чтения значений.
+pub struct KeyScanIter<'a> {
+    inner: ScanIter<'a>,
+}
+
+impl Iterator for KeyScanIter<'_> {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|r| r.map(|(k, _)| k))
+    }
+}
+
 // -------------------- локальные помощники --------------------
 
+/// Параметры chain-скана бакета.
+struct ChainScan<'p> {
+    prefix: Option<&'p [u8]>,
+    now: u32,
+    /// Только ключи: значения не раскрываются (cb получает пустой срез).
+    keys_only: bool,
+}
+
 #[derive(Debug)]
 enum DecideResult {
     Value(Vec<u8>, u64), // value, next_pid
@@ -288,7 +775,7 @@ enum DecideResult {
 
 /// Верхняя граница data‑area (до slot‑таблицы). None при переполнении вычислений.
 #[inline]
-fn data_end_for_page(hdr: &crate::page::kv::KvHeaderV3, ps: usize) -> Option<usize> {
+pub(super) fn data_end_for_page(hdr: &crate::page::kv::KvHeaderV3, ps: usize) -> Option<usize> {
     if hdr.table_slots == 0 {
         ps.checked_sub(TRAILER_LEN)
     } else {
@@ -324,7 +811,7 @@ fn read_record_at_checked<'a>(
 }
 
 /// Обойти все записи на странице в порядке "новые → старые" (reverse слоты).
-fn for_each_records_newest_first<'a, F>(page: &'a [u8], data_end: usize, mut f: F)
+pub(super) fn for_each_records_newest_first<'a, F>(page: &'a [u8], data_end: usize, mut f: F)
 where
     F: FnMut(&'a [u8], &'a [u8], u32, u8),
 {
@@ -0,0 +1,408 @@
+//! db/scan_page — постраничный скан со стабильным курсором (Db::scan_page).
+//!
+//! HTTP-API листает большое keyspace без состояния на сервере: вызов отдаёт до limit пар и
+//! непрозрачный курсор (base64url), с которого продолжает следующий вызов — в том же или в
+//! другом процессе. Курсор кодирует позицию chain-скана: бакет, закреплённую голову его цепочки,
+//! страницу и порядковый номер записи на ней (в порядке "новые → старые"), время скана (TTL-решения
+//! не плывут между вызовами), pin_lsn и тег префикса.
+//!
+//! Стабильность: страницы цепочек не переписываются (put/compaction пишут новые), поэтому курсор
+//! продолжает обход от той же головы, даже если бакет с тех пор дописан или компактирован, —
+//! внутри бакета результат соответствует состоянию на момент входа в него, без повторов и
+//! пропусков. Tail-wins решения по уже пройденной части восстанавливаются keys-only проходом от
+//! головы до позиции курсора (значения и OVERFLOW не читаются). Бакеты, до которых скан ещё не
+//! дошёл, берутся по текущей голове.
+//!
+//! Закрепление (snapshot pinning): освободить страницы старой цепочки могут
+//! sweep_orphan_overflow и alloc_check_fix. Пока курсор моложе QuiverConfig::scan_cursor_pin_secs,
+//! хэндл держит закрепление его цепочки, и оба оставляют её страницы (включая OVERFLOW-цепочки
+//! записей) нетронутыми. Закрепление — in-process: другой хэндл о нём не знает. Переиспользованная
+//! страница всё равно обнаруживается: pin_lsn — максимальный LSN страниц, виденных курсором, и
+//! страница на пути к позиции с большим LSN, не того типа или недостижимая позиция —
+//! ScanCursorInvalidError (как и испорченный курсор или курсор другого префикса). Клиент
+//! начинает скан заново (cursor = None).
+//!
+//! Порядок — по бакетам, внутри бакета — от головы к хвосту (не по ключу). Ключи — канонические
+//! (db/key_transform.rs); префикс, задающий маршрутный ключ (dir/routing.rs), сводит обход к
+//! одному бакету. Keydir-путь не используется: позиция курсора — страница цепочки.
+//! Значения декодируются трансформацией хэндла (db/value_transform.rs).
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::dir::NO_PAGE;
+use crate::metrics::{record_scan_cursor_invalid, record_scan_page_call, record_ttl_skipped};
+use crate::page::kv::kv_for_each_record;
+use crate::page::ovf::chain::OVF_MAX_CHAIN_PAGES_GUARD;
+use crate::page::{kv_header_read_v3, ovf_header_read_v3, PAGE_MAGIC, PAGE_TYPE_KV_RH3};
+use crate::util::{decode_ovf_placeholder_v3, now_secs};
+
+use super::core::Db;
+use super::scan::{data_end_for_page, for_each_records_newest_first};
+
+/// Пары страницы scan_page и курсор следующей (None — скан завершён).
+pub type ScanPageResult = (Vec<(Vec<u8>, Vec<u8>)>, Option<String>);
+
+/// Курсор scan_page нельзя продолжить (испорчен, другого префикса или цепочка переписана).
+/// Достаётся из anyhow через downcast_ref; скан начинают заново.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanCursorInvalidError {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl fmt::Display for ScanCursorInvalidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "scan cursor is not valid for {}: {} (restart the scan without a cursor)",
+            self.path.display(),
+            self.reason
+        )
+    }
+}
+
+impl std::error::Error for ScanCursorInvalidError {}
+
+// -------------------- курсор --------------------
+
+const SCAN_CURSOR_VERSION: u8 = 1;
+// version u8 + bucket u32 + head u64 + page u64 + slot u32 + pin_lsn u64 + now u32 + tag u32
+const SCAN_CURSOR_LEN: usize = 41;
+
+/// Позиция постраничного скана (сериализуется в курсор).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ScanCursor {
+    bucket: u32,
+    /// Закреплённая голова цепочки бакета (NO_PAGE — бакет ещё не начат).
+    head: u64,
+    /// Страница позиции; slot — номер следующей записи на ней (новые → старые).
+    page_id: u64,
+    slot: u32,
+    /// Максимальный LSN страниц цепочки, виденных курсором.
+    pin_lsn: u64,
+    /// Время TTL-решений текущего бакета.
+    now: u32,
+    /// crc32c канонического префикса (курсор другого скана отвергается).
+    prefix_tag: u32,
+}
+
+impl ScanCursor {
+    /// Начало бакета (голова закрепляется при входе).
+    fn bucket_start(bucket: u32, prefix_tag: u32) -> Self {
+        Self {
+            bucket,
+            head: NO_PAGE,
+            page_id: NO_PAGE,
+            slot: 0,
+            pin_lsn: 0,
+            now: 0,
+            prefix_tag,
+        }
+    }
+
+    fn encode(&self) -> String {
+        let mut b = [0u8; SCAN_CURSOR_LEN];
+        b[0] = SCAN_CURSOR_VERSION;
+        LittleEndian::write_u32(&mut b[1..5], self.bucket);
+        LittleEndian::write_u64(&mut b[5..13], self.head);
+        LittleEndian::write_u64(&mut b[13..21], self.page_id);
+        LittleEndian::write_u32(&mut b[21..25], self.slot);
+        LittleEndian::write_u64(&mut b[25..33], self.pin_lsn);
+        LittleEndian::write_u32(&mut b[33..37], self.now);
+        LittleEndian::write_u32(&mut b[37..41], self.prefix_tag);
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(b)
+    }
+
+    fn decode(s: &str) -> Option<Self> {
+        let b = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(s.trim())
+            .ok()?;
+        if b.len() != SCAN_CURSOR_LEN || b[0] != SCAN_CURSOR_VERSION {
+            return None;
+        }
+        Some(Self {
+            bucket: LittleEndian::read_u32(&b[1..5]),
+            head: LittleEndian::read_u64(&b[5..13]),
+            page_id: LittleEndian::read_u64(&b[13..21]),
+            slot: LittleEndian::read_u32(&b[21..25]),
+            pin_lsn: LittleEndian::read_u64(&b[25..33]),
+            now: LittleEndian::read_u32(&b[33..37]),
+            prefix_tag: LittleEndian::read_u32(&b[37..41]),
+        })
+    }
+}
+
+fn prefix_tag(prefix: Option<&[u8]>) -> u32 {
+    crc32c::crc32c(prefix.unwrap_or(&[]))
+}
+
+// -------------------- закрепления --------------------
+
+/// Закрепления цепочек курсорами хэндла: (бакет, голова) → срок.
+#[derive(Debug, Default)]
+pub(crate) struct ScanPins {
+    pins: Mutex<HashMap<(u32, u64), Instant>>,
+}
+
+impl ScanPins {
+    fn pin(&self, bucket: u32, head: u64, ttl: Duration) {
+        let deadline = Instant::now() + ttl;
+        let mut g = self.pins.lock().unwrap_or_else(|e| e.into_inner());
+        let e = g.entry((bucket, head)).or_insert(deadline);
+        *e = (*e).max(deadline);
+    }
+
+    /// Головы живых закреплений (истёкшие удаляются).
+    pub(super) fn live_heads(&self) -> Vec<u64> {
+        let now = Instant::now();
+        let mut g = self.pins.lock().unwrap_or_else(|e| e.into_inner());
+        g.retain(|_, deadline| *deadline > now);
+        g.keys().map(|&(_, head)| head).collect()
+    }
+}
+
+impl Db {
+    /// Страница постраничного скана: до limit пар (ключ, значение) с префиксом и курсор следующей
+    /// страницы (None — скан завершён). cursor = None — начать сначала. Короче limit бывает только
+    /// последняя страница (в том числе пустая). См. модульный комментарий.
+    pub fn scan_page(
+        &self,
+        prefix: Option<&[u8]>,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<ScanPageResult> {
+        let prefix = self.canonical_prefix_opt(prefix)?;
+        let prefix = prefix.as_deref();
+        record_scan_page_call();
+
+        let tag = prefix_tag(prefix);
+        let (first, end) = match self.scan_single_bucket(prefix) {
+            Some(b) => (b, b + 1),
+            None => (0, self.dir.bucket_count),
+        };
+        let mut cur = match cursor {
+            None => ScanCursor::bucket_start(first, tag),
+            Some(s) => ScanCursor::decode(s)
+                .filter(|c| c.prefix_tag == tag && (first..end).contains(&c.bucket))
+                .ok_or_else(|| self.scan_cursor_invalid("malformed cursor or another prefix"))?,
+        };
+
+        let limit = limit.max(1);
+        let mut items: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+        let mut page = vec![0u8; self.pager.meta.page_size as usize];
+        while cur.bucket < end {
+            if cur.head == NO_PAGE {
+                // Вход в бакет: закрепляем текущую голову
+                let head = self.dir.head(cur.bucket)?;
+                if head == NO_PAGE {
+                    cur.bucket += 1;
+                    continue;
+                }
+                cur.head = head;
+                cur.page_id = head;
+                cur.slot = 0;
+                cur.pin_lsn = self.pager.meta.last_lsn;
+                cur.now = now_secs();
+            }
+            if !self.scan_page_bucket(&mut cur, prefix, limit, &mut items, &mut page)? {
+                // limit набран посреди бакета
+                if self.scan_cursor_pin_secs > 0 {
+                    let ttl = Duration::from_secs(self.scan_cursor_pin_secs);
+                    self.scan_pins.pin(cur.bucket, cur.head, ttl);
+                }
+                return Ok((self.decode_pairs(items)?, Some(cur.encode())));
+            }
+            cur = ScanCursor::bucket_start(cur.bucket + 1, tag);
+            if items.len() >= limit {
+                // Бакет кончился ровно на limit: пустые бакеты впереди пропускаем
+                while cur.bucket < end && self.dir.head(cur.bucket)? == NO_PAGE {
+                    cur.bucket += 1;
+                }
+                let next = (cur.bucket < end).then(|| cur.encode());
+                return Ok((self.decode_pairs(items)?, next));
+            }
+        }
+        Ok((self.decode_pairs(items)?, None))
+    }
+
+    /// Обход бакета от cur.head; записи до позиции (cur.page_id, cur.slot) только восстанавливают
+    /// решения. true — бакет пройден; false — набран limit, cur указывает на следующую запись.
+    fn scan_page_bucket(
+        &self,
+        cur: &mut ScanCursor,
+        prefix: Option<&[u8]>,
+        limit: usize,
+        items: &mut Vec<(Vec<u8>, Vec<u8>)>,
+        page: &mut [u8],
+    ) -> Result<bool> {
+        let ps = page.len();
+        let now = cur.now;
+        // Ключи, по которым решение уже принято (отдан или удалён tombstone'ом)
+        let mut decided: HashSet<Vec<u8>> = HashSet::new();
+        let mut replay = !(cur.page_id == cur.head && cur.slot == 0);
+        let mut visited: HashSet<u64> = HashSet::new();
+        let mut pid = cur.head;
+
+        while pid != NO_PAGE {
+            let is_kv = visited.insert(pid)
+                && self.pager.read_page(pid, page).is_ok()
+                && &page[0..4] == PAGE_MAGIC
+                && LittleEndian::read_u16(&page[6..8]) == PAGE_TYPE_KV_RH3;
+            if !is_kv {
+                if replay {
+                    return Err(self.scan_cursor_invalid("bucket chain was rewritten"));
+                }
+                break;
+            }
+            let h = kv_header_read_v3(page)?;
+            if replay && h.lsn > cur.pin_lsn {
+                return Err(self.scan_cursor_invalid("chain page was reused"));
+            }
+            cur.pin_lsn = cur.pin_lsn.max(h.lsn);
+
+            let at_cursor = replay && pid == cur.page_id;
+            // Записи с номером < replay_until уже пройдены предыдущими вызовами
+            let replay_until = match (replay, at_cursor) {
+                (false, _) => 0,
+                (true, true) => cur.slot,
+                (true, false) => u32::MAX,
+            };
+            let data_end = data_end_for_page(&h, ps).unwrap_or(0);
+            let page_lsn = h.lsn;
+            let mut ord = 0u32;
+            let mut stop: Option<u32> = None;
+            let mut value_err: Option<anyhow::Error> = None;
+
+            for_each_records_newest_first(page, data_end, |k, v, expires_at_sec, vflags| {
+                let i = ord;
+                ord += 1;
+                if stop.is_some() || value_err.is_some() {
+                    return;
+                }
+                let replaying = i < replay_until;
+                if !replaying && items.len() >= limit {
+                    stop = Some(i);
+                    return;
+                }
+                if decided.contains(k) {
+                    return;
+                }
+                if let Some(pref) = prefix {
+                    if !k.starts_with(pref) {
+                        return;
+                    }
+                }
+                // Решения — как у scan_bucket_chain (tombstone приоритетен, TTL, range tombstone)
+                let under_cut = self.range_cut(k).is_some_and(|cut| page_lsn <= cut);
+                let is_tomb = (vflags & 0x1) == 1 || under_cut;
+                let ttl_ok = expires_at_sec == 0 || now < expires_at_sec;
+                if is_tomb || (ttl_ok && replaying) {
+                    decided.insert(k.to_vec());
+                } else if ttl_ok {
+                    match self.expand_value_if_needed(v) {
+                        Ok(value) => {
+                            items.push((k.to_vec(), value));
+                            decided.insert(k.to_vec());
+                        }
+                        Err(e) => {
+                            value_err = Some(e.context(format!(
+                                "scan_page: bucket {} page {}: value is unreadable",
+                                cur.bucket, pid
+                            )));
+                        }
+                    }
+                } else if !replaying {
+                    record_ttl_skipped();
+                }
+            });
+
+            if let Some(e) = value_err {
+                return Err(e);
+            }
+            if at_cursor {
+                if ord < cur.slot {
+                    return Err(self.scan_cursor_invalid("cursor slot is past the page end"));
+                }
+                replay = false;
+            }
+            if let Some(i) = stop {
+                cur.page_id = pid;
+                cur.slot = i;
+                return Ok(false);
+            }
+            if items.len() >= limit && h.next_page_id != NO_PAGE {
+                // Позиция — за последней записью страницы (страница уже видена курсором)
+                cur.page_id = pid;
+                cur.slot = ord;
+                return Ok(false);
+            }
+            pid = h.next_page_id;
+        }
+        if replay {
+            return Err(self.scan_cursor_invalid("cursor page is not in the bucket chain"));
+        }
+        Ok(true)
+    }
+
+    fn scan_cursor_invalid(&self, reason: &str) -> anyhow::Error {
+        record_scan_cursor_invalid();
+        anyhow!(ScanCursorInvalidError {
+            path: self.root.clone(),
+            reason: reason.to_string(),
+        })
+    }
+
+    /// Страницы KV-цепочек от голов и OVERFLOW-цепочек их записей (best-effort).
+    pub(crate) fn pages_reachable_from(&self, heads: Vec<u64>) -> HashSet<u64> {
+        let mut out: HashSet<u64> = HashSet::new();
+        if heads.is_empty() {
+            return out;
+        }
+        let ps = self.pager.meta.page_size as usize;
+        let mut page = vec![0u8; ps];
+        for head in heads {
+            let mut ovf_heads: Vec<u64> = Vec::new();
+            let mut pid = head;
+            while pid != NO_PAGE && out.insert(pid) {
+                if self.pager.read_page(pid, &mut page).is_err() {
+                    break;
+                }
+                let h = match kv_header_read_v3(&page) {
+                    Ok(h) => h,
+                    Err(_) => break,
+                };
+                kv_for_each_record(&page, |_k, v, _exp, vflags| {
+                    if (vflags & 0x1) != 0 {
+                        return;
+                    }
+                    if let Some((_total, ovf_head)) = decode_ovf_placeholder_v3(v) {
+                        ovf_heads.push(ovf_head);
+                    }
+                });
+                pid = h.next_page_id;
+            }
+            for ovf_head in ovf_heads {
+                let mut cur = ovf_head;
+                let mut guard = 0usize;
+                while cur != NO_PAGE && guard < OVF_MAX_CHAIN_PAGES_GUARD && out.insert(cur) {
+                    guard += 1;
+                    if self.pager.read_page(cur, &mut page).is_err() {
+                        break;
+                    }
+                    cur = match ovf_header_read_v3(&page) {
+                        Ok(h) => h.next_page_id,
+                        Err(_) => break,
+                    };
+                }
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,369 @@
+//! db/scrub — фоновый read-only scrub: непрерывная проверка трейлеров (CRC/AEAD) всех страниц.
+//!
+//! Идея как у ZFS scrub, но по сегментам QuiverDB:
+//! - Проходим page_id ∈ [0 .. meta.next_page_id) с ограничением скорости чтения (bytes/sec).
+//! - Страницы читаются мимо page cache (Pager::read_page_uncached) — кэш не маскирует
+//!   повреждения на носителе.
+//! - Прогресс (курсор) персистится в <root>/.scrub_state.json, поэтому прерванный проход
+//!   продолжается с места остановки (в т.ч. после рестарта процесса).
+//! - Для каждого сегмента записывается время последней полной проверки и число сбоев.
+//! - Сбои оформляются структурированными алертами (ScrubAlert): они возвращаются в отчёте
+//!   и дописываются JSON-строками в <root>/.scrub_alerts.jsonl.
+//!
+//! Scrub не пишет в сегменты/мета и работает поверх RO-хэндла (Db::open_ro).
+//! Состояние scrub — служебный side-car, не влияет на on-disk формат.
+//!
+//! NEW: scrub_step_at — шаг по пути без shared LOCK (Db::open_ro_unlocked), как у CLI: scrub
+//! рядом с живым writer'ом не блокирует его открытие. Сбойная страница в этом режиме
+//! перечитывается (recheck_failures) — первое чтение могло попасть на её запись writer'ом.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::metrics::{record_scrub_failure, record_scrub_pages, record_scrub_pass_completed};
+use crate::util::now_secs;
+
+use super::core::Db;
+
+pub const SCRUB_STATE_FILE: &str = ".scrub_state.json";
+pub const SCRUB_ALERTS_FILE: &str = ".scrub_alerts.jsonl";
+
+const SCRUB_STATE_VERSION: u32 = 1;
+
+/// Как часто (в страницах) сбрасывать курсор на диск внутри шага.
+const CHECKPOINT_EVERY_DEFAULT: u64 = 1024;
+
+/// Пауза перед повторным чтением сбойной страницы (recheck_failures).
+const RECHECK_DELAY: Duration = Duration::from_millis(20);
+
+#[inline]
+pub fn scrub_state_path(root: &Path) -> PathBuf {
+    root.join(SCRUB_STATE_FILE)
+}
+
+#[inline]
+pub fn scrub_alerts_path(root: &Path) -> PathBuf {
+    root.join(SCRUB_ALERTS_FILE)
+}
+
+/// Параметры scrub.
+#[derive(Debug, Clone)]
+pub struct ScrubOptions {
+    /// Лимит скорости чтения (байт/сек). 0 — без ограничения.
+    pub max_bytes_per_sec: u64,
+    /// Максимум страниц за один шаг run_step(). 0 — до конца текущего прохода.
+    pub max_pages: u64,
+    /// Период сброса курсора на диск (в страницах).
+    pub checkpoint_every: u64,
+    /// Дописывать алерты в <root>/.scrub_alerts.jsonl.
+    pub persist_alerts: bool,
+    /// Перед алертом перечитать сбойную страницу (scrub рядом с живым writer'ом).
+    pub recheck_failures: bool,
+}
+
+impl Default for ScrubOptions {
+    fn default() -> Self {
+        Self {
+            max_bytes_per_sec: 0,
+            max_pages: 0,
+            checkpoint_every: CHECKPOINT_EVERY_DEFAULT,
+            persist_alerts: true,
+            recheck_failures: false,
+        }
+    }
+}
+
+/// Информация по сегменту (обновляется, когда scrub проходит сегмент целиком).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SegmentScrubInfo {
+    /// Unix-время (сек) последней полной проверки сегмента.
+    pub last_verified_at: u64,
+    /// Число сбоев в последней полной проверке.
+    pub failures: u64,
+}
+
+/// Персистентное состояние scrub (<root>/.scrub_state.json).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScrubState {
+    pub version: u32,
+    /// Следующий page_id для проверки в текущем проходе.
+    pub cursor: u64,
+    /// Unix-время начала текущего прохода (0 — проход ещё не начинался).
+    pub pass_started_at: u64,
+    pub passes_completed: u64,
+    pub last_pass_completed_at: u64,
+    /// Сбои в текущем (незавершённом) сегменте.
+    pub seg_failures_pending: u64,
+    /// seg_no → информация о последней проверке.
+    pub segments: BTreeMap<u64, SegmentScrubInfo>,
+}
+
+impl ScrubState {
+    /// Загрузить состояние (пустое, если файла нет).
+    pub fn load(root: &Path) -> Result<Self> {
+        let p = scrub_state_path(root);
+        if !p.exists() {
+            return Ok(Self {
+                version: SCRUB_STATE_VERSION,
+                ..Default::default()
+            });
+        }
+        let bytes = std::fs::read(&p).with_context(|| format!("read {}", p.display()))?;
+        let st: ScrubState = serde_json::from_slice(&bytes)
+            .with_context(|| format!("parse scrub state {}", p.display()))?;
+        Ok(st)
+    }
+
+    /// Сохранить состояние атомарно (tmp + rename).
+    pub fn store(&self, root: &Path) -> Result<()> {
+        let p = scrub_state_path(root);
+        let tmp = root.join(format!("{}.tmp", SCRUB_STATE_FILE));
+        let bytes = serde_json::to_vec_pretty(self)?;
+        {
+            let mut f = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&tmp)
+                .with_context(|| format!("open {}", tmp.display()))?;
+            f.write_all(&bytes)?;
+            let _ = f.sync_all();
+        }
+        crate::util::fsx::replace_file(&tmp, &p)
+            .with_context(|| format!("rename {} -> {}", tmp.display(), p.display()))?;
+        Ok(())
+    }
+}
+
+/// Структурированный алерт о сбое проверки страницы.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrubAlert {
+    pub ts: u64,
+    pub page_id: u64,
+    pub seg_no: u64,
+    /// "integrity" (CRC/AEAD mismatch) или "io".
+    pub kind: String,
+    pub error: String,
+}
+
+/// Отчёт одного шага scrub.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScrubReport {
+    pub mode: String,
+    pub pages_total: u64,
+    pub pages_scanned: u64,
+    pub ok_pages: u64,
+    pub integrity_fail: u64,
+    pub io_fail: u64,
+    pub cursor: u64,
+    /// true — в этом шаге проход дошёл до конца (курсор сброшен в 0).
+    pub pass_completed: bool,
+    pub passes_completed: u64,
+    pub elapsed_ms: u64,
+    pub alerts: Vec<ScrubAlert>,
+}
+
+/// Scrubber поверх RO (или writer) хэндла Db.
+pub struct Scrubber<'a> {
+    db: &'a Db,
+    opts: ScrubOptions,
+    state: ScrubState,
+}
+
+impl<'a> Scrubber<'a> {
+    /// Создать scrubber и загрузить сохранённый прогресс.
+    pub fn new(db: &'a Db, opts: ScrubOptions) -> Result<Self> {
+        let mut state = ScrubState::load(&db.root)?;
+        // Если БД «усохла» (restore и т.п.) — начинаем заново.
+        if state.cursor > db.pager.meta.next_page_id {
+            state.cursor = 0;
+            state.pass_started_at = 0;
+            state.seg_failures_pending = 0;
+        }
+        Ok(Self { db, opts, state })
+    }
+
+    pub fn state(&self) -> &ScrubState {
+        &self.state
+    }
+
+    /// Сбросить прогресс (следующий шаг начнёт новый проход с page_id=0).
+    pub fn reset(&mut self) -> Result<()> {
+        self.state.cursor = 0;
+        self.state.pass_started_at = 0;
+        self.state.seg_failures_pending = 0;
+        self.state.store(&self.db.root)
+    }
+
+    /// Выполнить один шаг: до opts.max_pages страниц (или до конца прохода).
+    pub fn run_step(&mut self) -> Result<ScrubReport> {
+        let ps = self.db.pager.meta.page_size as usize;
+        let pages_total = self.db.pager.meta.next_page_id;
+        let pps = self.db.pager.pages_per_seg();
+        let checkpoint_every = self.opts.checkpoint_every.max(1);
+
+        let mut rep = ScrubReport {
+            mode: if self.db.pager.tde_enabled() {
+                "aead".into()
+            } else {
+                "crc".into()
+            },
+            pages_total,
+            ..Default::default()
+        };
+
+        if self.state.pass_started_at == 0 {
+            self.state.pass_started_at = now_unix();
+        }
+
+        let t0 = Instant::now();
+        let mut buf = vec![0u8; ps];
+
+        while self.state.cursor < pages_total {
+            if self.opts.max_pages > 0 && rep.pages_scanned >= self.opts.max_pages {
+                break;
+            }
+            let pid = self.state.cursor;
+            let seg_no = pid / pps + 1;
+            if pid.is_multiple_of(pps) {
+                self.state.seg_failures_pending = 0;
+            }
+
+            let mut res = self.db.pager.read_page_uncached(pid, &mut buf);
+            if res.is_err() && self.opts.recheck_failures {
+                std::thread::sleep(RECHECK_DELAY);
+                res = self.db.pager.read_page_uncached(pid, &mut buf);
+            }
+            match res {
+                Ok(()) => rep.ok_pages += 1,
+                Err(e) => {
+                    let msg = e.to_string();
+                    let msg_l = msg.to_ascii_lowercase();
+                    // Та же классификация, что и в doctor
+                    let kind = if msg_l.contains("checksum") || msg_l.contains("tag") {
+                        rep.integrity_fail += 1;
+                        "integrity"
+                    } else {
+                        rep.io_fail += 1;
+                        "io"
+                    };
+                    self.state.seg_failures_pending += 1;
+                    record_scrub_failure();
+                    rep.alerts.push(ScrubAlert {
+                        ts: now_unix(),
+                        page_id: pid,
+                        seg_no,
+                        kind: kind.to_string(),
+                        error: format!("{:#}", e),
+                    });
+                }
+            }
+            rep.pages_scanned += 1;
+            self.state.cursor += 1;
+
+            // Сегмент пройден целиком (или это хвост БД)
+            if self.state.cursor.is_multiple_of(pps) || self.state.cursor == pages_total {
+                self.state.segments.insert(
+                    seg_no,
+                    SegmentScrubInfo {
+                        last_verified_at: now_unix(),
+                        failures: self.state.seg_failures_pending,
+                    },
+                );
+                self.state.seg_failures_pending = 0;
+            }
+
+            if rep.pages_scanned.is_multiple_of(checkpoint_every) {
+                self.state.store(&self.db.root)?;
+            }
+
+            self.throttle(t0, rep.pages_scanned, ps as u64);
+        }
+
+        if self.state.cursor >= pages_total {
+            rep.pass_completed = true;
+            self.state.passes_completed += 1;
+            self.state.last_pass_completed_at = now_unix();
+            self.state.cursor = 0;
+            self.state.pass_started_at = 0;
+            record_scrub_pass_completed();
+        }
+
+        record_scrub_pages(rep.pages_scanned);
+        self.state.version = SCRUB_STATE_VERSION;
+        self.state.store(&self.db.root)?;
+        if self.opts.persist_alerts && !rep.alerts.is_empty() {
+            append_alerts(&self.db.root, &rep.alerts)?;
+        }
+
+        rep.cursor = self.state.cursor;
+        rep.passes_completed = self.state.passes_completed;
+        rep.elapsed_ms = t0.elapsed().as_millis() as u64;
+        Ok(rep)
+    }
+
+    /// Довести текущий проход до конца (игнорирует max_pages).
+    pub fn run_pass(&mut self) -> Result<ScrubReport> {
+        let saved = self.opts.max_pages;
+        self.opts.max_pages = 0;
+        let r = self.run_step();
+        self.opts.max_pages = saved;
+        r
+    }
+
+    /// Ограничение скорости: спим, если опережаем бюджет bytes/sec.
+    fn throttle(&self, t0: Instant, pages_done: u64, page_size: u64) {
+        let rate = self.opts.max_bytes_per_sec;
+        if rate == 0 {
+            return;
+        }
+        let bytes_done = pages_done.saturating_mul(page_size);
+        let due = Duration::from_secs_f64(bytes_done as f64 / rate as f64);
+        let el = t0.elapsed();
+        if due > el {
+            std::thread::sleep(due - el);
+        }
+    }
+}
+
+/// Один шаг scrub БД по пути без shared LOCK (Db::open_ro_unlocked): открытие writer'а другим
+/// процессом не блокируется. Снимок next_page_id берётся при открытии — страницы, выделенные
+/// позже, проверит следующий шаг. reset — начать проход заново.
+pub fn scrub_step_at(root: &Path, opts: ScrubOptions, reset: bool) -> Result<ScrubReport> {
+    let db = Db::open_ro_unlocked(root)?;
+    let opts = ScrubOptions {
+        recheck_failures: true,
+        ..opts
+    };
+    let mut s = Scrubber::new(&db, opts)?;
+    if reset {
+        s.reset()?;
+    }
+    s.run_step()
+}
+
+fn append_alerts(root: &Path, alerts: &[ScrubAlert]) -> Result<()> {
+    let p = scrub_alerts_path(root);
+    let mut f = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&p)
+        .with_context(|| format!("open {}", p.display()))?;
+    for a in alerts {
+        let line = serde_json::to_string(a)?;
+        f.write_all(line.as_bytes())?;
+        f.write_all(b"\n")?;
+    }
+    let _ = f.sync_all();
+    Ok(())
+}
+
+#[inline]
+fn now_unix() -> u64 {
+    now_secs() as u64
+}
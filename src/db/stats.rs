@@ -0,0 +1,219 @@
+//! db/stats — счётчики конкретного Db-хэндла и пользовательские хуки инструментирования.
+//!
+//! Глобальные метрики (crate::metrics) общие для процесса: при нескольких открытых БД их
+//! нельзя отнести к конкретному хэндлу. Здесь — per-handle счётчики (живут в Pager хэндла):
+//! - операции put/del/get (Db::put/del/get, Batch::put/del, get_many), байты ключей+значений;
+//! - get: попадания/промахи; commit: WAL-батчи и страницы (включая компактацию и bulk load);
+//! - page cache: hits/misses чтений этого хэндла.
+//!
+//! Хук (Db::set_instrumentation) получает события с длительностью:
+//! - on_put — Db::put / Db::del (Batch::put буферизует и хук не вызывает, время батча — в on_commit);
+//! - on_get — Db::get;
+//! - on_commit — каждый WAL-батч хэндла (после fsync WAL и записи страниц).
+//!
+//! Хук вызывается синхронно в потоке операции — он должен быть дешёвым. Без хука время не
+//! замеряется (Instant::now не вызывается).
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::core::Db;
+
+/// Снимок per-handle счётчиков (Db::stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DbStats {
+    pub puts: u64,
+    /// Байты ключей и значений, переданных в put.
+    pub put_bytes: u64,
+    pub dels: u64,
+    pub gets: u64,
+    pub get_hits: u64,
+    pub get_misses: u64,
+    /// Байты значений, возвращённых get.
+    pub get_bytes: u64,
+    pub commits: u64,
+    pub commit_pages: u64,
+    pub page_cache_hits: u64,
+    pub page_cache_misses: u64,
+}
+
+// NEW: сумма счётчиков нескольких хэндлов (DbManager::stats)
+impl std::ops::AddAssign for DbStats {
+    fn add_assign(&mut self, o: Self) {
+        self.puts += o.puts;
+        self.put_bytes += o.put_bytes;
+        self.dels += o.dels;
+        self.gets += o.gets;
+        self.get_hits += o.get_hits;
+        self.get_misses += o.get_misses;
+        self.get_bytes += o.get_bytes;
+        self.commits += o.commits;
+        self.commit_pages += o.commit_pages;
+        self.page_cache_hits += o.page_cache_hits;
+        self.page_cache_misses += o.page_cache_misses;
+    }
+}
+
+impl DbStats {
+    pub fn cache_hit_ratio(&self) -> f64 {
+        let total = self.page_cache_hits + self.page_cache_misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.page_cache_hits as f64 / total as f64
+        }
+    }
+}
+
+/// Событие put/del для хука.
+#[derive(Debug, Clone, Copy)]
+pub struct PutEvent<'a> {
+    pub key: &'a [u8],
+    pub value_len: usize,
+    /// true — Db::del (tombstone).
+    pub tombstone: bool,
+    pub duration: Duration,
+}
+
+/// Событие get для хука.
+#[derive(Debug, Clone, Copy)]
+pub struct GetEvent<'a> {
+    pub key: &'a [u8],
+    /// None — ключ не найден.
+    pub value_len: Option<usize>,
+    pub duration: Duration,
+}
+
+/// Событие коммита WAL-батча для хука.
+#[derive(Debug, Clone, Copy)]
+pub struct CommitEvent {
+    pub start_lsn: u64,
+    pub last_lsn: u64,
+    pub pages: usize,
+    pub heads: usize,
+    pub duration: Duration,
+}
+
+/// Пользовательский хук инструментирования. Все методы по умолчанию — no-op.
+pub trait DbInstrumentation: Send + Sync {
+    fn on_put(&self, _ev: &PutEvent<'_>) {}
+    fn on_get(&self, _ev: &GetEvent<'_>) {}
+    fn on_commit(&self, _ev: &CommitEvent) {}
+}
+
+/// Атомарные per-handle счётчики (поле Pager хэндла; чтения идут по &self).
+#[derive(Debug, Default)]
+pub(crate) struct HandleStats {
+    puts: AtomicU64,
+    put_bytes: AtomicU64,
+    dels: AtomicU64,
+    gets: AtomicU64,
+    get_hits: AtomicU64,
+    get_bytes: AtomicU64,
+    commits: AtomicU64,
+    commit_pages: AtomicU64,
+    page_cache_hits: AtomicU64,
+    page_cache_misses: AtomicU64,
+}
+
+impl HandleStats {
+    #[inline]
+    pub(crate) fn note_put(&self, key_len: usize, value_len: usize) {
+        self.puts.fetch_add(1, Ordering::Relaxed);
+        self.put_bytes
+            .fetch_add((key_len + value_len) as u64, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn note_del(&self) {
+        self.dels.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn note_get(&self, value_len: Option<usize>) {
+        self.gets.fetch_add(1, Ordering::Relaxed);
+        if let Some(n) = value_len {
+            self.get_hits.fetch_add(1, Ordering::Relaxed);
+            self.get_bytes.fetch_add(n as u64, Ordering::Relaxed);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn note_commit(&self, pages: usize) {
+        self.commits.fetch_add(1, Ordering::Relaxed);
+        self.commit_pages.fetch_add(pages as u64, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn note_cache_hit(&self) {
+        self.page_cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn note_cache_miss(&self) {
+        self.page_cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> DbStats {
+        let gets = self.gets.load(Ordering::Relaxed);
+        let get_hits = self.get_hits.load(Ordering::Relaxed);
+        DbStats {
+            puts: self.puts.load(Ordering::Relaxed),
+            put_bytes: self.put_bytes.load(Ordering::Relaxed),
+            dels: self.dels.load(Ordering::Relaxed),
+            gets,
+            get_hits,
+            get_misses: gets.saturating_sub(get_hits),
+            get_bytes: self.get_bytes.load(Ordering::Relaxed),
+            commits: self.commits.load(Ordering::Relaxed),
+            commit_pages: self.commit_pages.load(Ordering::Relaxed),
+            page_cache_hits: self.page_cache_hits.load(Ordering::Relaxed),
+            page_cache_misses: self.page_cache_misses.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        for c in [
+            &self.puts,
+            &self.put_bytes,
+            &self.dels,
+            &self.gets,
+            &self.get_hits,
+            &self.get_bytes,
+            &self.commits,
+            &self.commit_pages,
+            &self.page_cache_hits,
+            &self.page_cache_misses,
+        ] {
+            c.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Db {
+    /// Снимок счётчиков этого хэндла (с момента открытия или Db::reset_stats).
+    pub fn stats(&self) -> DbStats {
+        self.pager.stats.snapshot()
+    }
+
+    /// Обнулить счётчики этого хэндла.
+    pub fn reset_stats(&self) {
+        self.pager.stats.reset();
+    }
+
+    /// Зарегистрировать хук инструментирования (заменяет предыдущий).
+    pub fn set_instrumentation(&mut self, hook: Arc<dyn DbInstrumentation>) {
+        self.pager.instrumentation = Some(hook);
+    }
+
+    /// Снять хук инструментирования.
+    pub fn clear_instrumentation(&mut self) {
+        self.pager.instrumentation = None;
+    }
+
+    /// Зарегистрирован ли хук (замер длительностей включён).
+    pub fn has_instrumentation(&self) -> bool {
+        self.pager.instrumentation.is_some()
+    }
+}
@@ -0,0 +1,91 @@
+//! db/tde_kids — страницы по KID подписи (прогресс ротации ключей TDE, quiverdb status).
+//!
+//! Db::tde_kid_pages() проходит страницы [0 .. next_page_id) сырым чтением (без проверки
+//! трейлера — страницы KID без ключа тоже учитываются), пропуская free-лист, и группирует
+//! KV/OVERFLOW-страницы по отпечатку KID из заголовка (OFF_KEY_FP). Имя KID — из загруженных
+//! ключей, журнала эпох и проверочного блока meta; неизвестное — "#<fp hex>". Страницы до
+//! per-page KID (отпечаток 0) идут в untagged_pages.
+//!
+//! Учитываются и недостижимые остатки старых цепочек (после компактации) — в free-лист их
+//! возвращает alloc_check_fix. Отчёт read-only: работает на Db::open_ro.
+
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::free::FreeList;
+use crate::page::{page_key_fp, OFF_TYPE, PAGE_TYPE_KV_RH3, PAGE_TYPE_OVERFLOW3};
+
+use super::core::Db;
+
+/// Страницы, подписанные одним KID.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KidPages {
+    pub kid: String,
+    pub fingerprint: u32,
+    pub pages: u64,
+    /// KID текущего ключа хэндла.
+    pub current: bool,
+}
+
+/// Разбивка страниц по KID подписи (Db::tde_kid_pages).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TdeKidUsage {
+    /// По убыванию числа страниц.
+    pub kids: Vec<KidPages>,
+    /// Страницы без отпечатка KID (записаны до per-page KID или без TDE).
+    pub untagged_pages: u64,
+}
+
+impl TdeKidUsage {
+    /// Страницы не под текущим KID (включая untagged) — что ещё подписано прежними ключами.
+    pub fn pages_not_current(&self) -> u64 {
+        self.untagged_pages
+            + self
+                .kids
+                .iter()
+                .filter(|k| !k.current)
+                .map(|k| k.pages)
+                .sum::<u64>()
+    }
+}
+
+impl Db {
+    /// Число KV/OVERFLOW-страниц по KID подписи (см. модуль).
+    pub fn tde_kid_pages(&self) -> Result<TdeKidUsage> {
+        let ps = self.pager.meta.page_size as usize;
+        let mut page = vec![0u8; ps];
+        let free: HashSet<u64> = match FreeList::open(&self.root) {
+            Ok(fl) => fl.list().unwrap_or_default().into_iter().collect(),
+            Err(_) => HashSet::new(),
+        };
+        let mut by_fp: BTreeMap<u32, u64> = BTreeMap::new();
+        let mut u = TdeKidUsage::default();
+        for pid in 0..self.pager.meta.next_page_id {
+            if free.contains(&pid) || self.pager.read_local_page(pid, &mut page)? != Some(true) {
+                continue;
+            }
+            let ptype = LittleEndian::read_u16(&page[OFF_TYPE..OFF_TYPE + 2]);
+            if ptype != PAGE_TYPE_KV_RH3 && ptype != PAGE_TYPE_OVERFLOW3 {
+                continue;
+            }
+            match page_key_fp(&page) {
+                0 => u.untagged_pages += 1,
+                fp => *by_fp.entry(fp).or_insert(0) += 1,
+            }
+        }
+        u.kids = by_fp
+            .into_iter()
+            .map(|(fp, pages)| KidPages {
+                kid: self.pager.tde_kid_name_for_fp(fp),
+                fingerprint: fp,
+                pages,
+                current: self.pager.tde_key_fp == fp,
+            })
+            .collect();
+        u.kids
+            .sort_by(|a, b| b.pages.cmp(&a.pages).then(a.kid.cmp(&b.kid)));
+        Ok(u)
+    }
+}
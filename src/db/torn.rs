@@ -0,0 +1,164 @@
+//! db/torn — open-time проверка голов каталога и ремонт «порванных» страниц из WAL.
+//!
+//! Реплей WAL применяет образ страницы, если текущая страница не читается или её lsn старше.
+//! Но порванная запись может оставить страницу, которая проходит read_page (например, заголовок
+//! с новым lsn дописан, а трейлер CRC остался нулевым — в non-strict режиме такой трейлер
+//! считается допустимым): гейтинг пропускает образ, а ошибка всплывает позже, при первом доступе.
+//!
+//! После реплея writer (при clean_shutdown=false или QuiverConfig::verify_heads_on_open=true)
+//! проверяет страницу-голову каждого бакета мимо page cache:
+//! - трейлер (CRC/AEAD) и заголовок KV_RH3 должны читаться;
+//! - в CRC-режиме нулевой трейлер у головы считается подозрительным (writer всегда пишет CRC).
+//!
+//! Сбойная голова переписывается последним образом этой страницы из только что реплеенного WAL
+//! (pager::replay::ReplayImages; WAL к этому моменту уже усечён). Итог — TornPageReport
+//! (Db::open_repair_report): что починено и что осталось сломанным (с [WARN] в stderr).
+//! Db::verify_heads — та же проверка по запросу, без ремонта (в т.ч. на RO-хэндле).
+
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use serde::Serialize;
+
+use crate::dir::NO_PAGE;
+use crate::page::{
+    kv_header_read_v3, page_trailer_is_zero_crc32, OFF_TYPE, PAGE_MAGIC, PAGE_TYPE_KV_RH3,
+};
+use crate::pager::replay::ReplayImages;
+
+use super::core::Db;
+
+/// Лимит памяти под образы страниц, удерживаемые при реплее для ремонта голов.
+pub(crate) const REPAIR_IMAGES_CAP_BYTES: usize = 64 * 1024 * 1024;
+
+/// Сбойная страница-голова бакета.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TornPage {
+    pub bucket: u32,
+    pub page_id: u64,
+    /// Причина (ошибка чтения/разбора страницы).
+    pub error: String,
+    /// Страница переписана образом из WAL и теперь читается.
+    pub repaired: bool,
+}
+
+/// Итог проверки голов каталога.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TornPageReport {
+    /// Сколько голов (bucket с head != NO_PAGE) проверено.
+    pub heads_checked: u64,
+    /// Сколько образов KV-страниц из WAL было доступно для ремонта.
+    pub wal_images: u64,
+    /// Сбойные головы (починенные и нет).
+    pub pages: Vec<TornPage>,
+}
+
+impl TornPageReport {
+    pub fn repaired(&self) -> usize {
+        self.pages.iter().filter(|p| p.repaired).count()
+    }
+
+    /// Головы, оставшиеся сломанными.
+    pub fn unrepaired(&self) -> impl Iterator<Item = &TornPage> {
+        self.pages.iter().filter(|p| !p.repaired)
+    }
+
+    /// Все головы читаются (после ремонта).
+    pub fn is_clean(&self) -> bool {
+        self.unrepaired().next().is_none()
+    }
+}
+
+impl Db {
+    /// Проверить страницы-головы всех бакетов (без ремонта). Работает на любом хэндле.
+    pub fn verify_heads(&self) -> Result<TornPageReport> {
+        self.scan_heads()
+    }
+
+    /// Отчёт open-time проверки голов (None — проверка при открытии не выполнялась).
+    pub fn open_repair_report(&self) -> Option<&TornPageReport> {
+        self.torn_report.as_ref()
+    }
+
+    /// Open-time: проверить головы и починить сбойные из образов реплея (writer).
+    pub(super) fn repair_torn_heads(&mut self, images: &ReplayImages) -> Result<()> {
+        let mut rep = self.scan_heads()?;
+        rep.wal_images = images.len() as u64;
+
+        let mut buf = vec![0u8; self.pager.meta.page_size as usize];
+        let mut synced_segs: Vec<u64> = Vec::new();
+        for i in 0..rep.pages.len() {
+            let pid = rep.pages[i].page_id;
+            let img = match images.get(pid) {
+                Some(img) if self.head_image_usable(img) => img,
+                _ => continue,
+            };
+            // Образ уже был в WAL — пишем страницу напрямую
+            self.pager.write_page_raw(pid, img)?;
+            // Починенная страница должна пережить следующий краш независимо от data_fsync
+            let (seg_no, _) = self.pager.locate(pid);
+            if !synced_segs.contains(&seg_no) {
+                self.pager.open_seg_rw(seg_no, false)?.sync_all()?;
+                synced_segs.push(seg_no);
+            }
+            rep.pages[i].repaired = self.check_head_page(pid, &mut buf).is_ok();
+        }
+
+        for p in rep.unrepaired() {
+            eprintln!(
+                "[WARN] bucket {} head page {} is unreadable and has no usable WAL image: {}",
+                p.bucket, p.page_id, p.error
+            );
+        }
+        self.torn_report = Some(rep);
+        Ok(())
+    }
+
+    fn scan_heads(&self) -> Result<TornPageReport> {
+        let mut rep = TornPageReport::default();
+        let mut buf = vec![0u8; self.pager.meta.page_size as usize];
+        for b in 0..self.dir.bucket_count {
+            let pid = self.dir.head(b)?;
+            if pid == NO_PAGE {
+                continue;
+            }
+            rep.heads_checked += 1;
+            if let Err(error) = self.check_head_page(pid, &mut buf) {
+                rep.pages.push(TornPage {
+                    bucket: b,
+                    page_id: pid,
+                    error,
+                    repaired: false,
+                });
+            }
+        }
+        Ok(rep)
+    }
+
+    /// Голова читается мимо кэша, трейлер и заголовок KV_RH3 валидны.
+    fn check_head_page(&self, pid: u64, buf: &mut [u8]) -> std::result::Result<(), String> {
+        self.pager
+            .read_page_uncached(pid, buf)
+            .map_err(|e| format!("{:#}", e))?;
+        if &buf[0..4] != PAGE_MAGIC {
+            return Err("bad page magic".into());
+        }
+        let ptype = LittleEndian::read_u16(&buf[OFF_TYPE..OFF_TYPE + 2]);
+        if ptype != PAGE_TYPE_KV_RH3 {
+            return Err(format!("unexpected page type {} for a bucket head", ptype));
+        }
+        kv_header_read_v3(buf).map_err(|e| format!("{:#}", e))?;
+        if !self.pager.tde_enabled && page_trailer_is_zero_crc32(buf).unwrap_or(false) {
+            return Err("zero checksum trailer (page content is unverified)".into());
+        }
+        Ok(())
+    }
+
+    /// Образ из WAL годится для ремонта: KV_RH3 с читаемым заголовком и непустым трейлером.
+    fn head_image_usable(&self, img: &[u8]) -> bool {
+        img.len() == self.pager.meta.page_size as usize
+            && &img[0..4] == PAGE_MAGIC
+            && LittleEndian::read_u16(&img[OFF_TYPE..OFF_TYPE + 2]) == PAGE_TYPE_KV_RH3
+            && kv_header_read_v3(img).is_ok()
+            && (self.pager.tde_enabled || !page_trailer_is_zero_crc32(img).unwrap_or(true))
+    }
+}
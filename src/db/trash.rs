@@ -0,0 +1,139 @@
+//! db/trash — trash-режим удаления (QuiverConfig::trash_grace_secs) и Db::undelete.
+//!
+//! При trash_grace_secs > 0 del() (и Batch::del) пишет «мягкий» tombstone: поле expires_at_sec,
+//! которое у tombstone'ов не участвует в TTL (читатели отдают tombstone раньше проверки TTL),
+//! хранит срок корзины now + grace. Прежнее значение остаётся глубже в цепочке бакета.
+//!
+//! - Db::undelete(key): новейшая запись ключа — мягкий tombstone с неистёкшим сроком, под ним
+//!   живая версия → она записывается заново обычным put (новая голова). Иначе false.
+//! - Db::trash_entries(): ключи, которые сейчас можно вернуть (проход по цепочкам).
+//! - Компактация переносит мягкий tombstone и живую версию под ним, пока срок не истёк;
+//!   после — вычищает ключ как обычный tombstone (db/compaction.rs).
+//!
+//! Срок записан в самом tombstone'е: выключение trash-режима не отменяет уже начатые сроки.
+//! Жёсткие tombstone'ы (trash выключен, delete_prefix) вернуть нельзя.
+
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::dir::NO_PAGE;
+use crate::metrics::record_undelete;
+use crate::page::kv::kv_for_each_record;
+use crate::page::{kv_header_read_v3, OFF_TYPE, PAGE_MAGIC, PAGE_TYPE_KV_RH3};
+use crate::util::{decode_ovf_placeholder_v3, now_secs};
+
+use super::core::Db;
+
+/// Ключ в корзине (Db::trash_entries).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrashEntry {
+    pub bucket: u32,
+    pub key: Vec<u8>,
+    /// Срок корзины (секунды unix): после него компактация вычистит значение.
+    pub purge_after_sec: u32,
+    /// Длина значения, которое вернёт undelete (OVERFLOW — полная длина).
+    pub value_len: u64,
+}
+
+/// Мягкий tombstone с неистёкшим сроком (на момент `now`).
+#[inline]
+pub(crate) fn is_soft_tombstone(vflags: u8, expires_at_sec: u32, now: u32) -> bool {
+    (vflags & 0x1) == 1 && expires_at_sec != 0 && now < expires_at_sec
+}
+
+impl Db {
+    /// Срок для нового tombstone'а: 0 — trash-режим выключен (обычный tombstone).
+    pub(crate) fn trash_deadline(&self) -> u32 {
+        if self.trash_grace_secs == 0 {
+            return 0;
+        }
+        let grace = self.trash_grace_secs.min(u32::MAX as u64) as u32;
+        now_secs().saturating_add(grace)
+    }
+
+    /// Вернуть значение ключа, удалённого в trash-режиме, пока не истёк срок корзины.
+    /// true — значение восстановлено; false — ключ не в корзине (жив, не удалён мягко,
+    /// срок истёк или под tombstone'ом нет живой версии).
+    pub fn undelete(&mut self, key: &[u8]) -> Result<bool> {
+        self.pager.ensure_writable("undelete")?;
+        let now = now_secs();
+        // Новейшая версия и история под ней (get_versions учитывает range tombstones)
+        let versions = self.get_versions(key, usize::MAX)?;
+        let Some((newest, older)) = versions.split_first() else {
+            return Ok(false);
+        };
+        let vflags = if newest.is_tombstone() { 1 } else { 0 };
+        if !is_soft_tombstone(vflags, newest.expires_at_sec, now) {
+            return Ok(false);
+        }
+        let prev = older
+            .iter()
+            .take_while(|v| !v.is_tombstone())
+            .find(|v| !v.is_expired(now));
+        let Some(value) = prev.and_then(|v| v.value.clone()) else {
+            return Ok(false);
+        };
+        self.put(key, &value)?;
+        record_undelete();
+        Ok(true)
+    }
+
+    /// Ключи в корзине (новейшая запись — мягкий tombstone с неистёкшим сроком, под ним живая
+    /// версия), отсортированные по ключу. Проходит цепочки всех бакетов.
+    pub fn trash_entries(&self) -> Result<Vec<TrashEntry>> {
+        let now = now_secs();
+        let ps = self.pager.meta.page_size as usize;
+        let mut page = vec![0u8; ps];
+        let mut out = Vec::new();
+
+        for bucket in 0..self.dir.bucket_count {
+            // key -> None (не в корзине) | Some((срок, длина живой версии под tombstone'ом))
+            let mut seen: HashMap<Vec<u8>, Option<(u32, Option<u64>)>> = HashMap::new();
+            let mut pid = self.dir.head(bucket)?;
+            while pid != NO_PAGE {
+                self.pager.read_page(pid, &mut page)?;
+                if &page[0..4] != PAGE_MAGIC
+                    || LittleEndian::read_u16(&page[OFF_TYPE..OFF_TYPE + 2]) != PAGE_TYPE_KV_RH3
+                {
+                    break;
+                }
+                let h = kv_header_read_v3(&page)?;
+                kv_for_each_record(&page, |k, v, expires_at_sec, vflags| {
+                    let under_cut = self.range_cut(k).is_some_and(|c| h.lsn <= c);
+                    let Some(st) = seen.get_mut(k) else {
+                        // Первое (новейшее) вхождение ключа решает, в корзине ли он
+                        let soft = !under_cut && is_soft_tombstone(vflags, expires_at_sec, now);
+                        seen.insert(k.to_vec(), soft.then_some((expires_at_sec, None)));
+                        return;
+                    };
+                    if !matches!(st, Some((_, None))) {
+                        return;
+                    }
+                    if (vflags & 0x1) == 1 || under_cut {
+                        *st = None;
+                    } else if expires_at_sec == 0 || now < expires_at_sec {
+                        let len = decode_ovf_placeholder_v3(v)
+                            .map(|(total, _)| total)
+                            .unwrap_or(v.len() as u64);
+                        *st = Some((st.map(|(d, _)| d).unwrap_or(0), Some(len)));
+                    }
+                });
+                pid = h.next_page_id;
+            }
+            for (key, st) in seen {
+                if let Some((purge_after_sec, Some(value_len))) = st {
+                    out.push(TrashEntry {
+                        bucket,
+                        key,
+                        purge_after_sec,
+                        value_len,
+                    });
+                }
+            }
+        }
+        out.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(out)
+    }
+}
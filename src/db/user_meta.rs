@@ -0,0 +1,243 @@
+//! db/user_meta — область метаданных приложения (Db::meta_put / Db::meta_get).
+//!
+//! Небольшой KV (версия схемы, настройки приложения) вне пользовательского пространства ключей:
+//! записи лежат в отдельной цепочке KV_RH3-страниц (флаг страницы KV_FLAG_USER_META), голова —
+//! в meta (user_meta_head). Каталог бакетов на неё не ссылается, поэтому сканы, get/exists,
+//! компактация, фильтры компактации и export области не видят, а ключи области не пересекаются
+//! с пользовательскими (KeyTransform к ним не применяется).
+//!
+//! Запись (meta_put/meta_del/meta_update, writer) переписывает область целиком — она маленькая
+//! (до USER_META_MAX_BYTES): новые страницы коммитятся одним WAL-батчем, затем голова
+//! фиксируется в meta (tmp+rename), и только после этого страницы прежней цепочки уходят в
+//! free-лист (кроме закреплённых создаваемыми снапшотами, snapstore/pins — как у vacuum; их и
+//! страницы-сироты сбоя до записи meta вернёт в free-лист alloc_check_fix). Сбой до записи meta
+//! оставляет прежнюю область целиком; после возврата из вызова изменение долговечно.
+//! meta_update меняет несколько ключей атомарно.
+//!
+//! Область едет с бэкапами (BackupStreamManifest::user_meta_head, страницы — как обычные),
+//! persisted-снапшотами/ветками (SnapshotMetaV2::user_meta_head) и live clone (meta копируется).
+//! alloc_check/alloc_check_fix учитывают её страницы как достижимые. CDC (ship/apply,
+//! cdc::subscribe, history) страницы области не выдаёт как пользовательские изменения,
+//! follower'у область не реплицируется.
+//!
+//! RO-хэндл читает голову из meta на диске при каждом вызове — изменения writer'а видны сразу.
+
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+
+use crate::dir::NO_PAGE;
+use crate::meta::{read_meta, write_meta_overwrite, FEATURE_USER_META};
+use crate::metrics::{record_maint_pinned_pages_kept, record_user_meta_write};
+use crate::page::kv::kv_for_each_record;
+use crate::page::kv_pack::{KvPackItem, KvPagePacker};
+use crate::page::{kv_header_read_v3, kv_header_write_v3, OFF_TYPE, PAGE_MAGIC, PAGE_TYPE_KV_RH3};
+
+use super::core::Db;
+use super::maintenance::PinnedPages;
+
+/// Флаг заголовка KV-страницы: страница области метаданных приложения.
+pub const KV_FLAG_USER_META: u32 = 1 << 0;
+
+/// Предел суммарного размера области (ключи + значения).
+pub const USER_META_MAX_BYTES: usize = 256 * 1024;
+
+/// Страница области метаданных (по флагу заголовка KV_RH3).
+pub fn is_user_meta_page(page: &[u8]) -> bool {
+    page.len() >= OFF_TYPE + 2
+        && &page[0..4] == PAGE_MAGIC
+        && u16::from_le_bytes([page[OFF_TYPE], page[OFF_TYPE + 1]]) == PAGE_TYPE_KV_RH3
+        && kv_header_read_v3(page)
+            .map(|h| h.flags & KV_FLAG_USER_META != 0)
+            .unwrap_or(false)
+}
+
+impl Db {
+    /// Значение ключа области метаданных (None — нет).
+    pub fn meta_get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.read_user_meta()?.remove(key))
+    }
+
+    /// Все пары области в порядке ключей.
+    pub fn meta_entries(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        Ok(self.read_user_meta()?.into_iter().collect())
+    }
+
+    /// Записать ключ области метаданных (writer).
+    pub fn meta_put(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.meta_update(|m| {
+            m.insert(key.to_vec(), value.to_vec());
+            Ok(())
+        })
+    }
+
+    /// Удалить ключ области (writer). false — ключа не было (область не переписывается).
+    pub fn meta_del(&mut self, key: &[u8]) -> Result<bool> {
+        let mut found = false;
+        self.meta_update(|m| {
+            found = m.remove(key).is_some();
+            Ok(())
+        })?;
+        Ok(found)
+    }
+
+    /// Атомарно изменить несколько ключей области: f получает текущее содержимое, результат
+    /// записывается целиком (ошибка f — ничего не записано; без изменений — запись пропускается).
+    pub fn meta_update<F>(&mut self, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut BTreeMap<Vec<u8>, Vec<u8>>) -> Result<()>,
+    {
+        self.pager.ensure_writable("meta_put")?;
+        let before = self.read_user_meta()?;
+        let mut entries = before.clone();
+        f(&mut entries)?;
+        if entries == before {
+            return Ok(());
+        }
+        self.critical("meta_put", |db| db.write_user_meta(&entries))
+    }
+
+    /// Голова области: writer — из памяти (его meta авторитетна), RO — из meta на диске.
+    pub(crate) fn user_meta_head(&self) -> Result<u64> {
+        if self.readonly {
+            Ok(read_meta(&self.root)?.user_meta_head)
+        } else {
+            Ok(self.pager.meta.user_meta_head)
+        }
+    }
+
+    /// page_id страниц области (alloc_check: достижимые страницы).
+    pub(crate) fn user_meta_pages(&self) -> Result<Vec<u64>> {
+        let mut out = Vec::new();
+        self.walk_user_meta(|pid, _page| out.push(pid))?;
+        Ok(out)
+    }
+
+    fn read_user_meta(&self) -> Result<BTreeMap<Vec<u8>, Vec<u8>>> {
+        let mut out = BTreeMap::new();
+        self.walk_user_meta(|_pid, page| {
+            kv_for_each_record(page, |k, v, _exp, _vflags| {
+                out.entry(k.to_vec()).or_insert_with(|| v.to_vec());
+            });
+        })?;
+        Ok(out)
+    }
+
+    fn walk_user_meta(&self, mut f: impl FnMut(u64, &[u8])) -> Result<()> {
+        let ps = self.pager.meta.page_size as usize;
+        let mut page = vec![0u8; ps];
+        let mut pid = self.user_meta_head()?;
+        let mut guard = 0u64;
+        while pid != NO_PAGE {
+            guard += 1;
+            if guard > self.pager.meta.next_page_id {
+                return Err(anyhow!("user metadata chain loop at page {}", pid));
+            }
+            self.pager.read_page(pid, &mut page)?;
+            if !is_user_meta_page(&page) {
+                return Err(anyhow!("page {} is not a user metadata page", pid));
+            }
+            f(pid, &page);
+            pid = kv_header_read_v3(&page)?.next_page_id;
+        }
+        Ok(())
+    }
+
+    /// Новая цепочка области одним WAL-батчем, затем голова в meta.
+    fn write_user_meta(&mut self, entries: &BTreeMap<Vec<u8>, Vec<u8>>) -> Result<()> {
+        let total: usize = entries.iter().map(|(k, v)| k.len() + v.len()).sum();
+        if total > USER_META_MAX_BYTES {
+            return Err(anyhow!(
+                "user metadata too large: {} bytes (limit {})",
+                total,
+                USER_META_MAX_BYTES
+            ));
+        }
+        let old_pages = self.user_meta_pages()?;
+        let ps = self.pager.meta.page_size as usize;
+        let mut packer = KvPagePacker::new(ps);
+        let mut pages: Vec<(u64, Vec<u8>)> = Vec::new();
+        let mut head = NO_PAGE;
+        for (k, v) in entries {
+            if k.len() > u16::MAX as usize {
+                return Err(anyhow!("user metadata key too long (> u16::MAX)"));
+            }
+            let item = || KvPackItem {
+                key: k.clone(),
+                value: v.clone(),
+                expires_at_sec: 0,
+                vflags: 0,
+            };
+            if packer.try_add(item()) {
+                continue;
+            }
+            self.flush_user_meta_page(&mut packer, &mut pages, &mut head)?;
+            if !packer.try_add(item()) {
+                return Err(anyhow!(
+                    "user metadata entry {:?} does not fit in a page ({} bytes)",
+                    String::from_utf8_lossy(k),
+                    k.len() + v.len()
+                ));
+            }
+        }
+        self.flush_user_meta_page(&mut packer, &mut pages, &mut head)?;
+
+        if !pages.is_empty() {
+            let mut for_commit: Vec<(u64, &mut [u8])> = pages
+                .iter_mut()
+                .map(|(pid, buf)| (*pid, buf.as_mut_slice()))
+                .collect();
+            self.pager.commit_pages_batch(&mut for_commit)?;
+        }
+
+        // Голова — только после коммита страниц: сбой раньше оставляет прежнюю область
+        self.pager.meta.user_meta_head = head;
+        self.pager.meta.flags |= FEATURE_USER_META;
+        write_meta_overwrite(&self.root, &self.pager.meta)?;
+        record_user_meta_write();
+        self.free_user_meta_pages(&old_pages)
+    }
+
+    /// Страницы прежней цепочки — в free-лист (после фиксации новой головы). Закреплённые
+    /// создаваемыми снапшотами остаются (их вернёт alloc_check_fix).
+    fn free_user_meta_pages(&mut self, pages: &[u64]) -> Result<()> {
+        if pages.is_empty() {
+            return Ok(());
+        }
+        let pinned = match self.maint_pinned_pages()? {
+            PinnedPages::Pages(p) => p,
+            PinnedPages::SnapshotPending(_) => {
+                record_maint_pinned_pages_kept(pages.len() as u64);
+                return Ok(());
+            }
+        };
+        let mut kept = 0u64;
+        for &pid in pages {
+            if pinned.contains(&pid) {
+                kept += 1;
+                continue;
+            }
+            self.pager.free_page(pid)?;
+        }
+        record_maint_pinned_pages_kept(kept);
+        Ok(())
+    }
+
+    fn flush_user_meta_page(
+        &mut self,
+        packer: &mut KvPagePacker,
+        pages: &mut Vec<(u64, Vec<u8>)>,
+        head: &mut u64,
+    ) -> Result<()> {
+        if packer.is_empty() {
+            return Ok(());
+        }
+        let pid = self.pager.allocate_one_page()?;
+        let mut page = packer.finalize_into_page(pid, *head, 0)?;
+        let mut h = kv_header_read_v3(&page)?;
+        h.flags |= KV_FLAG_USER_META;
+        kv_header_write_v3(&mut page, &h)?;
+        pages.push((pid, page));
+        *head = pid;
+        Ok(())
+    }
+}
@@ -5,6 +5,9 @@
 //!    OVERFLOW placeholder сохраняется как есть.
 //! 2) Очистка сиротских OVERFLOW‑цепочек (sweep_orphan_overflow), чтобы освободить неиспользуемые страницы.
 //!
+//! NEW: фильтр компактации (Db::set_compaction_filter) применяется на шаге 1; OVERFLOW-цепочки
+//! удалённых/переписанных фильтром значений освобождаются шагом 2.
+//!
 //! Форматы на диске не меняются.
 //! Операция требует writer‑режима (эксклюзивного lock), т.к. создаёт новые страницы и
 //! обновляет directory head’ы, а затем модифицирует free‑лист.
@@ -12,14 +15,16 @@
 //! Возвращаемая структура VacuumSummary содержит отчёт компактора и число освобождённых OVERFLOW‑страниц.
 
 use anyhow::Result;
+use serde::Serialize;
 
 use super::compaction::CompactSummary;
 use super::core::Db;
 
 /// Сводка “вакуумной” операции: компактация + sweep сиротских OVERFLOW.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct VacuumSummary {
-    /// Отчёт по компактации всех бакетов.
+    /// Отчёт по компактации всех бакетов (в JSON — поля верхнего уровня).
+    #[serde(flatten)]
     pub compaction: CompactSummary,
     /// Сколько OVERFLOW‑страниц было освобождено sweep’ом.
     pub overflow_pages_freed: usize,
@@ -30,6 +35,11 @@ impl Db {
     ///
     /// Замечание: операция записи (writer‑режим).
     pub fn vacuum_all(&mut self) -> Result<VacuumSummary> {
+        self.pager.ensure_writable("vacuum_all")?;
+        self.critical("vacuum_all", |db| db.vacuum_all_unguarded())
+    }
+
+    fn vacuum_all_unguarded(&mut self) -> Result<VacuumSummary> {
         // 1) Компактация всех бакетов
         let comp = self.compact_all()?;
 
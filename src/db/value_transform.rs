@@ -0,0 +1,489 @@
+//! db/value_transform — кодирование значений на пути хранения (ValueTransform).
+//!
+//! Трансформация регистрируется в конфиге (QuiverConfig::value_transform /
+//! DbBuilder::value_transform): encode применяется к значению при записи (put, Batch,
+//! AutoBatcher, BulkLoader), decode — при чтении (get, get_many, сканы, scan_page,
+//! get_versions, get_json_path, TypedDb поверх них). Страницы, WAL, CDC/backup и компактация
+//! видят только закодированные байты; TTL, tombstone'ы и OVERFLOW работают поверх них как обычно.
+//!
+//! Встроенная ZstdDictValues — zstd со словарём, обученным на выборке значений
+//! (ZstdDictValues::train) и сохранённым под корнем (value_dict.bin). Повторяющиеся мелкие
+//! значения (JSON-записи одной схемы, логи) сжимаются сильнее, чем постраничным zstd без словаря.
+//! Формат значения: [tag u8] — 0 = как есть, 1 = [raw_len u32][кадр zstd со словарём];
+//! несжимаемые и короткие значения хранятся с тегом 0.
+//!
+//! NEW: версии словаря. value_dict.bin хранит несколько версий (P2VDICT2): новые значения
+//! сжимаются последней, значения с тегом 2 = [version u32][raw_len u32][кадр] читаются своей.
+//! `quiverdb dict-train` (Db::sample_values + train_value_dictionary) добавляет версию,
+//! обученную на текущих значениях БД (ZstdDictValues::add_version); id трансформации — по
+//! версии 1 и не меняется, поэтому meta и уже записанные значения остаются валидными.
+//!
+//! Идентификатор (id) фиксируется в meta (value_transform + required-бит FEATURE_VALUE_TRANSFORM)
+//! при первом открытии writer'ом пустой БД (next_page_id == 0) и едет с backup/snapshot/clone
+//! (DbIdentity; словарь — sidecar бэкапа и файл clone). Встроенная восстанавливается по id из
+//! meta и словарю под корнем — открытие без явной регистрации работает, включая CLI
+//! (`quiverdb init --value-dict`). Открытие с другой трансформацией, без неё (кроме встроенной)
+//! или с трансформацией для БД без неё — ValueTransformMismatchError.
+//!
+//! cdc::subscribe раскодирует значения встроенной трансформацией; с пользовательской
+//! ChangeEvent::value — закодированные байты.
+
+use anyhow::{anyhow, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use rand::Rng;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use zstd::bulk::{Compressor, Decompressor};
+use zstd::dict::{DecoderDictionary, EncoderDictionary};
+
+use crate::meta::{record_value_transform, MetaHeader};
+
+use super::core::Db;
+
+/// Кодирование значений (см. модульный комментарий).
+pub trait ValueTransform: Send + Sync {
+    /// Стабильный идентификатор (пишется в meta, до VALUE_TRANSFORM_ID_MAX байт).
+    fn id(&self) -> String;
+
+    /// Значение → байты хранения.
+    fn encode<'a>(&self, value: &'a [u8]) -> Result<Cow<'a, [u8]>>;
+
+    /// Байты хранения → значение (обратное encode).
+    fn decode<'a>(&self, stored: &'a [u8]) -> Result<Cow<'a, [u8]>>;
+}
+
+/// Трансформация в QuiverConfig (Clone/Debug для конфига).
+#[derive(Clone)]
+pub struct ValueTransformRef(pub Arc<dyn ValueTransform>);
+
+impl ValueTransformRef {
+    pub fn new(t: Arc<dyn ValueTransform>) -> Self {
+        Self(t)
+    }
+}
+
+impl fmt::Debug for ValueTransformRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ValueTransformRef({})", self.0.id())
+    }
+}
+
+/// Трансформация конфига не совпадает с записанной в meta. Достаётся из anyhow через downcast_ref.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueTransformMismatchError {
+    pub path: PathBuf,
+    /// Из meta ("" — нет).
+    pub recorded: String,
+    /// Из конфига ("" — нет).
+    pub configured: String,
+}
+
+impl fmt::Display for ValueTransformMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |s: &str| {
+            if s.is_empty() {
+                "none".to_string()
+            } else {
+                s.to_string()
+            }
+        };
+        write!(
+            f,
+            "value transform mismatch at {}: DB uses {}, handle configured with {}",
+            self.path.display(),
+            show(&self.recorded),
+            show(&self.configured)
+        )
+    }
+}
+
+impl std::error::Error for ValueTransformMismatchError {}
+
+// -------------------- встроенная: zstd со словарём --------------------
+
+/// Файл словаря под корнем БД.
+pub const VALUE_DICT_FILE: &str = "value_dict.bin";
+
+/// Одна версия: magic + level i32 + crc32c(dict) u32 + dict.
+const VALUE_DICT_MAGIC: &[u8; 8] = b"P2VDICT1";
+const VALUE_DICT_HDR: usize = 16;
+/// Версионированный: magic + count u32, затем [version u32][level i32][crc32c u32][len u32][dict].
+const VALUE_DICT_MAGIC_V2: &[u8; 8] = b"P2VDICT2";
+const VALUE_DICT_ENTRY_HDR: usize = 16;
+
+const TAG_RAW: u8 = 0;
+/// Словарь версии 1: [raw_len u32][кадр].
+const TAG_ZSTD: u8 = 1;
+/// Словарь версии N: [version u32][raw_len u32][кадр].
+const TAG_ZSTD_VERSIONED: u8 = 2;
+/// Короче — не сжимаем (кадр zstd со словарём не окупается).
+const MIN_COMPRESS_LEN: usize = 32;
+
+/// Версия словаря в value_dict.bin.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValueDictVersion {
+    pub version: u32,
+    pub level: i32,
+    pub crc32c: u32,
+    pub bytes: usize,
+}
+
+struct DictSlot {
+    info: ValueDictVersion,
+    cdict: EncoderDictionary<'static>,
+    ddict: DecoderDictionary<'static>,
+}
+
+/// zstd со словарём из value_dict.bin. Новые значения сжимаются последней версией словаря,
+/// читаются все версии файла (версия — в заголовке значения). id — по CRC версии 1 и не
+/// меняется при добавлении версий (ZstdDictValues::add_version, CLI `quiverdb dict-train`).
+pub struct ZstdDictValues {
+    /// По возрастанию версии; последняя — текущая.
+    slots: Vec<DictSlot>,
+}
+
+/// Обучить словарь zstd на выборке значений (до max_dict_bytes) без сохранения.
+pub fn train_value_dictionary<S: AsRef<[u8]>>(
+    samples: &[S],
+    max_dict_bytes: usize,
+) -> Result<Vec<u8>> {
+    zstd::dict::from_samples(samples, max_dict_bytes)
+        .context("train zstd value dictionary (need more or larger samples)")
+}
+
+impl ZstdDictValues {
+    /// Обучить словарь на выборке значений (до max_dict_bytes) и сохранить его под root.
+    pub fn train<S: AsRef<[u8]>>(
+        root: &Path,
+        samples: &[S],
+        max_dict_bytes: usize,
+        level: i32,
+    ) -> Result<Self> {
+        let dict = train_value_dictionary(samples, max_dict_bytes)?;
+        Self::from_dictionary(root, &dict, level)
+    }
+
+    /// Сохранить готовый словарь (например, `zstd --train`) под root как версию 1.
+    pub fn from_dictionary(root: &Path, dict: &[u8], level: i32) -> Result<Self> {
+        if dict.is_empty() {
+            return Err(anyhow!("empty zstd value dictionary"));
+        }
+        let mut buf = Vec::with_capacity(VALUE_DICT_HDR + dict.len());
+        buf.extend_from_slice(VALUE_DICT_MAGIC);
+        buf.extend_from_slice(&level.to_le_bytes());
+        buf.extend_from_slice(&crc32c::crc32c(dict).to_le_bytes());
+        buf.extend_from_slice(dict);
+        write_dict_file(root, &buf)?;
+        Ok(Self::build(&[(1, level, dict)]))
+    }
+
+    /// Добавить словарь следующей версией в value_dict.bin под root (файл должен существовать).
+    /// Хэндлы подхватывают её при следующем открытии; прежние версии остаются читаемыми.
+    pub fn add_version(root: &Path, dict: &[u8], level: i32) -> Result<u32> {
+        if dict.is_empty() {
+            return Err(anyhow!("empty zstd value dictionary"));
+        }
+        let path = root.join(VALUE_DICT_FILE);
+        let buf = fs::read(&path).with_context(|| format!("read {}", path.display()))?;
+        let mut entries = parse_dict_file(&buf)
+            .ok_or_else(|| anyhow!("corrupt value dictionary {}", path.display()))?;
+        let version = entries.last().map_or(1, |e| e.0 + 1);
+        entries.push((version, level, dict));
+
+        let total: usize = entries
+            .iter()
+            .map(|e| VALUE_DICT_ENTRY_HDR + e.2.len())
+            .sum();
+        let mut out = Vec::with_capacity(12 + total);
+        out.extend_from_slice(VALUE_DICT_MAGIC_V2);
+        out.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        for (ver, level, d) in &entries {
+            out.extend_from_slice(&ver.to_le_bytes());
+            out.extend_from_slice(&level.to_le_bytes());
+            out.extend_from_slice(&crc32c::crc32c(d).to_le_bytes());
+            out.extend_from_slice(&(d.len() as u32).to_le_bytes());
+            out.extend_from_slice(d);
+        }
+        write_dict_file(root, &out)?;
+        Ok(version)
+    }
+
+    /// Словарь (все версии) из value_dict.bin под root.
+    pub fn open(root: &Path) -> Result<Self> {
+        let path = root.join(VALUE_DICT_FILE);
+        let buf = fs::read(&path).with_context(|| format!("read {}", path.display()))?;
+        let entries = parse_dict_file(&buf)
+            .ok_or_else(|| anyhow!("corrupt value dictionary {}", path.display()))?;
+        Ok(Self::build(&entries))
+    }
+
+    fn build(entries: &[(u32, i32, &[u8])]) -> Self {
+        let slots = entries
+            .iter()
+            .map(|&(version, level, dict)| DictSlot {
+                info: ValueDictVersion {
+                    version,
+                    level,
+                    crc32c: crc32c::crc32c(dict),
+                    bytes: dict.len(),
+                },
+                cdict: EncoderDictionary::copy(dict, level),
+                ddict: DecoderDictionary::copy(dict),
+            })
+            .collect();
+        Self { slots }
+    }
+
+    fn current(&self) -> &DictSlot {
+        self.slots.last().expect("value dictionary has no versions")
+    }
+
+    /// Размер текущего словаря, байт.
+    pub fn dict_len(&self) -> usize {
+        self.current().info.bytes
+    }
+
+    /// Версия словаря, которой сжимаются новые значения.
+    pub fn current_version(&self) -> u32 {
+        self.current().info.version
+    }
+
+    /// Все версии словаря (по возрастанию).
+    pub fn versions(&self) -> Vec<ValueDictVersion> {
+        self.slots.iter().map(|s| s.info.clone()).collect()
+    }
+}
+
+impl ValueTransform for ZstdDictValues {
+    fn id(&self) -> String {
+        format!("zstd_dict:{:08x}", self.slots[0].info.crc32c)
+    }
+
+    fn encode<'a>(&self, value: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        let mut out = Vec::with_capacity(value.len() + 1);
+        if value.len() >= MIN_COMPRESS_LEN && value.len() <= u32::MAX as usize {
+            let cur = self.current();
+            let z = Compressor::with_prepared_dictionary(&cur.cdict)?.compress(value)?;
+            let hdr = if cur.info.version == 1 { 4 } else { 8 };
+            if z.len() + hdr < value.len() {
+                if cur.info.version == 1 {
+                    out.push(TAG_ZSTD);
+                } else {
+                    out.push(TAG_ZSTD_VERSIONED);
+                    out.extend_from_slice(&cur.info.version.to_le_bytes());
+                }
+                out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                out.extend_from_slice(&z);
+                return Ok(Cow::Owned(out));
+            }
+        }
+        out.push(TAG_RAW);
+        out.extend_from_slice(value);
+        Ok(Cow::Owned(out))
+    }
+
+    fn decode<'a>(&self, stored: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        let (version, body) = match stored.first() {
+            Some(&TAG_RAW) => return Ok(Cow::Borrowed(&stored[1..])),
+            Some(&TAG_ZSTD) if stored.len() >= 5 => (1, &stored[1..]),
+            Some(&TAG_ZSTD_VERSIONED) if stored.len() >= 9 => {
+                (LittleEndian::read_u32(&stored[1..5]), &stored[5..])
+            }
+            _ => return Err(anyhow!("value is not encoded with zstd_dict")),
+        };
+        let slot = self
+            .slots
+            .iter()
+            .find(|s| s.info.version == version)
+            .ok_or_else(|| {
+                anyhow!(
+                    "value uses zstd_dict version {} missing from {} (reopen to load new versions)",
+                    version,
+                    VALUE_DICT_FILE
+                )
+            })?;
+        let n = LittleEndian::read_u32(&body[0..4]) as usize;
+        let v = Decompressor::with_prepared_dictionary(&slot.ddict)?
+            .decompress(&body[4..], n)
+            .context("zstd_dict value decode")?;
+        if v.len() != n {
+            return Err(anyhow!("zstd_dict value length {} != {}", v.len(), n));
+        }
+        Ok(Cow::Owned(v))
+    }
+}
+
+/// Версии файла словаря (version, level, dict) по возрастанию версии.
+fn parse_dict_file(buf: &[u8]) -> Option<Vec<(u32, i32, &[u8])>> {
+    if buf.len() < 12 {
+        return None;
+    }
+    if &buf[0..8] == VALUE_DICT_MAGIC {
+        if buf.len() <= VALUE_DICT_HDR {
+            return None;
+        }
+        let level = LittleEndian::read_i32(&buf[8..12]);
+        let crc = LittleEndian::read_u32(&buf[12..16]);
+        let dict = &buf[VALUE_DICT_HDR..];
+        return (crc32c::crc32c(dict) == crc).then(|| vec![(1, level, dict)]);
+    }
+    if &buf[0..8] != VALUE_DICT_MAGIC_V2 {
+        return None;
+    }
+    let count = LittleEndian::read_u32(&buf[8..12]) as usize;
+    let mut out = Vec::with_capacity(count.min(1024));
+    let mut off = 12;
+    for _ in 0..count {
+        let hdr = buf.get(off..off + VALUE_DICT_ENTRY_HDR)?;
+        let version = LittleEndian::read_u32(&hdr[0..4]);
+        let level = LittleEndian::read_i32(&hdr[4..8]);
+        let crc = LittleEndian::read_u32(&hdr[8..12]);
+        let len = LittleEndian::read_u32(&hdr[12..16]) as usize;
+        off += VALUE_DICT_ENTRY_HDR;
+        let dict = buf.get(off..off.checked_add(len)?)?;
+        off += len;
+        let ordered = out.last().is_none_or(|p: &(u32, i32, &[u8])| p.0 < version);
+        if dict.is_empty() || crc32c::crc32c(dict) != crc || !ordered {
+            return None;
+        }
+        out.push((version, level, dict));
+    }
+    (off == buf.len() && !out.is_empty()).then_some(out)
+}
+
+/// Поставить сырой образ value_dict.bin (sidecar бэкапа) под root после проверки CRC.
+pub(crate) fn install_value_dict(root: &Path, file_bytes: &[u8]) -> Result<()> {
+    if parse_dict_file(file_bytes).is_none() {
+        return Err(anyhow!("corrupt value dictionary image"));
+    }
+    write_dict_file(root, file_bytes)
+}
+
+/// tmp + fsync + rename.
+fn write_dict_file(root: &Path, buf: &[u8]) -> Result<()> {
+    let path = root.join(VALUE_DICT_FILE);
+    let tmp = root.join(format!("{}.tmp", VALUE_DICT_FILE));
+    {
+        let mut f = fs::File::create(&tmp).with_context(|| format!("create {}", tmp.display()))?;
+        f.write_all(buf)?;
+        f.sync_all()?;
+    }
+    fs::rename(&tmp, &path).with_context(|| format!("install {}", path.display()))?;
+    Ok(())
+}
+
+/// Встроенная трансформация по идентификатору из meta (None — id не встроенный).
+/// Для zstd_dict словарь читается из root и должен совпасть с id.
+pub fn builtin_value_transform(root: &Path, id: &str) -> Result<Option<Arc<dyn ValueTransform>>> {
+    if !id.starts_with("zstd_dict:") {
+        return Ok(None);
+    }
+    let t = ZstdDictValues::open(root)?;
+    if t.id() != id {
+        return Err(anyhow!(
+            "value dictionary {} does not match the DB (has {}, DB uses {})",
+            root.join(VALUE_DICT_FILE).display(),
+            t.id(),
+            id
+        ));
+    }
+    Ok(Some(Arc::new(t)))
+}
+
+/// Трансформация хэндла по meta и конфигу (writer фиксирует её в пустой БД).
+pub(crate) fn resolve_value_transform(
+    root: &Path,
+    meta: &mut MetaHeader,
+    configured: Option<&ValueTransformRef>,
+    writer: bool,
+) -> Result<Option<Arc<dyn ValueTransform>>> {
+    let configured = configured.map(|r| r.0.clone());
+    let configured_id = configured.as_ref().map(|t| t.id()).unwrap_or_default();
+    let mismatch = || ValueTransformMismatchError {
+        path: root.to_path_buf(),
+        recorded: meta.value_transform.clone(),
+        configured: configured_id.clone(),
+    };
+    match configured {
+        None if meta.value_transform.is_empty() => Ok(None),
+        None => match builtin_value_transform(root, &meta.value_transform)? {
+            Some(t) => Ok(Some(t)),
+            None => Err(mismatch().into()),
+        },
+        Some(t) if configured_id == meta.value_transform => Ok(Some(t)),
+        // Пустая БД (ещё ни одной страницы) принимает трансформацию writer'а
+        Some(t) if meta.value_transform.is_empty() && writer && meta.next_page_id == 0 => {
+            let m = record_value_transform(root, &configured_id)?;
+            meta.value_transform = m.value_transform;
+            meta.flags = m.flags;
+            Ok(Some(t))
+        }
+        Some(_) => Err(mismatch().into()),
+    }
+}
+
+impl Db {
+    /// Идентификатор трансформации значений хэндла (None — значения хранятся как есть).
+    pub fn value_transform_id(&self) -> Option<String> {
+        self.value_transform.as_ref().map(|t| t.id())
+    }
+
+    /// Значение → байты хранения.
+    pub(crate) fn encode_value<'v>(&self, value: &'v [u8]) -> Result<Cow<'v, [u8]>> {
+        match self.value_transform.as_ref() {
+            Some(t) => t.encode(value),
+            None => Ok(Cow::Borrowed(value)),
+        }
+    }
+
+    /// Байты хранения → значение.
+    pub(crate) fn decode_value(&self, stored: Vec<u8>) -> Result<Vec<u8>> {
+        match self.value_transform.as_ref() {
+            Some(t) => Ok(t.decode(&stored)?.into_owned()),
+            None => Ok(stored),
+        }
+    }
+
+    /// Случайная выборка до n непустых значений (reservoir sampling по scan_stream), например
+    /// для обучения словаря (train_value_dictionary).
+    pub fn sample_values(&self, n: usize) -> Result<Vec<Vec<u8>>> {
+        let mut out: Vec<Vec<u8>> = Vec::with_capacity(n.min(1 << 16));
+        let mut seen = 0usize;
+        let mut rng = rand::thread_rng();
+        self.scan_stream(None, |_, v| {
+            if v.is_empty() || n == 0 {
+                return;
+            }
+            seen += 1;
+            if out.len() < n {
+                out.push(v.to_vec());
+            } else {
+                let j = rng.gen_range(0..seen);
+                if j < n {
+                    out[j] = v.to_vec();
+                }
+            }
+        })?;
+        Ok(out)
+    }
+
+    /// decode_value для списка пар скана.
+    pub(crate) fn decode_pairs(
+        &self,
+        pairs: Vec<(Vec<u8>, Vec<u8>)>,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        if self.value_transform.is_none() {
+            return Ok(pairs);
+        }
+        pairs
+            .into_iter()
+            .map(|(k, v)| Ok((k, self.decode_value(v)?)))
+            .collect()
+    }
+}
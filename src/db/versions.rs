@@ -0,0 +1,126 @@
+//! db/versions — история версий ключа (tail-wins цепочка) для аудита/отладки.
+//!
+//! Каждый put дописывает новую запись в голову цепочки бакета; старые записи того же ключа
+//! остаются глубже, пока их не вычистит компактация. Db::get_versions(key, limit) проходит
+//! цепочку head→tail и отдаёт до `limit` вхождений ключа «новые → старые», включая tombstone
+//! (value = None) и истёкшие по TTL записи (см. KeyVersion::is_expired) — как они лежат на диске.
+//!
+//! LSN версии — LSN страницы, на которой лежит запись (у записей нет собственного LSN):
+//! после компактации все перенесённые версии получают LSN коммита компактации.
+//!
+//! Компактация по умолчанию оставляет только новейшую версию. QuiverConfig::compaction_keep_versions
+//! (ENV P1_COMPACTION_KEEP_VERSIONS, default 1) сохраняет до M живых версий ключа: история
+//! обрывается на первом tombstone, истёкшие версии не переносятся.
+//!
+//! NEW: Db::key_history — журнал изменений ключа по LSN/батчам из индекса истории WAL
+//! (history.bin, QuiverConfig::history_index; см. wal/history.rs). В отличие от get_versions
+//! переживает компактацию: перенесённые копии видны как op=rewrite.
+
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::dir::NO_PAGE;
+use crate::page::kv::kv_for_each_record;
+use crate::page::ovf::chain::read_overflow_chain;
+use crate::page::{kv_header_read_v3, OFF_TYPE, PAGE_MAGIC, PAGE_TYPE_KV_RH3};
+use crate::util::decode_ovf_placeholder_v3;
+
+use crate::wal::{index_wal_history, key_history, HistoryEntry, HistoryIndexReport};
+
+use super::core::Db;
+
+/// Одна версия ключа из цепочки бакета.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyVersion {
+    /// LSN страницы, на которой лежит запись.
+    pub lsn: u64,
+    pub page_id: u64,
+    /// None — tombstone (del). OVERFLOW-значения раскрыты.
+    pub value: Option<Vec<u8>>,
+    /// 0 — бессрочно.
+    pub expires_at_sec: u32,
+}
+
+impl KeyVersion {
+    pub fn is_tombstone(&self) -> bool {
+        self.value.is_none()
+    }
+
+    /// Версия истекла по TTL на момент `now` (секунды unix).
+    pub fn is_expired(&self, now: u32) -> bool {
+        self.expires_at_sec != 0 && now >= self.expires_at_sec
+    }
+}
+
+impl Db {
+    /// Последние `limit` версий ключа («новые → старые»), насколько позволяет
+    /// некомпактированная цепочка (и compaction_keep_versions). Пустой Vec — ключа нет в цепочке.
+    pub fn get_versions(&self, key: &[u8], limit: usize) -> Result<Vec<KeyVersion>> {
+        let mut out: Vec<KeyVersion> = Vec::new();
+        if limit == 0 {
+            return Ok(out);
+        }
+        let key = self.canonical_key(key);
+        let key = key.as_ref();
+        let bucket = self.dir.bucket_of_key(key, self.pager.meta.hash_kind);
+        let mut pid = self.dir.head(bucket)?;
+        let mut page = vec![0u8; self.pager.meta.page_size as usize];
+        let cut = self.range_cut(key);
+
+        while pid != NO_PAGE && out.len() < limit {
+            self.pager.read_page(pid, &mut page)?;
+            if &page[0..4] != PAGE_MAGIC
+                || LittleEndian::read_u16(&page[OFF_TYPE..OFF_TYPE + 2]) != PAGE_TYPE_KV_RH3
+            {
+                break;
+            }
+            let h = kv_header_read_v3(&page)?;
+            // Глубже страницы range tombstone'а (Db::delete_prefix) версии удалены
+            if cut.is_some_and(|c| h.lsn <= c) {
+                break;
+            }
+
+            // Сырые вхождения страницы (плейсхолдеры раскрываются вне замыкания)
+            let mut found: Vec<(Option<Vec<u8>>, u32)> = Vec::new();
+            kv_for_each_record(&page, |k, v, expires_at_sec, vflags| {
+                if k == key && out.len() + found.len() < limit {
+                    let value = ((vflags & 0x1) == 0).then(|| v.to_vec());
+                    found.push((value, expires_at_sec));
+                }
+            });
+            for (value, expires_at_sec) in found {
+                let value = match value {
+                    Some(v) => match decode_ovf_placeholder_v3(&v) {
+                        Some((total, ovf_head)) => {
+                            Some(read_overflow_chain(&self.pager, ovf_head, total as usize)?)
+                        }
+                        None => Some(v),
+                    },
+                    None => None,
+                };
+                let value = value.map(|v| self.decode_value(v)).transpose()?;
+                out.push(KeyVersion {
+                    lsn: h.lsn,
+                    page_id: pid,
+                    value,
+                    expires_at_sec,
+                });
+            }
+
+            pid = h.next_page_id;
+        }
+        Ok(out)
+    }
+
+    /// Изменения ключа (put/del/expire/rewrite) по возрастанию LSN: history.bin + текущий WAL.
+    pub fn key_history(&self, key: &[u8]) -> Result<Vec<HistoryEntry>> {
+        key_history(&self.root, &self.canonical_key(key))
+    }
+
+    /// Writer‑операция: проиндексировать закоммиченные батчи текущего WAL в history.bin
+    /// (независимо от QuiverConfig::history_index; повторный вызов записи не дублирует).
+    pub fn index_history(&mut self) -> Result<HistoryIndexReport> {
+        self.pager.ensure_writable("index_history")?;
+        index_wal_history(&self.root)
+    }
+}
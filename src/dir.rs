@@ -7,7 +7,23 @@
 // - FIX (safety): в inplace‑режиме мы теперь ВСЕГДА делаем fsync файла каталога после записи голов и CRC,
 //   независимо от P1_DIR_FSYNC. Это снижает риск CRC mismatch при крэше в dev/bench режиме.
 // - NEW: предупреждение один раз при включённом inplace-режиме (bench-only).
-// - NEW: предупреждение один раз, если hash_kind != 1 (fallback на xxhash64(seed=0)).
+// - NEW: предупреждение один раз, если hash_kind не 1/2 (fallback на xxhash64(seed=0)).
+// - NEW: кеш голов в памяти + генерация heads.gen (кросс-процессная инвалидация).
+//   Writer после каждого обновления голов увеличивает u64-счётчик в <root>/heads.gen
+//   (mmap, без fsync — это только подсказка кешу). Читатели (RO-хэндлы, admin UI) сравнивают
+//   его с запомненным значением одним atomic load на head(); при расхождении массив голов
+//   перечитывается из dir-000 целиком. Без heads.gen (старые БД, RO до первого writer'а) —
+//   прежнее поведение: каждая head() читает 8 байт из файла.
+//   ENV P1_DIR_HEADS_CACHE=0|false|off|no — выключить кеш.
+// - NEW: heads_snapshot — согласованный снимок всех голов (сканы закрепляют его на старте).
+// - NEW: страничный формат P2DIR03 (dir/paged.rs) для больших bucket_count: open читает только
+//   заголовок, head() — один чанк через LRU чанков (инвалидация по heads.gen), обновление
+//   переписывает только затронутые чанки (двухслотовая запись). Выбирается Directory::create при
+//   buckets >= PAGED_DIR_AUTO_BUCKETS или ENV P1_DIR_PAGED=1 (P1_DIR_PAGED=0 — всегда P2DIR02).
+// - NEW: coalesce_head_updates — перед HEADS_UPDATE батча оставляет по одной записи на бакет и
+//   отбрасывает головы, совпадающие с текущими (метрика heads_update_skipped).
+// - NEW: маршрутизация по префиксу ключа (dir/routing.rs, hash_kind=2): bucket_for_key хэширует
+//   только BucketRouting::route_key — ключи `tenant/...` одного tenant'а в одном бакете.
 //
 // Формат:
 // MAGIC8 = "P2DIR02\0"
@@ -16,13 +32,28 @@
 // u32 crc32c  (CRC32C over [version u32][buckets u32] + heads bytes)
 // heads: buckets × u64 (LE), NO_PAGE = u64::MAX
 
+use crate::meta::{HASH_KIND_XX64_PREFIX, HASH_KIND_XX64_SEED0};
+use crate::util::fsx::{fsync_parent_dir, open_tmp_for_write, replace_file};
 use anyhow::{anyhow, Context, Result};
 use byteorder::{ByteOrder, LittleEndian};
 use crc32c::crc32c;
-use std::fs::OpenOptions;
+use memmap2::{Mmap, MmapMut};
+use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{OnceLock, RwLock};
+
+// NEW: страничный формат каталога (P2DIR03)
+mod paged;
+use paged::{create_paged_file, parse_header, PagedDir};
+// NEW: маршрутизация ключа в бакет по префиксу (составные ключи)
+pub mod routing;
+pub use paged::{
+    DirChunkCacheStats, PagedLayout, DEFAULT_CHUNK_HEADS, DIR_MAGIC_V3, DIR_VERSION_PAGED,
+    PAGED_DIR_AUTO_BUCKETS,
+};
+pub use routing::BucketRouting;
 
 pub const DIR_MAGIC: &[u8; 8] = b"P2DIR02\0";
 pub const DIR_VERSION: u32 = 2;
@@ -32,6 +63,12 @@ const SHARD0_FILE: &str = "dir-000";
 
 pub const NO_PAGE: u64 = u64::MAX;
 
+/// Side-car генерации голов каталога: 8 байт, u64 (LE на поддерживаемых платформах).
+pub const HEADS_GEN_FILE: &str = "heads.gen";
+
+/// Смещение массива голов в шарде: magic(8) + version(4) + buckets(4) + crc(4).
+const HEADS_OFF: u64 = 8 + 4 + 4 + 4;
+
 // ---------------- CRC helper (CRC32C) ----------------
 
 fn compute_dir_crc(version: u32, buckets: u32, heads_bytes: &[u8]) -> u32 {
@@ -45,22 +82,6 @@ fn compute_dir_crc(version: u32, buckets: u32, heads_bytes: &[u8]) -> u32 {
     crc32c(&buf)
 }
 
-#[cfg(unix)]
-fn fsync_parent_dir(path: &Path) -> std::io::Result<()> {
-    use std::fs::File;
-    if let Some(parent) = path.parent() {
-        if !parent.as_os_str().is_empty() {
-            let dir = File::open(parent)?;
-            dir.sync_all()?;
-        }
-    }
-    Ok(())
-}
-#[cfg(not(unix))]
-fn fsync_parent_dir(_path: &Path) -> std::io::Result<()> {
-    Ok(())
-}
-
 // --- ENV toggles (cached) ---
 
 fn dir_use_atomic() -> bool {
@@ -74,6 +95,30 @@ fn dir_use_atomic() -> bool {
     })
 }
 
+fn dir_heads_cache_enabled() -> bool {
+    static ON: OnceLock<bool> = OnceLock::new();
+    *ON.get_or_init(|| {
+        std::env::var("P1_DIR_HEADS_CACHE")
+            .ok()
+            .map(|s| s.trim().to_ascii_lowercase())
+            .map(|s| !(s == "0" || s == "false" || s == "off" || s == "no"))
+            .unwrap_or(true)
+    })
+}
+
+/// ENV P1_DIR_PAGED: 1 — всегда P2DIR03, 0 — всегда P2DIR02, не задан — по числу бакетов.
+fn dir_paged_wanted(buckets: u32) -> bool {
+    match std::env::var("P1_DIR_PAGED")
+        .ok()
+        .map(|s| s.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("1" | "true" | "yes" | "on") => true,
+        Some("0" | "false" | "no" | "off") => false,
+        _ => buckets >= PAGED_DIR_AUTO_BUCKETS,
+    }
+}
+
 // NEW: одноразовое предупреждение о неатомарном режиме
 fn warn_inplace_once() {
     static WARNED: OnceLock<()> = OnceLock::new();
@@ -91,7 +136,7 @@ fn warn_hash_kind_once(kind: u32) {
     static WARNED: OnceLock<()> = OnceLock::new();
     WARNED.get_or_init(|| {
         eprintln!(
-            "[WARN] Directory::bucket_of_key: hash_kind != 1 (got {}), \
+            "[WARN] Directory::bucket_of_key: unsupported hash_kind {}, \
              falling back to xxhash64(seed=0). Future versions may change hashing.",
             kind
         );
@@ -103,6 +148,38 @@ pub struct Directory {
     root: PathBuf,
     _shard_count: u32, // пока всегда 1
     pub bucket_count: u32,
+    // NEW: кеш голов (None — читаем с диска на каждую head())
+    heads_cache: Option<HeadsCache>,
+    // NEW: P2DIR03 — геометрия + LRU чанков и постоянный RO-хэндл шарда (None — P2DIR02)
+    paged: Option<(PagedDir, File)>,
+}
+
+/// mmap heads.gen: writer — на запись, читатели — только чтение.
+#[derive(Debug)]
+enum GenMap {
+    Ro(Mmap),
+    Rw(MmapMut),
+}
+
+impl GenMap {
+    #[inline]
+    fn cell(&self) -> &AtomicU64 {
+        let p = match self {
+            GenMap::Ro(m) => m.as_ptr(),
+            GenMap::Rw(m) => m.as_ptr(),
+        };
+        // mmap выровнен по странице, длина >= 8; запись идёт только через Rw-отображение
+        unsafe { &*(p as *const AtomicU64) }
+    }
+}
+
+#[derive(Debug)]
+struct HeadsCache {
+    gen: GenMap,
+    /// Генерация, соответствующая heads (u64::MAX — ещё не загружено).
+    seen: AtomicU64,
+    /// P2DIR03: пусто (головы кешируются чанками в PagedDir).
+    heads: RwLock<Vec<u64>>,
 }
 
 impl Directory {
@@ -120,6 +197,9 @@ impl Directory {
         if buckets == 0 {
             return Err(anyhow!("buckets must be > 0"));
         }
+        if dir_paged_wanted(buckets) {
+            return Self::create_paged(root, buckets, DEFAULT_CHUNK_HEADS);
+        }
 
         let path = Self::shard_path_static(root, 0);
         if path.exists() {
@@ -171,9 +251,27 @@ impl Directory {
             root: root.to_path_buf(),
             _shard_count: 1,
             bucket_count: buckets,
+            heads_cache: None,
+            paged: None,
         })
     }
 
+    /// NEW: создать каталог в страничном формате P2DIR03 (chunk_heads голов на чанк).
+    pub fn create_paged(root: &Path, buckets: u32, chunk_heads: u32) -> Result<Self> {
+        if buckets == 0 {
+            return Err(anyhow!("buckets must be > 0"));
+        }
+        let path = Self::shard_path_static(root, 0);
+        if path.exists() {
+            return Err(anyhow!(
+                "directory shard already exists at {}",
+                path.display()
+            ));
+        }
+        create_paged_file(&path, PagedLayout::new(buckets, chunk_heads))?;
+        Self::open(root)
+    }
+
     pub fn open(root: &Path) -> Result<Self> {
         let path = Self::shard_path_static(root, 0);
         let mut f = OpenOptions::new()
@@ -184,6 +282,24 @@ impl Directory {
         // magic
         let mut magic = [0u8; 8];
         f.read_exact(&mut magic)?;
+        if &magic == DIR_MAGIC_V3 {
+            // P2DIR03: только заголовок, чанки читаются лениво
+            let mut hdr = [0u8; 64];
+            f.seek(SeekFrom::Start(0))?;
+            f.read_exact(&mut hdr)
+                .with_context(|| format!("read paged directory header {}", path.display()))?;
+            let layout = parse_header(&hdr, &path)?;
+            if f.metadata()?.len() < layout.file_len() {
+                return Err(anyhow!("paged directory {} is truncated", path.display()));
+            }
+            return Ok(Self {
+                root: root.to_path_buf(),
+                _shard_count: 1,
+                bucket_count: layout.buckets,
+                heads_cache: None,
+                paged: Some((PagedDir::new(layout), f)),
+            });
+        }
         if &magic != DIR_MAGIC {
             return Err(anyhow!("bad directory magic at {}", path.display()));
         }
@@ -228,9 +344,35 @@ impl Directory {
             root: root.to_path_buf(),
             _shard_count: 1,
             bucket_count: buckets,
+            heads_cache: None,
+            paged: None,
         })
     }
 
+    /// NEW: каталог в страничном формате P2DIR03.
+    #[inline]
+    pub fn is_paged(&self) -> bool {
+        self.paged.is_some()
+    }
+
+    /// NEW: геометрия P2DIR03 (None — P2DIR02).
+    pub fn paged_layout(&self) -> Option<PagedLayout> {
+        self.paged.as_ref().map(|(p, _)| p.layout)
+    }
+
+    /// NEW: заполненность LRU чанков (None — P2DIR02).
+    pub fn chunk_cache_stats(&self) -> Option<DirChunkCacheStats> {
+        self.paged.as_ref().map(|(p, _)| p.stats())
+    }
+
+    /// Генерация для кеша чанков (None — кеш выключен: читаем чанк с диска).
+    #[inline]
+    fn cache_gen(&self) -> Option<u64> {
+        self.heads_cache
+            .as_ref()
+            .map(|c| c.gen.cell().load(Ordering::Acquire))
+    }
+
     pub fn head(&self, bucket: u32) -> Result<u64> {
         if bucket >= self.bucket_count {
             return Err(anyhow!(
@@ -239,9 +381,31 @@ impl Directory {
                 self.bucket_count - 1
             ));
         }
+        if let Some((p, f)) = self.paged.as_ref() {
+            return p.head(f, bucket, self.cache_gen());
+        }
+        if let Some(c) = self.heads_cache.as_ref() {
+            let gen = c.gen.cell().load(Ordering::Acquire);
+            if c.seen.load(Ordering::Acquire) != gen {
+                match self.read_all_heads() {
+                    Ok(h) => {
+                        *c.heads.write().unwrap_or_else(|e| e.into_inner()) = h;
+                        c.seen.store(gen, Ordering::Release);
+                    }
+                    // writer посреди inplace-обновления (CRC ещё не совпал) — читаем голову с диска
+                    Err(_) => return self.head_from_file(bucket),
+                }
+            }
+            let heads = c.heads.read().unwrap_or_else(|e| e.into_inner());
+            return Ok(heads[bucket as usize]);
+        }
+        self.head_from_file(bucket)
+    }
+
+    fn head_from_file(&self, bucket: u32) -> Result<u64> {
         let path = self.shard_path(0);
         // смещение: magic(8) + version(4) + buckets(4) + crc(4) + bucket*8
-        let offset = (8 + 4 + 4 + 4) as u64 + (bucket as u64) * 8;
+        let offset = HEADS_OFF + (bucket as u64) * 8;
         let mut f = OpenOptions::new().read(true).open(&path)?;
         f.seek(SeekFrom::Start(offset))?;
         let mut buf8 = [0u8; 8];
@@ -281,13 +445,203 @@ impl Directory {
             }
         }
 
-        if dir_use_atomic() {
-            self.set_heads_bulk_atomic(updates)
+        if let Some((p, _)) = self.paged.as_ref() {
+            let path = self.shard_path(0);
+            let f = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&path)
+                .with_context(|| format!("open directory shard {}", path.display()))?;
+            p.apply(&f, updates, self.cache_gen())?;
+        } else if dir_use_atomic() {
+            self.set_heads_bulk_atomic(updates)?;
         } else {
             // bench-only предупреждение (один раз)
             warn_inplace_once();
-            self.set_heads_bulk_inplace(updates)
+            self.set_heads_bulk_inplace(updates)?;
         }
+        self.publish_heads(updates);
+        Ok(())
+    }
+
+    /// NEW: свести обновления голов батча к реально изменённым бакетам.
+    ///
+    /// По одной записи на bucket (побеждает последняя), по возрастанию bucket; записи, чья голова
+    /// совпадает с текущей, отбрасываются (учёт в метриках). Результат пригоден и для
+    /// HEADS_UPDATE, и для set_heads_bulk.
+    pub fn coalesce_head_updates(&self, updates: &[(u32, u64)]) -> Result<Vec<(u32, u64)>> {
+        let norm = crate::wal::normalize_head_updates(updates);
+        let mut out = Vec::with_capacity(norm.len());
+        for (b, pid) in norm {
+            if self.head(b)? != pid {
+                out.push((b, pid));
+            }
+        }
+        let skipped = (updates.len() - out.len()) as u64;
+        if skipped > 0 {
+            crate::metrics::record_heads_update_skipped(skipped);
+        }
+        Ok(out)
+    }
+
+    // ------- heads cache / generation -------
+
+    /// NEW: включить кеш голов с генерацией heads.gen.
+    ///
+    /// writer=true — создать heads.gen при отсутствии и продвинуть генерацию (учесть правки
+    /// каталога до открытия: реплей WAL, restore, офлайн-утилиты). writer=false — только если
+    /// файл уже есть. Ok(false) — кеш не включён (ENV P1_DIR_HEADS_CACHE=0 или нет heads.gen).
+    pub fn enable_heads_cache(&mut self, writer: bool) -> Result<bool> {
+        if !dir_heads_cache_enabled() {
+            return Ok(false);
+        }
+        let p = self.root.join(HEADS_GEN_FILE);
+        let gen = if writer {
+            let f = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&p)
+                .with_context(|| format!("open {}", p.display()))?;
+            if f.metadata()?.len() < 8 {
+                f.set_len(8)?;
+            }
+            GenMap::Rw(
+                unsafe { MmapMut::map_mut(&f) }.with_context(|| format!("mmap {}", p.display()))?,
+            )
+        } else {
+            let f = match std::fs::File::open(&p) {
+                Ok(f) => f,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+                Err(e) => return Err(e).with_context(|| format!("open {}", p.display())),
+            };
+            if f.metadata()?.len() < 8 {
+                return Ok(false);
+            }
+            GenMap::Ro(unsafe { Mmap::map(&f) }.with_context(|| format!("mmap {}", p.display()))?)
+        };
+        if writer {
+            gen.cell().fetch_add(1, Ordering::AcqRel);
+        }
+        let g = gen.cell().load(Ordering::Acquire);
+        let heads = if self.paged.is_some() {
+            Vec::new()
+        } else {
+            self.read_all_heads()?
+        };
+        self.heads_cache = Some(HeadsCache {
+            gen,
+            seen: AtomicU64::new(g),
+            heads: RwLock::new(heads),
+        });
+        Ok(true)
+    }
+
+    /// NEW: согласованный снимок голов всех бакетов (один момент времени) — для длинных сканов.
+    ///
+    /// С кешем — копия кешированного массива (он всегда перечитывается из шарда целиком);
+    /// без кеша — шард целиком (tmp+rename даёт атомарную замену; в inplace-режиме CRC
+    /// может временно не совпадать — несколько повторов).
+    pub fn heads_snapshot(&self) -> Result<Vec<u64>> {
+        if let Some((p, f)) = self.paged.as_ref() {
+            // P2DIR03: чанк за чанком; с heads.gen — повтор, если writer успел обновить головы
+            for _ in 0..3 {
+                let g0 = self.cache_gen();
+                let h = p.read_all(f)?;
+                if g0.is_none() || g0 == self.cache_gen() {
+                    return Ok(h);
+                }
+            }
+            return p.read_all(f);
+        }
+        if let Some(c) = self.heads_cache.as_ref() {
+            if self.bucket_count > 0 {
+                // подтянуть актуальную генерацию
+                self.head(0)?;
+            }
+            return Ok(c.heads.read().unwrap_or_else(|e| e.into_inner()).clone());
+        }
+        let mut last_err = None;
+        for _ in 0..3 {
+            match self.read_all_heads() {
+                Ok(h) => return Ok(h),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| anyhow!("directory heads snapshot failed")))
+    }
+
+    /// Текущая генерация голов (None — кеш выключен).
+    pub fn heads_generation(&self) -> Option<u64> {
+        self.heads_cache
+            .as_ref()
+            .map(|c| c.gen.cell().load(Ordering::Acquire))
+    }
+
+    /// После записи голов: обновить свой кеш и продвинуть генерацию для остальных читателей.
+    fn publish_heads(&self, updates: &[(u32, u64)]) {
+        let c = match self.heads_cache.as_ref() {
+            Some(c) => c,
+            None => {
+                // Некешированный писатель (restore/утилиты): всё равно инвалидируем чужие кеши
+                bump_heads_gen_file(&self.root);
+                return;
+            }
+        };
+        let before = c.gen.cell().load(Ordering::Acquire);
+        if let Some((p, _)) = self.paged.as_ref() {
+            // Свои чанки уже обновлены в LRU (PagedDir::apply)
+            let g = c.gen.cell().fetch_add(1, Ordering::AcqRel).wrapping_add(1);
+            p.advance_seen(before, g);
+            c.seen.store(g, Ordering::Release);
+            return;
+        }
+        {
+            let mut heads = c.heads.write().unwrap_or_else(|e| e.into_inner());
+            if c.seen.load(Ordering::Acquire) == before {
+                // порядок updates сохраняется — последняя запись для bucket побеждает
+                for &(b, pid) in updates {
+                    heads[b as usize] = pid;
+                }
+            } else if let Ok(h) = self.read_all_heads() {
+                *heads = h;
+            }
+        }
+        let g = c.gen.cell().fetch_add(1, Ordering::AcqRel).wrapping_add(1);
+        c.seen.store(g, Ordering::Release);
+    }
+
+    /// Прочитать все головы шарда (с проверкой magic/версии/CRC).
+    fn read_all_heads(&self) -> Result<Vec<u64>> {
+        if let Some((p, f)) = self.paged.as_ref() {
+            return p.read_all(f);
+        }
+        let path = self.shard_path(0);
+        let bytes = std::fs::read(&path)
+            .with_context(|| format!("read directory shard {}", path.display()))?;
+        let need = HEADS_OFF as usize + self.bucket_count as usize * 8;
+        if bytes.len() < need || &bytes[0..8] != DIR_MAGIC {
+            return Err(anyhow!("bad directory shard {}", path.display()));
+        }
+        let version = LittleEndian::read_u32(&bytes[8..12]);
+        let buckets = LittleEndian::read_u32(&bytes[12..16]);
+        let stored_crc = LittleEndian::read_u32(&bytes[16..20]);
+        if buckets != self.bucket_count {
+            return Err(anyhow!(
+                "buckets mismatch: header={}, struct={}",
+                buckets,
+                self.bucket_count
+            ));
+        }
+        let heads_bytes = &bytes[HEADS_OFF as usize..need];
+        if stored_crc != 0 && compute_dir_crc(version, buckets, heads_bytes) != stored_crc {
+            return Err(anyhow!("directory CRC mismatch at {}", path.display()));
+        }
+        Ok(heads_bytes
+            .chunks_exact(8)
+            .map(LittleEndian::read_u64)
+            .collect())
     }
 
     // ------- atomic path (tmp+rename) -------
@@ -370,12 +724,8 @@ impl Directory {
         ));
         let _ = std::fs::remove_file(&tmp);
 
-        let mut tf = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(&tmp)
-            .with_context(|| format!("open tmp {}", tmp.display()))?;
+        let mut tf =
+            open_tmp_for_write(&tmp).with_context(|| format!("open tmp {}", tmp.display()))?;
 
         // header
         tf.write_all(DIR_MAGIC)?;
@@ -396,7 +746,7 @@ impl Directory {
         tf.write_all(&buf4)?;
         let _ = tf.sync_all();
 
-        std::fs::rename(&tmp, &path)?;
+        replace_file(&tmp, &path)?;
         let _ = fsync_parent_dir(&path);
         Ok(())
     }
@@ -486,17 +836,20 @@ impl Directory {
         Ok(())
     }
 
-    /// Вычислить bucket для ключа (xxhash64(seed=0)).
+    /// Вычислить bucket для ключа (xxhash64(seed=0) от ключа или его префикса — hash_kind=2).
     pub fn bucket_of_key(&self, key: &[u8], hash_kind: u32) -> u32 {
-        if hash_kind != 1 {
+        if hash_kind != HASH_KIND_XX64_SEED0 && hash_kind & 0xFF != HASH_KIND_XX64_PREFIX {
             warn_hash_kind_once(hash_kind);
         }
-        let h = hash64_xxseed0(key, hash_kind);
-        (h % (self.bucket_count as u64)) as u32
+        bucket_for_key(key, hash_kind, self.bucket_count)
     }
 
     /// Подсчитать количество используемых bucket'ов (head != NO_PAGE).
     pub fn count_used_buckets(&self) -> Result<u32> {
+        if self.paged.is_some() {
+            let heads = self.read_all_heads()?;
+            return Ok(heads.iter().filter(|&&pid| pid != NO_PAGE).count() as u32);
+        }
         let path = self.shard_path(0);
         let mut f = OpenOptions::new().read(true).open(&path)?;
         f.seek(SeekFrom::Start((8 + 4 + 4 + 4) as u64))?;
@@ -513,6 +866,34 @@ impl Directory {
     }
 }
 
+/// Продвинуть генерацию в существующем heads.gen (best-effort, без mmap).
+fn bump_heads_gen_file(root: &Path) {
+    let p = root.join(HEADS_GEN_FILE);
+    let mut f = match OpenOptions::new().read(true).write(true).open(&p) {
+        Ok(f) => f,
+        Err(_) => return,
+    };
+    let mut buf8 = [0u8; 8];
+    if f.read_exact(&mut buf8).is_err() {
+        return;
+    }
+    let next = u64::from_le_bytes(buf8).wrapping_add(1);
+    if f.seek(SeekFrom::Start(0)).is_ok() {
+        let _ = f.write_all(&next.to_le_bytes());
+    }
+}
+
+/// Bucket ключа без открытого каталога (CDC-фильтр: геометрия известна заранее).
+pub fn bucket_for_key(key: &[u8], hash_kind: u32, bucket_count: u32) -> u32 {
+    let key = if hash_kind & 0xFF == HASH_KIND_XX64_PREFIX {
+        BucketRouting::from_hash_kind(hash_kind).route_key(key)
+    } else {
+        key
+    };
+    let h = hash64_xxseed0(key, hash_kind);
+    (h % (bucket_count.max(1) as u64)) as u32
+}
+
 // xxhash64(seed=0) helper
 fn hash64_xxseed0(key: &[u8], _hash_kind: u32) -> u64 {
     use std::hash::Hasher;
@@ -0,0 +1,413 @@
+//! dir/paged — страничный формат каталога P2DIR03 для больших bucket_count.
+//!
+//! Классический P2DIR02 держит все головы одним массивом под одним CRC: open читает файл целиком,
+//! каждое обновление голов переписывает его целиком (tmp+rename), кеш голов — полный Vec в памяти.
+//! При миллионах бакетов это десятки мегабайт на каждый коммит.
+//!
+//! P2DIR03 делит головы на чанки по chunk_heads голов:
+//! - open читает только заголовок (64 B);
+//! - head() читает один чанк (с LRU чанков в памяти, инвалидация по heads.gen);
+//! - обновление переписывает только затронутые чанки.
+//!
+//! Атомарность чанка — два слота (shadow copy): новая версия пишется в неактивный слот
+//! с seq+1 и CRC, затем fsync; читатель берёт валидный слот с большим seq. Порванная запись
+//! оставляет прежний слот. Обновление нескольких чанков не атомарно как целое, но головы
+//! коммита уже записаны в WAL (HEADS_UPDATE) до записи каталога — реплей их доприменяет.
+//!
+//! Формат (LE):
+//!   header (64 B): [magic8="P2DIR03\0"][u32 version=3][u32 buckets][u32 chunk_heads][u32 chunks]
+//!                  [u8 reserved 40][u32 crc32c(header[0..60])]
+//!   chunk i, slot s (0|1) по смещению 64 + (i*2 + s) * slot_size:
+//!                  [u64 seq][u32 crc32c(seq8 + heads)][u32 reserved][heads: chunk_heads × u64]
+//!   Последний чанк хранит полный chunk_heads (хвост — NO_PAGE). seq=0 — пустой слот.
+
+use anyhow::{anyhow, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use crc32c::crc32c;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::util::fsx::fsync_parent_dir;
+
+use super::NO_PAGE;
+
+pub const DIR_MAGIC_V3: &[u8; 8] = b"P2DIR03\0";
+pub const DIR_VERSION_PAGED: u32 = 3;
+
+/// Голов в чанке по умолчанию (8 KiB на слот).
+pub const DEFAULT_CHUNK_HEADS: u32 = 1024;
+
+/// Начиная с этого bucket_count Directory::create выбирает страничный формат.
+pub const PAGED_DIR_AUTO_BUCKETS: u32 = 1 << 20;
+
+const HDR_V3: u64 = 64;
+const SLOT_HDR: usize = 16;
+
+/// ENV P1_DIR_CHUNK_CACHE — ёмкость LRU чанков (по умолчанию 256 чанков ≈ 2 MiB).
+fn chunk_cache_cap() -> usize {
+    std::env::var("P1_DIR_CHUNK_CACHE")
+        .ok()
+        .and_then(|s| s.trim().parse::<usize>().ok())
+        .unwrap_or(256)
+        .max(1)
+}
+
+/// Геометрия страничного каталога.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PagedLayout {
+    pub buckets: u32,
+    pub chunk_heads: u32,
+    pub chunks: u32,
+}
+
+impl PagedLayout {
+    pub fn new(buckets: u32, chunk_heads: u32) -> Self {
+        let chunk_heads = chunk_heads.max(1);
+        Self {
+            buckets,
+            chunk_heads,
+            chunks: buckets.div_ceil(chunk_heads),
+        }
+    }
+
+    #[inline]
+    fn slot_size(&self) -> u64 {
+        SLOT_HDR as u64 + self.chunk_heads as u64 * 8
+    }
+
+    #[inline]
+    fn slot_off(&self, chunk: u32, slot: u8) -> u64 {
+        HDR_V3 + (chunk as u64 * 2 + slot as u64) * self.slot_size()
+    }
+
+    /// Размер файла каталога.
+    pub fn file_len(&self) -> u64 {
+        HDR_V3 + self.chunks as u64 * 2 * self.slot_size()
+    }
+
+    #[inline]
+    pub fn locate(&self, bucket: u32) -> (u32, usize) {
+        (
+            bucket / self.chunk_heads,
+            (bucket % self.chunk_heads) as usize,
+        )
+    }
+}
+
+/// Версия чанка, прочитанная с диска.
+#[derive(Debug, Clone)]
+pub(crate) struct ChunkImage {
+    pub seq: u64,
+    pub slot: u8,
+    pub heads: Vec<u64>,
+}
+
+fn slot_crc(seq: u64, heads_bytes: &[u8]) -> u32 {
+    let mut buf = Vec::with_capacity(8 + heads_bytes.len());
+    buf.extend_from_slice(&seq.to_le_bytes());
+    buf.extend_from_slice(heads_bytes);
+    crc32c(&buf)
+}
+
+/// Создать файл P2DIR03 (все головы NO_PAGE, слот 0 каждого чанка — seq=1).
+pub(crate) fn create_paged_file(path: &Path, layout: PagedLayout) -> Result<()> {
+    let f = OpenOptions::new()
+        .create_new(true)
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("create directory shard {}", path.display()))?;
+    f.set_len(layout.file_len())?;
+
+    let mut hdr = [0u8; HDR_V3 as usize];
+    hdr[0..8].copy_from_slice(DIR_MAGIC_V3);
+    LittleEndian::write_u32(&mut hdr[8..12], DIR_VERSION_PAGED);
+    LittleEndian::write_u32(&mut hdr[12..16], layout.buckets);
+    LittleEndian::write_u32(&mut hdr[16..20], layout.chunk_heads);
+    LittleEndian::write_u32(&mut hdr[20..24], layout.chunks);
+    let crc = crc32c(&hdr[..60]);
+    LittleEndian::write_u32(&mut hdr[60..64], crc);
+    write_at(&f, 0, &hdr)?;
+
+    let empty = vec![NO_PAGE; layout.chunk_heads as usize];
+    for c in 0..layout.chunks {
+        write_slot(&f, &layout, c, 0, 1, &empty)?;
+    }
+    let _ = f.sync_all();
+    let _ = fsync_parent_dir(path);
+    Ok(())
+}
+
+/// Разобрать заголовок P2DIR03 (bytes — начало файла, >= 64 B).
+pub(crate) fn parse_header(bytes: &[u8], path: &Path) -> Result<PagedLayout> {
+    if bytes.len() < HDR_V3 as usize || &bytes[0..8] != DIR_MAGIC_V3 {
+        return Err(anyhow!("bad paged directory header at {}", path.display()));
+    }
+    if crc32c(&bytes[..60]) != LittleEndian::read_u32(&bytes[60..64]) {
+        return Err(anyhow!(
+            "paged directory header CRC mismatch at {}",
+            path.display()
+        ));
+    }
+    let version = LittleEndian::read_u32(&bytes[8..12]);
+    if version != DIR_VERSION_PAGED {
+        return Err(anyhow!(
+            "unsupported dir version {} at {}",
+            version,
+            path.display()
+        ));
+    }
+    let buckets = LittleEndian::read_u32(&bytes[12..16]);
+    let chunk_heads = LittleEndian::read_u32(&bytes[16..20]);
+    let chunks = LittleEndian::read_u32(&bytes[20..24]);
+    let layout = PagedLayout::new(buckets, chunk_heads);
+    if buckets == 0 || chunk_heads == 0 || layout.chunks != chunks {
+        return Err(anyhow!(
+            "inconsistent paged directory geometry at {}",
+            path.display()
+        ));
+    }
+    Ok(layout)
+}
+
+/// Прочитать актуальную версию чанка (валидный слот с большим seq).
+pub(crate) fn read_chunk(f: &File, layout: &PagedLayout, chunk: u32) -> Result<ChunkImage> {
+    let n = layout.chunk_heads as usize;
+    let mut buf = vec![0u8; SLOT_HDR + n * 8];
+    let mut best: Option<ChunkImage> = None;
+    for slot in 0..2u8 {
+        read_at(f, layout.slot_off(chunk, slot), &mut buf)?;
+        let seq = LittleEndian::read_u64(&buf[0..8]);
+        if seq == 0 || slot_crc(seq, &buf[SLOT_HDR..]) != LittleEndian::read_u32(&buf[8..12]) {
+            continue;
+        }
+        if best.as_ref().is_none_or(|b| seq > b.seq) {
+            best = Some(ChunkImage {
+                seq,
+                slot,
+                heads: buf[SLOT_HDR..]
+                    .chunks_exact(8)
+                    .map(LittleEndian::read_u64)
+                    .collect(),
+            });
+        }
+    }
+    best.ok_or_else(|| anyhow!("directory chunk {} has no valid slot", chunk))
+}
+
+fn write_slot(
+    f: &File,
+    layout: &PagedLayout,
+    chunk: u32,
+    slot: u8,
+    seq: u64,
+    heads: &[u64],
+) -> Result<()> {
+    debug_assert!(chunk < layout.chunks && heads.len() == layout.chunk_heads as usize);
+    let mut buf = vec![0u8; SLOT_HDR + heads.len() * 8];
+    for (i, h) in heads.iter().enumerate() {
+        LittleEndian::write_u64(&mut buf[SLOT_HDR + i * 8..SLOT_HDR + i * 8 + 8], *h);
+    }
+    LittleEndian::write_u64(&mut buf[0..8], seq);
+    let crc = slot_crc(seq, &buf[SLOT_HDR..]);
+    LittleEndian::write_u32(&mut buf[8..12], crc);
+    write_at(f, layout.slot_off(chunk, slot), &buf)
+}
+
+#[cfg(unix)]
+fn read_at(f: &File, off: u64, buf: &mut [u8]) -> Result<()> {
+    use std::os::unix::fs::FileExt;
+    f.read_exact_at(buf, off)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn write_at(f: &File, off: u64, buf: &[u8]) -> Result<()> {
+    use std::os::unix::fs::FileExt;
+    f.write_all_at(buf, off)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn read_at(f: &File, off: u64, buf: &mut [u8]) -> Result<()> {
+    use std::io::{Read, Seek, SeekFrom};
+    let mut f = f;
+    f.seek(SeekFrom::Start(off))?;
+    f.read_exact(buf)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn write_at(f: &File, off: u64, buf: &[u8]) -> Result<()> {
+    use std::io::{Seek, SeekFrom, Write};
+    let mut f = f;
+    f.seek(SeekFrom::Start(off))?;
+    f.write_all(buf)?;
+    Ok(())
+}
+
+// ---------------- LRU чанков ----------------
+
+#[derive(Debug)]
+struct ChunkLru {
+    cap: usize,
+    /// Генерация heads.gen, которой соответствуют чанки (u64::MAX — пусто).
+    seen: u64,
+    tick: u64,
+    map: HashMap<u32, (u64, ChunkImage)>,
+}
+
+impl ChunkLru {
+    fn touch(&mut self, chunk: u32) -> Option<&ChunkImage> {
+        self.tick += 1;
+        let t = self.tick;
+        self.map.get_mut(&chunk).map(|e| {
+            e.0 = t;
+            &e.1
+        })
+    }
+
+    fn put(&mut self, chunk: u32, img: ChunkImage) {
+        if self.map.len() >= self.cap && !self.map.contains_key(&chunk) {
+            if let Some((&victim, _)) = self.map.iter().min_by_key(|(_, e)| e.0) {
+                self.map.remove(&victim);
+            }
+        }
+        self.tick += 1;
+        self.map.insert(chunk, (self.tick, img));
+    }
+}
+
+/// Состояние страничного каталога хэндла: геометрия + LRU чанков.
+#[derive(Debug)]
+pub(crate) struct PagedDir {
+    pub layout: PagedLayout,
+    lru: Mutex<ChunkLru>,
+}
+
+/// Счётчики LRU чанков (diagnostics).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirChunkCacheStats {
+    pub chunks_cached: usize,
+    pub capacity: usize,
+}
+
+impl PagedDir {
+    pub(crate) fn new(layout: PagedLayout) -> Self {
+        Self {
+            layout,
+            lru: Mutex::new(ChunkLru {
+                cap: chunk_cache_cap(),
+                seen: u64::MAX,
+                tick: 0,
+                map: HashMap::new(),
+            }),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> DirChunkCacheStats {
+        let lru = self.lru.lock().unwrap_or_else(|e| e.into_inner());
+        DirChunkCacheStats {
+            chunks_cached: lru.map.len(),
+            capacity: lru.cap,
+        }
+    }
+
+    /// Актуальный чанк: из LRU (gen = Some — кеш разрешён) или с диска.
+    pub(crate) fn chunk(&self, f: &File, chunk: u32, gen: Option<u64>) -> Result<ChunkImage> {
+        let g = match gen {
+            Some(g) => g,
+            None => return read_chunk(f, &self.layout, chunk),
+        };
+        {
+            let mut lru = self.lru.lock().unwrap_or_else(|e| e.into_inner());
+            if lru.seen != g {
+                lru.map.clear();
+                lru.seen = g;
+            }
+            if let Some(img) = lru.touch(chunk) {
+                return Ok(img.clone());
+            }
+        }
+        let img = read_chunk(f, &self.layout, chunk)?;
+        let mut lru = self.lru.lock().unwrap_or_else(|e| e.into_inner());
+        // Генерация сменилась, пока читали, — не кешируем (следующий head() сбросит LRU)
+        if lru.seen == g {
+            lru.put(chunk, img.clone());
+        }
+        Ok(img)
+    }
+
+    /// Голова бакета.
+    pub(crate) fn head(&self, f: &File, bucket: u32, gen: Option<u64>) -> Result<u64> {
+        let (c, i) = self.layout.locate(bucket);
+        Ok(self.chunk(f, c, gen)?.heads[i])
+    }
+
+    /// writer: применить обновления (последняя запись для bucket побеждает), переписав
+    /// только затронутые чанки. gen — текущая генерация (None — без кеша).
+    pub(crate) fn apply(&self, f: &File, updates: &[(u32, u64)], gen: Option<u64>) -> Result<()> {
+        let mut by_chunk: Vec<(u32, usize, u64)> = updates
+            .iter()
+            .enumerate()
+            .map(|(n, &(b, pid))| {
+                let (c, _) = self.layout.locate(b);
+                (c, n, pid)
+            })
+            .collect();
+        by_chunk.sort_by_key(|&(c, n, _)| (c, n));
+        let mut i = 0;
+        while i < by_chunk.len() {
+            let c = by_chunk[i].0;
+            let mut cur = self.chunk(f, c, gen)?;
+            let mut changed = false;
+            while i < by_chunk.len() && by_chunk[i].0 == c {
+                let (b, pid) = updates[by_chunk[i].1];
+                let (_, off) = self.layout.locate(b);
+                if cur.heads[off] != pid {
+                    cur.heads[off] = pid;
+                    changed = true;
+                }
+                i += 1;
+            }
+            if !changed {
+                continue;
+            }
+            let next = ChunkImage {
+                seq: cur.seq + 1,
+                slot: cur.slot ^ 1,
+                heads: cur.heads,
+            };
+            write_slot(f, &self.layout, c, next.slot, next.seq, &next.heads)?;
+            f.sync_data()?;
+            if let Some(g) = gen {
+                let mut lru = self.lru.lock().unwrap_or_else(|e| e.into_inner());
+                if lru.seen == g {
+                    lru.put(c, next);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Генерация продвинута своим же обновлением: LRU остаётся валидным.
+    pub(crate) fn advance_seen(&self, before: u64, after: u64) {
+        let mut lru = self.lru.lock().unwrap_or_else(|e| e.into_inner());
+        if lru.seen == before {
+            lru.seen = after;
+        }
+    }
+
+    /// Все головы (чанк за чанком, мимо LRU).
+    pub(crate) fn read_all(&self, f: &File) -> Result<Vec<u64>> {
+        let mut out = Vec::with_capacity(self.layout.buckets as usize);
+        for c in 0..self.layout.chunks {
+            let img = read_chunk(f, &self.layout, c)?;
+            let take = (self.layout.buckets as usize - out.len()).min(img.heads.len());
+            out.extend_from_slice(&img.heads[..take]);
+        }
+        Ok(out)
+    }
+}
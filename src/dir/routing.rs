@@ -0,0 +1,179 @@
+//! dir/routing — маршрутизация ключа в бакет по префиксу (составные ключи `tenant/obj_id`).
+//!
+//! По умолчанию бакет — xxhash64(ключ) % buckets: ключи одного tenant'а разлетаются по всем
+//! бакетам, и scan_prefix("tenant/") обходит все цепочки. BucketRouting хэширует только
+//! начало ключа:
+//! - delimiter — ключ до первого вхождения байта (без него; нет вхождения — весь ключ);
+//! - prefix_len — не больше prefix_len первых байт (после отсечения по delimiter).
+//!
+//! Маршрутизация фиксируется при init и хранится в meta.hash_kind (HASH_KIND_XX64_PREFIX,
+//! параметры — в старших битах), поэтому без изменений переезжает с backup/snapshot/clone и
+//! известна CDC-фильтру; её required-бит FEATURE_BUCKET_ROUTING не даёт старым сборкам открыть
+//! такую БД с другой раскладкой. Раскладка hash_kind:
+//! - биты 0..7   — HASH_KIND_XX64_PREFIX (2);
+//! - биты 8..15  — байт-разделитель; бит 16 — разделитель задан;
+//! - биты 17..31 — prefix_len (0 — без ограничения длины; ≤ BUCKET_KEY_PREFIX_LEN_MAX).
+//!
+//! Префикс скана, однозначно задающий маршрутный ключ (route_of_prefix), сводит
+//! scan_prefix / scan_prefix_iter / scan_keys к одному бакету.
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use crate::meta::{HASH_KIND_XX64_PREFIX, HASH_KIND_XX64_SEED0};
+
+/// Предел prefix_len (15 бит в hash_kind).
+pub const BUCKET_KEY_PREFIX_LEN_MAX: u16 = 0x7FFF;
+
+const DELIM_SET: u32 = 1 << 16;
+
+/// Какая часть ключа определяет бакет (см. модульный комментарий).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BucketRouting {
+    pub delimiter: Option<u8>,
+    /// 0 — без ограничения.
+    pub prefix_len: u16,
+}
+
+impl BucketRouting {
+    /// Маршрутизация по всему ключу (по умолчанию).
+    pub fn full_key() -> Self {
+        Self::default()
+    }
+
+    pub fn by_delimiter(delimiter: u8) -> Self {
+        Self {
+            delimiter: Some(delimiter),
+            prefix_len: 0,
+        }
+    }
+
+    pub fn by_prefix_len(prefix_len: u16) -> Self {
+        Self {
+            delimiter: None,
+            prefix_len,
+        }
+    }
+
+    pub fn is_full_key(&self) -> bool {
+        self.delimiter.is_none() && self.prefix_len == 0
+    }
+
+    /// ENV для init: P1_BUCKET_KEY_DELIMITER (один байт, например "/"),
+    /// P1_BUCKET_KEY_PREFIX_LEN (N > 0).
+    pub fn from_env() -> Result<Self> {
+        let mut r = Self::full_key();
+        if let Ok(v) = std::env::var("P1_BUCKET_KEY_DELIMITER") {
+            r.delimiter = parse_delimiter(&v)?;
+        }
+        if let Ok(v) = std::env::var("P1_BUCKET_KEY_PREFIX_LEN") {
+            r.prefix_len = v
+                .trim()
+                .parse::<u16>()
+                .map_err(|e| anyhow!("P1_BUCKET_KEY_PREFIX_LEN: {}", e))?;
+        }
+        r.validate()?;
+        Ok(r)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.prefix_len > BUCKET_KEY_PREFIX_LEN_MAX {
+            return Err(anyhow!(
+                "bucket key prefix_len {} exceeds {}",
+                self.prefix_len,
+                BUCKET_KEY_PREFIX_LEN_MAX
+            ));
+        }
+        Ok(())
+    }
+
+    /// Значение meta.hash_kind для этой маршрутизации.
+    pub fn to_hash_kind(&self) -> u32 {
+        if self.is_full_key() {
+            return HASH_KIND_XX64_SEED0;
+        }
+        let mut hk = HASH_KIND_XX64_PREFIX;
+        if let Some(d) = self.delimiter {
+            hk |= (d as u32) << 8 | DELIM_SET;
+        }
+        hk | ((self.prefix_len as u32) & BUCKET_KEY_PREFIX_LEN_MAX as u32) << 17
+    }
+
+    /// Маршрутизация из meta.hash_kind (прочие виды — по всему ключу).
+    pub fn from_hash_kind(hash_kind: u32) -> Self {
+        if hash_kind & 0xFF != HASH_KIND_XX64_PREFIX {
+            return Self::full_key();
+        }
+        Self {
+            delimiter: (hash_kind & DELIM_SET != 0).then_some((hash_kind >> 8) as u8),
+            prefix_len: (hash_kind >> 17) as u16,
+        }
+    }
+
+    /// Часть ключа, по которой считается бакет.
+    pub fn route_key<'a>(&self, key: &'a [u8]) -> &'a [u8] {
+        let mut k = key;
+        if let Some(d) = self.delimiter {
+            if let Some(i) = k.iter().position(|&c| c == d) {
+                k = &k[..i];
+            }
+        }
+        self.cap(k)
+    }
+
+    /// Маршрутный ключ, общий для всех ключей с префиксом `prefix` (None — не определён:
+    /// такие ключи могут лежать в разных бакетах).
+    pub fn route_of_prefix<'a>(&self, prefix: &'a [u8]) -> Option<&'a [u8]> {
+        if self.is_full_key() {
+            return None;
+        }
+        if let Some(d) = self.delimiter {
+            if let Some(i) = prefix.iter().position(|&c| c == d) {
+                return Some(self.cap(&prefix[..i]));
+            }
+        }
+        // Разделителя в префиксе нет: маршрут задан, только если префикс покрывает prefix_len
+        let n = self.prefix_len as usize;
+        if n > 0 && prefix.len() >= n {
+            return Some(&prefix[..n]);
+        }
+        None
+    }
+
+    fn cap<'a>(&self, k: &'a [u8]) -> &'a [u8] {
+        let n = self.prefix_len as usize;
+        if n > 0 && k.len() > n {
+            &k[..n]
+        } else {
+            k
+        }
+    }
+}
+
+impl std::fmt::Display for BucketRouting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_full_key() {
+            return write!(f, "full_key");
+        }
+        let mut parts = Vec::new();
+        if let Some(d) = self.delimiter {
+            parts.push(format!("delimiter={:?}", d as char));
+        }
+        if self.prefix_len > 0 {
+            parts.push(format!("prefix_len={}", self.prefix_len));
+        }
+        write!(f, "prefix({})", parts.join(", "))
+    }
+}
+
+/// Разделитель из строки: ровно один байт ("" — не задан).
+pub fn parse_delimiter(s: &str) -> Result<Option<u8>> {
+    match s.as_bytes() {
+        [] => Ok(None),
+        [b] => Ok(Some(*b)),
+        _ => Err(anyhow!(
+            "bucket key delimiter must be a single byte, got {:?}",
+            s
+        )),
+    }
+}
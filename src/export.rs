@@ -0,0 +1,367 @@
+//! export — выгрузка всех живых ключей в порядке возрастания ключа (внешняя сортировка).
+//!
+//! Раскладка БД хэшевая (бакеты), порядок ключей она не даёт; downstream bulk-load инструментам
+//! часто нужен отсортированный вход. export_sorted_to_writer(&db, impl Write, opts):
+//! - scan_stream живых пар (TTL/tombstone-aware, опционально по префиксу) в буфер памяти;
+//! - при превышении бюджета (ExportOptions::mem_budget_bytes) буфер сортируется и сбрасывается
+//!   во временный run-файл (tmp_dir);
+//! - k-way merge run'ов (BinaryHeap) прямо в выходной поток; run-файлы удаляются по завершении
+//!   (и при ошибке).
+//!
+//! Ключи в скане уникальны, поэтому merge не дедуплицирует. Порядок — лексикографический по байтам.
+//! read_sorted_export(impl Read, cb) читает дамп, проверяя порядок, счётчик и CRC.
+//!
+//! Формат дампа (LE), P2EXS001:
+//!   header (32 B): [magic8][u32 version=1][u32 flags=0][u64 count][u64 created_unix_ms]
+//!   records:       [u32 klen][u32 vlen][key][value]  (ключи строго возрастают)
+//!   trailer (16 B): [u32 0xFFFF_FFFF][u32 crc32c(все records)][u64 count]
+//! Run-файлы — те же records без header/trailer.
+
+use anyhow::{anyhow, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::db::Db;
+use crate::util::fsx::{open_tmp_for_write, replace_file};
+
+pub const EXPORT_MAGIC: &[u8; 8] = b"P2EXS001";
+pub const EXPORT_VERSION: u32 = 1;
+const HDR_LEN: usize = 32;
+const END_MARKER: u32 = u32::MAX;
+/// Оценка накладных расходов одной пары в буфере (два Vec + кортеж).
+const ENTRY_OVERHEAD: usize = 64;
+
+/// Параметры выгрузки.
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// Бюджет памяти буфера сортировки (байт); при превышении — spill run'а на диск.
+    pub mem_budget_bytes: usize,
+    /// Каталог временных run-файлов (None — std::env::temp_dir()).
+    pub tmp_dir: Option<PathBuf>,
+    /// Выгружать только ключи с префиксом.
+    pub prefix: Option<Vec<u8>>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            mem_budget_bytes: 64 << 20,
+            tmp_dir: None,
+            prefix: None,
+        }
+    }
+}
+
+/// Итог выгрузки.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportReport {
+    pub keys: u64,
+    /// Сумма длин ключей и значений.
+    pub payload_bytes: u64,
+    /// Сколько run'ов сброшено на диск (0 — всё отсортировано в памяти).
+    pub runs_spilled: u64,
+    pub bytes_written: u64,
+}
+
+/// Выгрузить живые пары в отсортированном порядке в out.
+pub fn export_sorted_to_writer<W: Write>(
+    db: &Db,
+    out: W,
+    opts: &ExportOptions,
+) -> Result<ExportReport> {
+    let tmp_dir = opts.tmp_dir.clone().unwrap_or_else(std::env::temp_dir);
+    let mut runs = RunSet {
+        dir: tmp_dir,
+        paths: Vec::new(),
+    };
+    let mut rep = ExportReport::default();
+
+    let mut buf: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+    let mut buf_bytes = 0usize;
+    let mut spill_err: Option<anyhow::Error> = None;
+    db.scan_stream(opts.prefix.as_deref(), |k, v| {
+        if spill_err.is_some() {
+            return;
+        }
+        rep.keys += 1;
+        rep.payload_bytes += (k.len() + v.len()) as u64;
+        buf_bytes += k.len() + v.len() + ENTRY_OVERHEAD;
+        buf.push((k.to_vec(), v.to_vec()));
+        if buf_bytes >= opts.mem_budget_bytes {
+            if let Err(e) = runs.spill(&mut buf) {
+                spill_err = Some(e);
+            }
+            buf_bytes = 0;
+        }
+    })?;
+    if let Some(e) = spill_err {
+        return Err(e);
+    }
+    rep.runs_spilled = runs.paths.len() as u64;
+
+    let mut w = CountingWriter {
+        inner: BufWriter::new(out),
+        crc: 0,
+        written: 0,
+    };
+    let mut hdr = [0u8; HDR_LEN];
+    hdr[0..8].copy_from_slice(EXPORT_MAGIC);
+    LittleEndian::write_u32(&mut hdr[8..12], EXPORT_VERSION);
+    LittleEndian::write_u32(&mut hdr[12..16], 0);
+    LittleEndian::write_u64(&mut hdr[16..24], rep.keys);
+    LittleEndian::write_u64(&mut hdr[24..32], now_ms());
+    w.write_raw(&hdr)?;
+
+    buf.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    if runs.paths.is_empty() {
+        for (k, v) in &buf {
+            w.write_record(k, v)?;
+        }
+    } else {
+        // Остаток буфера — последний run (в памяти)
+        let mut sources: Vec<RunSource> = Vec::with_capacity(runs.paths.len() + 1);
+        for p in &runs.paths {
+            let f = File::open(p).with_context(|| format!("open run {}", p.display()))?;
+            sources.push(RunSource::File(BufReader::new(f)));
+        }
+        sources.push(RunSource::Mem(std::mem::take(&mut buf).into_iter()));
+
+        let mut heads: Vec<Option<Vec<u8>>> = vec![None; sources.len()];
+        let mut heap: BinaryHeap<Reverse<(Vec<u8>, usize)>> = BinaryHeap::new();
+        for (i, s) in sources.iter_mut().enumerate() {
+            if let Some((k, v)) = s.next_pair()? {
+                heads[i] = Some(v);
+                heap.push(Reverse((k, i)));
+            }
+        }
+        while let Some(Reverse((k, i))) = heap.pop() {
+            let v = heads[i].take().unwrap_or_default();
+            w.write_record(&k, &v)?;
+            if let Some((nk, nv)) = sources[i].next_pair()? {
+                heads[i] = Some(nv);
+                heap.push(Reverse((nk, i)));
+            }
+        }
+    }
+
+    let mut tr = [0u8; 16];
+    LittleEndian::write_u32(&mut tr[0..4], END_MARKER);
+    LittleEndian::write_u32(&mut tr[4..8], w.crc);
+    LittleEndian::write_u64(&mut tr[8..16], rep.keys);
+    w.write_raw(&tr)?;
+    w.inner.flush()?;
+    rep.bytes_written = w.written;
+    Ok(rep)
+}
+
+/// Выгрузить в файл (через tmp + rename); путь "-" — stdout.
+pub fn export_sorted_to_path(db: &Db, out: &Path, opts: &ExportOptions) -> Result<ExportReport> {
+    if out.as_os_str() == "-" {
+        let stdout = std::io::stdout();
+        return export_sorted_to_writer(db, stdout.lock(), opts);
+    }
+    let tmp = out.with_extension("tmp");
+    let rep = {
+        let f = open_tmp_for_write(&tmp).with_context(|| format!("open {}", tmp.display()))?;
+        let rep = export_sorted_to_writer(db, &f, opts);
+        if rep.is_ok() {
+            f.sync_all()?;
+        }
+        rep
+    };
+    let rep = match rep {
+        Ok(r) => r,
+        Err(e) => {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e);
+        }
+    };
+    replace_file(&tmp, out)
+        .with_context(|| format!("rename {} -> {}", tmp.display(), out.display()))?;
+    Ok(rep)
+}
+
+/// Прочитать отсортированный дамп: cb(key, value) для каждой пары по порядку.
+/// Проверяет magic/version, строгий порядок ключей, trailer (счётчик и CRC). Возвращает число пар.
+pub fn read_sorted_export<R, F>(r: R, mut cb: F) -> Result<u64>
+where
+    R: Read,
+    F: FnMut(&[u8], &[u8]) -> Result<()>,
+{
+    let mut r = BufReader::new(r);
+    let mut hdr = [0u8; HDR_LEN];
+    r.read_exact(&mut hdr).context("read export header")?;
+    if &hdr[0..8] != EXPORT_MAGIC {
+        return Err(anyhow!("bad export magic (expected {:?})", EXPORT_MAGIC));
+    }
+    let ver = LittleEndian::read_u32(&hdr[8..12]);
+    if ver != EXPORT_VERSION {
+        return Err(anyhow!("unsupported export version {}", ver));
+    }
+    let count = LittleEndian::read_u64(&hdr[16..24]);
+
+    let mut crc = 0u32;
+    let mut n = 0u64;
+    let mut prev: Option<Vec<u8>> = None;
+    let (mut k, mut v) = (Vec::new(), Vec::new());
+    loop {
+        let mut lens = [0u8; 8];
+        r.read_exact(&mut lens).context("read export record")?;
+        let klen = LittleEndian::read_u32(&lens[0..4]);
+        if klen == END_MARKER {
+            let want_crc = LittleEndian::read_u32(&lens[4..8]);
+            let mut cnt = [0u8; 8];
+            r.read_exact(&mut cnt).context("read export trailer")?;
+            let tr_count = LittleEndian::read_u64(&cnt);
+            if want_crc != crc {
+                return Err(anyhow!(
+                    "export crc mismatch (trailer {:#010x}, computed {:#010x})",
+                    want_crc,
+                    crc
+                ));
+            }
+            if tr_count != n || count != n {
+                return Err(anyhow!(
+                    "export count mismatch (header {}, trailer {}, read {})",
+                    count,
+                    tr_count,
+                    n
+                ));
+            }
+            return Ok(n);
+        }
+        let vlen = LittleEndian::read_u32(&lens[4..8]) as usize;
+        k.resize(klen as usize, 0);
+        v.resize(vlen, 0);
+        r.read_exact(&mut k).context("read export key")?;
+        r.read_exact(&mut v).context("read export value")?;
+        crc = crc32c::crc32c_append(crc, &lens);
+        crc = crc32c::crc32c_append(crc, &k);
+        crc = crc32c::crc32c_append(crc, &v);
+        if let Some(p) = prev.as_ref() {
+            if p.as_slice() >= k.as_slice() {
+                return Err(anyhow!("export keys out of order at record {}", n));
+            }
+        }
+        cb(&k, &v)?;
+        prev = Some(k.clone());
+        n += 1;
+    }
+}
+
+/// Прочитать дамп из файла.
+pub fn read_sorted_export_path<F>(path: &Path, cb: F) -> Result<u64>
+where
+    F: FnMut(&[u8], &[u8]) -> Result<()>,
+{
+    let f = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    read_sorted_export(f, cb).with_context(|| format!("read {}", path.display()))
+}
+
+// -------------------- internals --------------------
+
+/// Временные run-файлы; удаляются в Drop.
+struct RunSet {
+    dir: PathBuf,
+    paths: Vec<PathBuf>,
+}
+
+impl RunSet {
+    fn spill(&mut self, buf: &mut Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+        buf.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        let path = self.dir.join(format!(
+            ".qdb-export-{}-{}-{}.run",
+            std::process::id(),
+            now_ms(),
+            self.paths.len()
+        ));
+        // Регистрируем до записи — частичный файл тоже будет удалён
+        self.paths.push(path.clone());
+        let f = File::create(&path).with_context(|| format!("create run {}", path.display()))?;
+        let mut w = BufWriter::new(f);
+        for (k, v) in buf.drain(..) {
+            write_pair(&mut w, &k, &v)?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for RunSet {
+    fn drop(&mut self) {
+        for p in &self.paths {
+            let _ = std::fs::remove_file(p);
+        }
+    }
+}
+
+enum RunSource {
+    File(BufReader<File>),
+    Mem(std::vec::IntoIter<(Vec<u8>, Vec<u8>)>),
+}
+
+impl RunSource {
+    fn next_pair(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        match self {
+            RunSource::Mem(it) => Ok(it.next()),
+            RunSource::File(r) => {
+                let mut lens = [0u8; 8];
+                match r.read_exact(&mut lens) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                    Err(e) => return Err(e).context("read run"),
+                }
+                let mut k = vec![0u8; LittleEndian::read_u32(&lens[0..4]) as usize];
+                let mut v = vec![0u8; LittleEndian::read_u32(&lens[4..8]) as usize];
+                r.read_exact(&mut k).context("read run key")?;
+                r.read_exact(&mut v).context("read run value")?;
+                Ok(Some((k, v)))
+            }
+        }
+    }
+}
+
+fn write_pair<W: Write>(w: &mut W, k: &[u8], v: &[u8]) -> Result<[u8; 8]> {
+    let mut lens = [0u8; 8];
+    LittleEndian::write_u32(&mut lens[0..4], k.len() as u32);
+    LittleEndian::write_u32(&mut lens[4..8], v.len() as u32);
+    w.write_all(&lens)?;
+    w.write_all(k)?;
+    w.write_all(v)?;
+    Ok(lens)
+}
+
+struct CountingWriter<W: Write> {
+    inner: BufWriter<W>,
+    crc: u32,
+    written: u64,
+}
+
+impl<W: Write> CountingWriter<W> {
+    fn write_raw(&mut self, b: &[u8]) -> Result<()> {
+        self.inner.write_all(b)?;
+        self.written += b.len() as u64;
+        Ok(())
+    }
+
+    fn write_record(&mut self, k: &[u8], v: &[u8]) -> Result<()> {
+        let lens = write_pair(&mut self.inner, k, v)?;
+        self.crc = crc32c::crc32c_append(self.crc, &lens);
+        self.crc = crc32c::crc32c_append(self.crc, k);
+        self.crc = crc32c::crc32c_append(self.crc, v);
+        self.written += (8 + k.len() + v.len()) as u64;
+        Ok(())
+    }
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
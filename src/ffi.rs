@@ -7,9 +7,22 @@
 //! - Opaque-хэндл QdbDb управляет ресурсами Db (writer/reader).
 //! - Ошибки возвращаются через int (0=OK, -1=ERR) и out_err (char**).
 //! - Значения (get) возвращаются через QdbBuf {ptr,len} с явным освобождением qdb_buf_free().
+//! - NEW: qdb_get_into — значение копируется в буфер вызывающего (без malloc/qdb_buf_free; Python:
+//!   ctypes + bytearray/memoryview без PyBytes). Это не zero-copy: Db::get собирает значение в
+//!   Vec (аллокация + копия со страниц), затем оно копируется в dst.
+//! - NEW: qdb_get_view — собранный Db::get буфер отдаётся вызывающему как есть (QdbView, без
+//!   второй копии и без malloc); ptr/len валидны до qdb_view_release. Страницы кэша не
+//!   закрепляются: значение может лежать в нескольких OVERFLOW-страницах и быть сжато.
+//! - NEW: qdb_get_json_path (фичи "ffi" + "json") — только поле JSON-значения по пути,
+//!   компактным JSON-текстом в QdbBuf (Db::get_json_path), без передачи всего блоба.
 //!
 //! Безопасность/правила:
 //! - Все указатели проверяются на NULL; out-указатели должны быть валидны.
+//! - NEW: хэндл потокобезопасен — внутренний RwLock: чтения (get/get_into/get_view/exists/
+//!   get_json_path) идут параллельно под shared-блокировкой, put/del — под эксклюзивной.
+//!   qdb_close — только когда других вызовов по хэндлу нет.
+//! - GIL: Python ctypes сам отпускает GIL на время вызова. pyo3-обвязки (py.allow_threads) в
+//!   дереве нет — её обвязка должна отпускать GIL сама; C ABI состояния Python не требует.
 //! - Строки (path) — нуль-терминированные C-строки (UTF-8 если возможно).
 //! - Память под out-строки/буферы выделяется в Rust и освобождается qdb_string_free/qdb_buf_free.
 //!
@@ -21,8 +34,10 @@
 //!   cbindgen --crate QuiverDB --output quiverdb.h
 
 use std::ffi::{CStr, CString};
+use std::ops::{Deref, DerefMut};
 use std::ptr;
 use std::slice;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use libc::{c_char, c_int, c_uchar, c_uint, size_t};
 
@@ -34,16 +49,61 @@ use anyhow::Result; // re-export из lib.rs
 #[repr(C)]
 pub struct QdbDb {
     inner: *mut Db,
+    // Вызовы из нескольких потоков (Python ctypes отпускает GIL на время вызова): чтения — shared,
+    // put/del — exclusive
+    lock: RwLock<()>,
 }
 
 impl QdbDb {
     fn from_box(b: Box<Db>) -> *mut QdbDb {
         let raw_db = Box::into_raw(b);
-        let h = QdbDb { inner: raw_db };
+        let h = QdbDb {
+            inner: raw_db,
+            lock: RwLock::new(()),
+        };
         Box::into_raw(Box::new(h))
     }
-    unsafe fn as_mut_db<'a>(&self) -> Option<&'a mut Db> {
-        (self.inner as *mut Db).as_mut()
+    /// Эксклюзивный доступ к Db на время вызова (write-блокировка хэндла).
+    unsafe fn as_mut_db(&self) -> Option<DbGuard<'_>> {
+        let guard = self.lock.write().unwrap_or_else(|e| e.into_inner());
+        self.inner.as_mut().map(|db| DbGuard { _guard: guard, db })
+    }
+    /// Разделяемый доступ к Db (read-блокировка): параллельные чтения не ждут друг друга.
+    unsafe fn as_db(&self) -> Option<DbReadGuard<'_>> {
+        let guard = self.lock.read().unwrap_or_else(|e| e.into_inner());
+        self.inner
+            .as_ref()
+            .map(|db| DbReadGuard { _guard: guard, db })
+    }
+}
+
+struct DbGuard<'a> {
+    _guard: RwLockWriteGuard<'a, ()>,
+    db: &'a mut Db,
+}
+
+struct DbReadGuard<'a> {
+    _guard: RwLockReadGuard<'a, ()>,
+    db: &'a Db,
+}
+
+impl Deref for DbReadGuard<'_> {
+    type Target = Db;
+    fn deref(&self) -> &Db {
+        self.db
+    }
+}
+
+impl Deref for DbGuard<'_> {
+    type Target = Db;
+    fn deref(&self) -> &Db {
+        self.db
+    }
+}
+
+impl DerefMut for DbGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Db {
+        self.db
     }
 }
 
@@ -55,6 +115,11 @@ pub struct QdbBuf {
     pub len: size_t,
 }
 
+/// Значение, отданное qdb_get_view: владеет буфером, освобождается qdb_view_release.
+pub struct QdbView {
+    data: Vec<u8>,
+}
+
 // ---------- Helpers ----------
 
 unsafe fn cstr_to_path(c: *const c_char) -> Result<std::path::PathBuf, String> {
@@ -223,7 +288,7 @@ pub unsafe extern "C" fn qdb_put(
         }
     };
     match db_ref {
-        Ok(d) => match d.put(key, val) {
+        Ok(mut d) => match d.put(key, val) {
             Ok(_) => ret_ok(),
             Err(e) => {
                 set_err(out_err, &format!("{:#}", e));
@@ -266,7 +331,7 @@ pub unsafe extern "C" fn qdb_del(
         }
     };
     match db_ref {
-        Ok(d) => match d.del(key) {
+        Ok(mut d) => match d.del(key) {
             Ok(ex) => {
                 *out_existed = if ex { 1 } else { 0 };
                 ret_ok()
@@ -302,7 +367,7 @@ pub unsafe extern "C" fn qdb_exists(
     *out_present = 0;
 
     let db_ref = (&*db)
-        .as_mut_db()
+        .as_db()
         .ok_or_else(|| "invalid db handle".to_string());
     let key = match bytes_from(key_ptr, key_len) {
         Ok(k) => k,
@@ -349,7 +414,7 @@ pub unsafe extern "C" fn qdb_get(
     (*out_buf).len = 0;
 
     let db_ref = (&*db)
-        .as_mut_db()
+        .as_db()
         .ok_or_else(|| "invalid db handle".to_string());
     let key = match bytes_from(key_ptr, key_len) {
         Ok(k) => k,
@@ -395,6 +460,211 @@ pub unsafe extern "C" fn qdb_get(
     }
 }
 
+/// get без аллокации в Rust: значение копируется в буфер вызывающего (dst, dst_cap).
+///
+/// *out_found = 1/0; *out_len — длина значения (даже если не поместилось). Если
+/// *out_len > dst_cap, в dst ничего не пишется — вызывающий увеличивает буфер и повторяет.
+/// Для Python (ctypes): dst — bytearray/memoryview, значение читается без PyBytes-копии.
+///
+/// # Safety
+/// db — хэндл из qdb_open_*; key_ptr/dst валидны на key_len/dst_cap байт; out-указатели валидны.
+#[no_mangle]
+pub unsafe extern "C" fn qdb_get_into(
+    db: *mut QdbDb,
+    key_ptr: *const c_uchar,
+    key_len: size_t,
+    dst: *mut c_uchar,
+    dst_cap: size_t,
+    out_len: *mut size_t,
+    out_found: *mut c_int,
+    out_err: *mut *mut c_char,
+) -> c_int {
+    if db.is_null() {
+        set_err(out_err, "db is null");
+        return ret_err();
+    }
+    if out_len.is_null() || out_found.is_null() {
+        set_err(out_err, "out_len/out_found is null");
+        return ret_err();
+    }
+    *out_len = 0;
+    *out_found = 0;
+
+    let db_ref = (&*db)
+        .as_db()
+        .ok_or_else(|| "invalid db handle".to_string());
+    let key = match bytes_from(key_ptr, key_len) {
+        Ok(k) => k,
+        Err(e) => {
+            set_err(out_err, &e);
+            return ret_err();
+        }
+    };
+
+    match db_ref {
+        Ok(d) => match d.get(key) {
+            Ok(Some(v)) => {
+                let n = v.len();
+                *out_found = 1;
+                *out_len = n as size_t;
+                if n > 0 && n <= dst_cap {
+                    if dst.is_null() {
+                        set_err(out_err, "dst is null");
+                        return ret_err();
+                    }
+                    ptr::copy_nonoverlapping(v.as_ptr(), dst, n);
+                }
+                ret_ok()
+            }
+            Ok(None) => ret_ok(),
+            Err(e) => {
+                set_err(out_err, &format!("{:#}", e));
+                ret_err()
+            }
+        },
+        Err(e) => {
+            set_err(out_err, &e);
+            ret_err()
+        }
+    }
+}
+
+/// get с выдачей буфера значения без копии: *out_view — хэндл (NULL, если ключа нет),
+/// *out_ptr/*out_len — данные значения, валидные до qdb_view_release(*out_view).
+/// Для Python (ctypes): memoryview поверх (out_ptr, out_len), release — при закрытии view.
+///
+/// # Safety
+/// db — хэндл из qdb_open_*; key_ptr валиден на key_len байт; out-указатели валидны.
+#[no_mangle]
+pub unsafe extern "C" fn qdb_get_view(
+    db: *mut QdbDb,
+    key_ptr: *const c_uchar,
+    key_len: size_t,
+    out_view: *mut *mut QdbView,
+    out_ptr: *mut *const c_uchar,
+    out_len: *mut size_t,
+    out_err: *mut *mut c_char,
+) -> c_int {
+    if db.is_null() {
+        set_err(out_err, "db is null");
+        return ret_err();
+    }
+    if out_view.is_null() || out_ptr.is_null() || out_len.is_null() {
+        set_err(out_err, "out_view/out_ptr/out_len is null");
+        return ret_err();
+    }
+    *out_view = ptr::null_mut();
+    *out_ptr = ptr::null();
+    *out_len = 0;
+
+    let db_ref = (&*db)
+        .as_db()
+        .ok_or_else(|| "invalid db handle".to_string());
+    let key = match bytes_from(key_ptr, key_len) {
+        Ok(k) => k,
+        Err(e) => {
+            set_err(out_err, &e);
+            return ret_err();
+        }
+    };
+
+    match db_ref {
+        Ok(d) => match d.get(key) {
+            Ok(Some(v)) => {
+                let view = Box::new(QdbView { data: v });
+                *out_ptr = view.data.as_ptr();
+                *out_len = view.data.len() as size_t;
+                *out_view = Box::into_raw(view);
+                ret_ok()
+            }
+            Ok(None) => ret_ok(),
+            Err(e) => {
+                set_err(out_err, &format!("{:#}", e));
+                ret_err()
+            }
+        },
+        Err(e) => {
+            set_err(out_err, &e);
+            ret_err()
+        }
+    }
+}
+
+/// Поле JSON-значения ключа по пути (path — C-строка, см. db/json_path.rs) компактным
+/// JSON-текстом в *out_buf. Нет ключа или пути — ret 0 и пустой буфер (len=0).
+///
+/// # Safety
+/// db — хэндл из qdb_open_*; key_ptr валиден на key_len байт; path — C-строка;
+/// out-указатели валидны. Буфер освобождается qdb_buf_free.
+#[cfg(feature = "json")]
+#[no_mangle]
+pub unsafe extern "C" fn qdb_get_json_path(
+    db: *mut QdbDb,
+    key_ptr: *const c_uchar,
+    key_len: size_t,
+    path: *const c_char,
+    out_buf: *mut QdbBuf,
+    out_err: *mut *mut c_char,
+) -> c_int {
+    if db.is_null() {
+        set_err(out_err, "db is null");
+        return ret_err();
+    }
+    if out_buf.is_null() {
+        set_err(out_err, "out_buf is null");
+        return ret_err();
+    }
+    (*out_buf).ptr = ptr::null_mut();
+    (*out_buf).len = 0;
+
+    let key = match bytes_from(key_ptr, key_len) {
+        Ok(k) => k,
+        Err(e) => {
+            set_err(out_err, &e);
+            return ret_err();
+        }
+    };
+    if path.is_null() {
+        set_err(out_err, "path is null");
+        return ret_err();
+    }
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(p) => p,
+        Err(_) => {
+            set_err(out_err, "path is not valid UTF-8");
+            return ret_err();
+        }
+    };
+    let d = match (&*db).as_db() {
+        Some(d) => d,
+        None => {
+            set_err(out_err, "invalid db handle");
+            return ret_err();
+        }
+    };
+
+    match d.get_json_path(key, path) {
+        Ok(Some(v)) => {
+            let text = v.to_string();
+            let n = text.len();
+            let mem = libc::malloc(n);
+            if mem.is_null() {
+                set_err(out_err, "malloc failed");
+                return ret_err();
+            }
+            ptr::copy_nonoverlapping(text.as_ptr(), mem as *mut u8, n);
+            (*out_buf).ptr = mem as *mut u8;
+            (*out_buf).len = n as size_t;
+            ret_ok()
+        }
+        Ok(None) => ret_ok(),
+        Err(e) => {
+            set_err(out_err, &format!("{:#}", e));
+            ret_err()
+        }
+    }
+}
+
 // ---------- Free helpers for foreign code ----------
 
 #[no_mangle]
@@ -411,6 +681,17 @@ pub unsafe extern "C" fn qdb_buf_free(buf: QdbBuf) {
     }
 }
 
+/// Освободить значение qdb_get_view (NULL — no-op); указатель данных после вызова невалиден.
+///
+/// # Safety
+/// view — из qdb_get_view, освобождается ровно один раз.
+#[no_mangle]
+pub unsafe extern "C" fn qdb_view_release(view: *mut QdbView) {
+    if !view.is_null() {
+        drop(Box::from_raw(view));
+    }
+}
+
 // ---------- Misc ----------
 
 #[no_mangle]
@@ -10,6 +10,9 @@
 //! - Источник истины для количества — длина файла: (len - HDR) / 8.
 //! - Операции push/pop обновляют длину и fsync’ят файл (best-effort).
 //!
+//! - NEW: replace_all — атомарная перезапись всего списка (tmp + replace_file), используется
+//!   ремонтом аллокатора (db/alloc_check.rs).
+//!
 //! Примечание:
 //! - Это простой, однопоточный в терминах процесса API. Вызовы должны
 //!   выполняться под внешней синхронизацией на уровне Db/Pager.
@@ -20,6 +23,8 @@ use std::fs::OpenOptions;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
+use crate::util::fsx::{fsync_parent_dir, open_tmp_for_write, replace_file};
+
 const FREE_FILE: &str = "free";
 const FREE_MAGIC: &[u8; 8] = b"P2FREE01";
 const FREE_VER: u32 = 1;
@@ -141,6 +146,49 @@ impl FreeList {
         Ok(Some(page_id))
     }
 
+    /// NEW: все page_id списка (без изменения файла; диагностика, quiverdb du).
+    pub fn list(&self) -> Result<Vec<u64>> {
+        let buf = std::fs::read(&self.path)
+            .with_context(|| format!("read free {}", self.path.display()))?;
+        if (buf.len() as u64) < FREE_HDR_SIZE {
+            return Err(anyhow!(
+                "free file too small (< header): {}",
+                self.path.display()
+            ));
+        }
+        Ok(buf[FREE_HDR_SIZE as usize..]
+            .chunks_exact(8)
+            .map(LittleEndian::read_u64)
+            .collect())
+    }
+
+    /// NEW: атомарно заменить весь список (создаёт файл, если его нет).
+    pub fn replace_all(root: &Path, page_ids: &[u64]) -> Result<Self> {
+        let path = root.join(FREE_FILE);
+        let tmp = root.join(format!("{}.tmp", FREE_FILE));
+        let mut buf = Vec::with_capacity(FREE_HDR_SIZE as usize + page_ids.len() * 8);
+        buf.extend_from_slice(FREE_MAGIC);
+        let mut buf4 = [0u8; 4];
+        LittleEndian::write_u32(&mut buf4, FREE_VER);
+        buf.extend_from_slice(&buf4);
+        buf.extend_from_slice(&[0u8; 4]);
+        let mut buf8 = [0u8; 8];
+        for &pid in page_ids {
+            LittleEndian::write_u64(&mut buf8, pid);
+            buf.extend_from_slice(&buf8);
+        }
+        {
+            let mut f = open_tmp_for_write(&tmp)
+                .with_context(|| format!("open free tmp {}", tmp.display()))?;
+            f.write_all(&buf)?;
+            f.sync_all()?;
+        }
+        replace_file(&tmp, &path)
+            .with_context(|| format!("rename {} -> {}", tmp.display(), path.display()))?;
+        let _ = fsync_parent_dir(&path);
+        Ok(Self { path })
+    }
+
     /// Путь к free‑файлу (для диагностики).
     pub fn path(&self) -> &Path {
         &self.path
@@ -0,0 +1,528 @@
+//! kvfs/fuse — минимальный FUSE-сервер поверх KvView (протокол ядра через /dev/fuse, Linux).
+//!
+//! Без libfuse и fusermount: /dev/fuse открывается напрямую, ФС монтируется mount(2) как
+//! "fuse.quiverdb" с MS_RDONLY|MS_NOSUID|MS_NODEV и default_permissions — нужен CAP_SYS_ADMIN
+//! (root). Запросы обрабатываются по одному в потоке serve():
+//! - INIT, LOOKUP, GETATTR, OPEN/OPENDIR, READ, READDIR, RELEASE/RELEASEDIR, FLUSH, ACCESS,
+//!   STATFS, FORGET/BATCH_FORGET, INTERRUPT, DESTROY;
+//! - модификации (SETATTR, WRITE, CREATE, MKDIR, UNLINK, RENAME, SETXATTR, ...) — EROFS,
+//!   прочее — ENOSYS (ядро запоминает и больше такой запрос не шлёт).
+//!
+//! Снимок неизменен, поэтому ядру разрешено кэшировать атрибуты, записи каталогов и страницы
+//! файлов надолго (ATTR_TTL_SECS, FOPEN_KEEP_CACHE, FOPEN_CACHE_DIR).
+//!
+//! Завершение: `umount <mountpoint>`, kvfs::fuse::unmount или сигнал SIGINT/SIGTERM (после
+//! KvMount::unmount_on_signals) — ядро закрывает соединение, read(/dev/fuse) возвращает
+//! ENODEV и serve() выходит; pin снимка снимается при drop KvView.
+
+use anyhow::{anyhow, Context, Result};
+use std::ffi::CString;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use super::{KvAttr, KvNodeKind, KvView, KVFS_NAME_MAX};
+
+const FUSE_KERNEL_VERSION: u32 = 7;
+const FUSE_KERNEL_MINOR_VERSION: u32 = 31;
+/// До 7.23 fuse_init_out был короче (FUSE_COMPAT_22_INIT_OUT_SIZE).
+const INIT_OUT_COMPAT_22: usize = 24;
+
+const MAX_WRITE: u32 = 128 * 1024;
+/// Буфер запроса: max_write + заголовки (FUSE_BUFFER_HEADER_SIZE у libfuse).
+const REQ_BUF_SIZE: usize = MAX_WRITE as usize + 4096;
+const IN_HEADER_LEN: usize = 40;
+const OUT_HEADER_LEN: usize = 16;
+
+/// Сколько ядро кэширует атрибуты и записи каталогов.
+const ATTR_TTL_SECS: u64 = 3600;
+
+const FOPEN_KEEP_CACHE: u32 = 1 << 1;
+const FOPEN_CACHE_DIR: u32 = 1 << 3;
+
+const DT_DIR: u32 = 4;
+const DT_REG: u32 = 8;
+
+const FUSE_LOOKUP: u32 = 1;
+const FUSE_FORGET: u32 = 2;
+const FUSE_GETATTR: u32 = 3;
+const FUSE_SETATTR: u32 = 4;
+const FUSE_SYMLINK: u32 = 6;
+const FUSE_MKNOD: u32 = 8;
+const FUSE_MKDIR: u32 = 9;
+const FUSE_UNLINK: u32 = 10;
+const FUSE_RMDIR: u32 = 11;
+const FUSE_RENAME: u32 = 12;
+const FUSE_LINK: u32 = 13;
+const FUSE_OPEN: u32 = 14;
+const FUSE_READ: u32 = 15;
+const FUSE_WRITE: u32 = 16;
+const FUSE_STATFS: u32 = 17;
+const FUSE_RELEASE: u32 = 18;
+const FUSE_SETXATTR: u32 = 21;
+const FUSE_REMOVEXATTR: u32 = 24;
+const FUSE_FLUSH: u32 = 25;
+const FUSE_INIT: u32 = 26;
+const FUSE_OPENDIR: u32 = 27;
+const FUSE_READDIR: u32 = 28;
+const FUSE_RELEASEDIR: u32 = 29;
+const FUSE_ACCESS: u32 = 34;
+const FUSE_CREATE: u32 = 35;
+const FUSE_INTERRUPT: u32 = 36;
+const FUSE_DESTROY: u32 = 38;
+const FUSE_BATCH_FORGET: u32 = 42;
+const FUSE_FALLOCATE: u32 = 43;
+const FUSE_RENAME2: u32 = 45;
+const FUSE_COPY_FILE_RANGE: u32 = 47;
+
+/// Смонтированное представление (см. модульный комментарий).
+pub struct KvMount {
+    view: KvView,
+    dev: File,
+    mountpoint: PathBuf,
+    mounted: bool,
+    uid: u32,
+    gid: u32,
+}
+
+impl KvMount {
+    /// Смонтировать view в mountpoint (существующий каталог). Обслуживание — serve().
+    pub fn mount(view: KvView, mountpoint: &Path) -> Result<Self> {
+        if !mountpoint.is_dir() {
+            return Err(anyhow!(
+                "mountpoint {} is not a directory",
+                mountpoint.display()
+            ));
+        }
+        let dev = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/fuse")
+            .context("open /dev/fuse (is the fuse kernel module loaded?)")?;
+        // SAFETY: getuid/getgid не имеют предусловий.
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        let data = CString::new(format!(
+            "fd={},rootmode=40000,user_id={},group_id={},default_permissions",
+            dev.as_raw_fd(),
+            uid,
+            gid
+        ))?;
+        let source = CString::new("quiverdb")?;
+        let fstype = CString::new("fuse.quiverdb")?;
+        let target = CString::new(mountpoint.as_os_str().as_bytes())?;
+        // SAFETY: все строки NUL-терминированы и живут до конца вызова.
+        let rc = unsafe {
+            libc::mount(
+                source.as_ptr(),
+                target.as_ptr(),
+                fstype.as_ptr(),
+                libc::MS_RDONLY | libc::MS_NOSUID | libc::MS_NODEV,
+                data.as_ptr() as *const libc::c_void,
+            )
+        };
+        if rc != 0 {
+            let e = io::Error::last_os_error();
+            if e.raw_os_error() == Some(libc::EPERM) {
+                return Err(anyhow!(
+                    "mount {}: permission denied (mounting FUSE needs CAP_SYS_ADMIN; run as root)",
+                    mountpoint.display()
+                ));
+            }
+            return Err(e).with_context(|| format!("mount {}", mountpoint.display()));
+        }
+        Ok(Self {
+            view,
+            dev,
+            mountpoint: mountpoint.to_path_buf(),
+            mounted: true,
+            uid,
+            gid,
+        })
+    }
+
+    pub fn mountpoint(&self) -> &Path {
+        &self.mountpoint
+    }
+
+    pub fn view(&self) -> &KvView {
+        &self.view
+    }
+
+    /// Отмонтировать при SIGINT/SIGTERM (serve() затем выходит штатно).
+    pub fn unmount_on_signals(&self) -> Result<()> {
+        let path = CString::new(self.mountpoint.as_os_str().as_bytes())?;
+        let old = UNMOUNT_ON_SIGNAL.swap(path.into_raw(), Ordering::SeqCst);
+        if !old.is_null() {
+            // SAFETY: old получен из CString::into_raw в предыдущем вызове.
+            drop(unsafe { CString::from_raw(old) });
+        }
+        let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        // SAFETY: обработчик вызывает только umount2 (async-signal-safe syscall).
+        unsafe {
+            libc::signal(libc::SIGINT, handler);
+            libc::signal(libc::SIGTERM, handler);
+        }
+        Ok(())
+    }
+
+    /// Обслуживать запросы ядра до отмонтирования.
+    pub fn serve(mut self) -> Result<()> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let renewer = {
+            let (stop, pin) = (stop.clone(), self.view.pin());
+            std::thread::Builder::new()
+                .name("kvfs-pin".into())
+                .spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        if let Err(e) = pin.lock().unwrap_or_else(|e| e.into_inner()).renew() {
+                            eprintln!("[WARN] kvfs: renew snapshot pin: {:#}", e);
+                        }
+                        std::thread::sleep(Duration::from_millis(200));
+                    }
+                })?
+        };
+        let res = self.serve_loop();
+        stop.store(true, Ordering::Relaxed);
+        let _ = renewer.join();
+        res
+    }
+
+    fn serve_loop(&mut self) -> Result<()> {
+        let mut buf = vec![0u8; REQ_BUF_SIZE];
+        loop {
+            let n = match (&self.dev).read(&mut buf) {
+                Ok(n) => n,
+                Err(e) => match e.raw_os_error() {
+                    // ENOENT — запрос прерван до чтения
+                    Some(libc::EINTR) | Some(libc::EAGAIN) | Some(libc::ENOENT) => continue,
+                    Some(libc::ENODEV) => {
+                        self.mounted = false;
+                        return Ok(());
+                    }
+                    _ => return Err(e).context("read /dev/fuse"),
+                },
+            };
+            if n < IN_HEADER_LEN {
+                return Err(anyhow!("fuse: short request ({} bytes)", n));
+            }
+            if !self.dispatch(&buf[..n])? {
+                self.mounted = false;
+                return Ok(());
+            }
+        }
+    }
+
+    /// Один запрос; false — DESTROY (сессия закончена).
+    fn dispatch(&mut self, req: &[u8]) -> Result<bool> {
+        let opcode = u32_at(req, 4);
+        let unique = u64_at(req, 8);
+        let ino = u64_at(req, 16);
+        let body = &req[IN_HEADER_LEN..];
+        match opcode {
+            FUSE_INIT => self.on_init(unique, body)?,
+            FUSE_DESTROY => {
+                self.reply(unique, 0, &[])?;
+                return Ok(false);
+            }
+            // без ответа
+            FUSE_FORGET | FUSE_BATCH_FORGET | FUSE_INTERRUPT => {}
+            FUSE_LOOKUP => {
+                let name = body.split(|&b| b == 0).next().unwrap_or(&[]);
+                match self.view.lookup(ino, name) {
+                    Some(child) if name.len() <= KVFS_NAME_MAX => {
+                        self.reply_entry(unique, child)?
+                    }
+                    _ => self.reply(unique, libc::ENOENT, &[])?,
+                }
+            }
+            FUSE_GETATTR => match self.attr(ino) {
+                Ok(a) => {
+                    let mut out = Vec::with_capacity(104);
+                    put_u64(&mut out, ATTR_TTL_SECS);
+                    put_u32(&mut out, 0);
+                    put_u32(&mut out, 0);
+                    self.put_attr(&mut out, &a);
+                    self.reply(unique, 0, &out)?;
+                }
+                Err(errno) => self.reply(unique, errno, &[])?,
+            },
+            FUSE_OPEN | FUSE_OPENDIR => {
+                let want_dir = opcode == FUSE_OPENDIR;
+                let flags = if body.len() >= 4 { u32_at(body, 0) } else { 0 };
+                let errno = match self.view.attr(ino) {
+                    Ok(Some(a)) if (a.kind == KvNodeKind::Dir) != want_dir => {
+                        if want_dir {
+                            libc::ENOTDIR
+                        } else {
+                            libc::EISDIR
+                        }
+                    }
+                    Ok(Some(_)) if flags as i32 & libc::O_ACCMODE != libc::O_RDONLY => libc::EROFS,
+                    Ok(Some(_)) => 0,
+                    Ok(None) => libc::ENOENT,
+                    Err(e) => {
+                        eprintln!("[WARN] kvfs: open inode {}: {:#}", ino, e);
+                        libc::EIO
+                    }
+                };
+                if errno != 0 {
+                    self.reply(unique, errno, &[])?;
+                } else {
+                    let mut out = Vec::with_capacity(16);
+                    put_u64(&mut out, 0);
+                    put_u32(
+                        &mut out,
+                        if want_dir {
+                            FOPEN_CACHE_DIR
+                        } else {
+                            FOPEN_KEEP_CACHE
+                        },
+                    );
+                    put_u32(&mut out, 0);
+                    self.reply(unique, 0, &out)?;
+                }
+            }
+            FUSE_READ => {
+                let (off, size) = (u64_at(body, 8), u32_at(body, 16));
+                match self.view.read(ino, off, size.min(MAX_WRITE)) {
+                    Ok(data) => self.reply(unique, 0, &data)?,
+                    Err(e) => {
+                        eprintln!("[WARN] {:#}", e);
+                        self.reply(unique, libc::EIO, &[])?;
+                    }
+                }
+            }
+            FUSE_READDIR => {
+                let (off, size) = (u64_at(body, 8), u32_at(body, 16));
+                match self.readdir(ino, off, size as usize) {
+                    Some(out) => self.reply(unique, 0, &out)?,
+                    None => self.reply(unique, libc::ENOTDIR, &[])?,
+                }
+            }
+            FUSE_RELEASE | FUSE_RELEASEDIR | FUSE_FLUSH => self.reply(unique, 0, &[])?,
+            FUSE_ACCESS => {
+                let mask = if body.len() >= 4 { u32_at(body, 0) } else { 0 };
+                let errno = if mask as i32 & libc::W_OK != 0 {
+                    libc::EROFS
+                } else {
+                    0
+                };
+                self.reply(unique, errno, &[])?;
+            }
+            FUSE_STATFS => {
+                let st = self.view.stats();
+                let mut out = Vec::with_capacity(80);
+                put_u64(&mut out, 0); // blocks
+                put_u64(&mut out, 0); // bfree
+                put_u64(&mut out, 0); // bavail
+                put_u64(&mut out, st.files + st.dirs);
+                put_u64(&mut out, 0); // ffree
+                put_u32(&mut out, 4096); // bsize
+                put_u32(&mut out, KVFS_NAME_MAX as u32);
+                put_u32(&mut out, 4096); // frsize
+                out.resize(80, 0);
+                self.reply(unique, 0, &out)?;
+            }
+            FUSE_SETATTR | FUSE_SYMLINK | FUSE_MKNOD | FUSE_MKDIR | FUSE_UNLINK | FUSE_RMDIR
+            | FUSE_RENAME | FUSE_LINK | FUSE_WRITE | FUSE_SETXATTR | FUSE_REMOVEXATTR
+            | FUSE_CREATE | FUSE_FALLOCATE | FUSE_RENAME2 | FUSE_COPY_FILE_RANGE => {
+                self.reply(unique, libc::EROFS, &[])?
+            }
+            _ => self.reply(unique, libc::ENOSYS, &[])?,
+        }
+        Ok(true)
+    }
+
+    fn on_init(&mut self, unique: u64, body: &[u8]) -> Result<()> {
+        if body.len() < 16 {
+            return self.reply(unique, libc::EPROTO, &[]);
+        }
+        let (major, minor, max_readahead) = (u32_at(body, 0), u32_at(body, 4), u32_at(body, 8));
+        if major < FUSE_KERNEL_VERSION {
+            return self.reply(unique, libc::EPROTO, &[]);
+        }
+        let mut out = Vec::with_capacity(64);
+        put_u32(&mut out, FUSE_KERNEL_VERSION);
+        put_u32(&mut out, FUSE_KERNEL_MINOR_VERSION);
+        put_u32(&mut out, max_readahead);
+        put_u32(&mut out, 0); // flags: без async read/writeback — запросы по одному
+        put_u16(&mut out, 16); // max_background
+        put_u16(&mut out, 12); // congestion_threshold
+        put_u32(&mut out, MAX_WRITE);
+        put_u32(&mut out, 1); // time_gran
+        out.resize(64, 0);
+        if major == FUSE_KERNEL_VERSION && minor < 23 {
+            out.truncate(INIT_OUT_COMPAT_22);
+        }
+        self.reply(unique, 0, &out)
+    }
+
+    fn attr(&self, ino: u64) -> std::result::Result<KvAttr, i32> {
+        match self.view.attr(ino) {
+            Ok(Some(a)) => Ok(a),
+            Ok(None) => Err(libc::ENOENT),
+            Err(e) => {
+                eprintln!("[WARN] {:#}", e);
+                Err(libc::EIO)
+            }
+        }
+    }
+
+    fn reply_entry(&mut self, unique: u64, ino: u64) -> Result<()> {
+        let a = match self.attr(ino) {
+            Ok(a) => a,
+            Err(errno) => return self.reply(unique, errno, &[]),
+        };
+        let mut out = Vec::with_capacity(128);
+        put_u64(&mut out, ino);
+        put_u64(&mut out, 0); // generation: inode не переиспользуются
+        put_u64(&mut out, ATTR_TTL_SECS); // entry_valid
+        put_u64(&mut out, ATTR_TTL_SECS); // attr_valid
+        put_u32(&mut out, 0);
+        put_u32(&mut out, 0);
+        self.put_attr(&mut out, &a);
+        self.reply(unique, 0, &out)
+    }
+
+    /// fuse_attr (88 байт).
+    fn put_attr(&self, out: &mut Vec<u8>, a: &KvAttr) {
+        let t = self
+            .view
+            .created()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mode = match a.kind {
+            KvNodeKind::Dir => libc::S_IFDIR | 0o555,
+            KvNodeKind::File => libc::S_IFREG | 0o444,
+        };
+        put_u64(out, a.ino);
+        put_u64(out, a.size);
+        put_u64(out, a.size.div_ceil(512)); // blocks
+        for _ in 0..3 {
+            put_u64(out, t.as_secs()); // atime, mtime, ctime
+        }
+        for _ in 0..3 {
+            put_u32(out, t.subsec_nanos());
+        }
+        put_u32(out, mode);
+        put_u32(out, a.nlink);
+        put_u32(out, self.uid);
+        put_u32(out, self.gid);
+        put_u32(out, 0); // rdev
+        put_u32(out, 4096); // blksize
+        put_u32(out, 0); // flags
+    }
+
+    /// fuse_dirent'ы с позиции off ("." и ".." — позиции 0 и 1), не больше size байт.
+    fn readdir(&self, ino: u64, off: u64, size: usize) -> Option<Vec<u8>> {
+        let children = self.view.readdir(ino)?;
+        let parent = self.view.lookup(ino, b"..").unwrap_or(ino);
+        let dots = [
+            (ino, DT_DIR, b".".as_slice()),
+            (parent, DT_DIR, b"..".as_slice()),
+        ];
+        let entries = dots.into_iter().chain(children.iter().map(|e| {
+            let t = match e.kind {
+                KvNodeKind::Dir => DT_DIR,
+                KvNodeKind::File => DT_REG,
+            };
+            (e.ino, t, e.name.as_slice())
+        }));
+        let mut out = Vec::new();
+        for (i, (child, kind, name)) in entries.enumerate().skip(off as usize) {
+            let rec = (24 + name.len()).next_multiple_of(8);
+            if out.len() + rec > size {
+                break;
+            }
+            put_u64(&mut out, child);
+            put_u64(&mut out, i as u64 + 1); // off следующей записи
+            put_u32(&mut out, name.len() as u32);
+            put_u32(&mut out, kind);
+            out.extend_from_slice(name);
+            out.resize(out.len().next_multiple_of(8), 0);
+        }
+        Some(out)
+    }
+
+    fn reply(&mut self, unique: u64, errno: i32, payload: &[u8]) -> Result<()> {
+        let mut msg = Vec::with_capacity(OUT_HEADER_LEN + payload.len());
+        put_u32(&mut msg, (OUT_HEADER_LEN + payload.len()) as u32);
+        put_u32(&mut msg, (-errno) as u32);
+        put_u64(&mut msg, unique);
+        msg.extend_from_slice(payload);
+        match (&self.dev).write_all(&msg) {
+            Ok(()) => Ok(()),
+            // запрос прерван (ENOENT) или соединение уже закрыто (ENODEV)
+            Err(e) if matches!(e.raw_os_error(), Some(libc::ENOENT) | Some(libc::ENODEV)) => Ok(()),
+            Err(e) => Err(e).context("write /dev/fuse"),
+        }
+    }
+}
+
+impl Drop for KvMount {
+    fn drop(&mut self) {
+        if self.mounted {
+            let _ = unmount(&self.mountpoint);
+        }
+    }
+}
+
+/// Отмонтировать (lazy, MNT_DETACH): serve() смонтированной сессии выходит.
+pub fn unmount(mountpoint: &Path) -> Result<()> {
+    let target = CString::new(mountpoint.as_os_str().as_bytes())?;
+    // SAFETY: target NUL-терминирована и живёт до конца вызова.
+    let rc = unsafe { libc::umount2(target.as_ptr(), libc::MNT_DETACH) };
+    if rc != 0 {
+        return Err(io::Error::last_os_error())
+            .with_context(|| format!("umount {}", mountpoint.display()));
+    }
+    Ok(())
+}
+
+/// Путь для обработчика сигналов (CString::into_raw).
+static UNMOUNT_ON_SIGNAL: AtomicPtr<libc::c_char> = AtomicPtr::new(std::ptr::null_mut());
+
+extern "C" fn on_signal(_sig: libc::c_int) {
+    let p = UNMOUNT_ON_SIGNAL.load(Ordering::SeqCst);
+    if !p.is_null() {
+        // SAFETY: p — живая NUL-терминированная строка (не освобождается, пока установлена).
+        unsafe {
+            libc::umount2(p, libc::MNT_DETACH);
+        }
+    }
+}
+
+#[inline]
+fn u32_at(b: &[u8], off: usize) -> u32 {
+    b.get(off..off + 4)
+        .map_or(0, |s| u32::from_ne_bytes([s[0], s[1], s[2], s[3]]))
+}
+
+#[inline]
+fn u64_at(b: &[u8], off: usize) -> u64 {
+    b.get(off..off + 8).map_or(0, |s| {
+        let mut a = [0u8; 8];
+        a.copy_from_slice(s);
+        u64::from_ne_bytes(a)
+    })
+}
+
+#[inline]
+fn put_u16(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&v.to_ne_bytes());
+}
+
+#[inline]
+fn put_u32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_ne_bytes());
+}
+
+#[inline]
+fn put_u64(out: &mut Vec<u8>, v: u64) {
+    out.extend_from_slice(&v.to_ne_bytes());
+}
@@ -0,0 +1,463 @@
+//! kvfs — read-only файловое представление ключей (feature "fuse", CLI `quiverdb mount`).
+//!
+//! Ключи показываются файлами в дереве каталогов, полученном разбиением ключа по разделителю
+//! (по умолчанию '/'): ключ `users/42/profile` — файл `profile` в каталоге `users/42`,
+//! содержимое файла — значение. Удобно для отладки и для инструментов, которые умеют только ФС
+//! (grep -r, diff, rsync, редакторы).
+//!
+//! Согласованность: KvView — снимок на момент создания. Головы всех бакетов закрепляются
+//! (Directory::heads_snapshot) и регистрируются в реестре pin'ов снапшотов (snapstore/pins):
+//! vacuum writer'а (в том числе в другом процессе) не освобождает страницы их цепочек, пока
+//! представление живо. Дерево строится keys-only обходом цепочек от этих голов; значение
+//! читается лениво от страницы решающей записи (Db::get_from_page). Поэтому файлы показывают
+//! состояние на момент монтирования, сколько бы writer ни писал после. Исключение — TTL:
+//! запись, истёкшая после монтирования, становится нечитаемой (ENOENT).
+//!
+//! Хэндл БД — RO без shared LOCK (как digest у cdc-serve): долгоживущий mount не блокирует
+//! writer'а; безопасность страниц обеспечивает pin. Аренду pin'а продлевает фоновый поток
+//! сессии (kvfs::fuse), либо вызывающий код через KvView::renew_pin.
+//!
+//! Имена (escape_component):
+//! - печатный ASCII (кроме '%' и '/') и не управляющие символы валидного UTF-8 — как есть;
+//! - прочие байты — %XX; пустой компонент — "%", "." и ".." — "%2E" и "%2E%2E";
+//! - ключ, совпадающий с каталогом другого ключа (`a` и `a/b`), — файл с суффиксом '%' (`a%`);
+//!   экранированные имена не содержат '%' без двух hex-цифр, так что суффикс однозначен;
+//! - ключи с компонентом длиннее KVFS_NAME_MAX байт (после экранирования) пропускаются
+//!   (ФС их всё равно не адресует) и считаются в KvViewStats::skipped.
+//!
+//! Дерево держится в памяти (ключи всего снимка или префикса), значения — нет: кэшируются
+//! только размеры файлов и последнее прочитанное значение (чтение большого файла кусками).
+//! Протокол ядра (/dev/fuse, Linux) — подмодуль fuse.
+
+use anyhow::{anyhow, Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::db::Db;
+use crate::snapstore::SnapshotPinGuard;
+
+#[cfg(target_os = "linux")]
+pub mod fuse;
+
+/// inode корня (FUSE_ROOT_ID).
+pub const KVFS_ROOT_INO: u64 = 1;
+
+/// Максимальная длина имени файла/каталога в байтах (NAME_MAX Linux).
+pub const KVFS_NAME_MAX: usize = 255;
+
+/// Параметры представления.
+#[derive(Debug, Clone)]
+pub struct KvFsOptions {
+    /// Разделитель компонентов пути в ключе.
+    pub delimiter: u8,
+    /// Показывать только ключи с этим префиксом (префикс срезается с путей).
+    pub prefix: Option<Vec<u8>>,
+}
+
+impl Default for KvFsOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b'/',
+            prefix: None,
+        }
+    }
+}
+
+/// Тип узла.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KvNodeKind {
+    Dir,
+    File,
+}
+
+/// Атрибуты узла (то, что отдаётся в getattr/lookup).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KvAttr {
+    pub ino: u64,
+    pub kind: KvNodeKind,
+    /// Размер значения (для каталога — 0).
+    pub size: u64,
+    /// Число подкаталогов + 2 для каталога, 1 для файла.
+    pub nlink: u32,
+}
+
+/// Элемент листинга каталога.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KvDirEntry {
+    pub ino: u64,
+    pub kind: KvNodeKind,
+    pub name: Vec<u8>,
+}
+
+/// Сводка снимка.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KvViewStats {
+    /// LSN хэндла на момент снятия голов.
+    pub lsn: u64,
+    pub files: u64,
+    pub dirs: u64,
+    /// Ключи, не представимые именами ФС (компонент длиннее KVFS_NAME_MAX).
+    pub skipped: u64,
+}
+
+#[derive(Debug)]
+enum NodeData {
+    Dir {
+        children: BTreeMap<Vec<u8>, u64>,
+        subdirs: u32,
+    },
+    File {
+        key: Vec<u8>,
+        /// Страница решающей записи в закреплённой цепочке.
+        pid: u64,
+    },
+}
+
+#[derive(Debug)]
+struct Node {
+    parent: u64,
+    data: NodeData,
+}
+
+/// Снимок ключей БД в виде дерева (см. модульный комментарий).
+pub struct KvView {
+    db: Db,
+    /// nodes[ino - 1]
+    nodes: Vec<Node>,
+    stats: KvViewStats,
+    created: SystemTime,
+    pin: Arc<Mutex<SnapshotPinGuard>>,
+    sizes: Mutex<HashMap<u64, u64>>,
+    last_value: Mutex<Option<(u64, Arc<Vec<u8>>)>>,
+}
+
+impl KvView {
+    /// Открыть БД read-only (без LOCK) и снять снимок ключей.
+    pub fn open(root: &Path, opts: &KvFsOptions) -> Result<Self> {
+        let db = Db::open_ro_unlocked(root)
+            .with_context(|| format!("open {} read-only", root.display()))?;
+        Self::from_db(db, opts)
+    }
+
+    /// Снять снимок ключей открытого хэндла (обычно RO).
+    pub fn from_db(db: Db, opts: &KvFsOptions) -> Result<Self> {
+        let id = format!(
+            "kvfs-{}-{}",
+            std::process::id(),
+            SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or(0)
+        );
+        // pin в ожидании до снятия голов — как у SnapshotManager::create_persisted
+        let mut pin = SnapshotPinGuard::acquire(&db.root, &id).context("register kvfs pin")?;
+        let heads = db.dir.heads_snapshot()?;
+        let lsn = db.pager.meta.last_lsn;
+        pin.set_heads(lsn, heads.clone())?;
+
+        let prefix = match opts.prefix.as_deref() {
+            Some(p) => Some(db.canonical_prefix(p)?.into_owned()),
+            None => None,
+        };
+
+        let mut view = Self {
+            db,
+            nodes: vec![Node {
+                parent: KVFS_ROOT_INO,
+                data: NodeData::Dir {
+                    children: BTreeMap::new(),
+                    subdirs: 0,
+                },
+            }],
+            stats: KvViewStats {
+                lsn,
+                dirs: 1,
+                ..Default::default()
+            },
+            created: SystemTime::now(),
+            pin: Arc::new(Mutex::new(pin)),
+            sizes: Mutex::new(HashMap::new()),
+            last_value: Mutex::new(None),
+        };
+
+        let now = crate::util::now_secs();
+        let mut page = vec![0u8; view.db.pager.meta.page_size as usize];
+        let mut found: Vec<(Vec<u8>, u64)> = Vec::new();
+        for (b, &head) in heads.iter().enumerate() {
+            view.db
+                .for_each_live_key_in_bucket(b as u32, head, now, &mut page, |k, pid| {
+                    if prefix.as_deref().is_none_or(|p| k.starts_with(p)) {
+                        found.push((k.to_vec(), pid));
+                    }
+                })?;
+        }
+        found.sort();
+        let cut = prefix.as_ref().map_or(0, |p| p.len());
+        for (key, pid) in found {
+            view.insert_key(&key[cut..], &key, pid, opts.delimiter);
+        }
+        Ok(view)
+    }
+
+    pub fn stats(&self) -> KvViewStats {
+        self.stats
+    }
+
+    /// Время создания снимка (mtime/ctime всех узлов).
+    pub fn created(&self) -> SystemTime {
+        self.created
+    }
+
+    /// Pin снимка (для продления аренды из другого потока).
+    pub fn pin(&self) -> Arc<Mutex<SnapshotPinGuard>> {
+        self.pin.clone()
+    }
+
+    /// Продлить аренду pin'а (дёшево: реально пишет раз в треть аренды).
+    pub fn renew_pin(&self) -> Result<()> {
+        self.pin.lock().unwrap_or_else(|e| e.into_inner()).renew()
+    }
+
+    fn insert_key(&mut self, rel: &[u8], key: &[u8], pid: u64, delim: u8) {
+        let parts: Vec<Vec<u8>> = rel.split(|&c| c == delim).map(escape_component).collect();
+        // +1 — место под суффикс '%' при совпадении с каталогом
+        if parts.iter().any(|p| p.len() + 1 > KVFS_NAME_MAX) {
+            self.stats.skipped += 1;
+            return;
+        }
+        let (file, dirs) = parts.split_last().expect("split yields at least one part");
+        let mut cur = KVFS_ROOT_INO;
+        for d in dirs {
+            cur = self.child_dir(cur, d);
+        }
+        let ino = self.push(
+            cur,
+            NodeData::File {
+                key: key.to_vec(),
+                pid,
+            },
+        );
+        let mut name = file.clone();
+        if let Some(existing) = self.child(cur, &name) {
+            if self.kind_of(existing) == Some(KvNodeKind::Dir) {
+                name.push(b'%');
+            }
+        }
+        self.children_mut(cur).insert(name, ino);
+        self.stats.files += 1;
+    }
+
+    /// Подкаталог name в parent (создаётся; одноимённый файл получает суффикс '%').
+    fn child_dir(&mut self, parent: u64, name: &[u8]) -> u64 {
+        if let Some(ino) = self.child(parent, name) {
+            if self.kind_of(ino) == Some(KvNodeKind::Dir) {
+                return ino;
+            }
+            let children = self.children_mut(parent);
+            children.remove(name);
+            let mut renamed = name.to_vec();
+            renamed.push(b'%');
+            children.insert(renamed, ino);
+        }
+        let ino = self.push(
+            parent,
+            NodeData::Dir {
+                children: BTreeMap::new(),
+                subdirs: 0,
+            },
+        );
+        self.children_mut(parent).insert(name.to_vec(), ino);
+        if let NodeData::Dir { subdirs, .. } = &mut self.nodes[(parent - 1) as usize].data {
+            *subdirs += 1;
+        }
+        self.stats.dirs += 1;
+        ino
+    }
+
+    fn push(&mut self, parent: u64, data: NodeData) -> u64 {
+        self.nodes.push(Node { parent, data });
+        self.nodes.len() as u64
+    }
+
+    fn node(&self, ino: u64) -> Option<&Node> {
+        self.nodes.get(ino.checked_sub(1)? as usize)
+    }
+
+    fn children_mut(&mut self, dir: u64) -> &mut BTreeMap<Vec<u8>, u64> {
+        match &mut self.nodes[(dir - 1) as usize].data {
+            NodeData::Dir { children, .. } => children,
+            NodeData::File { .. } => unreachable!("kvfs: inode {} is not a directory", dir),
+        }
+    }
+
+    fn kind_of(&self, ino: u64) -> Option<KvNodeKind> {
+        self.node(ino).map(|n| match n.data {
+            NodeData::Dir { .. } => KvNodeKind::Dir,
+            NodeData::File { .. } => KvNodeKind::File,
+        })
+    }
+
+    fn child(&self, parent: u64, name: &[u8]) -> Option<u64> {
+        match &self.node(parent)?.data {
+            NodeData::Dir { children, .. } => children.get(name).copied(),
+            NodeData::File { .. } => None,
+        }
+    }
+
+    /// Найти узел по имени в каталоге parent ("." и ".." поддерживаются).
+    pub fn lookup(&self, parent: u64, name: &[u8]) -> Option<u64> {
+        match name {
+            b"." => self
+                .kind_of(parent)
+                .filter(|k| *k == KvNodeKind::Dir)
+                .map(|_| parent),
+            b".." => self.node(parent).map(|n| n.parent),
+            _ => self.child(parent, name),
+        }
+    }
+
+    /// Узел по относительному пути внутри представления ("" — корень).
+    pub fn resolve(&self, path: &str) -> Option<u64> {
+        path.split('/')
+            .filter(|c| !c.is_empty())
+            .try_fold(KVFS_ROOT_INO, |cur, c| self.lookup(cur, c.as_bytes()))
+    }
+
+    /// Ключ (канонический), которому соответствует файл.
+    pub fn key_of(&self, ino: u64) -> Option<&[u8]> {
+        match &self.node(ino)?.data {
+            NodeData::File { key, .. } => Some(key),
+            NodeData::Dir { .. } => None,
+        }
+    }
+
+    /// Атрибуты узла; размер файла — длина значения (первое обращение читает значение).
+    pub fn attr(&self, ino: u64) -> Result<Option<KvAttr>> {
+        let Some(node) = self.node(ino) else {
+            return Ok(None);
+        };
+        Ok(Some(match &node.data {
+            NodeData::Dir { subdirs, .. } => KvAttr {
+                ino,
+                kind: KvNodeKind::Dir,
+                size: 0,
+                nlink: 2 + subdirs,
+            },
+            NodeData::File { .. } => {
+                let cached = self.lock_sizes().get(&ino).copied();
+                let size = match cached {
+                    Some(s) => s,
+                    None => match self.value(ino)? {
+                        Some(v) => v.len() as u64,
+                        None => return Ok(None),
+                    },
+                };
+                KvAttr {
+                    ino,
+                    kind: KvNodeKind::File,
+                    size,
+                    nlink: 1,
+                }
+            }
+        }))
+    }
+
+    /// Листинг каталога (без "." и ".."); None — не каталог.
+    pub fn readdir(&self, ino: u64) -> Option<Vec<KvDirEntry>> {
+        match &self.node(ino)?.data {
+            NodeData::Dir { children, .. } => Some(
+                children
+                    .iter()
+                    .map(|(name, &child)| KvDirEntry {
+                        ino: child,
+                        kind: self.kind_of(child).unwrap_or(KvNodeKind::File),
+                        name: name.clone(),
+                    })
+                    .collect(),
+            ),
+            NodeData::File { .. } => None,
+        }
+    }
+
+    /// Значение файла на момент снимка; None — не файл или запись истекла по TTL.
+    pub fn value(&self, ino: u64) -> Result<Option<Arc<Vec<u8>>>> {
+        let Some(NodeData::File { key, pid }) = self.node(ino).map(|n| &n.data) else {
+            return Ok(None);
+        };
+        {
+            let last = self.last_value.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some((i, v)) = last.as_ref() {
+                if *i == ino {
+                    return Ok(Some(v.clone()));
+                }
+            }
+        }
+        let Some(v) = self
+            .db
+            .get_from_page(key, *pid)
+            .with_context(|| format!("kvfs: read value of inode {}", ino))?
+        else {
+            return Ok(None);
+        };
+        let v = Arc::new(v);
+        self.lock_sizes().insert(ino, v.len() as u64);
+        *self.last_value.lock().unwrap_or_else(|e| e.into_inner()) = Some((ino, v.clone()));
+        Ok(Some(v))
+    }
+
+    /// Кусок значения [off, off+size) (короче у конца файла).
+    pub fn read(&self, ino: u64, off: u64, size: u32) -> Result<Vec<u8>> {
+        let v = self
+            .value(ino)?
+            .ok_or_else(|| anyhow!("kvfs: inode {} is not a readable file", ino))?;
+        let start = (off.min(v.len() as u64)) as usize;
+        let end = start.saturating_add(size as usize).min(v.len());
+        Ok(v[start..end].to_vec())
+    }
+
+    fn lock_sizes(&self) -> std::sync::MutexGuard<'_, HashMap<u64, u64>> {
+        self.sizes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Имя компонента ключа в ФС (см. модульный комментарий).
+pub fn escape_component(c: &[u8]) -> Vec<u8> {
+    match c {
+        b"" => return b"%".to_vec(),
+        b"." => return b"%2E".to_vec(),
+        b".." => return b"%2E%2E".to_vec(),
+        _ => {}
+    }
+    fn push_escaped(out: &mut Vec<u8>, bytes: &[u8]) {
+        for b in bytes {
+            out.extend_from_slice(format!("%{:02X}", b).as_bytes());
+        }
+    }
+    let mut out = Vec::with_capacity(c.len());
+    let plain = |b: u8| (0x21..=0x7e).contains(&b) && b != b'%' && b != b'/';
+    match std::str::from_utf8(c) {
+        Ok(s) => {
+            let mut buf = [0u8; 4];
+            for ch in s.chars() {
+                let bytes = ch.encode_utf8(&mut buf).as_bytes();
+                if (ch.is_ascii() && plain(ch as u8)) || (!ch.is_ascii() && !ch.is_control()) {
+                    out.extend_from_slice(bytes);
+                } else {
+                    push_escaped(&mut out, bytes);
+                }
+            }
+        }
+        Err(_) => {
+            for &b in c {
+                if plain(b) {
+                    out.push(b);
+                } else {
+                    push_escaped(&mut out, &[b]);
+                }
+            }
+        }
+    }
+    out
+}
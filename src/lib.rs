@@ -25,10 +25,41 @@ pub mod util; // src/util/mod.rs
 // NEW: SnapStore v2 (контент-адресное хранилище с refcount)
 pub mod snapstore; // src/snapstore/mod.rs
 
+// NEW: потоковый бэкап/restore (один архив в impl Write/Read — stdout, S3, ssh)
+pub mod backup; // src/backup.rs
+
+// NEW: планировщик offsite-бэкапов (каталог/S3 через CLI, проверка SHA-256, lifecycle, restore-test)
+pub mod backup_sched; // src/backup_sched.rs
+
+// NEW: выгрузка живых ключей в порядке ключа (внешняя сортировка со spill во временные файлы)
+pub mod export; // src/export.rs
+
+// NEW: фреймворк миграций формата (реестр шагов, dry-run, прогресс, возобновление)
+pub mod migrate; // src/migrate.rs
+
+// NEW: процессный бюджет ресурсов (память кэшей/буферов, дескрипторы) с деградацией подсистем
+pub mod budget; // src/budget.rs
+
 // NEW: FFI (C ABI) — включается фичей "ffi"
 #[cfg(feature = "ffi")]
 pub mod ffi;
 
+// NEW: встроенный read-only admin UI (HTTP) — включается фичей "admin-ui"
+#[cfg(feature = "admin-ui")]
+pub mod admin_ui;
+
+// NEW: типизированная обёртка TypedDb<K, V> (serde) — включается фичей "serde"
+#[cfg(feature = "serde")]
+pub mod typed;
+
+// NEW: in-process поток CDC-событий (cdc::subscribe) — включается фичей "async"
+#[cfg(feature = "async")]
+pub mod cdc;
+
+// NEW: read-only FUSE-представление ключей (quiverdb mount) — включается фичей "fuse"
+#[cfg(feature = "fuse")]
+pub mod kvfs;
+
 // Удобные реэкспорты
 pub use db::Db;
 pub use dir::Directory;
@@ -38,4 +69,7 @@ pub use meta::{
 };
 
 // Реэкспорты crypto API (для удобства использования из внешнего кода)
-pub use crypto::{derive_gcm_nonce, EnvKeyProvider, KeyMaterial, KeyProvider, StaticKeyProvider};
+pub use crypto::{
+    derive_gcm_nonce, CompositeKeyProvider, EnvKeyProvider, KeyMaterial, KeyProvider,
+    StaticKeyProvider,
+};
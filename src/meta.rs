@@ -6,11 +6,29 @@
 //! u32 page_size       (4 KiB..=1 MiB, power of two)
 //! u32 format_flags    (в этом файле сохраняется/читается в поле `flags` для совместимости API)
 //! u64 next_page_id
-//! u32 hash_kind       (1 = xxhash64(seed=0))
+//! u32 hash_kind       (1 = xxhash64(seed=0); NEW: 2 = xxhash64 от префикса ключа, dir/routing.rs)
 //! u64 last_lsn
 //! u8  clean_shutdown  (1=clean, 0=unclean)
 //! u16 codec_default   (0=none,1=zstd,2=lz4)
 //! u8  checksum_kind   (забронировано; принудительно CRC32C)
+//! NEW: расширение (дописывается в конец; старые сборки читают только префикс и его не видят):
+//! [u8;16] db_uuid            (случайный идентификатор БД; нули — не назначен)
+//! u64 tde_aad_since_lsn      (с какого LSN AEAD-трейлеры используют AAD v2, см. FEATURE_TDE_AAD_V2)
+//! NEW: провенанс (там же, после tde_aad_since_lsn):
+//! u64 created_unix_ms        (когда создана БД; 0 — неизвестно)
+//! u8  created_by_len + bytes (версия QuiverDB, создавшей БД, до 32 байт)
+//! NEW: трансформация ключей (после created_by, db/key_transform.rs):
+//! u8  key_transform_len + bytes (идентификатор KeyTransform, до 64 байт; 0 — нет)
+//! NEW: метаданные приложения (после key_transform, db/user_meta.rs):
+//! u64 user_meta_head         (голова цепочки области метаданных; u64::MAX — пусто)
+//! NEW: проверочный блок ключа TDE (после user_meta_head, crypto/key_check.rs):
+//! u8  tde_check_kid_len + bytes (KID, которым запечатан блок)
+//! u8  tde_check_len + bytes     (nonce || AES-GCM(const) || tag; 0 — блока нет)
+//! NEW: трансформация значений (после tde_check, db/value_transform.rs):
+//! u8  value_transform_len + bytes (идентификатор ValueTransform, до 64 байт; 0 — нет)
+//! Meta без расширения читается с нулями в этих полях (user_meta_head — с u64::MAX).
+//! db_uuid назначается при init; writer назначает его meta старого формата при open
+//! (ensure_db_uuid), провенанс таким БД остаётся неизвестным.
 //!
 //! Политика:
 //! - Атомарная запись: tmp+rename, затем fsync родительского каталога (best‑effort на Windows).
@@ -20,11 +38,18 @@
 //! - checksum_kind теперь принудительно нормализуется к CRC32C при чтении/записи.
 //! - Если на диске значение отличалось от CRC32C — выводим предупреждение единожды и используем CRC32C.
 //! - На следующих шагах поле будет оставлено как “reserved” в документации, а параметр из API будет убран.
-
+//!
+//! NEW: feature flags в format_flags (формат meta не меняется):
+//! - биты 0..15  — required: без их поддержки БД читать нельзя (TDE, zstd OVERFLOW, KV packing).
+//!   Pager::open (а значит open/open_ro/CLI) падает сразу с перечнем неизвестных required-битов.
+//! - биты 16..31 — optional: сборка без поддержки может их игнорировать (кадры EXPIRY, heads.gen).
+//! - Writer при открытии проставляет биты используемых возможностей (add_features);
+//!   `quiverdb status` печатает включённые features.
+
+use crate::util::fsx::{fsync_parent_dir, open_tmp_for_write, replace_file};
 use anyhow::{anyhow, Context, Result};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-#[cfg(unix)]
-use std::fs::File;
+use serde::{Deserialize, Serialize};
 use std::fs::{self, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
@@ -36,6 +61,8 @@ const META_MAGIC: &[u8; 8] = b"P2DBMETA";
 const META_FILE: &str = "meta";
 
 pub const HASH_KIND_XX64_SEED0: u32 = 1;
+/// NEW: xxhash64(seed=0) от префикса ключа; параметры — в старших битах (dir/routing.rs).
+pub const HASH_KIND_XX64_PREFIX: u32 = 2;
 
 pub const CODEC_NONE: u16 = 0;
 pub const CODEC_ZSTD: u16 = 1;
@@ -48,13 +75,130 @@ pub const CKSUM_BLAKE3_128: u8 = 2;
 
 static WARNED_NON_CRC32C: OnceLock<()> = OnceLock::new();
 
+// ---- Feature flags (format_flags) ----
+
+/// Required: страницы с AEAD-трейлером (TDE).
+pub const FEATURE_TDE: u32 = 1 << 0;
+/// Required: OVERFLOW-цепочки, сжатые zstd (codec_default=zstd).
+pub const FEATURE_OVF_ZSTD: u32 = 1 << 1;
+/// Required: несколько записей на KV-странице (packing).
+pub const FEATURE_KV_PACKING: u32 = 1 << 2;
+/// Required: страничный каталог P2DIR03 (dir/paged.rs).
+pub const FEATURE_PAGED_DIR: u32 = 1 << 3;
+/// Required: AEAD-трейлеры страниц с LSN >= meta.tde_aad_since_lsn используют AAD v2
+/// (page_id, тип страницы, db_uuid) — см. page/checksum.rs.
+pub const FEATURE_TDE_AAD_V2: u32 = 1 << 4;
+/// Required: бакет считается от префикса ключа (HASH_KIND_XX64_PREFIX, dir/routing.rs).
+pub const FEATURE_BUCKET_ROUTING: u32 = 1 << 5;
+/// Required: ключи хранятся в канонической форме KeyTransform (meta.key_transform).
+pub const FEATURE_KEY_TRANSFORM: u32 = 1 << 6;
+/// Required: значения хранятся закодированными ValueTransform (meta.value_transform).
+pub const FEATURE_VALUE_TRANSFORM: u32 = 1 << 7;
+/// Required: WAL может содержать HEADS_UPDATE в delta-формате (wal/heads.rs); opt-in через
+/// QuiverConfig::wal_heads_delta.
+pub const FEATURE_HEADS_DELTA: u32 = 1 << 8;
+/// Optional: логические кадры EXPIRY в WAL.
+pub const FEATURE_WAL_EXPIRY: u32 = 1 << 16;
+/// Optional: генерация голов каталога (heads.gen) для RO-кешей.
+pub const FEATURE_HEADS_GEN: u32 = 1 << 17;
+/// Optional: область метаданных приложения (meta.user_meta_head, db/user_meta.rs).
+pub const FEATURE_USER_META: u32 = 1 << 18;
+
+/// Маска required-битов (низкие 16).
+pub const FEATURES_REQUIRED_MASK: u32 = 0x0000_FFFF;
+/// Required-биты, которые понимает эта сборка.
+pub const FEATURES_KNOWN_REQUIRED: u32 = FEATURE_TDE
+    | FEATURE_OVF_ZSTD
+    | FEATURE_KV_PACKING
+    | FEATURE_PAGED_DIR
+    | FEATURE_TDE_AAD_V2
+    | FEATURE_BUCKET_ROUTING
+    | FEATURE_KEY_TRANSFORM
+    | FEATURE_VALUE_TRANSFORM
+    | FEATURE_HEADS_DELTA;
+/// Optional-биты, которые понимает эта сборка.
+pub const FEATURES_KNOWN_OPTIONAL: u32 = FEATURE_WAL_EXPIRY | FEATURE_HEADS_GEN | FEATURE_USER_META;
+
+const FEATURE_NAMES: &[(u32, &str)] = &[
+    (FEATURE_TDE, "tde"),
+    (FEATURE_OVF_ZSTD, "ovf_zstd"),
+    (FEATURE_KV_PACKING, "kv_packing"),
+    (FEATURE_PAGED_DIR, "paged_dir"),
+    (FEATURE_TDE_AAD_V2, "tde_aad_v2"),
+    (FEATURE_BUCKET_ROUTING, "bucket_prefix_routing"),
+    (FEATURE_KEY_TRANSFORM, "key_transform"),
+    (FEATURE_VALUE_TRANSFORM, "value_transform"),
+    (FEATURE_HEADS_DELTA, "heads_delta"),
+    (FEATURE_WAL_EXPIRY, "wal_expiry"),
+    (FEATURE_HEADS_GEN, "heads_gen"),
+    (FEATURE_USER_META, "user_meta"),
+];
+
+/// Имена включённых features (неизвестные — "required_bitN"/"optional_bitN").
+pub fn feature_names(flags: u32) -> Vec<String> {
+    (0..32)
+        .map(|bit| 1u32 << bit)
+        .filter(|f| flags & f != 0)
+        .map(|f| match FEATURE_NAMES.iter().find(|(v, _)| *v == f) {
+            Some((_, n)) => n.to_string(),
+            None if f & FEATURES_REQUIRED_MASK != 0 => {
+                format!("required_bit{}", f.trailing_zeros())
+            }
+            None => format!("optional_bit{}", f.trailing_zeros()),
+        })
+        .collect()
+}
+
+/// Compatibility gate: ошибка, если в meta есть required-features, неизвестные этой сборке.
+/// Неизвестные optional-биты допускаются.
+pub fn check_features(root: &Path, h: &MetaHeader) -> Result<()> {
+    let unknown = h.flags & FEATURES_REQUIRED_MASK & !FEATURES_KNOWN_REQUIRED;
+    if unknown != 0 {
+        return Err(anyhow!(
+            "DB at {} requires features unsupported by this QuiverDB build: {} (format_flags=0x{:08x}); \
+             upgrade QuiverDB to open it",
+            root.display(),
+            feature_names(unknown).join(", "),
+            h.flags
+        ));
+    }
+    Ok(())
+}
+
+/// Добавить features в meta на диске (только при изменении). Возвращает итоговые флаги.
+pub fn add_features(root: &Path, features: u32) -> Result<u32> {
+    let mut m = read_meta(root)?;
+    if m.flags & features != features {
+        m.flags |= features;
+        write_meta_overwrite(root, &m)?;
+    }
+    Ok(m.flags)
+}
+
+/// NEW: включить AAD v2 для AEAD-трейлеров: страницы с LSN >= since_lsn подписываются с
+/// привязкой к page_id/типу/db_uuid. Назначает db_uuid, если его нет. Возвращает итоговую meta.
+/// Повторный вызов при уже включённом флаге ничего не меняет.
+pub fn enable_tde_aad_v2(root: &Path, since_lsn: u64) -> Result<MetaHeader> {
+    let mut m = read_meta(root)?;
+    if m.flags & FEATURE_TDE_AAD_V2 != 0 {
+        return Ok(m);
+    }
+    if m.db_uuid == [0u8; 16] {
+        m.db_uuid = new_db_uuid();
+    }
+    m.tde_aad_since_lsn = since_lsn;
+    m.flags |= FEATURE_TDE_AAD_V2;
+    write_meta_overwrite(root, &m)?;
+    Ok(m)
+}
+
 // ---- Структура заголовка ----
 
 #[derive(Debug, Clone)]
 pub struct MetaHeader {
     pub version: u32,   // == 4
     pub page_size: u32, // 4 KiB .. 1 MiB (power of two)
-    pub flags: u32,     // format_flags (v4) — сохраняем имя `flags` для совместимости
+    pub flags: u32,     // format_flags (v4): feature flags (required 0..15 / optional 16..31)
     pub next_page_id: u64,
     pub hash_kind: u32, // 1 = xxhash64(seed=0)
     pub last_lsn: u64,
@@ -62,6 +206,20 @@ pub struct MetaHeader {
     // Новые поля v4:
     pub codec_default: u16, // 0=none,1=zstd,2=lz4
     pub checksum_kind: u8,  // забронировано; принудительно CRC32C
+    // NEW: расширение meta
+    pub db_uuid: [u8; 16], // нули — не назначен (meta старого формата)
+    pub tde_aad_since_lsn: u64,
+    pub created_unix_ms: u64, // 0 — неизвестно
+    pub created_by: String,   // версия сборки, создавшей БД ("" — неизвестно)
+    // NEW: идентификатор KeyTransform ("" — ключи хранятся как есть)
+    pub key_transform: String,
+    // NEW: голова цепочки метаданных приложения (NO_PAGE — пусто)
+    pub user_meta_head: u64,
+    // NEW: проверочный блок ключа TDE (пустой — нет) и KID, которым он запечатан
+    pub tde_check_kid: String,
+    pub tde_check: Vec<u8>,
+    // NEW: идентификатор ValueTransform ("" — значения хранятся как есть)
+    pub value_transform: String,
 }
 
 impl Default for MetaHeader {
@@ -76,32 +234,183 @@ impl Default for MetaHeader {
             clean_shutdown: true,
             codec_default: CODEC_NONE,
             checksum_kind: CKSUM_CRC32C,
+            db_uuid: [0u8; 16],
+            tde_aad_since_lsn: 0,
+            created_unix_ms: 0,
+            created_by: String::new(),
+            key_transform: String::new(),
+            user_meta_head: crate::dir::NO_PAGE,
+            tde_check_kid: String::new(),
+            tde_check: Vec::new(),
+            value_transform: String::new(),
         }
     }
 }
 
-// ---- Внутренние утилиты ----
+/// Максимальная длина created_by на диске.
+const CREATED_BY_MAX: usize = 32;
+/// NEW: максимальная длина идентификатора KeyTransform на диске.
+pub const KEY_TRANSFORM_ID_MAX: usize = 64;
+/// NEW: максимальная длина идентификатора ValueTransform на диске.
+pub const VALUE_TRANSFORM_ID_MAX: usize = 64;
+
+impl MetaHeader {
+    /// NEW: маршрутизация ключей в бакеты (по hash_kind).
+    pub fn bucket_routing(&self) -> crate::dir::BucketRouting {
+        crate::dir::BucketRouting::from_hash_kind(self.hash_kind)
+    }
 
-#[inline]
-fn meta_path(root: &Path) -> PathBuf {
-    root.join(META_FILE)
-}
+    /// Назначен ли db_uuid.
+    pub fn has_db_uuid(&self) -> bool {
+        self.db_uuid != [0u8; 16]
+    }
 
-#[cfg(unix)]
-fn fsync_dir(path: &Path) -> std::io::Result<()> {
-    if let Some(parent) = path.parent() {
-        if !parent.as_os_str().is_empty() {
-            let dir = File::open(parent)?;
-            dir.sync_all()?;
+    /// Идентичность и провенанс БД (status, манифесты бэкапов, handshake CDC).
+    pub fn identity(&self) -> DbIdentity {
+        DbIdentity {
+            db_uuid: if self.has_db_uuid() {
+                format_uuid(&self.db_uuid)
+            } else {
+                String::new()
+            },
+            created_unix_ms: self.created_unix_ms,
+            created_by: self.created_by.clone(),
+            key_transform: self.key_transform.clone(),
+            value_transform: self.value_transform.clone(),
         }
     }
-    Ok(())
 }
-#[cfg(not(unix))]
-fn fsync_dir(_path: &Path) -> std::io::Result<()> {
+
+/// Идентичность БД: UUID и провенанс (пустые/нулевые поля — неизвестно).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DbIdentity {
+    pub db_uuid: String,
+    pub created_unix_ms: u64,
+    pub created_by: String,
+    /// NEW: идентификатор KeyTransform (едет с бэкапами/снапшотами; "" — нет).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub key_transform: String,
+    /// NEW: идентификатор ValueTransform (едет с бэкапами/снапшотами; "" — нет).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub value_transform: String,
+}
+
+/// Новый случайный UUID (v4, RFC 4122) для meta.db_uuid.
+pub fn new_db_uuid() -> [u8; 16] {
+    let mut u: [u8; 16] = rand::random();
+    u[6] = (u[6] & 0x0f) | 0x40;
+    u[8] = (u[8] & 0x3f) | 0x80;
+    u
+}
+
+/// Текстовая форма UUID (8-4-4-4-12, hex).
+pub fn format_uuid(u: &[u8; 16]) -> String {
+    let h: String = u.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &h[0..8],
+        &h[8..12],
+        &h[12..16],
+        &h[16..20],
+        &h[20..32]
+    )
+}
+
+/// Разобрать текстовый UUID (8-4-4-4-12 или 32 hex-символа).
+pub fn parse_uuid(s: &str) -> Result<[u8; 16]> {
+    let hex: String = s.trim().chars().filter(|c| *c != '-').collect();
+    if hex.len() != 32 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("invalid UUID '{}'", s.trim()));
+    }
+    let mut out = [0u8; 16];
+    for (i, b) in out.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)?;
+    }
+    Ok(out)
+}
+
+/// NEW: назначить db_uuid meta старого формата (writer open). Провенанс не выдумывается.
+/// Возвращает итоговую meta (без записи, если UUID уже есть).
+pub fn ensure_db_uuid(root: &Path) -> Result<MetaHeader> {
+    let mut m = read_meta(root)?;
+    if !m.has_db_uuid() {
+        m.db_uuid = new_db_uuid();
+        write_meta_overwrite(root, &m)?;
+    }
+    Ok(m)
+}
+
+/// NEW: принять идентичность источника (restore полного бэкапа/снапшота): UUID и провенанс.
+/// Пустой UUID источника (старый манифест) — meta не меняется.
+pub fn adopt_db_identity(m: &mut MetaHeader, id: &DbIdentity) -> Result<()> {
+    if id.db_uuid.is_empty() {
+        return Ok(());
+    }
+    m.db_uuid = parse_uuid(&id.db_uuid)?;
+    m.created_unix_ms = id.created_unix_ms;
+    m.created_by = id.created_by.clone();
+    // NEW: ключи источника — в канонической форме его KeyTransform
+    if !id.key_transform.is_empty() {
+        m.key_transform = id.key_transform.clone();
+        m.flags |= FEATURE_KEY_TRANSFORM;
+    }
+    // NEW: значения источника — закодированы его ValueTransform
+    if !id.value_transform.is_empty() {
+        m.value_transform = id.value_transform.clone();
+        m.flags |= FEATURE_VALUE_TRANSFORM;
+    }
     Ok(())
 }
 
+/// NEW: голова области метаданных приложения из бэкапа/снапшота (None — архив старой сборки).
+pub fn adopt_user_meta_head(m: &mut MetaHeader, head: Option<u64>) {
+    if let Some(head) = head {
+        m.user_meta_head = head;
+        if head != crate::dir::NO_PAGE {
+            m.flags |= FEATURE_USER_META;
+        }
+    }
+}
+
+/// NEW: записать идентификатор KeyTransform в meta и поднять required-бит (writer open).
+pub fn record_key_transform(root: &Path, id: &str) -> Result<MetaHeader> {
+    if id.is_empty() || id.len() > KEY_TRANSFORM_ID_MAX {
+        return Err(anyhow!(
+            "key transform id must be 1..={} bytes, got {:?}",
+            KEY_TRANSFORM_ID_MAX,
+            id
+        ));
+    }
+    let mut m = read_meta(root)?;
+    m.key_transform = id.to_string();
+    m.flags |= FEATURE_KEY_TRANSFORM;
+    write_meta_overwrite(root, &m)?;
+    Ok(m)
+}
+
+/// NEW: записать идентификатор ValueTransform в meta и поднять required-бит (writer open).
+pub fn record_value_transform(root: &Path, id: &str) -> Result<MetaHeader> {
+    if id.is_empty() || id.len() > VALUE_TRANSFORM_ID_MAX {
+        return Err(anyhow!(
+            "value transform id must be 1..={} bytes, got {:?}",
+            VALUE_TRANSFORM_ID_MAX,
+            id
+        ));
+    }
+    let mut m = read_meta(root)?;
+    m.value_transform = id.to_string();
+    m.flags |= FEATURE_VALUE_TRANSFORM;
+    write_meta_overwrite(root, &m)?;
+    Ok(m)
+}
+
+// ---- Внутренние утилиты ----
+
+#[inline]
+fn meta_path(root: &Path) -> PathBuf {
+    root.join(META_FILE)
+}
+
 /// Проверка корректности размера страницы (2^n, 4 KiB .. 1 MiB).
 pub fn validate_page_size(page_size: u32) -> Result<()> {
     const MAX: u32 = 1 << 20; // 1 MiB
@@ -128,21 +437,17 @@ pub fn write_meta_new(root: &Path, h: &MetaHeader) -> Result<()> {
     let tmp = root.join(format!("{}.tmp", META_FILE));
     let _ = fs::remove_file(&tmp); // best‑effort
 
-    let mut f = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(&tmp)
-        .with_context(|| format!("open meta tmp {}", tmp.display()))?;
+    let mut f =
+        open_tmp_for_write(&tmp).with_context(|| format!("open meta tmp {}", tmp.display()))?;
 
     // Пишем всегда с нормализацией checksum_kind к CRC32C
     write_meta_contents_crc32c(&mut f, h)?;
     f.sync_all()?; // flush tmp to disk
 
-    fs::rename(&tmp, &path)
+    replace_file(&tmp, &path)
         .with_context(|| format!("rename {} -> {}", tmp.display(), path.display()))?;
 
-    let _ = fsync_dir(&path);
+    let _ = fsync_parent_dir(&path);
     Ok(())
 }
 
@@ -154,20 +459,16 @@ pub fn write_meta_overwrite(root: &Path, h: &MetaHeader) -> Result<()> {
     let tmp = root.join(format!("{}.tmp", META_FILE));
     let _ = fs::remove_file(&tmp);
 
-    let mut f = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(&tmp)
-        .with_context(|| format!("open meta tmp {}", tmp.display()))?;
+    let mut f =
+        open_tmp_for_write(&tmp).with_context(|| format!("open meta tmp {}", tmp.display()))?;
 
     // Пишем всегда с нормализацией checksum_kind к CRC32C
     write_meta_contents_crc32c(&mut f, h)?;
     f.sync_all()?; // ensure tmp is on disk
 
-    fs::rename(&tmp, &path)
+    replace_file(&tmp, &path)
         .with_context(|| format!("rename {} -> {}", tmp.display(), path.display()))?;
-    let _ = fsync_dir(&path);
+    let _ = fsync_parent_dir(&path);
     Ok(())
 }
 
@@ -185,6 +486,34 @@ fn write_meta_contents_crc32c(f: &mut std::fs::File, h: &MetaHeader) -> Result<(
     f.write_u16::<LittleEndian>(h.codec_default)?;
     // Нормализуем: всегда CRC32C
     f.write_u8(CKSUM_CRC32C)?;
+    // NEW: расширение
+    f.write_all(&h.db_uuid)?;
+    f.write_u64::<LittleEndian>(h.tde_aad_since_lsn)?;
+    f.write_u64::<LittleEndian>(h.created_unix_ms)?;
+    let by = h.created_by.as_bytes();
+    let by = &by[..by.len().min(CREATED_BY_MAX)];
+    f.write_u8(by.len() as u8)?;
+    f.write_all(by)?;
+    let kt = h.key_transform.as_bytes();
+    let kt = &kt[..kt.len().min(KEY_TRANSFORM_ID_MAX)];
+    f.write_u8(kt.len() as u8)?;
+    f.write_all(kt)?;
+    f.write_u64::<LittleEndian>(h.user_meta_head)?;
+    // KID длиннее u8 не запечатывается (Pager::seal_tde_key_check) — пишем пустой блок
+    let (ck, cb) =
+        if h.tde_check_kid.len() <= u8::MAX as usize && h.tde_check.len() <= u8::MAX as usize {
+            (h.tde_check_kid.as_bytes(), h.tde_check.as_slice())
+        } else {
+            (&[][..], &[][..])
+        };
+    f.write_u8(ck.len() as u8)?;
+    f.write_all(ck)?;
+    f.write_u8(cb.len() as u8)?;
+    f.write_all(cb)?;
+    let vt = h.value_transform.as_bytes();
+    let vt = &vt[..vt.len().min(VALUE_TRANSFORM_ID_MAX)];
+    f.write_u8(vt.len() as u8)?;
+    f.write_all(vt)?;
     Ok(())
 }
 
@@ -224,6 +553,53 @@ pub fn read_meta(root: &Path) -> Result<MetaHeader> {
     let clean_shutdown = f.read_u8()? != 0;
     let codec_default = f.read_u16::<LittleEndian>()?;
     let checksum_kind_disk = f.read_u8()?;
+    // NEW: расширение (может отсутствовать у meta старого формата)
+    let mut db_uuid = [0u8; 16];
+    let mut tde_aad_since_lsn = 0u64;
+    let mut created_unix_ms = 0u64;
+    let mut created_by = String::new();
+    let mut key_transform = String::new();
+    let mut user_meta_head = crate::dir::NO_PAGE;
+    let mut tde_check_kid = String::new();
+    let mut tde_check = Vec::new();
+    let mut value_transform = String::new();
+    if f.read_exact(&mut db_uuid).is_ok() {
+        tde_aad_since_lsn = f.read_u64::<LittleEndian>().unwrap_or(0);
+        created_unix_ms = f.read_u64::<LittleEndian>().unwrap_or(0);
+        if let Ok(n) = f.read_u8() {
+            let mut by = vec![0u8; (n as usize).min(CREATED_BY_MAX)];
+            if f.read_exact(&mut by).is_ok() {
+                created_by = String::from_utf8_lossy(&by).into_owned();
+            }
+        }
+        if let Ok(n) = f.read_u8() {
+            let mut kt = vec![0u8; (n as usize).min(KEY_TRANSFORM_ID_MAX)];
+            if f.read_exact(&mut kt).is_ok() {
+                key_transform = String::from_utf8_lossy(&kt).into_owned();
+            }
+        }
+        user_meta_head = f.read_u64::<LittleEndian>().unwrap_or(crate::dir::NO_PAGE);
+        if let Ok(n) = f.read_u8() {
+            let mut kid = vec![0u8; n as usize];
+            if f.read_exact(&mut kid).is_ok() {
+                if let Ok(n) = f.read_u8() {
+                    let mut block = vec![0u8; n as usize];
+                    if f.read_exact(&mut block).is_ok() {
+                        tde_check_kid = String::from_utf8_lossy(&kid).into_owned();
+                        tde_check = block;
+                    }
+                }
+            }
+        }
+        if let Ok(n) = f.read_u8() {
+            let mut vt = vec![0u8; (n as usize).min(VALUE_TRANSFORM_ID_MAX)];
+            if f.read_exact(&mut vt).is_ok() {
+                value_transform = String::from_utf8_lossy(&vt).into_owned();
+            }
+        }
+    } else {
+        db_uuid = [0u8; 16];
+    }
 
     // Нормализация checksum_kind
     if checksum_kind_disk != CKSUM_CRC32C {
@@ -246,6 +622,15 @@ pub fn read_meta(root: &Path) -> Result<MetaHeader> {
         clean_shutdown,
         codec_default,
         checksum_kind: CKSUM_CRC32C,
+        db_uuid,
+        tde_aad_since_lsn,
+        created_unix_ms,
+        created_by,
+        key_transform,
+        user_meta_head,
+        tde_check_kid,
+        tde_check,
+        value_transform,
     })
 }
 
@@ -288,10 +673,24 @@ pub fn init_meta_v4(
         ..MetaHeader::default()
     };
     m.version = 4;
-    m.flags = 0;
+    m.flags = if codec_default == CODEC_ZSTD {
+        FEATURE_OVF_ZSTD
+    } else {
+        0
+    };
+    // NEW: маршрутизация по префиксу меняет раскладку ключей — старые сборки не должны её открывать
+    if hash_kind & 0xFF == HASH_KIND_XX64_PREFIX {
+        m.flags |= FEATURE_BUCKET_ROUTING;
+    }
     m.next_page_id = 0;
     m.last_lsn = 0;
     m.clean_shutdown = true;
+    m.db_uuid = new_db_uuid();
+    m.created_unix_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    m.created_by = format!("quiverdb {}", env!("CARGO_PKG_VERSION"));
 
     write_meta_new(root, &m)
 }
@@ -324,6 +723,27 @@ mod tests {
         assert_eq!(m2.last_lsn, 999);
     }
 
+    #[test]
+    fn feature_flags_gate_unknown_required_only() {
+        let root = std::env::temp_dir().join(format!("qdb2-meta-feat-{}", nanos_for_test()));
+        fs::create_dir_all(&root).unwrap();
+        init_meta_v4(&root, 4096, HASH_KIND_XX64_SEED0, CODEC_ZSTD, CKSUM_CRC32C).unwrap();
+        assert_eq!(read_meta(&root).unwrap().flags, FEATURE_OVF_ZSTD);
+
+        let flags = add_features(&root, FEATURE_TDE | FEATURE_HEADS_GEN | (1 << 30)).unwrap();
+        let m = read_meta(&root).unwrap();
+        assert_eq!(m.flags, flags);
+        check_features(&root, &m).unwrap();
+        assert_eq!(
+            feature_names(m.flags),
+            vec!["tde", "ovf_zstd", "heads_gen", "optional_bit30"]
+        );
+
+        add_features(&root, 1 << 9).unwrap();
+        let err = check_features(&root, &read_meta(&root).unwrap()).unwrap_err();
+        assert!(format!("{}", err).contains("required_bit9"), "{}", err);
+    }
+
     fn nanos_for_test() -> u128 {
         std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -17,8 +17,77 @@
 //! - NEW: WAL threshold flush — счётчики пороговых fsync вне явного батча
 //! - NEW: Compaction totals — итоговые счётчики выбранных/удалённых ключей и упакованных страниц
 //! - NEW: Value cache (OVERFLOW) — live‑статистика и счётчики попаданий/промахов
+//! - NEW: Scrub — проверенные страницы, сбои, завершённые проходы
+//! - NEW: Page cache prewarm — запуски, загруженные страницы, попадания по прогретым страницам
+//! - NEW: Readahead — заполнения окна, прочитанные окном страницы, попадания в окно
+//! - NEW: Commit pipelining — батчи, где fsync WAL шёл параллельно с записью сегментов
+//! - NEW: Slow IO — операции fsync WAL/записи сегментов дольше порога (util/iostall.rs)
+//!
+//! NEW: без блокировок на всех путях. Горячие счётчики пути чтения (page cache, keydir,
+//! bloom, TTL skip, prewarm/readahead hits) шардированы по потокам (ShardedCounter):
+//! каждый поток пишет в свою cache line, snapshot() суммирует шарды без блокировок.
+//! Сумма снимается не атомарно по шардам — при параллельной записи снимок может отставать
+//! на «летящие» инкременты, но счётчики монотонны и ничего не теряют.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+// ----- Sharded counters (hot paths) -----
+
+const COUNTER_SHARDS: usize = 16;
+
+/// Атомик в отдельной cache line (без false sharing между шардами).
+#[repr(align(64))]
+struct PaddedU64(AtomicU64);
+
+/// Счётчик, шардированный по потокам: add — Relaxed fetch_add в шард потока.
+struct ShardedCounter {
+    shards: [PaddedU64; COUNTER_SHARDS],
+}
+
+impl ShardedCounter {
+    const fn new() -> Self {
+        Self {
+            shards: [const { PaddedU64(AtomicU64::new(0)) }; COUNTER_SHARDS],
+        }
+    }
 
-use std::sync::atomic::{AtomicU64, Ordering};
+    #[inline]
+    fn add(&self, n: u64) {
+        self.shards[shard_index()].0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    fn sum(&self) -> u64 {
+        self.shards
+            .iter()
+            .fold(0u64, |acc, s| acc.wrapping_add(s.0.load(Ordering::Relaxed)))
+    }
+
+    fn reset(&self) {
+        for s in &self.shards {
+            s.0.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+/// Шард текущего потока: назначается по кругу при первом обращении.
+#[inline]
+fn shard_index() -> usize {
+    thread_local! {
+        static SHARD: Cell<usize> = const { Cell::new(usize::MAX) };
+    }
+    SHARD.with(|c| {
+        let v = c.get();
+        if v != usize::MAX {
+            return v;
+        }
+        let v = NEXT_SHARD.fetch_add(1, Ordering::Relaxed) % COUNTER_SHARDS;
+        c.set(v);
+        v
+    })
+}
 
 // ----- WAL -----
 static WAL_APPENDS_TOTAL: AtomicU64 = AtomicU64::new(0);
@@ -37,12 +106,12 @@ static WAL_THRESHOLD_FLUSH_PAGES: AtomicU64 = AtomicU64::new(0);
 static WAL_THRESHOLD_FLUSH_BYTES: AtomicU64 = AtomicU64::new(0);
 
 // ----- Page cache -----
-static PAGE_CACHE_HITS: AtomicU64 = AtomicU64::new(0);
-static PAGE_CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+static PAGE_CACHE_HITS: ShardedCounter = ShardedCounter::new();
+static PAGE_CACHE_MISSES: ShardedCounter = ShardedCounter::new();
 
 // ----- In-memory keydir fast-path -----
-static KEYDIR_HITS: AtomicU64 = AtomicU64::new(0);
-static KEYDIR_MISSES: AtomicU64 = AtomicU64::new(0);
+static KEYDIR_HITS: ShardedCounter = ShardedCounter::new();
+static KEYDIR_MISSES: ShardedCounter = ShardedCounter::new();
 
 // ----- Robin Hood -----
 static RH_PAGE_COMPACTIONS: AtomicU64 = AtomicU64::new(0);
@@ -67,18 +136,22 @@ static RESTORE_BYTES_WRITTEN: AtomicU64 = AtomicU64::new(0);
 static SNAPSHOT_FALLBACK_SCANS: AtomicU64 = AtomicU64::new(0);
 
 // NEW: TTL read-side — сколько раз запись была пропущена из-за истечения срока
-static TTL_SKIPPED: AtomicU64 = AtomicU64::new(0);
+static TTL_SKIPPED: ShardedCounter = ShardedCounter::new();
 
 // NEW: Bloom fast-path (v2.0.x)
-static BLOOM_TESTS: AtomicU64 = AtomicU64::new(0);
-static BLOOM_NEGATIVE: AtomicU64 = AtomicU64::new(0);
-static BLOOM_POSITIVE: AtomicU64 = AtomicU64::new(0);
+static BLOOM_TESTS: ShardedCounter = ShardedCounter::new();
+static BLOOM_NEGATIVE: ShardedCounter = ShardedCounter::new();
+static BLOOM_POSITIVE: ShardedCounter = ShardedCounter::new();
 static BLOOM_SKIPPED_STALE: AtomicU64 = AtomicU64::new(0);
 
 // NEW: Bloom delta-update (batch)
 static BLOOM_UPDATES_TOTAL: AtomicU64 = AtomicU64::new(0);
 static BLOOM_UPDATE_BYTES: AtomicU64 = AtomicU64::new(0);
 
+// NEW: Bloom auto-refresh (QuiverConfig::bloom_auto_refresh)
+static BLOOM_AUTO_REFRESHES: AtomicU64 = AtomicU64::new(0);
+static BLOOM_AUTO_REFRESH_BUCKETS: AtomicU64 = AtomicU64::new(0);
+
 // NEW: Packing (KV page packing)
 static PACK_PAGES: AtomicU64 = AtomicU64::new(0);
 static PACK_RECORDS: AtomicU64 = AtomicU64::new(0);
@@ -93,6 +166,191 @@ static COMPACTION_KEYS_SELECTED: AtomicU64 = AtomicU64::new(0);
 static COMPACTION_KEYS_DELETED: AtomicU64 = AtomicU64::new(0);
 static COMPACTION_PAGES_PACKED: AtomicU64 = AtomicU64::new(0);
 
+// NEW: Scrub
+static SCRUB_PAGES_VERIFIED: AtomicU64 = AtomicU64::new(0);
+static SCRUB_FAILURES: AtomicU64 = AtomicU64::new(0);
+static SCRUB_PASSES_COMPLETED: AtomicU64 = AtomicU64::new(0);
+
+// NEW: Page cache prewarm
+static PREWARM_RUNS: AtomicU64 = AtomicU64::new(0);
+static PREWARM_PAGES_LOADED: AtomicU64 = AtomicU64::new(0);
+static PREWARM_HITS: ShardedCounter = ShardedCounter::new();
+
+// NEW: Readahead (chain traversal)
+static READAHEAD_FILLS: AtomicU64 = AtomicU64::new(0);
+static READAHEAD_PAGES_READ: AtomicU64 = AtomicU64::new(0);
+static READAHEAD_HITS: ShardedCounter = ShardedCounter::new();
+
+// NEW: Commit pipelining
+static COMMIT_PIPELINED_BATCHES: AtomicU64 = AtomicU64::new(0);
+
+// NEW: WAL recovery
+static RECOVERY_REPLAYS: AtomicU64 = AtomicU64::new(0);
+static RECOVERY_FRAMES_APPLIED: AtomicU64 = AtomicU64::new(0);
+static RECOVERY_BYTES_SKIPPED: AtomicU64 = AtomicU64::new(0);
+
+// NEW: idempotent batches
+static IDEM_BATCHES_SKIPPED: AtomicU64 = AtomicU64::new(0);
+static IDEM_TOKENS_RECORDED: AtomicU64 = AtomicU64::new(0);
+
+// NEW: TTL expiry events
+static EXPIRY_EVENTS_EMITTED: AtomicU64 = AtomicU64::new(0);
+static EXPIRY_FRAMES_WRITTEN: AtomicU64 = AtomicU64::new(0);
+
+// NEW: HEADS_UPDATE coalescing / delta encoding
+static HEADS_UPDATE_FRAMES: AtomicU64 = AtomicU64::new(0);
+static HEADS_UPDATE_ENTRIES: AtomicU64 = AtomicU64::new(0);
+static HEADS_UPDATE_SKIPPED: AtomicU64 = AtomicU64::new(0);
+static HEADS_UPDATE_BYTES: AtomicU64 = AtomicU64::new(0);
+static HEADS_UPDATE_BYTES_LEGACY: AtomicU64 = AtomicU64::new(0);
+
+// NEW: slow IO (stall detector)
+static IO_STALLS_TOTAL: AtomicU64 = AtomicU64::new(0);
+static IO_STALL_MS_TOTAL: AtomicU64 = AtomicU64::new(0);
+static IO_STALL_MAX_MS: AtomicU64 = AtomicU64::new(0);
+
+// NEW: maintenance scheduler (maint-daemon)
+static MAINT_RUNS: AtomicU64 = AtomicU64::new(0);
+static MAINT_SNAPSHOTS_CREATED: AtomicU64 = AtomicU64::new(0);
+static MAINT_SNAPSHOTS_FAILED: AtomicU64 = AtomicU64::new(0);
+static MAINT_SNAPSHOTS_PRUNED: AtomicU64 = AtomicU64::new(0);
+
+// NEW: offsite backup scheduler (backup_sched)
+static BACKUP_SCHED_RUNS: AtomicU64 = AtomicU64::new(0);
+static BACKUP_SCHED_FAILURES: AtomicU64 = AtomicU64::new(0);
+static BACKUP_SCHED_PRUNED: AtomicU64 = AtomicU64::new(0);
+static RESTORE_TESTS_OK: AtomicU64 = AtomicU64::new(0);
+static RESTORE_TESTS_FAILED: AtomicU64 = AtomicU64::new(0);
+
+// NEW: freeze/thaw (Db::freeze)
+static DB_FREEZES: AtomicU64 = AtomicU64::new(0);
+static DB_FROZEN_MS_TOTAL: AtomicU64 = AtomicU64::new(0);
+static COMMITS_REJECTED_FROZEN: AtomicU64 = AtomicU64::new(0);
+
+// NEW: записи, отклонённые гейтом read-only (Pager::ensure_writable)
+static WRITES_REJECTED_READONLY: AtomicU64 = AtomicU64::new(0);
+
+// NEW: рост сегментов (pager/alloc.rs)
+static SEG_GROW_OPS: AtomicU64 = AtomicU64::new(0);
+static SEG_GROW_BYTES: AtomicU64 = AtomicU64::new(0);
+static SEG_GROW_STALL_US_TOTAL: AtomicU64 = AtomicU64::new(0);
+static SEG_GROW_STALL_MAX_US: AtomicU64 = AtomicU64::new(0);
+static SEG_FALLOCATE_FALLBACKS: AtomicU64 = AtomicU64::new(0);
+
+// NEW: возврат освобождённых страниц ФС (pager/trim.rs)
+static PUNCH_HOLE_OPS: AtomicU64 = AtomicU64::new(0);
+static PUNCH_HOLE_BYTES: AtomicU64 = AtomicU64::new(0);
+static PUNCH_HOLE_UNSUPPORTED: AtomicU64 = AtomicU64::new(0);
+
+// NEW: writable-ветки (snapstore/branch.rs)
+static BRANCH_PAGE_FETCHES: AtomicU64 = AtomicU64::new(0);
+
+// NEW: индекс истории ключей (wal/history.rs)
+static HISTORY_INDEX_RUNS: AtomicU64 = AtomicU64::new(0);
+static HISTORY_ENTRIES_INDEXED: AtomicU64 = AtomicU64::new(0);
+
+// NEW: ResourceBudget (budget.rs)
+static RESOURCE_BUDGET_DENIALS: AtomicU64 = AtomicU64::new(0);
+static RESOURCE_BUDGET_FD_DENIALS: AtomicU64 = AtomicU64::new(0);
+static RESOURCE_BUDGET_DEGRADATIONS: AtomicU64 = AtomicU64::new(0);
+
+// NEW: CDC verify (сверка digest'ов бакетов follower ↔ leader)
+static CDC_VERIFY_CHECKS: AtomicU64 = AtomicU64::new(0);
+static CDC_VERIFY_DIVERGENT_BUCKETS: AtomicU64 = AtomicU64::new(0);
+static CDC_VERIFY_DEFERRED: AtomicU64 = AtomicU64::new(0);
+
+// NEW: CDC resync (замена разошедшихся бакетов follower'а состоянием leader'а)
+static CDC_RESYNC_BUCKETS: AtomicU64 = AtomicU64::new(0);
+static CDC_RESYNC_PAGES: AtomicU64 = AtomicU64::new(0);
+static CDC_RESYNC_FAILED: AtomicU64 = AtomicU64::new(0);
+static CDC_RESYNC_DEFERRED: AtomicU64 = AtomicU64::new(0);
+
+// NEW: AutoBatcher (очередь put/del с автоматическим сбросом батчем)
+static AUTO_BATCH_FLUSHES: AtomicU64 = AtomicU64::new(0);
+static AUTO_BATCH_OPS: AtomicU64 = AtomicU64::new(0);
+static AUTO_BATCH_DROP_ERRORS: AtomicU64 = AtomicU64::new(0);
+
+// NEW: адаптивное окно group-commit (gauges — последнее решение)
+static WAL_COALESCE_WINDOW_US: AtomicU64 = AtomicU64::new(0);
+static WAL_COALESCE_P99_US: AtomicU64 = AtomicU64::new(0);
+static WAL_COALESCE_GROW: AtomicU64 = AtomicU64::new(0);
+static WAL_COALESCE_SHRINK: AtomicU64 = AtomicU64::new(0);
+static WAL_COALESCE_HOLD: AtomicU64 = AtomicU64::new(0);
+
+// NEW: panic-safety (хэндлы, отравленные паникой в критической секции)
+static DB_POISONED: AtomicU64 = AtomicU64::new(0);
+static WRITES_REJECTED_POISONED: AtomicU64 = AtomicU64::new(0);
+
+// NEW: проверка аллокатора (db/alloc_check.rs)
+static ALLOC_DOUBLE_USE_PAGES: AtomicU64 = AtomicU64::new(0);
+static ALLOC_FREELIST_REBUILDS: AtomicU64 = AtomicU64::new(0);
+
+// NEW: fsync сегментов батча (pager/segsync.rs)
+static SEG_FSYNC_BATCHES: AtomicU64 = AtomicU64::new(0);
+static SEG_FSYNC_CALLS: AtomicU64 = AtomicU64::new(0);
+static SEG_SYNCFS_CALLS: AtomicU64 = AtomicU64::new(0);
+static SEG_FSYNC_LAST_BATCH: AtomicU64 = AtomicU64::new(0);
+
+// NEW: префиксные сканы, сведённые к одному бакету (dir/routing.rs)
+static SCAN_PREFIX_SINGLE_BUCKET: AtomicU64 = AtomicU64::new(0);
+
+// NEW: постраничный скан Db::scan_page (db/scan_page.rs)
+static SCAN_PAGE_CALLS: AtomicU64 = AtomicU64::new(0);
+static SCAN_CURSOR_INVALID: AtomicU64 = AtomicU64::new(0);
+
+// NEW: cdc::subscribe (in-process CDC stream, feature "async")
+static CDC_SUBSCRIBE_EVENTS: AtomicU64 = AtomicU64::new(0);
+static CDC_SUBSCRIBE_GAPS: AtomicU64 = AtomicU64::new(0);
+
+// NEW: область метаданных приложения (Db::meta_put)
+static USER_META_WRITES: AtomicU64 = AtomicU64::new(0);
+
+// NEW: отказы проверочного блока ключа TDE при open (crypto/key_check.rs)
+static TDE_KEY_CHECK_FAILURES: AtomicU64 = AtomicU64::new(0);
+
+// NEW: чтения страниц, подписанных KID без загруженного ключа (per-page KID)
+static TDE_PAGE_KID_MISSING: AtomicU64 = AtomicU64::new(0);
+
+// NEW: сверка bloom.bin с реплеем WAL при открытии writer'а после краха
+static BLOOM_REPLAY_RECONCILED: AtomicU64 = AtomicU64::new(0);
+static BLOOM_REPLAY_INVALIDATED: AtomicU64 = AtomicU64::new(0);
+
+// NEW: страницы, оставленные вне free-листа закреплениями (scan_page / снапшоты)
+static MAINT_PINNED_PAGES_KEPT: AtomicU64 = AtomicU64::new(0);
+
+// NEW: hot-get кэш локаций (db/hot_get.rs)
+static HOT_GET_HITS: AtomicU64 = AtomicU64::new(0);
+static HOT_GET_MISSES: AtomicU64 = AtomicU64::new(0);
+static HOT_GET_INVALIDATIONS: AtomicU64 = AtomicU64::new(0);
+
+// NEW: reflink-копии (util/reflink.rs)
+static REFLINK_FILES: AtomicU64 = AtomicU64::new(0);
+static REFLINK_BYTES: AtomicU64 = AtomicU64::new(0);
+static REFLINK_FALLBACKS: AtomicU64 = AtomicU64::new(0);
+
+// NEW: TLS-слушатель CDC (перезагрузка сертификата, срок действия)
+static TLS_CERT_RELOADS: AtomicU64 = AtomicU64::new(0);
+static TLS_CERT_RELOAD_ERRORS: AtomicU64 = AtomicU64::new(0);
+static TLS_CERT_EXPIRY_WARNINGS: AtomicU64 = AtomicU64::new(0);
+static TLS_CERT_NOT_AFTER_UNIX: AtomicU64 = AtomicU64::new(0);
+
+// NEW: клиентская TLS-identity follower'а (P1_TLS_CLIENT_PFX: перезагрузка, срок действия)
+static TLS_CLIENT_CERT_RELOADS: AtomicU64 = AtomicU64::new(0);
+static TLS_CLIENT_CERT_RELOAD_ERRORS: AtomicU64 = AtomicU64::new(0);
+static TLS_CLIENT_CERT_EXPIRY_WARNINGS: AtomicU64 = AtomicU64::new(0);
+static TLS_CLIENT_CERT_NOT_AFTER_UNIX: AtomicU64 = AtomicU64::new(0);
+
+// NEW: trash-режим (мягкие tombstone'ы и undelete)
+static TRASH_DELETES: AtomicU64 = AtomicU64::new(0);
+static UNDELETES: AtomicU64 = AtomicU64::new(0);
+
+// NEW: политика хранения page cache (ciphertext / locked)
+static PAGE_CACHE_MLOCK_REJECTS: AtomicU64 = AtomicU64::new(0);
+static PAGE_CACHE_UNSEAL_FAILURES: AtomicU64 = AtomicU64::new(0);
+
+// NEW: TDE — страницы прежних эпох, проверенные ключом своего KID
+static TDE_EPOCH_KEY_VERIFIES: AtomicU64 = AtomicU64::new(0);
+
 #[derive(Debug, Clone, Default)]
 pub struct MetricsSnapshot {
     // WAL
@@ -157,6 +415,10 @@ pub struct MetricsSnapshot {
     pub bloom_updates_total: u64,
     pub bloom_update_bytes: u64,
 
+    // NEW: Bloom auto-refresh
+    pub bloom_auto_refreshes: u64,
+    pub bloom_auto_refresh_buckets: u64,
+
     // NEW: Packing
     pub pack_pages: u64,
     pub pack_records: u64,
@@ -177,6 +439,196 @@ pub struct MetricsSnapshot {
     pub value_cache_entries: u64,
     pub value_cache_hits: u64,
     pub value_cache_misses: u64,
+
+    // NEW: Scrub
+    pub scrub_pages_verified: u64,
+    pub scrub_failures: u64,
+    pub scrub_passes_completed: u64,
+
+    // NEW: Page cache prewarm
+    pub prewarm_runs: u64,
+    pub prewarm_pages_loaded: u64,
+    pub prewarm_hits: u64,
+
+    // NEW: Readahead
+    pub readahead_fills: u64,
+    pub readahead_pages_read: u64,
+    pub readahead_hits: u64,
+
+    // NEW: Commit pipelining
+    pub commit_pipelined_batches: u64,
+
+    // NEW: WAL recovery
+    pub recovery_replays: u64,
+    pub recovery_frames_applied: u64,
+    pub recovery_bytes_skipped: u64,
+
+    // NEW: idempotent batches
+    pub idem_batches_skipped: u64,
+    pub idem_tokens_recorded: u64,
+
+    // NEW: TTL expiry events
+    pub expiry_events_emitted: u64,
+    pub expiry_frames_written: u64,
+
+    // NEW: HEADS_UPDATE coalescing (skipped — дубли/неизменённые головы, отброшенные до WAL;
+    // bytes_legacy — сколько занял бы legacy-формат 12 B/запись)
+    pub heads_update_frames: u64,
+    pub heads_update_entries: u64,
+    pub heads_update_skipped: u64,
+    pub heads_update_bytes: u64,
+    pub heads_update_bytes_legacy: u64,
+
+    // NEW: slow IO
+    pub io_stalls_total: u64,
+    pub io_stall_ms_total: u64,
+    pub io_stall_max_ms: u64,
+
+    // NEW: maintenance scheduler
+    pub maint_runs: u64,
+    pub maint_snapshots_created: u64,
+    pub maint_snapshots_failed: u64,
+    pub maint_snapshots_pruned: u64,
+
+    // NEW: offsite backup scheduler
+    pub backup_sched_runs: u64,
+    pub backup_sched_failures: u64,
+    pub backup_sched_pruned: u64,
+    pub restore_tests_ok: u64,
+    pub restore_tests_failed: u64,
+
+    // NEW: freeze/thaw
+    pub db_freezes: u64,
+    pub db_frozen_ms_total: u64,
+    pub commits_rejected_frozen: u64,
+
+    // NEW: read-only гейт
+    pub writes_rejected_readonly: u64,
+
+    // NEW: рост сегментов
+    pub seg_grow_ops: u64,
+    pub seg_grow_bytes: u64,
+    pub seg_grow_stall_us_total: u64,
+    pub seg_grow_stall_max_us: u64,
+    pub seg_fallocate_fallbacks: u64,
+
+    // NEW: punch holes
+    pub punch_hole_ops: u64,
+    pub punch_hole_bytes: u64,
+    pub punch_hole_unsupported: u64,
+
+    // NEW: writable-ветки
+    pub branch_page_fetches: u64,
+
+    // NEW: индекс истории ключей
+    pub history_index_runs: u64,
+    pub history_entries_indexed: u64,
+
+    // NEW: ResourceBudget (счётчики + текущее потребление)
+    pub resource_budget_denials: u64,
+    pub resource_budget_fd_denials: u64,
+    pub resource_budget_degradations: u64,
+    pub budget_memory_bytes: u64,
+    pub budget_open_fds: u64,
+
+    // NEW: CDC verify
+    pub cdc_verify_checks: u64,
+    pub cdc_verify_divergent_buckets: u64,
+    pub cdc_verify_deferred: u64,
+
+    // NEW: CDC resync
+    pub cdc_resync_buckets: u64,
+    pub cdc_resync_pages: u64,
+    pub cdc_resync_failed: u64,
+    pub cdc_resync_deferred: u64,
+
+    // NEW: AutoBatcher
+    pub auto_batch_flushes: u64,
+    pub auto_batch_ops: u64,
+    pub auto_batch_drop_errors: u64,
+
+    // NEW: adaptive WAL coalescing
+    pub wal_coalesce_window_us: u64,
+    pub wal_coalesce_p99_us: u64,
+    pub wal_coalesce_grow: u64,
+    pub wal_coalesce_shrink: u64,
+    pub wal_coalesce_hold: u64,
+
+    // NEW: panic-safety
+    pub db_poisoned: u64,
+    pub writes_rejected_poisoned: u64,
+
+    // NEW: alloc check
+    pub alloc_double_use_pages: u64,
+    pub alloc_freelist_rebuilds: u64,
+
+    // NEW: fsync сегментов батча
+    pub seg_fsync_batches: u64,
+    pub seg_fsync_calls: u64,
+    pub seg_syncfs_calls: u64,
+    pub seg_fsync_last_batch: u64,
+
+    // NEW: scan_prefix_single_bucket — сканов по префиксу, обошедших один бакет
+    pub scan_prefix_single_bucket: u64,
+
+    // NEW: scan_page_calls / scan_cursor_invalid — страниц scan_page и отвергнутых курсоров
+    pub scan_page_calls: u64,
+    pub scan_cursor_invalid: u64,
+
+    // NEW: cdc::subscribe
+    pub cdc_subscribe_events: u64,
+    pub cdc_subscribe_gaps: u64,
+
+    // NEW: user metadata area
+    pub user_meta_writes: u64,
+
+    // NEW: TDE key check
+    pub tde_key_check_failures: u64,
+
+    // NEW: TDE per-page KID
+    pub tde_page_kid_missing: u64,
+
+    // NEW: bloom reconcile after WAL replay
+    pub bloom_replay_reconciled: u64,
+    pub bloom_replay_invalidated: u64,
+
+    // NEW: maint pins
+    pub maint_pinned_pages_kept: u64,
+
+    // NEW: hot get cache
+    pub hot_get_hits: u64,
+    pub hot_get_misses: u64,
+    pub hot_get_invalidations: u64,
+
+    // NEW: reflink
+    pub reflink_files: u64,
+    pub reflink_bytes: u64,
+    pub reflink_fallbacks: u64,
+
+    // NEW: TLS-слушатель CDC
+    pub tls_cert_reloads: u64,
+    pub tls_cert_reload_errors: u64,
+    pub tls_cert_expiry_warnings: u64,
+    pub tls_cert_not_after_unix: u64,
+
+    // NEW: клиентская TLS-identity follower'а
+    pub tls_client_cert_reloads: u64,
+    pub tls_client_cert_reload_errors: u64,
+    pub tls_client_cert_expiry_warnings: u64,
+    pub tls_client_cert_not_after_unix: u64,
+
+    // NEW: trash / undelete
+    pub trash_deletes: u64,
+    pub undeletes: u64,
+
+    // NEW: page cache storage policy
+    pub page_cache_mlock_rejects: u64,
+    pub page_cache_unseal_failures: u64,
+    /// live: байты под mlock (pager::cache::page_cache_locked_bytes)
+    pub page_cache_locked_bytes: u64,
+
+    // NEW: TDE per-KID routing
+    pub tde_epoch_key_verifies: u64,
 }
 
 impl MetricsSnapshot {
@@ -213,6 +665,15 @@ impl MetricsSnapshot {
             self.pack_records as f64 / self.pack_pages as f64
         }
     }
+
+    /// NEW: доля байтов HEADS_UPDATE, сэкономленная delta-кодированием (0.0 — нет данных).
+    pub fn heads_update_savings_ratio(&self) -> f64 {
+        if self.heads_update_bytes_legacy == 0 {
+            0.0
+        } else {
+            1.0 - self.heads_update_bytes as f64 / self.heads_update_bytes_legacy as f64
+        }
+    }
 }
 
 // ----- Recorders (WAL) -----
@@ -266,18 +727,18 @@ pub fn record_wal_threshold_flush(pages: u64, bytes: u64) {
 
 // ----- Recorders (Page cache) -----
 pub fn record_cache_hit() {
-    PAGE_CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+    PAGE_CACHE_HITS.add(1);
 }
 pub fn record_cache_miss() {
-    PAGE_CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+    PAGE_CACHE_MISSES.add(1);
 }
 
 // ----- Recorders (In‑memory keydir fast‑path) -----
 pub fn record_keydir_hit() {
-    KEYDIR_HITS.fetch_add(1, Ordering::Relaxed);
+    KEYDIR_HITS.add(1);
 }
 pub fn record_keydir_miss() {
-    KEYDIR_MISSES.fetch_add(1, Ordering::Relaxed);
+    KEYDIR_MISSES.add(1);
 }
 
 // ----- Recorders (Robin Hood) -----
@@ -333,17 +794,17 @@ pub fn record_snapshot_fallback_scan() {
 
 // NEW: TTL read-side skip
 pub fn record_ttl_skipped() {
-    TTL_SKIPPED.fetch_add(1, Ordering::Relaxed);
+    TTL_SKIPPED.add(1);
 }
 
 // NEW: Bloom fast-path recorders
 pub fn record_bloom_negative() {
-    BLOOM_TESTS.fetch_add(1, Ordering::Relaxed);
-    BLOOM_NEGATIVE.fetch_add(1, Ordering::Relaxed);
+    BLOOM_TESTS.add(1);
+    BLOOM_NEGATIVE.add(1);
 }
 pub fn record_bloom_positive() {
-    BLOOM_TESTS.fetch_add(1, Ordering::Relaxed);
-    BLOOM_POSITIVE.fetch_add(1, Ordering::Relaxed);
+    BLOOM_TESTS.add(1);
+    BLOOM_POSITIVE.add(1);
 }
 pub fn record_bloom_skipped_stale() {
     BLOOM_SKIPPED_STALE.fetch_add(1, Ordering::Relaxed);
@@ -355,6 +816,12 @@ pub fn record_bloom_update(bytes: u64) {
     BLOOM_UPDATE_BYTES.fetch_add(bytes, Ordering::Relaxed);
 }
 
+// NEW: Bloom auto-refresh recorder (число перестроенных бакетов)
+pub fn record_bloom_auto_refresh(buckets: u64) {
+    BLOOM_AUTO_REFRESHES.fetch_add(1, Ordering::Relaxed);
+    BLOOM_AUTO_REFRESH_BUCKETS.fetch_add(buckets, Ordering::Relaxed);
+}
+
 // ----- Recorders (Packing) -----
 pub fn record_pack_page(slots: u64) {
     PACK_PAGES.fetch_add(1, Ordering::Relaxed);
@@ -381,6 +848,424 @@ pub fn record_compaction_pages_packed(n: u64) {
     COMPACTION_PAGES_PACKED.fetch_add(n, Ordering::Relaxed);
 }
 
+// ----- Recorders (Scrub) -----
+pub fn record_scrub_pages(n: u64) {
+    SCRUB_PAGES_VERIFIED.fetch_add(n, Ordering::Relaxed);
+}
+pub fn record_scrub_failure() {
+    SCRUB_FAILURES.fetch_add(1, Ordering::Relaxed);
+}
+pub fn record_scrub_pass_completed() {
+    SCRUB_PASSES_COMPLETED.fetch_add(1, Ordering::Relaxed);
+}
+
+// ----- Recorders (Page cache prewarm) -----
+pub fn record_prewarm_run() {
+    PREWARM_RUNS.fetch_add(1, Ordering::Relaxed);
+}
+pub fn record_prewarm_pages_loaded(n: u64) {
+    PREWARM_PAGES_LOADED.fetch_add(n, Ordering::Relaxed);
+}
+/// Первый hit по странице, загруженной prewarm'ом.
+pub fn record_prewarm_hit() {
+    PREWARM_HITS.add(1);
+}
+
+// ----- Recorders (Readahead) -----
+pub fn record_readahead_fill(pages: u64) {
+    READAHEAD_FILLS.fetch_add(1, Ordering::Relaxed);
+    READAHEAD_PAGES_READ.fetch_add(pages, Ordering::Relaxed);
+}
+pub fn record_readahead_hit() {
+    READAHEAD_HITS.add(1);
+}
+
+// ----- Recorders (Commit pipelining) -----
+pub fn record_commit_pipelined() {
+    COMMIT_PIPELINED_BATCHES.fetch_add(1, Ordering::Relaxed);
+}
+
+// ----- Recorders (WAL recovery) -----
+pub fn record_recovery_replay() {
+    RECOVERY_REPLAYS.fetch_add(1, Ordering::Relaxed);
+}
+pub fn record_recovery_frames(n: u64) {
+    RECOVERY_FRAMES_APPLIED.fetch_add(n, Ordering::Relaxed);
+}
+pub fn record_recovery_skipped(bytes: u64) {
+    RECOVERY_BYTES_SKIPPED.fetch_add(bytes, Ordering::Relaxed);
+}
+
+// ----- Recorders (idempotent batches) -----
+pub fn record_idem_batch_skipped() {
+    IDEM_BATCHES_SKIPPED.fetch_add(1, Ordering::Relaxed);
+}
+pub fn record_idem_token_recorded() {
+    IDEM_TOKENS_RECORDED.fetch_add(1, Ordering::Relaxed);
+}
+
+// ----- Recorders (TTL expiry events) -----
+#[inline]
+pub fn record_expiry_events_emitted(n: u64) {
+    EXPIRY_EVENTS_EMITTED.fetch_add(n, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn record_expiry_frame_written() {
+    EXPIRY_FRAMES_WRITTEN.fetch_add(1, Ordering::Relaxed);
+}
+
+// ----- Recorders (HEADS_UPDATE) -----
+#[inline]
+pub fn record_heads_update_frame(entries: u64, bytes: u64, legacy_bytes: u64) {
+    HEADS_UPDATE_FRAMES.fetch_add(1, Ordering::Relaxed);
+    HEADS_UPDATE_ENTRIES.fetch_add(entries, Ordering::Relaxed);
+    HEADS_UPDATE_BYTES.fetch_add(bytes, Ordering::Relaxed);
+    HEADS_UPDATE_BYTES_LEGACY.fetch_add(legacy_bytes, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn record_heads_update_skipped(n: u64) {
+    HEADS_UPDATE_SKIPPED.fetch_add(n, Ordering::Relaxed);
+}
+
+// ----- Recorders (slow IO) -----
+#[inline]
+pub fn record_io_stall(ms: u64) {
+    IO_STALLS_TOTAL.fetch_add(1, Ordering::Relaxed);
+    IO_STALL_MS_TOTAL.fetch_add(ms, Ordering::Relaxed);
+    IO_STALL_MAX_MS.fetch_max(ms, Ordering::Relaxed);
+}
+
+// ----- Recorders (maintenance scheduler) -----
+pub fn record_maint_run() {
+    MAINT_RUNS.fetch_add(1, Ordering::Relaxed);
+}
+pub fn record_maint_snapshot_created() {
+    MAINT_SNAPSHOTS_CREATED.fetch_add(1, Ordering::Relaxed);
+}
+pub fn record_maint_snapshot_failed() {
+    MAINT_SNAPSHOTS_FAILED.fetch_add(1, Ordering::Relaxed);
+}
+pub fn record_maint_snapshots_pruned(n: u64) {
+    MAINT_SNAPSHOTS_PRUNED.fetch_add(n, Ordering::Relaxed);
+}
+
+// ----- Recorders (offsite backup scheduler) -----
+pub fn record_backup_sched_run() {
+    BACKUP_SCHED_RUNS.fetch_add(1, Ordering::Relaxed);
+}
+pub fn record_backup_sched_failure() {
+    BACKUP_SCHED_FAILURES.fetch_add(1, Ordering::Relaxed);
+}
+pub fn record_backup_sched_pruned(n: u64) {
+    BACKUP_SCHED_PRUNED.fetch_add(n, Ordering::Relaxed);
+}
+pub fn record_restore_test(ok: bool) {
+    if ok {
+        RESTORE_TESTS_OK.fetch_add(1, Ordering::Relaxed);
+    } else {
+        RESTORE_TESTS_FAILED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// ----- Recorders (freeze/thaw) -----
+pub fn record_db_freeze() {
+    DB_FREEZES.fetch_add(1, Ordering::Relaxed);
+}
+pub fn record_db_thaw(frozen_ms: u64) {
+    DB_FROZEN_MS_TOTAL.fetch_add(frozen_ms, Ordering::Relaxed);
+}
+pub fn record_commit_rejected_frozen() {
+    COMMITS_REJECTED_FROZEN.fetch_add(1, Ordering::Relaxed);
+}
+
+// ----- Recorders (read-only гейт) -----
+pub fn record_write_rejected_readonly() {
+    WRITES_REJECTED_READONLY.fetch_add(1, Ordering::Relaxed);
+}
+
+// ----- Recorders (рост сегментов) -----
+pub fn record_seg_grow(bytes: u64, stall_us: u64, fallocate_fallback: bool) {
+    SEG_GROW_OPS.fetch_add(1, Ordering::Relaxed);
+    SEG_GROW_BYTES.fetch_add(bytes, Ordering::Relaxed);
+    SEG_GROW_STALL_US_TOTAL.fetch_add(stall_us, Ordering::Relaxed);
+    SEG_GROW_STALL_MAX_US.fetch_max(stall_us, Ordering::Relaxed);
+    if fallocate_fallback {
+        SEG_FALLOCATE_FALLBACKS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// ----- Recorders (punch holes) -----
+pub fn record_punch_hole(bytes: u64) {
+    PUNCH_HOLE_OPS.fetch_add(1, Ordering::Relaxed);
+    PUNCH_HOLE_BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+pub fn record_punch_hole_unsupported() {
+    PUNCH_HOLE_UNSUPPORTED.fetch_add(1, Ordering::Relaxed);
+}
+
+// ----- Recorders (writable-ветки) -----
+pub fn record_branch_page_fetch() {
+    BRANCH_PAGE_FETCHES.fetch_add(1, Ordering::Relaxed);
+}
+
+// ----- Recorders (история ключей) -----
+pub fn record_history_index(entries: u64) {
+    HISTORY_INDEX_RUNS.fetch_add(1, Ordering::Relaxed);
+    HISTORY_ENTRIES_INDEXED.fetch_add(entries, Ordering::Relaxed);
+}
+
+// ----- Recorders (ResourceBudget) -----
+pub fn record_budget_denial() {
+    RESOURCE_BUDGET_DENIALS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_budget_fd_denial() {
+    RESOURCE_BUDGET_FD_DENIALS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_budget_degradation() {
+    RESOURCE_BUDGET_DEGRADATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+// ----- Recorders (CDC verify) -----
+/// Завершённая сверка: divergent — бакетов с расхождением.
+pub fn record_cdc_verify(divergent: u64) {
+    CDC_VERIFY_CHECKS.fetch_add(1, Ordering::Relaxed);
+    CDC_VERIFY_DIVERGENT_BUCKETS.fetch_add(divergent, Ordering::Relaxed);
+}
+
+/// Сверка отложена: источник ушёл дальше запрошенного LSN или digest недоступен.
+pub fn record_cdc_verify_deferred() {
+    CDC_VERIFY_DEFERRED.fetch_add(1, Ordering::Relaxed);
+}
+
+// ----- Recorders (CDC resync) -----
+/// Бакет заменён состоянием источника (pages — записано страниц).
+pub fn record_cdc_resync(pages: u64) {
+    CDC_RESYNC_BUCKETS.fetch_add(1, Ordering::Relaxed);
+    CDC_RESYNC_PAGES.fetch_add(pages, Ordering::Relaxed);
+}
+
+/// Resync не удался: состояние недоступно/неполно или digest после замены не совпал.
+pub fn record_cdc_resync_failed() {
+    CDC_RESYNC_FAILED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Resync отложен: источник ушёл дальше запрошенного LSN.
+pub fn record_cdc_resync_deferred() {
+    CDC_RESYNC_DEFERRED.fetch_add(1, Ordering::Relaxed);
+}
+
+// ----- Recorders (AutoBatcher) -----
+/// Очередь AutoBatcher сброшена одним батчем (ops — операций в нём).
+pub fn record_auto_batch_flush(ops: u64) {
+    AUTO_BATCH_FLUSHES.fetch_add(1, Ordering::Relaxed);
+    AUTO_BATCH_OPS.fetch_add(ops, Ordering::Relaxed);
+}
+
+/// Сброс очереди при drop AutoBatcher завершился ошибкой.
+pub fn record_auto_batch_drop_error() {
+    AUTO_BATCH_DROP_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+// ----- Recorders (adaptive WAL coalescing) -----
+/// Решение адаптивного окна group-commit (window_us / p99_us — состояние после решения).
+pub fn record_wal_coalesce_decision(
+    decision: crate::wal::CoalesceDecision,
+    window_us: u64,
+    p99_us: u64,
+) {
+    use crate::wal::CoalesceDecision;
+    WAL_COALESCE_WINDOW_US.store(window_us, Ordering::Relaxed);
+    WAL_COALESCE_P99_US.store(p99_us, Ordering::Relaxed);
+    match decision {
+        CoalesceDecision::Grow => WAL_COALESCE_GROW.fetch_add(1, Ordering::Relaxed),
+        CoalesceDecision::Shrink => WAL_COALESCE_SHRINK.fetch_add(1, Ordering::Relaxed),
+        CoalesceDecision::Hold => WAL_COALESCE_HOLD.fetch_add(1, Ordering::Relaxed),
+    };
+}
+
+// ----- Recorders (panic-safety) -----
+pub fn record_db_poisoned() {
+    DB_POISONED.fetch_add(1, Ordering::Relaxed);
+}
+pub fn record_write_rejected_poisoned() {
+    WRITES_REJECTED_POISONED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: страниц с двойным использованием, найденных проверкой аллокатора.
+pub fn record_alloc_double_use(pages: u64) {
+    ALLOC_DOUBLE_USE_PAGES.fetch_add(pages, Ordering::Relaxed);
+}
+
+/// NEW: free-лист пересобран из достижимости.
+pub fn record_alloc_freelist_rebuild() {
+    ALLOC_FREELIST_REBUILDS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: батч сбросил сегменты данных: calls системных вызовов, syncfs — одним syncfs(2).
+pub fn record_seg_fsync_batch(calls: u64, syncfs: bool) {
+    SEG_FSYNC_BATCHES.fetch_add(1, Ordering::Relaxed);
+    SEG_FSYNC_CALLS.fetch_add(calls, Ordering::Relaxed);
+    if syncfs {
+        SEG_SYNCFS_CALLS.fetch_add(1, Ordering::Relaxed);
+    }
+    SEG_FSYNC_LAST_BATCH.store(calls, Ordering::Relaxed);
+}
+
+/// NEW: префиксный скан обошёл один бакет (маршрутизация по префиксу).
+pub fn record_scan_prefix_single_bucket() {
+    SCAN_PREFIX_SINGLE_BUCKET.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: вызов Db::scan_page (одна страница результатов).
+pub fn record_scan_page_call() {
+    SCAN_PAGE_CALLS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: курсор scan_page отвергнут (испорчен или цепочка переписана).
+pub fn record_scan_cursor_invalid() {
+    SCAN_CURSOR_INVALID.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: события, выданные in-process CDC-потоком (cdc::subscribe).
+pub fn record_cdc_subscribe_events(n: u64) {
+    if n > 0 {
+        CDC_SUBSCRIBE_EVENTS.fetch_add(n, Ordering::Relaxed);
+    }
+}
+
+/// NEW: CDC-поток обнаружил пропуск LSN.
+pub fn record_cdc_subscribe_gap() {
+    CDC_SUBSCRIBE_GAPS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: область метаданных приложения переписана (meta_put/meta_del/meta_update).
+pub fn record_user_meta_write() {
+    USER_META_WRITES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: ключ TDE отсутствует или не прошёл проверочный блок meta.
+pub fn record_tde_key_check_failure() {
+    TDE_KEY_CHECK_FAILURES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: страница подписана KID, чей ключ не загружен (заголовок страницы, OFF_KEY_FP).
+pub fn record_tde_page_kid_missing() {
+    TDE_PAGE_KID_MISSING.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: bloom.bin доведён до свежего по бакетам из реплея WAL.
+pub fn record_bloom_replay_reconciled() {
+    BLOOM_REPLAY_RECONCILED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: bloom.bin с last_lsn впереди БД сброшен в устаревший.
+pub fn record_bloom_replay_invalidated() {
+    BLOOM_REPLAY_INVALIDATED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: sweep_orphan_overflow / alloc_check_fix оставили n страниц закреплённых цепочек.
+pub fn record_maint_pinned_pages_kept(n: u64) {
+    if n > 0 {
+        MAINT_PINNED_PAGES_KEPT.fetch_add(n, Ordering::Relaxed);
+    }
+}
+
+/// NEW: get ответил по локации из hot-get кэша.
+pub fn record_hot_get_hit() {
+    HOT_GET_HITS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: get при включённом hot-get кэше прошёл мимо него.
+pub fn record_hot_get_miss() {
+    HOT_GET_MISSES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: из hot-get кэша сняты n локаций.
+pub fn record_hot_get_invalidated(n: u64) {
+    if n > 0 {
+        HOT_GET_INVALIDATIONS.fetch_add(n, Ordering::Relaxed);
+    }
+}
+
+/// NEW: файл или диапазон страниц (bytes байт) склонирован reflink'ом.
+pub fn record_reflink(bytes: u64) {
+    REFLINK_FILES.fetch_add(1, Ordering::Relaxed);
+    REFLINK_BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// NEW: reflink не удался — копирование байтов.
+pub fn record_reflink_fallback() {
+    REFLINK_FALLBACKS.fetch_add(1, Ordering::Relaxed);
+}
+
+// ----- Recorders (TLS listener) -----
+/// NEW: сертификат TLS-слушателя перезагружен после изменения файлов.
+pub fn record_tls_cert_reload() {
+    TLS_CERT_RELOADS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: изменённые файлы сертификата не загрузились (остался прежний).
+pub fn record_tls_cert_reload_error() {
+    TLS_CERT_RELOAD_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: предупреждение об истечении сертификата TLS-слушателя.
+pub fn record_tls_cert_expiry_warning() {
+    TLS_CERT_EXPIRY_WARNINGS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: notAfter текущего сертификата TLS-слушателя (gauge, unix-секунды).
+pub fn set_tls_cert_not_after(unix: i64) {
+    TLS_CERT_NOT_AFTER_UNIX.store(unix.max(0) as u64, Ordering::Relaxed);
+}
+
+// ----- Recorders (TLS client identity) -----
+/// NEW: клиентская identity (PFX) / CA перезагружены после изменения файлов.
+pub fn record_tls_client_cert_reload() {
+    TLS_CLIENT_CERT_RELOADS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: изменённые PFX / CA не загрузились (остался прежний connector).
+pub fn record_tls_client_cert_reload_error() {
+    TLS_CLIENT_CERT_RELOAD_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: предупреждение об истечении клиентского сертификата.
+pub fn record_tls_client_cert_expiry_warning() {
+    TLS_CLIENT_CERT_EXPIRY_WARNINGS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// NEW: notAfter текущего клиентского сертификата (gauge, unix-секунды; 0 — нет identity).
+pub fn set_tls_client_cert_not_after(unix: i64) {
+    TLS_CLIENT_CERT_NOT_AFTER_UNIX.store(unix.max(0) as u64, Ordering::Relaxed);
+}
+
+// ----- Recorders (trash / undelete) -----
+pub fn record_trash_deletes(n: u64) {
+    TRASH_DELETES.fetch_add(n, Ordering::Relaxed);
+}
+pub fn record_undelete() {
+    UNDELETES.fetch_add(1, Ordering::Relaxed);
+}
+
+// ----- Recorders (page cache storage policy) -----
+pub fn record_page_cache_mlock_reject() {
+    PAGE_CACHE_MLOCK_REJECTS.fetch_add(1, Ordering::Relaxed);
+}
+pub fn record_page_cache_unseal_failure() {
+    PAGE_CACHE_UNSEAL_FAILURES.fetch_add(1, Ordering::Relaxed);
+}
+
+// ----- NEW: TDE per-KID routing -----
+
+pub fn record_tde_epoch_key_verify() {
+    TDE_EPOCH_KEY_VERIFIES.fetch_add(1, Ordering::Relaxed);
+}
+
 // ----- Snapshot / Reset -----
 pub fn snapshot() -> MetricsSnapshot {
     // live‑показатели из внешних модулей
@@ -403,15 +1288,15 @@ pub fn snapshot() -> MetricsSnapshot {
         wal_threshold_flush_pages: WAL_THRESHOLD_FLUSH_PAGES.load(Ordering::Relaxed),
         wal_threshold_flush_bytes: WAL_THRESHOLD_FLUSH_BYTES.load(Ordering::Relaxed),
 
-        page_cache_hits: PAGE_CACHE_HITS.load(Ordering::Relaxed),
-        page_cache_misses: PAGE_CACHE_MISSES.load(Ordering::Relaxed),
+        page_cache_hits: PAGE_CACHE_HITS.sum(),
+        page_cache_misses: PAGE_CACHE_MISSES.sum(),
 
         // NEW: explicit invalidations (берём live из кэша)
         page_cache_invalidations_total: crate::pager::cache::page_cache_invalidations_total(),
 
         // NEW
-        keydir_hits: KEYDIR_HITS.load(Ordering::Relaxed),
-        keydir_misses: KEYDIR_MISSES.load(Ordering::Relaxed),
+        keydir_hits: KEYDIR_HITS.sum(),
+        keydir_misses: KEYDIR_MISSES.sum(),
 
         rh_page_compactions: RH_PAGE_COMPACTIONS.load(Ordering::Relaxed),
 
@@ -430,16 +1315,18 @@ pub fn snapshot() -> MetricsSnapshot {
 
         snapshot_fallback_scans: SNAPSHOT_FALLBACK_SCANS.load(Ordering::Relaxed),
 
-        ttl_skipped: TTL_SKIPPED.load(Ordering::Relaxed),
+        ttl_skipped: TTL_SKIPPED.sum(),
 
-        bloom_tests: BLOOM_TESTS.load(Ordering::Relaxed),
-        bloom_negative: BLOOM_NEGATIVE.load(Ordering::Relaxed),
-        bloom_positive: BLOOM_POSITIVE.load(Ordering::Relaxed),
+        bloom_tests: BLOOM_TESTS.sum(),
+        bloom_negative: BLOOM_NEGATIVE.sum(),
+        bloom_positive: BLOOM_POSITIVE.sum(),
         bloom_skipped_stale: BLOOM_SKIPPED_STALE.load(Ordering::Relaxed),
 
         // NEW
         bloom_updates_total: BLOOM_UPDATES_TOTAL.load(Ordering::Relaxed),
         bloom_update_bytes: BLOOM_UPDATE_BYTES.load(Ordering::Relaxed),
+        bloom_auto_refreshes: BLOOM_AUTO_REFRESHES.load(Ordering::Relaxed),
+        bloom_auto_refresh_buckets: BLOOM_AUTO_REFRESH_BUCKETS.load(Ordering::Relaxed),
 
         // NEW: packing
         pack_pages: PACK_PAGES.load(Ordering::Relaxed),
@@ -461,6 +1348,136 @@ pub fn snapshot() -> MetricsSnapshot {
         value_cache_entries: vc_entries as u64,
         value_cache_hits: vc_hits,
         value_cache_misses: vc_misses,
+
+        // NEW: scrub
+        scrub_pages_verified: SCRUB_PAGES_VERIFIED.load(Ordering::Relaxed),
+        scrub_failures: SCRUB_FAILURES.load(Ordering::Relaxed),
+        scrub_passes_completed: SCRUB_PASSES_COMPLETED.load(Ordering::Relaxed),
+
+        // NEW: prewarm
+        prewarm_runs: PREWARM_RUNS.load(Ordering::Relaxed),
+        prewarm_pages_loaded: PREWARM_PAGES_LOADED.load(Ordering::Relaxed),
+        prewarm_hits: PREWARM_HITS.sum(),
+
+        // NEW: readahead
+        readahead_fills: READAHEAD_FILLS.load(Ordering::Relaxed),
+        readahead_pages_read: READAHEAD_PAGES_READ.load(Ordering::Relaxed),
+        readahead_hits: READAHEAD_HITS.sum(),
+
+        // NEW: commit pipelining
+        commit_pipelined_batches: COMMIT_PIPELINED_BATCHES.load(Ordering::Relaxed),
+
+        // NEW: recovery
+        recovery_replays: RECOVERY_REPLAYS.load(Ordering::Relaxed),
+        recovery_frames_applied: RECOVERY_FRAMES_APPLIED.load(Ordering::Relaxed),
+        recovery_bytes_skipped: RECOVERY_BYTES_SKIPPED.load(Ordering::Relaxed),
+
+        // NEW: idempotency
+        idem_batches_skipped: IDEM_BATCHES_SKIPPED.load(Ordering::Relaxed),
+        idem_tokens_recorded: IDEM_TOKENS_RECORDED.load(Ordering::Relaxed),
+
+        // NEW: expiry
+        expiry_events_emitted: EXPIRY_EVENTS_EMITTED.load(Ordering::Relaxed),
+        expiry_frames_written: EXPIRY_FRAMES_WRITTEN.load(Ordering::Relaxed),
+
+        // NEW: HEADS_UPDATE
+        heads_update_frames: HEADS_UPDATE_FRAMES.load(Ordering::Relaxed),
+        heads_update_entries: HEADS_UPDATE_ENTRIES.load(Ordering::Relaxed),
+        heads_update_skipped: HEADS_UPDATE_SKIPPED.load(Ordering::Relaxed),
+        heads_update_bytes: HEADS_UPDATE_BYTES.load(Ordering::Relaxed),
+        heads_update_bytes_legacy: HEADS_UPDATE_BYTES_LEGACY.load(Ordering::Relaxed),
+
+        // NEW: slow IO
+        io_stalls_total: IO_STALLS_TOTAL.load(Ordering::Relaxed),
+        io_stall_ms_total: IO_STALL_MS_TOTAL.load(Ordering::Relaxed),
+        io_stall_max_ms: IO_STALL_MAX_MS.load(Ordering::Relaxed),
+
+        // NEW: maintenance scheduler
+        maint_runs: MAINT_RUNS.load(Ordering::Relaxed),
+        maint_snapshots_created: MAINT_SNAPSHOTS_CREATED.load(Ordering::Relaxed),
+        maint_snapshots_failed: MAINT_SNAPSHOTS_FAILED.load(Ordering::Relaxed),
+        maint_snapshots_pruned: MAINT_SNAPSHOTS_PRUNED.load(Ordering::Relaxed),
+
+        // NEW: offsite backup scheduler
+        backup_sched_runs: BACKUP_SCHED_RUNS.load(Ordering::Relaxed),
+        backup_sched_failures: BACKUP_SCHED_FAILURES.load(Ordering::Relaxed),
+        backup_sched_pruned: BACKUP_SCHED_PRUNED.load(Ordering::Relaxed),
+        restore_tests_ok: RESTORE_TESTS_OK.load(Ordering::Relaxed),
+        restore_tests_failed: RESTORE_TESTS_FAILED.load(Ordering::Relaxed),
+        db_freezes: DB_FREEZES.load(Ordering::Relaxed),
+        db_frozen_ms_total: DB_FROZEN_MS_TOTAL.load(Ordering::Relaxed),
+        commits_rejected_frozen: COMMITS_REJECTED_FROZEN.load(Ordering::Relaxed),
+        writes_rejected_readonly: WRITES_REJECTED_READONLY.load(Ordering::Relaxed),
+        seg_grow_ops: SEG_GROW_OPS.load(Ordering::Relaxed),
+        seg_grow_bytes: SEG_GROW_BYTES.load(Ordering::Relaxed),
+        seg_grow_stall_us_total: SEG_GROW_STALL_US_TOTAL.load(Ordering::Relaxed),
+        seg_grow_stall_max_us: SEG_GROW_STALL_MAX_US.load(Ordering::Relaxed),
+        seg_fallocate_fallbacks: SEG_FALLOCATE_FALLBACKS.load(Ordering::Relaxed),
+        punch_hole_ops: PUNCH_HOLE_OPS.load(Ordering::Relaxed),
+        punch_hole_bytes: PUNCH_HOLE_BYTES.load(Ordering::Relaxed),
+        punch_hole_unsupported: PUNCH_HOLE_UNSUPPORTED.load(Ordering::Relaxed),
+        branch_page_fetches: BRANCH_PAGE_FETCHES.load(Ordering::Relaxed),
+        history_index_runs: HISTORY_INDEX_RUNS.load(Ordering::Relaxed),
+        history_entries_indexed: HISTORY_ENTRIES_INDEXED.load(Ordering::Relaxed),
+        resource_budget_denials: RESOURCE_BUDGET_DENIALS.load(Ordering::Relaxed),
+        resource_budget_fd_denials: RESOURCE_BUDGET_FD_DENIALS.load(Ordering::Relaxed),
+        resource_budget_degradations: RESOURCE_BUDGET_DEGRADATIONS.load(Ordering::Relaxed),
+        budget_memory_bytes: crate::budget::resource_usage().memory_bytes,
+        budget_open_fds: crate::budget::resource_usage().open_fds,
+        cdc_verify_checks: CDC_VERIFY_CHECKS.load(Ordering::Relaxed),
+        cdc_verify_divergent_buckets: CDC_VERIFY_DIVERGENT_BUCKETS.load(Ordering::Relaxed),
+        cdc_verify_deferred: CDC_VERIFY_DEFERRED.load(Ordering::Relaxed),
+        cdc_resync_buckets: CDC_RESYNC_BUCKETS.load(Ordering::Relaxed),
+        cdc_resync_pages: CDC_RESYNC_PAGES.load(Ordering::Relaxed),
+        cdc_resync_failed: CDC_RESYNC_FAILED.load(Ordering::Relaxed),
+        cdc_resync_deferred: CDC_RESYNC_DEFERRED.load(Ordering::Relaxed),
+        auto_batch_flushes: AUTO_BATCH_FLUSHES.load(Ordering::Relaxed),
+        auto_batch_ops: AUTO_BATCH_OPS.load(Ordering::Relaxed),
+        auto_batch_drop_errors: AUTO_BATCH_DROP_ERRORS.load(Ordering::Relaxed),
+        wal_coalesce_window_us: WAL_COALESCE_WINDOW_US.load(Ordering::Relaxed),
+        wal_coalesce_p99_us: WAL_COALESCE_P99_US.load(Ordering::Relaxed),
+        wal_coalesce_grow: WAL_COALESCE_GROW.load(Ordering::Relaxed),
+        wal_coalesce_shrink: WAL_COALESCE_SHRINK.load(Ordering::Relaxed),
+        wal_coalesce_hold: WAL_COALESCE_HOLD.load(Ordering::Relaxed),
+        db_poisoned: DB_POISONED.load(Ordering::Relaxed),
+        writes_rejected_poisoned: WRITES_REJECTED_POISONED.load(Ordering::Relaxed),
+        alloc_double_use_pages: ALLOC_DOUBLE_USE_PAGES.load(Ordering::Relaxed),
+        alloc_freelist_rebuilds: ALLOC_FREELIST_REBUILDS.load(Ordering::Relaxed),
+        seg_fsync_batches: SEG_FSYNC_BATCHES.load(Ordering::Relaxed),
+        seg_fsync_calls: SEG_FSYNC_CALLS.load(Ordering::Relaxed),
+        seg_syncfs_calls: SEG_SYNCFS_CALLS.load(Ordering::Relaxed),
+        seg_fsync_last_batch: SEG_FSYNC_LAST_BATCH.load(Ordering::Relaxed),
+        scan_prefix_single_bucket: SCAN_PREFIX_SINGLE_BUCKET.load(Ordering::Relaxed),
+        scan_page_calls: SCAN_PAGE_CALLS.load(Ordering::Relaxed),
+        scan_cursor_invalid: SCAN_CURSOR_INVALID.load(Ordering::Relaxed),
+        cdc_subscribe_events: CDC_SUBSCRIBE_EVENTS.load(Ordering::Relaxed),
+        cdc_subscribe_gaps: CDC_SUBSCRIBE_GAPS.load(Ordering::Relaxed),
+        user_meta_writes: USER_META_WRITES.load(Ordering::Relaxed),
+        tde_key_check_failures: TDE_KEY_CHECK_FAILURES.load(Ordering::Relaxed),
+        tde_page_kid_missing: TDE_PAGE_KID_MISSING.load(Ordering::Relaxed),
+        bloom_replay_reconciled: BLOOM_REPLAY_RECONCILED.load(Ordering::Relaxed),
+        bloom_replay_invalidated: BLOOM_REPLAY_INVALIDATED.load(Ordering::Relaxed),
+        maint_pinned_pages_kept: MAINT_PINNED_PAGES_KEPT.load(Ordering::Relaxed),
+        hot_get_hits: HOT_GET_HITS.load(Ordering::Relaxed),
+        hot_get_misses: HOT_GET_MISSES.load(Ordering::Relaxed),
+        hot_get_invalidations: HOT_GET_INVALIDATIONS.load(Ordering::Relaxed),
+        reflink_files: REFLINK_FILES.load(Ordering::Relaxed),
+        reflink_bytes: REFLINK_BYTES.load(Ordering::Relaxed),
+        reflink_fallbacks: REFLINK_FALLBACKS.load(Ordering::Relaxed),
+        tls_cert_reloads: TLS_CERT_RELOADS.load(Ordering::Relaxed),
+        tls_cert_reload_errors: TLS_CERT_RELOAD_ERRORS.load(Ordering::Relaxed),
+        tls_cert_expiry_warnings: TLS_CERT_EXPIRY_WARNINGS.load(Ordering::Relaxed),
+        tls_cert_not_after_unix: TLS_CERT_NOT_AFTER_UNIX.load(Ordering::Relaxed),
+        tls_client_cert_reloads: TLS_CLIENT_CERT_RELOADS.load(Ordering::Relaxed),
+        tls_client_cert_reload_errors: TLS_CLIENT_CERT_RELOAD_ERRORS.load(Ordering::Relaxed),
+        tls_client_cert_expiry_warnings: TLS_CLIENT_CERT_EXPIRY_WARNINGS.load(Ordering::Relaxed),
+        tls_client_cert_not_after_unix: TLS_CLIENT_CERT_NOT_AFTER_UNIX.load(Ordering::Relaxed),
+        trash_deletes: TRASH_DELETES.load(Ordering::Relaxed),
+        undeletes: UNDELETES.load(Ordering::Relaxed),
+        page_cache_mlock_rejects: PAGE_CACHE_MLOCK_REJECTS.load(Ordering::Relaxed),
+        page_cache_unseal_failures: PAGE_CACHE_UNSEAL_FAILURES.load(Ordering::Relaxed),
+        page_cache_locked_bytes: crate::pager::cache::page_cache_locked_bytes(),
+        tde_epoch_key_verifies: TDE_EPOCH_KEY_VERIFIES.load(Ordering::Relaxed),
     }
 }
 
@@ -480,12 +1497,12 @@ pub fn reset() {
     WAL_THRESHOLD_FLUSH_PAGES.store(0, Ordering::Relaxed);
     WAL_THRESHOLD_FLUSH_BYTES.store(0, Ordering::Relaxed);
 
-    PAGE_CACHE_HITS.store(0, Ordering::Relaxed);
-    PAGE_CACHE_MISSES.store(0, Ordering::Relaxed);
+    PAGE_CACHE_HITS.reset();
+    PAGE_CACHE_MISSES.reset();
 
     // NEW: keydir fast-path
-    KEYDIR_HITS.store(0, Ordering::Relaxed);
-    KEYDIR_MISSES.store(0, Ordering::Relaxed);
+    KEYDIR_HITS.reset();
+    KEYDIR_MISSES.reset();
 
     RH_PAGE_COMPACTIONS.store(0, Ordering::Relaxed);
 
@@ -504,16 +1521,18 @@ pub fn reset() {
 
     SNAPSHOT_FALLBACK_SCANS.store(0, Ordering::Relaxed);
 
-    TTL_SKIPPED.store(0, Ordering::Relaxed);
+    TTL_SKIPPED.reset();
 
-    BLOOM_TESTS.store(0, Ordering::Relaxed);
-    BLOOM_NEGATIVE.store(0, Ordering::Relaxed);
-    BLOOM_POSITIVE.store(0, Ordering::Relaxed);
+    BLOOM_TESTS.reset();
+    BLOOM_NEGATIVE.reset();
+    BLOOM_POSITIVE.reset();
     BLOOM_SKIPPED_STALE.store(0, Ordering::Relaxed);
 
     // NEW: bloom delta-update
     BLOOM_UPDATES_TOTAL.store(0, Ordering::Relaxed);
     BLOOM_UPDATE_BYTES.store(0, Ordering::Relaxed);
+    BLOOM_AUTO_REFRESHES.store(0, Ordering::Relaxed);
+    BLOOM_AUTO_REFRESH_BUCKETS.store(0, Ordering::Relaxed);
 
     // NEW: packing
     PACK_PAGES.store(0, Ordering::Relaxed);
@@ -529,6 +1548,131 @@ pub fn reset() {
     COMPACTION_KEYS_DELETED.store(0, Ordering::Relaxed);
     COMPACTION_PAGES_PACKED.store(0, Ordering::Relaxed);
 
+    // NEW: scrub
+    SCRUB_PAGES_VERIFIED.store(0, Ordering::Relaxed);
+    SCRUB_FAILURES.store(0, Ordering::Relaxed);
+    SCRUB_PASSES_COMPLETED.store(0, Ordering::Relaxed);
+
+    // NEW: prewarm
+    PREWARM_RUNS.store(0, Ordering::Relaxed);
+    PREWARM_PAGES_LOADED.store(0, Ordering::Relaxed);
+    PREWARM_HITS.reset();
+
+    // NEW: readahead
+    READAHEAD_FILLS.store(0, Ordering::Relaxed);
+    READAHEAD_PAGES_READ.store(0, Ordering::Relaxed);
+    READAHEAD_HITS.reset();
+
+    // NEW: commit pipelining
+    COMMIT_PIPELINED_BATCHES.store(0, Ordering::Relaxed);
+
+    // NEW: recovery
+    RECOVERY_REPLAYS.store(0, Ordering::Relaxed);
+    RECOVERY_FRAMES_APPLIED.store(0, Ordering::Relaxed);
+    RECOVERY_BYTES_SKIPPED.store(0, Ordering::Relaxed);
+
+    // NEW: idempotency
+    IDEM_BATCHES_SKIPPED.store(0, Ordering::Relaxed);
+    IDEM_TOKENS_RECORDED.store(0, Ordering::Relaxed);
+
+    // NEW: expiry
+    EXPIRY_EVENTS_EMITTED.store(0, Ordering::Relaxed);
+    EXPIRY_FRAMES_WRITTEN.store(0, Ordering::Relaxed);
+    HEADS_UPDATE_FRAMES.store(0, Ordering::Relaxed);
+    HEADS_UPDATE_ENTRIES.store(0, Ordering::Relaxed);
+    HEADS_UPDATE_SKIPPED.store(0, Ordering::Relaxed);
+    HEADS_UPDATE_BYTES.store(0, Ordering::Relaxed);
+    HEADS_UPDATE_BYTES_LEGACY.store(0, Ordering::Relaxed);
+
+    // NEW: slow IO
+    IO_STALLS_TOTAL.store(0, Ordering::Relaxed);
+    IO_STALL_MS_TOTAL.store(0, Ordering::Relaxed);
+    IO_STALL_MAX_MS.store(0, Ordering::Relaxed);
+
+    // NEW: maintenance scheduler
+    MAINT_RUNS.store(0, Ordering::Relaxed);
+    MAINT_SNAPSHOTS_CREATED.store(0, Ordering::Relaxed);
+    MAINT_SNAPSHOTS_FAILED.store(0, Ordering::Relaxed);
+    MAINT_SNAPSHOTS_PRUNED.store(0, Ordering::Relaxed);
+
+    // NEW: offsite backup scheduler
+    BACKUP_SCHED_RUNS.store(0, Ordering::Relaxed);
+    BACKUP_SCHED_FAILURES.store(0, Ordering::Relaxed);
+    BACKUP_SCHED_PRUNED.store(0, Ordering::Relaxed);
+    RESTORE_TESTS_OK.store(0, Ordering::Relaxed);
+    RESTORE_TESTS_FAILED.store(0, Ordering::Relaxed);
+    DB_FREEZES.store(0, Ordering::Relaxed);
+    DB_FROZEN_MS_TOTAL.store(0, Ordering::Relaxed);
+    COMMITS_REJECTED_FROZEN.store(0, Ordering::Relaxed);
+    WRITES_REJECTED_READONLY.store(0, Ordering::Relaxed);
+    SEG_GROW_OPS.store(0, Ordering::Relaxed);
+    SEG_GROW_BYTES.store(0, Ordering::Relaxed);
+    SEG_GROW_STALL_US_TOTAL.store(0, Ordering::Relaxed);
+    SEG_GROW_STALL_MAX_US.store(0, Ordering::Relaxed);
+    SEG_FALLOCATE_FALLBACKS.store(0, Ordering::Relaxed);
+    PUNCH_HOLE_OPS.store(0, Ordering::Relaxed);
+    PUNCH_HOLE_BYTES.store(0, Ordering::Relaxed);
+    PUNCH_HOLE_UNSUPPORTED.store(0, Ordering::Relaxed);
+    BRANCH_PAGE_FETCHES.store(0, Ordering::Relaxed);
+    HISTORY_INDEX_RUNS.store(0, Ordering::Relaxed);
+    HISTORY_ENTRIES_INDEXED.store(0, Ordering::Relaxed);
+    RESOURCE_BUDGET_DENIALS.store(0, Ordering::Relaxed);
+    RESOURCE_BUDGET_FD_DENIALS.store(0, Ordering::Relaxed);
+    RESOURCE_BUDGET_DEGRADATIONS.store(0, Ordering::Relaxed);
+    CDC_VERIFY_CHECKS.store(0, Ordering::Relaxed);
+    CDC_VERIFY_DIVERGENT_BUCKETS.store(0, Ordering::Relaxed);
+    CDC_VERIFY_DEFERRED.store(0, Ordering::Relaxed);
+    CDC_RESYNC_BUCKETS.store(0, Ordering::Relaxed);
+    CDC_RESYNC_PAGES.store(0, Ordering::Relaxed);
+    CDC_RESYNC_FAILED.store(0, Ordering::Relaxed);
+    CDC_RESYNC_DEFERRED.store(0, Ordering::Relaxed);
+    AUTO_BATCH_FLUSHES.store(0, Ordering::Relaxed);
+    AUTO_BATCH_OPS.store(0, Ordering::Relaxed);
+    AUTO_BATCH_DROP_ERRORS.store(0, Ordering::Relaxed);
+    WAL_COALESCE_WINDOW_US.store(0, Ordering::Relaxed);
+    WAL_COALESCE_P99_US.store(0, Ordering::Relaxed);
+    WAL_COALESCE_GROW.store(0, Ordering::Relaxed);
+    WAL_COALESCE_SHRINK.store(0, Ordering::Relaxed);
+    WAL_COALESCE_HOLD.store(0, Ordering::Relaxed);
+    DB_POISONED.store(0, Ordering::Relaxed);
+    WRITES_REJECTED_POISONED.store(0, Ordering::Relaxed);
+    ALLOC_DOUBLE_USE_PAGES.store(0, Ordering::Relaxed);
+    ALLOC_FREELIST_REBUILDS.store(0, Ordering::Relaxed);
+    SEG_FSYNC_BATCHES.store(0, Ordering::Relaxed);
+    SEG_FSYNC_CALLS.store(0, Ordering::Relaxed);
+    SEG_SYNCFS_CALLS.store(0, Ordering::Relaxed);
+    SEG_FSYNC_LAST_BATCH.store(0, Ordering::Relaxed);
+    SCAN_PREFIX_SINGLE_BUCKET.store(0, Ordering::Relaxed);
+    SCAN_PAGE_CALLS.store(0, Ordering::Relaxed);
+    SCAN_CURSOR_INVALID.store(0, Ordering::Relaxed);
+    CDC_SUBSCRIBE_EVENTS.store(0, Ordering::Relaxed);
+    CDC_SUBSCRIBE_GAPS.store(0, Ordering::Relaxed);
+    USER_META_WRITES.store(0, Ordering::Relaxed);
+    TDE_KEY_CHECK_FAILURES.store(0, Ordering::Relaxed);
+    TDE_PAGE_KID_MISSING.store(0, Ordering::Relaxed);
+    BLOOM_REPLAY_RECONCILED.store(0, Ordering::Relaxed);
+    BLOOM_REPLAY_INVALIDATED.store(0, Ordering::Relaxed);
+    MAINT_PINNED_PAGES_KEPT.store(0, Ordering::Relaxed);
+    HOT_GET_HITS.store(0, Ordering::Relaxed);
+    HOT_GET_MISSES.store(0, Ordering::Relaxed);
+    HOT_GET_INVALIDATIONS.store(0, Ordering::Relaxed);
+    REFLINK_FILES.store(0, Ordering::Relaxed);
+    REFLINK_BYTES.store(0, Ordering::Relaxed);
+    REFLINK_FALLBACKS.store(0, Ordering::Relaxed);
+    TLS_CERT_RELOADS.store(0, Ordering::Relaxed);
+    TLS_CERT_RELOAD_ERRORS.store(0, Ordering::Relaxed);
+    TLS_CERT_EXPIRY_WARNINGS.store(0, Ordering::Relaxed);
+    TLS_CERT_NOT_AFTER_UNIX.store(0, Ordering::Relaxed);
+    TLS_CLIENT_CERT_RELOADS.store(0, Ordering::Relaxed);
+    TLS_CLIENT_CERT_RELOAD_ERRORS.store(0, Ordering::Relaxed);
+    TLS_CLIENT_CERT_EXPIRY_WARNINGS.store(0, Ordering::Relaxed);
+    TLS_CLIENT_CERT_NOT_AFTER_UNIX.store(0, Ordering::Relaxed);
+    TRASH_DELETES.store(0, Ordering::Relaxed);
+    UNDELETES.store(0, Ordering::Relaxed);
+    PAGE_CACHE_MLOCK_REJECTS.store(0, Ordering::Relaxed);
+    PAGE_CACHE_UNSEAL_FAILURES.store(0, Ordering::Relaxed);
+    TDE_EPOCH_KEY_VERIFIES.store(0, Ordering::Relaxed);
+
     // Примечание: value cache counters/stats живут в модуле кэша; reset их не трогает.
     // Это согласуется с поведением Bloom cache (live‑значения).
 }
@@ -0,0 +1,386 @@
+//! migrate — фреймворк миграций формата БД (`quiverdb upgrade --to v3`).
+//!
+//! Миграция — упорядоченный реестр шагов (migration_steps) для целевой версии формата.
+//! Каждый шаг:
+//! - идемпотентен и умеет dry-run (только отчёт, без записи);
+//! - сообщает прогресс (MigrateProgress) через callback;
+//! - возобновляем: состояние (выполненные шаги, курсор page_id текущего шага) персистится
+//!   в <root>/.migrate_state.json, прерванный upgrade продолжается с места остановки.
+//!
+//! Запись идёт под writer-хэндлом (эксклюзивный LOCK, WAL реплеен при открытии); шаги,
+//! переписывающие страницы, обязаны коммитить их через WAL (Pager::commit_page), как обычная
+//! запись. dry-run работает поверх RO-хэндла.
+//!
+//! Цель v3 (текущий формат страниц P2PG v3 + meta v4):
+//! - v3-pages — аудит всех страниц [0..next_page_id): страницы P2PG с версией != 3 считаются
+//!   legacy. Декодера страниц v2 (page_rh) в этой сборке нет, поэтому такие страницы не
+//!   переписываются: dry-run их перечисляет, upgrade останавливается с ошибкой (курсор
+//!   сохраняется) — перепишите БД сборкой, которая их создала (export/import).
+//! - v3-feature-flags — проставить в meta feature flags, которые подразумевает формат v3
+//!   (kv_packing, ovf_zstd при codec_default=zstd), см. meta.rs.
+//!
+//! Downgrade не поддерживается: эта сборка пишет только формат v3.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::db::Db;
+use crate::meta::{add_features, CODEC_ZSTD, FEATURE_KV_PACKING, FEATURE_OVF_ZSTD};
+use crate::page::page_is_legacy;
+
+pub const MIGRATE_STATE_FILE: &str = ".migrate_state.json";
+
+const MIGRATE_STATE_VERSION: u32 = 1;
+const CHECKPOINT_EVERY_DEFAULT: u64 = 4096;
+/// Сколько page_id legacy-страниц перечислять в отчёте.
+const LEGACY_SAMPLE: usize = 16;
+
+#[inline]
+pub fn migrate_state_path(root: &Path) -> PathBuf {
+    root.join(MIGRATE_STATE_FILE)
+}
+
+/// Параметры upgrade.
+#[derive(Debug, Clone)]
+pub struct MigrateOptions {
+    /// Только отчёт: ничего не пишется (RO-хэндл, состояние не сохраняется).
+    pub dry_run: bool,
+    /// Период сброса курсора шага на диск (в страницах).
+    pub checkpoint_every: u64,
+}
+
+impl Default for MigrateOptions {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            checkpoint_every: CHECKPOINT_EVERY_DEFAULT,
+        }
+    }
+}
+
+/// Снимок прогресса текущего шага.
+#[derive(Debug, Clone, Default)]
+pub struct MigrateProgress {
+    pub step: &'static str,
+    pub pages_done: u64,
+    pub pages_total: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    /// Шаг выполнен в этом запуске.
+    Applied,
+    /// Шаг уже был выполнен ранее (по состоянию миграции) или изменений не требовалось.
+    AlreadyApplied,
+    /// dry-run: шаг внёс бы изменения.
+    WouldApply,
+}
+
+/// Итог одного шага.
+#[derive(Debug, Clone, Serialize)]
+pub struct StepReport {
+    pub id: &'static str,
+    pub description: &'static str,
+    pub status: StepStatus,
+    pub pages_scanned: u64,
+    pub pages_rewritten: u64,
+    /// Страницы формата, который эта сборка не умеет переписать.
+    pub legacy_pages: u64,
+    pub legacy_sample: Vec<u64>,
+    /// Шаг продолжен с сохранённого курсора.
+    pub resumed_from: Option<u64>,
+}
+
+/// Итог upgrade.
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationReport {
+    pub target: String,
+    pub dry_run: bool,
+    pub steps: Vec<StepReport>,
+}
+
+/// Шаг миграции.
+pub struct MigrationStep {
+    pub id: &'static str,
+    /// Целевая версия формата, к которой относится шаг.
+    pub target: &'static str,
+    pub description: &'static str,
+    run: fn(&mut StepCtx<'_>) -> Result<StepReport>,
+}
+
+/// Реестр шагов (в порядке выполнения).
+pub fn migration_steps() -> &'static [MigrationStep] {
+    const STEPS: &[MigrationStep] = &[
+        MigrationStep {
+            id: "v3-pages",
+            target: "v3",
+            description: "audit pages: every allocated P2PG page must be page format v3",
+            run: step_v3_pages,
+        },
+        MigrationStep {
+            id: "v3-feature-flags",
+            target: "v3",
+            description: "record format v3 feature flags in meta",
+            run: step_v3_feature_flags,
+        },
+    ];
+    STEPS
+}
+
+/// Поддерживаемые цели (`--to`).
+pub fn migration_targets() -> Vec<&'static str> {
+    let mut v: Vec<&'static str> = migration_steps().iter().map(|s| s.target).collect();
+    v.dedup();
+    v
+}
+
+/// Персистентное состояние миграции (<root>/.migrate_state.json).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MigrateState {
+    pub version: u32,
+    pub target: String,
+    pub completed: Vec<String>,
+    /// Шаг, прерванный на курсоре.
+    pub step: Option<String>,
+    pub cursor: u64,
+}
+
+impl MigrateState {
+    /// Загрузить состояние (None — миграций не было).
+    pub fn load(root: &Path) -> Result<Option<Self>> {
+        let p = migrate_state_path(root);
+        if !p.exists() {
+            return Ok(None);
+        }
+        let bytes = std::fs::read(&p).with_context(|| format!("read {}", p.display()))?;
+        let st = serde_json::from_slice(&bytes)
+            .with_context(|| format!("parse migrate state {}", p.display()))?;
+        Ok(Some(st))
+    }
+
+    /// Сохранить состояние атомарно (tmp + rename).
+    fn store(&self, root: &Path) -> Result<()> {
+        let p = migrate_state_path(root);
+        let tmp = root.join(format!("{}.tmp", MIGRATE_STATE_FILE));
+        {
+            let mut f = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&tmp)
+                .with_context(|| format!("open {}", tmp.display()))?;
+            f.write_all(&serde_json::to_vec_pretty(self)?)?;
+            f.sync_all()?;
+        }
+        crate::util::fsx::replace_file(&tmp, &p)
+            .with_context(|| format!("rename {} -> {}", tmp.display(), p.display()))?;
+        Ok(())
+    }
+}
+
+struct StepCtx<'a> {
+    db: &'a mut Db,
+    step: &'static MigrationStep,
+    state: &'a mut MigrateState,
+    opts: &'a MigrateOptions,
+    progress: &'a mut dyn FnMut(&MigrateProgress),
+}
+
+impl StepReport {
+    fn new(step: &MigrationStep, status: StepStatus) -> Self {
+        Self {
+            id: step.id,
+            description: step.description,
+            status,
+            pages_scanned: 0,
+            pages_rewritten: 0,
+            legacy_pages: 0,
+            legacy_sample: Vec::new(),
+            resumed_from: None,
+        }
+    }
+}
+
+impl StepCtx<'_> {
+    fn report(&self, status: StepStatus) -> StepReport {
+        StepReport::new(self.step, status)
+    }
+
+    /// Зафиксировать курсор текущего шага (не в dry-run).
+    fn checkpoint(&mut self, cursor: u64) -> Result<()> {
+        if self.opts.dry_run {
+            return Ok(());
+        }
+        self.state.step = Some(self.step.id.to_string());
+        self.state.cursor = cursor;
+        self.state.store(&self.db.root)
+    }
+}
+
+/// Обновить формат БД до target (см. migration_targets).
+pub fn upgrade(root: &Path, target: &str, opts: &MigrateOptions) -> Result<MigrationReport> {
+    upgrade_with_progress(root, target, opts, |_| {})
+}
+
+/// upgrade с callback'ом прогресса.
+pub fn upgrade_with_progress<F>(
+    root: &Path,
+    target: &str,
+    opts: &MigrateOptions,
+    mut progress: F,
+) -> Result<MigrationReport>
+where
+    F: FnMut(&MigrateProgress),
+{
+    let target = target.trim().to_ascii_lowercase();
+    if !migration_targets().contains(&target.as_str()) {
+        return Err(anyhow!(
+            "unknown migration target '{}'; supported: {} (downgrade is not supported: \
+             this build writes page format v3 only)",
+            target,
+            migration_targets().join(", ")
+        ));
+    }
+
+    let mut db = if opts.dry_run {
+        Db::open_ro(root)?
+    } else {
+        Db::open(root)?
+    };
+
+    let mut state = MigrateState::load(root)?.unwrap_or_default();
+    if state.target != target {
+        // Новая цель — начинаем с чистого состояния (completed шагов другой цели не переносим)
+        state = MigrateState {
+            version: MIGRATE_STATE_VERSION,
+            target: target.clone(),
+            ..Default::default()
+        };
+    }
+
+    let mut rep = MigrationReport {
+        target: target.clone(),
+        dry_run: opts.dry_run,
+        steps: Vec::new(),
+    };
+    for step in migration_steps().iter().filter(|s| s.target == target) {
+        if state.completed.iter().any(|c| c == step.id) {
+            rep.steps
+                .push(StepReport::new(step, StepStatus::AlreadyApplied));
+            continue;
+        }
+        let mut ctx = StepCtx {
+            db: &mut db,
+            step,
+            state: &mut state,
+            opts,
+            progress: &mut progress,
+        };
+        let sr = (step.run)(&mut ctx).with_context(|| format!("migration step {}", step.id))?;
+        if !opts.dry_run {
+            state.completed.push(step.id.to_string());
+            state.step = None;
+            state.cursor = 0;
+            state.store(root)?;
+        }
+        rep.steps.push(sr);
+    }
+    Ok(rep)
+}
+
+// -------------------- steps --------------------
+
+fn step_v3_pages(ctx: &mut StepCtx<'_>) -> Result<StepReport> {
+    let ps = ctx.db.pager.meta.page_size as usize;
+    let total = ctx.db.pager.meta.next_page_id;
+    let start = match ctx.state.step.as_deref() {
+        Some(s) if s == ctx.step.id => ctx.state.cursor.min(total),
+        _ => 0,
+    };
+    let mut rep = ctx.report(StepStatus::AlreadyApplied);
+    rep.resumed_from = (start > 0).then_some(start);
+
+    let mut buf = vec![0u8; ps];
+    let every = ctx.opts.checkpoint_every.max(1);
+    let mut legacy_first: Option<u64> = None;
+    for pid in start..total {
+        if read_page_raw(ctx.db, pid, &mut buf)? && page_is_legacy(&buf) {
+            rep.legacy_pages += 1;
+            legacy_first.get_or_insert(pid);
+            if rep.legacy_sample.len() < LEGACY_SAMPLE {
+                rep.legacy_sample.push(pid);
+            }
+        }
+        rep.pages_scanned += 1;
+        let done = pid + 1;
+        if done % every == 0 {
+            (ctx.progress)(&MigrateProgress {
+                step: ctx.step.id,
+                pages_done: done,
+                pages_total: total,
+            });
+            // Курсор не переходит за первую legacy-страницу: повторный запуск проверит её снова
+            if legacy_first.is_none() {
+                ctx.checkpoint(done)?;
+            }
+        }
+    }
+    (ctx.progress)(&MigrateProgress {
+        step: ctx.step.id,
+        pages_done: total,
+        pages_total: total,
+    });
+
+    if rep.legacy_pages > 0 {
+        if ctx.opts.dry_run {
+            rep.status = StepStatus::WouldApply;
+            return Ok(rep);
+        }
+        ctx.checkpoint(legacy_first.unwrap_or(0))?;
+        return Err(anyhow!(
+            "{} page(s) use a page format other than v3 (first: {:?}); this build has no \
+             decoder for them — rewrite the DB with the release that created it",
+            rep.legacy_pages,
+            rep.legacy_sample
+        ));
+    }
+    Ok(rep)
+}
+
+fn step_v3_feature_flags(ctx: &mut StepCtx<'_>) -> Result<StepReport> {
+    let mut want = FEATURE_KV_PACKING;
+    if ctx.db.pager.meta.codec_default == CODEC_ZSTD {
+        want |= FEATURE_OVF_ZSTD;
+    }
+    let on_disk = crate::meta::read_meta(&ctx.db.root)?.flags;
+    if on_disk & want == want {
+        return Ok(ctx.report(StepStatus::AlreadyApplied));
+    }
+    if ctx.opts.dry_run {
+        return Ok(ctx.report(StepStatus::WouldApply));
+    }
+    ctx.db.pager.meta.flags = add_features(&ctx.db.root, want)?;
+    Ok(ctx.report(StepStatus::Applied))
+}
+
+/// Прочитать страницу «как есть» (без проверки трейлера). false — страница физически
+/// отсутствует (сегмент короче).
+fn read_page_raw(db: &Db, pid: u64, buf: &mut [u8]) -> Result<bool> {
+    let (seg_no, off) = db.pager.locate(pid);
+    let path = db.pager.seg_path(seg_no);
+    let mut f = match OpenOptions::new().read(true).open(&path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(anyhow!("open {}: {}", path.display(), e)),
+    };
+    if f.metadata()?.len() < off + buf.len() as u64 {
+        return Ok(false);
+    }
+    f.seek(SeekFrom::Start(off))?;
+    f.read_exact(buf)?;
+    Ok(true)
+}
@@ -12,20 +12,52 @@
 //! - Nonce = derive_gcm_nonce(page_id, lsn);
 //! - update/verify считают тег над копией страницы с занулённым трейлером — данные страницы не изменяются.
 //!
+//! NEW: AAD v2 (AadFormat::V2, meta FEATURE_TDE_AAD_V2) — явная привязка записи к месту и БД:
+//! - AAD = "P2AEAD02" || page[0..16] || page_id u64 (физический, LE) || page type u16 || db_uuid[16];
+//! - страницу нельзя незаметно перенести на другой page_id или в другую БД с тем же ключом,
+//!   даже если nonce совпадёт (ротации, повтор LSN);
+//! - V1 (AAD = "P2AEAD01" || page[0..16]) остаётся для страниц, записанных до включения v2.
+//!
+//! NEW: per-page KID — writer пишет в заголовок (OFF_KEY_FP) отпечаток KID ключа до расчёта
+//! тега (поле покрыто тегом); чтение выбирает ключ по нему, страницы разных эпох читаются
+//! вперемешку. 0 — страница до per-page KID (ключ выбирается по LSN эпохи).
+//!
 //! Примечание (переходный шаг):
 //! - В сигнатурах page_update_checksum/page_verify_checksum параметр checksum_kind сохранён,
 //!   но игнорируется (всегда CRC32C). В следующем шаге он будет удалён из API и из meta.
 //!
 //! Strict helper verify_page_crc_strict_kind также сведён к CRC32C и игнорирует параметр kind.
+//!
+//! NEW: сам расчёт/проверка CRC32C-трейлера — в крейте quiverdb-format (page::checksum);
+//! здесь остаются ENV-тумблеры, батч-расчёт в потоках и AEAD (ключи/nonce — движок).
+//!
+//! NEW (perf): CRC32C считается без копии страницы — crc32c_append по телу страницы и затем
+//! по 16 нулевым байтам (эквивалентно CRC по странице с занулённым трейлером).
+//! - crc32c_hw_available() — process-wide проба аппаратной поддержки (SSE4.2 на x86_64,
+//!   CRC-расширение на aarch64); крейт crc32c сам выбирает HW-путь, проба нужна для диагностики.
+//! - page_update_checksums_batch — батч-расчёт трейлеров (используется commit_pages_batch*).
+//!   Крупные батчи (>= P1_CHECKSUM_PARALLEL_MIN страниц, по умолчанию 64) делятся на чанки
+//!   и считаются в scoped-потоках (до P1_CHECKSUM_THREADS, по умолчанию min(ncpu, 4)).
 
 use anyhow::{anyhow, Result};
-use byteorder::{ByteOrder, LittleEndian};
 use std::sync::OnceLock;
 
-use super::common::TRAILER_LEN;
+use super::common::{OFF_KEY_FP, OFF_TYPE, TRAILER_LEN};
+use quiverdb_format::page::checksum as fmt;
 
 /// Магия для AAD в AEAD-режиме (версионируемая).
 const AEAD_AAD_MAGIC: &[u8; 8] = b"P2AEAD01";
+/// NEW: магия AAD v2 (page_id, тип страницы, db_uuid).
+const AEAD_AAD_MAGIC_V2: &[u8; 8] = b"P2AEAD02";
+
+/// Формат AAD для AEAD-трейлера.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AadFormat {
+    /// "P2AEAD01" || page[0..16].
+    V1,
+    /// "P2AEAD02" || page[0..16] || page_id || page type || db_uuid.
+    V2 { db_uuid: [u8; 16] },
+}
 
 // ---------- ENV toggle: disable page checksum ----------
 
@@ -52,9 +84,34 @@ fn page_checksum_disabled() -> bool {
 
 // ---------- CRC32C (единственный режим) ----------
 
+/// Есть ли аппаратное ускорение CRC32C на этой машине (проба кешируется на процесс).
+pub fn crc32c_hw_available() -> bool {
+    static HW: OnceLock<bool> = OnceLock::new();
+    *HW.get_or_init(|| {
+        #[cfg(target_arch = "x86_64")]
+        {
+            std::arch::is_x86_feature_detected!("sse4.2")
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            std::arch::is_aarch64_feature_detected!("crc")
+        }
+        #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+        {
+            false
+        }
+    })
+}
+
+/// Записать CRC32C в трейлер (страница уже проверена на длину).
 #[inline]
-fn compute_crc32c(bytes: &[u8]) -> u32 {
-    crc32c::crc32c(bytes)
+fn write_crc_trailer(page: &mut [u8]) {
+    let digest32 = if page_checksum_disabled() {
+        0
+    } else {
+        fmt::compute_page_crc32c(page)
+    };
+    let _ = fmt::write_crc_trailer_value(page, digest32);
 }
 
 /// Обновить трейлер чексуммы страницы (CRC32C единственный).
@@ -63,30 +120,66 @@ pub fn page_update_checksum(page: &mut [u8], _checksum_kind: u8) -> Result<()> {
     if page.len() < TRAILER_LEN {
         return Err(anyhow!("page buffer too small for checksum"));
     }
-    let ps = page.len();
+    // При выключенных чексуммах трейлер просто зануляется — verify пройдёт без расчёта.
+    write_crc_trailer(page);
+    Ok(())
+}
 
-    if page_checksum_disabled() {
-        // Просто занулим трейлер — verify в этом режиме пройдёт без расчёта.
-        for b in &mut page[ps - TRAILER_LEN..ps] {
-            *b = 0;
+/// Батч-обновление CRC32C-трейлеров (эквивалентно page_update_checksum для каждой страницы).
+/// Крупные батчи считаются параллельно (см. заголовок модуля).
+/// Параметр checksum_kind игнорируется (переходный шаг).
+pub fn page_update_checksums_batch(
+    pages: &mut [(u64, &mut [u8])],
+    _checksum_kind: u8,
+) -> Result<()> {
+    if pages.iter().any(|(_, p)| p.len() < TRAILER_LEN) {
+        return Err(anyhow!("page buffer too small for checksum"));
+    }
+    let threads = checksum_threads();
+    if threads < 2 || pages.len() < checksum_parallel_min() || page_checksum_disabled() {
+        for (_, page) in pages.iter_mut() {
+            write_crc_trailer(page);
         }
         return Ok(());
     }
 
-    // Обнулить трейлер перед расчётом
-    for b in &mut page[ps - TRAILER_LEN..ps] {
-        *b = 0;
-    }
+    let chunk = pages.len().div_ceil(threads);
+    std::thread::scope(|s| {
+        for part in pages.chunks_mut(chunk) {
+            s.spawn(move || {
+                for (_, page) in part.iter_mut() {
+                    write_crc_trailer(page);
+                }
+            });
+        }
+    });
+    Ok(())
+}
 
-    // CRC32C по всей странице (с нулённым трейлером)
-    let digest32 = compute_crc32c(&page[..]);
+fn checksum_parallel_min() -> usize {
+    static V: OnceLock<usize> = OnceLock::new();
+    *V.get_or_init(|| {
+        std::env::var("P1_CHECKSUM_PARALLEL_MIN")
+            .ok()
+            .and_then(|s| s.trim().parse::<usize>().ok())
+            .filter(|&v| v > 0)
+            .unwrap_or(64)
+    })
+}
 
-    // Записать digest (LE) и занулить оставшиеся 12 байт
-    LittleEndian::write_u32(&mut page[ps - TRAILER_LEN..ps - TRAILER_LEN + 4], digest32);
-    for b in &mut page[ps - TRAILER_LEN + 4..ps] {
-        *b = 0;
-    }
-    Ok(())
+fn checksum_threads() -> usize {
+    static V: OnceLock<usize> = OnceLock::new();
+    *V.get_or_init(|| {
+        std::env::var("P1_CHECKSUM_THREADS")
+            .ok()
+            .and_then(|s| s.trim().parse::<usize>().ok())
+            .unwrap_or_else(|| {
+                std::thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(1)
+                    .min(4)
+            })
+    })
 }
 
 /// Проверить трейлер чексуммы страницы (CRC32C единственный). true = ок.
@@ -98,23 +191,8 @@ pub fn page_verify_checksum(page: &[u8], _checksum_kind: u8) -> Result<bool> {
     if page_checksum_disabled() {
         return Ok(true);
     }
-
-    let ps = page.len();
-
-    // Считаем stored==0 как “OK” (совместимость для нулевых/неинициализированных страниц).
-    let stored32 = LittleEndian::read_u32(&page[ps - TRAILER_LEN..ps - TRAILER_LEN + 4]);
-    if stored32 == 0 {
-        return Ok(true);
-    }
-
-    // Скопировать страницу, занулить трейлер и пересчитать CRC32C
-    let mut copy = page.to_vec();
-    for b in &mut copy[ps - TRAILER_LEN..ps] {
-        *b = 0;
-    }
-
-    let calc32 = compute_crc32c(&copy[..]);
-    Ok(stored32 == calc32)
+    // stored==0 — “OK” (совместимость для нулевых/неинициализированных страниц)
+    fmt::page_verify_crc32c(page)
 }
 
 // ---------- AES-256-GCM tag-only (TDE) ----------
@@ -126,15 +204,27 @@ use aes_gcm::{
 };
 
 /// Построить AAD для AEAD-тега:
-///   AAD = "P2AEAD01" || page[0..16] (MAGIC, version, type, page_id)
+///   V1: AAD = "P2AEAD01" || page[0..16] (MAGIC, version, type, page_id)
+///   V2: AAD = "P2AEAD02" || page[0..16] || page_id u64 || page type u16 || db_uuid[16]
 #[inline]
-fn build_aead_aad(page: &[u8]) -> Result<[u8; 24]> {
+fn build_aead_aad(page: &[u8], page_id: u64, aad_fmt: AadFormat) -> Result<Vec<u8>> {
     if page.len() < 16 {
         return Err(anyhow!("page too small (<16) for AEAD AAD"));
     }
-    let mut aad = [0u8; 24];
-    aad[0..8].copy_from_slice(AEAD_AAD_MAGIC);
-    aad[8..24].copy_from_slice(&page[0..16]);
+    let mut aad = Vec::with_capacity(50);
+    match aad_fmt {
+        AadFormat::V1 => {
+            aad.extend_from_slice(AEAD_AAD_MAGIC);
+            aad.extend_from_slice(&page[0..16]);
+        }
+        AadFormat::V2 { db_uuid } => {
+            aad.extend_from_slice(AEAD_AAD_MAGIC_V2);
+            aad.extend_from_slice(&page[0..16]);
+            aad.extend_from_slice(&page_id.to_le_bytes());
+            aad.extend_from_slice(&page[OFF_TYPE..OFF_TYPE + 2]);
+            aad.extend_from_slice(&db_uuid);
+        }
+    }
     Ok(aad)
 }
 
@@ -153,13 +243,15 @@ fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
 
 /// Вычислить 16‑байтовый AEAD‑тег для страницы:
 /// - Копируем страницу и зануляем её трейлер;
-/// - AAD = "P2AEAD01" || header[0..16];
+/// - AAD — по aad_fmt (V1/V2, см. build_aead_aad);
 /// - Возвращаем tag (payload страницы не модифицируется).
 #[inline]
 fn compute_aead_tag_for_page(
     page: &[u8],
     key_32: &[u8; 32],
     nonce12: &[u8; 12],
+    page_id: u64,
+    aad_fmt: AadFormat,
 ) -> Result<[u8; 16]> {
     if page.len() < TRAILER_LEN {
         return Err(anyhow!("page buffer too small for AEAD tag compute"));
@@ -177,7 +269,7 @@ fn compute_aead_tag_for_page(
     let nonce = Nonce::from_slice(nonce12);
 
     // AAD по копии (эквивалентно исходной странице для [0..16])
-    let aad = build_aead_aad(&tmp)?;
+    let aad = build_aead_aad(&tmp, page_id, aad_fmt)?;
 
     // encrypt_in_place_detached возвращает tag; изменённый tmp нас не волнует (это копия)
     let tag = cipher
@@ -189,29 +281,49 @@ fn compute_aead_tag_for_page(
     Ok(out)
 }
 
-/// Обновить трейлер страницы 16-байтовым AEAD‑тегом (AES-256‑GCM).
+/// Обновить трейлер страницы 16-байтовым AEAD‑тегом (AES-256‑GCM), AAD v1.
 /// Данные страницы не модифицируются — тег считается на копии.
 pub fn page_update_trailer_aead_tag(
     page: &mut [u8],
     key_32: &[u8; 32],
     nonce12: [u8; 12],
+) -> Result<()> {
+    update_trailer_aead(page, key_32, nonce12, 0, AadFormat::V1)
+}
+
+/// Проверить 16-байтовый AEAD‑тег в трейлере (AES-256‑GCM), AAD v1.
+/// - Возвращает true при совпадении, иначе false.
+/// - stored == 0..0 (все нули) возвращает false (строго).
+pub fn page_verify_trailer_aead_tag(
+    page: &[u8],
+    key_32: &[u8; 32],
+    nonce12: [u8; 12],
+) -> Result<bool> {
+    verify_trailer_aead(page, key_32, nonce12, 0, AadFormat::V1)
+}
+
+fn update_trailer_aead(
+    page: &mut [u8],
+    key_32: &[u8; 32],
+    nonce12: [u8; 12],
+    page_id: u64,
+    aad_fmt: AadFormat,
 ) -> Result<()> {
     if page.len() < TRAILER_LEN {
         return Err(anyhow!("page buffer too small for AEAD trailer"));
     }
     let ps = page.len();
-    let tag = compute_aead_tag_for_page(page, key_32, &nonce12)?;
+    let tag = compute_aead_tag_for_page(page, key_32, &nonce12, page_id, aad_fmt)?;
     page[ps - TRAILER_LEN..ps].copy_from_slice(&tag);
     Ok(())
 }
 
-/// Проверить 16-байтовый AEAD‑тег в трейлере (AES-256‑GCM).
-/// - Возвращает true при совпадении, иначе false.
-/// - stored == 0..0 (все нули) возвращает false (строго).
-pub fn page_verify_trailer_aead_tag(
+fn verify_trailer_aead(
     page: &[u8],
     key_32: &[u8; 32],
     nonce12: [u8; 12],
+    page_id: u64,
+    aad_fmt: AadFormat,
 ) -> Result<bool> {
     if page.len() < TRAILER_LEN {
         return Err(anyhow!("page buffer too small for AEAD verify"));
@@ -224,11 +336,11 @@ pub fn page_verify_trailer_aead_tag(
         return Ok(false);
     }
 
-    let calc = compute_aead_tag_for_page(page, key_32, &nonce12)?;
+    let calc = compute_aead_tag_for_page(page, key_32, &nonce12, page_id, aad_fmt)?;
     Ok(constant_time_eq(stored, &calc))
 }
 
-/// Удобная обёртка: обновить трейлер AEAD‑tag, дернув nonce из (page_id, lsn).
+/// Удобная обёртка: обновить трейлер AEAD‑tag (AAD v1), дернув nonce из (page_id, lsn).
 #[inline]
 pub fn page_update_trailer_aead_with(
     page: &mut [u8],
@@ -236,11 +348,10 @@ pub fn page_update_trailer_aead_with(
     page_id: u64,
     lsn: u64,
 ) -> Result<()> {
-    let nonce = derive_gcm_nonce(page_id, lsn);
-    page_update_trailer_aead_tag(page, key_32, nonce)
+    page_update_trailer_aead_fmt(page, key_32, page_id, lsn, AadFormat::V1)
 }
 
-/// Удобная обёртка: проверить AEAD‑tag, дернув nonce из (page_id, lsn).
+/// Удобная обёртка: проверить AEAD‑tag (AAD v1), дернув nonce из (page_id, lsn).
 #[inline]
 pub fn page_verify_trailer_aead_with(
     page: &[u8],
@@ -248,50 +359,73 @@ pub fn page_verify_trailer_aead_with(
     page_id: u64,
     lsn: u64,
 ) -> Result<bool> {
+    page_verify_trailer_aead_fmt(page, key_32, page_id, lsn, AadFormat::V1)
+}
+
+/// NEW: обновить трейлер AEAD‑tag с выбранным форматом AAD (V2 привязывает page_id/тип/db_uuid).
+#[inline]
+pub fn page_update_trailer_aead_fmt(
+    page: &mut [u8],
+    key_32: &[u8; 32],
+    page_id: u64,
+    lsn: u64,
+    aad_fmt: AadFormat,
+) -> Result<()> {
     let nonce = derive_gcm_nonce(page_id, lsn);
-    page_verify_trailer_aead_tag(page, key_32, nonce)
+    update_trailer_aead(page, key_32, nonce, page_id, aad_fmt)
 }
 
-// ---------- Helpers (чтение поля CRC из трейлера) ----------
+/// NEW: отпечаток KID для заголовка страницы (CRC32C имени; 0 зарезервирован — «не записан»).
+pub fn kid_fingerprint(kid: &str) -> u32 {
+    match crc32c::crc32c(kid.as_bytes()) {
+        0 => 1,
+        fp => fp,
+    }
+}
 
-/// Прочитать низшие 4 байта трейлера страницы как u32 (LE).
+/// NEW: отпечаток KID из заголовка KV_RH3/OVERFLOW3 (0 — не записан).
 #[inline]
-pub fn page_trailer_crc32_le(page: &[u8]) -> Result<u32> {
-    if page.len() < TRAILER_LEN {
-        return Err(anyhow!("page buffer too small for trailer read"));
+pub fn page_key_fp(page: &[u8]) -> u32 {
+    if page.len() < OFF_KEY_FP + 4 {
+        return 0;
     }
-    let ps = page.len();
-    Ok(LittleEndian::read_u32(
-        &page[ps - TRAILER_LEN..ps - TRAILER_LEN + 4],
-    ))
+    u32::from_le_bytes([
+        page[OFF_KEY_FP],
+        page[OFF_KEY_FP + 1],
+        page[OFF_KEY_FP + 2],
+        page[OFF_KEY_FP + 3],
+    ])
 }
 
-/// Проверка «нулевого трейлера» для CRC‑режима (stored CRC32 == 0).
+/// NEW: записать отпечаток KID в заголовок (до расчёта AEAD-тега).
 #[inline]
-pub fn page_trailer_is_zero_crc32(page: &[u8]) -> Result<bool> {
-    Ok(page_trailer_crc32_le(page)? == 0)
+pub fn set_page_key_fp(page: &mut [u8], fp: u32) {
+    if page.len() >= OFF_KEY_FP + 4 {
+        page[OFF_KEY_FP..OFF_KEY_FP + 4].copy_from_slice(&fp.to_le_bytes());
+    }
 }
 
+/// NEW: проверить AEAD‑tag с выбранным форматом AAD.
+#[inline]
+pub fn page_verify_trailer_aead_fmt(
+    page: &[u8],
+    key_32: &[u8; 32],
+    page_id: u64,
+    lsn: u64,
+    aad_fmt: AadFormat,
+) -> Result<bool> {
+    let nonce = derive_gcm_nonce(page_id, lsn);
+    verify_trailer_aead(page, key_32, nonce, page_id, aad_fmt)
+}
+
+// ---------- Helpers (чтение поля CRC из трейлера) ----------
+
+pub use fmt::{page_trailer_crc32_le, page_trailer_is_zero_crc32};
+
 // ---------- Strict verify helper (используется в pager/io) ----------
 
 /// Строгая CRC‑проверка трейлера страницы, игнорирующая ENV‑тумблеры.
 /// Всегда использует CRC32C (Castagnoli). Параметр checksum_kind игнорируется.
 pub fn verify_page_crc_strict_kind(page: &[u8], _checksum_kind: u8) -> Result<bool> {
-    if page.len() < TRAILER_LEN {
-        return Err(anyhow!("page buffer too small for strict CRC verify"));
-    }
-    let ps = page.len();
-
-    let stored32 = LittleEndian::read_u32(&page[ps - TRAILER_LEN..ps - TRAILER_LEN + 4]);
-    if stored32 == 0 {
-        return Ok(false);
-    }
-
-    let mut copy = page.to_vec();
-    for b in &mut copy[ps - TRAILER_LEN..ps] {
-        *b = 0;
-    }
-
-    let calc = compute_crc32c(&copy[..]);
-    Ok(stored32 == calc)
+    fmt::verify_page_crc_strict(page)
 }
@@ -2,19 +2,23 @@
 //!
 //! Layout/format details — см. docs/format.md.
 //!
+//! NEW: чистый формат вынесен в крейт quiverdb-format (no_std-friendly, без файловой системы);
+//! common/kv/kv_pack/ovf::header реэкспортируются оттуда — пути crate::page::* не меняются.
+//!
 //! Разделение по подмодулям:
-//! - common.rs   — общие константы/offset’ы, MAGIC/версия/типы, размеры заголовков.
-//! - checksum.rs — трейлер: CRC32C (2.0) и AEAD-tag (AES-GCM, 2.1 prep) + хелперы трейлера.
-//! - kv.rs       — KV_RH3: init/read/write заголовка и helper для чтения записи.
-//! - ovf.rs      — OVERFLOW3: init/read/write заголовка.
-//! - kv_pack.rs  — упаковка нескольких KV-записей на одну страницу (поддержка packing).
+//! - common      — общие константы/offset’ы, MAGIC/версия/типы, размеры заголовков. [format]
+//! - checksum.rs — трейлер: CRC32C (расчёт — format) + ENV-тумблеры/батч и AEAD-tag (AES-GCM).
+//! - kv          — KV_RH3: init/read/write заголовка и helper для чтения записи. [format]
+//! - ovf/        — OVERFLOW3: заголовок [format] + чтение цепочек через Pager (chain.rs).
+//! - kv_pack     — упаковка нескольких KV-записей на одну страницу (поддержка packing). [format]
+//!
+//! NEW: page_version / page_is_legacy — единая точка version-dispatch для сканеров (doctor, migrate):
+//! страницы P2PG с версией != PAGE_VERSION_V3 — legacy (декодера v2 в этой сборке нет), их не
+//! разбирают v3-хелперами, а перечисляют отдельно.
 
 pub mod checksum;
-pub mod common;
-pub mod kv;
 pub mod ovf;
-// NEW: публичный модуль для packing
-pub mod kv_pack;
+pub use quiverdb_format::page::{common, kv, kv_pack};
 
 // ---------------- re-exports (внешний API модуля page) ----------------
 
@@ -22,8 +26,12 @@ pub use common::{
     // KV header layout essentials
     KV_HDR_MIN,
     KV_OFF_LSN,
+    // NEW: отпечаток KID страницы (TDE)
+    OFF_KEY_FP,
     // offsets used by pager/commit/replay
     OFF_TYPE,
+    // NEW: версия формата страницы (migrate)
+    OFF_VERSION,
     // OVF header layout essentials
     OVF_HDR_MIN,
     OVF_OFF_LSN,
@@ -35,17 +43,28 @@ pub use common::{
 };
 
 pub use checksum::{
+    // NEW: батч-расчёт трейлеров и проба HW CRC32C
+    crc32c_hw_available,
+    // NEW: отпечаток KID в заголовке страницы
+    kid_fingerprint,
+    page_key_fp,
     // Helpers (CRC trailer field access / zero-check)
     page_trailer_crc32_le,
     page_trailer_is_zero_crc32,
     // 2.0 CRC32C
     page_update_checksum,
+    page_update_checksums_batch,
     // 2.1 prep (TDE, AES-GCM tag-only)
+    page_update_trailer_aead_fmt,
     page_update_trailer_aead_with,
     page_verify_checksum,
+    page_verify_trailer_aead_fmt,
     page_verify_trailer_aead_with,
+    set_page_key_fp,
     // NEW: строгая CRC‑проверка (единая точка правды)
     verify_page_crc_strict_kind,
+    // NEW: формат AAD (v2 — page_id, тип страницы, db_uuid)
+    AadFormat,
 };
 
 pub use kv::{
@@ -58,3 +77,19 @@ pub use kv::{
 };
 
 pub use ovf::{ovf_header_read_v3, ovf_header_write_v3, ovf_init_v3, OvfHeaderV3};
+
+/// Версия формата страницы P2PG (None — нет магии P2PG или буфер короче заголовка).
+pub fn page_version(page: &[u8]) -> Option<u16> {
+    if page.len() < OFF_VERSION + 2 || &page[0..4] != PAGE_MAGIC {
+        return None;
+    }
+    Some(u16::from_le_bytes([
+        page[OFF_VERSION],
+        page[OFF_VERSION + 1],
+    ]))
+}
+
+/// Страница P2PG старого формата (версия != v3).
+pub fn page_is_legacy(page: &[u8]) -> bool {
+    page_version(page).is_some_and(|v| v != PAGE_VERSION_V3)
+}
@@ -16,6 +16,9 @@
 //!
 //! NEW (tech‑debt): единая константа‑guard для длинных цепочек OVERFLOW:
 //! - OVF_MAX_CHAIN_PAGES_GUARD — предел числа страниц в одной цепочке (по умолчанию 1_000_000).
+//!
+//! NEW: страницы цепочки читаются через Pager::read_page_ra (readahead-окно, см. pager/io.rs) —
+//! цепочки OVERFLOW аллоцируются подряд, поэтому окно «вперёд» заменяет K одиночных чтений одним.
 
 use anyhow::{anyhow, Result};
 use std::io::{Cursor, Read};
@@ -23,6 +26,7 @@ use std::sync::OnceLock;
 
 use crate::dir::NO_PAGE;
 use crate::page::{ovf_header_read_v3, OVF_HDR_MIN};
+use crate::pager::io::ReadaheadWindow;
 use crate::pager::Pager;
 
 /// Максимально допустимый размер значения (байт), читаемого из OVERFLOW‑цепочки.
@@ -63,6 +67,7 @@ pub fn read_overflow_chain(pager: &Pager, mut head: u64, expected_len: usize) ->
     // Небольшой рабочий буфер для потокового чтения распакованных байт
     const TMP_BUF: usize = 64 * 1024;
     let mut tmp = vec![0u8; TMP_BUF];
+    let mut ra = ReadaheadWindow::new();
 
     while head != NO_PAGE {
         guard += 1;
@@ -74,7 +79,7 @@ pub fn read_overflow_chain(pager: &Pager, mut head: u64, expected_len: usize) ->
         }
 
         let mut page = vec![0u8; ps];
-        pager.read_page(head, &mut page)?;
+        pager.read_page_ra(head, &mut page, &mut ra)?;
         let h = ovf_header_read_v3(&page)?;
         let take = h.chunk_len as usize;
 
@@ -12,18 +12,124 @@
 //!
 //! Примечание: предаллокация изменяет только длину файла сегмента, но не логическое число
 //! выделенных страниц (next_page_id). Это прозрачная оптимизация I/O.
+//!
+//! NEW: политика роста сегментов (QuiverConfig::segment_growth, P1_SEGMENT_GROWTH):
+//! - exact (по умолчанию) — сегмент дорастает ровно до нужной страницы (+ P1_PREALLOC_PAGES);
+//! - chunk — рост кусками segment_grow_chunk_bytes (P1_SEGMENT_GROW_CHUNK_BYTES, по умолчанию
+//!   1 MiB): длина округляется вверх до границы куска, расширений в chunk/page_size раз меньше;
+//! - full — сегмент при первом касании сразу получает полный размер (SEGMENT_SIZE).
+//!
+//! segment_fallocate (P1_SEGMENT_FALLOCATE) — резервировать блоки (posix_fallocate на Linux)
+//! вместо set_len: без разреженных файлов, ENOSPC при росте, а не при записи страницы. Если
+//! ФС/платформа не умеет — fallback на set_len (seg_fallocate_fallbacks).
+//!
+//! Каждое расширение (allocate_pages / ensure_allocated) учитывается в метриках seg_grow_*:
+//! число, байты и время (stall) — суммарное и максимальное.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
 use std::sync::OnceLock;
+use std::time::Instant;
 
 use crate::free::FreeList;
 // write_meta_overwrite удалён из горячего пути
 // use crate::meta::write_meta_overwrite;
 
 use super::core::Pager;
+use crate::metrics::record_seg_grow;
+
+/// Размер куска роста по умолчанию (политика chunk).
+pub const DEFAULT_SEGMENT_GROW_CHUNK_BYTES: u64 = 1024 * 1024;
+
+/// Политика роста файлов сегментов (см. заголовок модуля).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SegmentGrowth {
+    #[default]
+    Exact,
+    Chunk,
+    Full,
+}
+
+impl SegmentGrowth {
+    /// Разбор "exact" | "chunk" | "full" (регистр не важен). None — неверное значение.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "exact" | "off" => Some(Self::Exact),
+            "chunk" | "chunked" => Some(Self::Chunk),
+            "full" | "segment" => Some(Self::Full),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Exact => "exact",
+            Self::Chunk => "chunk",
+            Self::Full => "full",
+        }
+    }
+}
+
+impl fmt::Display for SegmentGrowth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
 
 impl Pager {
+    /// NEW: политика роста сегментов (open_with_config). chunk_bytes — для SegmentGrowth::Chunk.
+    pub fn set_segment_growth(&mut self, growth: SegmentGrowth, chunk_bytes: u64, fallocate: bool) {
+        self.seg_growth = growth;
+        self.seg_grow_chunk_bytes = chunk_bytes;
+        self.seg_fallocate = fallocate;
+    }
+
+    /// Целевая длина сегмента, которому нужно need_len байт (по политике, в пределах сегмента).
+    pub(crate) fn segment_growth_target(&self, need_len: u64) -> u64 {
+        let cap = self.pages_per_seg() * self.meta.page_size as u64;
+        let target = match self.seg_growth {
+            SegmentGrowth::Exact => need_len,
+            SegmentGrowth::Chunk => {
+                // Кусок не меньше страницы и кратен ей — граница куска совпадает с границей страницы
+                let ps = self.meta.page_size as u64;
+                let chunk = self.seg_grow_chunk_bytes.max(ps).div_ceil(ps) * ps;
+                need_len.div_ceil(chunk).saturating_mul(chunk)
+            }
+            SegmentGrowth::Full => cap,
+        };
+        target.min(cap).max(need_len)
+    }
+
+    /// Дорастить открытый сегмент до need_len (политика роста + fallocate). Без fsync.
+    /// false — сегмент уже достаточно длинный.
+    pub(crate) fn grow_segment(&self, f: &File, seg_no: u64, need_len: u64) -> Result<bool> {
+        let cur_len = f.metadata()?.len();
+        if cur_len >= need_len {
+            return Ok(false);
+        }
+        let target = self.segment_growth_target(need_len);
+        let t0 = Instant::now();
+        let mut fell_back = false;
+        if self.seg_fallocate {
+            if fallocate_range(f, cur_len, target - cur_len).is_err() {
+                fell_back = true;
+                f.set_len(target)?;
+            }
+        } else {
+            f.set_len(target).with_context(|| {
+                format!(
+                    "grow segment {} to {}",
+                    self.seg_path(seg_no).display(),
+                    target
+                )
+            })?;
+        }
+        record_seg_grow(target - cur_len, t0.elapsed().as_micros() as u64, fell_back);
+        Ok(true)
+    }
+
     /// Аллокация последовательности новых страниц. Возвращает начальный page_id.
     ///
     /// Правила:
@@ -37,6 +143,7 @@ impl Pager {
     /// - Для последовательностей (>1) используем только “свежие” page_id (континуум),
     ///   чтобы сохранить требование непрерывности (нужно для OVERFLOW3 цепочек).
     pub fn allocate_pages(&mut self, count: u64) -> Result<u64> {
+        self.ensure_writable("allocate_pages")?;
         let start = self.meta.next_page_id;
         let end = start + count;
 
@@ -52,7 +159,12 @@ impl Pager {
         }
 
         // NEW: hot preallocation для последнего затронутого сегмента (если включено ENV).
-        if let Some((&last_seg_no, &need_len0)) = seg_max_len.iter().next_back() {
+        // Только для политики exact: chunk/full сами растят сегмент с запасом.
+        let last = match self.seg_growth {
+            SegmentGrowth::Exact => seg_max_len.iter().next_back(),
+            _ => None,
+        };
+        if let Some((&last_seg_no, &need_len0)) = last {
             let pre_pages = prealloc_pages();
             if pre_pages > 0 {
                 warn_prealloc_once(pre_pages);
@@ -73,11 +185,8 @@ impl Pager {
         // Увеличиваем длины сегментов до нужной (без fsync на расширение).
         for (seg_no, need_len) in seg_max_len {
             let f = self.open_seg_rw(seg_no, true)?;
-            let cur_len = f.metadata()?.len();
-            if cur_len < need_len {
-                // Дорастим файл до нужного размера; fsync сделает commit_pages_batch (коалесцировано).
-                f.set_len(need_len)?;
-            }
+            // Дорастим файл по политике роста; fsync сделает commit_pages_batch (коалесцировано).
+            self.grow_segment(&f, seg_no, need_len)?;
         }
 
         // Продвигаем next_page_id (в памяти; без write_meta_overwrite).
@@ -90,6 +199,7 @@ impl Pager {
     /// Попытка 1: взять page_id из free‑листа (<root>/free) — ТОЛЬКО если файл существует.
     /// Попытка 2: fallback на allocate_pages(1) — выделить новый page_id из хвоста.
     pub fn allocate_one_page(&mut self) -> Result<u64> {
+        self.ensure_writable("allocate_one_page")?;
         // Проверим, существует ли free‑лист; избегаем лишнего open() с ошибкой на каждый вызов.
         let free_path = self.root.join("free");
         if free_path.exists() {
@@ -131,3 +241,24 @@ fn warn_prealloc_once(pages: u64) {
         );
     });
 }
+
+// ---------- fallocate ----------
+
+/// Зарезервировать блоки [off, off+len) (файл растёт, если нужно). Err — не поддерживается.
+#[cfg(target_os = "linux")]
+fn fallocate_range(f: &File, off: u64, len: u64) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    // SAFETY: fd принадлежит живому File; posix_fallocate не трогает память процесса.
+    let rc =
+        unsafe { libc::posix_fallocate(f.as_raw_fd(), off as libc::off_t, len as libc::off_t) };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::from_raw_os_error(rc))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn fallocate_range(_f: &File, _off: u64, _len: u64) -> std::io::Result<()> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
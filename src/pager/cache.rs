@@ -22,18 +22,114 @@
 //! - page_cache_evictions_total() -> u64 — число выселений (диагностика).
 //! - page_cache_invalidations_total() -> u64 — число инвалидаций (диагностика).
 //! - page_cache_len() -> usize — текущее число страниц в кэше.
+//! - page_cache_capacity() -> usize — ёмкость (страниц).
+//! - NEW: page_cache_hot_pages(db_id, max) -> Vec<u64> — «горячие» page_id по recency (refbit/очередь),
+//!   используется для сохранения access-pattern снимка при clean shutdown.
+//! - NEW: page_cache_write_gen() + page_cache_put_prewarm(..., gen) — вставка prewarm-страницы
+//!   только если с момента чтения не было ни одной инвалидации (иначе страница могла устареть).
+//!   Prewarm-страницы помечаются; первый hit по такой странице считается prewarm hit (metrics).
+//!
+//! - NEW: политика хранения (page_cache_set_policy / QuiverConfig::page_cache_policy) — что кэш
+//!   держит в памяти процесса (для TDE-развёртываний, где открытый текст страниц нежелателен):
+//!     * plaintext (по умолчанию) — страницы как есть;
+//!     * ciphertext — каждая запись запечатана AES-256-GCM эфемерным ключом процесса (случайный,
+//!       живёт только в памяти; AAD = db_id+page_id), hit расшифровывает копию. Запись, не
+//!       прошедшая проверку тега, выбрасывается (промах, метрика page_cache_unseal_failures);
+//!     * locked — открытый текст в буферах под mlock, при вытеснении/инвалидации/очистке буфер
+//!       зануляется и разблокируется. Заблокированные байты учитываются (page_cache_locked_bytes)
+//!       и ограничены page_cache_mlock_max_bytes: сверх лимита или при отказе mlock (RLIMIT_MEMLOCK)
+//!       страница просто не кэшируется (метрика page_cache_mlock_rejects).
+//!
+//! Смена политики очищает кэш. Политика процесс-широкая, как и сам кэш (последний open задаёт).
+//!
+//! - NEW: квоты per-DB (page_cache_set_quota(db_id, Some(n)) — db/manager.rs): БД с квотой держит
+//!   в кэше не больше n страниц; сверх квоты вытесняется её же самая старая страница, а не чужие.
+//!   page_cache_db_len(db_id) — сколько страниц БД сейчас в кэше. Квоты переживают clear/configure.
+//!
+//! - NEW: байты записей учитываются в ResourceBudget (budget.rs, Subsystem::PageCache): при
+//!   тесном бюджете вставка вытесняет страницы кэша, пока новая не поместится, иначе пропускается.
 //!
 //! Примечание:
 //! - db_id — стабильный идентификатор БД (u64), см. Pager::db_id (u64).
 //! - Возвращается Option<Vec<u8>> (копия), чтобы не выдавать ссылку на внутренний буфер за пределы лока.
 
 use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Mutex, OnceLock};
 
-#[derive(Clone)]
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce, Tag};
+use rand::RngCore;
+
+use crate::budget::{self, Subsystem};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::crypto::harden::{mlock_region, munlock_region};
+
+/// Лимит mlock-памяти кэша по умолчанию (политика locked): 64 MiB.
+pub const DEFAULT_PAGE_CACHE_MLOCK_MAX_BYTES: u64 = 64 << 20;
+
+const SEAL_TAG_LEN: usize = 16;
+
+/// Политика хранения страниц в кэше (см. заголовок модуля).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PageCachePolicy {
+    #[default]
+    Plaintext,
+    Ciphertext,
+    Locked,
+}
+
+impl PageCachePolicy {
+    /// Разбор "plaintext" | "ciphertext" | "locked" (регистр не важен). None — неверное значение.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "plaintext" | "plain" => Some(Self::Plaintext),
+            "ciphertext" | "sealed" => Some(Self::Ciphertext),
+            "locked" | "mlock" => Some(Self::Locked),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Plaintext => "plaintext",
+            Self::Ciphertext => "ciphertext",
+            Self::Locked => "locked",
+        }
+    }
+}
+
+impl fmt::Display for PageCachePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Байты под mlock (политика locked); меняется в Drop записей, поэтому вне GlobalCache.
+static LOCKED_BYTES: AtomicU64 = AtomicU64::new(0);
+
 struct CacheEntry {
+    /// plaintext/locked — байты страницы; ciphertext — шифртекст + тег.
     buf: Vec<u8>,
+    /// ciphertext: счётчик nonce, которым запечатана запись.
+    nonce: u64,
+    /// locked: buf под mlock (Drop зануляет и разблокирует).
+    locked: bool,
     refbit: bool,
+    // NEW: страница загружена prewarm'ом и ещё не была запрошена
+    prewarmed: bool,
+}
+
+impl Drop for CacheEntry {
+    fn drop(&mut self) {
+        if self.locked {
+            self.buf.as_mut_slice().zeroize();
+            munlock_region(&self.buf);
+            LOCKED_BYTES.fetch_sub(self.buf.len() as u64, Ordering::Relaxed);
+        }
+    }
 }
 
 /// Ключ кэша: (уникальный id БД в процессе, page_id).
@@ -59,6 +155,17 @@ struct GlobalCache {
     // Счётчики (для метрик)
     evictions_total: u64,     // только реальные вытеснения алгоритмом
     invalidations_total: u64, // явные инвалидации по записи
+    // NEW: поколение записей — растёт на КАЖДЫЙ invalidate (даже если ключа нет в кэше)
+    write_gen: u64,
+    // NEW: политика хранения и её состояние
+    policy: PageCachePolicy,
+    mlock_max_bytes: u64,
+    /// ciphertext: эфемерный ключ процесса (создаётся при первом включении политики).
+    sealer: Option<Aes256Gcm>,
+    nonce_ctr: u64,
+    // NEW: страниц каждой БД в кэше и квоты per-DB (db_id → максимум страниц)
+    per_db: HashMap<u64, usize>,
+    quotas: HashMap<u64, usize>,
 }
 
 impl GlobalCache {
@@ -72,6 +179,13 @@ impl GlobalCache {
             configured: false,
             evictions_total: 0,
             invalidations_total: 0,
+            write_gen: 0,
+            policy: PageCachePolicy::Plaintext,
+            mlock_max_bytes: DEFAULT_PAGE_CACHE_MLOCK_MAX_BYTES,
+            sealer: None,
+            nonce_ctr: 0,
+            per_db: HashMap::new(),
+            quotas: HashMap::new(),
         }
     }
 
@@ -79,8 +193,7 @@ impl GlobalCache {
         if self.inited {
             if self.page_size != page_size && self.enabled() {
                 // Смена геометрии — сбросим содержимое, сохранив активную конфигурацию.
-                self.q.clear();
-                self.map.clear();
+                self.drop_all();
                 self.evictions_total = 0;
                 self.invalidations_total = 0;
                 self.page_size = page_size;
@@ -96,6 +209,16 @@ impl GlobalCache {
                 .and_then(|s| s.trim().parse::<usize>().ok())
                 .unwrap_or(0);
             self.cap_pages = cap;
+            if let Some(p) = std::env::var("P1_PAGE_CACHE_POLICY")
+                .ok()
+                .and_then(|s| PageCachePolicy::parse(&s))
+            {
+                let max = std::env::var("P1_PAGE_CACHE_MLOCK_MAX_BYTES")
+                    .ok()
+                    .and_then(|s| s.trim().parse::<u64>().ok())
+                    .unwrap_or(DEFAULT_PAGE_CACHE_MLOCK_MAX_BYTES);
+                self.set_policy(p, max);
+            }
         }
         self.page_size = page_size;
         self.inited = true;
@@ -115,25 +238,103 @@ impl GlobalCache {
         if !self.enabled() {
             return None;
         }
+        let ent = self.map.get(key)?;
+        let Some(page) = self.unseal(key, ent) else {
+            // Запечатанная запись не прошла проверку тега — выбрасываем, это промах
+            self.remove_entry(key);
+            crate::metrics::record_page_cache_unseal_failure();
+            return None;
+        };
         if let Some(ent) = self.map.get_mut(key) {
             ent.refbit = true;
+            if ent.prewarmed {
+                ent.prewarmed = false;
+                crate::metrics::record_prewarm_hit();
+            }
+        }
+        Some(page)
+    }
+
+    /// Запись для src по текущей политике. None — страницу кэшировать нельзя (лимит mlock).
+    fn make_entry(&mut self, key: &CacheKey, src: &[u8], refbit: bool) -> Option<CacheEntry> {
+        let mut ent = CacheEntry {
+            buf: Vec::new(),
+            nonce: 0,
+            locked: false,
+            refbit,
+            prewarmed: false,
+        };
+        match self.policy {
+            PageCachePolicy::Plaintext => ent.buf = src.to_vec(),
+            PageCachePolicy::Ciphertext => {
+                let sealer = self.sealer.as_ref()?;
+                self.nonce_ctr += 1;
+                ent.nonce = self.nonce_ctr;
+                let mut buf = Vec::with_capacity(src.len() + SEAL_TAG_LEN);
+                buf.extend_from_slice(src);
+                let tag = sealer
+                    .encrypt_in_place_detached(&seal_nonce(ent.nonce), &seal_aad(key), &mut buf)
+                    .ok()?;
+                buf.extend_from_slice(tag.as_slice());
+                ent.buf = buf;
+            }
+            PageCachePolicy::Locked => {
+                let len = src.len() as u64;
+                if LOCKED_BYTES.load(Ordering::Relaxed) + len > self.mlock_max_bytes {
+                    crate::metrics::record_page_cache_mlock_reject();
+                    return None;
+                }
+                // Сначала блокируем нулевой буфер, затем копируем открытый текст
+                let mut buf = vec![0u8; src.len()];
+                if !mlock_region(&buf) {
+                    crate::metrics::record_page_cache_mlock_reject();
+                    return None;
+                }
+                LOCKED_BYTES.fetch_add(len, Ordering::Relaxed);
+                buf.copy_from_slice(src);
+                ent.buf = buf;
+                ent.locked = true;
+            }
+        }
+        Some(ent)
+    }
+
+    /// Копия байтов страницы из записи. None — запечатанная запись повреждена.
+    fn unseal(&self, key: &CacheKey, ent: &CacheEntry) -> Option<Vec<u8>> {
+        if self.policy != PageCachePolicy::Ciphertext {
             return Some(ent.buf.clone());
         }
-        None
+        let sealer = self.sealer.as_ref()?;
+        let n = ent.buf.len().checked_sub(SEAL_TAG_LEN)?;
+        let mut page = ent.buf[..n].to_vec();
+        let tag = Tag::from_slice(&ent.buf[n..]);
+        sealer
+            .decrypt_in_place_detached(&seal_nonce(ent.nonce), &seal_aad(key), &mut page, tag)
+            .ok()?;
+        Some(page)
     }
 
     fn put(&mut self, key: CacheKey, src: &[u8]) {
         if !self.enabled() {
             return;
         }
-        // Вставка/обновление: если ключ уже есть — обновим буфер и refbit.
-        if let Some(ent) = self.map.get_mut(&key) {
-            ent.buf.resize(src.len(), 0);
-            ent.buf.copy_from_slice(src);
-            ent.refbit = true;
+        // Обновление: старая запись освобождается до создания новой (место под mlock-лимитом);
+        // ключ уже стоит в очереди.
+        if self.remove_entry(&key).is_some() {
+            // (без вытеснения: оно могло бы снять ключ с очереди)
+            if let Some(ent) = self.make_entry(&key, src, true) {
+                if budget::try_charge(Subsystem::PageCache, ent.buf.len() as u64) {
+                    self.insert_entry(key, ent);
+                }
+            }
             return;
         }
 
+        // NEW: БД на квоте вытесняет свою страницу
+        if self.at_quota(key.db_id) {
+            self.evict_one_of(key.db_id);
+        }
+
         // second-chance eviction до помещения нового элемента
         while self.map.len() >= self.cap_pages {
             if !self.evict_one() {
@@ -143,10 +344,71 @@ impl GlobalCache {
         }
 
         // Вставка нового элемента
-        let mut buf = vec![0u8; src.len()];
-        buf.copy_from_slice(src);
+        let Some(ent) = self.make_entry(&key, src, true) else {
+            return;
+        };
+        if !self.admit(&ent) {
+            return;
+        }
         self.q.push_back(key);
-        self.map.insert(key, CacheEntry { buf, refbit: true });
+        self.insert_entry(key, ent);
+    }
+
+    /// Prewarm-вставка: только если ключа ещё нет и поколение записей не изменилось.
+    /// Не вытесняет рабочие страницы: при заполненном кэше prewarm просто прекращается.
+    fn put_prewarm(&mut self, key: CacheKey, src: &[u8], gen: u64) -> bool {
+        if !self.enabled() || self.write_gen != gen || self.map.contains_key(&key) {
+            return false;
+        }
+        if self.map.len() >= self.cap_pages || self.at_quota(key.db_id) {
+            return false;
+        }
+        let Some(mut ent) = self.make_entry(&key, src, false) else {
+            return false;
+        };
+        if !budget::try_charge(Subsystem::PageCache, ent.buf.len() as u64) {
+            return false;
+        }
+        ent.prewarmed = true;
+        self.q.push_back(key);
+        self.insert_entry(key, ent);
+        true
+    }
+
+    /// Сменить политику хранения; при смене содержимое очищается (записи в старом формате).
+    fn set_policy(&mut self, policy: PageCachePolicy, mlock_max_bytes: u64) {
+        self.mlock_max_bytes = mlock_max_bytes;
+        if policy == PageCachePolicy::Ciphertext && self.sealer.is_none() {
+            let mut k = Zeroizing::new([0u8; 32]);
+            rand::rngs::OsRng.fill_bytes(&mut k[..]);
+            self.sealer = Some(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&k[..])));
+        }
+        if policy != self.policy {
+            self.drop_all();
+            self.policy = policy;
+        }
+    }
+
+    /// «Горячие» страницы db_id: сначала с refbit=1 (от свежих к старым), затем остальные.
+    fn hot_pages(&self, db_id: u64, max: usize) -> Vec<u64> {
+        let mut hot = Vec::new();
+        let mut cold = Vec::new();
+        for k in self.q.iter().rev() {
+            if k.db_id != db_id {
+                continue;
+            }
+            match self.map.get(k) {
+                Some(e) if e.refbit => hot.push(k.page_id),
+                Some(_) => cold.push(k.page_id),
+                None => {}
+            }
+        }
+        hot.extend(cold);
+        // очередь может содержать дубликаты (ленивая очистка после invalidate)
+        let mut seen = std::collections::HashSet::new();
+        hot.retain(|p| seen.insert(*p));
+        hot.truncate(max);
+        hot
     }
 
     fn evict_one(&mut self) -> bool {
@@ -160,7 +422,7 @@ impl GlobalCache {
                 }
             }
             // либо нет записи (ленивое очищение), либо refbit=0 — можно выселять
-            let _ = self.map.remove(&k);
+            let _ = self.remove_entry(&k);
             self.evictions_total = self.evictions_total.saturating_add(1);
             return true;
         }
@@ -168,10 +430,11 @@ impl GlobalCache {
     }
 
     fn invalidate(&mut self, key: &CacheKey) {
+        self.write_gen = self.write_gen.wrapping_add(1);
         if !self.enabled() {
             return;
         }
-        let present = self.map.remove(key).is_some();
+        let present = self.remove_entry(key).is_some();
         if present {
             // Очередь чистим лениво (remove из map достаточно для корректности second-chance)
             self.invalidations_total = self.invalidations_total.saturating_add(1);
@@ -181,8 +444,7 @@ impl GlobalCache {
     fn configure(&mut self, page_size: usize, cap_pages: usize) {
         // Меняем настройки: при смене page_size сбросим содержимое.
         if self.page_size != 0 && self.page_size != page_size {
-            self.q.clear();
-            self.map.clear();
+            self.drop_all();
             self.evictions_total = 0;
             self.invalidations_total = 0;
         }
@@ -192,8 +454,7 @@ impl GlobalCache {
         self.inited = true;
 
         if self.cap_pages == 0 {
-            self.q.clear();
-            self.map.clear();
+            self.drop_all();
             self.evictions_total = 0;
             self.invalidations_total = 0;
         } else {
@@ -207,11 +468,109 @@ impl GlobalCache {
     }
 
     fn clear(&mut self) {
-        self.q.clear();
-        self.map.clear();
+        self.drop_all();
         self.evictions_total = 0;
         self.invalidations_total = 0;
     }
+
+    // ---- NEW: учёт страниц per-DB (квоты) ----
+
+    fn insert_entry(&mut self, key: CacheKey, ent: CacheEntry) {
+        if self.map.insert(key, ent).is_none() {
+            *self.per_db.entry(key.db_id).or_insert(0) += 1;
+        }
+    }
+
+    fn remove_entry(&mut self, key: &CacheKey) -> Option<CacheEntry> {
+        let ent = self.map.remove(key)?;
+        budget::release(Subsystem::PageCache, ent.buf.len() as u64);
+        if let Some(n) = self.per_db.get_mut(&key.db_id) {
+            *n -= 1;
+            if *n == 0 {
+                self.per_db.remove(&key.db_id);
+            }
+        }
+        Some(ent)
+    }
+
+    fn drop_all(&mut self) {
+        let bytes: usize = self.map.values().map(|e| e.buf.len()).sum();
+        budget::release(Subsystem::PageCache, bytes as u64);
+        self.q.clear();
+        self.map.clear();
+        self.per_db.clear();
+    }
+
+    /// Учесть запись в ResourceBudget: при нехватке вытесняем страницы кэша (second-chance),
+    /// пока запись не поместится; false — не поместилась и в пустой кэш.
+    fn admit(&mut self, ent: &CacheEntry) -> bool {
+        let n = ent.buf.len() as u64;
+        while !budget::try_charge(Subsystem::PageCache, n) {
+            if !self.evict_one() {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn db_len(&self, db_id: u64) -> usize {
+        self.per_db.get(&db_id).copied().unwrap_or(0)
+    }
+
+    fn at_quota(&self, db_id: u64) -> bool {
+        self.quotas
+            .get(&db_id)
+            .is_some_and(|&q| self.db_len(db_id) >= q)
+    }
+
+    /// Вытеснить самую старую страницу db_id (FIFO внутри БД; refbit не учитывается).
+    fn evict_one_of(&mut self, db_id: u64) -> bool {
+        let Some(pos) = self
+            .q
+            .iter()
+            .position(|k| k.db_id == db_id && self.map.contains_key(k))
+        else {
+            return false;
+        };
+        if let Some(k) = self.q.remove(pos) {
+            let _ = self.remove_entry(&k);
+            self.evictions_total = self.evictions_total.saturating_add(1);
+            return true;
+        }
+        false
+    }
+
+    fn set_quota(&mut self, db_id: u64, quota: Option<usize>) {
+        match quota {
+            Some(q) => {
+                self.quotas.insert(db_id, q);
+                while self.db_len(db_id) > q {
+                    if !self.evict_one_of(db_id) {
+                        break;
+                    }
+                }
+            }
+            None => {
+                self.quotas.remove(&db_id);
+            }
+        }
+    }
+}
+
+#[inline]
+fn seal_nonce(n: u64) -> Nonce<aes_gcm::aead::consts::U12> {
+    let mut b = [0u8; 12];
+    b[..8].copy_from_slice(&n.to_le_bytes());
+    *Nonce::from_slice(&b)
+}
+
+/// AAD запечатанной записи: запись нельзя подставить под чужой (db_id, page_id).
+#[inline]
+fn seal_aad(key: &CacheKey) -> [u8; 16] {
+    let mut a = [0u8; 16];
+    a[..8].copy_from_slice(&key.db_id.to_le_bytes());
+    a[8..].copy_from_slice(&key.page_id.to_le_bytes());
+    a
 }
 
 static GLOBAL_CACHE: OnceLock<Mutex<GlobalCache>> = OnceLock::new();
@@ -264,6 +623,37 @@ pub fn page_cache_invalidate(db_id: u64, page_id: u64, page_size: usize) {
     }
 }
 
+/// Hot page ids of a DB (by recency metadata), at most `max`.
+pub fn page_cache_hot_pages(db_id: u64, max: usize) -> Vec<u64> {
+    if let Ok(cg) = cache_lock().lock() {
+        return cg.hot_pages(db_id, max);
+    }
+    Vec::new()
+}
+
+/// Current write generation (incremented on every invalidate).
+pub fn page_cache_write_gen() -> u64 {
+    if let Ok(cg) = cache_lock().lock() {
+        return cg.write_gen;
+    }
+    0
+}
+
+/// Prewarm insert: succeeds only if the page is absent and no invalidation happened since `gen`.
+pub fn page_cache_put_prewarm(
+    db_id: u64,
+    page_id: u64,
+    buf: &[u8],
+    page_size: usize,
+    gen: u64,
+) -> bool {
+    if let Ok(mut cg) = cache_lock().lock() {
+        cg.init_if_needed(page_size);
+        return cg.put_prewarm(CacheKey { db_id, page_id }, buf, gen);
+    }
+    false
+}
+
 /// Diagnostics: total number of evictions since start (or last reconfigure/clear).
 pub fn page_cache_evictions_total() -> u64 {
     if let Ok(cg) = cache_lock().lock() {
@@ -280,6 +670,14 @@ pub fn page_cache_invalidations_total() -> u64 {
     0
 }
 
+/// Diagnostics: configured capacity in pages (0 — cache disabled).
+pub fn page_cache_capacity() -> usize {
+    if let Ok(cg) = cache_lock().lock() {
+        return cg.cap_pages;
+    }
+    0
+}
+
 /// Diagnostics: current number of pages in the cache.
 pub fn page_cache_len() -> usize {
     if let Ok(cg) = cache_lock().lock() {
@@ -287,3 +685,40 @@ pub fn page_cache_len() -> usize {
     }
     0
 }
+
+/// Set the storage policy of the page cache (process-wide). Changing the policy clears the cache.
+/// `mlock_max_bytes` caps locked memory for PageCachePolicy::Locked.
+pub fn page_cache_set_policy(policy: PageCachePolicy, mlock_max_bytes: u64) {
+    if let Ok(mut cg) = cache_lock().lock() {
+        cg.set_policy(policy, mlock_max_bytes);
+    }
+}
+
+/// Current storage policy of the page cache.
+pub fn page_cache_policy() -> PageCachePolicy {
+    if let Ok(cg) = cache_lock().lock() {
+        return cg.policy;
+    }
+    PageCachePolicy::Plaintext
+}
+
+/// Limit how many pages of one DB the cache may hold (None — no per-DB limit). Excess pages of
+/// that DB are evicted at once; at the limit, inserts evict the DB's own oldest page.
+pub fn page_cache_set_quota(db_id: u64, quota: Option<usize>) {
+    if let Ok(mut cg) = cache_lock().lock() {
+        cg.set_quota(db_id, quota);
+    }
+}
+
+/// Diagnostics: pages of a DB currently in the cache.
+pub fn page_cache_db_len(db_id: u64) -> usize {
+    if let Ok(cg) = cache_lock().lock() {
+        return cg.db_len(db_id);
+    }
+    0
+}
+
+/// Diagnostics: bytes currently mlock'ed by cache entries (PageCachePolicy::Locked).
+pub fn page_cache_locked_bytes() -> u64 {
+    LOCKED_BYTES.load(Ordering::Relaxed)
+}
@@ -4,11 +4,22 @@
 //! - commit_pages_batch: BEGIN(start) → N×IMAGE → COMMIT(last) (один fsync WAL), запись всех страниц,
 //!   truncate WAL, meta.last_lsn=last (в памяти).
 //! - commit_pages_batch_with_heads: BEGIN → IMAGE* → HEADS_UPDATE → COMMIT (один fsync WAL).
+//! - NEW: commit_pages_batch_with_heads_idem: то же + кадр IDEMPOTENCY (digest токена) после BEGIN.
+//! - NEW: commit_pages_batch_with_heads_expiry: то же + логический кадр EXPIRY перед COMMIT
+//!   (батч без страниц получает собственный LSN, чтобы HEADS_UPDATE/EXPIRY не делили LSN
+//!   с предыдущим коммитом).
+//! - NEW: батч только из HEADS_UPDATE (без страниц) тоже получает собственный LSN — иначе реплей
+//!   отбросил бы его LSN-гейтингом (wal_lsn > last_heads_lsn) как устаревший.
+//! - NEW: write_pages_unlogged — запись страниц без IMAGE-кадров (bulk load): LSN/трейлеры,
+//!   запись сегментов и обязательный fsync данных; видимыми страницы делает последующий
+//!   HEADS_UPDATE-батч.
 //!
 //! Оптимизация записи данных батча:
 //! - Сегменты открываются по одному разу на батч; страницы сегмента пишутся через BufWriter
 //!   (крупный буфер), отсортированно по offset. Это существенно снижает количество системных вызовов.
 //!   Если data_fsync=true — выполняется flush и sync_all() по завершении записи сегмента.
+//!   NEW: fsync'и сегментов откладываются до конца батча — каждый затронутый сегмент
+//!   сбрасывается один раз, либо один syncfs(2) на все (seg_syncfs, pager/segsync.rs).
 //!
 //! NEW (2.1 prep): TDE AES‑GCM tag в трейлере при включённом pager.tde_enabled.
 //! - После установки LSN трейлер заполняется либо CRC32C (по умолчанию), либо AEAD‑tag (AES‑256‑GCM).
@@ -24,25 +35,65 @@
 //!
 //! NEW (perf/env): размер буфера записи сегмента настраивается через ENV P1_SEG_WRITE_BUF_MB
 //! (по умолчанию 16 MiB).
+//!
+//! NEW: pipelining батч-коммита — fsync WAL и запись сегментов батча выполняются параллельно
+//! (см. fsync_wal_and_write_pages; ENV P1_COMMIT_PIPELINE=0 — последовательный режим).
+//!
+//! NEW: батч-запись инвалидирует page cache для записанных страниц (как write_page_raw).
+//!
+//! NEW: буфер записи сегментов арендуется в ResourceBudget (budget.rs, Subsystem::WriteBuffers):
+//! при тесном бюджете он уменьшается вдвое, вплоть до одной страницы.
+//!
+//! NEW: при history_index ротация WAL сначала индексирует его батчи в history.bin
+//! (wal/history.rs); ошибка индексации — [WARN], коммит не проваливается.
+//! Нужно для повторно используемых page_id (free-лист) и для generation-гейта prewarm.
+//!
+//! NEW (perf): в CRC-режиме трейлеры батча считаются одним вызовом page_update_checksums_batch
+//! (без копий страниц; крупные батчи — параллельно), см. page/checksum.rs.
+//!
+//! NEW: пока хэндл заморожен (Db::freeze), все коммиты и write_pages_unlogged возвращают
+//! DbFrozenError до записи в WAL (см. db/freeze.rs).
+//!
+//! NEW: коммиты — критические секции (Pager::critical): паника внутри отравляет хэндл,
+//! дальнейшие записи получают DbPoisonedError до reopen (db/poison.rs).
+//!
+//! NEW: каждый WAL-батч учитывается в per-handle счётчиках (Pager::stats) и, при
+//! зарегистрированном хуке (Db::set_instrumentation), сообщается в on_commit с длительностью.
 
 use anyhow::{anyhow, Result};
 use byteorder::{ByteOrder, LittleEndian};
 use std::collections::BTreeMap;
 use std::io::{Seek, SeekFrom, Write};
 use std::sync::OnceLock;
+use std::time::Instant;
 
+use crate::budget::{self, Subsystem};
+use crate::db::stats::CommitEvent;
+use crate::meta::{write_meta_overwrite, FEATURE_HEADS_DELTA};
+use crate::metrics::record_commit_pipelined;
 use crate::page::{
     kv_header_read_v3, kv_header_write_v3, ovf_header_read_v3, ovf_header_write_v3,
-    page_update_checksum, page_update_trailer_aead_with, KV_OFF_LSN, OFF_TYPE, OVF_OFF_LSN,
-    PAGE_MAGIC, PAGE_TYPE_KV_RH3, PAGE_TYPE_OVERFLOW3, TRAILER_LEN,
+    page_update_checksum, page_update_checksums_batch, page_update_trailer_aead_fmt,
+    set_page_key_fp, KV_OFF_LSN, OFF_TYPE, OVF_OFF_LSN, PAGE_MAGIC, PAGE_TYPE_KV_RH3,
+    PAGE_TYPE_OVERFLOW3, TRAILER_LEN,
 };
-use crate::wal::Wal;
+use crate::pager::cache::page_cache_invalidate;
+use crate::util::iostall::{io_watch, IoOp};
+use crate::wal::writer::wal_disable_fsync;
+use crate::wal::{index_wal_history, IdemDigest, Wal, WAL_ROTATE_SIZE};
 
 use super::core::Pager;
+use super::segsync::DirtySegments;
 
 impl Pager {
     /// Коммит одиночной страницы: WAL (BEGIN/IMAGE/COMMIT) + запись страницы.
     pub fn commit_page(&mut self, page_id: u64, page: &mut [u8]) -> Result<()> {
+        self.critical("commit_page", |p| p.commit_page_unguarded(page_id, page))
+    }
+
+    fn commit_page_unguarded(&mut self, page_id: u64, page: &mut [u8]) -> Result<()> {
+        self.ensure_writable("commit_page")?;
+        self.ensure_not_frozen()?;
         if page.len() != self.meta.page_size as usize {
             return Err(anyhow!(
                 "buffer size {} != page_size {}",
@@ -54,6 +105,8 @@ impl Pager {
             return Err(anyhow!("commit_page: bad page magic"));
         }
 
+        let t0 = self.instrumentation.as_ref().map(|_| Instant::now());
+
         // [1] LSN и трейлер (CRC32C или AEAD)
         let lsn = self.meta.last_lsn.wrapping_add(1);
         set_v3_page_lsn_mut(page, lsn)?;
@@ -73,40 +126,35 @@ impl Pager {
         self.write_page_raw(page_id, page)?;
 
         // [4] Ротация WAL
-        wal.maybe_truncate()?;
+        self.rotate_wal(&mut wal)?;
 
         // [5] Обновить last_lsn (ТОЛЬКО в памяти; без write_meta_overwrite)
         self.meta.last_lsn = lsn;
+
+        // [6] NEW: per-handle счётчики и хук on_commit
+        self.note_committed(lsn, lsn, 1, 0, t0);
         Ok(())
     }
 
     /// Групповой коммит страниц: один WAL‑батч и одна fsync WAL.
     /// Запись страниц сгруппирована по сегментам — одно открытие/один fsync на сегмент.
     pub fn commit_pages_batch(&mut self, pages: &mut [(u64, &mut [u8])]) -> Result<()> {
+        self.critical("commit_pages_batch", |p| {
+            p.commit_pages_batch_unguarded(pages)
+        })
+    }
+
+    fn commit_pages_batch_unguarded(&mut self, pages: &mut [(u64, &mut [u8])]) -> Result<()> {
+        self.ensure_writable("commit_pages_batch")?;
+        self.ensure_not_frozen()?;
         if pages.is_empty() {
             return Ok(());
         }
+        let t0 = self.instrumentation.as_ref().map(|_| Instant::now());
 
         // [1] Присвоить LSN и обновить трейлер каждой странице
-        let start_lsn = self.meta.last_lsn.wrapping_add(1);
-        let mut cur_lsn = start_lsn;
-        for (pid, page_ref) in pages.iter_mut() {
-            let page: &mut [u8] = &mut *page_ref;
-            if page.len() != self.meta.page_size as usize {
-                return Err(anyhow!(
-                    "buffer size {} != page_size {}",
-                    page.len(),
-                    self.meta.page_size
-                ));
-            }
-            if &page[..4] != PAGE_MAGIC {
-                return Err(anyhow!("commit_pages_batch: bad page magic"));
-            }
-            set_v3_page_lsn_mut(page, cur_lsn)?;
-            self.update_page_trailer(*pid, page, cur_lsn)?;
-            cur_lsn = cur_lsn.wrapping_add(1);
-        }
-        let last_lsn = cur_lsn.wrapping_sub(1);
+        let (start_lsn, last_lsn) =
+            self.assign_batch_lsns_and_trailers(pages, "commit_pages_batch")?;
 
         // [2] WAL батч: BEGIN → IMAGE* → COMMIT, один fsync WAL
         let mut wal = Wal::open_for_append(&self.root)?;
@@ -119,16 +167,18 @@ impl Pager {
         }
         wal.append_commit(last_lsn)?;
         wal.end_batch();
-        wal.fsync()?; // fsync WAL
 
-        // [3] Запись страниц в сегменты (через BufWriter)
-        write_pages_grouped_by_segment(self, pages)?;
+        // [3] fsync WAL + запись страниц в сегменты (через BufWriter), с pipelining
+        fsync_wal_and_write_pages(self, &mut wal, pages)?;
 
         // [4] Ротация WAL
-        wal.maybe_truncate()?;
+        self.rotate_wal(&mut wal)?;
 
         // [5] Обновить last_lsn (в памяти; без write_meta_overwrite)
         self.meta.last_lsn = last_lsn;
+
+        // [6] NEW: per-handle счётчики и хук on_commit
+        self.note_committed(start_lsn, last_lsn, pages.len(), 0, t0);
         Ok(())
     }
 
@@ -139,72 +189,263 @@ impl Pager {
         pages: &mut [(u64, &mut [u8])],
         dir_updates: &[(u32, u64)],
     ) -> Result<()> {
-        if pages.is_empty() && dir_updates.is_empty() {
+        self.commit_pages_batch_with_heads_idem(pages, dir_updates, None)
+    }
+
+    /// То же, что commit_pages_batch_with_heads, с опциональным idempotency-токеном:
+    /// BEGIN(start) → IDEMPOTENCY(digest) → IMAGE* → HEADS_UPDATE → COMMIT(last).
+    pub fn commit_pages_batch_with_heads_idem(
+        &mut self,
+        pages: &mut [(u64, &mut [u8])],
+        dir_updates: &[(u32, u64)],
+        idem: Option<&IdemDigest>,
+    ) -> Result<()> {
+        self.commit_batch_inner(pages, dir_updates, idem, &[])
+    }
+
+    /// То же, что commit_pages_batch_with_heads, с логическим кадром EXPIRY:
+    /// BEGIN(start) → IMAGE* → HEADS_UPDATE → EXPIRY(payload) → COMMIT(last).
+    pub fn commit_pages_batch_with_heads_expiry(
+        &mut self,
+        pages: &mut [(u64, &mut [u8])],
+        dir_updates: &[(u32, u64)],
+        expiry_payload: &[u8],
+    ) -> Result<()> {
+        self.commit_batch_inner(pages, dir_updates, None, expiry_payload)
+    }
+
+    fn commit_batch_inner(
+        &mut self,
+        pages: &mut [(u64, &mut [u8])],
+        dir_updates: &[(u32, u64)],
+        idem: Option<&IdemDigest>,
+        expiry_payload: &[u8],
+    ) -> Result<()> {
+        self.critical("commit_pages_batch_with_heads", |p| {
+            p.commit_batch_unguarded(pages, dir_updates, idem, expiry_payload)
+        })
+    }
+
+    fn commit_batch_unguarded(
+        &mut self,
+        pages: &mut [(u64, &mut [u8])],
+        dir_updates: &[(u32, u64)],
+        idem: Option<&IdemDigest>,
+        expiry_payload: &[u8],
+    ) -> Result<()> {
+        self.ensure_writable("commit_pages_batch")?;
+        self.ensure_not_frozen()?;
+        if pages.is_empty() && dir_updates.is_empty() && expiry_payload.is_empty() {
             return Ok(());
         }
+        // NEW: длительность коммита — только при зарегистрированном хуке (db/stats.rs)
+        let t0 = self.instrumentation.as_ref().map(|_| Instant::now());
 
         // [1] Присвоить LSN и трейлеры страницам
-        let start_lsn = self.meta.last_lsn.wrapping_add(1);
-        let mut cur_lsn = start_lsn;
-        for (pid, page_ref) in pages.iter_mut() {
-            let page: &mut [u8] = &mut *page_ref;
-            if page.len() != self.meta.page_size as usize {
-                return Err(anyhow!(
-                    "buffer size {} != page_size {}",
-                    page.len(),
-                    self.meta.page_size
-                ));
-            }
-            if &page[..4] != PAGE_MAGIC {
-                return Err(anyhow!("commit_pages_batch_with_heads: bad page magic"));
-            }
-            set_v3_page_lsn_mut(page, cur_lsn)?;
-            self.update_page_trailer(*pid, page, cur_lsn)?;
-            cur_lsn = cur_lsn.wrapping_add(1);
+        let (start_lsn, mut last_lsn) =
+            self.assign_batch_lsns_and_trailers(pages, "commit_pages_batch_with_heads")?;
+        if pages.is_empty() {
+            last_lsn = start_lsn;
         }
-        let last_lsn = cur_lsn.wrapping_sub(1);
 
         // [2] WAL: BEGIN → IMAGE* → HEADS_UPDATE → COMMIT (один fsync)
         let mut wal = Wal::open_for_append(&self.root)?;
+        wal.set_heads_delta(self.meta.flags & FEATURE_HEADS_DELTA != 0);
         wal.start_batch();
         wal.append_begin(start_lsn)?;
+        if let Some(d) = idem {
+            wal.append_idempotency(start_lsn, d)?;
+        }
         let mut lsn_it = start_lsn;
         for (pid, page) in pages.iter_mut() {
             wal.append_page_image(lsn_it, *pid, *page)?;
             lsn_it = lsn_it.wrapping_add(1);
         }
         wal.append_heads_update(last_lsn, dir_updates)?;
+        wal.append_expiry(last_lsn, expiry_payload)?;
         wal.append_commit(last_lsn)?;
         wal.end_batch();
-        wal.fsync()?; // fsync WAL
 
-        // [3] Запись страниц в сегменты (через BufWriter)
-        write_pages_grouped_by_segment(self, pages)?;
+        // [3] fsync WAL + запись страниц в сегменты (через BufWriter), с pipelining
+        fsync_wal_and_write_pages(self, &mut wal, pages)?;
 
         // [4] Ротация WAL
-        wal.maybe_truncate()?;
+        self.rotate_wal(&mut wal)?;
 
         // [5] Обновить last_lsn (в памяти; без write_meta_overwrite)
         self.meta.last_lsn = last_lsn;
+
+        // [6] NEW: per-handle счётчики и хук on_commit
+        self.note_committed(start_lsn, last_lsn, pages.len(), dir_updates.len(), t0);
+        // [7] NEW: затронутые бакеты — для авто-обновления bloom (db/bloom_refresh.rs)
+        self.bloom_refresh.note_touched(dir_updates);
         Ok(())
     }
 
-    // ---------- trailer helper (CRC32C or AEAD) ----------
+    /// NEW: учесть закоммиченный WAL-батч в счётчиках хэндла и сообщить хуку (db/stats.rs).
+    fn note_committed(
+        &self,
+        start_lsn: u64,
+        last_lsn: u64,
+        pages: usize,
+        heads: usize,
+        t0: Option<Instant>,
+    ) {
+        self.stats.note_commit(pages);
+        if let (Some(h), Some(t0)) = (self.instrumentation.as_ref(), t0) {
+            h.on_commit(&CommitEvent {
+                start_lsn,
+                last_lsn,
+                pages,
+                heads,
+                duration: t0.elapsed(),
+            });
+        }
+    }
+
+    /// Запись страниц БЕЗ WAL (bulk load): LSN (от meta.last_lsn+1) и трейлеры, запись в сегменты
+    /// и fsync данных независимо от data_fsync.
+    /// Страницы должны быть недостижимы (новые page_id, на них не ссылаются головы) до
+    /// последующего коммита HEADS_UPDATE — при сбое до него они остаются сиротами.
+    /// NEW: до записи страниц диапазон LSN и next_page_id фиксируются в meta на диске: WAL
+    /// сирот не описывает, и без резерва после сбоя новые коммиты получили бы те же page_id и
+    /// LSN — LSN-гейтинг следующего реплея отбросил бы их настоящие образы.
+    pub fn write_pages_unlogged(&mut self, pages: &mut [(u64, &mut [u8])]) -> Result<()> {
+        self.critical("write_pages_unlogged", |p| {
+            p.write_pages_unlogged_unguarded(pages)
+        })
+    }
+
+    fn write_pages_unlogged_unguarded(&mut self, pages: &mut [(u64, &mut [u8])]) -> Result<()> {
+        self.ensure_writable("write_pages_unlogged")?;
+        self.ensure_not_frozen()?;
+        if pages.is_empty() {
+            return Ok(());
+        }
+        let (_start_lsn, last_lsn) =
+            self.assign_batch_lsns_and_trailers(pages, "write_pages_unlogged")?;
+        self.meta.last_lsn = last_lsn;
+        write_meta_overwrite(&self.root, &self.meta)?;
+        write_pages_grouped_by_segment_with(self, pages, true)?;
+        Ok(())
+    }
+
+    // ---------- trailer helpers (CRC32C or AEAD) ----------
+
+    /// Присвоить страницам батча последовательные LSN (от meta.last_lsn+1) и заполнить трейлеры.
+    /// В CRC-режиме трейлеры считаются одним батчем (page_update_checksums_batch).
+    /// Возвращает (start_lsn, last_lsn).
+    fn assign_batch_lsns_and_trailers(
+        &mut self,
+        pages: &mut [(u64, &mut [u8])],
+        ctx: &str,
+    ) -> Result<(u64, u64)> {
+        let start_lsn = self.meta.last_lsn.wrapping_add(1);
+        let mut cur_lsn = start_lsn;
+        for (pid, page_ref) in pages.iter_mut() {
+            let page: &mut [u8] = page_ref;
+            if page.len() != self.meta.page_size as usize {
+                return Err(anyhow!(
+                    "buffer size {} != page_size {}",
+                    page.len(),
+                    self.meta.page_size
+                ));
+            }
+            if &page[..4] != PAGE_MAGIC {
+                return Err(anyhow!("{}: bad page magic", ctx));
+            }
+            set_v3_page_lsn_mut(page, cur_lsn)?;
+            if self.tde_enabled {
+                self.update_page_trailer(*pid, page, cur_lsn)?;
+            }
+            cur_lsn = cur_lsn.wrapping_add(1);
+        }
+        if !self.tde_enabled {
+            page_update_checksums_batch(pages, self.meta.checksum_kind)?;
+        }
+        Ok((start_lsn, cur_lsn.wrapping_sub(1)))
+    }
 
     #[inline]
     fn update_page_trailer(&mut self, page_id: u64, page: &mut [u8], lsn: u64) -> Result<()> {
         if self.tde_enabled {
-            let key = self.tde_key_bytes()?; // ensure_tde_key внутри
-            page_update_trailer_aead_with(page, key, page_id, lsn)
+            let aad_fmt = self.tde_aad_format(lsn);
+            self.ensure_tde_key()?;
+            // NEW: KID подписи — в заголовок до расчёта тега (чтение выберет ключ по нему)
+            set_page_key_fp(page, self.tde_key_fp);
+            let key = self.tde_key_bytes()?;
+            page_update_trailer_aead_fmt(page, key, page_id, lsn, aad_fmt)
         } else {
             page_update_checksum(page, self.meta.checksum_kind)
         }
     }
+
+    /// Ротация WAL (maybe_truncate); при history_index батчи, которые сейчас будут усечены,
+    /// сначала попадают в history.bin.
+    fn rotate_wal(&self, wal: &mut Wal) -> Result<()> {
+        if self.history_index && wal.file_len()? > WAL_ROTATE_SIZE {
+            if let Err(e) = index_wal_history(&self.root) {
+                eprintln!("[WARN] history index before WAL rotation failed: {:#}", e);
+            }
+        }
+        wal.maybe_truncate()
+    }
 }
 
 // ---------------- helpers (локальные для этого файла) ----------------
 
+/// fsync WAL и запись страниц батча в сегменты.
+///
+/// Pipelining (по умолчанию включён; P1_COMMIT_PIPELINE=0 — выключить): fsync WAL выполняется
+/// в отдельном (scoped) потоке, пока основной поток пишет страницы в сегменты.
+/// Инвариант WAL-before-data обеспечивается порядком завершения: функция возвращает Ok
+/// только когда завершились ОБА действия, и только после этого вызывающий код публикует
+/// изменения (meta.last_lsn, головы каталога). До публикации новые страницы ни на что не
+/// ссылаются, поэтому их ранняя запись на диск безопасна при сбое: реплей без COMMIT их
+/// игнорирует, а видимое состояние остаётся прежним.
+/// Для батча из одной страницы (и при P1_WAL_DISABLE_FSYNC) поток не создаётся.
+fn fsync_wal_and_write_pages(
+    pager: &mut Pager,
+    wal: &mut Wal,
+    pages: &mut [(u64, &mut [u8])],
+) -> Result<()> {
+    if !commit_pipeline_enabled() || pages.len() < 2 || wal_disable_fsync() {
+        wal.fsync()?; // fsync WAL
+        return write_pages_grouped_by_segment(pager, pages);
+    }
+
+    record_commit_pipelined();
+    std::thread::scope(|s| {
+        let h = s.spawn(|| wal.fsync());
+        let wr = write_pages_grouped_by_segment(pager, pages);
+        let fs = h.join().map_err(|_| anyhow!("WAL fsync thread panicked"))?;
+        // Ошибка fsync WAL приоритетна: батч не считается закоммиченным.
+        fs?;
+        wr
+    })
+}
+
+fn commit_pipeline_enabled() -> bool {
+    static ON: OnceLock<bool> = OnceLock::new();
+    *ON.get_or_init(|| {
+        std::env::var("P1_COMMIT_PIPELINE")
+            .ok()
+            .map(|s| s.to_ascii_lowercase())
+            .map(|s| !(s == "0" || s == "false" || s == "off" || s == "no"))
+            .unwrap_or(true)
+    })
+}
+
 fn write_pages_grouped_by_segment(pager: &mut Pager, pages: &mut [(u64, &mut [u8])]) -> Result<()> {
+    write_pages_grouped_by_segment_with(pager, pages, false)
+}
+
+/// force_sync=true — fsync сегментов независимо от data_fsync (нет WAL-копии страниц).
+fn write_pages_grouped_by_segment_with(
+    pager: &mut Pager,
+    pages: &mut [(u64, &mut [u8])],
+    force_sync: bool,
+) -> Result<()> {
     use std::io::BufWriter;
 
     let ps_u64 = pager.meta.page_size as u64;
@@ -216,7 +457,14 @@ fn write_pages_grouped_by_segment(pager: &mut Pager, pages: &mut [(u64, &mut [u8
         groups.entry(seg_no).or_default().push((off, idx));
     }
 
-    let buf_cap = seg_write_buf_bytes();
+    let buf_lease = budget::lease(
+        Subsystem::WriteBuffers,
+        seg_write_buf_bytes() as u64,
+        ps_u64,
+    );
+    let buf_cap = buf_lease.bytes() as usize;
+    let mut written: Vec<u64> = Vec::new();
+    let mut dirty = DirtySegments::default();
 
     for (seg_no, mut entries) in groups {
         // Отсортируем по off
@@ -224,7 +472,10 @@ fn write_pages_grouped_by_segment(pager: &mut Pager, pages: &mut [(u64, &mut [u8
 
         // Откроем сегмент 1 раз и обернём BufWriter’ом
         let file = pager.open_seg_rw(seg_no, false)?;
+        let seg = pager.seg_path(seg_no);
+        let seg_bytes = entries.len() as u64 * ps_u64;
         let mut bw = BufWriter::with_capacity(buf_cap, file);
+        let w_write = io_watch(IoOp::SegWrite, &seg, seg_bytes);
 
         // Пишем все страницы этого сегмента последовательно
         let mut cur_pos: Option<u64> = None;
@@ -237,15 +488,24 @@ fn write_pages_grouped_by_segment(pager: &mut Pager, pages: &mut [(u64, &mut [u8
             let buf: &[u8] = &pages[idx].1;
             bw.write_all(buf)?;
             cur_pos = Some(off + ps_u64);
+            written.push(pages[idx].0);
         }
 
-        // Завершение: flush + fsync (если включён)
+        // Завершение: flush; fsync (если включён) — один раз на сегмент в конце батча
         bw.flush()?;
-        if pager.data_fsync {
-            let inner = bw.get_ref();
-            let _ = inner.sync_all();
+        drop(w_write);
+
+        // Инвалидация — после flush, когда новые байты уже в файле
+        for pid in written.drain(..) {
+            page_cache_invalidate(pager.db_id, pid, ps_u64 as usize);
+        }
+
+        if force_sync || pager.data_fsync {
+            let file = bw.into_inner().map_err(|e| e.into_error())?;
+            dirty.push(seg, file, seg_bytes);
         }
     }
+    dirty.sync(pager.seg_syncfs, force_sync)?;
     Ok(())
 }
 
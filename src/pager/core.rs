@@ -1,21 +1,84 @@
 //! pager/core — ядро Pager: структура, open(), флаг data_fsync и общие помощники.
+//!
+//! NEW: ключи TDE берутся из KeyProvider (QuiverConfig::tde_key_provider или
+//! CompositeKeyProvider::from_env); кроме ключа текущего KID загружаются ключи прежних эпох
+//! key_journal — страницы, подписанные до ротации, проверяются ключом своей эпохи.
+//!
+//! NEW: проверочный блок ключа в meta (crypto/key_check.rs): ensure_tde_key сверяет с ним
+//! загруженный ключ и отвергает отсутствующий/неверный ключ ошибкой TdeKeyError; writer
+//! запечатывает блок ключом текущего KID (seal_tde_key_check).
+//!
+//! NEW: формат AAD трейлера выбирается по LSN страницы (tde_aad_format): v2 (page_id, тип,
+//! db_uuid) для LSN >= meta.tde_aad_since_lsn при FEATURE_TDE_AAD_V2, иначе v1.
+//!
+//! NEW: режим открытия (set_read_only, Db::open_ro). Read-only pager отвергает все мутирующие
+//! пути — commit_*, write_page_raw, write_pages_unlogged, allocate_*, ensure_allocated,
+//! free_page — ошибкой ReadOnlyError до любых изменений на диске (WAL, сегменты, free-лист).
+//!
+//! NEW: panic-safety — Pager::critical ловит панику в критической секции и отравляет хэндл;
+//! ensure_writable отравленного хэндла возвращает DbPoisonedError (db/poison.rs).
 
 use anyhow::{anyhow, Context, Result};
 use std::fs::OpenOptions;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicU8;
+use std::sync::Arc;
 
 use crate::crypto::{
-    EnvKeyProvider,
-    EnvKmsProvider,
+    seal_key_check,
+    verify_key_check,
+    CompositeKeyProvider, // цепочка keyring/env/static по умолчанию
     KeyJournal,
     KeyProvider,
-    KeyRing,     // стор обёрнутых DEK
-    KmsProvider, // для метода unwrap()
+    SecretKey32, // ключ в защищённой странице (crypto::harden)
+    TdeKeyError,
 };
-use crate::meta::{read_meta, MetaHeader};
+use crate::db::bloom_refresh::BloomRefreshState;
+use crate::db::freeze::{DbFrozenError, FreezeInfo};
+use crate::db::poison::{panic_message, DbPoisonedError, PoisonInfo};
+use crate::db::stats::{DbInstrumentation, HandleStats};
+use crate::meta::{
+    check_features, read_meta, write_meta_overwrite, MetaHeader, FEATURE_TDE_AAD_V2,
+};
+use crate::page::{kid_fingerprint, AadFormat};
+use crate::snapstore::branch::BranchSource;
 
+use super::alloc::{SegmentGrowth, DEFAULT_SEGMENT_GROW_CHUNK_BYTES};
+use super::trim::PUNCH_UNKNOWN;
 use super::{DATA_SEG_EXT, DATA_SEG_PREFIX, SEGMENT_SIZE};
 
+/// Ключ прежней TDE-эпохи: страницы с lsn в [since_lsn, until_lsn) подписаны им.
+#[derive(Clone, Debug)]
+pub(crate) struct EpochKey {
+    pub since_lsn: u64,
+    pub until_lsn: u64,
+    pub kid: String,
+    // NEW: отпечаток KID (заголовок страницы, page::kid_fingerprint)
+    pub fp: u32,
+    pub key: SecretKey32,
+}
+
+/// Запись отклонена: хэндл открыт read-only (Db::open_ro). Достаётся из anyhow через downcast_ref.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadOnlyError {
+    pub path: PathBuf,
+    /// Отклонённая операция (put, commit_page, free_page, ...).
+    pub op: &'static str,
+}
+
+impl std::fmt::Display for ReadOnlyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: database is open read-only: {} (writer-only op; open without read-only mode)",
+            self.op,
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for ReadOnlyError {}
+
 /// Низкоуровневый менеджер страниц.
 pub struct Pager {
     pub root: PathBuf,
@@ -26,13 +89,56 @@ pub struct Pager {
     // ----- TDE (prep) -----
     pub(crate) tde_enabled: bool,
     pub(crate) tde_kid: Option<String>,
-    pub(crate) tde_key: Option<[u8; 32]>,
+    pub(crate) tde_key: Option<SecretKey32>,
+    // NEW: KID загруженного tde_key (tde_kid → журнал → провайдер)
+    pub(crate) tde_key_kid: Option<String>,
+    // NEW: отпечаток KID tde_key — пишется в заголовок каждой подписанной страницы (0 — нет ключа)
+    pub(crate) tde_key_fp: u32,
+    // NEW: источник ключей (None — CompositeKeyProvider::from_env) и ключи прежних эпох
+    pub(crate) key_provider: Option<Arc<dyn KeyProvider>>,
+    pub(crate) tde_epoch_keys: Vec<EpochKey>,
 
     // ----- OVF threshold (prep) -----
     pub(crate) ovf_threshold_bytes: Option<usize>,
 
     // ----- Stable DbId -----
     pub(crate) db_id: u64,
+
+    // ----- NEW: per-handle счётчики и хук инструментирования (db/stats.rs) -----
+    pub(crate) stats: HandleStats,
+    pub(crate) instrumentation: Option<Arc<dyn DbInstrumentation>>,
+
+    // ----- NEW: бакеты, затронутые коммитами, для авто-обновления bloom (db/bloom_refresh.rs) -----
+    pub(crate) bloom_refresh: BloomRefreshState,
+
+    // ----- NEW: заморозка коммитов (Db::freeze, db/freeze.rs) -----
+    pub(crate) frozen: Option<FreezeInfo>,
+
+    // ----- NEW: паника в критической секции (db/poison.rs) -----
+    pub(crate) poisoned: Option<PoisonInfo>,
+
+    // ----- NEW: режим открытия (read-only → все записи отвергаются) -----
+    pub(crate) read_only: bool,
+
+    // ----- NEW: политика роста сегментов (pager/alloc.rs) -----
+    pub(crate) seg_growth: SegmentGrowth,
+    pub(crate) seg_grow_chunk_bytes: u64,
+    pub(crate) seg_fallocate: bool,
+
+    // ----- NEW: возврат освобождённых страниц ФС (pager/trim.rs) -----
+    pub(crate) punch_holes: bool,
+    /// Результат пробы поддержки punch hole (общий для клонов хэндла).
+    pub(crate) punch_support: Arc<AtomicU8>,
+
+    // ----- NEW: syncfs(2) для fsync сегментов батча (pager/segsync.rs) -----
+    pub(crate) seg_syncfs: bool,
+
+    // ----- NEW: writable-ветка снапшота (snapstore/branch.rs) -----
+    /// Источник страниц, ещё не записанных в локальные сегменты (None — обычная БД).
+    pub(crate) branch: Option<Arc<BranchSource>>,
+
+    // ----- NEW: индекс истории ключей перед усечением WAL (wal/history.rs) -----
+    pub(crate) history_index: bool,
 }
 
 impl Pager {
@@ -45,6 +151,8 @@ impl Pager {
                 m.version
             ));
         }
+        // NEW: compatibility gate — неизвестные required feature flags
+        check_features(root, &m)?;
         let db_id = compute_db_id(root);
         Ok(Self {
             root: root.to_path_buf(),
@@ -53,8 +161,26 @@ impl Pager {
             tde_enabled: false,
             tde_kid: None,
             tde_key: None,
+            tde_key_kid: None,
+            tde_key_fp: 0,
+            key_provider: None,
+            tde_epoch_keys: Vec::new(),
             ovf_threshold_bytes: None,
             db_id,
+            stats: HandleStats::default(),
+            instrumentation: None,
+            bloom_refresh: BloomRefreshState::default(),
+            frozen: None,
+            poisoned: None,
+            read_only: false,
+            seg_growth: SegmentGrowth::Exact,
+            seg_grow_chunk_bytes: DEFAULT_SEGMENT_GROW_CHUNK_BYTES,
+            seg_fallocate: false,
+            punch_holes: false,
+            seg_syncfs: false,
+            punch_support: Arc::new(AtomicU8::new(PUNCH_UNKNOWN)),
+            branch: BranchSource::open(root)?.map(Arc::new),
+            history_index: false,
         })
     }
 
@@ -71,6 +197,8 @@ impl Pager {
     pub fn set_tde_enabled(&mut self, on: bool) {
         self.tde_enabled = on;
         self.tde_key = None;
+        self.tde_key_kid = None;
+        self.tde_epoch_keys.clear();
     }
     #[inline]
     pub fn tde_enabled(&self) -> bool {
@@ -84,6 +212,8 @@ impl Pager {
     pub fn set_tde_kid<S: Into<String>>(&mut self, kid: Option<S>) {
         self.tde_kid = kid.map(Into::into);
         self.tde_key = None;
+        self.tde_key_kid = None;
+        self.tde_epoch_keys.clear();
     }
     #[inline]
     pub fn tde_kid(&self) -> Option<&str> {
@@ -94,12 +224,61 @@ impl Pager {
         self.tde_enabled = enabled;
         self.tde_kid = kid;
         self.tde_key = None;
+        self.tde_key_kid = None;
+        self.tde_epoch_keys.clear();
+    }
+
+    /// Источник ключей TDE (None — CompositeKeyProvider::from_env: keyring → env → static).
+    pub fn set_key_provider(&mut self, provider: Option<Arc<dyn KeyProvider>>) {
+        self.key_provider = provider;
+        self.tde_key = None;
+        self.tde_key_kid = None;
+        self.tde_epoch_keys.clear();
+    }
+
+    /// KID прежних эпох, чьи ключи загружены (проверка страниц до ротации).
+    pub fn tde_epoch_kids(&self) -> Vec<String> {
+        self.tde_epoch_keys.iter().map(|e| e.kid.clone()).collect()
+    }
+
+    /// NEW: загруженный ключ прежней эпохи по отпечатку KID из заголовка страницы.
+    pub(crate) fn tde_epoch_key_by_fp(&self, fp: u32) -> Option<&SecretKey32> {
+        self.tde_epoch_keys
+            .iter()
+            .find(|e| e.fp == fp)
+            .map(|e| &e.key)
+    }
+
+    /// NEW: имя KID по отпечатку (загруженные ключи, журнал эпох, проверочный блок meta);
+    /// неизвестный — "#<fp hex>".
+    pub fn tde_kid_name_for_fp(&self, fp: u32) -> String {
+        let journal = KeyJournal::open(&self.root)
+            .and_then(|j| j.epochs())
+            .unwrap_or_default();
+        self.tde_key_kid
+            .iter()
+            .chain(self.tde_epoch_keys.iter().map(|e| &e.kid))
+            .chain(journal.iter().map(|(_, k)| k))
+            .chain(std::iter::once(&self.meta.tde_check_kid))
+            .find(|k| !k.is_empty() && kid_fingerprint(k) == fp)
+            .cloned()
+            .unwrap_or_else(|| format!("#{:08x}", fp))
+    }
+
+    /// Ключ эпохи, которой подписана страница с данным lsn (если это прежняя эпоха).
+    pub(crate) fn tde_epoch_key_for(&self, lsn: u64) -> Option<&SecretKey32> {
+        self.tde_epoch_keys
+            .iter()
+            .find(|e| e.since_lsn <= lsn && lsn < e.until_lsn)
+            .map(|e| &e.key)
     }
 
     /// Загрузить 32‑байтный ключ для AES‑GCM.
-    /// Порядок:
-    /// 1) Если есть keyring.bin и запись для KID — используем KMS (EnvKmsProvider) для unwrap.
-    /// 2) Иначе — EnvKeyProvider (P1_TDE_KEY_HEX/BASE64).
+    /// Источник — key_provider, иначе CompositeKeyProvider::from_env (keyring.bin + KMS → ENV-ключ
+    /// → P1_TDE_KEYS; порядок — P1_TDE_KEY_SOURCES). KID: tde_kid → последний KID журнала →
+    /// default_kid провайдера. Затем best-effort подгружаются ключи прежних эпох журнала.
+    /// NEW: ключ сверяется с проверочным блоком meta (если ключ KID блока доступен):
+    /// отсутствующий ключ или несовпадение — TdeKeyError.
     pub fn ensure_tde_key(&mut self) -> Result<()> {
         if !self.tde_enabled {
             return Ok(());
@@ -108,51 +287,101 @@ impl Pager {
             return Ok(());
         }
 
+        let provider: Arc<dyn KeyProvider> = match &self.key_provider {
+            Some(p) => p.clone(),
+            None => Arc::new(CompositeKeyProvider::from_env(&self.root)?),
+        };
+        let epochs = KeyJournal::open(&self.root)
+            .and_then(|j| j.epochs())
+            .unwrap_or_default();
+
         // Определим KID
-        let kid_to_use: String = if let Some(k) = self.tde_kid.as_ref() {
-            k.clone()
-        } else if let Ok(j) = KeyJournal::open(&self.root) {
-            if let Some(k) = j.last_kid()? {
-                k
-            } else {
-                EnvKeyProvider::from_env()
-                    .map(|p| p.default_kid().to_string())
-                    .unwrap_or_else(|_| "default".to_string())
-            }
-        } else {
-            EnvKeyProvider::from_env()
-                .map(|p| p.default_kid().to_string())
-                .unwrap_or_else(|_| "default".to_string())
+        let kid_to_use: String = match (self.tde_kid.as_ref(), epochs.last()) {
+            (Some(k), _) => k.clone(),
+            (None, Some((_, k))) => k.clone(),
+            (None, None) => provider.default_kid().to_string(),
         };
 
-        // 1) Попробуем KeyRing + KMS (если есть запись для KID)
-        if let Ok(kr) = KeyRing::open(&self.root) {
-            if let Ok(Some(wrapped)) = kr.get(&kid_to_use) {
-                let kms = EnvKmsProvider::from_env()
-                    .context("EnvKmsProvider (set P1_KMS_KEK_HEX or P1_KMS_KEK_BASE64)")?;
-                let (_kid, dek) = kms
-                    .unwrap(&wrapped)
-                    .with_context(|| format!("KMS unwrap for KID '{}'", kid_to_use))?;
-                if dek.len() != 32 {
-                    return Err(anyhow!("unwrapped DEK must be 32 bytes, got {}", dek.len()));
+        let km = provider.key(&kid_to_use).map_err(|e| {
+            crate::metrics::record_tde_key_check_failure();
+            TdeKeyError {
+                path: self.root.clone(),
+                kid: kid_to_use.clone(),
+                missing: true,
+                detail: format!("{:#}", e),
+            }
+        })?;
+
+        // Блок запечатан ключом другого KID (ротация) — сверяем его ключом, если он доступен
+        if !self.meta.tde_check.is_empty() {
+            let check_kid = self.meta.tde_check_kid.clone();
+            let ok = if check_kid == kid_to_use {
+                Some(verify_key_check(&km.key, &check_kid, &self.meta.tde_check))
+            } else {
+                provider
+                    .key(&check_kid)
+                    .ok()
+                    .map(|k| verify_key_check(&k.key, &check_kid, &self.meta.tde_check))
+            };
+            if ok == Some(false) {
+                crate::metrics::record_tde_key_check_failure();
+                return Err(TdeKeyError {
+                    path: self.root.clone(),
+                    kid: check_kid,
+                    missing: false,
+                    detail: String::new(),
                 }
-                let mut key = [0u8; 32];
-                key.copy_from_slice(&dek[..32]);
-                self.tde_key = Some(key);
-                return Ok(());
+                .into());
             }
         }
+        self.tde_key = Some(SecretKey32::new(km.key));
+        self.tde_key_fp = kid_fingerprint(&kid_to_use);
+        self.tde_key_kid = Some(kid_to_use.clone());
 
-        // 2) Fallback на EnvKeyProvider
-        let provider = EnvKeyProvider::from_env()
-            .context("EnvKeyProvider::from_env (set P1_TDE_KEY_HEX or P1_TDE_KEY_BASE64)")?;
-        let km = provider
-            .key(&kid_to_use)
-            .with_context(|| format!("load TDE key for KID '{}'", kid_to_use))?;
-        self.tde_key = Some(km.key);
+        // Ключи прежних эпох: отсутствующий ключ не ошибка (страницы эпохи не пройдут AEAD)
+        self.tde_epoch_keys.clear();
+        for (i, (since, kid)) in epochs.iter().enumerate() {
+            if *kid == kid_to_use {
+                continue;
+            }
+            let until = epochs.get(i + 1).map(|(s, _)| *s).unwrap_or(u64::MAX);
+            if let Ok(km) = provider.key(kid) {
+                self.tde_epoch_keys.push(EpochKey {
+                    since_lsn: *since,
+                    until_lsn: until,
+                    kid: kid.clone(),
+                    fp: kid_fingerprint(kid),
+                    key: SecretKey32::new(km.key),
+                });
+            }
+        }
         Ok(())
     }
 
+    /// NEW: запечатать проверочный блок meta ключом текущего KID (writer, после ensure_tde_key).
+    /// Блок уже под этим KID — ничего не делает; true — meta переписана.
+    pub fn seal_tde_key_check(&mut self) -> Result<bool> {
+        if !self.tde_enabled || self.read_only {
+            return Ok(false);
+        }
+        self.ensure_tde_key()?;
+        let kid = match self.tde_key_kid.clone() {
+            Some(k) if k.len() <= u8::MAX as usize => k,
+            _ => return Ok(false),
+        };
+        if !self.meta.tde_check.is_empty() && self.meta.tde_check_kid == kid {
+            return Ok(false);
+        }
+        let key = self
+            .tde_key
+            .as_deref()
+            .ok_or_else(|| anyhow!("TDE key is not available"))?;
+        self.meta.tde_check = seal_key_check(key, &kid)?;
+        self.meta.tde_check_kid = kid;
+        write_meta_overwrite(&self.root, &self.meta)?;
+        Ok(true)
+    }
+
     /// Получить ссылку на 32‑байтный ключ (после ensure_tde_key()).
     pub fn tde_key_bytes(&mut self) -> Result<&[u8; 32]> {
         if !self.tde_enabled {
@@ -160,18 +389,118 @@ impl Pager {
         }
         self.ensure_tde_key()?;
         self.tde_key
-            .as_ref()
+            .as_deref()
             .ok_or_else(|| anyhow!("TDE key is not available"))
     }
 
+    /// Формат AAD для AEAD-трейлера страницы с данным LSN.
+    #[inline]
+    pub(crate) fn tde_aad_format(&self, lsn: u64) -> AadFormat {
+        if self.meta.flags & FEATURE_TDE_AAD_V2 != 0 && lsn >= self.meta.tde_aad_since_lsn {
+            AadFormat::V2 {
+                db_uuid: self.meta.db_uuid,
+            }
+        } else {
+            AadFormat::V1
+        }
+    }
+
+    // ----- NEW: режим открытия -----
+    /// Read-only: все мутирующие операции pager'а возвращают ReadOnlyError.
+    pub fn set_read_only(&mut self, ro: bool) {
+        self.read_only = ro;
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Гейт записи: Err(ReadOnlyError) для read-only хэндла,
+    /// NEW: Err(DbPoisonedError) для отравленного паникой.
+    pub fn ensure_writable(&self, op: &'static str) -> Result<()> {
+        if let Some(p) = &self.poisoned {
+            crate::metrics::record_write_rejected_poisoned();
+            return Err(DbPoisonedError {
+                path: self.root.clone(),
+                op: p.op,
+                message: p.message.clone(),
+            }
+            .into());
+        }
+        if !self.read_only {
+            return Ok(());
+        }
+        crate::metrics::record_write_rejected_readonly();
+        Err(ReadOnlyError {
+            path: self.root.clone(),
+            op,
+        }
+        .into())
+    }
+
     // ----- OVF threshold -----
     pub fn set_ovf_threshold_bytes(&mut self, thr: Option<usize>) {
         self.ovf_threshold_bytes = thr;
     }
 
+    /// NEW: индексировать батчи WAL в history.bin перед ротацией (open_with_config).
+    pub fn set_history_index(&mut self, on: bool) {
+        self.history_index = on;
+    }
+
+    pub fn history_index(&self) -> bool {
+        self.history_index
+    }
+
     // ---------------- internal helpers ----------------
 
     /// Сколько страниц помещается в один сегмент при заданном page_size.
+    /// NEW: коммиты запрещены, пока хэндл заморожен (Db::freeze) — DbFrozenError.
+    pub(crate) fn ensure_not_frozen(&self) -> Result<()> {
+        match &self.frozen {
+            None => Ok(()),
+            Some(f) => {
+                crate::metrics::record_commit_rejected_frozen();
+                Err(DbFrozenError {
+                    path: self.root.clone(),
+                    lsn: f.lsn,
+                }
+                .into())
+            }
+        }
+    }
+
+    /// NEW: критическая секция — паника в f отравляет хэндл и раскручивается дальше.
+    pub(crate) fn critical<T>(
+        &mut self,
+        op: &'static str,
+        f: impl FnOnce(&mut Pager) -> Result<T>,
+    ) -> Result<T> {
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(self))) {
+            Ok(r) => r,
+            Err(payload) => {
+                self.poison(op, payload.as_ref());
+                std::panic::resume_unwind(payload)
+            }
+        }
+    }
+
+    /// Отравить хэндл (первая причина сохраняется: вложенные секции не перетирают её).
+    pub(crate) fn poison(&mut self, op: &'static str, payload: &(dyn std::any::Any + Send)) {
+        if self.poisoned.is_some() {
+            return;
+        }
+        let message = panic_message(payload);
+        crate::metrics::record_db_poisoned();
+        eprintln!(
+            "[ERROR] {}: panic in {} ({}); handle poisoned, writes refused until reopen",
+            self.root.display(),
+            op,
+            message
+        );
+        self.poisoned = Some(PoisonInfo { op, message });
+    }
+
     pub(crate) fn pages_per_seg(&self) -> u64 {
         let ps = self.meta.page_size as u64;
         (SEGMENT_SIZE / ps).max(1)
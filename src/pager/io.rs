@@ -3,8 +3,13 @@
 //! - read_page: чтение + проверка трейлера (CRC32C по умолчанию; AES‑GCM при TDE)
 //!   с процессным page cache
 //! - write_page_raw: запись + (опциональный) fsync данных сегмента
-//! - free_page: поместить page_id в free‑лист (минимальная реализация 2.0)
+//! - free_page: поместить page_id в free‑лист (минимальная реализация 2.0);
+//!   NEW: при punch_holes — сразу вернуть блоки страницы ФС (pager/trim.rs)
 //! - prefetch_page — прогревает страницу в процессный page cache
+//! - read_page_uncached — чтение мимо кэша (scrub), та же проверка трейлера
+//! - read_page_ra — чтение с readahead-окном для обхода цепочек (bucket chain / OVERFLOW)
+//! - NEW: writable-ветка (snapstore/branch.rs): страница, не записанная в локальный сегмент,
+//!   читается из SnapStore снапшота (та же проверка трейлера); readahead для ветки выключен
 //!
 //! Совместимость TDE:
 //! - Если TDE включён, для прочитанной страницы сначала проверяется AEAD‑тег.
@@ -21,8 +26,14 @@
 //! - По умолчанию не кэшируем OVERFLOW‑страниц; включить можно ENV P1_PAGE_CACHE_OVF=1.
 //! - Метрика cache_miss инкрементируется только для страниц, которые потенциально кэшируются.
 //! - NEW: Строгий запрет чтения “за хвост” логической аллокации: P1_READ_BEYOND_ALLOC_STRICT=1.
-
-use anyhow::{anyhow, Result};
+//! - NEW: Readahead для цепочек: ReadaheadWindow + Pager::read_page_ra.
+//!   При промахе окна одним read_exact читаются до K соседних страниц сегмента
+//!   (K = P1_READAHEAD_PAGES, по умолчанию 8; 0|1 — выключено). Направление окна адаптивное:
+//!   если цепочка идёт к меньшим page_id (типично для KV-цепочек, где новые страницы
+//!   prepend'ятся), окно заполняется «назад», иначе — «вперёд» (OVERFLOW-цепочки).
+//!   Каждая страница из окна проходит ту же проверку трейлера, что и read_page.
+
+use anyhow::{anyhow, Context, Result};
 use std::io::{Read, Seek, SeekFrom, Write};
 
 // Нужен для чтения lsn из заголовка (KV/Ovf)
@@ -30,11 +41,15 @@ use byteorder::{ByteOrder, LittleEndian};
 
 use crate::crypto::KeyJournal;
 use crate::free::FreeList;
-use crate::metrics::{record_cache_hit, record_cache_miss};
+use crate::metrics::{
+    record_cache_hit, record_cache_miss, record_readahead_fill, record_readahead_hit,
+    record_tde_epoch_key_verify, record_tde_page_kid_missing,
+};
 use crate::page::{
+    page_key_fp,
     page_trailer_is_zero_crc32,
     page_verify_checksum,
-    page_verify_trailer_aead_with,
+    page_verify_trailer_aead_fmt,
     verify_page_crc_strict_kind, // Единая строгая CRC‑проверка из модуля page
     KV_OFF_LSN,
     OFF_TYPE,
@@ -42,9 +57,11 @@ use crate::page::{
     PAGE_MAGIC,
     PAGE_TYPE_KV_RH3,
     PAGE_TYPE_OVERFLOW3,
-}; // NEW: Journal для epoch‑aware TDE fallback
+};
+use std::sync::OnceLock; // NEW: Journal для epoch‑aware TDE fallback
 
 use super::core::Pager;
+use crate::util::iostall::{io_watch, IoOp};
 
 // NEW: подключаем процессный кэш
 use crate::pager::cache::{
@@ -92,6 +109,38 @@ fn read_beyond_alloc_strict() -> bool {
         .unwrap_or(false)
 }
 
+// NEW: размер readahead-окна (страниц). 0|1 — readahead выключен.
+fn readahead_pages() -> u64 {
+    static RA: OnceLock<u64> = OnceLock::new();
+    *RA.get_or_init(|| {
+        std::env::var("P1_READAHEAD_PAGES")
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(8)
+    })
+}
+
+/// Окно readahead для последовательного обхода цепочки.
+/// Живёт на стороне вызывающего кода (один экземпляр на обход), Pager остаётся `&self`.
+#[derive(Default)]
+pub struct ReadaheadWindow {
+    start: u64,
+    count: u64,
+    buf: Vec<u8>,
+    last_pid: Option<u64>,
+}
+
+impl ReadaheadWindow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    fn contains(&self, page_id: u64) -> bool {
+        self.count > 0 && page_id >= self.start && page_id < self.start + self.count
+    }
+}
+
 // --------------------------- Обёртки для совместимости ---------------------------
 
 /// Programmatic configuration of the global page cache (process-wide).
@@ -110,6 +159,7 @@ impl Pager {
     /// - Если page_id >= meta.next_page_id — выделяем до неё включительно (allocate_pages).
     /// - Иначе убеждаемся, что соответствующий сегмент имеет достаточную длину.
     pub fn ensure_allocated(&mut self, page_id: u64) -> Result<()> {
+        self.ensure_writable("ensure_allocated")?;
         let (seg_no, off) = self.locate(page_id);
 
         if page_id >= self.meta.next_page_id {
@@ -118,15 +168,11 @@ impl Pager {
             return Ok(());
         }
 
-        // Убедимся, что сегмент физически достаточно длинный.
+        // Убедимся, что сегмент физически достаточно длинный (политика роста — pager/alloc.rs).
         let f = self.open_seg_rw(seg_no, true)?;
         let need_len = off + (self.meta.page_size as u64);
-        let cur_len = f.metadata()?.len();
-        if cur_len < need_len {
-            f.set_len(need_len)?;
-            if self.data_fsync {
-                let _ = f.sync_all();
-            }
+        if self.grow_segment(&f, seg_no, need_len)? && self.data_fsync {
+            let _ = f.sync_all();
         }
         Ok(())
     }
@@ -186,15 +232,177 @@ impl Pager {
             debug_assert_eq!(src.len(), buf.len());
             (&mut *buf).copy_from_slice(&src);
             record_cache_hit();
+            self.stats.note_cache_hit();
             return Ok(());
         }
 
-        // Miss: читаем с диска
+        // Miss: читаем с диска и верифицируем трейлер
+        self.read_page_from_disk_verified(page_id, buf)?;
+        self.cache_page_if_eligible(page_id, buf);
+
+        Ok(())
+    }
+
+    /// Положить только что проверенную страницу в кэш, если её тип кэшируемый.
+    fn cache_page_if_eligible(&self, page_id: u64, buf: &[u8]) {
+        let ps = self.meta.page_size as usize;
+        let mut cache_ok = false;
+        if buf.len() >= 12 && &buf[0..4] == PAGE_MAGIC {
+            let ptype = LittleEndian::read_u16(&buf[OFF_TYPE..OFF_TYPE + 2]);
+            cache_ok = match ptype {
+                t if t == PAGE_TYPE_OVERFLOW3 => cache_ovf_enabled(),
+                t if t == PAGE_TYPE_KV_RH3 => true,
+                _ => false,
+            };
+        }
+
+        if cache_ok {
+            pc_put(self.db_id, page_id, buf, ps);
+            record_cache_miss();
+            self.stats.note_cache_miss();
+        }
+    }
+
+    /// Прочитать страницу напрямую с диска (минуя page cache) и проверить трейлер.
+    /// Используется scrub-сканером: кэш не должен маскировать повреждения на носителе.
+    /// Кэш не заполняется.
+    pub fn read_page_uncached(&self, page_id: u64, buf: &mut [u8]) -> Result<()> {
+        let ps = self.meta.page_size as usize;
+        if buf.len() != ps {
+            return Err(anyhow!(
+                "buffer size {} != page_size {}",
+                buf.len(),
+                self.meta.page_size
+            ));
+        }
+        if self.tde_enabled && self.tde_key.is_none() {
+            return Err(anyhow!(
+                "TDE is enabled but key is not loaded; ensure pager.ensure_tde_key() on open"
+            ));
+        }
+        self.read_page_from_disk_verified(page_id, buf)
+    }
+
+    /// Чтение страницы цепочки с readahead-окном.
+    /// Семантика результата идентична read_page (включая кэширование KV-страниц).
+    pub fn read_page_ra(
+        &self,
+        page_id: u64,
+        buf: &mut [u8],
+        win: &mut ReadaheadWindow,
+    ) -> Result<()> {
+        let k = readahead_pages();
+        let ps = self.meta.page_size as usize;
+        let prev = win.last_pid.replace(page_id);
+        // Первая страница обхода: направление ещё неизвестно — обычное чтение.
+        if k <= 1 || prev.is_none() || buf.len() != ps || page_id >= self.meta.next_page_id {
+            return self.read_page(page_id, buf);
+        }
+        if (self.tde_enabled && self.tde_key.is_none()) || self.branch.is_some() {
+            return self.read_page(page_id, buf);
+        }
+
+        // Кэш приоритетнее окна
+        if let Some(src) = pc_get(self.db_id, page_id, ps) {
+            buf.copy_from_slice(&src);
+            record_cache_hit();
+            self.stats.note_cache_hit();
+            return Ok(());
+        }
+
+        if !win.contains(page_id) {
+            if self.fill_readahead_window(page_id, prev, k, win).is_err() {
+                // например, короткий сегмент — откатываемся на одиночное чтение
+                win.count = 0;
+                return self.read_page(page_id, buf);
+            }
+        } else {
+            record_readahead_hit();
+        }
+
+        let idx = (page_id - win.start) as usize;
+        buf.copy_from_slice(&win.buf[idx * ps..(idx + 1) * ps]);
+        self.verify_page_trailer(page_id, buf)?;
+        self.cache_page_if_eligible(page_id, buf);
+        Ok(())
+    }
+
+    /// Заполнить окно одним последовательным чтением (в пределах сегмента и next_page_id).
+    fn fill_readahead_window(
+        &self,
+        page_id: u64,
+        prev: Option<u64>,
+        k: u64,
+        win: &mut ReadaheadWindow,
+    ) -> Result<()> {
+        let ps = self.meta.page_size as u64;
+        let pps = self.pages_per_seg();
+        let seg_first = (page_id / pps) * pps;
+        let seg_end = (seg_first + pps).min(self.meta.next_page_id);
+
+        // Направление: цепочка «спускается» к меньшим pid → окно назад
+        let backward = matches!(prev, Some(p) if page_id < p);
+        let (start, end) = if backward {
+            (page_id.saturating_sub(k - 1).max(seg_first), page_id + 1)
+        } else {
+            (page_id, (page_id + k).min(seg_end))
+        };
+        let count = end - start;
+
+        let (seg_no, off) = self.locate(start);
+        let mut f = self.open_seg_rw(seg_no, false)?;
+        win.buf.resize((count * ps) as usize, 0);
+        f.seek(SeekFrom::Start(off))?;
+        f.read_exact(&mut win.buf)?;
+        win.start = start;
+        win.count = count;
+        record_readahead_fill(count);
+        Ok(())
+    }
+
+    /// Общая часть read_page/read_page_uncached: чтение с диска + TDE-aware верификация трейлера.
+    fn read_page_from_disk_verified(&self, page_id: u64, buf: &mut [u8]) -> Result<()> {
+        if let Some(br) = self.branch.as_deref() {
+            match self.read_local_page(page_id, buf)? {
+                Some(true) => {}
+                _ if br.fetch(page_id, buf)? => {}
+                Some(false) => {}
+                None => {
+                    return Err(anyhow!(
+                        "page {} is beyond its segment (branch of snapshot '{}')",
+                        page_id,
+                        br.info.snapshot_id
+                    ))
+                }
+            }
+            return self.verify_page_trailer(page_id, buf);
+        }
+        let (seg_no, off) = self.locate(page_id);
+        let mut f = self.open_seg_rw(seg_no, false)?;
+        f.seek(SeekFrom::Start(off))?;
+        f.read_exact(buf)?;
+        self.verify_page_trailer(page_id, buf)
+    }
+
+    /// NEW: сырое чтение страницы из локального сегмента (без проверки трейлера).
+    /// Some(true) — страница записана (PAGE_MAGIC); Some(false) — нули/мусор; None — сегмент
+    /// короче страницы (buf обнулён).
+    pub(crate) fn read_local_page(&self, page_id: u64, buf: &mut [u8]) -> Result<Option<bool>> {
+        let (seg_no, off) = self.locate(page_id);
+        let seg = self.seg_path(seg_no);
+        let seg_len = std::fs::metadata(&seg).map(|m| m.len()).unwrap_or(0);
+        if seg_len < off + buf.len() as u64 {
+            buf.fill(0);
+            return Ok(None);
+        }
         let mut f = self.open_seg_rw(seg_no, false)?;
         f.seek(SeekFrom::Start(off))?;
         f.read_exact(buf)?;
+        Ok(Some(&buf[0..4] == PAGE_MAGIC))
+    }
 
-        // Верификация трейлера (TDE-aware)
+    /// Верификация трейлера уже прочитанной страницы (TDE-aware, epoch-aware fallback).
+    fn verify_page_trailer(&self, page_id: u64, buf: &[u8]) -> Result<()> {
         if self.tde_enabled {
             if &buf[0..4] != PAGE_MAGIC {
                 return Err(anyhow!("bad page magic on TDE read"));
@@ -211,9 +419,41 @@ impl Pager {
             };
             let key = self
                 .tde_key
-                .as_ref()
+                .as_deref()
                 .ok_or_else(|| anyhow!("TDE key missing"))?;
-            let ok_aead = page_verify_trailer_aead_with(buf, key, page_id, lsn)?;
+            let aad_fmt = self.tde_aad_format(lsn);
+            // NEW: KID подписи из заголовка — ключ прежней эпохи выбирается сразу
+            let fp = page_key_fp(buf);
+            let foreign_fp = fp != 0 && fp != self.tde_key_fp;
+            let mut ok_aead = false;
+            if foreign_fp {
+                if let Some(ek) = self.tde_epoch_key_by_fp(fp) {
+                    ok_aead = page_verify_trailer_aead_fmt(buf, ek, page_id, lsn, aad_fmt)?;
+                    if ok_aead {
+                        record_tde_epoch_key_verify();
+                    }
+                } else {
+                    record_tde_page_kid_missing();
+                    return Err(anyhow!(
+                        "page {} is signed with TDE KID {} whose key is not loaded (lsn={})",
+                        page_id,
+                        self.tde_kid_name_for_fp(fp),
+                        lsn
+                    ));
+                }
+            }
+            if !ok_aead {
+                ok_aead = page_verify_trailer_aead_fmt(buf, key, page_id, lsn, aad_fmt)?;
+            }
+            if !ok_aead && fp == 0 {
+                // NEW: страница прежней эпохи (до ротации) — ключ её KID
+                if let Some(ek) = self.tde_epoch_key_for(lsn) {
+                    ok_aead = page_verify_trailer_aead_fmt(buf, ek, page_id, lsn, aad_fmt)?;
+                    if ok_aead {
+                        record_tde_epoch_key_verify();
+                    }
+                }
+            }
             if !ok_aead {
                 // Жёсткий режим — сразу ошибка
                 if tde_strict() {
@@ -260,23 +500,6 @@ impl Pager {
                 return Err(anyhow!("page {} checksum mismatch", page_id));
             }
         }
-
-        // Решим, стоит ли кэшировать страницу (по типу)
-        let mut cache_ok = false;
-        if buf.len() >= 12 && &buf[0..4] == PAGE_MAGIC {
-            let ptype = LittleEndian::read_u16(&buf[OFF_TYPE..OFF_TYPE + 2]);
-            cache_ok = match ptype {
-                t if t == PAGE_TYPE_OVERFLOW3 => cache_ovf_enabled(),
-                t if t == PAGE_TYPE_KV_RH3 => true,
-                _ => false,
-            };
-        }
-
-        if cache_ok {
-            pc_put(self.db_id, page_id, buf, ps);
-            record_cache_miss();
-        }
-
         Ok(())
     }
 
@@ -286,6 +509,30 @@ impl Pager {
         self.write_page_raw_with_fsync(page_id, buf, true)
     }
 
+    /// NEW: записать страницу page_id клоном ps байт файла src с offset 0 (reflink, util/reflink.rs).
+    /// Ok(false) — ФС не умеет клонировать диапазон; вызывающий код пишет байты write_page_raw.
+    pub(crate) fn clone_page_raw_from(
+        &mut self,
+        page_id: u64,
+        src: &std::fs::File,
+    ) -> Result<bool> {
+        self.ensure_writable("clone_page_raw_from")?;
+        self.ensure_not_frozen()?;
+        let ps = self.meta.page_size as u64;
+        self.ensure_allocated(page_id)?;
+        let (seg_no, off) = self.locate(page_id);
+        let f = self.open_seg_rw(seg_no, false)?;
+        if !crate::util::reflink::clone_range(src, 0, &f, off, ps) {
+            return Ok(false);
+        }
+        if self.data_fsync {
+            let _w = io_watch(IoOp::SegFsync, &self.seg_path(seg_no), ps);
+            let _ = f.sync_all();
+        }
+        pc_invalidate(self.db_id, page_id, ps as usize);
+        Ok(true)
+    }
+
     /// Вариант записи с управлением fsync данными (используется батч‑коммитом).
     /// Если do_fsync=false — только записывает; fsync данных должен сделать вызывающий код.
     pub(crate) fn write_page_raw_with_fsync(
@@ -294,6 +541,8 @@ impl Pager {
         buf: &[u8],
         do_fsync: bool,
     ) -> Result<()> {
+        self.ensure_writable("write_page_raw")?;
+        self.ensure_not_frozen()?;
         let ps = self.meta.page_size as usize;
 
         if buf.len() != ps {
@@ -308,9 +557,14 @@ impl Pager {
 
         let (seg_no, off) = self.locate(page_id);
         let mut f = self.open_seg_rw(seg_no, false)?;
-        f.seek(SeekFrom::Start(off))?;
-        f.write_all(buf)?;
+        let seg = self.seg_path(seg_no);
+        {
+            let _w = io_watch(IoOp::SegWrite, &seg, ps as u64);
+            f.seek(SeekFrom::Start(off))?;
+            f.write_all(buf)?;
+        }
         if do_fsync && self.data_fsync {
+            let _w = io_watch(IoOp::SegFsync, &seg, ps as u64);
             let _ = f.sync_all();
         }
 
@@ -319,11 +573,34 @@ impl Pager {
         Ok(())
     }
 
+    /// NEW: fsync всех существующих сегментов данных независимо от data_fsync (Db::freeze).
+    /// Возвращает число синхронизированных сегментов.
+    pub(crate) fn sync_all_segments(&self) -> Result<u64> {
+        let pps = self.pages_per_seg();
+        let segs = self.meta.next_page_id.div_ceil(pps);
+        let mut synced = 0u64;
+        // номера сегментов начинаются с 1 (см. locate)
+        for seg_no in 1..=segs {
+            let seg = self.seg_path(seg_no);
+            if !seg.exists() {
+                continue;
+            }
+            let f = self.open_seg_rw(seg_no, false)?;
+            let _w = io_watch(IoOp::SegFsync, &seg, 0);
+            f.sync_all()
+                .with_context(|| format!("fsync {}", seg.display()))?;
+            synced += 1;
+        }
+        Ok(synced)
+    }
+
     /// Поместить страницу в free‑лист (минимальная реализация 2.0).
     /// Замечания:
     /// - Не зануляет данные страницы на диске; только добавляет page_id в `<root>/free`.
+    ///   NEW: при punch_holes блоки страницы возвращаются ФС (страница читается нулями).
     /// - Вызовы должны происходить в writer‑контексте (внешняя синхронизация на уровне Db/lock).
     pub fn free_page(&self, page_id: u64) -> Result<()> {
+        self.ensure_writable("free_page")?;
         if page_id >= self.meta.next_page_id {
             return Ok(());
         }
@@ -331,7 +608,11 @@ impl Pager {
             Ok(fl) => fl,
             Err(_) => FreeList::create(&self.root)?,
         };
-        fl.push(page_id)
+        fl.push(page_id)?;
+        if self.punch_holes {
+            self.punch_page(page_id)?;
+        }
+        Ok(())
     }
 
     /// Префетч страницы в процессный cache (best-effort).
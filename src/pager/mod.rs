@@ -8,6 +8,9 @@
 //! - replay.rs — wal_replay_with_pager обёртка вокруг WAL v2 реплея.
 //! - cache.rs  — процессный кэш страниц (second-chance).
 //! - value_cache.rs — глобальный LRU‑кэш распакованных OVERFLOW‑значений (новое).
+//! - prewarm.rs — снимок горячих страниц при clean shutdown и фоновый прогрев кэша при open.
+//! - trim.rs   — punch hole для освобождённых страниц (возврат места ФС без vacuum).
+//! - segsync.rs — fsync сегментов батча: один раз на сегмент или один syncfs(2).
 //!
 //! Публичные константы экспортируются отсюда, чтобы внешний код мог их использовать
 //! (например, тесты ссылались на DATA_SEG_PREFIX/EXT).
@@ -30,6 +33,14 @@ pub mod replay;
 pub mod cache;
 // NEW: кэш распакованных OVERFLOW‑значений (LRU по байтам)
 pub mod value_cache;
+// NEW: прогрев page cache по access-pattern снимку
+pub mod prewarm;
+// NEW: punch hole для освобождённых страниц
+pub mod trim;
+// NEW: отложенный fsync сегментов батча (один на сегмент / syncfs)
+pub mod segsync;
 
 // Re-exports для внешнего API
-pub use core::Pager;
+pub use alloc::{SegmentGrowth, DEFAULT_SEGMENT_GROW_CHUNK_BYTES};
+pub use core::{Pager, ReadOnlyError};
+pub use trim::TrimReport;
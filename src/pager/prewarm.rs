@@ -0,0 +1,156 @@
+//! pager/prewarm — прогрев page cache по access-pattern снимку.
+//!
+//! Холодный рестарт даёт плохую латентность, пока кэш не наполнится. Поэтому:
+//! - при clean shutdown writer сохраняет компактный список «горячих» page_id
+//!   (по recency-метаданным кэша, см. cache::page_cache_hot_pages) в <root>/.hot_pages.bin;
+//! - при открытии с QuiverConfig::cache_prewarm=true фоновый поток читает эти страницы
+//!   (мимо кэша, с проверкой трейлера) и кладёт их в кэш.
+//!
+//! Корректность при параллельных записях:
+//! - Перед чтением каждой страницы берётся page_cache_write_gen(); вставка выполняется
+//!   только если поколение не изменилось (ни одной инвалидации за время чтения),
+//!   и ключа ещё нет в кэше. Иначе страница пропускается — устаревшие байты в кэш не попадут.
+//! - Prewarm не вытесняет рабочие страницы: при заполненном кэше поток завершается.
+//!
+//! Формат файла (LE):
+//!   [magic8 "P2HOT001"][u32 count][u32 reserved=0][count × u64 page_id][u32 crc32c(всё предыдущее)]
+
+use anyhow::{anyhow, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
+use crate::metrics::{record_prewarm_pages_loaded, record_prewarm_run};
+
+use super::cache::{
+    page_cache_capacity, page_cache_init, page_cache_len, page_cache_put_prewarm,
+    page_cache_write_gen,
+};
+use super::core::Pager;
+
+pub const HOT_PAGES_FILE: &str = ".hot_pages.bin";
+const HOT_MAGIC: &[u8; 8] = b"P2HOT001";
+
+#[inline]
+pub fn hot_pages_path(root: &Path) -> PathBuf {
+    root.join(HOT_PAGES_FILE)
+}
+
+/// Сохранить список горячих страниц (tmp + rename).
+pub fn write_hot_pages(root: &Path, ids: &[u64]) -> Result<()> {
+    let mut buf = Vec::with_capacity(16 + ids.len() * 8 + 4);
+    buf.extend_from_slice(HOT_MAGIC);
+    let mut tmp4 = [0u8; 4];
+    LittleEndian::write_u32(&mut tmp4, ids.len() as u32);
+    buf.extend_from_slice(&tmp4);
+    buf.extend_from_slice(&[0u8; 4]);
+    let mut tmp8 = [0u8; 8];
+    for id in ids {
+        LittleEndian::write_u64(&mut tmp8, *id);
+        buf.extend_from_slice(&tmp8);
+    }
+    LittleEndian::write_u32(&mut tmp4, crc32c::crc32c(&buf));
+    buf.extend_from_slice(&tmp4);
+
+    let path = hot_pages_path(root);
+    let tmp = root.join(format!("{}.tmp", HOT_PAGES_FILE));
+    {
+        let mut f = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&tmp)
+            .with_context(|| format!("open {}", tmp.display()))?;
+        f.write_all(&buf)?;
+        let _ = f.sync_all();
+    }
+    crate::util::fsx::replace_file(&tmp, &path)
+        .with_context(|| format!("rename {} -> {}", tmp.display(), path.display()))?;
+    Ok(())
+}
+
+/// Прочитать список горячих страниц (пустой, если файла нет).
+pub fn read_hot_pages(root: &Path) -> Result<Vec<u64>> {
+    let path = hot_pages_path(root);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let buf = std::fs::read(&path).with_context(|| format!("read {}", path.display()))?;
+    if buf.len() < 20 || &buf[0..8] != HOT_MAGIC {
+        return Err(anyhow!("bad hot pages file {}", path.display()));
+    }
+    let count = LittleEndian::read_u32(&buf[8..12]) as usize;
+    let need = 16 + count * 8 + 4;
+    if buf.len() != need {
+        return Err(anyhow!(
+            "hot pages file {} length {} != expected {}",
+            path.display(),
+            buf.len(),
+            need
+        ));
+    }
+    let stored = LittleEndian::read_u32(&buf[need - 4..need]);
+    if crc32c::crc32c(&buf[..need - 4]) != stored {
+        return Err(anyhow!("hot pages file {} crc mismatch", path.display()));
+    }
+    let mut ids = Vec::with_capacity(count);
+    for i in 0..count {
+        let off = 16 + i * 8;
+        ids.push(LittleEndian::read_u64(&buf[off..off + 8]));
+    }
+    Ok(ids)
+}
+
+impl Pager {
+    /// Запустить фоновый прогрев кэша по <root>/.hot_pages.bin.
+    /// Возвращает None, если списка нет (или он пуст/повреждён).
+    /// JoinHandle возвращает число загруженных в кэш страниц.
+    pub fn spawn_prewarm(&self) -> Option<JoinHandle<u64>> {
+        let ids = read_hot_pages(&self.root).ok()?;
+        if ids.is_empty() {
+            return None;
+        }
+
+        // Отдельный pager для потока (те же корень/db_id/TDE-ключ).
+        let mut pager = Pager::open(&self.root).ok()?;
+        pager.tde_enabled = self.tde_enabled;
+        pager.tde_kid = self.tde_kid.clone();
+        pager.tde_key = self.tde_key.clone();
+        pager.tde_epoch_keys = self.tde_epoch_keys.clone();
+
+        let h = std::thread::Builder::new()
+            .name("quiverdb-prewarm".into())
+            .spawn(move || pager.prewarm_pages(&ids))
+            .ok()?;
+        Some(h)
+    }
+
+    /// Синхронный прогрев: вернуть число страниц, реально помещённых в кэш.
+    pub fn prewarm_pages(&self, ids: &[u64]) -> u64 {
+        let ps = self.meta.page_size as usize;
+        page_cache_init(ps);
+        record_prewarm_run();
+
+        let mut buf = vec![0u8; ps];
+        let mut loaded = 0u64;
+        for &pid in ids {
+            if pid >= self.meta.next_page_id {
+                continue;
+            }
+            let gen = page_cache_write_gen();
+            if self.read_page_uncached(pid, &mut buf).is_err() {
+                continue;
+            }
+            if page_cache_put_prewarm(self.db_id, pid, &buf, ps, gen) {
+                loaded += 1;
+            } else if page_cache_len() >= page_cache_capacity() {
+                // кэш заполнен — дальше прогревать бессмысленно
+                break;
+            }
+        }
+        record_prewarm_pages_loaded(loaded);
+        loaded
+    }
+}
@@ -1,13 +1,23 @@
 //! pager/replay — обёртка WAL v2 реплея с LSN‑гейтингом (до ensure_allocated).
+//!
+//! NEW: wal_replay_with_pager_images — тот же реплей, но дополнительно удерживает в памяти
+//! последний образ каждой KV-страницы из WAL (ReplayImages, с лимитом по байтам). WAL после
+//! реплея усекается, поэтому open-time проверка голов каталога (db/torn.rs) чинит
+//! нечитаемые страницы именно из этих образов.
+//! NEW: ReplayImages::touched — покрытие реплея (wal::ReplayTouched) для сверки bloom.bin
+//! после краха (Db::reconcile_bloom_after_replay).
 
 use anyhow::Result;
 use byteorder::{ByteOrder, LittleEndian};
+use std::collections::HashMap;
 
 use crate::page::{
     KV_HDR_MIN, KV_OFF_LSN, OFF_TYPE, OVF_OFF_LSN, PAGE_MAGIC, PAGE_TYPE_KV_RH3,
     PAGE_TYPE_OVERFLOW3,
 };
-use crate::wal::wal_replay_if_any;
+use crate::wal::{
+    wal_replay_tracked, wal_salvage_with, RecoveryProgressHook, ReplayTouched, SalvageReport,
+};
 
 use super::core::Pager;
 
@@ -17,57 +27,168 @@ impl Pager {
     /// NEW: после успешного реплея актуализирует meta.next_page_id на диске,
     ///      чтобы последующие открывания могли читать восстановленные страницы.
     pub fn wal_replay_with_pager(root: &std::path::Path) -> Result<()> {
+        Self::wal_replay_with_pager_progress(root, None)
+    }
+
+    /// То же, что wal_replay_with_pager, с callback'ом прогресса восстановления
+    /// и fast-skip уже применённого префикса (см. wal/recovery.rs).
+    pub fn wal_replay_with_pager_progress(
+        root: &std::path::Path,
+        progress: Option<&RecoveryProgressHook>,
+    ) -> Result<()> {
+        Self::wal_replay_with_pager_images(root, progress, 0).map(|_| ())
+    }
+
+    /// Реплей с прогрессом, удерживающий последние образы KV-страниц из WAL
+    /// (не более keep_bytes байт; 0 — не удерживать).
+    pub(crate) fn wal_replay_with_pager_images(
+        root: &std::path::Path,
+        progress: Option<&RecoveryProgressHook>,
+        keep_bytes: usize,
+    ) -> Result<ReplayImages> {
         let mut pager = Pager::open(root)?;
-        let page_sz = pager.meta.page_size as usize;
+        let mut images = ReplayImages::with_cap(keep_bytes);
 
         // Фактическая верхняя граница выделенных страниц в процессе реплея.
         // pager.ensure_allocated() обновляет pager.meta.next_page_id в памяти, но meta на диске
-        // не меняется. Мы зафиксируем это после wal_replay_if_any.
-        wal_replay_if_any(root, |_wal_lsn, page_id, payload| {
-            let new_lsn = v3_page_lsn(payload);
-
-            let mut apply = true;
-            if page_id < pager.meta.next_page_id {
-                let mut cur = vec![0u8; page_sz];
-                if pager.read_page(page_id, &mut cur).is_ok() {
-                    if let (Some(nl), Some(cl)) = (new_lsn, v3_page_lsn(&cur)) {
-                        if cl >= nl {
-                            apply = false;
-                        }
-                    }
-                }
-            }
+        // не меняется. Мы зафиксируем это после реплея.
+        let mut touched = ReplayTouched::default();
+        let outcome = wal_replay_tracked(
+            root,
+            |_wal_lsn, page_id, payload| {
+                images.keep(page_id, payload);
+                pager.apply_replayed_page(page_id, payload)
+            },
+            progress,
+            &mut touched,
+        )?;
+        images.touched = touched;
 
-            if apply {
-                pager.ensure_allocated(page_id)?;
-                pager.write_page_raw(page_id, payload)?;
-                // ensure_allocated продвигает next_page_id в памяти. Строго на всякий.
-                let need_next = page_id.saturating_add(1);
-                if need_next > pager.meta.next_page_id {
-                    pager.meta.next_page_id = need_next;
-                }
-            }
-            Ok(())
-        })?;
+        // Страницы пропущенного (уже применённого ранее) префикса тоже учитываем.
+        if outcome.page_id_end > pager.meta.next_page_id {
+            pager.meta.next_page_id = outcome.page_id_end;
+        }
 
         // WAL-replay уже выставил last_lsn и clean_shutdown=true в meta на диске.
         // Допроставим корректный next_page_id (если вырос).
-        {
-            use crate::meta::{read_meta, write_meta_overwrite};
-            let mut m = read_meta(root)?;
-            if pager.meta.next_page_id > m.next_page_id {
-                m.next_page_id = pager.meta.next_page_id;
-                // Сохраняем last_lsn/clean_shutdown как есть (их уже установил wal_replay_if_any).
-                write_meta_overwrite(root, &m)?;
+        pager.persist_next_page_id(root)?;
+
+        Ok(images)
+    }
+
+    /// Salvage WAL после частичной порчи (см. wal/salvage.rs).
+    /// apply=false — только отчёт; apply=true — целые батчи применяются с тем же
+    /// LSN‑гейтингом страниц, что и реплей, затем актуализируется meta.next_page_id.
+    pub fn wal_salvage_with_pager(root: &std::path::Path, apply: bool) -> Result<SalvageReport> {
+        let mut pager = Pager::open(root)?;
+        let rep = wal_salvage_with(root, apply, |_wal_lsn, page_id, payload| {
+            pager.apply_replayed_page(page_id, payload)
+        })?;
+        if rep.applied {
+            pager.persist_next_page_id(root)?;
+        }
+        Ok(rep)
+    }
+
+    /// Применить образ страницы из WAL, если он новее текущей страницы (LSN‑гейтинг).
+    fn apply_replayed_page(&mut self, page_id: u64, payload: &[u8]) -> Result<()> {
+        let page_sz = self.meta.page_size as usize;
+        let new_lsn = v3_page_lsn(payload);
+
+        let mut apply = true;
+        if page_id < self.meta.next_page_id {
+            let mut cur = vec![0u8; page_sz];
+            if self.read_page(page_id, &mut cur).is_ok() {
+                if let (Some(nl), Some(cl)) = (new_lsn, v3_page_lsn(&cur)) {
+                    if cl >= nl {
+                        apply = false;
+                    }
+                }
             }
         }
 
+        if apply {
+            self.ensure_allocated(page_id)?;
+            self.write_page_raw(page_id, payload)?;
+            // ensure_allocated продвигает next_page_id в памяти. Строго на всякий.
+            let need_next = page_id.saturating_add(1);
+            if need_next > self.meta.next_page_id {
+                self.meta.next_page_id = need_next;
+            }
+        }
+        Ok(())
+    }
+
+    /// Допроставить next_page_id в meta на диске (если вырос); остальные поля не трогаются.
+    fn persist_next_page_id(&self, root: &std::path::Path) -> Result<()> {
+        use crate::meta::{read_meta, write_meta_overwrite};
+        let mut m = read_meta(root)?;
+        if self.meta.next_page_id > m.next_page_id {
+            m.next_page_id = self.meta.next_page_id;
+            write_meta_overwrite(root, &m)?;
+        }
         Ok(())
     }
 }
 
+// ---------------- replay images ----------------
+
+/// Последние образы KV-страниц, встреченные при реплее WAL (page_id → образ).
+#[derive(Debug, Default)]
+pub(crate) struct ReplayImages {
+    pages: HashMap<u64, Vec<u8>>,
+    bytes: usize,
+    cap: usize,
+    /// Образы, не удержанные из-за лимита.
+    pub dropped: u64,
+    /// Покрытие реплея (первый LSN, бакеты HEADS_UPDATE).
+    pub touched: ReplayTouched,
+}
+
+impl ReplayImages {
+    pub(crate) fn with_cap(cap: usize) -> Self {
+        Self {
+            cap,
+            ..Default::default()
+        }
+    }
+
+    /// Запомнить образ (более поздний кадр WAL замещает прежний образ той же страницы).
+    fn keep(&mut self, page_id: u64, payload: &[u8]) {
+        if self.cap == 0 || !is_kv_page(payload) {
+            return;
+        }
+        if let Some(old) = self.pages.get_mut(&page_id) {
+            self.bytes = self.bytes - old.len() + payload.len();
+            old.clear();
+            old.extend_from_slice(payload);
+            return;
+        }
+        if self.bytes + payload.len() > self.cap {
+            self.dropped += 1;
+            return;
+        }
+        self.bytes += payload.len();
+        self.pages.insert(page_id, payload.to_vec());
+    }
+
+    pub(crate) fn get(&self, page_id: u64) -> Option<&[u8]> {
+        self.pages.get(&page_id).map(|v| v.as_slice())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.pages.len()
+    }
+}
+
 // ---------------- helpers ----------------
 
+fn is_kv_page(buf: &[u8]) -> bool {
+    buf.len() >= KV_HDR_MIN
+        && &buf[..4] == PAGE_MAGIC
+        && LittleEndian::read_u16(&buf[OFF_TYPE..OFF_TYPE + 2]) == PAGE_TYPE_KV_RH3
+}
+
 fn v3_page_lsn(buf: &[u8]) -> Option<u64> {
     // Достаточно иметь минимальный заголовок страницы (64 байта для v3 KV/OVF).
     if buf.len() < KV_HDR_MIN {
@@ -0,0 +1,115 @@
+//! pager/segsync — fsync сегментов, затронутых одним батчем коммита.
+//!
+//! write_pages_grouped_by_segment пишет страницы батча посегментно и раньше сбрасывал каждый
+//! сегмент сразу после его записи. Теперь дескрипторы «грязных» сегментов собираются на весь
+//! батч (DirtySegments) и синхронизируются в конце, каждый ровно один раз:
+//! - по умолчанию — fsync каждого сегмента;
+//! - при QuiverConfig::seg_syncfs (ENV P1_SEG_SYNCFS), ≥ 2 сегментах и общей ФС (st_dev) —
+//!   один syncfs(2) (Linux). Ошибка syncfs — fallback на посегментный fsync.
+//!
+//! Семантика ошибок прежняя: при force_sync (write_pages_unlogged — у страниц нет WAL-копии)
+//! ошибка fsync проваливает батч, при data_fsync — best-effort.
+//! Счётчики: seg_fsync_batches, seg_fsync_calls, seg_syncfs_calls и gauge
+//! seg_fsync_last_batch (системных вызовов в последнем батче).
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::path::PathBuf;
+
+use crate::metrics::record_seg_fsync_batch;
+use crate::util::iostall::{io_watch, IoOp};
+
+use super::core::Pager;
+
+/// Сегменты батча, ожидающие fsync.
+#[derive(Default)]
+pub(crate) struct DirtySegments {
+    segs: Vec<(PathBuf, File, u64)>,
+}
+
+impl DirtySegments {
+    /// Запомнить записанный сегмент (path, дескриптор, записано байт).
+    pub(crate) fn push(&mut self, path: PathBuf, file: File, bytes: u64) {
+        self.segs.push((path, file, bytes));
+    }
+
+    /// Сбросить все сегменты; force — ошибка fsync возвращается (иначе best-effort).
+    /// Возвращает число выполненных системных вызовов.
+    pub(crate) fn sync(self, syncfs: bool, force: bool) -> Result<u64> {
+        if self.segs.is_empty() {
+            return Ok(0);
+        }
+        let total: u64 = self.segs.iter().map(|s| s.2).sum();
+        if syncfs && self.segs.len() > 1 && same_filesystem(&self.segs) {
+            let (path, file, _) = &self.segs[0];
+            let _w = io_watch(IoOp::SegFsync, path, total);
+            if syncfs_fd(file).is_ok() {
+                record_seg_fsync_batch(1, true);
+                return Ok(1);
+            }
+        }
+        let mut calls = 0u64;
+        for (path, file, bytes) in &self.segs {
+            let _w = io_watch(IoOp::SegFsync, path, *bytes);
+            calls += 1;
+            let r = file.sync_all();
+            if force {
+                r.with_context(|| format!("fsync {}", path.display()))?;
+            }
+        }
+        record_seg_fsync_batch(calls, false);
+        Ok(calls)
+    }
+}
+
+impl Pager {
+    /// NEW: syncfs(2) вместо посегментного fsync батча (open_with_config).
+    pub fn set_seg_syncfs(&mut self, on: bool) {
+        self.seg_syncfs = on;
+    }
+
+    pub fn seg_syncfs(&self) -> bool {
+        self.seg_syncfs
+    }
+}
+
+#[cfg(unix)]
+fn same_filesystem(segs: &[(PathBuf, File, u64)]) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let mut dev = None;
+    for (_, f, _) in segs {
+        let d = match f.metadata() {
+            Ok(md) => md.dev(),
+            Err(_) => return false,
+        };
+        if *dev.get_or_insert(d) != d {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(not(unix))]
+fn same_filesystem(_segs: &[(PathBuf, File, u64)]) -> bool {
+    false
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn syncfs_fd(f: &File) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    // SAFETY: fd принадлежит живому File; syncfs не трогает память процесса.
+    let rc = unsafe { libc::syncfs(f.as_raw_fd()) };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn syncfs_fd(_f: &File) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "syncfs is not available on this platform",
+    ))
+}
@@ -0,0 +1,207 @@
+//! pager/trim — возврат освобождённых страниц файловой системе (punch hole).
+//!
+//! Освобождённая страница (free-лист, sweep сиротских OVERFLOW) остаётся занятыми байтами
+//! сегмента до vacuum. При QuiverConfig::punch_holes (ENV P1_PUNCH_HOLES) free_page сразу
+//! пробивает дыру на месте страницы: fallocate(FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE)
+//! на Linux. Длина сегмента не меняется (смещения страниц прежние), блоки возвращаются ФС,
+//! страница читается нулями (как не записанная предаллокация) и переписывается целиком при
+//! повторной аллокации из free-листа.
+//!
+//! Поддержка ФС определяется один раз на хэндл (проба на временном файле в корне БД):
+//! tmpfs/ext4/xfs/btrfs умеют, часть ФС (и не-Linux платформы) — нет. Без поддержки punch —
+//! no-op: один [INFO] и метрика punch_hole_unsupported, free_page работает как прежде.
+//!
+//! Db::trim_free_pages — пробить дыры для всех страниц, уже лежащих в free-листе
+//! (например, после включения режима на существующей БД).
+
+use anyhow::Result;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::atomic::Ordering;
+use std::sync::OnceLock;
+
+use super::cache::page_cache_invalidate as pc_invalidate;
+use super::core::Pager;
+use crate::metrics::{record_punch_hole, record_punch_hole_unsupported};
+
+/// Состояние пробы поддержки punch hole (Pager::punch_support).
+pub(crate) const PUNCH_UNKNOWN: u8 = 0;
+pub(crate) const PUNCH_SUPPORTED: u8 = 1;
+pub(crate) const PUNCH_UNSUPPORTED: u8 = 2;
+
+/// Имя файла пробы (создаётся и удаляется в корне БД).
+const PROBE_FILE: &str = ".punch_probe";
+
+/// Итог Db::trim_free_pages.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TrimReport {
+    /// Поддерживает ли ФС корня punch hole.
+    pub supported: bool,
+    /// Страниц в free-листе.
+    pub free_pages: u64,
+    /// Страниц, для которых пробита дыра.
+    pub punched_pages: u64,
+    pub punched_bytes: u64,
+}
+
+impl Pager {
+    /// NEW: пробивать дыры для освобождаемых страниц (open_with_config).
+    pub fn set_punch_holes(&mut self, on: bool) {
+        self.punch_holes = on;
+    }
+
+    pub fn punch_holes(&self) -> bool {
+        self.punch_holes
+    }
+
+    /// Поддерживает ли ФС корня punch hole (проба один раз на хэндл, результат кэшируется).
+    pub fn punch_hole_supported(&self) -> bool {
+        match self.punch_support.load(Ordering::Relaxed) {
+            PUNCH_SUPPORTED => return true,
+            PUNCH_UNSUPPORTED => return false,
+            _ => {}
+        }
+        let ok = probe_punch_hole(&self.root);
+        self.punch_support.store(
+            if ok {
+                PUNCH_SUPPORTED
+            } else {
+                PUNCH_UNSUPPORTED
+            },
+            Ordering::Relaxed,
+        );
+        if !ok {
+            record_punch_hole_unsupported();
+            info_unsupported_once(&self.root);
+        }
+        ok
+    }
+
+    /// Пробить дыру на месте страницы (best-effort). true — блоки возвращены ФС.
+    /// Вызывается для страниц, которые уже в free-листе: содержимое больше не нужно.
+    pub(crate) fn punch_page(&self, page_id: u64) -> Result<bool> {
+        self.ensure_writable("punch_page")?;
+        // Ветка: пробитая (нулевая) страница снова читалась бы из снапшота
+        if page_id >= self.meta.next_page_id
+            || self.branch.is_some()
+            || !self.punch_hole_supported()
+        {
+            return Ok(false);
+        }
+        let ps = self.meta.page_size as u64;
+        let (seg_no, off) = self.locate(page_id);
+        let seg = self.seg_path(seg_no);
+        if std::fs::metadata(&seg).map(|m| m.len()).unwrap_or(0) < off + ps {
+            return Ok(false);
+        }
+        let f = self.open_seg_rw(seg_no, false)?;
+        if let Err(e) = punch_range(&f, off, ps) {
+            // ФС сегмента может отличаться от ФС пробы — выключаем до конца жизни хэндла
+            if is_unsupported(&e) {
+                self.punch_support
+                    .store(PUNCH_UNSUPPORTED, Ordering::Relaxed);
+                record_punch_hole_unsupported();
+                info_unsupported_once(&self.root);
+                return Ok(false);
+            }
+            return Err(anyhow::anyhow!(
+                "punch hole at page {} ({}): {}",
+                page_id,
+                seg.display(),
+                e
+            ));
+        }
+        // Кэш не должен отдавать прежнее содержимое
+        pc_invalidate(self.db_id, page_id, ps as usize);
+        record_punch_hole(ps);
+        Ok(true)
+    }
+
+    /// Пробить дыры для всех страниц списка (страницы free-листа).
+    pub(crate) fn punch_pages(&self, pids: &[u64]) -> Result<TrimReport> {
+        self.ensure_writable("trim_free_pages")?;
+        let mut rep = TrimReport {
+            supported: self.punch_hole_supported(),
+            free_pages: pids.len() as u64,
+            ..Default::default()
+        };
+        if !rep.supported {
+            return Ok(rep);
+        }
+        for &pid in pids {
+            if self.punch_page(pid)? {
+                rep.punched_pages += 1;
+                rep.punched_bytes += self.meta.page_size as u64;
+            }
+        }
+        Ok(rep)
+    }
+}
+
+/// Проба: временный файл из двух блоков, дыра в первом. Файл удаляется в любом случае.
+fn probe_punch_hole(root: &std::path::Path) -> bool {
+    let path = root.join(PROBE_FILE);
+    let ok = (|| -> std::io::Result<()> {
+        let mut f = OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .read(true)
+            .write(true)
+            .open(&path)?;
+        f.write_all(&[0xA5u8; 8192])?;
+        punch_range(&f, 0, 4096)
+    })()
+    .is_ok();
+    let _ = std::fs::remove_file(&path);
+    ok
+}
+
+fn is_unsupported(e: &std::io::Error) -> bool {
+    #[cfg(target_os = "linux")]
+    if matches!(
+        e.raw_os_error(),
+        Some(libc::EOPNOTSUPP) | Some(libc::ENOSYS) | Some(libc::EINVAL)
+    ) {
+        return true;
+    }
+    e.kind() == std::io::ErrorKind::Unsupported
+}
+
+fn info_unsupported_once(root: &std::path::Path) {
+    static WARNED: OnceLock<()> = OnceLock::new();
+    WARNED.get_or_init(|| {
+        eprintln!(
+            "[INFO] punch_holes: filesystem of {} does not support hole punching; \
+             freed pages keep their space until vacuum",
+            root.display()
+        );
+    });
+}
+
+// ---------- punch hole ----------
+
+/// Освободить блоки [off, off+len), не меняя длину файла. Err — не поддерживается/ошибка ФС.
+#[cfg(target_os = "linux")]
+fn punch_range(f: &File, off: u64, len: u64) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    // SAFETY: fd принадлежит живому File; fallocate не трогает память процесса.
+    let rc = unsafe {
+        libc::fallocate(
+            f.as_raw_fd(),
+            libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+            off as libc::off_t,
+            len as libc::off_t,
+        )
+    };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn punch_range(_f: &File, _off: u64, _len: u64) -> std::io::Result<()> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
@@ -23,6 +23,8 @@
 //! - Храним копии значений (Vec<u8>) — не отдаём ссылки на внутреннее хранилище.
 //! - Вставка/обновление учитывает лимит по байтам: вытесняем LRU‑элементы, пока не поместимся.
 //! - Если значение меньше min_size или больше cap_bytes — не кэшируем.
+//! - NEW: байты учитываются в ResourceBudget (budget.rs, Subsystem::ValueCache): при тесном
+//!   бюджете вытесняем LRU, пока значение не поместится, иначе не кэшируем.
 //!
 //! Интеграция
 //! - Использовать в местах раскрытия OVERFLOW: вместо прямого чтения цепочки сначала
@@ -31,6 +33,8 @@
 use std::collections::HashMap;
 use std::sync::{Mutex, OnceLock};
 
+use crate::budget::{self, Subsystem};
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 struct VCKey {
     db_id: u64,
@@ -105,6 +109,7 @@ impl ValueCache {
     }
 
     fn clear(&mut self) {
+        budget::release(Subsystem::ValueCache, self.used_bytes as u64);
         self.map.clear();
         self.head = None;
         self.tail = None;
@@ -168,9 +173,14 @@ impl ValueCache {
             if n > old_size {
                 let delta = n - old_size;
                 self.ensure_space(delta);
+                // NEW: рост не помещается в ResourceBudget — оставляем прежнее значение
+                if !budget::try_charge(Subsystem::ValueCache, delta as u64) {
+                    return;
+                }
                 self.used_bytes = self.used_bytes.saturating_add(delta);
             } else if old_size > n {
                 let delta = old_size - n;
+                budget::release(Subsystem::ValueCache, delta as u64);
                 self.used_bytes = self.used_bytes.saturating_sub(delta);
             }
 
@@ -200,6 +210,12 @@ impl ValueCache {
             // всё ещё нет места — не кэшируем
             return;
         }
+        // NEW: место в общем ResourceBudget — вытесняем LRU, пока не поместимся
+        while !budget::try_charge(Subsystem::ValueCache, n as u64) {
+            if !self.evict_one() {
+                return;
+            }
+        }
         let node = Node {
             value: bytes.to_vec(),
             prev: None,
@@ -239,6 +255,7 @@ impl ValueCache {
             self.tail = None;
         }
         if let Some(old) = self.map.remove(&k) {
+            budget::release(Subsystem::ValueCache, old.size as u64);
             self.used_bytes = self.used_bytes.saturating_sub(old.size);
         }
         true
@@ -0,0 +1,314 @@
+//! snapstore/branch — writable-ветки (branching) из persisted-снапшота для тестовых окружений.
+//!
+//! create_branch(src_root, dst_root, id) создаёт в dst_root полноценную БД (meta v4, каталог,
+//! пустой WAL, bloom.bin из манифеста), но НЕ копирует страницы: сегменты ветки пустые.
+//! Pager ветки (файл `<root>/branch.json`) читает страницу так:
+//! - страница записана локально (в сегменте есть PAGE_MAGIC) — как обычно;
+//! - иначе, если страница есть в снапшоте, — объект SnapStore источника (тот же трейлер
+//!   CRC/AEAD, та же проверка); метрика branch_page_fetches.
+//!
+//! Записи идут в локальные сегменты (copy-on-write на уровне страниц): commit переписывает
+//! страницу целиком, дальше она читается локально. Источник ветка не трогает.
+//!
+//! Жизненный цикл объектов:
+//! - create_branch закрепляет (add_ref) все объекты снапшота — snapshot-delete в источнике не
+//!   удалит страницы, нужные ветке;
+//! - карта page_id → объект хранится в самой ветке (`<root>/branch.manifest.json`), поэтому
+//!   манифест источника ветке после создания не нужен;
+//! - detach_branch дописывает в локальные сегменты все ещё не записанные страницы снапшота,
+//!   снимает branch.json (точка фиксации) и отпускает закрепление (dec_ref): ветка становится
+//!   обычной самостоятельной БД.
+//!
+//! Ограничения: идентичность (db_uuid) ветка наследует от источника (нужна для AAD v2 страниц
+//! снапшота); TDE-ветке нужен ключ источника. Live clone ветки не поддерживается (сегменты
+//! неполные) — сначала detach; backup/snapshot-create читают через Pager и видят полную БД.
+//! Punch hole в ветке выключен: пробитая страница читалась бы из снапшота.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::db::Db;
+use crate::metrics::record_branch_page_fetch;
+use crate::util::fsx::{fsync_parent_dir, replace_file};
+
+use super::manifest::{read_manifest, SnapshotManifestV2};
+use super::restore::{ensure_meta_and_dir, finish_root, install_sidecars};
+use super::{resolve_snapstore_dir, SnapStore};
+
+/// Маркер ветки в корне БД.
+pub const BRANCH_FILE: &str = "branch.json";
+/// Копия манифеста снапшота (карта page_id → объект) в корне ветки.
+pub const BRANCH_MANIFEST_FILE: &str = "branch.manifest.json";
+
+pub const BRANCH_VERSION: u32 = 1;
+
+/// Описание ветки (`<root>/branch.json`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchInfo {
+    pub version: u32,
+    /// Снапшот, от которого отведена ветка.
+    pub snapshot_id: String,
+    /// Корень источника (где делался снапшот).
+    pub source_root: PathBuf,
+    /// Каталог SnapStore источника (абсолютный; с учётом P1_SNAPSTORE_DIR на момент создания).
+    pub snapstore_dir: PathBuf,
+    pub created_unix_ms: u64,
+    /// LSN снапшота (точка ветвления).
+    pub base_lsn: u64,
+    /// Страниц снапшота, которые ветка может прочитать из SnapStore (закреплены refcount'ом).
+    pub snapshot_pages: u64,
+}
+
+/// Итог detach_branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BranchDetachReport {
+    pub snapshot_id: String,
+    pub snapshot_pages: u64,
+    /// Страниц, скопированных из SnapStore в локальные сегменты.
+    pub materialized_pages: u64,
+    /// Страниц снапшота, уже перезаписанных веткой.
+    pub local_pages: u64,
+}
+
+/// Прочитать описание ветки. None — корень не является веткой.
+pub fn read_branch_info(root: &Path) -> Result<Option<BranchInfo>> {
+    let path = root.join(BRANCH_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let s = fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+    let info: BranchInfo =
+        serde_json::from_str(&s).with_context(|| format!("parse {}", path.display()))?;
+    if info.version != BRANCH_VERSION {
+        return Err(anyhow!(
+            "{}: unsupported branch version {} (expected {})",
+            path.display(),
+            info.version,
+            BRANCH_VERSION
+        ));
+    }
+    Ok(Some(info))
+}
+
+/// Создать writable-ветку в dst_root (пустой или несуществующий каталог) из снапшота `id`
+/// SnapStore'а src_root. Страницы не копируются.
+pub fn create_branch(src_root: &Path, dst_root: &Path, id: &str) -> Result<BranchInfo> {
+    if dst_root.join("meta").exists() {
+        return Err(anyhow!(
+            "branch target {} already holds a database",
+            dst_root.display()
+        ));
+    }
+    let manifest = read_manifest(src_root, id)
+        .with_context(|| format!("read manifest '{}' at {}", id, src_root.display()))?;
+    let ss_dir = resolve_snapstore_dir(src_root);
+    let ss_dir = ss_dir.canonicalize().unwrap_or(ss_dir);
+    let ss = SnapStore::open_dir(&ss_dir)?;
+
+    let missing = manifest
+        .objects
+        .iter()
+        .filter(|o| !ss.has(&o.hash_hex))
+        .count();
+    if missing > 0 {
+        return Err(anyhow!(
+            "snapshot '{}': {} page object(s) missing in {}",
+            id,
+            missing,
+            ss_dir.display()
+        ));
+    }
+
+    // Закрепить объекты (при ошибке — отпустить уже закреплённые)
+    let mut pinned = 0usize;
+    let pin = (|| -> Result<()> {
+        for o in &manifest.objects {
+            ss.add_ref(&o.hash_hex)?;
+            pinned += 1;
+        }
+        Ok(())
+    })();
+    if let Err(e) = pin {
+        unpin(&ss, &manifest, pinned);
+        return Err(e.context("pin snapshot objects for branch"));
+    }
+
+    let info = BranchInfo {
+        version: BRANCH_VERSION,
+        snapshot_id: id.to_string(),
+        source_root: src_root
+            .canonicalize()
+            .unwrap_or_else(|_| src_root.to_path_buf()),
+        snapstore_dir: ss_dir,
+        created_unix_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0),
+        base_lsn: manifest.meta.lsn,
+        snapshot_pages: manifest.objects.len() as u64,
+    };
+    let built = (|| -> Result<()> {
+        let fresh = ensure_meta_and_dir(dst_root, &manifest, false)?;
+        write_json_atomic(&dst_root.join(BRANCH_MANIFEST_FILE), &manifest)?;
+        write_json_atomic(&dst_root.join(BRANCH_FILE), &info)?;
+        finish_root(dst_root, &manifest, fresh)?;
+        install_sidecars(&ss, dst_root, &manifest)
+    })();
+    if let Err(e) = built {
+        unpin(&ss, &manifest, pinned);
+        let _ = fs::remove_file(dst_root.join(BRANCH_FILE));
+        return Err(e.context(format!("create branch at {}", dst_root.display())));
+    }
+    Ok(info)
+}
+
+/// Сделать ветку самостоятельной БД: дописать непрочитанные страницы снапшота локально,
+/// снять branch.json и отпустить закрепление объектов. Writer-only.
+pub fn detach_branch(db: &mut Db) -> Result<BranchDetachReport> {
+    db.pager.ensure_writable("detach_branch")?;
+    let Some(src) = db.pager.branch.clone() else {
+        return Err(anyhow!("{} is not a branch", db.root.display()));
+    };
+    let ps = db.pager.meta.page_size as usize;
+    let mut buf = vec![0u8; ps];
+    let mut rep = BranchDetachReport {
+        snapshot_id: src.info.snapshot_id.clone(),
+        snapshot_pages: src.pages.len() as u64,
+        materialized_pages: 0,
+        local_pages: 0,
+    };
+
+    let mut pids: Vec<u64> = src.pages.keys().copied().collect();
+    pids.sort_unstable();
+    for pid in pids {
+        if db.pager.read_local_page(pid, &mut buf)? == Some(true) {
+            rep.local_pages += 1;
+            continue;
+        }
+        src.fetch(pid, &mut buf)?;
+        db.pager.write_page_raw_with_fsync(pid, &buf, false)?;
+        rep.materialized_pages += 1;
+    }
+    db.pager.sync_all_segments()?;
+
+    // Точка фиксации: без branch.json корень — обычная БД
+    let marker = db.root.join(BRANCH_FILE);
+    fs::remove_file(&marker).with_context(|| format!("remove {}", marker.display()))?;
+    let _ = fsync_parent_dir(&marker);
+    db.pager.branch = None;
+
+    let manifest = src.manifest()?;
+    let _ = fs::remove_file(db.root.join(BRANCH_MANIFEST_FILE));
+    unpin(&src.store, &manifest, manifest.objects.len());
+    Ok(rep)
+}
+
+impl Db {
+    /// Описание ветки, если БД открыта как writable-ветка снапшота (snapstore/branch.rs).
+    pub fn branch_info(&self) -> Option<&BranchInfo> {
+        self.pager.branch.as_deref().map(|b| &b.info)
+    }
+}
+
+// -------------------------- Pager side --------------------------
+
+/// Источник непрочитанных страниц ветки (загружается Pager::open при наличии branch.json).
+pub(crate) struct BranchSource {
+    pub(crate) info: BranchInfo,
+    root: PathBuf,
+    store: SnapStore,
+    pages: HashMap<u64, String>,
+}
+
+impl BranchSource {
+    /// None — корень не является веткой.
+    pub(crate) fn open(root: &Path) -> Result<Option<Self>> {
+        let Some(info) = read_branch_info(root)? else {
+            return Ok(None);
+        };
+        let src = Self {
+            root: root.to_path_buf(),
+            store: SnapStore::open_dir(&info.snapstore_dir)?,
+            pages: HashMap::new(),
+            info,
+        };
+        let pages = src
+            .manifest()?
+            .objects
+            .into_iter()
+            .map(|o| (o.page_id, o.hash_hex))
+            .collect();
+        Ok(Some(Self { pages, ..src }))
+    }
+
+    fn manifest(&self) -> Result<SnapshotManifestV2> {
+        let path = self.root.join(BRANCH_MANIFEST_FILE);
+        super::manifest::read_manifest_file(&path)
+            .with_context(|| format!("branch of snapshot '{}'", self.info.snapshot_id))
+    }
+
+    /// Прочитать страницу снапшота в buf. false — страницы нет в снапшоте.
+    pub(crate) fn fetch(&self, page_id: u64, buf: &mut [u8]) -> Result<bool> {
+        let Some(hash) = self.pages.get(&page_id) else {
+            return Ok(false);
+        };
+        let data = self.store.get(hash)?.ok_or_else(|| {
+            anyhow!(
+                "branch: object {} of page {} missing in {}",
+                hash,
+                page_id,
+                self.info.snapstore_dir.display()
+            )
+        })?;
+        if data.len() != buf.len() {
+            return Err(anyhow!(
+                "branch: object {} of page {} has {} bytes, expected {}",
+                hash,
+                page_id,
+                data.len(),
+                buf.len()
+            ));
+        }
+        buf.copy_from_slice(&data);
+        record_branch_page_fetch();
+        Ok(true)
+    }
+}
+
+impl std::fmt::Debug for BranchSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BranchSource")
+            .field("snapshot_id", &self.info.snapshot_id)
+            .field("pages", &self.pages.len())
+            .finish()
+    }
+}
+
+// -------------------------- helpers --------------------------
+
+fn unpin(ss: &SnapStore, manifest: &SnapshotManifestV2, n: usize) {
+    for o in manifest.objects.iter().take(n) {
+        if let Err(e) = ss.dec_ref(&o.hash_hex) {
+            eprintln!("[WARN] branch: unpin object {}: {:#}", o.hash_hex, e);
+        }
+    }
+}
+
+fn write_json_atomic<T: Serialize>(path: &Path, v: &T) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    let json = serde_json::to_vec_pretty(v)?;
+    {
+        let mut f = fs::File::create(&tmp).with_context(|| format!("create {}", tmp.display()))?;
+        f.write_all(&json)?;
+        f.sync_all()?;
+    }
+    replace_file(&tmp, path)
+        .with_context(|| format!("rename {} -> {}", tmp.display(), path.display()))?;
+    let _ = fsync_parent_dir(path);
+    Ok(())
+}
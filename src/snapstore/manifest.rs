@@ -11,13 +11,18 @@
 //! Структура (v2):
 //! - SnapshotManifestV2
 //!   - meta: SnapshotMetaV2 {
-//!         version=2,
-//!         id, parent, created_unix_ms, message, labels,
-//!         lsn, page_size, next_page_id, buckets,
-//!         hash_kind, codec_default
+//!     version=2,
+//!     id, parent, created_unix_ms, message, labels,
+//!     lsn, page_size, next_page_id, buckets,
+//!     hash_kind, codec_default, db, tde_aad_since_lsn (NEW: идентичность источника)
 //!     }
 //!   - heads: массив (bucket u32, head_pid u64)
-//!   - objects: массив ManifestObject { page_id u64, hash_hex String, bytes u64 }
+//!   - objects: массив ManifestObject { page_id u64, hash_hex String, bytes u64,
+//!     stored_bytes u64, codec u16 } (NEW: размер и кодек объекта в SnapStore, serde default)
+//!   - sidecars: массив ManifestSidecar { name, hash_hex, bytes, last_lsn } (NEW, serde default)
+//!   - checksum_hex: SHA-256 манифеста без checksum_hex/signature (NEW, необязательное)
+//!   - signature: ManifestSignature { alg, kid, public_key_hex, signature_hex, signed_unix_ms }
+//!     (NEW, необязательное; см. snapstore/signature)
 //!
 //! Примечание по совместимости:
 //! - Поля hash_kind/codec_default добавлены в 2.2. Для чтения старых манифестов
@@ -25,16 +30,18 @@
 
 use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs::{self, OpenOptions};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 // Для значений по умолчанию новых полей meta
-use crate::meta::{CODEC_NONE, HASH_KIND_XX64_SEED0};
+use crate::meta::{DbIdentity, CODEC_NONE, HASH_KIND_XX64_SEED0};
 
 // Используем общий резолвер каталога SnapStore из модуля snapstore
 use super::resolve_snapstore_dir;
+use super::signature::ManifestSignature;
 
 pub const SNAPSHOT_MANIFEST_VERSION_V2: u32 = 2;
 
@@ -66,6 +73,15 @@ pub struct SnapshotMetaV2 {
     pub hash_kind: u32, // 1 = xxhash64(seed=0)
     #[serde(default = "default_codec_default")]
     pub codec_default: u16, // 0=none, 1=zstd, 2=lz4 (резерв)
+
+    // NEW: идентичность источника (пусто — манифест старой сборки) и AAD v2 (0 — выключен)
+    #[serde(default)]
+    pub db: DbIdentity,
+    #[serde(default)]
+    pub tde_aad_since_lsn: u64,
+    /// NEW: голова области метаданных приложения (db/user_meta.rs; нет поля — старая сборка).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_meta_head: Option<u64>,
 }
 
 /// Привязка bucket -> head_pid для каталога в момент снапшота.
@@ -77,11 +93,40 @@ pub struct SnapshotHead {
 
 /// Объект контент-адресного хранилища (SnapStore), с которым связана страница.
 /// hash_hex ссылается на <snapstore_dir>/objects/<hh>/<rest>.
+/// hash_hex — SHA-256 образа страницы (bytes — его длина) до кодека хранения.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ManifestObject {
     pub page_id: u64,
     pub hash_hex: String,
     pub bytes: u64,
+    /// NEW: размер объекта так, как он лежит в SnapStore (после codec) — для HEAD-проверки
+    /// без скачивания. 0 — не записан (старые манифесты): проверяется по bytes.
+    #[serde(default)]
+    pub stored_bytes: u64,
+    /// NEW: кодек хранения объекта (meta::CODEC_*). SnapStore пишет сырые образы — CODEC_NONE.
+    #[serde(default)]
+    pub codec: u16,
+}
+
+impl ManifestObject {
+    /// Ожидаемый размер объекта в хранилище.
+    pub fn expected_stored_bytes(&self) -> u64 {
+        if self.stored_bytes > 0 {
+            self.stored_bytes
+        } else {
+            self.bytes
+        }
+    }
+}
+
+/// Образ sidecar-файла корня (bloom.bin), сохранённый объектом SnapStore.
+/// last_lsn — LSN, который покрывает образ (restore ставит его только при last_lsn == meta.lsn).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestSidecar {
+    pub name: String,
+    pub hash_hex: String,
+    pub bytes: u64,
+    pub last_lsn: u64,
 }
 
 /// Полный манифест снапшота (v2).
@@ -90,6 +135,16 @@ pub struct SnapshotManifestV2 {
     pub meta: SnapshotMetaV2,
     pub heads: Vec<SnapshotHead>,
     pub objects: Vec<ManifestObject>,
+    /// NEW: sidecar'ы для instant-ready restore (пусто — не сохранялись).
+    #[serde(default)]
+    pub sidecars: Vec<ManifestSidecar>,
+    /// NEW: SHA-256 (hex) компактного JSON манифеста без checksum_hex и signature
+    /// (SnapshotManifestV2::compute_checksum). Нет поля — не записан.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum_hex: Option<String>,
+    /// NEW: подпись Ed25519 (snapstore/signature; нет поля — манифест не подписан).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ManifestSignature>,
 }
 
 impl SnapshotManifestV2 {
@@ -120,9 +175,15 @@ impl SnapshotManifestV2 {
                 buckets,
                 hash_kind,
                 codec_default,
+                db: DbIdentity::default(),
+                tde_aad_since_lsn: 0,
+                user_meta_head: None,
             },
             heads: Vec::new(),
             objects: Vec::new(),
+            sidecars: Vec::new(),
+            checksum_hex: None,
+            signature: None,
         }
     }
 
@@ -132,13 +193,40 @@ impl SnapshotManifestV2 {
     }
 
     /// Добавить информацию о размещённом объекте (page_id -> hash_hex, bytes).
+    /// Объект хранится сырым образом: stored_bytes = bytes, codec = CODEC_NONE.
     pub fn add_object(&mut self, page_id: u64, hash_hex: String, bytes: u64) {
         self.objects.push(ManifestObject {
             page_id,
             hash_hex,
             bytes,
+            stored_bytes: bytes,
+            codec: CODEC_NONE,
         });
     }
+
+    /// NEW: контрольная сумма манифеста — SHA-256 компактного JSON без checksum_hex и signature.
+    pub fn compute_checksum(&self) -> Result<String> {
+        let mut m = self.clone();
+        m.checksum_hex = None;
+        m.signature = None;
+        let json = serde_json::to_vec(&m).context("serialize manifest for checksum")?;
+        Ok(hex_encode(&Sha256::digest(&json)))
+    }
+
+    /// NEW: записать checksum_hex (до подписи: подпись покрывает и контрольную сумму).
+    pub fn seal_checksum(&mut self) -> Result<()> {
+        self.checksum_hex = Some(self.compute_checksum()?);
+        Ok(())
+    }
+
+    /// NEW: Some(true/false) — совпадает ли checksum_hex с содержимым; None — не записан.
+    pub fn checksum_matches(&self) -> Option<bool> {
+        let want = self.checksum_hex.as_deref()?;
+        Some(
+            self.compute_checksum()
+                .is_ok_and(|got| got.eq_ignore_ascii_case(want)),
+        )
+    }
 }
 
 // --------- Paths/IO ----------
@@ -178,18 +266,25 @@ pub fn write_manifest(root: &Path, m: &SnapshotManifestV2) -> Result<PathBuf> {
             .with_context(|| format!("open tmp manifest {}", tmp.display()))?;
         f.write_all(json.as_bytes())?;
         f.flush()?;
+        // NEW: манифест — точка фиксации снапшота: на диске до rename
+        f.sync_all()?;
     }
-    fs::rename(&tmp, &path)
+    crate::util::fsx::replace_file(&tmp, &path)
         .with_context(|| format!("rename {} -> {}", tmp.display(), path.display()))?;
+    let _ = crate::util::fsx::fsync_parent_dir(&path);
     Ok(path)
 }
 
 /// Прочитать манифест (учитывает P1_SNAPSTORE_DIR).
 pub fn read_manifest(root: &Path, id: &str) -> Result<SnapshotManifestV2> {
-    let path = manifest_path(root, id);
+    read_manifest_file(&manifest_path(root, id))
+}
+
+/// Прочитать манифест по явному пути (<snapstore_dir>/manifests/<id>.json).
+pub(crate) fn read_manifest_file(path: &Path) -> Result<SnapshotManifestV2> {
     let mut f = OpenOptions::new()
         .read(true)
-        .open(&path)
+        .open(path)
         .with_context(|| format!("open manifest {}", path.display()))?;
     let mut buf = String::new();
     f.read_to_string(&mut buf)?;
@@ -6,6 +6,10 @@
 //! - manifest: форматы SnapshotManifestV2 и утилиты записи/чтения.
 //! - snapshot: SnapshotManager для создания persisted‑снапшота из открытой БД.
 //! - restore: восстановление БД из SnapStore+manifest v2 (полная БД в новый корень).
+//! - branch: writable-ветка из снапшота (copy-on-write: непрочитанные страницы — из SnapStore).
+//! - signature: подпись манифестов Ed25519 (ключ из KeyProvider) и её проверка.
+//! - verify: проверка снапшота против SnapStore (checksum, HEAD-размеры, выборочные SHA-256).
+//! - pins: реестр создаваемых снапшотов — обслуживание не освобождает страницы их голов.
 //!
 //! NEW (2.2):
 //! - P1_SNAPSTORE_DIR — переопределение пути SnapStore.
@@ -17,6 +21,7 @@
 //!   P1_SNAPSTORE_DIR=/mnt/snapstore     → /mnt/snapstore/{objects,refs}
 //!   P1_SNAPSTORE_DIR=.cache/snapstore   → <db_root>/.cache/snapstore/{objects,refs}
 
+use crate::util::fsx::replace_file;
 use anyhow::{anyhow, Context, Result};
 use byteorder::{ByteOrder, LittleEndian};
 use fs2::FileExt;
@@ -44,7 +49,12 @@ impl SnapStore {
     /// - если P1_SNAPSTORE_DIR абсолютный — используется как есть;
     /// - если P1_SNAPSTORE_DIR относительный — <root>/<P1_SNAPSTORE_DIR>.
     pub fn open_or_create(root: &Path) -> Result<Self> {
-        let dir = resolve_snapstore_dir(root);
+        Self::open_dir(&resolve_snapstore_dir(root))
+    }
+
+    /// Открыть или создать SnapStore в явно заданном каталоге (без P1_SNAPSTORE_DIR).
+    pub fn open_dir(dir: &Path) -> Result<Self> {
+        let dir = dir.to_path_buf();
         let objects = dir.join("objects");
         let refs = dir.join("refs");
         let lock_path = dir.join("snapstore.lock");
@@ -98,7 +108,7 @@ impl SnapStore {
                 f.write_all(bytes)?;
                 let _ = f.sync_all();
             }
-            fs::rename(&tmp, &obj_path)
+            replace_file(&tmp, &obj_path)
                 .with_context(|| format!("rename {} -> {}", tmp.display(), obj_path.display()))?;
         }
 
@@ -118,6 +128,15 @@ impl SnapStore {
         Ok(Some(buf))
     }
 
+    /// NEW: открыть файл объекта (restore клонирует его в сегмент, util/reflink.rs).
+    pub(crate) fn open_object(&self, hash_hex: &str) -> Result<Option<File>> {
+        match File::open(self.object_path(hash_hex)) {
+            Ok(f) => Ok(Some(f)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("open object {}", hash_hex)),
+        }
+    }
+
     /// Существует ли объект.
     pub fn has(&self, hash_hex: &str) -> bool {
         self.object_path(hash_hex).exists()
@@ -263,7 +282,7 @@ fn write_refcount(p: &Path, v: u64) -> Result<()> {
         f.write_all(&buf)?;
         let _ = f.sync_all();
     }
-    fs::rename(&tmp, p)?;
+    replace_file(&tmp, p)?;
     Ok(())
 }
 
@@ -273,7 +292,7 @@ fn write_refcount(p: &Path, v: u64) -> Result<()> {
 /// - пустой/неуказанный → <db_root>/.snapstore
 /// - абсолютный         → используем как есть
 /// - относительный      → <db_root>/<значение>
-pub(super) fn resolve_snapstore_dir(db_root: &Path) -> PathBuf {
+pub(crate) fn resolve_snapstore_dir(db_root: &Path) -> PathBuf {
     match std::env::var("P1_SNAPSTORE_DIR") {
         Ok(val) => {
             let s = val.trim();
@@ -297,7 +316,7 @@ pub mod manifest;
 
 pub use manifest::{
     generate_snapshot_id, list_manifests, manifest_path, manifests_dir, read_manifest,
-    write_manifest, ManifestObject, SnapshotManifestV2, SnapshotMetaV2,
+    write_manifest, ManifestObject, ManifestSidecar, SnapshotManifestV2, SnapshotMetaV2,
     SNAPSHOT_MANIFEST_VERSION_V2,
 };
 
@@ -305,7 +324,28 @@ pub use manifest::{
 
 mod restore;
 pub mod snapshot;
+// NEW: writable-ветки (copy-on-write поверх SnapStore)
+pub mod branch;
+// NEW: подпись манифестов (tamper evidence)
+pub mod signature;
+// NEW: проверка снапшота без полного скачивания объектов
+pub mod verify;
+// NEW: закрепление страниц создаваемого снапшота от освобождения обслуживанием
+pub mod pins;
 
 pub use snapshot::SnapshotManager;
 // NEW: реэкспорт функций восстановления
-pub use restore::{restore_from_id, restore_from_manifest};
+pub use branch::{create_branch, read_branch_info, BranchInfo, BRANCH_FILE};
+pub use pins::{
+    active_snapshot_pins, SnapshotPin, SnapshotPinGuard, SNAPSHOT_PINS_DIR, SNAPSHOT_PIN_LEASE_SECS,
+};
+pub use restore::{
+    restore_from_id, restore_from_id_with, restore_from_manifest, restore_from_manifest_with,
+};
+pub use signature::{
+    manifest_public_key, manifest_signature_status, sign_manifest, verify_manifest_signature,
+    ManifestSignature, ManifestSignatureError, SignatureStatus,
+};
+pub use verify::{
+    verify_manifest_objects, verify_snapshot, ObjectSource, SnapshotVerifyReport, VerifyOptions,
+};
@@ -0,0 +1,161 @@
+//! snapstore/pins — закрепление страниц создаваемого снапшота от освобождения обслуживанием.
+//!
+//! Persisted-снапшот после записи манифеста самодостаточен: все страницы — объекты SnapStore.
+//! Но пока SnapshotManager::create_persisted обходит страницы, он читает их из живого хранилища
+//! по головам, снятым в начале. Writer в другом процессе может в это время выполнить vacuum:
+//! компактация переписывает цепочки, sweep_orphan_overflow освобождает OVERFLOW-страницы старых
+//! версий, alloc_check_fix — старые KV-страницы, а следующие записи переиспользуют их. Снапшот
+//! тогда получил бы под старым page_id чужое (более новое) содержимое.
+//!
+//! Реестр: `<root>/.snapshot_pins/<id>.json` (SnapshotPin). Создатель снапшота:
+//! 1) пишет pin без голов (ожидание) — обслуживание ничего не освобождает;
+//! 2) снимает головы каталога (и голову области метаданных приложения) и дописывает их в pin;
+//! 3) обходит страницы, продлевая аренду (SNAPSHOT_PIN_LEASE_SECS);
+//! 4) удаляет pin (SnapshotPinGuard::drop).
+//!
+//! sweep_orphan_overflow и alloc_check_fix считают страницы, достижимые из голов активных
+//! pin'ов (KV-цепочки и OVERFLOW их записей), занятыми — как у курсоров scan_page. Pin в
+//! ожидании: sweep пропускается, alloc_check_fix отказывает. Компактация и trim страниц
+//! снапшота не трогают: компактация пишет новые страницы (из free-листа), trim пробивает только
+//! free-лист. Pin с истёкшей арендой (процесс создателя упал) игнорируется и удаляется.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Каталог реестра под корнем БД.
+pub const SNAPSHOT_PINS_DIR: &str = ".snapshot_pins";
+
+/// Аренда pin'а: без продления дольше этого — брошен (создатель упал).
+pub const SNAPSHOT_PIN_LEASE_SECS: u64 = 60;
+
+/// Запись реестра создаваемого снапшота.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotPin {
+    pub id: String,
+    pub process_id: u32,
+    /// LSN снапшота (0 — головы ещё не сняты).
+    pub lsn: u64,
+    /// Закреплённые головы цепочек; None — ожидание (освобождать нельзя ничего).
+    pub heads: Option<Vec<u64>>,
+    pub created_unix_ms: u64,
+    pub expires_unix_ms: u64,
+}
+
+/// Pin на время создания снапшота; удаляется при drop.
+pub struct SnapshotPinGuard {
+    path: PathBuf,
+    pin: SnapshotPin,
+    renewed: Instant,
+}
+
+impl SnapshotPinGuard {
+    /// Зарегистрировать pin в ожидании (до снятия голов).
+    pub fn acquire(root: &Path, id: &str) -> Result<Self> {
+        let dir = root.join(SNAPSHOT_PINS_DIR);
+        fs::create_dir_all(&dir).with_context(|| format!("create {}", dir.display()))?;
+        let now = now_unix_ms();
+        let g = Self {
+            path: dir.join(format!("{}.json", id)),
+            pin: SnapshotPin {
+                id: id.to_string(),
+                process_id: std::process::id(),
+                lsn: 0,
+                heads: None,
+                created_unix_ms: now,
+                expires_unix_ms: now + SNAPSHOT_PIN_LEASE_SECS * 1000,
+            },
+            renewed: Instant::now(),
+        };
+        g.store()?;
+        Ok(g)
+    }
+
+    /// Закрепить снятые головы (после этого обслуживание освобождает всё, кроме их страниц).
+    pub fn set_heads(&mut self, lsn: u64, heads: Vec<u64>) -> Result<()> {
+        self.pin.lsn = lsn;
+        self.pin.heads = Some(heads);
+        self.renew_now()
+    }
+
+    /// Продлить аренду, если прошла треть её срока (дёшево звать на каждой странице).
+    pub fn renew(&mut self) -> Result<()> {
+        if self.renewed.elapsed() < Duration::from_secs(SNAPSHOT_PIN_LEASE_SECS / 3) {
+            return Ok(());
+        }
+        self.renew_now()
+    }
+
+    pub fn pin(&self) -> &SnapshotPin {
+        &self.pin
+    }
+
+    fn renew_now(&mut self) -> Result<()> {
+        self.pin.expires_unix_ms = now_unix_ms() + SNAPSHOT_PIN_LEASE_SECS * 1000;
+        self.renewed = Instant::now();
+        self.store()
+    }
+
+    /// tmp + rename: читатель реестра не видит полузаписанный pin.
+    fn store(&self) -> Result<()> {
+        let tmp = self.path.with_extension("json.tmp");
+        {
+            let mut f =
+                fs::File::create(&tmp).with_context(|| format!("create {}", tmp.display()))?;
+            f.write_all(&serde_json::to_vec(&self.pin)?)?;
+            f.sync_all()?;
+        }
+        fs::rename(&tmp, &self.path).with_context(|| format!("install {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+impl Drop for SnapshotPinGuard {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Активные pin'ы под root (истёкшие удаляются, нечитаемые пропускаются).
+pub fn active_snapshot_pins(root: &Path) -> Result<Vec<SnapshotPin>> {
+    let dir = root.join(SNAPSHOT_PINS_DIR);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let now = now_unix_ms();
+    let mut out = Vec::new();
+    for e in fs::read_dir(&dir).with_context(|| format!("read_dir {}", dir.display()))? {
+        let path = e?.path();
+        if path.extension().and_then(|x| x.to_str()) != Some("json") {
+            continue;
+        }
+        let pin: SnapshotPin = match fs::read(&path)
+            .ok()
+            .and_then(|b| serde_json::from_slice(&b).ok())
+        {
+            Some(p) => p,
+            None => continue,
+        };
+        if pin.expires_unix_ms <= now {
+            eprintln!(
+                "[WARN] snapshot pin {} expired (creator pid {} gone?); removed",
+                pin.id, pin.process_id
+            );
+            let _ = fs::remove_file(&path);
+            continue;
+        }
+        out.push(pin);
+    }
+    out.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(out)
+}
+
+fn now_unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
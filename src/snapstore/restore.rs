@@ -7,27 +7,48 @@
 //! Публичные API:
 //! - restore_from_id(src_root, dst_root, id, verify)
 //! - restore_from_manifest(src_root, dst_root, &manifest, verify)
+//! - restore_from_id_with / restore_from_manifest_with — с RestoreOptions (verify + force +
+//!   require_signature)
 //!
 //! Поведение:
 //! - Создаёт (или валидирует) meta v4 и directory v2 в dst_root согласно полям manifest.meta.
 //! - Переносит все страницы (objects) в сегменты dst_root по их page_id (raw‑write).
 //! - Устанавливает directory heads и актуализирует meta.last_lsn/next_page_id (clean_shutdown=true).
 //! - Усечёт WAL до заголовка.
+//! - NEW: ставит sidecar'ы манифеста (bloom.bin), покрывающие ровно LSN снапшота;
+//!   остальные пропускаются с [WARN].
+//! - NEW: свежая dst принимает идентичность источника (db_uuid/провенанс) и AAD v2 из
+//!   manifest.meta; существующая dst с другим db_uuid отвергается (чужая БД).
+//! - NEW: проверка поколения: существующая dst новее снапшота (meta.last_lsn > manifest.meta.lsn)
+//!   отвергается RestoreConflictError (оба LSN в сообщении); откат БД к старому снапшоту на
+//!   месте — только с RestoreOptions::force (CLI `snapshot-restore --force`).
+//! - NEW: подпись манифеста проверяется до любых изменений в dst: invalid (манифест изменён) —
+//!   ManifestSignatureError всегда; RestoreOptions::require_signature требует valid.
+//! - NEW: если ФС умеет reflink (проба SnapStore → dst_root, util/reflink.rs), объект страницы
+//!   клонируется в сегмент по смещению страницы, а не копируется байтами; при отказе ФС
+//!   (невыровненная страница, разные ФС) — обычная запись.
 
 use anyhow::{anyhow, Context, Result};
 use std::path::Path;
 
+use crate::backup::{check_restore_target, RestoreOptions};
+use crate::bloom::sidecar::{BloomImage, BLOOM_FILE};
 use crate::dir::Directory;
 use crate::meta::{
+    adopt_db_identity,
+    adopt_user_meta_head,
     init_meta_v4,
     read_meta,
     write_meta_overwrite, // для дефолтов при валидации
     CKSUM_CRC32C,
+    FEATURE_TDE_AAD_V2,
 };
 use crate::pager::Pager;
+use crate::util::reflink::reflink_supported;
 use crate::wal::Wal;
 
 use super::manifest::{read_manifest, SnapshotManifestV2};
+use super::signature::check_manifest_signature;
 use super::SnapStore;
 
 /// Восстановить БД в dst_root по id снапшота из src_root/.snapstore/manifests/<id>.json.
@@ -38,6 +59,18 @@ pub fn restore_from_id(src_root: &Path, dst_root: &Path, id: &str, verify: bool)
     restore_from_manifest(src_root, dst_root, &manifest, verify)
 }
 
+/// Как restore_from_id, с опциями (проверка поколения dst).
+pub fn restore_from_id_with(
+    src_root: &Path,
+    dst_root: &Path,
+    id: &str,
+    opts: &RestoreOptions,
+) -> Result<()> {
+    let manifest = read_manifest(src_root, id)
+        .with_context(|| format!("read manifest '{}' at {}", id, src_root.display()))?;
+    restore_from_manifest_with(src_root, dst_root, &manifest, opts)
+}
+
 /// Восстановить БД в dst_root по заранее загруженному манифесту.
 /// src_root — место, где живёт SnapStore (objects + manifests).
 pub fn restore_from_manifest(
@@ -46,12 +79,30 @@ pub fn restore_from_manifest(
     manifest: &SnapshotManifestV2,
     verify: bool,
 ) -> Result<()> {
+    let opts = RestoreOptions {
+        verify,
+        ..Default::default()
+    };
+    restore_from_manifest_with(src_root, dst_root, manifest, &opts)
+}
+
+/// Как restore_from_manifest, с опциями (проверка поколения dst).
+pub fn restore_from_manifest_with(
+    src_root: &Path,
+    dst_root: &Path,
+    manifest: &SnapshotManifestV2,
+    opts: &RestoreOptions,
+) -> Result<()> {
+    let verify = opts.verify;
+    // 0) Подпись манифеста — до любых изменений в dst
+    check_manifest_signature(src_root, manifest, opts.require_signature)?;
+
     // 1) SnapStore (источник объектов страниц)
     let ss =
         SnapStore::open_or_create(src_root).context("open_or_create SnapStore at source root")?;
 
     // 2) Подготовка dst_root: meta v4 + directory v2
-    ensure_meta_and_dir(dst_root, manifest)?;
+    let fresh = ensure_meta_and_dir(dst_root, manifest, opts.force)?;
 
     // 3) Запись всех страниц в dst_root
     let ps = manifest.meta.page_size as usize;
@@ -60,8 +111,20 @@ pub fn restore_from_manifest(
     let mut pager =
         Pager::open(dst_root).with_context(|| format!("open pager at {}", dst_root.display()))?;
 
+    // NEW: reflink объектов в сегменты, если ФС умеет (иначе — запись байтов)
+    let mut reflink = reflink_supported(ss.dir_path(), dst_root);
+
     // Восстановим все страницы (objects)
     for obj in &manifest.objects {
+        if reflink {
+            if let Some(f) = ss.open_object(&obj.hash_hex)? {
+                if f.metadata()?.len() == ps as u64 && pager.clone_page_raw_from(obj.page_id, &f)? {
+                    continue;
+                }
+            }
+            // диапазон не склонировался — дальше байтами, без повторных попыток
+            reflink = false;
+        }
         let data = ss
             .get(&obj.hash_hex)
             .with_context(|| format!("snapstore get object {}", obj.hash_hex))?
@@ -80,7 +143,25 @@ pub fn restore_from_manifest(
         pager.write_page_raw(obj.page_id, &data)?;
     }
 
-    // 4) Установим directory heads
+    // 4-5) Directory heads, meta (last_lsn/next_page_id/clean_shutdown), пустой WAL
+    finish_root(dst_root, manifest, fresh)?;
+
+    // 6) Sidecars (instant-ready restore)
+    install_sidecars(&ss, dst_root, manifest)?;
+
+    Ok(())
+}
+
+// -------------------------- helpers --------------------------
+
+/// Установить heads каталога и meta по манифесту (clean_shutdown=true), усечь WAL.
+/// fresh — dst создана заново и принимает идентичность источника.
+pub(super) fn finish_root(
+    dst_root: &Path,
+    manifest: &SnapshotManifestV2,
+    fresh: bool,
+) -> Result<()> {
+    // Directory heads
     {
         let dir = Directory::open(dst_root)?;
         // set_heads_bulk — writer‑only API (pub(crate)), виден внутри crate'а
@@ -93,25 +174,62 @@ pub fn restore_from_manifest(
         }
     }
 
-    // 5) Установим meta.last_lsn/next_page_id/clean_shutdown и усечём WAL
+    // meta.last_lsn/next_page_id/clean_shutdown и пустой WAL
     {
         let mut m = read_meta(dst_root)?;
         m.last_lsn = manifest.meta.lsn;
         m.next_page_id = manifest.meta.next_page_id;
         m.clean_shutdown = true;
+        if fresh {
+            adopt_db_identity(&mut m, &manifest.meta.db)?;
+        }
+        if manifest.meta.tde_aad_since_lsn > 0 && m.flags & FEATURE_TDE_AAD_V2 == 0 {
+            m.flags |= FEATURE_TDE_AAD_V2;
+            m.tde_aad_since_lsn = manifest.meta.tde_aad_since_lsn;
+        }
+        adopt_user_meta_head(&mut m, manifest.meta.user_meta_head);
         write_meta_overwrite(dst_root, &m)?;
     }
     {
         let mut wal = Wal::open_for_append(dst_root)?;
         wal.truncate_to_header()?;
     }
-
     Ok(())
 }
 
-// -------------------------- helpers --------------------------
+/// Поставить sidecar'ы манифеста (bloom.bin), покрывающие ровно LSN снапшота.
+pub(super) fn install_sidecars(
+    ss: &SnapStore,
+    dst_root: &Path,
+    manifest: &SnapshotManifestV2,
+) -> Result<()> {
+    for sc in &manifest.sidecars {
+        if sc.name != BLOOM_FILE {
+            continue; // неизвестный sidecar
+        }
+        let data = ss
+            .get(&sc.hash_hex)
+            .with_context(|| format!("snapstore get sidecar {}", sc.hash_hex))?
+            .ok_or_else(|| anyhow!("snapstore sidecar object {} not found", sc.hash_hex))?;
+        match BloomImage::parse(data) {
+            Ok(img) if img.covers(manifest.meta.buckets, manifest.meta.lsn) => {
+                img.install(dst_root, manifest.meta.lsn)?;
+            }
+            Ok(_) => {
+                eprintln!("[WARN] restore: bloom.bin image does not cover the snapshot; skipped")
+            }
+            Err(e) => eprintln!("[WARN] restore: bloom.bin image skipped: {:#}", e),
+        }
+    }
+    Ok(())
+}
 
-fn ensure_meta_and_dir(dst_root: &Path, manifest: &SnapshotManifestV2) -> Result<()> {
+/// true — dst инициализирована заново (примет идентичность источника).
+pub(super) fn ensure_meta_and_dir(
+    dst_root: &Path,
+    manifest: &SnapshotManifestV2,
+    force: bool,
+) -> Result<bool> {
     // Создадим каталог dst_root при необходимости
     if !dst_root.exists() {
         std::fs::create_dir_all(dst_root)
@@ -132,11 +250,12 @@ fn ensure_meta_and_dir(dst_root: &Path, manifest: &SnapshotManifestV2) -> Result
 
         Directory::create(dst_root, manifest.meta.buckets)
             .with_context(|| "create directory v2 for restore")?;
-        return Ok(());
+        return Ok(true);
     }
 
     // Существующий meta: провалидируем базовые поля и при необходимости оставим как есть.
     let m_cur = read_meta(dst_root)?;
+    check_restore_target(dst_root, &manifest.meta.db, manifest.meta.lsn, force)?;
     if m_cur.page_size != manifest.meta.page_size {
         return Err(anyhow!(
             "dst meta.page_size={} mismatches snapshot.page_size={}",
@@ -166,5 +285,5 @@ fn ensure_meta_and_dir(dst_root: &Path, manifest: &SnapshotManifestV2) -> Result
             m_cur.codec_default, manifest.meta.codec_default
         );
     }
-    Ok(())
+    Ok(false)
 }
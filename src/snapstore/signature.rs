@@ -0,0 +1,298 @@
+//! snapstore/signature — подпись манифестов снапшотов (Ed25519) и её проверка.
+//!
+//! Tamper evidence для persisted-снапшотов: манифест перечисляет страницы по SHA-256 их
+//! содержимого (объекты SnapStore адресованы по содержимому), так что подпись манифеста
+//! покрывает и meta/heads/sidecars, и сами страницы. Любая правка манифеста ломает подпись.
+//!
+//! Ключ подписи выводится из ключа KeyProvider (TDE/KMS-цепочка, CompositeKeyProvider):
+//! seed Ed25519 = HMAC-SHA256(key(kid), "quiverdb-manifest-sign-v1"). Отдельный секрет не
+//! хранится; публичный ключ кладётся в манифест рядом с подписью.
+//!
+//! Подписываемые байты: SIGN_DOMAIN || компактный JSON манифеста без поля signature
+//! (порядок полей JSON — порядок полей структур, он стабилен).
+//!
+//! Статус проверки (SignatureStatus):
+//! - unsigned  — подписи нет;
+//! - valid     — подпись верна, публичный ключ доверенный: совпадает с выведенным из провайдера
+//!   для того же KID или указан в ENV P1_SNAPSHOT_TRUSTED_KEYS (hex через запятую);
+//! - untrusted — подпись верна, но ключ неизвестен (подписано чужим ключом);
+//! - invalid   — подпись не сходится (манифест изменён) или повреждена.
+//!
+//! Подпись при создании: QuiverConfig::snapshot_sign_kid (ENV P1_SNAPSHOT_SIGN_KID,
+//! CLI snapshot-create --sign-kid). Restore: invalid отвергается всегда, с
+//! RestoreOptions::require_signature (CLI --require-signature) — всё, кроме valid.
+//! Отказ — ManifestSignatureError (достаётся из anyhow через downcast_ref).
+
+use anyhow::{anyhow, Context, Result};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroize;
+
+use crate::crypto::{CompositeKeyProvider, KeyProvider};
+
+use super::manifest::SnapshotManifestV2;
+
+/// Единственный поддерживаемый алгоритм подписи.
+pub const MANIFEST_SIGNATURE_ALG: &str = "ed25519";
+
+/// Доменный префикс подписываемых байтов.
+const SIGN_DOMAIN: &[u8] = b"quiverdb-snapshot-manifest-v2\0";
+
+/// Контекст вывода seed из ключа KeyProvider.
+const SEED_CONTEXT: &[u8] = b"quiverdb-manifest-sign-v1";
+
+/// Подпись манифеста (поле SnapshotManifestV2::signature).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestSignature {
+    /// "ed25519"
+    pub alg: String,
+    /// KID ключа KeyProvider, из которого выведен ключ подписи.
+    pub kid: String,
+    /// Публичный ключ Ed25519 (hex, 32 байта).
+    pub public_key_hex: String,
+    /// Подпись (hex, 64 байта).
+    pub signature_hex: String,
+    pub signed_unix_ms: u64,
+}
+
+/// Результат проверки подписи манифеста.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureStatus {
+    Unsigned,
+    Valid,
+    Untrusted,
+    /// Причина (для сообщений/CLI).
+    Invalid(String),
+}
+
+impl SignatureStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignatureStatus::Unsigned => "unsigned",
+            SignatureStatus::Valid => "valid",
+            SignatureStatus::Untrusted => "untrusted",
+            SignatureStatus::Invalid(_) => "invalid",
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        matches!(self, SignatureStatus::Valid)
+    }
+}
+
+impl std::fmt::Display for SignatureStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureStatus::Invalid(reason) => write!(f, "invalid ({})", reason),
+            other => f.write_str(other.as_str()),
+        }
+    }
+}
+
+/// Снапшот отвергнут проверкой подписи. Достаётся из anyhow через downcast_ref.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestSignatureError {
+    /// Корень, где лежит SnapStore снапшота.
+    pub path: PathBuf,
+    pub snapshot_id: String,
+    pub status: SignatureStatus,
+}
+
+impl std::fmt::Display for ManifestSignatureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.status {
+            SignatureStatus::Invalid(reason) => write!(
+                f,
+                "snapshot {} at {}: manifest signature is invalid ({}); the manifest was modified \
+                 after signing",
+                self.snapshot_id,
+                self.path.display(),
+                reason
+            ),
+            st => write!(
+                f,
+                "snapshot {} at {}: manifest signature is {}, a valid signature is required \
+                 (--require-signature)",
+                self.snapshot_id,
+                self.path.display(),
+                st.as_str()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ManifestSignatureError {}
+
+/// Подписать манифест ключом, выведенным из ключа `kid` провайдера (прежняя подпись заменяется).
+pub fn sign_manifest(m: &mut SnapshotManifestV2, kp: &dyn KeyProvider, kid: &str) -> Result<()> {
+    let sk = signing_key(kp, kid)?;
+    let msg = signed_bytes(m)?;
+    let sig = sk.sign(&msg);
+    m.signature = Some(ManifestSignature {
+        alg: MANIFEST_SIGNATURE_ALG.to_string(),
+        kid: kid.to_string(),
+        public_key_hex: hex_encode(sk.verifying_key().as_bytes()),
+        signature_hex: hex_encode(&sig.to_bytes()),
+        signed_unix_ms: now_unix_ms(),
+    });
+    Ok(())
+}
+
+/// Публичный ключ подписи (hex) для `kid` провайдера — для P1_SNAPSHOT_TRUSTED_KEYS на стороне,
+/// где ключа нет.
+pub fn manifest_public_key(kp: &dyn KeyProvider, kid: &str) -> Result<String> {
+    Ok(hex_encode(signing_key(kp, kid)?.verifying_key().as_bytes()))
+}
+
+/// Проверить подпись манифеста. kp — провайдер, ключи которого считаются доверенными
+/// (вместе с P1_SNAPSHOT_TRUSTED_KEYS).
+pub fn verify_manifest_signature(
+    m: &SnapshotManifestV2,
+    kp: Option<&dyn KeyProvider>,
+) -> SignatureStatus {
+    let Some(sig) = m.signature.as_ref() else {
+        return SignatureStatus::Unsigned;
+    };
+    if sig.alg != MANIFEST_SIGNATURE_ALG {
+        return SignatureStatus::Invalid(format!("unsupported alg '{}'", sig.alg));
+    }
+    let vk = match hex_decode(&sig.public_key_hex)
+        .ok()
+        .and_then(|b| <[u8; 32]>::try_from(b).ok())
+        .and_then(|b| VerifyingKey::from_bytes(&b).ok())
+    {
+        Some(vk) => vk,
+        None => return SignatureStatus::Invalid("malformed public key".into()),
+    };
+    let signature = match hex_decode(&sig.signature_hex)
+        .ok()
+        .and_then(|b| <[u8; 64]>::try_from(b).ok())
+    {
+        Some(b) => Signature::from_bytes(&b),
+        None => return SignatureStatus::Invalid("malformed signature".into()),
+    };
+    let msg = match signed_bytes(m) {
+        Ok(b) => b,
+        Err(e) => return SignatureStatus::Invalid(format!("serialize manifest: {}", e)),
+    };
+    if vk.verify_strict(&msg, &signature).is_err() {
+        return SignatureStatus::Invalid("signature mismatch".into());
+    }
+
+    let pk = sig.public_key_hex.to_ascii_lowercase();
+    let trusted_env = trusted_keys_from_env().contains(&pk);
+    let trusted_kp =
+        kp.is_some_and(|p| manifest_public_key(p, &sig.kid).is_ok_and(|derived| derived == pk));
+    if trusted_env || trusted_kp {
+        SignatureStatus::Valid
+    } else {
+        SignatureStatus::Untrusted
+    }
+}
+
+/// Статус подписи с провайдером из окружения корня (CompositeKeyProvider::from_env).
+pub fn manifest_signature_status(root: &Path, m: &SnapshotManifestV2) -> SignatureStatus {
+    match CompositeKeyProvider::from_env(root) {
+        Ok(kp) => verify_manifest_signature(m, Some(&kp)),
+        Err(_) => verify_manifest_signature(m, None),
+    }
+}
+
+/// Проверка перед restore: invalid — всегда отказ; require — отказ для всего, кроме valid.
+pub(crate) fn check_manifest_signature(
+    root: &Path,
+    m: &SnapshotManifestV2,
+    require: bool,
+) -> Result<SignatureStatus> {
+    let status = manifest_signature_status(root, m);
+    let refuse = match &status {
+        SignatureStatus::Invalid(_) => true,
+        SignatureStatus::Valid => false,
+        SignatureStatus::Unsigned | SignatureStatus::Untrusted => require,
+    };
+    if refuse {
+        return Err(ManifestSignatureError {
+            path: root.to_path_buf(),
+            snapshot_id: m.meta.id.clone(),
+            status,
+        }
+        .into());
+    }
+    if status == SignatureStatus::Untrusted {
+        eprintln!(
+            "[WARN] snapshot {}: manifest is signed by an untrusted key (kid={})",
+            m.meta.id,
+            m.signature.as_ref().map(|s| s.kid.as_str()).unwrap_or("")
+        );
+    }
+    Ok(status)
+}
+
+// --------- Helpers ----------
+
+fn signing_key(kp: &dyn KeyProvider, kid: &str) -> Result<SigningKey> {
+    let km = kp
+        .key(kid)
+        .with_context(|| format!("load manifest signing key for KID '{}'", kid))?;
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(&km.key).map_err(|e| anyhow!("hmac init: {}", e))?;
+    mac.update(SEED_CONTEXT);
+    let mut seed: [u8; 32] = mac.finalize().into_bytes().into();
+    let sk = SigningKey::from_bytes(&seed);
+    seed.zeroize();
+    Ok(sk)
+}
+
+fn signed_bytes(m: &SnapshotManifestV2) -> Result<Vec<u8>> {
+    let mut unsigned = m.clone();
+    unsigned.signature = None;
+    let mut out = SIGN_DOMAIN.to_vec();
+    serde_json::to_writer(&mut out, &unsigned).context("serialize manifest for signing")?;
+    Ok(out)
+}
+
+fn trusted_keys_from_env() -> Vec<String> {
+    std::env::var("P1_SNAPSHOT_TRUSTED_KEYS")
+        .map(|s| {
+            s.split(',')
+                .map(|k| k.trim().to_ascii_lowercase())
+                .filter(|k| !k.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn now_unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for &b in bytes {
+        out.push(HEX[(b >> 4) as usize] as char);
+        out.push(HEX[(b & 0x0f) as usize] as char);
+    }
+    out
+}
+
+fn hex_decode(s: &str) -> Result<Vec<u8>> {
+    let s = s.trim();
+    if !s.len().is_multiple_of(2) {
+        return Err(anyhow!("odd hex length"));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(s.get(i..i + 2).unwrap_or("zz"), 16)
+                .map_err(|e| anyhow!("bad hex: {}", e))
+        })
+        .collect()
+}
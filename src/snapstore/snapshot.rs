@@ -7,6 +7,14 @@
 //!   * Читает manifest;
 //!   * Для каждого объекта вызывает dec_ref (объект удаляется при rc==0);
 //!   * Удаляет файл манифеста.
+//! - NEW: SnapshotManager::create_persisted_with_sidecars — то же + образ bloom.bin (если он
+//!   свежий на LSN снапшота) объектом SnapStore; restore ставит его, и восстановленная БД сразу
+//!   отвечает на промахи через Bloom. delete_persisted снимает ref и с объектов sidecar'ов.
+//! - NEW: при QuiverConfig::snapshot_sign_kid манифест подписывается Ed25519 (ключ — из
+//!   KeyProvider БД или CompositeKeyProvider::from_env), см. snapstore/signature.rs.
+//! - NEW: на время обхода страниц снапшот закреплён в реестре (snapstore/pins.rs): vacuum /
+//!   sweep_orphan_overflow / alloc_check_fix writer'а (в том числе другого процесса) не
+//!   освобождают страницы, достижимые из снятых голов, и не отдают их под новые записи.
 //!
 //! Примечание:
 //! - Путь SnapStore учитывает ENV P1_SNAPSTORE_DIR (см. snapstore::open_or_create).
@@ -15,12 +23,19 @@
 use anyhow::{Context, Result};
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
 
+use super::pins::SnapshotPinGuard;
+use super::signature::sign_manifest;
 use super::SnapStore;
+use crate::bloom::sidecar::{BloomImage, BLOOM_FILE};
+use crate::crypto::{CompositeKeyProvider, KeyProvider};
 use crate::db::Db;
+use crate::meta::FEATURE_TDE_AAD_V2;
 use crate::page::PAGE_MAGIC;
 use crate::snapstore::manifest::{
-    generate_snapshot_id, manifest_path, read_manifest, write_manifest, SnapshotManifestV2,
+    generate_snapshot_id, manifest_path, read_manifest, write_manifest, ManifestSidecar,
+    SnapshotManifestV2,
 };
 
 pub struct SnapshotManager;
@@ -40,6 +55,27 @@ impl SnapshotManager {
         message: Option<&str>,
         labels: &[&str],
         parent: Option<&str>,
+    ) -> Result<String> {
+        Self::create_persisted_inner(db, message, labels, parent, false)
+    }
+
+    /// NEW: create_persisted + образ bloom.bin для instant-ready restore.
+    /// Несвежий (last_lsn != LSN снапшота) или отсутствующий bloom.bin не сохраняется.
+    pub fn create_persisted_with_sidecars(
+        db: &Db,
+        message: Option<&str>,
+        labels: &[&str],
+        parent: Option<&str>,
+    ) -> Result<String> {
+        Self::create_persisted_inner(db, message, labels, parent, true)
+    }
+
+    fn create_persisted_inner(
+        db: &Db,
+        message: Option<&str>,
+        labels: &[&str],
+        parent: Option<&str>,
+        with_sidecars: bool,
     ) -> Result<String> {
         let root = &db.root;
         let meta = &db.pager.meta;
@@ -53,6 +89,8 @@ impl SnapshotManager {
 
         // Новый id снапшота
         let id = generate_snapshot_id();
+        // NEW: pin до снятия голов — пока их нет, обслуживание ничего не освобождает
+        let mut pin = SnapshotPinGuard::acquire(root, &id).context("register snapshot pin")?;
 
         // Инициализируем манифест (ВАЖНО: передаём hash_kind и codec_default из meta)
         let mut manifest = SnapshotManifestV2::new(
@@ -67,17 +105,28 @@ impl SnapshotManager {
             meta.hash_kind,
             meta.codec_default,
         );
+        manifest.meta.db = meta.identity();
+        if meta.flags & FEATURE_TDE_AAD_V2 != 0 {
+            manifest.meta.tde_aad_since_lsn = meta.tde_aad_since_lsn;
+        }
+        let user_meta_head = db.user_meta_head()?;
+        manifest.meta.user_meta_head = Some(user_meta_head);
 
         // Heads каталога (bucket -> head_pid)
+        let mut pinned = Vec::with_capacity(buckets as usize + 1);
         for b in 0..buckets {
             let head = db.dir.head(b).with_context(|| format!("dir.head({})", b))?;
             manifest.add_head(b, head);
+            pinned.push(head);
         }
+        pinned.push(user_meta_head);
+        pin.set_heads(lsn, pinned)?;
 
         // Обход всех страниц
         let mut page_buf = vec![0u8; ps];
 
         for pid in 0..next_page_id {
+            pin.renew()?;
             // read_page проверит CRC/AEAD. Если страница не аллоцирована/битая — пропускаем.
             match db.pager.read_page(pid, &mut page_buf) {
                 Ok(()) => {
@@ -100,6 +149,40 @@ impl SnapshotManager {
             }
         }
 
+        // Sidecars: bloom.bin, если он покрывает ровно срез снапшота
+        if with_sidecars {
+            match BloomImage::read(root)? {
+                Some(img) if img.covers(buckets, lsn) => {
+                    let (hash_hex, _existed, _rc) =
+                        ss.put(&img.bytes).context("snapstore put bloom.bin")?;
+                    manifest.sidecars.push(ManifestSidecar {
+                        name: BLOOM_FILE.to_string(),
+                        hash_hex,
+                        bytes: img.bytes.len() as u64,
+                        last_lsn: img.last_lsn,
+                    });
+                }
+                Some(img) => eprintln!(
+                    "[WARN] snapshot: bloom.bin is stale (last_lsn {} != snapshot lsn {}); not included",
+                    img.last_lsn, lsn
+                ),
+                None => {}
+            }
+        }
+
+        // NEW: контрольная сумма манифеста (до подписи — подпись покрывает и её)
+        manifest.seal_checksum()?;
+
+        // NEW: подпись манифеста (QuiverConfig::snapshot_sign_kid)
+        if let Some(kid) = db.snapshot_sign_kid.as_deref() {
+            let kp: Arc<dyn KeyProvider> = match &db.pager.key_provider {
+                Some(p) => p.clone(),
+                None => Arc::new(CompositeKeyProvider::from_env(root)?),
+            };
+            sign_manifest(&mut manifest, kp.as_ref(), kid)
+                .with_context(|| format!("sign snapshot manifest (kid={})", kid))?;
+        }
+
         // Сохраним манифест на диск
         let _path = write_manifest(root, &manifest).with_context(|| "write snapshot manifest")?;
 
@@ -133,11 +216,16 @@ impl SnapshotManager {
         let ss = SnapStore::open_or_create(root).context("open_or_create SnapStore")?;
 
         // 3) Декремент refcount по всем объектам
-        for obj in &m.objects {
+        let hashes = m
+            .objects
+            .iter()
+            .map(|o| &o.hash_hex)
+            .chain(m.sidecars.iter().map(|s| &s.hash_hex));
+        for hash_hex in hashes {
             // dec_ref безопасен: при достижении 0 объект и ref‑файл удаляются
             let _ = ss
-                .dec_ref(&obj.hash_hex)
-                .with_context(|| format!("dec_ref for object {}", obj.hash_hex))?;
+                .dec_ref(hash_hex)
+                .with_context(|| format!("dec_ref for object {}", hash_hex))?;
         }
 
         // 4) Удалим сам манифест
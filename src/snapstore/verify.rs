@@ -0,0 +1,185 @@
+//! snapstore/verify — проверка persisted-снапшота против SnapStore без полного скачивания.
+//!
+//! Рассчитано на удалённое/объектное хранилище (S3-подобное, смонтированный P1_SNAPSTORE_DIR):
+//! 1) checksum_hex манифеста (если записан) — манифест цел;
+//! 2) HEAD-фаза: для каждого уникального объекта (страницы + sidecar'ы) — есть ли он и
+//!    совпадает ли размер с stored_bytes манифеста (только метаданные);
+//! 3) spot-check: у выборки объектов (равномерно по списку, VerifyOptions::sample; full — все)
+//!    содержимое скачивается, раскодируется по codec и сверяется SHA-256 с hash_hex.
+//!
+//! Источник объектов — трейт ObjectSource (SnapStore реализует его; удалённое хранилище
+//! достаточно научить HEAD и GET).
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::meta::{CODEC_NONE, CODEC_ZSTD};
+
+use super::manifest::{read_manifest, SnapshotManifestV2};
+use super::SnapStore;
+
+/// Выборка spot-check по умолчанию (объектов).
+pub const DEFAULT_VERIFY_SAMPLE: usize = 16;
+
+/// Хранилище объектов снапшота: HEAD (размер) и GET (содержимое).
+pub trait ObjectSource {
+    /// Размер объекта; None — объекта нет.
+    fn head(&self, hash_hex: &str) -> Result<Option<u64>>;
+    /// Содержимое объекта (как лежит, до раскодирования); None — объекта нет.
+    fn fetch(&self, hash_hex: &str) -> Result<Option<Vec<u8>>>;
+}
+
+impl ObjectSource for SnapStore {
+    fn head(&self, hash_hex: &str) -> Result<Option<u64>> {
+        self.size(hash_hex)
+    }
+
+    fn fetch(&self, hash_hex: &str) -> Result<Option<Vec<u8>>> {
+        self.get(hash_hex)
+    }
+}
+
+/// Параметры проверки.
+#[derive(Debug, Clone, Copy)]
+pub struct VerifyOptions {
+    /// Сколько объектов скачать и сверить по SHA-256 (0 — только HEAD-фаза).
+    pub sample: usize,
+    /// Сверить содержимое всех объектов (sample игнорируется).
+    pub full: bool,
+}
+
+impl Default for VerifyOptions {
+    fn default() -> Self {
+        Self {
+            sample: DEFAULT_VERIFY_SAMPLE,
+            full: false,
+        }
+    }
+}
+
+/// Итог проверки снапшота.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SnapshotVerifyReport {
+    pub snapshot_id: String,
+    /// ok | mismatch | absent (checksum_hex не записан)
+    pub checksum: &'static str,
+    /// Записей objects + sidecars в манифесте.
+    pub objects: u64,
+    /// Уникальных объектов (dedup по hash_hex).
+    pub unique_objects: u64,
+    /// Сумма ожидаемых размеров уникальных объектов.
+    pub stored_bytes: u64,
+    /// Объекты, отсутствующие в хранилище (hash_hex).
+    pub missing: Vec<String>,
+    /// Объекты с размером, отличным от манифеста (hash_hex).
+    pub size_mismatch: Vec<String>,
+    /// Скачано и сверено по SHA-256.
+    pub hash_checked: u64,
+    pub hash_mismatch: Vec<String>,
+    /// Выбраны для сверки, но кодек хранения не поддерживается.
+    pub hash_skipped: u64,
+    pub ok: bool,
+}
+
+/// Проверить снапшот `id` в SnapStore корня (учитывает P1_SNAPSTORE_DIR).
+pub fn verify_snapshot(
+    root: &Path,
+    id: &str,
+    opts: &VerifyOptions,
+) -> Result<SnapshotVerifyReport> {
+    let m = read_manifest(root, id)
+        .with_context(|| format!("read manifest '{}' at {}", id, root.display()))?;
+    let ss = SnapStore::open_or_create(root).context("open SnapStore")?;
+    verify_manifest_objects(&m, &ss, opts)
+}
+
+/// Проверить объекты манифеста в произвольном хранилище.
+pub fn verify_manifest_objects(
+    m: &SnapshotManifestV2,
+    src: &dyn ObjectSource,
+    opts: &VerifyOptions,
+) -> Result<SnapshotVerifyReport> {
+    let mut rep = SnapshotVerifyReport {
+        snapshot_id: m.meta.id.clone(),
+        checksum: match m.checksum_matches() {
+            Some(true) => "ok",
+            Some(false) => "mismatch",
+            None => "absent",
+        },
+        objects: (m.objects.len() + m.sidecars.len()) as u64,
+        ..Default::default()
+    };
+
+    // hash_hex -> (ожидаемый размер в хранилище, codec); порядок — по hash (детерминированная выборка)
+    let mut unique: BTreeMap<&str, (u64, u16)> = BTreeMap::new();
+    for o in &m.objects {
+        unique
+            .entry(o.hash_hex.as_str())
+            .or_insert((o.expected_stored_bytes(), o.codec));
+    }
+    for sc in &m.sidecars {
+        unique
+            .entry(sc.hash_hex.as_str())
+            .or_insert((sc.bytes, CODEC_NONE));
+    }
+    rep.unique_objects = unique.len() as u64;
+
+    // HEAD-фаза: только метаданные
+    let mut present: Vec<(&str, u16)> = Vec::with_capacity(unique.len());
+    for (&hash, &(size, codec)) in &unique {
+        rep.stored_bytes += size;
+        match src.head(hash)? {
+            None => rep.missing.push(hash.to_string()),
+            Some(n) if n != size => rep.size_mismatch.push(hash.to_string()),
+            Some(_) => present.push((hash, codec)),
+        }
+    }
+
+    // Spot-check: равномерно по списку присутствующих объектов
+    let n = present.len();
+    let take = if opts.full { n } else { opts.sample.min(n) };
+    for i in 0..take {
+        let (hash, codec) = present[i * n / take];
+        let Some(stored) = src.fetch(hash)? else {
+            rep.missing.push(hash.to_string());
+            continue;
+        };
+        let image = match codec {
+            CODEC_NONE => stored,
+            CODEC_ZSTD => match zstd::stream::decode_all(stored.as_slice()) {
+                Ok(v) => v,
+                Err(_) => {
+                    rep.hash_mismatch.push(hash.to_string());
+                    continue;
+                }
+            },
+            _ => {
+                rep.hash_skipped += 1;
+                continue;
+            }
+        };
+        rep.hash_checked += 1;
+        if !hex_encode(&Sha256::digest(&image)).eq_ignore_ascii_case(hash) {
+            rep.hash_mismatch.push(hash.to_string());
+        }
+    }
+
+    rep.ok = rep.checksum != "mismatch"
+        && rep.missing.is_empty()
+        && rep.size_mismatch.is_empty()
+        && rep.hash_mismatch.is_empty();
+    Ok(rep)
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for &b in bytes {
+        out.push(HEX[(b >> 4) as usize] as char);
+        out.push(HEX[(b & 0x0f) as usize] as char);
+    }
+    out
+}
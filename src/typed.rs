@@ -0,0 +1,255 @@
+//! typed — типизированная обёртка TypedDb<K, V> над Db (фича "serde").
+//!
+//! Кодирование ключей и значений через serde с подключаемым кодеком (трейт Codec): встроенный
+//! JsonCodec; bincode/msgpack/CBOR подключаются реализацией Codec поверх своего крейта, например:
+//!
+//! ```ignore
+//! struct Bincode;
+//! impl QuiverDB::typed::Codec for Bincode {
+//!     fn name(&self) -> &'static str { "bincode" }
+//!     fn encode<T: serde::Serialize + ?Sized>(&self, v: &T) -> anyhow::Result<Vec<u8>> {
+//!         Ok(bincode::serialize(v)?)
+//!     }
+//!     fn decode<T: serde::de::DeserializeOwned>(&self, b: &[u8]) -> anyhow::Result<T> {
+//!         Ok(bincode::deserialize(b)?)
+//!     }
+//! }
+//! ```
+//!
+//! Ключ и значение могут иметь разные кодеки (TypedDb<K, V, KC, VC>). Формат хранения не меняется:
+//! в БД лежат закодированные байты, сырой Db доступен через db()/db_mut().
+//! Сканы декодируют лениво (итератор Result<(K, V)>); префиксный скан — по сырому префиксу
+//! закодированного ключа (порядок/префиксы зависят от кодека ключа).
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+
+use crate::db::batch::Batch;
+use crate::db::Db;
+
+/// Кодек serde-значений в байты.
+pub trait Codec {
+    fn name(&self) -> &'static str;
+    fn encode<T: Serialize + ?Sized>(&self, v: &T) -> Result<Vec<u8>>;
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T>;
+}
+
+/// JSON (serde_json), компактная форма.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn encode<T: Serialize + ?Sized>(&self, v: &T) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(v)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// Типизированный Db: K/V кодируются кодеками KC/VC.
+pub struct TypedDb<K, V, KC = JsonCodec, VC = JsonCodec> {
+    db: Db,
+    kc: KC,
+    vc: VC,
+    _pd: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V> TypedDb<K, V, JsonCodec, JsonCodec>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    /// Обёртка с JSON-кодеком для ключей и значений.
+    pub fn new(db: Db) -> Self {
+        Self::with_codecs(db, JsonCodec, JsonCodec)
+    }
+}
+
+impl<K, V, KC, VC> TypedDb<K, V, KC, VC>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    KC: Codec,
+    VC: Codec,
+{
+    pub fn with_codecs(db: Db, key_codec: KC, value_codec: VC) -> Self {
+        Self {
+            db,
+            kc: key_codec,
+            vc: value_codec,
+            _pd: PhantomData,
+        }
+    }
+
+    pub fn db(&self) -> &Db {
+        &self.db
+    }
+
+    pub fn db_mut(&mut self) -> &mut Db {
+        &mut self.db
+    }
+
+    pub fn into_inner(self) -> Db {
+        self.db
+    }
+
+    pub fn encode_key(&self, key: &K) -> Result<Vec<u8>> {
+        self.kc
+            .encode(key)
+            .with_context(|| format!("encode key ({})", self.kc.name()))
+    }
+
+    pub fn put(&mut self, key: &K, value: &V) -> Result<()> {
+        let k = self.encode_key(key)?;
+        let v = self
+            .vc
+            .encode(value)
+            .with_context(|| format!("encode value ({})", self.vc.name()))?;
+        self.db.put(&k, &v)
+    }
+
+    pub fn get(&self, key: &K) -> Result<Option<V>> {
+        let k = self.encode_key(key)?;
+        match self.db.get(&k)? {
+            Some(v) => Ok(Some(self.decode_value(&v)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn del(&mut self, key: &K) -> Result<bool> {
+        let k = self.encode_key(key)?;
+        self.db.del(&k)
+    }
+
+    pub fn exists(&self, key: &K) -> Result<bool> {
+        let k = self.encode_key(key)?;
+        self.db.exists(&k)
+    }
+
+    pub fn get_many(&self, keys: &[K]) -> Result<Vec<Option<V>>> {
+        let enc: Vec<Vec<u8>> = keys
+            .iter()
+            .map(|k| self.encode_key(k))
+            .collect::<Result<_>>()?;
+        let refs: Vec<&[u8]> = enc.iter().map(|k| k.as_slice()).collect();
+        self.db
+            .get_many(&refs)?
+            .into_iter()
+            .map(|v| v.map(|b| self.decode_value(&b)).transpose())
+            .collect()
+    }
+
+    /// Типизированный batch (один WAL-батч, как Db::batch).
+    pub fn batch<F>(&mut self, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut TypedBatch<'_, '_, K, V, KC, VC>) -> Result<()>,
+    {
+        let (kc, vc) = (&self.kc, &self.vc);
+        self.db.batch(|b| {
+            let mut tb = TypedBatch {
+                inner: b,
+                kc,
+                vc,
+                _pd: PhantomData,
+            };
+            f(&mut tb)
+        })
+    }
+
+    /// Все пары (ленивое декодирование).
+    pub fn scan(&self) -> Result<TypedIter<'_, K, V, KC, VC>> {
+        Ok(self.iter_of(self.db.scan_all()?))
+    }
+
+    /// Пары с сырым префиксом закодированного ключа.
+    pub fn scan_prefix_raw(&self, prefix: &[u8]) -> Result<TypedIter<'_, K, V, KC, VC>> {
+        Ok(self.iter_of(self.db.scan_prefix(prefix)?))
+    }
+
+    fn iter_of(&self, items: Vec<(Vec<u8>, Vec<u8>)>) -> TypedIter<'_, K, V, KC, VC> {
+        TypedIter {
+            items: items.into_iter(),
+            kc: &self.kc,
+            vc: &self.vc,
+            _pd: PhantomData,
+        }
+    }
+
+    fn decode_value(&self, bytes: &[u8]) -> Result<V> {
+        self.vc
+            .decode(bytes)
+            .with_context(|| format!("decode value ({})", self.vc.name()))
+    }
+}
+
+/// Типизированный batch.
+pub struct TypedBatch<'b, 'a, K, V, KC, VC> {
+    inner: &'b mut Batch<'a>,
+    kc: &'b KC,
+    vc: &'b VC,
+    _pd: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V, KC, VC> TypedBatch<'_, '_, K, V, KC, VC>
+where
+    K: Serialize,
+    V: Serialize,
+    KC: Codec,
+    VC: Codec,
+{
+    pub fn put(&mut self, key: &K, value: &V) -> Result<()> {
+        let k = self.kc.encode(key)?;
+        let v = self.vc.encode(value)?;
+        self.inner.put(&k, &v)
+    }
+
+    pub fn del(&mut self, key: &K) -> Result<bool> {
+        let k = self.kc.encode(key)?;
+        self.inner.del(&k)
+    }
+}
+
+/// Итератор декодированных пар скана.
+pub struct TypedIter<'a, K, V, KC, VC> {
+    items: std::vec::IntoIter<(Vec<u8>, Vec<u8>)>,
+    kc: &'a KC,
+    vc: &'a VC,
+    _pd: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V, KC, VC> Iterator for TypedIter<'_, K, V, KC, VC>
+where
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+    KC: Codec,
+    VC: Codec,
+{
+    type Item = Result<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (k, v) = self.items.next()?;
+        Some((|| {
+            let key = self
+                .kc
+                .decode(&k)
+                .with_context(|| format!("decode key ({})", self.kc.name()))?;
+            let val = self
+                .vc
+                .decode(&v)
+                .with_context(|| format!("decode value ({})", self.vc.name()))?;
+            Ok((key, val))
+        })())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.items.size_hint()
+    }
+}
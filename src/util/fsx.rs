@@ -0,0 +1,142 @@
+//! util/fsx — кросс-платформенные файловые примитивы (Unix + Windows).
+//!
+//! Все атомарные перезаписи служебных файлов (meta, dir, keyring, манифесты, side-car'ы) идут
+//! через tmp + replace_file + fsync_parent_dir. Различия платформ спрятаны здесь:
+//!
+//! - replace_file: Unix — rename(2). Windows — MoveFileExW(REPLACE_EXISTING) через std::fs::rename;
+//!   цель может быть временно занята (антивирус/индексатор/чужой хэндл без FILE_SHARE_DELETE) —
+//!   тогда несколько повторов с backoff, затем fallback: копирование tmp поверх цели с
+//!   FILE_FLAG_WRITE_THROUGH + FlushFileBuffers и удаление tmp.
+//! - fsync_parent_dir: Unix — fsync каталога. Windows — каталог открывается с
+//!   FILE_FLAG_BACKUP_SEMANTICS и сбрасывается FlushFileBuffers (best-effort: NTFS журналирует
+//!   метаданные rename, ошибка не фатальна).
+//! - open_tmp_for_write: tmp-файл для атомарной записи; на Windows — FILE_FLAG_WRITE_THROUGH.
+//! - long_path: Windows — префикс `\\?\` (и `\\?\UNC\`) для путей длиннее MAX_PATH; Unix — как есть.
+//!
+//! Блокировки (LOCK) — fs2: flock(2) на Unix, LockFileEx на Windows (shared/exclusive семантика
+//! одинакова; блокировка снимается при закрытии хэндла).
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[cfg(windows)]
+const FILE_FLAG_WRITE_THROUGH: u32 = 0x8000_0000;
+#[cfg(windows)]
+const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+
+/// Порог длины пути, после которого на Windows нужен префикс `\\?\` (MAX_PATH=260 минус запас
+/// под имя файла внутри каталога).
+#[cfg(windows)]
+const WIN_LONG_PATH_THRESHOLD: usize = 240;
+
+/// Сколько раз повторять rename на Windows при занятой цели.
+#[cfg(windows)]
+const WIN_RENAME_RETRIES: u32 = 8;
+
+/// Открыть tmp-файл для атомарной перезаписи (create + truncate).
+pub fn open_tmp_for_write(path: &Path) -> io::Result<File> {
+    let mut oo = OpenOptions::new();
+    oo.create(true).write(true).truncate(true);
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        oo.custom_flags(FILE_FLAG_WRITE_THROUGH);
+    }
+    oo.open(path)
+}
+
+/// Атомарно заменить dst файлом tmp (tmp уже записан и сброшен на диск).
+pub fn replace_file(tmp: &Path, dst: &Path) -> io::Result<()> {
+    #[cfg(not(windows))]
+    {
+        std::fs::rename(tmp, dst)
+    }
+    #[cfg(windows)]
+    {
+        let mut delay_ms = 5u64;
+        for _ in 0..WIN_RENAME_RETRIES {
+            match std::fs::rename(tmp, dst) {
+                Ok(()) => return Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                    std::thread::sleep(std::time::Duration::from_millis(delay_ms));
+                    delay_ms = (delay_ms * 2).min(200);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        copy_over_write_through(tmp, dst)?;
+        std::fs::remove_file(tmp)
+    }
+}
+
+/// Windows fallback: перезапись содержимого цели на месте (не атомарно, но durable).
+#[cfg(windows)]
+fn copy_over_write_through(tmp: &Path, dst: &Path) -> io::Result<()> {
+    use std::io::Write;
+    use std::os::windows::fs::OpenOptionsExt;
+    let bytes = std::fs::read(tmp)?;
+    let mut f = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .custom_flags(FILE_FLAG_WRITE_THROUGH)
+        .open(dst)?;
+    f.write_all(&bytes)?;
+    f.sync_all()
+}
+
+/// Сбросить на диск каталог, содержащий path (фиксирует rename/create).
+pub fn fsync_parent_dir(path: &Path) -> io::Result<()> {
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => return Ok(()),
+    };
+    #[cfg(unix)]
+    {
+        File::open(parent)?.sync_all()
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        let dir = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+            .open(parent)?;
+        dir.sync_all()
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = parent;
+        Ok(())
+    }
+}
+
+/// Путь, пригодный для файловых операций на длинных путях (Windows `\\?\`); на Unix — как есть.
+pub fn long_path(path: &Path) -> PathBuf {
+    #[cfg(windows)]
+    {
+        let s = path.as_os_str().to_string_lossy();
+        if s.len() < WIN_LONG_PATH_THRESHOLD || s.starts_with(r"\\?\") {
+            return path.to_path_buf();
+        }
+        // Существующий путь: canonicalize сам возвращает verbatim-форму (`\\?\C:\...`)
+        if let Ok(c) = std::fs::canonicalize(path) {
+            return c;
+        }
+        if !path.is_absolute() {
+            return path.to_path_buf();
+        }
+        // `\\?\` отключает нормализацию: "/" и "." должны быть разрешены заранее
+        let norm = s.replace('/', r"\");
+        if let Some(unc) = norm.strip_prefix(r"\\") {
+            return PathBuf::from(format!(r"\\?\UNC\{}", unc));
+        }
+        PathBuf::from(format!(r"\\?\{}", norm))
+    }
+    #[cfg(not(windows))]
+    {
+        path.to_path_buf()
+    }
+}
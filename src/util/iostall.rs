@@ -0,0 +1,211 @@
+//! util/iostall — детектор «подвисаний» IO (slow-IO диагностика) вокруг fsync WAL и записи сегментов.
+//!
+//! Когда диск подвисает, put просто висит без какой-либо видимости. IoWatch (RAII) оборачивает
+//! операцию: на время выполнения операция видна в реестре in-flight (recent_io_stalls отдаёт её
+//! с in_flight=true, как только она дольше порога, — видно из другого потока, пока put висит);
+//! по завершении операция дольше порога попадает в кольцо последних событий (64), в метрики
+//! (io_stalls_total / io_stall_ms_total / io_stall_max_ms) и, если включено, в stderr ([WARN]).
+//!
+//! Настройка процесс‑глобальная (как page cache): QuiverConfig::io_stall_ms (ENV P1_IO_STALL_MS,
+//! default 1000; 0 — выключить) и io_stall_log (ENV P1_IO_STALL_LOG) применяются при open.
+//! Db::last_io_stalls() — события файлов этой БД.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::metrics::record_io_stall;
+
+const RING_CAP: usize = 64;
+
+/// Наблюдаемая операция.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IoOp {
+    WalFsync,
+    SegWrite,
+    SegFsync,
+}
+
+impl IoOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IoOp::WalFsync => "wal_fsync",
+            IoOp::SegWrite => "seg_write",
+            IoOp::SegFsync => "seg_fsync",
+        }
+    }
+}
+
+/// Медленная (или всё ещё идущая) IO-операция.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IoStallEvent {
+    pub op: IoOp,
+    pub path: PathBuf,
+    /// Длительность (для in_flight — на момент запроса).
+    pub duration_ms: u64,
+    pub bytes: u64,
+    /// Время начала операции.
+    pub started_unix_ms: u64,
+    /// Операция ещё не завершилась.
+    pub in_flight: bool,
+}
+
+// Порог в микросекундах (0 — выключено)
+static THRESHOLD_US: AtomicU64 = AtomicU64::new(1_000_000);
+static LOG_WARN: AtomicBool = AtomicBool::new(false);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+struct InFlight {
+    op: IoOp,
+    path: PathBuf,
+    bytes: u64,
+    start: Instant,
+    started_unix_ms: u64,
+}
+
+fn in_flight() -> &'static Mutex<HashMap<u64, InFlight>> {
+    static M: OnceLock<Mutex<HashMap<u64, InFlight>>> = OnceLock::new();
+    M.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn ring() -> &'static Mutex<VecDeque<IoStallEvent>> {
+    static R: OnceLock<Mutex<VecDeque<IoStallEvent>>> = OnceLock::new();
+    R.get_or_init(|| Mutex::new(VecDeque::with_capacity(RING_CAP)))
+}
+
+/// Применить настройки из QuiverConfig (0 ms — выключить детектор).
+pub fn configure_io_stall(threshold_ms: u64, log_warn: bool) {
+    set_io_stall_threshold(Duration::from_millis(threshold_ms));
+    LOG_WARN.store(log_warn, Ordering::Relaxed);
+}
+
+/// Порог «медленной» операции (Duration::ZERO — выключить).
+pub fn set_io_stall_threshold(d: Duration) {
+    let us = d.as_micros().min(u64::MAX as u128) as u64;
+    // Ненулевой порог меньше микросекунды округляем вверх (иначе он выключил бы детектор)
+    let us = if us == 0 && !d.is_zero() { 1 } else { us };
+    THRESHOLD_US.store(us, Ordering::Relaxed);
+}
+
+pub fn io_stall_threshold() -> Duration {
+    Duration::from_micros(THRESHOLD_US.load(Ordering::Relaxed))
+}
+
+/// Начать наблюдение за операцией (guard; событие фиксируется в Drop).
+#[inline]
+pub fn io_watch(op: IoOp, path: &Path, bytes: u64) -> IoWatch {
+    if THRESHOLD_US.load(Ordering::Relaxed) == 0 {
+        return IoWatch { id: 0 };
+    }
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let rec = InFlight {
+        op,
+        path: path.to_path_buf(),
+        bytes,
+        start: Instant::now(),
+        started_unix_ms: now_ms(),
+    };
+    in_flight()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(id, rec);
+    IoWatch { id }
+}
+
+/// RAII-наблюдатель IO-операции.
+pub struct IoWatch {
+    id: u64,
+}
+
+impl Drop for IoWatch {
+    fn drop(&mut self) {
+        if self.id == 0 {
+            return;
+        }
+        let rec = match in_flight()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.id)
+        {
+            Some(r) => r,
+            None => return,
+        };
+        let el = rec.start.elapsed();
+        let thr_us = THRESHOLD_US.load(Ordering::Relaxed);
+        if thr_us == 0 || (el.as_micros() as u64) < thr_us {
+            return;
+        }
+        let ev = IoStallEvent {
+            op: rec.op,
+            path: rec.path,
+            duration_ms: el.as_millis() as u64,
+            bytes: rec.bytes,
+            started_unix_ms: rec.started_unix_ms,
+            in_flight: false,
+        };
+        record_io_stall(ev.duration_ms);
+        if LOG_WARN.load(Ordering::Relaxed) {
+            eprintln!(
+                "[WARN] slow IO: {} {} took {} ms ({} B)",
+                ev.op.as_str(),
+                ev.path.display(),
+                ev.duration_ms,
+                ev.bytes
+            );
+        }
+        let mut r = ring().lock().unwrap_or_else(|e| e.into_inner());
+        if r.len() == RING_CAP {
+            r.pop_front();
+        }
+        r.push_back(ev);
+    }
+}
+
+/// Последние медленные операции (старые → новые), затем идущие дольше порога (in_flight=true).
+/// root = Some — только файлы под этим корнем.
+pub fn recent_io_stalls(root: Option<&Path>) -> Vec<IoStallEvent> {
+    let keep = |p: &Path| root.is_none_or(|r| p.starts_with(r));
+    let mut out: Vec<IoStallEvent> = ring()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .filter(|e| keep(&e.path))
+        .cloned()
+        .collect();
+
+    let thr_us = THRESHOLD_US.load(Ordering::Relaxed);
+    if thr_us > 0 {
+        let fl = in_flight().lock().unwrap_or_else(|e| e.into_inner());
+        let mut cur: Vec<IoStallEvent> = fl
+            .values()
+            .filter(|r| keep(&r.path) && r.start.elapsed().as_micros() as u64 >= thr_us)
+            .map(|r| IoStallEvent {
+                op: r.op,
+                path: r.path.clone(),
+                duration_ms: r.start.elapsed().as_millis() as u64,
+                bytes: r.bytes,
+                started_unix_ms: r.started_unix_ms,
+                in_flight: true,
+            })
+            .collect();
+        cur.sort_by_key(|e| e.started_unix_ms);
+        out.extend(cur);
+    }
+    out
+}
+
+/// Очистить кольцо событий (in-flight не трогается).
+pub fn clear_io_stalls() {
+    ring().lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
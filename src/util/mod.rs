@@ -3,10 +3,16 @@
 //! Содержит:
 //! - now_secs(): текущее Unix-время в секундах (u32, saturating).
 //! - decode_ovf_placeholder_v3(): разбор TLV плейсхолдера OVERFLOW3 (v3).
+//! - NEW: fsx — кросс-платформенные tmp+rename/fsync каталога/длинные пути (Unix + Windows).
+//! - NEW: iostall — детектор медленного IO (fsync WAL / запись сегментов), см. Db::last_io_stalls.
+//! - NEW: reflink — клонирование файлов/диапазонов (FICLONE, clonefile) с fallback на копирование.
 //!
 //! Задача: убрать дублирование простых хелперов по коду и централизовать поведение.
 
-use byteorder::{ByteOrder, LittleEndian};
+pub mod fsx;
+pub mod iostall;
+// NEW: reflink-копии для clone/restore
+pub mod reflink;
 
 /// Текущее Unix-время в секундах, обрезанное к u32 (saturating).
 #[inline]
@@ -19,18 +25,8 @@ pub fn now_secs() -> u32 {
 }
 
 /// Разобрать OVERFLOW3 TLV-плейсхолдер v3: [tag=0x01 u8][len=16 u8][total_len u64][head_pid u64].
-///
-/// Возвращает Some((total_len, head_pid)) при успехе, иначе None.
-#[inline]
-pub fn decode_ovf_placeholder_v3(v: &[u8]) -> Option<(u64, u64)> {
-    if v.len() >= 18 && v[0] == 0x01 && v[1] == 16 {
-        let total = LittleEndian::read_u64(&v[2..10]);
-        let head = LittleEndian::read_u64(&v[10..18]);
-        Some((total, head))
-    } else {
-        None
-    }
-}
+/// Определён в крейте quiverdb-format (placeholder) вместе с encode_ovf_placeholder_v3.
+pub use quiverdb_format::placeholder::{decode_ovf_placeholder_v3, encode_ovf_placeholder_v3};
 
 #[cfg(test)]
 mod tests {
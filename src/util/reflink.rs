@@ -0,0 +1,165 @@
+//! util/reflink — клонирование файлов и диапазонов страниц (reflink) с прозрачным fallback.
+//!
+//! На ФС с copy-on-write (XFS с reflink=1, btrfs, APFS) копия файла — это новые ссылки на те же
+//! блоки: мгновенно и без лишнего места, пока блоки не перезаписаны. Используется там, где
+//! файлы/страницы дублируются как есть:
+//! - live clone (db/clone.rs) — сегменты, meta, каталог и side-car'ы целиком (clone_file);
+//! - restore из persisted-снапшота (snapstore/restore.rs) — объект SnapStore — это ровно байты
+//!   страницы, он клонируется в сегмент по смещению страницы (clone_range).
+//!
+//! Платформы:
+//! - Linux: ioctl FICLONE (файл) / FICLONERANGE (диапазон; смещения и длина кратны блоку ФС);
+//! - macOS: clonefile(2) для файла; диапазоны не поддерживаются;
+//! - прочие — только fallback.
+//!
+//! Fallback — обычное копирование (std::fs::copy: на Linux — copy_file_range, на NFS это
+//! server-side copy) или запись байтов вызывающим кодом. Ошибка клонирования (ФС не умеет,
+//! разные ФС, невыровненный диапазон) не фатальна. reflink_supported(src_dir, dst_dir) — проба
+//! на временном файле; вызывающий код пробует один раз на операцию и дальше не тратит syscalls.
+//!
+//! ENV P1_REFLINK=0|false|no|off — всегда копировать байты. Метрики: reflink_files,
+//! reflink_bytes, reflink_fallbacks.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::metrics::{record_reflink, record_reflink_fallback};
+
+/// Имя файла пробы (создаётся и удаляется в каталогах источника и цели).
+const PROBE_FILE: &str = ".reflink_probe";
+
+/// Чем была сделана копия (clone_file).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileCopyMethod {
+    Reflink,
+    Copy,
+}
+
+/// Разрешён ли reflink (ENV P1_REFLINK, по умолчанию — да).
+pub fn reflink_enabled() -> bool {
+    std::env::var("P1_REFLINK")
+        .map(|s| {
+            let s = s.trim().to_ascii_lowercase();
+            !(s == "0" || s == "false" || s == "no" || s == "off")
+        })
+        .unwrap_or(true)
+}
+
+/// Проба: умеет ли ФС клонировать файл из src_dir в dst_dir (обе должны существовать).
+pub fn reflink_supported(src_dir: &Path, dst_dir: &Path) -> bool {
+    if !reflink_enabled() {
+        return false;
+    }
+    let src = src_dir.join(PROBE_FILE);
+    let dst = dst_dir.join(format!("{}.clone", PROBE_FILE));
+    let ok = (|| -> io::Result<()> {
+        File::create(&src)?.write_all(&[0x5Au8; 4096])?;
+        let _ = fs::remove_file(&dst);
+        reflink_file(&src, &dst)
+    })()
+    .is_ok();
+    let _ = fs::remove_file(&src);
+    let _ = fs::remove_file(&dst);
+    ok
+}
+
+/// Копия src → dst (dst перезаписывается): reflink, если try_reflink и ФС умеет, иначе
+/// копирование. Возвращает (байт, способ).
+pub fn clone_file(src: &Path, dst: &Path, try_reflink: bool) -> io::Result<(u64, FileCopyMethod)> {
+    if try_reflink {
+        let _ = fs::remove_file(dst);
+        if reflink_file(src, dst).is_ok() {
+            let len = fs::metadata(dst)?.len();
+            record_reflink(len);
+            return Ok((len, FileCopyMethod::Reflink));
+        }
+        record_reflink_fallback();
+    }
+    Ok((fs::copy(src, dst)?, FileCopyMethod::Copy))
+}
+
+/// Клонировать [src_off, src_off+len) файла src в dst по dst_off. Ok(false) — не поддерживается
+/// (или диапазон не выровнен по блоку ФС): вызывающий код пишет байты сам.
+pub fn clone_range(src: &File, src_off: u64, dst: &File, dst_off: u64, len: u64) -> bool {
+    match reflink_range(src, src_off, dst, dst_off, len) {
+        Ok(()) => {
+            record_reflink(len);
+            true
+        }
+        Err(_) => {
+            record_reflink_fallback();
+            false
+        }
+    }
+}
+
+// ---------- платформенные примитивы ----------
+
+#[cfg(target_os = "linux")]
+fn reflink_file(src: &Path, dst: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    let s = File::open(src)?;
+    let d = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dst)?;
+    // SAFETY: оба fd принадлежат живым File; FICLONE не трогает память процесса.
+    let rc = unsafe { libc::ioctl(d.as_raw_fd(), libc::FICLONE as _, s.as_raw_fd()) };
+    if rc == 0 {
+        return Ok(());
+    }
+    let e = io::Error::last_os_error();
+    drop(d);
+    let _ = fs::remove_file(dst);
+    Err(e)
+}
+
+#[cfg(target_os = "macos")]
+fn reflink_file(src: &Path, dst: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    let s = CString::new(src.as_os_str().as_bytes())?;
+    let d = CString::new(dst.as_os_str().as_bytes())?;
+    // SAFETY: обе строки NUL-терминированы и живут до конца вызова.
+    let rc = unsafe { libc::clonefile(s.as_ptr(), d.as_ptr(), 0) };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn reflink_file(_src: &Path, _dst: &Path) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+#[cfg(target_os = "linux")]
+fn reflink_range(src: &File, src_off: u64, dst: &File, dst_off: u64, len: u64) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    let arg = libc::file_clone_range {
+        src_fd: src.as_raw_fd() as i64,
+        src_offset: src_off,
+        src_length: len,
+        dest_offset: dst_off,
+    };
+    // SAFETY: arg живёт до конца вызова; ядро только читает его.
+    let rc = unsafe { libc::ioctl(dst.as_raw_fd(), libc::FICLONERANGE as _, &arg) };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink_range(
+    _src: &File,
+    _src_off: u64,
+    _dst: &File,
+    _dst_off: u64,
+    _len: u64,
+) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
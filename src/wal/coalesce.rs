@@ -0,0 +1,179 @@
+//! wal/coalesce — адаптивное окно group-commit (QuiverConfig::wal_coalesce_adaptive).
+//!
+//! Статическое wal_coalesce_ms подходит одной нагрузке: при редких коммитах окно — чистая
+//! задержка, при плотных слишком малое окно даёт по fsync на каждый коммит. Контроллер
+//! подбирает окно по наблюдениям за WAL-файлом:
+//! - интервал между приходами коммитов в fsync (EWMA);
+//! - длительность самого fsync (EWMA);
+//! - латентность коммита (приход в fsync → LSN durable) — p99 по последним COALESCE_SAMPLES.
+//!
+//! Каждые COALESCE_DECIDE_EVERY fsync'ов принимается решение (допустимое окно — бюджет p99
+//! минус fsync, не больше потолка):
+//! - Shrink (окно ÷2, меньше COALESCE_STEP_US — 0): p99 выше бюджета, либо коммиты приходят
+//!   реже допустимого окна — ожидание ничего не группирует;
+//! - Grow (окно ×2, от COALESCE_STEP_US, до допустимого): p99 ниже 3/4 бюджета и коммиты
+//!   приходят чаще, чем длится fsync;
+//! - Hold — иначе.
+//!
+//! Состояние — на WalInner (общее для хэндлов одного файла, как и статическое окно);
+//! текущее окно и решения — метрики wal_coalesce_*.
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Образцов латентности коммита для оценки p99.
+pub const COALESCE_SAMPLES: usize = 256;
+/// Решение принимается раз в столько fsync'ов.
+pub const COALESCE_DECIDE_EVERY: u32 = 8;
+/// Минимальный ненулевой шаг окна, мкс.
+pub const COALESCE_STEP_US: u64 = 100;
+
+/// Решение контроллера.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoalesceDecision {
+    Grow,
+    Shrink,
+    Hold,
+}
+
+/// Снимок состояния контроллера.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoalesceStatus {
+    pub window_us: u64,
+    pub budget_us: u64,
+    pub max_window_us: u64,
+    pub p99_us: u64,
+    pub arrival_gap_us: f64,
+    pub fsync_us: f64,
+}
+
+/// Контроллер адаптивного окна (см. модульный комментарий).
+#[derive(Debug, Clone)]
+pub struct AdaptiveCoalesce {
+    budget_us: u64,
+    max_window_us: u64,
+    window_us: u64,
+    gap_ewma_us: Option<f64>,
+    fsync_ewma_us: Option<f64>,
+    latencies: VecDeque<u64>,
+    fsyncs_since_decision: u32,
+    last_arrival: Option<Instant>,
+}
+
+impl AdaptiveCoalesce {
+    /// budget_us — целевой p99 латентности коммита; max_window_us — потолок окна.
+    pub fn new(budget_us: u64, max_window_us: u64) -> Self {
+        Self {
+            budget_us: budget_us.max(1),
+            max_window_us,
+            window_us: 0,
+            gap_ewma_us: None,
+            fsync_ewma_us: None,
+            latencies: VecDeque::with_capacity(COALESCE_SAMPLES),
+            fsyncs_since_decision: 0,
+            last_arrival: None,
+        }
+    }
+
+    /// Текущее окно, мкс.
+    pub fn window_us(&self) -> u64 {
+        self.window_us
+    }
+
+    pub fn budget_us(&self) -> u64 {
+        self.budget_us
+    }
+
+    pub fn max_window_us(&self) -> u64 {
+        self.max_window_us
+    }
+
+    /// Коммит пришёл в fsync в момент `now`.
+    pub fn on_arrival(&mut self, now: Instant) {
+        if let Some(prev) = self.last_arrival.replace(now) {
+            self.on_arrival_gap(now.saturating_duration_since(prev).as_micros() as u64);
+        }
+    }
+
+    /// Интервал между соседними приходами коммитов, мкс.
+    pub fn on_arrival_gap(&mut self, gap_us: u64) {
+        self.gap_ewma_us = Some(ewma(self.gap_ewma_us, gap_us));
+    }
+
+    /// Коммит стал durable через `us` после прихода в fsync.
+    pub fn on_commit_latency(&mut self, us: u64) {
+        if self.latencies.len() == COALESCE_SAMPLES {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(us);
+    }
+
+    /// Завершён fsync длительностью `us`; Some — принято решение (окно уже обновлено).
+    pub fn on_fsync(&mut self, us: u64) -> Option<CoalesceDecision> {
+        self.fsync_ewma_us = Some(ewma(self.fsync_ewma_us, us));
+        self.fsyncs_since_decision += 1;
+        if self.fsyncs_since_decision < COALESCE_DECIDE_EVERY {
+            return None;
+        }
+        self.fsyncs_since_decision = 0;
+        Some(self.decide())
+    }
+
+    /// p99 латентности коммита по накопленным образцам (0 — образцов нет).
+    pub fn p99_us(&self) -> u64 {
+        if self.latencies.is_empty() {
+            return 0;
+        }
+        let mut v: Vec<u64> = self.latencies.iter().copied().collect();
+        v.sort_unstable();
+        let idx = (v.len() * 99).div_ceil(100).saturating_sub(1);
+        v[idx]
+    }
+
+    pub fn status(&self) -> CoalesceStatus {
+        CoalesceStatus {
+            window_us: self.window_us,
+            budget_us: self.budget_us,
+            max_window_us: self.max_window_us,
+            p99_us: self.p99_us(),
+            arrival_gap_us: self.gap_ewma_us.unwrap_or(0.0),
+            fsync_us: self.fsync_ewma_us.unwrap_or(0.0),
+        }
+    }
+
+    fn decide(&mut self) -> CoalesceDecision {
+        let p99 = self.p99_us();
+        let fsync = self.fsync_ewma_us.unwrap_or(0.0);
+        let allowed = (self.budget_us as f64 - fsync).max(0.0) as u64;
+        let allowed = allowed.min(self.max_window_us);
+        let gap = self.gap_ewma_us.unwrap_or(f64::INFINITY);
+
+        if p99 > self.budget_us || gap >= allowed as f64 || self.window_us > allowed {
+            if self.window_us == 0 {
+                return CoalesceDecision::Hold;
+            }
+            let half = (self.window_us / 2).min(allowed);
+            self.window_us = if half < COALESCE_STEP_US { 0 } else { half };
+            return CoalesceDecision::Shrink;
+        }
+        if p99.saturating_mul(4) < self.budget_us.saturating_mul(3) && gap < fsync {
+            let next = self
+                .window_us
+                .saturating_mul(2)
+                .max(COALESCE_STEP_US)
+                .min(allowed);
+            if next > self.window_us {
+                self.window_us = next;
+                return CoalesceDecision::Grow;
+            }
+        }
+        CoalesceDecision::Hold
+    }
+}
+
+fn ewma(prev: Option<f64>, sample: u64) -> f64 {
+    match prev {
+        Some(p) => p + (sample as f64 - p) / 8.0,
+        None => sample as f64,
+    }
+}
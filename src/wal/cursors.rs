@@ -0,0 +1,163 @@
+//! wal/cursors — именованные курсоры подписчиков fan-out сервера (`quiverdb cdc-serve`).
+//!
+//! Подписчик с именем (P1_CDC_SUBSCRIBER на стороне cdc-apply, поле HELLO) получает на leader'е
+//! курсор — последний подтверждённый (ACK) LSN. При переподключении по имени поток начинается
+//! с курсора: доставка at-least-once (кадры после ACK могут прийти повторно, но не теряются).
+//!
+//! Файл: <root>/.cdc_cursors.json — `{"<name>": {"acked_lsn", "updated_at", "peer"}}`,
+//! запись атомарная (tmp + rename). Каждое обновление перечитывает файл (read-modify-write),
+//! поэтому правки `quiverdb cdc-cursors` для отключённых подписчиков не теряются; курсор
+//! подключённого подписчика перезапишется его следующим ACK.
+//!
+//! Retention: курсоры, не обновлявшиеся дольше заданного срока, удаляются (prune).
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::util::fsx::{open_tmp_for_write, replace_file};
+
+pub const CURSORS_FILE: &str = ".cdc_cursors.json";
+
+/// Предел длины имени подписчика (байты UTF-8).
+pub const SUBSCRIBER_NAME_MAX: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriberCursor {
+    pub acked_lsn: u64,
+    /// Unix-время последнего обновления (секунды).
+    pub updated_at: u64,
+    /// Адрес подписчика при последнем обновлении.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer: Option<String>,
+}
+
+pub type SubscriberCursors = BTreeMap<String, SubscriberCursor>;
+
+#[inline]
+pub fn cursors_path(root: &Path) -> PathBuf {
+    root.join(CURSORS_FILE)
+}
+
+/// Имя подписчика: 1..=64 байт, [A-Za-z0-9._-].
+pub fn validate_subscriber_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > SUBSCRIBER_NAME_MAX {
+        return Err(anyhow!(
+            "subscriber name must be 1..={} bytes, got {}",
+            SUBSCRIBER_NAME_MAX,
+            name.len()
+        ));
+    }
+    if !name
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'_' | b'-'))
+    {
+        return Err(anyhow!(
+            "invalid subscriber name '{}' (allowed: A-Z a-z 0-9 . _ -)",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Прочитать курсоры (нет файла — пусто).
+pub fn load_cursors(root: &Path) -> Result<SubscriberCursors> {
+    let p = cursors_path(root);
+    match std::fs::read(&p) {
+        Ok(b) => serde_json::from_slice(&b).with_context(|| format!("parse {}", p.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SubscriberCursors::new()),
+        Err(e) => Err(e).with_context(|| format!("read {}", p.display())),
+    }
+}
+
+/// Записать курсоры атомарно.
+pub fn store_cursors(root: &Path, cursors: &SubscriberCursors) -> Result<()> {
+    let path = cursors_path(root);
+    let tmp = root.join(format!("{}.tmp", CURSORS_FILE));
+    {
+        let mut f = open_tmp_for_write(&tmp).with_context(|| format!("open {}", tmp.display()))?;
+        f.write_all(&serde_json::to_vec_pretty(cursors)?)?;
+        let _ = f.sync_all();
+    }
+    replace_file(&tmp, &path)
+        .with_context(|| format!("rename {} -> {}", tmp.display(), path.display()))?;
+    Ok(())
+}
+
+/// Курсор подписчика (None — неизвестен).
+pub fn load_cursor(root: &Path, name: &str) -> Result<Option<SubscriberCursor>> {
+    Ok(load_cursors(root)?.remove(name))
+}
+
+/// Обновить курсор подписчика значением acked_lsn (курсор не двигается назад — ACK
+/// старой сессии после reset/перезапуска его не откатывает, кроме явного set_cursor).
+pub fn advance_cursor(root: &Path, name: &str, acked_lsn: u64, peer: Option<&str>) -> Result<()> {
+    let mut all = load_cursors(root)?;
+    let c = all.entry(name.to_string()).or_insert(SubscriberCursor {
+        acked_lsn,
+        updated_at: 0,
+        peer: None,
+    });
+    c.acked_lsn = c.acked_lsn.max(acked_lsn);
+    c.updated_at = now_secs();
+    c.peer = peer.map(str::to_string);
+    store_cursors(root, &all)
+}
+
+/// Установить курсор явно (admin: reset на LSN).
+pub fn set_cursor(root: &Path, name: &str, acked_lsn: u64) -> Result<()> {
+    validate_subscriber_name(name)?;
+    let mut all = load_cursors(root)?;
+    let peer = all.get(name).and_then(|c| c.peer.clone());
+    all.insert(
+        name.to_string(),
+        SubscriberCursor {
+            acked_lsn,
+            updated_at: now_secs(),
+            peer,
+        },
+    );
+    store_cursors(root, &all)
+}
+
+/// Удалить курсор. Ok(false) — такого не было.
+pub fn delete_cursor(root: &Path, name: &str) -> Result<bool> {
+    let mut all = load_cursors(root)?;
+    if all.remove(name).is_none() {
+        return Ok(false);
+    }
+    store_cursors(root, &all)?;
+    Ok(true)
+}
+
+/// Удалить курсоры, не обновлявшиеся дольше `retention_secs` (0 — хранить всегда).
+/// Возвращает имена удалённых.
+pub fn prune_cursors(root: &Path, retention_secs: u64) -> Result<Vec<String>> {
+    if retention_secs == 0 {
+        return Ok(Vec::new());
+    }
+    let mut all = load_cursors(root)?;
+    let cutoff = now_secs().saturating_sub(retention_secs);
+    let stale: Vec<String> = all
+        .iter()
+        .filter(|(_, c)| c.updated_at < cutoff)
+        .map(|(n, _)| n.clone())
+        .collect();
+    if !stale.is_empty() {
+        for n in &stale {
+            all.remove(n);
+        }
+        store_cursors(root, &all)?;
+    }
+    Ok(stale)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -4,46 +4,16 @@
 //! - build_hdr_with_crc: построить заголовок записи WAL (28 байт) с рассчитанным CRC32C по
 //!   header[0..crc) + payload.
 //! - write_record: записать [header][payload] в writer (без seek(End); по текущей позиции).
+//! - NEW: CommitTimestamp — payload COMMIT-кадра с временем коммита (encode/decode).
 //!
-//! Зависимости:
-//! - Константы формата импортируются из супер-модуля (wal/mod.rs).
-//! - CRC берётся из crc32c_of_parts (общая утилита wal/mod.rs).
+//! build_hdr_with_crc и CommitTimestamp определены в крейте quiverdb-format (wal) и
+//! реэкспортируются отсюда; здесь — запись в Write и ENV-тумблер времени коммита.
 
 use anyhow::{anyhow, Result};
-use byteorder::{ByteOrder, LittleEndian};
 use std::io::Write;
+use std::sync::OnceLock;
 
-use super::{
-    crc32c_of_parts, WAL_REC_HDR_SIZE, WAL_REC_OFF_CRC32, WAL_REC_OFF_FLAGS, WAL_REC_OFF_LEN,
-    WAL_REC_OFF_LSN, WAL_REC_OFF_PAGE_ID, WAL_REC_OFF_RESERVED, WAL_REC_OFF_TYPE,
-};
-
-/// Построить заголовок WAL с заполненным CRC32C.
-/// CRC считается по header[0..WAL_REC_OFF_CRC32] + payload.
-pub fn build_hdr_with_crc(
-    rec_type: u8,
-    lsn: u64,
-    page_id: u64,
-    payload: &[u8],
-) -> [u8; WAL_REC_HDR_SIZE] {
-    let mut hdr = [0u8; WAL_REC_HDR_SIZE];
-    hdr[WAL_REC_OFF_TYPE] = rec_type;
-    hdr[WAL_REC_OFF_FLAGS] = 0;
-    LittleEndian::write_u16(&mut hdr[WAL_REC_OFF_RESERVED..WAL_REC_OFF_RESERVED + 2], 0);
-    LittleEndian::write_u64(&mut hdr[WAL_REC_OFF_LSN..WAL_REC_OFF_LSN + 8], lsn);
-    LittleEndian::write_u64(
-        &mut hdr[WAL_REC_OFF_PAGE_ID..WAL_REC_OFF_PAGE_ID + 8],
-        page_id,
-    );
-    LittleEndian::write_u32(
-        &mut hdr[WAL_REC_OFF_LEN..WAL_REC_OFF_LEN + 4],
-        payload.len() as u32,
-    );
-
-    let crc = crc32c_of_parts(&hdr[..WAL_REC_OFF_CRC32], payload);
-    LittleEndian::write_u32(&mut hdr[WAL_REC_OFF_CRC32..WAL_REC_OFF_CRC32 + 4], crc);
-    hdr
-}
+pub use quiverdb_format::wal::build_hdr_with_crc;
 
 /// Записать один WAL‑кадр [header][payload] в текущую позицию writer’а.
 ///
@@ -76,3 +46,19 @@ pub fn write_record<W: Write>(
     }
     Ok(())
 }
+
+// -------------------- COMMIT timestamp payload --------------------
+
+pub use quiverdb_format::wal::{CommitTimestamp, WAL_COMMIT_TS_LEN};
+
+/// Писать ли время коммита в COMMIT-кадры (ENV P1_WAL_COMMIT_TS, по умолчанию включено).
+pub fn commit_ts_enabled() -> bool {
+    static ON: OnceLock<bool> = OnceLock::new();
+    *ON.get_or_init(|| {
+        std::env::var("P1_WAL_COMMIT_TS")
+            .ok()
+            .map(|s| s.to_ascii_lowercase())
+            .map(|s| !(s == "0" || s == "false" || s == "off" || s == "no"))
+            .unwrap_or(true)
+    })
+}
@@ -0,0 +1,62 @@
+//! wal/expiry — логический кадр EXPIRY: ключи, физически вычищенные по TTL.
+//!
+//! Кадр пишется (при QuiverConfig::wal_expiry_events) в тот же батч, что и переписанная
+//! компактацией цепочка бакета: BEGIN → IMAGE* → HEADS_UPDATE → EXPIRY → COMMIT.
+//! LSN кадра = LSN коммита батча (purge LSN). Реплей кадр игнорирует (страниц он не меняет),
+//! follower (cdc-apply) и CDC-потребители превращают его в ExpiryEvent для инвалидации кэшей.
+//!
+//! Payload (LE): повторяющиеся [u32 bucket][u32 expired_at_sec][u16 klen][key bytes].
+
+use byteorder::{ByteOrder, LittleEndian};
+
+const EXPIRY_ENTRY_HDR: usize = 10;
+
+/// Событие истечения TTL ключа.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpiryEvent {
+    pub bucket: u32,
+    pub key: Vec<u8>,
+    /// Момент истечения (expires_at_sec из записи, unix seconds).
+    pub expired_at: u32,
+    /// LSN батча, в котором ключ был вычищен (0 — вычистка без WAL-коммита).
+    pub purge_lsn: u64,
+}
+
+/// Закодировать payload кадра EXPIRY.
+pub fn encode_expiry_payload(events: &[ExpiryEvent]) -> Vec<u8> {
+    let cap: usize = events.iter().map(|e| EXPIRY_ENTRY_HDR + e.key.len()).sum();
+    let mut out = Vec::with_capacity(cap);
+    let mut hdr = [0u8; EXPIRY_ENTRY_HDR];
+    for e in events {
+        LittleEndian::write_u32(&mut hdr[0..4], e.bucket);
+        LittleEndian::write_u32(&mut hdr[4..8], e.expired_at);
+        LittleEndian::write_u16(&mut hdr[8..10], e.key.len() as u16);
+        out.extend_from_slice(&hdr);
+        out.extend_from_slice(&e.key);
+    }
+    out
+}
+
+/// Разобрать payload кадра EXPIRY (purge_lsn = LSN кадра).
+/// Усечённый хвост игнорируется (forward-compatible, как HEADS_UPDATE).
+pub fn parse_expiry_payload(payload: &[u8], lsn: u64) -> Vec<ExpiryEvent> {
+    let mut out = Vec::new();
+    let mut off = 0usize;
+    while off + EXPIRY_ENTRY_HDR <= payload.len() {
+        let bucket = LittleEndian::read_u32(&payload[off..off + 4]);
+        let expired_at = LittleEndian::read_u32(&payload[off + 4..off + 8]);
+        let klen = LittleEndian::read_u16(&payload[off + 8..off + 10]) as usize;
+        let kstart = off + EXPIRY_ENTRY_HDR;
+        if kstart + klen > payload.len() {
+            break;
+        }
+        out.push(ExpiryEvent {
+            bucket,
+            key: payload[kstart..kstart + klen].to_vec(),
+            expired_at,
+            purge_lsn: lsn,
+        });
+        off = kstart + klen;
+    }
+    out
+}
@@ -0,0 +1,539 @@
+//! wal/filter — серверная фильтрация CDC-потока по диапазонам бакетов и префиксам ключей.
+//!
+//! Фильтр применяется отправителем (cdc-ship / cdc-serve) к кадрам WAL перед отправкой:
+//! - PAGE_IMAGE KV_RH3 — страница отправляется, если её бакет (по ключу любой записи) входит
+//!   в диапазоны и (если заданы префиксы) хотя бы один ключ на странице начинается с префикса;
+//! - PAGE_IMAGE OVERFLOW3 — отбрасывается, только если цепочку в этом же батче держит
+//!   отброшенная KV-страница (и ни одна отправленная); «чужие» overflow-страницы идут как есть;
+//! - HEADS_UPDATE / EXPIRY — из payload убираются записи бакетов/ключей вне фильтра
+//!   (пустой кадр не отправляется);
+//! - BEGIN / IDEMPOTENCY / COMMIT / TRUNCATE, страницы без записей и нераспознанные страницы —
+//!   всегда (консервативно): LSN follower'а продвигается и на «чужих» батчах.
+//!
+//! Решение по страницам принимается целым батчем: PAGE-кадры копятся до первого не-PAGE кадра
+//! (HEADS_UPDATE/COMMIT), поэтому хвост без COMMIT не отправляется.
+//!
+//! Гарантии: фильтр только по бакетам даёт согласованную частичную реплику (цепочка бакета
+//! целиком живёт в его страницах) — follower читает выбранные бакеты. Фильтр по префиксам —
+//! для целевых потребителей (индексаторы, декодеры): страницы того же бакета без совпадений
+//! не отправляются, поэтому как реплику для чтения такой follower не использовать.
+//!
+//! Фильтр задаёт получатель в HELLO (P1_CDC_FILTER_PREFIX / P1_CDC_FILTER_BUCKETS) или
+//! cdc-ship флагами --filter-prefix / --filter-buckets (флаги приоритетнее).
+
+use anyhow::{anyhow, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::expiry::{encode_expiry_payload, parse_expiry_payload};
+use super::heads::{
+    decode_heads_update_payload, encode_heads_update_payload, is_heads_delta_payload,
+};
+use super::reader::WalRecord;
+use super::{WAL_REC_BEGIN, WAL_REC_EXPIRY, WAL_REC_HEADS_UPDATE, WAL_REC_PAGE_IMAGE};
+use crate::dir::{bucket_for_key, Directory, NO_PAGE};
+use crate::meta::read_meta;
+use crate::page::kv::kv_for_each_record;
+use crate::page::{
+    ovf_header_read_v3, OFF_TYPE, PAGE_MAGIC, PAGE_TYPE_KV_RH3, PAGE_TYPE_OVERFLOW3,
+};
+use crate::util::decode_ovf_placeholder_v3;
+
+/// Пределы фильтра (HELLO должен уложиться в CTRL_MAX_LEN).
+pub const FILTER_MAX_PREFIXES: usize = 16;
+pub const FILTER_MAX_PREFIX_LEN: usize = 128;
+pub const FILTER_MAX_RANGES: usize = 16;
+
+/// Предел длины overflow-цепочки при разметке батча.
+const OVF_WALK_GUARD: usize = 1_000_000;
+
+/// Фильтр CDC-потока. Пустой — пропускать всё.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CdcFilter {
+    /// Префиксы ключей (любой из).
+    pub prefixes: Vec<Vec<u8>>,
+    /// Диапазоны бакетов [lo, hi] включительно (любой из).
+    pub buckets: Vec<(u32, u32)>,
+}
+
+impl CdcFilter {
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty() && self.buckets.is_empty()
+    }
+
+    pub fn matches_bucket(&self, bucket: u32) -> bool {
+        self.buckets.is_empty()
+            || self
+                .buckets
+                .iter()
+                .any(|&(lo, hi)| bucket >= lo && bucket <= hi)
+    }
+
+    pub fn matches_prefix(&self, key: &[u8]) -> bool {
+        self.prefixes.is_empty() || self.prefixes.iter().any(|p| key.starts_with(p))
+    }
+
+    pub fn matches_key(&self, bucket: u32, key: &[u8]) -> bool {
+        self.matches_bucket(bucket) && self.matches_prefix(key)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.prefixes.len() > FILTER_MAX_PREFIXES {
+            return Err(anyhow!(
+                "CDC filter: at most {} key prefixes, got {}",
+                FILTER_MAX_PREFIXES,
+                self.prefixes.len()
+            ));
+        }
+        if let Some(p) = self
+            .prefixes
+            .iter()
+            .find(|p| p.is_empty() || p.len() > FILTER_MAX_PREFIX_LEN)
+        {
+            return Err(anyhow!(
+                "CDC filter: key prefix must be 1..={} bytes, got {}",
+                FILTER_MAX_PREFIX_LEN,
+                p.len()
+            ));
+        }
+        if self.buckets.len() > FILTER_MAX_RANGES {
+            return Err(anyhow!(
+                "CDC filter: at most {} bucket ranges, got {}",
+                FILTER_MAX_RANGES,
+                self.buckets.len()
+            ));
+        }
+        if let Some(&(lo, hi)) = self.buckets.iter().find(|&&(lo, hi)| lo > hi) {
+            return Err(anyhow!("CDC filter: bad bucket range {}-{}", lo, hi));
+        }
+        Ok(())
+    }
+
+    /// Разобрать диапазоны бакетов: "0-99,512,1000-1999".
+    pub fn parse_buckets(spec: &str) -> Result<Vec<(u32, u32)>> {
+        let mut out = Vec::new();
+        for part in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let num = |s: &str| -> Result<u32> {
+                s.trim()
+                    .parse::<u32>()
+                    .with_context(|| format!("bad bucket number '{}' in '{}'", s.trim(), spec))
+            };
+            let r = match part.split_once('-') {
+                Some((lo, hi)) => (num(lo)?, num(hi)?),
+                None => {
+                    let b = num(part)?;
+                    (b, b)
+                }
+            };
+            if r.0 > r.1 {
+                return Err(anyhow!("bad bucket range '{}' (lo > hi)", part));
+            }
+            out.push(r);
+        }
+        Ok(out)
+    }
+
+    /// Фильтр из флагов/ENV: префиксы как есть (UTF-8), диапазоны — parse_buckets.
+    pub fn from_parts(prefixes: &[String], buckets: Option<&str>) -> Result<Self> {
+        let f = Self {
+            prefixes: prefixes.iter().map(|p| p.as_bytes().to_vec()).collect(),
+            buckets: match buckets {
+                Some(s) => Self::parse_buckets(s)?,
+                None => Vec::new(),
+            },
+        };
+        f.validate()?;
+        Ok(f)
+    }
+
+    /// P1_CDC_FILTER_PREFIX (через запятую) / P1_CDC_FILTER_BUCKETS. None — не задан.
+    pub fn from_env() -> Result<Option<Self>> {
+        let prefixes: Vec<String> = std::env::var("P1_CDC_FILTER_PREFIX")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+        let buckets = std::env::var("P1_CDC_FILTER_BUCKETS").ok();
+        let f = Self::from_parts(&prefixes, buckets.as_deref())
+            .context("P1_CDC_FILTER_PREFIX / P1_CDC_FILTER_BUCKETS")?;
+        Ok((!f.is_empty()).then_some(f))
+    }
+
+    /// [u8 n][u8 len][prefix]*n [u8 m][u32 lo][u32 hi]*m (LE).
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        let prefixes = &self.prefixes[..self.prefixes.len().min(FILTER_MAX_PREFIXES)];
+        out.push(prefixes.len() as u8);
+        for p in prefixes {
+            let p = &p[..p.len().min(FILTER_MAX_PREFIX_LEN)];
+            out.push(p.len() as u8);
+            out.extend_from_slice(p);
+        }
+        let ranges = &self.buckets[..self.buckets.len().min(FILTER_MAX_RANGES)];
+        out.push(ranges.len() as u8);
+        for &(lo, hi) in ranges {
+            out.extend_from_slice(&lo.to_le_bytes());
+            out.extend_from_slice(&hi.to_le_bytes());
+        }
+    }
+
+    /// Обратное к encode. Возвращает фильтр и число прочитанных байт.
+    pub fn decode(buf: &[u8]) -> Result<(Self, usize)> {
+        let short = || anyhow!("CDC: short filter in HELLO");
+        let mut off = 0usize;
+        let mut f = Self::default();
+        let n = *buf.get(off).ok_or_else(short)? as usize;
+        off += 1;
+        for _ in 0..n {
+            let len = *buf.get(off).ok_or_else(short)? as usize;
+            let p = buf.get(off + 1..off + 1 + len).ok_or_else(short)?;
+            f.prefixes.push(p.to_vec());
+            off += 1 + len;
+        }
+        let m = *buf.get(off).ok_or_else(short)? as usize;
+        off += 1;
+        for _ in 0..m {
+            let r = buf.get(off..off + 8).ok_or_else(short)?;
+            f.buckets.push((
+                LittleEndian::read_u32(&r[0..4]),
+                LittleEndian::read_u32(&r[4..8]),
+            ));
+            off += 8;
+        }
+        f.validate()?;
+        Ok((f, off))
+    }
+}
+
+impl fmt::Display for CdcFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "none");
+        }
+        let mut parts = Vec::new();
+        if !self.prefixes.is_empty() {
+            let p: Vec<String> = self
+                .prefixes
+                .iter()
+                .map(|p| format!("'{}'", String::from_utf8_lossy(p)))
+                .collect();
+            parts.push(format!("prefix={}", p.join(",")));
+        }
+        if !self.buckets.is_empty() {
+            let b: Vec<String> = self
+                .buckets
+                .iter()
+                .map(|&(lo, hi)| {
+                    if lo == hi {
+                        lo.to_string()
+                    } else {
+                        format!("{}-{}", lo, hi)
+                    }
+                })
+                .collect();
+            parts.push(format!("buckets={}", b.join(",")));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Счётчики фильтра.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FilterStats {
+    pub frames_in: u64,
+    pub frames_out: u64,
+    /// Отброшенные PAGE-кадры (KV + overflow).
+    pub pages_dropped: u64,
+    /// Байт payload отброшенных/урезанных кадров.
+    pub bytes_dropped: u64,
+}
+
+/// Кадр, который умеет фильтровать FrameFilter.
+pub trait FilterFrame {
+    fn rec_type(&self) -> u8;
+    fn page_id(&self) -> u64;
+    fn payload(&self) -> &[u8];
+}
+
+impl FilterFrame for WalRecord {
+    fn rec_type(&self) -> u8 {
+        self.rec_type
+    }
+    fn page_id(&self) -> u64 {
+        self.page_id
+    }
+    fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+}
+
+impl<T: FilterFrame + ?Sized> FilterFrame for Arc<T> {
+    fn rec_type(&self) -> u8 {
+        (**self).rec_type()
+    }
+    fn page_id(&self) -> u64 {
+        (**self).page_id()
+    }
+    fn payload(&self) -> &[u8] {
+        (**self).payload()
+    }
+}
+
+/// Разметка PAGE-кадра при буферизации.
+enum PageInfo {
+    /// keep — решение по фильтру; ovf — головы overflow-цепочек из плейсхолдеров.
+    Kv {
+        keep: bool,
+        ovf: Vec<u64>,
+    },
+    Ovf {
+        next: u64,
+    },
+    /// Пустая/нераспознанная страница — отправляется.
+    Other,
+}
+
+struct PendingPage<T> {
+    item: T,
+    page_id: u64,
+    len: usize,
+    info: PageInfo,
+}
+
+/// Потоковый фильтр кадров одной сессии. push возвращает кадры к отправке в исходном
+/// порядке; Some(payload) — урезанный payload (HEADS_UPDATE/EXPIRY).
+pub struct FrameFilter<T> {
+    filter: CdcFilter,
+    bucket_count: u32,
+    hash_kind: u32,
+    pending: Vec<PendingPage<T>>,
+    /// Отправленные KV-страницы текущего батча (для HEADS_UPDATE с префиксами).
+    kept_kv: HashSet<u64>,
+    stats: FilterStats,
+}
+
+impl<T: FilterFrame> FrameFilter<T> {
+    pub fn new(filter: CdcFilter, bucket_count: u32, hash_kind: u32) -> Self {
+        Self {
+            filter,
+            bucket_count: bucket_count.max(1),
+            hash_kind,
+            pending: Vec::new(),
+            kept_kv: HashSet::new(),
+            stats: FilterStats::default(),
+        }
+    }
+
+    /// Геометрия (bucket_count, hash_kind) — из БД-источника (пустой фильтр — без чтения).
+    pub fn open(root: &Path, filter: CdcFilter) -> Result<Self> {
+        if filter.is_empty() {
+            return Ok(Self::new(filter, 1, 1));
+        }
+        let meta = read_meta(root)?;
+        let dir = Directory::open(root)?;
+        Ok(Self::new(filter, dir.bucket_count, meta.hash_kind))
+    }
+
+    pub fn filter(&self) -> &CdcFilter {
+        &self.filter
+    }
+
+    pub fn stats(&self) -> FilterStats {
+        self.stats
+    }
+
+    /// Сбросить незавершённый батч (поток начинается заново, например догонянием из файла).
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.kept_kv.clear();
+    }
+
+    pub fn push(&mut self, item: T) -> Vec<(T, Option<Vec<u8>>)> {
+        self.stats.frames_in += 1;
+        if self.filter.is_empty() {
+            self.stats.frames_out += 1;
+            return vec![(item, None)];
+        }
+        let rec_type = item.rec_type();
+        if rec_type == WAL_REC_PAGE_IMAGE {
+            let info = self.classify(item.payload());
+            self.pending.push(PendingPage {
+                page_id: item.page_id(),
+                len: item.payload().len(),
+                item,
+                info,
+            });
+            return Vec::new();
+        }
+
+        let mut out = self.flush_pages();
+        if rec_type == WAL_REC_BEGIN {
+            self.kept_kv.clear();
+        }
+        let payload = item.payload();
+        let rewritten = match rec_type {
+            WAL_REC_HEADS_UPDATE => self.filter_heads(payload),
+            WAL_REC_EXPIRY => self.filter_expiry(payload),
+            _ => None,
+        };
+        let payload_len = payload.len();
+        match rewritten {
+            Some(pl) if pl.is_empty() => {
+                self.stats.bytes_dropped += payload_len as u64;
+            }
+            Some(pl) => {
+                self.stats.bytes_dropped += payload_len.saturating_sub(pl.len()) as u64;
+                self.stats.frames_out += 1;
+                out.push((item, Some(pl)));
+            }
+            None => {
+                self.stats.frames_out += 1;
+                out.push((item, None));
+            }
+        }
+        out
+    }
+
+    fn bucket_of(&self, key: &[u8]) -> u32 {
+        bucket_for_key(key, self.hash_kind, self.bucket_count)
+    }
+
+    fn classify(&self, page: &[u8]) -> PageInfo {
+        if page.len() < OFF_TYPE + 2 || &page[..4] != PAGE_MAGIC {
+            return PageInfo::Other;
+        }
+        match LittleEndian::read_u16(&page[OFF_TYPE..OFF_TYPE + 2]) {
+            t if t == PAGE_TYPE_KV_RH3 => {
+                let mut bucket: Option<u32> = None;
+                let mut any_prefix = false;
+                let mut ovf = Vec::new();
+                kv_for_each_record(page, |k, v, _, _| {
+                    if bucket.is_none() {
+                        bucket = Some(self.bucket_of(k));
+                    }
+                    any_prefix |= self.filter.matches_prefix(k);
+                    if let Some((_, head)) = decode_ovf_placeholder_v3(v) {
+                        ovf.push(head);
+                    }
+                });
+                match bucket {
+                    Some(b) => PageInfo::Kv {
+                        keep: self.filter.matches_bucket(b) && any_prefix,
+                        ovf,
+                    },
+                    None => PageInfo::Other,
+                }
+            }
+            t if t == PAGE_TYPE_OVERFLOW3 => match ovf_header_read_v3(page) {
+                Ok(h) => PageInfo::Ovf {
+                    next: h.next_page_id,
+                },
+                Err(_) => PageInfo::Other,
+            },
+            _ => PageInfo::Other,
+        }
+    }
+
+    /// Решить судьбу накопленных страниц батча.
+    fn flush_pages(&mut self) -> Vec<(T, Option<Vec<u8>>)> {
+        if self.pending.is_empty() {
+            return Vec::new();
+        }
+        let pending = std::mem::take(&mut self.pending);
+
+        // overflow-цепочки в пределах батча: pid -> next
+        let next: HashMap<u64, u64> = pending
+            .iter()
+            .filter_map(|p| match p.info {
+                PageInfo::Ovf { next } => Some((p.page_id, next)),
+                _ => None,
+            })
+            .collect();
+        let mut used_by_kept: HashSet<u64> = HashSet::new();
+        let mut used_by_dropped: HashSet<u64> = HashSet::new();
+        for p in &pending {
+            if let PageInfo::Kv { keep, ovf } = &p.info {
+                let set = if *keep {
+                    &mut used_by_kept
+                } else {
+                    &mut used_by_dropped
+                };
+                for &head in ovf {
+                    let mut pid = head;
+                    let mut guard = 0usize;
+                    while pid != NO_PAGE && guard < OVF_WALK_GUARD && set.insert(pid) {
+                        match next.get(&pid) {
+                            Some(&n) => pid = n,
+                            None => break,
+                        }
+                        guard += 1;
+                    }
+                }
+            }
+        }
+
+        let mut out = Vec::with_capacity(pending.len());
+        for p in pending {
+            let keep = match &p.info {
+                PageInfo::Kv { keep, .. } => *keep,
+                PageInfo::Ovf { .. } => {
+                    used_by_kept.contains(&p.page_id) || !used_by_dropped.contains(&p.page_id)
+                }
+                PageInfo::Other => true,
+            };
+            if keep {
+                if !matches!(p.info, PageInfo::Ovf { .. }) {
+                    self.kept_kv.insert(p.page_id);
+                }
+                self.stats.frames_out += 1;
+                out.push((p.item, None));
+            } else {
+                self.stats.pages_dropped += 1;
+                self.stats.bytes_dropped += p.len as u64;
+            }
+        }
+        out
+    }
+
+    /// None — payload без изменений; Some(пусто) — кадр не отправлять.
+    fn filter_heads(&self, payload: &[u8]) -> Option<Vec<u8>> {
+        let updates = decode_heads_update_payload(payload)?;
+        let by_prefix = !self.filter.prefixes.is_empty();
+        let kept: Vec<(u32, u64)> = updates
+            .iter()
+            .copied()
+            .filter(|&(b, pid)| {
+                self.filter.matches_bucket(b)
+                    && (!by_prefix || pid == NO_PAGE || self.kept_kv.contains(&pid))
+            })
+            .collect();
+        if kept.len() == updates.len() {
+            return None;
+        }
+        if kept.is_empty() {
+            return Some(Vec::new());
+        }
+        // формат исходного кадра сохраняется: legacy-поток остаётся legacy
+        Some(encode_heads_update_payload(
+            &kept,
+            is_heads_delta_payload(payload),
+        ))
+    }
+
+    fn filter_expiry(&self, payload: &[u8]) -> Option<Vec<u8>> {
+        let events = parse_expiry_payload(payload, 0);
+        let kept: Vec<_> = events
+            .iter()
+            .filter(|e| self.filter.matches_key(e.bucket, &e.key))
+            .cloned()
+            .collect();
+        if kept.len() == events.len() {
+            return None;
+        }
+        Some(encode_expiry_payload(&kept))
+    }
+}
@@ -0,0 +1,165 @@
+//! wal/heads — кодирование payload кадра HEADS_UPDATE.
+//!
+//! Два формата (различаются по длине payload):
+//! - legacy: повторяющиеся [u32 bucket][u64 head_pid] (LE), длина кратна 12;
+//! - delta (NEW): [u8 HEADS_DELTA_TAG][varint n] + n × [varint bucket_gap][varint zigzag(pid - prev_pid)],
+//!   бакеты строго по возрастанию (первый gap — абсолютный номер), prev_pid начинается с 0.
+//!   Если итоговая длина кратна 12, дописывается один нулевой байт — так delta-payload никогда
+//!   не спутать с legacy, а старые читатели отбрасывают его как «некорректную длину».
+//!
+//! По умолчанию писатель пишет legacy: старые follower'ы delta-кадры отбрасывают. Delta — только
+//! для БД с required-битом FEATURE_HEADS_DELTA в meta (opt-in QuiverConfig::wal_heads_delta);
+//! тогда выбирается более короткий вариант, экономия учитывается в метриках
+//! (heads_update_bytes / heads_update_bytes_legacy). CDC-передатчики дополнительно перекодируют
+//! delta в legacy для получателей без CAP_HEADS_DELTA.
+
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::BTreeMap;
+
+/// Маркер delta-формата (первый байт payload).
+pub const HEADS_DELTA_TAG: u8 = 0xD2;
+
+const LEGACY_ENTRY: usize = 12;
+
+/// Нормализовать обновления голов: по одному на бакет (побеждает последнее), по возрастанию bucket.
+pub fn normalize_head_updates(updates: &[(u32, u64)]) -> Vec<(u32, u64)> {
+    let sorted = updates.windows(2).all(|w| w[0].0 < w[1].0);
+    if sorted {
+        return updates.to_vec();
+    }
+    let mut m: BTreeMap<u32, u64> = BTreeMap::new();
+    for &(b, pid) in updates {
+        m.insert(b, pid);
+    }
+    m.into_iter().collect()
+}
+
+/// Legacy payload: [u32 bucket][u64 pid]*.
+pub fn encode_heads_update_legacy(updates: &[(u32, u64)]) -> Vec<u8> {
+    let mut out = vec![0u8; updates.len() * LEGACY_ENTRY];
+    for (c, &(b, pid)) in out.chunks_exact_mut(LEGACY_ENTRY).zip(updates) {
+        LittleEndian::write_u32(&mut c[0..4], b);
+        LittleEndian::write_u64(&mut c[4..12], pid);
+    }
+    out
+}
+
+/// Delta payload (см. заголовок модуля). Ожидает нормализованный вход.
+pub fn encode_heads_update_delta(updates: &[(u32, u64)]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(2 + updates.len() * 4);
+    out.push(HEADS_DELTA_TAG);
+    put_varint(&mut out, updates.len() as u64);
+    let mut prev_b = 0u32;
+    let mut prev_pid = 0u64;
+    for &(b, pid) in updates {
+        put_varint(&mut out, (b - prev_b) as u64);
+        put_varint(&mut out, zigzag(pid.wrapping_sub(prev_pid) as i64));
+        prev_b = b;
+        prev_pid = pid;
+    }
+    if out.len().is_multiple_of(LEGACY_ENTRY) {
+        out.push(0);
+    }
+    out
+}
+
+/// Payload для записи: обновления нормализуются; delta=false — всегда legacy, delta=true —
+/// более короткий из двух форматов.
+pub fn encode_heads_update_payload(updates: &[(u32, u64)], delta: bool) -> Vec<u8> {
+    let norm = normalize_head_updates(updates);
+    if !delta {
+        return encode_heads_update_legacy(&norm);
+    }
+    let delta = encode_heads_update_delta(&norm);
+    if delta.len() < norm.len() * LEGACY_ENTRY {
+        delta
+    } else {
+        encode_heads_update_legacy(&norm)
+    }
+}
+
+/// Payload в delta-формате (длина не кратна 12 и первый байт — HEADS_DELTA_TAG).
+pub fn is_heads_delta_payload(payload: &[u8]) -> bool {
+    !payload.len().is_multiple_of(LEGACY_ENTRY) && payload.first() == Some(&HEADS_DELTA_TAG)
+}
+
+/// Разобрать payload HEADS_UPDATE (оба формата). None — некорректный payload.
+pub fn decode_heads_update_payload(payload: &[u8]) -> Option<Vec<(u32, u64)>> {
+    if payload.is_empty() {
+        return None;
+    }
+    if payload.len().is_multiple_of(LEGACY_ENTRY) {
+        let mut updates: Vec<(u32, u64)> = payload
+            .chunks_exact(LEGACY_ENTRY)
+            .map(|c| {
+                (
+                    LittleEndian::read_u32(&c[0..4]),
+                    LittleEndian::read_u64(&c[4..12]),
+                )
+            })
+            .collect();
+        updates.sort_by_key(|e| e.0);
+        return Some(updates);
+    }
+    if payload[0] != HEADS_DELTA_TAG {
+        return None;
+    }
+    let mut off = 1usize;
+    let n = get_varint(payload, &mut off)?;
+    // Каждая запись — минимум 2 байта: отсекаем мусорный n до аллокации
+    if n > (payload.len() / 2) as u64 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(n as usize);
+    let mut b = 0u64;
+    let mut pid = 0u64;
+    for i in 0..n {
+        let gap = get_varint(payload, &mut off)?;
+        if i > 0 && gap == 0 {
+            return None;
+        }
+        b = b.checked_add(gap)?;
+        if b > u32::MAX as u64 {
+            return None;
+        }
+        pid = pid.wrapping_add(unzigzag(get_varint(payload, &mut off)?) as u64);
+        out.push((b as u32, pid));
+    }
+    // Хвост: пусто либо один байт выравнивания
+    match payload.len() - off {
+        0 => Some(out),
+        1 if payload[off] == 0 => Some(out),
+        _ => None,
+    }
+}
+
+#[inline]
+fn zigzag(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+#[inline]
+fn unzigzag(v: u64) -> i64 {
+    ((v >> 1) as i64) ^ -((v & 1) as i64)
+}
+
+fn put_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn get_varint(buf: &[u8], off: &mut usize) -> Option<u64> {
+    let mut v = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *buf.get(*off)?;
+        *off += 1;
+        v |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(v);
+        }
+    }
+    None
+}
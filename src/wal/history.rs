@@ -0,0 +1,441 @@
+//! wal/history — индекс истории ключей (key-hash → (LSN, op)) для forensic-запросов.
+//!
+//! Sidecar <root>/history.bin строится из WAL перед тем, как WAL усекается: ротация в
+//! коммите (WAL_ROTATE_SIZE), закрытие writer'а, open (до реплея), CLI checkpoint. Включение —
+//! QuiverConfig::history_index (ENV P1_HISTORY_INDEX). Запрос (key_history / CLI `history`)
+//! дополняет sidecar ещё не усечённым хвостом WAL, так что ответ актуален и для живой БД.
+//!
+//! Что индексируется (только закоммиченные батчи BEGIN..COMMIT):
+//! - записи KV-страниц из кадров PAGE_IMAGE: put / del (tombstone) / rewrite (та же версия,
+//!   что у последней известной записи ключа — копия компактации/vacuum или повтор того же put;
+//!   страницы области метаданных приложения, db/user_meta.rs, пропускаются);
+//! - ключи логического кадра EXPIRY (wal_expiry_events): expire.
+//!
+//! Формат (LE): заголовок 16 B [magic "P1HIST01"][u32 version][u32 reserved], далее записи
+//! по 56 B: [u64 key_hash][u64 lsn][u64 batch_lsn][u64 commit_unix_ms][u64 value_digest]
+//! [u64 page_id][u8 op][3 B pad][u32 value_len]. key_hash — xxhash64(seed=0) ключа (коллизии
+//! 64-битного хэша не разрешаются), batch_lsn — LSN COMMIT'а, commit_unix_ms — время из
+//! payload COMMIT (0 — не записано).
+//!
+//! Идемпотентность: батчи с batch_lsn <= максимального уже проиндексированного пропускаются
+//! (повторный проход после сбоя между индексацией и усечением WAL не дублирует записи);
+//! оборванная запись в конце файла отбрасывается.
+
+use anyhow::{anyhow, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::hash::Hasher;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::db::user_meta::is_user_meta_page;
+use crate::metrics::record_history_index;
+use crate::page::kv::kv_for_each_record;
+use crate::page::{OFF_TYPE, PAGE_MAGIC, PAGE_TYPE_KV_RH3};
+use crate::util::decode_ovf_placeholder_v3;
+
+use super::encode::CommitTimestamp;
+use super::expiry::parse_expiry_payload;
+use super::reader::WalStreamReader;
+use super::{
+    wal_path, WAL_HDR_SIZE, WAL_MAGIC, WAL_REC_BEGIN, WAL_REC_COMMIT, WAL_REC_EXPIRY,
+    WAL_REC_PAGE_IMAGE,
+};
+
+/// Имя sidecar-файла истории в корне БД.
+pub const HISTORY_FILE: &str = "history.bin";
+
+const HISTORY_MAGIC: &[u8; 8] = b"P1HIST01";
+const HISTORY_VERSION: u32 = 1;
+const HISTORY_HDR: usize = 16;
+const ENTRY_LEN: usize = 56;
+
+/// Вид изменения ключа.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryOp {
+    Put,
+    Del,
+    Expire,
+    Rewrite,
+}
+
+impl HistoryOp {
+    fn code(self) -> u8 {
+        match self {
+            HistoryOp::Put => 1,
+            HistoryOp::Del => 2,
+            HistoryOp::Expire => 3,
+            HistoryOp::Rewrite => 4,
+        }
+    }
+
+    fn from_code(c: u8) -> Option<Self> {
+        Some(match c {
+            1 => HistoryOp::Put,
+            2 => HistoryOp::Del,
+            3 => HistoryOp::Expire,
+            4 => HistoryOp::Rewrite,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HistoryOp::Put => "put",
+            HistoryOp::Del => "del",
+            HistoryOp::Expire => "expire",
+            HistoryOp::Rewrite => "rewrite",
+        }
+    }
+}
+
+/// Одно изменение ключа.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HistoryEntry {
+    /// LSN страницы с записью (для expire — LSN батча).
+    pub lsn: u64,
+    /// LSN COMMIT'а батча.
+    pub batch_lsn: u64,
+    /// Время коммита (unix ms; 0 — COMMIT без метки времени).
+    pub commit_unix_ms: u64,
+    pub op: HistoryOp,
+    /// KV-страница с записью (0 для expire).
+    pub page_id: u64,
+    /// Длина значения (OVERFLOW — полная длина; 0 для del/expire).
+    pub value_len: u32,
+    /// false — запись из ещё не усечённого WAL (в history.bin пока нет).
+    pub indexed: bool,
+    #[serde(skip)]
+    key_hash: u64,
+    #[serde(skip)]
+    value_digest: u64,
+}
+
+/// Итог прохода индексации.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct HistoryIndexReport {
+    /// Новых закоммиченных батчей с изменениями ключей.
+    pub batches: u64,
+    /// Добавленных записей.
+    pub entries: u64,
+    /// Из них rewrite.
+    pub rewrites: u64,
+    /// Максимальный проиндексированный batch_lsn после прохода.
+    pub indexed_lsn: u64,
+}
+
+pub fn history_path(root: &Path) -> PathBuf {
+    root.join(HISTORY_FILE)
+}
+
+/// xxhash64(seed=0) ключа — ключ индекса истории.
+pub fn history_key_hash(key: &[u8]) -> u64 {
+    let mut h = twox_hash::XxHash64::with_seed(0);
+    h.write(key);
+    h.finish()
+}
+
+/// Добавить в history.bin закоммиченные батчи WAL, которых там ещё нет. Вызывать до усечения
+/// WAL под блокировкой writer'а. Нет WAL — пустой отчёт.
+pub fn index_wal_history(root: &Path) -> Result<HistoryIndexReport> {
+    let path = history_path(root);
+    let (mut f, existing) = open_history_rw(&path)?;
+    let state = IndexState::from_entries(&existing);
+
+    let fresh = scan_wal(root, &state)?;
+    let mut rep = HistoryIndexReport {
+        indexed_lsn: state.max_batch_lsn,
+        ..Default::default()
+    };
+    if fresh.is_empty() {
+        return Ok(rep);
+    }
+
+    let mut buf = Vec::with_capacity(fresh.len() * ENTRY_LEN);
+    let mut last_batch = 0u64;
+    for e in &fresh {
+        buf.extend_from_slice(&encode_entry(e));
+        if e.batch_lsn != last_batch {
+            rep.batches += 1;
+            last_batch = e.batch_lsn;
+        }
+        if e.op == HistoryOp::Rewrite {
+            rep.rewrites += 1;
+        }
+        rep.indexed_lsn = rep.indexed_lsn.max(e.batch_lsn);
+    }
+    f.seek(SeekFrom::End(0))?;
+    f.write_all(&buf)
+        .with_context(|| format!("append {}", path.display()))?;
+    f.sync_all()?;
+    rep.entries = fresh.len() as u64;
+    record_history_index(rep.entries);
+    Ok(rep)
+}
+
+/// История ключа: history.bin + ещё не проиндексированный хвост WAL, по возрастанию LSN.
+/// Работает без блокировки writer'а (частичный хвост WAL/файла игнорируется).
+pub fn key_history(root: &Path, key: &[u8]) -> Result<Vec<HistoryEntry>> {
+    let kh = history_key_hash(key);
+    let existing = read_history(&history_path(root))?;
+    let state = IndexState::from_entries(&existing);
+    let mut out: Vec<HistoryEntry> = existing.into_iter().filter(|e| e.key_hash == kh).collect();
+    out.extend(
+        scan_wal(root, &state)?
+            .into_iter()
+            .filter(|e| e.key_hash == kh)
+            .map(|mut e| {
+                e.indexed = false;
+                e
+            }),
+    );
+    out.sort_by_key(|e| (e.lsn, e.batch_lsn));
+    Ok(out)
+}
+
+// ---------- internals ----------
+
+/// Что уже известно из history.bin: граница батчей и последняя версия каждого ключа.
+struct IndexState {
+    max_batch_lsn: u64,
+    last_digest: HashMap<u64, u64>,
+}
+
+impl IndexState {
+    fn from_entries(entries: &[HistoryEntry]) -> Self {
+        let mut st = IndexState {
+            max_batch_lsn: 0,
+            last_digest: HashMap::new(),
+        };
+        for e in entries {
+            st.max_batch_lsn = st.max_batch_lsn.max(e.batch_lsn);
+            st.note(e);
+        }
+        st
+    }
+
+    fn note(&mut self, e: &HistoryEntry) {
+        match e.op {
+            HistoryOp::Put | HistoryOp::Rewrite | HistoryOp::Del => {
+                self.last_digest.insert(e.key_hash, e.value_digest);
+            }
+            HistoryOp::Expire => {
+                self.last_digest.remove(&e.key_hash);
+            }
+        }
+    }
+}
+
+/// Закоммиченные батчи WAL с batch_lsn > state.max_batch_lsn, в порядке WAL.
+fn scan_wal(root: &Path, state: &IndexState) -> Result<Vec<HistoryEntry>> {
+    let wp = wal_path(root);
+    let mut f = match OpenOptions::new().read(true).open(&wp) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(anyhow!("open WAL {}: {}", wp.display(), e)),
+    };
+    let len = f.metadata()?.len();
+    if len < WAL_HDR_SIZE as u64 {
+        return Ok(Vec::new());
+    }
+    let mut hdr = [0u8; WAL_HDR_SIZE];
+    f.read_exact(&mut hdr)?;
+    if &hdr[..8] != WAL_MAGIC {
+        return Err(anyhow!("bad WAL magic in {}", wp.display()));
+    }
+
+    let mut last_digest = state.last_digest.clone();
+    let mut out = Vec::new();
+    let mut pending: Vec<HistoryEntry> = Vec::new();
+    let mut in_batch = false;
+    let mut rdr = WalStreamReader::new();
+    let mut pos = WAL_HDR_SIZE as u64;
+    loop {
+        // Порча/частичный хвост — конец индексации (незакоммиченный батч отбрасывается)
+        let Ok(Some((rec, next))) = rdr.read_next(&mut f, pos, len) else {
+            break;
+        };
+        pos = next;
+        match rec.rec_type {
+            WAL_REC_BEGIN => {
+                in_batch = true;
+                pending.clear();
+            }
+            WAL_REC_PAGE_IMAGE if in_batch => {
+                collect_page(&rec.payload, rec.lsn, rec.page_id, &mut pending)
+            }
+            WAL_REC_EXPIRY if in_batch => {
+                for ev in parse_expiry_payload(&rec.payload, rec.lsn) {
+                    pending.push(HistoryEntry {
+                        lsn: rec.lsn,
+                        batch_lsn: 0,
+                        commit_unix_ms: 0,
+                        op: HistoryOp::Expire,
+                        page_id: 0,
+                        value_len: 0,
+                        indexed: true,
+                        key_hash: history_key_hash(&ev.key),
+                        value_digest: 0,
+                    });
+                }
+            }
+            WAL_REC_COMMIT if in_batch => {
+                in_batch = false;
+                if rec.lsn <= state.max_batch_lsn {
+                    pending.clear();
+                    continue;
+                }
+                let ts = CommitTimestamp::decode(&rec.payload)
+                    .map(|t| t.wall_ms)
+                    .unwrap_or(0);
+                for mut e in pending.drain(..) {
+                    e.batch_lsn = rec.lsn;
+                    e.commit_unix_ms = ts;
+                    if e.op == HistoryOp::Put
+                        && last_digest.get(&e.key_hash) == Some(&e.value_digest)
+                    {
+                        e.op = HistoryOp::Rewrite;
+                    }
+                    match e.op {
+                        HistoryOp::Expire => {
+                            last_digest.remove(&e.key_hash);
+                        }
+                        _ => {
+                            last_digest.insert(e.key_hash, e.value_digest);
+                        }
+                    }
+                    out.push(e);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+/// Записи KV-страницы в порядке старые → новые.
+fn collect_page(page: &[u8], lsn: u64, page_id: u64, out: &mut Vec<HistoryEntry>) {
+    if page.len() < OFF_TYPE + 2
+        || &page[0..4] != PAGE_MAGIC
+        || LittleEndian::read_u16(&page[OFF_TYPE..OFF_TYPE + 2]) != PAGE_TYPE_KV_RH3
+        || is_user_meta_page(page)
+    {
+        return;
+    }
+    let first = out.len();
+    kv_for_each_record(page, |k, v, expires_at_sec, vflags| {
+        let tomb = (vflags & 0x1) == 1;
+        let value_len = if tomb {
+            0
+        } else {
+            decode_ovf_placeholder_v3(v)
+                .map(|(total, _)| total)
+                .unwrap_or(v.len() as u64)
+                .min(u32::MAX as u64) as u32
+        };
+        let mut h = twox_hash::XxHash64::with_seed(0);
+        h.write(v);
+        h.write_u32(expires_at_sec);
+        h.write_u8(vflags);
+        out.push(HistoryEntry {
+            lsn,
+            batch_lsn: 0,
+            commit_unix_ms: 0,
+            op: if tomb { HistoryOp::Del } else { HistoryOp::Put },
+            page_id,
+            value_len,
+            indexed: true,
+            key_hash: history_key_hash(k),
+            value_digest: h.finish(),
+        });
+    });
+    // kv_for_each_record обходит слоты новые → старые
+    out[first..].reverse();
+}
+
+/// Открыть (создать) history.bin на запись; оборванная запись в конце отбрасывается.
+fn open_history_rw(path: &Path) -> Result<(File, Vec<HistoryEntry>)> {
+    let mut f = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("open {}", path.display()))?;
+    let len = f.metadata()?.len();
+    if len < HISTORY_HDR as u64 {
+        let mut hdr = [0u8; HISTORY_HDR];
+        hdr[..8].copy_from_slice(HISTORY_MAGIC);
+        LittleEndian::write_u32(&mut hdr[8..12], HISTORY_VERSION);
+        f.set_len(0)?;
+        f.write_all(&hdr)?;
+        f.sync_all()?;
+        return Ok((f, Vec::new()));
+    }
+    let mut buf = Vec::with_capacity(len as usize);
+    f.read_to_end(&mut buf)?;
+    let entries = decode_history(path, &buf)?;
+    let whole = (HISTORY_HDR + entries.len() * ENTRY_LEN) as u64;
+    if whole != len {
+        f.set_len(whole)?;
+    }
+    Ok((f, entries))
+}
+
+fn read_history(path: &Path) -> Result<Vec<HistoryEntry>> {
+    match std::fs::read(path) {
+        Ok(buf) if buf.len() >= HISTORY_HDR => decode_history(path, &buf),
+        Ok(_) => Ok(Vec::new()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(anyhow!("read {}: {}", path.display(), e)),
+    }
+}
+
+fn decode_history(path: &Path, buf: &[u8]) -> Result<Vec<HistoryEntry>> {
+    if &buf[..8] != HISTORY_MAGIC {
+        return Err(anyhow!("bad history magic in {}", path.display()));
+    }
+    let ver = LittleEndian::read_u32(&buf[8..12]);
+    if ver != HISTORY_VERSION {
+        return Err(anyhow!(
+            "unsupported history version {} in {} (expected {})",
+            ver,
+            path.display(),
+            HISTORY_VERSION
+        ));
+    }
+    Ok(buf[HISTORY_HDR..]
+        .chunks_exact(ENTRY_LEN)
+        .filter_map(decode_entry)
+        .collect())
+}
+
+fn encode_entry(e: &HistoryEntry) -> [u8; ENTRY_LEN] {
+    let mut b = [0u8; ENTRY_LEN];
+    LittleEndian::write_u64(&mut b[0..8], e.key_hash);
+    LittleEndian::write_u64(&mut b[8..16], e.lsn);
+    LittleEndian::write_u64(&mut b[16..24], e.batch_lsn);
+    LittleEndian::write_u64(&mut b[24..32], e.commit_unix_ms);
+    LittleEndian::write_u64(&mut b[32..40], e.value_digest);
+    LittleEndian::write_u64(&mut b[40..48], e.page_id);
+    b[48] = e.op.code();
+    LittleEndian::write_u32(&mut b[52..56], e.value_len);
+    b
+}
+
+fn decode_entry(b: &[u8]) -> Option<HistoryEntry> {
+    Some(HistoryEntry {
+        key_hash: LittleEndian::read_u64(&b[0..8]),
+        lsn: LittleEndian::read_u64(&b[8..16]),
+        batch_lsn: LittleEndian::read_u64(&b[16..24]),
+        commit_unix_ms: LittleEndian::read_u64(&b[24..32]),
+        value_digest: LittleEndian::read_u64(&b[32..40]),
+        page_id: LittleEndian::read_u64(&b[40..48]),
+        op: HistoryOp::from_code(b[48])?,
+        value_len: LittleEndian::read_u32(&b[52..56]),
+        indexed: true,
+    })
+}
@@ -0,0 +1,285 @@
+//! wal/idempotency — idempotency-токены батчей (дедупликация повторной отправки).
+//!
+//! Сценарий: приложение отправило Db::batch, получило таймаут и повторяет тот же батч.
+//! Db::batch_idempotent(token, ..) пропускает батч, если батч с таким токеном уже закоммичен.
+//!
+//! Хранение:
+//! - Токен не хранится как есть — используется SHA-256 digest (токены могут содержать PII).
+//! - Кольцевой буфер последних N digest'ов в <root>/.idem_tokens.bin
+//!   (N = P1_IDEM_TOKENS при создании файла, по умолчанию 4096). Дедупликация гарантируется
+//!   только в пределах окна: токен, вытесненный N более новыми, снова считается новым.
+//! - В WAL батч с токеном несёт кадр IDEMPOTENCY (сразу после BEGIN, payload = digest32),
+//!   поэтому:
+//!   * реплей после краха дописывает токены закоммиченных батчей в кольцо
+//!     (кадр успел попасть в WAL, а side-car — нет);
+//!   * follower (cdc-apply) ведёт своё кольцо и пропускает батчи, чей токен уже применён
+//!     (IdemApplyGate) — после промоута дедупликация продолжает работать.
+//!
+//! Формат файла (LE):
+//!   [magic8 "P2IDEM01"][u32 capacity][u32 next_slot][u64 reserved=0]
+//!   [capacity × (digest32, u64 commit_lsn, u32 crc32c(digest+lsn), u32 reserved=0)]
+//! Слот с lsn=0 или неверным CRC считается пустым (порванная запись слота безопасна).
+//! Запись — на месте (слот + заголовок), без fsync: источник истины для свежих токенов — WAL.
+
+use anyhow::{Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::metrics::{record_idem_batch_skipped, record_idem_token_recorded};
+
+use super::{WAL_REC_BEGIN, WAL_REC_COMMIT, WAL_REC_IDEMPOTENCY};
+
+pub const IDEM_TOKENS_FILE: &str = ".idem_tokens.bin";
+const IDEM_MAGIC: &[u8; 8] = b"P2IDEM01";
+const IDEM_HDR: usize = 24;
+const IDEM_ENTRY: usize = 48;
+
+/// Длина digest'а токена (payload кадра IDEMPOTENCY).
+pub const IDEM_DIGEST_LEN: usize = 32;
+pub type IdemDigest = [u8; IDEM_DIGEST_LEN];
+
+#[inline]
+pub fn idem_tokens_path(root: &Path) -> PathBuf {
+    root.join(IDEM_TOKENS_FILE)
+}
+
+/// Digest idempotency-токена (SHA-256).
+pub fn idem_digest(token: &[u8]) -> IdemDigest {
+    let mut h = Sha256::new();
+    h.update(token);
+    h.finalize().into()
+}
+
+/// Разобрать payload кадра IDEMPOTENCY (None — неизвестная длина, кадр игнорируется).
+pub fn parse_idem_payload(payload: &[u8]) -> Option<IdemDigest> {
+    payload.try_into().ok()
+}
+
+/// Ёмкость кольца для новых файлов (ENV P1_IDEM_TOKENS, по умолчанию 4096).
+pub fn idem_ring_capacity() -> usize {
+    static V: OnceLock<usize> = OnceLock::new();
+    *V.get_or_init(|| {
+        std::env::var("P1_IDEM_TOKENS")
+            .ok()
+            .and_then(|s| s.trim().parse::<usize>().ok())
+            .unwrap_or(4096)
+            .max(1)
+    })
+}
+
+/// Кольцо последних закоммиченных токенов (side-car).
+pub struct IdemTokens {
+    path: PathBuf,
+    file: Option<File>,
+    next: usize,
+    slots: Vec<Option<(IdemDigest, u64)>>,
+    index: HashMap<IdemDigest, u64>,
+}
+
+impl IdemTokens {
+    /// Загрузить кольцо. Отсутствующий/повреждённый файл — пустое кольцо
+    /// (файл будет переписан при первой записи).
+    pub fn open(root: &Path) -> Result<Self> {
+        let path = idem_tokens_path(root);
+        let mut me = Self {
+            path,
+            file: None,
+            next: 0,
+            slots: vec![None; idem_ring_capacity()],
+            index: HashMap::new(),
+        };
+        let buf = match std::fs::read(&me.path) {
+            Ok(b) => b,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(me),
+            Err(e) => return Err(e).with_context(|| format!("read {}", me.path.display())),
+        };
+        if buf.len() < IDEM_HDR || &buf[0..8] != IDEM_MAGIC {
+            return Ok(me);
+        }
+        let cap = LittleEndian::read_u32(&buf[8..12]) as usize;
+        if cap == 0 || buf.len() != IDEM_HDR + cap * IDEM_ENTRY {
+            return Ok(me);
+        }
+        let mut slots = vec![None; cap];
+        for (i, slot) in slots.iter_mut().enumerate() {
+            let off = IDEM_HDR + i * IDEM_ENTRY;
+            let e = &buf[off..off + IDEM_ENTRY];
+            let lsn = LittleEndian::read_u64(&e[32..40]);
+            if lsn == 0 || crc32c::crc32c(&e[..40]) != LittleEndian::read_u32(&e[40..44]) {
+                continue;
+            }
+            let mut d = [0u8; IDEM_DIGEST_LEN];
+            d.copy_from_slice(&e[..32]);
+            me.index.insert(d, lsn);
+            *slot = Some((d, lsn));
+        }
+        me.slots = slots;
+        me.next = (LittleEndian::read_u32(&buf[12..16]) as usize) % cap;
+        me.file = Some(
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&me.path)
+                .with_context(|| format!("open {}", me.path.display()))?,
+        );
+        Ok(me)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// LSN коммита батча с этим токеном (None — токен не встречался в окне).
+    pub fn contains(&self, digest: &IdemDigest) -> Option<u64> {
+        self.index.get(digest).copied()
+    }
+
+    /// Запомнить токен закоммиченного батча. Повторная запись того же токена — no-op.
+    pub fn record(&mut self, digest: IdemDigest, commit_lsn: u64) -> Result<()> {
+        if self.index.contains_key(&digest) {
+            return Ok(());
+        }
+        let slot = self.next;
+        if let Some((old, _)) = self.slots[slot].take() {
+            self.index.remove(&old);
+        }
+        self.slots[slot] = Some((digest, commit_lsn));
+        self.index.insert(digest, commit_lsn);
+        self.next = (slot + 1) % self.slots.len();
+
+        if self.file.is_none() {
+            self.rewrite()?;
+        } else {
+            self.write_slot(slot)?;
+        }
+        record_idem_token_recorded();
+        Ok(())
+    }
+
+    fn header_bytes(&self) -> [u8; IDEM_HDR] {
+        let mut h = [0u8; IDEM_HDR];
+        h[0..8].copy_from_slice(IDEM_MAGIC);
+        LittleEndian::write_u32(&mut h[8..12], self.slots.len() as u32);
+        LittleEndian::write_u32(&mut h[12..16], self.next as u32);
+        h
+    }
+
+    fn entry_bytes(&self, slot: usize) -> [u8; IDEM_ENTRY] {
+        let mut e = [0u8; IDEM_ENTRY];
+        if let Some((d, lsn)) = &self.slots[slot] {
+            e[..32].copy_from_slice(d);
+            LittleEndian::write_u64(&mut e[32..40], *lsn);
+            let crc = crc32c::crc32c(&e[..40]);
+            LittleEndian::write_u32(&mut e[40..44], crc);
+        }
+        e
+    }
+
+    fn write_slot(&mut self, slot: usize) -> Result<()> {
+        let entry = self.entry_bytes(slot);
+        let hdr = self.header_bytes();
+        let f = self.file.as_mut().expect("idem tokens file is open");
+        f.seek(SeekFrom::Start((IDEM_HDR + slot * IDEM_ENTRY) as u64))?;
+        f.write_all(&entry)?;
+        f.seek(SeekFrom::Start(0))?;
+        f.write_all(&hdr)?;
+        Ok(())
+    }
+
+    /// Переписать файл целиком (tmp + rename) и открыть его для записи слотов.
+    fn rewrite(&mut self) -> Result<()> {
+        let mut buf = Vec::with_capacity(IDEM_HDR + self.slots.len() * IDEM_ENTRY);
+        buf.extend_from_slice(&self.header_bytes());
+        for i in 0..self.slots.len() {
+            buf.extend_from_slice(&self.entry_bytes(i));
+        }
+        let tmp = self
+            .path
+            .with_file_name(format!("{}.tmp", IDEM_TOKENS_FILE));
+        {
+            let mut f = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&tmp)
+                .with_context(|| format!("open {}", tmp.display()))?;
+            f.write_all(&buf)?;
+            let _ = f.sync_all();
+        }
+        crate::util::fsx::replace_file(&tmp, &self.path)
+            .with_context(|| format!("rename {} -> {}", tmp.display(), self.path.display()))?;
+        self.file = Some(
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&self.path)
+                .with_context(|| format!("open {}", self.path.display()))?,
+        );
+        Ok(())
+    }
+}
+
+/// Гейт для apply-пути follower'а: пропустить кадры батча, чей токен уже применён.
+///
+/// observe() вызывается для каждого кадра по порядку; false — эффект кадра применять не нужно.
+/// Токен применённого батча запоминается на COMMIT (с LSN коммита).
+pub struct IdemApplyGate {
+    tokens: IdemTokens,
+    pending: Option<IdemDigest>,
+    skipping: bool,
+    pub skipped_batches: u64,
+}
+
+impl IdemApplyGate {
+    pub fn open(root: &Path) -> Result<Self> {
+        Ok(Self {
+            tokens: IdemTokens::open(root)?,
+            pending: None,
+            skipping: false,
+            skipped_batches: 0,
+        })
+    }
+
+    pub fn observe(&mut self, rec_type: u8, lsn: u64, payload: &[u8]) -> Result<bool> {
+        match rec_type {
+            WAL_REC_BEGIN => {
+                self.pending = None;
+                self.skipping = false;
+                Ok(true)
+            }
+            WAL_REC_IDEMPOTENCY => {
+                if let Some(d) = parse_idem_payload(payload) {
+                    if self.tokens.contains(&d).is_some() {
+                        self.skipping = true;
+                        self.skipped_batches += 1;
+                        record_idem_batch_skipped();
+                    } else {
+                        self.pending = Some(d);
+                    }
+                }
+                Ok(true)
+            }
+            WAL_REC_COMMIT => {
+                let skipped = std::mem::replace(&mut self.skipping, false);
+                if let Some(d) = self.pending.take() {
+                    self.tokens.record(d, lsn)?;
+                }
+                Ok(!skipped)
+            }
+            _ => Ok(!self.skipping),
+        }
+    }
+}
@@ -8,9 +8,19 @@
 //! - reader.rs   — последовательное чтение кадров WAL с проверкой CRC.
 //! - net.rs      — CDC transport helpers (framing + HMAC-PSK).
 //! - state.rs    — общие helpers для персистентного состояния CDC/WAL (last_heads_lsn и т.п.). [NEW]
+//! - salvage.rs  — salvage-режим: ресинхронизация после порчи WAL, отчёт и применение целых батчей. [NEW]
+//! - recovery.rs — прогресс open-time восстановления и индекс границ батчей (fast-skip). [NEW]
+//! - idempotency.rs — кольцо idempotency-токенов батчей (дедупликация повторной отправки). [NEW]
+//! - expiry.rs   — логический кадр EXPIRY (ключи, вычищенные по TTL) и ExpiryEvent. [NEW]
+//! - heads.rs    — payload HEADS_UPDATE: legacy (12 B/запись) и компактный delta-формат. [NEW]
+//! - cursors.rs  — именованные курсоры подписчиков fan-out сервера cdc-serve. [NEW]
+//! - filter.rs   — серверная фильтрация CDC-потока по диапазонам бакетов / префиксам ключей. [NEW]
+//! - history.rs  — индекс истории ключей (key-hash → LSN/op) в sidecar history.bin. [NEW]
+//! - coalesce.rs — адаптивное окно group-commit по интервалу коммитов и латентности fsync. [NEW]
 //!
 //! В этом модуле (mod.rs) лежат:
-//! - публичные константы формата (импортируются снаружи как crate::wal::*),
+//! - публичные константы формата (импортируются снаружи как crate::wal::*; определены в крейте
+//!   quiverdb-format вместе с encode/decode заголовка кадра),
 //! - общие утилиты (crc32c_of_parts, write_wal_file_header, wal_path, stream_id генерация/чтение),
 //! - re-export публичных типов/функций из подмодулей.
 
@@ -23,48 +33,19 @@ use std::path::{Path, PathBuf};
 // -------------------- Публичные константы WAL v2 --------------------
 
 pub const WAL_FILE: &str = "wal-000001.log";
-pub const WAL_MAGIC: &[u8; 8] = b"P2WAL001";
-pub const WAL_HDR_SIZE: usize = 16; // magic8 + reserved u64(stream_id)
-
-// Offsets внутри заголовка файла
-pub const WAL_HDR_OFF_STREAM_ID: usize = 8; // 8 байт после MAGIC (LE u64)
-
-// Record header (v2): 28 bytes (включая поле CRC на смещении 24..28)
-pub const WAL_REC_HDR_SIZE: usize = 28;
-
-// Offsets внутри заголовка записи
-pub const WAL_REC_OFF_TYPE: usize = 0;
-pub const WAL_REC_OFF_FLAGS: usize = 1;
-pub const WAL_REC_OFF_RESERVED: usize = 2;
-pub const WAL_REC_OFF_LSN: usize = 4;
-pub const WAL_REC_OFF_PAGE_ID: usize = 12;
-pub const WAL_REC_OFF_LEN: usize = 20;
-pub const WAL_REC_OFF_CRC32: usize = 24;
-
-// Типы записей
-pub const WAL_REC_BEGIN: u8 = 1;
-pub const WAL_REC_PAGE_IMAGE: u8 = 2;
-pub const WAL_REC_PAGE_DELTA: u8 = 3; // зарезервировано — игнорируется реплеем в 2.0
-pub const WAL_REC_COMMIT: u8 = 4;
-pub const WAL_REC_TRUNCATE: u8 = 5;
-// NEW: атомарные обновления голов каталога (между IMAGE… и COMMIT в батче)
-pub const WAL_REC_HEADS_UPDATE: u8 = 6;
+// NEW: константы формата кадров — из крейта quiverdb-format (единая точка правды)
+pub use quiverdb_format::wal::{
+    crc32c_of_parts, WAL_HDR_OFF_STREAM_ID, WAL_HDR_SIZE, WAL_MAGIC, WAL_REC_BEGIN, WAL_REC_COMMIT,
+    WAL_REC_EXPIRY, WAL_REC_HDR_SIZE, WAL_REC_HEADS_UPDATE, WAL_REC_IDEMPOTENCY, WAL_REC_OFF_CRC32,
+    WAL_REC_OFF_FLAGS, WAL_REC_OFF_LEN, WAL_REC_OFF_LSN, WAL_REC_OFF_PAGE_ID, WAL_REC_OFF_RESERVED,
+    WAL_REC_OFF_TYPE, WAL_REC_PAGE_DELTA, WAL_REC_PAGE_IMAGE, WAL_REC_TRUNCATE,
+};
 
 // Порог ротации (можно вынести в конфиг позднее)
 pub const WAL_ROTATE_SIZE: u64 = 8 * 1024 * 1024;
 
 // -------------------- Общие утилиты (видны подмодулям и CLI) --------------------
 
-/// Инкрементальный CRC32C по двум срезам без аллокаций.
-///
-/// Ранее здесь был вариант с выделением временного буфера и копированием.
-/// Это горячий путь — используем crc32c_append, чтобы избежать аллокаций.
-#[inline]
-pub fn crc32c_of_parts(head_without_crc: &[u8], payload: &[u8]) -> u32 {
-    let c = crc32c::crc32c_append(0, head_without_crc);
-    crc32c::crc32c_append(c, payload)
-}
-
 /// Генерировать случайный stream_id (u64, LE при записи в заголовок).
 /// Идempotent в рамках процесса — генерируйте и сохраняйте отдельно (см. wal/state.rs).
 pub fn generate_stream_id() -> u64 {
@@ -104,12 +85,7 @@ pub fn wal_header_read_stream_id(f: &mut File) -> Result<u64> {
     let mut hdr = [0u8; WAL_HDR_SIZE];
     f.seek(SeekFrom::Start(0))?;
     std::io::Read::read_exact(f, &mut hdr)?;
-    if &hdr[..8] != WAL_MAGIC {
-        anyhow::bail!("bad WAL magic");
-    }
-    Ok(LittleEndian::read_u64(
-        &hdr[WAL_HDR_OFF_STREAM_ID..WAL_HDR_OFF_STREAM_ID + 8],
-    ))
+    quiverdb_format::wal::wal_file_header_stream_id(&hdr)
 }
 
 /// Построить путь к WAL-файлу для корня БД.
@@ -136,8 +112,59 @@ pub mod writer;
 // NEW: CDC transport helpers (framing + HMAC-PSK)
 pub mod net;
 
+// NEW: TLS-слушатель CDC с горячей перезагрузкой сертификата (cdc-serve tls+psk://)
+pub mod tls_server;
+
+// NEW: TLS-клиент CDC с перезагрузкой клиентской identity (P1_TLS_CLIENT_PFX) и проверкой срока
+pub mod tls_client;
+
 // NEW: персистентное состояние (last_heads_lsn и пр.)
 pub mod state;
 
-pub use replay::wal_replay_if_any;
+// NEW: прогресс восстановления + индекс границ батчей
+pub mod recovery;
+
+// NEW: salvage после частичной порчи WAL
+pub mod salvage;
+
+// NEW: idempotency-токены батчей
+pub mod idempotency;
+
+// NEW: события истечения TTL (логический кадр EXPIRY)
+pub mod expiry;
+
+// NEW: delta-кодирование HEADS_UPDATE
+pub mod heads;
+
+// NEW: именованные курсоры подписчиков cdc-serve
+pub mod cursors;
+
+// NEW: фильтрация CDC-потока по бакетам/префиксам ключей
+pub mod filter;
+
+// NEW: индекс истории ключей (forensic: какой батч менял ключ)
+pub mod history;
+
+// NEW: адаптивное окно group-commit (бюджет p99 латентности коммита)
+pub mod coalesce;
+
+pub use coalesce::{AdaptiveCoalesce, CoalesceDecision, CoalesceStatus};
+pub use encode::{CommitTimestamp, WAL_COMMIT_TS_LEN};
+pub use expiry::{encode_expiry_payload, parse_expiry_payload, ExpiryEvent};
+pub use filter::{CdcFilter, FilterFrame, FilterStats, FrameFilter};
+pub use heads::{
+    decode_heads_update_payload, encode_heads_update_legacy, encode_heads_update_payload,
+    is_heads_delta_payload, normalize_head_updates, HEADS_DELTA_TAG,
+};
+pub use history::{
+    index_wal_history, key_history, HistoryEntry, HistoryIndexReport, HistoryOp, HISTORY_FILE,
+};
+pub use idempotency::{idem_digest, IdemApplyGate, IdemDigest, IdemTokens};
+pub use recovery::{
+    default_recovery_progress, set_default_recovery_progress, RecoveryProgress,
+    RecoveryProgressHook,
+};
+pub use registry::group_coalesce_status;
+pub use replay::{wal_replay_if_any, wal_replay_tracked, wal_replay_with_progress, ReplayTouched};
+pub use salvage::{wal_salvage_scan, wal_salvage_with, SalvageReport};
 pub use writer::{Wal, WalGroupCfg};
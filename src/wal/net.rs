@@ -16,14 +16,82 @@
 //!   P1_TLS_CA_FILE             — PEM‑файл c CA (поддерживаются блоки CERTIFICATE/TRUSTED CERTIFICATE)
 //!   P1_TLS_CLIENT_PFX          — путь к PFX/PKCS#12 (mTLS; опц.)
 //!   P1_TLS_CLIENT_PFX_PASSWORD — пароль к PFX (mTLS; опц.)
+//!
+//! NEW: перезагрузка PFX/CA и проверка срока клиентского сертификата — wal/tls_client.rs.
+//!
+//! NEW: серверная сторона TLS (cdc-serve --listen tls+psk://) — wal/tls_server.rs.
+//!
+//! NEW: протокол потока v2 (handshake, capabilities, keepalive/ack) — управляющие сообщения
+//! идут в тех же PSK-фреймах (под HMAC), payload = [CTRL_MAGIC 8][u8 kind][тело LE]:
+//! - HELLO (получатель → отправитель, сразу после connect): версия, capabilities, запрошенный
+//!   since_lsn (u64::MAX — нет запроса), известный получателю stream_id, интервал keepalive;
+//! - WELCOME (отправитель → получатель, вместо v1 HELLO с WAL header): версия, общие
+//!   capabilities (пересечение), stream_id, стартовый LSN, согласованный keepalive,
+//!   NEW: db_uuid источника (дописан в конец тела; у старого отправителя — нули);
+//! - KEEPALIVE{last_lsn} (отправитель) → ACK{applied_lsn} (получатель): ping/pong раз в
+//!   интервал и в конце потока — отправитель видит, что follower жив и что он применил;
+//! - BYE{last_lsn} — штатный конец сессии.
+//!
+//! NEW: сжатие кадров (CAP_COMPRESS) — per-frame, алгоритм согласуется в handshake: HELLO несёт
+//! маску алгоритмов, которые получатель умеет распаковывать, WELCOME — выбранный отправителем
+//! (его P1_CDC_COMPRESS, если получатель его поддерживает, иначе без сжатия). Поэтому к одному
+//! leader'у подключаются follower'ы с разными настройками. Сжатый кадр:
+//! payload = [CMP_MAGIC 8][u8 algo][u32 raw_len][сжатые байты]; кадры короче
+//! P1_CDC_COMPRESS_MIN и те, что не уменьшились, идут как есть. Управляющие сообщения не сжимаются.
+//!
+//! NEW: receiver-driven resume — получатель кладёт в HELLO since_lsn = свой meta.last_lsn
+//! (LSN последнего полностью применённого батча, COMMIT), и отправитель без явного
+//! --since-lsn начинает поток с него: ручной учёт позиции между сессиями не нужен.
+//!
+//! NEW: фильтр потока (CAP_FILTER) — получатель кладёт в HELLO префиксы ключей и/или диапазоны
+//! бакетов, отправитель отбрасывает нерелевантные страницы и записи HEADS_UPDATE/EXPIRY
+//! (wal/filter.rs). Отправитель без CAP_FILTER шлёт полный поток.
+//!
+//! NEW: verify (CAP_DIGEST) — follower, догнавший отправителя (KEEPALIVE.last_lsn == свой
+//! applied LSN), перед ACK шлёт DIGEST_REQ{lsn, now, бакеты выборки}; отправитель отвечает
+//! DIGEST{lsn, status, (bucket, live_keys, hash)*} — digest живых ключей бакетов на том же LSN
+//! (db/digest.rs). status != ok — отправитель ушёл дальше (сверка откладывается) или digest
+//! недоступен. Расхождение follower сообщает сам (cdc-apply, P1_CDC_VERIFY_SAMPLE).
+//!
+//! NEW: resync (CAP_RESYNC) — разошедшийся при verify бакет follower чинит на следующем
+//! KEEPALIVE, где он снова догнал отправителя: перед ACK шлёт RESYNC_REQ{lsn, now, bucket};
+//! отправитель отвечает RESYNC_PAGE{page_id, образ}* и RESYNC_END{lsn, status, bucket, head,
+//! pages, digest} — состояние бакета на том же LSN в своей раскладке (db/resync.rs).
+//! RESYNC_PAGE крупнее CTRL_MAX_LEN (страница целиком) — follower читает его с пределом
+//! WAL-кадра. Статусы RESYNC_END — те же, что у DIGEST.
+//!
+//! WAL-кадр начинается с типа записи (малые числа), поэтому CTRL_MAGIC ('P'...) с ним не путается.
+//! Совместимость с v1: отправитель ждёт HELLO до P1_CDC_HELLO_WAIT_MS (по умолчанию 2000);
+//! не дождался — работает по v1 (HELLO = WAL header). Получатель v2, получивший v1 HELLO,
+//! продолжает по v1 (его HELLO отправитель v1 просто не читает).
+//!
+//! ENV (протокол):
+//!   P1_CDC_PROTO=1|2          — максимальная версия протокола (по умолчанию 2)
+//!   P1_CDC_KEEPALIVE_MS       — интервал keepalive (по умолчанию 5000; 0 — выкл.)
+//!   P1_CDC_HELLO_WAIT_MS      — сколько отправитель ждёт HELLO получателя (по умолчанию 2000)
+//!   P1_CDC_COMPRESS=none|zstd[:level] — сжатие кадров (по умолчанию zstd; none — выкл.)
+//!   P1_CDC_COMPRESS_MIN       — минимальный размер кадра для сжатия (по умолчанию 512)
+//!   P1_CDC_SUBSCRIBER         — имя подписчика в HELLO (именованный курсор cdc-serve)
+//!   P1_CDC_FILTER_PREFIX / P1_CDC_FILTER_BUCKETS — фильтр потока в HELLO (wal/filter.rs)
+//!   P1_CDC_RESUME=0|1         — получатель запрашивает since_lsn в HELLO (по умолчанию 1)
+//!   P1_CDC_VERIFY_SAMPLE      — бакетов на одну сверку digest'ов с отправителем (0 — выкл., по умолчанию)
+//!   P1_CDC_RESYNC=0|1         — follower пересинхронизирует разошедшиеся бакеты (по умолчанию 0)
 
 use anyhow::{anyhow, Context, Result};
 use base64::Engine;
 use byteorder::{ByteOrder, LittleEndian};
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
+use std::borrow::Cow;
 use std::io::{Read, Write};
 use std::net::TcpStream;
+use std::time::Duration;
+
+// NEW: разбор none|zstd[:level] — как у `backup --compress`
+use super::cursors::SUBSCRIBER_NAME_MAX;
+use super::filter::CdcFilter;
+use crate::backup::BackupCompression;
+use crate::db::{BucketDigest, BucketState};
 
 type HmacSha256 = Hmac<Sha256>;
 
@@ -174,7 +242,8 @@ fn read_exact_or_eof<R: Read>(r: &mut R, buf: &mut [u8]) -> Result<bool> {
             Ok(0) => return Ok(false),
             Ok(n) => off += n,
             Err(e) if e.kind() == ErrorKind::Interrupted => continue,
-            Err(e) => return Err(anyhow!("read error: {}", e)),
+            // io::Error сохраняется в цепочке (таймауты handshake/keepalive различимы по kind)
+            Err(e) => return Err(anyhow::Error::new(e).context("read error")),
         }
     }
     Ok(true)
@@ -216,7 +285,7 @@ fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
 
 // ----------------------------- TLS client (native-tls) -----------------------------
 
-use native_tls::{Certificate as NtCertificate, Identity as NtIdentity, TlsConnector, TlsStream};
+use native_tls::TlsStream;
 
 /// Поток ввода/вывода: TCP или TLS.
 pub enum IoStream {
@@ -247,61 +316,32 @@ impl Write for IoStream {
     }
 }
 
+impl IoStream {
+    /// Таймаут чтения нижележащего TCP-сокета (None — блокирующее чтение).
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> std::io::Result<()> {
+        match self {
+            IoStream::Plain(s) => s.set_read_timeout(dur),
+            IoStream::Tls(s) => s.get_ref().set_read_timeout(dur),
+        }
+    }
+}
+
 /// Открыть TLS‑поток (native-tls) для адреса "host:port".
 /// - Если задан P1_TLS_CA_FILE — добавляем кастомные CA (PEM).
 /// - Если заданы P1_TLS_CLIENT_PFX/P1_TLS_CLIENT_PFX_PASSWORD — включаем mTLS (PKCS#12).
 /// - SNI берём из P1_TLS_DOMAIN или host из addr.
+///
+/// NEW: connector общий на процесс (wal/tls_client.rs): PFX и CA перезагружаются при изменении
+/// файлов, срок клиентского сертификата проверяется.
 pub fn open_tls_psk_stream(addr: &str) -> Result<IoStream> {
-    let domain =
-        tls_domain_for_addr(addr).with_context(|| format!("derive SNI from addr '{}'", addr))?;
-    let tcp = TcpStream::connect(addr).map_err(|e| anyhow!("connect({}): {}", addr, e))?;
-
-    let mut builder = TlsConnector::builder();
-
-    // Кастомные CA (PEM). Поддерживаем "CERTIFICATE" и "TRUSTED CERTIFICATE".
-    if let Ok(ca_path) = std::env::var("P1_TLS_CA_FILE") {
-        let ca_pem =
-            std::fs::read(&ca_path).map_err(|e| anyhow!("read CA file {}: {}", ca_path, e))?;
-
-        let certs_der =
-            parse_pem_certs(&ca_pem).with_context(|| format!("parse PEM CA file {}", ca_path))?;
-        if certs_der.is_empty() {
-            return Err(anyhow!(
-                "no certificates found in CA file {} (expect BEGIN CERTIFICATE blocks)",
-                ca_path
-            ));
-        }
-        for der in certs_der {
-            let cert = NtCertificate::from_der(&der)
-                .map_err(|e| anyhow!("parse DER from {}: {}", ca_path, e))?;
-            builder.add_root_certificate(cert);
-        }
-    }
-
-    // mTLS через PKCS#12 (PFX)
-    if let (Ok(pfx_path), Ok(pfx_pwd)) = (
-        std::env::var("P1_TLS_CLIENT_PFX"),
-        std::env::var("P1_TLS_CLIENT_PFX_PASSWORD"),
-    ) {
-        let pfx_der =
-            std::fs::read(&pfx_path).map_err(|e| anyhow!("read PFX {}: {}", pfx_path, e))?;
-        let id = NtIdentity::from_pkcs12(&pfx_der, &pfx_pwd)
-            .map_err(|e| anyhow!("load PFX {}: {}", pfx_path, e))?;
-        builder.identity(id);
-    }
-
-    let connector = builder.build().map_err(|e| anyhow!("tls build: {}", e))?;
-    let tls = connector
-        .connect(&domain, tcp)
-        .map_err(|e| anyhow!("tls connect (SNI={}): {}", domain, e))?;
-    Ok(IoStream::Tls(tls))
+    super::tls_client::shared_from_env()?.connect(addr)
 }
 
 /// Простейший разбор PEM‑файла с извлечением всех сертификатов DER.
 /// Поддерживаются блоки:
 ///   -----BEGIN CERTIFICATE----- ... -----END CERTIFICATE-----
 ///   -----BEGIN TRUSTED CERTIFICATE----- ... -----END TRUSTED CERTIFICATE-----
-fn parse_pem_certs(pem_bytes: &[u8]) -> Result<Vec<Vec<u8>>> {
+pub(crate) fn parse_pem_certs(pem_bytes: &[u8]) -> Result<Vec<Vec<u8>>> {
     let text = String::from_utf8_lossy(pem_bytes);
     let mut out: Vec<Vec<u8>> = Vec::new();
 
@@ -350,7 +390,7 @@ fn parse_pem_certs(pem_bytes: &[u8]) -> Result<Vec<Vec<u8>>> {
 }
 
 /// Парсер host/SNI из "host:port" и "[ipv6]:port".
-fn tls_domain_for_addr(addr: &str) -> Result<String> {
+pub(super) fn tls_domain_for_addr(addr: &str) -> Result<String> {
     if let Ok(sni) = std::env::var("P1_TLS_DOMAIN") {
         let s = sni.trim();
         if !s.is_empty() {
@@ -372,3 +412,710 @@ fn tls_domain_for_addr(addr: &str) -> Result<String> {
     }
     Ok(addr.to_string())
 }
+
+// ----------------------------- Stream protocol v2 -----------------------------
+
+/// Маркер управляющего сообщения (первые 8 байт payload PSK-фрейма).
+pub const CTRL_MAGIC: &[u8; 8] = b"P2CTL002";
+/// Текущая версия протокола потока (v1 — HELLO = WAL header, без согласования).
+pub const CDC_PROTO_VERSION: u16 = 2;
+/// Верхняя граница размера управляющего сообщения (для max_len при чтении handshake).
+pub const CTRL_MAX_LEN: usize = 4096;
+/// since_lsn в HELLO: получатель не запрашивает стартовую позицию.
+pub const SINCE_LSN_NONE: u64 = u64::MAX;
+
+/// Поток защищён HMAC-PSK (всегда для tcp+psk/tls+psk).
+pub const CAP_HMAC: u32 = 1 << 0;
+/// Получатель понимает delta-формат HEADS_UPDATE (иначе отправитель перекодирует в legacy).
+pub const CAP_HEADS_DELTA: u32 = 1 << 1;
+/// Сжатие кадров (алгоритм — CdcHello::compress / CdcWelcome::compress).
+pub const CAP_COMPRESS: u32 = 1 << 2;
+/// KEEPALIVE/ACK ping-pong.
+pub const CAP_KEEPALIVE: u32 = 1 << 3;
+/// Серверный фильтр потока (CdcHello::filter).
+pub const CAP_FILTER: u32 = 1 << 4;
+/// Сверка digest'ов бакетов (DIGEST_REQ/DIGEST).
+pub const CAP_DIGEST: u32 = 1 << 5;
+/// Пересинхронизация бакета (RESYNC_REQ/RESYNC_PAGE/RESYNC_END).
+pub const CAP_RESYNC: u32 = 1 << 6;
+
+/// Capabilities этой сборки.
+pub fn local_caps() -> u32 {
+    CAP_HMAC | CAP_HEADS_DELTA | CAP_COMPRESS | CAP_KEEPALIVE | CAP_FILTER | CAP_DIGEST | CAP_RESYNC
+}
+
+/// Предел бакетов в одном DIGEST_REQ/DIGEST (ответ помещается в CTRL_MAX_LEN).
+pub const DIGEST_MAX_BUCKETS: usize = 128;
+/// Статусы DIGEST и RESYNC_END.
+pub const DIGEST_OK: u8 = 0;
+/// Состояние отправителя не совпадает с запрошенным LSN (ушёл дальше / коммит во время подсчёта).
+pub const DIGEST_LSN_MOVED: u8 = 1;
+/// Отправитель не смог посчитать digest.
+pub const DIGEST_UNAVAILABLE: u8 = 2;
+
+/// Маркер сжатого кадра (первые 8 байт payload PSK-фрейма).
+pub const CMP_MAGIC: &[u8; 8] = b"P2CMP001";
+/// [CMP_MAGIC 8][u8 algo][u32 raw_len]
+const CMP_HDR_LEN: usize = CMP_MAGIC.len() + 1 + 4;
+
+/// Алгоритмы сжатия кадров.
+pub const COMPRESS_NONE: u8 = 0;
+pub const COMPRESS_ZSTD: u8 = 1;
+
+const CTRL_HELLO: u8 = 1;
+const CTRL_WELCOME: u8 = 2;
+const CTRL_KEEPALIVE: u8 = 3;
+const CTRL_ACK: u8 = 4;
+const CTRL_BYE: u8 = 5;
+const CTRL_DIGEST_REQ: u8 = 6;
+const CTRL_DIGEST: u8 = 7;
+const CTRL_RESYNC_REQ: u8 = 8;
+const CTRL_RESYNC_PAGE: u8 = 9;
+const CTRL_RESYNC_END: u8 = 10;
+
+/// HELLO получателя.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CdcHello {
+    pub version: u16,
+    pub caps: u32,
+    /// SINCE_LSN_NONE — нет запроса.
+    pub since_lsn: u64,
+    /// 0 — получатель ещё не привязан к потоку.
+    pub stream_id: u64,
+    pub keepalive_ms: u32,
+    /// Маска алгоритмов, которые получатель умеет распаковывать (бит 1 << algo).
+    pub compress: u8,
+    /// Имя подписчика для именованного курсора на fan-out сервере (wal/cursors.rs).
+    pub subscriber: Option<String>,
+    /// Фильтр потока (None — полный поток).
+    pub filter: Option<CdcFilter>,
+}
+
+/// WELCOME отправителя — итог согласования.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CdcWelcome {
+    pub version: u16,
+    pub caps: u32,
+    pub stream_id: u64,
+    /// Кадры с lsn > start_lsn (или >= при P1_SHIP_SINCE_INCLUSIVE на отправителе).
+    pub start_lsn: u64,
+    /// 0 — keepalive выключен.
+    pub keepalive_ms: u32,
+    /// Алгоритм сжатия кадров отправителя (COMPRESS_NONE — без сжатия).
+    pub compress: u8,
+    /// db_uuid БД-источника (нули — неизвестен: старый отправитель или meta без UUID).
+    pub db_uuid: [u8; 16],
+}
+
+impl CdcWelcome {
+    pub fn has(&self, cap: u32) -> bool {
+        self.caps & cap == cap
+    }
+
+    pub fn keepalive(&self) -> Option<Duration> {
+        (self.has(CAP_KEEPALIVE) && self.keepalive_ms > 0)
+            .then(|| Duration::from_millis(self.keepalive_ms as u64))
+    }
+}
+
+/// Управляющее сообщение протокола v2.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CtrlMsg {
+    Hello(CdcHello),
+    Welcome(CdcWelcome),
+    Keepalive {
+        last_lsn: u64,
+    },
+    Ack {
+        applied_lsn: u64,
+    },
+    Bye {
+        last_lsn: u64,
+    },
+    /// Запрос digest'ов бакетов на LSN (живость TTL — на момент now).
+    DigestRequest {
+        lsn: u64,
+        now: u32,
+        buckets: Vec<u32>,
+    },
+    /// Ответ на DigestRequest (digests пуст при status != DIGEST_OK).
+    Digest {
+        lsn: u64,
+        status: u8,
+        digests: Vec<BucketDigest>,
+    },
+    /// Запрос состояния бакета на LSN (digest — на момент now).
+    ResyncRequest {
+        lsn: u64,
+        now: u32,
+        bucket: u32,
+    },
+    /// Образ одной страницы состояния бакета (до RESYNC_END).
+    ResyncPage {
+        page_id: u64,
+        image: Vec<u8>,
+    },
+    /// Конец состояния бакета: pages — число отправленных RESYNC_PAGE (0 при status != DIGEST_OK).
+    ResyncEnd {
+        lsn: u64,
+        status: u8,
+        bucket: u32,
+        head: u64,
+        pages: u32,
+        digest: BucketDigest,
+    },
+    /// Неизвестный вид (более новый peer) — пропускается.
+    Unknown(u8),
+}
+
+impl CtrlMsg {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(40);
+        out.extend_from_slice(CTRL_MAGIC);
+        let put16 = |out: &mut Vec<u8>, v: u16| out.extend_from_slice(&v.to_le_bytes());
+        let put32 = |out: &mut Vec<u8>, v: u32| out.extend_from_slice(&v.to_le_bytes());
+        let put64 = |out: &mut Vec<u8>, v: u64| out.extend_from_slice(&v.to_le_bytes());
+        match self {
+            CtrlMsg::Hello(h) => {
+                out.push(CTRL_HELLO);
+                put16(&mut out, h.version);
+                put32(&mut out, h.caps);
+                put64(&mut out, h.since_lsn);
+                put64(&mut out, h.stream_id);
+                put32(&mut out, h.keepalive_ms);
+                out.push(h.compress);
+                // [u8 len][имя] (0 — без имени)
+                let name = h.subscriber.as_deref().unwrap_or("").as_bytes();
+                let name = &name[..name.len().min(SUBSCRIBER_NAME_MAX)];
+                out.push(name.len() as u8);
+                out.extend_from_slice(name);
+                // [u8 has_filter][фильтр]
+                match &h.filter {
+                    Some(f) => {
+                        out.push(1);
+                        f.encode(&mut out);
+                    }
+                    None => out.push(0),
+                }
+            }
+            CtrlMsg::Welcome(w) => {
+                out.push(CTRL_WELCOME);
+                put16(&mut out, w.version);
+                put32(&mut out, w.caps);
+                put64(&mut out, w.stream_id);
+                put64(&mut out, w.start_lsn);
+                put32(&mut out, w.keepalive_ms);
+                out.push(w.compress);
+                out.extend_from_slice(&w.db_uuid);
+            }
+            CtrlMsg::Keepalive { last_lsn } => {
+                out.push(CTRL_KEEPALIVE);
+                put64(&mut out, *last_lsn);
+            }
+            CtrlMsg::Ack { applied_lsn } => {
+                out.push(CTRL_ACK);
+                put64(&mut out, *applied_lsn);
+            }
+            CtrlMsg::Bye { last_lsn } => {
+                out.push(CTRL_BYE);
+                put64(&mut out, *last_lsn);
+            }
+            CtrlMsg::DigestRequest { lsn, now, buckets } => {
+                out.push(CTRL_DIGEST_REQ);
+                put64(&mut out, *lsn);
+                put32(&mut out, *now);
+                let n = buckets.len().min(DIGEST_MAX_BUCKETS);
+                put16(&mut out, n as u16);
+                for &b in &buckets[..n] {
+                    put32(&mut out, b);
+                }
+            }
+            CtrlMsg::Digest {
+                lsn,
+                status,
+                digests,
+            } => {
+                out.push(CTRL_DIGEST);
+                put64(&mut out, *lsn);
+                out.push(*status);
+                let n = digests.len().min(DIGEST_MAX_BUCKETS);
+                put16(&mut out, n as u16);
+                for d in &digests[..n] {
+                    put32(&mut out, d.bucket);
+                    put64(&mut out, d.live_keys);
+                    put64(&mut out, d.hash);
+                }
+            }
+            CtrlMsg::ResyncRequest { lsn, now, bucket } => {
+                out.push(CTRL_RESYNC_REQ);
+                put64(&mut out, *lsn);
+                put32(&mut out, *now);
+                put32(&mut out, *bucket);
+            }
+            CtrlMsg::ResyncPage { page_id, image } => {
+                out.reserve(12 + image.len());
+                out.push(CTRL_RESYNC_PAGE);
+                put64(&mut out, *page_id);
+                put32(&mut out, image.len() as u32);
+                out.extend_from_slice(image);
+            }
+            CtrlMsg::ResyncEnd {
+                lsn,
+                status,
+                bucket,
+                head,
+                pages,
+                digest,
+            } => {
+                out.push(CTRL_RESYNC_END);
+                put64(&mut out, *lsn);
+                out.push(*status);
+                put32(&mut out, *bucket);
+                put64(&mut out, *head);
+                put32(&mut out, *pages);
+                put64(&mut out, digest.live_keys);
+                put64(&mut out, digest.hash);
+            }
+            CtrlMsg::Unknown(kind) => out.push(*kind),
+        }
+        out
+    }
+
+    /// Состояние бакета одной сессией resync: RESYNC_PAGE на каждую страницу, затем RESYNC_END.
+    pub fn resync_frames(state: BucketState) -> Vec<CtrlMsg> {
+        let end = CtrlMsg::ResyncEnd {
+            lsn: state.lsn,
+            status: DIGEST_OK,
+            bucket: state.bucket,
+            head: state.head,
+            pages: state.pages.len() as u32,
+            digest: state.digest,
+        };
+        let mut out: Vec<CtrlMsg> = state
+            .pages
+            .into_iter()
+            .map(|(page_id, image)| CtrlMsg::ResyncPage { page_id, image })
+            .collect();
+        out.push(end);
+        out
+    }
+
+    /// Разобрать payload. Ok(None) — это не управляющее сообщение (WAL-кадр / v1 HELLO).
+    /// Лишние байты в конце тела игнорируются (расширения новых версий).
+    pub fn decode(payload: &[u8]) -> Result<Option<CtrlMsg>> {
+        if !is_ctrl_payload(payload) {
+            return Ok(None);
+        }
+        let kind = payload[CTRL_MAGIC.len()];
+        let body = &payload[CTRL_MAGIC.len() + 1..];
+        let need = |n: usize| -> Result<()> {
+            if body.len() < n {
+                return Err(anyhow!(
+                    "CDC: short control message kind={} ({} < {} bytes)",
+                    kind,
+                    body.len(),
+                    n
+                ));
+            }
+            Ok(())
+        };
+        let r16 = |o: usize| LittleEndian::read_u16(&body[o..o + 2]);
+        let r32 = |o: usize| LittleEndian::read_u32(&body[o..o + 4]);
+        let r64 = |o: usize| LittleEndian::read_u64(&body[o..o + 8]);
+        // поле, добавленное позже: у более старого peer'а его нет
+        let r8_or0 = |o: usize| body.get(o).copied().unwrap_or(0);
+        let msg = match kind {
+            CTRL_HELLO => {
+                need(26)?;
+                let subscriber = read_name(body, 27)?;
+                let filter_off = 28 + subscriber.as_ref().map(|n| n.len()).unwrap_or(0);
+                let filter = match body.get(filter_off) {
+                    Some(1) => Some(CdcFilter::decode(&body[filter_off + 1..])?.0),
+                    _ => None,
+                };
+                CtrlMsg::Hello(CdcHello {
+                    version: r16(0),
+                    caps: r32(2),
+                    since_lsn: r64(6),
+                    stream_id: r64(14),
+                    keepalive_ms: r32(22),
+                    compress: r8_or0(26),
+                    subscriber,
+                    filter,
+                })
+            }
+            CTRL_WELCOME => {
+                need(26)?;
+                CtrlMsg::Welcome(CdcWelcome {
+                    version: r16(0),
+                    caps: r32(2),
+                    stream_id: r64(6),
+                    start_lsn: r64(14),
+                    keepalive_ms: r32(22),
+                    compress: r8_or0(26),
+                    db_uuid: body
+                        .get(27..43)
+                        .and_then(|b| b.try_into().ok())
+                        .unwrap_or([0u8; 16]),
+                })
+            }
+            CTRL_KEEPALIVE => {
+                need(8)?;
+                CtrlMsg::Keepalive { last_lsn: r64(0) }
+            }
+            CTRL_ACK => {
+                need(8)?;
+                CtrlMsg::Ack {
+                    applied_lsn: r64(0),
+                }
+            }
+            CTRL_BYE => {
+                need(8)?;
+                CtrlMsg::Bye { last_lsn: r64(0) }
+            }
+            CTRL_DIGEST_REQ => {
+                need(14)?;
+                let n = r16(12) as usize;
+                need(14 + n * 4)?;
+                CtrlMsg::DigestRequest {
+                    lsn: r64(0),
+                    now: r32(8),
+                    buckets: (0..n).map(|i| r32(14 + i * 4)).collect(),
+                }
+            }
+            CTRL_DIGEST => {
+                need(11)?;
+                let n = r16(9) as usize;
+                need(11 + n * 20)?;
+                CtrlMsg::Digest {
+                    lsn: r64(0),
+                    status: body[8],
+                    digests: (0..n)
+                        .map(|i| {
+                            let o = 11 + i * 20;
+                            BucketDigest {
+                                bucket: r32(o),
+                                live_keys: r64(o + 4),
+                                hash: r64(o + 12),
+                            }
+                        })
+                        .collect(),
+                }
+            }
+            CTRL_RESYNC_REQ => {
+                need(16)?;
+                CtrlMsg::ResyncRequest {
+                    lsn: r64(0),
+                    now: r32(8),
+                    bucket: r32(12),
+                }
+            }
+            CTRL_RESYNC_PAGE => {
+                need(12)?;
+                let n = r32(8) as usize;
+                need(12 + n)?;
+                CtrlMsg::ResyncPage {
+                    page_id: r64(0),
+                    image: body[12..12 + n].to_vec(),
+                }
+            }
+            CTRL_RESYNC_END => {
+                need(41)?;
+                let bucket = r32(9);
+                CtrlMsg::ResyncEnd {
+                    lsn: r64(0),
+                    status: body[8],
+                    bucket,
+                    head: r64(13),
+                    pages: r32(21),
+                    digest: BucketDigest {
+                        bucket,
+                        live_keys: r64(25),
+                        hash: r64(33),
+                    },
+                }
+            }
+            other => CtrlMsg::Unknown(other),
+        };
+        Ok(Some(msg))
+    }
+}
+
+/// Имя `[u8 len][bytes]` по смещению `off` (нет поля / длина 0 — None).
+fn read_name(body: &[u8], off: usize) -> Result<Option<String>> {
+    let Some(&len) = body.get(off) else {
+        return Ok(None);
+    };
+    if len == 0 {
+        return Ok(None);
+    }
+    let raw = body
+        .get(off + 1..off + 1 + len as usize)
+        .ok_or_else(|| anyhow!("CDC: short subscriber name in HELLO"))?;
+    let name =
+        std::str::from_utf8(raw).map_err(|_| anyhow!("CDC: subscriber name is not UTF-8"))?;
+    Ok(Some(name.to_string()))
+}
+
+/// Payload — управляющее сообщение v2.
+pub fn is_ctrl_payload(payload: &[u8]) -> bool {
+    payload.len() > CTRL_MAGIC.len() && &payload[..CTRL_MAGIC.len()] == CTRL_MAGIC
+}
+
+/// Записать управляющее сообщение PSK-фреймом.
+pub fn write_ctrl_psk<W: Write>(w: &mut W, seq: u64, msg: &CtrlMsg, psk: &[u8]) -> Result<()> {
+    write_framed_psk(w, seq, &msg.encode(), psk)?;
+    w.flush().context("flush CDC control frame")?;
+    Ok(())
+}
+
+/// HELLO получателя с параметрами из ENV.
+pub fn hello_from_env(since_lsn: u64, stream_id: u64) -> CdcHello {
+    CdcHello {
+        version: cdc_proto_from_env(),
+        caps: local_caps(),
+        since_lsn,
+        stream_id,
+        keepalive_ms: keepalive_ms_from_env(),
+        compress: compression_from_env().algo_mask(),
+        subscriber: subscriber_from_env(),
+        filter: None,
+    }
+}
+
+/// Отправитель: итог согласования по HELLO получателя (db_uuid источника заполняет вызывающий).
+/// Версия — минимальная, capabilities — пересечение, keepalive — меньший ненулевой
+/// (0 у любой стороны — выключен), сжатие — алгоритм отправителя, если получатель его умеет.
+pub fn negotiate(hello: &CdcHello, stream_id: u64, start_lsn: u64) -> CdcWelcome {
+    let local_ka = keepalive_ms_from_env();
+    let keepalive_ms = if local_ka == 0 || hello.keepalive_ms == 0 {
+        0
+    } else {
+        local_ka.min(hello.keepalive_ms)
+    };
+    let caps = hello.caps & local_caps();
+    let algo = compression_from_env().algo;
+    let compress =
+        if caps & CAP_COMPRESS != 0 && algo != COMPRESS_NONE && hello.compress & (1 << algo) != 0 {
+            algo
+        } else {
+            COMPRESS_NONE
+        };
+    CdcWelcome {
+        version: hello.version.min(cdc_proto_from_env()),
+        caps,
+        stream_id,
+        start_lsn,
+        keepalive_ms,
+        compress,
+        db_uuid: [0u8; 16],
+    }
+}
+
+/// Настройки сжатия кадров (P1_CDC_COMPRESS / P1_CDC_COMPRESS_MIN).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameCompression {
+    pub algo: u8,
+    /// Уровень zstd (0 — по умолчанию библиотеки).
+    pub level: i32,
+    /// Кадры короче не сжимаются.
+    pub min_len: usize,
+}
+
+impl FrameCompression {
+    /// Маска алгоритмов для HELLO: распаковывать умеем всё, что знаем, если сжатие не выключено.
+    pub fn algo_mask(&self) -> u8 {
+        if self.algo == COMPRESS_NONE {
+            0
+        } else {
+            1 << COMPRESS_ZSTD
+        }
+    }
+
+    /// Сжатый кадр (envelope) или исходный payload, если сжатие не выгодно/выключено.
+    pub fn encode<'a>(&self, payload: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        if self.algo != COMPRESS_ZSTD || payload.len() < self.min_len.max(CMP_HDR_LEN + 1) {
+            return Ok(Cow::Borrowed(payload));
+        }
+        let packed =
+            zstd::bulk::compress(payload, self.level).context("zstd compress CDC frame")?;
+        if packed.len() + CMP_HDR_LEN >= payload.len() {
+            return Ok(Cow::Borrowed(payload));
+        }
+        let mut out = Vec::with_capacity(CMP_HDR_LEN + packed.len());
+        out.extend_from_slice(CMP_MAGIC);
+        out.push(COMPRESS_ZSTD);
+        out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        out.extend_from_slice(&packed);
+        Ok(Cow::Owned(out))
+    }
+}
+
+/// Имя алгоритма для отчётов.
+pub fn compress_name(algo: u8) -> &'static str {
+    match algo {
+        COMPRESS_NONE => "none",
+        COMPRESS_ZSTD => "zstd",
+        _ => "unknown",
+    }
+}
+
+/// Payload — сжатый кадр.
+pub fn is_compressed_payload(payload: &[u8]) -> bool {
+    payload.len() >= CMP_HDR_LEN && &payload[..CMP_MAGIC.len()] == CMP_MAGIC
+}
+
+/// Распаковать кадр, если он сжат. `algo` — согласованный в WELCOME алгоритм,
+/// `max_len` — предел размера распакованного кадра.
+pub fn decode_frame_payload(payload: Vec<u8>, algo: u8, max_len: usize) -> Result<Vec<u8>> {
+    if !is_compressed_payload(&payload) {
+        return Ok(payload);
+    }
+    let got = payload[CMP_MAGIC.len()];
+    if algo == COMPRESS_NONE || got != algo {
+        return Err(anyhow!(
+            "CDC: compressed frame (algo={}) but session compression is {}",
+            compress_name(got),
+            compress_name(algo)
+        ));
+    }
+    let raw_len = LittleEndian::read_u32(&payload[CMP_MAGIC.len() + 1..CMP_HDR_LEN]) as usize;
+    if raw_len > max_len {
+        return Err(anyhow!(
+            "CDC: compressed frame too large ({} > {} bytes)",
+            raw_len,
+            max_len
+        ));
+    }
+    let raw = zstd::bulk::decompress(&payload[CMP_HDR_LEN..], raw_len)
+        .context("zstd decode of CDC frame")?;
+    if raw.len() != raw_len {
+        return Err(anyhow!(
+            "CDC: compressed frame length mismatch ({} vs {})",
+            raw.len(),
+            raw_len
+        ));
+    }
+    Ok(raw)
+}
+
+/// Результат ожидания фрейма с таймаутом.
+#[derive(Debug)]
+pub enum FrameWait {
+    Frame(u64, Vec<u8>),
+    Eof,
+    Timeout,
+}
+
+/// Прочитать следующий PSK-фрейм, ожидая не дольше `timeout`. После чтения сокет снова блокирующий.
+pub fn read_next_framed_psk_timeout(
+    s: &mut IoStream,
+    psk: &[u8],
+    max_len: usize,
+    timeout: Duration,
+) -> Result<FrameWait> {
+    s.set_read_timeout(Some(timeout.max(Duration::from_millis(1))))
+        .context("set CDC read timeout")?;
+    let res = read_next_framed_psk(s, psk, max_len);
+    let _ = s.set_read_timeout(None);
+    match res {
+        Ok(Some((seq, payload))) => Ok(FrameWait::Frame(seq, payload)),
+        Ok(None) => Ok(FrameWait::Eof),
+        Err(e) if is_timeout(&e) => Ok(FrameWait::Timeout),
+        Err(e) => Err(e),
+    }
+}
+
+/// Ошибка — таймаут чтения сокета.
+pub fn is_timeout(e: &anyhow::Error) -> bool {
+    e.chain().any(|c| {
+        c.downcast_ref::<std::io::Error>()
+            .map(|io| {
+                matches!(
+                    io.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                )
+            })
+            .unwrap_or(false)
+    })
+}
+
+/// P1_CDC_PROTO (1|2; по умолчанию текущая версия).
+pub fn cdc_proto_from_env() -> u16 {
+    std::env::var("P1_CDC_PROTO")
+        .ok()
+        .and_then(|s| s.trim().parse::<u16>().ok())
+        .map(|v| v.clamp(1, CDC_PROTO_VERSION))
+        .unwrap_or(CDC_PROTO_VERSION)
+}
+
+/// P1_CDC_KEEPALIVE_MS (по умолчанию 5000; 0 — выкл.).
+pub fn keepalive_ms_from_env() -> u32 {
+    std::env::var("P1_CDC_KEEPALIVE_MS")
+        .ok()
+        .and_then(|s| s.trim().parse::<u32>().ok())
+        .unwrap_or(5000)
+}
+
+/// P1_CDC_COMPRESS (none|zstd[:level], по умолчанию zstd) и P1_CDC_COMPRESS_MIN (по умолчанию 512).
+/// Некорректное значение — без сжатия.
+pub fn compression_from_env() -> FrameCompression {
+    let min_len = std::env::var("P1_CDC_COMPRESS_MIN")
+        .ok()
+        .and_then(|s| s.trim().parse::<usize>().ok())
+        .unwrap_or(512);
+    let spec = std::env::var("P1_CDC_COMPRESS").unwrap_or_else(|_| "zstd".to_string());
+    let (algo, level) = match spec.parse::<BackupCompression>() {
+        Ok(BackupCompression::Zstd(level)) => (COMPRESS_ZSTD, level),
+        _ => (COMPRESS_NONE, 0),
+    };
+    FrameCompression {
+        algo,
+        level,
+        min_len,
+    }
+}
+
+/// P1_CDC_SUBSCRIBER — имя подписчика для именованного курсора (пусто — без имени).
+pub fn subscriber_from_env() -> Option<String> {
+    std::env::var("P1_CDC_SUBSCRIBER")
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// P1_CDC_RESUME (по умолчанию включено; 0|false|no|off — HELLO без since_lsn).
+pub fn resume_from_env() -> bool {
+    std::env::var("P1_CDC_RESUME")
+        .ok()
+        .map(|s| s.trim().to_ascii_lowercase())
+        .map(|s| !(s == "0" || s == "false" || s == "no" || s == "off"))
+        .unwrap_or(true)
+}
+
+/// P1_CDC_VERIFY_SAMPLE — бакетов на одну сверку digest'ов (0 — verify выключен; по умолчанию 0).
+pub fn verify_sample_from_env() -> usize {
+    std::env::var("P1_CDC_VERIFY_SAMPLE")
+        .ok()
+        .and_then(|s| s.trim().parse::<usize>().ok())
+        .map(|n| n.min(DIGEST_MAX_BUCKETS))
+        .unwrap_or(0)
+}
+
+/// P1_CDC_RESYNC — follower пересинхронизирует разошедшиеся при verify бакеты (по умолчанию выкл.).
+pub fn resync_from_env() -> bool {
+    std::env::var("P1_CDC_RESYNC")
+        .ok()
+        .map(|s| s.trim().to_ascii_lowercase())
+        .map(|s| s == "1" || s == "true" || s == "yes" || s == "on")
+        .unwrap_or(false)
+}
+
+/// P1_CDC_HELLO_WAIT_MS (по умолчанию 2000).
+pub fn hello_wait_from_env() -> Duration {
+    let ms = std::env::var("P1_CDC_HELLO_WAIT_MS")
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .unwrap_or(2000);
+    Duration::from_millis(ms)
+}
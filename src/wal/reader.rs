@@ -6,7 +6,7 @@
 //! - Глобальная обёртка read_next_record(..) и thread‑local reader удалены.
 //!
 //! Поведение:
-//! - Валидирует CRC32C по header[0..crc) + payload.
+//! - Валидирует CRC32C по header[0..crc) + payload (разбор заголовка — quiverdb_format::wal).
 //! - Частичный хвост (неполный заголовок/полезная нагрузка) → Ok(None) как EOF.
 //! - Mid‑stream WAL header ("P2WAL001" + reserved 8 байт) пропускается ТОЛЬКО если предыдущая запись была TRUNCATE.
 //!
@@ -19,18 +19,16 @@
 //!   }
 
 use anyhow::{anyhow, Result};
-use byteorder::{ByteOrder, LittleEndian};
+use quiverdb_format::wal::WalFrameHeader;
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
 
 use super::{
-    crc32c_of_parts, WAL_HDR_SIZE, WAL_MAGIC, WAL_REC_HDR_SIZE, WAL_REC_OFF_CRC32,
-    WAL_REC_OFF_FLAGS, WAL_REC_OFF_LEN, WAL_REC_OFF_LSN, WAL_REC_OFF_PAGE_ID, WAL_REC_OFF_TYPE,
-    WAL_REC_TRUNCATE,
+    crc32c_of_parts, WAL_HDR_SIZE, WAL_MAGIC, WAL_REC_HDR_SIZE, WAL_REC_OFF_CRC32, WAL_REC_TRUNCATE,
 };
 
 /// Одна запись WAL, считанная с диска.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct WalRecord {
     pub rec_type: u8,
     pub flags: u8,
@@ -133,9 +131,9 @@ impl WalStreamReader {
             return Err(anyhow!("wal read header: {}", e));
         }
 
-        let payload_len =
-            LittleEndian::read_u32(&rhdr[WAL_REC_OFF_LEN..WAL_REC_OFF_LEN + 4]) as usize;
-        let total = WAL_REC_HDR_SIZE as u64 + payload_len as u64;
+        let h = WalFrameHeader::decode(&rhdr)?;
+        let payload_len = h.payload_len as usize;
+        let total = h.frame_len();
         let next_pos = pos + total;
 
         if next_pos > file_len {
@@ -152,7 +150,7 @@ impl WalStreamReader {
         }
 
         // CRC32C заголовок[0..crc) + payload
-        let stored_crc = LittleEndian::read_u32(&rhdr[WAL_REC_OFF_CRC32..WAL_REC_OFF_CRC32 + 4]);
+        let stored_crc = h.crc32c;
         let calc_crc = crc32c_of_parts(&rhdr[..WAL_REC_OFF_CRC32], &payload);
         if stored_crc != calc_crc {
             return Err(anyhow!(
@@ -164,12 +162,12 @@ impl WalStreamReader {
         }
 
         // Сформируем запись
-        let rec_type = rhdr[WAL_REC_OFF_TYPE];
+        let rec_type = h.rec_type;
         let rec = WalRecord {
             rec_type,
-            flags: rhdr[WAL_REC_OFF_FLAGS],
-            lsn: LittleEndian::read_u64(&rhdr[WAL_REC_OFF_LSN..WAL_REC_OFF_LSN + 8]),
-            page_id: LittleEndian::read_u64(&rhdr[WAL_REC_OFF_PAGE_ID..WAL_REC_OFF_PAGE_ID + 8]),
+            flags: h.flags,
+            lsn: h.lsn,
+            page_id: h.page_id,
             payload,
             pos,
             len_total: total,
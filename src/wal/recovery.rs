@@ -0,0 +1,251 @@
+//! wal/recovery — прогресс open-time восстановления и индекс границ батчей для fast-skip.
+//!
+//! Прогресс:
+//! - RecoveryProgress — снимок состояния реплея (кадры, байты, текущий LSN, ETA).
+//! - RecoveryProgressHook — callback, передаётся через QuiverConfig::recovery_progress
+//!   (DbBuilder::recovery_progress) или процессный дефолт set_default_recovery_progress
+//!   (его ставит CLI, чтобы печатать прогресс в stderr).
+//! - Callback вызывается только при реальном реплее (clean_shutdown=false): не чаще раза
+//!   в ~250 мс, на каждом чекпоинте индекса и финально с done=true.
+//!
+//! Fast-skip (индекс границ батчей, <root>/.wal_replay.idx):
+//! - Во время реплея после COMMIT, если с прошлого чекпоинта прочитано >= P1_REPLAY_CKPT_MB
+//!   (по умолчанию 1 MiB; 0 — выключить), реплей фиксирует meta.last_lsn = LSN батча и
+//!   дописывает в индекс границу (offset следующего кадра, lsn, page_id_end).
+//!   Страницы реплея пишутся с fsync данных (pager реплея открыт с data_fsync=true),
+//!   поэтому префикс до границы уже долговечен.
+//! - Прерванный реплей (kill/краш/паника) при следующем открытии стартует с последней
+//!   границы, чей lsn <= meta.last_lsn, — если индекс принадлежит этому же WAL
+//!   (совпадают stream_id и LSN первого кадра) и кадр по offset читается.
+//! - Успешный реплей удаляет индекс вместе с усечением WAL.
+//!
+//! Формат индекса (LE):
+//!   [magic8 "P2WRIX01"][u64 stream_id][u64 first_lsn][u32 count][u32 reserved=0]
+//!   [count × (u64 offset, u64 lsn, u64 page_id_end)][u32 crc32c(всё предыдущее)]
+
+use anyhow::{anyhow, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+pub const WAL_REPLAY_INDEX_FILE: &str = ".wal_replay.idx";
+const REPLAY_INDEX_MAGIC: &[u8; 8] = b"P2WRIX01";
+const REPLAY_INDEX_HDR: usize = 32;
+const REPLAY_INDEX_ENTRY: usize = 24;
+
+// -------------------- progress --------------------
+
+/// Снимок прогресса open-time восстановления.
+#[derive(Debug, Clone, Default)]
+pub struct RecoveryProgress {
+    /// Обработано кадров WAL (без пропущенного префикса).
+    pub frames_applied: u64,
+    /// Из них PAGE_IMAGE.
+    pub pages_applied: u64,
+    /// Позиция в WAL (байты, включая пропущенный префикс).
+    pub bytes_done: u64,
+    /// Длина WAL на момент старта реплея.
+    pub bytes_total: u64,
+    /// Сколько байт префикса пропущено по индексу границ батчей.
+    pub bytes_skipped: u64,
+    /// Максимальный встреченный LSN.
+    pub current_lsn: u64,
+    /// LSN последнего чекпоинта индекса (0 — не было).
+    pub checkpoint_lsn: u64,
+    /// Верхняя граница page_id+1 по всем PAGE_IMAGE (включая пропущенный префикс).
+    pub page_id_end: u64,
+    pub elapsed_ms: u64,
+    /// Оценка оставшегося времени (None — пока нечего оценивать).
+    pub eta_ms: Option<u64>,
+    pub done: bool,
+}
+
+impl RecoveryProgress {
+    /// Доля выполненного (0.0..=1.0).
+    pub fn fraction(&self) -> f64 {
+        if self.bytes_total == 0 {
+            1.0
+        } else {
+            (self.bytes_done as f64 / self.bytes_total as f64).min(1.0)
+        }
+    }
+
+    pub(crate) fn update_eta(&mut self) {
+        let processed = self.bytes_done.saturating_sub(self.bytes_skipped);
+        if processed == 0 || self.elapsed_ms == 0 {
+            self.eta_ms = None;
+            return;
+        }
+        let left = self.bytes_total.saturating_sub(self.bytes_done);
+        self.eta_ms = Some((left as f64 * self.elapsed_ms as f64 / processed as f64) as u64);
+    }
+}
+
+/// Callback прогресса восстановления.
+#[derive(Clone)]
+pub struct RecoveryProgressHook(Arc<dyn Fn(&RecoveryProgress) + Send + Sync>);
+
+impl RecoveryProgressHook {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&RecoveryProgress) + Send + Sync + 'static,
+    {
+        Self(Arc::new(f))
+    }
+
+    #[inline]
+    pub fn call(&self, p: &RecoveryProgress) {
+        (self.0)(p)
+    }
+}
+
+impl fmt::Debug for RecoveryProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RecoveryProgressHook(..)")
+    }
+}
+
+fn default_hook_slot() -> &'static Mutex<Option<RecoveryProgressHook>> {
+    static SLOT: OnceLock<Mutex<Option<RecoveryProgressHook>>> = OnceLock::new();
+    SLOT.get_or_init(|| Mutex::new(None))
+}
+
+/// Установить процессный дефолтный callback (используется, если в конфиге его нет).
+pub fn set_default_recovery_progress(hook: Option<RecoveryProgressHook>) {
+    *default_hook_slot().lock().unwrap() = hook;
+}
+
+/// Текущий процессный дефолтный callback.
+pub fn default_recovery_progress() -> Option<RecoveryProgressHook> {
+    default_hook_slot().lock().unwrap().clone()
+}
+
+/// Шаг чекпоинтов индекса (байты WAL). 0 — индекс не ведётся.
+pub(crate) fn replay_ckpt_bytes() -> u64 {
+    static V: OnceLock<u64> = OnceLock::new();
+    *V.get_or_init(|| {
+        std::env::var("P1_REPLAY_CKPT_MB")
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(1)
+            .saturating_mul(1024 * 1024)
+    })
+}
+
+// -------------------- batch boundary index --------------------
+
+/// Граница батча: offset кадра, следующего за COMMIT, и состояние на этот момент.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayBoundary {
+    pub offset: u64,
+    pub lsn: u64,
+    pub page_id_end: u64,
+}
+
+/// Индекс границ батчей для конкретного WAL (stream_id + LSN первого кадра).
+#[derive(Debug, Clone, Default)]
+pub struct ReplayIndex {
+    pub stream_id: u64,
+    pub first_lsn: u64,
+    pub entries: Vec<ReplayBoundary>,
+}
+
+#[inline]
+pub fn replay_index_path(root: &Path) -> PathBuf {
+    root.join(WAL_REPLAY_INDEX_FILE)
+}
+
+impl ReplayIndex {
+    /// Загрузить индекс. None — файла нет или он повреждён (тогда полный реплей).
+    pub fn load(root: &Path) -> Option<Self> {
+        let buf = std::fs::read(replay_index_path(root)).ok()?;
+        if buf.len() < REPLAY_INDEX_HDR + 4 || &buf[0..8] != REPLAY_INDEX_MAGIC {
+            return None;
+        }
+        let count = LittleEndian::read_u32(&buf[24..28]) as usize;
+        let need = REPLAY_INDEX_HDR + count * REPLAY_INDEX_ENTRY + 4;
+        if buf.len() != need {
+            return None;
+        }
+        let stored = LittleEndian::read_u32(&buf[need - 4..need]);
+        if crc32c::crc32c(&buf[..need - 4]) != stored {
+            return None;
+        }
+        let mut entries = Vec::with_capacity(count);
+        for i in 0..count {
+            let off = REPLAY_INDEX_HDR + i * REPLAY_INDEX_ENTRY;
+            entries.push(ReplayBoundary {
+                offset: LittleEndian::read_u64(&buf[off..off + 8]),
+                lsn: LittleEndian::read_u64(&buf[off + 8..off + 16]),
+                page_id_end: LittleEndian::read_u64(&buf[off + 16..off + 24]),
+            });
+        }
+        Some(Self {
+            stream_id: LittleEndian::read_u64(&buf[8..16]),
+            first_lsn: LittleEndian::read_u64(&buf[16..24]),
+            entries,
+        })
+    }
+
+    /// Сохранить индекс атомарно (tmp + rename, с fsync).
+    pub fn store(&self, root: &Path) -> Result<()> {
+        let mut buf =
+            Vec::with_capacity(REPLAY_INDEX_HDR + self.entries.len() * REPLAY_INDEX_ENTRY + 4);
+        let mut tmp8 = [0u8; 8];
+        let mut tmp4 = [0u8; 4];
+        buf.extend_from_slice(REPLAY_INDEX_MAGIC);
+        LittleEndian::write_u64(&mut tmp8, self.stream_id);
+        buf.extend_from_slice(&tmp8);
+        LittleEndian::write_u64(&mut tmp8, self.first_lsn);
+        buf.extend_from_slice(&tmp8);
+        LittleEndian::write_u32(&mut tmp4, self.entries.len() as u32);
+        buf.extend_from_slice(&tmp4);
+        buf.extend_from_slice(&[0u8; 4]);
+        for e in &self.entries {
+            for v in [e.offset, e.lsn, e.page_id_end] {
+                LittleEndian::write_u64(&mut tmp8, v);
+                buf.extend_from_slice(&tmp8);
+            }
+        }
+        LittleEndian::write_u32(&mut tmp4, crc32c::crc32c(&buf));
+        buf.extend_from_slice(&tmp4);
+
+        let path = replay_index_path(root);
+        let tmp = root.join(format!("{}.tmp", WAL_REPLAY_INDEX_FILE));
+        {
+            let mut f = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&tmp)
+                .with_context(|| format!("open {}", tmp.display()))?;
+            f.write_all(&buf)?;
+            f.sync_all()?;
+        }
+        crate::util::fsx::replace_file(&tmp, &path)
+            .with_context(|| format!("rename {} -> {}", tmp.display(), path.display()))?;
+        Ok(())
+    }
+
+    /// Удалить индекс (после успешного реплея).
+    pub fn remove(root: &Path) -> Result<()> {
+        let p = replay_index_path(root);
+        match std::fs::remove_file(&p) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(anyhow!("remove {}: {}", p.display(), e)),
+        }
+    }
+
+    /// Последняя граница, которую можно пропустить: lsn <= applied_lsn и offset в пределах WAL.
+    pub fn best_skip(&self, applied_lsn: u64, wal_len: u64) -> Option<ReplayBoundary> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|e| e.lsn <= applied_lsn && e.offset <= wal_len)
+            .copied()
+    }
+}
@@ -16,6 +16,8 @@
 //! Публичный API (для использования из writer.rs):
 //! - get_or_create_wal_inner(root) -> Arc<WalInner>
 //! - set_group_coalesce_ms(root, ms) — установить окно коалессации fsync.
+//! - NEW: set_group_adaptive_coalesce(root, ..) / group_coalesce_status(root) — адаптивное окно
+//!   (wal/coalesce.rs): контроллер живёт на WalInner и общий для всех хэндлов файла.
 
 use anyhow::{Context, Result};
 use std::fs::OpenOptions;
@@ -24,6 +26,7 @@ use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Condvar, Mutex, OnceLock};
 
+use super::coalesce::{AdaptiveCoalesce, CoalesceStatus};
 use super::{
     generate_stream_id, wal_header_read_stream_id, wal_path, write_wal_file_header_with_stream_id,
     WAL_HDR_SIZE, WAL_MAGIC,
@@ -49,6 +52,9 @@ pub struct WalInner {
     // Окно коалессации fsync (мс): обновляется через set_group_coalesce_ms(...)
     pub coalesce_ms: AtomicU64,
 
+    // NEW: адаптивное окно (None — статическое coalesce_ms)
+    pub adaptive: Mutex<Option<AdaptiveCoalesce>>,
+
     // Счётчик PAGE_IMAGE с момента последнего fsync (группа).
     pub pages_since_last_fsync: AtomicU64,
 
@@ -57,10 +63,13 @@ pub struct WalInner {
 
     // NEW: Уникальный идентификатор WAL‑потока (записан в header).
     pub stream_id: u64,
+
+    // NEW: путь файла WAL (для диагностики медленного IO)
+    pub path: PathBuf,
 }
 
 impl WalInner {
-    fn new(mut file: std::fs::File) -> Result<Self> {
+    fn new(mut file: std::fs::File, path: PathBuf) -> Result<Self> {
         // Убедимся, что валидный заголовок присутствует и получить/установить stream_id.
         let len = file.metadata()?.len();
 
@@ -109,9 +118,11 @@ impl WalInner {
             }),
             cv: Condvar::new(),
             coalesce_ms: AtomicU64::new(0),
+            adaptive: Mutex::new(None),
             pages_since_last_fsync: AtomicU64::new(0),
             bytes_since_last_fsync: AtomicU64::new(0),
             stream_id,
+            path,
         })
     }
 
@@ -120,6 +131,23 @@ impl WalInner {
         self.coalesce_ms.store(ms, Ordering::Relaxed);
     }
 
+    /// Включить адаптивное окно (budget_us — целевой p99 коммита, max_window_us — потолок)
+    /// или выключить (None). Контроллер с теми же параметрами сохраняет накопленное состояние.
+    pub fn set_adaptive(&self, params: Option<(u64, u64)>) {
+        let mut a = self.adaptive.lock().unwrap();
+        match params {
+            None => *a = None,
+            Some((budget_us, max_window_us)) => {
+                let same = a.as_ref().is_some_and(|c| {
+                    c.budget_us() == budget_us.max(1) && c.max_window_us() == max_window_us
+                });
+                if !same {
+                    *a = Some(AdaptiveCoalesce::new(budget_us, max_window_us));
+                }
+            }
+        }
+    }
+
     /// Получить stream_id WAL.
     pub fn get_stream_id(&self) -> u64 {
         self.stream_id
@@ -149,7 +177,7 @@ impl WalRegistry {
             .open(&path)
             .with_context(|| format!("open wal {}", path.display()))?;
         // Внутри WalInner::new проверим/запишем заголовок, установим stream_id и позиционируемся в конец
-        let inner = Arc::new(WalInner::new(f)?);
+        let inner = Arc::new(WalInner::new(f, path.clone())?);
         self.map.insert(path, inner.clone());
         Ok(inner)
     }
@@ -176,3 +204,21 @@ pub fn set_group_coalesce_ms(root: &Path, ms: u64) -> Result<()> {
     inner.set_coalesce_ms(ms);
     Ok(())
 }
+
+/// Включить/выключить адаптивное окно коалессации (глобально для файла в root).
+pub fn set_group_adaptive_coalesce(root: &Path, params: Option<(u64, u64)>) -> Result<()> {
+    let path = wal_path(root);
+    let mut reg = registry_lock().lock().unwrap();
+    let inner = reg.get_or_create(path)?;
+    inner.set_adaptive(params);
+    Ok(())
+}
+
+/// Состояние адаптивного окна WAL-файла в root (None — адаптивный режим выключен).
+pub fn group_coalesce_status(root: &Path) -> Result<Option<CoalesceStatus>> {
+    let path = wal_path(root);
+    let mut reg = registry_lock().lock().unwrap();
+    let inner = reg.get_or_create(path)?;
+    let a = inner.adaptive.lock().unwrap();
+    Ok(a.as_ref().map(|c| c.status()))
+}
@@ -9,23 +9,42 @@
 //!   (проверка CRC/partial tails — внутри reader).
 //! - HEADS_UPDATE гейтится по LSN: применяется только если wal_lsn > last_heads_lsn,
 //!   маркер хранится в <root>/.heads_lsn.bin (см. wal::state).
-//! - Этот модуль содержит только wal_replay_if_any(..).
+//! - Этот модуль содержит wal_replay_if_any(..) и wal_replay_with_progress(..).
 //!   Метод Pager::wal_replay_with_pager находится в src/pager/replay.rs.
+//!
+//! NEW: прогресс восстановления и fast-skip по индексу границ батчей (см. wal/recovery.rs).
+//! NEW: токены батчей с кадром IDEMPOTENCY дописываются в кольцо токенов на их COMMIT
+//!      (см. wal/idempotency.rs).
+//! NEW: wal_replay_tracked дополнительно сообщает, что покрыл реплей (ReplayTouched: LSN
+//!      первого прочитанного кадра и max LSN HEADS_UPDATE по бакетам) — по нему writer
+//!      доводит bloom.bin до свежего после краха (db/bloom_refresh.rs).
 
 use anyhow::{anyhow, Context, Result};
 use byteorder::{ByteOrder, LittleEndian};
+use std::collections::BTreeMap;
 use std::fs::OpenOptions;
 use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::metrics::{record_recovery_frames, record_recovery_replay, record_recovery_skipped};
 
 use super::{
     wal_path, write_wal_file_header, WAL_HDR_SIZE, WAL_MAGIC, WAL_REC_BEGIN, WAL_REC_COMMIT,
-    WAL_REC_HEADS_UPDATE, WAL_REC_PAGE_DELTA, WAL_REC_PAGE_IMAGE, WAL_REC_TRUNCATE,
+    WAL_REC_HEADS_UPDATE, WAL_REC_IDEMPOTENCY, WAL_REC_PAGE_DELTA, WAL_REC_PAGE_IMAGE,
+    WAL_REC_TRUNCATE,
 };
 // NEW: stateful stream reader
 use super::reader::WalStreamReader;
 // NEW: персистентное состояние для LSN-гейтинга HEADS_UPDATE
 use super::state::{load_last_heads_lsn, store_last_heads_lsn};
+// NEW: прогресс восстановления + индекс границ батчей
+use super::recovery::{
+    replay_ckpt_bytes, RecoveryProgress, RecoveryProgressHook, ReplayBoundary, ReplayIndex,
+};
+
+/// Минимальный интервал между вызовами callback'а прогресса.
+const PROGRESS_EVERY: Duration = Duration::from_millis(250);
 
 /// Реплей WAL v2 c CRC32C и LSN‑гейтингом (внутри apply_page).
 ///
@@ -36,7 +55,49 @@ use super::state::{load_last_heads_lsn, store_last_heads_lsn};
 /// - PAGE_IMAGE -> apply_page(lsn, page_id, payload).
 /// - HEADS_UPDATE -> применить set_heads_bulk к каталогу ТОЛЬКО если wal_lsn > last_heads_lsn; затем обновить last_heads_lsn.
 /// - По завершении — truncate до заголовка, meta.last_lsn=max, clean_shutdown=true.
-pub fn wal_replay_if_any<F>(root: &Path, mut apply_page: F) -> Result<()>
+pub fn wal_replay_if_any<F>(root: &Path, apply_page: F) -> Result<()>
+where
+    F: FnMut(u64, u64, &[u8]) -> Result<()>,
+{
+    wal_replay_with_progress(root, apply_page, None).map(|_| ())
+}
+
+/// Что покрыл реплей (wal_replay_tracked).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplayTouched {
+    /// LSN первого прочитанного кадра (после fast-skip); 0 — кадров не было.
+    pub first_lsn: u64,
+    /// Бакет → max LSN его HEADS_UPDATE среди прочитанных кадров (включая отсечённые
+    /// гейтингом .heads_lsn.bin — их головы уже в каталоге).
+    pub bucket_lsn: BTreeMap<u32, u64>,
+}
+
+/// Реплей WAL v2 с прогрессом и fast-skip уже применённого префикса.
+///
+/// Семантика как у wal_replay_if_any, плюс:
+/// - progress вызывается периодически и финально (done=true), только если реплей реально идёт;
+/// - прерванный ранее реплей продолжается с последней границы батча из <root>/.wal_replay.idx
+///   (lsn границы <= meta.last_lsn);
+/// - возвращает финальный RecoveryProgress (page_id_end учитывает и пропущенный префикс —
+///   вызывающий код по нему актуализирует next_page_id).
+pub fn wal_replay_with_progress<F>(
+    root: &Path,
+    apply_page: F,
+    progress: Option<&RecoveryProgressHook>,
+) -> Result<RecoveryProgress>
+where
+    F: FnMut(u64, u64, &[u8]) -> Result<()>,
+{
+    wal_replay_tracked(root, apply_page, progress, &mut ReplayTouched::default())
+}
+
+/// То же, что wal_replay_with_progress, с отчётом о покрытии в `touched`.
+pub fn wal_replay_tracked<F>(
+    root: &Path,
+    mut apply_page: F,
+    progress: Option<&RecoveryProgressHook>,
+    touched: &mut ReplayTouched,
+) -> Result<RecoveryProgress>
 where
     F: FnMut(u64, u64, &[u8]) -> Result<()>,
 {
@@ -44,7 +105,10 @@ where
 
     let wal_path = wal_path(root);
     if !wal_path.exists() {
-        return Ok(());
+        return Ok(RecoveryProgress {
+            done: true,
+            ..Default::default()
+        });
     }
 
     let mut f = OpenOptions::new()
@@ -58,7 +122,10 @@ where
         // файл мал — запишем новый header
         write_wal_file_header(&mut f)?;
         f.sync_all()?;
-        return Ok(());
+        return Ok(RecoveryProgress {
+            done: true,
+            ..Default::default()
+        });
     }
     let mut hdr16 = [0u8; WAL_HDR_SIZE];
     f.seek(SeekFrom::Start(0))?;
@@ -75,7 +142,11 @@ where
             f.set_len(WAL_HDR_SIZE as u64)?;
             f.sync_all()?;
         }
-        return Ok(());
+        let _ = ReplayIndex::remove(root);
+        return Ok(RecoveryProgress {
+            done: true,
+            ..Default::default()
+        });
     }
 
     // Реплей
@@ -83,6 +154,61 @@ where
     let len = f.metadata()?.len();
     let mut max_lsn = m.last_lsn;
 
+    let t0 = Instant::now();
+    let mut prog = RecoveryProgress {
+        bytes_total: len,
+        bytes_done: pos,
+        current_lsn: max_lsn,
+        ..Default::default()
+    };
+    if len > pos {
+        record_recovery_replay();
+    }
+
+    // NEW: индекс границ батчей — принадлежит ли он этому WAL?
+    let stream_id = LittleEndian::read_u64(&hdr16[8..16]);
+    let first_lsn = WalStreamReader::new()
+        .read_next(&mut f, pos, len)
+        .ok()
+        .flatten()
+        .map(|(r, _)| r.lsn)
+        .unwrap_or(0);
+    let mut index = match ReplayIndex::load(root) {
+        Some(ix) if ix.stream_id == stream_id && ix.first_lsn == first_lsn => ix,
+        _ => ReplayIndex {
+            stream_id,
+            first_lsn,
+            entries: Vec::new(),
+        },
+    };
+
+    // Fast-skip: префикс до границы уже применён (meta.last_lsn >= lsn границы)
+    if let Some(b) = index.best_skip(m.last_lsn, len) {
+        if boundary_readable(&mut f, b, len) {
+            prog.bytes_skipped = b.offset - pos;
+            prog.page_id_end = b.page_id_end;
+            prog.checkpoint_lsn = b.lsn;
+            record_recovery_skipped(prog.bytes_skipped);
+            pos = b.offset;
+            prog.bytes_done = pos;
+            if b.lsn > max_lsn {
+                max_lsn = b.lsn;
+            }
+        }
+    }
+    // Отбросим границы за пределами применённого (они будут переписаны)
+    let ckpt_lsn = prog.checkpoint_lsn;
+    index.entries.retain(|e| e.lsn <= ckpt_lsn);
+
+    let ckpt_every = replay_ckpt_bytes();
+    let mut last_ckpt_pos = pos;
+    let mut last_emit = t0;
+    if let Some(h) = progress {
+        if len > pos {
+            h.call(&prog);
+        }
+    }
+
     // Каталог для HEADS_UPDATE откроем лениво
     let mut dir_lazy: Option<crate::dir::Directory> = None;
 
@@ -92,46 +218,52 @@ where
     // NEW: stateful reader
     let mut reader = WalStreamReader::new();
 
-    while let Some((rec, next_pos)) = reader.read_next(&mut f, pos, len)? {
+    // NEW: idempotency-токены закоммиченных батчей → кольцо (лениво)
+    let mut idem_pending: Option<super::IdemDigest> = None;
+    let mut idem_lazy: Option<super::IdemTokens> = None;
+
+    while let Some((rec, next_pos)) = reader.read_next(&mut f, pos, len).with_context(|| {
+        format!(
+            "WAL replay stopped at offset {} of {}; intact frames after a damaged region \
+             can be recovered with `quiverdb wal-salvage`",
+            pos,
+            wal_path.display()
+        )
+    })? {
         // Учёт max LSN
         if rec.lsn > max_lsn {
             max_lsn = rec.lsn;
         }
+        if touched.first_lsn == 0 {
+            touched.first_lsn = rec.lsn;
+        }
+        let mut at_commit = false;
 
         match rec.rec_type {
             WAL_REC_PAGE_IMAGE => {
                 // PAGE_IMAGE — делегируем вызывающему коду (LSN‑гейтинг снаружи)
                 apply_page(rec.lsn, rec.page_id, &rec.payload)?;
+                prog.pages_applied += 1;
+                prog.page_id_end = prog.page_id_end.max(rec.page_id.saturating_add(1));
             }
             WAL_REC_HEADS_UPDATE => {
+                let updates = parse_heads_update_payload(&rec.payload);
+                for &(b, _) in &updates {
+                    let e = touched.bucket_lsn.entry(b).or_insert(0);
+                    *e = (*e).max(rec.lsn);
+                }
                 // LSN-гейтинг: применяем только если lsn > last_heads_lsn
                 if rec.lsn > last_heads_lsn {
-                    // payload = повторяющиеся [bucket u32][head_pid u64] (LE)
-                    let payload_len = rec.payload.len();
-                    if payload_len % 12 == 0 && payload_len > 0 {
+                    if !updates.is_empty() {
                         // Откроем каталог (лениво)
                         if dir_lazy.is_none() {
                             dir_lazy = Some(crate::dir::Directory::open(root)?);
                         }
                         let dir = dir_lazy.as_ref().unwrap();
-
-                        // Разберём payload в вектор апдейтов
-                        let mut updates: Vec<(u32, u64)> = Vec::with_capacity(payload_len / 12);
-                        let mut off = 0usize;
-                        while off + 12 <= payload_len {
-                            let b = LittleEndian::read_u32(&rec.payload[off..off + 4]);
-                            let pid = LittleEndian::read_u64(&rec.payload[off + 4..off + 12]);
-                            updates.push((b, pid));
-                            off += 12;
-                        }
-                        if !updates.is_empty() {
-                            // Отсортируем по bucket для стабильности (не обязательно)
-                            updates.sort_by_key(|e| e.0);
-                            dir.set_heads_bulk(&updates)?;
-                            // Обновим персистентный маркер
-                            last_heads_lsn = rec.lsn;
-                            let _ = store_last_heads_lsn(root, last_heads_lsn);
-                        }
+                        dir.set_heads_bulk(&updates)?;
+                        // Обновим персистентный маркер
+                        last_heads_lsn = rec.lsn;
+                        let _ = store_last_heads_lsn(root, last_heads_lsn);
                     }
                     // если длина некорректна — игнорируем (forward-compatible)
                 } else {
@@ -141,8 +273,23 @@ where
             WAL_REC_TRUNCATE => {
                 // ignore (маркер ротации в стриминге)
             }
-            WAL_REC_BEGIN | WAL_REC_COMMIT | WAL_REC_PAGE_DELTA => {
-                // В 2.0 PAGE_DELTA игнорируется; BEGIN/COMMIT — только маркеры.
+            WAL_REC_IDEMPOTENCY => {
+                idem_pending = super::idempotency::parse_idem_payload(&rec.payload);
+            }
+            WAL_REC_COMMIT => {
+                at_commit = true;
+                if let Some(d) = idem_pending.take() {
+                    if idem_lazy.is_none() {
+                        idem_lazy = Some(super::IdemTokens::open(root)?);
+                    }
+                    idem_lazy.as_mut().unwrap().record(d, rec.lsn)?;
+                }
+            }
+            WAL_REC_BEGIN => {
+                idem_pending = None;
+            }
+            WAL_REC_PAGE_DELTA => {
+                // В 2.0 PAGE_DELTA игнорируется.
             }
             _ => {
                 // unknown — игнор
@@ -150,6 +297,33 @@ where
         }
 
         pos = next_pos;
+        prog.frames_applied += 1;
+        prog.bytes_done = pos;
+        prog.current_lsn = max_lsn;
+
+        // Чекпоинт индекса на границе батча: префикс [..pos) применён и долговечен.
+        let mut emit = false;
+        if at_commit && ckpt_every > 0 && pos - last_ckpt_pos >= ckpt_every {
+            set_last_lsn(root, max_lsn)?;
+            index.entries.push(ReplayBoundary {
+                offset: pos,
+                lsn: max_lsn,
+                page_id_end: prog.page_id_end,
+            });
+            index.store(root)?;
+            last_ckpt_pos = pos;
+            prog.checkpoint_lsn = max_lsn;
+            emit = true;
+        }
+
+        if let Some(h) = progress {
+            if emit || last_emit.elapsed() >= PROGRESS_EVERY {
+                prog.elapsed_ms = t0.elapsed().as_millis() as u64;
+                prog.update_eta();
+                h.call(&prog);
+                last_emit = Instant::now();
+            }
+        }
     }
 
     // Успешный реплей: усечём до заголовка и проставим meta
@@ -157,5 +331,40 @@ where
     f.sync_all()?;
     set_last_lsn(root, max_lsn)?;
     set_clean_shutdown(root, true)?;
-    Ok(())
+    ReplayIndex::remove(root)?;
+
+    record_recovery_frames(prog.frames_applied);
+    prog.current_lsn = max_lsn;
+    prog.bytes_done = len;
+    prog.elapsed_ms = t0.elapsed().as_millis() as u64;
+    prog.eta_ms = Some(0);
+    prog.done = true;
+    if let Some(h) = progress {
+        if prog.bytes_total > WAL_HDR_SIZE as u64 {
+            h.call(&prog);
+        }
+    }
+    Ok(prog)
+}
+
+/// Разобрать payload HEADS_UPDATE (legacy или delta, см. wal/heads.rs), отсортировано по bucket.
+/// Некорректный payload → пустой вектор (forward-compatible).
+pub(crate) fn parse_heads_update_payload(payload: &[u8]) -> Vec<(u32, u64)> {
+    super::heads::decode_heads_update_payload(payload).unwrap_or_default()
+}
+
+/// Граница из индекса пригодна для пропуска: это конец WAL (хвост) либо
+/// по offset читается корректный кадр нового батча (lsn > lsn границы).
+fn boundary_readable(f: &mut std::fs::File, b: ReplayBoundary, len: u64) -> bool {
+    if b.offset < WAL_HDR_SIZE as u64 || b.offset > len {
+        return false;
+    }
+    if b.offset == len {
+        return true;
+    }
+    match WalStreamReader::new().read_next(f, b.offset, len) {
+        Ok(Some((rec, _))) => rec.lsn > b.lsn,
+        Ok(None) => true, // частичный хвост
+        Err(_) => false,
+    }
 }
@@ -0,0 +1,322 @@
+//! wal/salvage — восстановление после частичной порчи WAL (salvage mode).
+//!
+//! Обычный реплей идёт строго подряд и останавливается на первом кадре с неверной CRC —
+//! всё, что после, теряется, даже если последующие кадры целы. Salvage:
+//! - читает WAL целиком и при сбое (CRC mismatch, невозможная длина) ресинхронизируется:
+//!   побайтно ищет следующий правдоподобный заголовок кадра (известный тип, длина в пределах
+//!   файла) с корректной CRC32C (или mid-stream заголовок WAL "P2WAL001");
+//! - отчитывается о пропущенных регионах (offset, длина, причина);
+//! - собирает батчи BEGIN..COMMIT: батч «целый», если внутри него не было пропусков;
+//!   батчи без COMMIT или с пропуском внутри считаются повреждёнными и не применяются;
+//! - опционально (apply) применяет целые батчи тем же способом, что и реплей:
+//!   PAGE_IMAGE — через callback вызывающего (LSN-гейтинг по странице снаружи),
+//!   HEADS_UPDATE — с LSN-гейтингом по <root>/.heads_lsn.bin.
+//!   Перед применением исходный WAL копируется в <root>/wal-000001.log.salvage-<unix_ts>,
+//!   после — WAL усекается до заголовка, meta: last_lsn=max, clean_shutdown=true.
+//!
+//! Важно: если потерян целый батч (его кадры попали в пропущенный регион), более поздние
+//! батчи могут ссылаться на его страницы. Поэтому по умолчанию salvage только сообщает
+//! (dry-run), а применение — явное решение оператора.
+
+use anyhow::{anyhow, Context, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use serde::Serialize;
+use std::path::Path;
+
+use super::replay::parse_heads_update_payload;
+use super::state::{load_last_heads_lsn, store_last_heads_lsn};
+use super::{
+    crc32c_of_parts, wal_path, WAL_HDR_SIZE, WAL_MAGIC, WAL_REC_BEGIN, WAL_REC_COMMIT,
+    WAL_REC_EXPIRY, WAL_REC_HDR_SIZE, WAL_REC_HEADS_UPDATE, WAL_REC_IDEMPOTENCY, WAL_REC_OFF_CRC32,
+    WAL_REC_OFF_LEN, WAL_REC_OFF_LSN, WAL_REC_OFF_PAGE_ID, WAL_REC_OFF_TYPE, WAL_REC_PAGE_IMAGE,
+};
+use crate::util::now_secs;
+
+/// Пропущенный (нечитаемый) регион WAL.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedRegion {
+    pub start: u64,
+    pub end: u64,
+    /// "crc_mismatch" | "bad_header" | "partial_tail"
+    pub reason: String,
+}
+
+/// Батч BEGIN..COMMIT, найденный при сканировании.
+#[derive(Debug, Clone, Serialize)]
+pub struct SalvageBatch {
+    pub begin_pos: u64,
+    pub start_lsn: u64,
+    pub commit_lsn: u64,
+    pub pages: u64,
+    pub heads_updates: u64,
+    /// Целый (есть COMMIT и внутри нет пропущенных регионов).
+    pub intact: bool,
+    #[serde(skip)]
+    frames: Vec<(u64, u64)>, // (pos, len_total) кадров IMAGE/HEADS_UPDATE
+}
+
+/// Отчёт salvage.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SalvageReport {
+    pub wal_len: u64,
+    pub frames_ok: u64,
+    pub skipped: Vec<SkippedRegion>,
+    pub skipped_bytes: u64,
+    pub batches_intact: u64,
+    pub batches_damaged: u64,
+    /// Кадры IMAGE/HEADS_UPDATE вне какого-либо батча.
+    pub orphan_frames: u64,
+    pub salvageable_pages: u64,
+    pub max_intact_lsn: u64,
+    pub batches: Vec<SalvageBatch>,
+    pub applied: bool,
+    pub applied_batches: u64,
+    pub applied_pages: u64,
+    pub applied_heads_updates: u64,
+    pub backup_path: Option<String>,
+}
+
+/// Кадр, разобранный из буфера WAL.
+struct Frame {
+    rec_type: u8,
+    lsn: u64,
+    page_id: u64,
+    payload_off: usize,
+    payload_len: usize,
+    total: usize,
+}
+
+/// Попытаться разобрать кадр по смещению pos. Err — причина («crc_mismatch»/«bad_header»),
+/// Ok(None) — недостаточно байт до конца файла (частичный хвост).
+fn parse_frame(buf: &[u8], pos: usize) -> std::result::Result<Option<Frame>, &'static str> {
+    if pos + WAL_REC_HDR_SIZE > buf.len() {
+        return Ok(None);
+    }
+    let h = &buf[pos..pos + WAL_REC_HDR_SIZE];
+    let rec_type = h[WAL_REC_OFF_TYPE];
+    if !(WAL_REC_BEGIN..=WAL_REC_EXPIRY).contains(&rec_type) {
+        return Err("bad_header");
+    }
+    let payload_len = LittleEndian::read_u32(&h[WAL_REC_OFF_LEN..WAL_REC_OFF_LEN + 4]) as usize;
+    let total = WAL_REC_HDR_SIZE + payload_len;
+    if pos + total > buf.len() {
+        return Ok(None);
+    }
+    let payload = &buf[pos + WAL_REC_HDR_SIZE..pos + total];
+    let stored = LittleEndian::read_u32(&h[WAL_REC_OFF_CRC32..WAL_REC_OFF_CRC32 + 4]);
+    if crc32c_of_parts(&h[..WAL_REC_OFF_CRC32], payload) != stored {
+        return Err("crc_mismatch");
+    }
+    Ok(Some(Frame {
+        rec_type,
+        lsn: LittleEndian::read_u64(&h[WAL_REC_OFF_LSN..WAL_REC_OFF_LSN + 8]),
+        page_id: LittleEndian::read_u64(&h[WAL_REC_OFF_PAGE_ID..WAL_REC_OFF_PAGE_ID + 8]),
+        payload_off: pos + WAL_REC_HDR_SIZE,
+        payload_len,
+        total,
+    }))
+}
+
+/// Поиск следующей позиции ресинхронизации (валидный кадр или mid-stream заголовок WAL).
+fn resync(buf: &[u8], from: usize) -> Option<usize> {
+    (from..buf.len()).find(|&p| {
+        (p + WAL_HDR_SIZE <= buf.len() && &buf[p..p + 8] == WAL_MAGIC)
+            || matches!(parse_frame(buf, p), Ok(Some(_)))
+    })
+}
+
+/// Просканировать WAL (только чтение) и построить отчёт.
+pub fn wal_salvage_scan(root: &Path) -> Result<SalvageReport> {
+    let (_, rep) = scan(root)?;
+    Ok(rep)
+}
+
+fn scan(root: &Path) -> Result<(Vec<u8>, SalvageReport)> {
+    let path = wal_path(root);
+    let buf = std::fs::read(&path).with_context(|| format!("read wal {}", path.display()))?;
+    if buf.len() < WAL_HDR_SIZE || &buf[..8] != WAL_MAGIC {
+        return Err(anyhow!("bad WAL header in {}", path.display()));
+    }
+
+    let mut rep = SalvageReport {
+        wal_len: buf.len() as u64,
+        ..Default::default()
+    };
+    let mut open: Option<SalvageBatch> = None;
+    let mut pos = WAL_HDR_SIZE;
+
+    while pos < buf.len() {
+        // mid-stream заголовок WAL (после TRUNCATE или при ресинхронизации)
+        if pos + WAL_HDR_SIZE <= buf.len() && &buf[pos..pos + 8] == WAL_MAGIC {
+            pos += WAL_HDR_SIZE;
+            continue;
+        }
+        let frame = match parse_frame(&buf, pos) {
+            Ok(Some(f)) => f,
+            res => {
+                let next = resync(&buf, pos + 1);
+                let end = next.unwrap_or(buf.len());
+                let reason = match res {
+                    Err(r) => r,
+                    // «не хватает байт», но дальше есть целые кадры — испорчена длина
+                    _ if next.is_some() => "bad_header",
+                    _ => "partial_tail",
+                };
+                rep.skipped.push(SkippedRegion {
+                    start: pos as u64,
+                    end: end as u64,
+                    reason: reason.to_string(),
+                });
+                rep.skipped_bytes += (end - pos) as u64;
+                // Пропуск внутри открытого батча — батч повреждён
+                if let Some(b) = open.as_mut() {
+                    b.intact = false;
+                }
+                pos = end;
+                continue;
+            }
+        };
+        rep.frames_ok += 1;
+
+        match frame.rec_type {
+            WAL_REC_BEGIN => {
+                if let Some(mut b) = open.take() {
+                    // BEGIN без COMMIT предыдущего батча
+                    b.intact = false;
+                    rep.batches.push(b);
+                }
+                open = Some(SalvageBatch {
+                    begin_pos: pos as u64,
+                    start_lsn: frame.lsn,
+                    commit_lsn: 0,
+                    pages: 0,
+                    heads_updates: 0,
+                    intact: true,
+                    frames: Vec::new(),
+                });
+            }
+            WAL_REC_PAGE_IMAGE | WAL_REC_HEADS_UPDATE => match open.as_mut() {
+                Some(b) => {
+                    if frame.rec_type == WAL_REC_PAGE_IMAGE {
+                        b.pages += 1;
+                    } else {
+                        b.heads_updates += 1;
+                    }
+                    b.frames.push((pos as u64, frame.total as u64));
+                }
+                None => rep.orphan_frames += 1,
+            },
+            WAL_REC_IDEMPOTENCY => match open.as_mut() {
+                Some(b) => b.frames.push((pos as u64, frame.total as u64)),
+                None => rep.orphan_frames += 1,
+            },
+            WAL_REC_COMMIT => {
+                if let Some(mut b) = open.take() {
+                    b.commit_lsn = frame.lsn;
+                    rep.batches.push(b);
+                }
+            }
+            _ => {}
+        }
+        pos += frame.total;
+    }
+    if let Some(mut b) = open.take() {
+        b.intact = false; // нет COMMIT
+        rep.batches.push(b);
+    }
+
+    for b in &rep.batches {
+        if b.intact {
+            rep.batches_intact += 1;
+            rep.salvageable_pages += b.pages;
+            rep.max_intact_lsn = rep.max_intact_lsn.max(b.commit_lsn);
+        } else {
+            rep.batches_damaged += 1;
+        }
+    }
+    Ok((buf, rep))
+}
+
+/// Salvage: сканирование + (опционально) применение целых батчей.
+///
+/// apply_page(lsn, page_id, payload) — как в wal_replay_if_any (LSN-гейтинг у вызывающего).
+/// Вызывающий код должен держать эксклюзивную блокировку БД.
+pub fn wal_salvage_with<F>(root: &Path, apply: bool, mut apply_page: F) -> Result<SalvageReport>
+where
+    F: FnMut(u64, u64, &[u8]) -> Result<()>,
+{
+    use crate::meta::{read_meta, set_clean_shutdown, set_last_lsn};
+
+    let (buf, mut rep) = scan(root)?;
+    if !apply {
+        return Ok(rep);
+    }
+
+    // Копия исходного WAL для форензики
+    let path = wal_path(root);
+    let backup = path.with_file_name(format!(
+        "{}.salvage-{}",
+        path.file_name().and_then(|s| s.to_str()).unwrap_or("wal"),
+        now_secs()
+    ));
+    std::fs::copy(&path, &backup)
+        .with_context(|| format!("backup {} -> {}", path.display(), backup.display()))?;
+    rep.backup_path = Some(backup.display().to_string());
+
+    let mut last_heads_lsn = load_last_heads_lsn(root).unwrap_or(0);
+    let mut dir_lazy: Option<crate::dir::Directory> = None;
+    let mut max_lsn = read_meta(root)?.last_lsn;
+
+    let mut idem_lazy: Option<super::IdemTokens> = None;
+
+    for b in rep.batches.iter().filter(|b| b.intact) {
+        let mut idem: Option<super::IdemDigest> = None;
+        for &(fpos, _) in &b.frames {
+            let f = match parse_frame(&buf, fpos as usize) {
+                Ok(Some(f)) => f,
+                _ => return Err(anyhow!("salvage: frame at {} became unreadable", fpos)),
+            };
+            let payload = &buf[f.payload_off..f.payload_off + f.payload_len];
+            if f.rec_type == WAL_REC_PAGE_IMAGE {
+                apply_page(f.lsn, f.page_id, payload)?;
+                rep.applied_pages += 1;
+            } else if f.rec_type == WAL_REC_IDEMPOTENCY {
+                idem = super::idempotency::parse_idem_payload(payload);
+            } else if f.lsn > last_heads_lsn {
+                let updates = parse_heads_update_payload(payload);
+                if !updates.is_empty() {
+                    if dir_lazy.is_none() {
+                        dir_lazy = Some(crate::dir::Directory::open(root)?);
+                    }
+                    dir_lazy.as_ref().unwrap().set_heads_bulk(&updates)?;
+                    last_heads_lsn = f.lsn;
+                    let _ = store_last_heads_lsn(root, last_heads_lsn);
+                    rep.applied_heads_updates += 1;
+                }
+            }
+        }
+        if let Some(d) = idem {
+            if idem_lazy.is_none() {
+                idem_lazy = Some(super::IdemTokens::open(root)?);
+            }
+            idem_lazy.as_mut().unwrap().record(d, b.commit_lsn)?;
+        }
+        max_lsn = max_lsn.max(b.commit_lsn);
+        rep.applied_batches += 1;
+    }
+
+    // Всё, что можно было спасти, применено — WAL больше не нужен
+    {
+        let f = std::fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("open wal {}", path.display()))?;
+        f.set_len(WAL_HDR_SIZE as u64)?;
+        f.sync_all()?;
+    }
+    let _ = super::recovery::ReplayIndex::remove(root);
+    set_last_lsn(root, max_lsn)?;
+    set_clean_shutdown(root, true)?;
+    rep.applied = true;
+    Ok(rep)
+}
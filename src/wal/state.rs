@@ -5,29 +5,39 @@
 //!   * last_heads_lsn — LSN последнего применённого HEADS_UPDATE.
 //!   * last_seq       — последний принятый seq для tcp+psk кадров.
 //!   * stream_id      — идентификатор источника WAL (anti‑mix), читается из заголовка WAL.
+//!   * source_db_uuid — NEW: db_uuid БД-источника (из WELCOME протокола v2), anti‑mix по БД.
 //!
 //! Формат файлов (LE, 8 байт):
 //! - <root>/.heads_lsn.bin : [u64 last_heads_lsn]
 //! - <root>/.cdc_seq.bin   : [u64 last_seq]
 //! - <root>/.stream_id.bin : [u64 stream_id]
+//! - <root>/.source_uuid.bin : [u8;16 db_uuid] (NEW)
 //!
 //! Поведение:
 //! - Если файл отсутствует — load_* возвращает 0.
 //! - Запись: truncate + write 8 байт + sync_all() (best-effort).
 //!
+//! NEW: check_stream_source — общая проверка источника перед применением потока: stream_id и
+//! db_uuid источника сверяются с запомненными (первый валидный источник запоминается). Чужой
+//! поток — ForeignStreamError (достаётся через downcast_ref); force=true — предупредить и
+//! перепривязать follower к новому источнику.
+//!
 //! Замечание:
 //! - Хранилище намеренно простое (один u64). Этого достаточно для LSN/seq/stream‑id гейтинга,
 //!   заметно упрощает использование из CLI/реплея без мета-миграций.
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use byteorder::{ByteOrder, LittleEndian};
 use std::fs::OpenOptions;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
+use crate::meta::format_uuid;
+
 pub const HEADS_LSN_FILE: &str = ".heads_lsn.bin";
 pub const CDC_SEQ_FILE: &str = ".cdc_seq.bin";
 pub const STREAM_ID_FILE: &str = ".stream_id.bin";
+pub const SOURCE_UUID_FILE: &str = ".source_uuid.bin";
 
 #[inline]
 pub fn heads_lsn_path(root: &Path) -> PathBuf {
@@ -138,3 +148,127 @@ pub fn store_stream_id(root: &Path, v: u64) -> Result<()> {
     let _ = f.sync_all();
     Ok(())
 }
+
+#[inline]
+pub fn source_uuid_path(root: &Path) -> PathBuf {
+    root.join(SOURCE_UUID_FILE)
+}
+
+/// Загрузить db_uuid источника (None, если follower ещё не привязан к БД-источнику).
+pub fn load_source_db_uuid(root: &Path) -> Result<Option<[u8; 16]>> {
+    let p = source_uuid_path(root);
+    if !p.exists() {
+        return Ok(None);
+    }
+    let mut f = OpenOptions::new()
+        .read(true)
+        .open(&p)
+        .with_context(|| format!("open {}", p.display()))?;
+    let mut buf = [0u8; 16];
+    f.read_exact(&mut buf)?;
+    Ok((buf != [0u8; 16]).then_some(buf))
+}
+
+/// Сохранить db_uuid источника.
+pub fn store_source_db_uuid(root: &Path, v: &[u8; 16]) -> Result<()> {
+    let p = source_uuid_path(root);
+    let mut f = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(&p)
+        .with_context(|| format!("open {} for write", p.display()))?;
+    f.write_all(v)?;
+    let _ = f.sync_all();
+    Ok(())
+}
+
+/// Поток не от того источника, к которому привязан follower. Достаётся из anyhow через downcast_ref.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignStreamError {
+    pub path: PathBuf,
+    /// "stream_id" | "db_uuid"
+    pub what: &'static str,
+    pub expected: String,
+    pub incoming: String,
+}
+
+impl std::fmt::Display for ForeignStreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "WAL stream belongs to a different database: {} expects {} {}, incoming {} \
+             (use --force to re-bind the follower to the new source)",
+            self.path.display(),
+            self.what,
+            self.expected,
+            self.incoming
+        )
+    }
+}
+
+impl std::error::Error for ForeignStreamError {}
+
+/// Итог check_stream_source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StreamSourceCheck {
+    /// Источник запомнен впервые (stream_id и/или db_uuid).
+    pub bound: bool,
+    /// Расхождение пропущено по force (follower перепривязан).
+    pub forced: bool,
+}
+
+/// Проверить источник потока перед применением в follower `root`.
+/// incoming_uuid — db_uuid из WELCOME (нули — неизвестен: file://, протокол v1, старый отправитель).
+pub fn check_stream_source(
+    root: &Path,
+    incoming_stream_id: u64,
+    incoming_uuid: &[u8; 16],
+    force: bool,
+) -> Result<StreamSourceCheck> {
+    if incoming_stream_id == 0 {
+        return Err(anyhow!("incoming WAL stream_id is zero (invalid)"));
+    }
+    let mut out = StreamSourceCheck::default();
+    let mismatch = |what: &'static str, expected: String, incoming: String| -> Result<bool> {
+        let e = ForeignStreamError {
+            path: root.to_path_buf(),
+            what,
+            expected,
+            incoming,
+        };
+        if !force {
+            return Err(e.into());
+        }
+        eprintln!("[WARN] {}; --force: re-binding", e);
+        Ok(true)
+    };
+
+    if *incoming_uuid != [0u8; 16] {
+        match load_source_db_uuid(root)? {
+            Some(exp) if exp == *incoming_uuid => {}
+            Some(exp) => {
+                out.forced |= mismatch("db_uuid", format_uuid(&exp), format_uuid(incoming_uuid))?;
+                store_source_db_uuid(root, incoming_uuid)?;
+            }
+            None => {
+                store_source_db_uuid(root, incoming_uuid)?;
+                out.bound = true;
+            }
+        }
+    }
+
+    let local = load_stream_id(root)?;
+    if local == 0 {
+        store_stream_id(root, incoming_stream_id)?;
+        out.bound = true;
+    } else if local != incoming_stream_id {
+        out.forced |= mismatch(
+            "stream_id",
+            local.to_string(),
+            incoming_stream_id.to_string(),
+        )?;
+        store_stream_id(root, incoming_stream_id)?;
+    }
+    Ok(out)
+}
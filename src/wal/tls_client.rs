@@ -0,0 +1,383 @@
+//! wal/tls_client — TLS-клиент CDC (follower, cdc-ship) с перезагрузкой клиентской identity.
+//!
+//! `cdc-apply --from tls+psk://...` (и `cdc-ship --to tls+psk://...`) подключаются через
+//! ReloadingTlsConnector: TlsConnector (native-tls) из доверенных CA (P1_TLS_CA_FILE) и
+//! клиентской identity для mTLS (PKCS#12, P1_TLS_CLIENT_PFX + P1_TLS_CLIENT_PFX_PASSWORD).
+//!
+//! Перезагрузка: (mtime, len) файлов PFX и CA сверяются при каждом connect и в фоновом потоке
+//! spawn_watcher раз в reload_poll. При изменении собирается новый connector — вне блокировки
+//! состояния; новые подключения идут с новой identity, установленная сессия доживает на старой
+//! (cdc-apply сам переподключается на границе батча, см. cmd_cdc_apply). Не собравшиеся файлы
+//! (PFX записан наполовину, неверный пароль) — остаётся прежний connector, [WARN] и
+//! tls_client_cert_reload_errors; generation() растёт только на удачной перезагрузке.
+//!
+//! Срок действия: notAfter клиентского (leaf) сертификата из PFX (разбор PKCS#12 — openssl,
+//! там же, где native-tls; на Windows/macOS PFX разбирает системный backend, и срок не
+//! проверяется) сверяется при загрузке и каждом опросе. Меньше expiry_warn_days дней — [WARN]
+//! (не чаще раза в сутки) и tls_client_cert_expiry_warnings; gauge tls_client_cert_not_after_unix.
+//!
+//! ENV:
+//!   P1_TLS_CA_FILE             — PEM-файл c CA (блоки CERTIFICATE/TRUSTED CERTIFICATE)
+//!   P1_TLS_CLIENT_PFX          — PFX/PKCS#12 клиентской identity (mTLS; опц.)
+//!   P1_TLS_CLIENT_PFX_PASSWORD — пароль к PFX (mTLS; опц.)
+//!   P1_TLS_DOMAIN              — SNI/hostname (по умолчанию host из "host:port")
+//!   P1_TLS_RELOAD_POLL_SECS    — период опроса файлов (по умолчанию 30; 0 — без перезагрузки)
+//!   P1_TLS_EXPIRY_WARN_DAYS    — порог предупреждения об истечении (по умолчанию 14; 0 — выкл.)
+
+use anyhow::{anyhow, Context, Result};
+use native_tls::{Certificate, Identity, TlsConnector};
+use std::fs;
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use super::net::{parse_pem_certs, tls_domain_for_addr, IoStream};
+use super::tls_server::{
+    cert_not_after_unix, env_u64, format_unix_utc, now_unix, stamp, FileStamp, EXPIRY_WARN_EVERY,
+};
+use crate::metrics::{
+    record_tls_client_cert_expiry_warning, record_tls_client_cert_reload,
+    record_tls_client_cert_reload_error, set_tls_client_cert_not_after,
+};
+
+/// Параметры TLS-клиента.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TlsClientConfig {
+    /// Доверенные CA (PEM) в дополнение к системным.
+    pub ca_file: Option<PathBuf>,
+    /// Клиентская identity: (PFX, пароль).
+    pub pfx: Option<(PathBuf, String)>,
+    /// Период фонового опроса файлов (Duration::ZERO — без перезагрузки).
+    pub reload_poll: Duration,
+    /// Предупреждать, если до notAfter меньше стольких дней (0 — выкл.).
+    pub expiry_warn_days: u32,
+}
+
+// Без пароля PFX в выводе
+impl std::fmt::Debug for TlsClientConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsClientConfig")
+            .field("ca_file", &self.ca_file)
+            .field("pfx", &self.pfx.as_ref().map(|(p, _)| p))
+            .field("reload_poll", &self.reload_poll)
+            .field("expiry_warn_days", &self.expiry_warn_days)
+            .finish()
+    }
+}
+
+impl Default for TlsClientConfig {
+    fn default() -> Self {
+        Self {
+            ca_file: None,
+            pfx: None,
+            reload_poll: Duration::from_secs(30),
+            expiry_warn_days: 14,
+        }
+    }
+}
+
+impl TlsClientConfig {
+    /// Из ENV (всё необязательно; PFX — только вместе с паролем).
+    pub fn from_env() -> Self {
+        let path = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from)
+        };
+        let mut cfg = Self {
+            ca_file: path("P1_TLS_CA_FILE"),
+            ..Self::default()
+        };
+        if let (Some(pfx), Ok(pwd)) = (
+            path("P1_TLS_CLIENT_PFX"),
+            std::env::var("P1_TLS_CLIENT_PFX_PASSWORD"),
+        ) {
+            cfg.pfx = Some((pfx, pwd));
+        }
+        if let Some(secs) = env_u64("P1_TLS_RELOAD_POLL_SECS") {
+            cfg.reload_poll = Duration::from_secs(secs);
+        }
+        if let Some(days) = env_u64("P1_TLS_EXPIRY_WARN_DAYS") {
+            cfg.expiry_warn_days = days.min(u32::MAX as u64) as u32;
+        }
+        cfg
+    }
+
+    fn stamps(&self) -> [FileStamp; 2] {
+        [
+            self.pfx.as_ref().and_then(|(p, _)| stamp(p)),
+            self.ca_file.as_deref().and_then(stamp),
+        ]
+    }
+}
+
+struct State {
+    connector: Arc<TlsConnector>,
+    /// notAfter клиентского сертификата (None — нет identity или срок не известен).
+    not_after: Option<i64>,
+    /// Штампы файлов последней попытки загрузки (удачной или нет).
+    stamps: [FileStamp; 2],
+    /// Номер последней начатой попытки: устаревшая (обогнанная) попытка результат не ставит.
+    reload_gen: u64,
+    last_expiry_warn: Option<Instant>,
+}
+
+/// TlsConnector с перезагрузкой по изменению файлов (см. модульный комментарий).
+pub struct ReloadingTlsConnector {
+    cfg: TlsClientConfig,
+    state: Mutex<State>,
+    /// Число удачных перезагрузок.
+    generation: AtomicU64,
+}
+
+impl ReloadingTlsConnector {
+    /// Первая загрузка обязана удаться.
+    pub fn new(cfg: TlsClientConfig) -> Result<Self> {
+        let stamps = cfg.stamps();
+        let (connector, not_after) = load_connector(&cfg)?;
+        set_tls_client_cert_not_after(not_after.unwrap_or(0));
+        let me = Self {
+            cfg,
+            state: Mutex::new(State {
+                connector: Arc::new(connector),
+                not_after,
+                stamps,
+                reload_gen: 0,
+                last_expiry_warn: None,
+            }),
+            generation: AtomicU64::new(0),
+        };
+        me.check_expiry(&mut me.lock());
+        Ok(me)
+    }
+
+    pub fn config(&self) -> &TlsClientConfig {
+        &self.cfg
+    }
+
+    /// notAfter текущего клиентского сертификата (unix-секунды).
+    pub fn not_after_unix(&self) -> Option<i64> {
+        self.lock().not_after
+    }
+
+    /// Число удачных перезагрузок: изменилось — новые подключения идут с новой identity.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Проверить файлы сейчас: true — connector перезагружен. Новые файлы загружаются без
+    /// блокировки состояния (текущий connector в это время доступен).
+    pub fn poll(&self) -> bool {
+        let gen = {
+            let mut st = self.lock();
+            let stamps = self.cfg.stamps();
+            if stamps == st.stamps {
+                self.check_expiry(&mut st);
+                return false;
+            }
+            // Изменение закреплено за этой попыткой: параллельный опрос его не повторит
+            st.stamps = stamps;
+            st.reload_gen += 1;
+            st.reload_gen
+        };
+        let loaded = load_connector(&self.cfg);
+        let mut st = self.lock();
+        if st.reload_gen != gen {
+            // Файлы успели измениться ещё раз — результат поставит более поздняя попытка
+            return false;
+        }
+        let reloaded = self.install(&mut st, loaded);
+        self.check_expiry(&mut st);
+        reloaded
+    }
+
+    /// TCP connect + TLS-handshake к "host:port" (перед этим — опрос файлов).
+    pub fn connect(&self, addr: &str) -> Result<IoStream> {
+        let domain = tls_domain_for_addr(addr)
+            .with_context(|| format!("derive SNI from addr '{}'", addr))?;
+        let tcp = TcpStream::connect(addr).map_err(|e| anyhow!("connect({}): {}", addr, e))?;
+        if !self.cfg.reload_poll.is_zero() {
+            self.poll();
+        }
+        let connector = self.lock().connector.clone();
+        let tls = connector
+            .connect(&domain, tcp)
+            .map_err(|e| anyhow!("tls connect (SNI={}): {}", domain, e))?;
+        Ok(IoStream::Tls(tls))
+    }
+
+    /// Фоновый опрос раз в reload_poll (None — перезагрузка выключена).
+    pub fn spawn_watcher(self: &Arc<Self>) -> Result<Option<JoinHandle<()>>> {
+        if self.cfg.reload_poll.is_zero() {
+            return Ok(None);
+        }
+        let me = self.clone();
+        let h = std::thread::Builder::new()
+            .name("tls-client-watch".into())
+            .spawn(move || loop {
+                std::thread::sleep(me.cfg.reload_poll);
+                me.poll();
+            })
+            .context("spawn TLS client identity watcher")?;
+        Ok(Some(h))
+    }
+
+    fn install(&self, st: &mut State, loaded: Result<(TlsConnector, Option<i64>)>) -> bool {
+        match loaded {
+            Ok((connector, not_after)) => {
+                st.connector = Arc::new(connector);
+                st.not_after = not_after;
+                st.last_expiry_warn = None;
+                set_tls_client_cert_not_after(not_after.unwrap_or(0));
+                record_tls_client_cert_reload();
+                self.generation.fetch_add(1, Ordering::AcqRel);
+                match not_after {
+                    Some(t) => eprintln!(
+                        "tls: reloaded client identity {} (not after {})",
+                        self.identity_name(),
+                        format_unix_utc(t)
+                    ),
+                    None => eprintln!("tls: reloaded client identity {}", self.identity_name()),
+                }
+                true
+            }
+            Err(e) => {
+                record_tls_client_cert_reload_error();
+                eprintln!(
+                    "[WARN] tls: client identity files changed but cannot be loaded; keeping the previous one: {:#}",
+                    e
+                );
+                false
+            }
+        }
+    }
+
+    fn check_expiry(&self, st: &mut State) {
+        let Some(not_after) = st.not_after else {
+            return;
+        };
+        if self.cfg.expiry_warn_days == 0 {
+            return;
+        }
+        let left = not_after - now_unix();
+        if left >= self.cfg.expiry_warn_days as i64 * 86_400 {
+            return;
+        }
+        if st
+            .last_expiry_warn
+            .is_some_and(|t| t.elapsed() < EXPIRY_WARN_EVERY)
+        {
+            return;
+        }
+        st.last_expiry_warn = Some(Instant::now());
+        record_tls_client_cert_expiry_warning();
+        if left <= 0 {
+            eprintln!(
+                "[WARN] tls: client certificate {} has expired (not after {})",
+                self.identity_name(),
+                format_unix_utc(not_after)
+            );
+        } else {
+            eprintln!(
+                "[WARN] tls: client certificate {} expires in {} day(s) (not after {})",
+                self.identity_name(),
+                left / 86_400,
+                format_unix_utc(not_after)
+            );
+        }
+    }
+
+    fn identity_name(&self) -> String {
+        match &self.cfg.pfx {
+            Some((p, _)) => p.display().to_string(),
+            None => "(none)".to_string(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Общий connector процесса для текущих ENV (open_tls_psk_stream). Изменились ENV —
+/// собирается новый.
+pub fn shared_from_env() -> Result<Arc<ReloadingTlsConnector>> {
+    static SHARED: Mutex<Option<Arc<ReloadingTlsConnector>>> = Mutex::new(None);
+    let cfg = TlsClientConfig::from_env();
+    let mut g = SHARED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(c) = g.as_ref().filter(|c| c.cfg == cfg) {
+        return Ok(c.clone());
+    }
+    let c = Arc::new(ReloadingTlsConnector::new(cfg)?);
+    *g = Some(c.clone());
+    Ok(c)
+}
+
+/// Собрать connector; вернуть его и notAfter клиентского сертификата.
+fn load_connector(cfg: &TlsClientConfig) -> Result<(TlsConnector, Option<i64>)> {
+    let mut builder = TlsConnector::builder();
+
+    // Кастомные CA (PEM). Поддерживаем "CERTIFICATE" и "TRUSTED CERTIFICATE".
+    if let Some(ca) = &cfg.ca_file {
+        let ca_pem = fs::read(ca).map_err(|e| anyhow!("read CA file {}: {}", ca.display(), e))?;
+        let certs_der = parse_pem_certs(&ca_pem)
+            .with_context(|| format!("parse PEM CA file {}", ca.display()))?;
+        if certs_der.is_empty() {
+            return Err(anyhow!(
+                "no certificates found in CA file {} (expect BEGIN CERTIFICATE blocks)",
+                ca.display()
+            ));
+        }
+        for der in certs_der {
+            let cert = Certificate::from_der(&der)
+                .map_err(|e| anyhow!("parse DER from {}: {}", ca.display(), e))?;
+            builder.add_root_certificate(cert);
+        }
+    }
+
+    // mTLS через PKCS#12 (PFX)
+    let mut not_after = None;
+    if let Some((pfx, pwd)) = &cfg.pfx {
+        let pfx_der = fs::read(pfx).map_err(|e| anyhow!("read PFX {}: {}", pfx.display(), e))?;
+        let id = Identity::from_pkcs12(&pfx_der, pwd)
+            .map_err(|e| anyhow!("load PFX {}: {}", pfx.display(), e))?;
+        if let Some(leaf) =
+            pfx_leaf_der(&pfx_der, pwd).with_context(|| format!("parse PFX {}", pfx.display()))?
+        {
+            not_after = Some(
+                cert_not_after_unix(&leaf)
+                    .with_context(|| format!("parse certificate in {}", pfx.display()))?,
+            );
+        }
+        builder.identity(id);
+    }
+
+    let connector = builder.build().map_err(|e| anyhow!("tls build: {}", e))?;
+    Ok((connector, not_after))
+}
+
+/// DER клиентского сертификата из PKCS#12 (openssl — backend native-tls на этих платформах).
+#[cfg(not(any(target_os = "windows", target_vendor = "apple")))]
+fn pfx_leaf_der(pfx_der: &[u8], pwd: &str) -> Result<Option<Vec<u8>>> {
+    let parsed = openssl::pkcs12::Pkcs12::from_der(pfx_der)
+        .and_then(|p| p.parse2(pwd))
+        .map_err(|e| anyhow!("PKCS#12: {}", e))?;
+    parsed
+        .cert
+        .map(|c| {
+            c.to_der()
+                .map_err(|e| anyhow!("PKCS#12 certificate: {}", e))
+        })
+        .transpose()
+}
+
+/// Windows/macOS: PFX разбирает системный backend native-tls — срок не проверяется.
+#[cfg(any(target_os = "windows", target_vendor = "apple"))]
+fn pfx_leaf_der(_pfx_der: &[u8], _pwd: &str) -> Result<Option<Vec<u8>>> {
+    Ok(None)
+}
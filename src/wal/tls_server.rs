@@ -0,0 +1,505 @@
+//! wal/tls_server — TLS-слушатель CDC для follower'ов с горячей перезагрузкой сертификата.
+//!
+//! `quiverdb cdc-serve --listen tls+psk://host:port` принимает follower'ов
+//! (`cdc-apply --from tls+psk://...`) по TLS (native-tls); поверх — те же PSK-фреймы, что у
+//! tcp+psk. Серверная identity — PEM-сертификат (цепочка, первым — серверный) и PKCS#8 PEM-ключ.
+//!
+//! Перезагрузка без остановки слушателя: ReloadingTlsAcceptor опрашивает (mtime, len) файлов
+//! сертификата и ключа раз в reload_poll (фоновый поток spawn_watcher и каждое accept) и при
+//! изменении собирает новый TlsAcceptor — вне блокировки состояния, так что accept новых
+//! подключений во время перезагрузки не ждёт. Новые подключения получают новый сертификат, уже
+//! установленные сессии доживают на старом. Если новая пара не собирается (файлы записаны
+//! наполовину, ключ не от сертификата) — остаётся прежний acceptor, [WARN] и
+//! tls_cert_reload_errors; следующая попытка — при следующем изменении файлов.
+//!
+//! Срок действия: notAfter серверного сертификата (разбор X.509 DER, cert_not_after_unix)
+//! проверяется при загрузке и при каждом опросе. Меньше expiry_warn_days дней — [WARN] (не чаще
+//! раза в сутки на сертификат) и tls_cert_expiry_warnings; gauge tls_cert_not_after_unix —
+//! notAfter текущего сертификата.
+//!
+//! ENV:
+//!   P1_TLS_SERVER_CERT       — PEM-файл сертификата (цепочки) слушателя
+//!   P1_TLS_SERVER_KEY        — PEM-файл ключа (PKCS#8)
+//!   P1_TLS_RELOAD_POLL_SECS  — период опроса файлов (по умолчанию 30; 0 — без перезагрузки)
+//!   P1_TLS_EXPIRY_WARN_DAYS  — порог предупреждения об истечении (по умолчанию 14; 0 — выкл.)
+//!
+//! Клиентские сертификаты (mTLS follower'а) acceptor не запрашивает и не проверяет: слушатель
+//! построен на native-tls (как и TLS-клиент follower'а), а не на rustls, и у native-tls нет
+//! проверки клиентского сертификата на стороне сервера. Аутентификация подписчика — только PSK;
+//! P1_TLS_SERVER_CLIENT_CA (запрос mTLS) отвергается в from_env, а не молча игнорируется.
+
+use anyhow::{anyhow, Context, Result};
+use native_tls::{Identity, TlsAcceptor};
+use std::fs;
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use super::net::{parse_pem_certs, IoStream};
+use crate::metrics::{
+    record_tls_cert_expiry_warning, record_tls_cert_reload, record_tls_cert_reload_error,
+    set_tls_cert_not_after,
+};
+
+/// Повтор предупреждения об истечении — не чаще раза в столько.
+pub(super) const EXPIRY_WARN_EVERY: Duration = Duration::from_secs(24 * 3600);
+
+/// Предел проверочного handshake'а новой пары (self_handshake).
+const SELF_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Параметры TLS-слушателя.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsServerConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    /// Период опроса файлов (Duration::ZERO — без перезагрузки).
+    pub reload_poll: Duration,
+    /// Предупреждать, если до notAfter меньше стольких дней (0 — выкл.).
+    pub expiry_warn_days: u32,
+}
+
+impl TlsServerConfig {
+    pub fn new(cert: &Path, key: &Path) -> Self {
+        Self {
+            cert: cert.to_path_buf(),
+            key: key.to_path_buf(),
+            reload_poll: Duration::from_secs(30),
+            expiry_warn_days: 14,
+        }
+    }
+
+    /// Из ENV (P1_TLS_SERVER_CERT / P1_TLS_SERVER_KEY обязательны).
+    pub fn from_env() -> Result<Self> {
+        if std::env::var_os("P1_TLS_SERVER_CLIENT_CA").is_some_and(|v| !v.is_empty()) {
+            return Err(anyhow!(
+                "P1_TLS_SERVER_CLIENT_CA is set, but the TLS listener cannot verify client certificates \
+                 (native-tls has no server-side mTLS); followers are authenticated by the PSK only. \
+                 Unset it, or terminate mTLS in a proxy in front of cdc-serve"
+            ));
+        }
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .ok_or_else(|| anyhow!("TLS listener needs {} (PEM file)", name))
+        };
+        let mut cfg = Self::new(
+            Path::new(&var("P1_TLS_SERVER_CERT")?),
+            Path::new(&var("P1_TLS_SERVER_KEY")?),
+        );
+        if let Some(secs) = env_u64("P1_TLS_RELOAD_POLL_SECS") {
+            cfg.reload_poll = Duration::from_secs(secs);
+        }
+        if let Some(days) = env_u64("P1_TLS_EXPIRY_WARN_DAYS") {
+            cfg.expiry_warn_days = days.min(u32::MAX as u64) as u32;
+        }
+        Ok(cfg)
+    }
+}
+
+pub(super) fn env_u64(name: &str) -> Option<u64> {
+    std::env::var(name)
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+}
+
+/// (mtime, len) файла; None — файла нет.
+pub(super) type FileStamp = Option<(Option<SystemTime>, u64)>;
+
+pub(super) fn stamp(path: &Path) -> FileStamp {
+    fs::metadata(path)
+        .ok()
+        .map(|m| (m.modified().ok(), m.len()))
+}
+
+struct State {
+    acceptor: Arc<TlsAcceptor>,
+    not_after: i64,
+    /// Штампы файлов последней попытки загрузки (удачной или нет).
+    stamps: [FileStamp; 2],
+    /// Номер последней начатой попытки: устаревшая (обогнанная) попытка результат не ставит.
+    reload_gen: u64,
+    last_poll: Instant,
+    last_expiry_warn: Option<Instant>,
+}
+
+/// TlsAcceptor с перезагрузкой по изменению файлов (см. модульный комментарий).
+pub struct ReloadingTlsAcceptor {
+    cfg: TlsServerConfig,
+    state: Mutex<State>,
+}
+
+impl ReloadingTlsAcceptor {
+    /// Первая загрузка обязана удаться.
+    pub fn new(cfg: TlsServerConfig) -> Result<Self> {
+        let stamps = [stamp(&cfg.cert), stamp(&cfg.key)];
+        let (acceptor, not_after) = load_identity(&cfg.cert, &cfg.key)?;
+        set_tls_cert_not_after(not_after);
+        let me = Self {
+            cfg,
+            state: Mutex::new(State {
+                acceptor: Arc::new(acceptor),
+                not_after,
+                stamps,
+                reload_gen: 0,
+                last_poll: Instant::now(),
+                last_expiry_warn: None,
+            }),
+        };
+        me.check_expiry(&mut me.lock());
+        Ok(me)
+    }
+
+    pub fn config(&self) -> &TlsServerConfig {
+        &self.cfg
+    }
+
+    /// notAfter текущего сертификата (unix-секунды).
+    pub fn not_after_unix(&self) -> i64 {
+        self.lock().not_after
+    }
+
+    /// Текущий acceptor (с опросом файлов, если подошёл срок).
+    pub fn acceptor(&self) -> Arc<TlsAcceptor> {
+        let due = {
+            let st = self.lock();
+            !self.cfg.reload_poll.is_zero() && st.last_poll.elapsed() >= self.cfg.reload_poll
+        };
+        if due {
+            self.poll();
+        }
+        self.lock().acceptor.clone()
+    }
+
+    /// Проверить файлы сейчас: true — сертификат перезагружен. Новая пара загружается и
+    /// проверяется без блокировки состояния (текущий acceptor в это время доступен).
+    pub fn poll(&self) -> bool {
+        let gen = {
+            let mut st = self.lock();
+            st.last_poll = Instant::now();
+            let stamps = [stamp(&self.cfg.cert), stamp(&self.cfg.key)];
+            if stamps == st.stamps {
+                self.check_expiry(&mut st);
+                return false;
+            }
+            // Изменение закреплено за этой попыткой: параллельный опрос его не повторит
+            st.stamps = stamps;
+            st.reload_gen += 1;
+            st.reload_gen
+        };
+        let loaded = load_identity(&self.cfg.cert, &self.cfg.key);
+        let mut st = self.lock();
+        if st.reload_gen != gen {
+            // Файлы успели измениться ещё раз — результат поставит более поздняя попытка
+            return false;
+        }
+        let reloaded = self.install(&mut st, loaded);
+        self.check_expiry(&mut st);
+        reloaded
+    }
+
+    /// TLS-handshake на принятом сокете.
+    pub fn accept(&self, sock: TcpStream) -> Result<IoStream> {
+        let tls = self
+            .acceptor()
+            .accept(sock)
+            .map_err(|e| anyhow!("tls accept: {}", e))?;
+        Ok(IoStream::Tls(tls))
+    }
+
+    /// Фоновый опрос раз в reload_poll (None — перезагрузка выключена).
+    pub fn spawn_watcher(self: &Arc<Self>) -> Result<Option<JoinHandle<()>>> {
+        if self.cfg.reload_poll.is_zero() {
+            return Ok(None);
+        }
+        let me = self.clone();
+        let h = std::thread::Builder::new()
+            .name("tls-cert-watch".into())
+            .spawn(move || loop {
+                std::thread::sleep(me.cfg.reload_poll);
+                me.poll();
+            })
+            .context("spawn TLS certificate watcher")?;
+        Ok(Some(h))
+    }
+
+    fn install(&self, st: &mut State, loaded: Result<(TlsAcceptor, i64)>) -> bool {
+        match loaded {
+            Ok((acceptor, not_after)) => {
+                st.acceptor = Arc::new(acceptor);
+                st.not_after = not_after;
+                st.last_expiry_warn = None;
+                set_tls_cert_not_after(not_after);
+                record_tls_cert_reload();
+                eprintln!(
+                    "tls: reloaded certificate {} (not after {})",
+                    self.cfg.cert.display(),
+                    format_unix_utc(not_after)
+                );
+                true
+            }
+            Err(e) => {
+                record_tls_cert_reload_error();
+                eprintln!(
+                    "[WARN] tls: certificate files changed but cannot be loaded; keeping the previous one: {:#}",
+                    e
+                );
+                false
+            }
+        }
+    }
+
+    fn check_expiry(&self, st: &mut State) {
+        if self.cfg.expiry_warn_days == 0 {
+            return;
+        }
+        let left = st.not_after - now_unix();
+        if left >= self.cfg.expiry_warn_days as i64 * 86_400 {
+            return;
+        }
+        if st
+            .last_expiry_warn
+            .is_some_and(|t| t.elapsed() < EXPIRY_WARN_EVERY)
+        {
+            return;
+        }
+        st.last_expiry_warn = Some(Instant::now());
+        record_tls_cert_expiry_warning();
+        if left <= 0 {
+            eprintln!(
+                "[WARN] tls: certificate {} has expired (not after {})",
+                self.cfg.cert.display(),
+                format_unix_utc(st.not_after)
+            );
+        } else {
+            eprintln!(
+                "[WARN] tls: certificate {} expires in {} day(s) (not after {})",
+                self.cfg.cert.display(),
+                left / 86_400,
+                format_unix_utc(st.not_after)
+            );
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Собрать acceptor из PEM-пары; вернуть его и notAfter серверного (первого) сертификата.
+fn load_identity(cert: &Path, key: &Path) -> Result<(TlsAcceptor, i64)> {
+    let cert_pem = fs::read(cert).with_context(|| format!("read {}", cert.display()))?;
+    let key_pem = fs::read(key).with_context(|| format!("read {}", key.display()))?;
+    let leaf = parse_pem_certs(&cert_pem)
+        .with_context(|| format!("parse {}", cert.display()))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("no certificate in {}", cert.display()))?;
+    let not_after =
+        cert_not_after_unix(&leaf).with_context(|| format!("parse {}", cert.display()))?;
+    let id = Identity::from_pkcs8(&cert_pem, &key_pem).map_err(|e| {
+        anyhow!(
+            "load identity {} + {}: {}",
+            cert.display(),
+            key.display(),
+            e
+        )
+    })?;
+    let acceptor = TlsAcceptor::new(id).map_err(|e| anyhow!("tls acceptor: {}", e))?;
+    self_handshake(&acceptor).with_context(|| {
+        format!(
+            "self-check {} + {} (key does not match the certificate?)",
+            cert.display(),
+            key.display()
+        )
+    })?;
+    Ok((acceptor, not_after))
+}
+
+/// Handshake с самим собой через loopback: TlsAcceptor::new не сверяет ключ с сертификатом,
+/// а несовпадающая пара сломала бы все новые подключения. Каждый шаг ограничен
+/// SELF_CHECK_TIMEOUT: клиент, не дошедший до connect, не вешает перезагрузку.
+fn self_handshake(acceptor: &TlsAcceptor) -> Result<()> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    listener.set_nonblocking(true)?;
+    let addr = listener.local_addr()?;
+    let client = std::thread::spawn(move || -> Result<()> {
+        let connector = native_tls::TlsConnector::builder()
+            .danger_accept_invalid_certs(true)
+            .danger_accept_invalid_hostnames(true)
+            .build()
+            .map_err(|e| anyhow!("tls connector: {}", e))?;
+        let sock = TcpStream::connect_timeout(&addr, SELF_CHECK_TIMEOUT)?;
+        sock.set_read_timeout(Some(SELF_CHECK_TIMEOUT))?;
+        sock.set_write_timeout(Some(SELF_CHECK_TIMEOUT))?;
+        connector
+            .connect("localhost", sock)
+            .map_err(|e| anyhow!("handshake: {}", e))?;
+        Ok(())
+    });
+    let join = |client: JoinHandle<Result<()>>| {
+        client
+            .join()
+            .map_err(|_| anyhow!("self-check client panicked"))?
+    };
+
+    let deadline = Instant::now() + SELF_CHECK_TIMEOUT;
+    let sock = loop {
+        match listener.accept() {
+            Ok((sock, _)) => break sock,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                if client.is_finished() {
+                    // Клиент завершился, не подключившись, — его ошибка и есть причина
+                    join(client)?;
+                    return Err(anyhow!("self-check client exited without connecting"));
+                }
+                if Instant::now() >= deadline {
+                    return Err(anyhow!(
+                        "self-check: no connection within {:?}",
+                        SELF_CHECK_TIMEOUT
+                    ));
+                }
+                std::thread::sleep(Duration::from_millis(2));
+            }
+            Err(e) => return Err(anyhow!("self-check accept: {}", e)),
+        }
+    };
+    sock.set_nonblocking(false)?;
+    sock.set_read_timeout(Some(SELF_CHECK_TIMEOUT))?;
+    sock.set_write_timeout(Some(SELF_CHECK_TIMEOUT))?;
+    let served = acceptor
+        .accept(sock)
+        .map_err(|e| anyhow!("handshake: {}", e));
+    let connected = join(client);
+    served.map(|_| ())?;
+    connected
+}
+
+/// notAfter X.509-сертификата (DER) в unix-секундах.
+pub fn cert_not_after_unix(der: &[u8]) -> Result<i64> {
+    let (cert, _) = der_expect(der, 0x30, "Certificate")?;
+    let (tbs, _) = der_expect(cert, 0x30, "TBSCertificate")?;
+    let mut rest = tbs;
+    // version [0] EXPLICIT — необязательна
+    if rest.first() == Some(&0xA0) {
+        rest = der_next(rest)?.2;
+    }
+    // serialNumber, signature, issuer
+    for _ in 0..3 {
+        rest = der_next(rest)?.2;
+    }
+    let (validity, _) = der_expect(rest, 0x30, "Validity")?;
+    let (_, _, after) = der_next(validity)?; // notBefore
+    let (tag, time, _) = der_next(after)?;
+    let s = std::str::from_utf8(time)
+        .ok()
+        .filter(|s| s.is_ascii())
+        .ok_or_else(|| anyhow!("X.509: notAfter is not ASCII"))?;
+    let digits = s.strip_suffix('Z').unwrap_or(s);
+    // срезы ниже — по байтам: только ASCII-цифры (ни '+', ни многобайтных символов)
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(anyhow!("X.509: bad notAfter '{}'", s));
+    }
+    let (year, tail) = match tag {
+        // UTCTime YYMMDDHHMMSSZ (RFC 5280: YY < 50 — 20YY)
+        0x17 if digits.len() == 12 => {
+            let yy: i64 = parse_num(&digits[..2])?;
+            (if yy < 50 { 2000 + yy } else { 1900 + yy }, &digits[2..])
+        }
+        // GeneralizedTime YYYYMMDDHHMMSSZ
+        0x18 if digits.len() == 14 => (parse_num(&digits[..4])?, &digits[4..]),
+        _ => return Err(anyhow!("X.509: unsupported notAfter format '{}'", s)),
+    };
+    let (mon, day) = (parse_num(&tail[0..2])?, parse_num(&tail[2..4])?);
+    let (h, m, sec) = (
+        parse_num(&tail[4..6])?,
+        parse_num(&tail[6..8])?,
+        parse_num(&tail[8..10])?,
+    );
+    Ok(days_from_civil(year, mon, day) * 86_400 + h * 3600 + m * 60 + sec)
+}
+
+fn parse_num(s: &str) -> Result<i64> {
+    s.parse::<i64>()
+        .map_err(|_| anyhow!("X.509: bad time field '{}'", s))
+}
+
+/// Один DER TLV: (tag, содержимое, остаток).
+fn der_next(b: &[u8]) -> Result<(u8, &[u8], &[u8])> {
+    let short = || anyhow!("X.509: truncated DER");
+    let tag = *b.first().ok_or_else(short)?;
+    let l0 = *b.get(1).ok_or_else(short)?;
+    let (len, hdr) = if l0 < 0x80 {
+        (l0 as usize, 2)
+    } else {
+        let n = (l0 & 0x7f) as usize;
+        if n == 0 || n > 4 {
+            return Err(anyhow!("X.509: unsupported DER length"));
+        }
+        let bytes = b.get(2..2 + n).ok_or_else(short)?;
+        (
+            bytes.iter().fold(0usize, |acc, &x| (acc << 8) | x as usize),
+            2 + n,
+        )
+    };
+    let body = b.get(hdr..hdr + len).ok_or_else(short)?;
+    Ok((tag, body, &b[hdr + len..]))
+}
+
+fn der_expect<'a>(b: &'a [u8], tag: u8, what: &str) -> Result<(&'a [u8], &'a [u8])> {
+    let (t, body, rest) = der_next(b)?;
+    if t != tag {
+        return Err(anyhow!(
+            "X.509: expected {} (tag {:#x}), got {:#x}",
+            what,
+            tag,
+            t
+        ));
+    }
+    Ok((body, rest))
+}
+
+/// Дни от 1970-01-01 (алгоритм days_from_civil, пролептический григорианский календарь).
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// "YYYY-MM-DD HH:MM:SS UTC" для логов.
+pub(super) fn format_unix_utc(t: i64) -> String {
+    let (days, secs) = (t.div_euclid(86_400), t.rem_euclid(86_400));
+    // civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        y,
+        m,
+        d,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+pub(super) fn now_unix() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
@@ -1,13 +1,17 @@
 use anyhow::Result;
-use byteorder::{ByteOrder, LittleEndian};
 use std::io::{Seek, SeekFrom};
 use std::path::Path;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 use crate::metrics::{
+    // NEW: HEADS_UPDATE delta encoding
+    record_heads_update_frame,
     record_wal_append,
+    // NEW: адаптивное окно group-commit
+    record_wal_coalesce_decision,
     record_wal_flushed_lsn,
     record_wal_fsync,
     record_wal_pending_lsn,
@@ -15,24 +19,36 @@ use crate::metrics::{
     record_wal_threshold_flush,
     record_wal_truncation,
 };
+use crate::util::iostall::{io_watch, IoOp};
 
 use super::{
-    WAL_HDR_SIZE, WAL_REC_BEGIN, WAL_REC_COMMIT, WAL_REC_HDR_SIZE, WAL_REC_HEADS_UPDATE,
-    WAL_REC_PAGE_IMAGE, WAL_REC_TRUNCATE, WAL_ROTATE_SIZE,
+    WAL_HDR_SIZE, WAL_REC_BEGIN, WAL_REC_COMMIT, WAL_REC_EXPIRY, WAL_REC_HDR_SIZE,
+    WAL_REC_HEADS_UPDATE, WAL_REC_IDEMPOTENCY, WAL_REC_PAGE_IMAGE, WAL_REC_TRUNCATE,
+    WAL_ROTATE_SIZE,
 };
 
 use super::encode;
-use super::registry::{get_or_create_wal_inner, set_group_coalesce_ms, WalInner};
+use super::encode::{commit_ts_enabled, CommitTimestamp};
+use super::heads::{encode_heads_update_payload, normalize_head_updates};
+use super::registry::{
+    get_or_create_wal_inner, set_group_adaptive_coalesce, set_group_coalesce_ms, WalInner,
+};
 
 #[derive(Debug, Clone, Copy)]
 pub struct WalGroupCfg {
     pub coalesce_ms: u64,
+    /// NEW: адаптивное окно (wal/coalesce.rs); coalesce_ms становится потолком (0 — без потолка).
+    pub adaptive: bool,
+    /// Целевой p99 латентности коммита для адаптивного окна, мкс.
+    pub p99_budget_us: u64,
 }
 
 pub struct Wal {
     inner: Arc<WalInner>,
     // NEW: подавление пороговых fsync'ов во время батча
     in_batch: bool,
+    // NEW: delta-формат HEADS_UPDATE (только при FEATURE_HEADS_DELTA в meta)
+    heads_delta: bool,
 }
 
 impl Wal {
@@ -41,11 +57,26 @@ impl Wal {
         Ok(Self {
             inner,
             in_batch: false,
+            heads_delta: false,
         })
     }
 
     pub fn set_group_config(root: &Path, cfg: WalGroupCfg) -> Result<()> {
-        set_group_coalesce_ms(root, cfg.coalesce_ms)
+        set_group_coalesce_ms(root, cfg.coalesce_ms)?;
+        let adaptive = cfg.adaptive.then(|| {
+            let cap_us = match cfg.coalesce_ms {
+                0 => cfg.p99_budget_us,
+                ms => ms.saturating_mul(1000),
+            };
+            (cfg.p99_budget_us, cap_us)
+        });
+        set_group_adaptive_coalesce(root, adaptive)
+    }
+
+    /// NEW: разрешить delta-формат HEADS_UPDATE (вызывающий проверяет FEATURE_HEADS_DELTA).
+    #[inline]
+    pub fn set_heads_delta(&mut self, on: bool) {
+        self.heads_delta = on;
     }
 
     // NEW: сигнализировать писателю, что начался "ручной" батч (BEGIN..COMMIT)
@@ -104,11 +135,19 @@ impl Wal {
     }
 
     pub fn append_commit(&mut self, lsn: u64) -> Result<()> {
-        self.write_record(WAL_REC_COMMIT, lsn, 0, &[])?;
+        // NEW: payload COMMIT = время коммита (wall-clock + monotonic), см. encode::CommitTimestamp
+        let ts;
+        let payload: &[u8] = if commit_ts_enabled() {
+            ts = CommitTimestamp::now().encode();
+            &ts
+        } else {
+            &[]
+        };
+        self.write_record(WAL_REC_COMMIT, lsn, 0, payload)?;
         // учёт байтов
         self.inner
             .bytes_since_last_fsync
-            .fetch_add(WAL_REC_HDR_SIZE as u64, Ordering::Relaxed);
+            .fetch_add((WAL_REC_HDR_SIZE + payload.len()) as u64, Ordering::Relaxed);
         {
             let mut st = self.inner.flush.lock().unwrap();
             if lsn > st.pending_max_lsn {
@@ -123,6 +162,33 @@ impl Wal {
         Ok(())
     }
 
+    /// NEW: idempotency-токен батча (digest), пишется сразу после BEGIN.
+    pub fn append_idempotency(&mut self, lsn: u64, digest: &[u8]) -> Result<()> {
+        self.write_record(WAL_REC_IDEMPOTENCY, lsn, 0, digest)?;
+        // учёт байтов
+        self.inner
+            .bytes_since_last_fsync
+            .fetch_add((WAL_REC_HDR_SIZE + digest.len()) as u64, Ordering::Relaxed);
+        // вне батча — можно флашить по порогу
+        self.maybe_flush_by_threshold()?;
+        Ok(())
+    }
+
+    /// Логический кадр EXPIRY (payload — см. wal/expiry.rs). Пустой payload не пишется.
+    pub fn append_expiry(&mut self, lsn: u64, payload: &[u8]) -> Result<()> {
+        if payload.is_empty() {
+            return Ok(());
+        }
+        self.write_record(WAL_REC_EXPIRY, lsn, 0, payload)?;
+        // учёт байтов
+        self.inner
+            .bytes_since_last_fsync
+            .fetch_add((WAL_REC_HDR_SIZE + payload.len()) as u64, Ordering::Relaxed);
+        // вне батча — можно флашить по порогу
+        self.maybe_flush_by_threshold()?;
+        Ok(())
+    }
+
     pub fn append_truncate_marker(&mut self) -> Result<()> {
         self.write_record(WAL_REC_TRUNCATE, 0, 0, &[])?;
         // учёт байтов
@@ -138,15 +204,14 @@ impl Wal {
         if updates.is_empty() {
             return Ok(());
         }
-        let mut payload = Vec::with_capacity(updates.len() * 12);
-        let mut tmp4 = [0u8; 4];
-        let mut tmp8 = [0u8; 8];
-        for &(b, pid) in updates {
-            LittleEndian::write_u32(&mut tmp4, b);
-            payload.extend_from_slice(&tmp4);
-            LittleEndian::write_u64(&mut tmp8, pid);
-            payload.extend_from_slice(&tmp8);
-        }
+        // NEW: компактный delta-формат (см. wal/heads.rs) — только по opt-in, иначе legacy
+        let norm = normalize_head_updates(updates);
+        let payload = encode_heads_update_payload(&norm, self.heads_delta);
+        record_heads_update_frame(
+            norm.len() as u64,
+            payload.len() as u64,
+            norm.len() as u64 * 12,
+        );
         self.write_record(WAL_REC_HEADS_UPDATE, lsn, 0, &payload)?;
         // учёт байтов
         self.inner
@@ -178,25 +243,40 @@ impl Wal {
         if st.pending_max_lsn <= st.flushed_lsn {
             return Ok(());
         }
+        // NEW: приход коммита — наблюдение для адаптивного окна
+        let arrived = Instant::now();
+        let window_us = {
+            let mut a = self.inner.adaptive.lock().unwrap();
+            match a.as_mut() {
+                Some(c) => {
+                    c.on_arrival(arrived);
+                    Some(c.window_us())
+                }
+                None => None,
+            }
+        };
         let my_target = st.pending_max_lsn;
         if st.flushing {
             while st.flushed_lsn < my_target {
                 st = self.inner.cv.wait(st).unwrap();
             }
+            drop(st);
+            if window_us.is_some() {
+                self.observe_commit(arrived, None);
+            }
             return Ok(());
         }
         st.flushing = true;
         drop(st);
 
-        // Коалессация по времени (если задана)
-        let ms = self.inner.coalesce_ms.load(Ordering::Relaxed);
-        if ms > 0 {
+        // Коалессация по времени (если задана): адаптивное окно или статическое coalesce_ms
+        let wait = match window_us {
+            Some(us) => Duration::from_micros(us),
+            None => Duration::from_millis(self.inner.coalesce_ms.load(Ordering::Relaxed)),
+        };
+        if !wait.is_zero() {
             let guard = self.inner.flush.lock().unwrap();
-            let _ = self
-                .inner
-                .cv
-                .wait_timeout(guard, std::time::Duration::from_millis(ms))
-                .unwrap();
+            let _ = self.inner.cv.wait_timeout(guard, wait).unwrap();
         }
 
         let target = {
@@ -204,10 +284,14 @@ impl Wal {
             st2.pending_max_lsn
         };
 
+        let fsync_started = Instant::now();
         {
             let f = self.inner.file.lock().unwrap();
+            let bytes = self.inner.bytes_since_last_fsync.load(Ordering::Relaxed);
+            let _w = io_watch(IoOp::WalFsync, &self.inner.path, bytes);
             f.sync_all()?;
         }
+        let fsync_us = fsync_started.elapsed().as_micros() as u64;
 
         let pages_this_fsync = self.inner.pages_since_last_fsync.swap(0, Ordering::Relaxed);
         let _ = self.inner.bytes_since_last_fsync.swap(0, Ordering::Relaxed);
@@ -221,10 +305,33 @@ impl Wal {
         let _delta_lsn = st3.flushed_lsn.saturating_sub(prev_flushed);
         record_wal_fsync(pages_this_fsync);
         record_wal_flushed_lsn(st3.flushed_lsn);
+        drop(st3);
 
+        if window_us.is_some() {
+            self.observe_commit(arrived, Some(fsync_us));
+        }
         Ok(())
     }
 
+    // NEW: латентность коммита (и fsync лидера) — в адаптивный контроллер
+    fn observe_commit(&self, arrived: Instant, fsync_us: Option<u64>) {
+        let mut a = self.inner.adaptive.lock().unwrap();
+        let Some(c) = a.as_mut() else {
+            return;
+        };
+        c.on_commit_latency(arrived.elapsed().as_micros() as u64);
+        if let Some(us) = fsync_us {
+            if let Some(d) = c.on_fsync(us) {
+                record_wal_coalesce_decision(d, c.window_us(), c.p99_us());
+            }
+        }
+    }
+
+    /// Текущая длина файла WAL (байт, с заголовком).
+    pub fn file_len(&self) -> Result<u64> {
+        Ok(self.inner.file.lock().unwrap().metadata()?.len())
+    }
+
     pub fn maybe_truncate(&mut self) -> Result<()> {
         let mut f = self.inner.file.lock().unwrap();
         let len = f.metadata()?.len();
@@ -287,7 +394,7 @@ impl Wal {
 
 // ----------- helpers -----------
 
-fn wal_disable_fsync() -> bool {
+pub(crate) fn wal_disable_fsync() -> bool {
     static DISABLED: OnceLock<bool> = OnceLock::new();
     *DISABLED.get_or_init(|| {
         std::env::var("P1_WAL_DISABLE_FSYNC")
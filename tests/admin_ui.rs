@@ -0,0 +1,93 @@
+#![cfg(feature = "admin-ui")]
+
+use anyhow::Result;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+
+use QuiverDB::admin_ui::{route, AdminUi};
+use QuiverDB::db::Db;
+use QuiverDB::snapstore::SnapshotManager;
+
+#[test]
+fn admin_ui_serves_read_only_status_metrics_and_snapshots() -> Result<()> {
+    let root = unique_root("admin-ui");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"k", b"v")?;
+    }
+    let snap_id = {
+        let db = Db::open_ro(&root)?;
+        SnapshotManager::create_persisted(&db, Some("baseline"), &["ui"], None)?
+    };
+
+    let ui = AdminUi::bind("127.0.0.1:0", Some(root.clone()))?;
+    let addr = ui.local_addr().expect("tcp listener");
+    std::thread::spawn(move || ui.serve());
+
+    // Дашборд + restore-кнопки отключены
+    let (code, body) = http(addr, "GET", "/admin")?;
+    assert_eq!(code, 200);
+    assert!(body.contains("QuiverDB admin") && body.contains("disabled"));
+
+    // Статус: writer активен, пока открыт writer-хэндл
+    {
+        let _w = Db::open(&root)?;
+        let (code, body) = http(addr, "GET", "/admin/api/status")?;
+        assert_eq!(code, 200);
+        let v: serde_json::Value = serde_json::from_str(&body)?;
+        assert_eq!(v["writer_active"], true);
+        assert_eq!(v["directory"]["buckets"], 16);
+    }
+    let (_, body) = http(addr, "GET", "/admin/api/status")?;
+    let v: serde_json::Value = serde_json::from_str(&body)?;
+    assert_eq!(v["writer_active"], false);
+    assert_eq!(v["checkpoint"]["clean"], true);
+
+    let (_, body) = http(addr, "GET", "/admin/api/snapshots")?;
+    let v: serde_json::Value = serde_json::from_str(&body)?;
+    assert_eq!(v["snapshots"][0]["id"], snap_id.as_str());
+    assert_eq!(v["snapshots"][0]["message"], "baseline");
+
+    let (code, body) = http(addr, "GET", "/admin/api/metrics")?;
+    assert_eq!(code, 200);
+    assert!(body.contains("wal_appends_total"));
+
+    // Только чтение
+    let (code, _) = http(addr, "POST", "/admin/api/snapshots")?;
+    assert_eq!(code, 405);
+
+    // Не-/admin URL — не наш маршрут (встраивание в quiverdb_metrics)
+    assert!(route(Some(&root), "GET", "/metrics").is_none());
+    Ok(())
+}
+
+fn http(addr: std::net::SocketAddr, method: &str, path: &str) -> Result<(u16, String)> {
+    let mut s = TcpStream::connect(addr)?;
+    write!(
+        s,
+        "{} {} HTTP/1.1\r\nHost: x\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        method, path
+    )?;
+    let mut raw = String::new();
+    s.read_to_string(&mut raw)?;
+    let code = raw
+        .split_whitespace()
+        .nth(1)
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(0);
+    let body = raw.split_once("\r\n\r\n").map(|x| x.1).unwrap_or("");
+    Ok((code, body.to_string()))
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
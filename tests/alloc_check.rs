@@ -0,0 +1,155 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::{Db, PageOwner};
+use QuiverDB::dir::NO_PAGE;
+use QuiverDB::free::FreeList;
+
+fn non_empty_heads(db: &Db) -> Result<Vec<(u32, u64)>> {
+    let mut out = Vec::new();
+    for b in 0..db.dir.bucket_count {
+        let h = db.dir.head(b)?;
+        if h != NO_PAGE {
+            out.push((b, h));
+        }
+    }
+    Ok(out)
+}
+
+#[test]
+fn alloc_check_is_clean_on_healthy_db() -> Result<()> {
+    let root = unique_root("alloc-clean");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let mut db = Db::open(&root)?;
+    for i in 0..40u32 {
+        db.put(format!("k{}", i).as_bytes(), &[b'v'; 64])?;
+    }
+    db.put(b"big", &vec![b'x'; 20_000])?;
+    db.put(b"big", &vec![b'y'; 20_000])?;
+    db.compact_all()?;
+    db.sweep_orphan_overflow()?;
+
+    let rep = db.alloc_check()?;
+    assert!(rep.is_clean(), "{:?}", rep);
+    assert_eq!(rep.broken_chains, 0);
+    assert!(rep.reachable_pages > 0);
+    assert!(
+        rep.free_entries > 0,
+        "sweep should have freed old overflow pages"
+    );
+    // Doctor несёт тот же отчёт
+    assert_eq!(db.doctor_report()?.alloc, rep);
+    Ok(())
+}
+
+#[test]
+fn free_list_double_use_is_detected_and_fixed() -> Result<()> {
+    let root = unique_root("alloc-double");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let mut db = Db::open(&root)?;
+    for i in 0..40u32 {
+        db.put(format!("k{}", i).as_bytes(), format!("v{}", i).as_bytes())?;
+    }
+    let (bucket, head) = non_empty_heads(&db)?[0];
+    let total = db.pager.meta.next_page_id;
+
+    // Живая голова в free-листе, повтор и page_id вне диапазона
+    let fl = match FreeList::open(&root) {
+        Ok(fl) => fl,
+        Err(_) => FreeList::create(&root)?,
+    };
+    fl.push(head)?;
+    fl.push(head)?;
+    fl.push(total + 10)?;
+
+    let rep = db.alloc_check()?;
+    assert_eq!(rep.free_and_used, 1);
+    assert_eq!(rep.free_duplicates, 1);
+    assert_eq!(rep.free_out_of_range, 1);
+    assert_eq!(rep.double_use(), 2);
+    assert!(!rep.is_clean());
+    assert_eq!(rep.free_and_used_samples[0].page_id, head);
+    assert_eq!(
+        rep.free_and_used_samples[0].owner,
+        PageOwner::Bucket { bucket }
+    );
+
+    let fixed = db.alloc_check_fix()?;
+    assert_eq!(fixed.free_and_used, 1);
+    let f = fixed.fix.expect("fix report");
+    assert_eq!(f.free_before, 3);
+    assert!(!FreeList::open(&root)?.list()?.contains(&head));
+
+    let after = db.alloc_check()?;
+    assert!(after.is_clean(), "{:?}", after);
+    assert_eq!(after.unreferenced_pages, 0);
+    assert_eq!(after.free_entries, f.free_after);
+
+    // Новые записи не затирают живые данные
+    for i in 40..80u32 {
+        db.put(format!("k{}", i).as_bytes(), format!("v{}", i).as_bytes())?;
+    }
+    for i in 0..80u32 {
+        assert_eq!(
+            db.get(format!("k{}", i).as_bytes())?,
+            Some(format!("v{}", i).into_bytes())
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn cross_linked_and_broken_chains_are_reported() -> Result<()> {
+    let root = unique_root("alloc-cross");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let mut db = Db::open(&root)?;
+    for i in 0..40u32 {
+        db.put(format!("k{}", i).as_bytes(), b"v")?;
+    }
+    let heads = non_empty_heads(&db)?;
+    let (b1, h1) = heads[0];
+    let empty = (0..db.dir.bucket_count)
+        .find(|b| !heads.iter().any(|(hb, _)| hb == b))
+        .unwrap_or(heads[1].0);
+
+    // Второй бакет указывает на цепочку первого
+    db.set_dir_head(empty, h1)?;
+    let rep = db.alloc_check()?;
+    assert!(rep.cross_linked >= 1);
+    let c = rep.cross_link_samples[0];
+    assert_eq!(c.page_id, h1);
+    assert_eq!(
+        c.first,
+        PageOwner::Bucket {
+            bucket: b1.min(empty)
+        }
+    );
+    assert_eq!(
+        c.second,
+        PageOwner::Bucket {
+            bucket: b1.max(empty)
+        }
+    );
+
+    // Голова вне диапазона — обход оборван, ремонт отказывает
+    let total = db.pager.meta.next_page_id;
+    db.set_dir_head(empty, total + 100)?;
+    let rep = db.alloc_check()?;
+    assert_eq!(rep.broken_chains, 1);
+    let err = db.alloc_check_fix().unwrap_err();
+    assert!(format!("{:#}", err).contains("alloc fix refused"));
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
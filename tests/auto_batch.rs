@@ -0,0 +1,120 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use QuiverDB::db::{AutoBatchFlush, AutoBatchLimits, Db};
+
+fn limits(max_ops: usize, max_bytes: usize, max_delay: Duration) -> AutoBatchLimits {
+    AutoBatchLimits {
+        max_ops,
+        max_bytes,
+        max_delay,
+    }
+}
+
+#[test]
+fn flushes_on_op_and_byte_thresholds() -> Result<()> {
+    let root = unique_root("autobatch-thr");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    let mut db = Db::open(&root)?;
+    let commits0 = db.stats().commits;
+    {
+        let mut ab = db.auto_batcher(limits(10, 0, Duration::ZERO))?;
+        for i in 0..9 {
+            assert_eq!(ab.put(format!("k{}", i).as_bytes(), b"v")?, None);
+        }
+        assert_eq!(ab.pending(), 9);
+        assert_eq!(ab.db().get(b"k0")?, None);
+        // read-your-writes до сброса
+        assert_eq!(ab.get(b"k0")?, Some(b"v".to_vec()));
+        let f = ab.put(b"k9", b"v")?.expect("10th op flushes");
+        assert_eq!(f.ops, 10);
+        assert_eq!(ab.pending(), 0);
+        assert_eq!(ab.flushes(), 1);
+        assert_eq!(ab.db().get(b"k9")?, Some(b"v".to_vec()));
+    }
+    // Один батч — один коммит
+    assert_eq!(db.stats().commits, commits0 + 1);
+    assert_eq!(db.stats().puts, 10);
+
+    let mut ab = db.auto_batcher(limits(0, 1000, Duration::ZERO))?;
+    assert_eq!(ab.put(b"a", &[1u8; 600])?, None);
+    let f = ab.put(b"b", &[2u8; 600])?.expect("byte limit flushes");
+    assert_eq!(
+        f,
+        AutoBatchFlush {
+            ops: 2,
+            bytes: 1202
+        }
+    );
+    assert_eq!(ab.flush()?, AutoBatchFlush::default());
+    Ok(())
+}
+
+#[test]
+fn flushes_after_delay_and_on_drop() -> Result<()> {
+    let root = unique_root("autobatch-time");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    let mut db = Db::open(&root)?;
+    {
+        let mut ab = db.auto_batcher(limits(0, 0, Duration::from_millis(200)))?;
+        ab.put(b"t1", b"x")?;
+        assert_eq!(ab.tick()?, None);
+        std::thread::sleep(Duration::from_millis(250));
+        assert_eq!(ab.tick()?.map(|f| f.ops), Some(1));
+        assert_eq!(ab.db().get(b"t1")?, Some(b"x".to_vec()));
+
+        // Просроченная очередь уходит до следующей операции
+        ab.put(b"t2", b"y")?;
+        std::thread::sleep(Duration::from_millis(250));
+        assert_eq!(ab.put(b"t3", b"z")?.map(|f| f.ops), Some(1));
+        assert_eq!(ab.pending(), 1);
+        // t3 остаётся в очереди и уходит на drop
+    }
+    assert_eq!(db.get(b"t2")?, Some(b"y".to_vec()));
+    assert_eq!(db.get(b"t3")?, Some(b"z".to_vec()));
+
+    let mut ab = db.auto_batcher(AutoBatchLimits::default())?;
+    ab.put(b"t4", b"w")?;
+    assert_eq!(ab.finish()?.ops, 1);
+    assert_eq!(db.get(b"t4")?, Some(b"w".to_vec()));
+    Ok(())
+}
+
+#[test]
+fn preserves_operation_order_and_rejects_readers() -> Result<()> {
+    let root = unique_root("autobatch-order");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"old", b"1")?;
+        let mut ab = db.auto_batcher(AutoBatchLimits::default())?;
+        ab.put(b"k", b"first")?;
+        ab.put(b"k", b"second")?;
+        ab.del(b"old")?;
+        ab.put(b"gone", b"x")?;
+        ab.del(b"gone")?;
+        assert_eq!(ab.get(b"k")?, Some(b"second".to_vec()));
+        assert_eq!(ab.get(b"old")?, None);
+        assert_eq!(ab.get(b"gone")?, None);
+        assert_eq!(ab.finish()?.ops, 5);
+        assert_eq!(db.get(b"k")?, Some(b"second".to_vec()));
+        assert_eq!(db.get(b"old")?, None);
+        assert_eq!(db.get(b"gone")?, None);
+    }
+    let mut ro = Db::open_ro(&root)?;
+    assert!(ro.auto_batcher(AutoBatchLimits::default()).is_err());
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::backup::{
+    backup_to_path, partial_path, restore_from_path, BackupOptions, RestoreOptions,
+};
+use QuiverDB::db::Db;
+
+#[test]
+fn backup_to_path_finalizes_atomically() -> Result<()> {
+    let src = unique_root("part-src");
+    fs::create_dir_all(&src)?;
+    Db::init(&src, 4096, 8)?;
+    let out = unique_root("part-out").with_extension("p2bk");
+    {
+        let mut db = Db::open(&src)?;
+        db.put(b"k", b"v1")?;
+        backup_to_path(&db, &out, &BackupOptions::default())?;
+    }
+    assert!(out.exists());
+    assert!(!partial_path(&out).exists(), "no .partial after finalize");
+
+    let dst = unique_root("part-dst");
+    restore_from_path(&dst, &out, &RestoreOptions::default())?;
+    let db = Db::open_ro(&dst)?;
+    assert_eq!(db.get(b"k")?.as_deref(), Some(&b"v1"[..]));
+    Ok(())
+}
+
+#[test]
+fn restore_refuses_partial_backup() -> Result<()> {
+    let src = unique_root("part-src2");
+    fs::create_dir_all(&src)?;
+    Db::init(&src, 4096, 8)?;
+    let out = unique_root("part-out2").with_extension("p2bk");
+    {
+        let mut db = Db::open(&src)?;
+        db.put(b"k", b"v1")?;
+        backup_to_path(&db, &out, &BackupOptions::default())?;
+    }
+    let good = fs::read(&out)?;
+
+    // Процесс бэкапа «умер» посередине: есть только обрезанный .partial
+    let part = partial_path(&out);
+    fs::write(&part, &good[..good.len() / 2])?;
+    fs::remove_file(&out)?;
+
+    let dst = unique_root("part-dst2");
+    for p in [&out, &part] {
+        let err = restore_from_path(&dst, p, &RestoreOptions::default()).unwrap_err();
+        let msg = format!("{:#}", err);
+        assert!(msg.contains("is incomplete"), "msg: {}", msg);
+        assert!(msg.contains(".partial"), "msg: {}", msg);
+    }
+    assert!(!dst.join("meta").exists(), "target untouched");
+
+    // Прежний финальный архив рядом с незавершённым .partial остаётся валидным
+    fs::write(&out, &good)?;
+    restore_from_path(&dst, &out, &RestoreOptions::default())?;
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,236 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use QuiverDB::backup_sched::{
+    backup_chain, lifecycle_keep, list_offsite_backups, BackupSchedule, BackupScheduler,
+    CommandTarget, DirTarget, OffsiteBackupEntry,
+};
+use QuiverDB::db::maint_sched::read_maint_audit;
+use QuiverDB::db::{Db, MaintSchedule, MaintScheduler};
+use QuiverDB::metrics;
+
+const HOUR: u64 = 3_600_000;
+const DAY: u64 = 24 * HOUR;
+
+fn fresh_db(root: &Path) -> Result<Db> {
+    fs::create_dir_all(root)?;
+    Db::init(root, 4096, 16)?;
+    Db::open(root)
+}
+
+fn sched(full_every: u32, keep_daily: u32, keep_weekly: u32) -> BackupSchedule {
+    BackupSchedule {
+        interval_secs: 3600,
+        full_every,
+        keep_daily,
+        keep_weekly,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn incremental_chain_is_uploaded_verified_and_restorable() -> Result<()> {
+    let root = unique_root("bks-chain");
+    let remote = unique_root("bks-chain-remote");
+    let mut db = fresh_db(&root)?;
+
+    let bs = BackupScheduler::new(Box::new(DirTarget::new(&remote)?), sched(2, 0, 0))?;
+    let mut sch = MaintScheduler::new(&root, MaintSchedule::default())?.with_backup(bs);
+    let t0 = 20_000 * DAY;
+    for i in 0..4u64 {
+        for k in 0..20u64 {
+            db.put(
+                format!("k{}-{}", i, k).as_bytes(),
+                format!("v{}", i).as_bytes(),
+            )?;
+        }
+        db.del(format!("k{}-0", i.saturating_sub(1)).as_bytes())?;
+        let rep = sch.tick_at(&mut db, t0 + i * HOUR)?;
+        assert!(rep.errors.is_empty(), "{:?}", rep.errors);
+        assert!(rep.offsite.and_then(|o| o.backup).is_some());
+        // интервал не истёк — бэкапа нет
+        let idle = sch.tick_at(&mut db, t0 + i * HOUR + HOUR / 2)?;
+        assert!(idle.offsite.is_none());
+    }
+
+    // full, incr, incr, full (full_every=2)
+    let all = list_offsite_backups(&DirTarget::new(&remote)?)?;
+    let kinds: Vec<&str> = all.iter().map(|e| e.kind.as_str()).collect();
+    assert_eq!(kinds, vec!["full", "incr", "incr", "full"]);
+    assert_eq!(all[1].parent.as_deref(), Some(all[0].name.as_str()));
+    assert_eq!(all[2].since_lsn, all[1].lsn);
+    assert_eq!(backup_chain(&all, &all[2].name)?.len(), 3);
+
+    // хэш-манифест совпадает с объектом на цели
+    for e in &all {
+        let bytes = fs::read(remote.join(&e.name))?;
+        assert_eq!(bytes.len() as u64, e.bytes);
+    }
+
+    // restore-test по новейшей цепочке и ручное восстановление incr-цепочки
+    let bs = BackupScheduler::new(Box::new(DirTarget::new(&remote)?), sched(2, 0, 0))?;
+    let t = bs.restore_test()?.expect("backups exist");
+    assert_eq!(t.name, all[3].name);
+    assert_eq!(t.chain_len, 1);
+
+    let dst = unique_root("bks-chain-dst");
+    for e in backup_chain(&all, &all[2].name)? {
+        let f = fs::File::open(remote.join(&e.name))?;
+        QuiverDB::backup::restore_from_reader(&dst, f, true)?;
+    }
+    let r = Db::open_ro(&dst)?;
+    assert_eq!(r.get(b"k2-5")?.as_deref(), Some(&b"v2"[..]));
+    assert!(r.get(b"k1-0")?.is_none());
+    assert!(r.get(b"k3-1")?.is_none());
+    drop(r);
+
+    let audit = read_maint_audit(&root)?;
+    assert_eq!(
+        audit.iter().filter(|e| e.task == "backup" && e.ok).count(),
+        4
+    );
+    Ok(())
+}
+
+#[test]
+fn lifecycle_keeps_daily_weekly_and_whole_chains() -> Result<()> {
+    let e = |day: u64, hour: u64, kind: &str, parent: Option<&str>| OffsiteBackupEntry {
+        name: format!("qdb-{:013}-{}.qbk", day * DAY + hour * HOUR, kind),
+        kind: kind.to_string(),
+        parent: parent.map(str::to_string),
+        since_lsn: 0,
+        lsn: 0,
+        bytes: 0,
+        sha256: String::new(),
+        created_unix_ms: day * DAY + hour * HOUR,
+        codec: "none".into(),
+    };
+    // дни 7000..7020 (неделя = день/7): по бэкапу в день, full каждые 4 дня
+    let mut all: Vec<OffsiteBackupEntry> = Vec::new();
+    for d in 7000..7021u64 {
+        let parent = if (d - 7000) % 4 == 0 {
+            None
+        } else {
+            Some(all.last().unwrap().name.clone())
+        };
+        let kind = if parent.is_none() { "full" } else { "incr" };
+        all.push(e(d, 3, kind, parent.as_deref()));
+    }
+    let name = |d: u64| all[(d - 7000) as usize].name.clone();
+
+    // 2 дня + 2 недели; дни 7020 (full) и 7019 (incr → цепочка 7016..7019);
+    // недели: 1002 (7014..7020) → 7020, 1001 (7007..7013) → 7013 (цепочка 7012..7013)
+    let keep = lifecycle_keep(&all, 2, 2)?;
+    let mut want: Vec<String> = [7012, 7013, 7016, 7017, 7018, 7019, 7020]
+        .iter()
+        .map(|d| name(*d))
+        .collect();
+    want.sort();
+    assert_eq!(keep, want);
+
+    // 0/0 — всё
+    assert_eq!(lifecycle_keep(&all, 0, 0)?.len(), all.len());
+
+    // разорванная цепочка — ошибка (prune её не трогает)
+    let broken = vec![e(7000, 1, "incr", Some("qdb-missing.qbk"))];
+    assert!(lifecycle_keep(&broken, 1, 0).is_err());
+    Ok(())
+}
+
+#[test]
+fn prune_applies_lifecycle_on_the_target() -> Result<()> {
+    let root = unique_root("bks-prune");
+    let remote = unique_root("bks-prune-remote");
+    let mut db = fresh_db(&root)?;
+    let pruned0 = metrics::snapshot().backup_sched_pruned;
+
+    let mut bs = BackupScheduler::new(Box::new(DirTarget::new(&remote)?), sched(0, 2, 0))?;
+    let t0 = 30_000 * DAY;
+    let mut pruned = Vec::new();
+    for d in 0..4u64 {
+        db.put(format!("day{}", d).as_bytes(), b"x")?;
+        let rep = bs.tick_at(&db, t0 + d * DAY);
+        assert!(rep.errors.is_empty(), "{:?}", rep.errors);
+        pruned.extend(rep.pruned);
+    }
+    // каждый бэкап полный (full_every=0), хранятся два последних дня
+    let left = list_offsite_backups(bs.target())?;
+    assert_eq!(left.len(), 2);
+    assert!(left.iter().all(|e| e.is_full()));
+    assert_eq!(pruned.len(), 2);
+    let files: Vec<String> = fs::read_dir(&remote)?
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(files.len(), 4, "{:?}", files);
+    assert!(metrics::snapshot().backup_sched_pruned >= pruned0 + 2);
+
+    // незавершённая выгрузка (архив без манифеста) убирается prune
+    fs::write(remote.join("qdb-0000000000001-full.qbk"), b"partial")?;
+    bs.prune()?;
+    assert!(!remote.join("qdb-0000000000001-full.qbk").exists());
+    Ok(())
+}
+
+#[test]
+fn command_target_verifies_uploads_and_restore_test_catches_tampering() -> Result<()> {
+    let root = unique_root("bks-cmd");
+    let remote = unique_root("bks-cmd-remote");
+    fs::create_dir_all(&remote)?;
+    let mut db = fresh_db(&root)?;
+    db.put(b"a", b"1")?;
+    let r = remote.display().to_string();
+    let target = |put: String| CommandTarget {
+        put_cmd: put,
+        get_cmd: format!("cat '{}/{{name}}'", r),
+        list_cmd: format!("ls '{}'", r),
+        delete_cmd: format!("rm -f '{}/{{name}}'", r),
+    };
+
+    // цель теряет байты — проверка после выгрузки отвергает бэкап, манифеста нет
+    let failures0 = metrics::snapshot().backup_sched_failures;
+    let mut bad = BackupScheduler::new(
+        Box::new(target(format!("head -c 100 > '{}/{{name}}'", r))),
+        sched(7, 0, 0),
+    )?;
+    let rep = bad.tick_at(&db, 40_000 * DAY);
+    assert!(rep.backup.is_none());
+    assert!(rep.errors[0].contains("verify"), "{:?}", rep.errors);
+    assert!(list_offsite_backups(bad.target())?.is_empty());
+    assert!(metrics::snapshot().backup_sched_failures > failures0);
+    let audit = read_maint_audit(&root)?;
+    assert!(audit.iter().any(|e| e.task == "backup" && !e.ok));
+
+    // исправная цель
+    let mut good = BackupScheduler::new(
+        Box::new(target(format!("cat > '{}/{{name}}'", r))),
+        BackupSchedule {
+            restore_test_interval_secs: 3600,
+            ..sched(7, 0, 0)
+        },
+    )?;
+    let rep = good.tick_at(&db, 40_001 * DAY);
+    assert!(rep.errors.is_empty(), "{:?}", rep.errors);
+    let e = rep.backup.expect("backup uploaded");
+    assert!(rep.restore_test.is_some());
+    assert_eq!(good.target().list()?.len(), 2);
+
+    // порча объекта на цели — restore-test падает на сверке SHA-256
+    let p = remote.join(&e.name);
+    let mut bytes = fs::read(&p)?;
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xFF;
+    fs::write(&p, bytes)?;
+    let err = good.restore_test().unwrap_err();
+    assert!(format!("{:#}", err).contains("sha256"), "{:#}", err);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
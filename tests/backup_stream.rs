@@ -0,0 +1,133 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::backup::{
+    backup_to_writer, backup_to_writer_with, restore_from_reader, BackupCompression, BackupOptions,
+};
+use QuiverDB::db::Db;
+
+#[test]
+fn streaming_backup_full_and_incremental_roundtrip() -> Result<()> {
+    let src = unique_root("bks-src");
+    fs::create_dir_all(&src)?;
+    Db::init(&src, 4096, 16)?;
+
+    // [1] Полный бэкап в память (вместо stdout/S3) при открытом writer'е
+    let mut full = Vec::new();
+    let full_man = {
+        let mut db = Db::open(&src)?;
+        for i in 0..50u32 {
+            db.put(
+                format!("k{:03}", i).as_bytes(),
+                format!("v{}", i).as_bytes(),
+            )?;
+        }
+        db.put(b"big", &vec![7u8; 10_000])?; // overflow-цепочка
+        backup_to_writer(&db, &mut full, 0)?
+    };
+    assert!(full_man.pages > 0);
+    assert_eq!(full_man.heads, 16);
+
+    let dst = unique_root("bks-dst");
+    let rep = restore_from_reader(&dst, full.as_slice(), true)?;
+    assert_eq!(rep.pages, full_man.pages);
+    {
+        let db = Db::open_ro(&dst)?;
+        assert_eq!(db.get(b"k007")?.as_deref(), Some(&b"v7"[..]));
+        assert_eq!(db.get(b"big")?.map(|v| v.len()), Some(10_000));
+    }
+
+    // [2] Полный бэкап не разворачивается поверх существующей БД
+    assert!(restore_from_reader(&dst, full.as_slice(), false).is_err());
+
+    // [3] Инкремент: только новые страницы
+    let mut inc = Vec::new();
+    let inc_man = {
+        let mut db = Db::open(&src)?;
+        db.put(b"k007", b"updated")?;
+        db.put(b"fresh", b"1")?;
+        backup_to_writer(&db, &mut inc, full_man.lsn)?
+    };
+    assert!(inc_man.pages > 0 && inc_man.pages < full_man.pages);
+    restore_from_reader(&dst, inc.as_slice(), true)?;
+    {
+        let db = Db::open_ro(&dst)?;
+        assert_eq!(db.get(b"k007")?.as_deref(), Some(&b"updated"[..]));
+        assert_eq!(db.get(b"fresh")?.as_deref(), Some(&b"1"[..]));
+        assert_eq!(db.get(b"k010")?.as_deref(), Some(&b"v10"[..]));
+    }
+
+    // [4] Усечённый поток (нет END) отвергается
+    let cut = unique_root("bks-cut");
+    let r = restore_from_reader(&cut, &full[..full.len() - 10], false);
+    assert!(r.is_err());
+    Ok(())
+}
+
+#[test]
+fn streaming_backup_zstd_compression() -> Result<()> {
+    assert_eq!(
+        "zstd:7".parse::<BackupCompression>()?,
+        BackupCompression::Zstd(7)
+    );
+    assert!("zstd:99".parse::<BackupCompression>().is_err());
+    assert!("gzip".parse::<BackupCompression>().is_err());
+
+    let src = unique_root("bksz-src");
+    fs::create_dir_all(&src)?;
+    Db::init(&src, 4096, 8)?;
+    let (mut raw, mut packed) = (Vec::new(), Vec::new());
+    let zstd = BackupOptions {
+        since_lsn: 0,
+        compress: BackupCompression::Zstd(3),
+        ..Default::default()
+    };
+    let man = {
+        let mut db = Db::open(&src)?;
+        for i in 0..40u32 {
+            db.put(format!("key-{:04}", i).as_bytes(), &[b'x'; 200])?;
+        }
+        backup_to_writer(&db, &mut raw, 0)?;
+        backup_to_writer_with(&db, &mut packed, &zstd)?
+    };
+    assert_eq!(man.codec, "zstd");
+    assert!(man.stored_bytes < man.page_bytes + man.wal_bytes);
+    assert!(
+        packed.len() < raw.len() / 2,
+        "{} vs {}",
+        packed.len(),
+        raw.len()
+    );
+
+    let dst = unique_root("bksz-dst");
+    let rep = restore_from_reader(&dst, packed.as_slice(), true)?;
+    assert_eq!((rep.codec.as_str(), rep.compress_level), ("zstd", 3));
+
+    // Инкремент со сжатием: только страницы после базы
+    let mut inc = Vec::new();
+    {
+        let mut db = Db::open(&src)?;
+        db.put(b"key-0003", b"new")?;
+        let opts = BackupOptions {
+            since_lsn: man.lsn,
+            ..zstd
+        };
+        let m = backup_to_writer_with(&db, &mut inc, &opts)?;
+        assert!(m.pages < man.pages);
+    }
+    restore_from_reader(&dst, inc.as_slice(), true)?;
+    let db = Db::open_ro(&dst)?;
+    assert_eq!(db.get(b"key-0003")?.as_deref(), Some(&b"new"[..]));
+    assert_eq!(db.get(b"key-0039")?.map(|v| v.len()), Some(200));
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
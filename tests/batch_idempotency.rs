@@ -0,0 +1,90 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+use QuiverDB::meta::set_clean_shutdown;
+use QuiverDB::wal::idempotency::idem_tokens_path;
+use QuiverDB::wal::reader::WalStreamReader;
+use QuiverDB::wal::{IdemApplyGate, WAL_FILE, WAL_REC_PAGE_IMAGE};
+
+#[test]
+fn idempotent_batches_are_deduplicated_across_recovery_and_follower_apply() -> Result<()> {
+    let root = unique_root("batch-idem");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    let wal_copy = root.join("wal.copy");
+
+    // [1] Повторная отправка батча с тем же токеном — пропускается
+    {
+        let mut db = Db::open(&root)?;
+        assert!(db.batch_idempotent(b"req-1", |b| b.put(b"k", b"v1"))?);
+        let mut called = false;
+        let applied = db.batch_idempotent(b"req-1", |b| {
+            called = true;
+            b.put(b"k", b"v2")
+        })?;
+        assert!(!applied);
+        assert!(!called, "closure of a duplicate batch must not run");
+        assert_eq!(db.get(b"k")?.as_deref(), Some(&b"v1"[..]));
+
+        assert!(db.batch_idempotent(b"req-2", |b| b.put(b"k2", b"x"))?);
+        assert!(db.idempotency_token_lsn(b"req-2")?.is_some());
+        // Снимок WAL до clean shutdown (в нём оба батча с кадрами IDEMPOTENCY)
+        fs::copy(root.join(WAL_FILE), &wal_copy)?;
+    }
+    {
+        let mut db = Db::open(&root)?;
+        assert!(!db.batch_idempotent(b"req-2", |b| b.put(b"k2", b"y"))?);
+    }
+
+    // [2] «Краш» до записи side-car: реплей восстанавливает токены из WAL
+    fs::copy(&wal_copy, root.join(WAL_FILE))?;
+    fs::remove_file(idem_tokens_path(&root))?;
+    set_clean_shutdown(&root, false)?;
+    {
+        let mut db = Db::open(&root)?;
+        assert!(!db.batch_idempotent(b"req-1", |b| b.put(b"k", b"v3"))?);
+        assert!(db.batch_idempotent(b"req-3", |b| b.put(b"k3", b"z"))?);
+        assert_eq!(db.get(b"k")?.as_deref(), Some(&b"v1"[..]));
+    }
+
+    // [3] Follower: повторная доставка батчей с известным токеном пропускается
+    let follower = unique_root("batch-idem-follower");
+    fs::create_dir_all(&follower)?;
+    let mut gate = IdemApplyGate::open(&follower)?;
+    let pages_pass = |gate: &mut IdemApplyGate| -> Result<u64> {
+        let mut f = fs::File::open(&wal_copy)?;
+        let len = f.metadata()?.len();
+        let mut rdr = WalStreamReader::new();
+        let mut pos = QuiverDB::wal::WAL_HDR_SIZE as u64;
+        let mut applied = 0u64;
+        while let Some((rec, next)) = rdr.read_next(&mut f, pos, len)? {
+            if gate.observe(rec.rec_type, rec.lsn, &rec.payload)?
+                && rec.rec_type == WAL_REC_PAGE_IMAGE
+            {
+                applied += 1;
+            }
+            pos = next;
+        }
+        Ok(applied)
+    };
+    assert!(pages_pass(&mut gate)? > 0);
+    assert_eq!(gate.skipped_batches, 0);
+    assert_eq!(pages_pass(&mut gate)?, 0);
+    assert_eq!(gate.skipped_batches, 2);
+
+    // Кольцо follower'а персистентно
+    let mut gate2 = IdemApplyGate::open(&follower)?;
+    assert_eq!(pages_pass(&mut gate2)?, 0);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,118 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::bloom::BloomSidecar;
+use QuiverDB::config::{BloomAutoRefresh, QuiverConfig};
+use QuiverDB::db::Db;
+
+fn bloom_has(db: &Db, sc: &BloomSidecar, key: &[u8]) -> Result<bool> {
+    let bucket = db.dir.bucket_of_key(key, db.pager.meta.hash_kind);
+    sc.test(bucket, key)
+}
+
+/// Дрейф LSN: одиночный put после батча сразу перестраивает затронутый бакет.
+/// Пропуски: после N пропусков фильтра в хэндле, устаревший ещё до открытия фильтр
+/// перестраивается целиком.
+#[test]
+fn bloom_auto_refresh_by_drift_and_skips() -> Result<()> {
+    let root = unique_root("bloom-auto");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+
+    // 1) lsn_drift=1: фильтр свеж после каждой одиночной записи
+    {
+        let cfg = QuiverConfig::default().with_bloom_auto_refresh(BloomAutoRefresh::new(0, 1));
+        let mut db = Db::open_with_config(&root, cfg)?;
+        db.batch(|b| {
+            b.put(b"a1", b"1")?;
+            b.put(b"a2", b"2")
+        })?;
+        db.put(b"single", b"s")?;
+        db.del(b"a1")?;
+
+        let sc = BloomSidecar::open_ro(&root)?;
+        assert!(sc.is_fresh_for_db(&db), "refreshed after drift");
+        assert!(bloom_has(&db, &sc, b"single")?);
+        assert!(bloom_has(&db, &sc, b"a2")?);
+    }
+
+    // 2) Без политики: одиночный put оставляет фильтр устаревшим
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"late", b"l")?;
+        let sc = BloomSidecar::open_ro(&root)?;
+        assert!(!sc.is_fresh_for_db(&db));
+    }
+
+    // 3) skipped_stale=3: фильтр устарел до открытия — полный ребилд на третьем пропуске
+    {
+        let cfg = QuiverConfig::default().with_bloom_auto_refresh(BloomAutoRefresh::new(3, 0));
+        let db = Db::open_with_config(&root, cfg)?;
+        assert_eq!(db.get(b"missing")?, None);
+        assert_eq!(db.get(b"missing")?, None);
+        assert!(!BloomSidecar::open_ro(&root)?.is_fresh_for_db(&db));
+        assert_eq!(db.get(b"late")?.as_deref(), Some(&b"l"[..]));
+
+        let sc = BloomSidecar::open_ro(&root)?;
+        assert!(sc.is_fresh_for_db(&db), "refreshed after 3 skips");
+        assert!(bloom_has(&db, &sc, b"late")?);
+        assert!(bloom_has(&db, &sc, b"single")?);
+    }
+
+    // 4) RO-читатель видит свежий фильтр: промах отсекается bloom'ом
+    let ro = Db::open_ro(&root)?;
+    let sc = BloomSidecar::open_ro(&root)?;
+    assert!(sc.is_fresh_for_db(&ro));
+    assert_eq!(ro.get(b"late")?.as_deref(), Some(&b"l"[..]));
+    Ok(())
+}
+
+/// Batch не объявляет фильтр свежим без ключей одиночных put'ов, сделанных до него;
+/// refresh_bloom_touched — ручной запуск того же прохода.
+#[test]
+fn bloom_batch_includes_earlier_single_puts() -> Result<()> {
+    let root = unique_root("bloom-auto-batch");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+
+    let mut db = Db::open(&root)?;
+    db.batch(|b| b.put(b"first", b"1"))?;
+    for i in 0..8u32 {
+        db.put(format!("single-{i}").as_bytes(), b"s")?;
+    }
+    db.batch(|b| b.put(b"second", b"2"))?;
+
+    let sc = BloomSidecar::open_ro(&root)?;
+    assert!(sc.is_fresh_for_db(&db));
+    for i in 0..8u32 {
+        assert!(bloom_has(&db, &sc, format!("single-{i}").as_bytes())?);
+    }
+
+    db.put(b"manual", b"m")?;
+    assert!(!BloomSidecar::open_ro(&root)?.is_fresh_for_db(&db));
+    assert_eq!(db.refresh_bloom_touched()?, 1);
+    let sc = BloomSidecar::open_ro(&root)?;
+    assert!(sc.is_fresh_for_db(&db));
+    assert!(bloom_has(&db, &sc, b"manual")?);
+    assert_eq!(db.refresh_bloom_touched()?, 0, "nothing touched since");
+
+    // Строковый конфиг
+    let mut cfg = QuiverConfig::default();
+    cfg.set_field("bloom_auto_refresh", "64,100")?;
+    assert_eq!(cfg.bloom_auto_refresh, BloomAutoRefresh::new(64, 100));
+    assert_eq!(
+        cfg.field_value("bloom_auto_refresh").as_deref(),
+        Some("64,100")
+    );
+    assert!(cfg.set_field("bloom_auto_refresh", "x,1").is_err());
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
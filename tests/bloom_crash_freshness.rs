@@ -0,0 +1,133 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use QuiverDB::bloom::BloomSidecar;
+use QuiverDB::db::Db;
+use QuiverDB::meta::{read_meta, set_clean_shutdown};
+use QuiverDB::wal::WAL_FILE;
+
+fn bloom_has(db: &Db, sc: &BloomSidecar, key: &[u8]) -> Result<bool> {
+    let bucket = db.dir.bucket_of_key(key, db.pager.meta.hash_kind);
+    sc.test(bucket, key)
+}
+
+/// Крах после коммита батча, но до записи bloom.bin: реплей WAL доводит фильтр до свежего,
+/// и ключи батча в нём есть (без ложных промахов).
+#[test]
+fn bloom_reconciled_after_crash_between_commit_and_bloom_write() -> Result<()> {
+    let root = unique_root("bloom-crash");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    let (wal_copy, bloom_copy) = (root.join("wal.copy"), root.join("bloom.copy"));
+
+    {
+        let mut db = Db::open(&root)?;
+        db.batch(|b| {
+            b.put(b"a1", b"1")?;
+            b.put(b"a2", b"2")
+        })?;
+        fs::copy(root.join("bloom.bin"), &bloom_copy)?;
+        db.batch(|b| {
+            for i in 0..20 {
+                b.put(format!("b{}", i).as_bytes(), b"v")?;
+            }
+            Ok(())
+        })?;
+        fs::copy(root.join(WAL_FILE), &wal_copy)?;
+    }
+    // «Крах»: WAL не усечён, bloom.bin — до второго батча
+    crash_restore(&root, &wal_copy, Some(&bloom_copy))?;
+
+    {
+        let db = Db::open(&root)?;
+        let sc = BloomSidecar::open_ro(&root)?;
+        assert!(sc.is_fresh_for_db(&db), "fresh after replay");
+        for i in 0..20 {
+            assert!(bloom_has(&db, &sc, format!("b{}", i).as_bytes())?);
+        }
+        assert!(bloom_has(&db, &sc, b"a1")?);
+        assert!(QuiverDB::metrics::snapshot().bloom_replay_reconciled >= 1);
+    }
+
+    let ro = Db::open_ro(&root)?;
+    assert!(BloomSidecar::open_ro(&root)?.is_fresh_for_db(&ro));
+    assert_eq!(ro.get(b"b7")?.as_deref(), Some(&b"v"[..]));
+    Ok(())
+}
+
+/// Фильтр с last_lsn впереди БД (WAL потерян) при открытии сбрасывается в устаревший.
+#[test]
+fn bloom_ahead_of_db_is_invalidated_on_open() -> Result<()> {
+    let root = unique_root("bloom-ahead");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    {
+        let mut db = Db::open(&root)?;
+        db.batch(|b| b.put(b"k", b"v"))?;
+    }
+    let m = read_meta(&root)?.last_lsn;
+    BloomSidecar::open(&root)?.set_last_lsn(m + 100)?;
+
+    {
+        let db = Db::open(&root)?;
+        let sc = BloomSidecar::open_ro(&root)?;
+        assert_eq!(sc.last_lsn(), 0);
+        assert!(!sc.is_fresh_for_db(&db));
+        assert_eq!(db.get(b"k")?.as_deref(), Some(&b"v"[..]));
+        assert!(QuiverDB::metrics::snapshot().bloom_replay_invalidated >= 1);
+    }
+    Ok(())
+}
+
+/// WAL не покрывает коммиты после last_lsn фильтра — фильтр остаётся устаревшим,
+/// а не объявляется свежим без их ключей.
+#[test]
+fn bloom_stays_stale_when_wal_does_not_cover_the_gap() -> Result<()> {
+    let root = unique_root("bloom-gap");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    let (wal_copy, bloom_copy) = (root.join("wal.copy"), root.join("bloom.copy"));
+
+    let bloom_lsn;
+    {
+        let mut db = Db::open(&root)?;
+        db.batch(|b| b.put(b"a", b"1"))?;
+        bloom_lsn = db.pager.meta.last_lsn;
+        fs::copy(root.join("bloom.bin"), &bloom_copy)?;
+        // коммит мимо bloom; WAL этой сессии усекается при закрытии
+        db.put(b"single", b"s")?;
+    }
+    {
+        let mut db = Db::open(&root)?;
+        db.batch(|b| b.put(b"c", b"3"))?;
+        fs::copy(root.join(WAL_FILE), &wal_copy)?;
+    }
+    crash_restore(&root, &wal_copy, Some(&bloom_copy))?;
+
+    let db = Db::open(&root)?;
+    let sc = BloomSidecar::open_ro(&root)?;
+    assert_eq!(sc.last_lsn(), bloom_lsn);
+    assert!(!sc.is_fresh_for_db(&db));
+    assert_eq!(db.get(b"single")?.as_deref(), Some(&b"s"[..]));
+    assert_eq!(db.get(b"c")?.as_deref(), Some(&b"3"[..]));
+    Ok(())
+}
+
+fn crash_restore(root: &Path, wal_copy: &Path, bloom_copy: Option<&Path>) -> Result<()> {
+    fs::copy(wal_copy, root.join(WAL_FILE))?;
+    if let Some(b) = bloom_copy {
+        fs::copy(b, root.join("bloom.bin"))?;
+    }
+    set_clean_shutdown(root, false)?;
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,143 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use QuiverDB::db::Db;
+use QuiverDB::dir::BucketRouting;
+use QuiverDB::meta::{read_meta, FEATURE_BUCKET_ROUTING, HASH_KIND_XX64_SEED0};
+use QuiverDB::metrics;
+
+// Метрики процесс-глобальные — тесты файла идут по очереди.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+const PAGE: u32 = 4096;
+const BUCKETS: u32 = 64;
+
+fn fill(db: &mut Db, tenants: &[&str], per_tenant: usize) -> Result<()> {
+    for t in tenants {
+        for i in 0..per_tenant {
+            let k = format!("{}/obj-{:04}", t, i);
+            db.put(k.as_bytes(), format!("v-{}", k).as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn delimiter_routing_colocates_tenant_and_survives_reopen() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("route-delim");
+    fs::create_dir_all(&root)?;
+    Db::init_with_routing(&root, PAGE, BUCKETS, BucketRouting::by_delimiter(b'/'))?;
+    {
+        let mut db = Db::open(&root)?;
+        fill(&mut db, &["acme", "globex"], 50)?;
+        let b = db
+            .dir
+            .bucket_of_key(b"acme/obj-0000", db.pager.meta.hash_kind);
+        for i in 0..50 {
+            let k = format!("acme/obj-{:04}", i);
+            assert_eq!(
+                db.dir.bucket_of_key(k.as_bytes(), db.pager.meta.hash_kind),
+                b
+            );
+        }
+        assert_eq!(db.prefix_bucket(b"acme/"), Some(b));
+        assert_eq!(db.prefix_bucket(b"acme/obj-00"), Some(b));
+        // Префикс без разделителя не определяет бакет
+        assert_eq!(db.prefix_bucket(b"ac"), None);
+    }
+    let db = Db::open(&root)?;
+    assert_eq!(
+        db.pager.meta.bucket_routing(),
+        BucketRouting::by_delimiter(b'/')
+    );
+    for i in 0..50 {
+        let k = format!("globex/obj-{:04}", i);
+        assert_eq!(db.get(k.as_bytes())?, Some(format!("v-{}", k).into_bytes()));
+    }
+    Ok(())
+}
+
+#[test]
+fn tenant_prefix_scan_visits_single_bucket() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("route-scan");
+    fs::create_dir_all(&root)?;
+    Db::init_with_routing(&root, PAGE, BUCKETS, BucketRouting::by_delimiter(b'/'))?;
+    let mut db = Db::open(&root)?;
+    fill(&mut db, &["acme", "globex", "initech"], 30)?;
+    db.del(b"acme/obj-0007")?;
+
+    let before = metrics::snapshot().scan_prefix_single_bucket;
+    let mut got: Vec<Vec<u8>> = db
+        .scan_prefix(b"acme/")?
+        .into_iter()
+        .map(|(k, _)| k)
+        .collect();
+    got.sort();
+    let mut want: Vec<Vec<u8>> = (0..30)
+        .filter(|i| *i != 7)
+        .map(|i| format!("acme/obj-{:04}", i).into_bytes())
+        .collect();
+    want.sort();
+    assert_eq!(got, want);
+
+    let mut keys = db.scan_keys(Some(b"globex/obj-001"))?;
+    keys.sort();
+    assert_eq!(keys.len(), 10);
+    assert!(keys.iter().all(|k| k.starts_with(b"globex/obj-001")));
+
+    let it: Vec<_> = db
+        .scan_prefix_iter(b"initech/")?
+        .collect::<Result<Vec<_>>>()?;
+    assert_eq!(it.len(), 30);
+    assert!(metrics::snapshot().scan_prefix_single_bucket >= before + 3);
+
+    // Префикс без разделителя — полный скан, результат тот же
+    assert_eq!(db.scan_prefix(b"acm")?.len(), 29);
+    Ok(())
+}
+
+#[test]
+fn routing_is_persisted_in_meta_and_full_key_is_default() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("route-len");
+    fs::create_dir_all(&root)?;
+    Db::init_with_routing(&root, PAGE, BUCKETS, BucketRouting::by_prefix_len(4))?;
+    let m = read_meta(&root)?;
+    assert_ne!(m.flags & FEATURE_BUCKET_ROUTING, 0);
+    assert_eq!(m.bucket_routing(), BucketRouting::by_prefix_len(4));
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"user1234", b"a")?;
+        db.put(b"user9876", b"b")?;
+        let hk = db.pager.meta.hash_kind;
+        assert_eq!(
+            db.dir.bucket_of_key(b"user1234", hk),
+            db.dir.bucket_of_key(b"user9876", hk)
+        );
+        assert_eq!(db.scan_prefix(b"user")?.len(), 2);
+        assert_eq!(db.prefix_bucket(b"use"), None);
+    }
+
+    let plain = unique_root("route-full");
+    fs::create_dir_all(&plain)?;
+    Db::init_with_routing(&plain, PAGE, BUCKETS, BucketRouting::full_key())?;
+    let m = read_meta(&plain)?;
+    assert_eq!(m.hash_kind, HASH_KIND_XX64_SEED0);
+    assert_eq!(m.flags & FEATURE_BUCKET_ROUTING, 0);
+    let db = Db::open(&plain)?;
+    assert_eq!(db.prefix_bucket(b"user/"), None);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
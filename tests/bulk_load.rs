@@ -0,0 +1,170 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+use QuiverDB::db::Db;
+use QuiverDB::meta::set_clean_shutdown;
+use QuiverDB::page::kv_init_v3;
+use QuiverDB::pager::DATA_SEG_PREFIX;
+
+/// Bulk load (wal_images(false)): spill по маленькому бюджету, дедупликация (последний put
+/// побеждает), OVERFLOW, подвешивание к существующим цепочкам и переживание reopen.
+#[test]
+fn bulk_load_spills_dedups_and_persists() -> Result<()> {
+    let root = unique_root("bulk");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 64 * 1024, 32)?;
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"old", b"kept")?;
+        db.put(b"k00003", b"stale")?;
+    }
+
+    let big = vec![0xAB; 200_000];
+    {
+        let mut db = Db::open(&root)?;
+        let lsn0 = db.pager.meta.last_lsn;
+        let mut bl = db
+            .bulk_load()?
+            .wal_images(false)
+            .mem_budget(8 * 1024)
+            .partitions(8);
+        for i in (0..2000u32).rev() {
+            bl.put(
+                format!("k{:05}", i).as_bytes(),
+                format!("v{}", i).as_bytes(),
+            )?;
+        }
+        bl.put(b"k00007", b"second")?;
+        bl.put(b"big", &big)?;
+        let rep = bl.finish()?;
+        assert_eq!(rep.pairs_in, 2002);
+        assert_eq!(rep.pairs_written, 2001);
+        assert!(rep.spill_flushes > 0, "{:?}", rep);
+        assert!(rep.ovf_pages > 0 && rep.kv_pages > 0);
+        assert!(rep.lsn > lsn0);
+        assert!(
+            !root.join(".bulk_tmp").exists(),
+            "spill dir must be removed"
+        );
+
+        assert_eq!(db.get(b"k00007")?.as_deref(), Some(&b"second"[..]));
+        assert_eq!(db.get(b"k00003")?.as_deref(), Some(&b"v3"[..]));
+    }
+
+    let db = Db::open_ro(&root)?;
+    assert_eq!(db.get(b"old")?.as_deref(), Some(&b"kept"[..]));
+    assert_eq!(db.get(b"k01999")?.as_deref(), Some(&b"v1999"[..]));
+    assert_eq!(db.get(b"k00007")?.as_deref(), Some(&b"second"[..]));
+    assert_eq!(db.get(b"big")?.as_deref(), Some(big.as_slice()));
+    assert_eq!(db.scan_all()?.len(), 2002);
+    Ok(())
+}
+
+/// wal_images(true): страницы идут в WAL обычными батчами; данные переживают reopen с реплеем.
+#[test]
+fn bulk_load_with_wal_images() -> Result<()> {
+    let root = unique_root("bulk-wal");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 64 * 1024, 16)?;
+    {
+        let mut db = Db::open(&root)?;
+        let mut bl = db.bulk_load()?.wal_images(true).chunk_pages(2);
+        for i in 0..500u32 {
+            bl.put(format!("w{}", i).as_bytes(), b"x")?;
+        }
+        let rep = bl.finish()?;
+        assert_eq!(rep.pairs_written, 500);
+    }
+    set_clean_shutdown(&root, false)?;
+    let db = Db::open(&root)?;
+    assert_eq!(db.get(b"w499")?.as_deref(), Some(&b"x"[..]));
+    assert_eq!(db.scan_all()?.len(), 500);
+    Ok(())
+}
+
+/// Сбой между записью чанков (write_pages_unlogged) и HEADS_UPDATE: страницы-сироты с LSN выше
+/// WAL на диске. После reopen новые коммиты не должны переиспользовать их page_id/LSN — иначе
+/// LSN-гейтинг следующего реплея отбросит настоящий образ из WAL.
+#[test]
+fn crash_before_heads_update_does_not_shadow_later_commits() -> Result<()> {
+    let root = unique_root("bulk-crash");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let crashed = unique_root("bulk-crash-1");
+    let (orphan_end, orphan_lsn);
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"base", b"0")?;
+        // Чанк bulk load без последующего HEADS_UPDATE
+        let mut pages = Vec::new();
+        for _ in 0..4 {
+            let pid = db.pager.allocate_one_page()?;
+            let mut page = vec![0u8; 4096];
+            kv_init_v3(&mut page, pid, 0)?;
+            pages.push((pid, page));
+        }
+        let mut refs: Vec<(u64, &mut [u8])> = pages
+            .iter_mut()
+            .map(|(pid, p)| (*pid, p.as_mut_slice()))
+            .collect();
+        db.pager.write_pages_unlogged(&mut refs)?;
+        // «Краш»: состояние каталога в этот момент (Drop хэндла его уже не касается)
+        copy_dir(&root, &crashed)?;
+        orphan_end = db.pager.meta.next_page_id;
+        orphan_lsn = db.pager.meta.last_lsn;
+    }
+
+    // Reopen (реплей), новый коммит, снова «краш» — запись сегментов не дошла до диска
+    let before_put = unique_root("bulk-crash-seg");
+    copy_dir(&crashed, &before_put)?;
+    let crashed2 = unique_root("bulk-crash-2");
+    {
+        let mut db = Db::open(&crashed)?;
+        // Диапазон чанка зарезервирован до записи страниц
+        assert!(db.pager.meta.next_page_id >= orphan_end);
+        assert!(db.pager.meta.last_lsn >= orphan_lsn);
+        db.put(b"after", b"1")?;
+        copy_dir(&crashed, &crashed2)?;
+    }
+    for e in fs::read_dir(&before_put)? {
+        let e = e?;
+        if e.file_name().to_string_lossy().starts_with(DATA_SEG_PREFIX) {
+            fs::copy(e.path(), crashed2.join(e.file_name()))?;
+        }
+    }
+
+    // Реплей обязан восстановить put из WAL
+    let db = Db::open(&crashed2)?;
+    assert_eq!(db.get(b"base")?.as_deref(), Some(&b"0"[..]));
+    assert_eq!(db.get(b"after")?.as_deref(), Some(&b"1"[..]));
+    drop(db);
+    for d in [&root, &crashed, &before_put, &crashed2] {
+        let _ = fs::remove_dir_all(d);
+    }
+    Ok(())
+}
+
+// ---------- helpers ----------
+
+fn copy_dir(src: &Path, dst: &Path) -> Result<()> {
+    fs::create_dir_all(dst)?;
+    for e in fs::read_dir(src)? {
+        let e = e?;
+        if e.file_type()?.is_dir() {
+            copy_dir(&e.path(), &dst.join(e.file_name()))?;
+        } else {
+            fs::copy(e.path(), dst.join(e.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> std::path::PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::Db;
+use QuiverDB::metrics;
+use QuiverDB::pager::cache::{page_cache_clear, page_cache_len};
+use QuiverDB::pager::prewarm::{hot_pages_path, read_hot_pages};
+
+#[test]
+fn prewarm_from_hot_pages_snapshot() -> Result<()> {
+    let root = unique_root("prewarm");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+
+    let cfg = QuiverConfig::default()
+        .with_page_cache_pages(256)
+        .with_cache_prewarm(true);
+
+    // 1) writer: записываем и читаем (страницы попадают в кэш), Drop сохраняет hot-list
+    {
+        let mut db = Db::open_with_config(&root, cfg.clone())?;
+        for i in 0..64u32 {
+            db.put(format!("k{:03}", i).as_bytes(), b"v")?;
+        }
+        for i in 0..64u32 {
+            assert!(db.get(format!("k{:03}", i).as_bytes())?.is_some());
+        }
+    }
+    assert!(hot_pages_path(&root).exists());
+    let hot = read_hot_pages(&root)?;
+    assert!(!hot.is_empty());
+
+    // 2) холодный старт: кэш пуст, prewarm загружает страницы в фоне
+    page_cache_clear();
+    metrics::reset();
+    {
+        let mut db = Db::open_with_config(&root, cfg.clone())?;
+        let loaded = db.wait_prewarm().unwrap_or(0);
+        assert!(loaded > 0, "prewarm should load pages");
+        assert!(page_cache_len() as u64 >= loaded);
+
+        for i in 0..64u32 {
+            assert!(db.get(format!("k{:03}", i).as_bytes())?.is_some());
+        }
+        let m = metrics::snapshot();
+        assert_eq!(m.prewarm_runs, 1);
+        assert_eq!(m.prewarm_pages_loaded, loaded);
+        assert!(m.prewarm_hits > 0);
+    }
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
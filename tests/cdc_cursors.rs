@@ -0,0 +1,98 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::wal::cursors::{
+    advance_cursor, cursors_path, delete_cursor, load_cursor, load_cursors, prune_cursors,
+    set_cursor, store_cursors, validate_subscriber_name, SUBSCRIBER_NAME_MAX,
+};
+use QuiverDB::wal::net::{CdcHello, CtrlMsg, CDC_PROTO_VERSION};
+
+#[test]
+fn cursor_advances_only_forward_and_resets_explicitly() -> Result<()> {
+    let root = unique_root("cursors-adv");
+    fs::create_dir_all(&root)?;
+
+    assert!(load_cursors(&root)?.is_empty());
+    assert!(load_cursor(&root, "f1")?.is_none());
+
+    advance_cursor(&root, "f1", 10, Some("127.0.0.1:5000"))?;
+    advance_cursor(&root, "f1", 7, None)?; // ACK старой сессии — назад не двигается
+    let c = load_cursor(&root, "f1")?.expect("cursor f1");
+    assert_eq!(c.acked_lsn, 10);
+    assert!(c.updated_at > 0);
+
+    advance_cursor(&root, "f2", 3, Some("127.0.0.1:5001"))?;
+    assert_eq!(load_cursors(&root)?.len(), 2);
+
+    // явный reset двигает назад и сохраняет peer
+    set_cursor(&root, "f2", 0)?;
+    let c = load_cursor(&root, "f2")?.expect("cursor f2");
+    assert_eq!(c.acked_lsn, 0);
+    assert_eq!(c.peer.as_deref(), Some("127.0.0.1:5001"));
+
+    assert!(delete_cursor(&root, "f2")?);
+    assert!(!delete_cursor(&root, "f2")?);
+    assert_eq!(load_cursors(&root)?.keys().collect::<Vec<_>>(), vec!["f1"]);
+    assert!(cursors_path(&root).exists());
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn stale_cursors_are_pruned_by_retention() -> Result<()> {
+    let root = unique_root("cursors-prune");
+    fs::create_dir_all(&root)?;
+
+    advance_cursor(&root, "fresh", 5, None)?;
+    advance_cursor(&root, "stale", 5, None)?;
+    let mut all = load_cursors(&root)?;
+    all.get_mut("stale").unwrap().updated_at -= 10 * 3600;
+    store_cursors(&root, &all)?;
+
+    assert!(prune_cursors(&root, 0)?.is_empty(), "0 = keep forever");
+    assert_eq!(prune_cursors(&root, 3600)?, vec!["stale".to_string()]);
+    let left = load_cursors(&root)?;
+    assert_eq!(left.keys().collect::<Vec<_>>(), vec!["fresh"]);
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn subscriber_names_are_validated_and_carried_in_hello() -> Result<()> {
+    validate_subscriber_name("follower-1.eu_west")?;
+    assert!(validate_subscriber_name("").is_err());
+    assert!(validate_subscriber_name("bad name").is_err());
+    assert!(validate_subscriber_name("../etc").is_err());
+    assert!(validate_subscriber_name(&"x".repeat(SUBSCRIBER_NAME_MAX + 1)).is_err());
+
+    let root = unique_root("cursors-name");
+    fs::create_dir_all(&root)?;
+    assert!(set_cursor(&root, "no/slash", 1).is_err());
+    let _ = fs::remove_dir_all(&root);
+
+    let hello = CdcHello {
+        version: CDC_PROTO_VERSION,
+        caps: 0,
+        since_lsn: 9,
+        stream_id: 0,
+        keepalive_ms: 0,
+        compress: 0,
+        subscriber: Some("follower-1".into()),
+        filter: None,
+    };
+    let enc = CtrlMsg::Hello(hello.clone()).encode();
+    assert_eq!(CtrlMsg::decode(&enc)?, Some(CtrlMsg::Hello(hello)));
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
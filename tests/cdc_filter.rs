@@ -0,0 +1,263 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use QuiverDB::dir::{bucket_for_key, NO_PAGE};
+use QuiverDB::meta::set_clean_shutdown;
+use QuiverDB::page::kv::kv_for_each_record;
+use QuiverDB::page::ovf_header_read_v3;
+use QuiverDB::util::decode_ovf_placeholder_v3;
+use QuiverDB::wal::filter::{CdcFilter, FrameFilter};
+use QuiverDB::wal::net::{CdcHello, CtrlMsg, CDC_PROTO_VERSION};
+use QuiverDB::wal::reader::{WalRecord, WalStreamReader};
+use QuiverDB::wal::{
+    decode_heads_update_payload, encode, wal_path, write_wal_file_header_with_stream_id,
+    WAL_HDR_SIZE, WAL_REC_BEGIN, WAL_REC_COMMIT, WAL_REC_HEADS_UPDATE, WAL_REC_PAGE_IMAGE,
+};
+use QuiverDB::Db;
+
+const BUCKETS: u32 = 64;
+
+fn key(prefix: &str, i: usize) -> Vec<u8> {
+    format!("{}{}", prefix, i).into_bytes()
+}
+
+/// Лидер с ключами user:/order: (в т.ч. overflow) и удалениями; WAL читается при открытом writer'е
+/// (Drop усекает WAL).
+fn leader_frames(root: &Path) -> Result<Vec<WalRecord>> {
+    Db::init(root, 4096, BUCKETS)?;
+    let mut db = Db::open(root)?;
+    for i in 0..60 {
+        db.put(&key("user:", i), format!("u{}", i).as_bytes())?;
+        db.put(&key("order:", i), format!("o{}", i).as_bytes())?;
+    }
+    db.put(b"user:big", &vec![b'U'; 20_000])?;
+    db.put(b"order:big", &vec![b'O'; 20_000])?;
+    db.del(&key("user:", 3))?;
+    db.del(&key("order:", 4))?;
+
+    let mut f = OpenOptions::new().read(true).open(wal_path(root))?;
+    let len = f.metadata()?.len();
+    let mut rdr = WalStreamReader::new();
+    let mut pos = WAL_HDR_SIZE as u64;
+    let mut out = Vec::new();
+    while let Some((rec, next)) = rdr.read_next(&mut f, pos, len)? {
+        pos = next;
+        out.push(rec);
+    }
+    drop(db);
+    Ok(out)
+}
+
+fn run_filter(root: &Path, filter: CdcFilter, frames: &[WalRecord]) -> Result<Vec<WalRecord>> {
+    let mut ff = FrameFilter::open(root, filter)?;
+    let mut out = Vec::new();
+    for rec in frames.iter().cloned() {
+        for (mut rec, filtered) in ff.push(rec) {
+            if let Some(pl) = filtered {
+                rec.payload = pl;
+            }
+            out.push(rec);
+        }
+    }
+    assert!(ff.stats().pages_dropped > 0, "filter must drop something");
+    Ok(out)
+}
+
+fn count(frames: &[WalRecord], t: u8) -> usize {
+    frames.iter().filter(|r| r.rec_type == t).count()
+}
+
+#[test]
+fn bucket_filter_yields_consistent_partial_replica() -> Result<()> {
+    let leader = unique_root("cdc-filter-leader");
+    let follower = unique_root("cdc-filter-follower");
+    fs::create_dir_all(&leader)?;
+    fs::create_dir_all(&follower)?;
+
+    let frames = leader_frames(&leader)?;
+    let filter = CdcFilter {
+        buckets: CdcFilter::parse_buckets("0-15,40-47")?,
+        ..Default::default()
+    };
+    let out = run_filter(&leader, filter.clone(), &frames)?;
+
+    // служебные кадры батчей идут все, страниц — меньше
+    assert_eq!(count(&out, WAL_REC_BEGIN), count(&frames, WAL_REC_BEGIN));
+    assert_eq!(count(&out, WAL_REC_COMMIT), count(&frames, WAL_REC_COMMIT));
+    assert!(count(&out, WAL_REC_PAGE_IMAGE) < count(&frames, WAL_REC_PAGE_IMAGE));
+    for r in out.iter().filter(|r| r.rec_type == WAL_REC_HEADS_UPDATE) {
+        for (b, _) in decode_heads_update_payload(&r.payload).expect("heads payload") {
+            assert!(
+                filter.matches_bucket(b),
+                "foreign bucket {} in HEADS_UPDATE",
+                b
+            );
+        }
+    }
+
+    // follower: тот же init + отфильтрованный поток как WAL → реплей на open
+    Db::init(&follower, 4096, BUCKETS)?;
+    {
+        let mut f = OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .read(true)
+            .write(true)
+            .open(wal_path(&follower))?;
+        write_wal_file_header_with_stream_id(&mut f, 0)?;
+        for r in &out {
+            encode::write_record(&mut f, r.rec_type, r.lsn, r.page_id, &r.payload)?;
+        }
+    }
+    set_clean_shutdown(&follower, false)?;
+    // writer open реплеит WAL follower'а
+    drop(Db::open(&follower)?);
+    let src = Db::open_ro(&leader)?;
+    let dst = Db::open_ro(&follower)?;
+    let mut selected = 0;
+    let mut keys: Vec<Vec<u8>> = (0..60)
+        .flat_map(|i| [key("user:", i), key("order:", i)])
+        .collect();
+    keys.push(b"user:big".to_vec());
+    keys.push(b"order:big".to_vec());
+    for k in &keys {
+        let b = bucket_for_key(k, 1, BUCKETS);
+        if filter.matches_bucket(b) {
+            selected += 1;
+            assert_eq!(
+                dst.get(k)?,
+                src.get(k)?,
+                "key {:?}",
+                String::from_utf8_lossy(k)
+            );
+        } else {
+            assert_eq!(
+                dst.get(k)?,
+                None,
+                "key {:?} must be filtered out",
+                String::from_utf8_lossy(k)
+            );
+        }
+    }
+    assert!(selected > 0);
+
+    drop(dst);
+    drop(src);
+    let _ = fs::remove_dir_all(&leader);
+    let _ = fs::remove_dir_all(&follower);
+    Ok(())
+}
+
+/// Страницы overflow-цепочки значения `key` (по плейсхолдеру в KV-страницах потока).
+fn ovf_chain(frames: &[WalRecord], key: &[u8]) -> HashSet<u64> {
+    let mut head = None;
+    for r in frames.iter().filter(|r| r.rec_type == WAL_REC_PAGE_IMAGE) {
+        kv_for_each_record(&r.payload, |k, v, _, _| {
+            if k == key {
+                if let Some((_, h)) = decode_ovf_placeholder_v3(v) {
+                    head = Some(h);
+                }
+            }
+        });
+    }
+    let mut chain = HashSet::new();
+    let mut pid = head.expect("overflow placeholder");
+    while pid != NO_PAGE && chain.insert(pid) {
+        let page = frames
+            .iter()
+            .rev()
+            .find(|r| r.rec_type == WAL_REC_PAGE_IMAGE && r.page_id == pid)
+            .expect("overflow page in stream");
+        pid = ovf_header_read_v3(&page.payload).unwrap().next_page_id;
+    }
+    chain
+}
+
+#[test]
+fn prefix_filter_ships_only_matching_pages_and_their_overflow() -> Result<()> {
+    let leader = unique_root("cdc-filter-prefix");
+    fs::create_dir_all(&leader)?;
+    let frames = leader_frames(&leader)?;
+
+    let filter = CdcFilter::from_parts(&["user:".to_string()], None)?;
+    let out = run_filter(&leader, filter, &frames)?;
+    assert_eq!(count(&out, WAL_REC_COMMIT), count(&frames, WAL_REC_COMMIT));
+
+    let shipped: HashSet<(u64, u64)> = out
+        .iter()
+        .filter(|r| r.rec_type == WAL_REC_PAGE_IMAGE)
+        .map(|r| (r.lsn, r.page_id))
+        .collect();
+    for r in frames.iter().filter(|r| r.rec_type == WAL_REC_PAGE_IMAGE) {
+        let mut any = false;
+        let mut user = false;
+        kv_for_each_record(&r.payload, |k, _, _, _| {
+            any = true;
+            user |= k.starts_with(b"user:");
+        });
+        if any {
+            assert_eq!(
+                shipped.contains(&(r.lsn, r.page_id)),
+                user,
+                "KV page {} at lsn {}",
+                r.page_id,
+                r.lsn
+            );
+        }
+    }
+
+    let user_chain = ovf_chain(&frames, b"user:big");
+    let order_chain = ovf_chain(&frames, b"order:big");
+    let shipped_pids: HashSet<u64> = shipped.iter().map(|&(_, p)| p).collect();
+    assert!(user_chain.iter().all(|p| shipped_pids.contains(p)));
+    assert!(order_chain.iter().all(|p| !shipped_pids.contains(p)));
+
+    let _ = fs::remove_dir_all(&leader);
+    Ok(())
+}
+
+#[test]
+fn filter_specs_are_validated_and_carried_in_hello() -> Result<()> {
+    assert_eq!(
+        CdcFilter::parse_buckets("0-99, 512 ,1000-1999")?,
+        vec![(0, 99), (512, 512), (1000, 1999)]
+    );
+    assert!(CdcFilter::parse_buckets("9-1").is_err());
+    assert!(CdcFilter::parse_buckets("a-b").is_err());
+    let too_many: Vec<String> = (0..17).map(|i| format!("p{}", i)).collect();
+    assert!(CdcFilter::from_parts(&too_many, None).is_err());
+    assert!(CdcFilter::from_parts(&["x".repeat(129)], None).is_err());
+
+    let filter = CdcFilter::from_parts(&["user:".into(), "acct/".into()], Some("0-7,9"))?;
+    assert_eq!(filter.to_string(), "prefix='user:','acct/' buckets=0-7,9");
+    assert!(filter.matches_key(9, b"acct/1"));
+    assert!(!filter.matches_key(8, b"acct/1"));
+    assert!(!filter.matches_key(1, b"order:1"));
+
+    for subscriber in [None, Some("f1".to_string())] {
+        let hello = CdcHello {
+            version: CDC_PROTO_VERSION,
+            caps: 0,
+            since_lsn: 5,
+            stream_id: 0,
+            keepalive_ms: 0,
+            compress: 0,
+            subscriber,
+            filter: Some(filter.clone()),
+        };
+        let enc = CtrlMsg::Hello(hello.clone()).encode();
+        assert_eq!(CtrlMsg::decode(&enc)?, Some(CtrlMsg::Hello(hello)));
+    }
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+use QuiverDB::wal::state::{
+    check_stream_source, load_source_db_uuid, load_stream_id, ForeignStreamError,
+};
+
+#[test]
+fn follower_binds_first_source_and_refuses_foreign_streams() -> Result<()> {
+    let foll = unique_root("foreign-foll");
+    fs::create_dir_all(&foll)?;
+    Db::init(&foll, 4096, 8)?;
+    let (uuid_a, uuid_b) = ([0xa1u8; 16], [0xb2u8; 16]);
+
+    // Первый источник запоминается
+    let c = check_stream_source(&foll, 11, &uuid_a, false)?;
+    assert!(c.bound && !c.forced);
+    assert_eq!(load_stream_id(&foll)?, 11);
+    assert_eq!(load_source_db_uuid(&foll)?, Some(uuid_a));
+
+    // Тот же источник; поток без UUID (file://, v1) сверяется только по stream_id
+    assert!(!check_stream_source(&foll, 11, &uuid_a, false)?.bound);
+    check_stream_source(&foll, 11, &[0u8; 16], false)?;
+
+    // Другая БД — отказ (ничего не перезаписано), stream_id тоже сверяется
+    let err = check_stream_source(&foll, 11, &uuid_b, false).unwrap_err();
+    let fe = err
+        .downcast_ref::<ForeignStreamError>()
+        .expect("typed error");
+    assert_eq!(fe.what, "db_uuid");
+    assert!(err.to_string().contains("--force"));
+    assert_eq!(load_source_db_uuid(&foll)?, Some(uuid_a));
+    let err = check_stream_source(&foll, 12, &[0u8; 16], false).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<ForeignStreamError>().map(|e| e.what),
+        Some("stream_id")
+    );
+    assert_eq!(load_stream_id(&foll)?, 11);
+
+    // --force: применить и перепривязать follower
+    let c = check_stream_source(&foll, 12, &uuid_b, true)?;
+    assert!(c.forced);
+    assert_eq!(load_stream_id(&foll)?, 12);
+    assert_eq!(load_source_db_uuid(&foll)?, Some(uuid_b));
+    check_stream_source(&foll, 12, &uuid_b, false)?;
+
+    // Нулевой stream_id невалиден даже с force
+    assert!(check_stream_source(&foll, 0, &uuid_b, true).is_err());
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
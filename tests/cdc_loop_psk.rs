@@ -267,16 +267,5 @@ fn v3_page_lsn(buf: &[u8]) -> Option<u64> {
 
 #[inline]
 fn parse_heads_updates(buf: &[u8]) -> Vec<(u32, u64)> {
-    if buf.len() % 12 != 0 || buf.is_empty() {
-        return Vec::new();
-    }
-    let mut out = Vec::with_capacity(buf.len() / 12);
-    let mut off = 0usize;
-    while off + 12 <= buf.len() {
-        let b = LittleEndian::read_u32(&buf[off..off + 4]);
-        let pid = LittleEndian::read_u64(&buf[off + 4..off + 12]);
-        out.push((b, pid));
-        off += 12;
-    }
-    out
+    QuiverDB::wal::decode_heads_update_payload(buf).unwrap_or_default()
 }
@@ -0,0 +1,269 @@
+use anyhow::{anyhow, Result};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use QuiverDB::wal::encode::build_hdr_with_crc;
+use QuiverDB::wal::net::{
+    decode_frame_payload, hello_from_env, is_compressed_payload, is_ctrl_payload, local_caps,
+    negotiate, read_next_framed_psk, read_next_framed_psk_timeout, resume_from_env, write_ctrl_psk,
+    CdcHello, CdcWelcome, CtrlMsg, FrameCompression, FrameWait, IoStream, CAP_COMPRESS,
+    CAP_HEADS_DELTA, CAP_HMAC, CAP_KEEPALIVE, CDC_PROTO_VERSION, COMPRESS_NONE, COMPRESS_ZSTD,
+    CTRL_MAX_LEN, SINCE_LSN_NONE,
+};
+use QuiverDB::wal::WAL_REC_PAGE_IMAGE;
+
+const PSK: &[u8] = &[0x5A; 32];
+
+fn hello(keepalive_ms: u32) -> CdcHello {
+    CdcHello {
+        version: CDC_PROTO_VERSION,
+        caps: local_caps(),
+        since_lsn: SINCE_LSN_NONE,
+        stream_id: 0,
+        keepalive_ms,
+        compress: 1 << COMPRESS_ZSTD,
+        subscriber: None,
+        filter: None,
+    }
+}
+
+#[test]
+fn ctrl_messages_roundtrip_and_never_match_wal_frames() -> Result<()> {
+    let msgs = [
+        CtrlMsg::Hello(CdcHello {
+            since_lsn: 42,
+            stream_id: 7,
+            ..hello(1500)
+        }),
+        CtrlMsg::Hello(CdcHello {
+            subscriber: Some("follower-1".into()),
+            ..hello(1500)
+        }),
+        CtrlMsg::Welcome(CdcWelcome {
+            version: 2,
+            caps: CAP_HMAC | CAP_KEEPALIVE,
+            stream_id: 7,
+            start_lsn: 42,
+            keepalive_ms: 1500,
+            compress: COMPRESS_ZSTD,
+            db_uuid: [0x5a; 16],
+        }),
+        CtrlMsg::Keepalive { last_lsn: 99 },
+        CtrlMsg::Ack { applied_lsn: 98 },
+        CtrlMsg::Bye { last_lsn: 99 },
+    ];
+    for m in msgs {
+        let enc = m.encode();
+        assert!(is_ctrl_payload(&enc));
+        assert!(enc.len() <= CTRL_MAX_LEN);
+        assert_eq!(CtrlMsg::decode(&enc)?, Some(m.clone()));
+
+        // расширение новой версии (лишние байты) не ломает разбор
+        let mut ext = enc.clone();
+        ext.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(CtrlMsg::decode(&ext)?, Some(m));
+    }
+
+    // HELLO старого peer'а (без байта compress) — без сжатия
+    let mut old = CtrlMsg::Hello(hello(1500)).encode();
+    old.truncate(old.len() - 3);
+    let Some(CtrlMsg::Hello(h)) = CtrlMsg::decode(&old)? else {
+        return Err(anyhow!("expected HELLO"));
+    };
+    assert_eq!(h.compress, 0);
+
+    // усечённое тело — ошибка, неизвестный вид — Unknown
+    let enc = CtrlMsg::Keepalive { last_lsn: 1 }.encode();
+    assert!(CtrlMsg::decode(&enc[..enc.len() - 1]).is_err());
+    let mut unk = enc.clone();
+    unk[8] = 0xEE;
+    assert_eq!(CtrlMsg::decode(&unk)?, Some(CtrlMsg::Unknown(0xEE)));
+
+    // WAL-кадр — не управляющее сообщение
+    let payload = vec![0xAB; 64];
+    let mut frame = build_hdr_with_crc(WAL_REC_PAGE_IMAGE, 5, 1, &payload).to_vec();
+    frame.extend_from_slice(&payload);
+    assert!(!is_ctrl_payload(&frame));
+    assert_eq!(CtrlMsg::decode(&frame)?, None);
+    Ok(())
+}
+
+#[test]
+fn negotiate_intersects_caps_and_keepalive() {
+    // получатель без delta HEADS_UPDATE и с более коротким keepalive
+    let h = CdcHello {
+        caps: CAP_HMAC | CAP_KEEPALIVE | (1 << 30),
+        ..hello(1000)
+    };
+    let w = negotiate(&h, 11, 5);
+    assert_eq!(w.version, CDC_PROTO_VERSION);
+    assert!(w.has(CAP_HMAC) && w.has(CAP_KEEPALIVE));
+    assert!(!w.has(CAP_HEADS_DELTA), "receiver did not offer delta");
+    assert_eq!(w.caps & (1 << 30), 0, "unknown caps are dropped");
+    assert_eq!(w.keepalive(), Some(Duration::from_millis(1000)));
+    assert_eq!((w.stream_id, w.start_lsn), (11, 5));
+
+    // keepalive выключен у получателя — выключен в сессии
+    let w = negotiate(&hello(0), 11, 0);
+    assert_eq!(w.keepalive(), None);
+}
+
+/// Сжатие кадров: выбирается по маске получателя, мелкие/несжимаемые кадры идут как есть.
+#[test]
+fn frame_compression_is_negotiated_per_receiver() -> Result<()> {
+    // получатель без сжатия (старый или P1_CDC_COMPRESS=none) — кадры без сжатия
+    let w = negotiate(
+        &CdcHello {
+            compress: 0,
+            ..hello(0)
+        },
+        1,
+        0,
+    );
+    assert_eq!(w.compress, COMPRESS_NONE);
+    let w = negotiate(
+        &CdcHello {
+            caps: local_caps() & !CAP_COMPRESS,
+            ..hello(0)
+        },
+        1,
+        0,
+    );
+    assert_eq!(w.compress, COMPRESS_NONE);
+    // получатель с zstd — отправитель (по умолчанию zstd) сжимает
+    let w = negotiate(&hello(0), 1, 0);
+    assert_eq!(w.compress, COMPRESS_ZSTD);
+
+    let c = FrameCompression {
+        algo: COMPRESS_ZSTD,
+        level: 0,
+        min_len: 512,
+    };
+    let payload = vec![0u8; 200];
+    let mut frame = build_hdr_with_crc(WAL_REC_PAGE_IMAGE, 9, 3, &payload).to_vec();
+    frame.extend_from_slice(&payload);
+    // короче min_len — как есть
+    assert_eq!(c.encode(&frame)?.as_ref(), frame.as_slice());
+
+    let payload = vec![0x11; 4096];
+    let mut frame = build_hdr_with_crc(WAL_REC_PAGE_IMAGE, 9, 3, &payload).to_vec();
+    frame.extend_from_slice(&payload);
+    let wire = c.encode(&frame)?.into_owned();
+    assert!(is_compressed_payload(&wire) && wire.len() < frame.len() / 4);
+    assert_eq!(
+        decode_frame_payload(wire.clone(), COMPRESS_ZSTD, 8192)?,
+        frame
+    );
+
+    // несогласованное сжатие и превышение предела — ошибки; обычный кадр проходит как есть
+    assert!(decode_frame_payload(wire.clone(), COMPRESS_NONE, 8192).is_err());
+    assert!(decode_frame_payload(wire, COMPRESS_ZSTD, 1024).is_err());
+    assert_eq!(
+        decode_frame_payload(frame.clone(), COMPRESS_NONE, 8192)?,
+        frame
+    );
+    Ok(())
+}
+
+/// Resume: позиция follower'а едет в HELLO и становится start_lsn в WELCOME.
+#[test]
+fn resume_position_travels_in_hello() -> Result<()> {
+    std::env::remove_var("P1_CDC_RESUME");
+    assert!(resume_from_env(), "resume is on by default");
+    std::env::set_var("P1_CDC_RESUME", "off");
+    assert!(!resume_from_env());
+    std::env::remove_var("P1_CDC_RESUME");
+
+    let h = hello_from_env(1234, 9);
+    let enc = CtrlMsg::Hello(h).encode();
+    let Some(CtrlMsg::Hello(got)) = CtrlMsg::decode(&enc)? else {
+        return Err(anyhow!("expected HELLO"));
+    };
+    assert_eq!((got.since_lsn, got.stream_id), (1234, 9));
+
+    let w = negotiate(&got, 9, got.since_lsn);
+    assert_eq!(w.start_lsn, 1234);
+    Ok(())
+}
+
+/// Handshake и keepalive/ack поверх TCP: HELLO → WELCOME → KEEPALIVE → ACK → BYE.
+#[test]
+fn handshake_and_keepalive_over_tcp() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+
+    // «Отправитель»: ждёт HELLO, отвечает WELCOME, пингует и ждёт ACK
+    let sender = thread::spawn(move || -> Result<u64> {
+        let (sock, _) = listener.accept()?;
+        let mut s = IoStream::Plain(sock);
+        let h = match read_next_framed_psk_timeout(
+            &mut s,
+            PSK,
+            CTRL_MAX_LEN,
+            Duration::from_secs(5),
+        )? {
+            FrameWait::Frame(_, p) => match CtrlMsg::decode(&p)? {
+                Some(CtrlMsg::Hello(h)) => h,
+                other => return Err(anyhow!("expected HELLO, got {:?}", other)),
+            },
+            other => return Err(anyhow!("expected frame, got {:?}", other)),
+        };
+        let w = negotiate(&h, 77, 0);
+        write_ctrl_psk(&mut s, 1, &CtrlMsg::Welcome(w), PSK)?;
+        write_ctrl_psk(&mut s, 2, &CtrlMsg::Keepalive { last_lsn: 10 }, PSK)?;
+        let acked = match read_next_framed_psk_timeout(
+            &mut s,
+            PSK,
+            CTRL_MAX_LEN,
+            Duration::from_secs(5),
+        )? {
+            FrameWait::Frame(_, p) => match CtrlMsg::decode(&p)? {
+                Some(CtrlMsg::Ack { applied_lsn }) => applied_lsn,
+                other => return Err(anyhow!("expected ACK, got {:?}", other)),
+            },
+            other => return Err(anyhow!("expected frame, got {:?}", other)),
+        };
+        write_ctrl_psk(&mut s, 3, &CtrlMsg::Bye { last_lsn: 10 }, PSK)?;
+        Ok(acked)
+    });
+
+    // «Получатель»
+    let mut r = IoStream::Plain(TcpStream::connect(addr)?);
+    write_ctrl_psk(&mut r, 1, &CtrlMsg::Hello(hello(2000)), PSK)?;
+    let (_, p) = read_next_framed_psk(&mut r, PSK, CTRL_MAX_LEN)?.expect("WELCOME");
+    let Some(CtrlMsg::Welcome(w)) = CtrlMsg::decode(&p)? else {
+        return Err(anyhow!("expected WELCOME"));
+    };
+    assert_eq!(w.stream_id, 77);
+    assert_eq!(w.keepalive(), Some(Duration::from_millis(2000)));
+
+    let (_, p) = read_next_framed_psk(&mut r, PSK, CTRL_MAX_LEN)?.expect("KEEPALIVE");
+    assert_eq!(
+        CtrlMsg::decode(&p)?,
+        Some(CtrlMsg::Keepalive { last_lsn: 10 })
+    );
+    write_ctrl_psk(&mut r, 2, &CtrlMsg::Ack { applied_lsn: 10 }, PSK)?;
+    let (_, p) = read_next_framed_psk(&mut r, PSK, CTRL_MAX_LEN)?.expect("BYE");
+    assert_eq!(CtrlMsg::decode(&p)?, Some(CtrlMsg::Bye { last_lsn: 10 }));
+
+    assert_eq!(sender.join().unwrap()?, 10);
+    Ok(())
+}
+
+/// Молчащий peer (получатель v1) — таймаут ожидания, а не ошибка.
+#[test]
+fn hello_wait_times_out_on_silent_peer() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let peer = thread::spawn(move || -> Result<()> {
+        let (_sock, _) = listener.accept()?;
+        thread::sleep(Duration::from_millis(300));
+        Ok(())
+    });
+    let mut s = IoStream::Plain(TcpStream::connect(addr)?);
+    let got = read_next_framed_psk_timeout(&mut s, PSK, CTRL_MAX_LEN, Duration::from_millis(50))?;
+    assert!(matches!(got, FrameWait::Timeout), "got {:?}", got);
+    peer.join().unwrap()?;
+    Ok(())
+}
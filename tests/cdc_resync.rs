@@ -0,0 +1,182 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::resync::bucket_state_at;
+use QuiverDB::db::{BucketDigest, BucketState, Db};
+use QuiverDB::dir::NO_PAGE;
+use QuiverDB::wal::net::{local_caps, CtrlMsg, CAP_RESYNC, CTRL_MAX_LEN, DIGEST_OK};
+
+fn fill(db: &mut Db) -> Result<()> {
+    for i in 0..80 {
+        db.put(
+            format!("k{:03}", i).as_bytes(),
+            format!("v{}", i).as_bytes(),
+        )?;
+    }
+    db.put(b"big", &[3u8; 9_000])?;
+    Ok(())
+}
+
+#[test]
+fn resync_repairs_diverged_buckets() -> Result<()> {
+    let leader = unique_root("resync-leader");
+    let follower = unique_root("resync-follower");
+    for root in [&leader, &follower] {
+        fs::create_dir_all(root)?;
+        Db::init(root, 4096, 8)?;
+    }
+    let now = 1_000;
+    let mut l = Db::open(&leader)?;
+    let mut f = Db::open(&follower)?;
+    // Одинаковая история — одинаковая раскладка страниц
+    fill(&mut l)?;
+    fill(&mut f)?;
+    assert!(l
+        .digest_tree_at(now)?
+        .diff(&f.digest_tree_at(now)?)?
+        .is_empty());
+
+    // Расхождение: follower пропустил часть изменений leader'а (в т.ч. OVERFLOW-значение)
+    l.put(b"big", &[9u8; 12_000])?;
+    l.put(b"k007", b"changed")?;
+    l.del(b"k011")?;
+    l.put(b"fresh", &[5u8; 6_000])?;
+    let diverged = l.digest_tree_at(now)?.diff(&f.digest_tree_at(now)?)?;
+    assert!(!diverged.is_empty());
+
+    for &b in &diverged {
+        let state = l.bucket_state(b, now)?;
+        assert_eq!(state.bucket, b);
+        assert_eq!(state.digest, l.bucket_digests(&[b], now)?[0]);
+        let rep = f.apply_bucket_state(&state, now)?;
+        assert!(rep.digest_ok, "bucket {} still differs", b);
+        assert_eq!(rep.new_head, state.head);
+        assert_eq!(rep.pages_written, state.pages.len() as u64);
+    }
+    assert!(l
+        .digest_tree_at(now)?
+        .diff(&f.digest_tree_at(now)?)?
+        .is_empty());
+    assert_eq!(f.get(b"big")?, Some(vec![9u8; 12_000]));
+    assert_eq!(f.get(b"fresh")?, Some(vec![5u8; 6_000]));
+    assert_eq!(f.get(b"k007")?, Some(b"changed".to_vec()));
+    assert_eq!(f.get(b"k011")?, None);
+    drop(f);
+
+    // После переоткрытия замена сохранилась
+    let f = Db::open_ro(&follower)?;
+    assert!(l
+        .digest_tree_at(now)?
+        .diff(&f.digest_tree_at(now)?)?
+        .is_empty());
+    Ok(())
+}
+
+#[test]
+fn resync_messages_roundtrip() -> Result<()> {
+    assert_ne!(local_caps() & CAP_RESYNC, 0);
+
+    let req = CtrlMsg::ResyncRequest {
+        lsn: 42,
+        now: 1_000,
+        bucket: 3,
+    };
+    assert_eq!(CtrlMsg::decode(&req.encode())?, Some(req.clone()));
+    assert!(req.encode().len() <= CTRL_MAX_LEN);
+
+    let digest = BucketDigest {
+        bucket: 3,
+        live_keys: 2,
+        hash: 0xfeed,
+    };
+    let state = BucketState {
+        bucket: 3,
+        lsn: 42,
+        head: 7,
+        pages: vec![(7, vec![1u8; 64]), (9, vec![2u8; 64])],
+        digest,
+    };
+    let frames = CtrlMsg::resync_frames(state);
+    assert_eq!(frames.len(), 3);
+    for m in &frames {
+        assert_eq!(CtrlMsg::decode(&m.encode())?.as_ref(), Some(m));
+    }
+    assert_eq!(
+        frames[0],
+        CtrlMsg::ResyncPage {
+            page_id: 7,
+            image: vec![1u8; 64]
+        }
+    );
+    assert_eq!(
+        frames[2],
+        CtrlMsg::ResyncEnd {
+            lsn: 42,
+            status: DIGEST_OK,
+            bucket: 3,
+            head: 7,
+            pages: 2,
+            digest,
+        }
+    );
+
+    // Усечённые кадры не декодируются
+    let mut end = frames[2].encode();
+    end.truncate(end.len() - 1);
+    assert!(CtrlMsg::decode(&end).is_err());
+    Ok(())
+}
+
+#[test]
+fn apply_rejects_bad_state_and_reads_beside_writer() -> Result<()> {
+    let src = unique_root("resync-src");
+    let dst = unique_root("resync-dst");
+    for root in [&src, &dst] {
+        fs::create_dir_all(root)?;
+        Db::init(root, 4096, 4)?;
+    }
+    let now = 1_000;
+    let mut s = Db::open(&src)?;
+    fill(&mut s)?;
+    // Состояние рядом с живым writer'ом (без LOCK)
+    // (lsn у RO-хэндла может отставать от writer'а — сравниваются цепочка и digest)
+    let state = bucket_state_at(&src, 1, now)?;
+    let live = s.bucket_state(1, now)?;
+    assert!(state.head == live.head && state.pages == live.pages);
+    assert_eq!(state.digest, live.digest);
+    assert_ne!(state.head, NO_PAGE);
+    assert!(s.bucket_state(4, now).is_err());
+
+    let mut d = Db::open(&dst)?;
+    let before = d.bucket_digests(&[1], now)?;
+
+    let mut short = state.clone();
+    short.pages[0].1.truncate(100);
+    assert!(d.apply_bucket_state(&short, now).is_err());
+
+    let mut headless = state.clone();
+    headless.pages.retain(|(pid, _)| *pid != state.head);
+    assert!(d.apply_bucket_state(&headless, now).is_err());
+
+    let mut foreign = state.clone();
+    foreign.pages[0].1 = vec![0u8; 4096];
+    assert!(d.apply_bucket_state(&foreign, now).is_err());
+
+    let mut out_of_range = state.clone();
+    out_of_range.bucket = 4;
+    assert!(d.apply_bucket_state(&out_of_range, now).is_err());
+
+    // Отказ не трогает бакет
+    assert_eq!(d.bucket_digests(&[1], now)?, before);
+    assert!(d.apply_bucket_state(&state, now)?.digest_ok);
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
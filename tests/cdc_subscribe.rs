@@ -0,0 +1,200 @@
+#![cfg(feature = "async")]
+
+use anyhow::Result;
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::{pin, Pin};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+use futures_core::Stream;
+use QuiverDB::cdc::{self, ChangeEvent, ChangeOp, ChangeStream};
+use QuiverDB::metrics;
+use QuiverDB::Db;
+
+// Метрики процесс-глобальные — тесты файла идут по очереди.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+const PAGE: u32 = 4096;
+
+/// Минимальный executor: будит текущий поток.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(v) = fut.as_mut().poll(&mut cx) {
+            return v;
+        }
+        thread::park_timeout(Duration::from_millis(200));
+    }
+}
+
+/// Все уже закоммиченные события (без ожидания).
+fn drain(stream: &mut ChangeStream) -> Result<Vec<ChangeEvent>> {
+    let mut out = Vec::new();
+    while let Some(ev) = stream.try_next()? {
+        out.push(ev);
+    }
+    Ok(out)
+}
+
+#[test]
+fn stream_yields_puts_deletes_and_overflow_values() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("cdcsub-ops");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PAGE, 8)?;
+    let mut db = Db::open(&root)?;
+    let mut stream = cdc::subscribe(&root, 0)?;
+    assert!(drain(&mut stream)?.is_empty());
+
+    let big: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+    let events_before = metrics::snapshot().cdc_subscribe_events;
+    db.put(b"alpha", b"1")?;
+    db.put(b"big", &big)?;
+    db.del(b"alpha")?;
+    db.batch(|b| {
+        b.put(b"k1", b"v1")?;
+        b.put(b"k2", b"v2")?;
+        Ok(())
+    })?;
+
+    let evs = drain(&mut stream)?;
+    let ops: Vec<(ChangeOp, &[u8])> = evs.iter().map(|e| (e.op, e.key.as_slice())).collect();
+    assert_eq!(
+        ops,
+        vec![
+            (ChangeOp::Put, &b"alpha"[..]),
+            (ChangeOp::Put, &b"big"[..]),
+            (ChangeOp::Del, &b"alpha"[..]),
+            (ChangeOp::Put, &b"k1"[..]),
+            (ChangeOp::Put, &b"k2"[..]),
+        ]
+    );
+    assert_eq!(evs[0].value.as_deref(), Some(&b"1"[..]));
+    assert_eq!(evs[1].value.as_deref(), Some(big.as_slice()));
+    assert_eq!(evs[2].value, None);
+    assert!(evs.windows(2).all(|w| w[0].batch_lsn <= w[1].batch_lsn));
+    assert_eq!(evs[3].batch_lsn, evs[4].batch_lsn, "one batch, one commit");
+    assert_eq!(stream.last_lsn(), evs[4].batch_lsn);
+    assert!(metrics::snapshot().cdc_subscribe_events >= events_before + 5);
+
+    drop(db);
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn same_value_put_again_in_a_later_batch_is_delivered() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("cdcsub-reput");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PAGE, 8)?;
+    let mut db = Db::open(&root)?;
+    let mut stream = cdc::subscribe(&root, 0)?;
+
+    db.put(b"k", b"same")?;
+    db.put(b"k", b"same")?;
+    db.batch(|b| {
+        b.put(b"k", b"same")?;
+        Ok(())
+    })?;
+
+    let evs = drain(&mut stream)?;
+    assert_eq!(evs.len(), 3, "every batch is delivered: {:?}", evs);
+    assert!(evs
+        .iter()
+        .all(|e| e.op == ChangeOp::Put && e.value.as_deref() == Some(&b"same"[..])));
+    assert!(evs.windows(2).all(|w| w[0].batch_lsn < w[1].batch_lsn));
+
+    drop(db);
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn since_lsn_skips_already_seen_batches() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("cdcsub-since");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PAGE, 8)?;
+    let mut db = Db::open(&root)?;
+    for i in 0..5 {
+        db.put(format!("a{}", i).as_bytes(), b"x")?;
+    }
+    let first = drain(&mut cdc::subscribe(&root, 0)?)?;
+    assert_eq!(first.len(), 5);
+    let resume = first[2].batch_lsn;
+
+    db.put(b"b0", b"y")?;
+    let mut stream = cdc::subscribe(&root, resume)?;
+    let keys: Vec<Vec<u8>> = drain(&mut stream)?.into_iter().map(|e| e.key).collect();
+    assert_eq!(keys, vec![b"a3".to_vec(), b"a4".to_vec(), b"b0".to_vec()]);
+
+    drop(db);
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn next_waits_for_writes_from_another_thread() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("cdcsub-async");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PAGE, 8)?;
+    let mut stream = cdc::subscribe(&root, 0)?.with_poll_interval(Duration::from_millis(5));
+
+    // Writer держит хэндл открытым, пока события не прочитаны (закрытие усекает WAL)
+    let (done_tx, done_rx) = mpsc::channel::<()>();
+    let writer_root = root.clone();
+    let writer = thread::spawn(move || -> Result<()> {
+        let mut db = Db::open(&writer_root)?;
+        thread::sleep(Duration::from_millis(50));
+        db.put(b"late", b"1")?;
+        db.put(b"later", b"2")?;
+        let _ = done_rx.recv_timeout(Duration::from_secs(30));
+        Ok(())
+    });
+
+    // Первое событие — async next(), второе — через трейт futures_core::Stream
+    let got = block_on(async {
+        let mut keys = Vec::new();
+        if let Some(ev) = stream.next().await {
+            keys.push(ev?.key);
+        }
+        let via_trait = std::future::poll_fn(|cx| Stream::poll_next(Pin::new(&mut stream), cx));
+        if let Some(ev) = via_trait.await {
+            keys.push(ev?.key);
+        }
+        Ok::<_, anyhow::Error>(keys)
+    })?;
+    done_tx.send(()).ok();
+    writer.join().expect("writer thread")?;
+    assert_eq!(got, vec![b"late".to_vec(), b"later".to_vec()]);
+
+    drop(stream);
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
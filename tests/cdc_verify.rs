@@ -0,0 +1,201 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use QuiverDB::db::digest::bucket_digests_at;
+use QuiverDB::db::{BucketDigest, Db};
+use QuiverDB::dir::{Directory, NO_PAGE};
+use QuiverDB::meta::{init_meta_v4, CKSUM_CRC32C, CODEC_NONE, HASH_KIND_XX64_SEED0};
+use QuiverDB::page::{
+    kv_header_read_v3, kv_header_write_v3, kv_init_v3, page_update_checksum, KV_HDR_MIN,
+};
+use QuiverDB::pager::Pager;
+use QuiverDB::wal::net::{
+    local_caps, CtrlMsg, CAP_DIGEST, CTRL_MAX_LEN, DIGEST_LSN_MOVED, DIGEST_MAX_BUCKETS, DIGEST_OK,
+};
+
+#[test]
+fn replicas_with_different_histories_have_equal_digests() -> Result<()> {
+    let a = unique_root("verify-a");
+    let b = unique_root("verify-b");
+    for root in [&a, &b] {
+        fs::create_dir_all(root)?;
+        Db::init(root, 4096, 16)?;
+    }
+    let all: Vec<u32> = (0..16).collect();
+    let now = 1_000;
+
+    let mut da = Db::open(&a)?;
+    for i in 0..60 {
+        da.put(
+            format!("k{:03}", i).as_bytes(),
+            format!("v{}", i).as_bytes(),
+        )?;
+    }
+    da.put(b"big", &[7u8; 10_000])?;
+    // b: другой порядок, старые версии, удалённые ключи и одно значение в OVERFLOW
+    let mut db = Db::open(&b)?;
+    db.put(b"gone", b"x")?;
+    for i in (0..60).rev() {
+        db.put(format!("k{:03}", i).as_bytes(), b"old")?;
+    }
+    for i in 0..60 {
+        db.put(
+            format!("k{:03}", i).as_bytes(),
+            format!("v{}", i).as_bytes(),
+        )?;
+    }
+    db.put(b"big", &[1u8; 9_000])?;
+    db.put(b"big", &[7u8; 10_000])?;
+    db.del(b"gone")?;
+    assert_eq!(da.bucket_digests(&all, now)?, db.bucket_digests(&all, now)?);
+    let live: u64 = da
+        .bucket_digests(&all, now)?
+        .iter()
+        .map(|d| d.live_keys)
+        .sum();
+    assert_eq!(live, 61);
+
+    // Одно изменённое значение — расходится ровно его бакет
+    db.put(b"k007", b"drift")?;
+    let (ga, gb) = (da.bucket_digests(&all, now)?, db.bucket_digests(&all, now)?);
+    let diverged: Vec<u32> = ga
+        .iter()
+        .zip(&gb)
+        .filter(|(x, y)| x != y)
+        .map(|(x, _)| x.bucket)
+        .collect();
+    assert_eq!(diverged.len(), 1);
+    let d = diverged[0];
+    assert_eq!(ga[d as usize].live_keys, gb[d as usize].live_keys);
+
+    // Бакет вне диапазона — ошибка
+    assert!(da.bucket_digests(&[16], now).is_err());
+    Ok(())
+}
+
+#[test]
+fn ttl_drift_and_expiry_are_visible_in_digest() -> Result<()> {
+    let mk = |name: &str, expires: u32| -> Result<Db> {
+        let root = unique_root(name);
+        fs::create_dir_all(&root)?;
+        init_meta_v4(&root, 4096, HASH_KIND_XX64_SEED0, CODEC_NONE, CKSUM_CRC32C)?;
+        Directory::create(&root, 1)?;
+        let mut db = Db::open(&root)?;
+        let tail = write_page(&mut db.pager, b"forever", b"v", 0, NO_PAGE)?;
+        let head = write_page(&mut db.pager, b"session", b"s", expires, tail)?;
+        db.set_dir_head(0, head)?;
+        Ok(db)
+    };
+    let leader = mk("verify-ttl-l", 5_000)?;
+    let same = mk("verify-ttl-s", 5_000)?;
+    let drifted = mk("verify-ttl-d", 6_000)?;
+
+    let at = |db: &Db, now: u32| -> Result<BucketDigest> { Ok(db.bucket_digests(&[0], now)?[0]) };
+    assert_eq!(at(&leader, 1_000)?, at(&same, 1_000)?);
+    assert_eq!(at(&leader, 1_000)?.live_keys, 2);
+
+    // Тот же ключ и значение, другой срок — расхождение при равном числе ключей
+    let (l, d) = (at(&leader, 1_000)?, at(&drifted, 1_000)?);
+    assert_eq!(l.live_keys, d.live_keys);
+    assert_ne!(l.hash, d.hash);
+
+    // Живость — на заданный now: после 5000 у leader'а ключ истёк, у drifted ещё нет
+    assert_eq!(at(&leader, 5_500)?.live_keys, 1);
+    assert_eq!(at(&drifted, 5_500)?.live_keys, 2);
+    assert_eq!(at(&leader, 7_000)?, at(&drifted, 7_000)?);
+    Ok(())
+}
+
+#[test]
+fn digest_at_path_reads_beside_live_writer_and_ctrl_roundtrip() -> Result<()> {
+    let root = unique_root("verify-live");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let mut db = Db::open(&root)?;
+    for i in 0..40 {
+        db.put(format!("key-{}", i).as_bytes(), &[b'x'; 100])?;
+    }
+    let buckets = [0u32, 3, 7];
+    // writer держит LOCK — digest по пути его не ждёт
+    let at_path = bucket_digests_at(&root, &buckets, 1_000)?;
+    assert_eq!(at_path, db.bucket_digests(&buckets, 1_000)?);
+
+    assert_eq!(local_caps() & CAP_DIGEST, CAP_DIGEST);
+    let msgs = [
+        CtrlMsg::DigestRequest {
+            lsn: 42,
+            now: 1_000,
+            buckets: buckets.to_vec(),
+        },
+        CtrlMsg::Digest {
+            lsn: 42,
+            status: DIGEST_OK,
+            digests: at_path,
+        },
+        CtrlMsg::Digest {
+            lsn: 43,
+            status: DIGEST_LSN_MOVED,
+            digests: Vec::new(),
+        },
+    ];
+    for m in msgs {
+        let enc = m.encode();
+        assert_eq!(CtrlMsg::decode(&enc)?, Some(m));
+        assert!(CtrlMsg::decode(&enc[..enc.len() - 1]).is_err());
+    }
+
+    // Полная выборка помещается в управляющий кадр
+    let full = CtrlMsg::Digest {
+        lsn: 1,
+        status: DIGEST_OK,
+        digests: (0..DIGEST_MAX_BUCKETS as u32)
+            .map(|bucket| BucketDigest {
+                bucket,
+                live_keys: u64::MAX,
+                hash: u64::MAX,
+            })
+            .collect(),
+    };
+    assert!(full.encode().len() <= CTRL_MAX_LEN);
+    Ok(())
+}
+
+fn write_page(
+    pager: &mut Pager,
+    key: &[u8],
+    value: &[u8],
+    expires_at_sec: u32,
+    next: u64,
+) -> Result<u64> {
+    let ps = pager.meta.page_size as usize;
+    let pid = pager.allocate_one_page()?;
+    let mut page = vec![0u8; ps];
+    kv_init_v3(&mut page, pid, 0)?;
+    let off = KV_HDR_MIN;
+    LittleEndian::write_u16(&mut page[off..off + 2], key.len() as u16);
+    LittleEndian::write_u32(&mut page[off + 2..off + 6], value.len() as u32);
+    LittleEndian::write_u32(&mut page[off + 6..off + 10], expires_at_sec);
+    page[off + 10] = 0;
+    let base = off + 11;
+    page[base..base + key.len()].copy_from_slice(key);
+    page[base + key.len()..base + key.len() + value.len()].copy_from_slice(value);
+    let mut h = kv_header_read_v3(&page)?;
+    h.data_start = (base + key.len() + value.len()) as u32;
+    h.next_page_id = next;
+    kv_header_write_v3(&mut page, &h)?;
+    page_update_checksum(&mut page, pager.meta.checksum_kind)?;
+    pager.commit_page(pid, &mut page)?;
+    Ok(pid)
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,49 @@
+use anyhow::Result;
+
+use QuiverDB::page::{
+    kv_init_v3, page_update_checksum, page_update_checksums_batch, page_verify_checksum,
+    verify_page_crc_strict_kind,
+};
+
+#[test]
+fn batched_checksums_match_per_page_and_verify() -> Result<()> {
+    let ps = 4096usize;
+    // Больше порога параллельного расчёта (64 по умолчанию)
+    let n = 200u64;
+
+    let mut single: Vec<Vec<u8>> = Vec::new();
+    let mut batched: Vec<Vec<u8>> = Vec::new();
+    for pid in 0..n {
+        let mut p = vec![0u8; ps];
+        kv_init_v3(&mut p, pid, 0)?;
+        for (i, b) in p[64..ps - 16].iter_mut().enumerate() {
+            *b = (i as u64 ^ pid) as u8;
+        }
+        // мусор в трейлере не должен влиять на результат
+        p[ps - 1] = 0xAB;
+        single.push(p.clone());
+        batched.push(p);
+    }
+
+    for p in single.iter_mut() {
+        page_update_checksum(p, 0)?;
+    }
+    let mut refs: Vec<(u64, &mut [u8])> = batched
+        .iter_mut()
+        .enumerate()
+        .map(|(i, p)| (i as u64, p.as_mut_slice()))
+        .collect();
+    page_update_checksums_batch(&mut refs, 0)?;
+
+    for (a, b) in single.iter().zip(batched.iter()) {
+        assert_eq!(a, b);
+        assert!(page_verify_checksum(b, 0)?);
+        assert!(verify_page_crc_strict_kind(b, 0)?);
+    }
+
+    // Порча байта payload обнаруживается
+    let mut bad = batched[7].clone();
+    bad[100] ^= 0xFF;
+    assert!(!page_verify_checksum(&bad, 0)?);
+    Ok(())
+}
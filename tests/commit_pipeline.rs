@@ -0,0 +1,42 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+use QuiverDB::metrics;
+
+#[test]
+fn pipelined_batch_commit_is_durable() -> Result<()> {
+    let root = unique_root("commit-pipeline");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+
+    metrics::reset();
+    {
+        let mut db = Db::open(&root)?;
+        db.batch(|b| {
+            for i in 0..500u32 {
+                b.put(format!("k{:04}", i).as_bytes(), &[7u8; 100])?;
+            }
+            Ok(())
+        })?;
+        // Много страниц в батче → fsync WAL шёл параллельно с записью сегментов
+        assert!(metrics::snapshot().commit_pipelined_batches >= 1);
+    }
+
+    let db = Db::open_ro(&root)?;
+    for i in 0..500u32 {
+        let v = db.get(format!("k{:04}", i).as_bytes())?;
+        assert_eq!(v.as_deref(), Some(&[7u8; 100][..]));
+    }
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
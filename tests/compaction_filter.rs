@@ -0,0 +1,77 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::compaction_filter::{CompactionRecord, FilterDecision};
+use QuiverDB::db::Db;
+
+#[test]
+fn compaction_filter_drops_and_rewrites_records() -> Result<()> {
+    let root = unique_root("compaction-filter");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 4)?;
+    let mut db = Db::open(&root)?;
+
+    db.put(b"a", b"v1:old-schema")?;
+    db.put(b"b", b"v2:current")?;
+    db.put(b"c", b"v2:stale")?;
+    db.put(b"c", b"v2:fresh")?; // старая версия под новой
+    db.put(b"keep", b"plain")?;
+    // OVERFLOW-значение с устаревшей схемой: фильтр видит его целиком
+    let mut big = b"v1:".to_vec();
+    big.extend([b'x'; 20_000]);
+    db.put(b"big", &big)?;
+    let mut huge = b"v2:".to_vec();
+    huge.extend([b'y'; 20_000]);
+    db.put(b"huge", &huge)?;
+
+    db.set_compaction_filter(|rec: &CompactionRecord<'_>| {
+        if rec.value.starts_with(b"v1:") {
+            FilterDecision::Remove
+        } else if let Some(rest) = rec.value.strip_prefix(b"v2:") {
+            let mut nv = b"v3:".to_vec();
+            nv.extend_from_slice(rest);
+            FilterDecision::ChangeValue(nv)
+        } else {
+            FilterDecision::Keep
+        }
+    });
+
+    let sum = db.vacuum_all()?;
+    assert_eq!(sum.compaction.keys_filtered_sum, 2);
+    assert_eq!(sum.compaction.values_rewritten_sum, 3);
+    assert!(
+        sum.overflow_pages_freed > 0,
+        "old OVERFLOW chains must be swept"
+    );
+
+    assert_eq!(db.get(b"a")?, None);
+    assert_eq!(db.get(b"big")?, None);
+    assert_eq!(db.get(b"b")?.as_deref(), Some(&b"v3:current"[..]));
+    assert_eq!(db.get(b"c")?.as_deref(), Some(&b"v3:fresh"[..]));
+    assert_eq!(db.get(b"keep")?.as_deref(), Some(&b"plain"[..]));
+    let h = db.get(b"huge")?.expect("rewritten overflow value");
+    assert_eq!(&h[..3], b"v3:");
+    assert_eq!(h.len(), huge.len());
+
+    // Без фильтра — обычная компактация, значения не трогаются
+    db.clear_compaction_filter();
+    assert!(db.compaction_filter_name().is_none());
+    let sum = db.compact_all()?;
+    assert_eq!((sum.keys_filtered_sum, sum.values_rewritten_sum), (0, 0));
+    assert_eq!(db.get(b"b")?.as_deref(), Some(&b"v3:current"[..]));
+    drop(db);
+
+    let db = Db::open_ro(&root)?;
+    assert_eq!(db.get(b"huge")?.map(|v| v.len()), Some(huge.len()));
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,124 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::config::{
+    resolve_effective_config, ConfigSource, QuiverConfig, Severity, CONFIG_FIELDS,
+};
+use QuiverDB::db::Db;
+
+#[test]
+fn validate_flags_and_normalize_clamps_nonsense() -> Result<()> {
+    assert!(QuiverConfig::default().validate().issues.is_empty());
+
+    let mut cfg = QuiverConfig::default()
+        .with_page_cache_pages(usize::MAX)
+        .with_ovf_threshold_bytes(Some(0));
+    cfg.hot_prefix_len = 0;
+    cfg.snap_dedup = true;
+    cfg.snapstore_dir = Some("  ".into());
+    let v = cfg.validate();
+    let fields: Vec<&str> = v.issues.iter().map(|i| i.field).collect();
+    for f in [
+        "page_cache_pages",
+        "ovf_threshold_bytes",
+        "hot_prefix_len",
+        "snap_dedup",
+        "snapstore_dir",
+    ] {
+        assert!(
+            fields.contains(&f),
+            "missing issue for {}: {:?}",
+            f,
+            v.issues
+        );
+    }
+    assert!(v.has_errors());
+    assert_eq!(v.errors().next().unwrap().field, "snapstore_dir");
+
+    let fixed = cfg.normalize();
+    assert_eq!(fixed.len(), 3);
+    assert!(cfg.page_cache_pages < usize::MAX);
+    assert_eq!(cfg.ovf_threshold_bytes, None);
+    assert_eq!(cfg.hot_prefix_len, 1);
+    assert!(cfg.normalize().is_empty(), "normalize is idempotent");
+
+    // Строковый доступ к полям: round-trip и строгий разбор
+    let d = QuiverConfig::default();
+    for f in CONFIG_FIELDS {
+        let val = d.field_value(f.name).expect("known field");
+        let mut c = QuiverConfig::default();
+        c.set_field(f.name, &val)?;
+        assert_eq!(c.field_value(f.name), Some(val), "{}", f.name);
+    }
+    assert!(QuiverConfig::default().set_field("nope", "1").is_err());
+    assert!(QuiverConfig::default()
+        .set_field("data_fsync", "maybe")
+        .is_err());
+
+    // open нормализует конфиг вместо того, чтобы принять бессмыслицу
+    let root = unique_root("cfg-validate-open");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 4)?;
+    let cfg = QuiverConfig::default().with_compaction_keep_versions(u32::MAX);
+    let mut db = Db::open_with_config(&root, cfg)?;
+    db.put(b"k", b"v")?;
+    assert_eq!(db.get(b"k")?.as_deref(), Some(&b"v"[..]));
+    Ok(())
+}
+
+#[test]
+fn effective_config_reports_provenance_per_layer() -> Result<()> {
+    let dir = unique_root("cfg-effective");
+    fs::create_dir_all(&dir)?;
+    let file = dir.join("quiver.json");
+    fs::write(
+        &file,
+        r#"{"page_cache_pages": 8192, "data_fsync": true, "tde_kid": "k1"}"#,
+    )?;
+
+    // ENV-слой: поле, которое в тестах больше никто не читает
+    std::env::set_var("P1_IO_STALL_MS", "250");
+    let eff = resolve_effective_config(
+        Some(&file),
+        &[("data_fsync".to_string(), "false".to_string())],
+    )?;
+    std::env::remove_var("P1_IO_STALL_MS");
+
+    let entry = |name: &str| eff.entries.iter().find(|e| e.field == name).unwrap();
+    assert_eq!(eff.entries.len(), CONFIG_FIELDS.len());
+    assert_eq!(entry("page_cache_pages").source, ConfigSource::File);
+    assert_eq!(entry("page_cache_pages").value, "8192");
+    assert_eq!(entry("data_fsync").source, ConfigSource::Cli);
+    assert_eq!(entry("data_fsync").value, "false");
+    assert_eq!(entry("io_stall_ms").source, ConfigSource::Env);
+    assert_eq!(entry("io_stall_ms").value, "250");
+    assert_eq!(entry("snap_persist").source, ConfigSource::Default);
+    assert_eq!(eff.config.page_cache_pages, 8192);
+    assert!(!eff.config.data_fsync);
+
+    // tde_kid без tde_enabled — предупреждение, не ошибка
+    assert!(eff
+        .issues
+        .iter()
+        .any(|i| i.field == "tde_kid" && i.severity == Severity::Warning));
+
+    // Неизвестное поле файла и неверное значение override — ошибки
+    fs::write(&file, r#"{"page_cache_pagez": 1}"#)?;
+    assert!(resolve_effective_config(Some(&file), &[]).is_err());
+    assert!(resolve_effective_config(
+        None,
+        &[("page_cache_pages".to_string(), "lots".to_string())]
+    )
+    .is_err());
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
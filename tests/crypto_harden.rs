@@ -0,0 +1,66 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::crypto::{
+    hardening_status, memory_hardening_enabled, set_memory_hardening, KeyProvider, SecretKey32,
+    StaticKeyProvider,
+};
+use QuiverDB::db::Db;
+
+// Переключатель процесс-широкий: проверки — в одном тесте, последовательно.
+#[test]
+fn hardened_keys_are_locked_or_fall_back_gracefully() -> Result<()> {
+    set_memory_hardening(false);
+    let plain = SecretKey32::new([3u8; 32]);
+    assert!(!plain.is_locked() && !plain.is_dontdump());
+    assert_eq!(*plain, [3u8; 32]);
+    assert!(!format!("{:?}", plain).contains("3, 3"));
+
+    set_memory_hardening(true);
+    assert!(memory_hardening_enabled());
+    let before = hardening_status();
+    let k = SecretKey32::new([7u8; 32]);
+    assert_eq!(*k, [7u8; 32]);
+    let st = hardening_status();
+    if k.is_locked() {
+        assert_eq!(st.locked_regions, before.locked_regions + 1);
+        assert_eq!(st.locked_bytes, st.locked_regions * 4096);
+    } else {
+        // RLIMIT_MEMLOCK не хватило — ключ всё равно работает, отказ посчитан
+        assert_eq!(st.mlock_failures, before.mlock_failures + 1);
+    }
+    let c = k.clone();
+    assert_eq!(*c, *k);
+    drop(c);
+    drop(k);
+    assert_eq!(hardening_status().locked_regions, before.locked_regions);
+
+    // Провайдеры держат ключ в SecretKey32
+    let kp = StaticKeyProvider::new("k1", [9u8; 32]);
+    assert_eq!(kp.key("k1")?.key, [9u8; 32]);
+
+    // TDE-писатель/читатель с hardening'ом
+    let root = unique_root("crypto-harden");
+    Db::init(&root, 4096, 8)?;
+    std::env::set_var("P1_TDE_KEY_HEX", "5a".repeat(32));
+    let cfg = QuiverConfig::from_env().with_tde_enabled(true);
+    {
+        let mut db = Db::open_with_config(&root, cfg.clone())?;
+        db.put(b"secret", b"value")?;
+        assert_eq!(db.get(b"secret")?.as_deref(), Some(&b"value"[..]));
+    }
+    let ro = Db::open_ro_with_config(&root, cfg)?;
+    assert_eq!(ro.get(b"secret")?.as_deref(), Some(&b"value"[..]));
+    drop(ro);
+    set_memory_hardening(false);
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
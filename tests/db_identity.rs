@@ -0,0 +1,137 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::backup::{backup_to_writer, restore_from_reader};
+use QuiverDB::db::Db;
+use QuiverDB::meta::{format_uuid, parse_uuid, read_meta};
+use QuiverDB::snapstore::{restore_from_id, SnapshotManager};
+use QuiverDB::wal::net::{CdcWelcome, CtrlMsg, CAP_HMAC};
+
+#[test]
+fn db_identity_assigned_at_init_and_legacy_on_open() -> Result<()> {
+    let root = unique_root("ident-new");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let id = read_meta(&root)?.identity();
+    assert_eq!(id.db_uuid.len(), 36);
+    assert_eq!(format_uuid(&parse_uuid(&id.db_uuid)?), id.db_uuid);
+    assert!(id.created_unix_ms > 0);
+    assert!(id.created_by.starts_with("quiverdb "));
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"k", b"v")?;
+        assert_eq!(db.identity(), id);
+    }
+    // Переживает перезапись meta при закрытии
+    assert_eq!(read_meta(&root)?.identity(), id);
+
+    // Meta старого формата (без расширения): UUID назначает writer, провенанс неизвестен
+    let legacy = unique_root("ident-legacy");
+    fs::create_dir_all(&legacy)?;
+    Db::init(&legacy, 4096, 8)?;
+    let meta_p = legacy.join("meta");
+    let bytes = fs::read(&meta_p)?;
+    fs::write(&meta_p, &bytes[..44])?;
+    assert!(!read_meta(&legacy)?.has_db_uuid());
+    {
+        let db = Db::open(&legacy)?;
+        assert!(!db.identity().db_uuid.is_empty());
+    }
+    let m = read_meta(&legacy)?;
+    assert!(m.has_db_uuid());
+    assert_eq!(m.created_unix_ms, 0);
+    assert!(m.created_by.is_empty());
+    Ok(())
+}
+
+#[test]
+fn backup_restore_carries_identity_and_refuses_foreign_increment() -> Result<()> {
+    let a = unique_root("ident-a");
+    let b = unique_root("ident-b");
+    for r in [&a, &b] {
+        fs::create_dir_all(r)?;
+        Db::init(r, 4096, 8)?;
+        let mut db = Db::open(r)?;
+        db.put(b"k", b"v")?;
+    }
+    let a_id = read_meta(&a)?.identity();
+
+    // Полный restore принимает идентичность источника
+    let mut full = Vec::new();
+    let full_man = {
+        let db = Db::open(&a)?;
+        backup_to_writer(&db, &mut full, 0)?
+    };
+    assert_eq!(full_man.db, a_id);
+    let dst = unique_root("ident-dst");
+    restore_from_reader(&dst, full.as_slice(), true)?;
+    assert_eq!(read_meta(&dst)?.identity(), a_id);
+
+    // Инкремент БД B не накатывается на копию A (и dst не трогается)
+    let mut inc = Vec::new();
+    {
+        let mut db = Db::open(&b)?;
+        db.put(b"other", b"x")?;
+        backup_to_writer(&db, &mut inc, 1)?;
+    }
+    let err = restore_from_reader(&dst, inc.as_slice(), true).unwrap_err();
+    assert!(format!("{:#}", err).contains("but the backup is of database"));
+    {
+        let db = Db::open_ro(&dst)?;
+        assert_eq!(db.get(b"other")?, None);
+    }
+
+    // Инкремент той же БД — проходит
+    let mut inc_a = Vec::new();
+    {
+        let mut db = Db::open(&a)?;
+        db.put(b"more", b"y")?;
+        backup_to_writer(&db, &mut inc_a, full_man.lsn)?;
+    }
+    restore_from_reader(&dst, inc_a.as_slice(), true)?;
+    assert_eq!(read_meta(&dst)?.identity(), a_id);
+
+    // Снапшот A: свежая dst принимает UUID, чужая БД отвергается
+    let snap_id = {
+        let db = Db::open_ro(&a)?;
+        SnapshotManager::create_persisted(&db, None, &[], None)?
+    };
+    let snap_dst = unique_root("ident-snap");
+    fs::create_dir_all(&snap_dst)?;
+    restore_from_id(&a, &snap_dst, &snap_id, true)?;
+    assert_eq!(read_meta(&snap_dst)?.identity().db_uuid, a_id.db_uuid);
+    assert!(restore_from_id(&a, &b, &snap_id, true).is_err());
+    Ok(())
+}
+
+#[test]
+fn welcome_carries_source_db_uuid() -> Result<()> {
+    let w = CdcWelcome {
+        version: 2,
+        caps: CAP_HMAC,
+        stream_id: 7,
+        start_lsn: 10,
+        keepalive_ms: 0,
+        compress: 0,
+        db_uuid: [0xab; 16],
+    };
+    let enc = CtrlMsg::Welcome(w).encode();
+    assert_eq!(CtrlMsg::decode(&enc)?, Some(CtrlMsg::Welcome(w)));
+    // WELCOME старого отправителя (без UUID) — нули
+    let old = &enc[..enc.len() - 16];
+    match CtrlMsg::decode(old)? {
+        Some(CtrlMsg::Welcome(o)) => assert_eq!(o.db_uuid, [0u8; 16]),
+        other => panic!("unexpected {:?}", other),
+    }
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,138 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::{Db, DbManager, DbQuota, ManagerConfig};
+
+fn init_db(root: &PathBuf, page_size: u32) -> Result<()> {
+    fs::create_dir_all(root)?;
+    Db::init(root, page_size, 8)?;
+    Ok(())
+}
+
+#[test]
+fn open_all_in_aggregates_stats_and_close_all_releases_locks() -> Result<()> {
+    let parent = unique_root("mgr-all");
+    for t in ["t1", "t2", "t3"] {
+        init_db(&parent.join(t), 4096)?;
+    }
+    fs::create_dir_all(parent.join("not-a-db"))?;
+
+    let mgr = DbManager::new(ManagerConfig::from_env().with_workers(2));
+    let rep = mgr.open_all_in(&parent, false)?;
+    assert_eq!(rep.opened.len(), 3, "failed: {:?}", rep.failed);
+    assert!(rep.failed.is_empty());
+    assert_eq!(mgr.names(), vec!["t1", "t2", "t3"]);
+
+    for (i, name) in mgr.names().iter().enumerate() {
+        let h = mgr.get(name).unwrap();
+        let mut db = h.lock().unwrap();
+        for k in 0..=i {
+            db.put(format!("k{}", k).as_bytes(), name.as_bytes())?;
+        }
+    }
+    let st = mgr.stats();
+    assert_eq!(st.dbs.len(), 3);
+    assert_eq!(st.total.puts, 1 + 2 + 3);
+    assert_eq!(
+        st.dbs.iter().map(|d| d.stats.puts).sum::<u64>(),
+        st.total.puts
+    );
+
+    assert_eq!(mgr.close_all(), 3);
+    assert!(mgr.is_empty());
+
+    let db = Db::open(&parent.join("t3"))?;
+    assert_eq!(db.get(b"k2")?.as_deref(), Some(&b"t3"[..]));
+    Ok(())
+}
+
+#[test]
+fn per_db_quota_bounds_shared_page_cache() -> Result<()> {
+    let a = unique_root("mgr-quota-a");
+    let b = unique_root("mgr-quota-b");
+    init_db(&a, 4096)?;
+    init_db(&b, 4096)?;
+
+    let mgr = DbManager::new(ManagerConfig::from_env().with_page_cache_pages(256));
+    let ha = mgr.open_with_quota(
+        "a",
+        &a,
+        false,
+        DbQuota {
+            page_cache_pages: Some(4),
+        },
+    )?;
+    let hb = mgr.open("b", &b)?;
+    for h in [&ha, &hb] {
+        let mut db = h.lock().unwrap();
+        for i in 0..200u32 {
+            db.put(format!("key{:04}", i).as_bytes(), &[7u8; 600])?;
+        }
+        for i in 0..200u32 {
+            assert!(db.get(format!("key{:04}", i).as_bytes())?.is_some());
+        }
+    }
+
+    let st = mgr.stats();
+    let qa = st.dbs.iter().find(|d| d.name == "a").unwrap();
+    assert_eq!(qa.quota.page_cache_pages, Some(4));
+    assert!(
+        qa.page_cache_pages <= 4,
+        "a holds {} pages",
+        qa.page_cache_pages
+    );
+
+    // Ужесточение квоты вытесняет лишние страницы сразу
+    mgr.set_quota(
+        "b",
+        DbQuota {
+            page_cache_pages: Some(2),
+        },
+    )?;
+    let qb = mgr.stats().dbs.into_iter().find(|d| d.name == "b").unwrap();
+    assert!(
+        qb.page_cache_pages <= 2,
+        "b holds {} pages",
+        qb.page_cache_pages
+    );
+    Ok(())
+}
+
+#[test]
+fn names_are_unique_and_page_size_must_match() -> Result<()> {
+    let a = unique_root("mgr-names-a");
+    let b = unique_root("mgr-names-b");
+    let c = unique_root("mgr-names-c");
+    init_db(&a, 4096)?;
+    init_db(&b, 4096)?;
+    init_db(&c, 8192)?;
+
+    let mgr = DbManager::new(ManagerConfig::from_env());
+    let h1 = mgr.open_ro("a", &a)?;
+    let h2 = mgr.open_ro("a", &a)?;
+    assert!(std::sync::Arc::ptr_eq(&h1, &h2));
+    assert!(mgr.open("a", &b).is_err());
+    assert!(mgr.open("a", &a).is_err(), "mode change must be refused");
+
+    let err = mgr.open("c", &c).err().expect("page size mismatch");
+    assert!(format!("{:#}", err).contains("page_size"), "{:#}", err);
+    assert!(mgr.get("c").is_none());
+
+    mgr.open("b", &b)?;
+    assert_eq!(mgr.len(), 2);
+    assert!(mgr.close("b"));
+    assert!(!mgr.close("b"));
+    // Хэндл закрыт (Drop) — writer снова открывается
+    drop(Db::open(&b)?);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
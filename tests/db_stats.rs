@@ -0,0 +1,119 @@
+use anyhow::Result;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use QuiverDB::db::{CommitEvent, Db, DbInstrumentation, GetEvent, PutEvent};
+
+#[derive(Default)]
+struct Recorder {
+    puts: AtomicU64,
+    dels: AtomicU64,
+    gets_found: AtomicU64,
+    gets_missing: AtomicU64,
+    commits: Mutex<Vec<(u64, usize)>>,
+}
+
+impl DbInstrumentation for Recorder {
+    fn on_put(&self, ev: &PutEvent<'_>) {
+        if ev.tombstone {
+            self.dels.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.puts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    fn on_get(&self, ev: &GetEvent<'_>) {
+        match ev.value_len {
+            Some(_) => self.gets_found.fetch_add(1, Ordering::Relaxed),
+            None => self.gets_missing.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+    fn on_commit(&self, ev: &CommitEvent) {
+        assert!(ev.last_lsn >= ev.start_lsn);
+        self.commits.lock().unwrap().push((ev.last_lsn, ev.pages));
+    }
+}
+
+#[test]
+fn stats_are_per_handle() -> Result<()> {
+    let a = unique_root("stats-a");
+    let b = unique_root("stats-b");
+    Db::init(&a, 4096, 64)?;
+    Db::init(&b, 4096, 64)?;
+    let mut da = Db::open(&a)?;
+    let mut db = Db::open(&b)?;
+
+    for i in 0..10u32 {
+        da.put(format!("k{}", i).as_bytes(), b"value")?;
+    }
+    da.del(b"k0")?;
+    assert!(da.get(b"k1")?.is_some());
+    assert!(da.get(b"k0")?.is_none());
+    let _ = da.get_many(&[b"k2", b"nope"])?;
+
+    db.batch(|bt| {
+        bt.put(b"x", b"1")?;
+        bt.put(b"y", b"22")?;
+        bt.del(b"z")?;
+        Ok(())
+    })?;
+
+    let sa = da.stats();
+    assert_eq!(sa.puts, 10);
+    assert_eq!(sa.put_bytes, 10 * 2 + 10 * 5);
+    assert_eq!(sa.dels, 1);
+    assert_eq!((sa.gets, sa.get_hits, sa.get_misses), (4, 2, 2));
+    assert_eq!(sa.get_bytes, 10);
+    assert_eq!(sa.commits, 11);
+
+    let sb = db.stats();
+    assert_eq!((sb.puts, sb.dels, sb.put_bytes), (2, 1, 5));
+    assert_eq!(sb.gets, 0);
+    assert_eq!(sb.commits, 1);
+
+    da.reset_stats();
+    assert_eq!(da.stats().puts, 0);
+    assert_eq!(db.stats().puts, 2);
+    Ok(())
+}
+
+#[test]
+fn instrumentation_hook_sees_ops_and_commits() -> Result<()> {
+    let root = unique_root("stats-hook");
+    Db::init(&root, 4096, 64)?;
+    let mut db = Db::open(&root)?;
+    let rec = Arc::new(Recorder::default());
+    db.set_instrumentation(rec.clone());
+    assert!(db.has_instrumentation());
+
+    db.put(b"a", b"1")?;
+    db.put(b"b", &vec![7u8; 20_000])?; // OVERFLOW: несколько страниц в одном коммите
+    db.del(b"a")?;
+    assert!(db.get(b"b")?.is_some());
+    assert!(db.get(b"a")?.is_none());
+    db.batch(|bt| bt.put(b"c", b"3"))?;
+
+    assert_eq!(rec.puts.load(Ordering::Relaxed), 2);
+    assert_eq!(rec.dels.load(Ordering::Relaxed), 1);
+    assert_eq!(rec.gets_found.load(Ordering::Relaxed), 1);
+    assert_eq!(rec.gets_missing.load(Ordering::Relaxed), 1);
+    let commits = rec.commits.lock().unwrap().clone();
+    assert_eq!(commits.len(), 4);
+    assert!(commits[1].1 > 1);
+    assert!(commits.windows(2).all(|w| w[0].0 < w[1].0));
+    assert_eq!(commits.last().unwrap().0, db.pager.meta.last_lsn);
+
+    db.clear_instrumentation();
+    db.put(b"d", b"4")?;
+    assert_eq!(rec.puts.load(Ordering::Relaxed), 2);
+    assert_eq!(db.stats().puts, 4);
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,90 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+
+#[test]
+fn delete_prefix_hides_old_keys_and_compaction_retires_marker() -> Result<()> {
+    let root = unique_root("delete-prefix");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let mut db = Db::open(&root)?;
+
+    for i in 0..50u32 {
+        db.put(format!("tenant1/{:03}", i).as_bytes(), b"v")?;
+        db.put(format!("tenant2/{:03}", i).as_bytes(), b"w")?;
+    }
+    let big = vec![b'o'; 20_000];
+    db.put(b"tenant1/big", &big)?;
+
+    let lsn = db.delete_prefix(b"tenant1/")?;
+    assert!(lsn > 0);
+    assert_eq!(db.range_tombstones().len(), 1);
+
+    // Запись после маркера живая
+    db.put(b"tenant1/007", b"reborn")?;
+
+    assert_eq!(db.get(b"tenant1/000")?, None);
+    assert_eq!(db.get(b"tenant1/big")?, None);
+    assert!(!db.exists(b"tenant1/001")?);
+    assert_eq!(db.get(b"tenant1/007")?.as_deref(), Some(&b"reborn"[..]));
+    assert!(db.exists(b"tenant1/007")?);
+    assert_eq!(db.get(b"tenant2/000")?.as_deref(), Some(&b"w"[..]));
+    let many = db.get_many(&[b"tenant1/002", b"tenant1/007", b"tenant2/003"])?;
+    assert_eq!(many[0], None);
+    assert_eq!(many[1].as_deref(), Some(&b"reborn"[..]));
+    assert_eq!(many[2].as_deref(), Some(&b"w"[..]));
+    assert_eq!(
+        db.exists_many(&[b"tenant1/002", b"tenant1/007"])?,
+        vec![false, true]
+    );
+    let t1 = db.scan_prefix(b"tenant1/")?;
+    assert_eq!(t1, vec![(b"tenant1/007".to_vec(), b"reborn".to_vec())]);
+    assert_eq!(db.scan_all()?.len(), 51);
+    assert_eq!(db.get_versions(b"tenant1/007", 10)?.len(), 1);
+    drop(db);
+
+    // Маркер переживает переоткрытие (RO и writer)
+    {
+        let ro = Db::open_ro(&root)?;
+        assert_eq!(ro.get(b"tenant1/010")?, None);
+        assert_eq!(ro.scan_prefix(b"tenant1/")?.len(), 1);
+    }
+
+    let mut db = Db::open(&root)?;
+    assert_eq!(db.range_tombstones().len(), 1);
+    let sum = db.compact_all()?;
+    assert!(sum.keys_deleted_sum >= 50);
+    // Все бакеты компактированы — маркер снят, данные вычищены физически
+    assert!(db.range_tombstones().is_empty());
+    assert!(!root.join("range_tombstones.json").exists());
+    assert_eq!(db.get(b"tenant1/000")?, None);
+    assert_eq!(db.get(b"tenant1/007")?.as_deref(), Some(&b"reborn"[..]));
+    assert_eq!(db.scan_all()?.len(), 51);
+    Ok(())
+}
+
+#[test]
+fn delete_prefix_is_rejected_on_readonly_handle() -> Result<()> {
+    let root = unique_root("delete-prefix-ro");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 2)?;
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"a/1", b"x")?;
+    }
+    let mut ro = Db::open_ro(&root)?;
+    assert!(ro.delete_prefix(b"a/").is_err());
+    assert_eq!(ro.get(b"a/1")?.as_deref(), Some(&b"x"[..]));
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,125 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::digest::DIGEST_TREE_FANOUT;
+use QuiverDB::db::{BucketDigest, Db, DigestTree};
+
+fn naive_diff(a: &DigestTree, b: &DigestTree) -> Vec<u32> {
+    a.leaves
+        .iter()
+        .zip(&b.leaves)
+        .filter(|(x, y)| x != y)
+        .map(|(x, _)| x.bucket)
+        .collect()
+}
+
+#[test]
+fn tree_diff_finds_exactly_the_differing_buckets() -> Result<()> {
+    let a = unique_root("dtree-a");
+    let b = unique_root("dtree-b");
+    for root in [&a, &b] {
+        fs::create_dir_all(root)?;
+        // 300 бакетов — три внутренних уровня (19 -> 2 -> 1)
+        Db::init(root, 4096, 300)?;
+    }
+    let now = 1_000;
+    let mut da = Db::open(&a)?;
+    let mut db = Db::open(&b)?;
+    for i in 0..500 {
+        let k = format!("key-{:04}", i);
+        da.put(k.as_bytes(), b"v")?;
+        db.put(k.as_bytes(), b"v")?;
+    }
+    let (ta, tb) = (da.digest_tree_at(now)?, db.digest_tree_at(now)?);
+    assert_eq!(ta.levels.len(), 3);
+    assert_eq!(ta.root(), tb.root());
+    assert_ne!(ta.root(), 0);
+    assert!(ta.diff(&tb)?.is_empty());
+
+    // Расхождения: изменённое значение, лишний и удалённый ключ
+    db.put(b"key-0042", b"drift")?;
+    db.put(b"only-on-b", b"x")?;
+    db.del(b"key-0300")?;
+    let tb = db.digest_tree_at(now)?;
+    assert_ne!(ta.root(), tb.root());
+    let d = ta.diff(&tb)?;
+    assert!(!d.is_empty() && d.len() <= 3);
+    assert_eq!(d, naive_diff(&ta, &tb));
+    assert_eq!(tb.diff(&ta)?, d);
+
+    // После выравнивания — снова равны
+    da.put(b"key-0042", b"drift")?;
+    da.put(b"only-on-b", b"x")?;
+    da.del(b"key-0300")?;
+    assert_eq!(da.digest_tree_at(now)?.root(), tb.root());
+    Ok(())
+}
+
+#[test]
+fn bucket_digest_reports_lsn_and_matches_tree_leaf() -> Result<()> {
+    let root = unique_root("dtree-lsn");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let mut db = Db::open(&root)?;
+    db.put(b"alpha", b"1")?;
+    db.put(b"beta", b"2")?;
+    let tree = db.digest_tree()?;
+    assert_eq!(tree.leaves.len(), 8);
+    for leaf in &tree.leaves {
+        let (lsn, hash) = db.bucket_digest(leaf.bucket)?;
+        assert_eq!(lsn, tree.lsn);
+        assert_eq!(hash, leaf.hash);
+    }
+    assert!(db.bucket_digest(8).is_err());
+
+    let lsn0 = tree.lsn;
+    db.put(b"gamma", b"3")?;
+    let (lsn1, _) = db.bucket_digest(0)?;
+    assert!(lsn1 > lsn0);
+    drop(db);
+
+    // RO-хэндл видит то же дерево
+    let ro = Db::open_ro(&root)?;
+    let t_ro = ro.digest_tree_at(tree.now)?;
+    assert_eq!(t_ro.leaves.iter().map(|d| d.live_keys).sum::<u64>(), 3);
+    assert_eq!(t_ro.lsn, lsn1);
+    Ok(())
+}
+
+#[test]
+fn tree_shape_and_incomparable_trees() -> Result<()> {
+    let leaf = |b: u32, h: u64| BucketDigest {
+        bucket: b,
+        live_keys: u64::from(h != 0),
+        hash: h,
+    };
+    let one = DigestTree::from_leaves(1, 0, vec![leaf(0, 5)]);
+    assert_eq!(one.levels.len(), 1);
+    assert_ne!(one.root(), 0);
+
+    let n = DIGEST_TREE_FANOUT as u32;
+    let full = DigestTree::from_leaves(1, 0, (0..n).map(|b| leaf(b, 0)).collect());
+    assert_eq!(full.levels.len(), 1);
+    let over: Vec<BucketDigest> = (0..=n).map(|b| leaf(b, u64::from(b))).collect();
+    let t1 = DigestTree::from_leaves(1, 0, over.clone());
+    assert_eq!(t1.levels.len(), 2);
+    assert_eq!(t1.levels[0].len(), 2);
+
+    // Последний (неполный) узел тоже сравнивается
+    let mut changed = over;
+    changed[n as usize].hash = 999;
+    let t2 = DigestTree::from_leaves(2, 0, changed);
+    assert_eq!(t1.diff(&t2)?, vec![n]);
+
+    assert!(t1.diff(&full).is_err());
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+use QuiverDB::dir::{Directory, HEADS_GEN_FILE, NO_PAGE};
+
+/// Кешированный читатель каталога видит HEADS_UPDATE writer'а через генерацию heads.gen.
+#[test]
+fn dir_heads_cache_follows_writer_generation() -> Result<()> {
+    let root = unique_root("dir-heads-gen");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+
+    let mut db = Db::open(&root)?;
+    assert!(root.join(HEADS_GEN_FILE).exists());
+    let hk = db.pager.meta.hash_kind;
+    let bucket = db.dir.bucket_of_key(b"late", hk);
+
+    // Lock-free читатель (как admin UI/status) с кешем голов
+    let mut rd = Directory::open(&root)?;
+    assert!(rd.enable_heads_cache(false)?);
+    let g0 = rd.heads_generation().unwrap();
+    assert_eq!(rd.head(bucket)?, NO_PAGE);
+
+    db.put(b"late", b"v")?;
+    let g1 = rd.heads_generation().unwrap();
+    assert!(g1 > g0, "writer must bump generation");
+    let h = rd.head(bucket)?;
+    assert_ne!(h, NO_PAGE, "cached reader must reload heads");
+    assert_eq!(h, db.dir.head(bucket)?);
+
+    // Без новых обновлений генерация стабильна — кеш не перечитывается
+    assert_eq!(rd.heads_generation(), Some(g1));
+    drop(db);
+
+    // RO-хэндл после writer'а
+    let ro = Db::open_ro(&root)?;
+    assert!(ro.dir.heads_generation().is_some());
+    assert_eq!(ro.get(b"late")?.as_deref(), Some(&b"v"[..]));
+    drop(ro);
+
+    // Старые БД без heads.gen: RO читает головы с диска
+    fs::remove_file(root.join(HEADS_GEN_FILE))?;
+    let ro = Db::open_ro(&root)?;
+    assert!(ro.dir.heads_generation().is_none());
+    assert_eq!(ro.get(b"late")?.as_deref(), Some(&b"v"[..]));
+    drop(ro);
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -2,8 +2,13 @@ use anyhow::Result;
 use std::fs;
 use std::path::PathBuf;
 
+use std::io::{Seek, SeekFrom, Write};
 use QuiverDB::db::Db;
 
+use QuiverDB::page::{page_update_checksum, page_version, OFF_VERSION, PAGE_VERSION_V3};
+use QuiverDB::pager::cache::page_cache_clear;
+use QuiverDB::pager::{DATA_SEG_EXT, DATA_SEG_PREFIX, SEGMENT_SIZE};
+
 #[test]
 fn doctor_runs_ok() -> Result<()> {
     let root = unique_root("doctor");
@@ -31,6 +36,92 @@ fn doctor_runs_ok() -> Result<()> {
     Ok(())
 }
 
+/// Отчёт структурой (CLI --output) совпадает по ключам с legacy JSON doctor(true).
+#[test]
+fn doctor_report_serializes() -> Result<()> {
+    let root = unique_root("doctor-report");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"a", b"1")?;
+        db.put(b"b", b"2")?;
+    }
+
+    let db = Db::open_ro(&root)?;
+    let rep = db.doctor_report()?;
+    assert_eq!(rep.mode, "crc");
+    assert_eq!(rep.pages_total, db.pager.meta.next_page_id);
+    assert_eq!(rep.crc_fail + rep.io_fail, 0);
+    assert_eq!(rep.kv_pages, 2);
+
+    let v = serde_json::to_value(&rep)?;
+    for k in [
+        "mode",
+        "pages_total",
+        "ok_pages",
+        "zero_checksum",
+        "crc_fail",
+        "io_fail",
+        "kv_pages",
+        "overflow_pages",
+        "legacy_pages",
+        "other_magic",
+        "no_magic",
+    ] {
+        assert!(v.get(k).is_some(), "{k}");
+    }
+    assert_eq!(v["strict"]["doctor_strict"], serde_json::json!(false));
+    Ok(())
+}
+
+/// Страница P2PG не v3 считается legacy (а не KV по совпавшему коду типа).
+#[test]
+fn doctor_counts_legacy_pages_by_version() -> Result<()> {
+    let root = unique_root("doctor-legacy");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let ps = 4096usize;
+    let mut buf = vec![0u8; ps];
+    let pid = {
+        let mut db = Db::open(&root)?;
+        db.put(b"a", b"1")?;
+        db.put(b"b", b"2")?;
+        let mut target = None;
+        for pid in 0..db.pager.meta.next_page_id {
+            db.pager.read_page(pid, &mut buf)?;
+            if page_version(&buf) == Some(PAGE_VERSION_V3) {
+                target = Some(pid);
+                break;
+            }
+        }
+        target.expect("a v3 page")
+    };
+
+    // Переписать версию страницы в сегменте (с валидным CRC), мимо pager'а
+    buf[OFF_VERSION..OFF_VERSION + 2].copy_from_slice(&2u16.to_le_bytes());
+    page_update_checksum(&mut buf, 0)?;
+    let pps = SEGMENT_SIZE / ps as u64;
+    let seg = root.join(format!(
+        "{}{:06}.{}",
+        DATA_SEG_PREFIX,
+        pid / pps + 1,
+        DATA_SEG_EXT
+    ));
+    let mut f = fs::OpenOptions::new().write(true).open(seg)?;
+    f.seek(SeekFrom::Start((pid % pps) * ps as u64))?;
+    f.write_all(&buf)?;
+    f.sync_all()?;
+    page_cache_clear();
+
+    let db = Db::open_ro(&root)?;
+    let rep = db.doctor_report()?;
+    assert_eq!(rep.legacy_pages, 1);
+    assert_eq!(rep.kv_pages, 1);
+    assert_eq!(rep.crc_fail + rep.io_fail, 0);
+    Ok(())
+}
+
 fn unique_root(prefix: &str) -> PathBuf {
     let pid = std::process::id();
     let t = std::time::SystemTime::now()
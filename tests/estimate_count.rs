@@ -0,0 +1,73 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+
+fn fill(db: &mut Db) -> Result<()> {
+    for i in 0..2000u32 {
+        db.put(format!("user:{:05}", i).as_bytes(), b"u")?;
+    }
+    for i in 0..300u32 {
+        db.put(format!("item:{:05}", i).as_bytes(), &[7u8; 64])?;
+    }
+    for i in (0..2000u32).step_by(10) {
+        db.del(format!("user:{:05}", i).as_bytes())?;
+    }
+    Ok(())
+}
+
+#[test]
+fn sampled_estimate_brackets_true_count() -> Result<()> {
+    let root = unique_root("estimate-sample");
+    Db::init(&root, 4096, 128)?;
+    let mut db = Db::open(&root)?;
+    fill(&mut db)?;
+    let truth = db.scan_keys(Some(b"user:"))?.len() as u64;
+    assert_eq!(truth, 1800);
+
+    let e = db.estimate_count_prefix_sampled(b"user:", 32)?;
+    assert_eq!(e.method, "sample");
+    assert!(!e.exact);
+    assert_eq!((e.buckets_sampled, e.buckets_total), (32, 128));
+    assert!(e.low <= e.estimate && e.estimate <= e.high);
+    assert!(e.low <= truth && truth <= e.high, "{:?}", e);
+    assert!(e.keys_seen < truth);
+
+    // Все бакеты — точный счёт
+    let full = db.estimate_count_prefix_sampled(b"user:", 1000)?;
+    assert!(full.exact);
+    assert_eq!((full.estimate, full.low, full.high), (truth, truth, truth));
+
+    // Префикс без ключей
+    let none = db.estimate_count_prefix(b"nope:")?;
+    assert_eq!((none.estimate, none.high), (0, 0));
+    Ok(())
+}
+
+#[test]
+fn reader_estimate_uses_keydir_and_honours_prefix_deletes() -> Result<()> {
+    let root = unique_root("estimate-keydir");
+    Db::init(&root, 4096, 64)?;
+    {
+        let mut db = Db::open(&root)?;
+        fill(&mut db)?;
+        db.delete_prefix(b"item:")?;
+        db.put(b"item:fresh", b"x")?;
+    }
+
+    let ro = Db::open_ro(&root)?;
+    let e = ro.estimate_count_prefix(b"user:")?;
+    assert_eq!(e.method, "keydir");
+    assert_eq!(e.estimate, 1800);
+    assert_eq!(ro.estimate_count_prefix(b"item:")?.estimate, 1);
+    assert_eq!(ro.estimate_count_prefix(b"")?.estimate, 1801);
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,194 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::Db;
+use QuiverDB::dir::{Directory, NO_PAGE};
+use QuiverDB::meta::{init_meta_v4, CKSUM_CRC32C, CODEC_NONE, HASH_KIND_XX64_SEED0};
+use QuiverDB::page::{
+    kv_header_read_v3, kv_header_write_v3, kv_init_v3, page_update_checksum, KV_HDR_MIN,
+};
+use QuiverDB::pager::Pager;
+use QuiverDB::wal::reader::WalStreamReader;
+use QuiverDB::wal::{parse_expiry_payload, ExpiryEvent, WAL_FILE, WAL_HDR_SIZE, WAL_REC_EXPIRY};
+
+#[test]
+fn compaction_emits_expiry_events_to_subscribers_and_wal() -> Result<()> {
+    let root = unique_root("expiry-events");
+    fs::create_dir_all(&root)?;
+    init_meta_v4(&root, 4096, HASH_KIND_XX64_SEED0, CODEC_NONE, CKSUM_CRC32C)?;
+    Directory::create(&root, 1)?; // один бакет — все ключи в одной цепочке
+
+    let cfg = QuiverConfig::from_env().with_wal_expiry_events(true);
+    let mut db = Db::open_with_config(&root, cfg)?;
+
+    let expired_at = now_secs().saturating_sub(10);
+    // Цепочка (head → tail): gone(expired) → ttl-key(expired) → ttl-key(old, бессрочно)
+    let pager = &mut db.pager;
+    let old = write_page(pager, b"ttl-key", b"old", 0, NO_PAGE)?;
+    let mid = write_page(pager, b"ttl-key", b"expired", expired_at, old)?;
+    let head = write_page(pager, b"gone", b"x", expired_at, mid)?;
+    let solo = write_page(pager, b"solo", b"y", expired_at, NO_PAGE)?;
+    db.set_dir_head(0, head)?;
+    let rx = db.subscribe_expiry();
+
+    // [1] Компактация: "ttl-key" сохраняет старое значение, "gone" — вычищен по TTL
+    let rep = db.compact_bucket(0)?;
+    assert_eq!(rep.keys_kept, 1);
+    assert_eq!(rep.keys_expired, 1);
+    let ev: ExpiryEvent = rx.try_recv()?;
+    assert_eq!(ev.key, b"gone");
+    assert_eq!(ev.expired_at, expired_at);
+    assert_eq!(ev.purge_lsn, db.pager.meta.last_lsn);
+    assert!(rx.try_recv().is_err(), "live key must not produce an event");
+    assert_eq!(db.get(b"ttl-key")?.as_deref(), Some(&b"old"[..]));
+    let first_lsn = ev.purge_lsn;
+
+    // [2] Бакет без живых ключей: кадр EXPIRY уходит отдельным (пустым) батчем
+    db.set_dir_head(0, solo)?;
+    let rep = db.compact_bucket(0)?;
+    assert_eq!((rep.keys_kept, rep.keys_expired), (0, 1));
+    let ev = rx.try_recv()?;
+    assert_eq!(ev.key, b"solo");
+    assert!(ev.purge_lsn > first_lsn);
+
+    // [3] WAL несёт оба кадра EXPIRY (LSN кадра = purge LSN)
+    let from_wal = read_expiry_frames(&root.join(WAL_FILE))?;
+    let keys: Vec<(&[u8], u64)> = from_wal
+        .iter()
+        .map(|e| (e.key.as_slice(), e.purge_lsn))
+        .collect();
+    assert_eq!(
+        keys,
+        vec![(&b"gone"[..], first_lsn), (&b"solo"[..], ev.purge_lsn)]
+    );
+
+    // Отписавшийся получатель не мешает публикации
+    drop(rx);
+    db.publish_expiry_events(&from_wal);
+    Ok(())
+}
+
+/// Возобновление подписки: события после since_lsn — из WAL, затем живые; разрыв — ошибка.
+#[test]
+fn subscribe_expiry_since_backfills_from_wal() -> Result<()> {
+    let root = unique_root("expiry-resume");
+    fs::create_dir_all(&root)?;
+    init_meta_v4(&root, 4096, HASH_KIND_XX64_SEED0, CODEC_NONE, CKSUM_CRC32C)?;
+    Directory::create(&root, 1)?;
+
+    let cfg = QuiverConfig::from_env().with_wal_expiry_events(true);
+    let mut db = Db::open_with_config(&root, cfg.clone())?;
+    let expired_at = now_secs().saturating_sub(10);
+
+    // Подписчик «офлайн»: два батча вычистки проходят без него
+    let mut purge_lsns = Vec::new();
+    for key in [&b"a"[..], &b"b"[..]] {
+        let pid = write_page(&mut db.pager, key, b"x", expired_at, NO_PAGE)?;
+        db.set_dir_head(0, pid)?;
+        assert_eq!(db.compact_bucket(0)?.keys_expired, 1);
+        purge_lsns.push(db.pager.meta.last_lsn);
+    }
+
+    // Видел "a" — догоняет "b" из WAL
+    let rx = db.subscribe_expiry_since(purge_lsns[0])?;
+    let ev = rx.try_recv()?;
+    assert_eq!(
+        (ev.key.as_slice(), ev.purge_lsn),
+        (&b"b"[..], purge_lsns[1])
+    );
+    assert!(rx.try_recv().is_err());
+    // С начала WAL — оба
+    let all: Vec<Vec<u8>> = db
+        .subscribe_expiry_since(0)?
+        .try_iter()
+        .map(|e| e.key)
+        .collect();
+    assert_eq!(all, vec![b"a".to_vec(), b"b".to_vec()]);
+
+    // Дальше — живая доставка
+    let pid = write_page(&mut db.pager, b"c", b"x", expired_at, NO_PAGE)?;
+    db.set_dir_head(0, pid)?;
+    db.compact_bucket(0)?;
+    assert_eq!(rx.try_recv()?.key, b"c");
+    let last = db.pager.meta.last_lsn;
+    drop(rx);
+    drop(db);
+
+    // WAL усечён при закрытии: старый since_lsn больше не покрыт
+    let db = Db::open_with_config(&root, cfg)?;
+    let err = db.subscribe_expiry_since(purge_lsns[0]).unwrap_err();
+    assert!(format!("{:#}", err).contains("resync"), "{:#}", err);
+    assert!(db.subscribe_expiry_since(last)?.try_recv().is_err());
+    drop(db);
+
+    // Без wal_expiry_events пропущенные события восстановить нельзя
+    let db = Db::open_with_config(
+        &root,
+        QuiverConfig::from_env().with_wal_expiry_events(false),
+    )?;
+    assert!(db.subscribe_expiry_since(last - 1).is_err());
+    Ok(())
+}
+
+fn read_expiry_frames(wal: &Path) -> Result<Vec<ExpiryEvent>> {
+    let mut f = fs::File::open(wal)?;
+    let len = f.metadata()?.len();
+    let mut rdr = WalStreamReader::new();
+    let mut pos = WAL_HDR_SIZE as u64;
+    let mut out = Vec::new();
+    while let Some((rec, next)) = rdr.read_next(&mut f, pos, len)? {
+        if rec.rec_type == WAL_REC_EXPIRY {
+            out.extend(parse_expiry_payload(&rec.payload, rec.lsn));
+        }
+        pos = next;
+    }
+    Ok(out)
+}
+
+fn write_page(
+    pager: &mut Pager,
+    key: &[u8],
+    value: &[u8],
+    expires_at_sec: u32,
+    next: u64,
+) -> Result<u64> {
+    let ps = pager.meta.page_size as usize;
+    let pid = pager.allocate_one_page()?;
+    let mut page = vec![0u8; ps];
+    kv_init_v3(&mut page, pid, 0)?;
+    let off = KV_HDR_MIN;
+    LittleEndian::write_u16(&mut page[off..off + 2], key.len() as u16);
+    LittleEndian::write_u32(&mut page[off + 2..off + 6], value.len() as u32);
+    LittleEndian::write_u32(&mut page[off + 6..off + 10], expires_at_sec);
+    page[off + 10] = 0;
+    let base = off + 11;
+    page[base..base + key.len()].copy_from_slice(key);
+    page[base + key.len()..base + key.len() + value.len()].copy_from_slice(value);
+    let mut h = kv_header_read_v3(&page)?;
+    h.data_start = (base + key.len() + value.len()) as u32;
+    h.next_page_id = next;
+    kv_header_write_v3(&mut page, &h)?;
+    page_update_checksum(&mut page, pager.meta.checksum_kind)?;
+    pager.commit_page(pid, &mut page)?;
+    Ok(pid)
+}
+
+fn now_secs() -> u32 {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    now.as_secs().min(u32::MAX as u64) as u32
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,80 @@
+use anyhow::Result;
+use std::fs;
+
+use QuiverDB::db::Db;
+use QuiverDB::export::{export_sorted_to_path, read_sorted_export_path, ExportOptions};
+
+/// export-sorted: маленький бюджет памяти → несколько spill-run'ов; дамп строго упорядочен,
+/// содержит только живые ключи, run-файлы удалены.
+#[test]
+fn export_sorted_spills_and_merges() -> Result<()> {
+    let root = unique_root("export");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 64 * 1024, 16)?;
+    {
+        let mut db = Db::open(&root)?;
+        // Перемешанный порядок вставки
+        for i in 0..500u32 {
+            let n = (i * 7919) % 500;
+            db.put(
+                format!("k{:05}", n).as_bytes(),
+                format!("v{}", n).as_bytes(),
+            )?;
+        }
+        db.del(b"k00042")?;
+        db.put(b"k00007", b"new")?;
+    }
+
+    let tmp = root.join("tmp");
+    fs::create_dir_all(&tmp)?;
+    let out = root.join("dump.qex");
+    let db = Db::open_ro(&root)?;
+    let opts = ExportOptions {
+        mem_budget_bytes: 4096,
+        tmp_dir: Some(tmp.clone()),
+        prefix: None,
+    };
+    let rep = export_sorted_to_path(&db, &out, &opts)?;
+    assert_eq!(rep.keys, 499);
+    assert!(rep.runs_spilled > 1, "expected spills: {:?}", rep);
+    assert_eq!(fs::read_dir(&tmp)?.count(), 0, "run files must be removed");
+
+    let mut keys: Vec<Vec<u8>> = Vec::new();
+    let n = read_sorted_export_path(&out, |k, v| {
+        if k == b"k00007" {
+            assert_eq!(v, b"new");
+        }
+        keys.push(k.to_vec());
+        Ok(())
+    })?;
+    assert_eq!(n, 499);
+    assert!(keys.windows(2).all(|w| w[0] < w[1]));
+    assert!(!keys.iter().any(|k| k == b"k00042"));
+
+    // Префикс, всё в памяти
+    let opts = ExportOptions {
+        prefix: Some(b"k0049".to_vec()),
+        ..Default::default()
+    };
+    let rep = export_sorted_to_path(&db, &out, &opts)?;
+    assert_eq!((rep.keys, rep.runs_spilled), (10, 0));
+
+    // Порча тела → ошибка CRC при чтении
+    let mut bytes = fs::read(&out)?;
+    let mid = 32 + 10;
+    bytes[mid] ^= 0xFF;
+    fs::write(&out, &bytes)?;
+    assert!(read_sorted_export_path(&out, |_, _| Ok(())).is_err());
+    Ok(())
+}
+
+// ---------- helpers ----------
+
+fn unique_root(prefix: &str) -> std::path::PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
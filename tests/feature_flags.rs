@@ -0,0 +1,52 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+use QuiverDB::meta::{
+    add_features, feature_names, read_meta, FEATURE_HEADS_GEN, FEATURE_KV_PACKING, FEATURE_TDE,
+};
+
+#[test]
+fn writer_records_features_and_unknown_required_fail_fast() -> Result<()> {
+    let root = unique_root("features");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    assert_eq!(read_meta(&root)?.flags, 0);
+
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"k", b"v")?;
+    }
+    let m = read_meta(&root)?;
+    assert_eq!(m.flags & FEATURE_KV_PACKING, FEATURE_KV_PACKING);
+    assert_eq!(m.flags & FEATURE_HEADS_GEN, FEATURE_HEADS_GEN);
+    assert_eq!(m.flags & FEATURE_TDE, 0);
+    assert!(feature_names(m.flags).contains(&"kv_packing".to_string()));
+
+    // Неизвестный optional-бит не мешает открытию и переживает закрытие writer'а
+    add_features(&root, 1 << 29)?;
+    {
+        let db = Db::open(&root)?;
+        assert_eq!(db.get(b"k")?.as_deref(), Some(&b"v"[..]));
+    }
+    assert_ne!(read_meta(&root)?.flags & (1 << 29), 0);
+
+    // Неизвестный required-бит: open и open_ro падают сразу с понятной ошибкой
+    add_features(&root, 1 << 12)?;
+    for res in [Db::open(&root).map(|_| ()), Db::open_ro(&root).map(|_| ())] {
+        let msg = format!("{:#}", res.unwrap_err());
+        assert!(msg.contains("required_bit12"), "{}", msg);
+        assert!(msg.contains("unsupported"), "{}", msg);
+    }
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,159 @@
+#![cfg(feature = "ffi")]
+
+use std::ffi::CString;
+use std::fs;
+use std::path::PathBuf;
+use std::ptr;
+
+use QuiverDB::ffi::{
+    qdb_close, qdb_get_into, qdb_get_view, qdb_init, qdb_open_writer, qdb_put, qdb_view_release,
+    QdbDb, QdbView,
+};
+
+#[test]
+fn ffi_get_into_caller_buffer() {
+    let root = unique_root("ffi-get-into");
+    fs::create_dir_all(&root).unwrap();
+    let cpath = CString::new(root.to_str().unwrap()).unwrap();
+    let mut err: *mut libc::c_char = ptr::null_mut();
+
+    unsafe {
+        assert_eq!(qdb_init(cpath.as_ptr(), 4096, 16, &mut err), 0);
+        let mut db: *mut QdbDb = ptr::null_mut();
+        assert_eq!(qdb_open_writer(cpath.as_ptr(), &mut db, &mut err), 0);
+
+        let key = b"k";
+        let val = vec![0x5Au8; 10_000];
+        assert_eq!(
+            qdb_put(
+                db,
+                key.as_ptr(),
+                key.len(),
+                val.as_ptr(),
+                val.len(),
+                &mut err
+            ),
+            0
+        );
+
+        // Буфер мал: длина сообщается, буфер не тронут
+        let mut small = [0u8; 16];
+        let (mut len, mut found) = (0usize, 0i32);
+        let rc = qdb_get_into(
+            db,
+            key.as_ptr(),
+            key.len(),
+            small.as_mut_ptr(),
+            small.len(),
+            &mut len,
+            &mut found,
+            &mut err,
+        );
+        assert_eq!(rc, 0);
+        assert_eq!((found, len), (1, val.len()));
+        assert_eq!(small, [0u8; 16]);
+
+        // Буфер достаточен
+        let mut buf = vec![0u8; len];
+        let rc = qdb_get_into(
+            db,
+            key.as_ptr(),
+            key.len(),
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut len,
+            &mut found,
+            &mut err,
+        );
+        assert_eq!(rc, 0);
+        assert_eq!(buf, val);
+
+        // Отсутствующий ключ
+        let rc = qdb_get_into(
+            db,
+            b"nope".as_ptr(),
+            4,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut len,
+            &mut found,
+            &mut err,
+        );
+        assert_eq!((rc, found, len), (0, 0, 0));
+
+        qdb_close(db);
+    }
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn ffi_get_view_hands_out_value_buffer() {
+    let root = unique_root("ffi-get-view");
+    fs::create_dir_all(&root).unwrap();
+    let cpath = CString::new(root.to_str().unwrap()).unwrap();
+    let mut err: *mut libc::c_char = ptr::null_mut();
+
+    unsafe {
+        assert_eq!(qdb_init(cpath.as_ptr(), 4096, 16, &mut err), 0);
+        let mut db: *mut QdbDb = ptr::null_mut();
+        assert_eq!(qdb_open_writer(cpath.as_ptr(), &mut db, &mut err), 0);
+
+        // Значение больше страницы — OVERFLOW-цепочка
+        let key = b"big";
+        let val: Vec<u8> = (0..50_000u32).map(|i| i as u8).collect();
+        assert_eq!(
+            qdb_put(
+                db,
+                key.as_ptr(),
+                key.len(),
+                val.as_ptr(),
+                val.len(),
+                &mut err
+            ),
+            0
+        );
+
+        let mut view: *mut QdbView = ptr::null_mut();
+        let (mut p, mut len): (*const u8, usize) = (ptr::null(), 0);
+        let rc = qdb_get_view(
+            db,
+            key.as_ptr(),
+            key.len(),
+            &mut view,
+            &mut p,
+            &mut len,
+            &mut err,
+        );
+        assert_eq!(rc, 0);
+        assert!(!view.is_null());
+        assert_eq!(std::slice::from_raw_parts(p, len), val.as_slice());
+        qdb_view_release(view);
+
+        // Отсутствующий ключ: view = NULL, release(NULL) — no-op
+        let rc = qdb_get_view(
+            db,
+            b"nope".as_ptr(),
+            4,
+            &mut view,
+            &mut p,
+            &mut len,
+            &mut err,
+        );
+        assert_eq!(rc, 0);
+        assert!(view.is_null() && p.is_null());
+        assert_eq!(len, 0);
+        qdb_view_release(view);
+
+        qdb_close(db);
+    }
+    let _ = fs::remove_dir_all(&root);
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
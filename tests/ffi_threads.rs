@@ -0,0 +1,136 @@
+#![cfg(feature = "ffi")]
+
+use std::ffi::CString;
+use std::fs;
+use std::path::PathBuf;
+use std::ptr;
+
+use QuiverDB::ffi::{
+    qdb_close, qdb_exists, qdb_get_into, qdb_init, qdb_open_writer, qdb_put, QdbDb,
+};
+
+/// Один хэндл из нескольких потоков (как Python-потоки через ctypes без GIL).
+#[test]
+fn ffi_handle_shared_between_threads() {
+    let root = unique_root("ffi-threads");
+    fs::create_dir_all(&root).unwrap();
+    let cpath = CString::new(root.to_str().unwrap()).unwrap();
+    let mut err: *mut libc::c_char = ptr::null_mut();
+
+    let mut db: *mut QdbDb = ptr::null_mut();
+    unsafe {
+        assert_eq!(qdb_init(cpath.as_ptr(), 4096, 32, &mut err), 0);
+        assert_eq!(qdb_open_writer(cpath.as_ptr(), &mut db, &mut err), 0);
+    }
+    // Сырой указатель не Send — передаём адрес
+    let addr = db as usize;
+
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            std::thread::spawn(move || {
+                let db = addr as *mut QdbDb;
+                let mut err: *mut libc::c_char = ptr::null_mut();
+                for i in 0..200 {
+                    let k = format!("t{}-k{}", t, i);
+                    let v = format!("v{}", i);
+                    let rc =
+                        unsafe { qdb_put(db, k.as_ptr(), k.len(), v.as_ptr(), v.len(), &mut err) };
+                    assert_eq!(rc, 0);
+                }
+            })
+        })
+        .collect();
+    for th in threads {
+        th.join().unwrap();
+    }
+
+    unsafe {
+        for t in 0..4 {
+            for i in 0..200 {
+                let k = format!("t{}-k{}", t, i);
+                let mut present = 0;
+                assert_eq!(
+                    qdb_exists(db, k.as_ptr(), k.len(), &mut present, &mut err),
+                    0
+                );
+                assert_eq!(present, 1, "{}", k);
+            }
+        }
+        qdb_close(db);
+    }
+    let _ = fs::remove_dir_all(&root);
+}
+
+/// Читатели (shared-блокировка) параллельно с писателем (exclusive): каждое чтение видит
+/// значение целиком — либо старое, либо новое.
+#[test]
+fn ffi_readers_run_alongside_writer() {
+    let root = unique_root("ffi-rw");
+    fs::create_dir_all(&root).unwrap();
+    let cpath = CString::new(root.to_str().unwrap()).unwrap();
+    let mut err: *mut libc::c_char = ptr::null_mut();
+
+    let mut db: *mut QdbDb = ptr::null_mut();
+    unsafe {
+        assert_eq!(qdb_init(cpath.as_ptr(), 4096, 32, &mut err), 0);
+        assert_eq!(qdb_open_writer(cpath.as_ptr(), &mut db, &mut err), 0);
+        let v = [b'a'; 64];
+        assert_eq!(
+            qdb_put(db, b"hot".as_ptr(), 3, v.as_ptr(), v.len(), &mut err),
+            0
+        );
+    }
+    let addr = db as usize;
+
+    let writer = std::thread::spawn(move || {
+        let db = addr as *mut QdbDb;
+        let mut err: *mut libc::c_char = ptr::null_mut();
+        for i in 0..200u32 {
+            let v = [b'a' + (i % 26) as u8; 64];
+            let rc = unsafe { qdb_put(db, b"hot".as_ptr(), 3, v.as_ptr(), v.len(), &mut err) };
+            assert_eq!(rc, 0);
+        }
+    });
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            std::thread::spawn(move || {
+                let db = addr as *mut QdbDb;
+                let mut err: *mut libc::c_char = ptr::null_mut();
+                let mut buf = [0u8; 64];
+                for _ in 0..500 {
+                    let (mut len, mut found) = (0usize, 0i32);
+                    let rc = unsafe {
+                        qdb_get_into(
+                            db,
+                            b"hot".as_ptr(),
+                            3,
+                            buf.as_mut_ptr(),
+                            buf.len(),
+                            &mut len,
+                            &mut found,
+                            &mut err,
+                        )
+                    };
+                    assert_eq!((rc, found, len), (0, 1, 64));
+                    assert!(buf.iter().all(|&b| b == buf[0]), "torn value");
+                }
+            })
+        })
+        .collect();
+    writer.join().unwrap();
+    for r in readers {
+        r.join().unwrap();
+    }
+
+    unsafe { qdb_close(db) };
+    let _ = fs::remove_dir_all(&root);
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
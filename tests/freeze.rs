@@ -0,0 +1,129 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::freeze::{freeze_marker_path, read_freeze_marker};
+use QuiverDB::db::{Db, DbFrozenError};
+use QuiverDB::metrics;
+
+fn fresh_db(root: &Path, cfg: QuiverConfig) -> Result<Db> {
+    fs::create_dir_all(root)?;
+    Db::init(root, 4096, 16)?;
+    Db::open_with_config(root, cfg)
+}
+
+/// «Снапшот тома»: побайтовая копия корня, пока writer жив.
+fn copy_tree(src: &Path, dst: &Path) -> Result<()> {
+    fs::create_dir_all(dst)?;
+    for e in fs::read_dir(src)? {
+        let e = e?;
+        let to = dst.join(e.file_name());
+        if e.file_type()?.is_dir() {
+            copy_tree(&e.path(), &to)?;
+        } else {
+            fs::copy(e.path(), to)?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn freeze_rejects_commits_until_thaw() -> Result<()> {
+    let root = unique_root("freeze-basic");
+    let mut db = fresh_db(&root, QuiverConfig::default())?;
+    db.put(b"a", b"1")?;
+    let rejected0 = metrics::snapshot().commits_rejected_frozen;
+
+    let info = db.freeze()?;
+    assert!(db.is_frozen());
+    assert_eq!(info.lsn, db.pager.meta.last_lsn);
+    assert!(info.segments_synced >= 1);
+    assert_eq!(read_freeze_marker(&root)?, Some(info.clone()));
+    // повторный freeze — та же точка
+    assert_eq!(db.freeze()?, info);
+
+    // коммиты отклоняются типизированной ошибкой, чтения работают
+    let err = db.put(b"b", b"2").unwrap_err();
+    let fe = err
+        .chain()
+        .find_map(|c| c.downcast_ref::<DbFrozenError>())
+        .expect("DbFrozenError");
+    assert_eq!(fe.lsn, info.lsn);
+    assert!(db.del(b"a").is_err());
+    assert!(db.set_dir_head(0, 0).is_err());
+    assert_eq!(db.get(b"a")?.as_deref(), Some(&b"1"[..]));
+    assert!(metrics::snapshot().commits_rejected_frozen >= rejected0 + 3);
+
+    assert!(db.thaw()?);
+    assert!(!db.thaw()?);
+    assert!(!freeze_marker_path(&root).exists());
+    db.put(b"b", b"2")?;
+    assert_eq!(db.get(b"b")?.as_deref(), Some(&b"2"[..]));
+    Ok(())
+}
+
+#[test]
+fn volume_copy_taken_while_frozen_is_consistent() -> Result<()> {
+    let root = unique_root("freeze-vol");
+    let snap = unique_root("freeze-vol-snap");
+    // без fsync данных и с отложенным WAL-fsync: freeze обязан сбросить всё сам
+    let cfg = QuiverConfig::default()
+        .with_data_fsync(false)
+        .with_wal_coalesce_ms(5);
+    let mut db = fresh_db(&root, cfg)?;
+    for i in 0..300u32 {
+        db.put(format!("k{:04}", i).as_bytes(), &[b'v'; 700])?;
+    }
+    db.del(b"k0007")?;
+
+    let info = db.freeze()?;
+    copy_tree(&root, &snap)?;
+    db.thaw()?;
+    db.put(b"after", b"thaw")?;
+
+    // копия помечена консистентной точкой и открывается без потерь на этом LSN
+    assert_eq!(read_freeze_marker(&snap)?.map(|m| m.lsn), Some(info.lsn));
+    {
+        let r = Db::open_ro(&snap)?;
+        assert_eq!(r.get(b"k0299")?.map(|v| v.len()), Some(700));
+        assert!(r.get(b"k0007")?.is_none());
+        assert!(r.get(b"after")?.is_none());
+    }
+    // writer на копии снимает устаревший маркер
+    let w = Db::open(&snap)?;
+    assert!(w.pager.meta.last_lsn >= info.lsn);
+    assert!(!freeze_marker_path(&snap).exists());
+    drop(w);
+
+    drop(db);
+    let r = Db::open_ro(&root)?;
+    assert_eq!(r.get(b"after")?.as_deref(), Some(&b"thaw"[..]));
+    Ok(())
+}
+
+#[test]
+fn freeze_is_writer_only_and_drop_clears_marker() -> Result<()> {
+    let root = unique_root("freeze-drop");
+    {
+        let mut db = fresh_db(&root, QuiverConfig::default())?;
+        db.put(b"x", b"1")?;
+        db.freeze()?;
+        assert!(freeze_marker_path(&root).exists());
+    }
+    assert!(!freeze_marker_path(&root).exists());
+
+    let mut ro = Db::open_ro(&root)?;
+    assert!(ro.freeze().is_err());
+    assert!(!ro.is_frozen());
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
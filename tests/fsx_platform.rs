@@ -0,0 +1,76 @@
+use anyhow::Result;
+use fs2::FileExt;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+use QuiverDB::util::fsx::{fsync_parent_dir, long_path, open_tmp_for_write, replace_file};
+
+#[test]
+fn replace_file_overwrites_target_even_while_it_is_open() -> Result<()> {
+    use std::io::Write;
+    let root = unique_root("fsx-replace");
+    fs::create_dir_all(&root)?;
+    let dst = root.join("state");
+    fs::write(&dst, b"old")?;
+    let _reader = fs::File::open(&dst)?; // чужой открытый хэндл на цели
+
+    let tmp = root.join("state.tmp");
+    let mut f = open_tmp_for_write(&tmp)?;
+    f.write_all(b"new-contents")?;
+    f.sync_all()?;
+    drop(f);
+    replace_file(&tmp, &dst)?;
+    fsync_parent_dir(&dst)?;
+
+    assert_eq!(fs::read(&dst)?, b"new-contents");
+    assert!(!tmp.exists());
+    Ok(())
+}
+
+#[test]
+fn db_works_under_a_path_longer_than_max_path() -> Result<()> {
+    let mut root = unique_root("fsx-long");
+    while root.as_os_str().len() < 300 {
+        root.push("nested-directory-segment");
+    }
+    let root = long_path(&root);
+    Db::init(&root, 4096, 8)?;
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"k", b"v")?;
+    }
+    let db = Db::open_ro(&root)?;
+    assert_eq!(db.get(b"k")?.as_deref(), Some(&b"v"[..]));
+    Ok(())
+}
+
+#[test]
+fn writer_lock_excludes_shared_lockers() -> Result<()> {
+    let root = unique_root("fsx-lock");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let lock = root.join("LOCK");
+    {
+        let _w = Db::open(&root)?;
+        let f = fs::OpenOptions::new().read(true).write(true).open(&lock)?;
+        assert!(
+            f.try_lock_shared().is_err(),
+            "writer holds LOCK exclusively"
+        );
+    }
+    let _r = Db::open_ro(&root)?;
+    let f = fs::OpenOptions::new().read(true).write(true).open(&lock)?;
+    f.try_lock_shared()?; // читатели совместимы
+    assert!(f.try_lock_exclusive().is_err());
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
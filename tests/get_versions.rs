@@ -0,0 +1,101 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::Db;
+
+#[test]
+fn get_versions_returns_chain_history_newest_first() -> Result<()> {
+    let root = unique_root("get-versions");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 4)?;
+    let mut db = Db::open(&root)?;
+
+    db.put(b"k", b"v1")?;
+    db.put(b"k", b"v2")?;
+    db.del(b"k")?;
+    let big = vec![b'z'; 20_000]; // OVERFLOW-значение раскрывается целиком
+    db.put(b"k", &big)?;
+    db.put(b"other", b"x")?;
+
+    let vs = db.get_versions(b"k", 10)?;
+    let values: Vec<Option<Vec<u8>>> = vs.iter().map(|v| v.value.clone()).collect();
+    assert_eq!(
+        values,
+        vec![
+            Some(big.clone()),
+            None,
+            Some(b"v2".to_vec()),
+            Some(b"v1".to_vec())
+        ]
+    );
+    assert!(vs[1].is_tombstone());
+    // LSN не возрастает от новых к старым
+    assert!(vs.windows(2).all(|w| w[0].lsn >= w[1].lsn));
+    assert!(vs[0].lsn > vs[3].lsn);
+
+    assert_eq!(db.get_versions(b"k", 2)?.len(), 2);
+    assert!(db.get_versions(b"k", 0)?.is_empty());
+    assert!(db.get_versions(b"missing", 5)?.is_empty());
+
+    // По умолчанию компактация оставляет только новейшую версию
+    db.compact_all()?;
+    let vs = db.get_versions(b"k", 10)?;
+    assert_eq!(vs.len(), 1);
+    assert_eq!(vs[0].value.as_deref(), Some(&big[..]));
+    Ok(())
+}
+
+#[test]
+fn compaction_keeps_configured_number_of_versions() -> Result<()> {
+    let root = unique_root("keep-versions");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 2)?;
+    let cfg = QuiverConfig::from_env().with_compaction_keep_versions(3);
+    let mut db = Db::open_with_config(&root, cfg)?;
+
+    for i in 0..5u32 {
+        db.put(b"a", format!("a{}", i).as_bytes())?;
+    }
+    db.put(b"b", b"b-old")?;
+    db.del(b"b")?;
+    db.put(b"b", b"b-new")?;
+    db.put(b"gone", b"g")?;
+    db.del(b"gone")?;
+
+    db.compact_all()?;
+
+    let a: Vec<Vec<u8>> = db
+        .get_versions(b"a", 10)?
+        .into_iter()
+        .filter_map(|v| v.value)
+        .collect();
+    assert_eq!(a, vec![b"a4".to_vec(), b"a3".to_vec(), b"a2".to_vec()]);
+    // История обрывается на tombstone
+    let b = db.get_versions(b"b", 10)?;
+    assert_eq!(b.len(), 1);
+    assert_eq!(b[0].value.as_deref(), Some(&b"b-new"[..]));
+    assert!(db.get_versions(b"gone", 10)?.is_empty());
+
+    // Чтение видит новейшую версию, повторная компактация историю не теряет
+    assert_eq!(db.get(b"a")?.as_deref(), Some(&b"a4"[..]));
+    db.compact_all()?;
+    assert_eq!(db.get_versions(b"a", 10)?.len(), 3);
+    assert_eq!(db.get(b"a")?.as_deref(), Some(&b"a4"[..]));
+    drop(db);
+
+    let db = Db::open_ro(&root)?;
+    assert_eq!(db.get(b"a")?.as_deref(), Some(&b"a4"[..]));
+    assert_eq!(db.get_versions(b"a", 10)?.len(), 3);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
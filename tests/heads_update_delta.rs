@@ -0,0 +1,168 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::Db;
+use QuiverDB::dir::{Directory, NO_PAGE};
+use QuiverDB::meta::FEATURE_HEADS_DELTA;
+use QuiverDB::metrics;
+use QuiverDB::wal::{
+    decode_heads_update_payload, encode_heads_update_payload, normalize_head_updates,
+    HEADS_DELTA_TAG,
+};
+
+// метрики heads_update_* — процесс‑глобальные
+static SERIAL: Mutex<()> = Mutex::new(());
+
+#[test]
+fn delta_payload_roundtrips_and_legacy_still_decodes() -> Result<()> {
+    let updates: Vec<(u32, u64)> = vec![
+        (900, 5_000),
+        (3, 4_990),
+        (17, NO_PAGE),
+        (3, 4_999), // дубль бакета — побеждает последняя запись
+        (65_000, 12),
+    ];
+    let norm = normalize_head_updates(&updates);
+    assert_eq!(
+        norm,
+        vec![(3, 4_999), (17, NO_PAGE), (900, 5_000), (65_000, 12)]
+    );
+
+    // Без opt-in — всегда legacy (12 B/запись)
+    let plain = encode_heads_update_payload(&updates, false);
+    assert_eq!(plain.len(), norm.len() * 12);
+    assert_eq!(decode_heads_update_payload(&plain), Some(norm.clone()));
+
+    let delta = encode_heads_update_payload(&updates, true);
+    assert_eq!(delta[0], HEADS_DELTA_TAG);
+    assert_ne!(delta.len() % 12, 0);
+    assert!(delta.len() < norm.len() * 12);
+    assert_eq!(decode_heads_update_payload(&delta), Some(norm.clone()));
+
+    // Legacy-формат 12 B/запись (старые WAL) по-прежнему разбирается
+    let mut legacy = Vec::new();
+    for &(b, pid) in norm.iter().rev() {
+        legacy.extend_from_slice(&b.to_le_bytes());
+        legacy.extend_from_slice(&pid.to_le_bytes());
+    }
+    assert_eq!(decode_heads_update_payload(&legacy), Some(norm));
+
+    // Мусор и обрезанный delta — отвергаются
+    assert_eq!(decode_heads_update_payload(&[]), None);
+    assert_eq!(decode_heads_update_payload(&delta[..delta.len() - 2]), None);
+    let mut bad_tag = delta.clone();
+    bad_tag[0] ^= 0xFF;
+    assert_eq!(decode_heads_update_payload(&bad_tag), None);
+    Ok(())
+}
+
+#[test]
+fn batches_write_compact_heads_and_replay_applies_them() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let src = unique_root("heads-delta-src");
+    let dst = unique_root("heads-delta-dst");
+    fs::create_dir_all(&src)?;
+    Db::init(&src, 4096, 256)?;
+
+    let before = metrics::snapshot();
+    {
+        let cfg = QuiverConfig::default().with_wal_heads_delta(true);
+        let mut db = Db::open_with_config(&src, cfg)?;
+        assert_ne!(db.pager.meta.flags & FEATURE_HEADS_DELTA, 0);
+        db.batch(|b| {
+            for i in 0..400u32 {
+                b.put(format!("k{:04}", i).as_bytes(), b"v")?;
+            }
+            Ok(())
+        })?;
+        // Повтор текущих голов не попадает в HEADS_UPDATE
+        let heads: Vec<(u32, u64)> = (0..256u32)
+            .map(|b| Ok((b, db.dir.head(b)?)))
+            .collect::<Result<_>>()?;
+        assert!(db.dir.coalesce_head_updates(&heads)?.is_empty());
+        let mut changed = heads.clone();
+        changed.push((7, 1_000_000));
+        assert_eq!(
+            db.dir.coalesce_head_updates(&changed)?,
+            vec![(7, 1_000_000)]
+        );
+
+        // Копия живого writer'а: WAL ещё содержит HEADS_UPDATE батча
+        copy_dir(&src, &dst)?;
+    }
+    let after = metrics::snapshot();
+    assert!(after.heads_update_frames > before.heads_update_frames);
+    assert!(after.heads_update_skipped >= before.heads_update_skipped + 257);
+    let written = after.heads_update_bytes - before.heads_update_bytes;
+    let legacy = after.heads_update_bytes_legacy - before.heads_update_bytes_legacy;
+    assert!(written > 0 && written < legacy, "{} vs {}", written, legacy);
+
+    // Обнулим каталог копии: головы должен восстановить реплей delta-кадра
+    fs::remove_file(dst.join("dir-000"))?;
+    Directory::create(&dst, 256)?;
+    let db = Db::open(&dst)?;
+    for i in 0..400u32 {
+        assert_eq!(
+            db.get(format!("k{:04}", i).as_bytes())?.as_deref(),
+            Some(&b"v"[..]),
+            "k{:04}",
+            i
+        );
+    }
+    Ok(())
+}
+
+/// Без opt-in writer пишет legacy HEADS_UPDATE и не проставляет бит heads_delta.
+#[test]
+fn heads_update_stays_legacy_by_default() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("heads-legacy");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 256)?;
+
+    let before = metrics::snapshot();
+    {
+        let mut db = Db::open_with_config(&root, QuiverConfig::default())?;
+        assert_eq!(db.pager.meta.flags & FEATURE_HEADS_DELTA, 0);
+        db.batch(|b| {
+            for i in 0..400u32 {
+                b.put(format!("k{:04}", i).as_bytes(), b"v")?;
+            }
+            Ok(())
+        })?;
+    }
+    let after = metrics::snapshot();
+    let written = after.heads_update_bytes - before.heads_update_bytes;
+    let legacy = after.heads_update_bytes_legacy - before.heads_update_bytes_legacy;
+    assert!(written > 0);
+    assert_eq!(written, legacy);
+
+    let db = Db::open_ro(&root)?;
+    assert_eq!(db.pager.meta.flags & FEATURE_HEADS_DELTA, 0);
+    assert_eq!(db.get(b"k0399")?.as_deref(), Some(&b"v"[..]));
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for e in fs::read_dir(from)? {
+        let e = e?;
+        let name = e.file_name();
+        if name == "LOCK" || !e.file_type()?.is_file() {
+            continue;
+        }
+        fs::copy(e.path(), to.join(name))?;
+    }
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,139 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::Db;
+use QuiverDB::metrics;
+
+// Метрики процесс-глобальные — тесты файла идут по очереди.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn open(root: &Path, entries: usize) -> Result<Db> {
+    Db::open_with_config(
+        root,
+        QuiverConfig::default().with_hot_get_cache_entries(entries),
+    )
+}
+
+#[test]
+fn repeated_gets_hit_and_writes_invalidate_by_bucket() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("hotget-hit");
+    fs::create_dir_all(&root)?;
+    // Один бакет: любая запись инвалидирует все локации
+    Db::init(&root, 4096, 1)?;
+    let mut db = open(&root, 64)?;
+    db.put(b"a", b"1")?;
+    db.put(b"b", b"2")?;
+    db.put(b"big", &vec![7u8; 9000])?;
+
+    let m0 = metrics::snapshot();
+    for _ in 0..10 {
+        assert_eq!(db.get(b"a")?.as_deref(), Some(&b"1"[..]));
+        assert_eq!(db.get(b"big")?, Some(vec![7u8; 9000]));
+    }
+    let m1 = metrics::snapshot();
+    assert_eq!(db.hot_get_cache_len(), 2);
+    assert!(
+        m1.hot_get_hits >= m0.hot_get_hits + 18,
+        "all but the first gets hit"
+    );
+
+    // Запись другого ключа того же бакета снимает локации; ответы — свежие
+    db.put(b"c", b"3")?;
+    assert_eq!(db.hot_get_cache_len(), 0);
+    assert!(metrics::snapshot().hot_get_invalidations >= m1.hot_get_invalidations + 2);
+    db.put(b"a", b"1-new")?;
+    assert_eq!(db.get(b"a")?.as_deref(), Some(&b"1-new"[..]));
+    assert_eq!(db.get(b"a")?.as_deref(), Some(&b"1-new"[..]));
+
+    // Tombstone кэшируется как отрицательный ответ
+    db.del(b"b")?;
+    assert_eq!(db.get(b"b")?, None);
+    let h = metrics::snapshot().hot_get_hits;
+    assert_eq!(db.get(b"b")?, None);
+    assert_eq!(metrics::snapshot().hot_get_hits, h + 1);
+
+    // Батч тоже инвалидирует
+    db.batch(|b| b.put(b"a", b"from-batch"))?;
+    assert_eq!(db.get(b"a")?.as_deref(), Some(&b"from-batch"[..]));
+    Ok(())
+}
+
+#[test]
+fn cache_stays_correct_across_compaction_and_page_reuse() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("hotget-compact");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 4)?;
+    let mut db = open(&root, 256)?;
+    let val = |i: usize, gen: usize| format!("v{}-{}", i, gen).into_bytes();
+    for gen in 0..3 {
+        for i in 0..40 {
+            db.put(format!("k{:02}", i).as_bytes(), &val(i, gen))?;
+        }
+    }
+    for i in 0..40 {
+        assert_eq!(db.get(format!("k{:02}", i).as_bytes())?, Some(val(i, 2)));
+    }
+    assert_eq!(db.hot_get_cache_len(), 40);
+
+    // Компактация переписывает цепочки, ремонт возвращает старые страницы в лист,
+    // новые записи их переиспользуют
+    db.vacuum_all()?;
+    db.alloc_check_fix()?;
+    for i in 0..40 {
+        db.put(format!("x{:02}", i).as_bytes(), b"filler")?;
+    }
+    for i in 0..40 {
+        assert_eq!(db.get(format!("k{:02}", i).as_bytes())?, Some(val(i, 2)));
+        assert_eq!(db.get(format!("k{:02}", i).as_bytes())?, Some(val(i, 2)));
+    }
+    Ok(())
+}
+
+#[test]
+fn disabled_by_default_and_bounded_by_capacity() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("hotget-cap");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    {
+        let mut db = Db::open(&root)?;
+        for i in 0..20 {
+            db.put(format!("k{:02}", i).as_bytes(), b"v")?;
+        }
+        let h = metrics::snapshot().hot_get_hits;
+        for _ in 0..3 {
+            db.get(b"k01")?;
+        }
+        assert_eq!(db.hot_get_cache_len(), 0);
+        assert_eq!(metrics::snapshot().hot_get_hits, h);
+    }
+
+    let db = open(&root, 4)?;
+    for i in 0..20 {
+        assert_eq!(
+            db.get(format!("k{:02}", i).as_bytes())?.as_deref(),
+            Some(&b"v"[..])
+        );
+    }
+    assert_eq!(db.hot_get_cache_len(), 4);
+    // Последние прочитанные — в кэше, давно не читанные вытеснены
+    let h = metrics::snapshot().hot_get_hits;
+    db.get(b"k19")?;
+    assert_eq!(metrics::snapshot().hot_get_hits, h + 1);
+    db.get(b"k00")?;
+    assert_eq!(metrics::snapshot().hot_get_hits, h + 1);
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,67 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::hotkeys::read_hot_prefixes;
+use QuiverDB::db::Db;
+
+#[test]
+fn hot_prefix_profiler_finds_skewed_prefix() -> Result<()> {
+    let root = unique_root("hot-prefixes");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+
+    // Выключен по умолчанию
+    {
+        let db = Db::open_ro(&root)?;
+        assert!(db.hot_prefixes(5).is_none());
+    }
+
+    let cfg = QuiverConfig::from_env()
+        .with_hot_prefix_sample(1)
+        .with_hot_prefix_len(5);
+    {
+        let mut db = Db::open_with_config(&root, cfg.clone())?;
+        for i in 0..20u32 {
+            db.put(format!("user:{}", i).as_bytes(), b"x")?;
+        }
+        db.put(b"cold:1", b"y")?;
+        for _ in 0..10 {
+            let _ = db.get(b"user:3")?;
+        }
+        let _ = db.exists(b"cold:1")?;
+
+        let top = db.hot_prefixes(2).unwrap();
+        assert_eq!(top[0].prefix, "user:");
+        assert_eq!((top[0].writes, top[0].reads), (20, 10));
+        assert_eq!(top[1].prefix, "cold:");
+        assert_eq!(top[1].total(), 2);
+    } // drop → .hot_prefixes.json
+
+    let rep = read_hot_prefixes(&root)?.expect("profile persisted by writer");
+    assert_eq!(rep.prefix_len, 5);
+    assert_eq!(rep.ops_seen, 32);
+    assert_eq!(rep.prefixes[0].prefix_hex, "757365723a");
+
+    // Выборка 1/4: оценки масштабируются
+    let mut db = Db::open_with_config(&root, cfg.with_hot_prefix_sample(4))?;
+    db.batch(|b| {
+        for i in 0..40u32 {
+            b.put(format!("bulk:{}", i).as_bytes(), b"z")?;
+        }
+        Ok(())
+    })?;
+    let top = db.hot_prefixes(1).unwrap();
+    assert_eq!((top[0].prefix.as_str(), top[0].writes), ("bulk:", 40));
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
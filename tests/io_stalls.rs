@@ -0,0 +1,59 @@
+use anyhow::Result;
+use std::fs;
+use std::time::Duration;
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::Db;
+use QuiverDB::metrics;
+use QuiverDB::util::iostall::{recent_io_stalls, set_io_stall_threshold, IoOp};
+
+/// Детектор медленного IO: с минимальным порогом каждая fsync WAL/запись сегмента фиксируется
+/// (кольцо событий, метрики); Db::last_io_stalls отдаёт только файлы своей БД.
+#[test]
+fn io_stalls_recorded_per_db() -> Result<()> {
+    let root = unique_root("io-stall");
+    let other = unique_root("io-stall-other");
+    fs::create_dir_all(&root)?;
+    fs::create_dir_all(&other)?;
+    Db::init(&root, 64 * 1024, 16)?;
+    Db::init(&other, 64 * 1024, 16)?;
+
+    let before = metrics::snapshot().io_stalls_total;
+    {
+        // open применяет порог из конфига — затем понижаем до 1 нс (любая операция «медленная»)
+        let mut db = Db::open_with_config(&root, QuiverConfig::default().with_io_stall_ms(0))?;
+        // порог 0 — детектор выключен
+        db.put(b"z", b"0")?;
+        assert!(db.last_io_stalls().is_empty());
+        set_io_stall_threshold(Duration::from_nanos(1));
+        db.put(b"a", b"1")?;
+        db.batch(|b| {
+            b.put(b"b", b"2")?;
+            b.put(b"c", b"3")
+        })?;
+
+        let ev = db.last_io_stalls();
+        assert!(ev.iter().any(|e| e.op == IoOp::WalFsync), "{:?}", ev);
+        assert!(ev.iter().any(|e| e.op == IoOp::SegWrite), "{:?}", ev);
+        assert!(ev.iter().all(|e| e.path.starts_with(&root) && !e.in_flight));
+        assert!(metrics::snapshot().io_stalls_total > before);
+        assert!(recent_io_stalls(None).len() >= ev.len());
+
+        // Чужая БД не видит события этой
+        set_io_stall_threshold(Duration::ZERO);
+    }
+    let db2 = Db::open_ro(&other)?;
+    assert!(db2.last_io_stalls().is_empty());
+    Ok(())
+}
+
+// ---------- helpers ----------
+
+fn unique_root(prefix: &str) -> std::path::PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
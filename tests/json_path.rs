@@ -0,0 +1,139 @@
+#![cfg(feature = "json")]
+
+use anyhow::Result;
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::{Db, JsonPath, JsonPathSeg};
+
+fn sample_db(prefix: &str) -> Result<(PathBuf, Db)> {
+    let root = unique_root(prefix);
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    let mut db = Db::open(&root)?;
+    let doc = json!({
+        "id": 7,
+        "user": { "name": "ann", "tags": ["a", "b", "c"] },
+        "a.b": true,
+        "blob": "x".repeat(20_000),
+    });
+    db.put(b"doc", doc.to_string().as_bytes())?;
+    db.put(b"raw", b"not json")?;
+    Ok((root, db))
+}
+
+#[test]
+fn json_path_projects_nested_fields() -> Result<()> {
+    let (_root, db) = sample_db("json-path")?;
+
+    assert_eq!(db.get_json_path(b"doc", "$.id")?, Some(json!(7)));
+    assert_eq!(db.get_json_path(b"doc", "user.name")?, Some(json!("ann")));
+    assert_eq!(
+        db.get_json_path(b"doc", "$.user.tags[2]")?,
+        Some(json!("c"))
+    );
+    assert_eq!(db.get_json_path(b"doc", "$['a.b']")?, Some(json!(true)));
+    assert_eq!(
+        db.get_json_path(b"doc", "$.user")?,
+        Some(json!({ "name": "ann", "tags": ["a", "b", "c"] }))
+    );
+    assert_eq!(db.get_json_path(b"doc", "$")?.unwrap()["id"], json!(7));
+
+    let p = JsonPath::parse("$.user[\"tags\"][0]")?;
+    assert_eq!(
+        p.segments(),
+        &[
+            JsonPathSeg::Field("user".into()),
+            JsonPathSeg::Field("tags".into()),
+            JsonPathSeg::Index(0)
+        ]
+    );
+    assert_eq!(p.to_string(), "$.user.tags[0]");
+    assert_eq!(db.get_json_path_with(b"doc", &p)?, Some(json!("a")));
+    Ok(())
+}
+
+#[test]
+fn json_path_missing_and_errors() -> Result<()> {
+    let (_root, db) = sample_db("json-path-miss")?;
+
+    assert_eq!(db.get_json_path(b"nope", "$.id")?, None);
+    assert_eq!(db.get_json_path(b"doc", "$.missing")?, None);
+    assert_eq!(db.get_json_path(b"doc", "$.user.tags[9]")?, None);
+    assert_eq!(db.get_json_path(b"doc", "$.id.deeper")?, None);
+    assert_eq!(db.get_json_path(b"doc", "$.user[0]")?, None);
+
+    let e = db.get_json_path(b"raw", "$.id").unwrap_err();
+    assert!(format!("{:#}", e).contains("not valid JSON"), "{:#}", e);
+    for bad in ["$.", "$.user[", "$.user[x]", "$user"] {
+        let e = db.get_json_path(b"doc", bad).unwrap_err();
+        assert!(
+            e.to_string().contains("invalid JSON path"),
+            "{}: {}",
+            bad,
+            e
+        );
+    }
+    Ok(())
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn ffi_get_json_path_returns_only_the_field() -> Result<()> {
+    use std::ffi::CString;
+    use std::ptr;
+    use QuiverDB::ffi::{qdb_buf_free, qdb_close, qdb_get_json_path, qdb_open_writer, QdbBuf};
+
+    let (root, db) = sample_db("json-path-ffi")?;
+    drop(db);
+    let cpath = CString::new(root.to_str().unwrap())?;
+    let mut err: *mut libc::c_char = ptr::null_mut();
+    unsafe {
+        let mut h = ptr::null_mut();
+        assert_eq!(qdb_open_writer(cpath.as_ptr(), &mut h, &mut err), 0);
+        let key = b"doc";
+        let path = CString::new("$.user.tags")?;
+        let mut buf = QdbBuf {
+            ptr: ptr::null_mut(),
+            len: 0,
+        };
+        let rc = qdb_get_json_path(
+            h,
+            key.as_ptr(),
+            key.len(),
+            path.as_ptr(),
+            &mut buf,
+            &mut err,
+        );
+        assert_eq!(rc, 0);
+        let text = std::slice::from_raw_parts(buf.ptr, buf.len).to_vec();
+        assert_eq!(text, br#"["a","b","c"]"#);
+        qdb_buf_free(buf);
+
+        let missing = CString::new("$.nope")?;
+        let mut buf = QdbBuf {
+            ptr: ptr::null_mut(),
+            len: 0,
+        };
+        let rc = qdb_get_json_path(
+            h,
+            key.as_ptr(),
+            key.len(),
+            missing.as_ptr(),
+            &mut buf,
+            &mut err,
+        );
+        assert_eq!((rc, buf.len), (0, 0));
+        qdb_close(h);
+    }
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
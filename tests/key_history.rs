@@ -0,0 +1,125 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::Db;
+use QuiverDB::wal::{key_history, HistoryOp, HISTORY_FILE};
+
+fn fresh(name: &str) -> Result<PathBuf> {
+    let root = unique_root(name);
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    Ok(root)
+}
+
+fn history_cfg() -> QuiverConfig {
+    QuiverConfig::from_env().with_history_index(true)
+}
+
+#[test]
+fn close_indexes_history_and_survives_wal_truncation() -> Result<()> {
+    let root = fresh("hist-close")?;
+    {
+        let mut db = Db::open_with_config(&root, history_cfg())?;
+        db.put(b"k", b"v1")?;
+        db.put(b"other", b"x")?;
+        db.del(b"k")?;
+        db.put(b"k", b"v2-longer")?;
+    } // Drop: индексация, затем усечение WAL
+
+    assert!(root.join(HISTORY_FILE).exists());
+    let h = key_history(&root, b"k")?;
+    let ops: Vec<HistoryOp> = h.iter().map(|e| e.op).collect();
+    assert_eq!(ops, vec![HistoryOp::Put, HistoryOp::Del, HistoryOp::Put]);
+    assert!(h.iter().all(|e| e.indexed));
+    assert!(h.windows(2).all(|w| w[0].lsn < w[1].lsn));
+    assert!(h.iter().all(|e| e.batch_lsn >= e.lsn));
+    assert_eq!(h[0].value_len, 2);
+    assert_eq!(h[1].value_len, 0);
+    assert_eq!(h[2].value_len, 9);
+
+    // Реоткрытие (реплей пустого WAL) историю не теряет; у другого ключа — своя
+    let db = Db::open_ro(&root)?;
+    assert_eq!(db.key_history(b"k")?, h);
+    assert_eq!(db.key_history(b"other")?.len(), 1);
+    assert!(db.key_history(b"missing")?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn live_wal_changes_visible_before_indexing() -> Result<()> {
+    let root = fresh("hist-live")?;
+    let mut db = Db::open(&root)?;
+    db.put(b"k", b"a")?;
+    db.put(b"k", b"b")?;
+
+    // history.bin ещё нет — записи читаются из текущего WAL
+    assert!(!root.join(HISTORY_FILE).exists());
+    let h = db.key_history(b"k")?;
+    assert_eq!(h.len(), 2);
+    assert!(h.iter().all(|e| !e.indexed && e.op == HistoryOp::Put));
+
+    let rep = db.index_history()?;
+    assert_eq!(rep.entries, 2);
+    assert_eq!(rep.batches, 2);
+    assert!(rep.indexed_lsn >= h[1].batch_lsn);
+
+    let after = db.key_history(b"k")?;
+    assert_eq!(after.len(), 2);
+    assert!(after.iter().all(|e| e.indexed));
+    assert_eq!(
+        after.iter().map(|e| e.lsn).collect::<Vec<_>>(),
+        h.iter().map(|e| e.lsn).collect::<Vec<_>>()
+    );
+    Ok(())
+}
+
+#[test]
+fn reindex_is_idempotent_and_compaction_shows_rewrite() -> Result<()> {
+    let root = fresh("hist-idem")?;
+    let mut db = Db::open_with_config(&root, history_cfg())?;
+    db.put(b"k", b"v1")?;
+    db.put(b"k", b"v2")?;
+
+    assert_eq!(db.index_history()?.entries, 2);
+    let again = db.index_history()?;
+    assert_eq!(again.entries, 0);
+    assert_eq!(again.batches, 0);
+    assert_eq!(db.key_history(b"k")?.len(), 2);
+
+    // Компактация переносит живую версию — это rewrite, а не новый put
+    db.compact_all()?;
+    let h = db.key_history(b"k")?;
+    let ops: Vec<HistoryOp> = h.iter().map(|e| e.op).collect();
+    assert_eq!(&ops[..2], &[HistoryOp::Put, HistoryOp::Put]);
+    assert!(ops[2..].iter().all(|op| *op == HistoryOp::Rewrite));
+    assert!(ops.len() > 2, "compaction copy is not recorded: {:?}", ops);
+    drop(db);
+
+    // Drop проиндексировал хвост; повторный проход по пустому WAL ничего не добавляет
+    let len = fs::metadata(root.join(HISTORY_FILE))?.len();
+    let mut db = Db::open_with_config(&root, history_cfg())?;
+    assert_eq!(db.index_history()?.entries, 0);
+    assert_eq!(
+        db.key_history(b"k")?,
+        h.iter()
+            .map(|e| {
+                let mut e = e.clone();
+                e.indexed = true;
+                e
+            })
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(fs::metadata(root.join(HISTORY_FILE))?.len(), len);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
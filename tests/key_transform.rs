@@ -0,0 +1,163 @@
+use anyhow::Result;
+use std::borrow::Cow;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::{
+    Db, HashLongKeys, KeyTransform, KeyTransformMismatchError, LowercaseKeys, StripNamespace,
+};
+use QuiverDB::meta::{read_meta, FEATURE_KEY_TRANSFORM};
+
+fn cfg_with(t: Arc<dyn KeyTransform>) -> QuiverConfig {
+    QuiverConfig::from_env().with_key_transform(t)
+}
+
+/// Пользовательская трансформация (не встроенная): срезает пробелы по краям.
+struct Trim;
+
+impl KeyTransform for Trim {
+    fn id(&self) -> String {
+        "test-trim".to_string()
+    }
+
+    fn canonical_key<'a>(&self, key: &'a [u8]) -> Cow<'a, [u8]> {
+        Cow::Borrowed(key.trim_ascii())
+    }
+}
+
+#[test]
+fn lowercase_transform_applies_to_every_entry_point() -> Result<()> {
+    let root = unique_root("ktr-lower");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    let mut db = Db::open_with_config(&root, cfg_with(Arc::new(LowercaseKeys)))?;
+    assert_eq!(db.key_transform_id().as_deref(), Some("lowercase"));
+
+    db.put(b"User:ALICE", b"1")?;
+    assert_eq!(db.get(b"user:alice")?, Some(b"1".to_vec()));
+    assert_eq!(db.get(b"USER:Alice")?, Some(b"1".to_vec()));
+    assert!(db.exists(b"USER:ALICE")?);
+    db.batch(|b| b.put(b"User:BOB", b"2"))?;
+    assert_eq!(
+        db.get_many(&[b"user:bob".as_slice(), b"USER:ALICE".as_slice()])?,
+        vec![Some(b"2".to_vec()), Some(b"1".to_vec())]
+    );
+
+    // Сканы: префикс каноникализуется, ключи — канонические
+    let mut keys = db.scan_keys(Some(b"USER:"))?;
+    keys.sort();
+    assert_eq!(keys, vec![b"user:alice".to_vec(), b"user:bob".to_vec()]);
+
+    assert!(db.del(b"USER:BOB")?);
+    assert_eq!(db.get(b"user:bob")?, None);
+    assert_eq!(db.scan_prefix(b"User:")?.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn recorded_transform_is_enforced_on_open() -> Result<()> {
+    let root = unique_root("ktr-mismatch");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    {
+        let mut db = Db::open_with_config(&root, cfg_with(Arc::new(LowercaseKeys)))?;
+        db.put(b"KEY", b"v")?;
+    }
+    let m = read_meta(&root)?;
+    assert_eq!(m.key_transform, "lowercase");
+    assert_ne!(m.flags & FEATURE_KEY_TRANSFORM, 0);
+
+    // Другая трансформация — типизированная ошибка
+    let e = match Db::open_with_config(&root, cfg_with(Arc::new(HashLongKeys::new(64)?))) {
+        Ok(_) => panic!("open with a mismatched transform must fail"),
+        Err(e) => e,
+    };
+    let mm = e.downcast_ref::<KeyTransformMismatchError>().unwrap();
+    assert_eq!(mm.recorded, "lowercase");
+    assert_eq!(mm.configured, "hash_long:64");
+
+    // Без явной регистрации встроенная трансформация берётся из meta
+    {
+        let db = Db::open(&root)?;
+        assert_eq!(db.get(b"key")?, Some(b"v".to_vec()));
+        assert_eq!(db.get(b"Key")?, Some(b"v".to_vec()));
+    }
+
+    // Непустая БД без трансформации её не принимает
+    let plain = unique_root("ktr-plain");
+    fs::create_dir_all(&plain)?;
+    Db::init(&plain, 4096, 16)?;
+    {
+        let mut db = Db::open(&plain)?;
+        db.put(b"k", b"v")?;
+    }
+    let e = match Db::open_with_config(&plain, cfg_with(Arc::new(LowercaseKeys))) {
+        Ok(_) => panic!("a non-empty DB must not adopt a transform"),
+        Err(e) => e,
+    };
+    assert!(e.downcast_ref::<KeyTransformMismatchError>().is_some());
+
+    // Пользовательская трансформация: без неё БД не открыть
+    let custom = unique_root("ktr-custom");
+    fs::create_dir_all(&custom)?;
+    Db::init(&custom, 4096, 16)?;
+    {
+        let mut db = Db::open_with_config(&custom, cfg_with(Arc::new(Trim)))?;
+        db.put(b"  padded  ", b"v")?;
+        assert_eq!(db.get(b"padded")?, Some(b"v".to_vec()));
+    }
+    let e = match Db::open(&custom) {
+        Ok(_) => panic!("a custom transform must be registered to open"),
+        Err(e) => e,
+    };
+    assert_eq!(
+        e.downcast_ref::<KeyTransformMismatchError>()
+            .unwrap()
+            .configured,
+        ""
+    );
+    Ok(())
+}
+
+#[test]
+fn hash_long_and_strip_namespace_transforms() -> Result<()> {
+    let root = unique_root("ktr-hash");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    {
+        let mut db = Db::open_with_config(&root, cfg_with(Arc::new(HashLongKeys::new(64)?)))?;
+        // Ключ длиннее u16::MAX хранится в усечённой форме
+        let long: Vec<u8> = (0..100_000).map(|i| b'a' + (i % 26) as u8).collect();
+        db.put(&long, b"big-key")?;
+        db.put(b"short", b"s")?;
+        assert_eq!(db.get(&long)?, Some(b"big-key".to_vec()));
+        assert_eq!(db.canonical_key(&long).len(), 64);
+        assert_eq!(db.scan_prefix(b"abcdef")?.len(), 1);
+        // Префикс длиннее нехэшированной части не сопоставить
+        assert!(db.scan_prefix(&long[..60]).is_err());
+    }
+
+    let ns = unique_root("ktr-ns");
+    fs::create_dir_all(&ns)?;
+    Db::init(&ns, 4096, 16)?;
+    let mut db = Db::open_with_config(&ns, cfg_with(Arc::new(StripNamespace::new(b"app/")?)))?;
+    db.put(b"app/user:1", b"a")?;
+    db.put(b"user:2", b"b")?;
+    assert_eq!(db.get(b"user:1")?, Some(b"a".to_vec()));
+    assert_eq!(db.get(b"app/app/user:2")?, Some(b"b".to_vec()));
+    let mut keys = db.scan_keys(Some(b"app/user:"))?;
+    keys.sort();
+    assert_eq!(keys, vec![b"user:1".to_vec(), b"user:2".to_vec()]);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,190 @@
+#![cfg(feature = "fuse")]
+
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+use QuiverDB::kvfs::{escape_component, KvFsOptions, KvNodeKind, KvView, KVFS_ROOT_INO};
+
+fn names(view: &KvView, path: &str) -> Vec<String> {
+    let ino = view.resolve(path).expect("path exists");
+    view.readdir(ino)
+        .expect("is a directory")
+        .into_iter()
+        .map(|e| String::from_utf8(e.name).unwrap())
+        .collect()
+}
+
+fn read(view: &KvView, path: &str) -> Result<Vec<u8>> {
+    let ino = view.resolve(path).expect("path exists");
+    Ok(view.value(ino)?.expect("is a live file").to_vec())
+}
+
+#[test]
+fn keys_become_files_under_prefix_directories() -> Result<()> {
+    let root = unique_root("kvfs-tree");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"users/1/name", b"ann")?;
+        db.put(b"users/1/email", b"ann@example.com")?;
+        db.put(b"users/2/name", b"bob")?;
+        db.put(b"users", b"directory and key")?;
+        db.put(b"odd key", b"spaces")?;
+        db.put(b"big", &vec![9u8; 20_000])?;
+        db.put(b"gone", b"x")?;
+        db.del(b"gone")?;
+    }
+
+    let view = KvView::open(&root, &KvFsOptions::default())?;
+    assert_eq!(
+        names(&view, ""),
+        vec!["big", "odd%20key", "users", "users%"]
+    );
+    assert_eq!(names(&view, "users"), vec!["1", "2"]);
+    assert_eq!(names(&view, "users/1"), vec!["email", "name"]);
+    assert_eq!(read(&view, "users/1/email")?, b"ann@example.com");
+    assert_eq!(read(&view, "users%")?, b"directory and key");
+    assert_eq!(read(&view, "big")?, vec![9u8; 20_000]);
+    assert!(view.resolve("gone").is_none());
+
+    let big = view.resolve("big").unwrap();
+    let attr = view.attr(big)?.unwrap();
+    assert_eq!((attr.kind, attr.size), (KvNodeKind::File, 20_000));
+    assert_eq!(view.read(big, 19_990, 4096)?, vec![9u8; 10]);
+    assert_eq!(view.key_of(big), Some(&b"big"[..]));
+    let users = view.attr(view.resolve("users").unwrap())?.unwrap();
+    assert_eq!((users.kind, users.nlink), (KvNodeKind::Dir, 4));
+    assert_eq!(view.lookup(KVFS_ROOT_INO, b".."), Some(KVFS_ROOT_INO));
+
+    let st = view.stats();
+    assert_eq!((st.files, st.dirs, st.skipped), (6, 4, 0));
+
+    // Префикс срезается с путей, разделитель настраивается
+    let opts = KvFsOptions {
+        delimiter: b'/',
+        prefix: Some(b"users/1/".to_vec()),
+    };
+    let view = KvView::open(&root, &opts)?;
+    assert_eq!(names(&view, ""), vec!["email", "name"]);
+    let opts = KvFsOptions {
+        delimiter: b' ',
+        prefix: None,
+    };
+    let view = KvView::open(&root, &opts)?;
+    assert_eq!(read(&view, "odd/key")?, b"spaces");
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn view_keeps_mount_time_values_while_writer_continues() -> Result<()> {
+    let root = unique_root("kvfs-snap");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 2)?;
+    let mut db = Db::open(&root)?;
+    for i in 0..50 {
+        db.put(format!("k/{:02}", i).as_bytes(), &vec![i as u8; 3000])?;
+    }
+
+    let view = KvView::open(&root, &KvFsOptions::default())?;
+    assert_eq!(view.stats().files, 50);
+
+    // Перезапись, удаление и vacuum после снятия снимка
+    for i in 0..50 {
+        if i % 2 == 0 {
+            db.put(format!("k/{:02}", i).as_bytes(), b"new")?;
+        } else {
+            db.del(format!("k/{:02}", i).as_bytes())?;
+        }
+    }
+    db.put(b"k/new", b"after")?;
+    db.vacuum_all()?;
+
+    assert!(view.resolve("k/new").is_none());
+    for i in 0..50 {
+        assert_eq!(read(&view, &format!("k/{:02}", i))?, vec![i as u8; 3000]);
+    }
+    assert_eq!(db.get(b"k/00")?.as_deref(), Some(&b"new"[..]));
+    drop(view);
+    assert_eq!(fs::read_dir(root.join(".snapshot_pins"))?.count(), 0);
+
+    drop(db);
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn names_are_escaped_unambiguously() {
+    assert_eq!(escape_component(b"plain-name_1.txt"), b"plain-name_1.txt");
+    assert_eq!(escape_component(b""), b"%");
+    assert_eq!(escape_component(b"."), b"%2E");
+    assert_eq!(escape_component(b".."), b"%2E%2E");
+    assert_eq!(escape_component(b"50%"), b"50%25");
+    assert_eq!(escape_component(b"a\0b\n"), b"a%00b%0A");
+    assert_eq!(escape_component("ключ".as_bytes()), "ключ".as_bytes());
+    assert_eq!(escape_component(&[0xff, b'x']), b"%FFx");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn fuse_mount_serves_snapshot_files() -> Result<()> {
+    use QuiverDB::kvfs::fuse::{unmount, KvMount};
+
+    let root = unique_root("kvfs-fuse");
+    let mnt = unique_root("kvfs-mnt");
+    fs::create_dir_all(&root)?;
+    fs::create_dir_all(&mnt)?;
+    Db::init(&root, 4096, 4)?;
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"a/b/c", b"deep")?;
+        db.put(b"top", b"level")?;
+    }
+    let view = KvView::open(&root, &KvFsOptions::default())?;
+    let mount = match KvMount::mount(view, &mnt) {
+        Ok(m) => m,
+        Err(e) => {
+            // нужен root и /dev/fuse
+            eprintln!("skip: {:#}", e);
+            let _ = fs::remove_dir_all(&root);
+            let _ = fs::remove_dir_all(&mnt);
+            return Ok(());
+        }
+    };
+    let server = std::thread::spawn(move || mount.serve());
+
+    let res = (|| -> Result<()> {
+        assert_eq!(fs::read(mnt.join("a/b/c"))?, b"deep");
+        assert_eq!(fs::read_to_string(mnt.join("top"))?, "level");
+        let mut top: Vec<String> = fs::read_dir(&mnt)?
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        top.sort();
+        assert_eq!(top, vec!["a", "top"]);
+        assert!(fs::metadata(mnt.join("a/b"))?.is_dir());
+        assert_eq!(fs::metadata(mnt.join("top"))?.len(), 5);
+        assert!(fs::write(mnt.join("top"), b"x").is_err());
+        assert!(fs::write(mnt.join("new"), b"x").is_err());
+        Ok(())
+    })();
+    unmount(&mnt)?;
+    server.join().expect("serve thread")?;
+    res?;
+
+    let _ = fs::remove_dir_all(&root);
+    let _ = fs::remove_dir_all(&mnt);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
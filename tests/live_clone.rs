@@ -0,0 +1,109 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use QuiverDB::db::clone::{clone_live, CloneOptions};
+use QuiverDB::db::Db;
+
+#[test]
+fn live_clone_under_concurrent_writer_is_consistent_prefix() -> Result<()> {
+    let src = unique_root("clone-src");
+    let dst = unique_root("clone-dst");
+    fs::create_dir_all(&src)?;
+    Db::init(&src, 4096, 32)?;
+    {
+        let mut db = Db::open(&src)?;
+        for i in 0..200u32 {
+            db.put(format!("pre-{i}").as_bytes(), &[7u8; 600])?;
+        }
+    }
+
+    // Writer работает всё время клонирования (включая ротации WAL: батчи по ~1 MiB)
+    let stop = Arc::new(AtomicBool::new(false));
+    let written = Arc::new(AtomicU64::new(0));
+    let (stop2, written2, src2) = (stop.clone(), written.clone(), src.clone());
+    let writer = std::thread::spawn(move || -> Result<()> {
+        let mut db = Db::open(&src2)?;
+        let mut i = 0u64;
+        while !stop2.load(Ordering::Relaxed) && i < 400 {
+            db.batch(|b| {
+                for j in 0..32u64 {
+                    let n = i * 32 + j;
+                    b.put(format!("live-{n}").as_bytes(), &value_for(n))?;
+                }
+                Ok(())
+            })?;
+            i += 1;
+            written2.store(i * 32, Ordering::Relaxed);
+        }
+        Ok(())
+    });
+    while written.load(Ordering::Relaxed) < 32 * 20 {
+        std::thread::yield_now();
+    }
+
+    let opts = CloneOptions {
+        pause_wait_ms: 0,
+        ..Default::default()
+    };
+    let rep = clone_live(&src, &dst, &opts)?;
+    stop.store(true, Ordering::Relaxed);
+    writer.join().unwrap()?;
+    assert!(!rep.writer_paused, "writer held the lock during the clone");
+    assert!(rep.wal_frames_captured > 0);
+
+    // Клон: все предварительные ключи + непрерывный префикс записей writer'а
+    let db = Db::open_ro(&dst)?;
+    for i in 0..200u32 {
+        assert!(db.get(format!("pre-{i}").as_bytes())?.is_some());
+    }
+    let total = written.load(Ordering::Relaxed);
+    let mut present = 0u64;
+    for n in 0..total {
+        if let Some(v) = db.get(format!("live-{n}").as_bytes())? {
+            assert_eq!(
+                present, n,
+                "clone is not a prefix: key {} present after a gap",
+                n
+            );
+            assert_eq!(v, value_for(n));
+            present += 1;
+        }
+    }
+    assert!(
+        present >= 32 * 20,
+        "clone misses writes committed before it started"
+    );
+    assert!(
+        present.is_multiple_of(32),
+        "clone split a batch: {} keys",
+        present
+    );
+    drop(db);
+
+    // Клон — самостоятельная БД: writer открывается и пишет
+    let mut c = Db::open(&dst)?;
+    c.put(b"after-clone", b"ok")?;
+    assert_eq!(c.get(b"after-clone")?.as_deref(), Some(&b"ok"[..]));
+
+    // Непустой dst отвергается
+    assert!(clone_live(&src, &dst, &opts).is_err());
+    Ok(())
+}
+
+fn value_for(n: u64) -> Vec<u8> {
+    let mut v = format!("v-{n}-").into_bytes();
+    v.resize(1000, (n % 251) as u8);
+    v
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,45 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::{Db, DbLockedError};
+
+#[test]
+fn lock_nowait_returns_db_locked_error() -> Result<()> {
+    // ENV действует на весь тестовый бинарь — в этом файле один тест
+    std::env::set_var("P1_LOCK_NOWAIT", "1");
+
+    let root = unique_root("lock-nowait");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 64 * 1024, 16)?;
+
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"k", b"v")?;
+
+        // второй writer: LOCK занят — сразу ошибка вместо ожидания
+        let err = Db::open(&root).err().expect("second writer must fail");
+        let locked = err.downcast_ref::<DbLockedError>().expect("DbLockedError");
+        assert!(locked.exclusive);
+        assert!(locked.path.ends_with("LOCK"));
+
+        // RO-хэндл тоже не ждёт писателя
+        let err = Db::open_ro(&root).err().expect("ro open must fail");
+        let locked = err.downcast_ref::<DbLockedError>().expect("DbLockedError");
+        assert!(!locked.exclusive);
+    }
+
+    // после закрытия writer'а LOCK свободен
+    let db = Db::open_ro(&root)?;
+    assert_eq!(db.get(b"k")?.as_deref(), Some(&b"v"[..]));
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,181 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::config::DbBuilder;
+use QuiverDB::db::maint_sched::{read_maint_audit, scheduled_snapshots};
+use QuiverDB::db::{Db, MaintSchedule, MaintScheduler};
+use QuiverDB::metrics;
+use QuiverDB::snapstore::{list_manifests, read_manifest, restore_from_id, SnapshotManager};
+
+const MIN: u64 = 60_000;
+
+#[test]
+fn scheduled_snapshots_follow_interval_and_retention() -> Result<()> {
+    let root = unique_root("maint-snap");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    let mut db = Db::open(&root)?;
+
+    // ручной снапшот retention не трогает
+    db.put(b"k0", b"v0")?;
+    let manual = SnapshotManager::create_persisted(&db, Some("manual"), &[], None)?;
+
+    let sched = MaintSchedule {
+        snapshot_interval_secs: 60,
+        snapshot_retention: 2,
+        ..Default::default()
+    };
+    let mut sch = MaintScheduler::new(&root, sched)?;
+    let created0 = metrics::snapshot().maint_snapshots_created;
+
+    let t0 = 1_000 * MIN;
+    let mut ids = Vec::new();
+    for i in 1..=4u32 {
+        db.put(format!("k{}", i).as_bytes(), b"v")?;
+        let now = t0 + (i as u64 - 1) * MIN;
+        let rep = sch.tick_at(&mut db, now)?;
+        assert!(rep.errors.is_empty(), "{:?}", rep.errors);
+        ids.push(rep.snapshot_created.expect("snapshot is due"));
+        // до истечения интервала — ничего
+        let idle = sch.tick_at(&mut db, now + MIN / 2)?;
+        assert!(idle.snapshot_created.is_none() && idle.snapshots_pruned.is_empty());
+        assert_eq!(
+            sch.next_due_in(now + MIN / 2).map(|d| d.as_secs()),
+            Some(30)
+        );
+    }
+    assert!(metrics::snapshot().maint_snapshots_created >= created0 + 4);
+
+    // retention=2: остались два новейших плановых + ручной
+    let kept: Vec<String> = scheduled_snapshots(&root)?
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    assert_eq!(kept, ids[2..].to_vec());
+    let mut all = list_manifests(&root)?;
+    all.sort();
+    let mut want = vec![manual.clone(), ids[2].clone(), ids[3].clone()];
+    want.sort();
+    assert_eq!(all, want);
+
+    // цепочка parent и метка
+    let m = read_manifest(&root, &ids[3])?;
+    assert_eq!(m.meta.parent.as_deref(), Some(ids[2].as_str()));
+    assert!(m.meta.labels.iter().any(|l| l == "scheduled"));
+
+    // audit log: 4 снапшота и 2 prune
+    let audit = read_maint_audit(&root)?;
+    let snaps: Vec<_> = audit.iter().filter(|e| e.task == "snapshot").collect();
+    assert_eq!(snaps.len(), 4);
+    assert!(snaps.iter().all(|e| e.ok));
+    let pruned: Vec<Option<String>> = audit
+        .iter()
+        .filter(|e| e.task == "snapshot_prune")
+        .map(|e| e.snapshot_id.clone())
+        .collect();
+    assert_eq!(pruned, vec![Some(ids[0].clone()), Some(ids[1].clone())]);
+
+    // последний плановый снапшот восстанавливается
+    let dst = unique_root("maint-snap-dst");
+    restore_from_id(&root, &dst, &ids[3], true)?;
+    let r = Db::open_ro(&dst)?;
+    for i in 0..=4u32 {
+        assert!(r.get(format!("k{}", i).as_bytes())?.is_some());
+    }
+    Ok(())
+}
+
+#[test]
+fn restarted_scheduler_resumes_from_last_scheduled_snapshot() -> Result<()> {
+    let root = unique_root("maint-restart");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let mut db = Db::open(&root)?;
+    db.put(b"a", b"1")?;
+
+    let cfg = DbBuilder::from_default()
+        .snapshot_interval_secs(3600)
+        .snapshot_retention(5)
+        .build();
+    let sched = MaintSchedule::from_config(&cfg);
+    assert_eq!(sched.snapshot_interval_secs, 3600);
+    assert_eq!(sched.snapshot_retention, 5);
+
+    let first = MaintScheduler::new(&root, sched.clone())?
+        .tick(&mut db)?
+        .snapshot_created
+        .expect("first tick takes a snapshot");
+
+    // новый планировщик видит снапшот в манифестах: следующий — через час
+    let mut sch = MaintScheduler::new(&root, sched)?;
+    let rep = sch.tick(&mut db)?;
+    assert!(rep.snapshot_created.is_none());
+    let wait = sch.next_due_in(now_ms()).expect("snapshot is scheduled");
+    assert!(wait.as_secs() > 3500, "{:?}", wait);
+
+    // parent следующего — снапшот прошлого запуска
+    let rep = sch.tick_at(&mut db, now_ms() + 3600 * 1000)?;
+    let next = rep.snapshot_created.expect("due after an hour");
+    assert_eq!(
+        read_manifest(&root, &next)?.meta.parent.as_deref(),
+        Some(first.as_str())
+    );
+    Ok(())
+}
+
+#[test]
+fn auto_maintenance_runs_on_its_own_interval() -> Result<()> {
+    let root = unique_root("maint-auto");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let mut db = Db::open(&root)?;
+    for i in 0..40u32 {
+        db.put(
+            format!("k{:02}", i % 10).as_bytes(),
+            format!("v{}", i).as_bytes(),
+        )?;
+    }
+
+    let sched = MaintSchedule {
+        maint_interval_secs: 10,
+        max_buckets: 8,
+        ..Default::default()
+    };
+    let mut sch = MaintScheduler::new(&root, sched)?;
+    let t0 = 5_000 * MIN;
+    let rep = sch.tick_at(&mut db, t0)?;
+    assert!(rep.auto_maint.is_some());
+    assert!(rep.snapshot_created.is_none());
+    assert!(sch.tick_at(&mut db, t0 + 5_000)?.auto_maint.is_none());
+    assert!(sch.tick_at(&mut db, t0 + 10_000)?.auto_maint.is_some());
+    for i in 0..10u32 {
+        assert!(db.get(format!("k{:02}", i).as_bytes())?.is_some());
+    }
+
+    let audit = read_maint_audit(&root)?;
+    assert_eq!(audit.iter().filter(|e| e.task == "auto_maint").count(), 2);
+    assert!(list_manifests(&root)?.is_empty());
+
+    // read-only хэндл планировщику не подходит
+    drop(db);
+    let mut ro = Db::open_ro(&root)?;
+    assert!(sch.tick_at(&mut ro, t0 + 20_000).is_err());
+    Ok(())
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,164 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use QuiverDB::backup::RestoreOptions;
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::crypto::{KeyProvider, StaticKeyProvider};
+use QuiverDB::db::Db;
+use QuiverDB::snapstore::{
+    manifest_public_key, read_manifest, restore_from_id, restore_from_id_with,
+    verify_manifest_signature, write_manifest, ManifestSignatureError, SignatureStatus,
+    SnapshotManager,
+};
+
+fn sig_error(e: &anyhow::Error) -> Option<&ManifestSignatureError> {
+    e.chain()
+        .find_map(|c| c.downcast_ref::<ManifestSignatureError>())
+}
+
+/// БД с парой ключей и снапшот; kp — подписать ключом провайдера (KID "snap").
+fn make_snapshot(prefix: &str, kp: Option<Arc<dyn KeyProvider>>) -> Result<(PathBuf, String)> {
+    let root = unique_root(prefix);
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"alpha", b"1")?;
+        db.put(b"beta", b"2")?;
+    }
+    let mut cfg = QuiverConfig::from_env();
+    if let Some(kp) = kp {
+        cfg = cfg
+            .with_tde_key_provider(kp)
+            .with_snapshot_sign_kid(Some("snap"));
+    }
+    let db = Db::open_ro_with_config(&root, cfg)?;
+    let id = SnapshotManager::create_persisted(&db, Some("signed"), &[], None)?;
+    Ok((root, id))
+}
+
+#[test]
+fn signed_manifest_verifies_and_tampering_is_refused() -> Result<()> {
+    let kp: Arc<dyn KeyProvider> = Arc::new(StaticKeyProvider::new("snap", [0x11u8; 32]));
+    let (root, id) = make_snapshot("sign-ok", Some(kp.clone()))?;
+
+    let mut m = read_manifest(&root, &id)?;
+    let sig = m.signature.clone().expect("manifest is signed");
+    assert_eq!(sig.alg, "ed25519");
+    assert_eq!(sig.kid, "snap");
+    assert_eq!(
+        sig.public_key_hex,
+        manifest_public_key(kp.as_ref(), "snap")?
+    );
+    assert_eq!(
+        verify_manifest_signature(&m, Some(kp.as_ref())),
+        SignatureStatus::Valid
+    );
+    // Без провайдера ключ неизвестен, но подпись верна
+    assert_eq!(
+        verify_manifest_signature(&m, None),
+        SignatureStatus::Untrusted
+    );
+    // Чужой ключ того же KID не делает подпись доверенной
+    let other = StaticKeyProvider::new("snap", [0x22u8; 32]);
+    assert_eq!(
+        verify_manifest_signature(&m, Some(&other)),
+        SignatureStatus::Untrusted
+    );
+
+    // Подмена head любого бакета ломает подпись
+    m.heads[0].head_pid = m.heads[0].head_pid.wrapping_add(1);
+    assert!(matches!(
+        verify_manifest_signature(&m, Some(kp.as_ref())),
+        SignatureStatus::Invalid(_)
+    ));
+    write_manifest(&root, &m)?;
+
+    // Restore отвергает изменённый манифест даже без --require-signature, dst не тронута
+    let dst = unique_root("sign-ok-dst");
+    let err = restore_from_id(&root, &dst, &id, true).unwrap_err();
+    let e = sig_error(&err).expect("typed signature error");
+    assert_eq!(e.snapshot_id, id);
+    assert!(matches!(e.status, SignatureStatus::Invalid(_)));
+    assert!(!dst.join("meta").exists());
+
+    let _ = fs::remove_dir_all(&root);
+    let _ = fs::remove_dir_all(&dst);
+    Ok(())
+}
+
+#[test]
+fn unsigned_snapshot_restores_unless_signature_required() -> Result<()> {
+    let (root, id) = make_snapshot("sign-none", None)?;
+    let m = read_manifest(&root, &id)?;
+    assert!(m.signature.is_none());
+    assert_eq!(
+        verify_manifest_signature(&m, None),
+        SignatureStatus::Unsigned
+    );
+
+    let required = RestoreOptions {
+        verify: true,
+        require_signature: true,
+        ..Default::default()
+    };
+    let dst = unique_root("sign-none-dst");
+    let err = restore_from_id_with(&root, &dst, &id, &required).unwrap_err();
+    assert_eq!(
+        sig_error(&err).expect("typed signature error").status,
+        SignatureStatus::Unsigned
+    );
+
+    restore_from_id(&root, &dst, &id, true)?;
+    let db = Db::open_ro(&dst)?;
+    assert_eq!(db.get(b"alpha")?.as_deref(), Some(&b"1"[..]));
+
+    let _ = fs::remove_dir_all(&root);
+    let _ = fs::remove_dir_all(&dst);
+    Ok(())
+}
+
+#[test]
+fn required_signature_accepts_key_listed_as_trusted() -> Result<()> {
+    let kp: Arc<dyn KeyProvider> = Arc::new(StaticKeyProvider::new("snap", [0x33u8; 32]));
+    let (root, id) = make_snapshot("sign-trust", Some(kp.clone()))?;
+    let required = RestoreOptions {
+        verify: true,
+        require_signature: true,
+        ..Default::default()
+    };
+
+    // Ключа подписи нет в окружении restore — подпись верна, но не доверенная
+    let dst = unique_root("sign-trust-dst");
+    let err = restore_from_id_with(&root, &dst, &id, &required).unwrap_err();
+    assert_eq!(
+        sig_error(&err).expect("typed signature error").status,
+        SignatureStatus::Untrusted
+    );
+
+    // Публичный ключ в P1_SNAPSHOT_TRUSTED_KEYS (ключ уникален для этого теста)
+    std::env::set_var(
+        "P1_SNAPSHOT_TRUSTED_KEYS",
+        manifest_public_key(kp.as_ref(), "snap")?,
+    );
+    restore_from_id_with(&root, &dst, &id, &required)?;
+    std::env::remove_var("P1_SNAPSHOT_TRUSTED_KEYS");
+
+    let db = Db::open_ro(&dst)?;
+    assert_eq!(db.get(b"beta")?.as_deref(), Some(&b"2"[..]));
+
+    let _ = fs::remove_dir_all(&root);
+    let _ = fs::remove_dir_all(&dst);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,46 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use QuiverDB::metrics::{record_bloom_negative, record_cache_hit, record_keydir_miss, snapshot};
+
+/// Шардированные счётчики: параллельные инкременты из многих потоков не теряются,
+/// а snapshot() во время записи видит монотонно растущие значения.
+#[test]
+fn sharded_counters_sum_across_threads() {
+    const THREADS: u64 = 8;
+    const OPS: u64 = 20_000;
+
+    let before = snapshot();
+    let done = AtomicBool::new(false);
+    std::thread::scope(|s| {
+        let workers: Vec<_> = (0..THREADS)
+            .map(|_| {
+                s.spawn(|| {
+                    for _ in 0..OPS {
+                        record_cache_hit();
+                        record_keydir_miss();
+                        record_bloom_negative();
+                    }
+                })
+            })
+            .collect();
+        s.spawn(|| {
+            let mut prev = 0u64;
+            while !done.load(Ordering::Relaxed) {
+                let cur = snapshot().page_cache_hits;
+                assert!(cur >= prev, "snapshot went backwards: {} < {}", cur, prev);
+                prev = cur;
+            }
+        });
+        for w in workers {
+            w.join().unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+
+    let after = snapshot();
+    let total = THREADS * OPS;
+    assert_eq!(after.page_cache_hits - before.page_cache_hits, total);
+    assert_eq!(after.keydir_misses - before.keydir_misses, total);
+    assert_eq!(after.bloom_negative - before.bloom_negative, total);
+    assert_eq!(after.bloom_tests - before.bloom_tests, total);
+}
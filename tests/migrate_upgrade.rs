@@ -0,0 +1,132 @@
+use anyhow::Result;
+use std::fs;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use QuiverDB::db::Db;
+use QuiverDB::meta::{read_meta, write_meta_overwrite, FEATURE_KV_PACKING};
+use QuiverDB::migrate::{
+    migrate_state_path, upgrade, upgrade_with_progress, MigrateOptions, MigrateState, StepStatus,
+};
+use QuiverDB::page::OFF_VERSION;
+use QuiverDB::pager::{DATA_SEG_EXT, DATA_SEG_PREFIX};
+
+const PS: u32 = 4096;
+
+#[test]
+fn upgrade_dry_run_apply_and_rerun() -> Result<()> {
+    let root = unique_root("migrate");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PS, 8)?;
+    {
+        let mut db = Db::open(&root)?;
+        for i in 0..50u32 {
+            db.put(format!("k{i}").as_bytes(), b"v")?;
+        }
+    }
+    // БД без feature flags (как до их появления)
+    let mut m = read_meta(&root)?;
+    let pages = m.next_page_id;
+    m.flags = 0;
+    write_meta_overwrite(&root, &m)?;
+
+    let dry = MigrateOptions {
+        dry_run: true,
+        ..Default::default()
+    };
+    let rep = upgrade(&root, "v3", &dry)?;
+    assert!(rep.dry_run);
+    assert_eq!(rep.steps.len(), 2);
+    assert_eq!(rep.steps[0].id, "v3-pages");
+    assert_eq!(rep.steps[0].pages_scanned, pages);
+    assert_eq!(rep.steps[0].legacy_pages, 0);
+    assert_eq!(rep.steps[1].status, StepStatus::WouldApply);
+    assert_eq!(read_meta(&root)?.flags, 0, "dry-run must not write");
+    assert!(!migrate_state_path(&root).exists());
+
+    let mut seen = 0u64;
+    let opts = MigrateOptions {
+        checkpoint_every: 8,
+        ..Default::default()
+    };
+    let rep = upgrade_with_progress(&root, "V3", &opts, |p| seen = seen.max(p.pages_done))?;
+    assert_eq!(seen, pages);
+    assert!(rep.steps.iter().all(|s| s.status != StepStatus::WouldApply));
+    assert_ne!(read_meta(&root)?.flags & FEATURE_KV_PACKING, 0);
+    let st = MigrateState::load(&root)?.unwrap();
+    assert_eq!(st.completed, vec!["v3-pages", "v3-feature-flags"]);
+
+    let rep = upgrade(&root, "v3", &MigrateOptions::default())?;
+    assert!(rep
+        .steps
+        .iter()
+        .all(|s| s.status == StepStatus::AlreadyApplied && s.pages_scanned == 0));
+
+    let err = upgrade(&root, "v2", &MigrateOptions::default()).unwrap_err();
+    assert!(format!("{:#}", err).contains("downgrade"), "{:#}", err);
+    Ok(())
+}
+
+#[test]
+fn legacy_page_stops_upgrade_and_resume_continues_from_cursor() -> Result<()> {
+    let root = unique_root("migrate-legacy");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PS, 8)?;
+    {
+        let mut db = Db::open(&root)?;
+        for i in 0..50u32 {
+            db.put(format!("k{i}").as_bytes(), b"v")?;
+        }
+    }
+    let legacy = read_meta(&root)?.next_page_id / 2;
+    set_page_version(&root, legacy, 2)?;
+
+    let dry = MigrateOptions {
+        dry_run: true,
+        ..Default::default()
+    };
+    let rep = upgrade(&root, "v3", &dry)?;
+    assert_eq!(rep.steps[0].status, StepStatus::WouldApply);
+    assert_eq!(rep.steps[0].legacy_sample, vec![legacy]);
+
+    let opts = MigrateOptions {
+        checkpoint_every: 1,
+        ..Default::default()
+    };
+    let err = upgrade(&root, "v3", &opts).unwrap_err();
+    assert!(format!("{:#}", err).contains("page format"), "{:#}", err);
+    let st = MigrateState::load(&root)?.unwrap();
+    assert_eq!(st.step.as_deref(), Some("v3-pages"));
+    assert_eq!(st.cursor, legacy);
+    assert!(st.completed.is_empty());
+
+    // Страница переписана (здесь — возвращена версия 3): upgrade продолжает с курсора
+    set_page_version(&root, legacy, 3)?;
+    let rep = upgrade(&root, "v3", &opts)?;
+    assert_eq!(rep.steps[0].resumed_from, Some(legacy));
+    assert_eq!(rep.steps[0].legacy_pages, 0);
+    assert_eq!(
+        MigrateState::load(&root)?.unwrap().completed,
+        vec!["v3-pages", "v3-feature-flags"]
+    );
+    Ok(())
+}
+
+fn set_page_version(root: &Path, pid: u64, ver: u16) -> Result<()> {
+    let seg = root.join(format!("{}{:06}.{}", DATA_SEG_PREFIX, 1, DATA_SEG_EXT));
+    let mut f = fs::OpenOptions::new().read(true).write(true).open(seg)?;
+    let off = pid * PS as u64 + OFF_VERSION as u64;
+    f.seek(SeekFrom::Start(off))?;
+    f.write_all(&ver.to_le_bytes())?;
+    f.sync_all()?;
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,88 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+
+/// get_many/exists_many: несколько ключей на одной head-странице, часть из которых уходит
+/// в цепочку — решения по остальным ключам группы не должны зависеть от обхода цепочки.
+#[test]
+fn multi_get_shared_head_page_with_chain_fallback() -> Result<()> {
+    let root = unique_root("multi-get");
+    fs::create_dir_all(&root)?;
+    // 1 бакет: все ключи делят head-страницу
+    Db::init(&root, 4096, 1)?;
+
+    let mut db = Db::open(&root)?;
+    db.put(b"a", b"1")?;
+    db.put(b"b", b"2")?;
+    db.put(b"c", b"3")?;
+    // Новая head-страница с tombstone для "c"; "a"/"b" остаются глубже в цепочке
+    db.del(b"c")?;
+
+    let keys: [&[u8]; 4] = [b"a", b"b", b"c", b"zz"];
+    let many = db.get_many(&keys)?;
+    assert_eq!(many[0].as_deref(), Some(&b"1"[..]));
+    assert_eq!(many[1].as_deref(), Some(&b"2"[..]));
+    assert!(many[2].is_none(), "deleted key must stay deleted");
+    assert!(many[3].is_none());
+
+    let ex = db.exists_many(&keys)?;
+    assert_eq!(ex, vec![true, true, false, false]);
+
+    drop(db);
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+/// Векторный путь: ключи одного бакета решаются за один обход цепочки — чтений страниц
+/// не больше длины цепочки; результаты совпадают с одиночными get/exists.
+#[test]
+fn multi_get_walks_each_bucket_chain_once() -> Result<()> {
+    let root = unique_root("multi-get-walk");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 1)?;
+
+    let mut db = Db::open(&root)?;
+    // 60 одиночных put → цепочка из 60 страниц, новейшие — у головы
+    for i in 0..60u32 {
+        db.put(
+            format!("k{:02}", i).as_bytes(),
+            format!("v{}", i).as_bytes(),
+        )?;
+    }
+    db.put(b"big", &vec![0x5Au8; 10_000])?;
+    db.del(b"k07")?;
+    let chain_len = 62u64;
+
+    let names: Vec<String> = (0..60u32)
+        .map(|i| format!("k{:02}", i))
+        .chain(["big".to_string(), "missing".to_string()])
+        .collect();
+    let keys: Vec<&[u8]> = names.iter().map(|k| k.as_bytes()).collect();
+
+    db.reset_stats();
+    let many = db.get_many(&keys)?;
+    let st = db.stats();
+    let reads = st.page_cache_hits + st.page_cache_misses;
+    assert!(reads <= chain_len + 3, "pages read {}", reads); // + OVERFLOW-цепочка "big"
+
+    let ex = db.exists_many(&keys)?;
+    for (i, k) in keys.iter().enumerate() {
+        assert_eq!(many[i], db.get(k)?, "{}", names[i]);
+        assert_eq!(ex[i], db.exists(k)?, "{}", names[i]);
+    }
+    assert!(many[7].is_none() && !ex[7]);
+    assert_eq!(many[60].as_ref().map(|v| v.len()), Some(10_000));
+    assert!(many[61].is_none() && !ex[61]);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,146 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use QuiverDB::db::{Db, ReadOnlyError};
+use QuiverDB::meta::read_meta;
+use QuiverDB::wal::wal_path;
+
+type Op = (&'static str, fn(&mut Db) -> Result<()>);
+
+/// Мутирующие точки входа Db (их же вызывают команды CLI) и низкоуровневые пути pager'а.
+const WRITE_OPS: &[Op] = &[
+    ("put", |db| db.put(b"new", b"v")),
+    ("del", |db| db.del(b"a").map(|_| ())),
+    ("batch", |db| db.batch(|b| b.put(b"x", b"1"))),
+    ("batch_idempotent", |db| {
+        db.batch_idempotent(b"tok", |b| b.put(b"x", b"1"))
+            .map(|_| ())
+    }),
+    ("bulk_load", |db| db.bulk_load().map(|_| ())),
+    ("delete_prefix", |db| db.delete_prefix(b"a").map(|_| ())),
+    ("undelete", |db| db.undelete(b"a").map(|_| ())),
+    ("compact_bucket", |db| db.compact_bucket(0).map(|_| ())),
+    ("compact_all", |db| db.compact_all().map(|_| ())),
+    ("vacuum_all", |db| db.vacuum_all().map(|_| ())),
+    ("sweep_orphan_overflow", |db| {
+        db.sweep_orphan_overflow().map(|_| ())
+    }),
+    ("auto_maintenance", |db| {
+        db.auto_maintenance(4, true).map(|_| ())
+    }),
+    ("set_dir_head", |db| db.set_dir_head(0, 1)),
+    ("set_dir_heads_bulk", |db| db.set_dir_heads_bulk(&[(0, 1)])),
+    ("freeze", |db| db.freeze().map(|_| ())),
+    ("refresh_bloom_touched", |db| {
+        db.refresh_bloom_touched().map(|_| ())
+    }),
+    ("commit_page", |db| {
+        let mut page = vec![0u8; db.pager.meta.page_size as usize];
+        db.pager.commit_page(0, &mut page)
+    }),
+    ("write_page_raw", |db| {
+        let page = vec![0u8; db.pager.meta.page_size as usize];
+        db.pager.write_page_raw(0, &page)
+    }),
+    ("ensure_allocated", |db| db.pager.ensure_allocated(1_000)),
+    ("allocate_pages", |db| {
+        db.pager.allocate_pages(4).map(|_| ())
+    }),
+    ("allocate_one_page", |db| {
+        db.pager.allocate_one_page().map(|_| ())
+    }),
+    ("free_page", |db| db.pager.free_page(0)),
+];
+
+/// Отпечаток состояния на диске, которое может изменить запись.
+fn disk_state(root: &Path) -> Result<(u64, u64, u64, bool, u64)> {
+    let m = read_meta(root)?;
+    let wal_len = fs::metadata(wal_path(root)).map(|m| m.len()).unwrap_or(0);
+    let seg_len: u64 = fs::read_dir(root)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().ends_with(".p2seg"))
+        .map(|e| e.metadata().map(|m| m.len()).unwrap_or(0))
+        .sum();
+    Ok((
+        m.last_lsn,
+        m.next_page_id,
+        wal_len,
+        root.join("free").exists(),
+        seg_len,
+    ))
+}
+
+fn seeded_root(name: &str) -> Result<PathBuf> {
+    let root = unique_root(name);
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 4)?;
+    let mut db = Db::open(&root)?;
+    db.put(b"a", b"1")?;
+    db.put(b"b", b"2")?;
+    Ok(root)
+}
+
+#[test]
+fn read_only_handle_rejects_every_write_path() -> Result<()> {
+    let root = seeded_root("ro-matrix")?;
+    let before = disk_state(&root)?;
+
+    let mut ro = Db::open_ro(&root)?;
+    assert!(ro.pager.is_read_only());
+    for (name, op) in WRITE_OPS {
+        let err = op(&mut ro).expect_err(name);
+        let r = err
+            .chain()
+            .find_map(|c| c.downcast_ref::<ReadOnlyError>())
+            .unwrap_or_else(|| panic!("{}: expected ReadOnlyError, got {:#}", name, err));
+        assert_eq!(r.path, root, "{}", name);
+        assert!(format!("{:#}", err).contains("read-only"), "{}", name);
+    }
+    // Чтения по-прежнему работают, на диске ничего не изменилось
+    assert_eq!(ro.get(b"a")?.as_deref(), Some(&b"1"[..]));
+    drop(ro);
+    assert_eq!(disk_state(&root)?, before);
+    Ok(())
+}
+
+#[test]
+fn writer_handle_passes_the_gate() -> Result<()> {
+    let root = seeded_root("rw-matrix")?;
+    let mut db = Db::open(&root)?;
+    assert!(!db.pager.is_read_only());
+    db.pager.ensure_writable("test")?;
+    for (name, op) in WRITE_OPS {
+        // Оставляем writer'у только безопасные для структуры БД операции
+        if matches!(
+            *name,
+            "set_dir_head"
+                | "set_dir_heads_bulk"
+                | "commit_page"
+                | "write_page_raw"
+                | "free_page"
+                | "freeze"
+        ) {
+            continue;
+        }
+        if let Err(e) = op(&mut db) {
+            assert!(
+                !e.chain().any(|c| c.is::<ReadOnlyError>()),
+                "{}: writer rejected by RO gate: {:#}",
+                name,
+                e
+            );
+        }
+    }
+    assert_eq!(db.get(b"new")?.as_deref(), Some(&b"v"[..]));
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
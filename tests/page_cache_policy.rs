@@ -0,0 +1,103 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::Db;
+use QuiverDB::metrics;
+use QuiverDB::pager::cache::{
+    page_cache_clear, page_cache_len, page_cache_locked_bytes, page_cache_policy,
+    page_cache_set_policy, PageCachePolicy,
+};
+
+fn cfg(policy: PageCachePolicy, mlock_max: u64) -> QuiverConfig {
+    QuiverConfig::from_env()
+        .with_page_cache_pages(64)
+        .with_page_cache_policy(policy)
+        .with_page_cache_mlock_max_bytes(mlock_max)
+}
+
+fn fill_and_read(db: &mut Db) -> Result<()> {
+    for i in 0..40u32 {
+        db.put(format!("k{:03}", i).as_bytes(), &vec![i as u8; 900])?;
+    }
+    for _ in 0..2 {
+        for i in 0..40u32 {
+            let v = db.get(format!("k{:03}", i).as_bytes())?.unwrap();
+            assert_eq!(v, vec![i as u8; 900]);
+        }
+    }
+    Ok(())
+}
+
+// Кэш и политика процесс-широкие: все режимы — в одном тесте, последовательно.
+#[test]
+fn page_cache_policies_serve_reads_and_account_locked_memory() -> Result<()> {
+    let root = unique_root("pc-policy");
+    Db::init(&root, 4096, 8)?;
+
+    // ciphertext: записи запечатаны, чтения корректны и попадают в кэш
+    {
+        let mut db = Db::open_with_config(&root, cfg(PageCachePolicy::Ciphertext, 0))?;
+        assert_eq!(page_cache_policy(), PageCachePolicy::Ciphertext);
+        let hits0 = metrics::snapshot().page_cache_hits;
+        fill_and_read(&mut db)?;
+        let m = metrics::snapshot();
+        assert!(m.page_cache_hits > hits0);
+        assert_eq!(m.page_cache_unseal_failures, 0);
+        assert!(page_cache_len() > 0);
+        assert_eq!(page_cache_locked_bytes(), 0);
+    }
+
+    // locked: под mlock не больше лимита (2 страницы); сверх лимита страницы не кэшируются
+    {
+        let cap = 2 * 4096;
+        let mut db = Db::open_with_config(&root, cfg(PageCachePolicy::Locked, cap))?;
+        assert_eq!(page_cache_policy(), PageCachePolicy::Locked);
+        // смена политики очистила кэш
+        assert_eq!(page_cache_len(), 0);
+        fill_and_read(&mut db)?;
+        let locked = page_cache_locked_bytes();
+        assert!(locked <= cap);
+        assert_eq!(locked, page_cache_len() as u64 * 4096);
+        assert!(metrics::snapshot().page_cache_mlock_rejects > 0);
+    }
+    page_cache_clear();
+    assert_eq!(page_cache_locked_bytes(), 0);
+
+    // plaintext — обычное поведение
+    page_cache_set_policy(PageCachePolicy::Plaintext, 0);
+    let mut db = Db::open_with_config(&root, cfg(PageCachePolicy::Plaintext, 0))?;
+    fill_and_read(&mut db)?;
+    assert!(page_cache_len() > 2);
+    Ok(())
+}
+
+#[test]
+fn page_cache_policy_parses_config_values() {
+    assert_eq!(
+        PageCachePolicy::parse("Ciphertext"),
+        Some(PageCachePolicy::Ciphertext)
+    );
+    assert_eq!(
+        PageCachePolicy::parse("locked"),
+        Some(PageCachePolicy::Locked)
+    );
+    assert_eq!(PageCachePolicy::parse(""), Some(PageCachePolicy::Plaintext));
+    assert_eq!(PageCachePolicy::parse("bogus"), None);
+
+    let mut c = QuiverConfig::default();
+    c.set_field("page_cache_policy", "locked").unwrap();
+    assert_eq!(
+        c.field_value("page_cache_policy").as_deref(),
+        Some("locked")
+    );
+    assert!(c.set_field("page_cache_policy", "x").is_err());
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
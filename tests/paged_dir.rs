@@ -0,0 +1,126 @@
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use QuiverDB::db::Db;
+use QuiverDB::dir::{Directory, NO_PAGE};
+use QuiverDB::meta::{
+    init_meta_v4, read_meta, CKSUM_CRC32C, CODEC_NONE, FEATURE_PAGED_DIR, HASH_KIND_XX64_SEED0,
+};
+
+const BUCKETS: u32 = 1000;
+const CHUNK_HEADS: u32 = 16;
+
+fn init_paged(root: &Path) -> Result<()> {
+    fs::create_dir_all(root)?;
+    init_meta_v4(root, 4096, HASH_KIND_XX64_SEED0, CODEC_NONE, CKSUM_CRC32C)?;
+    let dir = Directory::create_paged(root, BUCKETS, CHUNK_HEADS)?;
+    assert!(dir.is_paged());
+    assert_eq!(
+        dir.paged_layout().unwrap().chunks,
+        BUCKETS.div_ceil(CHUNK_HEADS)
+    );
+    Ok(())
+}
+
+#[test]
+fn paged_directory_serves_writer_and_readers() -> Result<()> {
+    let root = unique_root("paged-dir");
+    init_paged(&root)?;
+    {
+        let mut db = Db::open(&root)?;
+        for i in 0..600u32 {
+            db.put(
+                format!("k{:04}", i).as_bytes(),
+                format!("v{}", i).as_bytes(),
+            )?;
+        }
+        for i in 0..100u32 {
+            db.del(format!("k{:04}", i).as_bytes())?;
+        }
+        assert_eq!(db.get(b"k0500")?.as_deref(), Some(&b"v500"[..]));
+        let cache = db.dir.chunk_cache_stats().unwrap();
+        assert!(cache.chunks_cached > 0 && cache.chunks_cached <= cache.capacity);
+        db.compact_all()?;
+    }
+    assert_ne!(read_meta(&root)?.flags & FEATURE_PAGED_DIR, 0);
+
+    let ro = Db::open_ro(&root)?;
+    assert!(ro.dir.is_paged());
+    for i in 0..600u32 {
+        let want = (i >= 100).then(|| format!("v{}", i).into_bytes());
+        assert_eq!(ro.get(format!("k{:04}", i).as_bytes())?, want, "k{:04}", i);
+    }
+    assert_eq!(ro.scan_prefix(b"k")?.len(), 500);
+    let heads = ro.dir.heads_snapshot()?;
+    assert_eq!(heads.len(), BUCKETS as usize);
+    let used = heads.iter().filter(|&&h| h != NO_PAGE).count() as u32;
+    assert_eq!(ro.dir.count_used_buckets()?, used);
+    Ok(())
+}
+
+#[test]
+fn torn_chunk_slot_falls_back_and_wal_replay_restores_heads() -> Result<()> {
+    let src = unique_root("paged-dir-torn-src");
+    init_paged(&src)?;
+    let dst = unique_root("paged-dir-torn-dst");
+    let key = b"torn-key";
+    let bucket;
+    {
+        let mut db = Db::open(&src)?;
+        bucket = db.dir.bucket_of_key(key, db.pager.meta.hash_kind);
+        db.put(key, b"v1")?;
+        db.put(key, b"v2")?;
+        // Копия живого writer'а: WAL ещё содержит HEADS_UPDATE обоих коммитов
+        copy_dir(&src, &dst)?;
+    }
+
+    // Порвём активный (новейший) слот чанка с головой ключа
+    let mut bytes = fs::read(dst.join("dir-000"))?;
+    let chunk = (bucket / CHUNK_HEADS) as usize;
+    let slot_size = 16 + CHUNK_HEADS as usize * 8;
+    let off = |s: usize| 64 + (chunk * 2 + s) * slot_size;
+    let seq = |b: &[u8], s: usize| LittleEndian::read_u64(&b[off(s)..off(s) + 8]);
+    let active = if seq(&bytes, 0) > seq(&bytes, 1) {
+        0
+    } else {
+        1
+    };
+    let pos = off(active) + 16 + (bucket % CHUNK_HEADS) as usize * 8;
+    bytes[pos] ^= 0xFF;
+    fs::write(dst.join("dir-000"), &bytes)?;
+
+    // Чанк читается из прежнего слота — каталог открывается, голова не «мусорная»
+    let dir = Directory::open(&dst)?;
+    let h = dir.head(bucket)?;
+    assert!(h == NO_PAGE || h < read_meta(&dst)?.next_page_id.max(64));
+    drop(dir);
+
+    // Writer реплеит HEADS_UPDATE из WAL и восстанавливает новейшую голову
+    let db = Db::open(&dst)?;
+    assert_eq!(db.get(key)?.as_deref(), Some(&b"v2"[..]));
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for e in fs::read_dir(from)? {
+        let e = e?;
+        let name = e.file_name();
+        if name == "LOCK" || !e.file_type()?.is_file() {
+            continue;
+        }
+        fs::copy(e.path(), to.join(name))?;
+    }
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
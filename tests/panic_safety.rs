@@ -0,0 +1,182 @@
+use anyhow::Result;
+use std::borrow::Cow;
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::compaction_filter::{CompactionRecord, FilterDecision};
+use QuiverDB::db::{CommitEvent, Db, DbInstrumentation, DbPoisonedError, KeyTransform};
+
+// Глушим вывод panic hook (он процесс-глобальный) — тесты с паниками идут по очереди.
+static PANIC_HOOK: Mutex<()> = Mutex::new(());
+
+fn quiet_panic<R>(f: impl FnOnce() -> R) -> std::thread::Result<R> {
+    let _g = PANIC_HOOK.lock().unwrap_or_else(|e| e.into_inner());
+    let prev = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let r = catch_unwind(AssertUnwindSafe(f));
+    std::panic::set_hook(prev);
+    r
+}
+
+struct PanickingCommitHook {
+    armed: AtomicBool,
+}
+
+impl DbInstrumentation for PanickingCommitHook {
+    fn on_commit(&self, _ev: &CommitEvent) {
+        if self.armed.load(Ordering::Relaxed) {
+            panic!("commit hook boom");
+        }
+    }
+}
+
+/// Пользовательская трансформация ключей: Db::open без неё БД не откроет.
+struct Trim;
+
+impl KeyTransform for Trim {
+    fn id(&self) -> String {
+        "test-trim".to_string()
+    }
+
+    fn canonical_key<'a>(&self, key: &'a [u8]) -> Cow<'a, [u8]> {
+        Cow::Borrowed(key.trim_ascii())
+    }
+}
+
+#[test]
+fn panic_in_batch_closure_does_not_poison() -> Result<()> {
+    let root = unique_root("panic-batch");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let mut db = Db::open(&root)?;
+    db.put(b"k0", b"v0")?;
+
+    let r = quiet_panic(|| {
+        db.batch(|b| {
+            b.put(b"k1", b"v1")?;
+            panic!("user closure boom");
+        })
+    });
+    assert!(r.is_err());
+    assert!(db.poisoned().is_none());
+    assert_eq!(db.get(b"k1")?, None);
+
+    db.put(b"k2", b"v2")?;
+    assert_eq!(db.get(b"k0")?.as_deref(), Some(&b"v0"[..]));
+    assert_eq!(db.get(b"k2")?.as_deref(), Some(&b"v2"[..]));
+    Ok(())
+}
+
+#[test]
+fn panic_in_commit_hook_poisons_and_reopen_recovers() -> Result<()> {
+    let root = unique_root("panic-commit");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let mut db = Db::open(&root)?;
+    let hook = Arc::new(PanickingCommitHook {
+        armed: AtomicBool::new(false),
+    });
+    db.set_instrumentation(hook.clone());
+    db.put(b"a", b"1")?;
+
+    hook.armed.store(true, Ordering::Relaxed);
+    let r = quiet_panic(|| db.put(b"b", b"2"));
+    assert!(r.is_err());
+    let info = db.poisoned().expect("handle must be poisoned").clone();
+    assert_eq!(info.op, "commit_pages_batch_with_heads");
+    assert!(info.message.contains("commit hook boom"));
+
+    // Записи отклоняются типизированной ошибкой, чтения работают
+    hook.armed.store(false, Ordering::Relaxed);
+    let err = db.put(b"c", b"3").unwrap_err();
+    let p = err
+        .downcast_ref::<DbPoisonedError>()
+        .expect("DbPoisonedError");
+    assert_eq!(p.path, root);
+    assert!(db.del(b"a").is_err());
+    assert_eq!(db.get(b"a")?.as_deref(), Some(&b"1"[..]));
+
+    // Reopen реплеит WAL: коммит, прерванный паникой хука, уже durable
+    let mut db = db.reopen()?;
+    assert!(db.poisoned().is_none());
+    assert_eq!(db.get(b"a")?.as_deref(), Some(&b"1"[..]));
+    assert_eq!(db.get(b"b")?.as_deref(), Some(&b"2"[..]));
+    db.put(b"c", b"3")?;
+    assert_eq!(db.get(b"c")?.as_deref(), Some(&b"3"[..]));
+    Ok(())
+}
+
+#[test]
+fn panic_in_compaction_filter_poisons_until_reopen() -> Result<()> {
+    let root = unique_root("panic-compact");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 4)?;
+    {
+        let mut db = Db::open(&root)?;
+        for i in 0..16u32 {
+            db.put(format!("k{}", i).as_bytes(), b"old")?;
+            db.put(format!("k{}", i).as_bytes(), b"new")?;
+        }
+        db.set_compaction_filter(|_rec: &CompactionRecord<'_>| -> FilterDecision {
+            panic!("filter boom")
+        });
+        let r = quiet_panic(|| db.compact_all());
+        assert!(r.is_err());
+        assert_eq!(db.poisoned().map(|p| p.op), Some("compact_bucket"));
+        assert!(db.vacuum_all().is_err());
+        assert!(db.put(b"x", b"y").is_err());
+    }
+
+    let mut db = Db::open(&root)?;
+    assert!(db.poisoned().is_none());
+    for i in 0..16u32 {
+        assert_eq!(
+            db.get(format!("k{}", i).as_bytes())?.as_deref(),
+            Some(&b"new"[..])
+        );
+    }
+    db.put(b"x", b"y")?;
+    assert_eq!(db.get(b"x")?.as_deref(), Some(&b"y"[..]));
+    Ok(())
+}
+
+/// reopen открывает с исходной QuiverConfig: пользовательская трансформация ключей сохраняется.
+#[test]
+fn reopen_keeps_open_config() -> Result<()> {
+    let root = unique_root("panic-reopen-cfg");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let cfg = QuiverConfig::from_env().with_key_transform(Arc::new(Trim));
+    let mut db = Db::open_with_config(&root, cfg)?;
+    let hook = Arc::new(PanickingCommitHook {
+        armed: AtomicBool::new(false),
+    });
+    db.set_instrumentation(hook.clone());
+    db.put(b"  a  ", b"1")?;
+
+    hook.armed.store(true, Ordering::Relaxed);
+    assert!(quiet_panic(|| db.put(b"b", b"2")).is_err());
+    assert!(db.poisoned().is_some());
+
+    let mut db = db.reopen()?;
+    assert!(db.poisoned().is_none());
+    assert_eq!(db.key_transform_id().as_deref(), Some("test-trim"));
+    assert_eq!(db.get(b"a ")?.as_deref(), Some(&b"1"[..]));
+    // хук после reopen не переносится
+    db.put(b" c", b"3")?;
+    assert_eq!(db.get(b"c")?.as_deref(), Some(&b"3"[..]));
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::Db;
+use QuiverDB::free::FreeList;
+use QuiverDB::metrics::snapshot as metrics_snapshot;
+use QuiverDB::pager::ReadOnlyError;
+
+/// Занятые блоки всех сегментов (байты, st_blocks * 512).
+fn seg_allocated(root: &Path) -> Result<u64> {
+    let mut total = 0u64;
+    for e in fs::read_dir(root)? {
+        let e = e?;
+        if e.file_name().to_string_lossy().ends_with(".p2seg") {
+            let m = e.metadata()?;
+            total += m.blocks() * 512;
+        }
+    }
+    Ok(total)
+}
+
+/// Большие значения (OVERFLOW), удаление и компактация: цепочки становятся сиротскими.
+fn make_orphans(root: &Path) -> Result<()> {
+    let mut db = Db::open(root)?;
+    for i in 0..40u32 {
+        db.put(format!("big{:03}", i).as_bytes(), &vec![0x5Au8; 16 * 1024])?;
+    }
+    db.put(b"keep", b"value")?;
+    for i in 0..40u32 {
+        db.del(format!("big{:03}", i).as_bytes())?;
+    }
+    db.compact_all()?;
+    Ok(())
+}
+
+fn fresh(name: &str) -> Result<PathBuf> {
+    let root = unique_root(name);
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    Ok(root)
+}
+
+#[test]
+fn sweep_punches_freed_pages() -> Result<()> {
+    let root = fresh("punch-sweep")?;
+    make_orphans(&root)?;
+
+    let mut db = Db::open_with_config(&root, QuiverConfig::from_env().with_punch_holes(true))?;
+    if !db.pager.punch_hole_supported() {
+        eprintln!("skip: filesystem of {} cannot punch holes", root.display());
+        return Ok(());
+    }
+    let before_blocks = seg_allocated(&root)?;
+    let before = metrics_snapshot();
+    let freed = db.sweep_orphan_overflow()? as u64;
+    let after = metrics_snapshot();
+    assert!(freed >= 40 * 4, "freed only {} page(s)", freed);
+    assert!(after.punch_hole_ops >= before.punch_hole_ops + freed);
+
+    let after_blocks = seg_allocated(&root)?;
+    assert!(
+        after_blocks + freed * 4096 <= before_blocks,
+        "blocks {} -> {} after freeing {} page(s)",
+        before_blocks,
+        after_blocks,
+        freed
+    );
+    // Длина сегментов не меняется; страницы остаются в free-листе
+    assert_eq!(FreeList::open(&root)?.count()?, freed);
+    assert_eq!(db.get(b"keep")?.as_deref(), Some(&b"value"[..]));
+
+    // Повторный sweep не находит пробитые страницы (они читаются нулями)
+    assert_eq!(db.sweep_orphan_overflow()?, 0);
+    Ok(())
+}
+
+#[test]
+fn trim_existing_free_list_and_reuse_pages() -> Result<()> {
+    let root = fresh("punch-trim")?;
+    make_orphans(&root)?;
+    {
+        // Режим выключен: sweep только пополняет free-лист
+        let mut db = Db::open_with_config(&root, QuiverConfig::from_env().with_punch_holes(false))?;
+        assert!(db.sweep_orphan_overflow()? > 0);
+    }
+    let free = FreeList::open(&root)?.count()?;
+    {
+        let mut db = Db::open(&root)?;
+        let rep = db.trim_free_pages()?;
+        assert_eq!(rep.free_pages, free);
+        if rep.supported {
+            assert_eq!(rep.punched_pages, free);
+            assert_eq!(rep.punched_bytes, free * 4096);
+        } else {
+            assert_eq!(rep.punched_pages, 0);
+        }
+        // Пробитые страницы переиспользуются из free-листа и переписываются целиком
+        for i in 0..200u32 {
+            db.put(format!("new{:04}", i).as_bytes(), &[3u8; 300])?;
+        }
+    }
+    assert!(FreeList::open(&root)?.count()? < free);
+
+    let db = Db::open_ro(&root)?;
+    for i in (0..200u32).step_by(17) {
+        assert_eq!(
+            db.get(format!("new{:04}", i).as_bytes())?.as_deref(),
+            Some(&[3u8; 300][..])
+        );
+    }
+    assert_eq!(db.get(b"keep")?.as_deref(), Some(&b"value"[..]));
+    Ok(())
+}
+
+#[test]
+fn trim_is_rejected_read_only() -> Result<()> {
+    let root = fresh("punch-ro")?;
+    make_orphans(&root)?;
+    let mut db = Db::open_ro(&root)?;
+    let err = db.trim_free_pages().unwrap_err();
+    assert!(err.downcast_ref::<ReadOnlyError>().is_some(), "{:#}", err);
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::Db;
+use QuiverDB::metrics;
+
+#[test]
+fn readahead_chain_and_overflow() -> Result<()> {
+    let root = unique_root("readahead");
+    fs::create_dir_all(&root)?;
+    // 1 бакет — все ключи в одной цепочке
+    Db::init(&root, 4096, 1)?;
+
+    // Без page cache: все чтения идут на диск (через окно)
+    let cfg = QuiverConfig::default().with_page_cache_pages(0);
+
+    let big: Vec<u8> = (0..64 * 1024u32).map(|i| (i % 251) as u8).collect();
+    {
+        let mut db = Db::open_with_config(&root, cfg.clone())?;
+        db.put(b"big", &big)?;
+        for i in 0..40u32 {
+            db.put(format!("k{:03}", i).as_bytes(), b"v")?;
+        }
+    }
+
+    metrics::reset();
+    {
+        let db = Db::open_with_config(&root, cfg.clone())?;
+        // Старейший ключ в хвосте цепочки: окно «назад»
+        assert_eq!(db.get(b"k000")?.as_deref(), Some(&b"v"[..]));
+        let m = metrics::snapshot();
+        assert!(m.readahead_fills > 0);
+        assert!(m.readahead_hits > 0);
+
+        // OVERFLOW-цепочка: окно «вперёд»
+        metrics::reset();
+        assert_eq!(db.get(b"big")?.as_deref(), Some(&big[..]));
+        let m = metrics::snapshot();
+        assert!(m.readahead_hits > 0);
+    }
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
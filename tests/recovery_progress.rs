@@ -0,0 +1,97 @@
+use anyhow::Result;
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use QuiverDB::db::Db;
+use QuiverDB::meta::{read_meta, set_clean_shutdown};
+use QuiverDB::metrics;
+use QuiverDB::page::kv_init_v3;
+use QuiverDB::pager::Pager;
+use QuiverDB::wal::recovery::replay_index_path;
+use QuiverDB::wal::{RecoveryProgress, RecoveryProgressHook, WAL_FILE, WAL_HDR_SIZE};
+
+#[test]
+fn interrupted_replay_resumes_from_batch_boundary_with_progress() -> Result<()> {
+    let root = unique_root("recovery-progress");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 64 * 1024, 16)?;
+
+    // 6 батчей по 10 страниц (~650 KiB WAL на батч) без записи meta — «краш»
+    let total_pages = 60u64;
+    {
+        let mut pager = Pager::open(&root)?;
+        let ps = pager.meta.page_size as usize;
+        for _ in 0..6 {
+            let mut bufs: Vec<(u64, Vec<u8>)> = Vec::new();
+            for _ in 0..10 {
+                let pid = pager.allocate_one_page()?;
+                let mut page = vec![0u8; ps];
+                kv_init_v3(&mut page, pid, 0)?;
+                bufs.push((pid, page));
+            }
+            let mut refs: Vec<(u64, &mut [u8])> = bufs
+                .iter_mut()
+                .map(|(p, b)| (*p, b.as_mut_slice()))
+                .collect();
+            pager.commit_pages_batch(&mut refs)?;
+        }
+    }
+    set_clean_shutdown(&root, false)?;
+    metrics::reset();
+
+    // [1] Реплей «обрывается» сразу после первого чекпоинта индекса
+    let abort = RecoveryProgressHook::new(|p: &RecoveryProgress| {
+        if p.checkpoint_lsn > 0 && !p.done {
+            panic!("simulated crash during recovery");
+        }
+    });
+    let r = catch_unwind(AssertUnwindSafe(|| {
+        Pager::wal_replay_with_pager_progress(&root, Some(&abort))
+    }));
+    assert!(r.is_err(), "replay must have been interrupted");
+    assert!(replay_index_path(&root).exists());
+    assert!(!read_meta(&root)?.clean_shutdown);
+
+    // [2] Повторный реплей пропускает применённый префикс и сообщает прогресс
+    let seen: Arc<Mutex<Vec<RecoveryProgress>>> = Arc::new(Mutex::new(Vec::new()));
+    let seen2 = seen.clone();
+    let hook = RecoveryProgressHook::new(move |p: &RecoveryProgress| {
+        seen2.lock().unwrap().push(p.clone());
+    });
+    Pager::wal_replay_with_pager_progress(&root, Some(&hook))?;
+
+    let seen = seen.lock().unwrap();
+    let last = seen.last().expect("progress reported");
+    assert!(last.done);
+    assert!(last.bytes_skipped > 0, "prefix must be skipped");
+    assert!(last.frames_applied > 0);
+    assert_eq!(last.bytes_done, last.bytes_total);
+    assert!(metrics::snapshot().recovery_bytes_skipped > 0);
+
+    // Все страницы на месте, next_page_id учтён и для пропущенного префикса
+    let m = read_meta(&root)?;
+    assert!(m.clean_shutdown);
+    assert!(m.next_page_id >= total_pages);
+    let pager = Pager::open(&root)?;
+    let mut buf = vec![0u8; pager.meta.page_size as usize];
+    for pid in 0..total_pages {
+        pager.read_page(pid, &mut buf)?;
+    }
+    assert!(!replay_index_path(&root).exists());
+    assert_eq!(
+        fs::metadata(root.join(WAL_FILE))?.len(),
+        WAL_HDR_SIZE as u64
+    );
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
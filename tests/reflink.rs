@@ -0,0 +1,119 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use QuiverDB::db::clone::{clone_live, CloneOptions};
+use QuiverDB::db::Db;
+use QuiverDB::snapstore::{restore_from_id, SnapshotManager};
+use QuiverDB::util::reflink::{clone_file, reflink_supported, FileCopyMethod};
+
+// P1_REFLINK — процесс-глобальный ENV: тесты файла идут по очереди.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn fill(root: &Path) -> Result<()> {
+    fs::create_dir_all(root)?;
+    Db::init(root, 4096, 8)?;
+    let mut db = Db::open(root)?;
+    for i in 0..100 {
+        db.put(
+            format!("k{:03}", i).as_bytes(),
+            format!("v{}", i).as_bytes(),
+        )?;
+    }
+    db.put(b"big", &vec![0xC3u8; 20_000])?;
+    Ok(())
+}
+
+fn check(root: &Path) -> Result<()> {
+    let db = Db::open_ro(root)?;
+    for i in 0..100 {
+        assert_eq!(
+            db.get(format!("k{:03}", i).as_bytes())?,
+            Some(format!("v{}", i).into_bytes())
+        );
+    }
+    assert_eq!(db.get(b"big")?, Some(vec![0xC3u8; 20_000]));
+    Ok(())
+}
+
+#[test]
+fn clone_file_matches_probe_and_falls_back_transparently() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = unique_root("reflink-file");
+    fs::create_dir_all(&dir)?;
+    let src = dir.join("src.bin");
+    let data: Vec<u8> = (0..64 * 1024u32).map(|i| (i * 31 % 251) as u8).collect();
+    fs::write(&src, &data)?;
+
+    let supported = reflink_supported(&dir, &dir);
+    // Проба за собой не оставляет файлов
+    assert_eq!(fs::read_dir(&dir)?.count(), 1);
+
+    let dst = dir.join("dst.bin");
+    fs::write(&dst, b"stale contents")?;
+    let (n, how) = clone_file(&src, &dst, supported)?;
+    assert_eq!(n, data.len() as u64);
+    assert_eq!(how == FileCopyMethod::Reflink, supported);
+    assert_eq!(fs::read(&dst)?, data);
+
+    // Копия и без попытки reflink
+    let (_, how) = clone_file(&src, &dir.join("copy.bin"), false)?;
+    assert_eq!(how, FileCopyMethod::Copy);
+    assert_eq!(fs::read(dir.join("copy.bin"))?, data);
+
+    // P1_REFLINK=off — проба отвечает «нет»
+    std::env::set_var("P1_REFLINK", "off");
+    let off = reflink_supported(&dir, &dir);
+    std::env::remove_var("P1_REFLINK");
+    assert!(!off);
+    Ok(())
+}
+
+#[test]
+fn live_clone_reports_reflinked_files() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let src = unique_root("reflink-clone-src");
+    let dst = unique_root("reflink-clone-dst");
+    fill(&src)?;
+    fs::create_dir_all(&dst)?;
+    let supported = reflink_supported(&src, &dst);
+
+    let rep = clone_live(&src, &dst, &CloneOptions::default())?;
+    assert!(rep.files_copied > 0);
+    if supported {
+        assert_eq!(rep.files_reflinked, rep.files_copied);
+    } else {
+        assert_eq!(rep.files_reflinked, 0);
+    }
+    check(&dst)
+}
+
+#[test]
+fn snapshot_restore_with_or_without_reflink() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let src = unique_root("reflink-snap-src");
+    fill(&src)?;
+    let id = {
+        let db = Db::open_ro(&src)?;
+        SnapshotManager::create_persisted(&db, Some("reflink"), &[], None)?
+    };
+    for reflink in ["on", "off"] {
+        std::env::set_var("P1_REFLINK", reflink);
+        let dst = unique_root("reflink-snap-dst");
+        fs::create_dir_all(&dst)?;
+        let r = restore_from_id(&src, &dst, &id, true);
+        std::env::remove_var("P1_REFLINK");
+        r?;
+        check(&dst)?;
+    }
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
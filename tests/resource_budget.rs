@@ -0,0 +1,118 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use QuiverDB::budget::{
+    resource_usage, set_resource_budget, ResourceBudget, ResourceBudgetError, Subsystem,
+};
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::Db;
+
+// Бюджет процесс-широкий — тесты этого файла не должны пересекаться
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn init_with_keys(root: &PathBuf, n: usize) -> Result<()> {
+    fs::create_dir_all(root)?;
+    Db::init(root, 4096, 8)?;
+    let mut db = Db::open(root)?;
+    for i in 0..n {
+        db.put(format!("key-{:04}", i).as_bytes(), &[b'v'; 200])?;
+    }
+    Ok(())
+}
+
+#[test]
+fn open_beyond_max_open_fds_fails_with_typed_error() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let a = unique_root("budget-fd-a");
+    let b = unique_root("budget-fd-b");
+    init_with_keys(&a, 1)?;
+    init_with_keys(&b, 1)?;
+
+    let used = resource_usage().open_fds;
+    set_resource_budget(ResourceBudget::unlimited().with_max_fds(used + 1));
+
+    let db_a = Db::open_ro(&a)?;
+    let err = Db::open_ro(&b)
+        .err()
+        .expect("second open must hit max_open_fds");
+    let be = err
+        .downcast_ref::<ResourceBudgetError>()
+        .expect("ResourceBudgetError");
+    assert_eq!(be.path, b);
+    assert_eq!(be.limit, used + 1);
+
+    // Закрытие возвращает дескриптор в бюджет
+    drop(db_a);
+    let db_b = Db::open_ro(&b)?;
+    assert_eq!(db_b.get(b"key-0000")?.as_deref(), Some(&[b'v'; 200][..]));
+    drop(db_b);
+
+    set_resource_budget(ResourceBudget::unlimited());
+    Ok(())
+}
+
+#[test]
+fn keydir_is_skipped_when_it_does_not_fit_the_budget() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("budget-keydir");
+    init_with_keys(&root, 300)?;
+
+    set_resource_budget(ResourceBudget::unlimited());
+    let db = Db::open_ro(&root)?;
+    assert!(db.has_mem_keydir());
+    assert!(resource_usage().bytes_of(Subsystem::Keydir) >= 300 * 8);
+    drop(db);
+
+    // Почти нет места: keydir не строится, чтения идут по цепочкам
+    let used = resource_usage().memory_bytes;
+    let cfg = QuiverConfig::from_env().with_memory_budget_bytes(used + 64);
+    let db = Db::open_ro_with_config(&root, cfg)?;
+    assert!(!db.has_mem_keydir());
+    assert_eq!(db.get(b"key-0123")?.as_deref(), Some(&[b'v'; 200][..]));
+    assert!(db.get(b"absent")?.is_none());
+    drop(db);
+
+    set_resource_budget(ResourceBudget::unlimited());
+    Ok(())
+}
+
+#[test]
+fn page_cache_stays_within_memory_budget() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("budget-pcache");
+    init_with_keys(&root, 400)?;
+
+    let base = resource_usage();
+    let limit = base.memory_bytes - base.bytes_of(Subsystem::PageCache) + 3 * 4096;
+    set_resource_budget(ResourceBudget::unlimited().with_memory_bytes(limit));
+
+    let cfg = QuiverConfig::from_env().with_page_cache_pages(1024);
+    let db = Db::open_with_config(&root, cfg)?;
+    for i in 0..400 {
+        let k = format!("key-{:04}", i);
+        assert!(db.get(k.as_bytes())?.is_some(), "{} lost", k);
+    }
+    let u = resource_usage();
+    assert!(
+        u.memory_bytes <= limit,
+        "usage {} over limit {}",
+        u.memory_bytes,
+        limit
+    );
+    let pc = u.bytes_of(Subsystem::PageCache);
+    assert!(pc > 0 && pc <= 3 * 4096, "page cache bytes {}", pc);
+    drop(db);
+
+    set_resource_budget(ResourceBudget::unlimited());
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,122 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::backup::{
+    backup_to_writer, restore_from_reader, restore_from_reader_with, RestoreConflictError,
+    RestoreOptions,
+};
+use QuiverDB::db::Db;
+use QuiverDB::meta::read_meta;
+use QuiverDB::snapstore::{restore_from_id, restore_from_id_with, SnapshotManager};
+
+fn conflict(e: &anyhow::Error) -> Option<&RestoreConflictError> {
+    e.chain()
+        .find_map(|c| c.downcast_ref::<RestoreConflictError>())
+}
+
+#[test]
+fn incremental_restore_refuses_newer_target_unless_forced() -> Result<()> {
+    let src = unique_root("gen-src");
+    fs::create_dir_all(&src)?;
+    Db::init(&src, 4096, 8)?;
+    let mut full = Vec::new();
+    let full_man = {
+        let mut db = Db::open(&src)?;
+        db.put(b"a", b"1")?;
+        backup_to_writer(&db, &mut full, 0)?
+    };
+    let mut inc = Vec::new();
+    {
+        let mut db = Db::open(&src)?;
+        db.put(b"b", b"2")?;
+        backup_to_writer(&db, &mut inc, full_man.lsn)?;
+    }
+
+    // Цель после полного restore ушла вперёд собственными коммитами
+    let dst = unique_root("gen-dst");
+    restore_from_reader(&dst, full.as_slice(), true)?;
+    {
+        let mut db = Db::open(&dst)?;
+        for i in 0..5u32 {
+            db.put(format!("local{}", i).as_bytes(), b"x")?;
+        }
+    }
+    let dst_lsn = read_meta(&dst)?.last_lsn;
+
+    let err = restore_from_reader(&dst, inc.as_slice(), true).unwrap_err();
+    let c = conflict(&err).expect("typed conflict");
+    assert_eq!(c.reason, "newer_target");
+    assert_eq!(c.target_lsn, dst_lsn);
+    let msg = format!("{:#}", err);
+    assert!(msg.contains(&format!("lsn={}", dst_lsn)));
+    assert!(msg.contains(&format!("lsn={}", c.backup_lsn)));
+    // Цель не тронута
+    assert_eq!(read_meta(&dst)?.last_lsn, dst_lsn);
+
+    // --force — накатить всё равно
+    let opts = RestoreOptions {
+        verify: true,
+        force: true,
+        ..Default::default()
+    };
+    restore_from_reader_with(&dst, inc.as_slice(), &opts)?;
+    let db = Db::open_ro(&dst)?;
+    assert_eq!(db.get(b"b")?.as_deref(), Some(&b"2"[..]));
+    Ok(())
+}
+
+#[test]
+fn snapshot_restore_in_place_rollback_requires_force() -> Result<()> {
+    let root = unique_root("gen-snap");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"k", b"old")?;
+    }
+    let id = {
+        let db = Db::open_ro(&root)?;
+        SnapshotManager::create_persisted(&db, None, &[], None)?
+    };
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"k", b"new")?;
+    }
+
+    // Откат на месте перезаписал бы более новые данные
+    let err = restore_from_id(&root, &root, &id, true).unwrap_err();
+    assert_eq!(conflict(&err).map(|c| c.reason), Some("newer_target"));
+    {
+        let db = Db::open_ro(&root)?;
+        assert_eq!(db.get(b"k")?.as_deref(), Some(&b"new"[..]));
+    }
+
+    let opts = RestoreOptions {
+        verify: true,
+        force: true,
+        ..Default::default()
+    };
+    restore_from_id_with(&root, &root, &id, &opts)?;
+    let db = Db::open_ro(&root)?;
+    assert_eq!(db.get(b"k")?.as_deref(), Some(&b"old"[..]));
+
+    // Другая БД отвергается с обоими UUID
+    let other = unique_root("gen-other");
+    fs::create_dir_all(&other)?;
+    Db::init(&other, 4096, 8)?;
+    let err = restore_from_id(&root, &other, &id, true).unwrap_err();
+    let c = conflict(&err).expect("typed conflict");
+    assert_eq!(c.reason, "foreign_db");
+    assert_ne!(c.target_uuid, c.backup_uuid);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,102 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::backup::{backup_to_writer_with, restore_from_reader, BackupOptions};
+use QuiverDB::bloom::BloomSidecar;
+use QuiverDB::db::Db;
+use QuiverDB::snapstore::{read_manifest, restore_from_id, SnapshotManager};
+
+fn fill_with_fresh_bloom(root: &PathBuf) -> Result<Db> {
+    fs::create_dir_all(root)?;
+    Db::init(root, 4096, 8)?;
+    let mut db = Db::open(root)?;
+    for i in 0..50u32 {
+        db.put(format!("key-{:03}", i).as_bytes(), b"v")?;
+    }
+    let mut sc = BloomSidecar::open_or_create_for_db(&db, 4096, 6)?;
+    sc.rebuild_all(&db)?;
+    assert!(sc.is_fresh_for_db(&db));
+    Ok(db)
+}
+
+#[test]
+fn backup_stream_carries_fresh_bloom_sidecar() -> Result<()> {
+    let src = unique_root("sc-bk-src");
+    let db = fill_with_fresh_bloom(&src)?;
+
+    let opts = BackupOptions {
+        include_sidecars: true,
+        ..Default::default()
+    };
+    let mut archive = Vec::new();
+    let man = backup_to_writer_with(&db, &mut archive, &opts)?;
+    assert_eq!(man.sidecars, vec!["bloom.bin".to_string()]);
+
+    let dst = unique_root("sc-bk-dst");
+    let rep = restore_from_reader(&dst, archive.as_slice(), true)?;
+    assert_eq!(rep.sidecars, vec!["bloom.bin".to_string()]);
+    let restored = Db::open_ro(&dst)?;
+    let sc = BloomSidecar::open_ro(&dst)?;
+    assert!(
+        sc.is_fresh_for_db(&restored),
+        "restored bloom must be ready"
+    );
+    for i in 0..50u32 {
+        let k = format!("key-{:03}", i);
+        let b = restored
+            .dir
+            .bucket_of_key(k.as_bytes(), restored.pager.meta.hash_kind);
+        assert!(sc.test(b, k.as_bytes())?, "bloom false negative for {}", k);
+        assert_eq!(restored.get(k.as_bytes())?.as_deref(), Some(&b"v"[..]));
+    }
+    drop(restored);
+
+    // Несвежий Bloom не попадает в архив (и restore его не ставит)
+    BloomSidecar::open(&src)?.set_last_lsn(0)?;
+    let mut stale = Vec::new();
+    let man = backup_to_writer_with(&db, &mut stale, &opts)?;
+    assert!(man.sidecars.is_empty());
+    let dst2 = unique_root("sc-bk-dst2");
+    let rep = restore_from_reader(&dst2, stale.as_slice(), true)?;
+    assert!(rep.sidecars.is_empty());
+    assert!(!dst2.join("bloom.bin").exists());
+    Ok(())
+}
+
+#[test]
+fn persisted_snapshot_restores_ready_bloom() -> Result<()> {
+    let src = unique_root("sc-snap-src");
+    drop(fill_with_fresh_bloom(&src)?);
+
+    let ro = Db::open_ro(&src)?;
+    let plain = SnapshotManager::create_persisted(&ro, None, &[], None)?;
+    assert!(read_manifest(&src, &plain)?.sidecars.is_empty());
+    let id = SnapshotManager::create_persisted_with_sidecars(&ro, Some("warm"), &[], None)?;
+    let m = read_manifest(&src, &id)?;
+    assert_eq!(m.sidecars.len(), 1);
+    assert_eq!(m.sidecars[0].last_lsn, m.meta.lsn);
+    drop(ro);
+
+    let dst = unique_root("sc-snap-dst");
+    restore_from_id(&src, &dst, &id, true)?;
+    let restored = Db::open_ro(&dst)?;
+    assert!(BloomSidecar::open_ro(&dst)?.is_fresh_for_db(&restored));
+    assert_eq!(restored.get(b"key-007")?.as_deref(), Some(&b"v"[..]));
+    assert_eq!(restored.get(b"missing")?, None);
+    drop(restored);
+
+    // delete снимает ref и с объекта sidecar'а
+    SnapshotManager::delete_persisted(&src, &id)?;
+    SnapshotManager::delete_persisted(&src, &plain)?;
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,68 @@
+use anyhow::Result;
+use std::fs;
+
+use QuiverDB::bloom::BloomSidecar;
+use QuiverDB::db::Db;
+use QuiverDB::meta::set_last_lsn;
+
+/// RO refresh: без изменений — no-op; продвинутый meta.last_lsn подхватывается,
+/// bloom/keydir переоткрываются, и bloom со старым LSN больше не считается свежим.
+#[test]
+fn ro_refresh_picks_up_meta_advance() -> Result<()> {
+    let root = unique_root("ro-refresh");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 64 * 1024, 32)?;
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"alpha", b"1")?;
+        db.put(b"beta", b"2")?;
+    }
+
+    let mut ro = Db::open_ro(&root)?;
+    {
+        let mut sc = BloomSidecar::open_or_create_for_db(&ro, 4096, 6)?;
+        sc.rebuild_all(&ro)?;
+    }
+    let lsn0 = ro.pager.meta.last_lsn;
+
+    let rep = ro.refresh()?;
+    assert!(!rep.changed, "nothing advanced: {:?}", rep);
+    assert_eq!(rep.last_lsn_after, lsn0);
+
+    // Внешнее продвижение meta (как если бы каталог обновили под читателем)
+    set_last_lsn(&root, lsn0 + 5)?;
+    let rep = ro.refresh()?;
+    assert!(rep.changed);
+    assert_eq!(rep.last_lsn_before, lsn0);
+    assert_eq!(rep.last_lsn_after, lsn0 + 5);
+    assert_eq!(ro.pager.meta.last_lsn, lsn0 + 5);
+    assert!(rep.bloom_reloaded);
+    assert!(rep.keydir_rebuilt);
+
+    // bloom.bin построен при lsn0 — теперь stale; чтения корректны мимо bloom
+    let sc = BloomSidecar::open_ro(&root)?;
+    assert!(!sc.is_fresh_for_db(&ro));
+    assert_eq!(ro.get(b"alpha")?.as_deref(), Some(&b"1"[..]));
+    assert_eq!(ro.get(b"beta")?.as_deref(), Some(&b"2"[..]));
+    assert!(ro.get(b"missing")?.is_none());
+
+    // Повторный refresh без изменений — no-op
+    assert!(!ro.refresh()?.changed);
+    drop(ro);
+
+    // Writer: refresh всегда no-op
+    let mut w = Db::open(&root)?;
+    assert!(!w.refresh()?.changed);
+    Ok(())
+}
+
+// ---------- helpers ----------
+
+fn unique_root(prefix: &str) -> std::path::PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+
+fn sorted(mut v: Vec<(Vec<u8>, Vec<u8>)>) -> Vec<(Vec<u8>, Vec<u8>)> {
+    v.sort();
+    v
+}
+
+fn fill(db: &mut Db) -> Result<()> {
+    for i in 0..200u32 {
+        db.put(
+            format!("user:{:03}", i).as_bytes(),
+            format!("u{}", i).as_bytes(),
+        )?;
+        db.put(format!("item:{:03}", i).as_bytes(), b"x")?;
+    }
+    db.put(b"user:big", &vec![0xA5u8; 12_000])?;
+    for i in (0..200u32).step_by(7) {
+        db.del(format!("user:{:03}", i).as_bytes())?;
+    }
+    // Несколько версий одного ключа — побеждает новейшая
+    db.put(b"user:001", b"new")?;
+    Ok(())
+}
+
+#[test]
+fn writer_iterator_matches_materialized_scans() -> Result<()> {
+    let root = unique_root("scan-iter-writer");
+    Db::init(&root, 4096, 16)?;
+    let mut db = Db::open(&root)?;
+    fill(&mut db)?;
+
+    let it: Vec<_> = db.scan_prefix_iter(b"user:")?.collect::<Result<_>>()?;
+    assert_eq!(it.len(), 200 - 29 + 1);
+    assert_eq!(sorted(it.clone()), sorted(db.scan_prefix(b"user:")?));
+    assert!(it.iter().any(|(k, v)| k == b"user:001" && v == b"new"));
+    assert!(it.iter().all(|(k, _)| k != b"user:007"));
+
+    let all: Vec<_> = db.scan_iter()?.collect::<Result<_>>()?;
+    assert_eq!(sorted(all), sorted(db.scan_all()?));
+
+    // Ленивость: взять первые N без полного скана
+    let first: Vec<_> = db.scan_iter()?.take(3).collect::<Result<_>>()?;
+    assert_eq!(first.len(), 3);
+    Ok(())
+}
+
+#[test]
+fn reader_iterator_uses_keydir_and_matches_scan_prefix() -> Result<()> {
+    let root = unique_root("scan-iter-ro");
+    Db::init(&root, 4096, 16)?;
+    {
+        let mut w = Db::open(&root)?;
+        fill(&mut w)?;
+    }
+
+    let ro = Db::open_ro(&root)?;
+    let expected = sorted(ro.scan_prefix(b"user:")?);
+    assert_eq!(expected.len(), 200 - 29 + 1);
+    let got: Vec<_> = ro.scan_prefix_iter(b"user:")?.collect::<Result<_>>()?;
+    assert_eq!(sorted(got), expected);
+    assert_eq!(ro.scan_iter()?.count(), 2 * 200 - 29 + 1);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,131 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use QuiverDB::db::Db;
+use QuiverDB::page::{PAGE_MAGIC, PAGE_TYPE_OVERFLOW3};
+use QuiverDB::pager::Pager;
+
+fn fill(db: &mut Db) -> Result<()> {
+    for i in 0..120u32 {
+        db.put(format!("k:{:03}", i).as_bytes(), b"v")?;
+        db.put(format!("o:{:03}", i).as_bytes(), b"x")?;
+    }
+    // OVERFLOW-значение: keys-only скан не должен его читать
+    db.put(b"k:big", &vec![0x5Au8; 20_000])?;
+    for i in (0..120u32).step_by(5) {
+        db.del(format!("k:{:03}", i).as_bytes())?;
+    }
+    db.delete_prefix(b"o:1")?;
+    Ok(())
+}
+
+fn expected_keys(db: &Db, prefix: Option<&[u8]>) -> Result<Vec<Vec<u8>>> {
+    let pairs = match prefix {
+        Some(p) => db.scan_prefix(p)?,
+        None => db.scan_all()?,
+    };
+    let mut keys: Vec<Vec<u8>> = pairs.into_iter().map(|(k, _)| k).collect();
+    keys.sort();
+    Ok(keys)
+}
+
+fn sorted(mut v: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    v.sort();
+    v
+}
+
+#[test]
+fn writer_keys_only_matches_full_scan() -> Result<()> {
+    let root = unique_root("scan-keys-writer");
+    Db::init(&root, 4096, 16)?;
+    let mut db = Db::open(&root)?;
+    fill(&mut db)?;
+
+    let keys = sorted(db.scan_keys(Some(b"k:"))?);
+    assert_eq!(keys.len(), 120 - 24 + 1);
+    assert_eq!(keys, expected_keys(&db, Some(b"k:"))?);
+    assert!(keys.iter().any(|k| k == b"k:big"));
+    assert!(keys.iter().all(|k| k != b"k:005"));
+
+    let all = sorted(db.scan_keys(None)?);
+    assert_eq!(all, expected_keys(&db, None)?);
+    assert!(all.iter().all(|k| !k.starts_with(b"o:1")));
+
+    let it: Vec<_> = db.scan_keys_iter(Some(b"k:"))?.collect::<Result<_>>()?;
+    assert_eq!(sorted(it), keys);
+    assert_eq!(db.scan_keys_iter(None)?.take(4).count(), 4);
+    Ok(())
+}
+
+#[test]
+fn stream_and_reader_keys_only_match_full_scan() -> Result<()> {
+    let root = unique_root("scan-keys-ro");
+    Db::init(&root, 4096, 16)?;
+    {
+        let mut db = Db::open(&root)?;
+        fill(&mut db)?;
+        let mut stream_keys = Vec::new();
+        db.scan_keys_stream(None, |k| stream_keys.push(k.to_vec()))?;
+        assert_eq!(sorted(stream_keys), expected_keys(&db, None)?);
+    }
+
+    // Reader (keydir fast-path) — тот же набор ключей
+    let ro = Db::open_ro(&root)?;
+    let expected = expected_keys(&ro, Some(b"k:"))?;
+    assert_eq!(sorted(ro.scan_keys(Some(b"k:"))?), expected);
+    let it: Vec<_> = ro.scan_keys_iter(Some(b"k:"))?.collect::<Result<_>>()?;
+    assert_eq!(sorted(it), expected);
+    Ok(())
+}
+
+#[test]
+fn keys_only_does_not_read_overflow_chains() -> Result<()> {
+    let root = unique_root("scan-keys-ovf");
+    Db::init(&root, 4096, 16)?;
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"small", b"v")?;
+        db.put(b"big", &vec![0x5Au8; 20_000])?;
+    }
+    // Испортим все OVERFLOW-страницы: значение "big" больше не читается
+    {
+        let mut pager = Pager::open(&root)?;
+        let ps = pager.meta.page_size as usize;
+        let mut buf = vec![0u8; ps];
+        let mut wiped = 0;
+        for pid in 0..pager.meta.next_page_id {
+            pager.read_page_uncached(pid, &mut buf)?;
+            if &buf[0..4] == PAGE_MAGIC && LittleEndian::read_u16(&buf[6..8]) == PAGE_TYPE_OVERFLOW3
+            {
+                pager.write_page_raw(pid, &vec![0u8; ps])?;
+                wiped += 1;
+            }
+        }
+        assert!(wiped > 0);
+    }
+
+    let mut db = Db::open(&root)?;
+    assert!(db.scan_all().is_err());
+    assert_eq!(
+        sorted(db.scan_keys(None)?),
+        vec![b"big".to_vec(), b"small".to_vec()]
+    );
+    db.put(b"other", b"x")?;
+    drop(db);
+
+    let ro = Db::open_ro(&root)?;
+    let keys: Vec<_> = ro.scan_keys_iter(None)?.collect::<Result<_>>()?;
+    assert_eq!(sorted(keys).len(), 3);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,184 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use QuiverDB::db::{Db, ScanCursorInvalidError};
+use QuiverDB::metrics;
+
+// Метрики процесс-глобальные — тесты файла идут по очереди.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+const PAGE: u32 = 4096;
+
+type Pairs = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Все страницы скана с шагом limit: пары и число вызовов.
+fn collect_pages(db: &Db, prefix: Option<&[u8]>, limit: usize) -> Result<(Pairs, usize)> {
+    let mut out = Vec::new();
+    let mut cursor: Option<String> = None;
+    let mut calls = 0;
+    loop {
+        let (items, next) = db.scan_page(prefix, cursor.as_deref(), limit)?;
+        calls += 1;
+        assert!(items.len() <= limit);
+        if next.is_some() {
+            assert_eq!(items.len(), limit, "only the last page may be short");
+        }
+        out.extend(items);
+        match next {
+            Some(c) => cursor = Some(c),
+            None => return Ok((out, calls)),
+        }
+    }
+}
+
+#[test]
+fn pages_cover_scan_exactly_once() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("scanpage-cover");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PAGE, 8)?;
+    let mut db = Db::open(&root)?;
+    for i in 0..300 {
+        db.put(
+            format!("user:{:04}", i).as_bytes(),
+            format!("v{}", i).as_bytes(),
+        )?;
+    }
+    // Перезаписи и удаления — на странице курсора должны решаться как в обычном скане
+    for i in (0..300).step_by(3) {
+        db.put(format!("user:{:04}", i).as_bytes(), b"updated")?;
+    }
+    for i in (0..300).step_by(7) {
+        db.del(format!("user:{:04}", i).as_bytes())?;
+    }
+    db.put(b"other:1", b"x")?;
+
+    let expected: BTreeMap<Vec<u8>, Vec<u8>> = db.scan_prefix(b"user:")?.into_iter().collect();
+    let calls_before = metrics::snapshot().scan_page_calls;
+    for limit in [1usize, 7, 64, 1000] {
+        let (got, calls) = collect_pages(&db, Some(b"user:"), limit)?;
+        let keys: BTreeSet<&Vec<u8>> = got.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys.len(), got.len(), "duplicate keys with limit {}", limit);
+        let got: BTreeMap<Vec<u8>, Vec<u8>> = got.into_iter().collect();
+        assert_eq!(got, expected, "limit {}", limit);
+        assert!(calls >= expected.len().div_ceil(limit));
+    }
+    assert!(metrics::snapshot().scan_page_calls > calls_before);
+
+    // Без префикса — всё, включая other:1
+    let (all, _) = collect_pages(&db, None, 50)?;
+    assert_eq!(all.len(), expected.len() + 1);
+    Ok(())
+}
+
+#[test]
+fn cursor_survives_writes_compaction_and_sweep() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("scanpage-pin");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PAGE, 2)?;
+    let mut db = Db::open(&root)?;
+    // Значения крупнее страницы — OVERFLOW-цепочки, которые sweep освободил бы после компактации
+    let old = |i: usize| vec![b'a' + (i % 26) as u8; 6000];
+    let new = |i: usize| vec![b'A' + (i % 26) as u8; 6000];
+    for i in 0..40 {
+        db.put(format!("k{:03}", i).as_bytes(), &old(i))?;
+    }
+
+    let (mut got, mut cursor) = db.scan_page(None, None, 5)?;
+    assert_eq!(got.len(), 5);
+    assert!(cursor.is_some());
+
+    // Всё переписано, старые цепочки компактированы и подметены, освобождённые страницы
+    // переиспользованы новыми записями
+    for i in 0..40 {
+        db.put(format!("k{:03}", i).as_bytes(), &new(i))?;
+    }
+    db.compact_all()?;
+    db.sweep_orphan_overflow()?;
+    for i in 0..200 {
+        db.put(format!("x{:03}", i).as_bytes(), b"n")?;
+    }
+
+    while let Some(c) = cursor {
+        let (items, next) = db.scan_page(None, Some(&c), 5)?;
+        got.extend(items);
+        cursor = next;
+    }
+    let keys: BTreeSet<Vec<u8>> = got.iter().map(|(k, _)| k.clone()).collect();
+    // Записи после входа в закреплённый бакет в нём не видны; следующий бакет — по текущей голове
+    let hk = db.pager.meta.hash_kind;
+    let (c0, _) = db.scan_page(None, None, 1)?;
+    let pinned_bucket = db.dir.bucket_of_key(&c0[0].0, hk);
+    let later = (0..200)
+        .map(|i| format!("x{:03}", i))
+        .filter(|k| db.dir.bucket_of_key(k.as_bytes(), hk) > pinned_bucket)
+        .count();
+    assert_eq!(keys.iter().filter(|k| k[0] == b'x').count(), later);
+    assert_eq!(keys.len(), got.len(), "no key is returned twice");
+    for i in 0..40 {
+        assert!(keys.contains(format!("k{:03}", i).as_bytes()));
+    }
+    // Закреплённый бакет отдаёт состояние на момент входа в него, прочие — текущее
+    let mut old_seen = 0;
+    for (k, v) in got.iter().filter(|(k, _)| k[0] == b'k') {
+        let i: usize = std::str::from_utf8(&k[1..])?.parse()?;
+        if *v == old(i) {
+            old_seen += 1;
+        } else {
+            assert_eq!(*v, new(i), "key {}", i);
+        }
+    }
+    assert!(old_seen >= 5);
+    Ok(())
+}
+
+#[test]
+fn malformed_or_foreign_cursor_is_rejected() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("scanpage-bad");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PAGE, 4)?;
+    let mut db = Db::open(&root)?;
+    for i in 0..50 {
+        db.put(format!("a:{:03}", i).as_bytes(), b"1")?;
+        db.put(format!("b:{:03}", i).as_bytes(), b"2")?;
+    }
+    let (_, cursor) = db.scan_page(Some(b"a:"), None, 10)?;
+    let cursor = cursor.expect("more pages");
+
+    let invalid_before = metrics::snapshot().scan_cursor_invalid;
+    for (prefix, c) in [
+        (Some(&b"a:"[..]), "not-a-cursor"),
+        (Some(&b"a:"[..]), ""),
+        // Курсор другого префикса
+        (Some(&b"b:"[..]), cursor.as_str()),
+        (None, cursor.as_str()),
+    ] {
+        let err = db.scan_page(prefix, Some(c), 10).unwrap_err();
+        let e = err
+            .downcast_ref::<ScanCursorInvalidError>()
+            .expect("typed cursor error");
+        assert_eq!(e.path, root);
+    }
+    assert!(metrics::snapshot().scan_cursor_invalid >= invalid_before + 4);
+
+    // Тот же курсор повторно (ретрай HTTP-запроса) — та же страница
+    let (p1, n1) = db.scan_page(Some(b"a:"), Some(&cursor), 10)?;
+    let (p2, n2) = db.scan_page(Some(b"a:"), Some(&cursor), 10)?;
+    assert_eq!(p1, p2);
+    assert_eq!(n1, n2);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
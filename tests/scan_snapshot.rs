@@ -0,0 +1,82 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use QuiverDB::db::Db;
+
+/// Chain-скан RO-хэндла закрепляет головы на старте: если каталог продвинулся под
+/// читателем посреди скана (внешнее применение), результат — состояние на момент начала.
+#[test]
+fn chain_scan_is_point_in_time_when_directory_moves_mid_scan() -> Result<()> {
+    // chain-путь (без in-memory keydir)
+    std::env::set_var("P1_MEM_KEYDIR", "0");
+
+    let a = unique_root("scan-snap-a");
+    fs::create_dir_all(&a)?;
+    Db::init(&a, 4096, 8)?;
+    {
+        let mut db = Db::open(&a)?;
+        for i in 0..40u32 {
+            db.put(format!("k{:02}", i).as_bytes(), b"old")?;
+        }
+    }
+
+    // Та же БД, продвинутая дальше: новые значения + компактация (цепочки переписаны)
+    let b = unique_root("scan-snap-b");
+    copy_dir(&a, &b)?;
+    {
+        let mut db = Db::open(&b)?;
+        for i in 0..40u32 {
+            db.put(format!("k{:02}", i).as_bytes(), b"new")?;
+        }
+        db.put(b"extra", b"new")?;
+        db.compact_all()?;
+    }
+
+    let ro = Db::open_ro(&a)?;
+    assert!(!ro.has_mem_keydir());
+    let mut seen: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+    let mut moved = false;
+    ro.scan_stream(None, |k, v| {
+        if !moved {
+            // Каталог и сегменты продвигаются под читателем посреди скана
+            copy_dir(&b, &a).expect("advance directory");
+            moved = true;
+        }
+        seen.push((k.to_vec(), v.to_vec()));
+    })?;
+    assert!(moved);
+    assert_eq!(seen.len(), 40, "keys: {:?}", seen.len());
+    assert!(
+        seen.iter().all(|(_, v)| v == b"old"),
+        "scan mixed old and new states"
+    );
+
+    // Следующий скан видит новое состояние целиком
+    let all = ro.scan_all()?;
+    assert_eq!(all.len(), 41);
+    assert!(all.iter().all(|(_, v)| v == b"new"));
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for e in fs::read_dir(from)? {
+        let e = e?;
+        let name = e.file_name();
+        if name == "LOCK" || !e.file_type()?.is_file() {
+            continue;
+        }
+        fs::copy(e.path(), to.join(name))?;
+    }
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,129 @@
+use anyhow::Result;
+use std::fs::{self, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use QuiverDB::db::scrub::{scrub_alerts_path, scrub_step_at, ScrubOptions, ScrubState, Scrubber};
+use QuiverDB::db::Db;
+
+#[test]
+fn scrub_resume_and_alerts() -> Result<()> {
+    let root = unique_root("scrub");
+    fs::create_dir_all(&root)?;
+
+    let page_size = 4096;
+    Db::init(&root, page_size, 8)?;
+    {
+        let mut db = Db::open(&root)?;
+        for i in 0..32u32 {
+            db.put(format!("k{:03}", i).as_bytes(), b"value")?;
+        }
+    }
+
+    // Шаг по 10 страниц: курсор персистится между хэндлами
+    let total = {
+        let db = Db::open_ro(&root)?;
+        let total = db.pager.meta.next_page_id;
+        assert!(total > 10);
+        let opts = ScrubOptions {
+            max_pages: 10,
+            ..Default::default()
+        };
+        let mut s = Scrubber::new(&db, opts)?;
+        let r = s.run_step()?;
+        assert_eq!(r.pages_scanned, 10);
+        assert!(!r.pass_completed);
+        total
+    };
+    assert_eq!(ScrubState::load(&root)?.cursor, 10);
+    {
+        let db = Db::open_ro(&root)?;
+        let mut s = Scrubber::new(&db, ScrubOptions::default())?;
+        let r = s.run_step()?;
+        assert_eq!(r.pages_scanned, total - 10);
+        assert!(r.pass_completed);
+        assert_eq!(r.integrity_fail + r.io_fail, 0);
+    }
+    let st = ScrubState::load(&root)?;
+    assert_eq!(st.cursor, 0);
+    assert_eq!(st.passes_completed, 1);
+    assert!(st
+        .segments
+        .get(&1)
+        .map(|s| s.last_verified_at > 0)
+        .unwrap_or(false));
+
+    // Повредим байт в странице 1 → integrity alert
+    {
+        let seg = root.join("data-000001.p2seg");
+        let mut f = OpenOptions::new().read(true).write(true).open(&seg)?;
+        f.seek(SeekFrom::Start(page_size as u64 + 100))?;
+        f.write_all(&[0xAB, 0xCD])?;
+    }
+    {
+        let db = Db::open_ro(&root)?;
+        let mut s = Scrubber::new(&db, ScrubOptions::default())?;
+        let r = s.run_step()?;
+        assert!(r.pass_completed);
+        assert_eq!(r.integrity_fail, 1);
+        assert_eq!(r.alerts.len(), 1);
+        assert_eq!(r.alerts[0].page_id, 1);
+        assert_eq!(r.alerts[0].kind, "integrity");
+        assert_eq!(s.state().segments.get(&1).unwrap().failures, 1);
+    }
+    let alerts = fs::read_to_string(scrub_alerts_path(&root))?;
+    assert_eq!(alerts.lines().count(), 1);
+
+    Ok(())
+}
+
+/// scrub_step_at (как CLI) не держит shared LOCK: writer открывается посреди медленного шага.
+#[test]
+fn scrub_does_not_block_writer_open() -> Result<()> {
+    let root = unique_root("scrub-writer");
+    fs::create_dir_all(&root)?;
+
+    let page_size = 4096u64;
+    Db::init(&root, page_size as u32, 8)?;
+    let total = {
+        let mut db = Db::open(&root)?;
+        for i in 0..40u32 {
+            db.put(format!("k{:03}", i).as_bytes(), b"value")?;
+        }
+        db.pager.meta.next_page_id
+    };
+
+    // ~2 секунды на проход
+    let opts = ScrubOptions {
+        max_bytes_per_sec: page_size * total / 2,
+        ..Default::default()
+    };
+    let scrub_root = root.clone();
+    let h = std::thread::spawn(move || scrub_step_at(&scrub_root, opts, false));
+
+    std::thread::sleep(Duration::from_millis(300));
+    {
+        let mut db = Db::open(&root)?;
+        assert!(!h.is_finished(), "scrub step finished too early");
+        db.put(b"during", b"scrub")?;
+    }
+
+    let r = h.join().expect("scrub thread")?;
+    assert!(r.pass_completed);
+    assert_eq!(r.pages_total, total);
+    assert_eq!(r.integrity_fail + r.io_fail, 0, "{:?}", r.alerts);
+
+    let db = Db::open_ro(&root)?;
+    assert_eq!(db.get(b"during")?.as_deref(), Some(&b"scrub"[..]));
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,102 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::Db;
+use QuiverDB::metrics;
+use QuiverDB::pager::SEGMENT_SIZE;
+
+// Метрики процесс-глобальные — тесты файла идут по очереди.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+const PAGE: u32 = 65536;
+
+fn cfg(syncfs: bool) -> QuiverConfig {
+    QuiverConfig::from_env()
+        .with_data_fsync(true)
+        .with_seg_syncfs(syncfs)
+}
+
+/// Значение, OVERFLOW-цепочка которого пересекает границу первого сегмента.
+fn spanning_value() -> Vec<u8> {
+    (0..(SEGMENT_SIZE as usize + (1 << 20)))
+        .map(|i| (i % 251) as u8)
+        .collect()
+}
+
+#[test]
+fn multi_segment_batch_fsyncs_each_segment_once() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("segsync-each");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PAGE, 4)?;
+    let big = spanning_value();
+    {
+        let mut db = Db::open_with_config(&root, cfg(false))?;
+        assert!(!db.pager.seg_syncfs());
+        let before = metrics::snapshot();
+        db.put(b"big", &big)?;
+        let after = metrics::snapshot();
+        assert_eq!(after.seg_fsync_last_batch, 2);
+        assert!(after.seg_fsync_batches > before.seg_fsync_batches);
+        assert!(after.seg_fsync_calls >= before.seg_fsync_calls + 2);
+    }
+    let db = Db::open_ro(&root)?;
+    assert_eq!(db.get(b"big")?.as_deref(), Some(&big[..]));
+    Ok(())
+}
+
+#[test]
+fn syncfs_flushes_multi_segment_batch_with_one_call() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("segsync-fs");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PAGE, 4)?;
+    let big = spanning_value();
+    {
+        let mut db = Db::open_with_config(&root, cfg(true))?;
+        assert!(db.pager.seg_syncfs());
+        let before = metrics::snapshot();
+        db.put(b"big", &big)?;
+        let after = metrics::snapshot();
+        if cfg!(target_os = "linux") {
+            assert_eq!(after.seg_fsync_last_batch, 1);
+            assert!(after.seg_syncfs_calls > before.seg_syncfs_calls);
+        } else {
+            assert_eq!(after.seg_fsync_last_batch, 2);
+        }
+    }
+    let db = Db::open_ro(&root)?;
+    assert_eq!(db.get(b"big")?.as_deref(), Some(&big[..]));
+    Ok(())
+}
+
+#[test]
+fn single_segment_batch_uses_one_fsync() -> Result<()> {
+    let _g = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let root = unique_root("segsync-one");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    let mut db = Db::open_with_config(&root, cfg(true))?;
+    db.batch(|b| {
+        for i in 0..200u32 {
+            b.put(format!("k{}", i).as_bytes(), &[b'v'; 100])?;
+        }
+        Ok(())
+    })?;
+    // Один сегмент — syncfs не нужен, ровно один fsync
+    assert_eq!(metrics::snapshot().seg_fsync_last_batch, 1);
+    assert_eq!(db.get(b"k7")?.as_deref(), Some(&[b'v'; 100][..]));
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
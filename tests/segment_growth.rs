@@ -0,0 +1,113 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::Db;
+use QuiverDB::meta::read_meta;
+use QuiverDB::metrics::snapshot as metrics_snapshot;
+use QuiverDB::pager::{SegmentGrowth, SEGMENT_SIZE};
+
+fn seg_lens(root: &Path) -> Result<Vec<u64>> {
+    let mut v: Vec<(String, u64)> = fs::read_dir(root)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().ends_with(".p2seg"))
+        .map(|e| {
+            let len = e.metadata().map(|m| m.len()).unwrap_or(0);
+            (e.file_name().to_string_lossy().into_owned(), len)
+        })
+        .collect();
+    v.sort();
+    Ok(v.into_iter().map(|(_, l)| l).collect())
+}
+
+fn fill(root: &Path, cfg: QuiverConfig, n: u32) -> Result<()> {
+    let mut db = Db::open_with_config(root, cfg)?;
+    for i in 0..n {
+        db.put(format!("k{:05}", i).as_bytes(), &[7u8; 600])?;
+    }
+    Ok(())
+}
+
+fn fresh(name: &str) -> Result<PathBuf> {
+    let root = unique_root(name);
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    Ok(root)
+}
+
+#[test]
+fn chunk_policy_grows_in_whole_chunks() -> Result<()> {
+    let root = fresh("seg-chunk")?;
+    let chunk = 64 * 1024;
+    let cfg = QuiverConfig::from_env().with_segment_growth(SegmentGrowth::Chunk, chunk);
+    let before = metrics_snapshot();
+    fill(&root, cfg, 300)?;
+    let after = metrics_snapshot();
+
+    let used = read_meta(&root)?.next_page_id * 4096;
+    let lens = seg_lens(&root)?;
+    assert_eq!(lens.len(), 1);
+    assert_eq!(lens[0] % chunk, 0, "len {} is not chunk-aligned", lens[0]);
+    assert!(lens[0] >= used && lens[0] < used + chunk);
+    assert!(after.seg_grow_ops > before.seg_grow_ops);
+    assert!(after.seg_grow_bytes >= before.seg_grow_bytes + lens[0]);
+
+    let db = Db::open_ro(&root)?;
+    assert_eq!(db.get(b"k00123")?.as_deref(), Some(&[7u8; 600][..]));
+    Ok(())
+}
+
+#[test]
+fn full_policy_preallocates_whole_segment() -> Result<()> {
+    let root = fresh("seg-full")?;
+    let cfg = QuiverConfig::from_env().with_segment_growth(SegmentGrowth::Full, 0);
+    fill(&root, cfg, 50)?;
+    assert_eq!(seg_lens(&root)?, vec![SEGMENT_SIZE]);
+    // Логическое число страниц не меняется — только длина файла
+    assert!(read_meta(&root)?.next_page_id * 4096 < SEGMENT_SIZE);
+    Ok(())
+}
+
+#[test]
+fn exact_policy_keeps_segments_tight() -> Result<()> {
+    let root = fresh("seg-exact")?;
+    let cfg = QuiverConfig::from_env().with_segment_growth(SegmentGrowth::Exact, 0);
+    fill(&root, cfg, 50)?;
+    assert_eq!(
+        seg_lens(&root)?,
+        vec![read_meta(&root)?.next_page_id * 4096]
+    );
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn fallocate_reserves_blocks_instead_of_sparse_growth() -> Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let root = fresh("seg-falloc")?;
+    let cfg = QuiverConfig::from_env()
+        .with_segment_growth(SegmentGrowth::Chunk, 1024 * 1024)
+        .with_segment_fallocate(true);
+    fill(&root, cfg, 20)?;
+    let seg = fs::read_dir(&root)?
+        .filter_map(|e| e.ok())
+        .find(|e| e.file_name().to_string_lossy().ends_with(".p2seg"))
+        .expect("segment");
+    let md = seg.metadata()?;
+    assert_eq!(md.len(), 1024 * 1024);
+    // ФС без fallocate — fallback на set_len (учтён в метриках), блоки не гарантированы
+    if metrics_snapshot().seg_fallocate_fallbacks == 0 {
+        assert!(md.blocks() * 512 >= md.len(), "segment is sparse");
+    }
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
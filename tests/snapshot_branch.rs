@@ -0,0 +1,161 @@
+use anyhow::Result;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use QuiverDB::db::clone::{clone_live, CloneOptions};
+use QuiverDB::db::Db;
+use QuiverDB::metrics::snapshot as metrics_snapshot;
+use QuiverDB::snapstore::branch::detach_branch;
+use QuiverDB::snapstore::{create_branch, read_manifest, SnapStore, SnapshotManager, BRANCH_FILE};
+
+/// Занятые блоки сегментов (сегменты ветки разреженные).
+fn seg_bytes(root: &Path) -> Result<u64> {
+    let mut total = 0;
+    for e in fs::read_dir(root)? {
+        let e = e?;
+        if e.file_name().to_string_lossy().ends_with(".p2seg") {
+            total += e.metadata()?.blocks() * 512;
+        }
+    }
+    Ok(total)
+}
+
+/// Источник со снапшотом; после снапшота источник меняется (ветка этого не видит).
+fn source_with_snapshot(name: &str) -> Result<(PathBuf, String)> {
+    let src = unique_root(name);
+    fs::create_dir_all(&src)?;
+    Db::init(&src, 4096, 16)?;
+    {
+        let mut db = Db::open(&src)?;
+        for i in 0..200u32 {
+            db.put(
+                format!("k{:04}", i).as_bytes(),
+                format!("v{}", i).as_bytes(),
+            )?;
+        }
+        db.put(b"big", &vec![0xC3u8; 20 * 1024])?;
+    }
+    let id = {
+        let db = Db::open_ro(&src)?;
+        SnapshotManager::create_persisted(&db, Some("prod"), &[], None)?
+    };
+    {
+        let mut db = Db::open(&src)?;
+        db.put(b"k0001", b"after-snapshot")?;
+    }
+    Ok((src, id))
+}
+
+#[test]
+fn branch_reads_snapshot_pages_and_writes_locally() -> Result<()> {
+    let (src, id) = source_with_snapshot("branch-cow-src")?;
+    let dst = unique_root("branch-cow-dst");
+
+    let info = create_branch(&src, &dst, &id)?;
+    assert_eq!(info.snapshot_id, id);
+    assert!(info.snapshot_pages > 0);
+    assert!(dst.join(BRANCH_FILE).exists());
+    // Страницы не копируются
+    assert_eq!(seg_bytes(&dst)?, 0);
+
+    let before = metrics_snapshot().branch_page_fetches;
+    {
+        let mut db = Db::open(&dst)?;
+        assert_eq!(db.branch_info().map(|b| b.base_lsn), Some(info.base_lsn));
+        assert_eq!(db.get(b"k0001")?.as_deref(), Some(&b"v1"[..]));
+        assert_eq!(
+            db.get(b"big")?.as_deref(),
+            Some(&vec![0xC3u8; 20 * 1024][..])
+        );
+        assert!(metrics_snapshot().branch_page_fetches > before);
+
+        db.put(b"k0002", b"branch")?;
+        db.put(b"only-in-branch", b"x")?;
+        db.del(b"k0003")?;
+    }
+    // Записи ветки — в её сегментах; прочие страницы по-прежнему из снапшота
+    assert!(seg_bytes(&dst)? > 0);
+    assert!(seg_bytes(&dst)? < info.snapshot_pages * 4096);
+    {
+        let db = Db::open_ro(&dst)?;
+        assert_eq!(db.get(b"k0002")?.as_deref(), Some(&b"branch"[..]));
+        assert_eq!(db.get(b"only-in-branch")?.as_deref(), Some(&b"x"[..]));
+        assert_eq!(db.get(b"k0003")?, None);
+        assert_eq!(db.get(b"k0150")?.as_deref(), Some(&b"v150"[..]));
+    }
+    // Источник не тронут
+    let db = Db::open_ro(&src)?;
+    assert_eq!(db.get(b"k0001")?.as_deref(), Some(&b"after-snapshot"[..]));
+    assert_eq!(db.get(b"k0002")?.as_deref(), Some(&b"v2"[..]));
+    assert_eq!(db.get(b"only-in-branch")?, None);
+    Ok(())
+}
+
+#[test]
+fn branch_pins_snapshot_until_detach() -> Result<()> {
+    let (src, id) = source_with_snapshot("branch-pin-src")?;
+    let dst = unique_root("branch-pin-dst");
+    let hashes: Vec<String> = read_manifest(&src, &id)?
+        .objects
+        .into_iter()
+        .map(|o| o.hash_hex)
+        .collect();
+    create_branch(&src, &dst, &id)?;
+
+    // Удаление снапшота в источнике не ломает ветку
+    SnapshotManager::delete_persisted(&src, &id)?;
+    let ss = SnapStore::open_or_create(&src)?;
+    assert!(hashes.iter().all(|h| ss.has(h)));
+
+    let rep = {
+        let mut db = Db::open(&dst)?;
+        db.put(b"k0010", b"local")?;
+        assert_eq!(db.get(b"k0100")?.as_deref(), Some(&b"v100"[..]));
+        let rep = detach_branch(&mut db)?;
+        assert!(db.branch_info().is_none());
+        rep
+    };
+    assert_eq!(rep.snapshot_id, id);
+    assert!(rep.materialized_pages > 0);
+    assert_eq!(rep.materialized_pages + rep.local_pages, rep.snapshot_pages);
+    assert!(!dst.join(BRANCH_FILE).exists());
+    // Закрепление снято: объекты удалённого снапшота ушли из SnapStore
+    assert!(hashes.iter().all(|h| !ss.has(h)));
+
+    let db = Db::open_ro(&dst)?;
+    assert!(db.branch_info().is_none());
+    assert_eq!(db.get(b"k0010")?.as_deref(), Some(&b"local"[..]));
+    assert_eq!(db.get(b"k0100")?.as_deref(), Some(&b"v100"[..]));
+    assert_eq!(
+        db.get(b"big")?.as_deref(),
+        Some(&vec![0xC3u8; 20 * 1024][..])
+    );
+    Ok(())
+}
+
+#[test]
+fn branch_refuses_existing_db_and_live_clone() -> Result<()> {
+    let (src, id) = source_with_snapshot("branch-guard-src")?;
+    let err = create_branch(&src, &src, &id).unwrap_err();
+    assert!(
+        format!("{:#}", err).contains("already holds a database"),
+        "{:#}",
+        err
+    );
+
+    let dst = unique_root("branch-guard-dst");
+    create_branch(&src, &dst, &id)?;
+    let clone_dst = unique_root("branch-guard-clone");
+    let err = clone_live(&dst, &clone_dst, &CloneOptions::default()).unwrap_err();
+    assert!(format!("{:#}", err).contains("branch-detach"), "{:#}", err);
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,146 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+use QuiverDB::free::FreeList;
+use QuiverDB::snapstore::{
+    active_snapshot_pins, restore_from_id, SnapshotManager, SnapshotPin, SnapshotPinGuard,
+    SNAPSHOT_PINS_DIR,
+};
+
+const PAGE: u32 = 4096;
+
+// Значения крупнее страницы — OVERFLOW-цепочки, которые vacuum освобождает после перезаписи
+fn old(i: usize) -> Vec<u8> {
+    vec![b'a' + (i % 26) as u8; 6000]
+}
+fn new(i: usize) -> Vec<u8> {
+    vec![b'A' + (i % 26) as u8; 6000]
+}
+
+fn fill(root: &PathBuf, n: usize) -> Result<Db> {
+    fs::create_dir_all(root)?;
+    Db::init(root, PAGE, 4)?;
+    let mut db = Db::open(root)?;
+    for i in 0..n {
+        db.put(format!("k{:03}", i).as_bytes(), &old(i))?;
+    }
+    Ok(db)
+}
+
+fn heads(db: &Db) -> Result<Vec<u64>> {
+    (0..db.dir.bucket_count).map(|b| db.dir.head(b)).collect()
+}
+
+#[test]
+fn vacuum_keeps_pages_of_pinned_snapshot_heads() -> Result<()> {
+    let root = unique_root("snappin-keep");
+    let mut db = fill(&root, 20)?;
+    let before_pages = db.pager.meta.next_page_id;
+
+    // Создатель снапшота (как будто в другом процессе) снял головы
+    let mut pin = SnapshotPinGuard::acquire(&root, "pin-keep")?;
+    pin.set_heads(db.pager.meta.last_lsn, heads(&db)?)?;
+    assert_eq!(active_snapshot_pins(&root)?.len(), 1);
+
+    for i in 0..20 {
+        db.put(format!("k{:03}", i).as_bytes(), &new(i))?;
+    }
+    let v = db.vacuum_all()?;
+    assert_eq!(v.overflow_pages_freed, 0, "pinned chains must not be freed");
+    let rep = db.alloc_check_fix()?;
+    let free: HashSet<u64> = FreeList::open(&root)?.list()?.into_iter().collect();
+    for pid in 0..before_pages {
+        assert!(
+            !free.contains(&pid),
+            "page {} of the pinned snapshot freed",
+            pid
+        );
+    }
+    assert!(rep.fix.is_some());
+
+    // Снапшот закончен — следующий vacuum освобождает старые цепочки
+    drop(pin);
+    assert!(active_snapshot_pins(&root)?.is_empty());
+    let v = db.vacuum_all()?;
+    assert!(v.overflow_pages_freed > 0);
+    for i in 0..20 {
+        assert_eq!(db.get(format!("k{:03}", i).as_bytes())?, Some(new(i)));
+    }
+    Ok(())
+}
+
+#[test]
+fn pending_pin_blocks_sweep_and_alloc_fix() -> Result<()> {
+    let root = unique_root("snappin-pending");
+    let mut db = fill(&root, 10)?;
+    for i in 0..10 {
+        db.put(format!("k{:03}", i).as_bytes(), &new(i))?;
+    }
+    db.compact_all()?;
+
+    {
+        // Головы ещё не сняты: освобождать нельзя ничего
+        let _pin = SnapshotPinGuard::acquire(&root, "pin-pending")?;
+        assert_eq!(db.sweep_orphan_overflow()?, 0);
+        let err = db.alloc_check_fix().expect_err("alloc fix must refuse");
+        assert!(err.to_string().contains("pin-pending"), "{}", err);
+    }
+    assert!(db.sweep_orphan_overflow()? > 0);
+    db.alloc_check_fix()?;
+    Ok(())
+}
+
+#[test]
+fn persisted_snapshot_unpins_and_expired_pins_are_ignored() -> Result<()> {
+    let root = unique_root("snappin-persist");
+    let mut db = fill(&root, 12)?;
+    let id = SnapshotManager::create_persisted(&db, Some("pins"), &[], None)?;
+    assert!(
+        active_snapshot_pins(&root)?.is_empty(),
+        "pin must be released"
+    );
+
+    // Брошенный pin упавшего создателя (аренда истекла)
+    let stale = SnapshotPin {
+        id: "stale".into(),
+        process_id: 1,
+        lsn: 0,
+        heads: None,
+        created_unix_ms: 1,
+        expires_unix_ms: 2,
+    };
+    let pin_path = root.join(SNAPSHOT_PINS_DIR).join("stale.json");
+    fs::write(&pin_path, serde_json::to_vec(&stale)?)?;
+
+    for i in 0..12 {
+        db.put(format!("k{:03}", i).as_bytes(), &new(i))?;
+    }
+    assert!(db.vacuum_all()?.overflow_pages_freed > 0);
+    assert!(!pin_path.exists(), "expired pin must be removed");
+    for i in 0..50 {
+        db.put(format!("x{:03}", i).as_bytes(), &new(i))?;
+    }
+    drop(db);
+
+    // Снапшот самодостаточен: старые значения после переиспользования страниц
+    let dst = unique_root("snappin-persist-dst");
+    fs::create_dir_all(&dst)?;
+    restore_from_id(&root, &dst, &id, true)?;
+    let r = Db::open_ro(&dst)?;
+    for i in 0..12 {
+        assert_eq!(r.get(format!("k{:03}", i).as_bytes())?, Some(old(i)));
+    }
+    assert_eq!(r.get(b"x000")?, None);
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,187 @@
+use anyhow::Result;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+use QuiverDB::meta::CODEC_NONE;
+use QuiverDB::snapstore::{
+    read_manifest, verify_manifest_objects, verify_snapshot, write_manifest, ObjectSource,
+    SnapStore, SnapshotManager, VerifyOptions,
+};
+
+fn make_snapshot(prefix: &str) -> Result<(PathBuf, String)> {
+    let root = unique_root(prefix);
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    {
+        let mut db = Db::open(&root)?;
+        for i in 0..40u32 {
+            db.put(format!("key{:03}", i).as_bytes(), &[b'v'; 200])?;
+        }
+    }
+    let db = Db::open_ro(&root)?;
+    let id = SnapshotManager::create_persisted(&db, None, &[], None)?;
+    Ok((root, id))
+}
+
+fn object_path(root: &std::path::Path, hash: &str) -> PathBuf {
+    root.join(".snapstore")
+        .join("objects")
+        .join(&hash[0..2])
+        .join(&hash[2..])
+}
+
+const FULL: VerifyOptions = VerifyOptions {
+    sample: 0,
+    full: true,
+};
+
+#[test]
+fn manifest_records_object_sizes_codec_and_checksum() -> Result<()> {
+    let (root, id) = make_snapshot("sv-fresh")?;
+    let m = read_manifest(&root, &id)?;
+    assert!(!m.objects.is_empty());
+    for o in &m.objects {
+        assert_eq!(o.stored_bytes, o.bytes);
+        assert_eq!(o.codec, CODEC_NONE);
+    }
+    assert_eq!(m.checksum_matches(), Some(true));
+
+    let rep = verify_snapshot(&root, &id, &FULL)?;
+    assert!(rep.ok, "{:?}", rep);
+    assert_eq!(rep.checksum, "ok");
+    assert_eq!(rep.hash_checked, rep.unique_objects);
+
+    // Правка манифеста без пересчёта контрольной суммы
+    let mut edited = m.clone();
+    edited.heads[0].head_pid += 1;
+    write_manifest(&root, &edited)?;
+    let rep = verify_snapshot(&root, &id, &VerifyOptions::default())?;
+    assert_eq!(rep.checksum, "mismatch");
+    assert!(!rep.ok);
+
+    // Манифест старого формата: нет checksum_hex/stored_bytes/codec
+    let mut old: serde_json::Value = serde_json::to_value(&m)?;
+    old.as_object_mut().unwrap().remove("checksum_hex");
+    for o in old["objects"].as_array_mut().unwrap() {
+        let o = o.as_object_mut().unwrap();
+        o.remove("stored_bytes");
+        o.remove("codec");
+    }
+    let old: QuiverDB::snapstore::SnapshotManifestV2 = serde_json::from_value(old)?;
+    assert_eq!(old.checksum_matches(), None);
+    let ss = SnapStore::open_or_create(&root)?;
+    let rep = verify_manifest_objects(&old, &ss, &FULL)?;
+    assert_eq!(rep.checksum, "absent");
+    assert!(rep.ok, "{:?}", rep);
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn size_check_and_hash_spot_check_find_damaged_objects() -> Result<()> {
+    let (root, id) = make_snapshot("sv-damage")?;
+    let m = read_manifest(&root, &id)?;
+    let mut hashes: Vec<String> = m.objects.iter().map(|o| o.hash_hex.clone()).collect();
+    hashes.sort();
+    hashes.dedup();
+    assert!(
+        hashes.len() >= 3,
+        "need distinct objects, got {}",
+        hashes.len()
+    );
+
+    // 1) то же содержимое по длине, но другие байты — HEAD не видит, SHA-256 видит
+    let p0 = object_path(&root, &hashes[0]);
+    let mut bytes = fs::read(&p0)?;
+    bytes[100] ^= 0xFF;
+    fs::write(&p0, &bytes)?;
+    // 2) усечённый объект
+    let p1 = object_path(&root, &hashes[1]);
+    let len = fs::metadata(&p1)?.len();
+    fs::OpenOptions::new()
+        .write(true)
+        .open(&p1)?
+        .set_len(len / 2)?;
+    // 3) пропавший объект
+    fs::remove_file(object_path(&root, &hashes[2]))?;
+
+    let sizes_only = verify_snapshot(
+        &root,
+        &id,
+        &VerifyOptions {
+            sample: 0,
+            full: false,
+        },
+    )?;
+    assert_eq!(sizes_only.missing, vec![hashes[2].clone()]);
+    assert_eq!(sizes_only.size_mismatch, vec![hashes[1].clone()]);
+    assert_eq!(sizes_only.hash_checked, 0);
+    assert!(!sizes_only.ok);
+
+    let full = verify_snapshot(&root, &id, &FULL)?;
+    assert_eq!(full.hash_mismatch, vec![hashes[0].clone()]);
+    assert_eq!(full.hash_checked, full.unique_objects - 2);
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+/// Удалённое хранилище: HEAD для всех объектов, GET — только для выборки.
+struct CountingStore {
+    objects: HashMap<String, Vec<u8>>,
+    fetches: Cell<u64>,
+}
+
+impl ObjectSource for CountingStore {
+    fn head(&self, hash_hex: &str) -> Result<Option<u64>> {
+        Ok(self.objects.get(hash_hex).map(|b| b.len() as u64))
+    }
+
+    fn fetch(&self, hash_hex: &str) -> Result<Option<Vec<u8>>> {
+        self.fetches.set(self.fetches.get() + 1);
+        Ok(self.objects.get(hash_hex).cloned())
+    }
+}
+
+#[test]
+fn custom_object_source_downloads_only_the_sample() -> Result<()> {
+    let (root, id) = make_snapshot("sv-remote")?;
+    let m = read_manifest(&root, &id)?;
+    let ss = SnapStore::open_or_create(&root)?;
+    let mut objects = HashMap::new();
+    for o in &m.objects {
+        objects.insert(o.hash_hex.clone(), ss.get(&o.hash_hex)?.unwrap());
+    }
+    let remote = CountingStore {
+        objects,
+        fetches: Cell::new(0),
+    };
+
+    let rep = verify_manifest_objects(
+        &m,
+        &remote,
+        &VerifyOptions {
+            sample: 2,
+            full: false,
+        },
+    )?;
+    assert!(rep.ok, "{:?}", rep);
+    assert_eq!(rep.hash_checked, 2);
+    assert_eq!(remote.fetches.get(), 2);
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
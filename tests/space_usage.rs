@@ -0,0 +1,113 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::{Db, SpaceUsage};
+use QuiverDB::snapstore::SnapshotManager;
+
+fn pages_accounted(u: &SpaceUsage) -> u64 {
+    u.kv_pages
+        + u.overflow_live_pages
+        + u.overflow_dead_pages
+        + u.overflow_orphan_pages
+        + u.free_pages
+        + u.unreachable_pages
+}
+
+#[test]
+fn du_splits_live_dead_and_overflow() -> Result<()> {
+    let root = unique_root("du-split");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    {
+        let mut db = Db::open(&root)?;
+        for i in 0..100u32 {
+            db.put(format!("k{:03}", i).as_bytes(), b"first")?;
+        }
+        // 20 перезаписей, 10 удалений, 3 больших значения (OVERFLOW)
+        for i in 0..20u32 {
+            db.put(format!("k{:03}", i).as_bytes(), b"second")?;
+        }
+        for i in 90..100u32 {
+            db.del(format!("k{:03}", i).as_bytes())?;
+        }
+        for i in 0..3u32 {
+            db.put(format!("big{}", i).as_bytes(), &vec![9u8; 20_000])?;
+        }
+    }
+
+    let u = Db::open_ro(&root)?.space_usage(3)?;
+    assert_eq!(u.live_keys, 93);
+    // 20 старых версий + 10 старых под tombstone'ами + 10 tombstone'ов
+    assert_eq!(u.dead_records, 40);
+    assert!(u.live_bytes > u.dead_bytes);
+    assert!(u.overflow_live_pages >= 15, "{}", u.overflow_live_pages);
+    assert_eq!(pages_accounted(&u), u.pages_total);
+    assert!(u.wal_bytes > 0);
+    assert!(u.segments_bytes >= u.pages_total * 4096);
+    assert!(u.total_bytes >= u.segments_bytes + u.wal_bytes);
+    assert_eq!(u.top_buckets.len(), 3);
+    assert!(u
+        .top_buckets
+        .windows(2)
+        .all(|w| w[0].total_bytes >= w[1].total_bytes));
+
+    // После компактации мёртвых записей не остаётся
+    {
+        let mut db = Db::open(&root)?;
+        db.vacuum_all()?;
+    }
+    let after = Db::open_ro(&root)?.space_usage(0)?;
+    assert_eq!(after.live_keys, 93);
+    assert_eq!(after.dead_records, 0);
+    assert_eq!(after.overflow_dead_pages, 0);
+    assert_eq!(after.overflow_orphan_pages, 0);
+    assert!(after.top_buckets.is_empty());
+    assert_eq!(pages_accounted(&after), after.pages_total);
+    Ok(())
+}
+
+#[test]
+fn du_reports_snapstore_and_json_shape() -> Result<()> {
+    let root = unique_root("du-snap");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 4)?;
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"a", b"1")?;
+    }
+    {
+        let db = Db::open_ro(&root)?;
+        assert_eq!(db.space_usage(10)?.snapstore.snapshots, 0);
+        SnapshotManager::create_persisted(&db, None, &[], None)?;
+    }
+    let u = Db::open_ro(&root)?.space_usage(10)?;
+    assert_eq!(u.snapstore.snapshots, 1);
+    assert!(u.snapstore.objects > 0);
+    assert!(u.snapstore.total_bytes >= u.snapstore.objects_bytes + u.snapstore.manifests_bytes);
+    assert!(u.total_bytes >= u.snapstore.total_bytes);
+
+    let v: serde_json::Value = serde_json::to_value(&u)?;
+    for k in [
+        "live_bytes",
+        "dead_bytes",
+        "overflow_live_pages",
+        "free_pages",
+        "wal_bytes",
+        "bloom_bytes",
+        "snapstore",
+        "top_buckets",
+    ] {
+        assert!(v.get(k).is_some(), "missing {}", k);
+    }
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
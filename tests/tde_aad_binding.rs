@@ -0,0 +1,125 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use QuiverDB::config::DbBuilder;
+use QuiverDB::crypto::StaticKeyProvider;
+use QuiverDB::db::Db;
+use QuiverDB::meta::{feature_names, read_meta, FEATURE_TDE_AAD_V2};
+use QuiverDB::pager::cache::page_cache_clear;
+
+const PS: usize = 4096;
+
+fn open_tde(root: &Path, aad_v2: bool) -> Result<Db> {
+    let cfg = DbBuilder::from_default()
+        .tde_enabled(true)
+        .tde_kid(Some("k1"))
+        .tde_key_provider(Arc::new(StaticKeyProvider::new("k1", [0x5a; 32])))
+        .tde_aad_v2(aad_v2)
+        .build();
+    Db::open_with_config(root, cfg)
+}
+
+fn seg(root: &Path) -> PathBuf {
+    root.join("data-000001.p2seg")
+}
+
+/// Две БД с одним ключом и одинаковой историей: страница 0 с тем же (page_id, lsn) в обеих.
+fn twin_dbs(tag: &str, aad_v2: bool) -> Result<(PathBuf, PathBuf)> {
+    let a = unique_root(&format!("{}-a", tag));
+    let b = unique_root(&format!("{}-b", tag));
+    for (root, val) in [(&a, b"value-A"), (&b, b"value-B")] {
+        fs::create_dir_all(root)?;
+        Db::init(root, PS as u32, 1)?;
+        let mut db = open_tde(root, aad_v2)?;
+        db.put(b"key", val)?;
+    }
+    Ok((a, b))
+}
+
+/// Переписать страницу 0 БД `dst` образом страницы 0 из `src`.
+fn transplant_page0(src: &Path, dst: &Path) -> Result<()> {
+    let img = fs::read(seg(src))?;
+    let mut d = fs::read(seg(dst))?;
+    d[..PS].copy_from_slice(&img[..PS]);
+    fs::write(seg(dst), d)?;
+    page_cache_clear();
+    Ok(())
+}
+
+#[test]
+fn aad_v2_rejects_page_from_another_db_with_same_key() -> Result<()> {
+    // AAD v1: ключ + nonce(page_id, lsn) совпадают — чужая страница проходит проверку
+    let (a, b) = twin_dbs("aad-v1", false)?;
+    assert_eq!(read_meta(&b)?.flags & FEATURE_TDE_AAD_V2, 0);
+    transplant_page0(&a, &b)?;
+    {
+        let db = open_tde(&b, false)?;
+        assert_eq!(db.get(b"key")?.as_deref(), Some(&b"value-A"[..]));
+    }
+
+    // AAD v2: в теге db_uuid — подмена обнаруживается
+    let (a2, b2) = twin_dbs("aad-v2", true)?;
+    let (ma, mb) = (read_meta(&a2)?, read_meta(&b2)?);
+    assert_ne!(mb.flags & FEATURE_TDE_AAD_V2, 0);
+    assert!(feature_names(mb.flags).contains(&"tde_aad_v2".to_string()));
+    assert_ne!(ma.db_uuid, mb.db_uuid);
+    assert_ne!(mb.db_uuid, [0u8; 16]);
+    {
+        let db = open_tde(&b2, true)?;
+        assert_eq!(db.get(b"key")?.as_deref(), Some(&b"value-B"[..]));
+    }
+    transplant_page0(&a2, &b2)?;
+    {
+        let db = open_tde(&b2, true)?;
+        assert!(
+            db.get(b"key").is_err(),
+            "foreign page must fail AEAD verify"
+        );
+    }
+
+    for r in [a, b, a2, b2] {
+        let _ = fs::remove_dir_all(r);
+    }
+    Ok(())
+}
+
+#[test]
+fn aad_v2_enabled_on_legacy_tde_db_keeps_old_pages_readable() -> Result<()> {
+    let root = unique_root("aad-upgrade");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PS as u32, 4)?;
+    {
+        let mut db = open_tde(&root, false)?;
+        db.put(b"old", b"v1")?;
+    }
+    let before = read_meta(&root)?;
+    assert_eq!(before.flags & FEATURE_TDE_AAD_V2, 0);
+
+    {
+        let mut db = open_tde(&root, true)?;
+        db.put(b"new", b"v2")?;
+    }
+    let m = read_meta(&root)?;
+    assert_ne!(m.flags & FEATURE_TDE_AAD_V2, 0);
+    assert_eq!(m.tde_aad_since_lsn, before.last_lsn + 1);
+
+    page_cache_clear();
+    let db = open_tde(&root, true)?;
+    assert_eq!(db.get(b"old")?.as_deref(), Some(&b"v1"[..]));
+    assert_eq!(db.get(b"new")?.as_deref(), Some(&b"v2"[..]));
+    drop(db);
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
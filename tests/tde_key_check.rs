@@ -0,0 +1,128 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use QuiverDB::config::DbBuilder;
+use QuiverDB::crypto::{KeyJournal, StaticKeyProvider, TdeKeyError};
+use QuiverDB::db::Db;
+use QuiverDB::meta::read_meta;
+use QuiverDB::pager::cache::page_cache_clear;
+
+fn cfg(kid: &str, key: u8) -> QuiverDB::config::QuiverConfig {
+    DbBuilder::from_default()
+        .tde_enabled(true)
+        .tde_kid(Some(kid))
+        .tde_key_provider(Arc::new(StaticKeyProvider::new(kid, [key; 32])))
+        .build()
+}
+
+fn init_tde(root: &Path) -> Result<()> {
+    fs::create_dir_all(root)?;
+    Db::init(root, 4096, 16)?;
+    let mut db = Db::open_with_config(root, cfg("k1", 0x11))?;
+    db.put(b"alpha", b"1")?;
+    Ok(())
+}
+
+fn key_error(e: &anyhow::Error) -> Option<&TdeKeyError> {
+    e.chain().find_map(|c| c.downcast_ref::<TdeKeyError>())
+}
+
+#[test]
+fn right_key_opens_and_check_block_is_sealed() -> Result<()> {
+    let root = unique_root("tde-kchk-ok");
+    init_tde(&root)?;
+
+    let m = read_meta(&root)?;
+    assert_eq!(m.tde_check_kid, "k1");
+    assert!(!m.tde_check.is_empty());
+
+    page_cache_clear();
+    let db = Db::open_ro_with_config(&root, cfg("k1", 0x11))?;
+    assert_eq!(db.get(b"alpha")?.as_deref(), Some(&b"1"[..]));
+    drop(db);
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn wrong_or_missing_key_fails_at_open() -> Result<()> {
+    let root = unique_root("tde-kchk-bad");
+    init_tde(&root)?;
+    page_cache_clear();
+
+    // Тот же KID, другой ключ — отказ до чтения страниц (writer и RO)
+    for ro in [false, true] {
+        let r = if ro {
+            Db::open_ro_with_config(&root, cfg("k1", 0x22))
+        } else {
+            Db::open_with_config(&root, cfg("k1", 0x22))
+        };
+        let e = r.err().expect("wrong key must fail open");
+        let k = key_error(&e).expect("TdeKeyError in chain");
+        assert_eq!(k.kid, "k1");
+        assert!(!k.missing);
+        assert!(e.to_string().contains("wrong or missing TDE key (kid=k1)"));
+    }
+
+    // Провайдер не знает KID
+    let bad = DbBuilder::from_default()
+        .tde_enabled(true)
+        .tde_kid(Some("k1"))
+        .tde_key_provider(Arc::new(StaticKeyProvider::new("other", [0x11; 32])))
+        .build();
+    let e = Db::open_with_config(&root, bad).err().expect("missing key");
+    let k = key_error(&e).expect("TdeKeyError in chain");
+    assert!(k.missing);
+    assert_eq!(k.kid, "k1");
+
+    // Блок не переписан неудачными попытками
+    let m = read_meta(&root)?;
+    assert_eq!(m.tde_check_kid, "k1");
+    let db = Db::open_with_config(&root, cfg("k1", 0x11))?;
+    assert_eq!(db.get(b"alpha")?.as_deref(), Some(&b"1"[..]));
+    drop(db);
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn rotation_reseals_check_under_new_kid() -> Result<()> {
+    let root = unique_root("tde-kchk-rot");
+    init_tde(&root)?;
+
+    // Новая эпоха k2: ключ k1 недоступен — блок k1 не проверить, writer запечатывает под k2
+    let since = read_meta(&root)?.last_lsn + 1;
+    KeyJournal::open_or_create(&root)?.add_epoch(since, "k2")?;
+    {
+        let mut db = Db::open_with_config(&root, cfg("k2", 0x33))?;
+        db.put(b"beta", b"2")?;
+    }
+    assert_eq!(read_meta(&root)?.tde_check_kid, "k2");
+
+    // Теперь неверный ключ k2 отвергается
+    page_cache_clear();
+    let e = Db::open_ro_with_config(&root, cfg("k2", 0x44))
+        .err()
+        .expect("wrong k2 key must fail open");
+    assert_eq!(key_error(&e).map(|k| k.kid.as_str()), Some("k2"));
+
+    let db = Db::open_ro_with_config(&root, cfg("k2", 0x33))?;
+    assert_eq!(db.get(b"beta")?.as_deref(), Some(&b"2"[..]));
+    drop(db);
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,136 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use QuiverDB::config::DbBuilder;
+use QuiverDB::crypto::{
+    CompositeKeyProvider, KeyJournal, KeyProvider, KidRoute, StaticKeyProvider,
+};
+use QuiverDB::db::Db;
+use QuiverDB::pager::cache::page_cache_clear;
+
+fn static_kp(kid: &str, b: u8) -> Arc<dyn KeyProvider> {
+    Arc::new(StaticKeyProvider::new(kid, [b; 32]))
+}
+
+fn open_with(root: &std::path::Path, kp: CompositeKeyProvider) -> Result<Db> {
+    let cfg = DbBuilder::from_default()
+        .tde_enabled(true)
+        .tde_key_provider(Arc::new(kp))
+        .build();
+    Db::open_with_config(root, cfg)
+}
+
+#[test]
+fn rotated_epoch_pages_readable_via_routed_provider() -> Result<()> {
+    let root = unique_root("tde-route");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+
+    // Эпоха k1: данные подписаны ключом k1
+    let j = KeyJournal::open_or_create(&root)?;
+    j.add_epoch(1, "k1")?;
+    {
+        let mut db = open_with(
+            &root,
+            CompositeKeyProvider::new().with_route(
+                "old",
+                KidRoute::parse("k1"),
+                static_kp("k1", 0x11),
+            ),
+        )?;
+        for i in 0..20u32 {
+            db.put(format!("a{:02}", i).as_bytes(), b"v1")?;
+        }
+        let since = db.pager.meta.last_lsn + 1;
+        j.add_epoch(since, "k2")?;
+    }
+
+    // Только ключ новой эпохи: страницы k1 не проходят AEAD
+    {
+        page_cache_clear();
+        let db = open_with(
+            &root,
+            CompositeKeyProvider::new().with_provider("new", static_kp("k2", 0x22)),
+        )?;
+        assert!(db.pager.tde_epoch_kids().is_empty());
+        assert!(
+            db.get(b"a00").is_err(),
+            "k1 page must not verify without k1 key"
+        );
+    }
+
+    // Цепочка: k2 из одного источника, k1 — по префиксу из другого
+    {
+        page_cache_clear();
+        let kp = CompositeKeyProvider::new()
+            .with_provider("new", static_kp("k2", 0x22))
+            .with_route("legacy", KidRoute::parse("k1*"), static_kp("k1", 0x11));
+        let mut db = open_with(&root, kp)?;
+        assert_eq!(db.pager.tde_epoch_kids(), vec!["k1".to_string()]);
+        for i in 0..20u32 {
+            let v = db.get(format!("a{:02}", i).as_bytes())?;
+            assert_eq!(v.as_deref(), Some(&b"v1"[..]));
+        }
+        db.put(b"b00", b"v2")?;
+        assert_eq!(db.get(b"b00")?.as_deref(), Some(&b"v2"[..]));
+    }
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn composite_precedence_fallthrough_and_env_sources() -> Result<()> {
+    // Приоритет: первый подходящий источник; ошибка источника — переход к следующему
+    let kp = CompositeKeyProvider::new()
+        .with_route("exact", KidRoute::Exact("k1".into()), static_kp("k1", 0x01))
+        .with_provider("wrong", static_kp("x", 0x02))
+        .with_provider("any-k1", static_kp("k1", 0x03))
+        .with_default_kid("k1");
+    assert_eq!(kp.default_kid(), "k1");
+    let (src, km) = kp.resolve("k1")?;
+    assert_eq!(src, "exact");
+    assert_eq!(km.key, [0x01; 32]);
+    let (src, _) = kp.resolve("x")?;
+    assert_eq!(src, "wrong");
+
+    let kp2 = CompositeKeyProvider::new()
+        .with_provider("a", static_kp("other", 0x04))
+        .with_provider("b", static_kp("k9", 0x05));
+    assert_eq!(kp2.resolve("k9")?.0, "b");
+    let err = format!("{:#}", kp2.key("nope").unwrap_err());
+    assert!(err.contains("a:") && err.contains("b:"), "{}", err);
+
+    // ENV: static-ключи прежних эпох и порядок источников
+    let root = unique_root("tde-route-env");
+    fs::create_dir_all(&root)?;
+    std::env::set_var("P1_TDE_KEY_SOURCES", "static");
+    std::env::set_var(
+        "P1_TDE_KEYS",
+        format!("old={},new={}", "aa".repeat(32), "bb".repeat(32)),
+    );
+    let kp3 = CompositeKeyProvider::from_env(&root)?;
+    assert_eq!(kp3.sources().len(), 2);
+    assert_eq!(kp3.key("old")?.key, [0xaa; 32]);
+    assert_eq!(kp3.key("new")?.key, [0xbb; 32]);
+    assert!(kp3.key("missing").is_err());
+
+    std::env::set_var("P1_TDE_KEY_SOURCES", "static,bogus");
+    assert!(CompositeKeyProvider::from_env(&root).is_err());
+    std::env::remove_var("P1_TDE_KEY_SOURCES");
+    std::env::remove_var("P1_TDE_KEYS");
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
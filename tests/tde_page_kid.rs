@@ -0,0 +1,146 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use QuiverDB::config::DbBuilder;
+use QuiverDB::crypto::{CompositeKeyProvider, KeyJournal, KeyProvider, StaticKeyProvider};
+use QuiverDB::db::Db;
+use QuiverDB::metrics;
+use QuiverDB::page::{kid_fingerprint, page_key_fp};
+use QuiverDB::pager::cache::page_cache_clear;
+
+fn static_kp(kid: &str, b: u8) -> Arc<dyn KeyProvider> {
+    Arc::new(StaticKeyProvider::new(kid, [b; 32]))
+}
+
+/// kid — KID, которым пишет хэндл; keys — доступные ключи.
+fn open_with(root: &Path, kid: &str, keys: &[(&str, u8)], ro: bool) -> Result<Db> {
+    let mut kp = CompositeKeyProvider::new();
+    for (k, b) in keys {
+        kp = kp.with_provider(*k, static_kp(k, *b));
+    }
+    let cfg = DbBuilder::from_default()
+        .tde_enabled(true)
+        .tde_kid(Some(kid))
+        .tde_key_provider(Arc::new(kp))
+        .build();
+    page_cache_clear();
+    if ro {
+        Db::open_ro_with_config(root, cfg)
+    } else {
+        Db::open_with_config(root, cfg)
+    }
+}
+
+fn fresh(tag: &str) -> Result<PathBuf> {
+    let root = unique_root(tag);
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    Ok(root)
+}
+
+#[test]
+fn pages_carry_kid_fingerprint_and_are_counted() -> Result<()> {
+    let root = fresh("pkid-count")?;
+    {
+        let mut db = open_with(&root, "k1", &[("k1", 0x11)], false)?;
+        for i in 0..30u32 {
+            db.put(format!("key{:03}", i).as_bytes(), b"v")?;
+        }
+        db.put(b"big", &vec![7u8; 10_000])?; // OVERFLOW-цепочка
+    }
+
+    let db = open_with(&root, "k1", &[("k1", 0x11)], true)?;
+    let mut page = vec![0u8; 4096];
+    db.pager.read_page(0, &mut page)?;
+    assert_eq!(page_key_fp(&page), kid_fingerprint("k1"));
+
+    let u = db.tde_kid_pages()?;
+    assert_eq!(u.untagged_pages, 0);
+    assert_eq!(u.kids.len(), 1);
+    assert_eq!(u.kids[0].kid, "k1");
+    assert!(u.kids[0].current);
+    assert!(u.kids[0].pages >= 4, "KV + overflow pages: {:?}", u);
+    assert_eq!(u.pages_not_current(), 0);
+    drop(db);
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn mixed_kid_pages_read_by_page_kid_not_lsn() -> Result<()> {
+    let root = fresh("pkid-mixed")?;
+    // Журнал: k2 «начинается» далеко впереди — выбор ключа по LSN отдал бы всё k1
+    let j = KeyJournal::open_or_create(&root)?;
+    j.add_epoch(1, "k1")?;
+    j.add_epoch(1_000_000, "k2")?;
+    let keys = [("k1", 0x11), ("k2", 0x22)];
+    {
+        let mut db = open_with(&root, "k1", &keys, false)?;
+        db.put(b"a", b"under-k1")?;
+    }
+    {
+        let mut db = open_with(&root, "k2", &keys, false)?;
+        db.put(b"b", b"under-k2")?;
+    }
+
+    // Текущий k1: страница k2 (lsn внутри эпохи k1) проверяется ключом своего KID
+    let before = metrics::snapshot().tde_epoch_key_verifies;
+    let db = open_with(&root, "k1", &keys, true)?;
+    assert_eq!(db.get(b"a")?.as_deref(), Some(&b"under-k1"[..]));
+    assert_eq!(db.get(b"b")?.as_deref(), Some(&b"under-k2"[..]));
+    assert!(metrics::snapshot().tde_epoch_key_verifies > before);
+
+    let u = db.tde_kid_pages()?;
+    let pages = |kid: &str| u.kids.iter().find(|k| k.kid == kid).map(|k| k.pages);
+    assert!(pages("k1").unwrap_or(0) >= 1);
+    assert!(pages("k2").unwrap_or(0) >= 1);
+    assert!(u.pages_not_current() >= 1);
+    drop(db);
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn page_with_unloaded_kid_fails_with_precise_error() -> Result<()> {
+    let root = fresh("pkid-missing")?;
+    let j = KeyJournal::open_or_create(&root)?;
+    j.add_epoch(1, "old")?;
+    {
+        let mut db = open_with(&root, "old", &[("old", 0x31)], false)?;
+        db.put(b"x", b"1")?;
+        let since = db.pager.meta.last_lsn + 1;
+        j.add_epoch(since, "new")?;
+    }
+
+    let before = metrics::snapshot().tde_page_kid_missing;
+    let db = open_with(&root, "new", &[("new", 0x32)], true)?;
+    let e = db
+        .get(b"x")
+        .expect_err("old page must not verify without its key");
+    let msg = format!("{:#}", e);
+    assert!(msg.contains("TDE KID old"), "{}", msg);
+    assert!(metrics::snapshot().tde_page_kid_missing > before);
+
+    let u = db.tde_kid_pages()?;
+    assert_eq!(
+        u.kids.iter().find(|k| k.kid == "old").map(|k| k.current),
+        Some(false)
+    );
+    drop(db);
+
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
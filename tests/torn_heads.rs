@@ -0,0 +1,162 @@
+use anyhow::Result;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::Db;
+use QuiverDB::dir::{Directory, NO_PAGE};
+use QuiverDB::meta::{read_meta, set_clean_shutdown, write_meta_overwrite};
+use QuiverDB::page::TRAILER_LEN;
+use QuiverDB::pager::{DATA_SEG_EXT, DATA_SEG_PREFIX};
+
+const PS: u32 = 4096;
+
+#[test]
+fn torn_head_with_stale_lsn_is_repaired_from_wal_on_open() -> Result<()> {
+    let src = unique_root("torn-src");
+    let dst = unique_root("torn-dst");
+    fs::create_dir_all(&src)?;
+    Db::init(&src, PS, 8)?;
+
+    {
+        let mut db = Db::open(&src)?;
+        for i in 0..40u32 {
+            db.put(format!("k{i}").as_bytes(), b"old")?;
+        }
+    }
+    // Снимок каталога живого writer'а: WAL ещё содержит образы переписанных страниц
+    // (page_id < meta.next_page_id), clean_shutdown=false
+    {
+        let mut db = Db::open(&src)?;
+        for i in 0..40u32 {
+            db.put(format!("k{i}").as_bytes(), format!("v{i}").as_bytes())?;
+        }
+        copy_dir(&src, &dst)?;
+        // meta снимка знает о всех выделенных страницах (так гейтинг реплея сравнивает lsn)
+        let mut m = read_meta(&dst)?;
+        m.next_page_id = db.pager.meta.next_page_id;
+        write_meta_overwrite(&dst, &m)?;
+    }
+
+    // Порванная запись: заголовок (с актуальным lsn) на месте, тело — мусор, трейлер нулевой.
+    // read_page в non-strict режиме такую страницу принимает, поэтому реплей её не перепишет.
+    let (bucket, pid) = first_head(&dst)?;
+    patch_page(&dst, pid, |p| {
+        p[256..1024].fill(0xAB);
+        let n = p.len();
+        p[n - TRAILER_LEN..].fill(0);
+    })?;
+
+    let db = Db::open(&dst)?;
+    let rep = db
+        .open_repair_report()
+        .expect("unclean open must verify heads");
+    assert!(rep.wal_images > 0);
+    assert_eq!(rep.pages.len(), 1, "{:?}", rep);
+    assert_eq!((rep.pages[0].bucket, rep.pages[0].page_id), (bucket, pid));
+    assert!(rep.pages[0].repaired, "{:?}", rep);
+    assert!(rep.is_clean());
+    for i in 0..40u32 {
+        let v = db.get(format!("k{i}").as_bytes())?;
+        assert_eq!(v.as_deref(), Some(format!("v{i}").as_bytes()));
+    }
+    assert!(db.verify_heads()?.is_clean());
+    Ok(())
+}
+
+#[test]
+fn unrepairable_head_is_reported_and_open_succeeds() -> Result<()> {
+    let root = unique_root("torn-broken");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PS, 8)?;
+    {
+        let mut db = Db::open(&root)?;
+        for i in 0..40u32 {
+            db.put(format!("k{i}").as_bytes(), b"v")?;
+        }
+    }
+    // Чистое закрытие: проверка голов не выполняется
+    {
+        let db = Db::open(&root)?;
+        assert!(db.open_repair_report().is_none());
+    }
+
+    // WAL пуст (усечён при закрытии) — чинить нечем
+    let (_bucket, pid) = first_head(&root)?;
+    patch_page(&root, pid, |p| p.fill(0xCD))?;
+    set_clean_shutdown(&root, false)?;
+
+    {
+        let db = Db::open(&root)?;
+        let rep = db
+            .open_repair_report()
+            .expect("unclean open must verify heads");
+        assert_eq!(rep.wal_images, 0);
+        let broken: Vec<_> = rep.unrepaired().map(|p| p.page_id).collect();
+        assert_eq!(broken, vec![pid]);
+        assert!(!rep.is_clean());
+    }
+
+    // Проверка по запросу и принудительная проверка при чистом открытии видят то же
+    {
+        let db = Db::open_ro(&root)?;
+        let rep = db.verify_heads()?;
+        assert_eq!(rep.unrepaired().count(), 1);
+    }
+    let db = Db::open_with_config(
+        &root,
+        QuiverConfig::default().with_verify_heads_on_open(true),
+    )?;
+    assert_eq!(db.open_repair_report().unwrap().unrepaired().count(), 1);
+    Ok(())
+}
+
+// ---------- helpers ----------
+
+fn first_head(root: &Path) -> Result<(u32, u64)> {
+    let dir = Directory::open(root)?;
+    for b in 0..dir.bucket_count {
+        let pid = dir.head(b)?;
+        if pid != NO_PAGE {
+            return Ok((b, pid));
+        }
+    }
+    anyhow::bail!("no bucket heads")
+}
+
+fn patch_page(root: &Path, pid: u64, f: impl FnOnce(&mut [u8])) -> Result<()> {
+    let seg = root.join(format!("{}{:06}.{}", DATA_SEG_PREFIX, 1, DATA_SEG_EXT));
+    let mut file = fs::OpenOptions::new().read(true).write(true).open(seg)?;
+    let mut page = vec![0u8; PS as usize];
+    file.seek(SeekFrom::Start(pid * PS as u64))?;
+    file.read_exact(&mut page)?;
+    f(&mut page);
+    file.seek(SeekFrom::Start(pid * PS as u64))?;
+    file.write_all(&page)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+fn copy_dir(src: &Path, dst: &Path) -> Result<()> {
+    fs::create_dir_all(dst)?;
+    for e in fs::read_dir(src)? {
+        let e = e?;
+        let to = dst.join(e.file_name());
+        if e.file_type()?.is_dir() {
+            copy_dir(&e.path(), &to)?;
+        } else if e.file_name() != "LOCK" {
+            fs::copy(e.path(), to)?;
+        }
+    }
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
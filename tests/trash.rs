@@ -0,0 +1,111 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::Db;
+
+fn trash_db(root: &Path, grace_secs: u64) -> Result<Db> {
+    fs::create_dir_all(root)?;
+    Db::init(root, 4096, 4)?;
+    Db::open_with_config(
+        root,
+        QuiverConfig::default().with_trash_grace_secs(grace_secs),
+    )
+}
+
+#[test]
+fn undelete_restores_previous_value_within_grace() -> Result<()> {
+    let root = unique_root("trash-basic");
+    let mut db = trash_db(&root, 3600)?;
+    db.put(b"a", b"1")?;
+    db.put(b"a", b"22")?;
+    db.put(b"b", b"x")?;
+    assert!(db.del(b"a")?);
+    assert!(db.get(b"a")?.is_none());
+
+    let trash = db.trash_entries()?;
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].key, b"a");
+    assert_eq!(trash[0].value_len, 2);
+
+    assert!(db.undelete(b"a")?);
+    assert_eq!(db.get(b"a")?.as_deref(), Some(&b"22"[..]));
+    // ключ жив — в корзине его нет
+    assert!(!db.undelete(b"a")?);
+    assert!(!db.undelete(b"b")?);
+    assert!(!db.undelete(b"missing")?);
+    assert!(db.trash_entries()?.is_empty());
+
+    // удаление из батча — тоже в корзину
+    db.batch(|b| {
+        b.del(b"b")?;
+        Ok(())
+    })?;
+    assert!(db.get(b"b")?.is_none());
+    assert!(db.undelete(b"b")?);
+    assert_eq!(db.get(b"b")?.as_deref(), Some(&b"x"[..]));
+    Ok(())
+}
+
+#[test]
+fn hard_deletes_are_not_restorable() -> Result<()> {
+    let root = unique_root("trash-hard");
+    {
+        let mut db = trash_db(&root, 0)?;
+        db.put(b"k", b"v")?;
+        db.del(b"k")?;
+        assert!(!db.undelete(b"k")?);
+        assert!(db.trash_entries()?.is_empty());
+    }
+    // delete_prefix поверх корзины — значение уходит насовсем
+    let mut db = Db::open_with_config(&root, QuiverConfig::default().with_trash_grace_secs(60))?;
+    db.put(b"p:1", b"v")?;
+    db.del(b"p:1")?;
+    db.delete_prefix(b"p:")?;
+    assert!(!db.undelete(b"p:1")?);
+    assert!(db.trash_entries()?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn compaction_keeps_trash_until_grace_expires() -> Result<()> {
+    let root = unique_root("trash-compact");
+    let mut db = trash_db(&root, 3600)?;
+    let big = vec![7u8; 20_000]; // OVERFLOW
+    db.put(b"big", &big)?;
+    db.put(b"keep", b"k")?;
+    db.put(b"gone", b"g")?;
+    db.del(b"big")?;
+
+    // компактация переносит мягкий tombstone и значение под ним
+    let sum = db.compact_all()?;
+    assert_eq!(sum.keys_trashed_sum, 1);
+    db.sweep_orphan_overflow()?;
+    assert!(db.get(b"big")?.is_none());
+    assert_eq!(db.trash_entries()?.len(), 1);
+    assert!(db.undelete(b"big")?);
+    assert_eq!(db.get(b"big")?, Some(big));
+
+    // после срока корзины — обычный purge
+    drop(db);
+    let mut db = Db::open_with_config(&root, QuiverConfig::default().with_trash_grace_secs(1))?;
+    db.del(b"gone")?;
+    std::thread::sleep(std::time::Duration::from_millis(2100));
+    assert!(db.trash_entries()?.is_empty());
+    assert!(!db.undelete(b"gone")?);
+    let sum = db.compact_all()?;
+    assert_eq!(sum.keys_trashed_sum, 0);
+    assert!(db.get_versions(b"gone", 8)?.is_empty());
+    assert_eq!(db.get(b"keep")?.as_deref(), Some(&b"k"[..]));
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,97 @@
+#![cfg(feature = "serde")]
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+use QuiverDB::typed::{Codec, JsonCodec, TypedDb};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct User {
+    name: String,
+    age: u32,
+    tags: Vec<String>,
+}
+
+/// Пользовательский кодек ключей: строка как есть (без JSON-кавычек).
+struct Utf8Key;
+
+impl Codec for Utf8Key {
+    fn name(&self) -> &'static str {
+        "utf8"
+    }
+    fn encode<T: Serialize + ?Sized>(&self, v: &T) -> Result<Vec<u8>> {
+        match serde_json::to_value(v)? {
+            serde_json::Value::String(s) => Ok(s.into_bytes()),
+            other => Err(anyhow::anyhow!("utf8 key codec: not a string: {}", other)),
+        }
+    }
+    fn decode<T: serde::de::DeserializeOwned>(&self, b: &[u8]) -> Result<T> {
+        let s = std::str::from_utf8(b)?;
+        Ok(serde_json::from_value(serde_json::Value::String(s.into()))?)
+    }
+}
+
+#[test]
+fn typed_db_roundtrip_batch_and_scan() -> Result<()> {
+    let root = unique_root("typed-db");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+
+    let mut t: TypedDb<u64, User> = TypedDb::new(Db::open(&root)?);
+    let alice = User {
+        name: "alice".into(),
+        age: 30,
+        tags: vec!["admin".into()],
+    };
+    t.put(&1, &alice)?;
+    t.batch(|b| {
+        for id in 2..5u64 {
+            let u = User {
+                name: format!("user{}", id),
+                age: id as u32,
+                tags: vec![],
+            };
+            b.put(&id, &u)?;
+        }
+        b.del(&4)?;
+        Ok(())
+    })?;
+    assert_eq!(t.get(&1)?, Some(alice.clone()));
+    assert!(t.exists(&3)? && !t.exists(&4)?);
+    assert!(t.del(&3)?);
+
+    let many = t.get_many(&[1, 2, 3])?;
+    assert_eq!(many[0].as_ref().map(|u| u.age), Some(30));
+    assert_eq!(many[1].as_ref().map(|u| u.name.as_str()), Some("user2"));
+    assert!(many[2].is_none());
+
+    let mut all: Vec<(u64, User)> = t.scan()?.collect::<Result<_>>()?;
+    all.sort_by_key(|(k, _)| *k);
+    assert_eq!(all.iter().map(|(k, _)| *k).collect::<Vec<_>>(), vec![1, 2]);
+
+    // Сырая запись, не декодируемая как User, — ошибка элемента скана, а не паника
+    t.db_mut().put(b"9", b"not json")?;
+    assert!(t.scan()?.any(|r| r.is_err()));
+    drop(t);
+
+    // Разные кодеки для ключа и значения
+    let mut s: TypedDb<String, User, Utf8Key, JsonCodec> =
+        TypedDb::with_codecs(Db::open(&root)?, Utf8Key, JsonCodec);
+    s.put(&"user:alice".to_string(), &alice)?;
+    assert_eq!(s.encode_key(&"user:alice".to_string())?, b"user:alice");
+    let hits: Vec<(String, User)> = s.scan_prefix_raw(b"user:")?.collect::<Result<_>>()?;
+    assert_eq!(hits, vec![("user:alice".to_string(), alice)]);
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
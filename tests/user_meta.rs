@@ -0,0 +1,183 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::backup::{backup_to_writer, restore_from_reader};
+use QuiverDB::db::{Db, USER_META_MAX_BYTES};
+use QuiverDB::meta::{read_meta, FEATURE_USER_META};
+use QuiverDB::snapstore::{restore_from_id, SnapshotManager};
+
+const PAGE: u32 = 4096;
+
+#[test]
+fn metadata_is_separate_from_user_keys() -> Result<()> {
+    let root = unique_root("umeta-basic");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PAGE, 8)?;
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"user:1", b"a")?;
+        db.meta_put(b"schema_version", b"3")?;
+        db.meta_put(b"app.theme", b"dark")?;
+        assert_eq!(db.meta_get(b"schema_version")?, Some(b"3".to_vec()));
+
+        // Ни сканы, ни get пользовательского пространства область не видят
+        assert_eq!(db.scan_all()?, vec![(b"user:1".to_vec(), b"a".to_vec())]);
+        assert_eq!(db.get(b"schema_version")?, None);
+        assert_eq!(db.meta_get(b"user:1")?, None);
+
+        // meta_update: ошибка замыкания — ничего не записано
+        let err = db.meta_update(|m| {
+            m.insert(b"schema_version".to_vec(), b"4".to_vec());
+            Err(anyhow::anyhow!("migration failed"))
+        });
+        assert!(err.is_err());
+        assert_eq!(db.meta_get(b"schema_version")?, Some(b"3".to_vec()));
+        db.meta_update(|m| {
+            m.insert(b"schema_version".to_vec(), b"4".to_vec());
+            m.remove(b"app.theme".as_slice());
+            Ok(())
+        })?;
+        assert!(!db.meta_del(b"missing")?);
+
+        let too_big = vec![0u8; USER_META_MAX_BYTES + 1];
+        assert!(db.meta_put(b"blob", &too_big).is_err());
+        db.meta_put(b"late", b"1")?;
+    }
+    {
+        let mut ro = Db::open_ro(&root)?;
+        assert_eq!(ro.meta_get(b"schema_version")?, Some(b"4".to_vec()));
+        assert!(ro.meta_put(b"x", b"y").is_err());
+    }
+
+    let db = Db::open(&root)?;
+    assert_eq!(
+        db.meta_entries()?,
+        vec![
+            (b"late".to_vec(), b"1".to_vec()),
+            (b"schema_version".to_vec(), b"4".to_vec()),
+        ]
+    );
+    drop(db);
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn metadata_spans_pages_and_survives_alloc_fix_and_compaction() -> Result<()> {
+    let root = unique_root("umeta-pages");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PAGE, 4)?;
+    let mut db = Db::open(&root)?;
+    // Больше одной страницы области
+    let value = vec![b's'; 900];
+    for i in 0..12 {
+        db.meta_put(format!("setting:{:02}", i).as_bytes(), &value)?;
+    }
+    assert!(db.space_usage(0)?.user_meta_pages > 1);
+
+    // Пересборка free-листа не должна вернуть страницы области в лист
+    let rep = db.alloc_check_fix()?;
+    assert!(rep.is_clean(), "{:?}", rep);
+    for i in 0..300 {
+        db.put(format!("k{:04}", i).as_bytes(), b"value")?;
+    }
+    db.compact_all()?;
+    for i in 0..12 {
+        let key = format!("setting:{:02}", i);
+        assert_eq!(db.meta_get(key.as_bytes())?, Some(value.clone()), "{}", key);
+    }
+    assert_eq!(db.scan_prefix(b"setting:")?.len(), 0);
+    drop(db);
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn repeated_updates_reuse_pages_of_the_previous_chain() -> Result<()> {
+    let root = unique_root("umeta-reuse");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, PAGE, 4)?;
+    let mut db = Db::open(&root)?;
+    // Две страницы области на версию
+    let value = vec![b'v'; 3000];
+    db.meta_put(b"a", &value)?;
+    db.meta_put(b"b", &value)?;
+    let pages_before = read_meta(&root)?.next_page_id;
+    for i in 0..50u32 {
+        db.meta_put(b"counter", &i.to_le_bytes())?;
+    }
+    // Прежняя цепочка уходит в free-лист и переиспользуется следующей записью
+    assert!(
+        read_meta(&root)?.next_page_id <= pages_before + 3,
+        "pages grew from {} to {}",
+        pages_before,
+        read_meta(&root)?.next_page_id
+    );
+    assert_eq!(db.meta_get(b"counter")?, Some(49u32.to_le_bytes().to_vec()));
+    assert_eq!(db.meta_get(b"a")?, Some(value.clone()));
+    let rep = db.alloc_check()?;
+    assert!(rep.is_clean(), "{:?}", rep);
+    drop(db);
+    let _ = fs::remove_dir_all(&root);
+    Ok(())
+}
+
+#[test]
+fn metadata_travels_with_backups_and_snapshots() -> Result<()> {
+    let src = unique_root("umeta-src");
+    fs::create_dir_all(&src)?;
+    Db::init(&src, PAGE, 8)?;
+    let mut full = Vec::new();
+    let full_man = {
+        let mut db = Db::open(&src)?;
+        db.put(b"k", b"v")?;
+        db.meta_put(b"schema_version", b"1")?;
+        backup_to_writer(&db, &mut full, 0)?
+    };
+    let dst = unique_root("umeta-dst");
+    restore_from_reader(&dst, full.as_slice(), true)?;
+    assert_eq!(
+        Db::open_ro(&dst)?.meta_get(b"schema_version")?,
+        Some(b"1".to_vec())
+    );
+
+    // Инкремент переносит новую версию области
+    let mut inc = Vec::new();
+    {
+        let mut db = Db::open(&src)?;
+        db.meta_put(b"schema_version", b"2")?;
+        backup_to_writer(&db, &mut inc, full_man.lsn)?;
+    }
+    restore_from_reader(&dst, inc.as_slice(), true)?;
+    assert_eq!(
+        Db::open_ro(&dst)?.meta_get(b"schema_version")?,
+        Some(b"2".to_vec())
+    );
+
+    let snap_id = {
+        let db = Db::open_ro(&src)?;
+        SnapshotManager::create_persisted(&db, None, &[], None)?
+    };
+    let snap_dst = unique_root("umeta-snap");
+    fs::create_dir_all(&snap_dst)?;
+    restore_from_id(&src, &snap_dst, &snap_id, true)?;
+    let db = Db::open_ro(&snap_dst)?;
+    assert_eq!(db.meta_get(b"schema_version")?, Some(b"2".to_vec()));
+    assert_eq!(db.get(b"k")?, Some(b"v".to_vec()));
+    assert_ne!(read_meta(&snap_dst)?.flags & FEATURE_USER_META, 0);
+    drop(db);
+    for r in [&src, &dst, &snap_dst] {
+        let _ = fs::remove_dir_all(r);
+    }
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
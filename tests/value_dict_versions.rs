@@ -0,0 +1,146 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use QuiverDB::backup::{backup_to_path, restore_from_path, BackupOptions, RestoreOptions};
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::{train_value_dictionary, Db, ValueTransform, ZstdDictValues, VALUE_DICT_FILE};
+use QuiverDB::meta::read_meta;
+
+fn order(i: usize) -> Vec<u8> {
+    format!(
+        r#"{{"order":{},"state":"paid","carrier":"dhl","address":{{"city":"city-{}","zip":"{:05}"}}}}"#,
+        i,
+        i % 31,
+        i * 7 % 100000
+    )
+    .into_bytes()
+}
+
+fn event(i: usize) -> Vec<u8> {
+    format!(
+        r#"[{},"login","ok","agent=Mozilla/5.0 (X11; Linux x86_64)","region=eu-{}"]"#,
+        i,
+        i % 5
+    )
+    .into_bytes()
+}
+
+/// БД со словарём версии 1, обученным на order(), и значениями o0000..o0199.
+fn dict_db(prefix: &str) -> Result<PathBuf> {
+    let root = unique_root(prefix);
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    let samples: Vec<Vec<u8>> = (0..300).map(order).collect();
+    let dict = ZstdDictValues::train(&root, &samples, 4096, 3)?;
+    let cfg = QuiverConfig::from_env().with_value_transform(Arc::new(dict));
+    let mut db = Db::open_with_config(&root, cfg)?;
+    for i in 0..200 {
+        db.put(format!("o{:04}", i).as_bytes(), &order(i))?;
+    }
+    Ok(root)
+}
+
+#[test]
+fn sample_values_returns_a_bounded_sample_of_live_values() -> Result<()> {
+    let root = dict_db("vdv-sample")?;
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"empty", b"")?;
+        assert!(db.del(b"o0000")?);
+    }
+    let db = Db::open_ro(&root)?;
+    let all = db.sample_values(1000)?;
+    assert_eq!(
+        all.len(),
+        199,
+        "every live non-empty value when n exceeds the count"
+    );
+    assert!(!all.contains(&order(0)));
+
+    let some = db.sample_values(50)?;
+    assert_eq!(some.len(), 50);
+    assert!(some.iter().all(|v| all.contains(v)), "decoded values only");
+    assert!(db.sample_values(0)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn added_version_compresses_new_writes_and_keeps_old_values_readable() -> Result<()> {
+    let root = dict_db("vdv-add")?;
+    let id = read_meta(&root)?.value_transform;
+
+    let samples: Vec<Vec<u8>> = (0..300).map(event).collect();
+    let dict = train_value_dictionary(&samples, 4096)?;
+    assert_eq!(ZstdDictValues::add_version(&root, &dict, 5)?, 2);
+
+    let t = ZstdDictValues::open(&root)?;
+    assert_eq!(t.current_version(), 2);
+    assert_eq!(t.id(), id, "id stays tied to version 1");
+    let vs = t.versions();
+    assert_eq!(vs.iter().map(|v| v.version).collect::<Vec<_>>(), vec![1, 2]);
+    assert_eq!((vs[1].level, vs[1].bytes), (5, dict.len()));
+
+    // Новые записи — версией 2 (тег 2 + номер версии)
+    let enc = t.encode(&event(7))?.into_owned();
+    assert_eq!(enc[0], 2);
+    assert_eq!(u32::from_le_bytes(enc[1..5].try_into()?), 2);
+    {
+        let mut db = Db::open(&root)?;
+        assert_eq!(db.value_transform_id().as_deref(), Some(id.as_str()));
+        for i in 0..100 {
+            db.put(format!("e{:04}", i).as_bytes(), &event(i))?;
+        }
+    }
+    let db = Db::open_ro(&root)?;
+    assert_eq!(db.get(b"o0123")?, Some(order(123)));
+    assert_eq!(db.get(b"e0042")?, Some(event(42)));
+    assert_eq!(db.scan_prefix(b"e")?.len(), 100);
+
+    // Хэндл, загрузивший словарь до новой версии, её значений не знает
+    let v1_only = {
+        let dir = unique_root("vdv-add-v1");
+        fs::create_dir_all(&dir)?;
+        let samples: Vec<Vec<u8>> = (0..300).map(order).collect();
+        ZstdDictValues::train(&dir, &samples, 4096, 3)?
+    };
+    let err = v1_only
+        .decode(&enc)
+        .expect_err("unknown dictionary version");
+    assert!(err.to_string().contains("version 2"), "{:#}", err);
+    Ok(())
+}
+
+#[test]
+fn versioned_dictionary_travels_with_backup() -> Result<()> {
+    let root = dict_db("vdv-bk")?;
+    let samples: Vec<Vec<u8>> = (0..300).map(event).collect();
+    ZstdDictValues::add_version(&root, &train_value_dictionary(&samples, 4096)?, 3)?;
+    let out = unique_root("vdv-bk-out").with_extension("p2bk");
+    {
+        let mut db = Db::open(&root)?;
+        db.put(b"e0001", &event(1))?;
+        backup_to_path(&db, &out, &BackupOptions::default())?;
+    }
+
+    let dst = unique_root("vdv-bk-dst");
+    restore_from_path(&dst, &out, &RestoreOptions::default())?;
+    assert_eq!(
+        fs::read(dst.join(VALUE_DICT_FILE))?,
+        fs::read(root.join(VALUE_DICT_FILE))?
+    );
+    let db = Db::open_ro(&dst)?;
+    assert_eq!(db.get(b"o0005")?, Some(order(5)));
+    assert_eq!(db.get(b"e0001")?, Some(event(1)));
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,222 @@
+use anyhow::Result;
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use QuiverDB::backup::{backup_to_path, restore_from_path, BackupOptions, RestoreOptions};
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::{
+    Db, ValueTransform, ValueTransformMismatchError, ZstdDictValues, VALUE_DICT_FILE,
+};
+use QuiverDB::meta::{read_meta, FEATURE_VALUE_TRANSFORM};
+
+/// Пользовательская трансформация (не встроенная): XOR каждого байта.
+struct Xor(u8);
+
+impl ValueTransform for Xor {
+    fn id(&self) -> String {
+        format!("test-xor:{}", self.0)
+    }
+
+    fn encode<'a>(&self, value: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        Ok(Cow::Owned(value.iter().map(|b| b ^ self.0).collect()))
+    }
+
+    fn decode<'a>(&self, stored: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        self.encode(stored)
+    }
+}
+
+fn json_value(i: usize) -> Vec<u8> {
+    format!(
+        r#"{{"id":{},"kind":"order","status":"shipped","customer":{{"name":"user-{}","tier":"gold"}},"items":[{}]}}"#,
+        i,
+        i % 97,
+        i % 13
+    )
+    .into_bytes()
+}
+
+#[test]
+fn zstd_dict_round_trips_and_persists_across_reopen_and_restore() -> Result<()> {
+    let root = unique_root("vtr-dict");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+    let samples: Vec<Vec<u8>> = (0..400).map(json_value).collect();
+    let dict = Arc::new(ZstdDictValues::train(&root, &samples, 4096, 3)?);
+    assert!(root.join(VALUE_DICT_FILE).exists());
+    let id = dict.id();
+    assert!(id.starts_with("zstd_dict:"));
+
+    let out = unique_root("vtr-dict-bk").with_extension("p2bk");
+    {
+        let cfg = QuiverConfig::from_env().with_value_transform(dict.clone());
+        let mut db = Db::open_with_config(&root, cfg)?;
+        assert_eq!(db.value_transform_id().as_deref(), Some(id.as_str()));
+        for i in 0..200 {
+            db.put(format!("k{:04}", i).as_bytes(), &json_value(i))?;
+        }
+        db.put(b"short", b"x")?;
+        assert_eq!(db.get(b"k0007")?, Some(json_value(7)));
+        // Хвост WAL в бэкапе: restore реплеит его уже со словарём
+        backup_to_path(&db, &out, &BackupOptions::default())?;
+    }
+    let m = read_meta(&root)?;
+    assert_eq!(m.value_transform, id);
+    assert_ne!(m.flags & FEATURE_VALUE_TRANSFORM, 0);
+
+    // Встроенная восстанавливается по id из meta и словарю под корнем
+    {
+        let db = Db::open(&root)?;
+        assert_eq!(db.value_transform_id().as_deref(), Some(id.as_str()));
+        assert_eq!(db.get(b"k0150")?, Some(json_value(150)));
+        assert_eq!(db.get(b"short")?, Some(b"x".to_vec()));
+    }
+    {
+        let db = Db::open_ro(&root)?;
+        let all = db.scan_prefix(b"k")?;
+        assert_eq!(all.len(), 200);
+        assert!(
+            all.iter()
+                .all(|(k, v)| *v
+                    == json_value(std::str::from_utf8(&k[1..]).unwrap().parse().unwrap()))
+        );
+    }
+
+    let dst = unique_root("vtr-dict-dst");
+    let man = restore_from_path(&dst, &out, &RestoreOptions::default())?;
+    assert!(man.sidecars.iter().any(|n| n == VALUE_DICT_FILE));
+    let db = Db::open_ro(&dst)?;
+    assert_eq!(db.value_transform_id().as_deref(), Some(id.as_str()));
+    assert_eq!(db.get(b"k0199")?, Some(json_value(199)));
+    Ok(())
+}
+
+#[test]
+fn mismatched_or_missing_transform_is_refused() -> Result<()> {
+    let root = unique_root("vtr-mismatch");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    {
+        let cfg = QuiverConfig::from_env().with_value_transform(Arc::new(Xor(0x5a)));
+        let mut db = Db::open_with_config(&root, cfg)?;
+        db.put(b"k", b"v")?;
+    }
+
+    // Пользовательская трансформация не восстанавливается по id — открыть без неё нельзя
+    let err = Db::open(&root)
+        .err()
+        .expect("open without transform must fail");
+    let mm = err
+        .downcast_ref::<ValueTransformMismatchError>()
+        .expect("typed mismatch error");
+    assert_eq!(mm.recorded, "test-xor:90");
+
+    let cfg = QuiverConfig::from_env().with_value_transform(Arc::new(Xor(1)));
+    let err = Db::open_with_config(&root, cfg)
+        .err()
+        .expect("other transform");
+    assert!(err.downcast_ref::<ValueTransformMismatchError>().is_some());
+
+    // БД без трансформации с данными не перекодируется задним числом
+    let plain = unique_root("vtr-plain");
+    fs::create_dir_all(&plain)?;
+    Db::init(&plain, 4096, 8)?;
+    {
+        let mut db = Db::open(&plain)?;
+        db.put(b"k", b"v")?;
+    }
+    let cfg = QuiverConfig::from_env().with_value_transform(Arc::new(Xor(0x5a)));
+    let err = Db::open_with_config(&plain, cfg).err().expect("plain DB");
+    assert!(err.downcast_ref::<ValueTransformMismatchError>().is_some());
+    Ok(())
+}
+
+#[test]
+fn custom_transform_applies_to_every_entry_point() -> Result<()> {
+    let root = unique_root("vtr-xor");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 8)?;
+    let cfg = QuiverConfig::from_env().with_value_transform(Arc::new(Xor(0x5a)));
+    let mut db = Db::open_with_config(&root, cfg)?;
+
+    db.put(b"a", b"plain-marker-A")?;
+    db.batch(|b| b.put(b"b", b"plain-marker-B"))?;
+    {
+        let mut bulk = db.bulk_load()?;
+        bulk.put(b"c", b"plain-marker-C")?;
+        bulk.finish()?;
+    }
+    db.put(b"a", b"plain-marker-A2")?;
+
+    assert_eq!(db.get(b"a")?, Some(b"plain-marker-A2".to_vec()));
+    assert_eq!(
+        db.get_many(&[b"b".as_slice(), b"c".as_slice(), b"zz".as_slice()])?,
+        vec![
+            Some(b"plain-marker-B".to_vec()),
+            Some(b"plain-marker-C".to_vec()),
+            None
+        ]
+    );
+    let mut all = db.scan_all()?;
+    all.sort();
+    assert_eq!(
+        all,
+        vec![
+            (b"a".to_vec(), b"plain-marker-A2".to_vec()),
+            (b"b".to_vec(), b"plain-marker-B".to_vec()),
+            (b"c".to_vec(), b"plain-marker-C".to_vec()),
+        ]
+    );
+    let mut streamed = Vec::new();
+    db.scan_stream(None, |k, v| streamed.push((k.to_vec(), v.to_vec())))?;
+    streamed.sort();
+    assert_eq!(streamed, all);
+    let mut iterated = db.scan_iter()?.collect::<Result<Vec<_>>>()?;
+    iterated.sort();
+    assert_eq!(iterated, all);
+    let (page, _) = db.scan_page(None, None, 10)?;
+    assert_eq!(page.len(), 3);
+    let versions: Vec<_> = db
+        .get_versions(b"a", 4)?
+        .into_iter()
+        .map(|v| v.value)
+        .collect();
+    assert_eq!(
+        versions,
+        vec![
+            Some(b"plain-marker-A2".to_vec()),
+            Some(b"plain-marker-A".to_vec())
+        ]
+    );
+    drop(db);
+
+    // На диске (страницы, WAL) — только закодированные байты
+    assert!(!tree_contains(&root, b"plain-marker")?);
+    Ok(())
+}
+
+fn tree_contains(dir: &Path, needle: &[u8]) -> Result<bool> {
+    for e in fs::read_dir(dir)? {
+        let p = e?.path();
+        let hit = if p.is_dir() {
+            tree_contains(&p, needle)?
+        } else {
+            fs::read(&p)?.windows(needle.len()).any(|w| w == needle)
+        };
+        if hit {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,117 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use QuiverDB::config::QuiverConfig;
+use QuiverDB::db::Db;
+use QuiverDB::wal::coalesce::{COALESCE_DECIDE_EVERY, COALESCE_STEP_US};
+use QuiverDB::wal::{group_coalesce_status, AdaptiveCoalesce, CoalesceDecision};
+
+/// Прогнать controller одним решением: n fsync'ов с интервалом gap и латентностью lat.
+fn round(c: &mut AdaptiveCoalesce, gap_us: u64, fsync_us: u64, lat_us: u64) -> CoalesceDecision {
+    for i in 1..=COALESCE_DECIDE_EVERY {
+        c.on_arrival_gap(gap_us);
+        c.on_commit_latency(lat_us);
+        if let Some(d) = c.on_fsync(fsync_us) {
+            assert_eq!(i, COALESCE_DECIDE_EVERY);
+            return d;
+        }
+    }
+    unreachable!("no decision after {} fsyncs", COALESCE_DECIDE_EVERY)
+}
+
+#[test]
+fn window_grows_under_dense_load_up_to_budget() {
+    // Бюджет 5 мс, fsync 1 мс, коммиты каждые 50 мкс: окно растёт до бюджет − fsync
+    let mut c = AdaptiveCoalesce::new(5_000, 10_000);
+    assert_eq!(c.window_us(), 0);
+    assert_eq!(round(&mut c, 50, 1_000, 1_200), CoalesceDecision::Grow);
+    assert_eq!(c.window_us(), COALESCE_STEP_US);
+    let mut prev = c.window_us();
+    for _ in 0..20 {
+        round(&mut c, 50, 1_000, 1_200);
+        assert!(c.window_us() >= prev);
+        prev = c.window_us();
+    }
+    assert_eq!(c.window_us(), 4_000);
+    assert_eq!(round(&mut c, 50, 1_000, 1_200), CoalesceDecision::Hold);
+
+    // Потолок (wal_coalesce_ms) ниже допустимого окна
+    let mut capped = AdaptiveCoalesce::new(5_000, 700);
+    for _ in 0..10 {
+        round(&mut capped, 50, 1_000, 1_200);
+    }
+    assert_eq!(capped.window_us(), 700);
+    assert_eq!(capped.status().max_window_us, 700);
+}
+
+#[test]
+fn window_shrinks_over_budget_and_when_idle() {
+    let mut c = AdaptiveCoalesce::new(5_000, 10_000);
+    for _ in 0..10 {
+        round(&mut c, 50, 1_000, 1_200);
+    }
+    let open = c.window_us();
+    assert!(open > COALESCE_STEP_US);
+
+    // p99 выше бюджета — окно ÷2
+    assert_eq!(round(&mut c, 50, 1_000, 9_000), CoalesceDecision::Shrink);
+    assert_eq!(c.window_us(), open / 2);
+    assert!(c.p99_us() > 5_000);
+    assert_eq!(c.status().p99_us, c.p99_us());
+
+    // Редкие коммиты (интервал больше бюджета): ожидание не группирует — окно до 0
+    let mut c = AdaptiveCoalesce::new(5_000, 10_000);
+    for _ in 0..10 {
+        round(&mut c, 50, 1_000, 1_200);
+    }
+    for _ in 0..64 {
+        round(&mut c, 50_000, 1_000, 1_200);
+    }
+    assert_eq!(c.window_us(), 0);
+    assert_eq!(round(&mut c, 50_000, 1_000, 1_200), CoalesceDecision::Hold);
+}
+
+#[test]
+fn adaptive_mode_is_set_per_wal_file_on_open() -> Result<()> {
+    let root = unique_root("adaptive-coalesce");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+
+    let cfg = QuiverConfig::default()
+        .with_wal_coalesce_adaptive(true)
+        .with_wal_coalesce_p99_budget_us(2_000)
+        .with_wal_coalesce_ms(1);
+    {
+        let mut db = Db::open_with_config(&root, cfg.clone())?;
+        let st = group_coalesce_status(&root)?.expect("adaptive mode on");
+        assert_eq!(st.budget_us, 2_000);
+        assert_eq!(st.max_window_us, 1_000);
+        for i in 0..64 {
+            db.put(format!("k{}", i).as_bytes(), b"v")?;
+        }
+        let st = group_coalesce_status(&root)?.unwrap();
+        assert!(st.window_us <= 1_000);
+        let mut c = cfg.clone();
+        c.wal_coalesce_p99_budget_us = 0;
+        assert!(c
+            .validate()
+            .issues
+            .iter()
+            .any(|i| i.field == "wal_coalesce_p99_budget_us"));
+    }
+    {
+        let db = Db::open_with_config(&root, QuiverConfig::default())?;
+        assert!(group_coalesce_status(&root)?.is_none());
+        assert_eq!(db.get(b"k63")?, Some(b"v".to_vec()));
+    }
+    Ok(())
+}
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use std::fs::{self, OpenOptions};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use QuiverDB::db::Db;
+use QuiverDB::wal::reader::WalStreamReader;
+use QuiverDB::wal::{CommitTimestamp, WAL_FILE, WAL_HDR_SIZE, WAL_REC_COMMIT};
+
+#[test]
+fn commit_frames_carry_wall_and_monotonic_time() -> Result<()> {
+    let root = unique_root("wal-commit-ts");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+
+    let t_before = now_ms();
+    let mut db = Db::open(&root)?;
+    db.put(b"a", b"1")?;
+    db.put(b"b", b"2")?;
+    let t_after = now_ms();
+
+    let mut f = OpenOptions::new().read(true).open(root.join(WAL_FILE))?;
+    let len = f.metadata()?.len();
+    let mut rdr = WalStreamReader::new();
+    let mut pos = WAL_HDR_SIZE as u64;
+    let mut commits: Vec<CommitTimestamp> = Vec::new();
+    while let Some((rec, next)) = rdr.read_next(&mut f, pos, len)? {
+        if rec.rec_type == WAL_REC_COMMIT {
+            commits.push(CommitTimestamp::decode(&rec.payload).expect("commit timestamp"));
+        }
+        pos = next;
+    }
+
+    assert_eq!(commits.len(), 2);
+    for ts in &commits {
+        assert!(ts.wall_ms >= t_before && ts.wall_ms <= t_after);
+    }
+    assert!(commits[1].mono_ns >= commits[0].mono_ns);
+    drop(db);
+
+    // Реплей/повторное открытие не спотыкаются о payload COMMIT
+    let db = Db::open_ro(&root)?;
+    assert_eq!(db.get(b"b")?.as_deref(), Some(&b"2"[..]));
+    Ok(())
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}
@@ -0,0 +1,98 @@
+use anyhow::Result;
+use std::fs;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use QuiverDB::db::Db;
+use QuiverDB::meta::{read_meta, set_clean_shutdown};
+use QuiverDB::page::kv_init_v3;
+use QuiverDB::pager::Pager;
+use QuiverDB::wal::{wal_salvage_scan, WAL_FILE, WAL_HDR_SIZE, WAL_REC_HDR_SIZE};
+
+#[test]
+fn salvage_skips_corrupted_batch_and_applies_later_ones() -> Result<()> {
+    let root = unique_root("wal-salvage");
+    fs::create_dir_all(&root)?;
+    Db::init(&root, 4096, 16)?;
+
+    // 3 батча по 2 страницы, без записи meta («краш»)
+    let mut batch_pids: Vec<Vec<u64>> = Vec::new();
+    {
+        let mut pager = Pager::open(&root)?;
+        let ps = pager.meta.page_size as usize;
+        for b in 0..3u8 {
+            let mut bufs: Vec<(u64, Vec<u8>)> = Vec::new();
+            for _ in 0..2 {
+                let pid = pager.allocate_one_page()?;
+                let mut page = vec![0u8; ps];
+                kv_init_v3(&mut page, pid, 0)?;
+                page[200] = 0xA0 + b;
+                bufs.push((pid, page));
+            }
+            batch_pids.push(bufs.iter().map(|(p, _)| *p).collect());
+            let mut refs: Vec<(u64, &mut [u8])> = bufs
+                .iter_mut()
+                .map(|(p, b)| (*p, b.as_mut_slice()))
+                .collect();
+            pager.commit_pages_batch(&mut refs)?;
+        }
+    }
+    set_clean_shutdown(&root, false)?;
+
+    // Портим payload первого IMAGE во втором батче
+    let before = wal_salvage_scan(&root)?;
+    assert_eq!(before.batches_intact, 3);
+    let bad_off = before.batches[1].begin_pos + 2 * WAL_REC_HDR_SIZE as u64 + 100;
+    {
+        let mut f = fs::OpenOptions::new()
+            .write(true)
+            .open(root.join(WAL_FILE))?;
+        f.seek(SeekFrom::Start(bad_off))?;
+        f.write_all(&[0xFF, 0x00, 0xFF, 0x00])?;
+    }
+
+    // Строгий реплей останавливается и подсказывает salvage
+    let err = Pager::wal_replay_with_pager(&root).unwrap_err();
+    assert!(format!("{:#}", err).contains("wal-salvage"));
+
+    // Dry-run: один пропущенный регион, третий батч цел
+    let rep = Pager::wal_salvage_with_pager(&root, false)?;
+    assert!(!rep.applied);
+    assert_eq!(rep.skipped.len(), 1);
+    assert_eq!(rep.skipped[0].reason, "crc_mismatch");
+    assert_eq!(rep.batches_intact, 2);
+    assert_eq!(rep.batches_damaged, 1);
+    assert!(!read_meta(&root)?.clean_shutdown);
+
+    // Apply: батчи 1 и 3 применены, WAL усечён, исходник сохранён
+    let rep = Pager::wal_salvage_with_pager(&root, true)?;
+    assert!(rep.applied);
+    assert_eq!(rep.applied_batches, 2);
+    assert_eq!(rep.applied_pages, 4);
+    assert!(PathBuf::from(rep.backup_path.as_ref().unwrap()).exists());
+    assert_eq!(
+        fs::metadata(root.join(WAL_FILE))?.len(),
+        WAL_HDR_SIZE as u64
+    );
+    let m = read_meta(&root)?;
+    assert!(m.clean_shutdown);
+
+    let pager = Pager::open(&root)?;
+    let mut buf = vec![0u8; pager.meta.page_size as usize];
+    for (b, marker) in [(0usize, 0xA0u8), (2, 0xA2)] {
+        for &pid in &batch_pids[b] {
+            pager.read_page(pid, &mut buf)?;
+            assert_eq!(buf[200], marker);
+        }
+    }
+    Ok(())
+}
+
+fn unique_root(prefix: &str) -> PathBuf {
+    let pid = std::process::id();
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("qdb2-{}-{}-{}", prefix, pid, t))
+}